@@ -0,0 +1,52 @@
+-- Coach links: доступ диетолога к данным клиента (только чтение)
+DO $$ BEGIN
+    CREATE TYPE coach_link_status AS ENUM ('pending', 'active', 'revoked');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE coaching_domain AS ENUM ('diary', 'weight', 'goals', 'fridge', 'community');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE coach_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    coach_email VARCHAR(255) NOT NULL,
+    coach_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    invite_token VARCHAR(255) UNIQUE NOT NULL,
+    status coach_link_status DEFAULT 'pending',
+    accepted_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Явные разрешения по доменам данных (diary да, community нет)
+CREATE TABLE coach_grants (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    link_id UUID NOT NULL REFERENCES coach_links(id) ON DELETE CASCADE,
+    domain coaching_domain NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE(link_id, domain)
+);
+
+-- Журнал активности пользователя (в т.ч. каждое чтение данных коучем)
+CREATE TABLE activity_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(100) NOT NULL,
+    details JSONB DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_coach_links_client ON coach_links(client_id, status);
+CREATE INDEX idx_coach_links_coach ON coach_links(coach_id, status);
+CREATE INDEX idx_coach_grants_link ON coach_grants(link_id);
+CREATE INDEX idx_activity_log_user_created ON activity_log(user_id, created_at DESC);
+
+CREATE TRIGGER update_coach_links_updated_at BEFORE UPDATE ON coach_links
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
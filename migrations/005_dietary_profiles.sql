@@ -0,0 +1,32 @@
+-- Диетические профили: аллергии с уровнем серьезности, непереносимости, диеты
+CREATE TABLE IF NOT EXISTS dietary_profiles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID UNIQUE NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- [{"allergen": "Peanuts", "severity": "LifeThreatening"}, ...]
+    allergies JSONB NOT NULL DEFAULT '[]',
+    intolerances JSONB NOT NULL DEFAULT '[]',
+    diets JSONB NOT NULL DEFAULT '[]',
+    custom_restrictions TEXT[] NOT NULL DEFAULT '{}',
+    severity_notes TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Старые записи аллергий без уровня серьезности (строки или объекты без severity) считаем Strict
+UPDATE dietary_profiles
+SET allergies = (
+    SELECT COALESCE(jsonb_agg(
+        CASE
+            WHEN jsonb_typeof(entry) = 'string'
+                THEN jsonb_build_object('allergen', entry #>> '{}', 'severity', 'Strict')
+            WHEN NOT entry ? 'severity'
+                THEN entry || '{"severity": "Strict"}'::jsonb
+            ELSE entry
+        END
+    ), '[]'::jsonb)
+    FROM jsonb_array_elements(allergies) AS entry
+);
+
+DROP TRIGGER IF EXISTS update_dietary_profiles_updated_at ON dietary_profiles;
+CREATE TRIGGER update_dietary_profiles_updated_at BEFORE UPDATE ON dietary_profiles
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
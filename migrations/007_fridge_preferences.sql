@@ -0,0 +1,12 @@
+-- Пользовательские настройки холодильника: один JSONB-документ на пользователя.
+-- Каждая настройка — отдельный ключ; отсутствующие ключи заполняются значениями по умолчанию в коде.
+CREATE TABLE fridge_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- {"categories": {"order": ["Vegetables", "Dairy"], "hidden": ["Fish"]}}
+    settings JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TRIGGER update_fridge_preferences_updated_at BEFORE UPDATE ON fridge_preferences
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
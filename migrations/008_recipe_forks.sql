@@ -0,0 +1,8 @@
+-- Публикация рецептов и копии ("форки") чужих рецептов с указанием автора оригинала
+ALTER TABLE recipes
+    ADD COLUMN is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN original_recipe_id UUID REFERENCES recipes(id) ON DELETE SET NULL,
+    ADD COLUMN original_author_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN forks_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_recipes_original_recipe_id ON recipes(original_recipe_id);
@@ -0,0 +1,32 @@
+-- Шаблоны приемов пищи ("мой обычный завтрак"): набор продуктов, который записывается в дневник одним вызовом
+CREATE TABLE meal_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Строки шаблона: те же поля питательности, что и у записи дневника, плюс порция по умолчанию
+CREATE TABLE meal_template_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES meal_templates(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    food_name VARCHAR(200) NOT NULL,
+    brand VARCHAR(100),
+    portion_size REAL NOT NULL,
+    unit VARCHAR(20) NOT NULL,
+    calories_per_100g REAL NOT NULL,
+    protein_per_100g REAL NOT NULL,
+    fat_per_100g REAL NOT NULL,
+    carbs_per_100g REAL NOT NULL,
+    fiber_per_100g REAL,
+    sugar_per_100g REAL,
+    sodium_per_100g REAL
+);
+
+CREATE INDEX idx_meal_templates_user_id ON meal_templates(user_id);
+CREATE INDEX idx_meal_template_items_template_id ON meal_template_items(template_id, position);
+
+CREATE TRIGGER update_meal_templates_updated_at BEFORE UPDATE ON meal_templates
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
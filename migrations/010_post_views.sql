@@ -0,0 +1,16 @@
+-- Просмотры постов: одна строка на зрителя в день (повторные просмотры в тот же день не учитываются).
+-- viewer_key: "user:<id>" для авторизованных, "anon:<хеш IP с дневной солью>" для анонимных.
+CREATE TABLE post_views (
+    post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    viewer_key VARCHAR(80) NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    source VARCHAR(50),
+    PRIMARY KEY (post_id, day, viewer_key)
+);
+
+-- Счетчик для ленты отдельно от posts, чтобы просмотры не меняли posts.updated_at
+CREATE TABLE post_view_counts (
+    post_id UUID PRIMARY KEY REFERENCES posts(id) ON DELETE CASCADE,
+    views_count BIGINT NOT NULL DEFAULT 0
+);
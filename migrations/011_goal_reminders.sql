@@ -0,0 +1,29 @@
+-- Напоминания о приближении срока цели и автоматическое истечение просроченных целей
+ALTER TYPE goal_status ADD VALUE IF NOT EXISTS 'expired';
+
+-- За сколько дней до target_date напоминать, например {7,3,1}
+ALTER TABLE goals ADD COLUMN reminder_days INTEGER[] NOT NULL DEFAULT '{}';
+
+-- Отправленные напоминания. target_date входит в ключ: после переноса срока напоминания
+-- для новой даты отправляются заново.
+CREATE TABLE goal_reminders_sent (
+    goal_id UUID NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    target_date DATE NOT NULL,
+    days_before INTEGER NOT NULL,
+    sent_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (goal_id, target_date, days_before)
+);
+
+-- Сохраненные уведомления пользователя (дублируют WebSocket-события для тех, кто был офлайн)
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    title VARCHAR(200) NOT NULL,
+    message TEXT NOT NULL,
+    data JSONB NOT NULL DEFAULT '{}',
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_notifications_user_unread ON notifications(user_id) WHERE read_at IS NULL;
@@ -0,0 +1,25 @@
+-- Временный статус «что я сейчас делаю» (готовлю, пеку...), виден подписчикам
+DO $$ BEGIN
+    CREATE TYPE activity_kind AS ENUM ('cooking', 'baking', 'meal_prep', 'shopping');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Не больше одного статуса на пользователя: новый статус заменяет предыдущий.
+-- Истекшие строки не удаляются сразу — при чтении учитывается только expires_at > NOW().
+CREATE TABLE activity_statuses (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    activity activity_kind NOT NULL,
+    recipe_id UUID REFERENCES recipes(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_activity_statuses_expires_at ON activity_statuses(expires_at);
+
+-- Настройки приватности сообщества; отсутствие строки означает значения по умолчанию
+CREATE TABLE community_privacy (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    share_activity BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
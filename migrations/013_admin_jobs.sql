@@ -0,0 +1,28 @@
+-- Фоновые задачи администратора (пересчет данных и т.п.) с сохраняемым прогрессом
+DO $$ BEGIN
+    CREATE TYPE admin_job_status AS ENUM ('pending', 'running', 'completed', 'failed');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Задача обрабатывает записи по возрастанию id; last_processed_id позволяет
+-- продолжить с того же места после перезапуска сервера.
+CREATE TABLE admin_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(100) NOT NULL,
+    status admin_job_status NOT NULL DEFAULT 'pending',
+    params JSONB NOT NULL DEFAULT '{}',
+    total INTEGER NOT NULL DEFAULT 0,
+    processed INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    last_processed_id UUID,
+    report JSONB NOT NULL DEFAULT '[]',
+    error TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_admin_jobs_unfinished ON admin_jobs(kind) WHERE status IN ('pending', 'running');
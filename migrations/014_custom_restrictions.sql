@@ -0,0 +1,16 @@
+-- Пользовательские ограничения становятся структурированными:
+-- [{"name": "Киви", "severity": "Strict", "keywords": ["киви", "kiwi"]}, ...]
+ALTER TABLE dietary_profiles ADD COLUMN custom_restrictions_json JSONB NOT NULL DEFAULT '[]';
+
+-- Старые строки переносим как ограничения уровня Strict с названием в качестве ключевого слова
+UPDATE dietary_profiles
+SET custom_restrictions_json = (
+    SELECT COALESCE(jsonb_agg(
+        jsonb_build_object('name', entry, 'severity', 'Strict', 'keywords', jsonb_build_array(entry))
+    ), '[]'::jsonb)
+    FROM unnest(custom_restrictions) AS entry
+    WHERE btrim(entry) <> ''
+);
+
+ALTER TABLE dietary_profiles DROP COLUMN custom_restrictions;
+ALTER TABLE dietary_profiles RENAME COLUMN custom_restrictions_json TO custom_restrictions;
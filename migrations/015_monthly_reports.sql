@@ -0,0 +1,15 @@
+-- Ежемесячные отчеты по холодильнику. (user_id, month) — ключ идемпотентности:
+-- отчет за месяц формируется один раз, и по API отдается тот же HTML, что ушел в письме.
+CREATE TABLE monthly_reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Первое число отчетного месяца
+    month DATE NOT NULL,
+    html TEXT NOT NULL,
+    -- NULL, пока письмо не отправлено
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE(user_id, month)
+);
+
+CREATE INDEX idx_monthly_reports_unsent ON monthly_reports(month) WHERE sent_at IS NULL;
@@ -0,0 +1,17 @@
+-- Жизненный цикл продукта: вместо удаления продукт получает статус и остается в истории
+DO $$ BEGIN
+    CREATE TYPE fridge_item_status AS ENUM ('active', 'consumed', 'wasted', 'removed');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Все существующие продукты становятся активными
+ALTER TABLE fridge_items
+    ADD COLUMN IF NOT EXISTS status fridge_item_status NOT NULL DEFAULT 'active',
+    ADD COLUMN IF NOT EXISTS finished_at TIMESTAMPTZ,
+    -- Стоимость съеденной и выброшенной части
+    ADD COLUMN IF NOT EXISTS consumed_value REAL NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS wasted_value REAL NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_fridge_items_user_status ON fridge_items(user_id, status);
+CREATE INDEX IF NOT EXISTS idx_fridge_items_history ON fridge_items(user_id, finished_at DESC) WHERE status <> 'active';
@@ -0,0 +1,16 @@
+-- История прогресса по целям: каждое обновление current_value
+CREATE TABLE goal_progress_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    goal_id UUID NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    value REAL NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_goal_progress_entries_goal ON goal_progress_entries(goal_id, recorded_at);
+
+-- Публичная ссылка на карточку цели; удаление строки отзывает доступ
+CREATE TABLE goal_share_tokens (
+    goal_id UUID PRIMARY KEY REFERENCES goals(id) ON DELETE CASCADE,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
@@ -0,0 +1,11 @@
+-- Видимость постов. Публичная лента для гостей читает только 'public',
+-- проверка выполняется при каждом чтении
+DO $$ BEGIN
+    CREATE TYPE post_visibility AS ENUM ('public', 'followers_only');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+ALTER TABLE posts ADD COLUMN visibility post_visibility NOT NULL DEFAULT 'public';
+
+CREATE INDEX idx_posts_public_feed ON posts(created_at DESC, id DESC) WHERE visibility = 'public';
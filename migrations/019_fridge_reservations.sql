@@ -0,0 +1,18 @@
+-- Мягкие резервы продуктов под слоты плана питания.
+-- Резерв перестает учитываться, когда дата слота прошла
+CREATE TABLE fridge_reservations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL REFERENCES fridge_items(id) ON DELETE CASCADE,
+    slot_id UUID NOT NULL,
+    slot_date DATE NOT NULL,
+    recipe_id UUID REFERENCES recipes(id) ON DELETE SET NULL,
+    ingredient_name VARCHAR(200) NOT NULL,
+    -- Количество в единицах продукта
+    quantity REAL NOT NULL CHECK (quantity > 0),
+    unit VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_fridge_reservations_item ON fridge_reservations(item_id, slot_date);
+CREATE INDEX idx_fridge_reservations_slot ON fridge_reservations(user_id, slot_id);
@@ -0,0 +1,17 @@
+-- Ключи идемпотентности (заголовок Idempotency-Key) для повторов запросов с мобильных клиентов.
+-- Ответ хранится 24 часа; просроченные ключи удаляются фоновой задачей
+CREATE TABLE idempotency_keys (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    idempotency_key VARCHAR(255) NOT NULL,
+    -- SHA-256 метода, пути и тела запроса
+    request_hash VARCHAR(64) NOT NULL,
+    -- 'in_progress' — запрос выполняется, 'completed' — ответ сохранен
+    status VARCHAR(20) NOT NULL DEFAULT 'in_progress',
+    response_status SMALLINT,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires ON idempotency_keys(expires_at);
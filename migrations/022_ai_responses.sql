@@ -0,0 +1,18 @@
+-- История ответов ИИ с провенансом: какие данные пользователя попали в промпт.
+-- Ссылки в provenance хранятся вместе с подписями (название продукта, дата дня дневника),
+-- поэтому след остается читаемым и после удаления исходных записей
+CREATE TABLE ai_responses (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 'personal_health' или 'fridge_analysis'
+    kind VARCHAR(50) NOT NULL,
+    template VARCHAR(100) NOT NULL,
+    provider VARCHAR(50) NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    degraded BOOLEAN NOT NULL DEFAULT FALSE,
+    response JSONB NOT NULL,
+    provenance JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_responses_user_created ON ai_responses(user_id, created_at DESC);
@@ -0,0 +1,12 @@
+-- WebSocket-уведомления, отложенные до конца тихих часов пользователя.
+-- Сами настройки уведомлений хранятся в fridge_preferences.settings->'notifications'
+CREATE TABLE queued_notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    websocket_event JSONB NOT NULL,
+    deliver_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_queued_notifications_deliver_at ON queued_notifications(deliver_at);
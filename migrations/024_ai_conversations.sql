@@ -0,0 +1,33 @@
+-- Диалоги с ИИ-помощником. Еженедельный разбор целей ('goal_review') создается планировщиком:
+-- review хранит данные недели, на которых построен диалог, outcome — итоговые решения по целям
+CREATE TABLE ai_conversations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 'chat' или 'goal_review'
+    kind VARCHAR(50) NOT NULL,
+    title VARCHAR(200) NOT NULL,
+    -- Понедельник недели, за которую проводится разбор
+    week_start DATE,
+    review JSONB,
+    outcome JSONB,
+    concluded_at TIMESTAMPTZ,
+    applied_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_conversations_user ON ai_conversations(user_id, updated_at DESC);
+-- Один разбор целей на пользователя в неделю
+CREATE UNIQUE INDEX idx_ai_conversations_goal_review_week
+    ON ai_conversations(user_id, week_start) WHERE kind = 'goal_review';
+
+CREATE TABLE ai_conversation_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    conversation_id UUID NOT NULL REFERENCES ai_conversations(id) ON DELETE CASCADE,
+    -- 'system', 'assistant' или 'user'
+    role VARCHAR(20) NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_conversation_messages_conversation ON ai_conversation_messages(conversation_id, created_at);
@@ -0,0 +1,35 @@
+-- Ссылки на продукты холодильника и что с ними происходит при окончательном удалении продукта
+-- (DELETE /fridge/history/{id}; удалить можно только закончившийся продукт):
+--   food_waste.original_item_id     — SET NULL: название, бренд, категория и единица хранятся в записи
+--   fridge_reservations.item_id     — CASCADE: резерв без продукта ничего не значит (019)
+--   queued_notifications.websocket_event — id продуктов в JSON не связаны ключом: это снимок на момент отправки
+-- Записи дневника на продукты холодильника не ссылаются.
+DO $$ BEGIN
+    CREATE TYPE waste_reason AS ENUM ('expired', 'spoiled', 'overcooked', 'notliked', 'toomuch', 'other');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS food_waste (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    original_item_id UUID,
+    name VARCHAR(200) NOT NULL,
+    brand VARCHAR(100),
+    wasted_quantity REAL NOT NULL,
+    unit VARCHAR(20) NOT NULL,
+    category fridge_category NOT NULL,
+    waste_reason waste_reason NOT NULL,
+    wasted_value REAL,
+    waste_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    notes TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE food_waste DROP CONSTRAINT IF EXISTS food_waste_original_item_id_fkey;
+ALTER TABLE food_waste
+    ADD CONSTRAINT food_waste_original_item_id_fkey
+    FOREIGN KEY (original_item_id) REFERENCES fridge_items(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_food_waste_user_date ON food_waste(user_id, waste_date DESC);
+CREATE INDEX IF NOT EXISTS idx_food_waste_original_item ON food_waste(original_item_id) WHERE original_item_id IS NOT NULL;
@@ -0,0 +1,29 @@
+-- Оценка качества питания за день (0–100) по дневнику; пересчитывается при запросе.
+-- components — вклад каждой составляющей рубрики (services::diet_quality::RUBRIC)
+CREATE TABLE diet_quality_scores (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    score SMALLINT NOT NULL CHECK (score BETWEEN 0 AND 100),
+    components JSONB NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, date)
+);
+
+-- Проверки самочувствия (POST /health/wellbeing); настроение сопоставляется с оценкой питания
+CREATE TABLE daily_wellbeing (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date TIMESTAMPTZ NOT NULL,
+    mood_score INTEGER,
+    energy_level INTEGER,
+    stress_level INTEGER,
+    sleep_hours REAL,
+    sleep_quality INTEGER,
+    water_intake_ml INTEGER,
+    exercise_minutes INTEGER,
+    notes TEXT,
+    symptoms TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_daily_wellbeing_user_date ON daily_wellbeing(user_id, date);
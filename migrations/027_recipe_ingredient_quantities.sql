@@ -0,0 +1,32 @@
+-- Структурированные количества ингредиентов (IngredientQuantity в models/recipe.rs).
+-- Колонки quantity + unit остаются хранилищем, но в каноническом виде:
+--   measured — quantity > 0 и единица веса или объема из utils/units.rs
+--   count    — quantity > 0, unit 'шт'
+--   to_taste — quantity 0, unit 'по вкусу'
+-- Строки, которые не разбираются, помечаются needs_review: количество правит автор рецепта.
+ALTER TABLE recipe_ingredients ADD COLUMN IF NOT EXISTS needs_review BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- "По вкусу" в любом написании, независимо от количества
+UPDATE recipe_ingredients
+SET quantity = 0, unit = 'по вкусу'
+WHERE LOWER(TRIM(unit)) IN ('по вкусу', 'по желанию', 'to taste');
+
+-- Штуки: пустая единица или любое написание штук
+UPDATE recipe_ingredients
+SET unit = 'шт'
+WHERE quantity > 0
+  AND RTRIM(LOWER(TRIM(unit)), '.') IN ('', 'шт', 'штук', 'штуки', 'pcs', 'pc', 'piece', 'pieces');
+
+-- Остальное без известной единицы или без положительного количества — на проверку автору
+UPDATE recipe_ingredients
+SET needs_review = TRUE
+WHERE unit <> 'по вкусу'
+  AND (
+    quantity <= 0
+    OR quantity IN ('NaN'::real, 'Infinity'::real)
+    OR RTRIM(LOWER(TRIM(unit)), '.') NOT IN (
+        'шт', 'кг', 'kg', 'килограмм', 'г', 'гр', 'g', 'грамм', 'л', 'l', 'литр', 'литра', 'мл', 'ml'
+    )
+  );
+
+CREATE INDEX IF NOT EXISTS idx_recipe_ingredients_needs_review ON recipe_ingredients(recipe_id) WHERE needs_review;
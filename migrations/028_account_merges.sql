@@ -0,0 +1,30 @@
+-- Объединение дублирующихся аккаунтов (POST /admin/users/merge).
+-- Данные исходного аккаунта переносятся на целевой по доменам, каждый домен — отдельной
+-- транзакцией; completed_domains пополняется в той же транзакции, поэтому прерванное
+-- объединение повторным запросом продолжается с первого незавершенного домена.
+-- Правила конфликтов:
+--   weight_entries, recipe_ratings — одна запись на дату / рецепт: остается запись целевого аккаунта
+--   recipe_favorites, likes        — дубликаты схлопываются
+--   follows                        — подписки между объединяемыми аккаунтами удаляются, повторные схлопываются
+--   dietary_profiles               — объединение ограничений; у аллергии остается более строгий уровень
+CREATE TABLE merge_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status admin_job_status NOT NULL DEFAULT 'running',
+    completed_domains TEXT[] NOT NULL DEFAULT '{}',
+    -- Сколько строк перенесено по каждому домену
+    counts JSONB NOT NULL DEFAULT '{}',
+    error TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    CHECK (source_user_id <> target_user_id)
+);
+
+-- Аккаунт объединяется только один раз
+CREATE UNIQUE INDEX idx_merge_jobs_source ON merge_jobs(source_user_id);
+
+-- Отключенный исходный аккаунт помнит, куда перенесены его данные
+ALTER TABLE users ADD COLUMN IF NOT EXISTS merged_into UUID REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,45 @@
+-- Шаги приготовления с фото и галерея рецепта.
+-- recipes.instructions остается: те же шаги одной строкой (по шагу на строку) для старых клиентов.
+
+-- Загруженные файлы: шаги и галерея ссылаются на них по id, владелец проверяется при сохранении рецепта
+CREATE TABLE media_files (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    url TEXT NOT NULL UNIQUE,
+    media_type TEXT NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_media_files_user ON media_files(user_id);
+
+CREATE TABLE recipe_steps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_id UUID NOT NULL REFERENCES recipes(id) ON DELETE CASCADE,
+    -- С 1
+    position INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    media_id UUID REFERENCES media_files(id) ON DELETE SET NULL,
+    UNIQUE (recipe_id, position)
+);
+
+CREATE INDEX idx_recipe_steps_media ON recipe_steps(media_id) WHERE media_id IS NOT NULL;
+
+CREATE TABLE recipe_media (
+    recipe_id UUID NOT NULL REFERENCES recipes(id) ON DELETE CASCADE,
+    media_id UUID NOT NULL REFERENCES media_files(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (recipe_id, media_id)
+);
+
+CREATE INDEX idx_recipe_media_media ON recipe_media(media_id);
+
+-- Существующие инструкции: шаг на каждую непустую строку
+INSERT INTO recipe_steps (recipe_id, position, text)
+SELECT recipe_id, ROW_NUMBER() OVER (PARTITION BY recipe_id ORDER BY line_number)::INTEGER, text
+FROM (
+    SELECT r.id AS recipe_id, line.number AS line_number, TRIM(line.text) AS text
+    FROM recipes r
+    CROSS JOIN LATERAL regexp_split_to_table(r.instructions, E'\\r?\\n') WITH ORDINALITY AS line(text, number)
+) lines
+WHERE text <> '';
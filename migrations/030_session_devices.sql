@@ -0,0 +1,35 @@
+-- Управление сессиями (GET/DELETE /auth/sessions).
+-- Строка user_sessions — одна сессия с момента входа: при обновлении токена refresh_token
+-- заменяется на месте, поэтому id сессии стабилен и попадает в access-токен (claim sid).
+-- Удаление строки отзывает сессию: access-токен перестает приниматься сразу, обновление — невозможно.
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS user_agent TEXT;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS browser VARCHAR(50);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS os VARCHAR(50);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS ip_address VARCHAR(64);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS location VARCHAR(100);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ DEFAULT NOW();
+
+UPDATE user_sessions SET last_used_at = created_at;
+
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user ON user_sessions(user_id, last_used_at DESC);
+
+-- Устройства (User-Agent + IP), с которых уже был вход; вход с нового устройства — событие безопасности
+CREATE TABLE known_devices (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    fingerprint VARCHAR(64) NOT NULL,
+    first_seen_at TIMESTAMPTZ DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (user_id, fingerprint)
+);
+
+-- Журнал событий безопасности аккаунта: вход с нового устройства, отзыв сессий
+CREATE TABLE security_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event VARCHAR(50) NOT NULL,
+    session_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_security_events_user ON security_events(user_id, created_at DESC);
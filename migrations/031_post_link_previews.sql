@@ -0,0 +1,14 @@
+-- Публичные ссылки на рецепты и карточки ссылок в постах.
+-- Ссылка на рецепт: RECIPE_SHARE_URL/{token}; отзыв — удаление строки токена.
+CREATE TABLE recipe_share_tokens (
+    recipe_id UUID PRIMARY KEY REFERENCES recipes(id) ON DELETE CASCADE,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Карточки ссылок из текста поста (LinkPreview в models/community.rs) хранятся в самом посте,
+-- чтобы лента не делала join с рецептами. Карточки рецепта находятся по recipe_id
+-- и переписываются при изменении рецепта и отзыве ссылки.
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS link_previews JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX IF NOT EXISTS idx_posts_link_previews ON posts USING GIN (link_previews jsonb_path_ops);
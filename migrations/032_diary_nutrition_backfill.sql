@@ -0,0 +1,40 @@
+-- Заполнение питательности старых записей дневника (POST /diary/backfill-nutrition).
+-- Источник значений записи: user — введены пользователем, database — из food_items,
+-- ai_estimate — оценка ИИ. Сводки могут исключать ai_estimate (?exclude_estimates=true).
+DO $$ BEGIN
+    CREATE TYPE nutrition_source AS ENUM ('user', 'database', 'ai_estimate');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+ALTER TABLE diary_entries ADD COLUMN IF NOT EXISTS nutrition_source nutrition_source NOT NULL DEFAULT 'user';
+
+-- Кандидаты на заполнение: записи без калорий, введенные пользователем
+CREATE INDEX IF NOT EXISTS idx_diary_entries_missing_nutrition
+    ON diary_entries(user_id, id) WHERE calories_per_100g = 0 AND nutrition_source = 'user';
+
+ALTER TYPE admin_job_status ADD VALUE IF NOT EXISTS 'cancelled';
+
+-- Записи обрабатываются пачками по возрастанию id; last_processed_id и счетчики
+-- обновляются в транзакции пачки, поэтому прерванная задача продолжается с места остановки
+CREATE TABLE nutrition_backfill_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status admin_job_status NOT NULL DEFAULT 'pending',
+    use_ai BOOLEAN NOT NULL DEFAULT FALSE,
+    total INTEGER NOT NULL DEFAULT 0,
+    processed INTEGER NOT NULL DEFAULT 0,
+    filled_from_database INTEGER NOT NULL DEFAULT 0,
+    filled_by_ai INTEGER NOT NULL DEFAULT 0,
+    unresolved INTEGER NOT NULL DEFAULT 0,
+    last_processed_id UUID,
+    error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Не больше одной незавершенной задачи на пользователя
+CREATE UNIQUE INDEX idx_nutrition_backfill_jobs_active
+    ON nutrition_backfill_jobs(user_id) WHERE status IN ('pending', 'running');
@@ -0,0 +1,19 @@
+-- Единый документ настроек пользователя (GET/PATCH /api/v1/preferences).
+-- Бывшая таблица fridge_preferences: разделы документа — categories, checkin, reports,
+-- notifications, eating_window, а также новые general и privacy.
+ALTER TABLE fridge_preferences RENAME TO user_preferences;
+ALTER TRIGGER update_fridge_preferences_updated_at ON user_preferences
+    RENAME TO update_user_preferences_updated_at;
+
+-- Время последнего изменения каждого раздела: {"notifications": "2024-05-01T10:00:00Z"}.
+-- Разделы пишутся по принципу "последняя запись побеждает", клиент сверяет время раздела.
+ALTER TABLE user_preferences ADD COLUMN section_updated_at JSONB NOT NULL DEFAULT '{}';
+
+-- Существующие разделы считаются измененными в момент последнего изменения строки
+UPDATE user_preferences p
+SET section_updated_at = COALESCE(
+        (SELECT jsonb_object_agg(key, to_jsonb(COALESCE(p.updated_at, NOW())))
+         FROM jsonb_object_keys(p.settings) AS key),
+        '{}'::jsonb
+    ),
+    settings = p.settings || '{"version": 1}'::jsonb;
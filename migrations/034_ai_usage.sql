@@ -0,0 +1,30 @@
+-- Учет расходов на ИИ: один вызов провайдера — одна строка.
+-- Токены берутся из ответа провайдера (блок usage у OpenAI/Groq) или оцениваются по длине
+-- текста (Gemini); стоимость — по таблице цен моделей на момент вызова.
+CREATE TABLE ai_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    provider VARCHAR(20) NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    feature VARCHAR(50) NOT NULL,
+    -- Вызов по запросу пользователя; фоновые вызовы при исчерпанном бюджете не выполняются
+    essential BOOLEAN NOT NULL DEFAULT TRUE,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    tokens_estimated BOOLEAN NOT NULL DEFAULT FALSE,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_usage_created_at ON ai_usage(created_at);
+CREATE INDEX idx_ai_usage_user ON ai_usage(user_id, created_at);
+
+-- Предупреждения о расходе месячного бюджета (80% и 100%): не больше одного на порог в месяц
+CREATE TABLE ai_budget_alerts (
+    month DATE NOT NULL,
+    threshold_percent SMALLINT NOT NULL,
+    spent_usd DOUBLE PRECISION NOT NULL,
+    budget_usd DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (month, threshold_percent)
+);
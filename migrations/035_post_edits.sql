@@ -0,0 +1,16 @@
+-- Правки постов и комментариев. edited_at — время последней правки текста
+-- (updated_at меняется при любом изменении строки, например карточек ссылок).
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS edited_at TIMESTAMPTZ;
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS edit_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS edited_at TIMESTAMPTZ;
+
+-- Предыдущие версии текста поста: хранятся последние 5, видны автору и модераторам
+CREATE TABLE post_edits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    -- Когда этот текст был заменен
+    replaced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_post_edits_post ON post_edits(post_id, replaced_at DESC);
@@ -0,0 +1,18 @@
+-- Нормализованные теги рецептов ("Завтраки", "breakfast" → "завтрак").
+-- recipes.tags хранит те же теги для ответов API; здесь — для поиска и похожих рецептов.
+-- Теги существующих рецептов переносит приложение при запуске: правила нормализации в коде.
+CREATE TABLE recipe_tags (
+    recipe_id UUID NOT NULL REFERENCES recipes(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (recipe_id, tag)
+);
+
+CREATE INDEX idx_recipe_tags_tag ON recipe_tags(tag);
+
+-- Вес тега по редкости (IDF): пересчитывается периодически
+CREATE TABLE recipe_tag_weights (
+    tag TEXT PRIMARY KEY,
+    recipes_count INTEGER NOT NULL,
+    weight DOUBLE PRECISION NOT NULL,
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,19 @@
+-- Отзывы на разделы отчета о холодильнике. report_id — запись истории ответов ИИ,
+-- section_id — id раздела в сохраненном ответе; тип раздела копируется для агрегатов.
+-- По последним отзывам на тип раздела он опускается в отчете ниже или пропадает из него.
+CREATE TABLE fridge_report_feedback (
+    report_id UUID NOT NULL REFERENCES ai_responses(id) ON DELETE CASCADE,
+    section_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 'overview', 'attention_needed', 'usage_recommendations', 'optimization_tips'
+    section_kind VARCHAR(50) NOT NULL,
+    -- 'helpful', 'not_helpful', 'wrong'
+    rating VARCHAR(20) NOT NULL,
+    comment TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (report_id, section_id)
+);
+
+CREATE INDEX idx_fridge_report_feedback_user_kind ON fridge_report_feedback(user_id, section_kind, updated_at DESC);
+CREATE INDEX idx_fridge_report_feedback_updated ON fridge_report_feedback(updated_at);
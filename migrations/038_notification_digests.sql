@@ -0,0 +1,23 @@
+-- Буфер объединения уведомлений сообщества: события одного типа от одного источника
+-- (автор постов, пост) для одного получателя за окно попадают в inbox одной записью
+CREATE TABLE notification_digests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    source_id UUID NOT NULL,
+    -- Ключи событий: повтор того же события не увеличивает счетчик
+    event_keys TEXT[] NOT NULL DEFAULT '{}',
+    actor_names TEXT[] NOT NULL DEFAULT '{}',
+    data JSONB NOT NULL DEFAULT '{}',
+    first_event_at TIMESTAMPTZ NOT NULL,
+    flush_after TIMESTAMPTZ NOT NULL,
+    notification_id UUID REFERENCES notifications(id) ON DELETE SET NULL,
+    flushed_at TIMESTAMPTZ
+);
+
+-- Одна открытая сводка на получателя, тип и источник
+CREATE UNIQUE INDEX idx_notification_digests_pending
+    ON notification_digests(user_id, kind, source_id) WHERE flushed_at IS NULL;
+CREATE INDEX idx_notification_digests_source ON notification_digests(user_id, kind, source_id);
+CREATE INDEX idx_notification_digests_flush_after
+    ON notification_digests(flush_after) WHERE flushed_at IS NULL;
@@ -0,0 +1,19 @@
+-- A/B-эксперименты объявлены в коде (services/experiments.rs); здесь — их остановка
+-- и события показа. После остановки все пользователи получают контрольный вариант.
+CREATE TABLE experiment_stops (
+    experiment_key VARCHAR(100) PRIMARY KEY,
+    stopped_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    stopped_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Первый показ эксперимента пользователю: вариант фиксируется в момент показа,
+-- повторные показы не записываются
+CREATE TABLE experiment_exposures (
+    experiment_key VARCHAR(100) NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    variant VARCHAR(50) NOT NULL,
+    exposed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (experiment_key, user_id)
+);
+
+CREATE INDEX idx_experiment_exposures_key_time ON experiment_exposures(experiment_key, exposed_at);
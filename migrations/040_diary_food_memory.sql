@@ -0,0 +1,15 @@
+-- Последние порции продуктов из дневника: по нормализованному названию хранятся
+-- до 3 разных порций со счетчиком использований. У пользователя не больше 500 продуктов,
+-- давно не использованные вытесняются при записи.
+CREATE TABLE diary_food_memory (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    food_key VARCHAR(255) NOT NULL,
+    -- Название в том виде, в каком его ввели последний раз
+    food_name VARCHAR(255) NOT NULL,
+    -- [{portion_size, unit, meal_type, uses, last_used_at}], последняя использованная — первой
+    portions JSONB NOT NULL DEFAULT '[]',
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, food_key)
+);
+
+CREATE INDEX idx_diary_food_memory_user_used ON diary_food_memory(user_id, last_used_at DESC);
@@ -0,0 +1,18 @@
+-- Отметки «приготовил(а)» на постах с рецептами: одна на пользователя и пост,
+-- повторная отметка обновляет оценку, фото и заметку. Считаются отдельно от лайков.
+CREATE TABLE post_cooked (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating SMALLINT CHECK (rating BETWEEN 1 AND 5),
+    media_id UUID REFERENCES media_files(id) ON DELETE SET NULL,
+    note TEXT,
+    -- Фото и заметку видят все, кто видит пост; иначе — только автор поста и сам пользователь
+    is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (post_id, user_id)
+);
+
+CREATE INDEX idx_post_cooked_post_updated ON post_cooked(post_id, updated_at DESC);
+CREATE INDEX idx_post_cooked_media ON post_cooked(media_id) WHERE media_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Уменьшенная копия загруженного изображения (400px, webp) для сеток и лент
+ALTER TABLE media_files ADD COLUMN thumbnail_url TEXT;
+
+-- Фото приема пищи. Файл удаляется очисткой медиа, когда на него больше никто не ссылается.
+ALTER TABLE diary_entries ADD COLUMN media_id UUID REFERENCES media_files(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_diary_entries_user_photos ON diary_entries(user_id, consumed_at DESC) WHERE media_id IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Просмотры виджета рецепта на чужих сайтах (embed.json по публичной ссылке).
+-- Счетчик на рецепте, а не на токене: отзыв и повторная публикация ссылки его не сбрасывают.
+ALTER TABLE recipes ADD COLUMN embed_views BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Архив целей: завершенные и брошенные цели скрываются из списка, но не удаляются
+ALTER TABLE goals ADD COLUMN archived_at TIMESTAMPTZ;
+
+-- Подтверждения массового удаления записей дневника. Токен из предпросмотра действует
+-- 10 минут и один раз; хранятся хеш токена и хеш выборки, которую он подтверждает.
+CREATE TABLE bulk_delete_confirmations (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    selection_hash VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_bulk_delete_confirmations_user ON bulk_delete_confirmations(user_id, expires_at);
@@ -0,0 +1,51 @@
+-- Продукты холодильника хранятся в базе, а не в памяти процесса: все поля FridgeItem
+DO $$ BEGIN
+    CREATE TYPE allergen AS ENUM (
+        'peanuts', 'treenuts', 'milk', 'eggs', 'fish', 'shellfish', 'soy',
+        'wheat', 'sesame', 'sulfites', 'celery', 'mustard', 'lupin', 'molluscs'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE intolerance AS ENUM (
+        'lactose', 'gluten', 'fructose', 'histamine', 'sorbitol',
+        'sucrose', 'fodmap', 'caffeine', 'alcohol', 'tyramine'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE diet_type AS ENUM (
+        'vegan', 'vegetarian', 'glutenfree', 'dairyfree', 'keto', 'paleo', 'mediterranean',
+        'lowcarb', 'lowfat', 'halal', 'kosher', 'raw', 'pescatarian', 'flexitarian'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+ALTER TABLE fridge_items
+    ADD COLUMN IF NOT EXISTS price_per_unit REAL,
+    ADD COLUMN IF NOT EXISTS total_price REAL,
+    ADD COLUMN IF NOT EXISTS store VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS contains_allergens allergen[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS contains_intolerances intolerance[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS suitable_for_diets diet_type[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS ingredients TEXT,
+    ADD COLUMN IF NOT EXISTS nutritional_info TEXT;
+
+UPDATE fridge_items SET created_at = COALESCE(created_at, NOW()), updated_at = COALESCE(updated_at, created_at, NOW())
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE fridge_items
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+UPDATE food_waste SET created_at = waste_date WHERE created_at IS NULL;
+ALTER TABLE food_waste ALTER COLUMN created_at SET NOT NULL;
+
+-- Фильтры списка продуктов: статус, категория, место хранения
+CREATE INDEX IF NOT EXISTS idx_fridge_items_user_category ON fridge_items(user_id, status, category);
+CREATE INDEX IF NOT EXISTS idx_fridge_items_expiry ON fridge_items(user_id, expiry_date) WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_food_waste_user_date ON food_waste(user_id, waste_date);
@@ -0,0 +1,13 @@
+-- Журнал частичных списаний продуктов: сколько съедено, на какую сумму и заметка («на борщ»)
+CREATE TABLE IF NOT EXISTS fridge_consumptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL REFERENCES fridge_items(id) ON DELETE CASCADE,
+    quantity REAL NOT NULL,
+    unit VARCHAR(20) NOT NULL,
+    consumed_value REAL NOT NULL DEFAULT 0,
+    note TEXT,
+    consumed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_fridge_consumptions_user_item ON fridge_consumptions(user_id, item_id, consumed_at);
@@ -0,0 +1,17 @@
+-- Что понято из заметки продукта: напоминание «использовать до» и ссылка на рецепт.
+-- Строка пересчитывается при каждом сохранении продукта и удаляется вместе с ним.
+CREATE TABLE IF NOT EXISTS fridge_note_links (
+    item_id UUID PRIMARY KEY REFERENCES fridge_items(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    remind_on DATE,
+    -- Фраза заметки, из которой понята дата
+    reminder_phrase TEXT,
+    reminder_sent_at TIMESTAMPTZ,
+    recipe_id UUID REFERENCES recipes(id) ON DELETE SET NULL,
+    recipe_name VARCHAR(200),
+    understood TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_fridge_note_links_user ON fridge_note_links(user_id);
+CREATE INDEX IF NOT EXISTS idx_fridge_note_links_due ON fridge_note_links(remind_on) WHERE reminder_sent_at IS NULL;
@@ -0,0 +1,11 @@
+-- Журнал смен категорий: по нему аналитика восстанавливает категорию на момент покупки
+CREATE TABLE IF NOT EXISTS fridge_category_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL REFERENCES fridge_items(id) ON DELETE CASCADE,
+    from_category fridge_category NOT NULL,
+    to_category fridge_category NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_fridge_category_changes_user ON fridge_category_changes(user_id, item_id, changed_at);
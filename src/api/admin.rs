@@ -0,0 +1,424 @@
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    response::Json as ResponseJson,
+    routing::{get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+use crate::{
+    config::Config,
+    db::{DbPool, DbPools, ReadConsistency},
+    models::{
+        account_merge::AccountMergeResult,
+        admin_job::{AdminJob, RecalculateNutritionParams},
+        admin_user::{AdminUserDetail, AdminUserSummary},
+        ai_response::SectionFeedbackStats,
+        ai_usage::AiCostReport,
+        experiment::{ExperimentExposure, ExperimentStop, ExperimentSummary},
+    },
+    services::{
+        account_merge::AccountMergeService,
+        admin_jobs::{AdminJobService, RecipeNutritionRecalculator},
+        admin_users::AdminUserService,
+        ai_costs::{month_start, AiCostService},
+        mail::MailService,
+        monthly_reports::parse_month,
+        auth::Claims,
+        clock::{Clock, SandboxClock, SandboxClockMode, SharedClock},
+        experiments::Experiments,
+        feature_flags::{FeatureFlags, FEATURES},
+        fridge_report_feedback::FridgeReportFeedbackService,
+        realtime::{RealtimeService, RealtimeStats},
+        task_supervisor::{SupervisorStatus, TaskSupervisor},
+    },
+    utils::errors::AppError,
+};
+
+/// Все маршруты защищены `auth_middleware` + `admin_middleware` (см. app.rs)
+pub fn routes() -> Router {
+    Router::new()
+        .route("/features", get(list_feature_flags))
+        .route("/features/:key", put(set_feature_flag).delete(reset_feature_flag))
+        .route("/features/:key/users/:user_id", put(set_user_feature_override).delete(remove_user_feature_override))
+        .route("/experiments", get(list_experiments))
+        .route("/experiments/:key/stop", post(stop_experiment))
+        .route("/experiments/:key/exposures", get(export_experiment_exposures))
+        .route("/realtime/stats", get(get_realtime_stats))
+        .route("/tasks", get(get_background_tasks))
+        .route("/sandbox/clock", get(get_sandbox_clock).put(set_sandbox_clock))
+        .route("/ai/costs", get(get_ai_costs))
+        .route("/ai/report-sections", get(get_report_section_feedback))
+        .route("/recipes/recalculate-nutrition", post(recalculate_recipe_nutrition))
+        .route("/jobs/:id", get(get_job))
+        .route("/users", get(search_users))
+        .route("/users/merge", post(merge_users))
+        .route("/users/:id", get(get_user_detail))
+        .route("/users/:id/suspend", post(suspend_user))
+        .route("/users/:id/unsuspend", post(unsuspend_user))
+        .route("/users/:id/force-password-reset", post(force_password_reset))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFeatureFlagRequest {
+    pub enabled: bool,
+    /// Процент пользователей (0-100), для которых флаг включен. None — для всех.
+    pub rollout_percentage: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetUserOverrideRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExposureExportParams {
+    /// Только показы начиная с этого момента (для инкрементальной выгрузки)
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AiCostParams {
+    /// Пока поддерживается только `month`
+    pub period: Option<String>,
+    /// Месяц в формате YYYY-MM; по умолчанию текущий
+    pub month: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportSectionFeedbackParams {
+    /// За сколько последних дней считать отзывы, 1-365; по умолчанию 30
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchParams {
+    /// Подстрока email, имени или фамилии
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SuspendUserRequest {
+    /// Причина показывается пользователю в ответе 403
+    #[validate(length(min = 1, max = 500))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeUsersRequest {
+    /// Аккаунт-дубликат: его данные переносятся, сам он блокируется
+    pub source_user_id: Uuid,
+    /// Аккаунт, который остается
+    pub target_user_id: Uuid,
+    /// Только посчитать, сколько строк будет перенесено
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminFeatureFlagResponse {
+    pub key: String,
+    pub description: String,
+    pub default_enabled: bool,
+    pub global_enabled: Option<bool>,
+    pub rollout_percentage: Option<i32>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_feature_flags(
+    Extension(flags): Extension<FeatureFlags>,
+) -> Result<ResponseJson<Vec<AdminFeatureFlagResponse>>, AppError> {
+    let states = flags.list_global_states().await?;
+
+    let response = FEATURES
+        .iter()
+        .map(|definition| {
+            let state = states.get(definition.key);
+            AdminFeatureFlagResponse {
+                key: definition.key.to_string(),
+                description: definition.description.to_string(),
+                default_enabled: definition.default_enabled,
+                global_enabled: state.map(|s| s.enabled),
+                rollout_percentage: state.and_then(|s| s.rollout_percentage),
+                updated_by: state.and_then(|s| s.updated_by),
+                updated_at: state.map(|s| s.updated_at),
+            }
+        })
+        .collect();
+
+    Ok(ResponseJson(response))
+}
+
+pub async fn set_feature_flag(
+    Extension(flags): Extension<FeatureFlags>,
+    claims: Claims,
+    Path(key): Path<String>,
+    Json(payload): Json<SetFeatureFlagRequest>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let state = flags.set_global(&key, payload.enabled, payload.rollout_percentage, claims.sub).await?;
+
+    Ok(ResponseJson(serde_json::json!({
+        "key": state.key,
+        "enabled": state.enabled,
+        "rollout_percentage": state.rollout_percentage,
+    })))
+}
+
+pub async fn reset_feature_flag(
+    Extension(flags): Extension<FeatureFlags>,
+    Path(key): Path<String>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    flags.reset_global(&key).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "Feature flag reset to default"})))
+}
+
+pub async fn set_user_feature_override(
+    Extension(flags): Extension<FeatureFlags>,
+    Path((key, user_id)): Path<(String, Uuid)>,
+    Json(payload): Json<SetUserOverrideRequest>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    flags.set_user_override(&key, user_id, payload.enabled).await?;
+
+    Ok(ResponseJson(serde_json::json!({
+        "key": key,
+        "user_id": user_id,
+        "enabled": payload.enabled,
+    })))
+}
+
+pub async fn remove_user_feature_override(
+    Extension(flags): Extension<FeatureFlags>,
+    Path((key, user_id)): Path<(String, Uuid)>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    flags.remove_user_override(&key, user_id).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "User override removed"})))
+}
+
+/// Эксперименты: статус, распределение пользователей по вариантам и число показов
+pub async fn list_experiments(
+    Extension(experiments): Extension<Experiments>,
+) -> Result<ResponseJson<Vec<ExperimentSummary>>, AppError> {
+    Ok(ResponseJson(experiments.summaries().await?))
+}
+
+/// Остановка эксперимента: все пользователи возвращаются к контрольному варианту
+pub async fn stop_experiment(
+    Extension(experiments): Extension<Experiments>,
+    claims: Claims,
+    Path(key): Path<String>,
+) -> Result<ResponseJson<ExperimentStop>, AppError> {
+    Ok(ResponseJson(experiments.stop(&key, claims.sub).await?))
+}
+
+/// Сырые события показа для анализа вне сервера
+pub async fn export_experiment_exposures(
+    Extension(experiments): Extension<Experiments>,
+    Path(key): Path<String>,
+    Query(params): Query<ExposureExportParams>,
+) -> Result<ResponseJson<Vec<ExperimentExposure>>, AppError> {
+    Ok(ResponseJson(experiments.exposures(&key, params.since).await?))
+}
+
+/// Подробная статистика WebSocket: лимиты, соединения по пользователям, список клиентов
+pub async fn get_realtime_stats(
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+) -> Result<ResponseJson<RealtimeStats>, AppError> {
+    Ok(ResponseJson(realtime_service.get_stats().await))
+}
+
+/// Фоновые задачи: последний запуск и успех, ошибки подряд, когда будет перезапуск
+pub async fn get_background_tasks(
+    Extension(tasks): Extension<TaskSupervisor>,
+) -> Result<ResponseJson<SupervisorStatus>, AppError> {
+    Ok(ResponseJson(tasks.status()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SandboxClockResponse {
+    pub now: DateTime<Utc>,
+    #[serde(flatten)]
+    pub mode: SandboxClockMode,
+}
+
+fn sandbox_clock_response(clock: &SandboxClock) -> SandboxClockResponse {
+    SandboxClockResponse { now: clock.now(), mode: clock.mode() }
+}
+
+fn require_sandbox_clock(clock: Option<Arc<SandboxClock>>) -> Result<Arc<SandboxClock>, AppError> {
+    clock.ok_or_else(|| AppError::NotFound("Sandbox clock is disabled (set SANDBOX_CLOCK outside production)".to_string()))
+}
+
+/// Время песочницы и режим часов
+pub async fn get_sandbox_clock(
+    Extension(clock): Extension<Option<Arc<SandboxClock>>>,
+) -> Result<ResponseJson<SandboxClockResponse>, AppError> {
+    let clock = require_sandbox_clock(clock)?;
+    Ok(ResponseJson(sandbox_clock_response(&clock)))
+}
+
+/// Сдвиг или остановка времени песочницы: `{"mode": "offset", "seconds": 86400}`,
+/// `{"mode": "frozen", "at": "2026-10-18T23:59:00Z"}`, `{"mode": "real"}`
+pub async fn set_sandbox_clock(
+    Extension(clock): Extension<Option<Arc<SandboxClock>>>,
+    claims: Claims,
+    Json(mode): Json<SandboxClockMode>,
+) -> Result<ResponseJson<SandboxClockResponse>, AppError> {
+    let clock = require_sandbox_clock(clock)?;
+    clock.set_mode(mode);
+    tracing::warn!("⏰ Sandbox clock set to {:?} by admin {}", mode, claims.sub);
+    Ok(ResponseJson(sandbox_clock_response(&clock)))
+}
+
+/// Оценка расходов на ИИ за месяц: по провайдерам, функциям, моделям и пользователям,
+/// прогноз на конец месяца и состояние бюджета
+pub async fn get_ai_costs(
+    Extension(pools): Extension<DbPools>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
+    Query(params): Query<AiCostParams>,
+) -> Result<ResponseJson<AiCostReport>, AppError> {
+    if params.period.as_deref().is_some_and(|period| period != "month") {
+        return Err(AppError::BadRequest("Unsupported period, expected month".to_string()));
+    }
+
+    let now = clock.now();
+    let month = match params.month.as_deref() {
+        Some(month) => parse_month(month)?,
+        None => month_start(now),
+    };
+
+    // Отчет только читает журнал вызовов — реплики достаточно
+    let report = AiCostService::new(pools.read(ReadConsistency::Replica).clone(), config.ai_costs)
+        .report(month, now)
+        .await?;
+    Ok(ResponseJson(report))
+}
+
+/// Отзывы на разделы отчета о холодильнике по типам разделов, худшие — первыми
+pub async fn get_report_section_feedback(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    Query(params): Query<ReportSectionFeedbackParams>,
+) -> Result<ResponseJson<Vec<SectionFeedbackStats>>, AppError> {
+    let days = params.days.unwrap_or(30);
+    if !(1..=365).contains(&days) {
+        return Err(AppError::BadRequest("days must be between 1 and 365".to_string()));
+    }
+
+    let stats = FridgeReportFeedbackService::new(pools.read(ReadConsistency::Replica).clone())
+        .admin_stats(clock.now() - Duration::days(days))
+        .await?;
+    Ok(ResponseJson(stats))
+}
+
+/// Запускает фоновый пересчет питательности рецептов (всех или с ингредиентом `?ingredient_name=`).
+/// Прогресс доступен через GET /admin/jobs/{id}.
+pub async fn recalculate_recipe_nutrition(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Query(params): Query<RecalculateNutritionParams>,
+) -> Result<ResponseJson<AdminJob>, AppError> {
+    let recalculator = RecipeNutritionRecalculator::new(pool);
+    let job = recalculator.enqueue(claims.sub, params).await?;
+
+    Ok(ResponseJson(job))
+}
+
+pub async fn get_job(
+    Extension(pool): Extension<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<AdminJob>, AppError> {
+    let job_service = AdminJobService::new(pool);
+    let job = job_service.get_job(id).await?;
+
+    Ok(ResponseJson(job))
+}
+
+/// Поиск пользователей для поддержки
+pub async fn search_users(
+    Extension(pool): Extension<DbPool>,
+    Query(params): Query<UserSearchParams>,
+) -> Result<ResponseJson<Vec<AdminUserSummary>>, AppError> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let admin_user_service = AdminUserService::new(pool);
+    let users = admin_user_service.search_users(params.q.as_deref(), limit, offset).await?;
+
+    Ok(ResponseJson(users))
+}
+
+/// Состояние аккаунта: метаданные, счетчики и сессии, без содержимого записей
+pub async fn get_user_detail(
+    Extension(pool): Extension<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<AdminUserDetail>, AppError> {
+    let admin_user_service = AdminUserService::new(pool);
+    let detail = admin_user_service.user_detail(id).await?;
+
+    Ok(ResponseJson(detail))
+}
+
+pub async fn suspend_user(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<SuspendUserRequest>,
+) -> Result<ResponseJson<AdminUserSummary>, AppError> {
+    payload.validate()?;
+
+    let admin_user_service = AdminUserService::new(pool);
+    let user = admin_user_service.suspend(claims.sub, id, &payload.reason).await?;
+
+    Ok(ResponseJson(user))
+}
+
+pub async fn unsuspend_user(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<AdminUserSummary>, AppError> {
+    let admin_user_service = AdminUserService::new(pool);
+    let user = admin_user_service.unsuspend(claims.sub, id).await?;
+
+    Ok(ResponseJson(user))
+}
+
+/// Объединяет аккаунт-дубликат с основным. Прерванное объединение продолжается
+/// повторным запросом с теми же аккаунтами; `dry_run` возвращает число строк по доменам.
+pub async fn merge_users(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<MergeUsersRequest>,
+) -> Result<ResponseJson<AccountMergeResult>, AppError> {
+    let merge_service = AccountMergeService::new(pool);
+    let result = if payload.dry_run {
+        merge_service.preview(payload.source_user_id, payload.target_user_id).await?
+    } else {
+        merge_service.merge(claims.sub, payload.source_user_id, payload.target_user_id).await?
+    };
+
+    Ok(ResponseJson(result))
+}
+
+/// Завершает все сессии пользователя и отправляет ему ссылку для сброса пароля
+pub async fn force_password_reset(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let admin_user_service = AdminUserService::new(pool);
+    admin_user_service.force_password_reset(claims.sub, id, &MailService::from_env()).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "Sessions revoked and password reset email sent"})))
+}
@@ -1,35 +1,95 @@
 use axum::{
-    extract::{State, Json},
-    response::Json as ResponseJson,
+    extract::{State, Json, Path},
+    http::header,
+    response::{IntoResponse, Json as ResponseJson},
+    routing::{get, post},
     Extension,
+    Router,
 };
 use serde::{Deserialize, Serialize};
-use chrono::Timelike;
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
 use rand::Rng;
-use crate::services::ai::AiService;
+use validator::Validate;
+use uuid::Uuid;
+use crate::services::ai::{AiService, AiResponseMeta};
+use crate::services::ai_generation::{AiFeature, GenerationOverride};
+use crate::services::ai_history::AiHistoryService;
+use crate::services::card_actions::{attach_actions, ActionContext, CardActionService};
+use crate::services::clock::SharedClock;
+use crate::models::card_action::{CardAction, CardActionResult};
+use crate::services::fridge_report_feedback::{FridgeReportFeedbackService, ReportSectionWeights};
+use crate::services::preferences::{PreferencesService, UserLocale};
+use crate::models::ai_response::{
+    AiResponseKind, AiResponseProvenance, ReportFeedbackRequest, ReportFeedbackResponse, ReportSection,
+};
+use crate::models::conversation::{
+    AppliedGoalChange, Conversation, ConversationKind, ConversationMessage, GoalReviewContext, GoalReviewOutcome,
+};
+use crate::services::ai_conversations::ConversationService;
+use crate::services::goal_review::GoalReviewService;
+use crate::services::fasting::FastingService;
+use crate::models::health::{FastingState, FastingStatus};
+use crate::services::mock_ai::{self, MockEndpoint, MockScenario, MockScenarioDescription};
+use crate::config::Config;
 use crate::utils::errors::AppError;
 use crate::services::auth::Claims;
+use crate::app::AppState;
+use crate::utils::sanitize::{prompt_safe, user_data_block, PROMPT_DATA_NOTICE, MAX_PROMPT_FIELD_CHARS};
+
+pub fn routes(state: &AppState) -> Router {
+    Router::new()
+        .route("/chat", post(chat_with_ai))
+        .route("/generate-recipe", post(generate_recipe))
+        .route("/analyze-nutrition", post(analyze_nutrition))
+        .route("/proactive-message", post(generate_proactive_message))
+        .route("/actions/execute", post(execute_card_action))
+        // Новые маршруты для интеграции с холодильником
+        .route("/fridge/analyze", post(analyze_fridge))
+        .route("/fridge/recipes", post(generate_fridge_recipes))
+        .route("/fridge/report", get(fridge_quick_report))
+        .route("/fridge/report/:report_id/feedback", post(submit_fridge_report_feedback))
+        // История ответов ИИ и данные, на которых они построены
+        .route("/responses/export", get(export_ai_history))
+        .route("/responses/:id/provenance", get(get_response_provenance))
+        // Диалоги с помощником, в том числе еженедельный разбор целей
+        .route("/conversations", get(list_conversations).post(create_conversation))
+        .route("/conversations/:id", get(get_conversation))
+        .route("/conversations/:id/messages", post(send_conversation_message))
+        .route("/conversations/:id/conclude", post(conclude_conversation))
+        .route("/conversations/:id/apply", post(apply_conversation_outcome))
+        // Сценарии Mock-провайдера для разработки фронтенда
+        .route("/mock-scenarios", get(list_mock_scenarios))
+        .with_state(state.ai_service.clone())
+}
 
 #[derive(Debug, Deserialize)]
 pub struct AiChatRequest {
     pub message: String,
     pub context: Option<String>, // Контекст пользователя (цели, предпочтения и т.д.)
+    /// Настройки генерации поверх серверных (урезаются до допустимых границ)
+    #[serde(default)]
+    pub generation: Option<GenerationOverride>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AiCard {
     pub title: String,
     pub content: String,
     pub emoji: Option<String>,
     pub category: Option<String>, // nutrition, health, recipe, motivation, general
     pub priority: Option<String>, // high, medium, low
+    /// Что выполнит нажатие (POST /ai/actions/execute); нет — карточка только текстовая
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<CardAction>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AiChatResponse {
     pub response: String,
     pub suggestions: Option<Vec<String>>, // Дополнительные предложения
     pub cards: Option<Vec<AiCard>>, // Структурированные карточки
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,6 +107,8 @@ pub struct AiProactiveMessage {
     pub urgency: String, // high, medium, low
     pub cards: Option<Vec<AiCard>>,
     pub suggestions: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,6 +118,8 @@ pub struct RecipeGenerationRequest {
     pub cuisine_type: Option<String>,
     pub cooking_time: Option<i32>, // в минутах
     pub difficulty: Option<String>,
+    #[serde(default)]
+    pub generation: Option<GenerationOverride>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,16 +127,27 @@ pub struct FridgeAnalysisRequest {
     pub analysis_type: String, // "report", "recipes", "expiry", "waste", "shopping"
     pub max_recipes: Option<u8>,
     pub include_diet_check: Option<bool>,
+    pub locale: Option<crate::utils::format::Locale>,
+    #[serde(default)]
+    pub generation: Option<GenerationOverride>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FridgeAnalysisResponse {
     pub summary: String,
+    /// Разделы полного отчета; `id` раздела — для POST /ai/fridge/report/{response_id}/feedback
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<ReportSection>,
     pub recommendations: Vec<String>,
     pub recipes: Option<Vec<crate::services::ai::GeneratedRecipe>>,
     pub alerts: Vec<crate::services::ai::FridgeAlert>,
     pub insights: Vec<String>,
     pub cards: Option<Vec<AiCard>>,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
+    /// Запись в истории ответов ИИ (GET /ai/responses/{id}/provenance)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -87,47 +162,84 @@ pub struct FridgeRecipeRequest {
 pub struct FridgeRecipeResponse {
     pub recipes: Vec<crate::services::ai::GeneratedRecipe>,
     pub missing_ingredients_summary: Vec<String>,
+    /// Недостающие ингредиенты с советами: уже есть в холодильнике, обычно выбрасывается и т.п.
+    pub shopping_list: Vec<crate::models::shopping::ShoppingListItem>,
     pub shopping_suggestions: Vec<String>,
     pub cards: Option<Vec<AiCard>>,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
+    /// Запись в истории ответов ИИ (GET /ai/responses/{id}/provenance)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<Uuid>,
 }
 
 /// Обработчик для общения с ИИ-помощником
 pub async fn chat_with_ai(
     State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
+    claims: Claims,
     Json(request): Json<AiChatRequest>,
 ) -> Result<ResponseJson<AiChatResponse>, AppError> {
-    // Формируем контекстный промпт
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    if let Some(mut fixture) = ai_service.mock_fixture::<AiChatResponse>(MockEndpoint::Chat)? {
+        // Сценарий без готовых подсказок получает их так же, как ответ провайдера
+        if fixture.suggestions.is_none() && fixture.cards.is_none() {
+            let (suggestions, cards) = chat_followups(&request.message, &fixture.response);
+            fixture.suggestions = Some(suggestions);
+            fixture.cards = cards;
+        }
+        return Ok(ResponseJson(fixture));
+    }
+
+    // Формируем контекстный промпт; текст пользователя передаем отдельными блоками данных
     let context_prompt = if let Some(context) = &request.context {
         format!(
-            "Контекст пользователя: {}. Вопрос пользователя: {}",
-            context,
-            request.message
+            "{}Контекст пользователя:\n{}Вопрос пользователя:\n{}",
+            PROMPT_DATA_NOTICE,
+            user_data_block("context", context),
+            user_data_block("question", &request.message)
         )
     } else {
         format!(
-            "Ты - ИИ помощник в кулинарном приложении IT Cook. Помогай пользователям с рецептами, советами по готовке, планированию питания и достижению целей. Вопрос: {}",
-            request.message
+            "Ты - ИИ помощник в кулинарном приложении IT Cook. Помогай пользователям с рецептами, советами по готовке, планированию питания и достижению целей. {}Вопрос:\n{}",
+            PROMPT_DATA_NOTICE,
+            user_data_block("question", &request.message)
         )
     };
 
     // Получаем ответ от ИИ
-    let ai_response = ai_service.generate_response(&context_prompt).await?;
-    
-    // Генерируем дополнительные предложения на основе ответа
-    let suggestions = generate_suggestions(&request.message, &ai_response);
-    
-    // Генерируем карточки для структурированного ответа
-    let cards = generate_response_cards(&request.message, &request.context);
+    let completion = ai_service.complete_with(&context_prompt, AiFeature::Chat, request.generation.as_ref()).await?;
+    let ai_response = completion.text;
+    let (suggestions, cards) = chat_followups(&request.message, &ai_response);
 
     Ok(ResponseJson(AiChatResponse {
         response: ai_response,
         suggestions: Some(suggestions),
         cards,
+        meta: completion.meta,
     }))
 }
 
-/// Генерирует предложения для продолжения разговора
-fn generate_suggestions(user_message: &str, _ai_response: &str) -> Vec<String> {
+/// Подсказки и карточки по содержанию ответа; если в ответе не нашлось ни блюд, ни времени,
+/// ни цифр, или подсказок меньше трех — добираются подсказки по ключевым словам вопроса
+fn chat_followups(user_message: &str, ai_response: &str) -> (Vec<String>, Option<Vec<AiCard>>) {
+    let followups = crate::services::chat_followups::from_answer(user_message, ai_response);
+    let (mut suggestions, mut cards) = match followups {
+        Some(followups) if !followups.cards.is_empty() => (followups.suggestions, Some(followups.cards)),
+        Some(followups) => (followups.suggestions, keyword_cards(user_message)),
+        None => (Vec::new(), keyword_cards(user_message)),
+    };
+    suggestions.extend(keyword_suggestions(user_message));
+    // Действия без данных пользователя: вода, таймеры, цели из шаблонов
+    if let Some(cards) = cards.as_mut() {
+        attach_actions(cards, "chat", &ActionContext::default());
+    }
+
+    (crate::services::chat_followups::without_question(user_message, suggestions), cards)
+}
+
+/// Подсказки по ключевым словам вопроса
+fn keyword_suggestions(user_message: &str) -> Vec<String> {
     let user_lower = user_message.to_lowercase();
     
     if user_lower.contains("суп") {
@@ -175,8 +287,8 @@ fn generate_suggestions(user_message: &str, _ai_response: &str) -> Vec<String> {
     }
 }
 
-/// Генерирует структурированные карточки для ответа
-fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Option<Vec<AiCard>> {
+/// Карточки по ключевым словам вопроса
+fn keyword_cards(user_message: &str) -> Option<Vec<AiCard>> {
     let user_lower = user_message.to_lowercase();
     
     // Проверяем конкретные блюда
@@ -188,6 +300,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🍜".to_string()),
                 category: Some("recipe".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "🥄 Совет по подаче".to_string(),
@@ -195,6 +308,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🥄".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
             AiCard {
                 title: "📊 Пищевая ценность".to_string(),
@@ -202,6 +316,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("📊".to_string()),
                 category: Some("nutrition".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ])
     } else if user_lower.contains("салат") {
@@ -212,6 +327,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🥗".to_string()),
                 category: Some("recipe".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "🌿 Заправка".to_string(),
@@ -219,6 +335,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🌿".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ])
     } else if user_lower.contains("мясо") || user_lower.contains("курица") || user_lower.contains("говядина") {
@@ -229,6 +346,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🍖".to_string()),
                 category: Some("recipe".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "🔥 Способ приготовления".to_string(),
@@ -236,6 +354,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🔥".to_string()),
                 category: Some("health".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ])
     } else if user_lower.contains("рецепт") || user_lower.contains("готовить") || user_lower.contains("приготовить") {
@@ -246,6 +365,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🍳".to_string()),
                 category: Some("recipe".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
             AiCard {
                 title: "⏱️ Экономия времени".to_string(),
@@ -253,6 +373,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("⏱️".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("low".to_string()),
+                action: None,
             },
         ])
     } else if user_lower.contains("диета") || user_lower.contains("похудеть") {
@@ -263,6 +384,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🥗".to_string()),
                 category: Some("nutrition".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "💧 Гидратация".to_string(),
@@ -270,6 +392,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("💧".to_string()),
                 category: Some("health".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ])
     } else if user_lower.contains("привет") || user_lower.contains("здравствуй") {
@@ -280,6 +403,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("👋".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "✨ Начните с целей".to_string(),
@@ -287,6 +411,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("✨".to_string()),
                 category: Some("motivation".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ])
     } else {
@@ -298,6 +423,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("💡".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
             AiCard {
                 title: "🍽️ Подача блюда".to_string(),
@@ -305,6 +431,7 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
                 emoji: Some("🍽️".to_string()),
                 category: Some("general".to_string()),
                 priority: Some("low".to_string()),
+                action: None,
             },
         ])
     }
@@ -313,35 +440,61 @@ fn generate_response_cards(user_message: &str, _context: &Option<String>) -> Opt
 /// Генерация рецепта на основе ингредиентов и предпочтений
 pub async fn generate_recipe(
     State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
+    Extension(pool): Extension<crate::db::DbPool>,
+    UserLocale(locale): UserLocale,
+    claims: Claims,
     Json(request): Json<RecipeGenerationRequest>,
 ) -> Result<ResponseJson<AiChatResponse>, AppError> {
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    if let Some(response) = ai_service.mock_fixture::<AiChatResponse>(MockEndpoint::GenerateRecipe)? {
+        return Ok(ResponseJson(response));
+    }
+    let glossary = PreferencesService::new(pool).glossary(claims.sub, locale).await?;
+
+    let ingredients: Vec<String> = request.ingredients
+        .iter()
+        .map(|ingredient| prompt_safe(ingredient, MAX_PROMPT_FIELD_CHARS))
+        .collect();
+
     let mut prompt = format!(
-        "Создай подробный рецепт используя эти ингредиенты: {}",
-        request.ingredients.join(", ")
+        "{}Создай подробный рецепт используя эти ингредиенты:\n{}",
+        PROMPT_DATA_NOTICE,
+        user_data_block("ingredients", &ingredients.join(", "))
     );
+    if let Some(glossary_block) = glossary.prompt_block(request.ingredients.iter().map(String::as_str)) {
+        prompt.push_str(&glossary_block);
+    }
+
+    let mut preferences = String::new();
 
     if let Some(restrictions) = &request.dietary_restrictions {
-        prompt.push_str(&format!(". Учти диетические ограничения: {}", restrictions.join(", ")));
+        preferences.push_str(&format!("Диетические ограничения: {}\n", prompt_safe(&restrictions.join(", "), MAX_PROMPT_FIELD_CHARS)));
     }
 
     if let Some(cuisine) = &request.cuisine_type {
-        prompt.push_str(&format!(". Стиль кухни: {}", cuisine));
+        preferences.push_str(&format!("Стиль кухни: {}\n", prompt_safe(cuisine, MAX_PROMPT_FIELD_CHARS)));
     }
 
-    if let Some(time) = request.cooking_time {
-        prompt.push_str(&format!(". Время приготовления не более {} минут", time));
+    if let Some(difficulty) = &request.difficulty {
+        preferences.push_str(&format!("Уровень сложности: {}\n", prompt_safe(difficulty, MAX_PROMPT_FIELD_CHARS)));
     }
 
-    if let Some(difficulty) = &request.difficulty {
-        prompt.push_str(&format!(". Уровень сложности: {}", difficulty));
+    if !preferences.is_empty() {
+        prompt.push_str("Учти пожелания пользователя:\n");
+        prompt.push_str(&user_data_block("preferences", &preferences));
     }
 
-    prompt.push_str(". Предоставь: название, список ингредиентов с количествами, пошаговые инструкции, время приготовления, и советы по подаче.");
+    if let Some(time) = request.cooking_time {
+        prompt.push_str(&format!("Время приготовления не более {} минут.\n", time));
+    }
+
+    prompt.push_str("Предоставь: название, список ингредиентов с количествами, пошаговые инструкции, время приготовления, и советы по подаче.");
 
-    let ai_response = ai_service.generate_response(&prompt).await?;
+    let completion = ai_service.complete_with(&prompt, AiFeature::RecipeGeneration, request.generation.as_ref()).await?;
 
     Ok(ResponseJson(AiChatResponse {
-        response: ai_response,
+        response: completion.text,
         suggestions: Some(vec![
             "Изменить ингредиенты".to_string(),
             "Упростить рецепт".to_string(),
@@ -354,8 +507,10 @@ pub async fn generate_recipe(
                 emoji: Some("🍳".to_string()),
                 category: Some("recipe".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
         ]),
+        meta: completion.meta,
     }))
 }
 
@@ -363,25 +518,35 @@ pub async fn generate_recipe(
 pub struct NutritionAnalysisRequest {
     pub recipe_text: String,
     pub servings: Option<i32>,
+    #[serde(default)]
+    pub generation: Option<GenerationOverride>,
 }
 
 /// Анализ пищевой ценности рецепта
 pub async fn analyze_nutrition(
     State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
+    claims: Claims,
     Json(request): Json<NutritionAnalysisRequest>,
 ) -> Result<ResponseJson<AiChatResponse>, AppError> {
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    if let Some(response) = ai_service.mock_fixture::<AiChatResponse>(MockEndpoint::AnalyzeNutrition)? {
+        return Ok(ResponseJson(response));
+    }
+
     let servings = request.servings.unwrap_or(1);
     
     let prompt = format!(
-        "Проанализируй пищевую ценность этого рецепта на {} порций и предоставь приблизительные данные о калориях, белках, жирах, углеводах, витаминах и минералах. Рецепт: {}",
+        "{}Проанализируй пищевую ценность этого рецепта на {} порций и предоставь приблизительные данные о калориях, белках, жирах, углеводах, витаминах и минералах. Рецепт:\n{}",
+        PROMPT_DATA_NOTICE,
         servings,
-        request.recipe_text
+        user_data_block("recipe", &request.recipe_text)
     );
 
-    let ai_response = ai_service.generate_response(&prompt).await?;
+    let completion = ai_service.complete_with(&prompt, AiFeature::NutritionAnalysis, request.generation.as_ref()).await?;
 
     Ok(ResponseJson(AiChatResponse {
-        response: ai_response,
+        response: completion.text,
         suggestions: Some(vec![
             "Как снизить калорийность?".to_string(),
             "Добавить больше белка".to_string(),
@@ -394,28 +559,105 @@ pub async fn analyze_nutrition(
                 emoji: Some("📊".to_string()),
                 category: Some("nutrition".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
         ]),
+        meta: completion.meta,
     }))
 }
 
 /// Генерирует активное сообщение от ИИ при заходе в профиль
 pub async fn generate_proactive_message(
-    _state: State<AiService>,
+    State(ai_service): State<AiService>,
+    Extension(pool): Extension<crate::db::DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
     Json(request): Json<ProactiveMessageRequest>,
 ) -> Result<ResponseJson<AiProactiveMessage>, AppError> {
     
     // Получаем текущий час для контекстных сообщений
-    let current_hour = chrono::Utc::now().hour();
-    
-    // Генерируем активное сообщение на основе времени и контекста
-    let proactive_message = generate_contextual_proactive_message(current_hour, &request);
+    let now = clock.now();
+    let current_hour = now.hour();
     
+    // Сообщения строятся по шаблонам, без вызова модели
+    let meta = ai_service.response_meta();
+
+    // Голодание с окном питания, которое скоро откроется, важнее сообщения по времени суток
+    let fasting = FastingService::new(pool.clone()).status(claims.sub, now).await?;
+    let mut proactive_message = match fasting_proactive_message(&fasting, now, &meta) {
+        Some(message) => message,
+        // Генерируем активное сообщение на основе времени и контекста
+        None => generate_contextual_proactive_message(current_hour, &request, &meta),
+    };
+
+    // Действия карточек по данным пользователя: например, обычный завтрак — в дневник одним нажатием
+    let context = CardActionService::new(pool).context(claims.sub).await?;
+    if let Some(cards) = proactive_message.cards.as_mut() {
+        attach_actions(cards, &proactive_message.trigger_type, &context);
+    }
+
     Ok(ResponseJson(proactive_message))
 }
 
+/// Выполняет действие карточки от имени пользователя и возвращает созданный ресурс
+pub async fn execute_card_action(
+    Extension(pool): Extension<crate::db::DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(action): Json<CardAction>,
+) -> Result<ResponseJson<CardActionResult>, AppError> {
+    let result = CardActionService::new(pool).with_clock(clock).execute(claims.sub, action).await?;
+    Ok(ResponseJson(result))
+}
+
+/// За сколько часов до открытия окна питания активное сообщение говорит о голодании
+const FASTING_MESSAGE_LEAD_HOURS: i64 = 3;
+
+/// Сообщение о голодании, если окно питания откроется в ближайшие `FASTING_MESSAGE_LEAD_HOURS` часов
+fn fasting_proactive_message(status: &FastingStatus, now: DateTime<Utc>, meta: &AiResponseMeta) -> Option<AiProactiveMessage> {
+    if status.state != Some(FastingState::Fasting) {
+        return None;
+    }
+    let hours_fasted = status.hours_since_last_meal?;
+    let minutes_to_open = (status.window_opens_at? - now).num_minutes();
+    if minutes_to_open > FASTING_MESSAGE_LEAD_HOURS * 60 {
+        return None;
+    }
+
+    let opens_in = if minutes_to_open >= 60 {
+        format!("{} ч", (minutes_to_open as f64 / 60.0).round())
+    } else {
+        format!("{} мин", minutes_to_open.max(1))
+    };
+
+    Some(AiProactiveMessage {
+        message: format!(
+            "⏳ Ты не ешь уже {} ч, окно питания откроется через {} — план на первый приём?",
+            hours_fasted.floor(),
+            opens_in
+        ),
+        trigger_type: "fasting".to_string(),
+        urgency: "medium".to_string(),
+        cards: Some(vec![
+            AiCard {
+                title: "🥗 Первый приём после голодания".to_string(),
+                content: "Начни с белка и овощей — так проще избежать переедания".to_string(),
+                emoji: Some("🥗".to_string()),
+                category: Some("nutrition".to_string()),
+                priority: Some("high".to_string()),
+                action: None,
+            },
+        ]),
+        suggestions: Some(vec![
+            "Что съесть после голодания?".to_string(),
+            "Рецепты из моего холодильника".to_string(),
+        ]),
+        meta: meta.clone(),
+    })
+}
+
 /// Генерирует контекстное активное сообщение на основе времени и активности пользователя
-fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRequest) -> AiProactiveMessage {
+fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRequest, meta: &AiResponseMeta) -> AiProactiveMessage {
     use rand::Rng;
     let mut rng = rand::thread_rng();
     
@@ -438,6 +680,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("⚡".to_string()),
                             category: Some("recipe".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                         AiCard {
                             title: "☕ Бодрящий напиток".to_string(),
@@ -445,6 +688,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("☕".to_string()),
                             category: Some("health".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                     ]),
                     suggestions: Some(vec![
@@ -452,6 +696,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         "Натуральные энергетики".to_string(),
                         "Быстрые рецепты на утро".to_string(),
                     ]),
+                    meta: meta.clone(),
                 },
             ]
         } else {
@@ -467,6 +712,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("💤".to_string()),
                             category: Some("health".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                         AiCard {
                             title: "🥛 Сбалансированный завтрак".to_string(),
@@ -474,6 +720,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🥛".to_string()),
                             category: Some("nutrition".to_string()),
                             priority: Some("medium".to_string()),
+                            action: None,
                         },
                     ]),
                     suggestions: Some(vec![
@@ -481,6 +728,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         "Сбалансированные завтраки".to_string(),
                         "Режим питания и сна".to_string(),
                     ]),
+                    meta: meta.clone(),
                 },
                 AiProactiveMessage {
                     message: "🌅 Доброе утро! Я вижу, ты не завтракал. Хочешь рецепт за 5 минут? Быстро, вкусно и полезно!".to_string(),
@@ -493,6 +741,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("⏱️".to_string()),
                             category: Some("recipe".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                         AiCard {
                             title: "🍌 Быстрая альтернатива".to_string(),
@@ -500,6 +749,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🍌".to_string()),
                             category: Some("recipe".to_string()),
                             priority: Some("medium".to_string()),
+                            action: None,
                         },
                     ]),
                     suggestions: Some(vec![
@@ -507,6 +757,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         "Что пить с утра?".to_string(),
                         "Полезные перекусы".to_string(),
                     ]),
+                    meta: meta.clone(),
                 },
             ]
         };
@@ -528,6 +779,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🌳".to_string()),
                             category: Some("motivation".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                         AiCard {
                             title: "🍫 Натуральные антидепрессанты".to_string(),
@@ -535,6 +787,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🍫".to_string()),
                             category: Some("health".to_string()),
                             priority: Some("medium".to_string()),
+                            action: None,
                         },
                     ]),
                     suggestions: Some(vec![
@@ -542,6 +795,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         "Активности на свежем воздухе".to_string(),
                         "Быстрые упражнения".to_string(),
                     ]),
+                    meta: meta.clone(),
                 },
             ]
         } else {
@@ -557,6 +811,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🥜".to_string()),
                             category: Some("nutrition".to_string()),
                             priority: Some("high".to_string()),
+                            action: None,
                         },
                         AiCard {
                             title: "🚶‍♂️ Микро-активность".to_string(),
@@ -564,6 +819,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                             emoji: Some("🚶‍♂️".to_string()),
                             category: Some("motivation".to_string()),
                             priority: Some("medium".to_string()),
+                            action: None,
                         },
                     ]),
                     suggestions: Some(vec![
@@ -571,6 +827,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         "Быстрые упражнения".to_string(),
                         "Полезные сладости".to_string(),
                     ]),
+                    meta: meta.clone(),
                 },
             ]
         };
@@ -591,6 +848,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         emoji: Some("📊".to_string()),
                         category: Some("nutrition".to_string()),
                         priority: Some("high".to_string()),
+                        action: None,
                     },
                     AiCard {
                         title: "🌙 Легкий ужин".to_string(),
@@ -598,6 +856,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         emoji: Some("🌙".to_string()),
                         category: Some("health".to_string()),
                         priority: Some("medium".to_string()),
+                        action: None,
                     },
                 ]),
                 suggestions: Some(vec![
@@ -605,6 +864,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                     "Легкие ужины".to_string(),
                     "План на завтра".to_string(),
                 ]),
+                meta: meta.clone(),
             },
             AiProactiveMessage {
                 message: "🎯 Отличная работа сегодня! Помни: каждое здоровое решение - это шаг к твоей цели. Гордись собой!".to_string(),
@@ -617,6 +877,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         emoji: Some("🏆".to_string()),
                         category: Some("motivation".to_string()),
                         priority: Some("high".to_string()),
+                        action: None,
                     },
                     AiCard {
                         title: "📅 Завтрашние цели".to_string(),
@@ -624,6 +885,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                         emoji: Some("📅".to_string()),
                         category: Some("general".to_string()),
                         priority: Some("medium".to_string()),
+                        action: None,
                     },
                 ]),
                 suggestions: Some(vec![
@@ -631,6 +893,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                     "Планирование завтра".to_string(),
                     "Мотивационные советы".to_string(),
                 ]),
+                meta: meta.clone(),
             },
         ];
         return evening_messages[rng.gen_range(0..evening_messages.len())].clone();
@@ -648,6 +911,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                 emoji: Some("😴".to_string()),
                 category: Some("health".to_string()),
                 priority: Some("high".to_string()),
+                action: None,
             },
             AiCard {
                 title: "🛏️ Подготовка ко сну".to_string(),
@@ -655,6 +919,7 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
                 emoji: Some("🛏️".to_string()),
                 category: Some("health".to_string()),
                 priority: Some("medium".to_string()),
+                action: None,
             },
         ]),
         suggestions: Some(vec![
@@ -662,17 +927,26 @@ fn generate_contextual_proactive_message(hour: u32, request: &ProactiveMessageRe
             "Вечерние ритуалы".to_string(),
             "Режим отдыха".to_string(),
         ]),
+        meta: meta.clone(),
     }
 }
 
 /// Анализ холодильника с ИИ-помощником
 pub async fn analyze_fridge(
+    State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
     Extension(pool): Extension<crate::db::DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    UserLocale(user_locale): UserLocale,
     claims: Claims,
     Json(payload): Json<FridgeAnalysisRequest>,
 ) -> Result<ResponseJson<FridgeAnalysisResponse>, AppError> {
-    let ai_service = AiService::from_env();
-    let fridge_service = crate::services::fridge::FridgeService::new(pool);
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    let fridge_service = crate::services::fridge::FridgeService::new(pool.clone()).with_clock(clock.clone());
+    let dietary_restrictions = crate::services::dietary::DietaryService::new(pool.clone())
+        .get_profile(claims.sub)
+        .await?
+        .map(|profile| crate::services::dietary::to_restriction(&profile));
     
     // Определяем тип анализа
     let analysis_type = match payload.analysis_type.as_str() {
@@ -681,17 +955,33 @@ pub async fn analyze_fridge(
         "expiry" => crate::services::ai::FridgeAnalysisType::ExpiryAlert,
         "waste" => crate::services::ai::FridgeAnalysisType::WasteAnalysis,
         "shopping" => crate::services::ai::FridgeAnalysisType::ShoppingSuggestions,
+        "dietary" => crate::services::ai::FridgeAnalysisType::DietaryCheck,
         _ => crate::services::ai::FridgeAnalysisType::FullReport,
     };
+    let section_weights = match analysis_type {
+        crate::services::ai::FridgeAnalysisType::FullReport => {
+            FridgeReportFeedbackService::new(pool.clone()).weights(claims.sub).await?
+        }
+        _ => ReportSectionWeights::default(),
+    };
+    // Названия продуктов — на языке запроса, если он указан, иначе на языке пользователя
+    let glossary = PreferencesService::new(pool.clone())
+        .glossary(claims.sub, payload.locale.unwrap_or(user_locale))
+        .await?;
     
     let request = crate::services::ai::FridgeAnalysisRequest {
         analysis_type,
         include_recipes: Some(payload.analysis_type == "recipes" || payload.analysis_type == "report"),
-        dietary_restrictions: None, // TODO: Получать из профиля пользователя
+        dietary_restrictions: dietary_restrictions.map(|restriction| vec![restriction]),
         max_recipes: payload.max_recipes,
+        locale: payload.locale.unwrap_or_default(),
+        generation: payload.generation,
+        section_weights,
+        glossary,
     };
     
-    let result = ai_service.analyze_fridge(claims.sub, request, &fridge_service).await?;
+    let mut result = ai_service.analyze_fridge(claims.sub, request, &fridge_service).await?;
+    let provenance = result.provenance.take();
     
     // Создаем карточки на основе результатов
     let mut cards = Vec::new();
@@ -703,6 +993,7 @@ pub async fn analyze_fridge(
         emoji: Some("📊".to_string()),
         category: Some("fridge".to_string()),
         priority: Some("high".to_string()),
+        action: None,
     });
     
     // Карточки для критических уведомлений
@@ -730,42 +1021,54 @@ pub async fn analyze_fridge(
                     crate::services::ai::AlertUrgency::High => "medium".to_string(),
                     _ => "low".to_string(),
                 }),
+                action: None,
             });
         }
     }
     
-    Ok(ResponseJson(FridgeAnalysisResponse {
+    let mut response = FridgeAnalysisResponse {
         summary: result.summary,
+        sections: result.sections,
         recommendations: result.recommendations,
         recipes: result.recipes,
         alerts: result.alerts,
         insights: result.insights,
         cards: Some(cards),
-    }))
+        meta: result.meta,
+        response_id: None,
+    };
+    response.response_id = AiHistoryService::new(pool)
+        .record_or_warn(claims.sub, AiResponseKind::FridgeAnalysis, provenance.as_ref(), &response)
+        .await;
+
+    Ok(ResponseJson(response))
 }
 
 /// Генерация рецептов на основе содержимого холодильника
 pub async fn generate_fridge_recipes(
+    State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
     Extension(pool): Extension<crate::db::DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    UserLocale(locale): UserLocale,
     claims: Claims,
     Json(payload): Json<FridgeRecipeRequest>,
 ) -> Result<ResponseJson<FridgeRecipeResponse>, AppError> {
-    let ai_service = AiService::from_env();
-    let fridge_service = crate::services::fridge::FridgeService::new(pool);
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    let fridge_service = crate::services::fridge::FridgeService::new(pool.clone()).with_clock(clock.clone());
     
-    // Создаем диетические ограничения если указаны
-    let dietary_restrictions = payload.dietary_restrictions.map(|_restrictions| {
-        crate::services::ai::DietaryRestriction {
-            allergens: Vec::new(), // TODO: Парсить из строк
-            intolerances: Vec::new(),
-            diets: Vec::new(),
-        }
-    });
+    // Диетические ограничения (с уровнями серьезности аллергий) берем из профиля пользователя
+    let dietary_restrictions = crate::services::dietary::DietaryService::new(pool.clone())
+        .get_profile(claims.sub)
+        .await?
+        .map(|profile| crate::services::dietary::to_restriction(&profile));
+    let glossary = PreferencesService::new(pool.clone()).glossary(claims.sub, locale).await?;
     
-    let recipes = ai_service.generate_recipes_from_fridge(
+    let (recipes, meta, provenance) = ai_service.generate_recipes_from_fridge(
         claims.sub,
         payload.max_recipes,
         dietary_restrictions,
+        glossary,
         &fridge_service,
     ).await?;
     
@@ -776,6 +1079,11 @@ pub async fn generate_fridge_recipes(
     }
     all_missing.sort();
     all_missing.dedup();
+
+    // Рецепты готовятся сегодня — к этому дню и проверяем запасы
+    let shopping_list = crate::services::shopping_advice::ShoppingAdviceService::new(pool.clone())
+        .advise(claims.sub, all_missing.clone(), clock.now().date_naive())
+        .await?;
     
     // Создаем карточки для рецептов
     let mut cards = Vec::new();
@@ -786,6 +1094,7 @@ pub async fn generate_fridge_recipes(
             emoji: Some("🍽️".to_string()),
             category: Some("recipe".to_string()),
             priority: if i == 0 { Some("high".to_string()) } else { Some("medium".to_string()) },
+            action: None,
         });
     }
     
@@ -797,30 +1106,46 @@ pub async fn generate_fridge_recipes(
             emoji: Some("🛒".to_string()),
             category: Some("shopping".to_string()),
             priority: Some("medium".to_string()),
+            action: Some(CardAction::AddToShoppingList { items: all_missing.clone() }),
         });
     }
     
-    Ok(ResponseJson(FridgeRecipeResponse {
+    let mut response = FridgeRecipeResponse {
         recipes,
         missing_ingredients_summary: all_missing,
+        shopping_list,
         shopping_suggestions: vec![
             "Планируйте покупки заранее".to_string(),
             "Покупайте только необходимые ингредиенты".to_string(),
             "Проверьте сроки годности при покупке".to_string(),
         ],
         cards: Some(cards),
-    }))
+        meta,
+        response_id: None,
+    };
+    response.response_id = AiHistoryService::new(pool)
+        .record_or_warn(claims.sub, AiResponseKind::FridgeAnalysis, provenance.as_ref(), &response)
+        .await;
+
+    Ok(ResponseJson(response))
 }
 
 /// Быстрый отчет о состоянии холодильника
 pub async fn fridge_quick_report(
+    State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
     Extension(pool): Extension<crate::db::DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    UserLocale(locale): UserLocale,
     claims: Claims,
 ) -> Result<ResponseJson<FridgeAnalysisResponse>, AppError> {
-    let ai_service = AiService::from_env();
-    let fridge_service = crate::services::fridge::FridgeService::new(pool);
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    let fridge_service = crate::services::fridge::FridgeService::new(pool.clone()).with_clock(clock.clone());
     
-    let result = ai_service.create_fridge_report(claims.sub, &fridge_service).await?;
+    let section_weights = FridgeReportFeedbackService::new(pool.clone()).weights(claims.sub).await?;
+    let glossary = PreferencesService::new(pool.clone()).glossary(claims.sub, locale).await?;
+    let mut result = ai_service.create_fridge_report(claims.sub, section_weights, glossary, &fridge_service).await?;
+    let provenance = result.provenance.take();
     
     // Создаем карточки
     let cards = vec![
@@ -830,15 +1155,263 @@ pub async fn fridge_quick_report(
             emoji: Some("🏠".to_string()),
             category: Some("fridge".to_string()),
             priority: Some("high".to_string()),
+            action: None,
         },
     ];
     
-    Ok(ResponseJson(FridgeAnalysisResponse {
+    let mut response = FridgeAnalysisResponse {
         summary: result.summary,
+        sections: result.sections,
         recommendations: result.recommendations,
         recipes: result.recipes,
         alerts: result.alerts,
         insights: result.insights,
         cards: Some(cards),
+        meta: result.meta,
+        response_id: None,
+    };
+    response.response_id = AiHistoryService::new(pool)
+        .record_or_warn(claims.sub, AiResponseKind::FridgeAnalysis, provenance.as_ref(), &response)
+        .await;
+
+    Ok(ResponseJson(response))
+}
+
+/// Отзывы на разделы отчета о холодильнике. Разделы, которые пользователь раз за разом
+/// отмечает бесполезными, в следующих отчетах опускаются в конец, а затем пропадают.
+pub async fn submit_fridge_report_feedback(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(report_id): Path<Uuid>,
+    Json(payload): Json<ReportFeedbackRequest>,
+) -> Result<ResponseJson<ReportFeedbackResponse>, AppError> {
+    payload.validate()?;
+
+    let response = FridgeReportFeedbackService::new(pool)
+        .record(claims.sub, report_id, &payload.sections)
+        .await?;
+    Ok(ResponseJson(response))
+}
+
+/// Какие данные пользователя попали в промпт ответа: ссылки с подписями, без текста промпта
+pub async fn get_response_provenance(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(response_id): Path<Uuid>,
+) -> Result<ResponseJson<AiResponseProvenance>, AppError> {
+    let provenance = AiHistoryService::new(pool).provenance(claims.sub, response_id).await?;
+    Ok(ResponseJson(provenance))
+}
+
+/// Выгрузка всей истории ответов ИИ пользователя одним JSON-файлом
+pub async fn export_ai_history(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let export = AiHistoryService::new(pool).export(claims.sub).await?;
+
+    Ok((
+        [
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"ai-history.json\""),
+            (header::CACHE_CONTROL, "private, no-store"),
+        ],
+        ResponseJson(export),
+    ))
+}
+
+/// Самое длинное сообщение пользователя в диалоге
+const MAX_CONVERSATION_MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConversationRequest {
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationMessageRequest {
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationSummary {
+    pub id: Uuid,
+    pub kind: String,
+    pub title: String,
+    pub week_start: Option<NaiveDate>,
+    pub concluded_at: Option<DateTime<Utc>>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Conversation> for ConversationSummary {
+    fn from(conversation: &Conversation) -> Self {
+        Self {
+            id: conversation.id,
+            kind: conversation.kind.clone(),
+            title: conversation.title.clone(),
+            week_start: conversation.week_start,
+            concluded_at: conversation.concluded_at,
+            applied_at: conversation.applied_at,
+            created_at: conversation.created_at,
+            updated_at: conversation.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    #[serde(flatten)]
+    pub conversation: ConversationSummary,
+    /// Данные недели, на которых построен разбор целей
+    pub review: Option<GoalReviewContext>,
+    pub outcome: Option<GoalReviewOutcome>,
+    pub messages: Vec<ConversationMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationReplyResponse {
+    pub user_message: ConversationMessage,
+    pub assistant_message: ConversationMessage,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppliedGoalReviewResponse {
+    pub applied: Vec<AppliedGoalChange>,
+}
+
+pub async fn list_conversations(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<ConversationSummary>>, AppError> {
+    let conversations = ConversationService::new(pool).list(claims.sub).await?;
+    Ok(ResponseJson(conversations.iter().map(ConversationSummary::from).collect()))
+}
+
+pub async fn create_conversation(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Json(request): Json<CreateConversationRequest>,
+) -> Result<ResponseJson<ConversationResponse>, AppError> {
+    let title = request
+        .title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Новый разговор".to_string());
+    if title.chars().count() > 200 {
+        return Err(AppError::BadRequest("Conversation title must be at most 200 characters".to_string()));
+    }
+
+    let conversation = ConversationService::new(pool)
+        .create(claims.sub, ConversationKind::Chat, &title, None, &[])
+        .await?
+        .ok_or_else(|| AppError::InternalServerError("Failed to create conversation".to_string()))?;
+
+    Ok(ResponseJson(ConversationResponse {
+        conversation: ConversationSummary::from(&conversation),
+        review: None,
+        outcome: None,
+        messages: Vec::new(),
     }))
 }
+
+pub async fn get_conversation(
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<ConversationResponse>, AppError> {
+    let service = ConversationService::new(pool);
+    let conversation = service.get(claims.sub, id).await?;
+    let messages = service.messages(conversation.id).await?;
+
+    Ok(ResponseJson(ConversationResponse {
+        conversation: ConversationSummary::from(&conversation),
+        review: conversation.review.map(|review| review.0),
+        outcome: conversation.outcome.map(|outcome| outcome.0),
+        messages,
+    }))
+}
+
+pub async fn send_conversation_message(
+    State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ConversationMessageRequest>,
+) -> Result<ResponseJson<ConversationReplyResponse>, AppError> {
+    let content = request.content.trim();
+    if content.is_empty() {
+        return Err(AppError::BadRequest("Message must not be empty".to_string()));
+    }
+    if content.chars().count() > MAX_CONVERSATION_MESSAGE_CHARS {
+        return Err(AppError::BadRequest(format!(
+            "Message must be at most {} characters",
+            MAX_CONVERSATION_MESSAGE_CHARS
+        )));
+    }
+
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    let (user_message, assistant_message, meta) = ConversationService::new(pool)
+        .reply(&ai_service, claims.sub, id, content)
+        .await?;
+
+    Ok(ResponseJson(ConversationReplyResponse { user_message, assistant_message, meta }))
+}
+
+/// Завершает разбор целей: решение по каждой цели (оставить или изменить с новым значением)
+pub async fn conclude_conversation(
+    State(ai_service): State<AiService>,
+    mock_scenario: MockScenario,
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<GoalReviewOutcome>, AppError> {
+    let ai_service = ai_service.with_mock_scenario(mock_scenario).for_user(claims.sub);
+    let outcome = GoalReviewService::new(pool, ai_service).conclude(claims.sub, id).await?;
+    Ok(ResponseJson(outcome))
+}
+
+/// Применяет итог разбора к целям одним вызовом
+pub async fn apply_conversation_outcome(
+    State(ai_service): State<AiService>,
+    Extension(pool): Extension<crate::db::DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<AppliedGoalReviewResponse>, AppError> {
+    let applied = GoalReviewService::new(pool, ai_service.for_user(claims.sub)).apply(claims.sub, id).await?;
+    Ok(ResponseJson(AppliedGoalReviewResponse { applied }))
+}
+
+/// Доступные сценарии Mock-провайдера (заголовок X-Mock-Scenario или ?mock_scenario=).
+/// В production не публикуется.
+pub async fn list_mock_scenarios(
+    State(ai_service): State<AiService>,
+    Extension(config): Extension<Config>,
+) -> Result<ResponseJson<MockScenariosResponse>, AppError> {
+    if config.is_production() {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
+
+    Ok(ResponseJson(MockScenariosResponse {
+        active: ai_service.is_mock(),
+        header: "X-Mock-Scenario",
+        query_param: mock_ai::MOCK_SCENARIO_QUERY,
+        default_scenario: mock_ai::DEFAULT_SCENARIO,
+        scenarios: mock_ai::describe_scenarios(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MockScenariosResponse {
+    /// Сценарии применяются только когда активен Mock-провайдер
+    pub active: bool,
+    pub header: &'static str,
+    pub query_param: &'static str,
+    pub default_scenario: &'static str,
+    pub scenarios: Vec<MockScenarioDescription>,
+}
@@ -1,8 +1,10 @@
+use std::{net::SocketAddr, sync::Arc};
+
 use axum::{
-    extract::{Extension, Json},
-    http::StatusCode,
+    extract::{ConnectInfo, Extension, Json, Path, Query},
+    http::{HeaderMap, StatusCode},
     response::Json as ResponseJson,
-    routing::{post, get},
+    routing::{post, get, delete},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -11,10 +13,22 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 use crate::{
+    config::Config,
     db::DbPool,
-    models::user::{User, CreateUser, UserRole},
-    services::auth::{AuthService, Claims},
-    utils::errors::AppError,
+    models::{
+        api_token::{ApiTokenInfo, CreateApiToken, TokenScope},
+        user::{User, CreateUser, SessionInfo, UserRole},
+    },
+    services::{
+        api_tokens::ApiTokenService,
+        auth::{AuthService, AuthTokens, Claims},
+        demo_seed::{DemoSeedService, DemoSeedSummary, DemoCleanupSummary, DEFAULT_DEMO_SEED},
+        feature_flags::FeatureFlags,
+        mail::MailService,
+        realtime::WebSocketManager,
+        sessions::SessionService,
+    },
+    utils::{device::DeviceInfo, errors::AppError},
 };
 
 pub fn routes() -> Router {
@@ -22,12 +36,18 @@ pub fn routes() -> Router {
         .route("/register", post(register))
         .route("/login", post(login))
         .route("/refresh", post(refresh_token))
+        .route("/password-reset", post(reset_password))
 }
 
 pub fn protected_routes() -> Router {
     Router::new()
         .route("/me", get(get_current_user))
         .route("/logout", post(logout))
+        .route("/sessions", get(list_sessions).delete(revoke_other_sessions))
+        .route("/sessions/:id", delete(revoke_session))
+        .route("/seed-demo-data", post(seed_demo_data).delete(remove_demo_data))
+        .route("/tokens", post(create_api_token).get(list_api_tokens))
+        .route("/tokens/:id", delete(revoke_api_token))
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -37,8 +57,10 @@ pub struct RegisterRequest {
     #[validate(length(min = 6, max = 100))]
     pub password: String,
     #[validate(length(min = 2, max = 50))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub first_name: String,
     #[validate(length(min = 2, max = 50))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub last_name: String,
     pub date_of_birth: Option<DateTime<Utc>>,
     pub gender: Option<String>,
@@ -55,6 +77,14 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 6, max = 100))]
+    pub new_password: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub access_token: String,
@@ -87,6 +117,8 @@ impl From<User> for UserResponse {
 
 pub async fn register(
     Extension(pool): Extension<DbPool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<ResponseJson<AuthResponse>, AppError> {
     payload.validate()?;
@@ -104,8 +136,10 @@ pub async fn register(
         role: UserRole::User,
     };
 
-    let auth_service = AuthService::new(pool);
-    let (user, tokens) = auth_service.register(create_user).await?;
+    let device = DeviceInfo::from_request(&headers, connect_info.map(|ConnectInfo(addr)| addr.ip()));
+    let auth_service = AuthService::new(pool.clone());
+    let (user, tokens) = auth_service.register(create_user, device.clone()).await?;
+    record_login(pool, &user, &tokens, &device).await;
 
     Ok(ResponseJson(AuthResponse {
         access_token: tokens.access_token,
@@ -116,12 +150,16 @@ pub async fn register(
 
 pub async fn login(
     Extension(pool): Extension<DbPool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<ResponseJson<AuthResponse>, AppError> {
     payload.validate()?;
 
-    let auth_service = AuthService::new(pool);
-    let (user, tokens) = auth_service.login(&payload.email, &payload.password).await?;
+    let device = DeviceInfo::from_request(&headers, connect_info.map(|ConnectInfo(addr)| addr.ip()));
+    let auth_service = AuthService::new(pool.clone());
+    let (user, tokens) = auth_service.login(&payload.email, &payload.password, device.clone()).await?;
+    record_login(pool, &user, &tokens, &device).await;
 
     Ok(ResponseJson(AuthResponse {
         access_token: tokens.access_token,
@@ -130,6 +168,26 @@ pub async fn login(
     }))
 }
 
+/// Учет устройства входа не должен мешать входу: ошибки только пишутся в лог
+async fn record_login(pool: DbPool, user: &User, tokens: &AuthTokens, device: &DeviceInfo) {
+    let session_service = SessionService::new(pool);
+    if let Err(e) = session_service.record_login(user, tokens.session_id, device, &MailService::from_env()).await {
+        tracing::warn!("Failed to record login device for user {}: {}", user.id, e);
+    }
+}
+
+/// Новый пароль по ссылке из письма (после принудительного сброса администратором)
+pub async fn reset_password(
+    Extension(pool): Extension<DbPool>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    payload.validate()?;
+
+    let auth_service = AuthService::new(pool);
+    auth_service.reset_password(&payload.token, &payload.new_password).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn refresh_token(
     Extension(pool): Extension<DbPool>,
     Json(payload): Json<serde_json::Value>,
@@ -170,3 +228,313 @@ pub async fn logout(
     auth_service.logout(claims.sub).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Где выполнен вход: действующие сессии с устройством и отметкой текущей
+pub async fn list_sessions(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<SessionInfo>>, AppError> {
+    let sessions = SessionService::new(pool).list(claims.sub, claims.sid).await?;
+    Ok(ResponseJson(sessions))
+}
+
+/// Удаленный выход: токены сессии перестают приниматься сразу, ее WebSocket-соединения закрываются
+pub async fn revoke_session(
+    Extension(pool): Extension<DbPool>,
+    Extension(ws_manager): Extension<Arc<WebSocketManager>>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    SessionService::new(pool).revoke(claims.sub, id).await?;
+    ws_manager.disconnect_sessions(&[id]).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionsResponse {
+    pub revoked: Vec<Uuid>,
+}
+
+/// Выход на всех устройствах, кроме текущего
+pub async fn revoke_other_sessions(
+    Extension(pool): Extension<DbPool>,
+    Extension(ws_manager): Extension<Arc<WebSocketManager>>,
+    claims: Claims,
+) -> Result<ResponseJson<RevokeSessionsResponse>, AppError> {
+    let revoked = SessionService::new(pool).revoke_others(claims.sub, claims.sid).await?;
+    ws_manager.disconnect_sessions(&revoked).await;
+
+    Ok(ResponseJson(RevokeSessionsResponse { revoked }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeedDemoDataParams {
+    /// Один и тот же seed дает одинаковый набор данных (для воспроизводимого QA)
+    pub seed: Option<u64>,
+}
+
+/// Заполнение аккаунта демо-данными (онбординг и QA)
+pub async fn seed_demo_data(
+    Extension(pool): Extension<DbPool>,
+    Extension(config): Extension<Config>,
+    Extension(feature_flags): Extension<FeatureFlags>,
+    claims: Claims,
+    Query(params): Query<SeedDemoDataParams>,
+) -> Result<ResponseJson<DemoSeedSummary>, AppError> {
+    // В production доступно только демо-аккаунтам
+    if config.is_production() && !feature_flags.enabled(claims.sub, "demo_account").await? {
+        return Err(AppError::Forbidden("Demo data seeding is disabled in production".to_string()));
+    }
+
+    let user = AuthService::new(pool.clone()).get_user_by_id(claims.sub).await?;
+
+    let demo_service = DemoSeedService::new(pool);
+    demo_service.ensure_eligible(user.id, user.created_at).await?;
+    let summary = demo_service.seed(user.id, params.seed.unwrap_or(DEFAULT_DEMO_SEED)).await?;
+
+    Ok(ResponseJson(summary))
+}
+
+/// Удаление только демо-записей — данные, добавленные пользователем, не затрагиваются
+pub async fn remove_demo_data(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<DemoCleanupSummary>, AppError> {
+    let demo_service = DemoSeedService::new(pool);
+    let summary = demo_service.cleanup(claims.sub).await?;
+
+    Ok(ResponseJson(summary))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiTokenRequest {
+    #[validate(length(min = 1, max = 100))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateApiTokenResponse {
+    /// Секрет показывается только в этом ответе
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiTokenInfo,
+}
+
+/// Персональный токен для скриптов и интеграций (pat_...)
+pub async fn create_api_token(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, ResponseJson<CreateApiTokenResponse>), AppError> {
+    payload.validate()?;
+
+    let token_service = ApiTokenService::new(pool);
+    let (info, token) = token_service.create_token(CreateApiToken {
+        user_id: claims.sub,
+        name: payload.name,
+        scopes: payload.scopes,
+        expires_at: payload.expires_at,
+    }).await?;
+
+    Ok((StatusCode::CREATED, ResponseJson(CreateApiTokenResponse { token, info })))
+}
+
+pub async fn list_api_tokens(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<ApiTokenInfo>>, AppError> {
+    let token_service = ApiTokenService::new(pool);
+    let tokens = token_service.list_tokens(claims.sub).await?;
+
+    Ok(ResponseJson(tokens))
+}
+
+pub async fn revoke_api_token(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let token_service = ApiTokenService::new(pool);
+    token_service.revoke_token(claims.sub, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    use tokio::sync::oneshot::error::TryRecvError;
+
+    use super::*;
+    use crate::{
+        models::notification::NotificationPreferences,
+        services::{preferences::PreferencesService, realtime::DisconnectReason},
+        test_support::{insert_user, mail_server},
+        utils::{errors::ErrorCode, format::Locale},
+    };
+
+    fn device(user_agent: &str, ip: &str) -> DeviceInfo {
+        let (browser, os) = crate::utils::device::parse_user_agent(user_agent);
+        DeviceInfo {
+            user_agent: Some(user_agent.to_string()),
+            browser: browser.map(str::to_string),
+            os: os.map(str::to_string),
+            ip_address: Some(ip.to_string()),
+            location: None,
+        }
+    }
+
+    const PHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 Version/17.0 Mobile/15E148 Safari/604.1";
+    const LAPTOP: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 Chrome/120.0 Safari/537.36";
+    const TABLET: &str = "Mozilla/5.0 (Linux; Android 14; Tab) AppleWebKit/537.36 Chrome/120.0 Safari/537.36";
+
+    async fn sign_in(pool: &PgPool, user_id: Uuid, user_agent: &str) -> AuthTokens {
+        let auth = AuthService::new(pool.clone());
+        let user = auth.get_user_by_id(user_id).await.unwrap();
+        auth.generate_tokens(&user, device(user_agent, "203.0.113.7")).await.unwrap()
+    }
+
+    fn claims(pool: &PgPool, tokens: &AuthTokens) -> Claims {
+        AuthService::new(pool.clone()).verify_token(&tokens.access_token).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn remote_revocation_kills_refresh_and_the_websocket(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let auth = AuthService::new(pool.clone());
+        let phone = sign_in(&pool, user_id, PHONE).await;
+        let laptop = sign_in(&pool, user_id, LAPTOP).await;
+
+        let manager = Arc::new(WebSocketManager::new());
+        let mut phone_ws = manager.add_client(user_id, Some(phone.session_id), "Анна".to_string(), Locale::Ru).await.unwrap();
+        let mut laptop_ws = manager.add_client(user_id, Some(laptop.session_id), "Анна".to_string(), Locale::Ru).await.unwrap();
+
+        let ResponseJson(sessions) = list_sessions(Extension(pool.clone()), claims(&pool, &laptop)).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        let current: Vec<Uuid> = sessions.iter().filter(|session| session.current).map(|session| session.id).collect();
+        assert_eq!(current, vec![laptop.session_id]);
+
+        let status = revoke_session(
+            Extension(pool.clone()),
+            Extension(manager.clone()),
+            claims(&pool, &laptop),
+            Path(phone.session_id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        // Соединение отозванной сессии закрыто сразу, соседнее осталось
+        assert_eq!(phone_ws.evicted.try_recv(), Ok(DisconnectReason::SessionRevoked));
+        assert_eq!(laptop_ws.evicted.try_recv(), Err(TryRecvError::Empty));
+
+        // Ни refresh-токен, ни еще не истекший access-токен отозванной сессии не принимаются
+        let refreshed = auth.refresh_token(&phone.refresh_token).await;
+        assert!(matches!(refreshed, Err(AppError::Unauthorized(_))), "{:?}", refreshed.map(|_| ()));
+        let rejected = auth.check_account_status(&claims(&pool, &phone)).await.unwrap_err();
+        assert_eq!(rejected.code(), ErrorCode::SessionRevoked);
+
+        let renewed = auth.refresh_token(&laptop.refresh_token).await.unwrap();
+        assert_eq!(renewed.session_id, laptop.session_id);
+        auth.check_account_status(&claims(&pool, &renewed)).await.unwrap();
+
+        let (events,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM security_events WHERE user_id = $1 AND event = 'session_revoked' AND session_id = $2"
+        )
+        .bind(user_id)
+        .bind(phone.session_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(events, 1);
+
+        // Повторный отзыв и отзыв чужой сессии — 404
+        let again = revoke_session(Extension(pool.clone()), Extension(manager.clone()), claims(&pool, &laptop), Path(phone.session_id)).await;
+        assert!(matches!(again, Err(AppError::NotFound(_))));
+        let stranger = insert_user(&pool, "Олег").await;
+        let stranger_tokens = sign_in(&pool, stranger, LAPTOP).await;
+        let foreign = revoke_session(Extension(pool.clone()), Extension(manager), claims(&pool, &stranger_tokens), Path(laptop.session_id)).await;
+        assert!(matches!(foreign, Err(AppError::NotFound(_))));
+        assert!(auth.refresh_token(&renewed.refresh_token).await.is_ok());
+    }
+
+    #[sqlx::test]
+    async fn sign_out_everywhere_else_keeps_the_current_session(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let auth = AuthService::new(pool.clone());
+        let phone = sign_in(&pool, user_id, PHONE).await;
+        let laptop = sign_in(&pool, user_id, LAPTOP).await;
+        let tablet = sign_in(&pool, user_id, TABLET).await;
+
+        let manager = Arc::new(WebSocketManager::new());
+        let mut registrations = Vec::new();
+        for tokens in [&phone, &laptop, &tablet] {
+            let registration = manager.add_client(user_id, Some(tokens.session_id), "Анна".to_string(), Locale::Ru).await.unwrap();
+            registrations.push((tokens.session_id, registration));
+        }
+
+        let ResponseJson(response) = revoke_other_sessions(Extension(pool.clone()), Extension(manager.clone()), claims(&pool, &laptop))
+            .await
+            .unwrap();
+        let mut revoked = response.revoked;
+        revoked.sort();
+        let mut expected = vec![phone.session_id, tablet.session_id];
+        expected.sort();
+        assert_eq!(revoked, expected);
+
+        for (session_id, registration) in registrations.iter_mut() {
+            let closed = registration.evicted.try_recv();
+            if *session_id == laptop.session_id {
+                assert_eq!(closed, Err(TryRecvError::Empty));
+            } else {
+                assert_eq!(closed, Ok(DisconnectReason::SessionRevoked));
+            }
+        }
+
+        assert!(auth.refresh_token(&phone.refresh_token).await.is_err());
+        assert!(auth.refresh_token(&tablet.refresh_token).await.is_err());
+        assert!(auth.refresh_token(&laptop.refresh_token).await.is_ok());
+    }
+
+    #[sqlx::test]
+    async fn new_device_login_is_audited_and_emailed_when_enabled(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let user = AuthService::new(pool.clone()).get_user_by_id(user_id).await.unwrap();
+        let (mail, received, _) = mail_server().await;
+        let sessions = SessionService::new(pool.clone());
+
+        // Первое устройство аккаунта и повторный вход с него новыми не считаются
+        assert!(!sessions.record_login(&user, Uuid::new_v4(), &device(LAPTOP, "203.0.113.7"), &mail).await.unwrap());
+        assert!(!sessions.record_login(&user, Uuid::new_v4(), &device(LAPTOP, "203.0.113.7"), &mail).await.unwrap());
+
+        // Тот же браузер с другого адреса — новое устройство; письмо выключено по умолчанию
+        assert!(sessions.record_login(&user, Uuid::new_v4(), &device(LAPTOP, "198.51.100.1"), &mail).await.unwrap());
+        assert!(received.lock().unwrap().is_empty());
+
+        PreferencesService::new(pool.clone())
+            .set_notifications(user_id, NotificationPreferences { new_device_email: true, ..Default::default() })
+            .await
+            .unwrap();
+        let phone_session = Uuid::new_v4();
+        assert!(sessions.record_login(&user, phone_session, &device(PHONE, "198.51.100.1"), &mail).await.unwrap());
+
+        let emails = received.lock().unwrap().clone();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0]["subject"], "Вход с нового устройства");
+        assert!(emails[0]["html"].as_str().unwrap().contains("198.51.100.1"));
+
+        let events: Vec<(Option<Uuid>,)> = sqlx::query_as(
+            "SELECT session_id FROM security_events WHERE user_id = $1 AND event = 'new_device_login' ORDER BY created_at"
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].0, Some(phone_session));
+    }
+}
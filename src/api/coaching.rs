@@ -0,0 +1,494 @@
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    response::Json as ResponseJson,
+    routing::{get, post, put, delete},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    db::DbPool,
+    api::{
+        diary::{DiaryEntryResponse, DiaryQueryParams},
+        fridge::FridgeItemResponse,
+        goals::{GoalResponse, GoalQueryParams, WeightEntryResponse, WeightQueryParams},
+    },
+    models::{
+        activity::ActivityLogEntry,
+        coaching::{CoachLink, CoachLinkStatus, CoachingDomain, CreateCoachLink},
+    },
+    services::{
+        activity::ActivityService,
+        auth::Claims,
+        clock::SharedClock,
+        coaching::CoachingService,
+        diary::DiaryService,
+        fridge::FridgeService,
+        goal::GoalService,
+    },
+    utils::errors::AppError,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        // Сторона клиента
+        .route("/invites", post(create_invite))
+        .route("/links", get(get_my_coach_links))
+        .route("/links/:id/grants", put(update_grants))
+        .route("/links/:id", delete(revoke_link))
+        .route("/activity", get(get_coach_activity))
+        // Сторона коуча
+        .route("/invites/:token/accept", post(accept_invite))
+        .route("/clients", get(get_clients))
+        .route("/clients/:id/diary", get(get_client_diary))
+        .route("/clients/:id/weight", get(get_client_weight))
+        .route("/clients/:id/goals", get(get_client_goals))
+        .route("/clients/:id/fridge", get(get_client_fridge))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCoachInviteRequest {
+    #[validate(email)]
+    pub coach_email: String,
+    pub domains: Vec<CoachingDomain>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGrantsRequest {
+    pub domains: Vec<CoachingDomain>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQueryParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoachLinkResponse {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub coach_email: String,
+    pub coach_id: Option<Uuid>,
+    pub status: CoachLinkStatus,
+    /// Токен приглашения показывается только клиенту, пока приглашение не принято
+    pub invite_token: Option<String>,
+    pub domains: Vec<CoachingDomain>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CoachLinkResponse {
+    fn from_link(link: CoachLink, domains: Vec<CoachingDomain>, show_token: bool) -> Self {
+        let invite_token = if show_token && link.status == CoachLinkStatus::Pending {
+            Some(link.invite_token)
+        } else {
+            None
+        };
+
+        Self {
+            id: link.id,
+            client_id: link.client_id,
+            coach_email: link.coach_email,
+            coach_id: link.coach_id,
+            status: link.status,
+            invite_token,
+            domains,
+            accepted_at: link.accepted_at,
+            revoked_at: link.revoked_at,
+            created_at: link.created_at,
+        }
+    }
+}
+
+pub async fn create_invite(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CreateCoachInviteRequest>,
+) -> Result<ResponseJson<CoachLinkResponse>, AppError> {
+    payload.validate()?;
+
+    if payload.coach_email.eq_ignore_ascii_case(&claims.email) {
+        return Err(AppError::BadRequest("You cannot invite yourself as a coach".to_string()));
+    }
+
+    let coaching_service = CoachingService::new(pool);
+    let link = coaching_service.create_invite(CreateCoachLink {
+        client_id: claims.sub,
+        coach_email: payload.coach_email,
+        domains: payload.domains,
+    }).await?;
+    let domains = coaching_service.get_link_domains(link.id).await?;
+
+    Ok(ResponseJson(CoachLinkResponse::from_link(link, domains, true)))
+}
+
+pub async fn get_my_coach_links(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<CoachLinkResponse>>, AppError> {
+    let coaching_service = CoachingService::new(pool);
+    let links = coaching_service.get_client_links(claims.sub).await?;
+
+    let mut response = Vec::with_capacity(links.len());
+    for link in links {
+        let domains = coaching_service.get_link_domains(link.id).await?;
+        response.push(CoachLinkResponse::from_link(link, domains, true));
+    }
+
+    Ok(ResponseJson(response))
+}
+
+pub async fn update_grants(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateGrantsRequest>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let coaching_service = CoachingService::new(pool);
+    let domains = coaching_service.update_grants(id, claims.sub, payload.domains).await?;
+
+    Ok(ResponseJson(serde_json::json!({ "link_id": id, "domains": domains })))
+}
+
+pub async fn revoke_link(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let coaching_service = CoachingService::new(pool);
+    coaching_service.revoke_link(id, claims.sub).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "Coach access revoked"})))
+}
+
+/// Журнал действий коучей с данными текущего пользователя
+pub async fn get_coach_activity(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Query(params): Query<ActivityQueryParams>,
+) -> Result<ResponseJson<Vec<ActivityLogEntry>>, AppError> {
+    let activity_service = ActivityService::new(pool);
+    let entries = activity_service.get_user_activity(
+        claims.sub,
+        Some("coach_"),
+        params.limit.unwrap_or(50),
+        params.offset.unwrap_or(0),
+    ).await?;
+
+    Ok(ResponseJson(entries))
+}
+
+pub async fn accept_invite(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(token): Path<String>,
+) -> Result<ResponseJson<CoachLinkResponse>, AppError> {
+    let coaching_service = CoachingService::new(pool);
+    let link = coaching_service.accept_invite(&token, &claims).await?;
+    let domains = coaching_service.get_link_domains(link.id).await?;
+
+    Ok(ResponseJson(CoachLinkResponse::from_link(link, domains, false)))
+}
+
+pub async fn get_clients(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<CoachLinkResponse>>, AppError> {
+    let coaching_service = CoachingService::new(pool);
+    let links = coaching_service.get_coach_links(claims.sub).await?;
+
+    let mut response = Vec::with_capacity(links.len());
+    for link in links {
+        let domains = coaching_service.get_link_domains(link.id).await?;
+        response.push(CoachLinkResponse::from_link(link, domains, false));
+    }
+
+    Ok(ResponseJson(response))
+}
+
+pub async fn get_client_diary(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(client_id): Path<Uuid>,
+    Query(params): Query<DiaryQueryParams>,
+) -> Result<ResponseJson<Vec<DiaryEntryResponse>>, AppError> {
+    CoachingService::new(pool.clone())
+        .authorize_read(claims.sub, client_id, CoachingDomain::Diary, "diary")
+        .await?;
+
+    let diary_service = DiaryService::new(pool);
+    let entries = diary_service.get_user_entries(
+        client_id,
+        params.date,
+        params.meal_type,
+        params.limit.unwrap_or(50),
+        params.offset.unwrap_or(0),
+    ).await?;
+
+    let response: Vec<DiaryEntryResponse> = entries.into_iter().map(Into::into).collect();
+    Ok(ResponseJson(response))
+}
+
+pub async fn get_client_weight(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(client_id): Path<Uuid>,
+    Query(params): Query<WeightQueryParams>,
+) -> Result<ResponseJson<Vec<WeightEntryResponse>>, AppError> {
+    CoachingService::new(pool.clone())
+        .authorize_read(claims.sub, client_id, CoachingDomain::Weight, "weight")
+        .await?;
+
+    let goal_service = GoalService::new(pool).with_clock(clock);
+    let entries = goal_service.get_weight_history(
+        client_id,
+        params.start_date,
+        params.end_date,
+        params.limit.unwrap_or(100),
+    ).await?;
+
+    let response: Vec<WeightEntryResponse> = entries.into_iter().map(|entry| {
+        WeightEntryResponse {
+            id: entry.id,
+            weight: entry.weight,
+            date: entry.date,
+            notes: entry.notes,
+            bmi: None,
+            weight_change: None,
+            created_at: entry.created_at,
+        }
+    }).collect();
+
+    Ok(ResponseJson(response))
+}
+
+pub async fn get_client_goals(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(client_id): Path<Uuid>,
+    Query(params): Query<GoalQueryParams>,
+) -> Result<ResponseJson<Vec<GoalResponse>>, AppError> {
+    CoachingService::new(pool.clone())
+        .authorize_read(claims.sub, client_id, CoachingDomain::Goals, "goals")
+        .await?;
+
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
+    let goals = goal_service.get_user_goals(
+        client_id,
+        params.goal_type,
+        params.status,
+        params.include_archived,
+        params.limit.unwrap_or(50),
+        params.offset.unwrap_or(0),
+    ).await?;
+
+    let today = clock.now().date_naive();
+    let response: Vec<GoalResponse> = goals.into_iter().map(|goal| GoalResponse::new(goal, today)).collect();
+    Ok(ResponseJson(response))
+}
+
+pub async fn get_client_fridge(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(client_id): Path<Uuid>,
+) -> Result<ResponseJson<Vec<FridgeItemResponse>>, AppError> {
+    CoachingService::new(pool.clone())
+        .authorize_read(claims.sub, client_id, CoachingDomain::Fridge, "fridge")
+        .await?;
+
+    let fridge_service = FridgeService::new(pool).with_clock(clock.clone());
+    let items = fridge_service.get_user_items(client_id, None, None, None).await?;
+
+    let now = clock.now();
+    let response: Vec<FridgeItemResponse> = items.into_iter().map(|item| FridgeItemResponse::new(item, now)).collect();
+    Ok(ResponseJson(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::{Extension, Json, Path, Query},
+        http::StatusCode,
+        response::IntoResponse,
+    };
+    use chrono::{Duration, Utc};
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        services::clock,
+        test_support::{claims_for, insert_diary_entry, insert_fridge_item, insert_user},
+    };
+
+    async fn coach_reads(pool: &PgPool, client_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM activity_log WHERE user_id = $1 AND action = 'coach_read'")
+            .bind(client_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn status<T>(result: Result<T, AppError>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(e) => e.into_response().status(),
+        }
+    }
+
+    /// Клиент приглашает коуча с доменами, коуч принимает; возвращает id связи
+    async fn linked(pool: &PgPool, client: &Claims, coach: &Claims, domains: Vec<CoachingDomain>) -> Uuid {
+        let ResponseJson(invite) = create_invite(
+            Extension(pool.clone()),
+            client.clone(),
+            Json(CreateCoachInviteRequest { coach_email: coach.email.clone(), domains }),
+        ).await.unwrap();
+        let ResponseJson(link) = accept_invite(
+            Extension(pool.clone()),
+            coach.clone(),
+            Path(invite.invite_token.unwrap()),
+        ).await.unwrap();
+        assert_eq!(link.status, CoachLinkStatus::Active);
+        link.id
+    }
+
+    /// Статусы чтения коучем всех четырех доменов: дневник, вес, цели, холодильник
+    async fn read_all(pool: &PgPool, coach: &Claims, client_id: Uuid) -> [StatusCode; 4] {
+        let diary_params = || Query(DiaryQueryParams { date: None, meal_type: None, limit: None, offset: None });
+        [
+            status(get_client_diary(Extension(pool.clone()), coach.clone(), Path(client_id), diary_params()).await),
+            status(get_client_weight(
+                Extension(pool.clone()), Extension(clock::system()), coach.clone(), Path(client_id),
+                Query(WeightQueryParams { start_date: None, end_date: None, limit: None }),
+            ).await),
+            status(get_client_goals(
+                Extension(pool.clone()), Extension(clock::system()), coach.clone(), Path(client_id),
+                Query(GoalQueryParams { goal_type: None, status: None, include_archived: false, limit: None, offset: None }),
+            ).await),
+            status(get_client_fridge(Extension(pool.clone()), Extension(clock::system()), coach.clone(), Path(client_id)).await),
+        ]
+    }
+
+    #[sqlx::test]
+    async fn coach_reads_granted_domains_only_until_revoked(pool: PgPool) {
+        let client_id = insert_user(&pool, "Клиент").await;
+        let client = claims_for(&pool, client_id).await;
+        let coach = claims_for(&pool, insert_user(&pool, "Коуч").await).await;
+        let stranger = claims_for(&pool, insert_user(&pool, "Чужой").await).await;
+        insert_diary_entry(&pool, client_id, "breakfast", 300.0, 10.0, Utc::now() - Duration::hours(1)).await;
+        insert_fridge_item(&pool, client_id, "Молоко", None, Utc::now() - Duration::days(1)).await;
+
+        let link_id = linked(&pool, &client, &coach, vec![CoachingDomain::Diary, CoachingDomain::Fridge]).await;
+
+        let diary_params = Query(DiaryQueryParams { date: None, meal_type: None, limit: None, offset: None });
+        let ResponseJson(diary) = get_client_diary(Extension(pool.clone()), coach.clone(), Path(client_id), diary_params)
+            .await
+            .unwrap();
+        assert_eq!(diary.len(), 1);
+        let ResponseJson(fridge) = get_client_fridge(Extension(pool.clone()), Extension(clock::system()), coach.clone(), Path(client_id))
+            .await
+            .unwrap();
+        assert_eq!(fridge.len(), 1);
+        assert_eq!(coach_reads(&pool, client_id).await, 2);
+
+        // Вес и цели без гранта — 403; отказы в журнал чтений не попадают
+        let ok = StatusCode::OK;
+        let forbidden = StatusCode::FORBIDDEN;
+        assert_eq!(read_all(&pool, &coach, client_id).await, [ok, forbidden, forbidden, ok]);
+        assert_eq!(coach_reads(&pool, client_id).await, 4);
+        assert_eq!(read_all(&pool, &stranger, client_id).await, [forbidden; 4]);
+
+        let ResponseJson(revoked) = revoke_link(Extension(pool.clone()), client.clone(), Path(link_id)).await.unwrap();
+        assert_eq!(revoked["message"], "Coach access revoked");
+
+        // Тот же коуч сразу теряет доступ ко всем доменам
+        assert_eq!(read_all(&pool, &coach, client_id).await, [forbidden; 4]);
+        assert_eq!(coach_reads(&pool, client_id).await, 4);
+    }
+
+    #[sqlx::test]
+    async fn grants_update_applies_to_the_next_read(pool: PgPool) {
+        let client_id = insert_user(&pool, "Клиент").await;
+        let client = claims_for(&pool, client_id).await;
+        let coach = claims_for(&pool, insert_user(&pool, "Коуч").await).await;
+        let link_id = linked(&pool, &client, &coach, vec![CoachingDomain::Weight]).await;
+
+        let (ok, forbidden) = (StatusCode::OK, StatusCode::FORBIDDEN);
+        assert_eq!(read_all(&pool, &coach, client_id).await, [forbidden, ok, forbidden, forbidden]);
+
+        let ResponseJson(updated) = update_grants(
+            Extension(pool.clone()),
+            client.clone(),
+            Path(link_id),
+            Json(UpdateGrantsRequest { domains: vec![CoachingDomain::Diary, CoachingDomain::Goals] }),
+        ).await.unwrap();
+        assert_eq!(updated["link_id"], link_id.to_string());
+        assert_eq!(updated["domains"], serde_json::json!([CoachingDomain::Diary, CoachingDomain::Goals]));
+        assert_eq!(read_all(&pool, &coach, client_id).await, [ok, forbidden, ok, forbidden]);
+    }
+
+    #[sqlx::test]
+    async fn invite_is_bound_to_the_coach_email(pool: PgPool) {
+        let client = claims_for(&pool, insert_user(&pool, "Клиент").await).await;
+        let coach = claims_for(&pool, insert_user(&pool, "Коуч").await).await;
+        let other = claims_for(&pool, insert_user(&pool, "Другой").await).await;
+
+        let ResponseJson(invite) = create_invite(
+            Extension(pool.clone()),
+            client.clone(),
+            Json(CreateCoachInviteRequest { coach_email: coach.email.clone(), domains: vec![CoachingDomain::Diary] }),
+        ).await.unwrap();
+        let token = invite.invite_token.unwrap();
+
+        let result = accept_invite(Extension(pool.clone()), other, Path(token.clone())).await;
+        assert_eq!(status(result), StatusCode::FORBIDDEN);
+        let ResponseJson(accepted) = accept_invite(Extension(pool.clone()), coach.clone(), Path(token.clone())).await.unwrap();
+        assert_eq!((accepted.id, accepted.status, accepted.coach_id), (invite.id, CoachLinkStatus::Active, Some(coach.sub)));
+        // Коучу токен приглашения не показывается
+        assert_eq!(accepted.invite_token, None);
+        // Принятое приглашение повторно не принимается
+        let result = accept_invite(Extension(pool.clone()), coach, Path(token)).await;
+        assert_eq!(status(result), StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    async fn coach_activity_pages_skip_other_actions(pool: PgPool) {
+        let client_id = insert_user(&pool, "Анна").await;
+        let now = Utc::now();
+        // Две записи коуча старше трех посторонних
+        for (action, minutes_ago) in [("coach_read", 50), ("coach_invite_created", 40), ("login", 30), ("login", 20), ("login", 10)] {
+            sqlx::query("INSERT INTO activity_log (user_id, action, created_at) VALUES ($1, $2, $3)")
+                .bind(client_id)
+                .bind(action)
+                .bind(now - Duration::minutes(minutes_ago))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let page = |limit, offset| {
+            let pool = pool.clone();
+            async move {
+                let ResponseJson(entries) = get_coach_activity(
+                    Extension(pool.clone()),
+                    claims_for(&pool, client_id).await,
+                    Query(ActivityQueryParams { limit: Some(limit), offset: Some(offset) }),
+                )
+                .await
+                .unwrap();
+                entries.into_iter().map(|entry| entry.action).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(page(2, 0).await, ["coach_invite_created", "coach_read"]);
+        assert_eq!(page(1, 1).await, ["coach_read"]);
+        assert!(page(1, 2).await.is_empty());
+    }
+}
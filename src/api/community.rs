@@ -1,59 +1,133 @@
 use axum::{
-    extract::{Extension, Json, Path, Query},
-    response::Json as ResponseJson,
+    extract::{Extension, Json, Path, Query, RawQuery},
+    http::{header, HeaderMap, HeaderValue},
+    response::{Json as ResponseJson, Response},
     routing::{get, post, put, delete},
+    middleware::from_fn,
     Router,
 };
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::sync::Arc;
 
 use crate::{
-    db::DbPool,
-    models::community::{Post, CreatePost, PostType, Comment, CreateComment, Like, Follow},
-    services::{auth::Claims, community::CommunityService, media::MediaService},
-    utils::errors::AppError,
+    config::Config,
+    middleware::idempotency_middleware,
+    db::{DbPool, DbPools, ReadConsistency},
+    models::community::{
+        CreatePost, PostType, PostVisibility, CreateComment,
+        ActivityKind, FriendActivityStatus, SetActivityStatus, LinkPreview, PostEdit,
+        CookedIt, CookedItEntry, CookedItInput,
+    },
+    models::diary::DiaryEntry,
+    services::{
+        activity_status::ActivityStatusService,
+        auth::Claims,
+        clock::SharedClock,
+        community::CommunityService,
+        link_previews::{LinkPreviewCache, LinkPreviewService},
+        media::{MediaLibrary, MediaService},
+        notification_digests::CommunityNotifier,
+        post_cooked::CookedItService,
+        post_edits::PostEditService,
+        post_views::{PostViewAggregator, PostViewService, Viewer},
+        preferences::PreferencesService,
+        realtime::RealtimeService,
+    },
+    utils::{errors::AppError, etag::{json_with_etag, ETagBuilder}, rate_limit},
 };
 
 pub fn routes() -> Router {
     Router::new()
-        .route("/posts", post(create_post))
+        .route("/posts", post(create_post).layer(from_fn(idempotency_middleware)))
         .route("/posts", get(get_feed))
-        .route("/posts/{id}", get(get_post))
-        .route("/posts/{id}", put(update_post))
-        .route("/posts/{id}", delete(delete_post))
-        .route("/posts/{id}/like", post(toggle_like))
-        .route("/posts/{id}/comments", post(create_comment))
-        .route("/posts/{id}/comments", get(get_comments))
-        .route("/comments/{id}", put(update_comment))
-        .route("/comments/{id}", delete(delete_comment))
-        .route("/users/{id}/follow", post(toggle_follow))
-        .route("/users/{id}/posts", get(get_user_posts))
-        .route("/users/{id}/followers", get(get_followers))
-        .route("/users/{id}/following", get(get_following))
+        .route("/posts/:id", get(get_post))
+        .route("/posts/:id", put(update_post))
+        .route("/posts/:id", delete(delete_post))
+        .route("/posts/:id/like", post(toggle_like))
+        .route("/posts/:id/cooked", post(mark_cooked))
+        .route("/posts/:id/cooked", get(get_cooked_gallery))
+        .route("/posts/:id/comments", post(create_comment))
+        .route("/posts/:id/comments", get(get_comments))
+        .route("/comments/:id", put(update_comment))
+        .route("/comments/:id", delete(delete_comment))
+        .route("/users/:id/follow", post(toggle_follow))
+        .route("/users/:id/posts", get(get_user_posts))
+        .route("/users/:id/followers", get(get_followers))
+        .route("/users/:id/following", get(get_following))
         .route("/trending", get(get_trending_posts))
         .route("/upload", post(upload_media))
+        .route("/posts/:id/analytics", get(get_post_analytics))
+        .route("/posts/:id/edits", get(get_post_edits))
+        .route("/status", get(get_my_status))
+        .route("/status", post(set_status))
+        .route("/status", delete(clear_status))
+        .route("/status/privacy", put(update_status_privacy))
+        .route("/users/:id/status", get(get_user_status))
+        .route("/following/activity", get(get_following_activity))
+}
+
+/// Маршруты с необязательной авторизацией
+pub fn public_routes() -> Router {
+    Router::new()
+        .route("/posts/:id/view", post(record_post_view))
+}
+
+/// Маршруты для гостей (лендинг): без авторизации, с ограничением частоты по IP
+pub fn anonymous_routes() -> Router {
+    Router::new()
+        .route("/feed", get(get_public_feed))
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreatePostRequest {
     #[validate(length(min = 1, max = 1000))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub content: String,
     pub post_type: PostType,
     pub recipe_id: Option<Uuid>,
     pub media_urls: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized_vec")]
     pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub location: Option<String>,
+    /// Не указана — из настроек приватности пользователя
+    #[serde(default)]
+    pub visibility: Option<PostVisibility>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateCommentRequest {
     #[validate(length(min = 1, max = 500))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub content: String,
     pub parent_comment_id: Option<Uuid>,
 }
 
+/// Отметка «приготовил(а)»; все поля необязательны — тело может быть `{}`
+#[derive(Debug, Deserialize, Validate)]
+pub struct CookedItRequest {
+    #[validate(range(min = 1, max = 5))]
+    pub rating: Option<i16>,
+    /// Фото из POST /community/upload
+    pub media_id: Option<Uuid>,
+    #[validate(length(max = 500))]
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub note: Option<String>,
+    /// Показывать фото и заметку всем, кто видит пост
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CookedItQueryParams {
+    /// Записать блюдо в дневник одной порцией
+    #[serde(default)]
+    pub log_meal: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FeedQueryParams {
     pub post_type: Option<PostType>,
@@ -63,6 +137,12 @@ pub struct FeedQueryParams {
     pub offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PublicFeedQueryParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UserPostsQueryParams {
     pub post_type: Option<PostType>,
@@ -73,6 +153,7 @@ pub struct UserPostsQueryParams {
 #[derive(Debug, Serialize, Clone)]
 pub struct PostResponse {
     pub id: Uuid,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
     pub content: String,
     pub post_type: PostType,
     pub recipe_id: Option<Uuid>,
@@ -83,21 +164,58 @@ pub struct PostResponse {
     pub likes_count: i32,
     pub comments_count: i32,
     pub shares_count: i32,
+    pub views_count: i64,
+    /// Отметки «приготовил(а)»; считаются отдельно от лайков
+    pub cooked_count: i64,
     pub is_liked: bool,
     pub author: UserSummary,
+    pub link_previews: Vec<LinkPreview>,
+    /// Текст менялся после публикации
+    pub is_edited: bool,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub edit_count: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Пост в публичной ленте. Отдельный тип, а не `PostResponse` с вычищенными полями:
+/// персональные поля (is_liked, id автора, фамилия, место) в нем отсутствуют вовсе.
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicPostResponse {
+    pub id: Uuid,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
+    pub content: String,
+    pub post_type: PostType,
+    pub recipe_id: Option<Uuid>,
+    pub recipe_name: Option<String>,
+    pub media_urls: Vec<String>,
+    pub tags: Vec<String>,
+    pub likes_count: i64,
+    pub comments_count: i64,
+    pub cooked_count: i64,
+    pub author: PublicAuthor,
+    pub link_previews: Vec<LinkPreview>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicAuthor {
+    pub first_name: String,
+    pub avatar_url: Option<String>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct CommentResponse {
     pub id: Uuid,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
     pub content: String,
     pub parent_comment_id: Option<Uuid>,
     pub likes_count: i32,
     pub replies_count: i32,
     pub is_liked: bool,
     pub author: UserSummary,
+    pub is_edited: bool,
+    pub edited_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -119,8 +237,122 @@ pub struct FollowResponse {
     pub followed_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PostViewQueryParams {
+    /// Откуда пришел зритель (feed, profile, share_link, ...)
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CookedItResponse {
+    pub cooked: CookedIt,
+    pub cooked_count: i64,
+    /// У рецепта поста есть питательность: блюдо можно записать в дневник (`?log_meal=true`)
+    pub can_log_meal: bool,
+    pub diary_entry: Option<DiaryEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CookedGalleryResponse {
+    /// Все отметки, включая закрытые
+    pub cooked_count: i64,
+    pub average_rating: Option<f64>,
+    pub entries: Vec<CookedItEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostAnalyticsResponse {
+    pub post_id: Uuid,
+    pub period_days: i32,
+    pub views: i64,
+    /// Авторизованные пользователи без повторов за весь период
+    pub unique_viewers: i64,
+    pub anonymous_views: i64,
+    pub likes: i64,
+    pub comments: i64,
+    pub daily: Vec<PostAnalyticsDay>,
+    /// Только просмотры, для которых был передан `source`
+    pub referrers: Vec<ReferrerCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostAnalyticsDay {
+    pub date: NaiveDate,
+    pub views: i64,
+    pub anonymous_views: i64,
+    pub likes: i64,
+    pub comments: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferrerCount {
+    pub source: String,
+    pub views: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetStatusRequest {
+    pub activity: ActivityKind,
+    pub recipe_id: Option<Uuid>,
+    #[serde(default = "default_status_minutes")]
+    pub expires_in_minutes: i64,
+}
+
+fn default_status_minutes() -> i64 {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusPrivacyRequest {
+    pub share_activity: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MyStatusResponse {
+    pub share_activity: bool,
+    pub status: Option<ActivityStatusResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityStatusResponse {
+    pub user: ActivityStatusUser,
+    pub activity: ActivityKind,
+    pub recipe_id: Option<Uuid>,
+    pub recipe_name: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityStatusUser {
+    pub id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub avatar_url: Option<String>,
+}
+
+impl From<FriendActivityStatus> for ActivityStatusResponse {
+    fn from(status: FriendActivityStatus) -> Self {
+        Self {
+            user: ActivityStatusUser {
+                id: status.user_id,
+                first_name: status.first_name,
+                last_name: status.last_name,
+                avatar_url: status.avatar_url,
+            },
+            activity: status.activity,
+            recipe_id: status.recipe_id,
+            recipe_name: status.recipe_name,
+            started_at: status.created_at,
+            expires_at: status.expires_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct MediaUploadResponse {
+    /// Id для ссылок на файл (шаги и галерея рецепта)
+    pub id: Uuid,
     pub url: String,
     pub thumbnail_url: Option<String>,
     pub media_type: String,
@@ -129,11 +361,20 @@ pub struct MediaUploadResponse {
 
 pub async fn create_post(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(preview_cache): Extension<LinkPreviewCache>,
+    notifier: CommunityNotifier,
     claims: Claims,
     Json(payload): Json<CreatePostRequest>,
 ) -> Result<ResponseJson<PostResponse>, AppError> {
     payload.validate()?;
 
+    let visibility = match payload.visibility {
+        Some(visibility) => visibility,
+        None => PreferencesService::new(pool.clone()).for_user(claims.sub).await?.privacy.default_post_visibility,
+    };
+
+    let link_previews = LinkPreviewService::new(pool.clone()).previews_for(&payload.content, &preview_cache).await;
     let create_post = CreatePost {
         author_id: claims.sub,
         content: payload.content,
@@ -142,20 +383,28 @@ pub async fn create_post(
         media_urls: payload.media_urls.unwrap_or_default(),
         tags: payload.tags.unwrap_or_default(),
         location: payload.location,
+        visibility,
+        link_previews,
     };
 
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool.clone()).with_clock(clock.clone());
     let post = community_service.create_post(create_post).await?;
 
+    notifier.post_published(post.id, claims.sub, &post.content, clock.now()).await;
+
     Ok(ResponseJson(post))
 }
 
+/// Лента с ETag; счетчики просмотров входят в хеш, поэтому новый просмотр тоже меняет версию
 pub async fn get_feed(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
+    headers: HeaderMap,
+    RawQuery(raw_query): RawQuery,
     Query(params): Query<FeedQueryParams>,
-) -> Result<ResponseJson<Vec<PostResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+) -> Result<Response, AppError> {
+    let community_service = CommunityService::with_pools(pools.clone()).with_clock(clock);
     let posts = community_service.get_feed(
         claims.sub,
         params.post_type,
@@ -165,40 +414,109 @@ pub async fn get_feed(
         params.offset.unwrap_or(0),
     ).await?;
 
-    Ok(ResponseJson(posts))
+    let posts = with_counts(pools.read(ReadConsistency::Replica).clone(), posts).await;
+    let etag = ETagBuilder::for_user(claims.sub)
+        .part(raw_query.unwrap_or_default().as_bytes())
+        .content(&posts)
+        .build();
+    Ok(json_with_etag(&headers, etag, posts))
+}
+
+/// Публичная лента для лендинга. Ответ не зависит от пользователя,
+/// поэтому его можно кешировать и в общих кешах (CDN).
+pub async fn get_public_feed(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    headers: HeaderMap,
+    Query(params): Query<PublicFeedQueryParams>,
+) -> Result<Response, AppError> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 50);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let community_service = CommunityService::with_pools(pools).with_clock(clock);
+    let posts = community_service.get_public_feed(limit, offset).await?;
+
+    // Общий для всех ответ — ETag без id пользователя
+    let etag = ETagBuilder::new().part(&limit.to_le_bytes()).part(&offset.to_le_bytes()).content(&posts).build();
+    let mut response = json_with_etag(&headers, etag, posts);
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=30"));
+    Ok(response)
+}
+
+/// Подставляет счетчики просмотров и отметок «приготовил(а)»; при ошибке лента отдается без них
+async fn with_counts(pool: DbPool, mut posts: Vec<PostResponse>) -> Vec<PostResponse> {
+    let ids: Vec<Uuid> = posts.iter().map(|post| post.id).collect();
+
+    match PostViewService::new(pool.clone()).views_counts(&ids).await {
+        Ok(counts) => {
+            for post in posts.iter_mut() {
+                post.views_count = counts.get(&post.id).copied().unwrap_or(0);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to load post views counts: {:?}", e),
+    }
+    match CookedItService::new(pool).cooked_counts(&ids).await {
+        Ok(counts) => {
+            for post in posts.iter_mut() {
+                post.cooked_count = counts.get(&post.id).copied().unwrap_or(0);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to load post cooked counts: {:?}", e),
+    }
+
+    posts
 }
 
 pub async fn get_post(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<PostResponse>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool.clone()).with_clock(clock);
     let post = community_service.get_post_by_id(id, Some(claims.sub)).await?;
+    let post = with_counts(pool, vec![post]).await.remove(0);
 
     Ok(ResponseJson(post))
 }
 
 pub async fn update_post(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(preview_cache): Extension<LinkPreviewCache>,
+    Extension(config): Extension<Config>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreatePostRequest>,
 ) -> Result<ResponseJson<PostResponse>, AppError> {
     payload.validate()?;
 
-    let community_service = CommunityService::new(pool);
-    let post = community_service.update_post(id, claims.sub, payload).await?;
+    let link_previews = LinkPreviewService::new(pool.clone()).previews_for(&payload.content, &preview_cache).await;
+    let community_service = CommunityService::new(pool).with_clock(clock);
+    let post = community_service
+        .update_post(id, claims.sub, payload, link_previews, &config.post_edits)
+        .await?;
 
     Ok(ResponseJson(post))
 }
 
+/// Прежние версии текста поста (последние 5); только автору и модераторам
+pub async fn get_post_edits(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<Vec<PostEdit>>, AppError> {
+    let edits = PostEditService::new(pool).history(id, &claims).await?;
+    Ok(ResponseJson(edits))
+}
+
 pub async fn delete_post(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     community_service.delete_post(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Post deleted successfully"})))
@@ -206,11 +524,16 @@ pub async fn delete_post(
 
 pub async fn toggle_like(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    notifier: CommunityNotifier,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock.clone());
     let is_liked = community_service.toggle_post_like(id, claims.sub).await?;
+    if is_liked {
+        notifier.post_liked(id, claims.sub, clock.now()).await;
+    }
 
     Ok(ResponseJson(serde_json::json!({
         "is_liked": is_liked,
@@ -218,8 +541,61 @@ pub async fn toggle_like(
     })))
 }
 
+/// Отметка «приготовил(а)» на посте с рецептом; повторный вызов обновляет ее.
+/// Автор поста получает уведомление только о первой отметке пользователя.
+pub async fn mark_cooked(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    notifier: CommunityNotifier,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(params): Query<CookedItQueryParams>,
+    Json(payload): Json<CookedItRequest>,
+) -> Result<ResponseJson<CookedItResponse>, AppError> {
+    payload.validate()?;
+
+    let input = CookedItInput {
+        rating: payload.rating,
+        media_id: payload.media_id,
+        note: payload.note.filter(|note| !note.is_empty()),
+        is_public: payload.is_public,
+    };
+    let outcome = CookedItService::new(pool.clone()).with_clock(clock.clone()).mark(id, claims.sub, input, params.log_meal).await?;
+
+    if outcome.created {
+        notifier.post_cooked(id, claims.sub, outcome.cooked.rating, outcome.cooked_count, clock.now()).await;
+    }
+
+    Ok(ResponseJson(CookedItResponse {
+        cooked: outcome.cooked,
+        cooked_count: outcome.cooked_count,
+        can_log_meal: outcome.can_log_meal,
+        diary_entry: outcome.diary_entry,
+    }))
+}
+
+pub async fn get_cooked_gallery(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PublicFeedQueryParams>,
+) -> Result<ResponseJson<CookedGalleryResponse>, AppError> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 50);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let gallery = CookedItService::new(pool).gallery(id, claims.sub, limit, offset).await?;
+
+    Ok(ResponseJson(CookedGalleryResponse {
+        cooked_count: gallery.cooked_count,
+        average_rating: gallery.average_rating,
+        entries: gallery.entries,
+    }))
+}
+
 pub async fn create_comment(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    notifier: CommunityNotifier,
     claims: Claims,
     Path(post_id): Path<Uuid>,
     Json(payload): Json<CreateCommentRequest>,
@@ -233,19 +609,22 @@ pub async fn create_comment(
         parent_comment_id: payload.parent_comment_id,
     };
 
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool.clone()).with_clock(clock.clone());
     let comment = community_service.create_comment(create_comment).await?;
 
+    notifier.comment_added(post_id, comment.id, claims.sub, &comment.content, clock.now()).await;
+
     Ok(ResponseJson(comment))
 }
 
 pub async fn get_comments(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(post_id): Path<Uuid>,
     Query(params): Query<FeedQueryParams>,
 ) -> Result<ResponseJson<Vec<CommentResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     let comments = community_service.get_post_comments(
         post_id,
         Some(claims.sub),
@@ -258,13 +637,14 @@ pub async fn get_comments(
 
 pub async fn update_comment(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateCommentRequest>,
 ) -> Result<ResponseJson<CommentResponse>, AppError> {
     payload.validate()?;
 
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     let comment = community_service.update_comment(id, claims.sub, payload.content).await?;
 
     Ok(ResponseJson(comment))
@@ -272,10 +652,11 @@ pub async fn update_comment(
 
 pub async fn delete_comment(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     community_service.delete_comment(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Comment deleted successfully"})))
@@ -283,6 +664,7 @@ pub async fn delete_comment(
 
 pub async fn toggle_follow(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(user_id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
@@ -290,7 +672,7 @@ pub async fn toggle_follow(
         return Err(AppError::BadRequest("Cannot follow yourself".to_string()));
     }
 
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     let is_following = community_service.toggle_follow(claims.sub, user_id).await?;
 
     Ok(ResponseJson(serde_json::json!({
@@ -301,11 +683,12 @@ pub async fn toggle_follow(
 
 pub async fn get_user_posts(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(user_id): Path<Uuid>,
     Query(params): Query<UserPostsQueryParams>,
 ) -> Result<ResponseJson<Vec<PostResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool.clone()).with_clock(clock);
     let posts = community_service.get_user_posts(
         user_id,
         Some(claims.sub),
@@ -313,16 +696,18 @@ pub async fn get_user_posts(
         params.limit.unwrap_or(20),
         params.offset.unwrap_or(0),
     ).await?;
+    let posts = with_counts(pool, posts).await;
 
     Ok(ResponseJson(posts))
 }
 
 pub async fn get_followers(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(user_id): Path<Uuid>,
 ) -> Result<ResponseJson<Vec<FollowResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     let followers = community_service.get_followers(user_id).await?;
 
     Ok(ResponseJson(followers))
@@ -330,27 +715,32 @@ pub async fn get_followers(
 
 pub async fn get_following(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(user_id): Path<Uuid>,
 ) -> Result<ResponseJson<Vec<FollowResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+    let community_service = CommunityService::new(pool).with_clock(clock);
     let following = community_service.get_following(user_id).await?;
 
     Ok(ResponseJson(following))
 }
 
 pub async fn get_trending_posts(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
-) -> Result<ResponseJson<Vec<PostResponse>>, AppError> {
-    let community_service = CommunityService::new(pool);
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let community_service = CommunityService::with_pools(pools.clone()).with_clock(clock);
     let posts = community_service.get_trending_posts(Some(claims.sub)).await?;
+    let posts = with_counts(pools.read(ReadConsistency::Replica).clone(), posts).await;
 
-    Ok(ResponseJson(posts))
+    let etag = ETagBuilder::for_user(claims.sub).content(&posts).build();
+    Ok(json_with_etag(&headers, etag, posts))
 }
 
 pub async fn upload_media(
-    Extension(_pool): Extension<DbPool>,
+    Extension(pool): Extension<DbPool>,
     claims: Claims,
     // TODO: Implement multipart file upload
 ) -> Result<ResponseJson<MediaUploadResponse>, AppError> {
@@ -359,6 +749,494 @@ pub async fn upload_media(
     // Placeholder implementation
     let placeholder_data = vec![0u8; 1024]; // 1KB placeholder
     let upload_result = media_service.upload_file(claims.sub, placeholder_data).await?;
+    MediaLibrary::new(pool).record(claims.sub, &upload_result).await?;
     
     Ok(ResponseJson(upload_result))
 }
+
+/// Учитывает просмотр поста. Запись в БД происходит пачками в фоне,
+/// поэтому вызов можно отправлять на каждое появление поста на экране.
+pub async fn record_post_view(
+    Extension(post_views): Extension<PostViewAggregator>,
+    claims: Option<Claims>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PostViewQueryParams>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let viewer = match claims {
+        Some(claims) => Viewer::User(claims.sub),
+        None => post_views.anonymous_viewer(&client_ip(&headers)),
+    };
+
+    let counted = post_views.record(id, viewer, params.source.as_deref());
+
+    Ok(ResponseJson(serde_json::json!({ "counted": counted })))
+}
+
+/// IP клиента из заголовков прокси; используется только для хеширования анонимных просмотров
+fn client_ip(headers: &HeaderMap) -> String {
+    rate_limit::client_ip(headers).unwrap_or_else(|| "unknown".to_string())
+}
+
+pub async fn get_post_analytics(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<PostAnalyticsResponse>, AppError> {
+    let post_view_service = PostViewService::new(pool);
+    let analytics = post_view_service.get_post_analytics(id, claims.sub).await?;
+
+    Ok(ResponseJson(analytics))
+}
+
+pub async fn get_my_status(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<MyStatusResponse>, AppError> {
+    let activity_service = ActivityStatusService::new(pool);
+    let share_activity = activity_service.share_activity(claims.sub).await?;
+    let status = activity_service.get_status(claims.sub).await?;
+
+    Ok(ResponseJson(MyStatusResponse {
+        share_activity,
+        status: status.map(ActivityStatusResponse::from),
+    }))
+}
+
+/// Устанавливает статус «готовлю сейчас» и рассылает его подписчикам
+pub async fn set_status(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    claims: Claims,
+    Json(payload): Json<SetStatusRequest>,
+) -> Result<ResponseJson<ActivityStatusResponse>, AppError> {
+    let activity_service = ActivityStatusService::new(pool).with_clock(clock);
+    let status = activity_service.set_status(SetActivityStatus {
+        user_id: claims.sub,
+        activity: payload.activity,
+        recipe_id: payload.recipe_id,
+        expires_in_minutes: payload.expires_in_minutes,
+    }).await?;
+
+    let follower_ids = activity_service.follower_ids(claims.sub).await?;
+    let _ = realtime_service.notify_friend_activity(&follower_ids, &status).await;
+
+    Ok(ResponseJson(status.into()))
+}
+
+pub async fn clear_status(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let activity_service = ActivityStatusService::new(pool);
+    activity_service.clear_status(claims.sub).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "Status cleared"})))
+}
+
+/// Включает или отключает показ статуса; при отключении текущий статус удаляется
+pub async fn update_status_privacy(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<StatusPrivacyRequest>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let activity_service = ActivityStatusService::new(pool);
+    let share_activity = activity_service.set_share_activity(claims.sub, payload.share_activity).await?;
+
+    Ok(ResponseJson(serde_json::json!({"share_activity": share_activity})))
+}
+
+/// Статус для профиля пользователя; `null`, если статуса нет, он истек или скрыт настройками
+pub async fn get_user_status(
+    Extension(pool): Extension<DbPool>,
+    _claims: Claims,
+    Path(user_id): Path<Uuid>,
+) -> Result<ResponseJson<Option<ActivityStatusResponse>>, AppError> {
+    let activity_service = ActivityStatusService::new(pool);
+    let status = activity_service.get_status(user_id).await?;
+
+    Ok(ResponseJson(status.map(ActivityStatusResponse::from)))
+}
+
+pub async fn get_following_activity(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<ActivityStatusResponse>>, AppError> {
+    let activity_service = ActivityStatusService::new(pool);
+    let statuses = activity_service.following_activity(claims.sub).await?;
+
+    Ok(ResponseJson(statuses.into_iter().map(ActivityStatusResponse::from).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use chrono::SubsecRound;
+    use crate::services::realtime::{WebSocketEvent, WebSocketManager};
+    use crate::test_support::{claims_for, frozen_clock, insert_user};
+    use crate::utils::format::Locale;
+
+    #[sqlx::test]
+    async fn script_in_post_comes_back_escaped(pool: PgPool) {
+        let payload: CreatePostRequest = serde_json::from_value(serde_json::json!({
+            "content": "Мой суп <script>alert(\"xss\")</script><b>вкусный</b> & 2 < 3",
+            "post_type": "Text"
+        }))
+        .unwrap();
+        assert_eq!(payload.content, "Мой суп вкусный & 2 < 3");
+
+        let post = CommunityService::new(pool.clone())
+            .create_post(CreatePost {
+                author_id: insert_user(&pool, "Анна").await,
+                content: payload.content,
+                post_type: payload.post_type,
+                recipe_id: None,
+                media_urls: Vec::new(),
+                tags: Vec::new(),
+                location: None,
+                visibility: PostVisibility::Public,
+                link_previews: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let json = serde_json::to_value(&post).unwrap();
+        assert_eq!(json["content"], "Мой суп вкусный &amp; 2 &lt; 3");
+    }
+
+    #[sqlx::test]
+    async fn status_is_pushed_to_followers_only_while_sharing_is_enabled(pool: PgPool) {
+        let cook = insert_user(&pool, "Анна").await;
+        let follower = insert_user(&pool, "Борис").await;
+        sqlx::query("INSERT INTO follows (follower_id, following_id) VALUES ($1, $2)")
+            .bind(follower)
+            .bind(cook)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let manager = Arc::new(WebSocketManager::new());
+        let mut registration = manager.add_client(follower, None, "Борис".to_string(), Locale::Ru).await.unwrap();
+        let realtime = Arc::new(RealtimeService::new(manager));
+        let request = || SetStatusRequest {
+            activity: ActivityKind::Cooking,
+            recipe_id: None,
+            expires_in_minutes: 60,
+        };
+        // Истечение статуса сверяется с временем базы, поэтому часы стоят на текущей секунде
+        let now = Utc::now().trunc_subsecs(0);
+        let clock: SharedClock = frozen_clock(now);
+
+        let ResponseJson(status) = set_status(
+            Extension(pool.clone()),
+            Extension(clock.clone()),
+            Extension(realtime.clone()),
+            claims_for(&pool, cook).await,
+            Json(request()),
+        )
+        .await
+        .unwrap();
+        assert_eq!((status.user.id, status.user.first_name.as_str()), (cook, "Анна"));
+        assert_eq!((status.activity, status.recipe_id), (ActivityKind::Cooking, None));
+        assert_eq!((status.started_at, status.expires_at), (now, now + chrono::Duration::minutes(60)));
+        match registration.receiver.try_recv().unwrap() {
+            WebSocketEvent::FriendActivity { user_id, activity, .. } => {
+                assert_eq!((user_id, activity), (cook, ActivityKind::Cooking));
+            }
+            other => panic!("expected FriendActivity, got {:?}", other),
+        }
+
+        let ResponseJson(privacy) = update_status_privacy(
+            Extension(pool.clone()),
+            claims_for(&pool, cook).await,
+            Json(StatusPrivacyRequest { share_activity: false }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(privacy, serde_json::json!({ "share_activity": false }));
+
+        let rejected = set_status(
+            Extension(pool.clone()),
+            Extension(clock),
+            Extension(realtime),
+            claims_for(&pool, cook).await,
+            Json(request()),
+        )
+        .await;
+        assert!(matches!(rejected, Err(AppError::Forbidden(_))));
+        assert!(registration.receiver.try_recv().is_err());
+
+        let listing = get_following_activity(Extension(pool.clone()), claims_for(&pool, follower).await)
+            .await
+            .unwrap()
+            .0;
+        assert!(listing.is_empty());
+    }
+
+    fn keys(value: &serde_json::Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn public_post_response_has_no_private_fields() {
+        let post = PublicPostResponse {
+            id: Uuid::new_v4(),
+            content: "Борщ".to_string(),
+            post_type: PostType::Text,
+            recipe_id: None,
+            recipe_name: None,
+            media_urls: vec!["https://cdn.example.com/borscht.jpg".to_string()],
+            tags: Vec::new(),
+            likes_count: 2,
+            comments_count: 1,
+            cooked_count: 0,
+            author: PublicAuthor { first_name: "Анна".to_string(), avatar_url: None },
+            link_previews: Vec::new(),
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&post).unwrap();
+
+        // Набор полей задан типом: персональных полей нет ни при каких данных
+        assert_eq!(
+            keys(&json),
+            vec![
+                "author", "comments_count", "content", "cooked_count", "created_at", "id",
+                "likes_count", "link_previews", "media_urls", "post_type", "recipe_id", "recipe_name", "tags",
+            ]
+        );
+        assert_eq!(keys(&json["author"]), vec!["avatar_url", "first_name"]);
+        for private in ["is_liked", "is_bookmarked", "location", "views_count", "updated_at", "edit_count"] {
+            assert!(json.get(private).is_none(), "{}", private);
+        }
+        assert!(json["likes_count"].is_number() && json["comments_count"].is_number());
+    }
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid, content: &str, visibility: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO posts (author_id, content, post_type, visibility, media_urls, location) \
+             VALUES ($1, $2, 'text', $3::post_visibility, ARRAY['https://cdn.example.com/photo.jpg'], 'Москва') RETURNING id",
+        )
+        .bind(author_id)
+        .bind(content)
+        .bind(visibility)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn public_feed_serves_guests_only_public_posts_at_read_time(pool: PgPool) {
+        use axum::http::{header, Method, Request, StatusCode};
+        use tower::ServiceExt;
+
+        use crate::test_support::{request, send, test_router};
+
+        let author = insert_user(&pool, "Анна").await;
+        let reader = insert_user(&pool, "Борис").await;
+        let soup = insert_post(&pool, author, "Суп", "public").await;
+        insert_post(&pool, author, "Только для подписчиков", "followers_only").await;
+        let pie = insert_post(&pool, author, "Пирог", "public").await;
+        sqlx::query("INSERT INTO likes (user_id, post_id) VALUES ($1, $2), ($3, $2)")
+            .bind(author)
+            .bind(soup)
+            .bind(reader)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO comments (post_id, author_id, content) VALUES ($1, $2, 'Вкусно')")
+            .bind(soup)
+            .bind(reader)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let router = test_router(&pool);
+
+        // Без токена, новые посты первыми
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/public/community/feed", None, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let posts = body.as_array().unwrap();
+        let ids: Vec<&str> = posts.iter().map(|post| post["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec![pie.to_string(), soup.to_string()]);
+
+        let soup_json = &posts[1];
+        assert_eq!((soup_json["likes_count"].as_i64(), soup_json["comments_count"].as_i64()), (Some(2), Some(1)));
+        assert_eq!(soup_json["media_urls"], serde_json::json!(["https://cdn.example.com/photo.jpg"]));
+        assert_eq!(keys(&soup_json["author"]), vec!["avatar_url", "first_name"]);
+        assert_eq!(soup_json["author"]["first_name"], "Анна");
+        assert!(soup_json.get("location").is_none() && soup_json.get("is_liked").is_none());
+
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/public/community/feed?limit=1&offset=1", None, None)).await;
+        assert_eq!(body[0]["id"], soup.to_string());
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/api/v1/public/community/feed").body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "public, max-age=30");
+
+        // Видимость проверяется при чтении: пост, ставший followers_only, сразу пропадает
+        sqlx::query("UPDATE posts SET visibility = 'followers_only' WHERE id = $1")
+            .bind(pie)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/public/community/feed", None, None)).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["id"], soup.to_string());
+    }
+
+    #[sqlx::test]
+    async fn editing_a_missing_post_or_comment_is_a_404(pool: PgPool) {
+        use axum::http::{Method, StatusCode};
+
+        use crate::test_support::{access_token, request, send, test_router};
+
+        let author = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, author).await;
+        let router = test_router(&pool);
+        let body = serde_json::json!({ "content": "Новый текст", "post_type": "Text" });
+
+        let (status, _) = send(
+            &router,
+            request(Method::PUT, &format!("/api/v1/community/posts/{}", Uuid::new_v4()), Some(&token), Some(body)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(
+            &router,
+            request(
+                Method::PUT,
+                &format!("/api/v1/community/comments/{}", Uuid::new_v4()),
+                Some(&token),
+                Some(serde_json::json!({ "content": "Новый текст" })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let edits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_edits").fetch_one(&pool).await.unwrap();
+        assert_eq!(edits, 0);
+    }
+
+    #[sqlx::test]
+    async fn posts_likes_comments_and_follows_are_persisted(pool: PgPool) {
+        use axum::http::{Method, StatusCode};
+
+        use crate::test_support::{access_token, request, send, test_router};
+
+        let author = insert_user(&pool, "Анна").await;
+        let reader = insert_user(&pool, "Борис").await;
+        let author_token = access_token(&pool, author).await;
+        let reader_token = access_token(&pool, reader).await;
+        let router = test_router(&pool);
+
+        let (status, created) = send(
+            &router,
+            request(
+                Method::POST,
+                "/api/v1/community/posts",
+                Some(&author_token),
+                Some(serde_json::json!({ "content": "Суп для подписчиков", "post_type": "Text", "visibility": "followers_only" })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let post_id = created["id"].as_str().unwrap().to_string();
+        assert_eq!(created["author"]["first_name"], "Анна");
+        let post_uri = format!("/api/v1/community/posts/{}", post_id);
+
+        // Пост для подписчиков не виден, пока читатель не подписан
+        let feed_ids = |body: &serde_json::Value| -> Vec<String> {
+            body.as_array().unwrap().iter().map(|post| post["id"].as_str().unwrap().to_string()).collect()
+        };
+        let (_, feed) = send(&router, request(Method::GET, "/api/v1/community/posts", Some(&reader_token), None)).await;
+        assert!(feed_ids(&feed).is_empty());
+        let (status, _) = send(&router, request(Method::POST, &format!("{}/like", post_uri), Some(&reader_token), None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let follow_uri = format!("/api/v1/community/users/{}/follow", author);
+        let (_, body) = send(&router, request(Method::POST, &follow_uri, Some(&reader_token), None)).await;
+        assert_eq!(body["is_following"], true);
+        let (_, followers) =
+            send(&router, request(Method::GET, &format!("/api/v1/community/users/{}/followers", author), Some(&reader_token), None)).await;
+        assert_eq!(followers[0]["user"]["id"], reader.to_string());
+
+        let (status, updated) = send(
+            &router,
+            request(
+                Method::PUT,
+                &post_uri,
+                Some(&author_token),
+                Some(serde_json::json!({ "content": "Суп для своих", "post_type": "Text", "tags": ["soup"] })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((updated["content"].as_str(), updated["edit_count"].as_i64()), (Some("Суп для своих"), Some(1)));
+        assert_eq!(updated["tags"], serde_json::json!(["soup"]));
+
+        let (_, feed) = send(&router, request(Method::GET, "/api/v1/community/posts?tag=soup", Some(&reader_token), None)).await;
+        assert_eq!(feed_ids(&feed), vec![post_id.clone()]);
+        let (_, user_posts) =
+            send(&router, request(Method::GET, &format!("/api/v1/community/users/{}/posts", author), Some(&reader_token), None)).await;
+        assert_eq!(feed_ids(&user_posts), vec![post_id.clone()]);
+
+        let (_, body) = send(&router, request(Method::POST, &format!("{}/like", post_uri), Some(&reader_token), None)).await;
+        assert_eq!(body["is_liked"], true);
+        let (status, comment) = send(
+            &router,
+            request(Method::POST, &format!("{}/comments", post_uri), Some(&reader_token), Some(serde_json::json!({ "content": "Вкусно" }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(
+            &router,
+            request(
+                Method::POST,
+                &format!("{}/comments", post_uri),
+                Some(&author_token),
+                Some(serde_json::json!({ "content": "Спасибо", "parent_comment_id": comment["id"] })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, post) = send(&router, request(Method::GET, &post_uri, Some(&reader_token), None)).await;
+        assert_eq!((post["likes_count"].as_i64(), post["comments_count"].as_i64()), (Some(1), Some(2)));
+        assert_eq!(post["is_liked"], true);
+        let (_, comments) = send(&router, request(Method::GET, &format!("{}/comments", post_uri), Some(&reader_token), None)).await;
+        let comments = comments.as_array().unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!((comments[0]["content"].as_str(), comments[0]["replies_count"].as_i64()), (Some("Вкусно"), Some(1)));
+
+        // Повторный лайк снимает его; чужой пост удалить нельзя
+        let (_, body) = send(&router, request(Method::POST, &format!("{}/like", post_uri), Some(&reader_token), None)).await;
+        assert_eq!(body["is_liked"], false);
+        let (status, _) = send(&router, request(Method::DELETE, &post_uri, Some(&reader_token), None)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Удаление комментария уносит и ответы на него
+        let (status, _) = send(
+            &router,
+            request(Method::DELETE, &format!("/api/v1/community/comments/{}", comment["id"].as_str().unwrap()), Some(&reader_token), None),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, comments) = send(&router, request(Method::GET, &format!("{}/comments", post_uri), Some(&reader_token), None)).await;
+        assert!(comments.as_array().unwrap().is_empty());
+
+        let (status, _) = send(&router, request(Method::DELETE, &post_uri, Some(&author_token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&router, request(Method::GET, &post_uri, Some(&author_token), None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, feed) = send(&router, request(Method::GET, "/api/v1/community/posts", Some(&reader_token), None)).await;
+        assert!(feed_ids(&feed).is_empty());
+    }
+}
@@ -1,35 +1,70 @@
+use std::sync::Arc;
+
 use axum::{
-    extract::{Extension, Json, Path, Query},
-    response::Json as ResponseJson,
+    extract::{Extension, Json, Path, Query, RawQuery},
+    http::HeaderMap,
+    response::{Json as ResponseJson, Response},
     routing::{get, post, put, delete},
+    middleware::from_fn,
     Router,
 };
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 use uuid::Uuid;
-use chrono::{DateTime, Utc, NaiveDate};
+use chrono::{DateTime, Duration, Utc, NaiveDate};
 
 use crate::{
-    db::DbPool,
-    models::diary::{DiaryEntry, CreateDiaryEntry, NutritionSummary},
-    services::{auth::Claims, diary::DiaryService},
-    utils::errors::AppError,
+    middleware::idempotency_middleware,
+    db::{DbPool, DbPools, ReadConsistency},
+    models::diary::{
+        DiaryEntry, CreateDiaryEntry, NutritionSummary, CreateMealTemplate, CreateMealTemplateItem,
+        LogMealTemplate, MealTemplateItem, MealTemplateWithItems, NutritionTotals, RemainingBudget,
+        NutritionSource, NutritionBackfillJob, FoodMemoryResponse, DiaryPhotoPage, DiaryPhotoMonth,
+    },
+    models::bulk::BulkItemFailure,
+    api::notifications::{MAX_UTC_OFFSET_MINUTES, MIN_UTC_OFFSET_MINUTES},
+    services::{
+        auth::Claims, calorie_budget::CalorieBudgetService, clock::SharedClock, diary::DiaryService, fasting::FastingService,
+        meal_templates::MealTemplateService, notification_delivery::NotificationDelivery, realtime::RealtimeService,
+        ai::AiService, nutrition_backfill::NutritionBackfillService, preferences::PreferencesService,
+        food_memory::{portion_presets, FoodMemoryService},
+        diary_photos::DiaryPhotoService, media::MediaLibrary,
+        diary_bulk::{DiaryBulkDeleteService, DiarySelection},
+    },
+    utils::{errors::AppError, etag::{json_with_etag, ETagBuilder}, food_category::portion_kind},
 };
 
 pub fn routes() -> Router {
     Router::new()
-        .route("/", post(create_entry))
+        .route("/", post(create_entry).layer(from_fn(idempotency_middleware)))
         .route("/", get(get_entries))
-        .route("/{id}", get(get_entry))
-        .route("/{id}", put(update_entry))
-        .route("/{id}", delete(delete_entry))
-        .route("/summary/{date}", get(get_daily_summary))
+        .route("/:id", get(get_entry))
+        .route("/:id", put(update_entry))
+        .route("/:id", delete(delete_entry))
+        .route("/remaining", get(get_remaining_budget))
+        .route("/food-memory", get(get_food_memory))
+        .route("/photos", get(get_photos))
+        .route("/photos/stats", get(get_photo_stats))
+        .route("/bulk-delete", post(bulk_delete_entries))
+        .route("/backfill-nutrition", post(start_nutrition_backfill))
+        .route("/backfill-nutrition/:id", get(get_nutrition_backfill))
+        .route("/backfill-nutrition/:id/cancel", post(cancel_nutrition_backfill))
+        .route("/summary/range", get(get_summary_range))
+        .route("/summary/:date", get(get_daily_summary))
         .route("/nutrition/week", get(get_weekly_nutrition))
+        .route("/templates", post(create_template))
+        .route("/templates", get(get_templates))
+        .route("/templates/:id", get(get_template))
+        .route("/templates/:id", put(update_template))
+        .route("/templates/:id", delete(delete_template))
+        .route("/templates/:id/log", post(log_template))
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateDiaryEntryRequest {
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub food_name: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub brand: Option<String>,
     pub portion_size: f32,
     pub unit: String, // "g", "ml", "piece", etc.
@@ -40,10 +75,18 @@ pub struct CreateDiaryEntryRequest {
     pub fiber_per_100g: Option<f32>,
     pub sugar_per_100g: Option<f32>,
     pub sodium_per_100g: Option<f32>,
-    pub meal_type: String, // "breakfast", "lunch", "dinner", "snack"
+    /// "breakfast", "lunch", "dinner", "snack"; нет — определяется по `consumed_at` и настройкам `meal_times`
+    #[serde(default)]
+    pub meal_type: Option<String>,
     pub consumed_at: Option<DateTime<Utc>>,
+    /// Фото из POST /community/upload — попадает в фотогалерею дневника
+    #[serde(default)]
+    pub media_id: Option<Uuid>,
 }
 
+/// Насколько `consumed_at` может опережать время сервера: запас на часы устройства
+const MAX_CONSUMED_AT_AHEAD_HOURS: i64 = 24;
+
 #[derive(Debug, Deserialize)]
 pub struct DiaryQueryParams {
     pub date: Option<NaiveDate>,
@@ -52,6 +95,85 @@ pub struct DiaryQueryParams {
     pub offset: Option<i64>,
 }
 
+/// Продукт, для которого запрашиваются запомненные и типовые порции
+#[derive(Debug, Deserialize)]
+pub struct FoodMemoryQuery {
+    pub name: String,
+}
+
+/// Период сводки; даты — местные дни пользователя, обе включительно
+#[derive(Debug, Deserialize)]
+pub struct SummaryRangeQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Период галереи — местные дни, обе даты включительно
+#[derive(Debug, Deserialize)]
+pub struct PhotoGalleryQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhotoStatsResponse {
+    pub total_photos: i64,
+    pub months: Vec<DiaryPhotoMonth>,
+}
+
+/// Массовое удаление: `ids` или `filter`. Без `confirm` — только предпросмотр с токеном подтверждения;
+/// удаление — тот же запрос с `confirm: true` и этим токеном.
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Option<Vec<Uuid>>,
+    pub filter: Option<BulkDeleteFilter>,
+    #[serde(default)]
+    pub confirm: bool,
+    pub confirmation_token: Option<String>,
+}
+
+/// Местные дни с `from` по `to` включительно; без `meal_type` — все приемы пищи
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteFilter {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub meal_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BulkDeleteResponse {
+    Preview {
+        matched: usize,
+        failures: Vec<BulkItemFailure>,
+        confirmation_token: String,
+        expires_at: DateTime<Utc>,
+    },
+    Deleted {
+        deleted: usize,
+        failures: Vec<BulkItemFailure>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemainingBudgetQuery {
+    /// Смещение местного времени от UTC в минутах: определяет, какой день сейчас
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Не учитывать записи с оценкой питательности от ИИ
+    #[serde(default)]
+    pub exclude_estimates: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NutritionBackfillRequest {
+    /// Для продуктов, которых нет в базе, запросить оценку у ИИ
+    #[serde(default)]
+    pub use_ai: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct DiaryEntryResponse {
     pub id: Uuid,
@@ -67,8 +189,16 @@ pub struct DiaryEntryResponse {
     pub total_sugar: Option<f32>,
     pub total_sodium: Option<f32>,
     pub meal_type: String,
+    pub nutrition_source: NutritionSource,
+    pub media_id: Option<Uuid>,
     pub consumed_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// Прием пищи записан вне окна питания; запись при этом сохраняется
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub outside_eating_window: bool,
+    /// `meal_type` определен сервером по времени записи — интерфейс может предложить его исправить
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub meal_type_inferred: bool,
 }
 
 impl From<DiaryEntry> for DiaryEntryResponse {
@@ -89,18 +219,167 @@ impl From<DiaryEntry> for DiaryEntryResponse {
             total_sugar: entry.sugar_per_100g.map(|s| s * multiplier),
             total_sodium: entry.sodium_per_100g.map(|s| s * multiplier),
             meal_type: entry.meal_type,
+            nutrition_source: entry.nutrition_source,
+            media_id: entry.media_id,
             consumed_at: entry.consumed_at,
             created_at: entry.created_at,
+            outside_eating_window: false,
+            meal_type_inferred: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MealTemplateRequest {
+    #[validate(length(min = 1, max = 100))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub name: String,
+    pub items: Vec<MealTemplateItemRequest>,
+}
+
+/// Строка шаблона: те же поля, что и у записи дневника, `portion_size` — порция по умолчанию
+#[derive(Debug, Deserialize)]
+pub struct MealTemplateItemRequest {
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub food_name: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub brand: Option<String>,
+    pub portion_size: f32,
+    pub unit: String,
+    pub calories_per_100g: f32,
+    pub protein_per_100g: f32,
+    pub fat_per_100g: f32,
+    pub carbs_per_100g: f32,
+    pub fiber_per_100g: Option<f32>,
+    pub sugar_per_100g: Option<f32>,
+    pub sodium_per_100g: Option<f32>,
+}
+
+impl From<MealTemplateItemRequest> for CreateMealTemplateItem {
+    fn from(item: MealTemplateItemRequest) -> Self {
+        Self {
+            food_name: item.food_name,
+            brand: item.brand,
+            portion_size: item.portion_size,
+            unit: item.unit,
+            calories_per_100g: item.calories_per_100g,
+            protein_per_100g: item.protein_per_100g,
+            fat_per_100g: item.fat_per_100g,
+            carbs_per_100g: item.carbs_per_100g,
+            fiber_per_100g: item.fiber_per_100g,
+            sugar_per_100g: item.sugar_per_100g,
+            sodium_per_100g: item.sodium_per_100g,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogMealTemplateRequest {
+    pub date: NaiveDate,
+    pub meal_type: String, // "breakfast", "lunch", "dinner", "snack"
+    /// Порции, отличающиеся от порций шаблона
+    #[serde(default)]
+    pub portions: Vec<PortionOverride>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortionOverride {
+    pub item_id: Uuid,
+    pub portion_size: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MealTemplateResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub items: Vec<MealTemplateItemResponse>,
+    /// Итог по порциям по умолчанию
+    pub totals: NutritionTotals,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MealTemplateItemResponse {
+    pub id: Uuid,
+    pub food_name: String,
+    pub brand: Option<String>,
+    pub portion_size: f32,
+    pub unit: String,
+    pub calories_per_100g: f32,
+    pub protein_per_100g: f32,
+    pub fat_per_100g: f32,
+    pub carbs_per_100g: f32,
+    pub fiber_per_100g: Option<f32>,
+    pub sugar_per_100g: Option<f32>,
+    pub sodium_per_100g: Option<f32>,
+    pub totals: NutritionTotals,
+}
+
+impl From<MealTemplateItem> for MealTemplateItemResponse {
+    fn from(item: MealTemplateItem) -> Self {
+        Self {
+            totals: item.nutrition_for(item.portion_size),
+            id: item.id,
+            food_name: item.food_name,
+            brand: item.brand,
+            portion_size: item.portion_size,
+            unit: item.unit,
+            calories_per_100g: item.calories_per_100g,
+            protein_per_100g: item.protein_per_100g,
+            fat_per_100g: item.fat_per_100g,
+            carbs_per_100g: item.carbs_per_100g,
+            fiber_per_100g: item.fiber_per_100g,
+            sugar_per_100g: item.sugar_per_100g,
+            sodium_per_100g: item.sodium_per_100g,
+        }
+    }
+}
+
+impl From<MealTemplateWithItems> for MealTemplateResponse {
+    fn from(template: MealTemplateWithItems) -> Self {
+        let totals = template.totals();
+
+        Self {
+            id: template.template.id,
+            name: template.template.name,
+            items: template.items.into_iter().map(Into::into).collect(),
+            totals,
+            created_at: template.template.created_at,
+            updated_at: template.template.updated_at,
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct LogMealTemplateResponse {
+    pub entries: Vec<DiaryEntryResponse>,
+    pub daily_summary: NutritionSummary,
+}
+
 pub async fn create_entry(
     Extension(pool): Extension<DbPool>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<CreateDiaryEntryRequest>,
 ) -> Result<ResponseJson<DiaryEntryResponse>, AppError> {
     payload.validate()?;
+    let now = clock.now();
+    let consumed_at = payload.consumed_at.unwrap_or(now);
+    validate_consumed_at(consumed_at, now)?;
+    if let Some(media_id) = payload.media_id {
+        MediaLibrary::new(pool.clone()).owned_urls(claims.sub, &[media_id]).await?;
+    }
+
+    let (meal_type, meal_type_inferred) = match payload.meal_type.filter(|meal_type| !meal_type.trim().is_empty()) {
+        Some(meal_type) => (meal_type, false),
+        None => {
+            let preferences = PreferencesService::new(pool.clone()).for_user(claims.sub).await?;
+            let meal_type = preferences.meal_times.meal_type_at(consumed_at, preferences.general.utc_offset_minutes);
+            (meal_type.to_string(), true)
+        }
+    };
 
     let create_entry = CreateDiaryEntry {
         user_id: claims.sub,
@@ -115,22 +394,140 @@ pub async fn create_entry(
         fiber_per_100g: payload.fiber_per_100g,
         sugar_per_100g: payload.sugar_per_100g,
         sodium_per_100g: payload.sodium_per_100g,
-        meal_type: payload.meal_type,
-        consumed_at: payload.consumed_at.unwrap_or_else(Utc::now),
+        media_id: payload.media_id,
+        meal_type,
+        consumed_at,
     };
 
-    let diary_service = DiaryService::new(pool);
+    let diary_service = DiaryService::new(pool.clone()).with_clock(clock);
     let entry = diary_service.create_entry(create_entry).await?;
 
-    Ok(ResponseJson(entry.into()))
+    // Проверка окна питания не должна мешать записи: при ошибке флаг просто не ставится
+    let delivery = NotificationDelivery::new(pool.clone(), realtime_service);
+    let outside_eating_window = match FastingService::new(pool).check_entry(&delivery, &entry, now).await {
+        Ok(outside) => outside,
+        Err(e) => {
+            tracing::warn!("Failed to check eating window for diary entry {}: {}", entry.id, e);
+            false
+        }
+    };
+
+    Ok(ResponseJson(DiaryEntryResponse { outside_eating_window, meal_type_inferred, ..entry.into() }))
 }
 
-pub async fn get_entries(
+/// Запись не может быть из будущего дальше, чем на `MAX_CONSUMED_AT_AHEAD_HOURS`
+fn validate_consumed_at(consumed_at: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), AppError> {
+    if consumed_at > now + Duration::hours(MAX_CONSUMED_AT_AHEAD_HOURS) {
+        return Err(AppError::BadRequest(format!(
+            "consumed_at must not be more than {} hours in the future",
+            MAX_CONSUMED_AT_AHEAD_HOURS
+        )));
+    }
+    Ok(())
+}
+
+/// Фотогалерея дневника: записи с фото по местным дням, для сетки
+pub async fn get_photos(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
+    Query(params): Query<PhotoGalleryQuery>,
+) -> Result<ResponseJson<DiaryPhotoPage>, AppError> {
+    let limit = params.limit.unwrap_or(60).clamp(1, 200);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let page = DiaryPhotoService::new(pool)
+        .with_clock(clock)
+        .photos(claims.sub, params.from, params.to, limit, offset)
+        .await?;
+
+    Ok(ResponseJson(page))
+}
+
+/// Число фото по месяцам — шкала прокрутки галереи
+pub async fn get_photo_stats(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<PhotoStatsResponse>, AppError> {
+    let months = DiaryPhotoService::new(pool).monthly_counts(claims.sub).await?;
+
+    Ok(ResponseJson(PhotoStatsResponse {
+        total_photos: months.iter().map(|month| month.photos).sum(),
+        months,
+    }))
+}
+
+pub async fn bulk_delete_entries(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<BulkDeleteRequest>,
+) -> Result<ResponseJson<BulkDeleteResponse>, AppError> {
+    let selection = match (payload.ids, payload.filter) {
+        (Some(ids), None) => DiarySelection::Ids(ids),
+        (None, Some(filter)) => DiarySelection::Filter {
+            from: filter.from,
+            to: filter.to,
+            meal_type: filter.meal_type.filter(|meal_type| !meal_type.trim().is_empty()),
+        },
+        _ => return Err(AppError::BadRequest("Provide either 'ids' or 'filter'".to_string())),
+    };
+    let service = DiaryBulkDeleteService::new(pool).with_clock(clock);
+
+    if !payload.confirm {
+        let preview = service.preview(claims.sub, &selection).await?;
+        return Ok(ResponseJson(BulkDeleteResponse::Preview {
+            matched: preview.matched,
+            failures: preview.failures,
+            confirmation_token: preview.confirmation_token,
+            expires_at: preview.expires_at,
+        }));
+    }
+
+    let token = payload
+        .confirmation_token
+        .ok_or_else(|| AppError::BadRequest("confirmation_token is required with confirm".to_string()))?;
+    let outcome = service.delete(claims.sub, &selection, &token).await?;
+
+    Ok(ResponseJson(BulkDeleteResponse::Deleted { deleted: outcome.deleted, failures: outcome.failures }))
+}
+
+/// Последние порции продукта (до 3, последняя — первой) и типовые порции по типу продукта:
+/// граммы для твердых, миллилитры для жидких, штуки для штучных
+pub async fn get_food_memory(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Query(query): Query<FoodMemoryQuery>,
+) -> Result<ResponseJson<FoodMemoryResponse>, AppError> {
+    let name = query.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("name is required".to_string()));
+    }
+
+    let kind = portion_kind(name);
+    let remembered = FoodMemoryService::new(pool)
+        .get(claims.sub, name)
+        .await?
+        .map(|memory| memory.portions)
+        .unwrap_or_default();
+
+    Ok(ResponseJson(FoodMemoryResponse {
+        name: name.to_string(),
+        portion_kind: kind,
+        remembered,
+        presets: portion_presets(kind).to_vec(),
+    }))
+}
+
+/// Список записей с ETag: повторный запрос с If-None-Match получает 304, пока записи не изменились
+pub async fn get_entries(
+    Extension(pools): Extension<DbPools>,
+    claims: Claims,
+    headers: HeaderMap,
+    RawQuery(raw_query): RawQuery,
     Query(params): Query<DiaryQueryParams>,
-) -> Result<ResponseJson<Vec<DiaryEntryResponse>>, AppError> {
-    let diary_service = DiaryService::new(pool);
+) -> Result<Response, AppError> {
+    let diary_service = DiaryService::with_pools(pools);
     let entries = diary_service.get_user_entries(
         claims.sub,
         params.date,
@@ -140,7 +537,11 @@ pub async fn get_entries(
     ).await?;
 
     let response: Vec<DiaryEntryResponse> = entries.into_iter().map(Into::into).collect();
-    Ok(ResponseJson(response))
+    let etag = ETagBuilder::for_user(claims.sub)
+        .part(raw_query.unwrap_or_default().as_bytes())
+        .content(&response)
+        .build();
+    Ok(json_with_etag(&headers, etag, response))
 }
 
 pub async fn get_entry(
@@ -156,13 +557,17 @@ pub async fn get_entry(
 
 pub async fn update_entry(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateDiaryEntryRequest>,
 ) -> Result<ResponseJson<DiaryEntryResponse>, AppError> {
     payload.validate()?;
+    if let Some(consumed_at) = payload.consumed_at {
+        validate_consumed_at(consumed_at, clock.now())?;
+    }
 
-    let diary_service = DiaryService::new(pool);
+    let diary_service = DiaryService::new(pool).with_clock(clock);
     let entry = diary_service.update_entry(id, claims.sub, payload).await?;
 
     Ok(ResponseJson(entry.into()))
@@ -180,22 +585,251 @@ pub async fn delete_entry(
 }
 
 pub async fn get_daily_summary(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
     claims: Claims,
     Path(date): Path<NaiveDate>,
 ) -> Result<ResponseJson<NutritionSummary>, AppError> {
-    let diary_service = DiaryService::new(pool);
+    let diary_service = DiaryService::with_pools(pools);
     let summary = diary_service.get_daily_summary(claims.sub, date).await?;
 
     Ok(ResponseJson(summary))
 }
 
-pub async fn get_weekly_nutrition(
+/// Сводки по дням за период до 92 дней (по умолчанию — последняя неделя), по возрастанию даты.
+/// Дни без записей приходят с нулями.
+pub async fn get_summary_range(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<SummaryRangeQuery>,
+) -> Result<ResponseJson<Vec<NutritionSummary>>, AppError> {
+    let diary_service = DiaryService::with_pools(pools).with_clock(clock);
+    let summaries = diary_service.get_summary_range(claims.sub, query.from, query.to).await?;
+
+    Ok(ResponseJson(summaries))
+}
+
+/// Сколько калорий и БЖУ осталось на сегодня и как распределить остаток по приемам пищи
+pub async fn get_remaining_budget(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<RemainingBudgetQuery>,
+) -> Result<ResponseJson<RemainingBudget>, AppError> {
+    if !(MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&query.utc_offset_minutes) {
+        return Err(AppError::BadRequest("utc_offset_minutes must be between -720 and 840".to_string()));
+    }
+
+    // Только чтение — с реплики
+    let remaining = CalorieBudgetService::new(pools.read(ReadConsistency::Replica).clone())
+        .remaining(claims.sub, clock.now(), query.utc_offset_minutes, query.exclude_estimates)
+        .await?;
+
+    Ok(ResponseJson(remaining))
+}
+
+/// Запускает заполнение питательности записей без калорий. Без тела запроса — только из базы продуктов.
+pub async fn start_nutrition_backfill(
     Extension(pool): Extension<DbPool>,
+    Extension(ai_service): Extension<AiService>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    claims: Claims,
+    payload: Option<Json<NutritionBackfillRequest>>,
+) -> Result<ResponseJson<NutritionBackfillJob>, AppError> {
+    let use_ai = payload.map(|Json(payload)| payload.use_ai).unwrap_or(false);
+    let job = NutritionBackfillService::new(pool, ai_service, realtime_service)
+        .enqueue(claims.sub, use_ai)
+        .await?;
+
+    Ok(ResponseJson(job))
+}
+
+/// Прогресс задачи заполнения
+pub async fn get_nutrition_backfill(
+    Extension(pool): Extension<DbPool>,
+    Extension(ai_service): Extension<AiService>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<NutritionBackfillJob>, AppError> {
+    let job = NutritionBackfillService::new(pool, ai_service, realtime_service)
+        .get(claims.sub, id)
+        .await?;
+
+    Ok(ResponseJson(job))
+}
+
+pub async fn cancel_nutrition_backfill(
+    Extension(pool): Extension<DbPool>,
+    Extension(ai_service): Extension<AiService>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<NutritionBackfillJob>, AppError> {
+    let job = NutritionBackfillService::new(pool, ai_service, realtime_service)
+        .cancel(claims.sub, id)
+        .await?;
+
+    Ok(ResponseJson(job))
+}
+
+/// Последние семь дней, начиная с сегодняшнего; то же, что /summary/range
+pub async fn get_weekly_nutrition(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<Vec<NutritionSummary>>, AppError> {
-    let diary_service = DiaryService::new(pool);
+    let diary_service = DiaryService::with_pools(pools).with_clock(clock);
     let summaries = diary_service.get_weekly_nutrition(claims.sub).await?;
 
     Ok(ResponseJson(summaries))
 }
+
+pub async fn create_template(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<MealTemplateRequest>,
+) -> Result<ResponseJson<MealTemplateResponse>, AppError> {
+    payload.validate()?;
+
+    let template_service = MealTemplateService::new(pool);
+    let template = template_service.create_template(CreateMealTemplate {
+        user_id: claims.sub,
+        name: payload.name,
+        items: payload.items.into_iter().map(Into::into).collect(),
+    }).await?;
+
+    Ok(ResponseJson(template.into()))
+}
+
+pub async fn get_templates(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<MealTemplateResponse>>, AppError> {
+    let template_service = MealTemplateService::new(pool);
+    let templates = template_service.list_templates(claims.sub).await?;
+
+    Ok(ResponseJson(templates.into_iter().map(Into::into).collect()))
+}
+
+pub async fn get_template(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<MealTemplateResponse>, AppError> {
+    let template_service = MealTemplateService::new(pool);
+    let template = template_service.get_template(claims.sub, id).await?;
+
+    Ok(ResponseJson(template.into()))
+}
+
+pub async fn update_template(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<MealTemplateRequest>,
+) -> Result<ResponseJson<MealTemplateResponse>, AppError> {
+    payload.validate()?;
+
+    let template_service = MealTemplateService::new(pool);
+    let template = template_service.update_template(id, CreateMealTemplate {
+        user_id: claims.sub,
+        name: payload.name,
+        items: payload.items.into_iter().map(Into::into).collect(),
+    }).await?;
+
+    Ok(ResponseJson(template.into()))
+}
+
+pub async fn delete_template(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let template_service = MealTemplateService::new(pool);
+    template_service.delete_template(claims.sub, id).await?;
+
+    Ok(ResponseJson(serde_json::json!({"message": "Template deleted successfully"})))
+}
+
+/// Записывает все продукты шаблона в дневник одним вызовом
+pub async fn log_template(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<LogMealTemplateRequest>,
+) -> Result<ResponseJson<LogMealTemplateResponse>, AppError> {
+    let template_service = MealTemplateService::new(pool).with_clock(clock);
+    let (entries, daily_summary) = template_service.log_template(claims.sub, id, LogMealTemplate {
+        date: payload.date,
+        meal_type: payload.meal_type,
+        portion_overrides: payload.portions
+            .into_iter()
+            .map(|portion| (portion.item_id, portion.portion_size))
+            .collect(),
+    }).await?;
+
+    Ok(ResponseJson(LogMealTemplateResponse {
+        entries: entries.into_iter().map(Into::into).collect(),
+        daily_summary,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use chrono::{NaiveTime, TimeZone};
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    fn entry(meal_type: Option<&str>, consumed_at: DateTime<Utc>) -> serde_json::Value {
+        json!({
+            "food_name": "Гречка",
+            "portion_size": 200.0,
+            "unit": "g",
+            "calories_per_100g": 110.0,
+            "protein_per_100g": 4.0,
+            "fat_per_100g": 1.0,
+            "carbs_per_100g": 21.0,
+            "meal_type": meal_type,
+            "consumed_at": consumed_at,
+        })
+    }
+
+    #[sqlx::test]
+    async fn meal_type_is_inferred_only_when_missing(pool: sqlx::PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user).await;
+        PreferencesService::new(pool.clone())
+            .update(user, |preferences| {
+                preferences.general.utc_offset_minutes = 180;
+                preferences.meal_times.lunch_from = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+            })
+            .await
+            .unwrap();
+        let day = Utc::now().date_naive() - Duration::days(2);
+        let utc = |hour: u32, minute: u32| Utc.from_utc_datetime(&day.and_hms_opt(hour, minute, 0).unwrap());
+
+        let mut created = Vec::new();
+        for (meal_type, consumed_at) in [
+            // 08:59 и 09:00 UTC — 11:59 и 12:00 по Москве, граница обеда из настроек
+            (None, utc(8, 59)),
+            (None, utc(9, 0)),
+            (Some(""), utc(9, 0)),
+            (Some("snack"), utc(9, 0)),
+            (Some("breakfast"), utc(19, 0)),
+        ] {
+            let (status, body) = send(&router, request(Method::POST, "/api/v1/diary", Some(&token), Some(entry(meal_type, consumed_at)))).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            created.push((body["meal_type"].as_str().unwrap().to_string(), body["meal_type_inferred"] == true));
+        }
+
+        let expected = [("breakfast", true), ("lunch", true), ("lunch", true), ("snack", false), ("breakfast", false)];
+        let expected: Vec<(String, bool)> = expected.iter().map(|(meal_type, inferred)| (meal_type.to_string(), *inferred)).collect();
+        assert_eq!(created, expected);
+    }
+}
@@ -0,0 +1,26 @@
+use axum::{
+    extract::Extension,
+    response::Json as ResponseJson,
+    routing::get,
+    Router,
+};
+
+use crate::{
+    models::feature_flag::ResolvedFlag,
+    services::{auth::Claims, feature_flags::FeatureFlags},
+    utils::errors::AppError,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(get_my_features))
+}
+
+/// Итоговые значения флагов для текущего пользователя — фронтенд скрывает по ним UI
+pub async fn get_my_features(
+    Extension(flags): Extension<FeatureFlags>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<ResolvedFlag>>, AppError> {
+    let resolved = flags.resolve_all(claims.sub).await?;
+    Ok(ResponseJson(resolved))
+}
@@ -1,38 +1,88 @@
 use axum::{
-    extract::{Extension, Json, Path, Query},
-    response::Json as ResponseJson,
-    routing::{get, post, put, delete},
+    extract::{rejection::QueryRejection, DefaultBodyLimit, Extension, FromRequestParts, Json, Path, Query},
+    http::{request::Parts, HeaderMap},
+    response::{Json as ResponseJson, Response},
+    routing::{get, patch, post, put, delete},
+    middleware::from_fn,
     Router,
 };
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError, ValidationErrors};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 
 use crate::{
-    db::DbPool,
+    api::versioning::{ApiVersion, PageParams, Paginated},
+    config::{Config, FridgeLimitsConfig},
+    middleware::{deprecation_middleware, idempotency_middleware},
+    db::{DbPool, DbPools},
     models::{
-        fridge::{FridgeItem, CreateFridgeItem, FridgeCategory, FoodWaste, CreateFoodWaste, WasteReason, ExpenseAnalytics, EconomyInsights, Allergen, Intolerance, DietType},
-        presets::{FoodPresets, AllergenInfo, IntoleranceInfo, DietInfo, ProductPreset}
+        fridge::{FridgeItem, CreateFridgeItem, UpdateFridgeItem, FridgeCategory, FridgeStats, AnalyticsOptions, CategoryAsOf, CategoryChange, RecategorizationCandidate, CategoryPreferences, FoodWaste, WasteHistoryEntry, CreateFoodWaste, WasteReason, ExpenseAnalytics, EconomyInsights, Allergen, Intolerance, DietType, DietaryProfile, DietaryWarning, UpdateDietaryProfile, FridgeComplianceReport, CheckinAdjustment, CheckinDay, CheckinPreferences, CheckinSummary, ExpiryUrgency, FridgeItemStatus, FridgeHistoryEntry, FridgeItemNoteLinks, FridgeReservation, NameSuggestion, PlanSlot, ReservationConflict, SlotReservation, StoreSuggestion},
+        presets::{FoodPresets, StarterPackSelection, StarterPackView},
+        diary::DiaryEntry,
+        snack::SnackSuggestionsResponse,
+    },
+    services::{
+        auth::Claims,
+        clock::SharedClock,
+        fridge::{store_key, FridgeService, CHECKIN_STALE_DAYS},
+        fridge_autocomplete,
+        fridge_notes::FridgeNoteService,
+        fridge_reservations::{available_quantity, FridgeReservationService},
+        ai::{AiService, AiResponseMeta, ItemIdea},
+        ai_providers::ImageInput,
+        dietary::{self, DietaryService},
+        feature_flags::FeatureFlags,
+        preferences::{parse_category_list, PreferencesService, UserLocale},
+        snack_suggestions::{SnackLimits, SnackSuggestionService},
+        starter_packs::StarterPackService,
+    },
+    utils::{
+        errors::AppError,
+        etag::{json_with_etag, ETagBuilder},
+        format::Locale,
+        receipt::{normalize_receipt, ReceiptItem, ReceiptLineIssue, ReceiptReconciliation},
+        units::{to_base, BaseUnit},
     },
-    services::{auth::Claims, fridge::FridgeService, ai::AiService},
-    utils::errors::AppError,
 };
 
 pub fn routes() -> Router {
     Router::new()
-        .route("/", post(add_item))
-        .route("/", get(get_items))
-        .route("/{id}", get(get_item))
-        .route("/{id}", put(update_item))
-        .route("/{id}", delete(remove_item))
+        .route("/", post(add_item).layer(from_fn(idempotency_middleware)))
+        // v1 — массив, v2 — страница в конверте; v1 помечается устаревшим
+        .route("/", get(get_items).layer(from_fn(deprecation_middleware)))
+        .route("/:id", get(get_item))
+        .route("/:id", put(update_item))
+        .route("/:id", patch(patch_item))
+        .route("/:id", delete(remove_item))
+        .route("/:id/ideas", get(get_item_ideas))
+        .route("/:id/consume", post(consume_item))
+        .route("/:id/reservations", get(get_item_reservations))
+        .route("/plan-slots/:slot_id/reservations", post(reserve_plan_slot).delete(release_plan_slot))
+        .route("/plan-slots/:slot_id/cook", post(cook_plan_slot))
+        .route("/history", get(get_history))
+        .route("/history/:id", delete(purge_history_item))
         .route("/suggestions", get(get_recipe_suggestions))
+        .route("/snack-suggestions", get(get_snack_suggestions))
+        .route("/snack-suggestions/:id/log", post(log_snack))
         .route("/expiring", get(get_expiring_items))
+        .route("/stats", get(get_stats))
         .route("/categories", get(get_categories))
-        .route("/waste", post(add_waste))
+        .route("/categories/preferences", get(get_category_preferences).put(update_category_preferences))
+        .route("/recategorize", post(recategorize))
+        .route("/checkin", get(get_checkin).post(submit_checkin))
+        .route("/checkin/settings", get(get_checkin_settings).put(update_checkin_settings))
+        .route("/waste", post(add_waste).layer(from_fn(idempotency_middleware)))
         .route("/waste", get(get_waste_history))
         .route("/analytics/expenses", get(get_expense_analytics))
         .route("/analytics/insights", get(get_economy_insights))
+        .route("/dietary-profile", get(get_dietary_profile).put(update_dietary_profile))
+        .route("/dietary-profile/compliance", get(get_compliance_report))
+        .route("/autocomplete/names", get(get_name_suggestions))
+        .route("/autocomplete/stores", get(get_store_suggestions))
+        .route("/starter-packs", get(get_starter_packs))
+        .route("/starter-packs/:id/apply", post(apply_starter_pack).layer(from_fn(idempotency_middleware)))
+        .route("/receipt-scan", post(scan_receipt).layer(DefaultBodyLimit::max(RECEIPT_SCAN_BODY_LIMIT)))
 }
 
 pub fn public_routes() -> Router {
@@ -46,25 +96,36 @@ pub fn public_routes() -> Router {
         .route("/autocomplete", get(get_autocomplete_options))
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateFridgeItemRequest {
     #[validate(length(min = 1, max = 100))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub name: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub brand: Option<String>,
     pub quantity: f32,
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub unit: String,
     pub category: FridgeCategory,
     pub price_per_unit: Option<f32>,
     pub total_price: Option<f32>,
     pub expiry_date: Option<DateTime<Utc>>,
     pub purchase_date: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub location: Option<String>, // "fridge", "freezer", "pantry"
+    /// Магазин; подсказки — GET /fridge/autocomplete/stores
+    #[validate(length(max = 100))]
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub store: Option<String>,
     // Новые поля для диетических ограничений
     pub contains_allergens: Option<Vec<Allergen>>,
     pub contains_intolerances: Option<Vec<Intolerance>>,
     pub suitable_for_diets: Option<Vec<DietType>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub ingredients: Option<String>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub nutritional_info: Option<String>,
 }
 
@@ -73,7 +134,56 @@ pub struct FridgeQueryParams {
     pub category: Option<FridgeCategory>,
     pub location: Option<String>,
     pub expiring_days: Option<i32>,
+    /// Для /expiring: включать ли уже просроченные продукты (по умолчанию да)
+    pub include_expired: Option<bool>,
     pub search: Option<String>,
+    /// Магазин без учета регистра и лишних пробелов
+    pub store: Option<String>,
+    /// По умолчанию — только продукты, которые сейчас в холодильнике
+    pub status: Option<FridgeItemStatus>,
+}
+
+/// Запрос списка продуктов: фильтры, страница v2 и исходная строка запроса, которая входит в ETag
+pub struct ItemsQuery {
+    pub raw: Option<String>,
+    pub filters: FridgeQueryParams,
+    pub page: PageParams,
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ItemsQuery
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let invalid = |e: QueryRejection| AppError::BadRequest(e.body_text());
+
+        Ok(Self {
+            raw: parts.uri.query().map(str::to_string),
+            filters: Query::try_from_uri(&parts.uri).map_err(invalid)?.0,
+            page: Query::try_from_uri(&parts.uri).map_err(invalid)?.0,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConsumeItemRequest {
+    /// Сколько съедено; без значения — весь остаток. Больше остатка — ошибка
+    pub quantity: Option<f32>,
+    /// Заметка к списанию ("на борщ"), сохраняется в журнале списаний продукта
+    #[validate(length(max = 500))]
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub note: Option<String>,
+    /// Отклонить запрос, если он затрагивает продукт, зарезервированный под план питания
+    #[serde(default)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQueryParams {
+    pub status: Option<FridgeItemStatus>,
 }
 
 #[derive(Debug, Serialize)]
@@ -87,24 +197,71 @@ pub struct FridgeItemResponse {
     pub price_per_unit: Option<f32>,
     pub total_price: Option<f32>,
     pub calculated_total_value: f32, // Автоматически рассчитанная стоимость
+    /// Цена за `normalized_unit` (кг, л или шт) для сравнения продуктов в разных единицах
+    pub unit_price_normalized: Option<f64>,
+    pub normalized_unit: Option<BaseUnit>,
     pub expiry_date: Option<DateTime<Utc>>,
     pub purchase_date: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_optional_escaped")]
     pub notes: Option<String>,
     pub location: Option<String>,
+    pub store: Option<String>,
     pub days_until_expiry: Option<i32>,
     pub is_expired: bool,
+    /// Срочность по сроку годности: expired / today / soon (1–3 дня) / this_week (4–7 дней) / later
+    pub urgency: Option<ExpiryUrgency>,
+    pub status: FridgeItemStatus,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Зарезервировано под план питания и свободно (в единицах продукта)
+    pub reserved_quantity: f32,
+    pub available_quantity: f32,
+    /// Списание задело зарезервированную часть; заполняется только при списании
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservation_conflict: Option<ReservationConflict>,
+    /// Категория скрыта пользователем (продукт все равно возвращается в списках)
+    pub category_hidden: bool,
+    /// Совпадения с пользовательскими ограничениями; заполняется только при добавлении и изменении
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dietary_warnings: Vec<DietaryWarning>,
+    /// Напоминание и рецепт, понятые из заметки («до пятницы», «@recipe:Борщ»)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_links: Option<FridgeItemNoteLinks>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-impl From<FridgeItem> for FridgeItemResponse {
-    fn from(item: FridgeItem) -> Self {
-        let now = Utc::now();
+impl FridgeItemResponse {
+    fn with_category_preferences(mut self, preferences: &CategoryPreferences) -> Self {
+        self.category_hidden = preferences.is_hidden(&self.category);
+        self
+    }
+
+    fn with_note_links(mut self, links: Option<FridgeItemNoteLinks>) -> Self {
+        self.note_links = links;
+        self
+    }
+
+    fn with_reserved(mut self, reserved: f32) -> Self {
+        if self.status == FridgeItemStatus::Active {
+            self.reserved_quantity = reserved;
+            self.available_quantity = available_quantity(self.quantity, reserved);
+        }
+        self
+    }
+}
+
+impl FridgeItemResponse {
+    /// Ответ для продукта; срок годности и срочность — относительно `now`
+    pub fn new(item: FridgeItem, now: DateTime<Utc>) -> Self {
         let days_until_expiry = item.expiry_date.map(|exp| {
             (exp - now).num_days() as i32
         });
-        let is_expired = days_until_expiry.map_or(false, |days| days < 0);
+        // Для закончившихся продуктов срочность не имеет смысла
+        let urgency = if item.is_active() { item.expiry_urgency(now) } else { None };
+        let is_expired = urgency == Some(ExpiryUrgency::Expired);
         let calculated_total_value = item.calculate_total_value();
+        let normalized = item.normalized_unit_price();
+        let available_quantity = if item.is_active() { item.quantity } else { 0.0 };
 
         Self {
             id: item.id,
@@ -116,12 +273,24 @@ impl From<FridgeItem> for FridgeItemResponse {
             price_per_unit: item.price_per_unit,
             total_price: item.total_price,
             calculated_total_value,
+            unit_price_normalized: normalized.map(|(price, _)| price),
+            normalized_unit: normalized.map(|(_, unit)| unit),
             expiry_date: item.expiry_date,
             purchase_date: Some(item.purchase_date),
             notes: item.notes,
             location: item.location,
+            store: item.store,
             days_until_expiry,
             is_expired,
+            urgency,
+            status: item.status,
+            finished_at: item.finished_at,
+            reserved_quantity: 0.0,
+            available_quantity,
+            reservation_conflict: None,
+            category_hidden: false,
+            dietary_warnings: Vec::new(),
+            note_links: None,
             created_at: item.created_at,
             updated_at: item.updated_at,
         }
@@ -139,13 +308,32 @@ pub struct RecipeSuggestion {
     pub ai_generated: bool,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ItemIdeasQueryParams {
+    /// Разрешить идеи с другими продуктами из холодильника
+    pub use_fridge: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemIdeasResponse {
+    pub item_id: Uuid,
+    pub item_name: String,
+    pub expiry_date: Option<DateTime<Utc>>,
+    pub ideas: Vec<ItemIdea>,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
+}
+
 pub async fn add_item(
     Extension(pool): Extension<DbPool>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<CreateFridgeItemRequest>,
 ) -> Result<ResponseJson<FridgeItemResponse>, AppError> {
     println!("🔍 ADD ITEM: Received request from user {}", claims.sub);
     payload.validate()?;
+    validate_item_limits(&payload, &config.fridge_limits, clock.now())?;
 
     let create_item = CreateFridgeItem {
         user_id: claims.sub,
@@ -157,9 +345,10 @@ pub async fn add_item(
         price_per_unit: payload.price_per_unit,
         total_price: payload.total_price,
         expiry_date: payload.expiry_date,
-        purchase_date: payload.purchase_date.unwrap_or_else(Utc::now),
+        purchase_date: payload.purchase_date.unwrap_or_else(|| clock.now()),
         notes: payload.notes,
         location: payload.location,
+        store: payload.store,
         // Новые поля для диетических ограничений
         contains_allergens: payload.contains_allergens.unwrap_or_default(),
         contains_intolerances: payload.contains_intolerances.unwrap_or_default(),
@@ -168,72 +357,479 @@ pub async fn add_item(
         nutritional_info: payload.nutritional_info,
     };
 
-    let fridge_service = FridgeService::new(pool);
+    let fridge_service = FridgeService::new(pool.clone()).with_clock(clock.clone());
     let item = fridge_service.add_item(create_item).await?;
+    let note_links = FridgeNoteService::new(pool.clone()).apply(&item, clock.now().date_naive()).await?;
 
-    Ok(ResponseJson(item.into()))
+    Ok(ResponseJson(with_restriction_warnings(pool, claims.sub, item, clock.now()).await?.with_note_links(note_links)))
 }
 
+/// Поддерживает If-None-Match: ETag зависит от пользователя, фильтров, последнего
+/// изменения продуктов, их количества и настроек категорий.
+/// Продукты холодильника. v1 — весь список массивом; v2 — `{items, pagination}` с `?limit=&offset=`
 pub async fn get_items(
     Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
-    Query(params): Query<FridgeQueryParams>,
-) -> Result<ResponseJson<Vec<FridgeItemResponse>>, AppError> {
+    version: ApiVersion,
+    headers: HeaderMap,
+    ItemsQuery { raw: raw_query, filters: params, page }: ItemsQuery,
+) -> Result<Response, AppError> {
     println!("🔍 GET ITEMS: Received request from user {}", claims.sub);
-    let fridge_service = FridgeService::new(pool);
-    let items = fridge_service.get_user_items(
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock.clone());
+    let mut items = fridge_service.get_items_by_status(
         claims.sub,
+        params.status.unwrap_or_default(),
         params.category,
         params.location,
         params.search,
     ).await?;
+    retain_store(&mut items, params.store.as_deref());
+    let preferences = PreferencesService::new(pool.clone()).get_categories(claims.sub).await?;
+    let mut note_links = FridgeNoteService::new(pool.clone()).user_links(claims.sub).await?;
+    let reserved = FridgeReservationService::new(pool).with_clock(clock.clone()).reserved_quantities(claims.sub).await?;
 
-    let response: Vec<FridgeItemResponse> = items.into_iter().map(Into::into).collect();
-    Ok(ResponseJson(response))
+    // Резервы не меняют updated_at продукта, поэтому входят в ETag отдельно
+    let mut reserved_parts: Vec<(Uuid, f32)> = items
+        .iter()
+        .filter_map(|item| reserved.get(&item.id).map(|quantity| (item.id, *quantity)))
+        .collect();
+    reserved_parts.sort_by_key(|(id, _)| *id);
+    // Отметка об отправленном напоминании тоже не меняет updated_at
+    let mut sent_reminders: Vec<Uuid> = note_links
+        .iter()
+        .filter(|(_, links)| links.reminder.as_ref().is_some_and(|reminder| reminder.sent))
+        .map(|(id, _)| *id)
+        .collect();
+    sent_reminders.sort();
+
+    let etag = ETagBuilder::for_user(claims.sub)
+        // Форма ответа зависит от версии — ETag тоже
+        .part(version.as_str().as_bytes())
+        .part(raw_query.unwrap_or_default().as_bytes())
+        .timestamp(items.iter().map(|item| item.updated_at).max())
+        .part(&items.len().to_le_bytes())
+        .content(&preferences)
+        .content(&reserved_parts)
+        .content(&sent_reminders)
+        .build();
+
+    let response: Vec<FridgeItemResponse> = items
+        .into_iter()
+        .map(|item| {
+            let item_reserved = reserved.get(&item.id).copied().unwrap_or(0.0);
+            let item_links = note_links.remove(&item.id);
+            FridgeItemResponse::new(item, clock.now())
+                .with_category_preferences(&preferences)
+                .with_reserved(item_reserved)
+                .with_note_links(item_links)
+        })
+        .collect();
+
+    Ok(match version {
+        ApiVersion::V1 => json_with_etag(&headers, etag, response),
+        ApiVersion::V2 => json_with_etag(&headers, etag, Paginated::from_all(response, &page)),
+    })
 }
 
 pub async fn get_item(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<FridgeItemResponse>, AppError> {
-    let fridge_service = FridgeService::new(pool);
+    let fridge_service = FridgeService::new(pool.clone()).with_clock(clock.clone());
     let item = fridge_service.get_item_by_id(id, claims.sub).await?;
+    let note_links = FridgeNoteService::new(pool.clone()).links(claims.sub, item.id).await?;
+    let reserved = FridgeReservationService::new(pool).with_clock(clock.clone()).reserved_quantities(claims.sub).await?;
+    let item_reserved = reserved.get(&item.id).copied().unwrap_or(0.0);
 
-    Ok(ResponseJson(item.into()))
+    Ok(ResponseJson(FridgeItemResponse::new(item, clock.now()).with_reserved(item_reserved).with_note_links(note_links)))
+}
+
+/// Отметить продукт (или его часть) съеденным. Если списание задевает резерв
+/// под план питания, в ответе есть reservation_conflict; со strict=true запрос отклоняется.
+/// Когда остаток доходит до нуля, продукт переходит в историю со статусом Consumed.
+pub async fn consume_item(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ConsumeItemRequest>,
+) -> Result<ResponseJson<FridgeItemResponse>, AppError> {
+    payload.validate()?;
+
+    let reservation_service = FridgeReservationService::new(pool).with_clock(clock.clone());
+    let (item, conflict) = reservation_service
+        .consume_item(claims.sub, id, payload.quantity, payload.note, payload.strict)
+        .await?;
+    let reserved = reservation_service.reserved_quantities(claims.sub).await?;
+    let item_reserved = reserved.get(&item.id).copied().unwrap_or(0.0);
+
+    let mut response = FridgeItemResponse::new(item, clock.now()).with_reserved(item_reserved);
+    response.reservation_conflict = conflict;
+    Ok(ResponseJson(response))
+}
+
+/// Действующие резервы продукта под план питания (для отладки)
+pub async fn get_item_reservations(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<Vec<FridgeReservation>>, AppError> {
+    let reservation_service = FridgeReservationService::new(pool).with_clock(clock);
+    let reservations = reservation_service.item_reservations(claims.sub, id).await?;
+
+    Ok(ResponseJson(reservations))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReservePlanSlotRequest {
+    pub date: NaiveDate,
+    pub recipe_id: Uuid,
+}
+
+/// Зарезервировать продукты под рецепт слота плана питания. Прежний резерв слота заменяется;
+/// в ответе — резервы и чего не хватило
+pub async fn reserve_plan_slot(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(slot_id): Path<Uuid>,
+    Json(payload): Json<ReservePlanSlotRequest>,
+) -> Result<ResponseJson<SlotReservation>, AppError> {
+    let slot = PlanSlot { slot_id, date: payload.date, recipe_id: Some(payload.recipe_id) };
+    let reservation = FridgeReservationService::new(pool).with_clock(clock).reserve_recipe(claims.sub, slot).await?;
+
+    Ok(ResponseJson(reservation))
+}
+
+/// Снять резерв слота (слот удален из плана или рецепт заменен)
+pub async fn release_plan_slot(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(slot_id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let released = FridgeReservationService::new(pool).release_slot(claims.sub, slot_id).await?;
+
+    Ok(ResponseJson(serde_json::json!({ "reservations_released": released })))
+}
+
+/// Рецепт слота приготовлен: резерв списывается как съеденное
+pub async fn cook_plan_slot(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(slot_id): Path<Uuid>,
+) -> Result<ResponseJson<Vec<FridgeItemResponse>>, AppError> {
+    let cooked = FridgeReservationService::new(pool).with_clock(clock.clone()).cook_slot(claims.sub, slot_id).await?;
+
+    Ok(ResponseJson(cooked.into_iter().map(|item| FridgeItemResponse::new(item, clock.now())).collect()))
+}
+
+/// История продуктов: съеденные, выброшенные и удаленные
+pub async fn get_history(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(params): Query<HistoryQueryParams>,
+) -> Result<ResponseJson<Vec<FridgeHistoryEntry>>, AppError> {
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock);
+    let history = fridge_service.get_history(claims.sub, params.status).await?;
+
+    Ok(ResponseJson(history))
+}
+
+/// Окончательное удаление закончившегося продукта из истории вместе с его резервами.
+/// Записи об отходах остаются, но теряют ссылку на продукт.
+pub async fn purge_history_item(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    let fridge_service = FridgeService::new(pool.clone()).with_clock(clock);
+    let item = fridge_service.get_item_by_id(id, claims.sub).await?;
+    // Резервы активного продукта не трогаем: purge_item его все равно отклонит
+    let released = match item.is_active() {
+        true => 0,
+        false => FridgeReservationService::new(pool.clone()).drop_item_reservations(claims.sub, id).await?,
+    };
+    fridge_service.purge_item(id, claims.sub).await?;
+
+    Ok(ResponseJson(serde_json::json!({
+        "message": "Item deleted permanently",
+        "reservations_released": released,
+    })))
+}
+
+/// Режим "доесть продукт": быстрые идеи для одного (обычно истекающего) продукта
+pub async fn get_item_ideas(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(ai_service): Extension<AiService>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ItemIdeasQueryParams>,
+) -> Result<ResponseJson<ItemIdeasResponse>, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+    let item = fridge_service.get_item_by_id(id, claims.sub).await?;
+
+    let other_items = if params.use_fridge.unwrap_or(false) {
+        let items = fridge_service.get_user_items(claims.sub, None, None, None).await?;
+        Some(items.into_iter().filter(|other| other.id != item.id).map(|other| other.name).collect())
+    } else {
+        None
+    };
+
+    let (ideas, meta) = ai_service.quick_item_ideas(&item, other_items).await?;
+
+    Ok(ResponseJson(ItemIdeasResponse {
+        item_id: item.id,
+        item_name: item.name,
+        expiry_date: item.expiry_date,
+        ideas,
+        meta,
+    }))
 }
 
 pub async fn update_item(
     Extension(pool): Extension<DbPool>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateFridgeItemRequest>,
 ) -> Result<ResponseJson<FridgeItemResponse>, AppError> {
     payload.validate()?;
+    validate_item_limits(&payload, &config.fridge_limits, clock.now())?;
 
-    let fridge_service = FridgeService::new(pool);
+    let fridge_service = FridgeService::new(pool.clone()).with_clock(clock.clone());
     let item = fridge_service.update_item(id, claims.sub, payload).await?;
+    let note_links = FridgeNoteService::new(pool.clone()).apply(&item, clock.now().date_naive()).await?;
+
+    Ok(ResponseJson(with_restriction_warnings(pool, claims.sub, item, clock.now()).await?.with_note_links(note_links)))
+}
+
+/// Частичное изменение продукта: меняются только переданные поля.
+/// Пределы проверяются по значениям, которые получатся после изменения.
+pub async fn patch_item(
+    Extension(pool): Extension<DbPool>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateFridgeItem>,
+) -> Result<ResponseJson<FridgeItemResponse>, AppError> {
+    payload.validate()?;
+    if matches!(payload.name, Some(None)) {
+        let mut errors = ValidationErrors::new();
+        errors.add("name", limit_violation("required", "name must not be null".to_string()));
+        return Err(AppError::Validation(errors));
+    }
+
+    let now = clock.now();
+    let fridge_service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+    let item = fridge_service
+        .patch_item(id, claims.sub, payload, |item| check_item_limits(item.into(), &config.fridge_limits, now))
+        .await?;
+    let note_links = FridgeNoteService::new(pool.clone()).apply(&item, now.date_naive()).await?;
+
+    Ok(ResponseJson(with_restriction_warnings(pool, claims.sub, item, now).await?.with_note_links(note_links)))
+}
 
-    Ok(ResponseJson(item.into()))
+/// Проверяет, что значения продукта правдоподобны: количество положительное и не больше предела
+/// своей величины (150 000 г сравниваются со 100 кг), цены неотрицательные и не больше предела,
+/// срок годности не слишком далеко, дата покупки не в будущем. Все нарушения возвращаются разом;
+/// у каждого — код и предел в `params`.
+pub fn validate_item_limits(payload: &CreateFridgeItemRequest, limits: &FridgeLimitsConfig, now: DateTime<Utc>) -> Result<(), AppError> {
+    check_item_limits(
+        ItemValues {
+            quantity: payload.quantity,
+            unit: &payload.unit,
+            price_per_unit: payload.price_per_unit,
+            total_price: payload.total_price,
+            expiry_date: payload.expiry_date,
+            purchase_date: payload.purchase_date,
+        },
+        limits,
+        now,
+    )
+}
+
+/// Значения, которые проверяет `validate_item_limits`: из запроса или из продукта после частичного изменения
+struct ItemValues<'a> {
+    quantity: f32,
+    unit: &'a str,
+    price_per_unit: Option<f32>,
+    total_price: Option<f32>,
+    expiry_date: Option<DateTime<Utc>>,
+    purchase_date: Option<DateTime<Utc>>,
+}
+
+impl<'a> From<&'a FridgeItem> for ItemValues<'a> {
+    /// Дата покупки при изменении не меняется и не проверяется
+    fn from(item: &'a FridgeItem) -> Self {
+        Self {
+            quantity: item.quantity,
+            unit: &item.unit,
+            price_per_unit: item.price_per_unit,
+            total_price: item.total_price,
+            expiry_date: item.expiry_date,
+            purchase_date: None,
+        }
+    }
+}
+
+fn check_item_limits(payload: ItemValues<'_>, limits: &FridgeLimitsConfig, now: DateTime<Utc>) -> Result<(), AppError> {
+    let mut errors = ValidationErrors::new();
+
+    let quantity = payload.quantity as f64;
+    // Неизвестные единицы (упаковки, пачки) ограничиваются как штуки
+    let (base_quantity, base_unit) = to_base(quantity, payload.unit).unwrap_or((quantity, BaseUnit::Piece));
+    let (max_quantity, max_unit) = match base_unit {
+        BaseUnit::Kg => (limits.max_kg, "kg"),
+        BaseUnit::L => (limits.max_liters, "l"),
+        BaseUnit::Piece => (limits.max_pieces, "pcs"),
+    };
+    if !quantity.is_finite() || quantity <= 0.0 {
+        let mut error = limit_violation("quantity_not_positive", "Quantity must be positive".to_string());
+        error.add_param("min_exclusive".into(), &0);
+        errors.add("quantity", error);
+    } else if base_quantity > max_quantity {
+        let mut error = limit_violation(
+            "quantity_too_large",
+            format!("Quantity {} {} exceeds the limit of {} {}", payload.quantity, payload.unit.trim(), max_quantity, max_unit),
+        );
+        error.add_param("max".into(), &max_quantity);
+        error.add_param("max_unit".into(), &max_unit);
+        errors.add("quantity", error);
+    }
+
+    for (field, price) in [("price_per_unit", payload.price_per_unit), ("total_price", payload.total_price)] {
+        let Some(price) = price else { continue };
+        if !price.is_finite() || price < 0.0 {
+            let mut error = limit_violation("price_negative", format!("{} must not be negative", field));
+            error.add_param("min".into(), &0);
+            errors.add(field, error);
+        } else if price as f64 > limits.max_price {
+            let mut error = limit_violation("price_too_large", format!("{} {} exceeds the limit of {}", field, price, limits.max_price));
+            error.add_param("max".into(), &limits.max_price);
+            errors.add(field, error);
+        }
+    }
+
+    let latest_expiry = now + Duration::days(365 * limits.max_expiry_years);
+    if payload.expiry_date.is_some_and(|expiry| expiry > latest_expiry) {
+        let mut error = limit_violation(
+            "expiry_too_far",
+            format!("expiry_date must be within {} years from now", limits.max_expiry_years),
+        );
+        error.add_param("max".into(), &latest_expiry);
+        errors.add("expiry_date", error);
+    }
+
+    let latest_purchase = now + Duration::hours(limits.purchase_date_tolerance_hours);
+    if payload.purchase_date.is_some_and(|purchase| purchase > latest_purchase) {
+        let mut error = limit_violation("purchase_date_in_future", "purchase_date must not be in the future".to_string());
+        error.add_param("max".into(), &latest_purchase);
+        errors.add("purchase_date", error);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+fn limit_violation(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message.into());
+    error
+}
+
+/// Предупреждает (не блокируя сохранение), если продукт попадает под пользовательские ограничения
+async fn with_restriction_warnings(pool: DbPool, user_id: Uuid, item: FridgeItem, now: DateTime<Utc>) -> Result<FridgeItemResponse, AppError> {
+    let warnings = DietaryService::new(pool)
+        .get_profile(user_id)
+        .await?
+        .map(|profile| dietary::custom_restriction_warnings(&profile, &item))
+        .unwrap_or_default();
+
+    let mut response = FridgeItemResponse::new(item, now);
+    response.dietary_warnings = warnings;
+    Ok(response)
 }
 
 pub async fn remove_item(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let fridge_service = FridgeService::new(pool);
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
     fridge_service.remove_item(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Item removed successfully"})))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SnackQueryParams {
+    /// Лимит калорий на порцию (по умолчанию 200)
+    pub max_calories: Option<f32>,
+    /// Минимум белка в порции, г
+    pub min_protein: Option<f32>,
+}
+
+impl SnackQueryParams {
+    fn limits(&self) -> Result<SnackLimits, AppError> {
+        let max_calories = self.max_calories.unwrap_or(200.0);
+        if !max_calories.is_finite() || max_calories <= 0.0 {
+            return Err(AppError::BadRequest("max_calories must be positive".to_string()));
+        }
+
+        Ok(SnackLimits {
+            max_calories,
+            min_protein: self.min_protein.filter(|value| value.is_finite()).unwrap_or(0.0).max(0.0),
+        })
+    }
+}
+
+/// Перекусы из холодильника под лимит калорий: до 5 продуктов с порцией и КБЖУ порции
+pub async fn get_snack_suggestions(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(params): Query<SnackQueryParams>,
+) -> Result<ResponseJson<SnackSuggestionsResponse>, AppError> {
+    let limits = params.limits()?;
+    let response = SnackSuggestionService::new(pool).suggest(claims.sub, limits, clock.now()).await?;
+    Ok(ResponseJson(response))
+}
+
+/// Записать предложенный перекус в дневник; лимиты те же, что у подсказок
+pub async fn log_snack(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(params): Query<SnackQueryParams>,
+) -> Result<ResponseJson<DiaryEntry>, AppError> {
+    let limits = params.limits()?;
+    let entry = SnackSuggestionService::new(pool).log(claims.sub, id, limits, clock.now()).await?;
+    Ok(ResponseJson(entry))
+}
+
 pub async fn get_recipe_suggestions(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(ai_service): Extension<AiService>,
     claims: Claims,
 ) -> Result<ResponseJson<Vec<RecipeSuggestion>>, AppError> {
-    let fridge_service = FridgeService::new(pool);
-    let ai_service = AiService::from_env();
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
     
     let available_items = fridge_service.get_user_items(claims.sub, None, None, None).await?;
     let suggestions = ai_service.generate_recipe_suggestions(available_items).await?;
@@ -243,31 +839,306 @@ pub async fn get_recipe_suggestions(
 
 pub async fn get_expiring_items(
     Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<FridgeQueryParams>,
 ) -> Result<ResponseJson<Vec<FridgeItemResponse>>, AppError> {
     let days = params.expiring_days.unwrap_or(3);
     
-    let fridge_service = FridgeService::new(pool);
-    let items = fridge_service.get_expiring_items(claims.sub, Some(days as u32)).await?;
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock.clone());
+    let include_expired = params.include_expired.unwrap_or(true);
+    let mut items = fridge_service.get_expiring_items(claims.sub, Some(days.max(0) as u32), include_expired).await?;
+    retain_store(&mut items, params.store.as_deref());
+    let preferences = PreferencesService::new(pool).get_categories(claims.sub).await?;
+
+    let response: Vec<FridgeItemResponse> = items
+        .into_iter()
+        .map(|item| FridgeItemResponse::new(item, clock.now()).with_category_preferences(&preferences))
+        .collect();
+    Ok(ResponseJson(response))
+}
+
+/// Фильтр `?store=`: "Лента " и "лента" — один магазин
+fn retain_store(items: &mut Vec<FridgeItem>, store: Option<&str>) {
+    if let Some(key) = store.and_then(store_key) {
+        items.retain(|item| item.store.as_deref().and_then(store_key).as_ref() == Some(&key));
+    }
+}
+
+/// Сводка по холодильнику: всего, просрочено, истекает в ближайшие 3 дня, по категориям и местам
+pub async fn get_stats(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<FridgeStats>, AppError> {
+    let stats = FridgeService::with_pools(pools).with_clock(clock).get_stats(claims.sub).await?;
+    Ok(ResponseJson(stats))
+}
+
+/// Видимые категории в порядке, выбранном пользователем
+pub async fn get_categories(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Vec<FridgeCategory>>, AppError> {
+    let preferences = PreferencesService::new(pool).get_categories(claims.sub).await?;
+    Ok(ResponseJson(preferences.effective_order()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryPreferencesRequest {
+    /// Категории в нужном порядке; не перечисленные идут следом в порядке по умолчанию
+    #[serde(default)]
+    pub order: Vec<String>,
+    #[serde(default)]
+    pub hidden: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryPreferencesResponse {
+    pub order: Vec<FridgeCategory>,
+    pub hidden: Vec<FridgeCategory>,
+    pub effective_order: Vec<FridgeCategory>,
+}
+
+impl From<CategoryPreferences> for CategoryPreferencesResponse {
+    fn from(preferences: CategoryPreferences) -> Self {
+        Self {
+            effective_order: preferences.effective_order(),
+            order: preferences.order,
+            hidden: preferences.hidden,
+        }
+    }
+}
+
+pub async fn get_category_preferences(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<CategoryPreferencesResponse>, AppError> {
+    let preferences = PreferencesService::new(pool).get_categories(claims.sub).await?;
+    Ok(ResponseJson(preferences.into()))
+}
+
+pub async fn update_category_preferences(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CategoryPreferencesRequest>,
+) -> Result<ResponseJson<CategoryPreferencesResponse>, AppError> {
+    let preferences = validate_category_preferences(payload)?;
+
+    let saved = PreferencesService::new(pool).set_categories(claims.sub, preferences).await?;
+    Ok(ResponseJson(saved.into()))
+}
+
+/// Проверяет запрос и приводит его к сохраняемым настройкам категорий
+pub fn validate_category_preferences(payload: CategoryPreferencesRequest) -> Result<CategoryPreferences, AppError> {
+    let preferences = CategoryPreferences {
+        order: parse_category_list("order", &payload.order)?,
+        hidden: parse_category_list("hidden", &payload.hidden)?,
+    };
+
+    if preferences.effective_order().is_empty() {
+        return Err(AppError::BadRequest("At least one category must stay visible".to_string()));
+    }
+
+    Ok(preferences)
+}
+
+// Еженедельная проверка холодильника
+
+#[derive(Debug, Serialize)]
+pub struct CheckinResponse {
+    pub last_checkin_at: Option<DateTime<Utc>>,
+    pub stale_after_days: i64,
+    /// Сначала продукты, которые давно не обновлялись
+    pub items: Vec<CheckinItemResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckinItemResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub brand: Option<String>,
+    pub quantity: f32,
+    pub unit: String,
+    pub category: FridgeCategory,
+    pub expiry_date: Option<DateTime<Utc>>,
+    pub last_updated: DateTime<Utc>,
+    pub days_since_update: i64,
+    pub needs_review: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckinRequest {
+    pub adjustments: Vec<CheckinAdjustment>,
+}
+
+/// Не больше стольких смен категорий за один запрос
+const MAX_RECATEGORIZE_CHANGES: usize = 500;
+
+/// `preview` — продукты категории с подсказками, ничего не меняет;
+/// `apply` — смена категорий пакетом, все или ничего
+#[derive(Debug, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RecategorizeRequest {
+    Preview { category: FridgeCategory },
+    Apply { changes: Vec<CategoryChangeRequest> },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryChangeRequest {
+    pub item_id: Uuid,
+    pub new_category: FridgeCategory,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RecategorizeResponse {
+    Preview {
+        category: FridgeCategory,
+        items: Vec<RecategorizationCandidate>,
+    },
+    Apply {
+        /// Продукты, у которых категория уже была такой
+        unchanged: usize,
+        changes: Vec<CategoryChange>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckinSettingsRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub day: CheckinDay,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckinSettingsResponse {
+    pub enabled: bool,
+    pub day: CheckinDay,
+    pub last_checkin_at: Option<DateTime<Utc>>,
+}
+
+impl From<CheckinPreferences> for CheckinSettingsResponse {
+    fn from(preferences: CheckinPreferences) -> Self {
+        Self {
+            enabled: preferences.enabled,
+            day: preferences.day,
+            last_checkin_at: preferences.last_checkin_at,
+        }
+    }
+}
+
+/// Список продуктов для сверки количеств
+pub async fn get_checkin(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<CheckinResponse>, AppError> {
+    let items = FridgeService::new(pool.clone()).with_clock(clock.clone()).get_checkin_items(claims.sub).await?;
+    let preferences = PreferencesService::new(pool).for_user(claims.sub).await?;
+
+    let now = clock.now();
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let days_since_update = (now - item.updated_at).num_days();
+            CheckinItemResponse {
+                id: item.id,
+                name: item.name,
+                brand: item.brand,
+                quantity: item.quantity,
+                unit: item.unit,
+                category: item.category,
+                expiry_date: item.expiry_date,
+                last_updated: item.updated_at,
+                days_since_update,
+                needs_review: days_since_update >= CHECKIN_STALE_DAYS,
+            }
+        })
+        .collect();
+
+    Ok(ResponseJson(CheckinResponse {
+        last_checkin_at: preferences.checkin.last_checkin_at,
+        stale_after_days: CHECKIN_STALE_DAYS,
+        items,
+    }))
+}
+
+/// Применяет корректировки пакетом: либо все, либо ни одной
+pub async fn submit_checkin(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<CheckinRequest>,
+) -> Result<ResponseJson<CheckinSummary>, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+    let summary = fridge_service.apply_checkin(claims.sub, payload.adjustments).await?;
+
+    Ok(ResponseJson(summary))
+}
+
+/// Перенос продуктов между категориями. Аналитика по умолчанию группирует по текущей категории;
+/// с `?category_as_of=historical` — по категории на момент покупки.
+pub async fn recategorize(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<RecategorizeRequest>,
+) -> Result<ResponseJson<RecategorizeResponse>, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+
+    let response = match payload {
+        RecategorizeRequest::Preview { category } => RecategorizeResponse::Preview {
+            items: fridge_service.preview_recategorization(claims.sub, category.clone()).await?,
+            category,
+        },
+        RecategorizeRequest::Apply { changes } => {
+            if changes.is_empty() {
+                return Err(AppError::BadRequest("At least one change is required".to_string()));
+            }
+            if changes.len() > MAX_RECATEGORIZE_CHANGES {
+                return Err(AppError::BadRequest(format!(
+                    "Too many changes: at most {} per request",
+                    MAX_RECATEGORIZE_CHANGES
+                )));
+            }
+
+            let requested = changes.len();
+            let changes = fridge_service
+                .recategorize(claims.sub, changes.into_iter().map(|change| (change.item_id, change.new_category)).collect())
+                .await?;
+            RecategorizeResponse::Apply {
+                unchanged: requested - changes.len(),
+                changes,
+            }
+        }
+    };
 
-    let response: Vec<FridgeItemResponse> = items.into_iter().map(Into::into).collect();
     Ok(ResponseJson(response))
 }
 
-pub async fn get_categories() -> Result<ResponseJson<Vec<FridgeCategory>>, AppError> {
-    Ok(ResponseJson(vec![
-        FridgeCategory::Dairy,
-        FridgeCategory::Meat,
-        FridgeCategory::Fish,
-        FridgeCategory::Vegetables,
-        FridgeCategory::Fruits,
-        FridgeCategory::Grains,
-        FridgeCategory::Beverages,
-        FridgeCategory::Condiments,
-        FridgeCategory::Snacks,
-        FridgeCategory::Other,
-    ]))
+pub async fn get_checkin_settings(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<CheckinSettingsResponse>, AppError> {
+    let preferences = PreferencesService::new(pool).for_user(claims.sub).await?;
+    Ok(ResponseJson(preferences.checkin.into()))
+}
+
+pub async fn update_checkin_settings(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CheckinSettingsRequest>,
+) -> Result<ResponseJson<CheckinSettingsResponse>, AppError> {
+    let saved = PreferencesService::new(pool)
+        .update(claims.sub, |settings| {
+            settings.checkin.enabled = payload.enabled;
+            settings.checkin.day = payload.day;
+        })
+        .await?;
+
+    Ok(ResponseJson(saved.checkin.into()))
 }
 
 // Новые handler'ы для отходов и аналитики
@@ -276,14 +1147,28 @@ pub async fn get_categories() -> Result<ResponseJson<Vec<FridgeCategory>>, AppEr
 pub struct CreateFoodWasteRequest {
     pub original_item_id: Option<Uuid>,
     #[validate(length(min = 1, max = 100))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub name: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub brand: Option<String>,
     pub wasted_quantity: f32,
     pub unit: String,
     pub category: FridgeCategory,
     pub waste_reason: WasteReason,
     pub wasted_value: Option<f32>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
+    /// Отклонить запрос, если продукт зарезервирован под план питания
+    #[serde(default)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FoodWasteResponse {
+    #[serde(flatten)]
+    pub waste: FoodWaste,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservation_conflict: Option<ReservationConflict>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -295,15 +1180,39 @@ pub struct WasteQueryParams {
 #[derive(Debug, Deserialize)]
 pub struct AnalyticsQueryParams {
     pub period: Option<String>, // "day", "week", "month"
+    /// Группировать по текущей категории продуктов или по категории на момент покупки
+    #[serde(default)]
+    pub category_as_of: CategoryAsOf,
+    /// Учитывать покупки-выбросы (намного дороже обычных) в суммах
+    #[serde(default)]
+    pub include_outliers: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InsightsQueryParams {
+    #[serde(default)]
+    pub category_as_of: CategoryAsOf,
+    #[serde(default)]
+    pub include_outliers: bool,
+}
+
+fn analytics_options(category_as_of: CategoryAsOf, include_outliers: bool, limits: &FridgeLimitsConfig) -> AnalyticsOptions {
+    AnalyticsOptions {
+        category_as_of,
+        include_outliers,
+        outlier_multiple: limits.outlier_multiple,
+    }
 }
 
 pub async fn add_waste(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<CreateFoodWasteRequest>,
-) -> Result<ResponseJson<FoodWaste>, AppError> {
+) -> Result<ResponseJson<FoodWasteResponse>, AppError> {
     payload.validate()?;
 
+    let strict = payload.strict;
     let create_waste = CreateFoodWaste {
         user_id: claims.sub,
         original_item_id: payload.original_item_id,
@@ -317,19 +1226,19 @@ pub async fn add_waste(
         notes: payload.notes,
     };
 
-    let fridge_service = FridgeService::new(pool);
-    let waste = fridge_service.add_waste(create_waste).await?;
+    let (waste, conflict) = FridgeReservationService::new(pool).with_clock(clock).add_waste(create_waste, strict).await?;
 
-    Ok(ResponseJson(waste))
+    Ok(ResponseJson(FoodWasteResponse { waste, reservation_conflict: conflict }))
 }
 
 pub async fn get_waste_history(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<WasteQueryParams>,
-) -> Result<ResponseJson<Vec<FoodWaste>>, AppError> {
-    let fridge_service = FridgeService::new(pool);
-    let waste_history = fridge_service.get_waste_history(
+) -> Result<ResponseJson<Vec<WasteHistoryEntry>>, AppError> {
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock);
+    let waste_history = fridge_service.get_waste_entries(
         claims.sub,
         params.start_date,
         params.end_date,
@@ -340,23 +1249,42 @@ pub async fn get_waste_history(
 
 pub async fn get_expense_analytics(
     Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<AnalyticsQueryParams>,
 ) -> Result<ResponseJson<ExpenseAnalytics>, AppError> {
     let period = params.period.as_deref().unwrap_or("week");
     
-    let fridge_service = FridgeService::new(pool);
-    let analytics = fridge_service.get_expense_analytics(claims.sub, period).await?;
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock);
+    let mut analytics = fridge_service.get_expense_analytics(
+        claims.sub,
+        period,
+        analytics_options(params.category_as_of, params.include_outliers, &config.fridge_limits),
+    ).await?;
+
+    // Разбивка по категориям — в порядке, выбранном пользователем
+    let preferences = PreferencesService::new(pool).get_categories(claims.sub).await?;
+    analytics.category_breakdown.sort_by_key(|expense| preferences.sort_key(&expense.category));
 
     Ok(ResponseJson(analytics))
 }
 
 pub async fn get_economy_insights(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(config): Extension<Config>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
+    UserLocale(locale): UserLocale,
+    Query(params): Query<InsightsQueryParams>,
 ) -> Result<ResponseJson<EconomyInsights>, AppError> {
-    let fridge_service = FridgeService::new(pool);
-    let insights = fridge_service.get_economy_insights(claims.sub).await?;
+    let fridge_service = FridgeService::with_pools(pools).with_clock(clock);
+    let insights = fridge_service.get_economy_insights(
+        claims.sub,
+        analytics_options(params.category_as_of, params.include_outliers, &config.fridge_limits),
+        locale,
+    ).await?;
 
     Ok(ResponseJson(insights))
 }
@@ -376,37 +1304,42 @@ pub struct ProductSearchQuery {
 
 /// GET /api/fridge/presets/allergens
 /// Получить список всех доступных аллергенов с подробной информацией
-pub async fn get_allergen_presets() -> Result<ResponseJson<Vec<AllergenInfo>>, AppError> {
+pub async fn get_allergen_presets(headers: HeaderMap) -> Result<Response, AppError> {
     let allergens = FoodPresets::get_allergen_info();
-    Ok(ResponseJson(allergens))
+    let etag = ETagBuilder::new().content(&allergens).build();
+    Ok(json_with_etag(&headers, etag, allergens))
 }
 
 /// GET /api/fridge/presets/intolerances
 /// Получить список всех доступных непереносимостей с подробной информацией
-pub async fn get_intolerance_presets() -> Result<ResponseJson<Vec<IntoleranceInfo>>, AppError> {
+pub async fn get_intolerance_presets(headers: HeaderMap) -> Result<Response, AppError> {
     let intolerances = FoodPresets::get_intolerance_info();
-    Ok(ResponseJson(intolerances))
+    let etag = ETagBuilder::new().content(&intolerances).build();
+    Ok(json_with_etag(&headers, etag, intolerances))
 }
 
 /// GET /api/fridge/presets/diets
 /// Получить список всех доступных диет с подробной информацией
-pub async fn get_diet_presets() -> Result<ResponseJson<Vec<DietInfo>>, AppError> {
+pub async fn get_diet_presets(headers: HeaderMap) -> Result<Response, AppError> {
     let diets = FoodPresets::get_diet_info();
-    Ok(ResponseJson(diets))
+    let etag = ETagBuilder::new().content(&diets).build();
+    Ok(json_with_etag(&headers, etag, diets))
 }
 
 /// GET /api/fridge/presets/products
 /// Получить список всех предустановленных продуктов с информацией о диетических ограничениях
-pub async fn get_product_presets() -> Result<ResponseJson<Vec<ProductPreset>>, AppError> {
+pub async fn get_product_presets(headers: HeaderMap) -> Result<Response, AppError> {
     let products = FoodPresets::get_product_presets();
-    Ok(ResponseJson(products))
+    let etag = ETagBuilder::new().content(&products).build();
+    Ok(json_with_etag(&headers, etag, products))
 }
 
 /// GET /api/fridge/presets/products/search?name=&category=&diet=&without_allergen=&without_intolerance=
 /// Поиск продуктов по различным критериям
 pub async fn search_product_presets(
+    headers: HeaderMap,
     Query(query): Query<ProductSearchQuery>,
-) -> Result<ResponseJson<Vec<ProductPreset>>, AppError> {
+) -> Result<Response, AppError> {
     let mut products = FoodPresets::get_product_presets();
 
     // Фильтрация по имени
@@ -434,7 +1367,8 @@ pub async fn search_product_presets(
         products.retain(|p| !p.common_intolerances.contains(intolerance));
     }
 
-    Ok(ResponseJson(products))
+    let etag = ETagBuilder::new().content(&products).build();
+    Ok(json_with_etag(&headers, etag, products))
 }
 
 // =============================================================================
@@ -450,12 +1384,559 @@ pub struct AutocompleteResponse {
 
 /// GET /api/fridge/autocomplete
 /// Получить все доступные опции для автозаполнения форм
-pub async fn get_autocomplete_options() -> Result<ResponseJson<AutocompleteResponse>, AppError> {
+pub async fn get_autocomplete_options(headers: HeaderMap) -> Result<Response, AppError> {
     let response = AutocompleteResponse {
         allergens: FoodPresets::get_all_allergens(),
         intolerances: FoodPresets::get_all_intolerances(),
         diets: FoodPresets::get_all_diets(),
     };
-    
-    Ok(ResponseJson(response))
+    let etag = ETagBuilder::new().content(&response).build();
+    Ok(json_with_etag(&headers, etag, response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NameSuggestionQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+/// GET /api/v1/fridge/autocomplete/names?q=мол
+/// Подсказки названия при вводе: свои продукты, затем каталог пресетов — с полями для предзаполнения формы
+pub async fn get_name_suggestions(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<NameSuggestionQuery>,
+) -> Result<ResponseJson<Vec<NameSuggestion>>, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+    Ok(ResponseJson(fridge_autocomplete::suggest_names(&fridge_service, claims.sub, &query.q).await?))
+}
+
+/// GET /api/v1/fridge/autocomplete/stores?q=лен
+/// Магазины из покупок пользователя, недавние выше; без `q` — все
+pub async fn get_store_suggestions(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<NameSuggestionQuery>,
+) -> Result<ResponseJson<Vec<StoreSuggestion>>, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+    Ok(ResponseJson(fridge_autocomplete::suggest_stores(&fridge_service, claims.sub, &query.q).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StarterPackQueryParams {
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+/// GET /api/v1/fridge/starter-packs
+/// Стартовые наборы для пустого холодильника; продукты, не подходящие по диетическому профилю,
+/// убраны и перечислены с причиной
+pub async fn get_starter_packs(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Query(params): Query<StarterPackQueryParams>,
+) -> Result<ResponseJson<Vec<StarterPackView>>, AppError> {
+    let packs = StarterPackService::new(pool).list(claims.sub, params.locale).await?;
+    Ok(ResponseJson(packs))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyStarterPackRequest {
+    /// Какие продукты набора добавить; количество можно поменять
+    pub items: Vec<StarterPackSelection>,
+}
+
+/// POST /api/v1/fridge/starter-packs/{id}/apply
+pub async fn apply_starter_pack(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<String>,
+    Json(payload): Json<ApplyStarterPackRequest>,
+) -> Result<ResponseJson<Vec<FridgeItemResponse>>, AppError> {
+    let items = StarterPackService::new(pool)
+        .apply(claims.sub, &id, &payload.items, clock.now())
+        .await?;
+    Ok(ResponseJson(items.into_iter().map(|item| FridgeItemResponse::new(item, clock.now())).collect()))
+}
+
+/// Наибольший размер фотографии чека (после декодирования base64)
+const MAX_RECEIPT_IMAGE_BYTES: usize = 4 * 1024 * 1024;
+/// Тело запроса со снимком в base64 больше самого снимка примерно на треть
+const RECEIPT_SCAN_BODY_LIMIT: usize = MAX_RECEIPT_IMAGE_BYTES / 3 * 4 + 64 * 1024;
+const RECEIPT_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp", "image/heic"];
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiptScanRequest {
+    /// Фотография чека в base64 (без префикса `data:`)
+    pub image_base64: String,
+    pub mime_type: String,
+}
+
+/// Черновик продукта из строки чека: `item` после проверки отправляется в POST /fridge как есть
+#[derive(Debug, Serialize)]
+pub struct ReceiptDraftItem {
+    pub raw_text: String,
+    pub item: CreateFridgeItemRequest,
+    /// Строку удалось разобрать без допущений
+    pub confident: bool,
+    pub issues: Vec<ReceiptLineIssue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReceiptScanResponse {
+    pub items: Vec<ReceiptDraftItem>,
+    pub reconciliation: ReceiptReconciliation,
+    pub meta: AiResponseMeta,
+}
+
+impl ReceiptDraftItem {
+    fn new(line: ReceiptItem, purchase_date: DateTime<Utc>) -> Self {
+        let confident = line.is_confident();
+        Self {
+            item: CreateFridgeItemRequest {
+                name: line.name,
+                brand: None,
+                quantity: line.quantity as f32,
+                unit: line.unit.to_string(),
+                category: line.category,
+                price_per_unit: line.price_per_unit.map(|price| price as f32),
+                total_price: line.total_price.map(|price| price as f32),
+                expiry_date: None,
+                purchase_date: Some(purchase_date),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: None,
+                contains_intolerances: None,
+                suitable_for_diets: None,
+                ingredients: None,
+                nutritional_info: None,
+            },
+            raw_text: line.raw_text,
+            confident,
+            issues: line.issues,
+        }
+    }
+}
+
+/// POST /api/v1/fridge/receipt-scan
+/// Черновики продуктов по фотографии чека и сверка с итогом. Ничего не сохраняет:
+/// пользователь правит черновики и добавляет их сам. Доступно за флагом `fridge_photo_scan`.
+pub async fn scan_receipt(
+    Extension(ai_service): Extension<AiService>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(flags): Extension<FeatureFlags>,
+    claims: Claims,
+    Json(payload): Json<ReceiptScanRequest>,
+) -> Result<ResponseJson<ReceiptScanResponse>, AppError> {
+    flags.require(claims.sub, "fridge_photo_scan").await?;
+
+    let mime_type = payload.mime_type.trim().to_lowercase();
+    if !RECEIPT_IMAGE_TYPES.contains(&mime_type.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported image type '{}', expected one of: {}",
+            payload.mime_type,
+            RECEIPT_IMAGE_TYPES.join(", ")
+        )));
+    }
+    let data_base64 = payload.image_base64.trim().to_string();
+    if data_base64.is_empty() {
+        return Err(AppError::BadRequest("Receipt image is empty".to_string()));
+    }
+    if data_base64.len() / 4 * 3 > MAX_RECEIPT_IMAGE_BYTES {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Receipt image is larger than {} MB",
+            MAX_RECEIPT_IMAGE_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let (receipt, meta) = ai_service
+        .read_receipt(&ImageInput { mime_type, data_base64 })
+        .await?;
+    let normalized = normalize_receipt(&receipt);
+    let now = clock.now();
+
+    Ok(ResponseJson(ReceiptScanResponse {
+        items: normalized.items.into_iter().map(|line| ReceiptDraftItem::new(line, now)).collect(),
+        reconciliation: normalized.reconciliation,
+        meta,
+    }))
+}
+
+pub async fn get_dietary_profile(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<DietaryProfile>, AppError> {
+    let dietary_service = DietaryService::new(pool);
+    let profile = dietary_service
+        .get_profile(claims.sub)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Dietary profile not found".to_string()))?;
+
+    Ok(ResponseJson(profile))
+}
+
+/// Обновление профиля; у аллергии без `severity` уровень по умолчанию — Strict
+pub async fn update_dietary_profile(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Json(payload): Json<UpdateDietaryProfile>,
+) -> Result<ResponseJson<DietaryProfile>, AppError> {
+    let dietary_service = DietaryService::new(pool);
+    let profile = dietary_service.update_profile(claims.sub, payload).await?;
+
+    Ok(ResponseJson(profile))
+}
+
+/// Проверка содержимого холодильника на соответствие диетическому профилю
+pub async fn get_compliance_report(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<FridgeComplianceReport>, AppError> {
+    let profile = DietaryService::new(pool.clone())
+        .get_profile(claims.sub)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Dietary profile not found".to_string()))?;
+
+    let fridge_service = FridgeService::new(pool).with_clock(clock.clone());
+    let items = fridge_service.get_user_items(claims.sub, None, None, None).await?;
+
+    Ok(ResponseJson(dietary::build_compliance_report(&profile, &items, clock.now())))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::ACCEPT_LANGUAGE, Method, StatusCode};
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::clock;
+    use crate::test_support::{access_token, claims_for, insert_fridge_item, insert_user, request, send, test_router};
+    use crate::utils::{format::Locale, i18n};
+
+    async fn count(pool: &PgPool, table: &str, item_id: Uuid) -> i64 {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {} WHERE item_id = $1", table))
+            .bind(item_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    fn waste(user_id: Uuid, item_id: Uuid, quantity: f32, brand: Option<&str>) -> CreateFoodWaste {
+        CreateFoodWaste {
+            user_id,
+            original_item_id: Some(item_id),
+            name: "Молоко".to_string(),
+            brand: brand.map(str::to_string),
+            wasted_quantity: quantity,
+            unit: "мл".to_string(),
+            category: FridgeCategory::Dairy,
+            waste_reason: WasteReason::Spoiled,
+            wasted_value: Some(20.0),
+            notes: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn purging_a_referenced_item_detaches_or_drops_every_reference(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let claims = claims_for(&pool, user_id).await;
+        let item = insert_fridge_item(&pool, user_id, "Молоко", Some("на блины"), Utc::now() - Duration::days(3)).await;
+        sqlx::query("UPDATE fridge_items SET quantity = 1000, unit = 'мл', category = 'dairy', brand = 'Простоквашино' WHERE id = $1")
+            .bind(item.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Все, что ссылается на продукт: частичное списание, отходы с брендом и без,
+        // резерв под план, смена категории, разбор заметки и уведомление в очереди
+        FridgeReservationService::new(pool.clone()).consume_item(user_id, item.id, Some(200.0), None, false).await.unwrap();
+        let reservations = FridgeReservationService::new(pool.clone());
+        reservations.add_waste(waste(user_id, item.id, 100.0, None), false).await.unwrap();
+        reservations.add_waste(waste(user_id, item.id, 50.0, Some("Домик в деревне")), false).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO fridge_reservations (user_id, item_id, slot_id, slot_date, ingredient_name, quantity, unit)
+            VALUES ($1, $2, $3, CURRENT_DATE + 1, 'молоко', 300, 'мл')
+            "#
+        )
+        .bind(user_id)
+        .bind(item.id)
+        .bind(Uuid::new_v4())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO fridge_category_changes (user_id, item_id, from_category, to_category) VALUES ($1, $2, 'other', 'dairy')")
+            .bind(user_id)
+            .bind(item.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO fridge_note_links (item_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(item.id)
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let event = serde_json::json!({ "type": "ExpiryWarning", "item_id": item.id });
+        sqlx::query("INSERT INTO queued_notifications (user_id, kind, websocket_event, deliver_at) VALUES ($1, 'expiry', $2, NOW() + INTERVAL '1 hour')")
+            .bind(user_id)
+            .bind(&event)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let fridge_service = FridgeService::new(pool.clone());
+        let entries = fridge_service.get_waste_entries(user_id, None, None).await.unwrap();
+        assert!(entries.iter().all(|entry| entry.source_item_exists));
+
+        // Активный продукт удалить окончательно нельзя — ссылки не трогаются
+        let blocked = purge_history_item(Extension(pool.clone()), Extension(clock::system()), claims.clone(), Path(item.id)).await;
+        assert!(matches!(blocked, Err(AppError::BadRequest(_))));
+        assert_eq!(count(&pool, "fridge_reservations", item.id).await, 1);
+
+        // Чужой продукт не находится
+        let stranger = claims_for(&pool, insert_user(&pool, "Олег").await).await;
+        fridge_service.remove_item(item.id, user_id).await.unwrap();
+        assert!(matches!(purge_history_item(Extension(pool.clone()), Extension(clock::system()), stranger, Path(item.id)).await, Err(AppError::NotFound(_))));
+
+        let ResponseJson(body) = purge_history_item(Extension(pool.clone()), Extension(clock::system()), claims, Path(item.id)).await.unwrap();
+        assert_eq!(body["reservations_released"], 1);
+
+        assert!(matches!(fridge_service.get_item_by_id(item.id, user_id).await, Err(AppError::NotFound(_))));
+        for table in ["fridge_reservations", "fridge_consumptions", "fridge_category_changes", "fridge_note_links"] {
+            assert_eq!(count(&pool, table, item.id).await, 0, "{}", table);
+        }
+
+        // Отходы остались, без ссылки, но с описанием продукта
+        let mut entries = fridge_service.get_waste_entries(user_id, None, None).await.unwrap();
+        entries.sort_by(|a, b| a.waste.wasted_quantity.total_cmp(&b.waste.wasted_quantity));
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| !entry.source_item_exists && entry.waste.original_item_id.is_none()));
+        assert!(entries.iter().all(|entry| entry.waste.name == "Молоко" && entry.waste.category == FridgeCategory::Dairy));
+        let brands: Vec<Option<&str>> = entries.iter().map(|entry| entry.waste.brand.as_deref()).collect();
+        assert_eq!(brands, vec![Some("Домик в деревне"), Some("Простоквашино")]);
+        let serialized = serde_json::to_value(&entries[0]).unwrap();
+        assert_eq!((serialized["source_item_exists"].as_bool(), serialized["original_item_id"].is_null()), (Some(false), true));
+
+        // Уведомление — снимок на момент отправки: остается как есть
+        let (queued,): (serde_json::Value,) = sqlx::query_as("SELECT websocket_event FROM queued_notifications WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(queued, event);
+
+        assert!(matches!(
+            purge_history_item(Extension(pool.clone()), Extension(clock::system()), claims_for(&pool, user_id).await, Path(item.id)).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    fn limits() -> FridgeLimitsConfig {
+        FridgeLimitsConfig {
+            max_kg: 100.0,
+            max_liters: 100.0,
+            max_pieces: 1000.0,
+            max_price: 1_000_000.0,
+            max_expiry_years: 10,
+            purchase_date_tolerance_hours: 24,
+            outlier_multiple: 10.0,
+        }
+    }
+
+    fn limits_now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    fn item_request(quantity: f32, unit: &str) -> CreateFridgeItemRequest {
+        CreateFridgeItemRequest {
+            name: "Молоко".to_string(),
+            brand: None,
+            quantity,
+            unit: unit.to_string(),
+            category: FridgeCategory::Dairy,
+            price_per_unit: None,
+            total_price: None,
+            expiry_date: None,
+            purchase_date: None,
+            notes: None,
+            location: None,
+            store: None,
+            contains_allergens: None,
+            contains_intolerances: None,
+            suitable_for_diets: None,
+            ingredients: None,
+            nutritional_info: None,
+        }
+    }
+
+    /// Коды нарушений по полям; пусто, если значения допустимы
+    fn violations(payload: &CreateFridgeItemRequest) -> Vec<(String, String)> {
+        let errors = match validate_item_limits(payload, &limits(), limits_now()) {
+            Ok(()) => return Vec::new(),
+            Err(AppError::Validation(errors)) => errors,
+            Err(other) => panic!("expected a validation error, got {:?}", other),
+        };
+        let mut found: Vec<(String, String)> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| errors.iter().map(move |error| (field.to_string(), error.code.to_string())))
+            .collect();
+        found.sort();
+        found
+    }
+
+    fn violation(field: &str, code: &str) -> Vec<(String, String)> {
+        vec![(field.to_string(), code.to_string())]
+    }
+
+    #[test]
+    fn quantity_is_bounded_by_its_unit_family() {
+        for (quantity, unit) in [(100.0, "кг"), (100_000.0, "г"), (100.0, "л"), (100_000.0, "мл"), (1000.0, "шт"), (1000.0, "упаковка")] {
+            assert!(violations(&item_request(quantity, unit)).is_empty(), "{} {}", quantity, unit);
+        }
+        for (quantity, unit) in [(100.5, "кг"), (150_000.0, "г"), (101.0, "л"), (100_001.0, "мл"), (1001.0, "шт"), (1001.0, "упаковка")] {
+            assert_eq!(violations(&item_request(quantity, unit)), violation("quantity", "quantity_too_large"), "{} {}", quantity, unit);
+        }
+    }
+
+    #[test]
+    fn grams_are_compared_with_the_limit_in_kilograms() {
+        let Err(AppError::Validation(errors)) = validate_item_limits(&item_request(150_000.0, "г"), &limits(), limits_now()) else {
+            panic!("150 kg must exceed the limit");
+        };
+        let error = &errors.field_errors()["quantity"][0];
+        assert_eq!(error.params["max"], serde_json::json!(100.0));
+        assert_eq!(error.params["max_unit"], serde_json::json!("kg"));
+    }
+
+    #[test]
+    fn quantity_must_be_positive() {
+        for quantity in [0.0, -1.0, f32::NAN] {
+            assert_eq!(violations(&item_request(quantity, "шт")), violation("quantity", "quantity_not_positive"), "{}", quantity);
+        }
+    }
+
+    #[test]
+    fn prices_must_be_non_negative_and_within_the_limit() {
+        let priced = |price_per_unit: Option<f32>, total_price: Option<f32>| CreateFridgeItemRequest {
+            price_per_unit,
+            total_price,
+            ..item_request(1.0, "шт")
+        };
+
+        assert!(violations(&priced(Some(0.0), Some(1_000_000.0))).is_empty());
+        assert_eq!(violations(&priced(Some(-1.0), None)), violation("price_per_unit", "price_negative"));
+        assert_eq!(violations(&priced(None, Some(1_000_001.0))), violation("total_price", "price_too_large"));
+        assert_eq!(violations(&priced(None, Some(f32::INFINITY))), violation("total_price", "price_negative"));
+    }
+
+    #[test]
+    fn expiry_and_purchase_dates_are_bounded_from_now() {
+        let dated = |expiry_date: Option<DateTime<Utc>>, purchase_date: Option<DateTime<Utc>>| CreateFridgeItemRequest {
+            expiry_date,
+            purchase_date,
+            ..item_request(1.0, "шт")
+        };
+        let latest_expiry = limits_now() + Duration::days(365 * 10);
+        let latest_purchase = limits_now() + Duration::hours(24);
+
+        assert!(violations(&dated(Some(latest_expiry), Some(latest_purchase))).is_empty());
+        assert_eq!(violations(&dated(Some(latest_expiry + Duration::seconds(1)), None)), violation("expiry_date", "expiry_too_far"));
+        assert_eq!(
+            violations(&dated(None, Some(latest_purchase + Duration::seconds(1)))),
+            violation("purchase_date", "purchase_date_in_future")
+        );
+        // Просроченный продукт и давняя покупка допустимы
+        assert!(violations(&dated(Some(limits_now() - Duration::days(30)), Some(limits_now() - Duration::days(400)))).is_empty());
+    }
+
+    #[test]
+    fn every_violation_is_reported_at_once() {
+        let payload = CreateFridgeItemRequest {
+            price_per_unit: Some(-5.0),
+            total_price: Some(2_000_000.0),
+            expiry_date: Some(limits_now() + Duration::days(365 * 20)),
+            purchase_date: Some(limits_now() + Duration::days(7)),
+            ..item_request(5000.0, "кг")
+        };
+
+        let codes: Vec<String> = violations(&payload).into_iter().map(|(field, code)| format!("{}: {}", field, code)).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "expiry_date: expiry_too_far",
+                "price_per_unit: price_negative",
+                "purchase_date: purchase_date_in_future",
+                "quantity: quantity_too_large",
+                "total_price: price_too_large",
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn economy_tips_follow_the_users_language(pool: PgPool) {
+        let user_id = insert_user(&pool, "Emma").await;
+        PreferencesService::new(pool.clone())
+            .update(user_id, |preferences| preferences.general.locale = Some("en".to_string()))
+            .await
+            .unwrap();
+        let token = access_token(&pool, user_id).await;
+
+        // Настройка пользователя важнее заголовка браузера
+        let mut insights = request(Method::GET, "/api/v1/fridge/analytics/insights", Some(&token), None);
+        insights.headers_mut().insert(ACCEPT_LANGUAGE, "ru-RU,ru;q=0.9".parse().unwrap());
+        let (status, body) = send(&test_router(&pool), insights).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let expected: Vec<&str> = ["fridge.tip.great_job", "fridge.tip.check_expiry", "fridge.tip.plan_menu"]
+            .into_iter()
+            .map(|key| i18n::t(Locale::En, key))
+            .collect();
+        assert_eq!(body["tips"], serde_json::json!(expected));
+        assert_ne!(i18n::t(Locale::En, "fridge.tip.plan_menu"), i18n::t(Locale::Ru, "fridge.tip.plan_menu"));
+    }
+
+    #[sqlx::test]
+    async fn consuming_more_than_is_left_is_a_400_and_notes_go_to_the_journal(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+        let item = insert_fridge_item(&pool, user_id, "Яйца", None, chrono::Utc::now()).await;
+        sqlx::query("UPDATE fridge_items SET quantity = 10, total_price = 150 WHERE id = $1").bind(item.id).execute(&pool).await.unwrap();
+        let uri = format!("/api/v1/fridge/{}/consume", item.id);
+
+        let (status, body) = send(&router, request(Method::POST, &uri, Some(&token), Some(serde_json::json!({ "quantity": 12.0 })))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert!(body.to_string().contains("only 10 pcs left"), "{}", body);
+        assert_eq!(count(&pool, "fridge_consumptions", item.id).await, 0);
+
+        let note = serde_json::json!({ "quantity": 3.0, "note": "  на омлет  " });
+        let (status, body) = send(&router, request(Method::POST, &uri, Some(&token), Some(note))).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["quantity"], 7.0);
+        let (status, _) = send(&router, request(Method::POST, &uri, Some(&token), Some(serde_json::json!({ "quantity": 7.0, "note": "" })))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let journal: Vec<(f32, f32, Option<String>)> = sqlx::query_as(
+            "SELECT quantity, consumed_value, note FROM fridge_consumptions WHERE item_id = $1 ORDER BY quantity"
+        )
+        .bind(item.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(journal, vec![(3.0, 45.0, Some("на омлет".to_string())), (7.0, 105.0, None)]);
+
+        // Продукт закончился: следующее списание — тоже 400, журнал не меняется
+        let (status, _) = send(&router, request(Method::POST, &uri, Some(&token), Some(serde_json::json!({})))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(count(&pool, "fridge_consumptions", item.id).await, 2);
+    }
 }
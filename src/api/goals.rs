@@ -2,6 +2,7 @@ use axum::{
     extract::{Extension, Json, Path, Query},
     response::Json as ResponseJson,
     routing::{get, post, put, delete},
+    middleware::from_fn,
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -10,32 +11,66 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc, NaiveDate};
 
 use crate::{
+    middleware::idempotency_middleware,
     db::DbPool,
-    models::goal::{Goal, CreateGoal, GoalType, GoalStatus, WeightEntry, Achievement},
-    services::{auth::Claims, goal::GoalService, health::HealthService},
-    utils::errors::AppError,
+    models::bulk::BulkItemFailure,
+    models::goal::{Goal, CreateGoal, GoalType, GoalStatus, GoalShareCard, GoalSuggestion, GoalSuggestionKind},
+    services::{
+        auth::Claims,
+        clock::SharedClock,
+        goal::GoalService,
+        goal_share::GoalShareService,
+        goal_suggestions::{suggest, GoalSuggestionService},
+        health::HealthService,
+        preferences::UserLocale,
+    },
+    utils::{errors::AppError, format::Locale},
 };
 
 pub fn routes() -> Router {
     Router::new()
-        .route("/", post(create_goal))
+        .route("/", post(create_goal).layer(from_fn(idempotency_middleware)))
         .route("/", get(get_goals))
-        .route("/{id}", get(get_goal))
-        .route("/{id}", put(update_goal))
-        .route("/{id}", delete(delete_goal))
-        .route("/{id}/progress", post(update_progress))
+        .route("/:id", get(get_goal))
+        .route("/:id", put(update_goal))
+        .route("/:id", delete(delete_goal))
+        .route("/:id/progress", post(update_progress))
         .route("/weight", post(add_weight_entry))
         .route("/weight", get(get_weight_history))
         .route("/bmr", get(calculate_bmr))
         .route("/tdee", get(calculate_tdee))
         .route("/achievements", get(get_achievements))
         .route("/stats", get(get_health_stats))
+        .route("/archive", post(archive_goals))
+        .route("/suggestions", get(get_goal_suggestions))
+        .route("/suggestions/:kind/accept", post(accept_goal_suggestion))
+        .route("/:id/share-card/token", post(create_share_token).delete(revoke_share_token))
+}
+
+/// Карточка цели открывается и без входа — по публичному токену
+pub fn public_routes() -> Router {
+    Router::new()
+        .route("/:id/share-card", get(get_share_card))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareCardQueryParams {
+    pub public_token: Option<String>,
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareTokenResponse {
+    pub public_token: String,
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateGoalRequest {
     #[validate(length(min = 1, max = 200))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub title: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub description: Option<String>,
     pub goal_type: GoalType,
     pub target_value: f32,
@@ -44,11 +79,19 @@ pub struct CreateGoalRequest {
     pub target_date: Option<NaiveDate>,
     pub daily_target: Option<f32>,
     pub weekly_target: Option<f32>,
+    pub reminders: Option<GoalReminderSettings>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GoalReminderSettings {
+    /// Например `[7, 3, 1]` — напоминания за неделю, за три дня и накануне срока
+    pub days_before_deadline: Vec<i32>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdateProgressRequest {
     pub value: f32,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
 }
 
@@ -56,6 +99,7 @@ pub struct UpdateProgressRequest {
 pub struct WeightEntryRequest {
     pub weight: f32,
     pub date: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
 }
 
@@ -63,6 +107,9 @@ pub struct WeightEntryRequest {
 pub struct GoalQueryParams {
     pub goal_type: Option<GoalType>,
     pub status: Option<GoalStatus>,
+    /// Показать и цели из архива
+    #[serde(default)]
+    pub include_archived: bool,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
@@ -90,30 +137,18 @@ pub struct GoalResponse {
     pub progress_percentage: f32,
     pub days_remaining: Option<i32>,
     pub is_on_track: bool,
+    pub reminder_days: Vec<i32>,
+    pub archived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-impl From<Goal> for GoalResponse {
-    fn from(goal: Goal) -> Self {
-        let progress_percentage = if goal.target_value > 0.0 {
-            (goal.current_value / goal.target_value * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-
-        let days_remaining = goal.target_date.map(|target| {
-            let today = chrono::Utc::now().date_naive();
-            (target - today).num_days() as i32
-        });
-
-        let is_on_track = match (goal.target_date, days_remaining) {
-            (Some(_), Some(days)) if days > 0 => {
-                let expected_progress = 100.0 - (days as f32 / 30.0 * 100.0); // Simplified calculation
-                progress_percentage >= expected_progress
-            }
-            _ => progress_percentage >= 50.0, // Default threshold
-        };
+impl GoalResponse {
+    /// Оставшиеся дни и "в графике" считаются на дату `today`
+    pub fn new(goal: Goal, today: NaiveDate) -> Self {
+        let progress_percentage = goal.progress_percentage();
+        let days_remaining = goal.days_remaining(today);
+        let is_on_track = goal.is_on_track(today);
 
         Self {
             id: goal.id,
@@ -130,12 +165,36 @@ impl From<Goal> for GoalResponse {
             progress_percentage,
             days_remaining,
             is_on_track,
+            reminder_days: goal.reminder_days,
+            archived_at: goal.archived_at,
             created_at: goal.created_at,
             updated_at: goal.updated_at,
         }
     }
 }
 
+/// Архивирование завершенных и брошенных целей, измененных раньше `before` (UTC)
+#[derive(Debug, Deserialize)]
+pub struct ArchiveGoalsRequest {
+    pub before: NaiveDate,
+    /// Только эти цели; без списка — все подходящие
+    pub ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveGoalsResponse {
+    pub archived: Vec<Uuid>,
+    pub failures: Vec<BulkItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoalSuggestionsResponse {
+    /// Есть неделя записей в дневнике или заполнены вес, рост и активность
+    pub eligible: bool,
+    pub days_logged: i64,
+    pub suggestions: Vec<GoalSuggestion>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct WeightEntryResponse {
     pub id: Uuid,
@@ -172,6 +231,7 @@ pub struct AchievementResponse {
 
 pub async fn create_goal(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<CreateGoalRequest>,
 ) -> Result<ResponseJson<GoalResponse>, AppError> {
@@ -189,63 +249,92 @@ pub async fn create_goal(
         daily_target: payload.daily_target,
         weekly_target: payload.weekly_target,
         status: GoalStatus::Active,
+        reminder_days: reminder_days(payload.reminders.as_ref())?,
     };
 
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
     let goal = goal_service.create_goal(create_goal).await?;
 
-    Ok(ResponseJson(goal.into()))
+    Ok(ResponseJson(GoalResponse::new(goal, clock.now().date_naive())))
+}
+
+/// Не больше 10 напоминаний, каждое — от 1 до 365 дней до срока; повторы убираются
+pub fn reminder_days(settings: Option<&GoalReminderSettings>) -> Result<Vec<i32>, AppError> {
+    let Some(settings) = settings else {
+        return Ok(Vec::new());
+    };
+
+    if settings.days_before_deadline.len() > 10 {
+        return Err(AppError::BadRequest("At most 10 reminders per goal are allowed".to_string()));
+    }
+    if let Some(days) = settings.days_before_deadline.iter().find(|days| !(1..=365).contains(*days)) {
+        return Err(AppError::BadRequest(format!(
+            "Reminder days must be between 1 and 365, got {}",
+            days
+        )));
+    }
+
+    let mut days = settings.days_before_deadline.clone();
+    days.sort_unstable_by(|a, b| b.cmp(a));
+    days.dedup();
+    Ok(days)
 }
 
 pub async fn get_goals(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<GoalQueryParams>,
 ) -> Result<ResponseJson<Vec<GoalResponse>>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
     let goals = goal_service.get_user_goals(
         claims.sub,
         params.goal_type,
         params.status,
+        params.include_archived,
         params.limit.unwrap_or(50),
         params.offset.unwrap_or(0),
     ).await?;
 
-    let response: Vec<GoalResponse> = goals.into_iter().map(Into::into).collect();
+    let today = clock.now().date_naive();
+    let response: Vec<GoalResponse> = goals.into_iter().map(|goal| GoalResponse::new(goal, today)).collect();
     Ok(ResponseJson(response))
 }
 
 pub async fn get_goal(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<GoalResponse>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
     let goal = goal_service.get_goal_by_id(id, claims.sub).await?;
 
-    Ok(ResponseJson(goal.into()))
+    Ok(ResponseJson(GoalResponse::new(goal, clock.now().date_naive())))
 }
 
 pub async fn update_goal(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateGoalRequest>,
 ) -> Result<ResponseJson<GoalResponse>, AppError> {
     payload.validate()?;
 
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
     let goal = goal_service.update_goal(id, claims.sub, payload).await?;
 
-    Ok(ResponseJson(goal.into()))
+    Ok(ResponseJson(GoalResponse::new(goal, clock.now().date_naive())))
 }
 
 pub async fn delete_goal(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock);
     goal_service.delete_goal(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Goal deleted successfully"})))
@@ -253,28 +342,65 @@ pub async fn delete_goal(
 
 pub async fn update_progress(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateProgressRequest>,
 ) -> Result<ResponseJson<GoalResponse>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock.clone());
     let goal = goal_service.update_progress(id, claims.sub, payload.value, payload.notes).await?;
 
-    Ok(ResponseJson(goal.into()))
+    Ok(ResponseJson(GoalResponse::new(goal, clock.now().date_naive())))
+}
+
+/// Данные для карточки «поделиться целью»: владельцу — всегда, остальным — по `public_token`
+pub async fn get_share_card(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Option<Claims>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ShareCardQueryParams>,
+) -> Result<ResponseJson<GoalShareCard>, AppError> {
+    let card = GoalShareService::new(pool)
+        .with_clock(clock)
+        .share_card(id, claims.map(|claims| claims.sub), params.public_token.as_deref(), params.locale)
+        .await?;
+
+    Ok(ResponseJson(card))
+}
+
+pub async fn create_share_token(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<ShareTokenResponse>, AppError> {
+    let public_token = GoalShareService::new(pool).create_token(id, claims.sub).await?;
+    Ok(ResponseJson(ShareTokenResponse { public_token }))
+}
+
+pub async fn revoke_share_token(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    GoalShareService::new(pool).revoke_token(id, claims.sub).await?;
+    Ok(ResponseJson(serde_json::json!({"message": "Share link revoked"})))
 }
 
 pub async fn add_weight_entry(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<WeightEntryRequest>,
 ) -> Result<ResponseJson<WeightEntryResponse>, AppError> {
-    let goal_service = GoalService::new(pool.clone());
-    let health_service = HealthService::new(pool);
+    let today = clock.now().date_naive();
+    let goal_service = GoalService::new(pool.clone()).with_clock(clock.clone());
+    let health_service = HealthService::new(pool).with_clock(clock);
     
     let entry = goal_service.add_weight_entry(
         claims.sub,
         payload.weight,
-        payload.date.unwrap_or_else(|| chrono::Utc::now().date_naive()),
+        payload.date.unwrap_or(today),
         payload.notes,
     ).await?;
 
@@ -299,10 +425,11 @@ pub async fn add_weight_entry(
 
 pub async fn get_weight_history(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<WeightQueryParams>,
 ) -> Result<ResponseJson<Vec<WeightEntryResponse>>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock);
     let entries = goal_service.get_weight_history(
         claims.sub,
         params.start_date,
@@ -327,9 +454,10 @@ pub async fn get_weight_history(
 
 pub async fn calculate_bmr(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let health_service = HealthService::new(pool);
+    let health_service = HealthService::new(pool).with_clock(clock);
     let bmr = health_service.calculate_bmr(claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({
@@ -340,9 +468,10 @@ pub async fn calculate_bmr(
 
 pub async fn calculate_tdee(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let health_service = HealthService::new(pool);
+    let health_service = HealthService::new(pool).with_clock(clock);
     let tdee = health_service.calculate_tdee(claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({
@@ -351,11 +480,59 @@ pub async fn calculate_tdee(
     })))
 }
 
+pub async fn archive_goals(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<ArchiveGoalsRequest>,
+) -> Result<ResponseJson<ArchiveGoalsResponse>, AppError> {
+    let before = payload.before.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let outcome = GoalService::new(pool).with_clock(clock.clone())
+        .archive(claims.sub, before, payload.ids.as_deref(), clock.now())
+        .await?;
+
+    Ok(ResponseJson(ArchiveGoalsResponse { archived: outcome.archived, failures: outcome.failures }))
+}
+
+/// Цели, которые стоит поставить, с расчетом по данным пользователя.
+/// Цель, которая уже есть (активная или на паузе), не предлагается.
+pub async fn get_goal_suggestions(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    UserLocale(locale): UserLocale,
+) -> Result<ResponseJson<GoalSuggestionsResponse>, AppError> {
+    let inputs = GoalSuggestionService::new(pool).with_clock(clock).inputs(claims.sub).await?;
+
+    Ok(ResponseJson(GoalSuggestionsResponse {
+        eligible: inputs.eligible(),
+        days_logged: inputs.days_logged,
+        suggestions: suggest(&inputs, locale),
+    }))
+}
+
+/// Создает цель из предложения; значение пересчитывается по текущим данным
+pub async fn accept_goal_suggestion(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    UserLocale(locale): UserLocale,
+    Path(kind): Path<GoalSuggestionKind>,
+) -> Result<ResponseJson<GoalResponse>, AppError> {
+    let goal = GoalSuggestionService::new(pool)
+        .with_clock(clock.clone())
+        .accept(claims.sub, kind, locale)
+        .await?;
+
+    Ok(ResponseJson(GoalResponse::new(goal, clock.now().date_naive())))
+}
+
 pub async fn get_achievements(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<Vec<AchievementResponse>>, AppError> {
-    let goal_service = GoalService::new(pool);
+    let goal_service = GoalService::new(pool).with_clock(clock);
     let achievements = goal_service.get_user_achievements(claims.sub).await?;
 
     let response: Vec<AchievementResponse> = achievements.into_iter().map(|achievement| {
@@ -374,9 +551,10 @@ pub async fn get_achievements(
 
 pub async fn get_health_stats(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<HealthStatsResponse>, AppError> {
-    let health_service = HealthService::new(pool);
+    let health_service = HealthService::new(pool).with_clock(clock);
     let stats = health_service.get_comprehensive_stats(claims.sub).await?;
 
     Ok(ResponseJson(stats))
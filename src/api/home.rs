@@ -0,0 +1,67 @@
+use axum::{
+    extract::Extension,
+    http::header,
+    response::{IntoResponse, Json as ResponseJson},
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+use chrono::NaiveDate;
+use tracing::warn;
+
+use crate::{
+    db::DbPool,
+    services::{auth::Claims, clock::SharedClock, home::HomeService},
+    utils::errors::AppError,
+};
+
+/// Бюджет размера ответа для главного экрана
+const RESPONSE_SIZE_BUDGET: usize = 2048;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/summary", get(get_home_summary))
+}
+
+/// Сводка для главного экрана: только счетчики.
+/// `null` в поле означает, что соответствующий подзапрос не удался или не успел.
+#[derive(Debug, Serialize)]
+pub struct HomeSummaryResponse {
+    pub fridge_items: Option<usize>,
+    pub expiring_soon: Option<usize>,
+    pub calories_today: Option<CaloriesSummary>,
+    pub active_goals: Option<ActiveGoalsSummary>,
+    pub unread_notifications: Option<i64>,
+    pub diary_streak_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaloriesSummary {
+    pub consumed: i32,
+    pub goal: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveGoalsSummary {
+    pub count: usize,
+    pub closest_deadline: Option<NaiveDate>,
+}
+
+/// Заменяет шесть запросов мобильного приложения при запуске одним
+pub async fn get_home_summary(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let home_service = HomeService::new(pool).with_clock(clock);
+    let summary = home_service.get_summary(claims.sub).await;
+
+    let size = serde_json::to_vec(&summary)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to serialize home summary: {}", e)))?
+        .len();
+    if size > RESPONSE_SIZE_BUDGET {
+        warn!("Home summary is {} bytes, budget is {} bytes", size, RESPONSE_SIZE_BUDGET);
+    }
+
+    Ok(([(header::CACHE_CONTROL, "private, max-age=60")], ResponseJson(summary)))
+}
@@ -0,0 +1,65 @@
+use axum::{
+    response::Json as ResponseJson,
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+
+use chrono::NaiveDate;
+
+use crate::{api::versioning::ApiVersion, utils::errors::ErrorCode};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/error-codes", get(get_error_codes))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorCodeDescription {
+    pub code: &'static str,
+    pub description: &'static str,
+}
+
+/// Все коды ошибок (`error.code` в ответах) — по этому списку фронтенд генерирует константы
+pub async fn get_error_codes() -> ResponseJson<Vec<ErrorCodeDescription>> {
+    ResponseJson(
+        ErrorCode::ALL
+            .iter()
+            .map(|code| ErrorCodeDescription {
+                code: code.as_str(),
+                description: code.description(),
+            })
+            .collect(),
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiVersionInfo {
+    pub version: ApiVersion,
+    pub prefix: String,
+    pub deprecated: bool,
+    pub deprecated_since: Option<NaiveDate>,
+    /// После этой даты версия перестанет отвечать
+    pub sunset: Option<NaiveDate>,
+    pub successor: Option<ApiVersion>,
+}
+
+/// Поддерживаемые версии API и даты их отключения (GET /api/meta/versions — вне версий)
+pub async fn get_versions() -> ResponseJson<Vec<ApiVersionInfo>> {
+    ResponseJson(
+        ApiVersion::ALL
+            .iter()
+            .map(|version| {
+                let deprecation = version.deprecation();
+                ApiVersionInfo {
+                    version: *version,
+                    prefix: version.prefix(),
+                    deprecated: deprecation.is_some(),
+                    deprecated_since: deprecation.map(|deprecation| deprecation.deprecated_since),
+                    sunset: deprecation.map(|deprecation| deprecation.sunset),
+                    successor: deprecation.map(|deprecation| deprecation.successor),
+                }
+            })
+            .collect(),
+    )
+}
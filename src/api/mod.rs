@@ -7,3 +7,13 @@ pub mod community;
 pub mod websocket;
 pub mod ai;
 pub mod personal_health;
+pub mod coaching;
+pub mod admin;
+pub mod features;
+pub mod home;
+pub mod reports;
+pub mod notifications;
+pub mod meta;
+pub mod preferences;
+pub mod versioning;
+pub mod timeline;
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Extension, Json},
+    response::Json as ResponseJson,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::FridgeCategory,
+        notification::{NotificationChannels, NotificationPreferences, QuietHours, MAX_EXPIRY_LEAD_DAYS},
+    },
+    services::{auth::Claims, clock::SharedClock, preferences::PreferencesService},
+    utils::errors::AppError,
+};
+
+/// Допустимые смещения часовых поясов: от UTC-12 до UTC+14
+pub const MIN_UTC_OFFSET_MINUTES: i32 = -12 * 60;
+pub const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/preferences", get(get_notification_preferences).put(update_notification_preferences))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationPreferencesRequest {
+    /// Категория → дни до истечения срока (0–14) или `null`, чтобы не уведомлять
+    #[serde(default)]
+    pub expiry_lead_days: HashMap<String, Option<i64>>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub channels: NotificationChannels,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub muted_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub new_device_email: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationPreferencesResponse {
+    #[serde(flatten)]
+    pub preferences: NotificationPreferences,
+    /// Порог для каждой категории с учетом значения по умолчанию
+    pub effective_expiry_lead_days: Vec<CategoryLeadDays>,
+    pub muted_now: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryLeadDays {
+    pub category: FridgeCategory,
+    pub lead_days: Option<u8>,
+}
+
+impl NotificationPreferencesResponse {
+    /// `muted_now` — на момент `now` по часам приложения
+    pub fn new(preferences: NotificationPreferences, now: DateTime<Utc>) -> Self {
+        Self {
+            effective_expiry_lead_days: FridgeCategory::ALL
+                .iter()
+                .map(|category| CategoryLeadDays {
+                    category: category.clone(),
+                    lead_days: preferences.expiry_lead_days(category),
+                })
+                .collect(),
+            muted_now: preferences.is_muted(now),
+            preferences,
+        }
+    }
+}
+
+pub async fn get_notification_preferences(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<NotificationPreferencesResponse>, AppError> {
+    let preferences = PreferencesService::new(pool).get_notifications(claims.sub).await?;
+    Ok(ResponseJson(NotificationPreferencesResponse::new(preferences, clock.now())))
+}
+
+pub async fn update_notification_preferences(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<NotificationPreferencesRequest>,
+) -> Result<ResponseJson<NotificationPreferencesResponse>, AppError> {
+    let now = clock.now();
+    let preferences = validate_preferences(payload, now)?;
+
+    let saved = PreferencesService::new(pool)
+        .with_clock(clock)
+        .set_notifications(claims.sub, preferences)
+        .await?;
+    Ok(ResponseJson(NotificationPreferencesResponse::new(saved, now)))
+}
+
+/// Проверяет запрос и приводит его к сохраняемым настройкам
+pub fn validate_preferences(payload: NotificationPreferencesRequest, now: DateTime<Utc>) -> Result<NotificationPreferences, AppError> {
+    let mut expiry_lead_days = HashMap::with_capacity(payload.expiry_lead_days.len());
+    for (name, lead_days) in payload.expiry_lead_days {
+        let category = FridgeCategory::parse(&name)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown category in expiry_lead_days: {}", name)))?;
+
+        let lead_days = match lead_days {
+            Some(days) if (0..=MAX_EXPIRY_LEAD_DAYS as i64).contains(&days) => Some(days as u8),
+            Some(_) => {
+                return Err(AppError::BadRequest(format!(
+                    "expiry_lead_days for {} must be between 0 and {} days",
+                    name, MAX_EXPIRY_LEAD_DAYS
+                )));
+            }
+            None => None,
+        };
+
+        if expiry_lead_days.insert(category, lead_days).is_some() {
+            return Err(AppError::BadRequest(format!("Duplicate category in expiry_lead_days: {}", name)));
+        }
+    }
+
+    if let Some(quiet_hours) = &payload.quiet_hours {
+        if quiet_hours.start == quiet_hours.end {
+            return Err(AppError::BadRequest("Quiet hours start and end must differ".to_string()));
+        }
+        if !(MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&quiet_hours.utc_offset_minutes) {
+            return Err(AppError::BadRequest("utc_offset_minutes must be between -720 and 840".to_string()));
+        }
+    }
+
+    if payload.muted && payload.muted_until.is_some_and(|until| until <= now) {
+        return Err(AppError::BadRequest("muted_until must be in the future".to_string()));
+    }
+
+    Ok(NotificationPreferences {
+        expiry_lead_days,
+        quiet_hours: payload.quiet_hours,
+        channels: payload.channels,
+        muted: payload.muted,
+        muted_until: payload.muted_until,
+        new_device_email: payload.new_device_email,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use chrono::{Duration, NaiveTime};
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    fn payload(value: serde_json::Value) -> NotificationPreferencesRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn rejection(value: serde_json::Value) -> String {
+        match validate_preferences(payload(value), Utc::now()) {
+            Err(AppError::BadRequest(message)) => message,
+            other => panic!("expected BadRequest, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn thresholds_and_quiet_window_are_validated() {
+        let preferences = validate_preferences(
+            payload(json!({ "expiry_lead_days": { "meat": 2, "Grains": null, "dairy": 0, "fish": 14 } })),
+            Utc::now(),
+        )
+        .unwrap();
+        assert_eq!(preferences.expiry_lead_days(&FridgeCategory::Meat), Some(2));
+        assert_eq!(preferences.expiry_lead_days(&FridgeCategory::Grains), None);
+        assert_eq!(preferences.expiry_lead_days(&FridgeCategory::Dairy), Some(0));
+        assert_eq!(preferences.expiry_lead_days(&FridgeCategory::Fruits), Some(3));
+
+        assert!(rejection(json!({ "expiry_lead_days": { "meat": 15 } })).contains("between 0 and 14"));
+        assert!(rejection(json!({ "expiry_lead_days": { "meat": -1 } })).contains("between 0 and 14"));
+        assert!(rejection(json!({ "expiry_lead_days": { "cake": 1 } })).contains("Unknown category"));
+        assert!(rejection(json!({ "quiet_hours": { "start": "22:00:00", "end": "22:00:00" } })).contains("must differ"));
+        assert!(rejection(json!({ "quiet_hours": { "start": "22:00:00", "end": "08:00:00", "utc_offset_minutes": 900 } })).contains("utc_offset_minutes"));
+        let past = Utc::now() - Duration::hours(1);
+        assert!(rejection(json!({ "muted": true, "muted_until": past })).contains("future"));
+
+        let overnight = validate_preferences(payload(json!({ "quiet_hours": { "start": "22:00:00", "end": "08:00:00" } })), Utc::now()).unwrap();
+        assert_eq!(overnight.quiet_hours.unwrap().end, NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+    }
+
+    #[sqlx::test]
+    async fn preferences_round_trip_through_the_api(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user).await;
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/notifications/preferences", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["channels"]["expiring_items"], "both");
+        assert!(body["effective_expiry_lead_days"].as_array().unwrap().iter().all(|entry| entry["lead_days"] == 3));
+
+        let update = json!({
+            "expiry_lead_days": { "meat": 2, "grains": null },
+            "quiet_hours": { "start": "22:00:00", "end": "08:00:00", "utc_offset_minutes": 180 },
+            "channels": { "expiring_items": "websocket", "goal_reminders": "off" }
+        });
+        let (status, _) = send(&router, request(Method::PUT, "/api/v1/notifications/preferences", Some(&token), Some(update))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/notifications/preferences", Some(&token), None)).await;
+        let lead_days = |category: &str| {
+            body["effective_expiry_lead_days"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|entry| entry["category"] == category)
+                .unwrap()["lead_days"]
+                .clone()
+        };
+        assert_eq!((lead_days("Meat"), lead_days("Grains"), lead_days("Dairy")), (json!(2), json!(null), json!(3)));
+        assert_eq!(body["quiet_hours"]["utc_offset_minutes"], 180);
+        assert_eq!((body["channels"]["expiring_items"].as_str(), body["channels"]["goal_reminders"].as_str()), (Some("websocket"), Some("off")));
+
+        let (status, _) = send(&router, request(Method::PUT, "/api/v1/notifications/preferences", Some(&token), Some(json!({ "expiry_lead_days": { "meat": 30 } })))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
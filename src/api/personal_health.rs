@@ -1,15 +1,49 @@
 use axum::{
-    extract::{State, Json, Path},
+    extract::{FromRequestParts, State, Json, Query},
+    http::request::Parts,
+    Extension,
     response::Json as ResponseJson,
+    routing::{get, post},
+    Router,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 
-use crate::services::personal_health_assistant::{PersonalHealthAssistant, HealthContext, UserHealthSummary, NutritionSummary, PersonalizedResponse};
-use crate::services::ai::AiService;
+use crate::services::personal_health_assistant::{PersonalHealthAssistant, HealthTone, HealthContext, UserHealthSummary, NutritionSummary, PersonalizedResponse};
+use crate::services::ai::{AiService, AiResponseMeta};
+use crate::services::mock_ai::MockScenario;
+use crate::services::ai_history::AiHistoryService;
+use crate::services::auth::Claims;
+use crate::services::clock::SharedClock;
+use crate::services::experiments::{Experiments, HEALTH_TONE_EXPERIMENT};
+use crate::services::diet_quality::{diet_mood_insight, DietQualitySeries, DietQualityService, MAX_DIET_QUALITY_DAYS};
+use crate::services::wellbeing::WellbeingService;
+use crate::services::fasting::FastingService;
+use crate::services::preferences::{PreferencesService, UserLocale};
+use crate::api::notifications::{MAX_UTC_OFFSET_MINUTES, MIN_UTC_OFFSET_MINUTES};
+use crate::models::fridge::CheckinDay;
+use crate::models::ai_response::AiResponseKind;
+use crate::db::{DbPool, DbPools, ReadConsistency};
 use crate::models::health::*;
 use crate::utils::errors::AppError;
+use crate::utils::format::Locale;
+use crate::utils::i18n;
+use crate::app::AppState;
+
+pub fn routes(state: &AppState) -> Router {
+    Router::new()
+        .route("/chat", post(personal_health_chat))
+        .route("/wellbeing", post(daily_wellbeing_check))
+        .route("/dashboard", get(health_dashboard))
+        .route("/recommendations", get(get_recommendations))
+        .route("/mood-analysis", post(mood_analysis))
+        .route("/diet-quality", get(get_diet_quality))
+        .route("/mood-insights", get(get_mood_insights))
+        .route("/fasting", get(get_fasting_status))
+        .route("/fasting/window", get(get_eating_window).put(update_eating_window).delete(delete_eating_window))
+        .with_state(state.ai_service.clone())
+}
 
 #[derive(Debug, Deserialize)]
 pub struct PersonalChatRequest {
@@ -26,17 +60,38 @@ pub struct WellbeingCheckRequest {
     pub sleep_quality: Option<i32>,
     pub water_intake_ml: Option<i32>,
     pub exercise_minutes: Option<i32>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized_vec")]
     pub symptoms: Vec<String>,
 }
 
+/// Окно питания в местном времени; может переходить через полночь (18:00–02:00)
+#[derive(Debug, Deserialize)]
+pub struct EatingWindowRequest {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Дни, в которые окно действует; пусто — каждый день
+    #[serde(default)]
+    pub weekdays: Vec<CheckinDay>,
+    #[serde(default)]
+    pub notify_outside: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct HealthDashboardResponse {
     pub current_wellbeing: Option<DailyWellbeing>,
     pub insights: Vec<HealthInsight>,
     pub recommendations: Vec<PersonalizedRecommendation>,
     pub weekly_trends: WeeklyTrends,
+    /// Оценка питания за сегодня; нет, если в дневнике сегодня пусто
+    pub diet_quality: Option<DietQualityDay>,
+    pub fasting: FastingStatus,
     pub motivational_message: String,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,17 +104,62 @@ pub struct WeeklyTrends {
     pub total_exercise_minutes: i32,
 }
 
+/// Все, из чего собирается помощник для диалоговых эндпоинтов: пользователь, язык,
+/// часы запроса, тон из эксперимента и сценарий Mock-провайдера
+pub struct AssistantRequest {
+    pub claims: Claims,
+    pub clock: SharedClock,
+    locale: Locale,
+    experiments: Experiments,
+    mock_scenario: MockScenario,
+}
+
+impl AssistantRequest {
+    async fn assistant(&self, ai_service: AiService) -> Result<PersonalHealthAssistant, AppError> {
+        Ok(PersonalHealthAssistant::new(ai_service.with_mock_scenario(self.mock_scenario.clone()).for_user(self.claims.sub))
+            .with_tone(health_tone(&self.experiments, self.claims.sub).await?)
+            .with_locale(self.locale)
+            .with_clock(self.clock.clone()))
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for AssistantRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let extension_missing = |e: axum::extract::rejection::ExtensionRejection| AppError::InternalServerError(e.to_string());
+
+        let Extension(clock) = Extension::<SharedClock>::from_request_parts(parts, state).await.map_err(extension_missing)?;
+        let Extension(experiments) = Extension::<Experiments>::from_request_parts(parts, state).await.map_err(extension_missing)?;
+
+        Ok(Self {
+            claims: Claims::from_request_parts(parts, state).await?,
+            clock,
+            locale: UserLocale::from_request_parts(parts, state).await?.0,
+            experiments,
+            mock_scenario: MockScenario::from_request_parts(parts, state).await?,
+        })
+    }
+}
+
 /// Персонализированный чат с заботливым ИИ-помощником
 pub async fn personal_health_chat(
     State(ai_service): State<AiService>,
-    Json(request): Json<PersonalChatRequest>,
+    Extension(pool): Extension<DbPool>,
+    request: AssistantRequest,
+    Json(chat): Json<PersonalChatRequest>,
 ) -> Result<ResponseJson<PersonalizedResponse>, AppError> {
-    let assistant = PersonalHealthAssistant::new(ai_service);
+    let assistant = request.assistant(ai_service).await?;
     
     // В реальном приложении здесь бы загружались данные пользователя из БД
-    let health_context = create_mock_health_context();
+    let health_context = create_mock_health_context(request.clock.now());
     
-    let response = assistant.get_personalized_response(&request.message, &health_context).await?;
+    let mut response = assistant.get_personalized_response(&chat.message, &health_context).await?;
+    response.response_id = record_response(pool, request.claims.sub, &response).await;
     
     Ok(ResponseJson(response))
 }
@@ -67,34 +167,38 @@ pub async fn personal_health_chat(
 /// Ежедневная проверка самочувствия
 pub async fn daily_wellbeing_check(
     State(ai_service): State<AiService>,
-    Json(request): Json<WellbeingCheckRequest>,
+    Extension(pool): Extension<DbPool>,
+    request: AssistantRequest,
+    Json(check): Json<WellbeingCheckRequest>,
 ) -> Result<ResponseJson<PersonalizedResponse>, AppError> {
-    let assistant = PersonalHealthAssistant::new(ai_service);
+    let now = request.clock.now();
+    let assistant = request.assistant(ai_service).await?;
     
     // Создаем запись о самочувствии
     let wellbeing = DailyWellbeing {
         id: Uuid::new_v4(),
-        user_id: Uuid::new_v4(), // В реальном приложении - ID из токена
-        date: Utc::now(),
-        mood_score: request.mood_score,
-        energy_level: request.energy_level,
-        stress_level: request.stress_level,
-        sleep_hours: request.sleep_hours,
-        sleep_quality: request.sleep_quality,
-        water_intake_ml: request.water_intake_ml,
-        exercise_minutes: request.exercise_minutes,
-        notes: request.notes,
-        symptoms: request.symptoms,
-        created_at: Utc::now(),
+        user_id: request.claims.sub,
+        date: now,
+        mood_score: check.mood_score,
+        energy_level: check.energy_level,
+        stress_level: check.stress_level,
+        sleep_hours: check.sleep_hours,
+        sleep_quality: check.sleep_quality,
+        water_intake_ml: check.water_intake_ml,
+        exercise_minutes: check.exercise_minutes,
+        notes: check.notes,
+        symptoms: check.symptoms,
+        created_at: now,
     };
     
-    // В реальном приложении здесь сохранялось бы в БД
+    WellbeingService::new(pool.clone()).record(&wellbeing).await?;
     
     // Генерируем персонализированный ответ на основе данных
     let health_context = create_health_context_from_wellbeing(&wellbeing);
     let message = generate_wellbeing_summary(&wellbeing);
     
-    let response = assistant.get_personalized_response(&message, &health_context).await?;
+    let mut response = assistant.get_personalized_response(&message, &health_context).await?;
+    response.response_id = record_response(pool, request.claims.sub, &response).await;
     
     Ok(ResponseJson(response))
 }
@@ -102,11 +206,22 @@ pub async fn daily_wellbeing_check(
 /// Панель здоровья с инсайтами и рекомендациями
 pub async fn health_dashboard(
     State(ai_service): State<AiService>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    UserLocale(locale): UserLocale,
 ) -> Result<ResponseJson<HealthDashboardResponse>, AppError> {
-    let assistant = PersonalHealthAssistant::new(ai_service);
+    let now = clock.now();
+    let assistant = PersonalHealthAssistant::new(ai_service.for_user(claims.sub)).with_locale(locale).with_clock(clock);
+    let diet_quality = DietQualityService::new(pools.clone())
+        .series(claims.sub, now.date_naive(), 1, false)
+        .await?
+        .days
+        .pop();
+    let fasting = FastingService::new(pools.primary().clone()).status(claims.sub, now).await?;
     
     // В реальном приложении загружались бы данные пользователя
-    let health_context = create_mock_health_context();
+    let health_context = create_mock_health_context(now);
     
     let insights = assistant.generate_health_insights(&health_context, "").await?;
     let recommendations = assistant.generate_personalized_recommendations(&health_context).await?;
@@ -123,7 +238,10 @@ pub async fn health_dashboard(
             total_water_ml: 14000,
             total_exercise_minutes: 180,
         },
-        motivational_message: "Вы заботитесь о своем здоровье уже 7 дней подряд! Это отличная привычка. 🌟".to_string(),
+        diet_quality,
+        fasting,
+        motivational_message: i18n::t(locale, "health.dashboard.motivation").to_string(),
+        meta: assistant.response_meta(),
     };
     
     Ok(ResponseJson(dashboard))
@@ -132,9 +250,10 @@ pub async fn health_dashboard(
 /// Получить персонализированные рекомендации
 pub async fn get_recommendations(
     State(ai_service): State<AiService>,
+    Extension(clock): Extension<SharedClock>,
 ) -> Result<ResponseJson<Vec<PersonalizedRecommendation>>, AppError> {
-    let assistant = PersonalHealthAssistant::new(ai_service);
-    let health_context = create_mock_health_context();
+    let assistant = PersonalHealthAssistant::new(ai_service).with_clock(clock.clone());
+    let health_context = create_mock_health_context(clock.now());
     
     let recommendations = assistant.generate_personalized_recommendations(&health_context).await?;
     
@@ -144,9 +263,11 @@ pub async fn get_recommendations(
 /// Анализ настроения и предложения
 pub async fn mood_analysis(
     State(ai_service): State<AiService>,
+    Extension(pool): Extension<DbPool>,
+    request: AssistantRequest,
     Json(mood_data): Json<serde_json::Value>,
 ) -> Result<ResponseJson<PersonalizedResponse>, AppError> {
-    let assistant = PersonalHealthAssistant::new(ai_service);
+    let assistant = request.assistant(ai_service).await?;
     
     let mood_score = mood_data["mood_score"].as_i64().unwrap_or(5) as i32;
     let notes = mood_data["notes"].as_str().unwrap_or("");
@@ -156,15 +277,155 @@ pub async fn mood_analysis(
         mood_score, notes
     );
     
-    let health_context = create_mock_health_context();
-    let response = assistant.get_personalized_response(&message, &health_context).await?;
+    let health_context = create_mock_health_context(request.clock.now());
+    let mut response = assistant.get_personalized_response(&message, &health_context).await?;
+    response.response_id = record_response(pool, request.claims.sub, &response).await;
     
     Ok(ResponseJson(response))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DietQualityQuery {
+    pub days: Option<i64>,
+    /// Не учитывать записи дневника с оценкой питательности от ИИ
+    #[serde(default)]
+    pub exclude_estimates: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MoodInsightsResponse {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Дни, где есть и оценка питания, и оценка настроения
+    pub days_compared: usize,
+    /// Нет, пока в каждой группе дней меньше трех
+    pub diet_quality: Option<DietMoodInsight>,
+}
+
+/// Ежедневная оценка качества питания по дневнику и разбор по составляющим рубрики
+pub async fn get_diet_quality(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<DietQualityQuery>,
+) -> Result<ResponseJson<DietQualitySeries>, AppError> {
+    let days = diet_quality_days(query.days)?;
+    let series = DietQualityService::new(pools)
+        .series(claims.sub, clock.now().date_naive(), days, query.exclude_estimates)
+        .await?;
+
+    Ok(ResponseJson(series))
+}
+
+/// Связь настроения с оценкой питания. Считается без модели.
+pub async fn get_mood_insights(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Query(query): Query<DietQualityQuery>,
+) -> Result<ResponseJson<MoodInsightsResponse>, AppError> {
+    let days = diet_quality_days(query.days)?;
+    let series = DietQualityService::new(pools.clone())
+        .series(claims.sub, clock.now().date_naive(), days, query.exclude_estimates)
+        .await?;
+    let moods = WellbeingService::new(pools.read(ReadConsistency::Replica).clone())
+        .daily_moods(claims.sub, series.start_date, series.end_date)
+        .await?;
+
+    Ok(ResponseJson(MoodInsightsResponse {
+        start_date: series.start_date,
+        end_date: series.end_date,
+        days_compared: series.days.iter().filter(|day| moods.contains_key(&day.date)).count(),
+        diet_quality: diet_mood_insight(&series.days, &moods),
+    }))
+}
+
+/// Голодание сейчас: окно питания, время с последнего приема пищи и самый долгий перерыв за неделю
+pub async fn get_fasting_status(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<FastingStatus>, AppError> {
+    let status = FastingService::new(pool).status(claims.sub, clock.now()).await?;
+    Ok(ResponseJson(status))
+}
+
+pub async fn get_eating_window(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<Option<EatingWindow>>, AppError> {
+    let window = PreferencesService::new(pool).get_eating_window(claims.sub).await?;
+    Ok(ResponseJson(window))
+}
+
+pub async fn update_eating_window(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<EatingWindowRequest>,
+) -> Result<ResponseJson<Option<EatingWindow>>, AppError> {
+    let window = validate_eating_window(payload)?;
+    let saved = PreferencesService::new(pool).with_clock(clock).set_eating_window(claims.sub, Some(window)).await?;
+    Ok(ResponseJson(saved))
+}
+
+pub async fn delete_eating_window(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+) -> Result<ResponseJson<Option<EatingWindow>>, AppError> {
+    let saved = PreferencesService::new(pool).with_clock(clock).set_eating_window(claims.sub, None).await?;
+    Ok(ResponseJson(saved))
+}
+
+pub fn validate_eating_window(payload: EatingWindowRequest) -> Result<EatingWindow, AppError> {
+    if payload.start == payload.end {
+        return Err(AppError::BadRequest("Eating window start and end must differ".to_string()));
+    }
+    if !(MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&payload.utc_offset_minutes) {
+        return Err(AppError::BadRequest("utc_offset_minutes must be between -720 and 840".to_string()));
+    }
+    for (index, day) in payload.weekdays.iter().enumerate() {
+        if payload.weekdays[..index].contains(day) {
+            return Err(AppError::BadRequest(format!("Duplicate day in weekdays: {}", day.as_str())));
+        }
+    }
+
+    Ok(EatingWindow {
+        start: payload.start,
+        end: payload.end,
+        utc_offset_minutes: payload.utc_offset_minutes,
+        weekdays: payload.weekdays,
+        notify_outside: payload.notify_outside,
+        last_notified_on: None,
+    })
+}
+
+fn diet_quality_days(days: Option<i64>) -> Result<i64, AppError> {
+    let days = days.unwrap_or(30);
+    if !(1..=MAX_DIET_QUALITY_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!("days must be between 1 and {}", MAX_DIET_QUALITY_DAYS)));
+    }
+    Ok(days)
+}
+
 // Вспомогательные функции
 
-fn create_mock_health_context() -> HealthContext {
+/// Сохраняет ответ в историю ответов ИИ вместе с провенансом
+/// Тон ответа помощника по эксперименту `health_tone`; запрос к помощнику — показ эксперимента
+async fn health_tone(experiments: &Experiments, user_id: Uuid) -> Result<HealthTone, AppError> {
+    let context = experiments.context(user_id).await?;
+    context.expose(HEALTH_TONE_EXPERIMENT).await;
+    Ok(HealthTone::from_variant(context.variant(HEALTH_TONE_EXPERIMENT)))
+}
+
+async fn record_response(pool: DbPool, user_id: Uuid, response: &PersonalizedResponse) -> Option<Uuid> {
+    AiHistoryService::new(pool)
+        .record_or_warn(user_id, AiResponseKind::PersonalHealth, response.provenance.as_ref(), response)
+        .await
+}
+
+fn create_mock_health_context(now: DateTime<Utc>) -> HealthContext {
     HealthContext {
         user_profile: UserHealthSummary {
             name: "Александра".to_string(),
@@ -185,7 +446,7 @@ fn create_mock_health_context() -> HealthContext {
             DailyWellbeing {
                 id: Uuid::new_v4(),
                 user_id: Uuid::new_v4(),
-                date: Utc::now(),
+                date: now,
                 mood_score: Some(7),
                 energy_level: Some(6),
                 stress_level: Some(5),
@@ -195,12 +456,12 @@ fn create_mock_health_context() -> HealthContext {
                 exercise_minutes: Some(30),
                 notes: None,
                 symptoms: vec![],
-                created_at: Utc::now(),
+                created_at: now,
             }
         ],
         recent_nutrition: vec![
             NutritionSummary {
-                date: Utc::now(),
+                date: now,
                 calories: 1850.0,
                 protein: 85.0,
                 carbs: 220.0,
@@ -265,3 +526,40 @@ fn generate_wellbeing_summary(wellbeing: &DailyWellbeing) -> String {
     summary.push_str(" Проанализируй мое состояние и дай рекомендации.");
     summary
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use sqlx::PgPool;
+
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    #[sqlx::test]
+    async fn assistant_endpoints_answer_and_record_the_response(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+
+        let (status, body) = send(
+            &router,
+            request(Method::POST, "/api/v1/health/mood-analysis", Some(&token), Some(serde_json::json!({ "mood_score": 6 }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(!body["response"].as_str().unwrap().is_empty());
+
+        let (status, body) = send(
+            &router,
+            request(Method::POST, "/api/v1/health/chat", Some(&token), Some(serde_json::json!({ "message": "Как мне лучше спать?" }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ai_responses WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(recorded, 2);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Extension, Json},
+    response::Json as ResponseJson,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    api::{
+        fridge::{validate_category_preferences, CategoryPreferencesRequest},
+        notifications::{validate_preferences, NotificationPreferencesRequest, MAX_UTC_OFFSET_MINUTES, MIN_UTC_OFFSET_MINUTES},
+        personal_health::{validate_eating_window, EatingWindowRequest},
+    },
+    db::DbPool,
+    models::{
+        diary::{DiaryReminderPreferences, MealTimeBoundaries},
+        fridge::{CheckinPreferences, ReportPreferences},
+        preferences::{GeneralPreferences, GlossaryPreferences, PreferencesDocument, PreferencesSection, PrivacyPreferences, UserPreferences},
+    },
+    services::{
+        auth::Claims,
+        clock::SharedClock,
+        preferences::{merge_patch, section_value, set_eating_window, PreferencesService},
+    },
+    utils::errors::AppError,
+};
+
+const MAX_LOCALE_LENGTH: usize = 35;
+const MAX_GLOSSARY_OVERRIDES: usize = 100;
+const MAX_GLOSSARY_TERM_CHARS: usize = 100;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(get_preferences).patch(update_preferences))
+}
+
+/// Все настройки пользователя со значениями по умолчанию и временем изменения разделов
+pub async fn get_preferences(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<PreferencesDocument>, AppError> {
+    let document = PreferencesService::new(pool).document(claims.sub).await?;
+    Ok(ResponseJson(document))
+}
+
+/// Частичное обновление: `{"notifications": {"muted": true}}` меняет одно поле раздела,
+/// `{"eating_window": null}` сбрасывает раздел к значению по умолчанию.
+/// Каждый затронутый раздел проверяется целиком; при ошибке не сохраняется ничего.
+pub async fn update_preferences(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<Map<String, Value>>,
+) -> Result<ResponseJson<PreferencesDocument>, AppError> {
+    let now = clock.now();
+    let document = PreferencesService::new(pool)
+        .with_clock(clock)
+        .try_update(claims.sub, |preferences| apply_patch(preferences, payload, now))
+        .await?;
+
+    Ok(ResponseJson(document))
+}
+
+fn apply_patch(preferences: &mut UserPreferences, patch: Map<String, Value>, now: DateTime<Utc>) -> Result<(), AppError> {
+    for (name, section_patch) in patch {
+        let section = PreferencesSection::parse(&name)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown preferences section: {}", name)))?;
+
+        let mut value = if section_patch.is_null() {
+            section_value(&UserPreferences::default(), section)?
+        } else {
+            section_value(preferences, section)?
+        };
+        merge_patch(&mut value, section_patch);
+
+        apply_section(preferences, section, value, now)?;
+    }
+
+    Ok(())
+}
+
+/// Проверяет раздел после слияния и записывает его в документ. Служебные поля
+/// (даты последних напоминаний и проверок) клиент изменить не может.
+fn apply_section(
+    preferences: &mut UserPreferences,
+    section: PreferencesSection,
+    value: Value,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    match section {
+        PreferencesSection::General => {
+            let general: GeneralPreferences = parse_section(section, value)?;
+            validate_general(&general)?;
+            preferences.general = general;
+        }
+        PreferencesSection::Categories => {
+            let request: CategoryPreferencesRequest = parse_section(section, value)?;
+            preferences.categories = validate_category_preferences(request)?;
+        }
+        PreferencesSection::Checkin => {
+            let checkin: CheckinPreferences = parse_section(section, value)?;
+            preferences.checkin.enabled = checkin.enabled;
+            preferences.checkin.day = checkin.day;
+        }
+        PreferencesSection::Reports => {
+            preferences.reports = parse_section::<ReportPreferences>(section, value)?;
+        }
+        PreferencesSection::Notifications => {
+            let request: NotificationPreferencesRequest = parse_section(section, value)?;
+            preferences.notifications = validate_preferences(request, now)?;
+        }
+        PreferencesSection::EatingWindow => {
+            let request: Option<EatingWindowRequest> = parse_section(section, value)?;
+            let window = request.map(validate_eating_window).transpose()?;
+            set_eating_window(preferences, window);
+        }
+        PreferencesSection::Privacy => {
+            preferences.privacy = parse_section::<PrivacyPreferences>(section, value)?;
+        }
+        PreferencesSection::MealTimes => {
+            let boundaries: MealTimeBoundaries = parse_section(section, value)?;
+            if !(boundaries.lunch_from < boundaries.dinner_from && boundaries.dinner_from < boundaries.snack_from) {
+                return Err(AppError::BadRequest(
+                    "Meal times must be in order: lunch_from < dinner_from < snack_from".to_string(),
+                ));
+            }
+            preferences.meal_times = boundaries;
+        }
+        PreferencesSection::DiaryReminder => {
+            let reminder: DiaryReminderPreferences = parse_section(section, value)?;
+            preferences.diary_reminder.enabled = reminder.enabled;
+            preferences.diary_reminder.time = reminder.time;
+        }
+        PreferencesSection::Glossary => {
+            let glossary: GlossaryPreferences = parse_section(section, value)?;
+            preferences.glossary = validate_glossary(glossary)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_general(general: &GeneralPreferences) -> Result<(), AppError> {
+    if let Some(locale) = &general.locale {
+        let valid = !locale.is_empty()
+            && locale.len() <= MAX_LOCALE_LENGTH
+            && locale.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+        if !valid {
+            return Err(AppError::BadRequest(format!("Invalid locale: {}", locale)));
+        }
+    }
+    if !(MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&general.utc_offset_minutes) {
+        return Err(AppError::BadRequest("utc_offset_minutes must be between -720 and 840".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Названия без лишних пробелов; пустые и слишком длинные отклоняются
+fn validate_glossary(glossary: GlossaryPreferences) -> Result<GlossaryPreferences, AppError> {
+    if glossary.overrides.len() > MAX_GLOSSARY_OVERRIDES {
+        return Err(AppError::BadRequest(format!("At most {} glossary overrides are allowed", MAX_GLOSSARY_OVERRIDES)));
+    }
+
+    let clean = |term: &str| term.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut overrides = BTreeMap::new();
+    for (name, preferred) in &glossary.overrides {
+        let (name, preferred) = (clean(name), clean(preferred));
+        let valid = |term: &str| !term.is_empty() && term.chars().count() <= MAX_GLOSSARY_TERM_CHARS;
+        if !valid(&name) || !valid(&preferred) {
+            return Err(AppError::BadRequest(format!(
+                "Glossary terms must be non-empty and at most {} characters",
+                MAX_GLOSSARY_TERM_CHARS
+            )));
+        }
+        overrides.insert(name, preferred);
+    }
+
+    Ok(GlossaryPreferences { overrides })
+}
+
+fn parse_section<T: DeserializeOwned>(section: PreferencesSection, value: Value) -> Result<T, AppError> {
+    serde_json::from_value(value)
+        .map_err(|e| AppError::BadRequest(format!("Invalid {} preferences: {}", section.as_str(), e)))
+}
@@ -1,6 +1,7 @@
 use axum::{
     extract::{Extension, Json, Path, Query},
-    response::Json as ResponseJson,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json as ResponseJson, Response},
     routing::{get, post, put, delete},
     Router,
 };
@@ -8,56 +9,151 @@ use serde::{Deserialize, Serialize};
 use validator::Validate;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::{
-    db::DbPool,
-    models::recipe::{Recipe, CreateRecipe, RecipeCategory, DifficultyLevel, RecipeIngredient},
-    services::{auth::Claims, recipe::RecipeService, ai::AiService},
-    utils::errors::AppError,
+    config::Config,
+    db::{DbPool, DbPools},
+    models::recipe::{CreateRecipe, CookableRecipe, RecipeCategory, DifficultyLevel, RecipeIngredient, RecipeCostEstimate, IngredientQuantity, RecipeEmbed, RecipeStepInput, RelatedRecipe},
+    services::{auth::Claims, clock::SharedClock, recipe::RecipeService, recipe_tags::RecipeTagService, cookable::{sort_by_cost, CookableService, DEFAULT_MIN_MATCH}, recipe_cost::{estimate_cost, CostIngredient, RecipeCostService}, recipe_share::{provider_url, share_url, RecipeShareService, SharedRecipeEmbed}, ai::AiService, fridge::FridgeService, preferences::{PreferencesService, UserLocale}, realtime::RealtimeService},
+    utils::{errors::AppError, etag::{json_with_etag, ETagBuilder}, ingredient_matcher::IngredientIndex, rate_limit::IpRateLimiter},
 };
 
+/// Виджет меняется редко: браузеры и CDN держат ответ час и еще сутки отдают устаревший,
+/// пока обновляют его в фоне
+const EMBED_CACHE_MAX_AGE_SECS: u32 = 3600;
+const EMBED_CACHE_CONTROL: &str = "public, max-age=3600, stale-while-revalidate=86400";
+/// Размер iframe из oEmbed
+const EMBED_WIDTH: u32 = 400;
+const EMBED_HEIGHT: u32 = 600;
+const EMBED_PROVIDER_NAME: &str = "IT Cook";
+
 pub fn routes() -> Router {
     Router::new()
         .route("/", post(create_recipe))
         .route("/", get(get_recipes))
-        .route("/{id}", get(get_recipe))
-        .route("/{id}", put(update_recipe))
-        .route("/{id}", delete(delete_recipe))
-        .route("/{id}/favorite", post(toggle_favorite))
-        .route("/{id}/rating", post(rate_recipe))
+        .route("/:id", get(get_recipe))
+        .route("/:id", put(update_recipe))
+        .route("/:id", delete(delete_recipe))
+        .route("/:id/favorite", post(toggle_favorite))
+        .route("/:id/rating", post(rate_recipe))
+        .route("/:id/fork", post(fork_recipe))
+        .route("/:id/forks", get(get_recipe_forks))
+        .route("/:id/related", get(get_related_recipes))
+        .route("/:id/cost", get(get_recipe_cost))
+        .route("/:id/share", get(get_share_link).post(create_share_link).delete(revoke_share_link))
         .route("/search", get(search_recipes))
         .route("/generate", post(generate_ai_recipe))
         .route("/popular", get(get_popular_recipes))
         .route("/favorites", get(get_favorite_recipes))
+        .route("/cookable", get(get_cookable_recipes))
+}
+
+/// Данные рецептов для виджетов на чужих сайтах: по публичной ссылке, без авторизации.
+/// CORS этих маршрутов открыт для любого источника (см. `app::build_router`).
+pub fn embed_routes() -> Router {
+    Router::new()
+        .route("/:token/embed.json", get(get_recipe_embed))
 }
 
+/// Лимит запросов виджета на один токен: рецепт, встроенный на популярном сайте,
+/// не должен нагружать API
+#[derive(Clone)]
+pub struct EmbedRateLimiter(pub IpRateLimiter);
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateRecipeRequest {
     #[validate(length(min = 1, max = 200))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub name: String,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub description: Option<String>,
     pub category: RecipeCategory,
     pub difficulty: DifficultyLevel,
     pub prep_time_minutes: Option<i32>,
     pub cook_time_minutes: Option<i32>,
     pub servings: Option<i32>,
-    pub instructions: String,
+    pub instructions: RecipeInstructionsRequest,
+    /// Фото галереи рецепта по порядку — id файлов, загруженных пользователем
+    #[serde(default)]
+    pub gallery: Vec<Uuid>,
     pub ingredients: Vec<CreateRecipeIngredientRequest>,
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized_vec")]
     pub tags: Vec<String>,
     pub image_url: Option<String>,
     pub source_url: Option<String>,
     pub nutrition_per_serving: Option<NutritionInfoRequest>,
+    /// Опубликовать рецепт, чтобы другие могли сохранить его себе; `false` отменяет публикацию
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+/// Инструкции: устаревшая строка (шаг — каждая непустая строка) или список шагов
+/// `[{"text": "...", "media_id": "..."}]`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RecipeInstructionsRequest {
+    Text(#[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")] String),
+    Steps(Vec<RecipeStepInput>),
+}
+
+impl RecipeInstructionsRequest {
+    /// Шаги рецепта; пустые шаги отклоняются
+    pub fn into_steps(self) -> Result<Vec<RecipeStepInput>, AppError> {
+        let steps = match self {
+            RecipeInstructionsRequest::Text(text) => RecipeStepInput::from_instructions(&text),
+            RecipeInstructionsRequest::Steps(steps) => steps
+                .into_iter()
+                .map(|step| RecipeStepInput { text: step.text.trim().to_string(), ..step })
+                .collect(),
+        };
+
+        if steps.is_empty() {
+            return Err(AppError::BadRequest("Recipe must have at least one instruction step".to_string()));
+        }
+        if let Some(index) = steps.iter().position(|step| step.text.is_empty()) {
+            return Err(AppError::BadRequest(format!("Step {} has no text", index + 1)));
+        }
+        // Шаг хранится одной строкой в устаревшем поле instructions
+        if let Some(index) = steps.iter().position(|step| step.text.contains('\n')) {
+            return Err(AppError::BadRequest(format!("Step {} must be a single line", index + 1)));
+        }
+
+        Ok(steps)
+    }
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateRecipeIngredientRequest {
     #[validate(length(min = 1, max = 100))]
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
     pub name: String,
-    pub quantity: f32,
-    pub unit: String,
+    /// Количество: `{"kind": "measured", "value": 200, "unit": "г"}`, `{"kind": "count", "value": 2}`
+    /// или `{"kind": "to_taste"}`
+    #[serde(default)]
+    pub amount: Option<IngredientQuantity>,
+    /// Устаревший формат `quantity` + `unit` — читается, если нет `amount`
+    #[serde(default)]
+    pub quantity: Option<f32>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub unit: Option<String>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub notes: Option<String>,
 }
 
+impl CreateRecipeIngredientRequest {
+    /// Проверенное количество из `amount` или из устаревших `quantity` + `unit`
+    pub fn parsed_amount(&self) -> Result<IngredientQuantity, AppError> {
+        let parsed = match &self.amount {
+            Some(amount) => amount.clone().validated(),
+            None => IngredientQuantity::from_legacy(self.quantity.unwrap_or(0.0), self.unit.as_deref().unwrap_or("")),
+        };
+
+        parsed.map_err(|message| AppError::BadRequest(format!("Ingredient '{}': {}", self.name, message)))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct NutritionInfoRequest {
     pub calories: Option<f32>,
@@ -79,6 +175,23 @@ pub struct RecipeQueryParams {
     pub tags: Option<String>, // comma-separated
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Отметить, какие ингредиенты уже есть в холодильнике пользователя
+    pub with_availability: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityQueryParams {
+    pub with_availability: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CookableQueryParams {
+    /// Учитывать также публичный каталог, а не только свои и сохраненные рецепты
+    pub include_public: Option<bool>,
+    /// Минимальная доля имеющихся ингредиентов в процентах (по умолчанию 60)
+    pub min_match: Option<f64>,
+    /// `cost` — от дешевых к дорогим; по умолчанию — по совпадению с холодильником
+    pub sort: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -94,13 +207,16 @@ pub struct GenerateRecipeRequest {
 #[derive(Debug, Deserialize)]
 pub struct RatingRequest {
     pub rating: i32, // 1-5
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub comment: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct RecipeResponse {
     pub id: Uuid,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
     pub name: String,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_optional_escaped")]
     pub description: Option<String>,
     pub category: RecipeCategory,
     pub difficulty: DifficultyLevel,
@@ -108,7 +224,11 @@ pub struct RecipeResponse {
     pub cook_time_minutes: Option<i32>,
     pub total_time_minutes: Option<i32>,
     pub servings: Option<i32>,
+    /// Шаги одной строкой — для клиентов, которые еще не читают `steps`
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
     pub instructions: String,
+    pub steps: Vec<RecipeStepResponse>,
+    pub gallery: Vec<RecipeMediaResponse>,
     pub ingredients: Vec<RecipeIngredientResponse>,
     pub tags: Vec<String>,
     pub image_url: Option<String>,
@@ -120,14 +240,181 @@ pub struct RecipeResponse {
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Заполняется только при `with_availability=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability: Option<AvailabilitySummary>,
+    /// Есть только у копий чужих рецептов
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<RecipeAttribution>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeStepResponse {
+    pub position: i32,
+    #[serde(serialize_with = "crate::utils::sanitize::serialize_escaped")]
+    pub text: String,
+    pub media_id: Option<Uuid>,
+    pub media_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeMediaResponse {
+    pub media_id: Uuid,
+    pub url: String,
+}
+
+/// Шаги и галерея для ответа; `urls` — адреса файлов по id
+pub fn step_responses(steps: &[RecipeStepInput], urls: &HashMap<Uuid, String>) -> Vec<RecipeStepResponse> {
+    steps
+        .iter()
+        .zip(1..)
+        .map(|(step, position)| RecipeStepResponse {
+            position,
+            text: step.text.clone(),
+            media_id: step.media_id,
+            media_url: step.media_id.and_then(|id| urls.get(&id).cloned()),
+        })
+        .collect()
+}
+
+pub fn gallery_responses(gallery: &[Uuid], urls: &HashMap<Uuid, String>) -> Vec<RecipeMediaResponse> {
+    gallery
+        .iter()
+        .filter_map(|id| urls.get(id).map(|url| RecipeMediaResponse { media_id: *id, url: url.clone() }))
+        .collect()
+}
+
+/// Откуда взята копия рецепта ("адаптировано из …")
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeAttribution {
+    /// `None`, если оригинал уже удален
+    pub original_recipe_id: Option<Uuid>,
+    pub original_author_id: Option<Uuid>,
+    pub original_author_name: Option<String>,
+    pub label: String,
+}
+
+/// Публичная ссылка на рецепт; в постах сообщества по ней строится карточка рецепта
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeShareLinkResponse {
+    pub token: String,
+    pub url: String,
+    /// Просмотры виджета рецепта на чужих сайтах
+    pub embed_views: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedFormat {
+    #[default]
+    Json,
+    /// Документ oEmbed для платформ, которые разворачивают ссылки
+    Oembed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbedQueryParams {
+    #[serde(default)]
+    pub format: EmbedFormat,
+}
+
+/// oEmbed 1.0, тип `rich` (https://oembed.com)
+#[derive(Debug, Clone, Serialize)]
+pub struct OEmbedResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub version: &'static str,
+    pub title: String,
+    pub author_name: String,
+    pub provider_name: &'static str,
+    pub provider_url: String,
+    pub cache_age: u32,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OEmbedResponse {
+    fn rich(recipe: SharedRecipeEmbed) -> Self {
+        let RecipeEmbed { name, url, .. } = recipe.embed;
+        Self {
+            kind: "rich",
+            version: "1.0",
+            title: name,
+            author_name: recipe.author_name,
+            provider_name: EMBED_PROVIDER_NAME,
+            provider_url: provider_url(),
+            cache_age: EMBED_CACHE_MAX_AGE_SECS,
+            // В адресе только база ссылок из настроек и буквенно-цифровой токен — экранировать нечего
+            html: format!(
+                r#"<iframe src="{}?embed=1" width="{}" height="{}" style="border:0" loading="lazy"></iframe>"#,
+                url, EMBED_WIDTH, EMBED_HEIGHT
+            ),
+            width: EMBED_WIDTH,
+            height: EMBED_HEIGHT,
+        }
+    }
+}
+
+/// Статистика копий рецепта — только счетчик, без данных пользователей
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeForksResponse {
+    pub recipe_id: Uuid,
+    pub forks_count: i32,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct RecipeIngredientResponse {
     pub name: String,
+    /// `None`, если количество ждет правки автора (`needs_review`)
+    pub amount: Option<IngredientQuantity>,
+    /// Устаревшие поля: повторяют `amount` на время перехода клиентов
     pub quantity: f32,
     pub unit: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub needs_review: bool,
     pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+}
+
+impl RecipeIngredientResponse {
+    pub fn new(name: String, amount: IngredientQuantity, notes: Option<String>) -> Self {
+        let (quantity, unit) = amount.legacy_parts();
+        Self {
+            name,
+            amount: Some(amount),
+            quantity,
+            unit,
+            needs_review: false,
+            notes,
+            available: None,
+        }
+    }
+
+    /// Строка без разобранного количества отдается как хранится, с флагом `needs_review`
+    pub fn from_row(ingredient: RecipeIngredient) -> Self {
+        match ingredient.amount() {
+            Some(amount) => Self::new(ingredient.name, amount, ingredient.notes),
+            None => Self {
+                name: ingredient.name,
+                amount: None,
+                quantity: ingredient.quantity,
+                unit: ingredient.unit,
+                needs_review: true,
+                notes: ingredient.notes,
+                available: None,
+            },
+        }
+    }
+}
+
+/// Сколько ингредиентов рецепта уже есть в холодильнике
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilitySummary {
+    pub have: usize,
+    pub total: usize,
+    pub missing_names: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -143,6 +430,7 @@ pub struct NutritionInfoResponse {
 
 pub async fn create_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Json(payload): Json<CreateRecipeRequest>,
 ) -> Result<ResponseJson<RecipeResponse>, AppError> {
@@ -156,26 +444,30 @@ pub async fn create_recipe(
         prep_time_minutes: payload.prep_time_minutes,
         cook_time_minutes: payload.cook_time_minutes,
         servings: payload.servings,
-        instructions: payload.instructions,
+        steps: payload.instructions.into_steps()?,
+        gallery: payload.gallery,
         tags: payload.tags,
         image_url: payload.image_url,
         source_url: payload.source_url,
         created_by: claims.sub,
+        is_public: payload.is_public,
     };
 
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     let recipe = recipe_service.create_recipe(create_recipe, payload.ingredients, payload.nutrition_per_serving).await?;
 
     Ok(ResponseJson(recipe))
 }
 
 pub async fn get_recipes(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(config): Extension<Config>,
     claims: Claims,
     Query(params): Query<RecipeQueryParams>,
 ) -> Result<ResponseJson<Vec<RecipeResponse>>, AppError> {
-    let recipe_service = RecipeService::new(pool);
-    let recipes = recipe_service.get_recipes(
+    let recipe_service = RecipeService::with_pools(pools.clone()).with_clock(clock.clone());
+    let mut recipes = recipe_service.get_recipes(
         Some(claims.sub),
         params.category,
         params.difficulty,
@@ -187,29 +479,78 @@ pub async fn get_recipes(
         params.offset.unwrap_or(0),
     ).await?;
 
+    if params.with_availability.unwrap_or(false) {
+        let index = fridge_index(pools.primary().clone(), clock, &config, claims.sub).await?;
+        for recipe in recipes.iter_mut() {
+            apply_availability(recipe, &index);
+        }
+    }
+
     Ok(ResponseJson(recipes))
 }
 
+/// Поддерживает If-None-Match. Ответ собирается из нескольких таблиц (рейтинги, избранное,
+/// наличие в холодильнике), поэтому ETag считается по содержимому, а не по updated_at рецепта.
 pub async fn get_recipe(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(config): Extension<Config>,
     claims: Claims,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> Result<ResponseJson<RecipeResponse>, AppError> {
-    let recipe_service = RecipeService::new(pool);
-    let recipe = recipe_service.get_recipe_by_id(id, Some(claims.sub)).await?;
+    Query(params): Query<AvailabilityQueryParams>,
+) -> Result<Response, AppError> {
+    let recipe_service = RecipeService::with_pools(pools.clone()).with_clock(clock.clone());
+    let mut recipe = recipe_service.get_recipe_by_id(id, Some(claims.sub)).await?;
 
-    Ok(ResponseJson(recipe))
+    if params.with_availability.unwrap_or(false) {
+        let index = fridge_index(pools.primary().clone(), clock, &config, claims.sub).await?;
+        apply_availability(&mut recipe, &index);
+    }
+
+    let etag = ETagBuilder::for_user(claims.sub).content(&recipe).build();
+    Ok(json_with_etag(&headers, etag, recipe))
+}
+
+/// Продукты холодильника загружаются один раз на запрос, независимо от числа рецептов.
+/// Для очень больших холодильников сопоставление упрощается до точного совпадения названий.
+async fn fridge_index(pool: DbPool, clock: SharedClock, config: &Config, user_id: Uuid) -> Result<IngredientIndex, AppError> {
+    let fridge_service = FridgeService::new(pool).with_clock(clock);
+    let items = fridge_service.get_user_items(user_id, None, None, None).await?;
+    let exact_only = items.len() > config.recipe_availability_max_fridge_items;
+
+    Ok(IngredientIndex::new(items.iter().map(|item| item.name.as_str()), exact_only))
+}
+
+fn apply_availability(recipe: &mut RecipeResponse, index: &IngredientIndex) {
+    let mut missing_names = Vec::new();
+
+    for ingredient in recipe.ingredients.iter_mut() {
+        let available = index.contains(&ingredient.name);
+        if !available {
+            missing_names.push(ingredient.name.clone());
+        }
+        ingredient.available = Some(available);
+    }
+
+    let total = recipe.ingredients.len();
+    recipe.availability = Some(AvailabilitySummary {
+        have: total - missing_names.len(),
+        total,
+        missing_names,
+    });
 }
 
 pub async fn update_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateRecipeRequest>,
 ) -> Result<ResponseJson<RecipeResponse>, AppError> {
     payload.validate()?;
 
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     let recipe = recipe_service.update_recipe(id, claims.sub, payload).await?;
 
     Ok(ResponseJson(recipe))
@@ -217,21 +558,85 @@ pub async fn update_recipe(
 
 pub async fn delete_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     recipe_service.delete_recipe(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Recipe deleted successfully"})))
 }
 
+pub async fn create_share_link(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<RecipeShareLinkResponse>, AppError> {
+    let link = RecipeShareService::new(pool).create_token(id, claims.sub).await?;
+    Ok(ResponseJson(RecipeShareLinkResponse { url: share_url(&link.token), token: link.token, embed_views: link.embed_views }))
+}
+
+/// Действующая ссылка и просмотры виджета; видит только автор
+pub async fn get_share_link(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<RecipeShareLinkResponse>, AppError> {
+    let link = RecipeShareService::new(pool).link(id, claims.sub).await?;
+    Ok(ResponseJson(RecipeShareLinkResponse { url: share_url(&link.token), token: link.token, embed_views: link.embed_views }))
+}
+
+/// Рецепт для виджета или документ oEmbed (`?format=oembed`). Просмотром считается
+/// только запрос данных виджета: oEmbed запрашивают платформы при разворачивании ссылки.
+pub async fn get_recipe_embed(
+    Extension(pool): Extension<DbPool>,
+    Extension(EmbedRateLimiter(limiter)): Extension<EmbedRateLimiter>,
+    Path(token): Path<String>,
+    Query(params): Query<EmbedQueryParams>,
+) -> Result<Response, AppError> {
+    if token.is_empty() || token.len() > 64 || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::NotFound("Recipe not found".to_string()));
+    }
+    if !limiter.check(&token) {
+        return Err(AppError::TooManyRequests("Rate limit exceeded, try again later".to_string()));
+    }
+
+    let share_service = RecipeShareService::new(pool);
+    let recipe = share_service
+        .embed(&token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+    let cache = [(header::CACHE_CONTROL, EMBED_CACHE_CONTROL)];
+
+    match params.format {
+        EmbedFormat::Json => {
+            // Виджет отдается и без учета просмотра
+            if let Err(e) = share_service.record_embed_view(recipe.recipe_id).await {
+                tracing::warn!("Failed to record embed view: {:?}", e);
+            }
+            Ok((cache, ResponseJson(recipe.embed)).into_response())
+        }
+        EmbedFormat::Oembed => Ok((cache, ResponseJson(OEmbedResponse::rich(recipe))).into_response()),
+    }
+}
+
+pub async fn revoke_share_link(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<serde_json::Value>, AppError> {
+    RecipeShareService::new(pool).revoke_token(id, claims.sub).await?;
+    Ok(ResponseJson(serde_json::json!({"message": "Share link revoked"})))
+}
+
 pub async fn toggle_favorite(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
 ) -> Result<ResponseJson<serde_json::Value>, AppError> {
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     let is_favorite = recipe_service.toggle_favorite(id, claims.sub).await?;
 
     Ok(ResponseJson(serde_json::json!({
@@ -242,6 +647,7 @@ pub async fn toggle_favorite(
 
 pub async fn rate_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<RatingRequest>,
@@ -250,20 +656,66 @@ pub async fn rate_recipe(
         return Err(AppError::BadRequest("Rating must be between 1 and 5".to_string()));
     }
 
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     recipe_service.rate_recipe(id, claims.sub, payload.rating, payload.comment).await?;
 
     Ok(ResponseJson(serde_json::json!({"message": "Recipe rated successfully"})))
 }
 
-pub async fn search_recipes(
+/// Сохраняет чужой опубликованный рецепт себе, сохраняя ссылку на автора
+pub async fn fork_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(realtime_service): Extension<Arc<RealtimeService>>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<RecipeResponse>, AppError> {
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
+    let (fork, original) = recipe_service.fork_recipe(id, claims.sub).await?;
+
+    let forked_by_name = format!("{} {}", claims.first_name, claims.last_name);
+    let _ = realtime_service.notify_recipe_forked(
+        original.created_by,
+        original.id,
+        original.name,
+        forked_by_name,
+        original.forks_count,
+    ).await;
+
+    Ok(ResponseJson(fork))
+}
+
+pub async fn get_recipe_forks(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<RecipeForksResponse>, AppError> {
+    let recipe_service = RecipeService::with_pools(pools).with_clock(clock);
+    let forks = recipe_service.get_forks_count(id, claims.sub).await?;
+
+    Ok(ResponseJson(forks))
+}
+
+/// Похожие рецепты: больше общих тегов, и чем реже тег, тем он важнее
+pub async fn get_related_recipes(
+    Extension(pools): Extension<DbPools>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<Vec<RelatedRecipe>>, AppError> {
+    let related = RecipeTagService::with_pools(pools).related(id, claims.sub).await?;
+    Ok(ResponseJson(related))
+}
+
+pub async fn search_recipes(
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
     Query(params): Query<RecipeQueryParams>,
 ) -> Result<ResponseJson<Vec<RecipeResponse>>, AppError> {
     let search_query = params.search.unwrap_or_default();
     
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::with_pools(pools).with_clock(clock);
     let recipes = recipe_service.search_recipes(
         search_query,
         Some(claims.sub),
@@ -278,21 +730,26 @@ pub async fn search_recipes(
 
 pub async fn generate_ai_recipe(
     Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(ai_service): Extension<AiService>,
+    UserLocale(locale): UserLocale,
     claims: Claims,
     Json(payload): Json<GenerateRecipeRequest>,
 ) -> Result<ResponseJson<RecipeResponse>, AppError> {
     payload.validate()?;
 
-    let ai_service = AiService::from_env();
-    let recipe_service = RecipeService::new(pool);
+    let glossary = PreferencesService::new(pool.clone()).glossary(claims.sub, locale).await?;
+    let recipe_service = RecipeService::new(pool).with_clock(clock);
     
-    let generated_recipe = ai_service.generate_recipe(
+    let mut generated_recipe = ai_service.generate_recipe(
         &payload.description,
         payload.available_ingredients.unwrap_or_default(),
         payload.dietary_restrictions.unwrap_or_default(),
         payload.max_prep_time,
         payload.servings,
     ).await?;
+    // Ингредиенты сохраняются под привычными пользователю названиями — как его продукты
+    generated_recipe.normalize_terms(&glossary);
 
     // Сохраняем AI-сгенерированный рецепт
     let create_recipe = CreateRecipe {
@@ -308,19 +765,28 @@ pub async fn generate_ai_recipe(
         prep_time_minutes: payload.max_prep_time,
         cook_time_minutes: Some(20), // Парсим из cook_time
         servings: Some(generated_recipe.servings as i32),
-        instructions: generated_recipe.instructions.join("\n"),
+        // Шаги от модели — только текст, без фото
+        steps: generated_recipe.instructions.iter().flat_map(|step| RecipeStepInput::from_instructions(step)).collect(),
+        gallery: Vec::new(),
         tags: vec!["AI-generated".to_string()],
         image_url: None,
         source_url: Some("AI Generated".to_string()),
         created_by: claims.sub,
+        is_public: false,
     };
 
     // Конвертируем ингредиенты AI в формат для сохранения
     let recipe_ingredients: Vec<CreateRecipeIngredientRequest> = generated_recipe.ingredients.into_iter()
         .map(|ingredient| CreateRecipeIngredientRequest {
             name: ingredient.name,
-            quantity: 1.0, // Базовое количество
-            unit: ingredient.unit,
+            // Количество от модели — текстом ("200", "1,5", "по вкусу"); что не разобралось — "по вкусу"
+            amount: Some(
+                ingredient.amount.trim().replace(',', ".").parse::<f32>().ok()
+                    .and_then(|quantity| IngredientQuantity::from_legacy(quantity, &ingredient.unit).ok())
+                    .unwrap_or(IngredientQuantity::ToTaste),
+            ),
+            quantity: None,
+            unit: None,
             notes: if ingredient.available_in_fridge {
                 Some("Available in fridge".to_string())
             } else {
@@ -339,21 +805,372 @@ pub async fn generate_ai_recipe(
 }
 
 pub async fn get_popular_recipes(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<Vec<RecipeResponse>>, AppError> {
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::with_pools(pools).with_clock(clock);
     let recipes = recipe_service.get_popular_recipes(Some(claims.sub)).await?;
 
     Ok(ResponseJson(recipes))
 }
 
 pub async fn get_favorite_recipes(
-    Extension(pool): Extension<DbPool>,
+    Extension(pools): Extension<DbPools>,
+    Extension(clock): Extension<SharedClock>,
     claims: Claims,
 ) -> Result<ResponseJson<Vec<RecipeResponse>>, AppError> {
-    let recipe_service = RecipeService::new(pool);
+    let recipe_service = RecipeService::with_pools(pools).with_clock(clock);
     let recipes = recipe_service.get_favorite_recipes(claims.sub).await?;
 
     Ok(ResponseJson(recipes))
 }
+
+/// «Что приготовить прямо сейчас» — сопоставление рецептов с холодильником без ИИ
+pub async fn get_cookable_recipes(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    Extension(config): Extension<Config>,
+    claims: Claims,
+    Query(params): Query<CookableQueryParams>,
+) -> Result<ResponseJson<Vec<CookableRecipe>>, AppError> {
+    let min_match = params.min_match.unwrap_or(DEFAULT_MIN_MATCH);
+    if !(0.0..=100.0).contains(&min_match) {
+        return Err(AppError::BadRequest("min_match must be between 0 and 100".to_string()));
+    }
+    let sort_by_price = match params.sort.as_deref() {
+        None | Some("match") => false,
+        Some("cost") => true,
+        Some(other) => return Err(AppError::BadRequest(format!("Unknown sort: {}, expected match or cost", other))),
+    };
+
+    let cookable_service = CookableService::new(pool, config.recipe_availability_max_fridge_items).with_clock(clock);
+    let mut recipes = cookable_service
+        .find_cookable(claims.sub, params.include_public.unwrap_or(false), min_match)
+        .await?;
+    if sort_by_price {
+        sort_by_cost(&mut recipes);
+    }
+
+    Ok(ResponseJson(recipes))
+}
+
+/// Примерная стоимость рецепта по ценам продуктов, которые покупал пользователь
+pub async fn get_recipe_cost(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<ResponseJson<RecipeCostEstimate>, AppError> {
+    let recipe = RecipeService::new(pool.clone()).with_clock(clock).get_recipe_by_id(id, Some(claims.sub)).await?;
+    let prices = RecipeCostService::new(pool).price_book(claims.sub).await?;
+
+    let estimate = estimate_cost(
+        recipe.ingredients.iter().map(|ingredient| CostIngredient {
+            name: &ingredient.name,
+            amount: ingredient.amount.clone(),
+        }),
+        recipe.servings,
+        &prices,
+    );
+
+    Ok(ResponseJson(estimate))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::clock;
+    use crate::services::realtime::{WebSocketEvent, WebSocketManager};
+    use crate::test_support::{claims_for, insert_fridge_item, insert_recipe, insert_user};
+    use crate::utils::format::Locale;
+
+    async fn recipe_with_ingredients(pool: &PgPool, user_id: Uuid, names: &[&str]) -> Uuid {
+        RecipeService::new(pool.clone())
+            .create_recipe(
+                CreateRecipe {
+                    name: "Курица с рисом".to_string(),
+                    description: None,
+                    category: RecipeCategory::Dinner,
+                    difficulty: DifficultyLevel::Easy,
+                    prep_time_minutes: Some(10),
+                    cook_time_minutes: Some(30),
+                    servings: Some(2),
+                    steps: vec![RecipeStepInput::text("Смешайте и запеките.")],
+                    gallery: Vec::new(),
+                    tags: Vec::new(),
+                    image_url: None,
+                    source_url: None,
+                    created_by: user_id,
+                    is_public: false,
+                },
+                names
+                    .iter()
+                    .map(|name| CreateRecipeIngredientRequest {
+                        name: name.to_string(),
+                        amount: None,
+                        quantity: Some(100.0),
+                        unit: Some("г".to_string()),
+                        notes: None,
+                    })
+                    .collect(),
+                None,
+            )
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn list(pool: &PgPool, claims: Claims, config: Config, query: serde_json::Value) -> Vec<RecipeResponse> {
+        let params: RecipeQueryParams = serde_json::from_value(query).unwrap();
+        get_recipes(
+            Extension(DbPools::single(pool.clone())),
+            Extension(clock::system()),
+            Extension(config),
+            claims,
+            Query(params),
+        )
+        .await
+        .unwrap()
+        .0
+    }
+
+    fn config(max_fridge_items: usize) -> Config {
+        let mut config = Config::new().unwrap();
+        config.recipe_availability_max_fridge_items = max_fridge_items;
+        config
+    }
+
+    #[sqlx::test]
+    async fn availability_summary_counts_matched_ingredients(pool: PgPool) {
+        let user_id = insert_user(&pool, "Лена").await;
+        for name in ["Молоко", "Рис белый", "Куриная грудка охлажденная", "Сахар"] {
+            insert_fridge_item(&pool, user_id, name, None, Utc::now()).await;
+        }
+        let ingredients = ["Молоко", "Рис", "Куриная грудка", "Мука", "Соль"];
+        recipe_with_ingredients(&pool, user_id, &ingredients).await;
+        recipe_with_ingredients(&pool, user_id, &[]).await;
+        let claims = || claims_for(&pool, user_id);
+
+        let recipes = list(&pool, claims().await, config(100), serde_json::json!({ "with_availability": true })).await;
+        let full = recipes.iter().find(|recipe| !recipe.ingredients.is_empty()).unwrap();
+        let summary = full.availability.as_ref().unwrap();
+        assert_eq!((summary.have, summary.total), (3, 5));
+        assert_eq!(summary.missing_names, ["Мука", "Соль"]);
+        let flags: Vec<(&str, Option<bool>)> =
+            full.ingredients.iter().map(|ingredient| (ingredient.name.as_str(), ingredient.available)).collect();
+        assert_eq!(
+            flags,
+            [("Молоко", Some(true)), ("Рис", Some(true)), ("Куриная грудка", Some(true)), ("Мука", Some(false)), ("Соль", Some(false))]
+        );
+
+        // Рецепт без ингредиентов: 0 из 0
+        let empty = recipes.iter().find(|recipe| recipe.ingredients.is_empty()).unwrap();
+        let summary = empty.availability.as_ref().unwrap();
+        assert_eq!((summary.have, summary.total, summary.missing_names.len()), (0, 0, 0));
+
+        // В холодильнике больше продуктов, чем порог: только точные совпадения названий
+        let recipes = list(&pool, claims().await, config(3), serde_json::json!({ "with_availability": true })).await;
+        let full = recipes.iter().find(|recipe| !recipe.ingredients.is_empty()).unwrap();
+        let summary = full.availability.as_ref().unwrap();
+        assert_eq!((summary.have, summary.total), (1, 5));
+        assert_eq!(summary.missing_names, ["Рис", "Куриная грудка", "Мука", "Соль"]);
+    }
+
+    #[sqlx::test]
+    async fn listing_without_the_flag_is_unchanged(pool: PgPool) {
+        let user_id = insert_user(&pool, "Лена").await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, Utc::now()).await;
+        recipe_with_ingredients(&pool, user_id, &["Молоко", "Мука"]).await;
+
+        let service_bytes = serde_json::to_vec(
+            &RecipeService::new(pool.clone())
+                .get_recipes(Some(user_id), None, None, None, None, None, None, 20, 0)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        for query in [serde_json::json!({}), serde_json::json!({ "with_availability": false })] {
+            let recipes = list(&pool, claims_for(&pool, user_id).await, config(100), query).await;
+            let bytes = serde_json::to_vec(&recipes).unwrap();
+            assert_eq!(bytes, service_bytes);
+
+            let body = String::from_utf8(bytes).unwrap();
+            assert!(!body.contains("\"available\"") && !body.contains("\"availability\""));
+        }
+    }
+
+    #[sqlx::test]
+    async fn fork_notifies_the_original_author(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let forker = insert_user(&pool, "Борис").await;
+        let original = insert_recipe(&pool, author, "Окрошка", true).await;
+        let manager = Arc::new(WebSocketManager::new());
+        let mut registration = manager.add_client(author, None, "Анна".to_string(), Locale::Ru).await.unwrap();
+        let realtime = Arc::new(RealtimeService::new(manager));
+
+        let fork = fork_recipe(
+            Extension(pool.clone()),
+            Extension(clock::system()),
+            Extension(realtime.clone()),
+            claims_for(&pool, forker).await,
+            Path(original),
+        )
+        .await
+        .unwrap()
+        .0;
+        assert_eq!(fork.attribution.unwrap().original_recipe_id, Some(original));
+
+        match registration.receiver.try_recv().unwrap() {
+            WebSocketEvent::RecipeForked { recipe_id, title, forks_count, .. } => {
+                assert_eq!((recipe_id, title.as_str(), forks_count), (original, "Окрошка", 1));
+            }
+            other => panic!("expected RecipeForked, got {:?}", other),
+        }
+
+        // Отклоненная копия уведомления не дает
+        let private = insert_recipe(&pool, author, "Секретный суп", false).await;
+        let rejected = fork_recipe(
+            Extension(pool.clone()),
+            Extension(clock::system()),
+            Extension(realtime),
+            claims_for(&pool, forker).await,
+            Path(private),
+        )
+        .await;
+        assert!(matches!(rejected, Err(AppError::Forbidden(_))));
+        assert!(registration.receiver.try_recv().is_err());
+    }
+
+    fn parsed(body: serde_json::Value) -> Result<IngredientQuantity, String> {
+        let request: CreateRecipeIngredientRequest = serde_json::from_value(body).unwrap();
+        request.parsed_amount().map_err(|e| match e {
+            AppError::BadRequest(message) => message,
+            other => panic!("unexpected error {:?}", other),
+        })
+    }
+
+    #[test]
+    fn ingredient_input_accepts_structured_and_legacy_shapes() {
+        let measured = |value: f32, unit: &str| IngredientQuantity::Measured { value, unit: unit.to_string() };
+        let cases: Vec<(serde_json::Value, Result<IngredientQuantity, &str>)> = vec![
+            (json!({ "name": "Мука", "amount": { "kind": "measured", "value": 200, "unit": "г" } }), Ok(measured(200.0, "г"))),
+            (json!({ "name": "Яйца", "amount": { "kind": "count", "value": 2 } }), Ok(IngredientQuantity::Count { value: 2.0 })),
+            (json!({ "name": "Соль", "amount": { "kind": "to_taste" } }), Ok(IngredientQuantity::ToTaste)),
+            (json!({ "name": "Яйца", "amount": { "kind": "measured", "value": 2, "unit": "шт" } }), Ok(IngredientQuantity::Count { value: 2.0 })),
+            // Устаревший формат
+            (json!({ "name": "Мука", "quantity": 200, "unit": "г" }), Ok(measured(200.0, "г"))),
+            (json!({ "name": "Яйца", "quantity": 2, "unit": "" }), Ok(IngredientQuantity::Count { value: 2.0 })),
+            (json!({ "name": "Яйца", "quantity": 2 }), Ok(IngredientQuantity::Count { value: 2.0 })),
+            (json!({ "name": "Соль", "quantity": 0, "unit": "по вкусу" }), Ok(IngredientQuantity::ToTaste)),
+            // Если есть оба формата, читается `amount`
+            (json!({ "name": "Мука", "amount": { "kind": "measured", "value": 1, "unit": "кг" }, "quantity": 5, "unit": "г" }), Ok(measured(1.0, "кг"))),
+            (json!({ "name": "Соль" }), Err("Ingredient 'Соль': quantity must be greater than zero")),
+            (json!({ "name": "Сахар", "quantity": -1, "unit": "г" }), Err("Ingredient 'Сахар': quantity must not be negative")),
+            (json!({ "name": "Мука", "amount": { "kind": "count", "value": -2 } }), Err("quantity must not be negative")),
+            (json!({ "name": "Масло", "quantity": 2, "unit": "ст. л." }), Err("Ingredient 'Масло': unknown unit 'ст. л.'; accepted units: г, кг")),
+            (json!({ "name": "Масло", "amount": { "kind": "measured", "value": 2, "unit": "стакан" } }), Err("unknown unit 'стакан'")),
+        ];
+
+        for (body, expected) in cases {
+            match (parsed(body.clone()), expected) {
+                (Ok(amount), Ok(expected)) => assert_eq!(amount, expected, "{}", body),
+                (Err(message), Err(expected)) => assert!(message.contains(expected), "{}: {}", body, message),
+                (amount, expected) => panic!("{}: got {:?}, expected {:?}", body, amount, expected),
+            }
+        }
+
+        let unknown_kind: Result<CreateRecipeIngredientRequest, _> =
+            serde_json::from_value(json!({ "name": "Мука", "amount": { "kind": "pinch" } }));
+        assert!(unknown_kind.is_err());
+    }
+
+    #[test]
+    fn ingredient_output_has_structured_and_legacy_fields() {
+        let response = RecipeIngredientResponse::new("Яйца".to_string(), IngredientQuantity::Count { value: 2.0 }, None);
+        let body = serde_json::to_value(&response).unwrap();
+        assert_eq!(body["amount"], json!({ "kind": "count", "value": 2.0 }));
+        assert_eq!((body["quantity"].as_f64(), body["unit"].as_str()), (Some(2.0), Some("шт")));
+        assert!(body.get("needs_review").is_none());
+
+        let to_taste = serde_json::to_value(RecipeIngredientResponse::new("Соль".to_string(), IngredientQuantity::ToTaste, None)).unwrap();
+        assert_eq!((to_taste["amount"]["kind"].as_str(), to_taste["quantity"].as_f64(), to_taste["unit"].as_str()), (Some("to_taste"), Some(0.0), Some("по вкусу")));
+
+        let row = RecipeIngredient {
+            id: Uuid::new_v4(),
+            recipe_id: Uuid::nil(),
+            name: "Мука".to_string(),
+            quantity: 2.0,
+            unit: "стакана".to_string(),
+            notes: None,
+            needs_review: true,
+        };
+        let body = serde_json::to_value(RecipeIngredientResponse::from_row(row)).unwrap();
+        assert!(body["amount"].is_null());
+        assert_eq!((body["quantity"].as_f64(), body["unit"].as_str(), body["needs_review"].as_bool()), (Some(2.0), Some("стакана"), Some(true)));
+    }
+
+    #[sqlx::test]
+    async fn migration_parser_agrees_with_the_rust_parser(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let recipe_id = insert_recipe(&pool, user_id, "Блины", true).await;
+
+        // Строки в устаревшем виде, как до миграции
+        let rows: [(f32, &str); 14] = [
+            (200.0, "г"),
+            (200.0, "гр."),
+            (1.0, "kg"),
+            (0.5, "литра"),
+            (2.0, ""),
+            (3.0, "штуки"),
+            (3.0, "PCS"),
+            (0.0, "по вкусу"),
+            (1.0, "To Taste"),
+            (0.0, "г"),
+            (-1.0, "мл"),
+            (2.0, "стакана"),
+            (1.0, "ст. л."),
+            (0.0, ""),
+        ];
+        for (quantity, unit) in rows {
+            sqlx::query("INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit) VALUES ($1, $2, $3, $4)")
+                .bind(recipe_id)
+                .bind(format!("{} {}", quantity, unit))
+                .bind(quantity)
+                .bind(unit)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // Миграция повторяема: прогоняем ее еще раз поверх новых строк
+        sqlx::Executor::execute(&pool, include_str!("../../migrations/027_recipe_ingredient_quantities.sql")).await.unwrap();
+
+        let migrated: Vec<RecipeIngredient> = sqlx::query_as("SELECT * FROM recipe_ingredients WHERE recipe_id = $1")
+            .bind(recipe_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let by_name = |name: &str| migrated.iter().find(|row| row.name == name).unwrap();
+
+        for (quantity, unit) in rows {
+            let row = by_name(&format!("{} {}", quantity, unit));
+            let rust = IngredientQuantity::from_legacy(quantity, unit);
+            assert_eq!(row.needs_review, rust.is_err(), "{} {}", quantity, unit);
+            // Разобранная строка хранится в каноническом виде: тот же результат при повторном разборе
+            if let Ok(amount) = rust {
+                assert_eq!(row.amount(), Some(amount.clone()), "{} {}", quantity, unit);
+            }
+        }
+
+        assert_eq!((by_name("2 ").quantity, by_name("2 ").unit.as_str()), (2.0, "шт"));
+        assert_eq!((by_name("1 To Taste").quantity, by_name("1 To Taste").unit.as_str()), (0.0, "по вкусу"));
+        let flagged: Vec<&str> = migrated.iter().filter(|row| row.needs_review).map(|row| row.name.as_str()).collect();
+        assert_eq!(flagged.len(), 5);
+    }
+}
+
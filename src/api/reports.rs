@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Extension, Json, Query},
+    response::{Html, Json as ResponseJson},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+
+use crate::{
+    db::DbPool,
+    models::fridge::ReportPreferences,
+    services::{
+        auth::Claims,
+        clock::SharedClock,
+        monthly_reports::{parse_month, MonthlyReportService},
+        preferences::PreferencesService,
+    },
+    utils::errors::AppError,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/monthly", get(get_monthly_report))
+        .route("/settings", get(get_report_settings).put(update_report_settings))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MonthlyReportQuery {
+    /// Месяц в формате YYYY-MM
+    pub month: String,
+}
+
+/// Сохраненный отчет за месяц — тот же HTML, что был отправлен по почте
+pub async fn get_monthly_report(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+    Query(query): Query<MonthlyReportQuery>,
+) -> Result<Html<String>, AppError> {
+    let month = parse_month(&query.month)?;
+    let report = MonthlyReportService::new(pool).get_report(claims.sub, month).await?;
+
+    Ok(Html(report.html))
+}
+
+pub async fn get_report_settings(
+    Extension(pool): Extension<DbPool>,
+    claims: Claims,
+) -> Result<ResponseJson<ReportPreferences>, AppError> {
+    let preferences = PreferencesService::new(pool).for_user(claims.sub).await?;
+    Ok(ResponseJson(preferences.reports))
+}
+
+pub async fn update_report_settings(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    Json(payload): Json<ReportPreferences>,
+) -> Result<ResponseJson<ReportPreferences>, AppError> {
+    let saved = PreferencesService::new(pool)
+        .with_clock(clock)
+        .update(claims.sub, |settings| settings.reports = payload)
+        .await?;
+
+    Ok(ResponseJson(saved.reports))
+}
@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Extension, Query},
+    response::Json as ResponseJson,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    db::DbPool,
+    models::timeline::{TimelineEventType, TimelinePage},
+    services::{
+        auth::Claims,
+        clock::SharedClock,
+        preferences::UserLocale,
+        timeline::{TimelineCursor, TimelineQuery, TimelineService},
+    },
+    utils::errors::AppError,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(get_timeline))
+}
+
+/// `types` — типы событий через запятую; `cursor` — `next_cursor` предыдущей страницы
+#[derive(Debug, Deserialize)]
+pub struct TimelineQueryParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub types: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Лента активности: свои события из всех модулей, новые первыми
+pub async fn get_timeline(
+    Extension(pool): Extension<DbPool>,
+    Extension(clock): Extension<SharedClock>,
+    claims: Claims,
+    UserLocale(locale): UserLocale,
+    Query(params): Query<TimelineQueryParams>,
+) -> Result<ResponseJson<TimelinePage>, AppError> {
+    let query = TimelineQuery {
+        from: params.from,
+        to: params.to,
+        types: parse_types(params.types.as_deref())?,
+        cursor: params.cursor.as_deref().map(TimelineCursor::decode).transpose()?,
+        limit: params.limit.unwrap_or(50).clamp(1, 200),
+    };
+
+    let page = TimelineService::new(pool)
+        .with_clock(clock)
+        .events(claims.sub, &query, locale)
+        .await?;
+
+    Ok(ResponseJson(page))
+}
+
+/// Список типов через запятую; неизвестный тип — ошибка со списком допустимых
+fn parse_types(value: Option<&str>) -> Result<Vec<TimelineEventType>, AppError> {
+    let mut types = Vec::new();
+    for name in value.unwrap_or("").split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let event_type = TimelineEventType::parse(name).ok_or_else(|| {
+            let known: Vec<&str> = TimelineEventType::ALL.iter().map(|kind| kind.as_str()).collect();
+            AppError::BadRequest(format!("Unknown event type '{}', expected one of: {}", name, known.join(", ")))
+        })?;
+        if !types.contains(&event_type) {
+            types.push(event_type);
+        }
+    }
+    Ok(types)
+}
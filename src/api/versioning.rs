@@ -0,0 +1,246 @@
+use axum::{
+    extract::{FromRequestParts, OriginalUri},
+    http::request::Parts,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::errors::AppError;
+
+/// Переопределение версии из пути: `X-API-Version: 2` на `/api/v1/...` отвечает как v2
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Размер страницы по умолчанию и наибольший для списков v2
+const DEFAULT_PAGE_LIMIT: usize = 50;
+const MAX_PAGE_LIMIT: usize = 200;
+
+/// Версия API запроса. Обработчик, подключенный к нескольким версиям, один;
+/// различается только преобразование ответа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+/// Когда версия объявлена устаревшей и когда перестанет работать
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Deprecation {
+    pub deprecated_since: NaiveDate,
+    pub sunset: NaiveDate,
+    pub successor: ApiVersion,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    /// Префикс маршрутов версии: `/api/v1`
+    pub fn prefix(&self) -> String {
+        format!("/api/{}", self.as_str())
+    }
+
+    /// "v2", "V2" или просто "2"
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let number = value.strip_prefix(['v', 'V']).unwrap_or(value);
+        Self::ALL.into_iter().find(|version| &version.as_str()[1..] == number)
+    }
+
+    /// Версия по префиксу пути `/api/v{N}/...`
+    pub fn from_path(path: &str) -> Option<Self> {
+        let segment = path.strip_prefix("/api/")?.split('/').next()?;
+        Self::ALL.into_iter().find(|version| version.as_str() == segment)
+    }
+
+    /// Заголовки Deprecation и Sunset ставятся на эндпоинты этой версии, которые меняются в следующей
+    pub fn deprecation(&self) -> Option<Deprecation> {
+        match self {
+            ApiVersion::V1 => Some(Deprecation {
+                deprecated_since: NaiveDate::from_ymd_opt(2026, 11, 1).expect("valid date"),
+                sunset: NaiveDate::from_ymd_opt(2027, 5, 1).expect("valid date"),
+                successor: ApiVersion::V2,
+            }),
+            ApiVersion::V2 => None,
+        }
+    }
+}
+
+/// Версия из заголовка `X-API-Version`, иначе из префикса пути; без того и другого — v1
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ApiVersion
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(value) = parts.headers.get(API_VERSION_HEADER) {
+            let value = value.to_str().unwrap_or_default();
+            return ApiVersion::parse(value)
+                .ok_or_else(|| AppError::BadRequest(format!("Unsupported API version: {}", value)));
+        }
+
+        // Внутри nest путь укорочен — версию видно только в исходном URI
+        let path = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map(|OriginalUri(uri)| uri.path())
+            .unwrap_or_else(|| parts.uri.path());
+
+        Ok(ApiVersion::from_path(path).unwrap_or(ApiVersion::V1))
+    }
+}
+
+/// `?limit=&offset=` для списков v2
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageInfo {
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+    pub has_more: bool,
+}
+
+/// Конверт списков v2: элементы страницы и сведения о странице
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub pagination: PageInfo,
+}
+
+impl<T> Paginated<T> {
+    /// Страница из полного списка
+    pub fn from_all(items: Vec<T>, params: &PageParams) -> Self {
+        let total = items.len();
+        let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+        let offset = params.offset.unwrap_or(0);
+        let items: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+
+        Self {
+            pagination: PageInfo {
+                total,
+                limit,
+                offset,
+                has_more: offset + items.len() < total,
+            },
+            items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, HeaderMap, Method, Request, StatusCode},
+        Router,
+    };
+    use chrono::{Duration, Utc};
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_fridge_item, insert_user, request, test_router};
+
+    const DEPRECATION_HEADERS: [&str; 3] = ["deprecation", "sunset", "link"];
+
+    async fn fetch(router: &Router, request: Request<Body>) -> (StatusCode, HeaderMap, serde_json::Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, headers, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+    }
+
+    fn with_version(mut request: Request<Body>, version: &str) -> Request<Body> {
+        request.headers_mut().insert(API_VERSION_HEADER, version.parse().unwrap());
+        request
+    }
+
+    fn assert_v1_list(headers: &HeaderMap, body: &serde_json::Value, len: usize) {
+        assert_eq!(body.as_array().map(Vec::len), Some(len), "{}", body);
+        assert_eq!(headers["deprecation"], "@1793491200");
+        assert_eq!(headers["sunset"], "Sat, 01 May 2027 00:00:00 GMT");
+        assert_eq!(headers[header::LINK], "</api/v2>; rel=\"successor-version\"");
+    }
+
+    fn assert_v2_page(headers: &HeaderMap, body: &serde_json::Value, items: usize, total: usize) {
+        assert_eq!(body["items"].as_array().map(Vec::len), Some(items), "{}", body);
+        assert_eq!(body["pagination"]["total"], total);
+        for name in DEPRECATION_HEADERS {
+            assert!(!headers.contains_key(name), "{} on v2", name);
+        }
+    }
+
+    #[test]
+    fn versions_parse_from_headers_and_paths() {
+        assert_eq!(ApiVersion::parse("2"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse(" V1 "), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse("v3"), None);
+        assert_eq!(ApiVersion::from_path("/api/v2/fridge"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::from_path("/api/v10/fridge"), None);
+        assert_eq!(ApiVersion::from_path("/health"), None);
+        assert!(ApiVersion::V2.deprecation().is_none());
+    }
+
+    #[test]
+    fn pages_are_clamped_and_report_what_is_left() {
+        let page = |limit, offset| Paginated::from_all((0..5).collect::<Vec<i32>>(), &PageParams { limit, offset });
+
+        let first = page(Some(2), None);
+        assert_eq!(first.items, vec![0, 1]);
+        assert!(first.pagination.has_more);
+        let last = page(Some(2), Some(4));
+        assert_eq!(last.items, vec![4]);
+        assert!(!last.pagination.has_more);
+        assert_eq!(page(Some(0), None).pagination.limit, 1);
+        assert_eq!(page(Some(10_000), None).pagination.limit, MAX_PAGE_LIMIT);
+        assert!(page(None, Some(10)).items.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn v1_and_v2_keep_their_shapes_and_only_v1_is_deprecated(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        for name in ["Молоко", "Сыр", "Кефир"] {
+            insert_fridge_item(&pool, user_id, name, None, Utc::now() - Duration::days(1)).await;
+        }
+
+        let (status, headers, body) = fetch(&router, request(Method::GET, "/api/v1/fridge", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_v1_list(&headers, &body, 3);
+
+        let (status, headers, body) = fetch(&router, request(Method::GET, "/api/v2/fridge?limit=2", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_v2_page(&headers, &body, 2, 3);
+        assert_eq!(body["pagination"]["has_more"], true);
+
+        // Заголовок X-API-Version важнее пути — и для формы ответа, и для заголовков устаревания
+        let (_, headers, body) = fetch(&router, with_version(request(Method::GET, "/api/v1/fridge", Some(&token), None), "2")).await;
+        assert_v2_page(&headers, &body, 3, 3);
+        let (_, headers, body) = fetch(&router, with_version(request(Method::GET, "/api/v2/fridge", Some(&token), None), "v1")).await;
+        assert_v1_list(&headers, &body, 3);
+        let (status, _, body) = fetch(&router, with_version(request(Method::GET, "/api/v1/fridge", Some(&token), None), "7")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+        // Эндпоинты v1, которые в v2 не меняются, не помечаются устаревшими
+        let (status, headers, _) = fetch(&router, request(Method::GET, "/api/v1/fridge/stats", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        for name in DEPRECATION_HEADERS {
+            assert!(!headers.contains_key(name), "{} on unchanged endpoint", name);
+        }
+    }
+}
@@ -7,31 +7,42 @@ use axum::{
 };
 use serde::Serialize;
 
-use crate::services::{
-    auth::Claims,
-    realtime::{WebSocketManager, handle_websocket, RealtimeService},
+use crate::{
+    services::{
+        auth::Claims,
+        preferences::UserLocale,
+        realtime::{WebSocketManager, handle_websocket, RealtimeService},
+        realtime_schema::{self, SchemaCatalog},
+    },
+    utils::errors::AppError,
 };
 
 pub fn routes() -> Router {
     Router::new()
         .route("/ws", get(websocket_handler))
         .route("/stats", get(get_realtime_stats))
+        .route("/schema", get(get_event_schema))
 }
 
-/// WebSocket endpoint для подключения клиентов
+/// WebSocket endpoint для подключения клиентов.
+/// Регистрация до upgrade, чтобы при глобальном лимите сразу ответить 503.
 async fn websocket_handler(
     ws: WebSocketUpgrade,
     claims: Claims,
+    UserLocale(locale): UserLocale,
     Extension(ws_manager): Extension<Arc<WebSocketManager>>,
-) -> Response {
-    ws.on_upgrade(move |socket| handle_websocket(socket, claims, ws_manager))
+) -> Result<Response, AppError> {
+    let user_name = format!("{} {}", claims.first_name, claims.last_name);
+    let registration = ws_manager.add_client(claims.sub, claims.sid, user_name, locale).await?;
+
+    Ok(ws.on_upgrade(move |socket| handle_websocket(socket, claims, registration, ws_manager)))
 }
 
 /// Получение статистики WebSocket подключений
 async fn get_realtime_stats(
     _claims: Claims,
     Extension(realtime_service): Extension<Arc<RealtimeService>>,
-) -> Result<axum::Json<RealtimeStatsResponse>, crate::utils::errors::AppError> {
+) -> Result<axum::Json<RealtimeStatsResponse>, AppError> {
     let stats = realtime_service.get_stats().await;
     
     Ok(axum::Json(RealtimeStatsResponse {
@@ -41,6 +52,11 @@ async fn get_realtime_stats(
     }))
 }
 
+/// JSON Schema всех событий и сообщений клиента для текущей и предыдущей версии
+async fn get_event_schema(_claims: Claims) -> axum::Json<SchemaCatalog> {
+    axum::Json(realtime_schema::catalog())
+}
+
 #[derive(Serialize)]
 struct RealtimeStatsResponse {
     connected_clients: usize,
@@ -0,0 +1,938 @@
+use axum::{
+    extract::Extension,
+    http::{header, Method, HeaderValue, HeaderName},
+    routing::get,
+    Json, Router,
+    middleware as axum_middleware,
+};
+use std::{sync::Arc, time::Duration};
+use tower_http::{compression::CompressionLayer, cors::{Any, CorsLayer}, trace::TraceLayer};
+use tracing::{info, instrument};
+
+use crate::{
+    api::{self, versioning::{ApiVersion, API_VERSION_HEADER}},
+    config::Config,
+    db::{DbPool, DbPools},
+    middleware::{self, AuthState},
+    models::api_token::TokenScope,
+    telemetry,
+    utils::rate_limit::IpRateLimiter,
+    services::{
+        ai::AiService,
+        ai_costs::AiCostTracker,
+        clock::{self, SandboxClock, SharedClock},
+        experiments::Experiments,
+        feature_flags::FeatureFlags,
+        realtime::{ConnectionLimits, RealtimeBuffers, WebSocketManager, RealtimeService},
+        post_views::PostViewAggregator,
+        link_previews::LinkPreviewCache,
+        task_supervisor::TaskSupervisor,
+    },
+};
+
+/// Общее состояние приложения, которое получают все группы маршрутов
+#[derive(Clone)]
+pub struct AppState {
+    /// Основной пул (записи); то же, что `db_pools.primary()`
+    pub db_pool: DbPool,
+    /// Основной пул и реплика — для сервисов, которые умеют читать с реплики
+    pub db_pools: DbPools,
+    pub config: Config,
+    pub ws_manager: Arc<WebSocketManager>,
+    pub realtime_service: Arc<RealtimeService>,
+    pub feature_flags: FeatureFlags,
+    /// A/B-эксперименты: варианты пользователей и записи показов
+    pub experiments: Experiments,
+    pub ai_service: AiService,
+    pub post_views: PostViewAggregator,
+    pub link_previews: LinkPreviewCache,
+    /// Фоновые задачи под надзором: перезапуск после сбоев, состояние для /health
+    pub tasks: TaskSupervisor,
+    /// Текущее время для сервисов; в песочнице — `sandbox_clock`
+    pub clock: SharedClock,
+    /// Управляемые часы, если включен SANDBOX_CLOCK
+    pub sandbox_clock: Option<Arc<SandboxClock>>,
+}
+
+impl AppState {
+    pub fn new(config: Config, db_pools: DbPools) -> Self {
+        let db_pool = db_pools.primary().clone();
+        let sandbox_clock = config.sandbox_clock.then(|| Arc::new(SandboxClock::new()));
+        let clock: SharedClock = match &sandbox_clock {
+            Some(sandbox_clock) => {
+                info!("⏰ Sandbox clock enabled: time can be shifted via the admin API");
+                sandbox_clock.clone()
+            }
+            None => clock::system(),
+        };
+        let ws_manager = Arc::new(WebSocketManager::with_limits(ConnectionLimits {
+            max_per_user: config.ws_max_connections_per_user,
+            max_global: config.ws_max_connections_global,
+        }).with_buffers(RealtimeBuffers {
+            global: config.ws_global_buffer,
+            channel: config.ws_channel_buffer,
+            outgoing_queue: config.ws_outgoing_queue,
+        }).with_clock(clock.clone()));
+        let realtime_service = Arc::new(RealtimeService::new(ws_manager.clone()));
+        let feature_flags = FeatureFlags::new(db_pool.clone());
+        let experiments = Experiments::new(db_pool.clone()).with_clock(clock.clone());
+        let post_views = PostViewAggregator::new(db_pool.clone()).with_clock(clock.clone());
+        let ai_service = AiService::from_env(&config)
+            .with_cost_tracker(AiCostTracker::new(db_pool.clone(), config.ai_costs.clone()))
+            .with_clock(clock.clone());
+        info!(
+            "🤖 AI provider: {} ({}), capabilities: [{}]",
+            ai_service.provider_name(),
+            ai_service.model_name(),
+            ai_service.capabilities().names().join(", ")
+        );
+
+        Self {
+            db_pool,
+            db_pools,
+            config,
+            ws_manager,
+            realtime_service,
+            feature_flags,
+            experiments,
+            ai_service,
+            post_views,
+            link_previews: LinkPreviewCache::default(),
+            tasks: TaskSupervisor::new(),
+            clock,
+            sandbox_clock,
+        }
+    }
+}
+
+/// Собирает весь роутер приложения: публичные и защищенные группы, CORS и общие Extension-слои
+pub fn build_router(state: AppState) -> Router {
+    let auth = || axum_middleware::from_fn_with_state(AuthState::jwt_only(state.db_pool.clone()), middleware::auth_middleware);
+    let optional_auth = || axum_middleware::from_fn_with_state(AuthState::optional(state.db_pool.clone()), middleware::auth_middleware);
+    // Группы, доступные и по персональным токенам (pat_...) с соответствующими скоупами
+    let auth_scoped = |read, write| {
+        axum_middleware::from_fn_with_state(
+            AuthState::with_token_scopes(state.db_pool.clone(), read, write),
+            middleware::auth_middleware,
+        )
+    };
+
+    Router::new()
+        .route("/health", get(health_check))
+        // Публичные роуты аутентификации (не требуют токена)
+        .nest("/api/v1/auth", api::auth::routes())
+        // Справочник кодов ошибок для фронтенда
+        .nest("/api/v1/meta", api::meta::routes())
+        // Список версий API — вне версий, чтобы его мог прочитать клиент любой версии
+        .route("/api/meta/versions", get(api::meta::get_versions))
+        // Защищенные роуты аутентификации (требуют токена)
+        .nest("/api/v1/auth", api::auth::protected_routes().layer(auth()))
+        // Остальные защищенные роуты (требуют токена)
+        .nest("/api/v1/diary", api::diary::routes()
+            .layer(auth_scoped(TokenScope::ReadDiary, TokenScope::WriteDiary)))
+        // Холодильник отвечает в v1 и v2: обработчики общие, формат ответа выбирается по `ApiVersion`.
+        // Справочники (/presets/*, /autocomplete) подключаются после слоя авторизации и открыты всем
+        .merge(nest_versions("/fridge", &ApiVersion::ALL, api::fridge::routes()
+            .layer(auth_scoped(TokenScope::ReadFridge, TokenScope::WriteFridge))
+            .merge(api::fridge::public_routes())))
+        .nest("/api/v1/recipes", api::recipes::routes()
+            .layer(auth_scoped(TokenScope::ReadRecipes, TokenScope::WriteRecipes)))
+        .nest("/api/v1/goals", api::goals::routes()
+            .layer(auth_scoped(TokenScope::ReadGoals, TokenScope::WriteGoals)))
+        // Карточка цели доступна по публичному токену и без входа
+        .nest("/api/v1/goals", api::goals::public_routes().layer(optional_auth()))
+        .nest("/api/v1/home", api::home::routes().layer(auth()))
+        .nest("/api/v1/community", api::community::routes().layer(auth()))
+        // Просмотры постов учитываются и для анонимных зрителей
+        .nest("/api/v1/community", api::community::public_routes().layer(optional_auth()))
+        // Лента для гостей (лендинг): без авторизации, с лимитом по IP
+        .nest("/api/v1/public/community", api::community::anonymous_routes()
+            .layer(axum_middleware::from_fn_with_state(
+                IpRateLimiter::per_minute(state.config.public_feed_rate_limit_per_minute),
+                middleware::ip_rate_limit_middleware,
+            )))
+        .nest("/api/v1/coaching", api::coaching::routes().layer(auth()))
+        .nest("/api/v1/features", api::features::routes().layer(auth()))
+        .nest("/api/v1/reports", api::reports::routes().layer(auth()))
+        .nest("/api/v1/notifications", api::notifications::routes().layer(auth()))
+        .nest("/api/v1/preferences", api::preferences::routes().layer(auth()))
+        .nest("/api/v1/timeline", api::timeline::routes().layer(auth()))
+        // Админские роуты: сначала auth_middleware, затем проверка роли
+        .nest("/api/v1/admin", api::admin::routes()
+            .layer(axum_middleware::from_fn(middleware::admin_middleware))
+            .layer(auth()))
+        .nest("/api/v1/realtime", api::websocket::routes().layer(auth()))
+        .nest("/api/v1/ai", api::ai::routes(&state).layer(auth()))
+        .nest("/api/v1/health", api::personal_health::routes(&state).layer(auth()))
+        // CORS фронтенда — на все маршруты выше; слой не действует на маршруты, добавленные после него
+        .layer(cors_layer())
+        // Виджеты рецептов на чужих сайтах: свой открытый CORS, лимит на публичный токен
+        .nest("/api/v1/public/recipes", api::recipes::embed_routes()
+            .layer(Extension(api::recipes::EmbedRateLimiter(
+                IpRateLimiter::per_minute(state.config.embed_rate_limit_per_minute),
+            )))
+            .layer(embed_cors_layer()))
+        // gzip/br по Accept-Encoding; маленькие ответы и 304 не сжимаются
+        .layer(CompressionLayer::new())
+        .layer(Extension(state.db_pool))
+        .layer(Extension(state.db_pools))
+        .layer(Extension(state.config))
+        .layer(Extension(state.ws_manager))
+        .layer(Extension(state.realtime_service))
+        .layer(Extension(state.feature_flags))
+        .layer(Extension(state.experiments))
+        .layer(Extension(state.ai_service))
+        .layer(Extension(state.post_views))
+        .layer(Extension(state.link_previews))
+        .layer(Extension(state.tasks))
+        .layer(Extension(state.clock))
+        .layer(Extension(state.sandbox_clock))
+        // Span на каждый запрос — корень трассы с запросами к БД и вызовами ИИ
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::request_span)
+                .on_response(telemetry::record_response)
+                // Ошибки уже пишет AppError; статус ответа попадает в span
+                .on_failure(()),
+        )
+}
+
+/// Одна группа маршрутов под несколькими версиями: `/api/v1{path}`, `/api/v2{path}`, ...
+fn nest_versions(path: &str, versions: &[ApiVersion], routes: Router) -> Router {
+    versions.iter().fold(Router::new(), |router, version| {
+        router.nest(&format!("{}{}", version.prefix(), path), routes.clone())
+    })
+}
+
+/// Источники фронтенда, которым разрешены запросы; проверяются при запуске (diagnostics)
+pub const CORS_ORIGINS: [&str; 4] = [
+    "http://localhost:3000",
+    "http://localhost:3001",
+    "https://ai-cook-frontend.vercel.app",
+    "https://ai-cook-frontend-git-main-fodis-projects-dcba8b75.vercel.app",
+];
+
+fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(CORS_ORIGINS.map(|origin| origin.parse::<HeaderValue>().unwrap()))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            HeaderName::from_static("authorization"),
+            HeaderName::from_static("content-type"),
+            HeaderName::from_static("x-requested-with"),
+            header::IF_NONE_MATCH,
+            HeaderName::from_static(crate::services::idempotency::IDEMPOTENCY_KEY_HEADER),
+            HeaderName::from_static(crate::services::mock_ai::MOCK_SCENARIO_HEADER),
+            HeaderName::from_static(API_VERSION_HEADER),
+        ])
+        // ETag — для If-None-Match; остальные — предупреждения об устаревшей версии API
+        .expose_headers([
+            header::ETAG,
+            HeaderName::from_static("deprecation"),
+            HeaderName::from_static("sunset"),
+            header::LINK,
+        ])
+        .allow_credentials(true)
+}
+
+/// CORS встраиваемых маршрутов: любой источник, только чтение, без cookie и авторизации.
+/// Подключается только к `/api/v1/public/recipes`, не к API с авторизацией.
+fn embed_cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET])
+        .max_age(Duration::from_secs(86400))
+}
+
+/// Всегда 200, пока процесс отвечает; сломанные фоновые задачи переводят статус в `degraded`
+#[instrument(skip_all)]
+async fn health_check(Extension(tasks): Extension<TaskSupervisor>) -> Json<serde_json::Value> {
+    let status = tasks.status();
+    let failing: Vec<&str> = status.tasks.iter().filter(|task| task.degraded).map(|task| task.name).collect();
+
+    Json(serde_json::json!({
+        "status": if status.degraded { "degraded" } else { "ok" },
+        "message": "IT Cook Backend is running! 🍽️",
+        "failing_tasks": failing,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, Response, StatusCode},
+        Router,
+    };
+    use chrono::{Duration, Utc};
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+    use tower_http::decompression::Decompression;
+    use uuid::Uuid;
+
+    use axum::http::Method;
+
+    use crate::models::api_token::{CreateApiToken, TokenScope};
+    use crate::services::api_tokens::ApiTokenService;
+    use crate::test_support::{
+        access_token, insert_diary_entry, insert_fridge_item, insert_user, request, send, test_router,
+    };
+
+    /// По одному маршруту без параметров пути из каждой защищенной группы
+    const PROTECTED: [(&str, &str); 19] = [
+        ("GET", "/api/v1/auth/me"),
+        ("GET", "/api/v1/diary"),
+        ("GET", "/api/v1/fridge"),
+        ("GET", "/api/v2/fridge"),
+        ("GET", "/api/v1/recipes"),
+        ("GET", "/api/v1/goals"),
+        ("GET", "/api/v1/home/summary"),
+        ("GET", "/api/v1/community/posts"),
+        ("GET", "/api/v1/coaching/links"),
+        ("GET", "/api/v1/features"),
+        ("GET", "/api/v1/reports/settings"),
+        ("GET", "/api/v1/notifications/preferences"),
+        ("GET", "/api/v1/preferences"),
+        ("GET", "/api/v1/timeline"),
+        ("GET", "/api/v1/realtime/schema"),
+        ("POST", "/api/v1/ai/proactive-message"),
+        ("GET", "/api/v1/health/dashboard"),
+        ("GET", "/api/v1/admin/tasks"),
+        ("GET", "/api/v1/admin/features"),
+    ];
+
+    fn get(uri: &str, token: Option<&str>, if_none_match: Option<&str>) -> Request<Body> {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        if let Some(etag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn etag_of<B>(response: &Response<B>) -> String {
+        response.headers().get(header::ETAG).expect("ETag header").to_str().unwrap().to_string()
+    }
+
+    async fn body_bytes<B: axum::body::HttpBody>(response: Response<B>) -> Vec<u8>
+    where
+        B::Error: std::fmt::Debug,
+    {
+        hyper::body::to_bytes(response.into_body()).await.unwrap().to_vec()
+    }
+
+    /// Первый запрос — 200 с ETag, повтор с If-None-Match — 304 без тела; возвращает ETag
+    async fn assert_revalidates(router: &Router, uri: &str, token: Option<&str>) -> String {
+        let response = router.clone().oneshot(get(uri, token, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let etag = etag_of(&response);
+
+        let response = router.clone().oneshot(get(uri, token, Some(&etag))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", uri);
+        assert_eq!(etag_of(&response), etag);
+        assert!(body_bytes(response).await.is_empty());
+        etag
+    }
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid, content: &str) {
+        sqlx::query("INSERT INTO posts (author_id, content, post_type) VALUES ($1, $2, 'text')")
+            .bind(author_id)
+            .bind(content)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn fridge_list_revalidates_and_changes_after_mutation(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, Utc::now() - Duration::days(1)).await;
+
+        for uri in ["/api/v1/fridge", "/api/v2/fridge"] {
+            assert_revalidates(&router, uri, Some(&token)).await;
+        }
+        let before = assert_revalidates(&router, "/api/v1/fridge", Some(&token)).await;
+
+        let create = Request::post("/api/v1/fridge")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name":"Кефир","quantity":1,"unit":"l","category":"Dairy"}"#))
+            .unwrap();
+        let response = router.clone().oneshot(create).await.unwrap();
+        let status = response.status();
+        assert!(status.is_success(), "{} {}", status, String::from_utf8_lossy(&body_bytes(response).await));
+
+        // Старая версия больше не подходит: полный ответ с новым ETag
+        let response = router.clone().oneshot(get("/api/v1/fridge", Some(&token), Some(&before))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(etag_of(&response), before);
+    }
+
+    #[sqlx::test]
+    async fn presets_are_mounted_without_auth_and_revalidate(pool: PgPool) {
+        let router = test_router(&pool);
+
+        for uri in [
+            "/api/v1/fridge/presets/allergens",
+            "/api/v1/fridge/presets/intolerances",
+            "/api/v1/fridge/presets/diets",
+            "/api/v1/fridge/presets/products",
+            "/api/v1/fridge/presets/products/search?name=%D0%BC%D0%BE%D0%BB%D0%BE%D0%BA%D0%BE",
+            "/api/v1/fridge/autocomplete",
+            "/api/v2/fridge/presets/allergens",
+        ] {
+            assert_revalidates(&router, uri, None).await;
+        }
+
+        // Остальной холодильник по-прежнему требует входа
+        let response = router.clone().oneshot(get("/api/v1/fridge", None, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    async fn diary_entries_revalidate_per_user_and_change_after_new_entry(pool: PgPool) {
+        let router = test_router(&pool);
+        let anna = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+        let (anna_token, boris_token) = (access_token(&pool, anna).await, access_token(&pool, boris).await);
+        insert_diary_entry(&pool, anna, "breakfast", 300.0, 10.0, Utc::now() - Duration::hours(2)).await;
+
+        let before = assert_revalidates(&router, "/api/v1/diary", Some(&anna_token)).await;
+        let response = router.clone().oneshot(get("/api/v1/diary", Some(&anna_token), None)).await.unwrap();
+        let entries: serde_json::Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+        assert_eq!(entries.as_array().unwrap().len(), 1);
+
+        // Пустой дневник другого пользователя — другой ETag, чужая версия не дает 304
+        let response = router.clone().oneshot(get("/api/v1/diary", Some(&boris_token), Some(&before))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(etag_of(&response), before);
+
+        insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, Utc::now() - Duration::hours(1)).await;
+        let response = router.clone().oneshot(get("/api/v1/diary", Some(&anna_token), Some(&before))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(etag_of(&response), before);
+    }
+
+    #[sqlx::test]
+    async fn community_feeds_revalidate_and_change_after_new_post(pool: PgPool) {
+        let router = test_router(&pool);
+        let author = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, author).await;
+        insert_post(&pool, author, "Первый пост").await;
+
+        let feed = assert_revalidates(&router, "/api/v1/community/posts", Some(&token)).await;
+        let public = assert_revalidates(&router, "/api/v1/public/community/feed", None).await;
+        let response = router.clone().oneshot(get("/api/v1/public/community/feed", None, Some(&public))).await.unwrap();
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "public, max-age=30");
+
+        insert_post(&pool, author, "Второй пост").await;
+        for (uri, token, etag) in [
+            ("/api/v1/community/posts", Some(token.as_str()), feed),
+            ("/api/v1/public/community/feed", None, public),
+        ] {
+            let response = router.clone().oneshot(get(uri, token, Some(&etag))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_ne!(etag_of(&response), etag, "{}", uri);
+        }
+    }
+
+    #[sqlx::test]
+    async fn large_fridge_list_round_trips_through_compression(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let notes = "Купить еще к выходным, хранить на верхней полке. ".repeat(8);
+        for index in 0..300 {
+            insert_fridge_item(&pool, user_id, &format!("Продукт {}", index), Some(&notes), Utc::now() - Duration::days(1)).await;
+        }
+
+        let plain = body_bytes(router.clone().oneshot(get("/api/v1/fridge", Some(&token), None)).await.unwrap()).await;
+        assert!(plain.len() > 100 * 1024, "response is only {} bytes", plain.len());
+
+        let mut request = get("/api/v1/fridge", Some(&token), None);
+        request.headers_mut().insert(header::ACCEPT_ENCODING, "gzip".parse().unwrap());
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert!(response.headers().contains_key(header::ETAG));
+        let compressed = body_bytes(response).await;
+        assert!(compressed.len() < plain.len() / 4, "{} of {} bytes", compressed.len(), plain.len());
+
+        // Клиент с распаковкой получает тот же JSON
+        let client = Decompression::new(router.clone());
+        let response = client.oneshot(get("/api/v1/fridge", Some(&token), None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let decoded = body_bytes(response).await;
+        let items: serde_json::Value = serde_json::from_slice(&decoded).unwrap();
+        assert_eq!(items.as_array().unwrap().len(), 300);
+        assert_eq!(decoded, plain);
+    }
+
+    #[sqlx::test]
+    async fn auth_layer_guards_protected_groups(pool: PgPool) {
+        let router = test_router(&pool);
+
+        for (method, uri) in PROTECTED {
+            let method: Method = method.parse().unwrap();
+            let (status, body) = send(&router, request(method.clone(), uri, None, None)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+            assert_eq!(body["error"]["code"], "ERR_UNAUTHORIZED", "{} {}", method, uri);
+
+            let (status, _) = send(&router, request(method.clone(), uri, Some("not-a-jwt"), None)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {} with a broken token", method, uri);
+        }
+    }
+
+    #[sqlx::test]
+    async fn valid_token_passes_the_auth_layer_and_admin_check(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let admin_id = insert_user(&pool, "Админ").await;
+        sqlx::query("UPDATE users SET role = 'admin' WHERE id = $1").bind(admin_id).execute(&pool).await.unwrap();
+        let admin_token = access_token(&pool, admin_id).await;
+
+        for (method, uri) in PROTECTED {
+            let method: Method = method.parse().unwrap();
+            let body = (method == Method::POST).then(|| serde_json::json!({}));
+            let (status, _) = send(&router, request(method.clone(), uri, Some(&token), body)).await;
+            if uri.starts_with("/api/v1/admin/") {
+                // Админская группа: auth_middleware пропускает, проверка роли — нет
+                assert_eq!(status, StatusCode::FORBIDDEN, "{} {}", method, uri);
+            } else {
+                assert_ne!(status, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+                assert_ne!(status, StatusCode::FORBIDDEN, "{} {}", method, uri);
+            }
+        }
+
+        for uri in ["/api/v1/admin/tasks", "/api/v1/admin/features"] {
+            let (status, _) = send(&router, request(Method::GET, uri, Some(&admin_token), None)).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+        }
+    }
+
+    #[sqlx::test]
+    async fn public_routes_skip_the_auth_layer(pool: PgPool) {
+        let router = test_router(&pool);
+
+        for uri in [
+            "/health",
+            "/api/meta/versions",
+            "/api/v1/meta/error-codes",
+            "/api/v1/fridge/presets/allergens",
+            "/api/v2/fridge/autocomplete",
+            "/api/v1/public/community/feed",
+        ] {
+            let (status, _) = send(&router, request(Method::GET, uri, None, None)).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+        }
+
+        // Публичные POST без тела доходят до обработчика: ошибка разбора тела, а не 401
+        for uri in ["/api/v1/auth/login", "/api/v1/auth/register"] {
+            let (status, _) = send(&router, request(Method::POST, uri, None, Some(serde_json::json!({})))).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+        }
+    }
+
+    async fn personal_token(pool: &PgPool, user_id: Uuid, scopes: Vec<TokenScope>) -> (Uuid, String) {
+        let (info, secret) = ApiTokenService::new(pool.clone())
+            .create_token(CreateApiToken { user_id, name: "Дашборд".to_string(), scopes, expires_at: None })
+            .await
+            .unwrap();
+        (info.id, secret)
+    }
+
+    fn new_fridge_item() -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "name": "Кефир", "quantity": 1, "unit": "l", "category": "Dairy" }))
+    }
+
+    #[sqlx::test]
+    async fn read_scoped_token_reads_fridge_but_cannot_write(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Макс").await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, Utc::now()).await;
+        let (_, reader) = personal_token(&pool, user_id, vec![TokenScope::ReadFridge]).await;
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&reader), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().map(Vec::len), Some(1));
+
+        let (status, _) = send(&router, request(Method::POST, "/api/v1/fridge", Some(&reader), new_fridge_item())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Чужой скоуп и группы, не принимающие токены
+        for uri in ["/api/v1/diary", "/api/v1/recipes", "/api/v1/home/summary", "/api/v1/auth/tokens"] {
+            let (status, _) = send(&router, request(Method::GET, uri, Some(&reader), None)).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        }
+
+        // Отказ не из-за тела запроса: с write:fridge тот же POST проходит
+        let (_, writer) = personal_token(&pool, user_id, vec![TokenScope::WriteFridge]).await;
+        let (status, _) = send(&router, request(Method::POST, "/api/v1/fridge", Some(&writer), new_fridge_item())).await;
+        assert!(status.is_success(), "{}", status);
+    }
+
+    #[sqlx::test]
+    async fn revoked_and_expired_tokens_get_401(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Макс").await;
+        let (token_id, secret) = personal_token(&pool, user_id, vec![TokenScope::ReadFridge]).await;
+
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&secret), None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        ApiTokenService::new(pool.clone()).revoke_token(user_id, token_id).await.unwrap();
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&secret), None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "ERR_UNAUTHORIZED");
+
+        let (expired_id, expired) = personal_token(&pool, user_id, vec![TokenScope::ReadFridge]).await;
+        sqlx::query("UPDATE api_tokens SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+            .bind(expired_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&expired), None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Похожая на токен, но неизвестная строка
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge", Some("pat_unknown"), None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    async fn using_a_token_updates_last_used_at(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Макс").await;
+        let (token_id, secret) = personal_token(&pool, user_id, vec![TokenScope::ReadFridge]).await;
+        let service = ApiTokenService::new(pool.clone());
+        let last_used = || async {
+            service.list_tokens(user_id).await.unwrap().into_iter().find(|token| token.id == token_id).unwrap().last_used_at
+        };
+
+        assert_eq!(last_used().await, None);
+
+        let started = Utc::now();
+        send(&router, request(Method::GET, "/api/v1/fridge", Some(&secret), None)).await;
+        let first = last_used().await.expect("set on first use");
+        assert!(first >= started - Duration::seconds(1));
+
+        // Отказ по скоупу — тоже использование токена
+        send(&router, request(Method::POST, "/api/v1/fridge", Some(&secret), new_fridge_item())).await;
+        let second = last_used().await.unwrap();
+        assert!(second >= first);
+
+        // Секрет хранится только в виде хеша
+        let (stored_hash,): (String,) = sqlx::query_as("SELECT token_hash FROM api_tokens WHERE id = $1")
+            .bind(token_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_ne!(stored_hash, secret);
+        assert_eq!(stored_hash, crate::services::api_tokens::hash_token(&secret));
+    }
+
+    /// Данные пользователя A, к которым аудит пробует добраться от имени пользователя B
+    struct Tenant {
+        token: String,
+        diary_entry: Uuid,
+        fridge_item: Uuid,
+        goal: Uuid,
+        waste: Uuid,
+        wellbeing: Uuid,
+        notification: Uuid,
+    }
+
+    impl Tenant {
+        async fn seed(pool: &PgPool, router: &Router) -> Self {
+            use crate::models::health::DailyWellbeing;
+            use crate::services::{notifications::NotificationService, wellbeing::WellbeingService};
+
+            let user_id = insert_user(pool, "Анна").await;
+            let token = access_token(pool, user_id).await;
+            let diary_entry = insert_diary_entry(pool, user_id, "breakfast", 300.0, 10.0, Utc::now() - Duration::hours(2)).await;
+            let fridge_item = insert_fridge_item(pool, user_id, "Молоко", None, Utc::now() - Duration::days(1)).await.id;
+
+            let goal = serde_json::json!({ "title": "Пробежать 100 км", "goal_type": "Exercise", "target_value": 100.0, "unit": "km" });
+            let (status, goal) = send(router, request(Method::POST, "/api/v1/goals", Some(&token), Some(goal))).await;
+            assert_eq!(status, StatusCode::OK, "{}", goal);
+
+            let waste = serde_json::json!({
+                "name": "Хлеб", "wasted_quantity": 1, "unit": "pcs", "category": "Grains", "waste_reason": "Spoiled"
+            });
+            let (status, waste) = send(router, request(Method::POST, "/api/v1/fridge/waste", Some(&token), Some(waste))).await;
+            assert_eq!(status, StatusCode::OK, "{}", waste);
+
+            let wellbeing = Uuid::new_v4();
+            WellbeingService::new(pool.clone())
+                .record(&DailyWellbeing {
+                    id: wellbeing,
+                    user_id,
+                    date: Utc::now() - Duration::hours(1),
+                    mood_score: Some(7),
+                    energy_level: Some(6),
+                    stress_level: None,
+                    sleep_hours: None,
+                    sleep_quality: None,
+                    water_intake_ml: None,
+                    exercise_minutes: None,
+                    notes: None,
+                    symptoms: Vec::new(),
+                    created_at: Utc::now(),
+                })
+                .await
+                .unwrap();
+            let notification = NotificationService::new(pool.clone())
+                .create(user_id, "goal_reminder", "Цель", "Осталось 10 км", serde_json::json!({}))
+                .await
+                .unwrap();
+
+            Self {
+                token,
+                diary_entry,
+                fridge_item,
+                goal: Uuid::parse_str(goal["id"].as_str().unwrap()).unwrap(),
+                waste: Uuid::parse_str(waste["id"].as_str().unwrap()).unwrap(),
+                wellbeing,
+                notification,
+            }
+        }
+
+        fn ids(&self) -> [Uuid; 6] {
+            [self.diary_entry, self.fridge_item, self.goal, self.waste, self.wellbeing, self.notification]
+        }
+
+        fn uri(&self, template: &str) -> String {
+            template
+                .replace("{diary}", &self.diary_entry.to_string())
+                .replace("{fridge}", &self.fridge_item.to_string())
+                .replace("{goal}", &self.goal.to_string())
+        }
+    }
+
+    /// Списки пользователя: в ответе другому пользователю не должно быть ни одного чужого id
+    const OWNED_LISTS: [&str; 11] = [
+        "/api/v1/diary",
+        "/api/v1/diary/photos",
+        "/api/v1/fridge",
+        "/api/v1/fridge/history",
+        "/api/v1/fridge/waste",
+        "/api/v1/fridge/expiring",
+        "/api/v1/fridge/stats",
+        "/api/v1/goals",
+        "/api/v1/home/summary",
+        "/api/v1/timeline",
+        "/api/v1/health/mood-insights",
+    ];
+
+    /// Чтение и изменение конкретной записи по id; чужая запись — 404 или 403
+    fn owned_records() -> Vec<(Method, &'static str, Option<serde_json::Value>)> {
+        let diary_entry = serde_json::json!({
+            "food_name": "Чужой завтрак", "portion_size": 100, "unit": "g",
+            "calories_per_100g": 100, "protein_per_100g": 1, "fat_per_100g": 1, "carbs_per_100g": 1
+        });
+        let fridge_item = serde_json::json!({ "name": "Чужое молоко", "quantity": 1, "unit": "l", "category": "Dairy" });
+        let goal = serde_json::json!({ "title": "Чужая цель", "goal_type": "Exercise", "target_value": 1.0, "unit": "km" });
+        vec![
+            (Method::GET, "/api/v1/diary/{diary}", None),
+            (Method::PUT, "/api/v1/diary/{diary}", Some(diary_entry)),
+            (Method::DELETE, "/api/v1/diary/{diary}", None),
+            (Method::GET, "/api/v1/fridge/{fridge}", None),
+            (Method::GET, "/api/v1/fridge/{fridge}/ideas", None),
+            (Method::GET, "/api/v1/fridge/{fridge}/reservations", None),
+            (Method::PUT, "/api/v1/fridge/{fridge}", Some(fridge_item)),
+            (Method::PATCH, "/api/v1/fridge/{fridge}", Some(serde_json::json!({ "quantity": 0.5 }))),
+            (Method::POST, "/api/v1/fridge/{fridge}/consume", Some(serde_json::json!({ "quantity": 0.1 }))),
+            (Method::DELETE, "/api/v1/fridge/{fridge}", None),
+            (Method::GET, "/api/v1/goals/{goal}", None),
+            (Method::PUT, "/api/v1/goals/{goal}", Some(goal)),
+            (Method::POST, "/api/v1/goals/{goal}/progress", Some(serde_json::json!({ "value": 99.0 }))),
+            (Method::POST, "/api/v1/goals/{goal}/share-card/token", None),
+            (Method::DELETE, "/api/v1/goals/{goal}", None),
+        ]
+    }
+
+    /// Снимок записей владельца: после попыток чужого пользователя он не должен измениться
+    async fn owner_snapshot(router: &Router, owner: &Tenant) -> Vec<serde_json::Value> {
+        let mut snapshot = Vec::new();
+        for (method, template, _) in owned_records() {
+            if method == Method::GET {
+                let (status, body) = send(router, request(Method::GET, &owner.uri(template), Some(&owner.token), None)).await;
+                assert_eq!(status, StatusCode::OK, "owner {} {}", template, body);
+                snapshot.push(body);
+            }
+        }
+        for uri in ["/api/v1/fridge/waste", "/api/v1/home/summary"] {
+            snapshot.push(send(router, request(Method::GET, uri, Some(&owner.token), None)).await.1);
+        }
+        snapshot
+    }
+
+    #[sqlx::test]
+    async fn other_users_never_see_owned_records_in_lists(pool: PgPool) {
+        let router = test_router(&pool);
+        let anna = Tenant::seed(&pool, &router).await;
+        let boris = insert_user(&pool, "Борис").await;
+        let boris_token = access_token(&pool, boris).await;
+
+        for uri in OWNED_LISTS {
+            let (status, body) = send(&router, request(Method::GET, uri, Some(&boris_token), None)).await;
+            assert_eq!(status, StatusCode::OK, "{} {}", uri, body);
+            let text = body.to_string();
+            for id in anna.ids() {
+                assert!(!text.contains(&id.to_string()), "{} leaks {}: {}", uri, id, text);
+            }
+        }
+
+        // Счетчики без id: уведомления и настроение тоже считаются только по своим записям
+        let (_, summary) = send(&router, request(Method::GET, "/api/v1/home/summary", Some(&boris_token), None)).await;
+        assert_eq!(summary["unread_notifications"], 0);
+        let (_, summary) = send(&router, request(Method::GET, "/api/v1/home/summary", Some(&anna.token), None)).await;
+        assert_eq!(summary["unread_notifications"], 1);
+
+        // Контроль: владельцу списки отдают его записи
+        let (_, timeline) = send(&router, request(Method::GET, "/api/v1/timeline", Some(&anna.token), None)).await;
+        assert!(timeline.to_string().contains(&anna.wellbeing.to_string()), "{}", timeline);
+        let (_, waste) = send(&router, request(Method::GET, "/api/v1/fridge/waste", Some(&anna.token), None)).await;
+        assert!(waste.to_string().contains(&anna.waste.to_string()), "{}", waste);
+    }
+
+    #[sqlx::test]
+    async fn other_users_cannot_read_or_change_owned_records(pool: PgPool) {
+        let router = test_router(&pool);
+        let anna = Tenant::seed(&pool, &router).await;
+        let boris = insert_user(&pool, "Борис").await;
+        let boris_token = access_token(&pool, boris).await;
+        let before = owner_snapshot(&router, &anna).await;
+
+        for (method, template, body) in owned_records() {
+            let uri = anna.uri(template);
+            let (status, response) = send(&router, request(method.clone(), &uri, Some(&boris_token), body)).await;
+            assert!(
+                status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN,
+                "{} {} answered {} {}",
+                method,
+                template,
+                status,
+                response
+            );
+        }
+
+        // Списание чужого продукта в отходы не трогает сам продукт
+        let waste = serde_json::json!({
+            "original_item_id": anna.fridge_item, "name": "Молоко", "wasted_quantity": 1, "unit": "l",
+            "category": "Dairy", "waste_reason": "Expired"
+        });
+        send(&router, request(Method::POST, "/api/v1/fridge/waste", Some(&boris_token), Some(waste))).await;
+
+        assert_eq!(owner_snapshot(&router, &anna).await, before);
+
+        // Те же изменения от владельца проходят: отказ был из-за чужой записи, а не из-за тела
+        for (method, template, body) in owned_records() {
+            if method == Method::PUT {
+                let (status, response) = send(&router, request(method, &anna.uri(template), Some(&anna.token), body)).await;
+                assert_eq!(status, StatusCode::OK, "owner PUT {} {}", template, response);
+            }
+        }
+    }
+
+    async fn shared_recipe(pool: &PgPool, router: &Router) -> (String, String, String) {
+        let author = insert_user(pool, "Анна").await;
+        let token = access_token(pool, author).await;
+        let recipe_id = crate::test_support::insert_recipe(pool, author, "Шарлотка", true).await;
+        let share_uri = format!("/api/v1/recipes/{}/share", recipe_id);
+        let (status, link) = send(router, request(Method::POST, &share_uri, Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK, "{}", link);
+        (link["token"].as_str().unwrap().to_string(), token, share_uri)
+    }
+
+    fn preflight(uri: &str, origin: &str, method: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(uri)
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn embeds_are_open_to_any_site_while_the_api_is_not(pool: PgPool) {
+        let router = test_router(&pool);
+        let (share_token, _, _) = shared_recipe(&pool, &router).await;
+        let embed_uri = format!("/api/v1/public/recipes/{}/embed.json", share_token);
+        let blog = "https://blog.example";
+
+        let response = router.clone().oneshot(preflight(&embed_uri, blog, "GET")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS], "GET");
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "86400");
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let mut embed = request(Method::GET, &embed_uri, None, None);
+        embed.headers_mut().insert(header::ORIGIN, blog.parse().unwrap());
+        let response = router.clone().oneshot(embed).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        // API с авторизацией чужому сайту ничего не разрешает, фронтенду — с cookie
+        let response = router.clone().oneshot(preflight("/api/v1/recipes", blog, "GET")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        let frontend = crate::app::CORS_ORIGINS[0];
+        let response = router.clone().oneshot(preflight("/api/v1/recipes", frontend, "POST")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], frontend);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[sqlx::test]
+    async fn oembed_document_is_a_valid_rich_embed(pool: PgPool) {
+        let router = test_router(&pool);
+        let (share_token, _, _) = shared_recipe(&pool, &router).await;
+
+        let uri = format!("/api/v1/public/recipes/{}/embed.json?format=oembed", share_token);
+        let response = router.clone().oneshot(request(Method::GET, &uri, None, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert!(response.headers()[header::CACHE_CONTROL].to_str().unwrap().contains("max-age="));
+        let document: serde_json::Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+
+        // Обязательные поля oEmbed 1.0 и типа rich
+        assert_eq!(document["type"], "rich");
+        assert_eq!(document["version"], "1.0");
+        assert_eq!(document["title"], "Шарлотка");
+        assert_eq!(document["author_name"], "Анна");
+        assert!(document["provider_name"].as_str().is_some_and(|name| !name.is_empty()));
+        assert!(document["provider_url"].as_str().unwrap().starts_with("http"));
+        assert!(document["cache_age"].as_u64().unwrap() > 0);
+        let (width, height) = (document["width"].as_u64().unwrap(), document["height"].as_u64().unwrap());
+        assert!(width > 0 && height > 0);
+        let html = document["html"].as_str().unwrap();
+        assert!(html.starts_with("<iframe ") && html.ends_with("</iframe>"), "{}", html);
+        assert!(html.contains(&share_token));
+        assert!(html.contains(&format!(r#"width="{}""#, width)) && html.contains(&format!(r#"height="{}""#, height)));
+
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/public/recipes/unknown/embed.json?format=oembed", None, None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    async fn only_widget_requests_count_as_embed_views(pool: PgPool) {
+        let router = test_router(&pool);
+        let (share_token, author_token, share_uri) = shared_recipe(&pool, &router).await;
+        let embed_uri = format!("/api/v1/public/recipes/{}/embed.json", share_token);
+
+        for _ in 0..2 {
+            let (status, embed) = send(&router, request(Method::GET, &embed_uri, None, None)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(embed["name"], "Шарлотка");
+        }
+        let (status, _) = send(&router, request(Method::GET, &format!("{}?format=oembed", embed_uri), None, None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, link) = send(&router, request(Method::GET, &share_uri, Some(&author_token), None)).await;
+        assert_eq!(link["embed_views"], 2);
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt::Display;
+
+use chrono::{Duration, Utc};
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+
+use crate::{
+    config::Config,
+    db::DbPool,
+    diagnostics::{DiagnosticsReport, StartupEnv},
+    services::{
+        admin_users::AdminUserService, api_tokens::ApiTokenService, fridge::FridgeService, post_views::PostViewService,
+        recipe_tags::RecipeTagService,
+    },
+};
+
+// Служебные команды для эксплуатации. Результат каждой команды — одна строка JSON,
+// последняя в stdout; код выхода 0 только при успехе, чтобы деплой мог на нем останавливаться.
+
+/// IT Cook backend: HTTP-сервер и служебные команды
+#[derive(Debug, Parser)]
+#[command(name = "it-cook", version)]
+pub struct Cli {
+    /// Без команды запускается сервер, как и раньше
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Запустить HTTP-сервер (по умолчанию)
+    Serve,
+    /// Применить миграции из ./migrations
+    Migrate,
+    /// Выдать права администратора пользователю с этим email или создать такого администратора
+    CreateAdmin {
+        #[arg(long)]
+        email: String,
+    },
+    /// Проверить окружение и конфигурацию так же, как при запуске сервера
+    CheckConfig,
+    /// Пересчитать кэши аналитики из исходных данных: нормализованные теги рецептов,
+    /// веса тегов и счетчики просмотров постов
+    RecomputeBenchmarks,
+    /// Окончательно удалить мягко удаленные записи старше срока хранения: отозванные токены API
+    /// и продукты, которые пользователь убрал из холодильника без причины
+    PurgeSoftDeleted {
+        /// Только посчитать, что будет удалено
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(i64).range(1..))]
+        older_than_days: i64,
+    },
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Serve => "serve",
+            Command::Migrate => "migrate",
+            Command::CreateAdmin { .. } => "create-admin",
+            Command::CheckConfig => "check-config",
+            Command::RecomputeBenchmarks => "recompute-benchmarks",
+            Command::PurgeSoftDeleted { .. } => "purge-soft-deleted",
+        }
+    }
+}
+
+/// Выполняет служебную команду, печатает результат и возвращает код выхода
+pub async fn run(command: Command) -> i32 {
+    let name = command.name();
+    let (ok, output) = match execute(command).await {
+        Ok(result) => (true, json!({ "command": name, "ok": true, "result": result })),
+        Err(error) => (false, json!({ "command": name, "ok": false, "error": error })),
+    };
+
+    println!("{}", output);
+    if ok {
+        0
+    } else {
+        1
+    }
+}
+
+/// Ошибка команды: сообщение или, для check-config, весь отчет диагностики
+fn failure(error: impl Display) -> Value {
+    json!(error.to_string())
+}
+
+async fn execute(command: Command) -> Result<Value, Value> {
+    match command {
+        Command::Serve => Err(failure("serve is not a maintenance command")),
+        Command::CheckConfig => check_config(&StartupEnv::from_process()).await,
+        command => execute_on(command, connect().await?).await,
+    }
+}
+
+/// Команды, которым нужна база; пул передается снаружи, чтобы их можно было прогнать на тестовой базе
+async fn execute_on(command: Command, pool: DbPool) -> Result<Value, Value> {
+    match command {
+        Command::Serve | Command::CheckConfig => Err(failure(format!("{} does not use the database", command.name()))),
+        Command::Migrate => {
+            let migrator = sqlx::migrate!("./migrations");
+            migrator.run(&pool).await.map_err(|e| failure(format!("Migration failed: {}", e)))?;
+            Ok(json!({
+                "migrations": migrator.iter().count(),
+                "latest_version": migrator.iter().map(|migration| migration.version).max(),
+            }))
+        }
+        Command::CreateAdmin { email } => {
+            let grant = AdminUserService::new(pool).create_admin(&email).await.map_err(failure)?;
+            serde_json::to_value(grant).map_err(failure)
+        }
+        Command::RecomputeBenchmarks => {
+            let tags = RecipeTagService::new(pool.clone());
+            let recipes_normalized = tags.backfill().await.map_err(failure)?;
+            let tags_weighted = tags.refresh_weights().await.map_err(failure)?;
+            let view_counts_corrected = PostViewService::new(pool).recount_views().await.map_err(failure)?;
+            Ok(json!({
+                "recipes_normalized": recipes_normalized,
+                "tags_weighted": tags_weighted,
+                "view_counts_corrected": view_counts_corrected,
+            }))
+        }
+        Command::PurgeSoftDeleted { dry_run, older_than_days } => {
+            let cutoff = Utc::now() - Duration::days(older_than_days);
+            let revoked_api_tokens = ApiTokenService::new(pool.clone())
+                .purge_revoked(cutoff, dry_run)
+                .await
+                .map_err(failure)?;
+            let removed_fridge_items = FridgeService::new(pool)
+                .purge_removed(cutoff, dry_run)
+                .await
+                .map_err(failure)?;
+            Ok(json!({
+                "dry_run": dry_run,
+                "cutoff": cutoff,
+                "revoked_api_tokens": revoked_api_tokens,
+                "removed_fridge_items": removed_fridge_items,
+            }))
+        }
+    }
+}
+
+/// Те же проверки, что при запуске сервера, плюс разбор конфигурации
+async fn check_config(env: &StartupEnv) -> Result<Value, Value> {
+    let report = DiagnosticsReport::collect(env).await;
+    let ok = report.ok;
+    let report = serde_json::to_value(report).map_err(failure)?;
+    if !ok {
+        return Err(report);
+    }
+
+    Config::new().map_err(|e| failure(format!("Failed to load configuration: {}", e)))?;
+    Ok(report)
+}
+
+async fn connect() -> Result<DbPool, Value> {
+    let config = Config::new().map_err(|e| failure(format!("Failed to load configuration: {}", e)))?;
+    DbPool::connect(&config.database_url)
+        .await
+        .map_err(|e| failure(format!("Database connection failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_support::{insert_fridge_item, insert_user};
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn parse(args: &[&str]) -> Result<Option<Command>, clap::Error> {
+        Cli::try_parse_from(std::iter::once("it-cook").chain(args.iter().copied())).map(|cli| cli.command)
+    }
+
+    /// Адрес тестовой базы этого теста
+    fn database_url(pool: &PgPool) -> String {
+        let base = std::env::var("DATABASE_URL").unwrap();
+        let database = pool.connect_options().get_database().unwrap().to_string();
+        format!("{}/{}", &base[..base.rfind('/').unwrap()], database)
+    }
+
+    #[test]
+    fn arguments_parse_into_commands() {
+        assert!(parse(&[]).unwrap().is_none(), "no subcommand starts the server");
+        assert!(matches!(parse(&["serve"]).unwrap(), Some(Command::Serve)));
+        assert!(matches!(parse(&["migrate"]).unwrap(), Some(Command::Migrate)));
+        assert!(matches!(parse(&["check-config"]).unwrap(), Some(Command::CheckConfig)));
+        assert!(matches!(parse(&["recompute-benchmarks"]).unwrap(), Some(Command::RecomputeBenchmarks)));
+        assert!(matches!(
+            parse(&["create-admin", "--email", "ops@example.com"]).unwrap(),
+            Some(Command::CreateAdmin { email }) if email == "ops@example.com"
+        ));
+        assert!(matches!(
+            parse(&["purge-soft-deleted", "--dry-run"]).unwrap(),
+            Some(Command::PurgeSoftDeleted { dry_run: true, older_than_days: 30 })
+        ));
+        assert!(matches!(
+            parse(&["purge-soft-deleted", "--older-than-days", "7"]).unwrap(),
+            Some(Command::PurgeSoftDeleted { dry_run: false, older_than_days: 7 })
+        ));
+
+        for invalid in [
+            vec!["create-admin"],
+            vec!["purge-soft-deleted", "--older-than-days", "0"],
+            vec!["purge-soft-deleted", "--older-than-days", "week"],
+            vec!["drop-database"],
+        ] {
+            assert!(parse(&invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[sqlx::test]
+    async fn check_config_passes_on_a_valid_environment_and_returns_the_report_on_errors(pool: PgPool) {
+        let url = database_url(&pool);
+        let report = check_config(&StartupEnv::with_vars(&[("DATABASE_URL", &url), ("JWT_SECRET", SECRET), ("PORT", "0")]))
+            .await
+            .unwrap();
+        assert_eq!(report["ok"], true);
+        assert_eq!(report["errors"], json!([]));
+
+        let failed = check_config(&StartupEnv::with_vars(&[("RUST_ENV", "production"), ("DATABASE_URL", &url), ("PORT", "0")]))
+            .await
+            .unwrap_err();
+        assert_eq!(failed["ok"], false);
+        assert_eq!(failed["errors"][0]["message"], "JWT_SECRET is not set");
+    }
+
+    #[sqlx::test]
+    async fn create_admin_creates_promotes_and_is_idempotent(pool: PgPool) {
+        let create = |email: &str| Command::CreateAdmin { email: email.to_string() };
+
+        let created = execute_on(create(" Ops@Example.com "), pool.clone()).await.unwrap();
+        assert_eq!((created["created"].as_bool(), created["promoted"].as_bool()), (Some(true), Some(false)));
+        assert_eq!(created["user"]["email"], "ops@example.com");
+        assert!(created["temporary_password"].as_str().is_some_and(|password| !password.is_empty()));
+
+        let again = execute_on(create("ops@example.com"), pool.clone()).await.unwrap();
+        assert_eq!((again["created"].as_bool(), again["promoted"].as_bool()), (Some(false), Some(false)));
+        assert!(again.get("temporary_password").is_none());
+
+        let user_id = insert_user(&pool, "Анна").await;
+        let promoted = execute_on(create(&format!("{}@example.com", user_id)), pool.clone()).await.unwrap();
+        assert_eq!((promoted["created"].as_bool(), promoted["promoted"].as_bool()), (Some(false), Some(true)));
+        let role: String = sqlx::query_scalar("SELECT role::text FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(role, "admin");
+
+        assert_eq!(execute_on(create("not-an-email"), pool.clone()).await.unwrap_err(), json!("Bad request: Invalid email"));
+    }
+
+    #[sqlx::test]
+    async fn purge_soft_deleted_counts_on_dry_run_and_removes_only_old_tombstones(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let now = Utc::now();
+        let old_removed = insert_fridge_item(&pool, user_id, "Кефир", None, now - Duration::days(60)).await;
+        let recent_removed = insert_fridge_item(&pool, user_id, "Сыр", None, now - Duration::days(60)).await;
+        let old_consumed = insert_fridge_item(&pool, user_id, "Хлеб", None, now - Duration::days(60)).await;
+        for (id, status, days_ago) in [(old_removed.id, "removed", 40), (recent_removed.id, "removed", 5), (old_consumed.id, "consumed", 40)] {
+            sqlx::query("UPDATE fridge_items SET status = $2::fridge_item_status, finished_at = $3 WHERE id = $1")
+                .bind(id)
+                .bind(status)
+                .bind(now - Duration::days(days_ago))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for revoked_days_ago in [Some(40), Some(5), None] {
+            let id = Uuid::new_v4();
+            sqlx::query("INSERT INTO api_tokens (id, user_id, name, token_prefix, token_hash, revoked_at) VALUES ($1, $2, 'ci', 'pat_', $1::text, $3)")
+                .bind(id)
+                .bind(user_id)
+                .bind(revoked_days_ago.map(|days| now - Duration::days(days)))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let purge = |dry_run| Command::PurgeSoftDeleted { dry_run, older_than_days: 30 };
+        let counted = execute_on(purge(true), pool.clone()).await.unwrap();
+        assert_eq!((counted["revoked_api_tokens"].as_u64(), counted["removed_fridge_items"].as_u64()), (Some(1), Some(1)));
+        let purged = execute_on(purge(false), pool.clone()).await.unwrap();
+        assert_eq!((purged["revoked_api_tokens"].as_u64(), purged["removed_fridge_items"].as_u64()), (Some(1), Some(1)));
+
+        let items: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM fridge_items WHERE user_id = $1 ORDER BY name")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(!items.contains(&old_removed.id));
+        let tokens: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_tokens").fetch_one(&pool).await.unwrap();
+        assert_eq!(tokens, 2);
+
+        let nothing_left = execute_on(purge(false), pool.clone()).await.unwrap();
+        assert_eq!((nothing_left["revoked_api_tokens"].as_u64(), nothing_left["removed_fridge_items"].as_u64()), (Some(0), Some(0)));
+    }
+
+    #[sqlx::test]
+    async fn recompute_benchmarks_repairs_view_counters(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post_id: Uuid = sqlx::query_scalar("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'Борщ', 'text') RETURNING id")
+            .bind(author)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        for viewer in ["user:a", "user:b", "anon:c"] {
+            sqlx::query("INSERT INTO post_views (post_id, day, viewer_key) VALUES ($1, CURRENT_DATE, $2)")
+                .bind(post_id)
+                .bind(viewer)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO post_view_counts (post_id, views_count) VALUES ($1, 40)")
+            .bind(post_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = execute_on(Command::RecomputeBenchmarks, pool.clone()).await.unwrap();
+        assert_eq!(result["view_counts_corrected"], 1);
+        let views: i64 = sqlx::query_scalar("SELECT views_count FROM post_view_counts WHERE post_id = $1")
+            .bind(post_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(views, 3);
+
+        let again = execute_on(Command::RecomputeBenchmarks, pool.clone()).await.unwrap();
+        assert_eq!(again["view_counts_corrected"], 0);
+    }
+}
@@ -1,13 +1,260 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
+
+use crate::models::fridge::DEFAULT_OUTLIER_MULTIPLE;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub database_url: String,
+    /// Реплика только для чтения (DATABASE_READ_URL); без нее чтения идут в основную базу
+    pub database_read_url: Option<String>,
     pub jwt_secret: String,
     pub openai_api_key: Option<String>,
     pub cloudinary_url: Option<String>,
     pub port: u16,
+    pub environment: String,
+    pub allow_mock_ai: bool,
+    pub ws_max_connections_per_user: usize,
+    pub ws_max_connections_global: usize,
+    /// Буферы событий: общий канал, канал группы и очередь одного соединения
+    pub ws_global_buffer: usize,
+    pub ws_channel_buffer: usize,
+    pub ws_outgoing_queue: usize,
+    pub recipe_availability_max_fridge_items: usize,
+    pub public_feed_rate_limit_per_minute: u32,
+    /// Запросов в минуту к виджету одного рецепта (на публичный токен)
+    pub embed_rate_limit_per_minute: u32,
+    /// Окно, за которое одинаковые уведомления сообщества объединяются в одно
+    pub notification_digest_window_minutes: i64,
+    /// Часы песочницы, которые администратор может сдвинуть (SANDBOX_CLOCK); в production всегда выключены
+    pub sandbox_clock: bool,
+    pub ai_resilience: AiResilienceConfig,
+    pub ai_costs: AiCostConfig,
+    pub post_edits: PostEditConfig,
+    pub fridge_limits: FridgeLimitsConfig,
+}
+
+/// Таймауты запросов к провайдерам ИИ и параметры размыкателя (circuit breaker)
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct AiResilienceConfig {
+    pub connect_timeout: Duration,
+    /// Весь запрос целиком, включая чтение ответа
+    pub request_timeout: Duration,
+    /// Столько сбоев подряд в пределах `circuit_window` размыкают цепь
+    pub circuit_failure_threshold: u32,
+    pub circuit_window: Duration,
+    /// Сколько цепь остается разомкнутой до пробного запроса
+    pub circuit_cooldown: Duration,
+}
+
+impl Default for AiResilienceConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            circuit_failure_threshold: 5,
+            circuit_window: Duration::from_secs(60),
+            circuit_cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl AiResilienceConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |key: &str, default: Duration| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .map_or(default, Duration::from_secs)
+        };
+
+        Self {
+            connect_timeout: secs("AI_CONNECT_TIMEOUT_SECS", defaults.connect_timeout),
+            request_timeout: secs("AI_REQUEST_TIMEOUT_SECS", defaults.request_timeout),
+            circuit_failure_threshold: env::var("AI_CIRCUIT_FAILURE_THRESHOLD")
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.circuit_failure_threshold),
+            circuit_window: secs("AI_CIRCUIT_WINDOW_SECS", defaults.circuit_window),
+            circuit_cooldown: secs("AI_CIRCUIT_COOLDOWN_SECS", defaults.circuit_cooldown),
+        }
+    }
+}
+
+/// Цена модели в долларах за миллион токенов
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// Учет расходов на ИИ: цены моделей и месячный бюджет
+#[derive(Debug, Clone, Deserialize)]
+pub struct AiCostConfig {
+    /// Модель → цена; встроенные цены можно переопределить через AI_MODEL_PRICES
+    pub prices: HashMap<String, ModelPrice>,
+    /// Месячный бюджет в долларах; при превышении фоновые функции ИИ переходят на
+    /// детерминированные ответы. Нет — без ограничения.
+    pub monthly_budget_usd: Option<f64>,
+}
+
+impl AiCostConfig {
+    /// Встроенные цены провайдеров (USD за 1M входных / выходных токенов)
+    const BUILTIN_PRICES: [(&'static str, f64, f64); 3] = [
+        ("gpt-3.5-turbo", 0.50, 1.50),
+        ("gemini-1.5-flash", 0.075, 0.30),
+        ("llama-3.1-8b-instant", 0.05, 0.08),
+    ];
+
+    pub fn from_env() -> Self {
+        let mut prices: HashMap<String, ModelPrice> = Self::BUILTIN_PRICES
+            .iter()
+            .map(|(model, input, output)| {
+                (model.to_string(), ModelPrice { input_per_million: *input, output_per_million: *output })
+            })
+            .collect();
+        if let Ok(value) = env::var("AI_MODEL_PRICES") {
+            prices.extend(parse_model_prices(&value));
+        }
+
+        Self {
+            prices,
+            monthly_budget_usd: env::var("AI_MONTHLY_BUDGET_USD")
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite() && *value > 0.0),
+        }
+    }
+}
+
+/// Окно правки постов, у которых уже есть отклик
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PostEditConfig {
+    /// После этого срока с публикации текст можно только подправить
+    pub window_hours: i64,
+    /// Больше лайков — текст можно только подправить, даже в пределах окна
+    pub max_likes: i64,
+    /// Правка с таким или большим сходством со старым текстом (0.0–1.0) считается мелкой
+    /// (опечатка) и разрешена всегда
+    pub small_edit_similarity: f64,
+}
+
+impl PostEditConfig {
+    pub fn from_env() -> Self {
+        let number = |key: &str, default: i64| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|value| *value >= 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            window_hours: number("POST_EDIT_WINDOW_HOURS", 24),
+            max_likes: number("POST_EDIT_MAX_LIKES", 10),
+            small_edit_similarity: env::var("POST_SMALL_EDIT_SIMILARITY")
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| (0.0..=1.0).contains(value))
+                .unwrap_or(0.9),
+        }
+    }
+}
+
+/// Пределы правдоподобия для продуктов холодильника: защищают аналитику от опечаток
+/// вроде 5000 вместо 500 г
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FridgeLimitsConfig {
+    /// Наибольшее количество продукта в кг (для г — в пересчете)
+    pub max_kg: f64,
+    /// Наибольшее количество в литрах (для мл — в пересчете)
+    pub max_liters: f64,
+    /// Наибольшее количество в штуках и в единицах, которых нет в справочнике
+    pub max_pieces: f64,
+    /// Наибольшая цена (за единицу и за все), руб.
+    pub max_price: f64,
+    /// На сколько лет вперед может быть срок годности
+    pub max_expiry_years: i64,
+    /// На сколько часов дата покупки может быть в будущем (часовые пояса, часы устройства)
+    pub purchase_date_tolerance_hours: i64,
+    /// Покупка дороже медианной во столько раз не учитывается в аналитике без `include_outliers`
+    pub outlier_multiple: f64,
+}
+
+impl FridgeLimitsConfig {
+    pub fn from_env() -> Self {
+        let number = |key: &str, default: f64| {
+            env::var(key)
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite() && *value > 0.0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_kg: number("FRIDGE_MAX_KG", 100.0),
+            max_liters: number("FRIDGE_MAX_LITERS", 100.0),
+            max_pieces: number("FRIDGE_MAX_PIECES", 1000.0),
+            max_price: number("FRIDGE_MAX_PRICE", 1_000_000.0),
+            max_expiry_years: number("FRIDGE_MAX_EXPIRY_YEARS", 10.0) as i64,
+            purchase_date_tolerance_hours: number("FRIDGE_PURCHASE_DATE_TOLERANCE_HOURS", 24.0) as i64,
+            outlier_multiple: number("FRIDGE_OUTLIER_MULTIPLE", DEFAULT_OUTLIER_MULTIPLE),
+        }
+    }
+}
+
+/// Экспорт трасс OpenTelemetry по OTLP. Читается до `Config::new`, потому что логирование
+/// настраивается первым. Без OTEL_EXPORTER_OTLP_ENDPOINT экспорт выключен.
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+    /// Доля экспортируемых трасс (0.0–1.0); дочерние span следуют решению корневого
+    pub sample_rate: f64,
+}
+
+impl TelemetryConfig {
+    pub fn from_env() -> Self {
+        Self {
+            otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            service_name: env::var("OTEL_SERVICE_NAME")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| "itcook-backend".to_string()),
+            sample_rate: env::var("OTEL_TRACES_SAMPLER_ARG")
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| (0.0..=1.0).contains(value))
+                .unwrap_or(1.0),
+        }
+    }
+}
+
+/// Разбирает `gpt-4o-mini=0.15/0.6,gemini-1.5-flash=0.075/0.3`; записи с ошибками пропускаются
+pub fn parse_model_prices(value: &str) -> Vec<(String, ModelPrice)> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (model, price) = entry.split_once('=')?;
+            let (input, output) = price.split_once('/')?;
+            let price = ModelPrice {
+                input_per_million: input.trim().parse().ok()?,
+                output_per_million: output.trim().parse().ok()?,
+            };
+            let valid = [price.input_per_million, price.output_per_million]
+                .iter()
+                .all(|value| value.is_finite() && *value >= 0.0);
+            (valid && !model.trim().is_empty()).then(|| (model.trim().to_string(), price))
+        })
+        .collect()
 }
 
 impl Config {
@@ -42,14 +289,119 @@ impl Config {
                 3000
             });
 
+        let environment = env::var("RUST_ENV")
+            .unwrap_or_else(|_| "development".to_string());
+
+        let ws_max_connections_per_user = env::var("WS_MAX_CONNECTIONS_PER_USER")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(5);
+
+        let ws_max_connections_global = env::var("WS_MAX_CONNECTIONS_GLOBAL")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(10_000);
+
+        // Размеры буферов WebSocket-событий; медленный клиент сначала заполняет свою очередь
+        let ws_buffer = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(default)
+        };
+        let ws_global_buffer = ws_buffer("WS_GLOBAL_BUFFER", 1000);
+        let ws_channel_buffer = ws_buffer("WS_CHANNEL_BUFFER", 100);
+        let ws_outgoing_queue = ws_buffer("WS_OUTGOING_QUEUE", 256);
+
+        // Больше продуктов — сопоставление ингредиентов только по точному названию
+        let recipe_availability_max_fridge_items = env::var("RECIPE_AVAILABILITY_MAX_FRIDGE_ITEMS")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(300);
+
+        // Запросов в минуту с одного IP к публичной ленте сообщества
+        let public_feed_rate_limit_per_minute = env::var("PUBLIC_FEED_RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+            .unwrap_or(60);
+
+        // Запросов в минуту к виджету одного рецепта; ответы кешируются, так что до API доходит немногое
+        let embed_rate_limit_per_minute = env::var("EMBED_RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+            .unwrap_or(120);
+
+        let notification_digest_window_minutes = env::var("NOTIFICATION_DIGEST_WINDOW_MINUTES")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|minutes| *minutes > 0)
+            .unwrap_or(30);
+
+        let sandbox_clock = env::var("SANDBOX_CLOCK")
+            .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        let sandbox_clock = if sandbox_clock && environment.eq_ignore_ascii_case("production") {
+            println!("⚠️ SANDBOX_CLOCK is ignored in production");
+            false
+        } else {
+            sandbox_clock
+        };
+
         println!("✅ Config created successfully");
 
         Ok(Config {
             database_url,
+            database_read_url: env::var("DATABASE_READ_URL").ok().filter(|url| !url.trim().is_empty()),
             jwt_secret,
             openai_api_key: env::var("OPENAI_API_KEY").ok(),
             cloudinary_url: env::var("CLOUDINARY_URL").ok(),
             port,
+            environment,
+            allow_mock_ai: Self::allow_mock_ai_from_env(),
+            ws_max_connections_per_user,
+            ws_max_connections_global,
+            ws_global_buffer,
+            ws_channel_buffer,
+            ws_outgoing_queue,
+            recipe_availability_max_fridge_items,
+            public_feed_rate_limit_per_minute,
+            embed_rate_limit_per_minute,
+            notification_digest_window_minutes,
+            sandbox_clock,
+            ai_resilience: AiResilienceConfig::from_env(),
+            ai_costs: AiCostConfig::from_env(),
+            post_edits: PostEditConfig::from_env(),
+            fridge_limits: FridgeLimitsConfig::from_env(),
         })
     }
+
+    pub fn is_production(&self) -> bool {
+        self.environment.eq_ignore_ascii_case("production")
+    }
+
+    /// Можно ли отдавать ответы Mock-провайдера ИИ.
+    /// В production запрещено, если явно не разрешено через ALLOW_MOCK_AI (для staging).
+    pub fn mock_ai_allowed(&self) -> bool {
+        !self.is_production() || self.allow_mock_ai
+    }
+
+    /// То же, что `mock_ai_allowed`, но без загрузки полной конфигурации —
+    /// для сервисов, которые создаются через `from_env()`.
+    pub fn mock_ai_allowed_from_env() -> bool {
+        !Self::is_production_from_env() || Self::allow_mock_ai_from_env()
+    }
+
+    /// То же, что `is_production`, без загрузки полной конфигурации
+    pub fn is_production_from_env() -> bool {
+        env::var("RUST_ENV")
+            .map(|value| value.eq_ignore_ascii_case("production"))
+            .unwrap_or(false)
+    }
+
+    fn allow_mock_ai_from_env() -> bool {
+        env::var("ALLOW_MOCK_AI")
+            .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+    }
 }
@@ -1,8 +1,102 @@
-use sqlx::{PgPool, Pool, Postgres};
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    query::{Query, QueryAs, QueryScalar},
+    FromRow, PgPool, Pool, Postgres,
+};
 use tracing::{info, instrument};
+use uuid::Uuid;
 
 pub type DbPool = Pool<Postgres>;
 
+/// Основной пул и необязательная реплика для чтения. Все записи идут в основной пул;
+/// без DATABASE_READ_URL чтения тоже идут в него.
+#[derive(Debug, Clone)]
+pub struct DbPools {
+    primary: DbPool,
+    replica: Option<DbPool>,
+}
+
+/// Насколько свежими должны быть читаемые данные
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConsistency {
+    /// Отставание реплики на несколько секунд допустимо: списки, поиск, ленты, аналитика
+    Replica,
+    /// В этом же запросе уже была запись, и ее нужно увидеть — читаем из основного пула
+    AfterWrite,
+}
+
+impl DbPools {
+    pub fn new(primary: DbPool, replica: Option<DbPool>) -> Self {
+        Self { primary, replica }
+    }
+
+    /// Без реплики: для сервисов, которые создаются из одного пула
+    pub fn single(pool: DbPool) -> Self {
+        Self::new(pool, None)
+    }
+
+    /// Пул для записей и транзакций
+    pub fn primary(&self) -> &DbPool {
+        &self.primary
+    }
+
+    /// Пул для чтения; после записи в том же запросе — всегда основной
+    pub fn read(&self, consistency: ReadConsistency) -> &DbPool {
+        match (consistency, &self.replica) {
+            (ReadConsistency::Replica, Some(replica)) => replica,
+            _ => &self.primary,
+        }
+    }
+}
+
+/// Запросы к данным одного пользователя. Id пользователя всегда параметр `$1` и
+/// привязывается сам, поэтому запрос без фильтра по владельцу не собрать: в debug-сборке
+/// SQL без `user_id = $1` (или вставка, где `user_id` не первая колонка) — паника.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserScope {
+    user_id: Uuid,
+}
+
+impl UserScope {
+    pub fn new(user_id: Uuid) -> Self {
+        Self { user_id }
+    }
+
+    pub fn query<'q>(&self, sql: &'q str) -> Query<'q, Postgres, PgArguments> {
+        debug_assert_scoped(sql);
+        sqlx::query(sql).bind(self.user_id)
+    }
+
+    pub fn query_as<'q, O>(&self, sql: &'q str) -> QueryAs<'q, Postgres, O, PgArguments>
+    where
+        O: for<'r> FromRow<'r, PgRow>,
+    {
+        debug_assert_scoped(sql);
+        sqlx::query_as(sql).bind(self.user_id)
+    }
+
+    pub fn query_scalar<'q, O>(&self, sql: &'q str) -> QueryScalar<'q, Postgres, O, PgArguments>
+    where
+        (O,): for<'r> FromRow<'r, PgRow>,
+    {
+        debug_assert_scoped(sql);
+        sqlx::query_scalar(sql).bind(self.user_id)
+    }
+}
+
+fn debug_assert_scoped(sql: &str) {
+    debug_assert!(is_user_scoped(sql), "User-scoped query does not filter by user_id = $1: {}", sql);
+}
+
+/// Запрос ограничен владельцем: фильтр `user_id = $1` или вставка с `user_id` первой колонкой
+fn is_user_scoped(sql: &str) -> bool {
+    let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ").replace("( ", "(");
+    if sql.to_uppercase().starts_with("INSERT") {
+        return sql.contains("(user_id,") && sql.contains("($1,");
+    }
+    sql.contains("user_id = $1")
+}
+
 #[instrument]
 pub async fn init_db(database_url: &str) -> Result<DbPool, sqlx::Error> {
     println!("🔍 init_db() started");
@@ -25,3 +119,75 @@ pub async fn init_db(database_url: &str) -> Result<DbPool, sqlx::Error> {
     info!("Database connection established");
     Ok(pool)
 }
+
+/// Подключение к реплике для чтения. Реплика недоступна — чтения остаются на основном пуле,
+/// чтобы сбой реплики не мешал запуску.
+pub async fn init_read_replica(database_read_url: &str) -> Option<DbPool> {
+    info!("Connecting to read replica...");
+
+    match PgPool::connect(database_read_url).await {
+        Ok(pool) => {
+            println!("✅ Read replica connected");
+            Some(pool)
+        }
+        Err(e) => {
+            println!("⚠️ Read replica connection failed, reads will use the primary database: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        models::fridge::FridgeItemStatus,
+        services::{diary::DiaryService, fridge::FridgeService},
+        test_support::{counting_pool, insert_diary_entry, insert_fridge_item, insert_user},
+    };
+
+    #[sqlx::test]
+    async fn listings_and_summaries_use_replica_and_writes_use_primary(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let now = Utc::now();
+        let item = insert_fridge_item(&pool, user_id, "Молоко", None, now).await;
+        insert_diary_entry(&pool, user_id, "breakfast", 200.0, 10.0, now).await;
+
+        let (primary, primary_queries) = counting_pool(&pool);
+        let (replica, replica_queries) = counting_pool(&pool);
+        let pools = DbPools::new(primary, Some(replica));
+        let fridge = FridgeService::with_pools(pools.clone());
+
+        assert_eq!(fridge.get_user_items(user_id, None, None, None).await.unwrap().len(), 1);
+        fridge.get_history(user_id, None).await.unwrap();
+        fridge.get_stats(user_id).await.unwrap();
+        fridge.get_expense_analytics(user_id, "week", Default::default()).await.unwrap();
+        let summaries = DiaryService::with_pools(pools.clone()).get_summary_range(user_id, None, None).await.unwrap();
+        assert_eq!(summaries.iter().map(|day| day.total_calories).sum::<f32>(), 200.0);
+
+        assert_eq!(primary_queries.load(Ordering::SeqCst), 0);
+        let reads = replica_queries.load(Ordering::SeqCst);
+        assert!(reads >= 5);
+
+        // Изменение и чтение после него — только основной пул
+        fridge.remove_item(item.id, user_id).await.unwrap();
+        assert_eq!(fridge.get_item_by_id(item.id, user_id).await.unwrap().status, FridgeItemStatus::Removed);
+        assert!(primary_queries.load(Ordering::SeqCst) > 0);
+        assert_eq!(replica_queries.load(Ordering::SeqCst), reads);
+    }
+
+    #[sqlx::test]
+    async fn reads_fall_back_to_primary_without_replica(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (primary, primary_queries) = counting_pool(&pool);
+        let pools = DbPools::single(primary);
+
+        FridgeService::with_pools(pools.clone()).get_user_items(user_id, None, None, None).await.unwrap();
+        assert!(primary_queries.load(Ordering::SeqCst) > 0);
+        assert!(std::ptr::eq(pools.read(ReadConsistency::Replica), pools.primary()));
+    }
+}
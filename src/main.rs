@@ -1,31 +1,47 @@
-use axum::{
-    extract::Extension,
-    http::{StatusCode, Method, HeaderValue, HeaderName},
-    routing::{get},
-    Router,
-    middleware as axum_middleware,
-};
 use std::net::SocketAddr;
-use std::sync::Arc;
-use tower_http::cors::CorsLayer;
-use tracing::{info, instrument};
+use clap::Parser;
+use tracing::info;
 
 mod api;
+mod app;
 mod db;
 mod models;
 mod services;
 mod utils;
 mod config;
+mod diagnostics;
 mod middleware;
+mod cli;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
 
 use config::Config;
-use services::ai::AiService;
-use services::realtime::{WebSocketManager, RealtimeService};
+
+/// Сколько ждать завершения текущих запусков фоновых задач при остановке
+const TASK_SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Без подкоманды — сервер, чтобы прежние команды запуска в деплое продолжали работать
+    match cli::Cli::parse().command {
+        None | Some(cli::Command::Serve) => serve().await,
+        Some(command) => {
+            // Логи служебных команд — в stderr; результат — последняя строка stdout
+            tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+            std::process::exit(cli::run(command).await)
+        }
+    }
+}
+
+async fn serve() -> Result<(), Box<dyn std::error::Error>> {
     // Set up panic hook to capture panics
     std::panic::set_hook(Box::new(|panic_info| {
+        // Паника в фоновой задаче перехватывается супервизором, процесс продолжает работать
+        if let Some(task) = services::task_supervisor::current_task() {
+            println!("💥 PANIC in background task '{}': {}", task, panic_info);
+            return;
+        }
         println!("💥 PANIC OCCURRED: {}", panic_info);
         if let Some(location) = panic_info.location() {
             println!("💥 Panic location: {}:{}", location.file(), location.line());
@@ -36,13 +52,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing FIRST
     println!("🔧 Initializing tracing subscriber...");
     
-    // Try to initialize tracing with error handling
-    match std::panic::catch_unwind(|| {
-        tracing_subscriber::fmt::init();
-    }) {
-        Ok(_) => println!("✅ Tracing subscriber initialized"),
-        Err(_) => {
-            println!("❌ Failed to initialize tracing subscriber");
+    // Логи и, если задан OTEL_EXPORTER_OTLP_ENDPOINT, экспорт трасс
+    match telemetry::init(&config::TelemetryConfig::from_env()) {
+        Ok(true) => println!("✅ Tracing subscriber initialized, exporting traces via OTLP"),
+        Ok(false) => println!("✅ Tracing subscriber initialized"),
+        Err(e) => {
+            println!("❌ Failed to initialize tracing subscriber: {}", e);
             return Err("Failed to initialize tracing".into());
         }
     }
@@ -50,13 +65,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🚀 IT Cook Backend initializing...");
     println!("🔍 Debug: Main function started successfully");
     
-    // Check all environment variables
-    println!("🔍 Debug: Checking environment variables...");
-    println!("PORT: {}", std::env::var("PORT").unwrap_or("not set".to_string()));
-    println!("RUST_LOG: {}", std::env::var("RUST_LOG").unwrap_or("not set".to_string()));
-    println!("DATABASE_URL: {}", std::env::var("DATABASE_URL").map(|url| format!("{}...", &url[..std::cmp::min(50, url.len())])).unwrap_or("not set".to_string()));
-    println!("JWT_SECRET: {}", std::env::var("JWT_SECRET").map(|secret| format!("{}...", &secret[..std::cmp::min(10, secret.len())])).unwrap_or("not set".to_string()));
-    println!("SQLX_OFFLINE: {}", std::env::var("SQLX_OFFLINE").unwrap_or("not set".to_string()));
+    // Все проверки окружения сразу: при ошибках — один отчет со всеми проблемами и выход
+    let startup_env = diagnostics::StartupEnv::from_process();
+    let report = diagnostics::DiagnosticsReport::collect(&startup_env).await;
+    report.print(&startup_env);
+    if !report.ok {
+        std::process::exit(1);
+    }
     
     // Load configuration
     println!("📁 Loading configuration...");
@@ -100,60 +115,83 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Run migrations - закомментировано, так как миграции уже применены
     // sqlx::migrate!("./migrations").run(&db_pool).await?;
 
-    // Initialize WebSocket manager and realtime service
-    let ws_manager = Arc::new(WebSocketManager::new());
-    let realtime_service = Arc::new(RealtimeService::new(ws_manager.clone()));
-    
-    // Start cleanup task for inactive WebSocket connections
-    realtime_service.start_cleanup_task();
+    // Реплика для тяжелых чтений, если задана; иначе все идет в основную базу
+    let read_replica = match &config.database_read_url {
+        Some(database_read_url) => db::init_read_replica(database_read_url).await,
+        None => None,
+    };
+    let db_pools = db::DbPools::new(db_pool, read_replica);
+
+    // Общее состояние приложения (WebSocket, realtime, feature flags, ИИ)
+    let state = app::AppState::new(config, db_pools);
+
+    // Очистка неактивных WebSocket-соединений — под супервизором фоновых задач
+    let tasks = state.tasks.clone();
+    state.realtime_service.register_cleanup_task(&tasks);
+
+    // Перенос сводок уведомлений сообщества в inbox после окна объединения
+    services::notification_digests::NotificationDigestService::new(
+        state.db_pool.clone(),
+        state.config.notification_digest_window_minutes,
+    )
+    .with_clock(state.clock.clone())
+    .register_flush_task(&tasks);
+
+    // Периодический сброс буфера просмотров постов в БД
+    state.post_views.start_flush_task();
+
+    // Напоминания о сроках целей и истечение просроченных целей
+    services::goal_reminders::GoalReminderScheduler::new(state.db_pool.clone(), state.realtime_service.clone())
+        .with_clock(state.clock.clone())
+        .start();
+
+    // Напоминания о еженедельной проверке холодильника
+    services::fridge_checkin::FridgeCheckinScheduler::new(state.db_pool.clone(), state.realtime_service.clone())
+        .with_clock(state.clock.clone())
+        .start();
+
+    // Вечернее напоминание о дневнике тем, кто за день ничего не записал
+    services::diary_reminders::DiaryReminderScheduler::new(state.db_pool.clone(), state.realtime_service.clone())
+        .with_clock(state.clock.clone())
+        .start();
+
+    // Уведомления о сроках годности и доставка уведомлений, отложенных на тихие часы
+    services::expiry_notifications::ExpiryNotificationScheduler::new(state.db_pool.clone(), state.realtime_service.clone())
+        .with_clock(state.clock.clone())
+        .start();
+    services::notification_delivery::NotificationDelivery::new(state.db_pool.clone(), state.realtime_service.clone()).start_queue_sweep();
+
+    // Ежемесячные отчеты по холодильнику на почту (первого числа)
+    services::monthly_reports::MonthlyReportScheduler::new(state.db_pool.clone(), services::mail::MailService::from_env())
+        .with_clock(state.clock.clone())
+        .start();
+
+    // Еженедельный разбор целей с помощником (по воскресеньям, для тех, кто включил его в настройках)
+    services::goal_review::GoalReviewScheduler::new(state.db_pool.clone(), state.ai_service.clone(), state.realtime_service.clone())
+        .with_clock(state.clock.clone())
+        .start();
+
+    // Удаление просроченных ключей идемпотентности
+    services::idempotency::IdempotencyService::new(state.db_pool.clone()).start_cleanup_task();
+
+    // Нормализация тегов старых рецептов и пересчет весов тегов для похожих рецептов
+    services::recipe_tags::RecipeTagService::new(state.db_pool.clone()).start_refresh_task();
+
+    // Продолжить административные задачи, прерванные перезапуском
+    if let Err(e) = services::admin_jobs::RecipeNutritionRecalculator::new(state.db_pool.clone()).resume_unfinished().await {
+        println!("⚠️ Failed to resume admin jobs: {:?}", e);
+    }
+    let nutrition_backfill = services::nutrition_backfill::NutritionBackfillService::new(
+        state.db_pool.clone(),
+        state.ai_service.clone(),
+        state.realtime_service.clone(),
+    );
+    if let Err(e) = nutrition_backfill.resume_unfinished().await {
+        println!("⚠️ Failed to resume nutrition backfill jobs: {:?}", e);
+    }
 
     // Build our application with routes
-    let app = Router::new()
-        .route("/health", get(health_check))
-        // Публичные роуты аутентификации (не требуют токена)
-        .nest("/api/v1/auth", api::auth::routes())
-        // Публичные роуты для предустановленных данных холодильника
-        // .nest("/api/v1/fridge", api::fridge::public_routes())
-        // Защищенные роуты аутентификации (требуют токена)
-        .nest("/api/v1/auth", api::auth::protected_routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        // Остальные защищенные роуты (требуют токена)
-        .nest("/api/v1/diary", api::diary::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/fridge", api::fridge::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/recipes", api::recipes::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/goals", api::goals::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/community", api::community::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/realtime", api::websocket::routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/ai", ai_routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .nest("/api/v1/health", health_routes()
-            .layer(axum_middleware::from_fn_with_state(db_pool.clone(), middleware::auth_middleware)))
-        .layer(
-            CorsLayer::new()
-                .allow_origin([
-                    "http://localhost:3000".parse::<HeaderValue>().unwrap(),
-                    "http://localhost:3001".parse::<HeaderValue>().unwrap(),
-                    "https://ai-cook-frontend.vercel.app".parse::<HeaderValue>().unwrap(),
-                    "https://ai-cook-frontend-git-main-fodis-projects-dcba8b75.vercel.app".parse::<HeaderValue>().unwrap()
-                ])
-                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
-                .allow_headers([
-                    HeaderName::from_static("authorization"),
-                    HeaderName::from_static("content-type"),
-                    HeaderName::from_static("x-requested-with"),
-                ])
-                .allow_credentials(true)
-        )
-        .layer(Extension(db_pool))
-        .layer(Extension(config))
-        .layer(Extension(ws_manager))
-        .layer(Extension(realtime_service));
+    let app = app::build_router(state);
 
     // Получаем порт из переменной окружения PORT или используем значение по умолчанию
     let port = std::env::var("PORT")
@@ -183,10 +221,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     println!("🌐 Starting server on http://0.0.0.0:{}", port);
     
-    match axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await 
-    {
+    let served = axum::Server::bind(&addr)
+        // Адрес соединения нужен для лимитов по IP, если запрос пришел не через прокси
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await;
+
+    // Сервер больше не принимает запросы — останавливаем фоновые задачи
+    tasks.shutdown(TASK_SHUTDOWN_GRACE).await;
+    telemetry::shutdown();
+
+    match served {
         Ok(_) => {
             println!("✅ Server stopped gracefully");
             Ok(())
@@ -198,34 +243,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
-#[instrument]
-async fn health_check() -> Result<String, StatusCode> {
-    Ok("IT Cook Backend is running! 🍽️\n".to_string())
-}
+/// Ctrl+C или SIGTERM (остановка контейнера)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            println!("⚠️ Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
 
-fn ai_routes() -> Router {
-    use axum::routing::{get, post};
-    
-    Router::new()
-        .route("/chat", post(api::ai::chat_with_ai))
-        .route("/generate-recipe", post(api::ai::generate_recipe))
-        .route("/analyze-nutrition", post(api::ai::analyze_nutrition))
-        .route("/proactive-message", post(api::ai::generate_proactive_message))
-        // Новые маршруты для интеграции с холодильником
-        .route("/fridge/analyze", post(api::ai::analyze_fridge))
-        .route("/fridge/recipes", post(api::ai::generate_fridge_recipes))
-        .route("/fridge/report", get(api::ai::fridge_quick_report))
-        .with_state(AiService::from_env())
-}
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                println!("⚠️ Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-fn health_routes() -> Router {
-    use axum::routing::{get, post};
-    
-    Router::new()
-        .route("/chat", post(api::personal_health::personal_health_chat))
-        .route("/wellbeing", post(api::personal_health::daily_wellbeing_check))
-        .route("/dashboard", get(api::personal_health::health_dashboard))
-        .route("/recommendations", get(api::personal_health::get_recommendations))
-        .route("/mood-analysis", post(api::personal_health::mood_analysis))
-        .with_state(AiService::from_env())
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    println!("🛑 Shutdown signal received, stopping server...");
 }
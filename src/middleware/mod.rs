@@ -1,22 +1,58 @@
+use std::net::SocketAddr;
+
 use axum::{
-    extract::State,
-    http::{Request, header::AUTHORIZATION},
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::{Request, Method, StatusCode, HeaderValue, header::{AUTHORIZATION, CONTENT_TYPE, LINK, RETRY_AFTER}},
     middleware::Next,
-    response::Response,
-    body::Body,
+    response::{IntoResponse, Response},
+    body::{self, Body, Full},
 };
 use async_trait::async_trait;
 
 use crate::{
-    services::auth::{AuthService, Claims},
-    utils::errors::AppError,
+    api::versioning::ApiVersion,
+    services::{
+        api_tokens::ApiTokenService,
+        auth::{AuthService, Claims},
+        idempotency::{request_hash, IdempotencyClaim, IdempotencyService, IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH},
+    },
+    models::{
+        api_token::{TokenScope, API_TOKEN_PREFIX},
+        user::UserRole,
+    },
+    utils::{errors::{AppError, ErrorCode}, rate_limit::{client_ip, IpRateLimiter}},
     db::DbPool,
 };
 
 pub struct AuthMiddleware;
 
+/// Состояние `auth_middleware` для группы маршрутов
+#[derive(Clone)]
+pub struct AuthState {
+    pub pool: DbPool,
+    /// Скоупы (чтение, запись) для персональных токенов; `None` — группа доступна только по JWT
+    pub token_scopes: Option<(TokenScope, TokenScope)>,
+    /// `false` — запросы без токена пропускаются без Claims (обработчик берет `Option<Claims>`)
+    pub required: bool,
+}
+
+impl AuthState {
+    pub fn jwt_only(pool: DbPool) -> Self {
+        Self { pool, token_scopes: None, required: true }
+    }
+
+    pub fn with_token_scopes(pool: DbPool, read: TokenScope, write: TokenScope) -> Self {
+        Self { pool, token_scopes: Some((read, write)), required: true }
+    }
+
+    /// Токен необязателен, но если передан — проверяется как обычно
+    pub fn optional(pool: DbPool) -> Self {
+        Self { pool, token_scopes: None, required: false }
+    }
+}
+
 pub async fn auth_middleware(
-    State(pool): State<DbPool>,
+    State(auth_state): State<AuthState>,
     mut request: Request<Body>,
     next: Next<Body>,
 ) -> Result<Response, AppError> {
@@ -33,13 +69,33 @@ pub async fn auth_middleware(
             println!("🔐 AUTH MIDDLEWARE: Token found");
             token
         },
+        None if !auth_state.required => {
+            println!("🔐 AUTH MIDDLEWARE: No token found, proceeding anonymously");
+            return Ok(next.run(request).await);
+        }
         None => {
             println!("🔐 AUTH MIDDLEWARE: No token found");
             return Err(AppError::Unauthorized("Missing authorization token".to_string()));
         }
     };
 
-    let auth_service = AuthService::new(pool);
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let claims = ApiTokenService::new(auth_state.pool.clone()).authenticate(token).await?;
+        println!("🔐 AUTH MIDDLEWARE: API token verified for user {}", claims.sub);
+
+        // GET/HEAD требуют скоуп чтения, остальные методы — скоуп записи
+        let (read, write) = auth_state.token_scopes
+            .ok_or_else(|| AppError::Forbidden("API tokens are not accepted for this endpoint".to_string()))?;
+        let required = if matches!(*request.method(), Method::GET | Method::HEAD) { read } else { write };
+        claims.require_scope(required)?;
+        AuthService::new(auth_state.pool).check_account_status(&claims).await?;
+
+        tracing::Span::current().record("user.id", tracing::field::display(claims.sub));
+        request.extensions_mut().insert(claims);
+        return Ok(next.run(request).await);
+    }
+
+    let auth_service = AuthService::new(auth_state.pool);
     let claims = match auth_service.verify_token(token) {
         Ok(claims) => {
             println!("🔐 AUTH MIDDLEWARE: Token verified for user {}", claims.sub);
@@ -50,14 +106,36 @@ pub async fn auth_middleware(
             return Err(e);
         }
     };
+
+    // Блокировка и принудительный выход действуют и на уже выданные JWT
+    auth_service.check_account_status(&claims).await?;
     
-    // Add claims to request extensions
+    // Add claims to request extensions; пользователь запроса — в span для трассировки
+    tracing::Span::current().record("user.id", tracing::field::display(claims.sub));
     request.extensions_mut().insert(claims);
     
     println!("🔐 AUTH MIDDLEWARE: Proceeding to handler");
     Ok(next.run(request).await)
 }
 
+/// Пропускает только администраторов. Должен стоять после `auth_middleware`.
+pub async fn admin_middleware(
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, AppError> {
+    let is_admin = request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| matches!(claims.role, UserRole::Admin))
+        .unwrap_or(false);
+
+    if !is_admin {
+        return Err(AppError::Forbidden("Admin access required".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
 // Extractor for claims
 #[axum::async_trait]
 impl<S> axum::extract::FromRequestParts<S> for Claims
@@ -77,3 +155,148 @@ where
             .ok_or_else(|| AppError::Unauthorized("Missing claims".to_string()))
     }
 }
+
+/// Ограничение частоты по IP для маршрутов без авторизации. IP берется из заголовков
+/// прокси, а без них — из адреса соединения.
+pub async fn ip_rate_limit_middleware(
+    State(limiter): State<IpRateLimiter>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, AppError> {
+    let ip = client_ip(request.headers())
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    if !limiter.check(&ip) {
+        return Err(AppError::TooManyRequests("Rate limit exceeded, try again later".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Заголовки Deprecation, Sunset и Link на эндпоинт, который меняется в следующей версии API.
+/// Подключается к отдельному обработчику (`get(handler).layer(from_fn(deprecation_middleware))`);
+/// заголовки ставятся, только если версия запроса (путь или `X-API-Version`) устарела.
+pub async fn deprecation_middleware(
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = request.into_parts();
+    let version = ApiVersion::from_request_parts(&mut parts, &()).await?;
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+
+    if let Some(deprecation) = version.deprecation() {
+        let deprecated_since = deprecation.deprecated_since.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc();
+        let sunset = deprecation.sunset.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc();
+        let headers = [
+            // RFC 9745: дата в формате structured field (@unix-время)
+            ("deprecation", format!("@{}", deprecated_since.timestamp())),
+            // RFC 8594: HTTP-date
+            ("sunset", sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+        ];
+        for (name, value) in headers {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", deprecation.successor.prefix())) {
+            response.headers_mut().insert(LINK, link);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Поддержка заголовка Idempotency-Key для создающих запросов. Подключается к отдельному
+/// обработчику (`post(handler).layer(from_fn(idempotency_middleware))`) внутри группы
+/// с `auth_middleware`. Повтор с тем же ключом и телом возвращает сохраненный ответ,
+/// с другим телом — 422, пока первый запрос выполняется — 409 с Retry-After.
+/// Неуспешные ответы не сохраняются: такой запрос можно повторить с тем же ключом.
+pub async fn idempotency_middleware(
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, AppError> {
+    let key = match request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .map(str::trim)
+            .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+            .ok_or_else(|| AppError::BadRequest("Invalid Idempotency-Key header".to_string()))?
+            .to_string(),
+        None => return Ok(next.run(request).await),
+    };
+
+    let (user_id, pool) = match (request.extensions().get::<Claims>(), request.extensions().get::<DbPool>()) {
+        (Some(claims), Some(pool)) => (claims.sub, pool.clone()),
+        // Без пользователя ключ не к кому привязать
+        _ => return Ok(next.run(request).await),
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+    let path = parts.uri.path_and_query().map(|path| path.as_str()).unwrap_or_else(|| parts.uri.path());
+    let hash = request_hash(parts.method.as_str(), path, &body);
+
+    let service = IdempotencyService::new(pool);
+    match service.claim(user_id, &key, &hash).await? {
+        IdempotencyClaim::Acquired => {}
+        IdempotencyClaim::Replay { status, body } => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+            return Ok((
+                status,
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static("application/json")),
+                    (axum::http::HeaderName::from_static("idempotent-replayed"), HeaderValue::from_static("true")),
+                ],
+                body,
+            )
+                .into_response());
+        }
+        IdempotencyClaim::InProgress => {
+            let mut response = AppError::Conflict("A request with this Idempotency-Key is still in progress".to_string())
+                .with_code(ErrorCode::IdempotencyInProgress)
+                .into_response();
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from_static("1"));
+            return Ok(response);
+        }
+        IdempotencyClaim::Mismatch => {
+            return Err(AppError::UnprocessableEntity(
+                "Idempotency-Key was already used with a different request body".to_string(),
+            )
+            .with_code(ErrorCode::IdempotencyMismatch));
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    if !response.status().is_success() {
+        if let Err(e) = service.release(user_id, &key).await {
+            println!("⚠️ Failed to release idempotency key: {:?}", e);
+        }
+        return Ok(response);
+    }
+
+    let (parts, response_body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(response_body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let _ = service.release(user_id, &key).await;
+            return Err(AppError::InternalServerError(format!("Failed to read response body: {}", e)));
+        }
+    };
+
+    if let Err(e) = service.complete(user_id, &key, parts.status.as_u16(), &bytes).await {
+        // Запрос уже выполнен — ответ отдаем, даже если его не удалось сохранить
+        println!("⚠️ Failed to store idempotent response: {:?}", e);
+    }
+
+    Ok(Response::from_parts(parts, body::boxed(Full::from(bytes))))
+}
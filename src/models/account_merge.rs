@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::models::admin_job::AdminJobStatus;
+
+/// Части аккаунта, которые переносятся при объединении, в порядке переноса.
+/// Каждый домен переносится в своей транзакции.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeDomain {
+    /// Продукты холодильника, отходы и резервы под план питания
+    Fridge,
+    Diary,
+    /// Цели (с историей прогресса и ссылками на карточки) и достижения
+    Goals,
+    Weight,
+    /// Свои рецепты и оценки чужих
+    Recipes,
+    Favorites,
+    /// Посты и лайки
+    Posts,
+    Comments,
+    Follows,
+    DietaryProfile,
+    Notifications,
+}
+
+impl MergeDomain {
+    pub const ALL: [MergeDomain; 11] = [
+        MergeDomain::Fridge,
+        MergeDomain::Diary,
+        MergeDomain::Goals,
+        MergeDomain::Weight,
+        MergeDomain::Recipes,
+        MergeDomain::Favorites,
+        MergeDomain::Posts,
+        MergeDomain::Comments,
+        MergeDomain::Follows,
+        MergeDomain::DietaryProfile,
+        MergeDomain::Notifications,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeDomain::Fridge => "fridge",
+            MergeDomain::Diary => "diary",
+            MergeDomain::Goals => "goals",
+            MergeDomain::Weight => "weight",
+            MergeDomain::Recipes => "recipes",
+            MergeDomain::Favorites => "favorites",
+            MergeDomain::Posts => "posts",
+            MergeDomain::Comments => "comments",
+            MergeDomain::Follows => "follows",
+            MergeDomain::DietaryProfile => "dietary_profile",
+            MergeDomain::Notifications => "notifications",
+        }
+    }
+}
+
+/// Объединение аккаунтов. `completed_domains` — уже перенесенные домены:
+/// повторный запуск их пропускает. `counts` — перенесенные строки по доменам.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MergeJob {
+    pub id: Uuid,
+    pub source_user_id: Uuid,
+    pub target_user_id: Uuid,
+    pub status: AdminJobStatus,
+    pub completed_domains: Vec<String>,
+    pub counts: serde_json::Value,
+    pub error: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Сколько строк домена принадлежит исходному аккаунту (до переноса, с учетом дубликатов)
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeDomainCount {
+    pub domain: MergeDomain,
+    pub rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountMergeResult {
+    pub source_user_id: Uuid,
+    pub target_user_id: Uuid,
+    pub dry_run: bool,
+    /// Для пробного запуска — что будет перенесено, иначе — что перенесено
+    pub domains: Vec<MergeDomainCount>,
+    /// `None` для пробного запуска
+    pub job: Option<MergeJob>,
+}
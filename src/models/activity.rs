@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Запись журнала активности пользователя.
+/// `actor_id` отличается от `user_id`, когда действие совершил кто-то другой (коуч, администратор).
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ActivityLogEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "admin_job_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AdminJobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// Остановлена пользователем; уже обработанное сохраняется
+    Cancelled,
+}
+
+/// Фоновая задача администратора. `processed` включает и пропущенные записи,
+/// `failed` — только пропущенные; подробности о пропусках лежат в `report`.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AdminJob {
+    pub id: Uuid,
+    pub kind: String,
+    pub status: AdminJobStatus,
+    pub params: serde_json::Value,
+    pub total: i32,
+    pub processed: i32,
+    pub failed: i32,
+    pub last_processed_id: Option<Uuid>,
+    pub report: serde_json::Value,
+    pub error: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Параметры пересчета питательности рецептов
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecalculateNutritionParams {
+    /// Пересчитать только рецепты с этим ингредиентом (без учета регистра)
+    pub ingredient_name: Option<String>,
+}
+
+/// Рецепт, который не удалось пересчитать; его питательность осталась прежней
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedRecipe {
+    pub recipe_id: Uuid,
+    pub recipe_name: String,
+    pub reason: String,
+    pub unresolved_ingredients: Vec<String>,
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::models::user::UserRole;
+
+/// Действия администратора, которые пишутся в журнал
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    SuspendUser,
+    UnsuspendUser,
+    ForcePasswordReset,
+    MergeUsers,
+    GrantAdmin,
+}
+
+impl AdminAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminAction::SuspendUser => "suspend_user",
+            AdminAction::UnsuspendUser => "unsuspend_user",
+            AdminAction::ForcePasswordReset => "force_password_reset",
+            AdminAction::MergeUsers => "merge_users",
+            AdminAction::GrantAdmin => "grant_admin",
+        }
+    }
+}
+
+/// Строка поиска пользователей для поддержки
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminUserSummary {
+    pub id: Uuid,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub role: UserRole,
+    pub is_verified: bool,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Состояние аккаунта для поддержки: только метаданные и счетчики, без содержимого
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminUserDetail {
+    #[serde(flatten)]
+    pub summary: AdminUserSummary,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    pub sessions_invalidated_at: Option<DateTime<Utc>>,
+    pub counts: AdminUserCounts,
+    /// Последний вход или обновление сессии, использование токена API
+    pub last_activity_at: Option<DateTime<Utc>>,
+    pub active_sessions: i64,
+    pub active_api_tokens: i64,
+    /// Последние действия администраторов с этим аккаунтом
+    pub admin_actions: Vec<AdminAuditEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdminUserCounts {
+    pub fridge_items: i64,
+    pub diary_entries: i64,
+    pub recipes: i64,
+    pub posts: i64,
+    pub goals: i64,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminAuditEntry {
+    pub id: Uuid,
+    pub admin_id: Option<Uuid>,
+    pub action: String,
+    pub target_user_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Результат `it-cook create-admin`
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminGrant {
+    pub user: AdminUserSummary,
+    /// Аккаунт создан этой командой
+    pub created: bool,
+    /// Существующему пользователю выданы права администратора
+    pub promoted: bool,
+    /// Только для созданного аккаунта; показывается один раз, сменить после первого входа
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temporary_password: Option<String>,
+}
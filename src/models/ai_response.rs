@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Какой конвейер построил ответ ИИ
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AiResponseKind {
+    PersonalHealth,
+    FridgeAnalysis,
+}
+
+impl AiResponseKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiResponseKind::PersonalHealth => "personal_health",
+            AiResponseKind::FridgeAnalysis => "fridge_analysis",
+        }
+    }
+}
+
+/// Какие данные пользователя попали в промпт. Только ссылки с подписями,
+/// без текста промпта; подписи копируются, чтобы след пережил удаление записей.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AiProvenance {
+    pub template: String,
+    pub provider: String,
+    pub model: String,
+    /// Ответ построен без модели (Mock-провайдер или fallback)
+    pub degraded: bool,
+    pub diary_days: Vec<DiaryDayRef>,
+    pub wellbeing_entries: Vec<WellbeingEntryRef>,
+    pub fridge_items: Vec<FridgeItemRef>,
+    pub waste_entries: Vec<WasteEntryRef>,
+}
+
+/// День дневника питания, сводка которого попала в промпт
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiaryDayRef {
+    pub date: NaiveDate,
+    pub calories: f32,
+    pub water_ml: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WellbeingEntryRef {
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub mood_score: Option<i32>,
+    pub stress_level: Option<i32>,
+    pub sleep_hours: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FridgeItemRef {
+    pub id: Uuid,
+    pub name: String,
+    pub quantity: f32,
+    pub unit: String,
+    pub expiry_date: Option<DateTime<Utc>>,
+    /// Продукт был в списке истекающих
+    pub expiring: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WasteEntryRef {
+    pub id: Uuid,
+    pub name: String,
+    pub wasted_quantity: f32,
+    pub unit: String,
+    pub waste_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AiResponseRecord {
+    pub id: Uuid,
+    pub kind: String,
+    pub response: serde_json::Value,
+    pub provenance: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Ответ GET /ai/responses/{id}/provenance
+#[derive(Debug, Clone, Serialize)]
+pub struct AiResponseProvenance {
+    pub response_id: Uuid,
+    pub kind: String,
+    pub created_at: DateTime<Utc>,
+    pub provenance: serde_json::Value,
+}
+
+/// Запись истории в выгрузке
+#[derive(Debug, Clone, Serialize)]
+pub struct AiResponseExportEntry {
+    pub id: Uuid,
+    pub kind: String,
+    pub created_at: DateTime<Utc>,
+    pub response: serde_json::Value,
+    pub provenance: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiHistoryExport {
+    pub user_id: Uuid,
+    pub exported_at: DateTime<Utc>,
+    pub total: usize,
+    pub responses: Vec<AiResponseExportEntry>,
+}
+
+/// Раздел отчета о холодильнике; порядок вариантов — порядок разделов в отчете
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportSectionKind {
+    Overview,
+    AttentionNeeded,
+    UsageRecommendations,
+    OptimizationTips,
+}
+
+impl ReportSectionKind {
+    pub const ALL: [ReportSectionKind; 4] = [
+        ReportSectionKind::Overview,
+        ReportSectionKind::AttentionNeeded,
+        ReportSectionKind::UsageRecommendations,
+        ReportSectionKind::OptimizationTips,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportSectionKind::Overview => "overview",
+            ReportSectionKind::AttentionNeeded => "attention_needed",
+            ReportSectionKind::UsageRecommendations => "usage_recommendations",
+            ReportSectionKind::OptimizationTips => "optimization_tips",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+
+    /// Заголовок раздела: так же он называется в промпте, по нему ответ модели делится на разделы
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReportSectionKind::Overview => "Общее состояние холодильника",
+            ReportSectionKind::AttentionNeeded => "Продукты, требующие внимания",
+            ReportSectionKind::UsageRecommendations => "Рекомендации по использованию",
+            ReportSectionKind::OptimizationTips => "Советы по оптимизации",
+        }
+    }
+}
+
+/// Раздел отчета; `id` нужен для отзыва на конкретный раздел
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSection {
+    pub id: Uuid,
+    pub kind: ReportSectionKind,
+    pub title: String,
+    /// Текст модели для раздела
+    pub content: String,
+    /// Пункты, собранные без модели: уведомления, рекомендации, наблюдения
+    #[serde(default)]
+    pub items: Vec<String>,
+    /// Пользователь не раз отмечал такие разделы бесполезными: раздел в конце и короче
+    #[serde(default)]
+    pub demoted: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionRating {
+    Helpful,
+    NotHelpful,
+    /// В разделе ошибка — считается отрицательным отзывом, как и `not_helpful`
+    Wrong,
+}
+
+impl SectionRating {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SectionRating::Helpful => "helpful",
+            SectionRating::NotHelpful => "not_helpful",
+            SectionRating::Wrong => "wrong",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SectionFeedback {
+    pub section_id: Uuid,
+    pub rating: SectionRating,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
+    pub comment: Option<String>,
+}
+
+/// Тело POST /ai/fridge/report/{report_id}/feedback: отзывы на один или несколько разделов
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReportFeedbackRequest {
+    #[validate(length(min = 1, max = 4))]
+    pub sections: Vec<SectionFeedback>,
+}
+
+/// Как тип раздела показывается пользователю в следующих отчетах
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionPlacement {
+    Normal,
+    /// В конце отчета и короче
+    Demoted,
+    /// Не попадает ни в промпт, ни в ответ
+    Omitted,
+}
+
+/// Последние отзывы пользователя на тип раздела и то, что из них следует
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionPreference {
+    pub kind: ReportSectionKind,
+    pub helpful: i64,
+    /// `not_helpful` и `wrong`
+    pub negative: i64,
+    pub placement: SectionPlacement,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportFeedbackResponse {
+    pub report_id: Uuid,
+    pub recorded: usize,
+    /// Что изменится в следующих отчетах пользователя
+    pub sections: Vec<SectionPreference>,
+}
+
+/// Отзывы на тип раздела по всем пользователям (админка), худшие — первыми
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionFeedbackStats {
+    pub kind: ReportSectionKind,
+    pub helpful: i64,
+    pub not_helpful: i64,
+    pub wrong: i64,
+    /// Доля отрицательных отзывов, 0-1
+    pub negative_rate: f64,
+    /// У скольких пользователей раздел сейчас опущен или скрыт
+    pub users_demoted: i64,
+    pub users_omitted: i64,
+    /// Последние комментарии к отрицательным отзывам
+    pub recent_comments: Vec<String>,
+}
@@ -0,0 +1,45 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Расходы по одному срезу: провайдеру, функции или модели
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AiCostBreakdown {
+    pub key: String,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AiUserCost {
+    pub user_id: Uuid,
+    pub email: String,
+    pub calls: i64,
+    pub cost_usd: f64,
+}
+
+/// Расходы на ИИ за календарный месяц (оценка по таблице цен, а не счет провайдера)
+#[derive(Debug, Clone, Serialize)]
+pub struct AiCostReport {
+    pub month: NaiveDate,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub calls: i64,
+    pub cost_usd: f64,
+    /// Доля стоимости вызовов, токены которых оценены по длине текста
+    pub estimated_cost_usd: f64,
+    /// Прогноз на конец месяца при текущем темпе; для прошедших месяцев — итог
+    pub projected_month_end_usd: f64,
+    pub budget_usd: Option<f64>,
+    pub budget_used_percent: Option<f64>,
+    /// Фоновые функции ИИ отключены до конца месяца
+    pub non_essential_paused: bool,
+    pub by_provider: Vec<AiCostBreakdown>,
+    pub by_feature: Vec<AiCostBreakdown>,
+    pub by_model: Vec<AiCostBreakdown>,
+    /// Пользователи с наибольшими расходами
+    pub top_users: Vec<AiUserCost>,
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Префикс персонального токена — по нему middleware отличает токен от JWT
+pub const API_TOKEN_PREFIX: &str = "pat_";
+
+/// Права персонального токена
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TokenScope {
+    #[serde(rename = "read:fridge")]
+    ReadFridge,
+    #[serde(rename = "write:fridge")]
+    WriteFridge,
+    #[serde(rename = "read:diary")]
+    ReadDiary,
+    #[serde(rename = "write:diary")]
+    WriteDiary,
+    #[serde(rename = "read:recipes")]
+    ReadRecipes,
+    #[serde(rename = "write:recipes")]
+    WriteRecipes,
+    #[serde(rename = "read:goals")]
+    ReadGoals,
+    #[serde(rename = "write:goals")]
+    WriteGoals,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::ReadFridge => "read:fridge",
+            TokenScope::WriteFridge => "write:fridge",
+            TokenScope::ReadDiary => "read:diary",
+            TokenScope::WriteDiary => "write:diary",
+            TokenScope::ReadRecipes => "read:recipes",
+            TokenScope::WriteRecipes => "write:recipes",
+            TokenScope::ReadGoals => "read:goals",
+            TokenScope::WriteGoals => "write:goals",
+        }
+    }
+}
+
+/// Токен в БД: хранится только SHA-256 хеш секрета
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    #[sqlx(json)]
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateApiToken {
+    pub user_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Публичное представление токена (без хеша)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiTokenInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiToken> for ApiTokenInfo {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        }
+    }
+}
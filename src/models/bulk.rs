@@ -0,0 +1,25 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Почему элемент массовой операции пропущен
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkFailureReason {
+    /// Нет такого элемента или он чужой — эти случаи неразличимы
+    NotFound,
+    /// Элемент есть, но под операцию не подходит
+    NotEligible,
+}
+
+/// Пропущенный элемент: остальные обрабатываются как обычно
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkItemFailure {
+    pub id: Uuid,
+    pub reason: BulkFailureReason,
+}
+
+impl BulkItemFailure {
+    pub fn not_found(id: Uuid) -> Self {
+        Self { id, reason: BulkFailureReason::NotFound }
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::recipes::RecipeResponse,
+    models::{diary::DiaryEntry, goal::Goal, shopping::ShoppingListItem},
+};
+
+/// Действие карточки ИИ: что сервер выполнит по нажатию (POST /ai/actions/execute).
+/// Сервер добавляет действие, только когда может опереться на данные пользователя;
+/// карточка без действия — просто текст.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CardAction {
+    OpenRecipe { recipe_id: Uuid },
+    LogWater { amount_ml: i32 },
+    StartTimer { minutes: u32 },
+    CreateGoalFromTemplate { template: GoalTemplate },
+    AddToShoppingList { items: Vec<String> },
+    PrefilledDiaryEntry { draft: DiaryDraft },
+}
+
+/// Готовые цели, которые можно создать одним нажатием
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalTemplate {
+    /// 2 л воды в день
+    DailyWater,
+    /// 100 г белка в день
+    ProteinIntake,
+    /// 30 минут активности в день
+    DailyExercise,
+}
+
+/// Запись дневника, заполненная по прошлым записям пользователя; клиент может поправить
+/// поля перед выполнением
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct DiaryDraft {
+    #[validate(length(min = 1, max = 200))]
+    pub food_name: String,
+    #[validate(length(max = 200))]
+    pub brand: Option<String>,
+    #[validate(range(min = 0.1, max = 5000.0))]
+    pub portion_size: f32,
+    #[validate(length(min = 1, max = 20))]
+    pub unit: String,
+    #[validate(range(min = 0.0, max = 900.0))]
+    pub calories_per_100g: f32,
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub protein_per_100g: f32,
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub fat_per_100g: f32,
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub carbs_per_100g: f32,
+    #[validate(length(min = 1, max = 20))]
+    pub meal_type: String,
+}
+
+/// Ресурс, который создало или открыло действие
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CardActionResult {
+    Recipe { recipe: Box<RecipeResponse> },
+    WaterLogged { wellbeing_id: Uuid, amount_ml: i32 },
+    Timer { minutes: u32, ends_at: DateTime<Utc> },
+    Goal { goal: Goal },
+    ShoppingList { items: Vec<ShoppingListItem> },
+    DiaryEntry { entry: DiaryEntry },
+}
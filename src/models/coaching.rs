@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "coach_link_status", rename_all = "lowercase")]
+pub enum CoachLinkStatus {
+    Pending,
+    Active,
+    Revoked,
+}
+
+/// Домен данных клиента, к которому коуч может получить доступ на чтение
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
+#[sqlx(type_name = "coaching_domain", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CoachingDomain {
+    Diary,
+    Weight,
+    Goals,
+    Fridge,
+    Community,
+}
+
+impl CoachingDomain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoachingDomain::Diary => "diary",
+            CoachingDomain::Weight => "weight",
+            CoachingDomain::Goals => "goals",
+            CoachingDomain::Fridge => "fridge",
+            CoachingDomain::Community => "community",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct CoachLink {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub coach_email: String,
+    pub coach_id: Option<Uuid>,
+    pub invite_token: String,
+    pub status: CoachLinkStatus,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateCoachLink {
+    pub client_id: Uuid,
+    pub coach_email: String,
+    pub domains: Vec<CoachingDomain>,
+}
@@ -1,9 +1,12 @@
 // Placeholder models
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::models::recipe::DifficultyLevel;
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "post_type", rename_all = "lowercase")]
 pub enum PostType {
@@ -14,6 +17,16 @@ pub enum PostType {
     Achievement,
 }
 
+/// Кто видит пост. Гостям на лендинге показываются только `Public`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "post_visibility", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PostVisibility {
+    #[default]
+    Public,
+    FollowersOnly,
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Post {
     pub id: Uuid,
@@ -24,6 +37,56 @@ pub struct Post {
     pub media_urls: Vec<String>,
     pub tags: Vec<String>,
     pub location: Option<String>,
+    pub visibility: PostVisibility,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Прежняя версия текста поста
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PostEdit {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub content: String,
+    /// Когда этот текст был заменен
+    pub replaced_at: DateTime<Utc>,
+}
+
+/// Отметка «приготовил(а)» на посте с рецептом: одна на пользователя и пост
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct CookedIt {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub user_id: Uuid,
+    /// Оценка от 1 до 5
+    pub rating: Option<i16>,
+    pub media_id: Option<Uuid>,
+    pub note: Option<String>,
+    /// Фото и заметку видят все, кто видит пост; иначе — только автор поста
+    pub is_public: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CookedItInput {
+    pub rating: Option<i16>,
+    pub media_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub is_public: bool,
+}
+
+/// Отметка в галерее поста: с именем приготовившего и ссылкой на фото
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct CookedItEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub first_name: String,
+    pub avatar_url: Option<String>,
+    pub rating: Option<i16>,
+    pub photo_url: Option<String>,
+    pub note: Option<String>,
+    pub is_public: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -37,6 +100,8 @@ pub struct CreatePost {
     pub media_urls: Vec<String>,
     pub tags: Vec<String>,
     pub location: Option<String>,
+    pub visibility: PostVisibility,
+    pub link_previews: Vec<LinkPreview>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -74,3 +139,80 @@ pub struct Follow {
     pub following_id: Uuid,
     pub created_at: DateTime<Utc>,
 }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "activity_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Cooking,
+    Baking,
+    MealPrep,
+    Shopping,
+}
+
+/// Текущий статус пользователя; считается активным, пока не наступил `expires_at`
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ActivityStatus {
+    pub user_id: Uuid,
+    pub activity: ActivityKind,
+    pub recipe_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetActivityStatus {
+    pub user_id: Uuid,
+    pub activity: ActivityKind,
+    pub recipe_id: Option<Uuid>,
+    pub expires_in_minutes: i64,
+}
+
+/// Статус вместе с именем пользователя и названием рецепта — то, что видят подписчики
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FriendActivityStatus {
+    pub user_id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub avatar_url: Option<String>,
+    pub activity: ActivityKind,
+    pub recipe_id: Option<Uuid>,
+    pub recipe_name: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPreviewKind {
+    /// Публичная ссылка на рецепт из приложения
+    Recipe,
+    /// Сторонний сайт: данные OpenGraph
+    External,
+}
+
+/// Карточка ссылки из текста поста. Хранится в `posts.link_previews`, чтобы лента не делала join с рецептами.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub url: String,
+    pub kind: LinkPreviewKind,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    /// Рецепт по ссылке: по нему карточка обновляется при изменении рецепта
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<RecipeLinkPreview>,
+    /// Ссылка отозвана или рецепт удален: остается только название
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeLinkPreview {
+    pub name: String,
+    pub image_url: Option<String>,
+    pub total_time_minutes: Option<i32>,
+    pub difficulty: DifficultyLevel,
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::models::goal::GoalType;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationKind {
+    Chat,
+    /// Еженедельный разбор целей, начатый помощником
+    GoalReview,
+}
+
+impl ConversationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversationKind::Chat => "chat",
+            ConversationKind::GoalReview => "goal_review",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageRole {
+    /// Данные, на которых построен диалог; модель получает их как контекст
+    System,
+    Assistant,
+    User,
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::System => "system",
+            MessageRole::Assistant => "assistant",
+            MessageRole::User => "user",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub kind: String,
+    pub title: String,
+    pub week_start: Option<NaiveDate>,
+    pub review: Option<Json<GoalReviewContext>>,
+    pub outcome: Option<Json<GoalReviewOutcome>>,
+    pub concluded_at: Option<DateTime<Utc>>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Conversation {
+    pub fn is_goal_review(&self) -> bool {
+        self.kind == ConversationKind::GoalReview.as_str()
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ConversationMessage {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub role: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Данные недели для разбора целей. Собираются детерминированно из истории прогресса,
+/// веса и дневника; текст вопросов — работа модели.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalReviewContext {
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub goals: Vec<GoalWeekSummary>,
+    pub weight: Option<WeightDelta>,
+    pub diary: DiaryAdherence,
+    /// Дней подряд с записями в дневнике на конец недели
+    pub diary_streak_days: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalWeekSummary {
+    pub goal_id: Uuid,
+    pub title: String,
+    pub goal_type: GoalType,
+    pub unit: String,
+    pub target_value: f32,
+    pub daily_target: Option<f32>,
+    /// Значение на начало недели (последняя точка истории до нее или первая за неделю)
+    pub start_value: Option<f32>,
+    pub current_value: f32,
+    pub week_change: Option<f32>,
+    /// Сколько раз за неделю обновлялся прогресс
+    pub progress_updates: usize,
+    /// Для целей по калориям и белку — выполнение дневной нормы по дневнику
+    pub daily: Option<DailyTargetStats>,
+    pub progress_percentage: f32,
+    pub on_track: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DailyTargetStats {
+    pub days_hit: u32,
+    pub days_missed: u32,
+    /// Дни без записей в дневнике не считаются ни выполненными, ни пропущенными
+    pub days_without_data: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightDelta {
+    pub start_date: NaiveDate,
+    pub start_weight: f32,
+    pub end_date: NaiveDate,
+    pub end_weight: f32,
+    pub delta: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiaryAdherence {
+    pub days_logged: u32,
+    pub days_in_week: u32,
+    pub adherence_percentage: f64,
+}
+
+/// Итог разбора: решение по каждой цели недели
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalReviewOutcome {
+    pub summary: String,
+    pub goals: Vec<GoalDecision>,
+    /// Итог построен без модели — все цели оставлены как есть
+    pub degraded: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalDecision {
+    pub goal_id: Uuid,
+    pub title: String,
+    pub action: GoalAction,
+    pub current_target: f32,
+    /// Только для `adjust`
+    pub suggested_target: Option<f32>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalAction {
+    Keep,
+    Adjust,
+}
+
+/// Изменение цели, примененное из итога разбора
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedGoalChange {
+    pub goal_id: Uuid,
+    pub title: String,
+    pub previous_target: f32,
+    pub new_target: f32,
+}
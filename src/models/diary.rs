@@ -1,7 +1,80 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
-use chrono::{DateTime, Utc, NaiveDate};
+use chrono::{DateTime, Duration, Utc, NaiveDate, NaiveTime};
+
+use crate::{models::admin_job::AdminJobStatus, utils::food_category::PortionKind};
+
+/// Откуда взяты значения питательности записи
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "nutrition_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NutritionSource {
+    /// Введены пользователем
+    #[default]
+    User,
+    /// Заполнены из базы продуктов
+    Database,
+    /// Оценка ИИ; сводки могут ее исключать
+    AiEstimate,
+}
+
+/// Границы приемов пищи по местному времени: до `lunch_from` — завтрак, до `dinner_from` — обед,
+/// до `snack_from` — ужин, позже — перекус
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MealTimeBoundaries {
+    pub lunch_from: NaiveTime,
+    pub dinner_from: NaiveTime,
+    pub snack_from: NaiveTime,
+}
+
+impl Default for MealTimeBoundaries {
+    fn default() -> Self {
+        Self {
+            lunch_from: NaiveTime::from_hms_opt(11, 0, 0).unwrap_or_default(),
+            dinner_from: NaiveTime::from_hms_opt(16, 0, 0).unwrap_or_default(),
+            snack_from: NaiveTime::from_hms_opt(21, 0, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl MealTimeBoundaries {
+    /// Прием пищи, к которому относится `consumed_at` в часовом поясе пользователя
+    pub fn meal_type_at(&self, consumed_at: DateTime<Utc>, utc_offset_minutes: i32) -> &'static str {
+        let local_time = (consumed_at + Duration::minutes(utc_offset_minutes as i64)).time();
+        if local_time < self.lunch_from {
+            "breakfast"
+        } else if local_time < self.dinner_from {
+            "lunch"
+        } else if local_time < self.snack_from {
+            "dinner"
+        } else {
+            "snack"
+        }
+    }
+}
+
+/// Вечернее напоминание о дневнике: приходит в `time` по местному времени,
+/// только если за местный день нет ни одной записи
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiaryReminderPreferences {
+    pub enabled: bool,
+    pub time: NaiveTime,
+    /// Местная дата последнего напоминания — не больше одного в день
+    pub last_reminded_on: Option<NaiveDate>,
+}
+
+impl Default for DiaryReminderPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: NaiveTime::from_hms_opt(20, 0, 0).unwrap_or_default(),
+            last_reminded_on: None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct DiaryEntry {
@@ -19,6 +92,9 @@ pub struct DiaryEntry {
     pub sugar_per_100g: Option<f32>,
     pub sodium_per_100g: Option<f32>,
     pub meal_type: String,
+    pub nutrition_source: NutritionSource,
+    /// Фото приема пищи (`media_files`)
+    pub media_id: Option<Uuid>,
     pub consumed_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -38,6 +114,7 @@ pub struct CreateDiaryEntry {
     pub fiber_per_100g: Option<f32>,
     pub sugar_per_100g: Option<f32>,
     pub sodium_per_100g: Option<f32>,
+    pub media_id: Option<Uuid>,
     pub meal_type: String,
     pub consumed_at: DateTime<Utc>,
 }
@@ -130,3 +207,338 @@ pub struct CreateFoodItem {
     pub sodium_per_100g: Option<f32>,
     pub created_by: Uuid,
 }
+
+impl FoodItem {
+    /// Питательность для заданного количества граммов
+    pub fn nutrition_for(&self, grams: f32) -> NutritionTotals {
+        let multiplier = grams / 100.0;
+        NutritionTotals {
+            calories: self.calories_per_100g * multiplier,
+            protein: self.protein_per_100g * multiplier,
+            fat: self.fat_per_100g * multiplier,
+            carbs: self.carbs_per_100g * multiplier,
+            fiber: self.fiber_per_100g.unwrap_or(0.0) * multiplier,
+            sugar: self.sugar_per_100g.unwrap_or(0.0) * multiplier,
+            sodium: self.sodium_per_100g.unwrap_or(0.0) * multiplier,
+        }
+    }
+}
+
+/// Шаблон приема пищи ("мой обычный завтрак")
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MealTemplate {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Строка шаблона с порцией по умолчанию
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MealTemplateItem {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub position: i32,
+    pub food_name: String,
+    pub brand: Option<String>,
+    pub portion_size: f32,
+    pub unit: String,
+    pub calories_per_100g: f32,
+    pub protein_per_100g: f32,
+    pub fat_per_100g: f32,
+    pub carbs_per_100g: f32,
+    pub fiber_per_100g: Option<f32>,
+    pub sugar_per_100g: Option<f32>,
+    pub sodium_per_100g: Option<f32>,
+}
+
+impl MealTemplateItem {
+    /// Питательность строки для заданной порции
+    pub fn nutrition_for(&self, portion_size: f32) -> NutritionTotals {
+        let multiplier = portion_size / 100.0;
+        NutritionTotals {
+            calories: self.calories_per_100g * multiplier,
+            protein: self.protein_per_100g * multiplier,
+            fat: self.fat_per_100g * multiplier,
+            carbs: self.carbs_per_100g * multiplier,
+            fiber: self.fiber_per_100g.unwrap_or(0.0) * multiplier,
+            sugar: self.sugar_per_100g.unwrap_or(0.0) * multiplier,
+            sodium: self.sodium_per_100g.unwrap_or(0.0) * multiplier,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateMealTemplate {
+    pub user_id: Uuid,
+    pub name: String,
+    pub items: Vec<CreateMealTemplateItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateMealTemplateItem {
+    pub food_name: String,
+    pub brand: Option<String>,
+    pub portion_size: f32,
+    pub unit: String,
+    pub calories_per_100g: f32,
+    pub protein_per_100g: f32,
+    pub fat_per_100g: f32,
+    pub carbs_per_100g: f32,
+    pub fiber_per_100g: Option<f32>,
+    pub sugar_per_100g: Option<f32>,
+    pub sodium_per_100g: Option<f32>,
+}
+
+/// Шаблон вместе со строками (в порядке `position`)
+#[derive(Debug, Clone)]
+pub struct MealTemplateWithItems {
+    pub template: MealTemplate,
+    pub items: Vec<MealTemplateItem>,
+}
+
+impl MealTemplateWithItems {
+    /// Итог по порциям по умолчанию — считается при каждом чтении, поэтому всегда актуален
+    pub fn totals(&self) -> NutritionTotals {
+        self.items
+            .iter()
+            .fold(NutritionTotals::default(), |totals, item| totals.plus(&item.nutrition_for(item.portion_size)))
+    }
+}
+
+/// Запись шаблона в дневник: дата, прием пищи и переопределенные порции строк
+#[derive(Debug, Clone)]
+pub struct LogMealTemplate {
+    pub date: NaiveDate,
+    pub meal_type: String,
+    pub portion_overrides: Vec<(Uuid, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct NutritionTotals {
+    pub calories: f32,
+    pub protein: f32,
+    pub fat: f32,
+    pub carbs: f32,
+    pub fiber: f32,
+    pub sugar: f32,
+    pub sodium: f32,
+}
+
+impl NutritionTotals {
+    pub fn plus(self, other: &NutritionTotals) -> NutritionTotals {
+        NutritionTotals {
+            calories: self.calories + other.calories,
+            protein: self.protein + other.protein,
+            fat: self.fat + other.fat,
+            carbs: self.carbs + other.carbs,
+            fiber: self.fiber + other.fiber,
+            sugar: self.sugar + other.sugar,
+            sodium: self.sodium + other.sodium,
+        }
+    }
+}
+
+/// Калории и БЖУ за день (граммы)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MacroBudget {
+    pub calories: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbs: f64,
+}
+
+/// Откуда взята дневная норма
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetSource {
+    /// Активная цель по калориям
+    Goal,
+    /// Расход энергии по профилю (Миффлин — Сан Жеор с коэффициентом активности)
+    Tdee,
+}
+
+/// Сколько калорий из остатка отвести на еще не записанный прием пищи
+#[derive(Debug, Clone, Serialize)]
+pub struct MealSlotSuggestion {
+    pub meal_type: &'static str,
+    pub calories: f64,
+    /// Доля остатка, 0–1
+    pub share: f64,
+}
+
+/// Остаток дневной нормы на текущий местный день.
+/// Без цели и без заполненного профиля норма, остаток и подсказки пустые, `setup_needed` — true.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemainingBudget {
+    pub date: NaiveDate,
+    pub source: Option<BudgetSource>,
+    pub budget: Option<MacroBudget>,
+    pub consumed: MacroBudget,
+    pub remaining: Option<MacroBudget>,
+    pub over_budget: bool,
+    /// Приемы пищи, в которых сегодня уже есть записи
+    pub logged_meals: Vec<String>,
+    pub suggestions: Vec<MealSlotSuggestion>,
+    pub setup_needed: bool,
+}
+
+/// Заполнение питательности старых записей дневника без калорий.
+/// `processed` включает и записи, для которых значения найти не удалось (`unresolved`).
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct NutritionBackfillJob {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: AdminJobStatus,
+    pub use_ai: bool,
+    pub total: i32,
+    pub processed: i32,
+    pub filled_from_database: i32,
+    pub filled_by_ai: i32,
+    pub unresolved: i32,
+    pub last_processed_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Порция, которую пользователь уже записывал для продукта
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RememberedPortion {
+    pub portion_size: f32,
+    pub unit: String,
+    /// Прием пищи, в который порция была записана последний раз
+    pub meal_type: String,
+    pub uses: i32,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Запомненные порции продукта: последняя использованная — первой
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FoodMemory {
+    pub food_name: String,
+    #[sqlx(json)]
+    pub portions: Vec<RememberedPortion>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Типовая порция для быстрого выбора
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PortionPreset {
+    pub portion_size: f32,
+    pub unit: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodMemoryResponse {
+    pub name: String,
+    pub portion_kind: PortionKind,
+    /// Пусто, если продукт еще не записывали
+    pub remembered: Vec<RememberedPortion>,
+    pub presets: Vec<PortionPreset>,
+}
+
+/// Фото приема пищи для сетки галереи
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct DiaryPhoto {
+    pub entry_id: Uuid,
+    pub media_id: Uuid,
+    pub url: String,
+    /// Миниатюра 400px; у файлов, загруженных до миниатюр, ее нет — клиенту остается `url`
+    pub thumbnail_url: Option<String>,
+    pub meal_type: String,
+    pub food_name: String,
+    /// Калории порции
+    pub calories: f32,
+    pub consumed_at: DateTime<Utc>,
+    /// Местный день пользователя, по которому фото группируются
+    #[serde(skip)]
+    pub local_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiaryPhotoDay {
+    pub date: NaiveDate,
+    pub photos: Vec<DiaryPhoto>,
+}
+
+/// Страница галереи: дни по убыванию, фото внутри дня — новые первыми.
+/// День может продолжиться на следующей странице.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiaryPhotoPage {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub days: Vec<DiaryPhotoDay>,
+    pub has_more: bool,
+}
+
+/// Число фото за местный месяц — для шкалы прокрутки галереи
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct DiaryPhotoMonth {
+    /// "2026-10"
+    pub month: String,
+    pub photos: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn boundary_minute_belongs_to_the_next_meal() {
+        let boundaries = MealTimeBoundaries::default();
+        let cases = [
+            ((0, 0), "breakfast"),
+            ((10, 59), "breakfast"),
+            ((11, 0), "lunch"),
+            ((15, 59), "lunch"),
+            ((16, 0), "dinner"),
+            ((20, 59), "dinner"),
+            ((21, 0), "snack"),
+            ((23, 59), "snack"),
+        ];
+
+        for ((hour, minute), meal_type) in cases {
+            assert_eq!(boundaries.meal_type_at(at(hour, minute), 0), meal_type, "{:02}:{:02}", hour, minute);
+        }
+    }
+
+    #[test]
+    fn local_time_decides_the_meal() {
+        let boundaries = MealTimeBoundaries::default();
+
+        // 09:30 UTC — обед в Москве (UTC+3) и все еще завтрак в Нью-Йорке (UTC-4)
+        assert_eq!(boundaries.meal_type_at(at(9, 30), 180), "lunch");
+        assert_eq!(boundaries.meal_type_at(at(9, 30), -240), "breakfast");
+        // Местное время переходит через полночь: 22:30 UTC в UTC+3 — 01:30 следующего дня
+        assert_eq!(boundaries.meal_type_at(at(22, 30), 180), "breakfast");
+        assert_eq!(boundaries.meal_type_at(at(1, 0), -180), "snack");
+    }
+
+    #[test]
+    fn custom_boundaries_move_the_meals() {
+        let boundaries = MealTimeBoundaries {
+            lunch_from: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            dinner_from: NaiveTime::from_hms_opt(18, 30, 0).unwrap(),
+            snack_from: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        };
+
+        assert_eq!(boundaries.meal_type_at(at(12, 0), 0), "breakfast");
+        assert_eq!(boundaries.meal_type_at(at(18, 29), 0), "lunch");
+        assert_eq!(boundaries.meal_type_at(at(18, 30), 0), "dinner");
+        assert_eq!(boundaries.meal_type_at(at(21, 59), 0), "dinner");
+        // Неполный JSON берет недостающие границы по умолчанию
+        let partial: MealTimeBoundaries = serde_json::from_str(r#"{ "lunch_from": "12:00:00" }"#).unwrap();
+        assert_eq!(partial.lunch_from, NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+        assert_eq!(partial.snack_from, MealTimeBoundaries::default().snack_from);
+    }
+}
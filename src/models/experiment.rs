@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Эксперимент, объявленный в коде. Первый вариант — контрольный.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ExperimentDefinition {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub variants: &'static [ExperimentVariant],
+    /// Начало и конец в RFC 3339; нет — без ограничения
+    pub starts_at: Option<&'static str>,
+    pub ends_at: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ExperimentVariant {
+    pub name: &'static str,
+    /// Доля пользователей относительно суммы весов всех вариантов
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentStatus {
+    Scheduled,
+    Running,
+    Ended,
+    Stopped,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ExperimentStop {
+    pub experiment_key: String,
+    pub stopped_by: Option<Uuid>,
+    pub stopped_at: DateTime<Utc>,
+}
+
+/// Первый показ эксперимента пользователю
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ExperimentExposure {
+    pub experiment_key: String,
+    pub user_id: Uuid,
+    pub variant: String,
+    pub exposed_at: DateTime<Utc>,
+}
+
+/// Эксперимент в админке: сколько пользователей увидели каждый вариант
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentSummary {
+    #[serde(flatten)]
+    pub definition: ExperimentDefinition,
+    pub status: ExperimentStatus,
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub stopped_by: Option<Uuid>,
+    /// Вариант → сколько пользователей видели эксперимент; варианты без показов — с нулем
+    pub exposure_counts: BTreeMap<String, i64>,
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Флаг, объявленный в коде, со значением по умолчанию
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FeatureDefinition {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub default_enabled: bool,
+}
+
+/// Глобальное состояние флага из БД
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FeatureFlagState {
+    pub key: String,
+    pub enabled: bool,
+    pub rollout_percentage: Option<i32>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FeatureFlagOverride {
+    pub user_id: Uuid,
+    pub flag_key: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Откуда взято итоговое значение флага
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Default,
+    Global,
+    Rollout,
+    UserOverride,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedFlag {
+    pub key: String,
+    pub enabled: bool,
+    pub source: FlagSource,
+}
@@ -1,7 +1,11 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{postgres::{PgHasArrayType, PgTypeInfo}, FromRow};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use validator::Validate;
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+
+use crate::utils::{format::{round_money, Locale}, i18n, ingredient_matcher::normalize_ingredient, units::{to_base, BaseUnit}};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "fridge_category", rename_all = "lowercase")]
@@ -18,6 +22,52 @@ pub enum FridgeCategory {
     Other,
 }
 
+impl FridgeCategory {
+    /// Порядок категорий по умолчанию
+    pub const ALL: [FridgeCategory; 10] = [
+        FridgeCategory::Dairy,
+        FridgeCategory::Meat,
+        FridgeCategory::Fish,
+        FridgeCategory::Vegetables,
+        FridgeCategory::Fruits,
+        FridgeCategory::Grains,
+        FridgeCategory::Beverages,
+        FridgeCategory::Condiments,
+        FridgeCategory::Snacks,
+        FridgeCategory::Other,
+    ];
+
+    /// Разбор названия категории без учета регистра ("dairy", "Dairy")
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .iter()
+            .find(|category| format!("{:?}", category).eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn label_ru(&self) -> &'static str {
+        self.label(Locale::Ru)
+    }
+
+    /// Название категории для пользователя
+    pub fn label(&self, locale: Locale) -> &'static str {
+        let key = match self {
+            FridgeCategory::Dairy => "category.dairy",
+            FridgeCategory::Meat => "category.meat",
+            FridgeCategory::Fish => "category.fish",
+            FridgeCategory::Vegetables => "category.vegetables",
+            FridgeCategory::Fruits => "category.fruits",
+            FridgeCategory::Grains => "category.grains",
+            FridgeCategory::Beverages => "category.beverages",
+            FridgeCategory::Condiments => "category.condiments",
+            FridgeCategory::Snacks => "category.snacks",
+            FridgeCategory::Other => "category.other",
+        };
+        i18n::t(locale, key)
+    }
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct FridgeItem {
     pub id: Uuid,
@@ -33,16 +83,134 @@ pub struct FridgeItem {
     pub purchase_date: DateTime<Utc>,
     pub notes: Option<String>,
     pub location: Option<String>, // "fridge", "freezer", "pantry"
+    /// Магазин, где куплен продукт (свободный текст)
+    pub store: Option<String>,
     // Новые поля для диетических ограничений
     pub contains_allergens: Vec<Allergen>, // Содержит аллергены
     pub contains_intolerances: Vec<Intolerance>, // Содержит непереносимые вещества  
     pub suitable_for_diets: Vec<DietType>, // Подходит для диет
     pub ingredients: Option<String>, // Состав продукта
     pub nutritional_info: Option<String>, // Пищевая ценность
+    /// Продукт не удаляется: съеденный, выброшенный или удаленный остается в истории
+    pub status: FridgeItemStatus,
+    /// Когда продукт перестал быть активным
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Стоимость уже съеденной части
+    pub consumed_value: f32,
+    /// Стоимость выброшенной части
+    pub wasted_value: f32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Жизненный цикл продукта в холодильнике
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "fridge_item_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum FridgeItemStatus {
+    #[default]
+    Active,
+    /// Съеден полностью
+    Consumed,
+    /// Выброшен (остаток целиком)
+    Wasted,
+    /// Удален пользователем без указания причины
+    Removed,
+}
+
+/// Мягкий резерв продукта под слот плана питания. Количество — в единицах продукта.
+/// Резерв действует до конца дня слота, потом перестает учитываться.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FridgeReservation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+    pub slot_id: Uuid,
+    pub slot_date: NaiveDate,
+    pub recipe_id: Option<Uuid>,
+    pub ingredient_name: String,
+    pub quantity: f32,
+    pub unit: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Слот плана питания, под который резервируются продукты
+#[derive(Debug, Clone, Copy)]
+pub struct PlanSlot {
+    pub slot_id: Uuid,
+    pub date: NaiveDate,
+    pub recipe_id: Option<Uuid>,
+}
+
+/// Ингредиент, которого не хватило для резерва (количество — в единицах рецепта)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReservationShortfall {
+    pub ingredient_name: String,
+    pub missing_quantity: f64,
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotReservation {
+    pub slot_id: Uuid,
+    pub reservations: Vec<FridgeReservation>,
+    pub shortfalls: Vec<ReservationShortfall>,
+}
+
+/// Списание затронуло зарезервированный остаток продукта
+#[derive(Debug, Clone, Serialize)]
+pub struct ReservationConflict {
+    /// Сколько из списанного было зарезервировано (в единицах продукта)
+    pub reserved_quantity_used: f32,
+    pub unit: String,
+    /// Слоты плана, резерв которых больше не обеспечен
+    pub slot_ids: Vec<Uuid>,
+}
+
+/// Что понято из заметки продукта: напоминание использовать к дате и ссылка на рецепт
+#[derive(Debug, Clone, Serialize)]
+pub struct FridgeItemNoteLinks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminder: Option<ItemUseReminder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<LinkedRecipe>,
+    /// Пояснения для пользователя: «Напоминание создано на 26.04.2026 («до пятницы»)»
+    pub understood: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemUseReminder {
+    pub remind_on: NaiveDate,
+    /// Фраза заметки, из которой понята дата
+    pub phrase: String,
+    pub sent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkedRecipe {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Запись истории: что случилось с продуктом, когда и на какую сумму
+#[derive(Debug, Clone, Serialize)]
+pub struct FridgeHistoryEntry {
+    pub item_id: Uuid,
+    pub name: String,
+    pub brand: Option<String>,
+    pub category: FridgeCategory,
+    /// Количество в момент, когда продукт закончился
+    pub quantity: f32,
+    pub unit: String,
+    pub status: FridgeItemStatus,
+    pub purchase_date: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub purchased_value: f64,
+    pub consumed_value: f64,
+    pub wasted_value: f64,
+    pub waste_reasons: Vec<WasteReason>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateFridgeItem {
     pub user_id: Uuid,
@@ -57,6 +225,7 @@ pub struct CreateFridgeItem {
     pub purchase_date: DateTime<Utc>,
     pub notes: Option<String>,
     pub location: Option<String>,
+    pub store: Option<String>,
     // Новые поля для диетических ограничений
     pub contains_allergens: Vec<Allergen>,
     pub contains_intolerances: Vec<Intolerance>,
@@ -65,42 +234,149 @@ pub struct CreateFridgeItem {
     pub nutritional_info: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Насколько срочно нужно использовать продукт. Дни считаются полными сутками
+/// от текущего момента, как в `days_until_expiry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryUrgency {
+    /// Срок уже прошел
+    Expired,
+    /// Истекает в ближайшие 24 часа
+    Today,
+    /// Через 1–3 дня
+    Soon,
+    /// Через 4–7 дней
+    ThisWeek,
+    /// Больше чем через неделю
+    Later,
+}
+
+impl ExpiryUrgency {
+    /// Продукт, срок которого истекает ровно в `now`, еще не считается просроченным
+    pub fn for_expiry(expiry: DateTime<Utc>, now: DateTime<Utc>) -> Self {
+        if expiry < now {
+            return ExpiryUrgency::Expired;
+        }
+
+        match (expiry - now).num_days() {
+            0 => ExpiryUrgency::Today,
+            1..=3 => ExpiryUrgency::Soon,
+            4..=7 => ExpiryUrgency::ThisWeek,
+            _ => ExpiryUrgency::Later,
+        }
+    }
+}
+
+/// Частичное изменение продукта: отсутствующее поле не меняется.
+/// У `expiry_date`, `notes` и `location` `null` очищает значение, пустой `brand` — тоже.
+/// Название обязательно: `"name": null` — ошибка проверки, а не «без изменений».
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct UpdateFridgeItem {
-    pub name: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_nullable_sanitized")]
+    pub name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub brand: Option<String>,
     pub quantity: Option<f32>,
+    #[validate(length(min = 1))]
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub unit: Option<String>,
     pub category: Option<FridgeCategory>,
     pub price_per_unit: Option<f32>,
     pub total_price: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
     pub expiry_date: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_nullable_sanitized")]
     pub notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_nullable_sanitized")]
     pub location: Option<Option<String>>,
 }
 
+/// Присутствующее поле, даже `null`, — `Some`; отсутствующее дает `None` через `#[serde(default)]`
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 impl FridgeItem {
-    pub fn is_expired(&self) -> bool {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
         match self.expiry_date {
-            Some(expiry) => expiry < Utc::now(),
+            Some(expiry) => expiry < now,
             None => false,
         }
     }
 
-    pub fn days_until_expiry(&self) -> Option<i32> {
-        self.expiry_date.map(|expiry| {
-            let now = Utc::now();
-            (expiry - now).num_days() as i32
-        })
+    pub fn days_until_expiry(&self, now: DateTime<Utc>) -> Option<i32> {
+        self.expiry_date.map(|expiry| (expiry - now).num_days() as i32)
     }
 
-    pub fn is_expiring_soon(&self, days: i32) -> bool {
-        match self.days_until_expiry() {
+    pub fn is_expiring_soon(&self, days: i32, now: DateTime<Utc>) -> bool {
+        match self.days_until_expiry(now) {
             Some(days_left) => days_left <= days && days_left >= 0,
             None => false,
         }
     }
 
+    /// Срочность по сроку годности на момент `now`; `None`, если срок не указан
+    pub fn expiry_urgency(&self, now: DateTime<Utc>) -> Option<ExpiryUrgency> {
+        self.expiry_date.map(|expiry| ExpiryUrgency::for_expiry(expiry, now))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == FridgeItemStatus::Active
+    }
+
+    /// Стоимость того, что еще лежит в холодильнике
+    pub fn remaining_value(&self) -> f32 {
+        if self.is_active() {
+            self.calculate_total_value()
+        } else {
+            0.0
+        }
+    }
+
+    /// Стоимость покупки: съеденное + выброшенное + остаток.
+    /// Для удаленного продукта учитывается только то, что было списано до удаления.
+    pub fn purchased_value(&self) -> f32 {
+        self.consumed_value + self.wasted_value + self.remaining_value()
+    }
+
+    /// Списывает `quantity` (или весь остаток) как съеденное либо выброшенное и возвращает
+    /// стоимость списанного. Если остаток закончился, продукт получает статус `outcome`;
+    /// количество и цена тогда остаются такими, какими были в момент списания.
+    pub fn write_off(&mut self, quantity: Option<f32>, outcome: FridgeItemStatus, now: DateTime<Utc>) -> f32 {
+        let remaining_value = self.calculate_total_value();
+        let quantity = quantity.unwrap_or(self.quantity).clamp(0.0, self.quantity.max(0.0));
+        let finishes = quantity >= self.quantity;
+
+        let value = if finishes {
+            remaining_value
+        } else if self.quantity > 0.0 {
+            round_money(remaining_value as f64 * quantity as f64 / self.quantity as f64) as f32
+        } else {
+            0.0
+        };
+
+        match outcome {
+            FridgeItemStatus::Wasted => self.wasted_value += value,
+            _ => self.consumed_value += value,
+        }
+
+        if finishes {
+            self.status = outcome;
+            self.finished_at = Some(now);
+        } else {
+            self.quantity -= quantity;
+            self.total_price = Some(round_money((remaining_value - value) as f64) as f32);
+        }
+
+        self.updated_at = now;
+        value
+    }
+
     // Новые методы для расчета стоимости
     pub fn calculate_total_value(&self) -> f32 {
         self.total_price.unwrap_or_else(|| {
@@ -108,17 +384,16 @@ impl FridgeItem {
         })
     }
 
-    pub fn calculate_waste_value(&self, wasted_quantity: f32) -> f32 {
-        if self.quantity > 0.0 {
-            let value_per_unit = self.calculate_total_value() / self.quantity;
-            value_per_unit * wasted_quantity
-        } else {
-            0.0
+    /// Цена за кг / л / шт — чтобы сравнивать продукты, купленные в разных единицах.
+    /// `None`, если цена неизвестна или единица не приводится к базовой.
+    pub fn normalized_unit_price(&self) -> Option<(f64, BaseUnit)> {
+        let (base_quantity, base) = to_base(self.quantity as f64, &self.unit)?;
+        if base_quantity <= 0.0 {
+            return None;
         }
-    }
 
-    pub fn calculate_remaining_value(&self) -> f32 {
-        self.calculate_total_value()
+        let total = self.total_price.or_else(|| self.price_per_unit.map(|price| price * self.quantity))?;
+        Some((round_money(total as f64 / base_quantity), base))
     }
 }
 
@@ -162,6 +437,16 @@ pub struct FoodWaste {
     pub created_at: DateTime<Utc>,
 }
 
+/// Запись об отходах в истории. `source_item_exists` — продукт, с которого списаны отходы,
+/// еще хранится и на него можно сослаться; после окончательного удаления продукта
+/// `original_item_id` обнуляется, а название, бренд и категория остаются в самой записи.
+#[derive(Debug, Clone, Serialize)]
+pub struct WasteHistoryEntry {
+    #[serde(flatten)]
+    pub waste: FoodWaste,
+    pub source_item_exists: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "waste_reason", rename_all = "lowercase")]
 pub enum WasteReason {
@@ -173,6 +458,19 @@ pub enum WasteReason {
     Other,        // Другое
 }
 
+impl WasteReason {
+    pub fn label_ru(&self) -> &'static str {
+        match self {
+            WasteReason::Expired => "Истек срок годности",
+            WasteReason::Spoiled => "Испортился",
+            WasteReason::Overcooked => "Переготовлен",
+            WasteReason::NotLiked => "Не понравился",
+            WasteReason::TooMuch => "Слишком много приготовили",
+            WasteReason::Other => "Другое",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateFoodWaste {
     pub user_id: Uuid,
@@ -187,46 +485,143 @@ pub struct CreateFoodWaste {
     pub notes: Option<String>,
 }
 
-// Модели для аналитики расходов и экономии
+// Модели для аналитики расходов и экономии.
+// Денежные поля в f64 и округлены до копеек, проценты — до десятых (см. utils::format)
 #[derive(Debug, Clone, Serialize)]
 pub struct ExpenseAnalytics {
     pub period: String, // "day", "week", "month"
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
-    pub total_purchased: f32,   // Общая сумма купленных продуктов
-    pub total_wasted: f32,      // Общая сумма выброшенных продуктов
-    pub waste_percentage: f32,  // Процент отходов
-    pub savings_potential: f32, // Потенциальная экономия
+    pub total_purchased: f64,   // Общая сумма купленных продуктов
+    pub total_consumed: f64,    // Съедено
+    pub total_wasted: f64,      // Общая сумма выброшенных продуктов
+    /// Еще лежит в холодильнике: total_purchased = total_consumed + total_wasted + active_value
+    pub active_value: f64,
+    /// Доля выброшенного от использованного (съеденное + выброшенное)
+    pub waste_percentage: f64,
+    pub savings_potential: f64, // Потенциальная экономия
     pub category_breakdown: Vec<CategoryExpense>,
+    /// По магазинам, сначала самые крупные траты; продукты без магазина — в группе "unspecified"
+    pub store_breakdown: Vec<StoreExpense>,
+    /// По какой категории продуктов сгруппирован `category_breakdown`
+    pub category_as_of: CategoryAsOf,
     pub waste_by_reason: Vec<WasteByReason>,
+    /// Когда пользователь последний раз сверял количества; без проверок данные могут быть неточными
+    pub last_checkin_at: Option<DateTime<Utc>>,
+    pub days_since_checkin: Option<i64>,
+    /// Медианная покупка пользователя, от которой считаются выбросы
+    pub median_purchase: Option<f64>,
+    /// Покупки-выбросы за период (вероятно, ошибки ввода)
+    pub outliers: Vec<OutlierPurchase>,
+    /// Учтены ли выбросы в суммах; по умолчанию — нет
+    pub outliers_included: bool,
+}
+
+/// Покупка, которая дороже медианной покупки пользователя в `outlier_multiple` раз и больше
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlierPurchase {
+    pub item_id: Uuid,
+    pub name: String,
+    pub purchased_value: f64,
+    /// Во сколько раз дороже медианной покупки
+    pub times_median: f64,
+}
+
+/// Параметры расчета аналитики расходов
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyticsOptions {
+    pub category_as_of: CategoryAsOf,
+    /// Учитывать покупки-выбросы в суммах
+    pub include_outliers: bool,
+    /// Во сколько раз покупка должна быть дороже медианной, чтобы считаться выбросом
+    pub outlier_multiple: f64,
+}
+
+/// Выброс — покупка дороже медианной в 10 раз и больше
+pub const DEFAULT_OUTLIER_MULTIPLE: f64 = 10.0;
+
+impl Default for AnalyticsOptions {
+    fn default() -> Self {
+        Self {
+            category_as_of: CategoryAsOf::Current,
+            include_outliers: false,
+            outlier_multiple: DEFAULT_OUTLIER_MULTIPLE,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CategoryExpense {
     pub category: FridgeCategory,
-    pub purchased: f32,
-    pub wasted: f32,
-    pub waste_percentage: f32,
+    pub purchased: f64,
+    pub consumed: f64,
+    pub wasted: f64,
+    pub waste_percentage: f64,
+}
+
+/// Траты и отходы по одному магазину
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreExpense {
+    /// Название в том виде, как его вводили последний раз, или "unspecified"
+    pub store: String,
+    pub purchased: f64,
+    pub wasted: f64,
+    /// Доля выброшенного от использованного (съеденное + выброшенное)
+    pub waste_percentage: f64,
+    pub item_count: usize,
+}
+
+/// Какую категорию продукта учитывать в аналитике, если ее меняли после покупки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryAsOf {
+    /// Текущую категорию
+    #[default]
+    Current,
+    /// Категорию на момент покупки
+    Historical,
+}
+
+/// Смена категории продукта через перекатегоризацию
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryChange {
+    pub item_id: Uuid,
+    pub from: FridgeCategory,
+    pub to: FridgeCategory,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Продукт в предпросмотре перекатегоризации. Подсказка есть, только если
+/// по названию продукт относится к другой категории.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecategorizationCandidate {
+    pub item_id: Uuid,
+    pub name: String,
+    pub status: FridgeItemStatus,
+    pub category: FridgeCategory,
+    pub suggested_category: Option<FridgeCategory>,
+    /// Уверенность подсказки от 0 до 1
+    pub confidence: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct WasteByReason {
     pub reason: WasteReason,
-    pub amount: f32,
-    pub percentage: f32,
+    pub amount: f64,
+    pub percentage: f64,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DailyExpense {
     pub date: String,
-    pub purchased: f32,
-    pub wasted: f32,
+    pub purchased: f64,
+    pub wasted: f64,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct EconomyInsights {
-    pub total_savings_this_month: f32,
-    pub avg_waste_percentage: f32,
+    pub total_savings_this_month: f64,
+    pub avg_waste_percentage: f64,
     pub most_wasted_category: Option<FridgeCategory>,
     pub best_category: Option<FridgeCategory>, // Категория с наименьшими отходами
     pub tips: Vec<String>, // Советы по экономии
@@ -287,16 +682,235 @@ pub enum DietType {
     Flexitarian,  // Флекситарианская
 }
 
+// Массивы перечислений в fridge_items (миграция 045): derive(sqlx::Type) их не объявляет
+impl PgHasArrayType for Allergen {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_allergen")
+    }
+}
+
+impl PgHasArrayType for Intolerance {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_intolerance")
+    }
+}
+
+impl PgHasArrayType for DietType {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_diet_type")
+    }
+}
+
+impl Allergen {
+    /// Все аллергены в порядке справочника (автозаполнение, пресеты)
+    pub const ALL: [Allergen; 14] = [
+        Allergen::Peanuts,
+        Allergen::TreeNuts,
+        Allergen::Milk,
+        Allergen::Eggs,
+        Allergen::Fish,
+        Allergen::Shellfish,
+        Allergen::Soy,
+        Allergen::Wheat,
+        Allergen::Sesame,
+        Allergen::Sulfites,
+        Allergen::Celery,
+        Allergen::Mustard,
+        Allergen::Lupin,
+        Allergen::Molluscs,
+    ];
+
+    /// Место варианта в `ALL`. Match без `_`: новый вариант не скомпилируется, пока ему
+    /// не назначат место здесь и не добавят в `ALL` рядом
+    const fn position(&self) -> usize {
+        match self {
+            Allergen::Peanuts => 0,
+            Allergen::TreeNuts => 1,
+            Allergen::Milk => 2,
+            Allergen::Eggs => 3,
+            Allergen::Fish => 4,
+            Allergen::Shellfish => 5,
+            Allergen::Soy => 6,
+            Allergen::Wheat => 7,
+            Allergen::Sesame => 8,
+            Allergen::Sulfites => 9,
+            Allergen::Celery => 10,
+            Allergen::Mustard => 11,
+            Allergen::Lupin => 12,
+            Allergen::Molluscs => 13,
+        }
+    }
+}
+
+// `ALL` совпадает с `position`: без пропусков, повторов и перестановок
+const _: () = {
+    let mut i = 0;
+    while i < Allergen::ALL.len() {
+        assert!(Allergen::ALL[i].position() == i);
+        i += 1;
+    }
+};
+
+impl Intolerance {
+    /// Все непереносимости в порядке справочника (автозаполнение, пресеты)
+    pub const ALL: [Intolerance; 10] = [
+        Intolerance::Lactose,
+        Intolerance::Gluten,
+        Intolerance::Fructose,
+        Intolerance::Histamine,
+        Intolerance::Sorbitol,
+        Intolerance::Sucrose,
+        Intolerance::FODMAP,
+        Intolerance::Caffeine,
+        Intolerance::Alcohol,
+        Intolerance::Tyramine,
+    ];
+
+    /// Место варианта в `ALL`. Match без `_`: новый вариант не скомпилируется, пока ему
+    /// не назначат место здесь и не добавят в `ALL` рядом
+    const fn position(&self) -> usize {
+        match self {
+            Intolerance::Lactose => 0,
+            Intolerance::Gluten => 1,
+            Intolerance::Fructose => 2,
+            Intolerance::Histamine => 3,
+            Intolerance::Sorbitol => 4,
+            Intolerance::Sucrose => 5,
+            Intolerance::FODMAP => 6,
+            Intolerance::Caffeine => 7,
+            Intolerance::Alcohol => 8,
+            Intolerance::Tyramine => 9,
+        }
+    }
+}
+
+// `ALL` совпадает с `position`: без пропусков, повторов и перестановок
+const _: () = {
+    let mut i = 0;
+    while i < Intolerance::ALL.len() {
+        assert!(Intolerance::ALL[i].position() == i);
+        i += 1;
+    }
+};
+
+impl Allergen {
+    // Ключевые слова для поиска аллергена в названиях ингредиентов
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Allergen::Peanuts => &["арахис", "peanut"],
+            Allergen::TreeNuts => &["орех", "миндал", "фундук", "кешью", "фисташ", "nut", "almond"],
+            Allergen::Milk => &["молок", "сыр", "йогурт", "сливк", "творог", "кефир", "сметан", "milk", "cheese"],
+            Allergen::Eggs => &["яйц", "яич", "омлет", "egg"],
+            Allergen::Fish => &["рыб", "лосос", "тунец", "треск", "fish", "salmon"],
+            Allergen::Shellfish => &["кревет", "краб", "омар", "лобстер", "лангуст", "shrimp", "prawn", "crab", "lobster"],
+            Allergen::Soy => &["соев", "соя", "тофу", "soy"],
+            Allergen::Wheat => &["пшени", "мук", "хлеб", "макарон", "wheat", "flour"],
+            Allergen::Sesame => &["кунжут", "тахин", "sesame"],
+            Allergen::Sulfites => &["вино", "сухофрукт", "sulfite"],
+            Allergen::Celery => &["сельдер", "celery", "celeriac"],
+            Allergen::Mustard => &["горчиц", "горчичн", "дижон", "mustard", "dijon"],
+            Allergen::Lupin => &["люпин", "lupin"],
+            Allergen::Molluscs => &[
+                "миди", "кальмар", "осьмин", "устриц", "гребеш", "каракатиц", "улитк", "моллюск",
+                "squid", "mussel", "oyster", "octopus", "scallop", "clam", "calamari", "mollus",
+            ],
+        }
+    }
+}
+
+// Насколько серьезна аллергия: от этого зависит, фильтруем мы продукт/рецепт или только предупреждаем
+// Хранится внутри JSONB-поля allergies профиля
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum AllergenSeverity {
+    Avoid,           // Предпочитает избегать — только пометка
+    #[default]
+    Strict,          // Строго исключить — заметное предупреждение, но без блокировки
+    LifeThreatening, // Опасно для жизни — жесткая фильтрация
+}
+
+impl AllergenSeverity {
+    pub fn blocks(&self) -> bool {
+        matches!(self, AllergenSeverity::LifeThreatening)
+    }
+
+    pub fn warning_severity(&self) -> WarningSeverity {
+        match self {
+            AllergenSeverity::LifeThreatening => WarningSeverity::Critical,
+            AllergenSeverity::Strict => WarningSeverity::High,
+            AllergenSeverity::Avoid => WarningSeverity::Low,
+        }
+    }
+
+    pub fn label_ru(&self) -> &'static str {
+        match self {
+            AllergenSeverity::LifeThreatening => "опасно для жизни, исключить полностью",
+            AllergenSeverity::Strict => "строго избегать",
+            AllergenSeverity::Avoid => "желательно избегать",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AllergyEntry {
+    pub allergen: Allergen,
+    #[serde(default)]
+    pub severity: AllergenSeverity,
+}
+
+/// Ограничение, которого нет среди стандартных аллергенов (киви, корица...).
+/// Проверяется так же, как аллергены: по названию и составу продукта.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomRestriction {
+    pub name: String,
+    #[serde(default)]
+    pub severity: AllergenSeverity,
+    /// Слова для поиска в названии и составе; пустой список означает поиск по `name`
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+impl CustomRestriction {
+    /// Упоминается ли ограничение в тексте (без учета регистра и диакритики)
+    pub fn matches(&self, text: &str) -> bool {
+        let text = normalize_ingredient(text);
+        if text.is_empty() {
+            return false;
+        }
+
+        let keywords: Vec<&str> = if self.keywords.is_empty() {
+            vec![self.name.as_str()]
+        } else {
+            self.keywords.iter().map(String::as_str).collect()
+        };
+
+        keywords
+            .into_iter()
+            .map(normalize_ingredient)
+            .filter(|keyword| !keyword.is_empty())
+            .any(|keyword| contains_phrase(&text, &keyword))
+    }
+}
+
+/// Вхождение фразы с начала слова: "киви" находится в "сок киви банан", но "ром" не находится в "паром"
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase)
+        .any(|(index, _)| index == 0 || text[..index].ends_with(' '))
+}
+
 // Модели для работы с диетическими ограничениями
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct DietaryProfile {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub allergies: Vec<Allergen>,
+    #[sqlx(json)]
+    pub allergies: Vec<AllergyEntry>,
+    #[sqlx(json)]
     pub intolerances: Vec<Intolerance>, 
+    #[sqlx(json)]
     pub diets: Vec<DietType>,
-    pub custom_restrictions: Vec<String>, // Дополнительные ограничения от пользователя
+    #[sqlx(json)]
+    pub custom_restrictions: Vec<CustomRestriction>, // Дополнительные ограничения от пользователя
     pub severity_notes: Option<String>, // Заметки о серьезности ограничений
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -305,19 +919,21 @@ pub struct DietaryProfile {
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateDietaryProfile {
     pub user_id: Uuid,
-    pub allergies: Vec<Allergen>,
+    pub allergies: Vec<AllergyEntry>,
     pub intolerances: Vec<Intolerance>,
     pub diets: Vec<DietType>,
-    pub custom_restrictions: Vec<String>,
+    pub custom_restrictions: Vec<CustomRestriction>,
     pub severity_notes: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct UpdateDietaryProfile {
-    pub allergies: Option<Vec<Allergen>>,
+    pub allergies: Option<Vec<AllergyEntry>>,
     pub intolerances: Option<Vec<Intolerance>>,
     pub diets: Option<Vec<DietType>>,
-    pub custom_restrictions: Option<Vec<String>>,
+    /// Текст очищается в `DietaryService::update_profile`
+    pub custom_restrictions: Option<Vec<CustomRestriction>>,
+    #[serde(default, deserialize_with = "crate::utils::sanitize::deserialize_optional_sanitized")]
     pub severity_notes: Option<String>,
 }
 
@@ -344,6 +960,7 @@ pub struct DietaryWarning {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DietaryWarningType {
     Allergy,      // Аллерген
+    CustomRestriction, // Пользовательское ограничение
     Intolerance,  // Непереносимость
     DietViolation,// Нарушение диеты
     CrossContamination, // Перекрестное загрязнение
@@ -389,3 +1006,195 @@ pub struct SuggestedItem {
     pub estimated_price_range: Option<(f32, f32)>,
     pub nutritional_benefits: Vec<String>,
 }
+
+// Разделы документа настроек пользователя (см. models::preferences)
+
+/// Ежемесячный отчет по холодильнику на почту и еженедельный разбор целей с помощником;
+/// по умолчанию выключены
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportPreferences {
+    #[serde(default)]
+    pub monthly_email: bool,
+    #[serde(default)]
+    pub weekly_goal_review: bool,
+}
+
+/// Еженедельная проверка холодильника: напоминание в выбранный день и дата последней проверки
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckinPreferences {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub day: CheckinDay,
+    #[serde(default)]
+    pub last_checkin_at: Option<DateTime<Utc>>,
+    /// Дата последнего напоминания — чтобы не напоминать дважды в один день
+    #[serde(default)]
+    pub last_reminded_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckinDay {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    #[default]
+    Sunday,
+}
+
+impl CheckinDay {
+    /// Значение в JSONB-документе настроек (совпадает с serde-представлением)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckinDay::Monday => "monday",
+            CheckinDay::Tuesday => "tuesday",
+            CheckinDay::Wednesday => "wednesday",
+            CheckinDay::Thursday => "thursday",
+            CheckinDay::Friday => "friday",
+            CheckinDay::Saturday => "saturday",
+            CheckinDay::Sunday => "sunday",
+        }
+    }
+
+    pub fn from_weekday(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Mon => CheckinDay::Monday,
+            Weekday::Tue => CheckinDay::Tuesday,
+            Weekday::Wed => CheckinDay::Wednesday,
+            Weekday::Thu => CheckinDay::Thursday,
+            Weekday::Fri => CheckinDay::Friday,
+            Weekday::Sat => CheckinDay::Saturday,
+            Weekday::Sun => CheckinDay::Sunday,
+        }
+    }
+}
+
+/// Что сделать с продуктом при проверке холодильника
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CheckinAction {
+    /// Количество не изменилось
+    Confirm,
+    /// Указать фактический остаток
+    NewQuantity { quantity: f32 },
+    /// Продукт съеден — удаляется без записи об отходах
+    MarkFinished,
+    /// Продукт выброшен — удаляется с записью об отходах
+    MarkWasted { reason: WasteReason },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckinAdjustment {
+    pub item_id: Uuid,
+    #[serde(flatten)]
+    pub action: CheckinAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckinSummary {
+    pub confirmed: usize,
+    pub adjusted: usize,
+    pub finished: usize,
+    pub wasted: usize,
+    /// finished + wasted
+    pub removed: usize,
+    pub total_value_written_off: f64,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Порядок и скрытие категорий: сначала категории пользователя, затем остальные в порядке по умолчанию
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryPreferences {
+    #[serde(default)]
+    pub order: Vec<FridgeCategory>,
+    #[serde(default)]
+    pub hidden: Vec<FridgeCategory>,
+}
+
+impl CategoryPreferences {
+    /// Полный порядок всех категорий (включая скрытые)
+    pub fn full_order(&self) -> Vec<FridgeCategory> {
+        let mut order: Vec<FridgeCategory> = Vec::with_capacity(FridgeCategory::ALL.len());
+        for category in self.order.iter().chain(FridgeCategory::ALL.iter()) {
+            if !order.contains(category) {
+                order.push(category.clone());
+            }
+        }
+        order
+    }
+
+    /// Видимые категории в пользовательском порядке
+    pub fn effective_order(&self) -> Vec<FridgeCategory> {
+        self.full_order()
+            .into_iter()
+            .filter(|category| !self.is_hidden(category))
+            .collect()
+    }
+
+    pub fn is_hidden(&self, category: &FridgeCategory) -> bool {
+        self.hidden.contains(category)
+    }
+
+    /// Ключ сортировки: видимые по порядку пользователя, скрытые — в конце
+    pub fn sort_key(&self, category: &FridgeCategory) -> (bool, usize) {
+        let position = self
+            .full_order()
+            .iter()
+            .position(|c| c == category)
+            .unwrap_or(FridgeCategory::ALL.len());
+        (self.is_hidden(category), position)
+    }
+}
+
+/// Подсказка названия при вводе продукта — с полями для предзаполнения формы
+#[derive(Debug, Clone, Serialize)]
+pub struct NameSuggestion {
+    pub name: String,
+    pub source: NameSuggestionSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    /// Последнее количество и единица, в которой продукт вносился
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    pub category: FridgeCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Последняя известная цена за единицу `unit`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_per_unit: Option<f32>,
+    /// Срок хранения в днях: по прошлой покупке или типичный из каталога
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shelf_life_days: Option<i32>,
+    pub contains_allergens: Vec<Allergen>,
+    pub contains_intolerances: Vec<Intolerance>,
+    pub suitable_for_diets: Vec<DietType>,
+    /// Сколько раз пользователь вносил продукт (только для `personal`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub times_used: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Магазин из истории покупок пользователя для подсказки при вводе
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreSuggestion {
+    pub store: String,
+    pub times_used: u32,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Откуда взята подсказка названия
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameSuggestionSource {
+    /// Продукты, которые пользователь уже вносил
+    Personal,
+    /// Каталог предустановленных продуктов
+    Preset,
+}
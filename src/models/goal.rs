@@ -5,7 +5,7 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc, NaiveDate};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
-#[sqlx(type_name = "goal_type", rename_all = "lowercase")]
+#[sqlx(type_name = "goal_type", rename_all = "snake_case")]
 pub enum GoalType {
     WeightLoss,
     WeightGain,
@@ -24,6 +24,8 @@ pub enum GoalStatus {
     Completed,
     Paused,
     Cancelled,
+    /// Срок прошел, а цель не достигнута — выставляется планировщиком напоминаний
+    Expired,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -40,10 +42,44 @@ pub struct Goal {
     pub daily_target: Option<f32>,
     pub weekly_target: Option<f32>,
     pub status: GoalStatus,
+    /// За сколько дней до `target_date` присылать напоминания
+    pub reminder_days: Vec<i32>,
+    /// Завершенная или брошенная цель убрана в архив: в списке только с `include_archived`
+    pub archived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Goal {
+    pub fn progress_percentage(&self) -> f32 {
+        if self.target_value > 0.0 {
+            (self.current_value / self.target_value * 100.0).min(100.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn days_remaining(&self, today: NaiveDate) -> Option<i32> {
+        self.target_date.map(|target| (target - today).num_days() as i32)
+    }
+
+    pub fn is_on_track(&self, today: NaiveDate) -> bool {
+        let progress_percentage = self.progress_percentage();
+
+        match (self.target_date, self.days_remaining(today)) {
+            (Some(_), Some(days)) if days > 0 => {
+                let expected_progress = 100.0 - (days as f32 / 30.0 * 100.0); // Simplified calculation
+                progress_percentage >= expected_progress
+            }
+            _ => progress_percentage >= 50.0, // Default threshold
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.target_value > 0.0 && self.current_value >= self.target_value
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateGoal {
     pub user_id: Uuid,
@@ -57,6 +93,31 @@ pub struct CreateGoal {
     pub daily_target: Option<f32>,
     pub weekly_target: Option<f32>,
     pub status: GoalStatus,
+    pub reminder_days: Vec<i32>,
+}
+
+/// Вид предложенной цели
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalSuggestionKind {
+    Calories,
+    Protein,
+    Water,
+    /// Цель типа `Other` в процентах: доля выброшенных продуктов
+    WasteReduction,
+}
+
+/// Цель, рассчитанная по профилю, дневнику и холодильнику; создается одним вызовом
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalSuggestion {
+    pub kind: GoalSuggestionKind,
+    pub title: String,
+    pub goal_type: GoalType,
+    /// Дневная цель (для отходов — доля за месяц)
+    pub target_value: f32,
+    pub unit: String,
+    /// Почему именно это значение — с цифрами пользователя
+    pub rationale: String,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -79,3 +140,43 @@ pub struct Achievement {
     pub earned_at: DateTime<Utc>,
     pub goal_related: Option<Uuid>,
 }
+
+/// Значение прогресса цели в момент обновления
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct GoalProgressPoint {
+    pub recorded_at: DateTime<Utc>,
+    pub value: f32,
+}
+
+/// Данные для карточки «поделиться целью». Содержит только эту цель
+/// и имя владельца — без фамилии, email и других целей.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalShareCard {
+    pub goal_id: Uuid,
+    pub owner_first_name: String,
+    pub title: String,
+    pub goal_type: GoalType,
+    pub status: GoalStatus,
+    pub unit: String,
+    /// Первое записанное значение прогресса
+    pub start_value: f32,
+    pub current_value: f32,
+    pub target_value: f32,
+    pub progress_percentage: f32,
+    pub started_at: DateTime<Utc>,
+    /// Когда цель была достигнута; `None`, пока она не выполнена
+    pub completed_at: Option<DateTime<Utc>>,
+    pub duration_days: i64,
+    /// Не больше 20 точек, равномерно выбранных из истории прогресса
+    pub progress: Vec<GoalProgressPoint>,
+    pub achievement: Option<ShareCardAchievement>,
+    pub share_text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareCardAchievement {
+    pub title: String,
+    pub description: String,
+    pub icon: String,
+    pub earned_at: DateTime<Utc>,
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use uuid::Uuid;
 
+use crate::models::fridge::CheckinDay;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthProfile {
     pub user_id: Uuid,
@@ -117,3 +119,97 @@ pub enum RecommendationCategory {
     MindfulnessStress,
     Routine,
 }
+
+/// Составляющие оценки качества питания (см. `services::diet_quality::RUBRIC`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DietQualityFactor {
+    /// Белок относительно дневной цели, г
+    Protein,
+    /// Клетчатка, г
+    Fiber,
+    /// Сахар, г — чем меньше, тем лучше
+    Sugar,
+    /// Натрий, мг — чем меньше, тем лучше
+    Sodium,
+    /// Овощи и фрукты в дневнике: сколько из двух групп встретилось
+    Produce,
+    /// Сколько основных приемов пищи (завтрак, обед, ужин) записано
+    MealRegularity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DietQualityComponent {
+    pub factor: DietQualityFactor,
+    pub score: f64,
+    pub max_score: f64,
+    pub value: f64,
+    pub target: f64,
+}
+
+/// Оценка питания за день, 0–100
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DietQualityDay {
+    pub date: NaiveDate,
+    pub score: u8,
+    pub components: Vec<DietQualityComponent>,
+    pub entries_count: usize,
+}
+
+/// Корреляция оценки питания с настроением: среднее настроение в дни с высокой оценкой
+/// и в остальные дни. Считается детерминированно, без модели.
+#[derive(Debug, Clone, Serialize)]
+pub struct DietMoodInsight {
+    pub threshold: u8,
+    pub high_score_days: usize,
+    pub other_days: usize,
+    pub avg_mood_high_score: f64,
+    pub avg_mood_other: f64,
+    /// Разница средних (положительная — настроение выше в дни с хорошим питанием)
+    pub difference: f64,
+    pub finding: String,
+}
+
+/// Окно питания (интервальное голодание) в местном времени пользователя.
+/// Окно может переходить через полночь (18:00–02:00) и относится к дню, в который открывается.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EatingWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Смещение местного времени от UTC в минутах (Москва — 180)
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Дни, в которые окно действует; пусто — каждый день
+    #[serde(default)]
+    pub weekdays: Vec<CheckinDay>,
+    /// Уведомлять о записи в дневнике вне окна (не чаще раза в день)
+    #[serde(default)]
+    pub notify_outside: bool,
+    /// Местная дата последнего такого уведомления
+    #[serde(default)]
+    pub last_notified_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FastingState {
+    Eating,
+    Fasting,
+}
+
+/// Состояние голодания на текущий момент. Приемом пищи считаются записи дневника
+/// от `MIN_MEAL_CALORIES` ккал: вода и напитки без калорий голодание не прерывают.
+#[derive(Debug, Clone, Serialize)]
+pub struct FastingStatus {
+    /// Нет, если окно питания не настроено
+    pub state: Option<FastingState>,
+    pub eating_window: Option<EatingWindow>,
+    pub last_meal_at: Option<DateTime<Utc>>,
+    pub hours_since_last_meal: Option<f64>,
+    /// Когда откроется следующее окно (если сейчас голодание)
+    pub window_opens_at: Option<DateTime<Utc>>,
+    /// Когда закроется текущее окно (если сейчас окно питания)
+    pub window_closes_at: Option<DateTime<Utc>>,
+    /// Самый долгий перерыв между приемами пищи за последние дни, включая текущий
+    pub longest_recent_fast_hours: Option<f64>,
+}
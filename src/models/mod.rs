@@ -6,3 +6,22 @@ pub mod goal;
 pub mod community;
 pub mod health;
 pub mod presets;
+pub mod coaching;
+pub mod activity;
+pub mod feature_flag;
+pub mod experiment;
+pub mod api_token;
+pub mod admin_job;
+pub mod report;
+pub mod admin_user;
+pub mod ai_response;
+pub mod notification;
+pub mod conversation;
+pub mod account_merge;
+pub mod preferences;
+pub mod ai_usage;
+pub mod shopping;
+pub mod snack;
+pub mod card_action;
+pub mod bulk;
+pub mod timeline;
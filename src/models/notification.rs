@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::models::fridge::FridgeCategory;
+
+/// За сколько дней до истечения срока уведомлять, если для категории ничего не задано
+pub const DEFAULT_EXPIRY_LEAD_DAYS: u8 = 3;
+
+pub const MAX_EXPIRY_LEAD_DAYS: u8 = 14;
+
+/// Настройки уведомлений (раздел документа user_preferences)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    /// За сколько дней до истечения срока уведомлять по категориям; `null` — не уведомлять.
+    /// Для отсутствующих категорий — `DEFAULT_EXPIRY_LEAD_DAYS`.
+    #[serde(default)]
+    pub expiry_lead_days: HashMap<FridgeCategory, Option<u8>>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub channels: NotificationChannels,
+    /// Все уведомления выключены (до `muted_until`, если указано)
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub muted_until: Option<DateTime<Utc>>,
+    /// Письмо о входе с нового устройства; не зависит от `muted`
+    #[serde(default)]
+    pub new_device_email: bool,
+}
+
+impl NotificationPreferences {
+    pub fn is_muted(&self, now: DateTime<Utc>) -> bool {
+        self.muted && self.muted_until.is_none_or(|until| until > now)
+    }
+
+    /// Порог для категории; `None` — об этой категории не уведомлять
+    pub fn expiry_lead_days(&self, category: &FridgeCategory) -> Option<u8> {
+        self.expiry_lead_days
+            .get(category)
+            .copied()
+            .unwrap_or(Some(DEFAULT_EXPIRY_LEAD_DAYS))
+    }
+}
+
+/// Тихие часы в местном времени пользователя; окно может переходить через полночь (22:00–08:00)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Смещение местного времени от UTC в минутах (Москва — 180)
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+/// Куда доставлять уведомление
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Websocket,
+    Inbox,
+    #[default]
+    Both,
+    Off,
+}
+
+impl NotificationChannel {
+    pub fn websocket(&self) -> bool {
+        matches!(self, NotificationChannel::Websocket | NotificationChannel::Both)
+    }
+
+    pub fn inbox(&self) -> bool {
+        matches!(self, NotificationChannel::Inbox | NotificationChannel::Both)
+    }
+}
+
+/// Канал для каждого типа событий
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationChannels {
+    #[serde(default)]
+    pub expiring_items: NotificationChannel,
+    #[serde(default)]
+    pub goal_reminders: NotificationChannel,
+    #[serde(default)]
+    pub fridge_checkin: NotificationChannel,
+    #[serde(default)]
+    pub eating_window: NotificationChannel,
+    #[serde(default)]
+    pub diary_reminder: NotificationChannel,
+}
+
+/// Типы событий, которыми управляют настройки уведомлений
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEvent {
+    ExpiringItems,
+    /// Приближение срока цели и истечение цели
+    GoalReminders,
+    FridgeCheckin,
+    /// Запись в дневнике вне окна питания
+    EatingWindow,
+    /// За день в дневнике ничего не записано
+    DiaryReminder,
+}
+
+impl NotificationChannels {
+    pub fn for_event(&self, event: NotificationEvent) -> NotificationChannel {
+        match event {
+            NotificationEvent::ExpiringItems => self.expiring_items,
+            NotificationEvent::GoalReminders => self.goal_reminders,
+            NotificationEvent::FridgeCheckin => self.fridge_checkin,
+            NotificationEvent::EatingWindow => self.eating_window,
+            NotificationEvent::DiaryReminder => self.diary_reminder,
+        }
+    }
+}
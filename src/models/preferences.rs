@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+
+use crate::models::{
+    community::PostVisibility,
+    diary::{DiaryReminderPreferences, MealTimeBoundaries},
+    fridge::{CategoryPreferences, CheckinPreferences, ReportPreferences},
+    health::EatingWindow,
+    notification::NotificationPreferences,
+};
+
+/// Версия документа настроек. Увеличивается, когда меняется смысл существующих полей;
+/// новые поля версию не меняют — они добавляются с `#[serde(default)]`.
+pub const PREFERENCES_VERSION: u32 = 1;
+
+// Настройки пользователя (таблица user_preferences, JSONB-документ)
+
+/// Документ настроек. Отсутствующие поля получают значения по умолчанию при чтении,
+/// неизвестные (из более новой версии) игнорируются, поэтому старый и новый код
+/// читают документы друг друга.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserPreferences {
+    /// 0 — документ, записанный до появления версий
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub general: GeneralPreferences,
+    #[serde(default)]
+    pub categories: CategoryPreferences,
+    #[serde(default)]
+    pub checkin: CheckinPreferences,
+    #[serde(default)]
+    pub reports: ReportPreferences,
+    #[serde(default)]
+    pub notifications: NotificationPreferences,
+    /// Окно питания для интервального голодания; нет — не отслеживается
+    #[serde(default)]
+    pub eating_window: Option<EatingWindow>,
+    #[serde(default)]
+    pub privacy: PrivacyPreferences,
+    /// По ним определяется прием пищи, если клиент не указал его в записи дневника
+    #[serde(default)]
+    pub meal_times: MealTimeBoundaries,
+    #[serde(default)]
+    pub diary_reminder: DiaryReminderPreferences,
+    #[serde(default)]
+    pub glossary: GlossaryPreferences,
+}
+
+impl UserPreferences {
+    /// Раздел документа в JSON (с учетом значений по умолчанию)
+    pub fn section(&self, section: PreferencesSection) -> serde_json::Result<serde_json::Value> {
+        match section {
+            PreferencesSection::General => serde_json::to_value(&self.general),
+            PreferencesSection::Categories => serde_json::to_value(&self.categories),
+            PreferencesSection::Checkin => serde_json::to_value(&self.checkin),
+            PreferencesSection::Reports => serde_json::to_value(&self.reports),
+            PreferencesSection::Notifications => serde_json::to_value(&self.notifications),
+            PreferencesSection::EatingWindow => serde_json::to_value(&self.eating_window),
+            PreferencesSection::Privacy => serde_json::to_value(&self.privacy),
+            PreferencesSection::MealTimes => serde_json::to_value(self.meal_times),
+            PreferencesSection::DiaryReminder => serde_json::to_value(&self.diary_reminder),
+            PreferencesSection::Glossary => serde_json::to_value(&self.glossary),
+        }
+    }
+}
+
+/// Разделы документа; каждый раздел — ключ верхнего уровня и единица записи
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreferencesSection {
+    General,
+    Categories,
+    Checkin,
+    Reports,
+    Notifications,
+    EatingWindow,
+    Privacy,
+    MealTimes,
+    DiaryReminder,
+    Glossary,
+}
+
+impl PreferencesSection {
+    pub const ALL: [PreferencesSection; 10] = [
+        PreferencesSection::General,
+        PreferencesSection::Categories,
+        PreferencesSection::Checkin,
+        PreferencesSection::Reports,
+        PreferencesSection::Notifications,
+        PreferencesSection::EatingWindow,
+        PreferencesSection::Privacy,
+        PreferencesSection::MealTimes,
+        PreferencesSection::DiaryReminder,
+        PreferencesSection::Glossary,
+    ];
+
+    /// Ключ раздела в документе (совпадает с serde-представлением поля)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreferencesSection::General => "general",
+            PreferencesSection::Categories => "categories",
+            PreferencesSection::Checkin => "checkin",
+            PreferencesSection::Reports => "reports",
+            PreferencesSection::Notifications => "notifications",
+            PreferencesSection::EatingWindow => "eating_window",
+            PreferencesSection::Privacy => "privacy",
+            PreferencesSection::MealTimes => "meal_times",
+            PreferencesSection::DiaryReminder => "diary_reminder",
+            PreferencesSection::Glossary => "glossary",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|section| section.as_str() == name)
+    }
+}
+
+/// Язык, часовой пояс и единицы измерения
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeneralPreferences {
+    /// Язык интерфейса (BCP 47, например `ru` или `en-US`); нет — по языку браузера
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Смещение местного времени от UTC в минутах (Москва — 180)
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default)]
+    pub units: UnitSystem,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+/// Свои названия продуктов для ответов ИИ: «как бывает» → «как называть»
+/// (`{"творожок": "творог"}`). Дополняют глоссарий языка пользователя.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GlossaryPreferences {
+    #[serde(default)]
+    pub overrides: BTreeMap<String, String>,
+}
+
+/// Приватность: видимость новых постов, если она не указана при создании
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivacyPreferences {
+    #[serde(default)]
+    pub default_post_visibility: PostVisibility,
+}
+
+/// Документ вместе со временем последнего изменения каждого раздела
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PreferencesDocument {
+    pub preferences: UserPreferences,
+    /// Раздел → время последнего изменения; разделов, которые не меняли, здесь нет
+    pub updated_at: BTreeMap<String, DateTime<Utc>>,
+}
+
+/// Строка `user_preferences`: только то, из чего собирается документ
+#[derive(Debug, Clone, FromRow)]
+pub struct PreferencesRecord {
+    #[sqlx(json)]
+    pub settings: UserPreferences,
+    #[sqlx(json)]
+    pub section_updated_at: BTreeMap<String, DateTime<Utc>>,
+}
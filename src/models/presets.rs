@@ -53,6 +53,76 @@ pub struct ProductPreset {
     pub typical_shelf_life_days: Option<i32>,
     pub storage_location: String,
     pub nutritional_highlights: Vec<String>,
+    // Быстрые способы использовать продукт: только сам продукт и базовые запасы (крупа, масло, специи)
+    pub quick_uses: Vec<String>,
+}
+
+// Долгохранящаяся замена скоропортящегося продукта (заморозка, ультрапастеризация)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LongLifeAlternative {
+    pub product: String,
+    pub alternative: String,
+    pub shelf_life_days: i32,
+}
+
+// Стартовый набор для пустого холодильника: продукты каталога с типичным количеством
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarterPack {
+    pub id: String,
+    pub name_ru: String,
+    pub name_en: String,
+    pub description_ru: String,
+    pub description_en: String,
+    pub items: Vec<StarterPackItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarterPackItem {
+    // Название продукта в каталоге (`ProductPreset::name`) — оно же название на русском
+    pub preset_name: String,
+    pub name_en: String,
+    pub quantity: f32,
+    pub unit: String,
+}
+
+// Стартовый набор с учетом диетического профиля пользователя, на языке запроса
+#[derive(Debug, Clone, Serialize)]
+pub struct StarterPackView {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub items: Vec<StarterPackItemView>,
+    // Продукты, убранные из набора из-за профиля, с причиной
+    pub excluded_items: Vec<ExcludedStarterItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StarterPackItemView {
+    // Ключ продукта для `POST /starter-packs/{id}/apply`
+    pub preset_name: String,
+    pub name: String,
+    pub quantity: f32,
+    pub unit: String,
+    pub category: FridgeCategory,
+    pub location: String,
+    pub shelf_life_days: Option<i32>,
+    // Ограничение, которое пользователь предпочитает избегать, но не исключил строго
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedStarterItem {
+    pub preset_name: String,
+    pub name: String,
+    pub reason: String,
+}
+
+// Выбранный продукт набора; количество можно поменять, единица остается из набора
+#[derive(Debug, Clone, Deserialize)]
+pub struct StarterPackSelection {
+    pub preset_name: String,
+    pub quantity: Option<f32>,
 }
 
 pub struct FoodPresets;
@@ -60,8 +130,13 @@ pub struct FoodPresets;
 impl FoodPresets {
     // Получить информацию обо всех аллергенах
     pub fn get_allergen_info() -> Vec<AllergenInfo> {
-        vec![
-            AllergenInfo {
+        Allergen::ALL.iter().map(Self::allergen_info).collect()
+    }
+
+    // Справка по аллергену. Match без `_`: новый вариант не скомпилируется без своей справки
+    fn allergen_info(allergen: &Allergen) -> AllergenInfo {
+        match allergen {
+            Allergen::Peanuts => AllergenInfo {
                 allergen: Allergen::Peanuts,
                 name_en: "Peanuts".to_string(),
                 name_ru: "Арахис".to_string(),
@@ -81,7 +156,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![Allergen::TreeNuts, Allergen::Soy],
             },
-            AllergenInfo {
+            Allergen::TreeNuts => AllergenInfo {
                 allergen: Allergen::TreeNuts,
                 name_en: "Tree Nuts".to_string(),
                 name_ru: "Орехи".to_string(),
@@ -102,7 +177,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![Allergen::Peanuts],
             },
-            AllergenInfo {
+            Allergen::Milk => AllergenInfo {
                 allergen: Allergen::Milk,
                 name_en: "Milk".to_string(),
                 name_ru: "Молочные продукты".to_string(),
@@ -123,7 +198,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![],
             },
-            AllergenInfo {
+            Allergen::Eggs => AllergenInfo {
                 allergen: Allergen::Eggs,
                 name_en: "Eggs".to_string(),
                 name_ru: "Яйца".to_string(),
@@ -143,7 +218,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![],
             },
-            AllergenInfo {
+            Allergen::Fish => AllergenInfo {
                 allergen: Allergen::Fish,
                 name_en: "Fish".to_string(),
                 name_ru: "Рыба".to_string(),
@@ -162,7 +237,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![Allergen::Shellfish],
             },
-            AllergenInfo {
+            Allergen::Shellfish => AllergenInfo {
                 allergen: Allergen::Shellfish,
                 name_en: "Shellfish".to_string(),
                 name_ru: "Морепродукты".to_string(),
@@ -180,9 +255,9 @@ impl FoodPresets {
                     "Chitosan".to_string(),
                     "Некоторые добавки".to_string(),
                 ],
-                cross_reactions: vec![Allergen::Fish],
+                cross_reactions: vec![Allergen::Fish, Allergen::Molluscs],
             },
-            AllergenInfo {
+            Allergen::Soy => AllergenInfo {
                 allergen: Allergen::Soy,
                 name_en: "Soy".to_string(),
                 name_ru: "Соя".to_string(),
@@ -202,7 +277,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![Allergen::Peanuts],
             },
-            AllergenInfo {
+            Allergen::Wheat => AllergenInfo {
                 allergen: Allergen::Wheat,
                 name_en: "Wheat".to_string(),
                 name_ru: "Пшеница".to_string(),
@@ -221,7 +296,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![],
             },
-            AllergenInfo {
+            Allergen::Sesame => AllergenInfo {
                 allergen: Allergen::Sesame,
                 name_en: "Sesame".to_string(),
                 name_ru: "Кунжут".to_string(),
@@ -240,7 +315,7 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![],
             },
-            AllergenInfo {
+            Allergen::Sulfites => AllergenInfo {
                 allergen: Allergen::Sulfites,
                 name_en: "Sulfites".to_string(),
                 name_ru: "Сульфиты".to_string(),
@@ -259,13 +334,104 @@ impl FoodPresets {
                 ],
                 cross_reactions: vec![],
             },
-        ]
+            Allergen::Celery => AllergenInfo {
+                allergen: Allergen::Celery,
+                name_en: "Celery".to_string(),
+                name_ru: "Сельдерей".to_string(),
+                description: "Аллергия на стебли, корень, листья и семена сельдерея; реакцию вызывает и термически обработанный продукт".to_string(),
+                severity: "High".to_string(),
+                common_sources: vec![
+                    "Стеблевой и корневой сельдерей".to_string(),
+                    "Сельдерейная соль".to_string(),
+                    "Супы и бульоны".to_string(),
+                    "Салаты".to_string(),
+                    "Celery salt".to_string(),
+                ],
+                hidden_sources: vec![
+                    "Бульонные кубики".to_string(),
+                    "Смеси специй".to_string(),
+                    "Колбасы и мясные полуфабрикаты".to_string(),
+                    "Natural flavoring".to_string(),
+                    "Spice blends".to_string(),
+                ],
+                cross_reactions: vec![],
+            },
+            Allergen::Mustard => AllergenInfo {
+                allergen: Allergen::Mustard,
+                name_en: "Mustard".to_string(),
+                name_ru: "Горчица".to_string(),
+                description: "Аллергия на семена, порошок и листья горчицы; возможна тяжелая реакция даже на малое количество".to_string(),
+                severity: "High".to_string(),
+                common_sources: vec![
+                    "Столовая горчица".to_string(),
+                    "Горчичный порошок".to_string(),
+                    "Горчичное масло".to_string(),
+                    "Dijon mustard".to_string(),
+                ],
+                hidden_sources: vec![
+                    "Майонез".to_string(),
+                    "Соусы и заправки".to_string(),
+                    "Маринады".to_string(),
+                    "Карри и смеси специй".to_string(),
+                    "Salad dressing".to_string(),
+                    "Pickles".to_string(),
+                ],
+                cross_reactions: vec![],
+            },
+            Allergen::Lupin => AllergenInfo {
+                allergen: Allergen::Lupin,
+                name_en: "Lupin".to_string(),
+                name_ru: "Люпин".to_string(),
+                description: "Аллергия на бобовое растение люпин, муку и белок из него; часто сочетается с аллергией на арахис".to_string(),
+                severity: "High".to_string(),
+                common_sources: vec![
+                    "Люпиновая мука".to_string(),
+                    "Семена люпина".to_string(),
+                    "Безглютеновая выпечка".to_string(),
+                    "Lupin flour".to_string(),
+                ],
+                hidden_sources: vec![
+                    "Хлеб и макароны с добавлением люпиновой муки".to_string(),
+                    "Растительные заменители мяса".to_string(),
+                    "Lupin protein".to_string(),
+                    "Vegetable protein".to_string(),
+                ],
+                cross_reactions: vec![Allergen::Peanuts, Allergen::Soy],
+            },
+            Allergen::Molluscs => AllergenInfo {
+                allergen: Allergen::Molluscs,
+                name_en: "Molluscs".to_string(),
+                name_ru: "Моллюски".to_string(),
+                description: "Аллергия на двустворчатых, брюхоногих и головоногих моллюсков".to_string(),
+                severity: "Critical".to_string(),
+                common_sources: vec![
+                    "Мидии".to_string(),
+                    "Устрицы".to_string(),
+                    "Кальмары".to_string(),
+                    "Осьминоги".to_string(),
+                    "Гребешки".to_string(),
+                    "Улитки".to_string(),
+                ],
+                hidden_sources: vec![
+                    "Устричный соус".to_string(),
+                    "Рыбные супы и паэлья".to_string(),
+                    "Oyster sauce".to_string(),
+                    "Seafood stock".to_string(),
+                ],
+                cross_reactions: vec![Allergen::Shellfish],
+            },
+        }
     }
 
     // Получить информацию обо всех непереносимостях
     pub fn get_intolerance_info() -> Vec<IntoleranceInfo> {
-        vec![
-            IntoleranceInfo {
+        Intolerance::ALL.iter().map(Self::intolerance_info).collect()
+    }
+
+    // Справка по непереносимости. Match без `_`: новый вариант не скомпилируется без своей справки
+    fn intolerance_info(intolerance: &Intolerance) -> IntoleranceInfo {
+        match intolerance {
+            Intolerance::Lactose => IntoleranceInfo {
                 intolerance: Intolerance::Lactose,
                 name_en: "Lactose Intolerance".to_string(),
                 name_ru: "Непереносимость лактозы".to_string(),
@@ -290,7 +456,7 @@ impl FoodPresets {
                 ],
                 severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
             },
-            IntoleranceInfo {
+            Intolerance::Gluten => IntoleranceInfo {
                 intolerance: Intolerance::Gluten,
                 name_en: "Gluten Intolerance".to_string(),
                 name_ru: "Непереносимость глютена".to_string(),
@@ -318,7 +484,7 @@ impl FoodPresets {
                 ],
                 severity_levels: vec!["Sensitivity".to_string(), "Intolerance".to_string(), "Celiac Disease".to_string()],
             },
-            IntoleranceInfo {
+            Intolerance::Fructose => IntoleranceInfo {
                 intolerance: Intolerance::Fructose,
                 name_en: "Fructose Intolerance".to_string(),
                 name_ru: "Непереносимость фруктозы".to_string(),
@@ -344,7 +510,7 @@ impl FoodPresets {
                 ],
                 severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
             },
-            IntoleranceInfo {
+            Intolerance::Histamine => IntoleranceInfo {
                 intolerance: Intolerance::Histamine,
                 name_en: "Histamine Intolerance".to_string(),
                 name_ru: "Непереносимость гистамина".to_string(),
@@ -369,7 +535,7 @@ impl FoodPresets {
                 ],
                 severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
             },
-            IntoleranceInfo {
+            Intolerance::FODMAP => IntoleranceInfo {
                 intolerance: Intolerance::FODMAP,
                 name_en: "FODMAP Intolerance".to_string(),
                 name_ru: "Непереносимость FODMAP".to_string(),
@@ -395,7 +561,139 @@ impl FoodPresets {
                 ],
                 severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
             },
-        ]
+            Intolerance::Sorbitol => IntoleranceInfo {
+                intolerance: Intolerance::Sorbitol,
+                name_en: "Sorbitol Intolerance".to_string(),
+                name_ru: "Непереносимость сорбитола".to_string(),
+                description: "Плохое всасывание сахарного спирта сорбитола, часто вместе с непереносимостью фруктозы".to_string(),
+                symptoms: vec![
+                    "Вздутие".to_string(),
+                    "Газы".to_string(),
+                    "Диарея".to_string(),
+                    "Боли в животе".to_string(),
+                ],
+                avoid_foods: vec![
+                    "Жевательная резинка без сахара".to_string(),
+                    "Диетические сладости".to_string(),
+                    "Сливы и чернослив".to_string(),
+                    "Груши".to_string(),
+                    "Абрикосы".to_string(),
+                ],
+                safe_alternatives: vec![
+                    "Бананы".to_string(),
+                    "Цитрусовые".to_string(),
+                    "Ягоды".to_string(),
+                    "Обычный сахар в умеренном количестве".to_string(),
+                ],
+                severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
+            },
+            Intolerance::Sucrose => IntoleranceInfo {
+                intolerance: Intolerance::Sucrose,
+                name_en: "Sucrose Intolerance".to_string(),
+                name_ru: "Непереносимость сахарозы".to_string(),
+                description: "Недостаток фермента сахаразы-изомальтазы: обычный сахар не расщепляется".to_string(),
+                symptoms: vec![
+                    "Диарея".to_string(),
+                    "Вздутие".to_string(),
+                    "Газы".to_string(),
+                    "Спазмы в животе".to_string(),
+                ],
+                avoid_foods: vec![
+                    "Сахар".to_string(),
+                    "Сладкая выпечка".to_string(),
+                    "Сладкие напитки".to_string(),
+                    "Сгущенное молоко".to_string(),
+                    "Сладкие фрукты (манго, ананас)".to_string(),
+                ],
+                safe_alternatives: vec![
+                    "Глюкоза".to_string(),
+                    "Ягоды".to_string(),
+                    "Несладкие молочные продукты".to_string(),
+                    "Мясо, рыба, яйца".to_string(),
+                ],
+                severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
+            },
+            Intolerance::Caffeine => IntoleranceInfo {
+                intolerance: Intolerance::Caffeine,
+                name_en: "Caffeine Sensitivity".to_string(),
+                name_ru: "Чувствительность к кофеину".to_string(),
+                description: "Выраженная реакция на кофеин даже в небольших дозах".to_string(),
+                symptoms: vec![
+                    "Учащенное сердцебиение".to_string(),
+                    "Тревожность".to_string(),
+                    "Бессонница".to_string(),
+                    "Головные боли".to_string(),
+                    "Тремор".to_string(),
+                ],
+                avoid_foods: vec![
+                    "Кофе".to_string(),
+                    "Черный и зеленый чай".to_string(),
+                    "Энергетические напитки".to_string(),
+                    "Кола".to_string(),
+                    "Темный шоколад".to_string(),
+                ],
+                safe_alternatives: vec![
+                    "Кофе без кофеина".to_string(),
+                    "Травяные чаи".to_string(),
+                    "Цикорий".to_string(),
+                    "Ройбуш".to_string(),
+                ],
+                severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
+            },
+            Intolerance::Alcohol => IntoleranceInfo {
+                intolerance: Intolerance::Alcohol,
+                name_en: "Alcohol Intolerance".to_string(),
+                name_ru: "Непереносимость алкоголя".to_string(),
+                description: "Организм медленно расщепляет алкоголь: реакция появляется даже на небольшое количество".to_string(),
+                symptoms: vec![
+                    "Покраснение лица".to_string(),
+                    "Заложенность носа".to_string(),
+                    "Учащенное сердцебиение".to_string(),
+                    "Тошнота".to_string(),
+                    "Головные боли".to_string(),
+                ],
+                avoid_foods: vec![
+                    "Вино и пиво".to_string(),
+                    "Крепкие напитки".to_string(),
+                    "Блюда, приготовленные с вином".to_string(),
+                    "Десерты с ликером".to_string(),
+                    "Спиртовые экстракты (ваниль)".to_string(),
+                ],
+                safe_alternatives: vec![
+                    "Безалкогольные напитки".to_string(),
+                    "Бульон вместо вина в соусах".to_string(),
+                    "Виноградный сок".to_string(),
+                    "Безспиртовые ароматизаторы".to_string(),
+                ],
+                severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
+            },
+            Intolerance::Tyramine => IntoleranceInfo {
+                intolerance: Intolerance::Tyramine,
+                name_en: "Tyramine Intolerance".to_string(),
+                name_ru: "Непереносимость тирамина".to_string(),
+                description: "Реакция на тирамин из выдержанных и ферментированных продуктов; особенно опасна при приеме ингибиторов МАО".to_string(),
+                symptoms: vec![
+                    "Мигрень".to_string(),
+                    "Повышение давления".to_string(),
+                    "Учащенное сердцебиение".to_string(),
+                    "Покраснение кожи".to_string(),
+                ],
+                avoid_foods: vec![
+                    "Выдержанные сыры".to_string(),
+                    "Вяленое и копченое мясо".to_string(),
+                    "Квашеная капуста".to_string(),
+                    "Соевый соус".to_string(),
+                    "Красное вино".to_string(),
+                ],
+                safe_alternatives: vec![
+                    "Свежие сыры (творог, моцарелла)".to_string(),
+                    "Свежее мясо и рыба".to_string(),
+                    "Свежие овощи".to_string(),
+                    "Рис".to_string(),
+                ],
+                severity_levels: vec!["Mild".to_string(), "Moderate".to_string(), "Severe".to_string()],
+            },
+        }
     }
 
     // Получить информацию обо всех диетах
@@ -622,6 +920,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(7),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Кальций".to_string(), "Белок".to_string(), "Витамин B12".to_string()],
+                quick_uses: vec!["Каша на молоке из риса или овсянки".to_string(), "Омлет-пашот: молоко взбить с солью и запечь в кружке".to_string(), "Домашний творог: молоко нагреть с лимонным соком".to_string()],
             },
             ProductPreset {
                 name: "Сыр твердый".to_string(),
@@ -632,6 +931,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(30),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Кальций".to_string(), "Белок".to_string(), "Витамин A".to_string()],
+                quick_uses: vec!["Сырные чипсы: тертый сыр запечь на сухой сковороде".to_string(), "Рис с сыром и черным перцем".to_string(), "Гренки с расплавленным сыром".to_string()],
             },
             ProductPreset {
                 name: "Йогурт натуральный".to_string(),
@@ -642,6 +942,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(14),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Пробиотики".to_string(), "Белок".to_string(), "Кальций".to_string()],
+                quick_uses: vec!["Соус к мясу: йогурт с солью, перцем и сушеным чесноком".to_string(), "Маринад для курицы или овощей".to_string(), "Замороженный йогурт порционными кубиками".to_string()],
             },
             
             // Мясные продукты
@@ -654,6 +955,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(3),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Белок".to_string(), "Ниацин".to_string(), "Селен".to_string()],
+                quick_uses: vec!["Курица, обжаренная кусочками со специями".to_string(), "Рис с курицей в одной сковороде".to_string(), "Прозрачный куриный бульон".to_string()],
             },
             ProductPreset {
                 name: "Говядина".to_string(),
@@ -664,6 +966,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(5),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Железо".to_string(), "Белок".to_string(), "Витамин B12".to_string()],
+                quick_uses: vec!["Быстрая обжарка тонкими полосками с перцем".to_string(), "Говядина, тушенная с рисом".to_string(), "Фарш для котлет на сковороде".to_string()],
             },
             
             // Рыба
@@ -676,6 +979,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(2),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Омега-3".to_string(), "Белок".to_string(), "Витамин D".to_string()],
+                quick_uses: vec!["Лосось на сковороде с солью и перцем".to_string(), "Рис с кусочками лосося".to_string(), "Слабосоленый лосось за сутки в соли с сахаром".to_string()],
             },
             ProductPreset {
                 name: "Креветки".to_string(),
@@ -686,6 +990,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(1),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Белок".to_string(), "Йод".to_string(), "Селен".to_string()],
+                quick_uses: vec!["Креветки, обжаренные в масле со специями".to_string(), "Рис с креветками".to_string(), "Креветки, отваренные в подсоленной воде".to_string()],
             },
             
             // Овощи
@@ -698,6 +1003,18 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(7),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Витамин C".to_string(), "Фолат".to_string(), "Клетчатка".to_string()],
+                quick_uses: vec!["Брокколи, обжаренная в масле с чесноком".to_string(), "Запеченная брокколи со специями".to_string(), "Крем-суп из брокколи".to_string()],
+            },
+            ProductPreset {
+                name: "Капуста белокочанная".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::FODMAP],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::Keto, DietType::Paleo, DietType::GlutenFree],
+                typical_shelf_life_days: Some(30),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Витамин C".to_string(), "Витамин K".to_string(), "Клетчатка".to_string()],
+                quick_uses: vec!["Тушеная капуста с рисом и специями".to_string(), "Быстрая квашеная капуста с солью".to_string(), "Капуста, обжаренная в масле с тмином".to_string()],
             },
             ProductPreset {
                 name: "Авокадо".to_string(),
@@ -708,6 +1025,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(5),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Здоровые жиры".to_string(), "Калий".to_string(), "Клетчатка".to_string()],
+                quick_uses: vec!["Гуакамоле: авокадо размять с солью и перцем".to_string(), "Рис с авокадо и соевым соусом".to_string(), "Тосты с авокадо".to_string()],
             },
             
             // Фрукты
@@ -720,6 +1038,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(30),
                 storage_location: "fridge".to_string(),
                 nutritional_highlights: vec!["Клетчатка".to_string(), "Витамин C".to_string(), "Антиоксиданты".to_string()],
+                quick_uses: vec!["Печеные яблоки с корицей".to_string(), "Яблочное пюре без сахара".to_string(), "Рисовая каша с яблоком".to_string()],
             },
             ProductPreset {
                 name: "Банан".to_string(),
@@ -730,6 +1049,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(7),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Калий".to_string(), "Витамин B6".to_string(), "Энергия".to_string()],
+                quick_uses: vec!["Банановые оладьи из банана и яйца".to_string(), "Замороженные бананы для смузи".to_string(), "Жареный банан с корицей".to_string()],
             },
             
             // Зерновые
@@ -742,6 +1062,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(365),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Углеводы".to_string(), "Энергия".to_string()],
+                quick_uses: vec!["Жареный рис с соевым соусом".to_string(), "Рисовая каша".to_string(), "Рисовые котлеты из остатков риса".to_string()],
             },
             ProductPreset {
                 name: "Хлеб пшеничный".to_string(),
@@ -752,6 +1073,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(5),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Углеводы".to_string(), "Клетчатка".to_string(), "B витамины".to_string()],
+                quick_uses: vec!["Сухарики с маслом и специями".to_string(), "Гренки на сковороде".to_string(), "Панировочные сухари".to_string()],
             },
             ProductPreset {
                 name: "Киноа".to_string(),
@@ -762,6 +1084,7 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(365),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Полный белок".to_string(), "Клетчатка".to_string(), "Железо".to_string()],
+                quick_uses: vec!["Киноа с обжаренными специями".to_string(), "Теплый салат из киноа с маслом".to_string(), "Киноа-каша".to_string()],
             },
             
             // Орехи и семена
@@ -774,8 +1097,316 @@ impl FoodPresets {
                 typical_shelf_life_days: Some(365),
                 storage_location: "pantry".to_string(),
                 nutritional_highlights: vec!["Здоровые жиры".to_string(), "Витамин E".to_string(), "Магний".to_string()],
+                quick_uses: vec!["Обжаренный миндаль с солью".to_string(), "Миндальная посыпка для каши".to_string(), "Миндальная паста из обжаренных орехов".to_string()],
+            },
+
+            // Базовые продукты для стартовых наборов
+            ProductPreset {
+                name: "Яйца куриные".to_string(),
+                category: FridgeCategory::Dairy,
+                common_allergens: vec![Allergen::Eggs],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegetarian, DietType::Keto, DietType::Paleo, DietType::Mediterranean, DietType::GlutenFree, DietType::DairyFree],
+                typical_shelf_life_days: Some(25),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Белок".to_string(), "Витамин B12".to_string(), "Холин".to_string()],
+                quick_uses: vec!["Омлет с солью и перцем".to_string(), "Яйца вкрутую для перекуса".to_string(), "Рис с яйцом на сковороде".to_string()],
+            },
+            ProductPreset {
+                name: "Масло сливочное".to_string(),
+                category: FridgeCategory::Dairy,
+                common_allergens: vec![Allergen::Milk],
+                common_intolerances: vec![Intolerance::Lactose],
+                suitable_diets: vec![DietType::Vegetarian, DietType::Keto, DietType::GlutenFree],
+                typical_shelf_life_days: Some(30),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Витамин A".to_string(), "Жиры".to_string()],
+                quick_uses: vec!["Гренки на сливочном масле".to_string(), "Рис с маслом и зеленью".to_string(), "Каша со сливочным маслом".to_string()],
+            },
+            ProductPreset {
+                name: "Сыр фета".to_string(),
+                category: FridgeCategory::Dairy,
+                common_allergens: vec![Allergen::Milk],
+                common_intolerances: vec![Intolerance::Lactose],
+                suitable_diets: vec![DietType::Vegetarian, DietType::Mediterranean, DietType::GlutenFree],
+                typical_shelf_life_days: Some(30),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Кальций".to_string(), "Белок".to_string()],
+                quick_uses: vec!["Салат из помидоров с фетой".to_string(), "Запеченная фета с маслом и перцем".to_string(), "Фета, размятая с зеленью, на хлеб".to_string()],
+            },
+            ProductPreset {
+                name: "Тофу".to_string(),
+                category: FridgeCategory::Other,
+                common_allergens: vec![Allergen::Soy],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean],
+                typical_shelf_life_days: Some(14),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Белок".to_string(), "Кальций".to_string(), "Железо".to_string()],
+                quick_uses: vec!["Тофу, обжаренный кубиками с соевым соусом".to_string(), "Рис с тофу".to_string(), "Скрэмбл из тофу со специями".to_string()],
+            },
+            ProductPreset {
+                name: "Помидоры".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::Histamine],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Keto, DietType::Paleo],
+                typical_shelf_life_days: Some(7),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Ликопин".to_string(), "Витамин C".to_string(), "Калий".to_string()],
+                quick_uses: vec!["Помидоры, запеченные с маслом и солью".to_string(), "Быстрый томатный соус".to_string(), "Салат из помидоров с маслом".to_string()],
+            },
+            ProductPreset {
+                name: "Огурцы".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Keto, DietType::Paleo],
+                typical_shelf_life_days: Some(7),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Вода".to_string(), "Витамин K".to_string()],
+                quick_uses: vec!["Малосольные огурцы за сутки".to_string(), "Салат из огурцов с солью и маслом".to_string(), "Огурцы с рисом и соевым соусом".to_string()],
+            },
+            ProductPreset {
+                name: "Лук репчатый".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::FODMAP],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Paleo],
+                typical_shelf_life_days: Some(60),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Витамин C".to_string(), "Антиоксиданты".to_string()],
+                quick_uses: vec!["Жареный лук для гарнира".to_string(), "Карамелизованный лук".to_string(), "Луковый суп на воде со специями".to_string()],
+            },
+            ProductPreset {
+                name: "Чеснок".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::FODMAP],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Keto, DietType::Paleo],
+                typical_shelf_life_days: Some(90),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Аллицин".to_string(), "Антиоксиданты".to_string()],
+                quick_uses: vec!["Чесночное масло для жарки".to_string(), "Запеченный чеснок".to_string(), "Чесночные гренки".to_string()],
+            },
+            ProductPreset {
+                name: "Картофель".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean],
+                typical_shelf_life_days: Some(60),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Калий".to_string(), "Витамин C".to_string(), "Углеводы".to_string()],
+                quick_uses: vec!["Картофель, запеченный дольками со специями".to_string(), "Картофельное пюре на воде с маслом".to_string(), "Жареный картофель с луком".to_string()],
+            },
+            ProductPreset {
+                name: "Морковь".to_string(),
+                category: FridgeCategory::Vegetables,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Paleo],
+                typical_shelf_life_days: Some(30),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Бета-каротин".to_string(), "Клетчатка".to_string()],
+                quick_uses: vec!["Морковь, запеченная с маслом".to_string(), "Морковный салат с чесноком".to_string(), "Тушеная морковь с рисом".to_string()],
+            },
+            ProductPreset {
+                name: "Лимон".to_string(),
+                category: FridgeCategory::Fruits,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Keto, DietType::Paleo],
+                typical_shelf_life_days: Some(30),
+                storage_location: "fridge".to_string(),
+                nutritional_highlights: vec!["Витамин C".to_string()],
+                quick_uses: vec!["Лимонная заправка с маслом".to_string(), "Вода с лимоном".to_string(), "Лимонная цедра для выпечки и рыбы".to_string()],
             },
+            ProductPreset {
+                name: "Масло оливковое".to_string(),
+                category: FridgeCategory::Condiments,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean, DietType::Keto, DietType::Paleo],
+                typical_shelf_life_days: Some(365),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Мононенасыщенные жиры".to_string(), "Витамин E".to_string()],
+                quick_uses: vec!["Заправка для салата с лимоном".to_string(), "Обжарка овощей".to_string(), "Хлеб, запеченный с маслом и чесноком".to_string()],
+            },
+            ProductPreset {
+                name: "Макароны".to_string(),
+                category: FridgeCategory::Grains,
+                common_allergens: vec![Allergen::Wheat],
+                common_intolerances: vec![Intolerance::Gluten],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::DairyFree, DietType::Mediterranean],
+                typical_shelf_life_days: Some(730),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Углеводы".to_string(), "Энергия".to_string()],
+                quick_uses: vec!["Макароны с маслом и сыром".to_string(), "Паста с томатным соусом".to_string(), "Макароны с чесноком и маслом".to_string()],
+            },
+            ProductPreset {
+                name: "Гречка".to_string(),
+                category: FridgeCategory::Grains,
+                common_allergens: vec![],
+                common_intolerances: vec![],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree],
+                typical_shelf_life_days: Some(365),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Белок".to_string(), "Железо".to_string(), "Магний".to_string()],
+                quick_uses: vec!["Гречка с маслом и луком".to_string(), "Гречневая каша на молоке".to_string(), "Гречка с грибами и луком".to_string()],
+            },
+            ProductPreset {
+                name: "Овсяные хлопья".to_string(),
+                category: FridgeCategory::Grains,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::Gluten],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::DairyFree],
+                typical_shelf_life_days: Some(365),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Клетчатка".to_string(), "Бета-глюкан".to_string(), "Магний".to_string()],
+                quick_uses: vec!["Овсянка на воде с бананом".to_string(), "Ленивая овсянка в банке".to_string(), "Овсяное печенье из хлопьев и банана".to_string()],
+            },
+            ProductPreset {
+                name: "Нут".to_string(),
+                category: FridgeCategory::Grains,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::FODMAP],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean],
+                typical_shelf_life_days: Some(365),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Белок".to_string(), "Клетчатка".to_string(), "Железо".to_string()],
+                quick_uses: vec!["Хумус: нут размять с маслом и лимоном".to_string(), "Запеченный хрустящий нут".to_string(), "Нут, тушенный с помидорами".to_string()],
+            },
+            ProductPreset {
+                name: "Чечевица".to_string(),
+                category: FridgeCategory::Grains,
+                common_allergens: vec![],
+                common_intolerances: vec![Intolerance::FODMAP],
+                suitable_diets: vec![DietType::Vegan, DietType::Vegetarian, DietType::GlutenFree, DietType::DairyFree, DietType::Mediterranean],
+                typical_shelf_life_days: Some(365),
+                storage_location: "pantry".to_string(),
+                nutritional_highlights: vec!["Белок".to_string(), "Фолат".to_string(), "Железо".to_string()],
+                quick_uses: vec!["Суп из красной чечевицы".to_string(), "Чечевица с рисом и луком".to_string(), "Чечевичные котлеты".to_string()],
+            },
+        ]
+    }
+
+    // Долгохранящиеся замены скоропортящихся продуктов
+    pub fn get_long_life_alternatives() -> Vec<LongLifeAlternative> {
+        [
+            ("Шпинат", "Шпинат замороженный", 180),
+            ("Брокколи", "Брокколи замороженная", 240),
+            ("Зеленый горошек", "Зеленый горошек замороженный", 240),
+            ("Ягоды", "Ягоды замороженные", 240),
+            ("Зелень", "Зелень замороженная", 180),
+            ("Куриная грудка", "Куриное филе замороженное", 180),
+            ("Лосось", "Лосось замороженный", 90),
+            ("Креветки", "Креветки замороженные", 180),
+            ("Молоко коровье", "Молоко ультрапастеризованное", 90),
+            ("Хлеб пшеничный", "Хлеб в нарезке для заморозки", 90),
         ]
+        .into_iter()
+        .map(|(product, alternative, shelf_life_days)| LongLifeAlternative {
+            product: product.to_string(),
+            alternative: alternative.to_string(),
+            shelf_life_days,
+        })
+        .collect()
+    }
+
+    // Стартовые наборы для пустого холодильника; каждый продукт есть в `get_product_presets`
+    pub fn get_starter_packs() -> Vec<StarterPack> {
+        #[allow(clippy::type_complexity)]
+        let packs: [(&str, &str, &str, &str, &str, &[(&str, &str, f32, &str)]); 3] = [
+            (
+                "basic",
+                "Базовый набор",
+                "Basic staples",
+                "Продукты на каждый день: каши, гарниры, молочное и овощи",
+                "Everyday essentials: grains, sides, dairy and vegetables",
+                &[
+                    ("Молоко коровье", "Milk", 1.0, "л"),
+                    ("Яйца куриные", "Eggs", 10.0, "шт"),
+                    ("Масло сливочное", "Butter", 0.2, "кг"),
+                    ("Сыр твердый", "Hard cheese", 0.3, "кг"),
+                    ("Хлеб пшеничный", "Wheat bread", 1.0, "шт"),
+                    ("Рис белый", "White rice", 1.0, "кг"),
+                    ("Гречка", "Buckwheat", 1.0, "кг"),
+                    ("Макароны", "Pasta", 0.5, "кг"),
+                    ("Картофель", "Potatoes", 2.0, "кг"),
+                    ("Морковь", "Carrots", 1.0, "кг"),
+                    ("Лук репчатый", "Onions", 1.0, "кг"),
+                    ("Куриная грудка", "Chicken breast", 0.5, "кг"),
+                    ("Яблоко", "Apples", 1.0, "кг"),
+                    ("Банан", "Bananas", 1.0, "кг"),
+                ],
+            ),
+            (
+                "mediterranean",
+                "Средиземноморская кухня",
+                "Mediterranean kitchen",
+                "Оливковое масло, овощи, рыба, бобовые и фета",
+                "Olive oil, vegetables, fish, legumes and feta",
+                &[
+                    ("Масло оливковое", "Olive oil", 0.5, "л"),
+                    ("Помидоры", "Tomatoes", 1.0, "кг"),
+                    ("Огурцы", "Cucumbers", 0.5, "кг"),
+                    ("Лук репчатый", "Onions", 0.5, "кг"),
+                    ("Чеснок", "Garlic", 3.0, "шт"),
+                    ("Лимон", "Lemons", 3.0, "шт"),
+                    ("Сыр фета", "Feta cheese", 0.2, "кг"),
+                    ("Йогурт натуральный", "Plain yogurt", 0.5, "кг"),
+                    ("Лосось", "Salmon", 0.4, "кг"),
+                    ("Креветки", "Shrimp", 0.3, "кг"),
+                    ("Нут", "Chickpeas", 0.5, "кг"),
+                    ("Макароны", "Pasta", 0.5, "кг"),
+                    ("Миндаль", "Almonds", 0.2, "кг"),
+                    ("Авокадо", "Avocado", 2.0, "шт"),
+                ],
+            ),
+            (
+                "vegan-start",
+                "Веган-старт",
+                "Vegan starter",
+                "Растительный белок, крупы, овощи и фрукты",
+                "Plant protein, grains, vegetables and fruit",
+                &[
+                    ("Тофу", "Tofu", 0.4, "кг"),
+                    ("Нут", "Chickpeas", 0.5, "кг"),
+                    ("Чечевица", "Lentils", 0.5, "кг"),
+                    ("Киноа", "Quinoa", 0.5, "кг"),
+                    ("Рис белый", "White rice", 1.0, "кг"),
+                    ("Овсяные хлопья", "Rolled oats", 0.5, "кг"),
+                    ("Брокколи", "Broccoli", 0.5, "кг"),
+                    ("Авокадо", "Avocado", 2.0, "шт"),
+                    ("Помидоры", "Tomatoes", 0.5, "кг"),
+                    ("Банан", "Bananas", 1.0, "кг"),
+                    ("Яблоко", "Apples", 1.0, "кг"),
+                    ("Миндаль", "Almonds", 0.2, "кг"),
+                    ("Масло оливковое", "Olive oil", 0.5, "л"),
+                ],
+            ),
+        ];
+
+        packs
+            .into_iter()
+            .map(|(id, name_ru, name_en, description_ru, description_en, items)| StarterPack {
+                id: id.to_string(),
+                name_ru: name_ru.to_string(),
+                name_en: name_en.to_string(),
+                description_ru: description_ru.to_string(),
+                description_en: description_en.to_string(),
+                items: items
+                    .iter()
+                    .map(|(preset_name, name_en, quantity, unit)| StarterPackItem {
+                        preset_name: preset_name.to_string(),
+                        name_en: name_en.to_string(),
+                        quantity: *quantity,
+                        unit: unit.to_string(),
+                    })
+                    .collect(),
+            })
+            .collect()
     }
 
     // Получить информацию о продукте по имени
@@ -811,38 +1442,12 @@ impl FoodPresets {
 
     // Получить все доступные аллергены для автозаполнения
     pub fn get_all_allergens() -> Vec<Allergen> {
-        vec![
-            Allergen::Peanuts,
-            Allergen::TreeNuts,
-            Allergen::Milk,
-            Allergen::Eggs,
-            Allergen::Fish,
-            Allergen::Shellfish,
-            Allergen::Soy,
-            Allergen::Wheat,
-            Allergen::Sesame,
-            Allergen::Sulfites,
-            Allergen::Celery,
-            Allergen::Mustard,
-            Allergen::Lupin,
-            Allergen::Molluscs,
-        ]
+        Allergen::ALL.to_vec()
     }
 
     // Получить все доступные непереносимости для автозаполнения
     pub fn get_all_intolerances() -> Vec<Intolerance> {
-        vec![
-            Intolerance::Lactose,
-            Intolerance::Gluten,
-            Intolerance::Fructose,
-            Intolerance::Histamine,
-            Intolerance::Sorbitol,
-            Intolerance::Sucrose,
-            Intolerance::FODMAP,
-            Intolerance::Caffeine,
-            Intolerance::Alcohol,
-            Intolerance::Tyramine,
-        ]
+        Intolerance::ALL.to_vec()
     }
 
     // Получить все доступные типы диет для автозаполнения
@@ -865,3 +1470,51 @@ impl FoodPresets {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_cyrillic(text: &str) -> bool {
+        text.chars().any(|c| matches!(c, 'а'..='я' | 'А'..='Я' | 'ё' | 'Ё'))
+    }
+
+    #[test]
+    fn every_allergen_has_info_in_both_languages() {
+        let info = FoodPresets::get_allergen_info();
+        assert_eq!(info.len(), Allergen::ALL.len());
+
+        for allergen in Allergen::ALL {
+            let entry = info.iter().find(|entry| entry.allergen == allergen).unwrap_or_else(|| panic!("no info for {:?}", allergen));
+            assert!(!entry.name_en.is_empty() && entry.name_en.is_ascii(), "{:?}: {}", allergen, entry.name_en);
+            assert!(is_cyrillic(&entry.name_ru), "{:?}: {}", allergen, entry.name_ru);
+            assert!(!entry.description.is_empty(), "{:?}", allergen);
+            assert!(!entry.common_sources.is_empty(), "{:?}", allergen);
+            assert!(!entry.cross_reactions.contains(&allergen), "{:?} cross-reacts with itself", allergen);
+        }
+    }
+
+    #[test]
+    fn every_intolerance_has_info_in_both_languages() {
+        let info = FoodPresets::get_intolerance_info();
+        assert_eq!(info.len(), Intolerance::ALL.len());
+
+        for intolerance in Intolerance::ALL {
+            let entry = info
+                .iter()
+                .find(|entry| entry.intolerance == intolerance)
+                .unwrap_or_else(|| panic!("no info for {:?}", intolerance));
+            assert!(!entry.name_en.is_empty() && entry.name_en.is_ascii(), "{:?}: {}", intolerance, entry.name_en);
+            assert!(is_cyrillic(&entry.name_ru), "{:?}: {}", intolerance, entry.name_ru);
+            assert!(!entry.avoid_foods.is_empty(), "{:?}", intolerance);
+        }
+    }
+
+    #[test]
+    fn molluscs_and_shellfish_cross_react() {
+        let info = FoodPresets::get_allergen_info();
+        let cross = |allergen: Allergen| info.iter().find(|entry| entry.allergen == allergen).unwrap().cross_reactions.clone();
+        assert!(cross(Allergen::Molluscs).contains(&Allergen::Shellfish));
+        assert!(cross(Allergen::Shellfish).contains(&Allergen::Molluscs));
+    }
+}
@@ -4,6 +4,8 @@ use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::utils::units::{base_factor, to_base, BaseUnit, ACCEPTED_UNITS};
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "recipe_category", rename_all = "lowercase")]
 pub enum RecipeCategory {
@@ -40,6 +42,12 @@ pub struct Recipe {
     pub image_url: Option<String>,
     pub source_url: Option<String>,
     pub created_by: Uuid,
+    /// Рецепт виден другим пользователям и может быть сохранен ими себе
+    pub is_public: bool,
+    /// Для копии чужого рецепта — откуда и у кого она взята
+    pub original_recipe_id: Option<Uuid>,
+    pub original_author_id: Option<Uuid>,
+    pub forks_count: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -53,11 +61,56 @@ pub struct CreateRecipe {
     pub prep_time_minutes: Option<i32>,
     pub cook_time_minutes: Option<i32>,
     pub servings: Option<i32>,
-    pub instructions: String,
+    pub steps: Vec<RecipeStepInput>,
+    /// Фото галереи рецепта по порядку
+    pub gallery: Vec<Uuid>,
     pub tags: Vec<String>,
     pub image_url: Option<String>,
     pub source_url: Option<String>,
     pub created_by: Uuid,
+    pub is_public: bool,
+}
+
+/// Шаг приготовления. `recipes.instructions` хранит те же шаги одной строкой
+/// (по шагу на строку) для старых клиентов и поиска.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RecipeStep {
+    pub id: Uuid,
+    pub recipe_id: Uuid,
+    /// С 1
+    pub position: i32,
+    pub text: String,
+    pub media_id: Option<Uuid>,
+}
+
+/// Шаг из запроса: текст и необязательное фото, загруженное автором рецепта
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecipeStepInput {
+    #[serde(deserialize_with = "crate::utils::sanitize::deserialize_sanitized")]
+    pub text: String,
+    #[serde(default)]
+    pub media_id: Option<Uuid>,
+}
+
+impl RecipeStepInput {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self { text: text.into(), media_id: None }
+    }
+
+    /// Шаги из устаревшей строки инструкций: по шагу на каждую непустую строку
+    pub fn from_instructions(instructions: &str) -> Vec<Self> {
+        instructions
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(Self::text)
+            .collect()
+    }
+}
+
+/// Устаревшая строка инструкций из шагов
+pub fn instructions_text<'a>(steps: impl IntoIterator<Item = &'a str>) -> String {
+    steps.into_iter().collect::<Vec<_>>().join("\n")
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -68,4 +121,320 @@ pub struct RecipeIngredient {
     pub quantity: f32,
     pub unit: String,
     pub notes: Option<String>,
+    /// Количество не удалось разобрать при переходе на структурированный формат — автору нужно его поправить
+    pub needs_review: bool,
+}
+
+impl RecipeIngredient {
+    /// Структурированное количество. `None` — строка ждет правки автора (`needs_review`)
+    /// или хранит значение, которое не разбирается.
+    pub fn amount(&self) -> Option<IngredientQuantity> {
+        if self.needs_review {
+            return None;
+        }
+        IngredientQuantity::from_legacy(self.quantity, &self.unit).ok()
+    }
+}
+
+/// Написания "по вкусу" в устаревшем поле `unit`
+const TO_TASTE_UNITS: &[&str] = &["по вкусу", "по желанию", "to taste"];
+/// Как количества хранятся в колонках `quantity` + `unit` и отдаются в устаревших полях ответа
+const COUNT_UNIT: &str = "шт";
+const TO_TASTE_UNIT: &str = "по вкусу";
+
+/// Количество ингредиента рецепта
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IngredientQuantity {
+    /// Вес или объем: единица из `utils::units`
+    Measured { value: f32, unit: String },
+    /// Штуки: "2 яйца"
+    Count { value: f32 },
+    /// Без количества: не учитывается в стоимости, питательности и резервах
+    ToTaste,
+}
+
+impl IngredientQuantity {
+    /// Разбор устаревшей пары `quantity` + `unit`: "по вкусу" → `ToTaste`,
+    /// пустая единица или штуки → `Count`, остальное — `Measured` с известной единицей.
+    /// Ошибка — текст для автора рецепта.
+    pub fn from_legacy(quantity: f32, unit: &str) -> Result<Self, String> {
+        let unit = unit.trim();
+        if TO_TASTE_UNITS.contains(&unit.to_lowercase().as_str()) {
+            return Ok(IngredientQuantity::ToTaste);
+        }
+
+        if unit.is_empty() {
+            IngredientQuantity::Count { value: quantity }.validated()
+        } else {
+            IngredientQuantity::Measured { value: quantity, unit: unit.to_string() }.validated()
+        }
+    }
+
+    /// Проверка значения и единицы. `Measured` в штуках приводится к `Count`.
+    pub fn validated(self) -> Result<Self, String> {
+        let value = match &self {
+            IngredientQuantity::ToTaste => return Ok(self),
+            IngredientQuantity::Measured { value, .. } | IngredientQuantity::Count { value } => *value,
+        };
+
+        if !value.is_finite() {
+            return Err("quantity must be a number".to_string());
+        }
+        if value < 0.0 {
+            return Err(format!("quantity must not be negative (got {})", value));
+        }
+        if value == 0.0 {
+            return Err("quantity must be greater than zero; use {\"kind\": \"to_taste\"} for ingredients without an amount".to_string());
+        }
+
+        match self {
+            IngredientQuantity::Measured { value, unit } => match base_factor(&unit) {
+                Some((_, BaseUnit::Piece)) => Ok(IngredientQuantity::Count { value }),
+                Some(_) => Ok(IngredientQuantity::Measured { value, unit: unit.trim().to_string() }),
+                None => Err(format!(
+                    "unknown unit '{}'; accepted units: {}, or \"{}\"",
+                    unit,
+                    ACCEPTED_UNITS.join(", "),
+                    TO_TASTE_UNIT
+                )),
+            },
+            other => Ok(other),
+        }
+    }
+
+    /// Значение и единица для расчетов; у "по вкусу" их нет
+    pub fn measure(&self) -> Option<(f32, &str)> {
+        match self {
+            IngredientQuantity::Measured { value, unit } => Some((*value, unit.as_str())),
+            IngredientQuantity::Count { value } => Some((*value, COUNT_UNIT)),
+            IngredientQuantity::ToTaste => None,
+        }
+    }
+
+    /// Количество в кг / л / шт
+    pub fn to_base(&self) -> Option<(f64, BaseUnit)> {
+        let (value, unit) = self.measure()?;
+        to_base(value as f64, unit)
+    }
+
+    /// Пара `quantity` + `unit` для хранения и устаревших полей ответа
+    pub fn legacy_parts(&self) -> (f32, String) {
+        self.measure()
+            .map_or((0.0, TO_TASTE_UNIT.to_string()), |(value, unit)| (value, unit.to_string()))
+    }
+}
+
+/// Рецепт, который можно приготовить из продуктов холодильника (без участия ИИ)
+#[derive(Debug, Clone, Serialize)]
+pub struct CookableRecipe {
+    pub recipe_id: Uuid,
+    pub name: String,
+    pub category: RecipeCategory,
+    pub difficulty: DifficultyLevel,
+    pub total_time_minutes: Option<i32>,
+    pub image_url: Option<String>,
+    /// Рецепт пользователя или сохраненный им; `false` — из публичного каталога
+    pub is_saved: bool,
+    /// Доля ингредиентов, которые есть в холодильнике, 0..100
+    pub match_percentage: f64,
+    pub have: usize,
+    pub total: usize,
+    pub missing_ingredients: Vec<String>,
+    /// Ингредиенты, закрывающие продукты с истекающим сроком
+    pub uses_expiring: Vec<String>,
+    pub cost_estimate: RecipeCostEstimate,
+}
+
+/// Примерная стоимость рецепта по ценам из холодильника пользователя и справочным ценам
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeCostEstimate {
+    pub currency: &'static str,
+    /// Сумма по оцененным ингредиентам; неоцененные в нее не входят
+    pub total: f64,
+    /// `None`, если в рецепте не указано число порций
+    pub per_serving: Option<f64>,
+    pub servings: Option<i32>,
+    pub ingredients: Vec<IngredientCost>,
+    pub unpriced: Vec<UnpricedIngredient>,
+    pub confidence: CostConfidence,
+    /// Все ингредиенты оценены — `total` можно считать полной стоимостью
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngredientCost {
+    pub name: String,
+    pub quantity: f32,
+    pub unit: String,
+    pub cost: f64,
+    /// Цена за кг / л / шт, по которой посчитана стоимость
+    pub unit_price: f64,
+    pub base_unit: BaseUnit,
+    pub source: PriceSource,
+}
+
+/// Откуда взята цена ингредиента
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// Из продуктов, которые пользователь покупал
+    Personal,
+    /// Из встроенной таблицы средних цен
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnpricedIngredient {
+    pub name: String,
+    pub reason: UnpricedReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnpricedReason {
+    /// Цена неизвестна ни из холодильника, ни из таблицы
+    NoPrice,
+    /// Количество не указано ("по вкусу")
+    NoQuantity,
+    /// Единица ингредиента не приводится к кг / л / шт
+    UnknownUnit,
+    /// Цена известна в других единицах (за шт, а в рецепте граммы)
+    IncompatibleUnit,
+}
+
+/// Сколько ингредиентов оценено по своим ценам, по справочным и не оценено
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CostConfidence {
+    pub personal: usize,
+    pub default: usize,
+    pub unpriced: usize,
+}
+
+/// Похожий рецепт: общие нормализованные теги, взвешенные по редкости
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RelatedRecipe {
+    pub id: Uuid,
+    pub name: String,
+    pub category: RecipeCategory,
+    pub image_url: Option<String>,
+    /// Общие теги, самые редкие первыми
+    pub shared_tags: Vec<String>,
+    pub score: f64,
+}
+
+/// Рецепт для виджета на чужих сайтах. Схема стабильна: поля только добавляются,
+/// при несовместимых изменениях растет `schema_version`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeEmbed {
+    pub schema_version: u32,
+    pub name: String,
+    pub image: Option<String>,
+    pub time: RecipeEmbedTime,
+    pub servings: Option<i32>,
+    pub ingredients: Vec<RecipeEmbedIngredient>,
+    pub steps: Vec<RecipeEmbedStep>,
+    /// Публичная страница рецепта
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RecipeEmbedTime {
+    pub prep_minutes: Option<i32>,
+    pub cook_minutes: Option<i32>,
+    pub total_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeEmbedIngredient {
+    pub name: String,
+    /// `None` — количество ждет правки автора
+    pub amount: Option<IngredientQuantity>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeEmbedStep {
+    pub text: String,
+    pub image: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn measured(value: f32, unit: &str) -> IngredientQuantity {
+        IngredientQuantity::Measured { value, unit: unit.to_string() }
+    }
+
+    #[test]
+    fn legacy_pairs_are_parsed_or_explained() {
+        let cases: Vec<(f32, &str, Result<IngredientQuantity, &str>)> = vec![
+            (200.0, "г", Ok(measured(200.0, "г"))),
+            (1.5, " кг. ", Ok(measured(1.5, "кг."))),
+            (0.5, "l", Ok(measured(0.5, "l"))),
+            (2.0, "", Ok(IngredientQuantity::Count { value: 2.0 })),
+            (3.0, "шт", Ok(IngredientQuantity::Count { value: 3.0 })),
+            (3.0, "PCS", Ok(IngredientQuantity::Count { value: 3.0 })),
+            (0.0, "по вкусу", Ok(IngredientQuantity::ToTaste)),
+            (5.0, "По вкусу", Ok(IngredientQuantity::ToTaste)),
+            (0.0, "to taste", Ok(IngredientQuantity::ToTaste)),
+            (0.0, "г", Err("greater than zero")),
+            (0.0, "", Err("greater than zero")),
+            (-1.0, "г", Err("must not be negative")),
+            (f32::NAN, "г", Err("must be a number")),
+            (2.0, "ст. л.", Err("unknown unit 'ст. л.'; accepted units: г, кг, мл, л, шт, g, kg, ml, l, pcs, or \"по вкусу\"")),
+        ];
+
+        for (quantity, unit, expected) in cases {
+            match (IngredientQuantity::from_legacy(quantity, unit), expected) {
+                (Ok(parsed), Ok(expected)) => assert_eq!(parsed, expected, "{} {}", quantity, unit),
+                (Err(message), Err(expected)) => assert!(message.contains(expected), "{} {}: {}", quantity, unit, message),
+                (parsed, expected) => panic!("{} {}: got {:?}, expected {:?}", quantity, unit, parsed, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn structured_form_round_trips_and_keeps_legacy_parts() {
+        let cases = [
+            (measured(200.0, "г"), json!({ "kind": "measured", "value": 200.0, "unit": "г" }), (200.0, "г")),
+            (IngredientQuantity::Count { value: 2.0 }, json!({ "kind": "count", "value": 2.0 }), (2.0, "шт")),
+            (IngredientQuantity::ToTaste, json!({ "kind": "to_taste" }), (0.0, "по вкусу")),
+        ];
+
+        for (amount, shape, (quantity, unit)) in cases {
+            assert_eq!(serde_json::to_value(&amount).unwrap(), shape);
+            assert_eq!(serde_json::from_value::<IngredientQuantity>(shape).unwrap(), amount);
+            assert_eq!(amount.legacy_parts(), (quantity, unit.to_string()));
+            // Устаревшая пара разбирается обратно в то же количество
+            assert_eq!(IngredientQuantity::from_legacy(quantity, unit).unwrap(), amount);
+        }
+
+        assert!(serde_json::from_value::<IngredientQuantity>(json!({ "kind": "spoon", "value": 1 })).is_err());
+        assert!(serde_json::from_value::<IngredientQuantity>(json!({ "kind": "measured", "value": 1 })).is_err());
+        // Штуки в форме measured приводятся к count
+        assert_eq!(measured(4.0, "штук").validated(), Ok(IngredientQuantity::Count { value: 4.0 }));
+        assert_eq!(measured(250.0, "мл").to_base(), Some((0.25, BaseUnit::L)));
+        assert_eq!(IngredientQuantity::ToTaste.to_base(), None);
+    }
+
+    #[test]
+    fn rows_waiting_for_review_have_no_amount() {
+        let row = |quantity: f32, unit: &str, needs_review: bool| RecipeIngredient {
+            id: Uuid::new_v4(),
+            recipe_id: Uuid::nil(),
+            name: "Мука".to_string(),
+            quantity,
+            unit: unit.to_string(),
+            notes: None,
+            needs_review,
+        };
+
+        assert_eq!(row(200.0, "г", false).amount(), Some(measured(200.0, "г")));
+        assert_eq!(row(200.0, "г", true).amount(), None);
+        assert_eq!(row(2.0, "стакана", false).amount(), None);
+    }
 }
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Сохраненный ежемесячный отчет; `month` — первое число отчетного месяца
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MonthlyReport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub month: NaiveDate,
+    pub html: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Строка списка покупок; `advice` — почему эту покупку стоит пересмотреть
+#[derive(Debug, Clone, Serialize)]
+pub struct ShoppingListItem {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advice: Option<ShoppingAdvice>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShoppingAdvice {
+    pub reason: ShoppingAdviceReason,
+    pub action: ShoppingAction,
+    /// Объяснение для пользователя
+    pub message: String,
+    /// Продукт холодильника, который можно использовать вместо покупки
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fridge_item_id: Option<Uuid>,
+    /// Долгохранящаяся замена из справочника продуктов
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alternative: Option<String>,
+    /// Доля покупок этого продукта, ушедших в отходы (0..1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waste_rate: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShoppingAdviceReason {
+    /// Продукт уже есть и будет годен к дате готовки
+    InStock,
+    /// Продукт есть, но скоро испортится — его стоит использовать первым
+    ExpiringInStock,
+    /// Этот продукт обычно выбрасывается
+    HabitualWaste,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShoppingAction {
+    Skip,
+    ReduceQuantity,
+    SwitchToLongLasting,
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::fridge::ExpiryUrgency;
+
+/// Перекус из продукта холодильника: порция, укладывающаяся в лимит калорий
+#[derive(Debug, Clone, Serialize)]
+pub struct SnackSuggestion {
+    pub item_id: Uuid,
+    pub name: String,
+    pub portion_grams: f32,
+    /// КБЖУ этой порции
+    pub calories: f32,
+    pub protein: f32,
+    pub fat: f32,
+    pub carbs: f32,
+    /// Белок на 100 ккал — по нему (и по сроку годности) упорядочены подсказки
+    pub protein_per_100_kcal: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_urgency: Option<ExpiryUrgency>,
+    /// Как подать — из быстрых способов использовать продукт
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serving_idea: Option<String>,
+    pub nutrition_source: SnackNutritionSource,
+}
+
+/// Откуда взята пищевая ценность продукта
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnackNutritionSource {
+    /// Поле «Пищевая ценность» продукта
+    Label,
+    /// База продуктов дневника
+    FoodDatabase,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnackSuggestionsResponse {
+    pub suggestions: Vec<SnackSuggestion>,
+    /// Продукты без данных о пищевой ценности — их не удалось оценить
+    pub unevaluated_items: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Тип события ленты активности
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventType {
+    DiaryEntryLogged,
+    FridgeItemAdded,
+    FridgeItemConsumed,
+    FridgeItemWasted,
+    RecipeCreated,
+    RecipeCooked,
+    GoalProgressed,
+    GoalCompleted,
+    PostPublished,
+    AchievementEarned,
+    WellbeingCheckin,
+}
+
+impl TimelineEventType {
+    pub const ALL: [TimelineEventType; 11] = [
+        TimelineEventType::DiaryEntryLogged,
+        TimelineEventType::FridgeItemAdded,
+        TimelineEventType::FridgeItemConsumed,
+        TimelineEventType::FridgeItemWasted,
+        TimelineEventType::RecipeCreated,
+        TimelineEventType::RecipeCooked,
+        TimelineEventType::GoalProgressed,
+        TimelineEventType::GoalCompleted,
+        TimelineEventType::PostPublished,
+        TimelineEventType::AchievementEarned,
+        TimelineEventType::WellbeingCheckin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEventType::DiaryEntryLogged => "diary_entry_logged",
+            TimelineEventType::FridgeItemAdded => "fridge_item_added",
+            TimelineEventType::FridgeItemConsumed => "fridge_item_consumed",
+            TimelineEventType::FridgeItemWasted => "fridge_item_wasted",
+            TimelineEventType::RecipeCreated => "recipe_created",
+            TimelineEventType::RecipeCooked => "recipe_cooked",
+            TimelineEventType::GoalProgressed => "goal_progressed",
+            TimelineEventType::GoalCompleted => "goal_completed",
+            TimelineEventType::PostPublished => "post_published",
+            TimelineEventType::AchievementEarned => "achievement_earned",
+            TimelineEventType::WellbeingCheckin => "wellbeing_checkin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// Событие ленты: `reference_id` — объект, который открывает клиент
+/// (запись дневника, продукт, рецепт, цель, пост...), `summary_fields` — поля для строки ленты
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    #[serde(rename = "type")]
+    pub event_type: TimelineEventType,
+    pub timestamp: DateTime<Utc>,
+    pub title: String,
+    pub reference_id: Uuid,
+    pub summary_fields: serde_json::Value,
+}
+
+/// Страница ленты; `next_cursor` отсутствует на последней странице
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelinePage {
+    pub events: Vec<TimelineEvent>,
+    pub next_cursor: Option<String>,
+}
@@ -3,6 +3,8 @@ use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc, Datelike};
 
+use crate::utils::device::DeviceInfo;
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "user_role", rename_all = "lowercase")]
 pub enum UserRole {
@@ -74,6 +76,22 @@ pub struct CreateUserSession {
     pub user_id: Uuid,
     pub refresh_token: String,
     pub expires_at: DateTime<Utc>,
+    pub device: DeviceInfo,
+}
+
+/// Сессия в списке "где выполнен вход" (без refresh-токена)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub ip_address: Option<String>,
+    pub location: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    /// Сессия, из которой сделан запрос
+    pub current: bool,
 }
 
 impl User {
@@ -0,0 +1,767 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{types::Json, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        account_merge::{AccountMergeResult, MergeDomain, MergeDomainCount, MergeJob},
+        admin_job::AdminJobStatus,
+        admin_user::AdminAction,
+        fridge::DietaryProfile,
+    },
+    services::{
+        admin_users::audit,
+        dietary::merge_profiles,
+        fridge_autocomplete,
+    },
+    utils::errors::AppError,
+};
+
+/// Причина блокировки исходного аккаунта — ее видит тот, кто попробует в него войти
+const MERGED_ACCOUNT_REASON: &str = "This account was merged into another account; sign in with that account instead";
+
+/// SQL одного домена. `$1` — исходный аккаунт, `$2` — целевой.
+/// `conflicts` удаляют строки, которые после переноса нарушили бы уникальность
+/// (остается строка целевого аккаунта), `moves` переносят остальное.
+/// `count` — строки исходного аккаунта для пробного запуска, параметр только `$1`.
+struct DomainPlan {
+    conflicts: &'static [&'static str],
+    moves: &'static [&'static str],
+    count: &'static str,
+}
+
+fn plan(domain: MergeDomain) -> DomainPlan {
+    match domain {
+        MergeDomain::Fridge => DomainPlan {
+            conflicts: &[],
+            moves: &[
+                "UPDATE fridge_items SET user_id = $2, updated_at = NOW() WHERE user_id = $1",
+                "UPDATE food_waste SET user_id = $2 WHERE user_id = $1",
+                "UPDATE fridge_reservations SET user_id = $2 WHERE user_id = $1",
+                "UPDATE fridge_category_changes SET user_id = $2 WHERE user_id = $1",
+                "UPDATE fridge_consumptions SET user_id = $2 WHERE user_id = $1",
+            ],
+            count: "SELECT (SELECT COUNT(*) FROM fridge_items WHERE user_id = $1) + (SELECT COUNT(*) FROM food_waste WHERE user_id = $1)",
+        },
+        MergeDomain::Diary => DomainPlan {
+            conflicts: &[],
+            moves: &[
+                "UPDATE diary_entries SET user_id = $2 WHERE user_id = $1",
+                "UPDATE daily_wellbeing SET user_id = $2 WHERE user_id = $1",
+            ],
+            count: "SELECT (SELECT COUNT(*) FROM diary_entries WHERE user_id = $1) + (SELECT COUNT(*) FROM daily_wellbeing WHERE user_id = $1)",
+        },
+        MergeDomain::Goals => DomainPlan {
+            conflicts: &[],
+            // История прогресса, напоминания и ссылки на карточки привязаны к цели и переезжают вместе с ней
+            moves: &[
+                "UPDATE goals SET user_id = $2, updated_at = NOW() WHERE user_id = $1",
+                "UPDATE achievements SET user_id = $2 WHERE user_id = $1",
+            ],
+            count: "SELECT (SELECT COUNT(*) FROM goals WHERE user_id = $1) + (SELECT COUNT(*) FROM achievements WHERE user_id = $1)",
+        },
+        MergeDomain::Weight => DomainPlan {
+            conflicts: &[
+                "DELETE FROM weight_entries s WHERE s.user_id = $1 \
+                 AND EXISTS (SELECT 1 FROM weight_entries t WHERE t.user_id = $2 AND t.date = s.date)",
+            ],
+            moves: &["UPDATE weight_entries SET user_id = $2 WHERE user_id = $1"],
+            count: "SELECT COUNT(*) FROM weight_entries WHERE user_id = $1",
+        },
+        MergeDomain::Recipes => DomainPlan {
+            conflicts: &[
+                "DELETE FROM recipe_ratings s WHERE s.user_id = $1 \
+                 AND EXISTS (SELECT 1 FROM recipe_ratings t WHERE t.user_id = $2 AND t.recipe_id = s.recipe_id)",
+            ],
+            moves: &[
+                "UPDATE recipes SET created_by = $2, updated_at = NOW() WHERE created_by = $1",
+                "UPDATE recipes SET original_author_id = $2 WHERE original_author_id = $1",
+                "UPDATE recipe_ratings SET user_id = $2 WHERE user_id = $1",
+                // Фото шагов и галерей переходят вместе с рецептами
+                "UPDATE media_files SET user_id = $2 WHERE user_id = $1",
+            ],
+            count: "SELECT (SELECT COUNT(*) FROM recipes WHERE created_by = $1) + (SELECT COUNT(*) FROM recipe_ratings WHERE user_id = $1)",
+        },
+        MergeDomain::Favorites => DomainPlan {
+            conflicts: &[
+                "DELETE FROM recipe_favorites s WHERE s.user_id = $1 \
+                 AND EXISTS (SELECT 1 FROM recipe_favorites t WHERE t.user_id = $2 AND t.recipe_id = s.recipe_id)",
+            ],
+            moves: &["UPDATE recipe_favorites SET user_id = $2 WHERE user_id = $1"],
+            count: "SELECT COUNT(*) FROM recipe_favorites WHERE user_id = $1",
+        },
+        MergeDomain::Posts => DomainPlan {
+            conflicts: &[
+                "DELETE FROM likes s WHERE s.user_id = $1 \
+                 AND EXISTS (SELECT 1 FROM likes t WHERE t.user_id = $2 AND (t.post_id = s.post_id OR t.comment_id = s.comment_id))",
+            ],
+            moves: &[
+                "UPDATE posts SET author_id = $2, updated_at = NOW() WHERE author_id = $1",
+                "UPDATE likes SET user_id = $2 WHERE user_id = $1",
+            ],
+            count: "SELECT (SELECT COUNT(*) FROM posts WHERE author_id = $1) + (SELECT COUNT(*) FROM likes WHERE user_id = $1)",
+        },
+        MergeDomain::Comments => DomainPlan {
+            conflicts: &[],
+            moves: &["UPDATE comments SET author_id = $2, updated_at = NOW() WHERE author_id = $1"],
+            count: "SELECT COUNT(*) FROM comments WHERE author_id = $1",
+        },
+        MergeDomain::Follows => DomainPlan {
+            conflicts: &[
+                // Подписка между объединяемыми аккаунтами стала бы подпиской на себя
+                "DELETE FROM follows WHERE (follower_id = $1 AND following_id = $2) OR (follower_id = $2 AND following_id = $1)",
+                "DELETE FROM follows s WHERE s.follower_id = $1 \
+                 AND EXISTS (SELECT 1 FROM follows t WHERE t.follower_id = $2 AND t.following_id = s.following_id)",
+                "DELETE FROM follows s WHERE s.following_id = $1 \
+                 AND EXISTS (SELECT 1 FROM follows t WHERE t.following_id = $2 AND t.follower_id = s.follower_id)",
+            ],
+            moves: &[
+                "UPDATE follows SET follower_id = $2 WHERE follower_id = $1",
+                "UPDATE follows SET following_id = $2 WHERE following_id = $1",
+            ],
+            count: "SELECT COUNT(*) FROM follows WHERE follower_id = $1 OR following_id = $1",
+        },
+        // Профили объединяются в `merge_dietary_profile`
+        MergeDomain::DietaryProfile => DomainPlan {
+            conflicts: &[],
+            moves: &[],
+            count: "SELECT COUNT(*) FROM dietary_profiles WHERE user_id = $1",
+        },
+        MergeDomain::Notifications => DomainPlan {
+            conflicts: &[],
+            moves: &["UPDATE notifications SET user_id = $2 WHERE user_id = $1"],
+            count: "SELECT COUNT(*) FROM notifications WHERE user_id = $1",
+        },
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MergeAccountRow {
+    id: Uuid,
+    suspended_at: Option<DateTime<Utc>>,
+    merged_into: Option<Uuid>,
+}
+
+/// Объединение дублирующихся аккаунтов: данные исходного аккаунта переносятся на целевой,
+/// исходный блокируется. Каждый домен переносится своей транзакцией и отмечается в `merge_jobs`
+/// в той же транзакции, поэтому прерванное объединение продолжается повторным запросом.
+pub struct AccountMergeService {
+    pool: DbPool,
+}
+
+impl AccountMergeService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Пробный запуск: сколько строк каждого домена принадлежит исходному аккаунту
+    pub async fn preview(&self, source_user_id: Uuid, target_user_id: Uuid) -> Result<AccountMergeResult, AppError> {
+        self.check_accounts(source_user_id, target_user_id).await?;
+
+        let mut domains = Vec::with_capacity(MergeDomain::ALL.len());
+        for domain in MergeDomain::ALL {
+            let (rows,): (i64,) = sqlx::query_as(plan(domain).count)
+                .bind(source_user_id)
+                .fetch_one(&self.pool)
+                .await?;
+            domains.push(MergeDomainCount { domain, rows });
+        }
+
+        Ok(AccountMergeResult {
+            source_user_id,
+            target_user_id,
+            dry_run: true,
+            domains,
+            job: None,
+        })
+    }
+
+    /// Переносит данные и блокирует исходный аккаунт. Повторный вызов с теми же аккаунтами
+    /// продолжает прерванное объединение или возвращает уже завершенное.
+    pub async fn merge(&self, admin_id: Uuid, source_user_id: Uuid, target_user_id: Uuid) -> Result<AccountMergeResult, AppError> {
+        if admin_id == source_user_id {
+            return Err(AppError::BadRequest("Admins cannot merge away their own account".to_string()));
+        }
+        self.check_accounts(source_user_id, target_user_id).await?;
+
+        let job = sqlx::query_as::<_, MergeJob>(
+            r#"
+            INSERT INTO merge_jobs (source_user_id, target_user_id, created_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (source_user_id) DO UPDATE
+            SET status = CASE WHEN merge_jobs.status = 'completed' THEN merge_jobs.status ELSE 'running' END,
+                error = NULL,
+                updated_at = NOW()
+            RETURNING *
+            "#
+        )
+        .bind(source_user_id)
+        .bind(target_user_id)
+        .bind(admin_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if job.target_user_id != target_user_id {
+            return Err(AppError::Conflict(format!(
+                "Account {} is already being merged into {}",
+                source_user_id, job.target_user_id
+            )));
+        }
+
+        let job = if job.status == AdminJobStatus::Completed {
+            job
+        } else {
+            match self.run(admin_id, &job).await {
+                Ok(job) => job,
+                Err(e) => {
+                    sqlx::query(
+                        "UPDATE merge_jobs SET status = 'failed', error = $2, updated_at = NOW() WHERE id = $1"
+                    )
+                    .bind(job.id)
+                    .bind(e.to_string())
+                    .execute(&self.pool)
+                    .await?;
+                    return Err(e);
+                }
+            }
+        };
+
+        let domains = MergeDomain::ALL
+            .iter()
+            .map(|domain| MergeDomainCount {
+                domain: *domain,
+                rows: job.counts.get(domain.as_str()).and_then(|rows| rows.as_i64()).unwrap_or(0),
+            })
+            .collect();
+
+        Ok(AccountMergeResult {
+            source_user_id,
+            target_user_id,
+            dry_run: false,
+            domains,
+            job: Some(job),
+        })
+    }
+
+    /// Исходный аккаунт либо еще не объединялся, либо уже объединен именно с целевым
+    async fn check_accounts(&self, source_user_id: Uuid, target_user_id: Uuid) -> Result<(), AppError> {
+        if source_user_id == target_user_id {
+            return Err(AppError::BadRequest("Source and target accounts must be different".to_string()));
+        }
+
+        let accounts = sqlx::query_as::<_, MergeAccountRow>(
+            "SELECT id, suspended_at, merged_into FROM users WHERE id = ANY($1)"
+        )
+        .bind(vec![source_user_id, target_user_id])
+        .fetch_all(&self.pool)
+        .await?;
+
+        let find = |id: Uuid, label: &str| {
+            accounts
+                .iter()
+                .find(|account| account.id == id)
+                .ok_or_else(|| AppError::NotFound(format!("{} account not found", label)))
+        };
+        let source = find(source_user_id, "Source")?;
+        let target = find(target_user_id, "Target")?;
+
+        if target.merged_into.is_some() || target.suspended_at.is_some() {
+            return Err(AppError::BadRequest("Target account is suspended or has been merged into another account".to_string()));
+        }
+        if let Some(merged_into) = source.merged_into.filter(|merged_into| *merged_into != target_user_id) {
+            return Err(AppError::Conflict(format!(
+                "Account {} has already been merged into {}",
+                source_user_id, merged_into
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn run(&self, admin_id: Uuid, job: &MergeJob) -> Result<MergeJob, AppError> {
+        for domain in MergeDomain::ALL {
+            if !job.completed_domains.iter().any(|completed| completed == domain.as_str()) {
+                self.merge_domain(job, domain).await?;
+            }
+        }
+
+        self.finish(admin_id, job).await
+    }
+
+    async fn merge_domain(&self, job: &MergeJob, domain: MergeDomain) -> Result<(), AppError> {
+        let (source, target) = (job.source_user_id, job.target_user_id);
+        let mut tx = self.pool.begin().await?;
+
+        // Блокировка строки задачи: параллельный запуск того же объединения ждет и пропускает домен
+        let (completed_domains,): (Vec<String>,) = sqlx::query_as(
+            "SELECT completed_domains FROM merge_jobs WHERE id = $1 FOR UPDATE"
+        )
+        .bind(job.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if completed_domains.iter().any(|completed| completed == domain.as_str()) {
+            return Ok(());
+        }
+
+        let plan = plan(domain);
+        for statement in plan.conflicts {
+            sqlx::query(statement).bind(source).bind(target).execute(&mut *tx).await?;
+        }
+
+        let mut moved: u64 = 0;
+        for statement in plan.moves {
+            moved += sqlx::query(statement).bind(source).bind(target).execute(&mut *tx).await?.rows_affected();
+        }
+
+        match domain {
+            MergeDomain::Fridge => {
+                fridge_autocomplete::invalidate(source);
+                fridge_autocomplete::invalidate(target);
+            }
+            MergeDomain::DietaryProfile => {
+                moved += merge_dietary_profile(&mut tx, source, target).await?;
+            }
+            _ => {}
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE merge_jobs
+            SET completed_domains = array_append(completed_domains, $2),
+                counts = counts || jsonb_build_object($2, $3),
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(job.id)
+        .bind(domain.as_str())
+        .bind(moved as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Блокирует исходный аккаунт и записывает объединение в журнал администраторов
+    /// и в журналы активности обоих пользователей
+    async fn finish(&self, admin_id: Uuid, job: &MergeJob) -> Result<MergeJob, AppError> {
+        let (source, target) = (job.source_user_id, job.target_user_id);
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET suspended_at = COALESCE(suspended_at, NOW()),
+                suspension_reason = $3,
+                merged_into = $2,
+                sessions_invalidated_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(source)
+        .bind(target)
+        .bind(MERGED_ACCOUNT_REASON)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE api_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+
+        let job = sqlx::query_as::<_, MergeJob>(
+            "UPDATE merge_jobs SET status = 'completed', finished_at = NOW(), updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(job.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let details = json!({
+            "merge_job_id": job.id,
+            "source_user_id": source,
+            "target_user_id": target,
+            "counts": job.counts,
+        });
+        audit(&mut tx, Some(admin_id), AdminAction::MergeUsers, source, details.clone()).await?;
+        audit(&mut tx, Some(admin_id), AdminAction::MergeUsers, target, details.clone()).await?;
+
+        for (user_id, action) in [(source, "account_merged_into"), (target, "account_merged_from")] {
+            sqlx::query(
+                "INSERT INTO activity_log (id, user_id, actor_id, action, details) VALUES ($1, $2, $3, $4, $5)"
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(admin_id)
+            .bind(action)
+            .bind(&details)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(job)
+    }
+}
+
+/// Профиль целевого аккаунта дополняется ограничениями исходного (`dietary::merge_profiles`),
+/// профиль исходного удаляется. Возвращает 1, если у исходного аккаунта был профиль.
+async fn merge_dietary_profile(tx: &mut Transaction<'_, Postgres>, source: Uuid, target: Uuid) -> Result<u64, AppError> {
+    let Some(source_profile) = sqlx::query_as::<_, DietaryProfile>("SELECT * FROM dietary_profiles WHERE user_id = $1")
+        .bind(source)
+        .fetch_optional(&mut **tx)
+        .await?
+    else {
+        return Ok(0);
+    };
+
+    let target_profile = sqlx::query_as::<_, DietaryProfile>("SELECT * FROM dietary_profiles WHERE user_id = $1")
+        .bind(target)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    let merged = merge_profiles(target, target_profile.as_ref(), &source_profile);
+
+    sqlx::query(
+        r#"
+        INSERT INTO dietary_profiles (user_id, allergies, intolerances, diets, custom_restrictions, severity_notes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id) DO UPDATE
+        SET allergies = EXCLUDED.allergies,
+            intolerances = EXCLUDED.intolerances,
+            diets = EXCLUDED.diets,
+            custom_restrictions = EXCLUDED.custom_restrictions,
+            severity_notes = EXCLUDED.severity_notes
+        "#
+    )
+    .bind(merged.user_id)
+    .bind(Json(&merged.allergies))
+    .bind(Json(&merged.intolerances))
+    .bind(Json(&merged.diets))
+    .bind(Json(&merged.custom_restrictions))
+    .bind(&merged.severity_notes)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query("DELETE FROM dietary_profiles WHERE user_id = $1")
+        .bind(source)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{insert_diary_entry, insert_fridge_item, insert_media, insert_recipe, insert_user};
+
+    async fn exec(pool: &PgPool, sql: &str, binds: &[Uuid]) {
+        let mut query = sqlx::query(sql);
+        for id in binds {
+            query = query.bind(*id);
+        }
+        query.execute(pool).await.unwrap_or_else(|e| panic!("{}: {}", sql, e));
+    }
+
+    async fn count(pool: &PgPool, sql: &str, id: Uuid) -> i64 {
+        let (rows,): (i64,) = sqlx::query_as(sql).bind(id).fetch_one(pool).await.unwrap();
+        rows
+    }
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid) -> Uuid {
+        let (id,): (Uuid,) = sqlx::query_as("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'пост', 'text') RETURNING id")
+            .bind(author_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    /// Строки исходного аккаунта во всех таблицах, которые переносит объединение
+    const SOURCE_ROWS: &[(&str, &str)] = &[
+        ("fridge_items", "SELECT COUNT(*) FROM fridge_items WHERE user_id = $1"),
+        ("food_waste", "SELECT COUNT(*) FROM food_waste WHERE user_id = $1"),
+        ("diary_entries", "SELECT COUNT(*) FROM diary_entries WHERE user_id = $1"),
+        ("daily_wellbeing", "SELECT COUNT(*) FROM daily_wellbeing WHERE user_id = $1"),
+        ("goals", "SELECT COUNT(*) FROM goals WHERE user_id = $1"),
+        ("achievements", "SELECT COUNT(*) FROM achievements WHERE user_id = $1"),
+        ("weight_entries", "SELECT COUNT(*) FROM weight_entries WHERE user_id = $1"),
+        ("recipes", "SELECT COUNT(*) FROM recipes WHERE created_by = $1"),
+        ("recipe_ratings", "SELECT COUNT(*) FROM recipe_ratings WHERE user_id = $1"),
+        ("media_files", "SELECT COUNT(*) FROM media_files WHERE user_id = $1"),
+        ("recipe_favorites", "SELECT COUNT(*) FROM recipe_favorites WHERE user_id = $1"),
+        ("posts", "SELECT COUNT(*) FROM posts WHERE author_id = $1"),
+        ("likes", "SELECT COUNT(*) FROM likes WHERE user_id = $1"),
+        ("comments", "SELECT COUNT(*) FROM comments WHERE author_id = $1"),
+        ("follows", "SELECT COUNT(*) FROM follows WHERE follower_id = $1 OR following_id = $1"),
+        ("dietary_profiles", "SELECT COUNT(*) FROM dietary_profiles WHERE user_id = $1"),
+        ("notifications", "SELECT COUNT(*) FROM notifications WHERE user_id = $1"),
+        ("user_sessions", "SELECT COUNT(*) FROM user_sessions WHERE user_id = $1"),
+        ("api_tokens", "SELECT COUNT(*) FROM api_tokens WHERE user_id = $1 AND revoked_at IS NULL"),
+    ];
+
+    /// Исходный аккаунт с данными в каждом домене и пересечениями с целевым:
+    /// вес за тот же день, одинаковые оценка, избранное, лайк и подписка, подписка друг на друга
+    async fn populate(pool: &PgPool, source: Uuid, target: Uuid, third: Uuid) {
+        let item = insert_fridge_item(pool, source, "молоко", None, Utc::now() - Duration::days(1)).await;
+        exec(
+            pool,
+            "INSERT INTO food_waste (user_id, original_item_id, name, wasted_quantity, unit, category, waste_reason) \
+             VALUES ($1, $2, 'молоко', 100, 'мл', 'dairy', 'spoiled')",
+            &[source, item.id],
+        )
+        .await;
+
+        insert_diary_entry(pool, source, "breakfast", 350.0, 12.0, Utc::now()).await;
+        exec(pool, "INSERT INTO daily_wellbeing (user_id, date, mood_score) VALUES ($1, NOW(), 4)", &[source]).await;
+
+        exec(
+            pool,
+            "INSERT INTO goals (user_id, title, goal_type, target_value, unit) VALUES ($1, 'Белок', 'protein_intake', 120, 'g')",
+            &[source],
+        )
+        .await;
+        exec(
+            pool,
+            "INSERT INTO achievements (user_id, title, description, icon) VALUES ($1, 'Неделя', 'Семь дней подряд', 'star')",
+            &[source],
+        )
+        .await;
+
+        exec(pool, "INSERT INTO weight_entries (user_id, weight, date) VALUES ($1, 70, '2026-10-01'), ($1, 69.5, '2026-10-02')", &[source]).await;
+        exec(pool, "INSERT INTO weight_entries (user_id, weight, date) VALUES ($1, 82, '2026-10-01')", &[target]).await;
+
+        let own_recipe = insert_recipe(pool, source, "Сырники", true).await;
+        let shared_recipe = insert_recipe(pool, third, "Борщ", true).await;
+        insert_media(pool, source).await;
+        exec(pool, "INSERT INTO recipe_ratings (recipe_id, user_id, rating) VALUES ($1, $2, 5)", &[shared_recipe, source]).await;
+        exec(pool, "INSERT INTO recipe_ratings (recipe_id, user_id, rating) VALUES ($1, $2, 3)", &[shared_recipe, target]).await;
+        exec(pool, "INSERT INTO recipe_favorites (recipe_id, user_id) VALUES ($1, $3), ($2, $3)", &[shared_recipe, own_recipe, source]).await;
+        exec(pool, "INSERT INTO recipe_favorites (recipe_id, user_id) VALUES ($1, $2)", &[shared_recipe, target]).await;
+
+        insert_post(pool, source).await;
+        let third_post = insert_post(pool, third).await;
+        exec(pool, "INSERT INTO likes (user_id, post_id) VALUES ($1, $3), ($2, $3)", &[source, target, third_post]).await;
+        exec(pool, "INSERT INTO comments (post_id, author_id, content) VALUES ($1, $2, 'вкусно')", &[third_post, source]).await;
+
+        exec(
+            pool,
+            "INSERT INTO follows (follower_id, following_id) VALUES ($1, $2), ($1, $3), ($2, $3), ($3, $1)",
+            &[source, target, third],
+        )
+        .await;
+
+        exec(
+            pool,
+            r#"INSERT INTO dietary_profiles (user_id, allergies) VALUES ($1, '[{"allergen": "Peanuts", "severity": "LifeThreatening"}]')"#,
+            &[source],
+        )
+        .await;
+        exec(
+            pool,
+            r#"INSERT INTO dietary_profiles (user_id, allergies) VALUES ($1, '[{"allergen": "Milk", "severity": "Avoid"}]')"#,
+            &[target],
+        )
+        .await;
+
+        exec(pool, "INSERT INTO notifications (user_id, kind, title, message) VALUES ($1, 'expiry', 'Молоко', 'Скоро истечет')", &[source]).await;
+        exec(pool, "INSERT INTO user_sessions (user_id, refresh_token, expires_at) VALUES ($1, $2::text, NOW() + INTERVAL '7 days')", &[source, Uuid::new_v4()]).await;
+        exec(
+            pool,
+            "INSERT INTO api_tokens (id, user_id, name, token_prefix, token_hash) VALUES ($2, $1, 'cli', 'tok', $2::text)",
+            &[source, Uuid::new_v4()],
+        )
+        .await;
+    }
+
+    fn rows(result: &AccountMergeResult, domain: MergeDomain) -> i64 {
+        result.domains.iter().find(|count| count.domain == domain).map(|count| count.rows).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn merge_moves_a_fully_populated_account_once(pool: PgPool) {
+        let admin = insert_user(&pool, "Админ").await;
+        let source = insert_user(&pool, "Вера").await;
+        let target = insert_user(&pool, "Вера").await;
+        let third = insert_user(&pool, "Олег").await;
+        populate(&pool, source, target, third).await;
+        let service = AccountMergeService::new(pool.clone());
+
+        // Пробный запуск считает строки исходного аккаунта вместе с дубликатами и ничего не меняет
+        let preview = service.preview(source, target).await.unwrap();
+        assert!(preview.dry_run && preview.job.is_none());
+        let expected_preview = [
+            (MergeDomain::Fridge, 2),
+            (MergeDomain::Diary, 2),
+            (MergeDomain::Goals, 2),
+            (MergeDomain::Weight, 2),
+            (MergeDomain::Recipes, 2),
+            (MergeDomain::Favorites, 2),
+            (MergeDomain::Posts, 2),
+            (MergeDomain::Comments, 1),
+            (MergeDomain::Follows, 3),
+            (MergeDomain::DietaryProfile, 1),
+            (MergeDomain::Notifications, 1),
+        ];
+        for (domain, expected) in expected_preview {
+            assert_eq!(rows(&preview, domain), expected, "preview of {}", domain.as_str());
+        }
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM fridge_items WHERE user_id = $1", source).await, 1);
+
+        let merged = service.merge(admin, source, target).await.unwrap();
+        let job = merged.job.clone().unwrap();
+        assert!(!merged.dry_run);
+        assert_eq!(job.status, AdminJobStatus::Completed);
+        assert_eq!(job.completed_domains.len(), MergeDomain::ALL.len());
+        // Перенесенное без дубликатов: оценка, избранное, лайк и вес за тот же день остались за целевым
+        let expected_moved = [
+            (MergeDomain::Fridge, 2),
+            (MergeDomain::Weight, 1),
+            (MergeDomain::Favorites, 1),
+            (MergeDomain::Posts, 1),
+            (MergeDomain::Follows, 1),
+            (MergeDomain::DietaryProfile, 1),
+        ];
+        for (domain, expected) in expected_moved {
+            assert_eq!(rows(&merged, domain), expected, "moved from {}", domain.as_str());
+        }
+
+        for (table, sql) in SOURCE_ROWS {
+            assert_eq!(count(&pool, sql, source).await, 0, "{} still belongs to the source account", table);
+        }
+
+        let weights: Vec<(chrono::NaiveDate, f32)> =
+            sqlx::query_as("SELECT date, weight FROM weight_entries WHERE user_id = $1 ORDER BY date")
+                .bind(target)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights[0].1, 82.0, "the target's weight wins on the same day");
+        assert_eq!(weights[1].1, 69.5);
+
+        let (rating,): (i32,) = sqlx::query_as("SELECT rating FROM recipe_ratings WHERE user_id = $1")
+            .bind(target)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rating, 3, "the target's rating wins");
+
+        let follows: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            "SELECT follower_id, following_id FROM follows WHERE follower_id = $1 OR following_id = $1 ORDER BY follower_id = $1 DESC"
+        )
+        .bind(target)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(follows, vec![(target, third), (third, target)]);
+
+        let profile = sqlx::query_as::<_, DietaryProfile>("SELECT * FROM dietary_profiles WHERE user_id = $1")
+            .bind(target)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let mut allergens: Vec<String> = profile.allergies.iter().map(|entry| format!("{:?}", entry.allergen)).collect();
+        allergens.sort();
+        assert_eq!(allergens, vec!["Milk", "Peanuts"]);
+
+        let (merged_into, reason): (Option<Uuid>, Option<String>) =
+            sqlx::query_as("SELECT merged_into, suspension_reason FROM users WHERE id = $1 AND suspended_at IS NOT NULL")
+                .bind(source)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(merged_into, Some(target));
+        assert_eq!(reason.as_deref(), Some(MERGED_ACCOUNT_REASON));
+
+        let audited: Vec<(Uuid,)> = sqlx::query_as(
+            "SELECT target_user_id FROM admin_audit_log WHERE admin_id = $1 AND action = 'merge_users' ORDER BY target_user_id = $2"
+        )
+        .bind(admin)
+        .bind(target)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(audited, vec![(source,), (target,)]);
+        let activity: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT user_id, action FROM activity_log WHERE actor_id = $1 ORDER BY action DESC"
+        )
+        .bind(admin)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(activity, vec![(source, "account_merged_into".to_string()), (target, "account_merged_from".to_string())]);
+
+        // Повтор возвращает ту же завершенную задачу и не пишет журналы заново
+        let again = service.merge(admin, source, target).await.unwrap();
+        let again_job = again.job.unwrap();
+        assert_eq!(again_job.id, job.id);
+        assert_eq!(again_job.counts, job.counts);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM activity_log WHERE actor_id = $1", admin).await, 2);
+
+        // Объединенный аккаунт нельзя влить в другой, а в него самого — ничего
+        let err = service.merge(admin, source, third).await.unwrap_err();
+        assert!(matches!(err, AppError::Conflict(_)), "{:?}", err);
+        let err = service.preview(third, source).await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)), "{:?}", err);
+    }
+
+    #[sqlx::test]
+    async fn invalid_pairs_are_rejected_before_a_job_is_created(pool: PgPool) {
+        let admin = insert_user(&pool, "Админ").await;
+        let source = insert_user(&pool, "Вера").await;
+        let service = AccountMergeService::new(pool.clone());
+
+        let cases = vec![
+            ("same account", service.merge(admin, source, source).await),
+            ("admin merges themselves away", service.merge(admin, admin, source).await),
+        ];
+        for (case, result) in cases {
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{}: {:?}", case, result.map(|_| ()));
+        }
+        let missing = service.merge(admin, source, Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM merge_jobs WHERE created_by = $1", admin).await, 0);
+    }
+
+    #[sqlx::test]
+    async fn interrupted_merge_resumes_from_the_first_unfinished_domain(pool: PgPool) {
+        let admin = insert_user(&pool, "Админ").await;
+        let source = insert_user(&pool, "Вера").await;
+        let target = insert_user(&pool, "Вера").await;
+        let item = insert_fridge_item(&pool, source, "молоко", None, Utc::now()).await;
+        insert_diary_entry(&pool, source, "lunch", 500.0, 20.0, Utc::now()).await;
+
+        // Прерванный запуск: холодильник уже перенесен, дальше произошла ошибка
+        exec(&pool, "UPDATE fridge_items SET user_id = $2 WHERE id = $1", &[item.id, target]).await;
+        exec(
+            &pool,
+            r#"INSERT INTO merge_jobs (source_user_id, target_user_id, created_by, status, completed_domains, counts, error)
+               VALUES ($1, $2, $3, 'failed', '{fridge}', '{"fridge": 1}', 'connection reset')"#,
+            &[source, target, admin],
+        )
+        .await;
+
+        let result = AccountMergeService::new(pool.clone()).merge(admin, source, target).await.unwrap();
+        let job = result.job.clone().unwrap();
+        assert_eq!(job.status, AdminJobStatus::Completed);
+        assert_eq!(job.error, None);
+        assert_eq!(job.completed_domains.first().map(String::as_str), Some("fridge"));
+        assert_eq!(job.completed_domains.iter().filter(|domain| *domain == "fridge").count(), 1);
+        assert_eq!(rows(&result, MergeDomain::Fridge), 1);
+        assert_eq!(rows(&result, MergeDomain::Diary), 1);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM diary_entries WHERE user_id = $1", target).await, 1);
+    }
+}
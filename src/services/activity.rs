@@ -0,0 +1,68 @@
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::activity::ActivityLogEntry,
+    utils::errors::AppError,
+};
+
+pub struct ActivityService {
+    pool: DbPool,
+}
+
+impl ActivityService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Записать действие в журнал активности пользователя
+    pub async fn log(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        action: &str,
+        details: serde_json::Value,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO activity_log (id, user_id, actor_id, action, details)
+            VALUES ($1, $2, $3, $4, $5)
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(actor_id)
+        .bind(action)
+        .bind(details)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Журнал пользователя, новые первыми; `action_prefix` оставляет только действия с этим префиксом
+    pub async fn get_user_activity(
+        &self,
+        user_id: Uuid,
+        action_prefix: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ActivityLogEntry>, AppError> {
+        let entries = sqlx::query_as::<_, ActivityLogEntry>(
+            r#"
+            SELECT * FROM activity_log
+            WHERE user_id = $1 AND ($2::TEXT IS NULL OR starts_with(action, $2))
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#
+        )
+        .bind(user_id)
+        .bind(action_prefix)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
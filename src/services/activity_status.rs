@@ -0,0 +1,349 @@
+use uuid::Uuid;
+use chrono::Duration;
+
+use crate::{
+    db::DbPool,
+    models::community::{ActivityStatus, FriendActivityStatus, SetActivityStatus},
+    services::clock::{self, SharedClock},
+    utils::errors::AppError,
+};
+
+pub const MIN_STATUS_MINUTES: i64 = 5;
+pub const MAX_STATUS_MINUTES: i64 = 8 * 60;
+
+/// Статус вместе с данными для отображения; истекшие и скрытые настройками приватности отфильтрованы
+const ACTIVE_STATUS_SELECT: &str = r#"
+    SELECT s.user_id, u.first_name, u.last_name, u.avatar_url, s.activity, s.recipe_id,
+           r.name AS recipe_name, s.expires_at, s.created_at
+    FROM activity_statuses s
+    JOIN users u ON u.id = s.user_id
+    LEFT JOIN recipes r ON r.id = s.recipe_id
+    LEFT JOIN community_privacy p ON p.user_id = s.user_id
+    WHERE s.expires_at > NOW() AND COALESCE(p.share_activity, TRUE)
+"#;
+
+/// Временные статусы «готовлю сейчас». Истечение ленивое: строка остается в таблице,
+/// но после `expires_at` нигде не возвращается и перезаписывается следующим статусом.
+pub struct ActivityStatusService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl ActivityStatusService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — от них отсчитывается срок статуса
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Разрешил ли пользователь показывать свой статус (по умолчанию — да)
+    pub async fn share_activity(&self, user_id: Uuid) -> Result<bool, AppError> {
+        let share: Option<(bool,)> = sqlx::query_as(
+            "SELECT share_activity FROM community_privacy WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(share.map(|(share,)| share).unwrap_or(true))
+    }
+
+    /// При отключении текущий статус сразу удаляется
+    pub async fn set_share_activity(&self, user_id: Uuid, enabled: bool) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO community_privacy (user_id, share_activity)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET share_activity = EXCLUDED.share_activity, updated_at = NOW()
+            "#
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .execute(&mut *tx)
+        .await?;
+
+        if !enabled {
+            sqlx::query("DELETE FROM activity_statuses WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(enabled)
+    }
+
+    /// Устанавливает статус, заменяя предыдущий
+    pub async fn set_status(&self, status: SetActivityStatus) -> Result<FriendActivityStatus, AppError> {
+        if !(MIN_STATUS_MINUTES..=MAX_STATUS_MINUTES).contains(&status.expires_in_minutes) {
+            return Err(AppError::BadRequest(format!(
+                "expires_in_minutes must be between {} and {}",
+                MIN_STATUS_MINUTES, MAX_STATUS_MINUTES
+            )));
+        }
+        if !self.share_activity(status.user_id).await? {
+            return Err(AppError::Forbidden("Activity sharing is disabled in privacy settings".to_string()));
+        }
+        if let Some(recipe_id) = status.recipe_id {
+            self.ensure_recipe_access(status.user_id, recipe_id).await?;
+        }
+
+        let now = self.clock.now();
+        let expires_at = now + Duration::minutes(status.expires_in_minutes);
+        sqlx::query_as::<_, ActivityStatus>(
+            r#"
+            INSERT INTO activity_statuses (user_id, activity, recipe_id, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE
+            SET activity = EXCLUDED.activity,
+                recipe_id = EXCLUDED.recipe_id,
+                expires_at = EXCLUDED.expires_at,
+                created_at = EXCLUDED.created_at
+            RETURNING *
+            "#
+        )
+        .bind(status.user_id)
+        .bind(status.activity)
+        .bind(status.recipe_id)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.get_status(status.user_id)
+            .await?
+            .ok_or_else(|| AppError::InternalServerError("Activity status was not saved".to_string()))
+    }
+
+    pub async fn clear_status(&self, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM activity_statuses WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Активный статус пользователя для профиля
+    pub async fn get_status(&self, user_id: Uuid) -> Result<Option<FriendActivityStatus>, AppError> {
+        let status = sqlx::query_as::<_, FriendActivityStatus>(
+            &format!("{} AND s.user_id = $1", ACTIVE_STATUS_SELECT)
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(status)
+    }
+
+    /// Активные статусы людей, на которых подписан пользователь; свежие первыми
+    pub async fn following_activity(&self, user_id: Uuid) -> Result<Vec<FriendActivityStatus>, AppError> {
+        let statuses = sqlx::query_as::<_, FriendActivityStatus>(
+            &format!(
+                "{} AND s.user_id IN (SELECT following_id FROM follows WHERE follower_id = $1) ORDER BY s.created_at DESC",
+                ACTIVE_STATUS_SELECT
+            )
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(statuses)
+    }
+
+    pub async fn follower_ids(&self, user_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+        let ids: Vec<(Uuid,)> = sqlx::query_as("SELECT follower_id FROM follows WHERE following_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// К статусу можно привязать только свой или публичный рецепт
+    async fn ensure_recipe_access(&self, user_id: Uuid, recipe_id: Uuid) -> Result<(), AppError> {
+        let (accessible,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM recipes WHERE id = $1 AND (is_public OR created_by = $2))"
+        )
+        .bind(recipe_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if !accessible {
+            return Err(AppError::Forbidden("Recipe is not accessible".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::community::ActivityKind;
+    use crate::test_support::{insert_recipe, insert_user};
+
+    fn cooking(user_id: Uuid, recipe_id: Option<Uuid>) -> SetActivityStatus {
+        SetActivityStatus {
+            user_id,
+            activity: ActivityKind::Cooking,
+            recipe_id,
+            expires_in_minutes: 60,
+        }
+    }
+
+    async fn follow(pool: &PgPool, follower_id: Uuid, following_id: Uuid) {
+        sqlx::query("INSERT INTO follows (follower_id, following_id) VALUES ($1, $2)")
+            .bind(follower_id)
+            .bind(following_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn expired_status_is_hidden_everywhere(pool: PgPool) {
+        let cook = insert_user(&pool, "Анна").await;
+        let follower = insert_user(&pool, "Борис").await;
+        follow(&pool, follower, cook).await;
+        let service = ActivityStatusService::new(pool.clone());
+
+        let status = service.set_status(cooking(cook, None)).await.unwrap();
+        let minutes = (status.expires_at - status.created_at).num_minutes();
+        assert!((59..=60).contains(&minutes));
+        assert_eq!(service.following_activity(follower).await.unwrap().len(), 1);
+
+        sqlx::query("UPDATE activity_statuses SET expires_at = NOW() - INTERVAL '1 minute' WHERE user_id = $1")
+            .bind(cook)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(service.get_status(cook).await.unwrap().is_none());
+        assert!(service.following_activity(follower).await.unwrap().is_empty());
+
+        // Истекшая строка перезаписывается новым статусом
+        service.set_status(cooking(cook, None)).await.unwrap();
+        assert!(service.get_status(cook).await.unwrap().is_some());
+    }
+
+    #[sqlx::test]
+    async fn new_status_replaces_the_previous_one(pool: PgPool) {
+        let cook = insert_user(&pool, "Анна").await;
+        let recipe = insert_recipe(&pool, cook, "Борщ", false).await;
+        let service = ActivityStatusService::new(pool.clone());
+
+        service.set_status(cooking(cook, None)).await.unwrap();
+        let replaced = service
+            .set_status(SetActivityStatus {
+                activity: ActivityKind::Baking,
+                expires_in_minutes: 30,
+                ..cooking(cook, Some(recipe))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(replaced.activity, ActivityKind::Baking);
+        assert_eq!(replaced.recipe_id, Some(recipe));
+        assert_eq!(replaced.recipe_name.as_deref(), Some("Борщ"));
+
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM activity_statuses WHERE user_id = $1")
+            .bind(cook)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        let current = service.get_status(cook).await.unwrap().unwrap();
+        assert_eq!(current.activity, ActivityKind::Baking);
+        assert_eq!(current.expires_at, replaced.expires_at);
+
+        service.clear_status(cook).await.unwrap();
+        assert!(service.get_status(cook).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn disabling_sharing_hides_status_and_blocks_new_ones(pool: PgPool) {
+        let cook = insert_user(&pool, "Анна").await;
+        let follower = insert_user(&pool, "Борис").await;
+        follow(&pool, follower, cook).await;
+        let service = ActivityStatusService::new(pool.clone());
+
+        assert!(service.share_activity(cook).await.unwrap());
+        service.set_status(cooking(cook, None)).await.unwrap();
+
+        assert!(!service.set_share_activity(cook, false).await.unwrap());
+        assert!(!service.share_activity(cook).await.unwrap());
+        assert!(service.get_status(cook).await.unwrap().is_none());
+        assert!(service.following_activity(follower).await.unwrap().is_empty());
+
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM activity_statuses WHERE user_id = $1")
+            .bind(cook)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 0);
+
+        let rejected = service.set_status(cooking(cook, None)).await;
+        assert!(matches!(rejected, Err(AppError::Forbidden(_))));
+
+        service.set_share_activity(cook, true).await.unwrap();
+        service.set_status(cooking(cook, None)).await.unwrap();
+        assert_eq!(service.following_activity(follower).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn invalid_duration_and_foreign_private_recipe_are_rejected(pool: PgPool) {
+        let cook = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+        let private = insert_recipe(&pool, other, "Секретный суп", false).await;
+        let public = insert_recipe(&pool, other, "Окрошка", true).await;
+        let service = ActivityStatusService::new(pool.clone());
+
+        for minutes in [MIN_STATUS_MINUTES - 1, MAX_STATUS_MINUTES + 1] {
+            let rejected = service
+                .set_status(SetActivityStatus { expires_in_minutes: minutes, ..cooking(cook, None) })
+                .await;
+            assert!(matches!(rejected, Err(AppError::BadRequest(_))), "{} minutes", minutes);
+        }
+        for minutes in [MIN_STATUS_MINUTES, MAX_STATUS_MINUTES] {
+            service
+                .set_status(SetActivityStatus { expires_in_minutes: minutes, ..cooking(cook, None) })
+                .await
+                .unwrap();
+        }
+
+        let rejected = service.set_status(cooking(cook, Some(private))).await;
+        assert!(matches!(rejected, Err(AppError::Forbidden(_))));
+
+        let status = service.set_status(cooking(cook, Some(public))).await.unwrap();
+        assert_eq!(status.recipe_name.as_deref(), Some("Окрошка"));
+    }
+
+    #[sqlx::test]
+    async fn following_activity_lists_only_followed_users(pool: PgPool) {
+        let viewer = insert_user(&pool, "Анна").await;
+        let followed = insert_user(&pool, "Борис").await;
+        let stranger = insert_user(&pool, "Вера").await;
+        follow(&pool, viewer, followed).await;
+        let service = ActivityStatusService::new(pool.clone());
+
+        service.set_status(cooking(followed, None)).await.unwrap();
+        service.set_status(cooking(stranger, None)).await.unwrap();
+
+        let statuses = service.following_activity(viewer).await.unwrap();
+        assert_eq!(statuses.iter().map(|s| s.user_id).collect::<Vec<_>>(), vec![followed]);
+        assert_eq!(service.follower_ids(followed).await.unwrap(), vec![viewer]);
+        assert!(service.follower_ids(stranger).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+use sqlx::types::Json;
+use tracing::{info, warn, Instrument};
+
+use crate::{
+    db::DbPool,
+    models::{
+        admin_job::{AdminJob, AdminJobStatus, RecalculateNutritionParams, SkippedRecipe},
+        diary::{FoodItem, NutritionTotals},
+        recipe::{IngredientQuantity, RecipeIngredient},
+    },
+    telemetry,
+    utils::{errors::AppError, units::BaseUnit},
+};
+
+pub const RECALCULATE_NUTRITION_JOB: &str = "recalculate_recipe_nutrition";
+
+/// Сколько рецептов обрабатывается за одну транзакцию
+const BATCH_SIZE: i64 = 50;
+
+pub struct AdminJobService {
+    pool: DbPool,
+}
+
+impl AdminJobService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_job(&self, id: Uuid) -> Result<AdminJob, AppError> {
+        sqlx::query_as::<_, AdminJob>("SELECT * FROM admin_jobs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Job not found".to_string()))
+    }
+}
+
+/// Пересчет `recipe_nutrition` по строкам ингредиентов и данным `food_items`.
+/// Рецепты обрабатываются пачками по возрастанию id; после каждой пачки прогресс
+/// и позиция сохраняются в той же транзакции, что и новые значения.
+#[derive(Clone)]
+pub struct RecipeNutritionRecalculator {
+    pool: DbPool,
+}
+
+#[derive(sqlx::FromRow)]
+struct RecipeRow {
+    id: Uuid,
+    name: String,
+    servings: Option<i32>,
+}
+
+impl RecipeNutritionRecalculator {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Создает задачу и запускает ее в фоне
+    pub async fn enqueue(&self, created_by: Uuid, params: RecalculateNutritionParams) -> Result<AdminJob, AppError> {
+        let (running,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM admin_jobs WHERE kind = $1 AND status IN ('pending', 'running')"
+        )
+        .bind(RECALCULATE_NUTRITION_JOB)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if running > 0 {
+            return Err(AppError::BadRequest("Nutrition recalculation is already in progress".to_string()));
+        }
+
+        let (total,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM recipes r
+            WHERE $1::text IS NULL OR EXISTS (
+                SELECT 1 FROM recipe_ingredients i
+                WHERE i.recipe_id = r.id AND LOWER(i.name) = LOWER($1)
+            )
+            "#
+        )
+        .bind(&params.ingredient_name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let job = sqlx::query_as::<_, AdminJob>(
+            r#"
+            INSERT INTO admin_jobs (id, kind, params, total, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(RECALCULATE_NUTRITION_JOB)
+        .bind(Json(&params))
+        .bind(total as i32)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.clone().spawn(job.id);
+        Ok(job)
+    }
+
+    /// Продолжает задачи, прерванные перезапуском сервера
+    pub async fn resume_unfinished(&self) -> Result<usize, AppError> {
+        let ids: Vec<(Uuid,)> = sqlx::query_as(
+            "SELECT id FROM admin_jobs WHERE kind = $1 AND status IN ('pending', 'running') ORDER BY created_at"
+        )
+        .bind(RECALCULATE_NUTRITION_JOB)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for (id,) in &ids {
+            info!("Resuming nutrition recalculation job {}", id);
+            self.clone().spawn(*id);
+        }
+
+        Ok(ids.len())
+    }
+
+    fn spawn(self, job_id: Uuid) {
+        let span = telemetry::detached_job_span("recipe_nutrition_recalculation");
+        tokio::spawn(async move {
+            if let Err(e) = self.run(job_id).await {
+                warn!("Nutrition recalculation job {} failed: {:?}", job_id, e);
+                let _ = sqlx::query(
+                    "UPDATE admin_jobs SET status = 'failed', error = $2, finished_at = NOW(), updated_at = NOW() WHERE id = $1"
+                )
+                .bind(job_id)
+                .bind(e.to_string())
+                .execute(&self.pool)
+                .await;
+            }
+        }.instrument(span));
+    }
+
+    pub async fn run(&self, job_id: Uuid) -> Result<AdminJob, AppError> {
+        let job = sqlx::query_as::<_, AdminJob>(
+            r#"
+            UPDATE admin_jobs
+            SET status = 'running', started_at = COALESCE(started_at, NOW()), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(job_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let params: RecalculateNutritionParams = serde_json::from_value(job.params.clone())
+            .map_err(|e| AppError::InternalServerError(format!("Invalid job params: {}", e)))?;
+
+        let mut cursor = job.last_processed_id;
+        while let Some(last_id) = self.process_batch(job_id, cursor, &params).await? {
+            cursor = Some(last_id);
+        }
+
+        let job = sqlx::query_as::<_, AdminJob>(
+            r#"
+            UPDATE admin_jobs
+            SET status = $2, finished_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(job_id)
+        .bind(AdminJobStatus::Completed)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(
+            "Nutrition recalculation job {} completed: {} processed, {} skipped",
+            job.id, job.processed, job.failed
+        );
+        Ok(job)
+    }
+
+    /// Обрабатывает следующую пачку после `cursor`. Возвращает id последнего рецепта
+    /// или `None`, если рецептов больше нет.
+    async fn process_batch(
+        &self,
+        job_id: Uuid,
+        cursor: Option<Uuid>,
+        params: &RecalculateNutritionParams,
+    ) -> Result<Option<Uuid>, AppError> {
+        let recipes = sqlx::query_as::<_, RecipeRow>(
+            r#"
+            SELECT r.id, r.name, r.servings FROM recipes r
+            WHERE ($1::uuid IS NULL OR r.id > $1)
+              AND ($2::text IS NULL OR EXISTS (
+                  SELECT 1 FROM recipe_ingredients i
+                  WHERE i.recipe_id = r.id AND LOWER(i.name) = LOWER($2)
+              ))
+            ORDER BY r.id
+            LIMIT $3
+            "#
+        )
+        .bind(cursor)
+        .bind(&params.ingredient_name)
+        .bind(BATCH_SIZE)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let Some(last_id) = recipes.last().map(|recipe| recipe.id) else {
+            return Ok(None);
+        };
+
+        let recipe_ids: Vec<Uuid> = recipes.iter().map(|recipe| recipe.id).collect();
+        let ingredients = sqlx::query_as::<_, RecipeIngredient>(
+            "SELECT * FROM recipe_ingredients WHERE recipe_id = ANY($1)"
+        )
+        .bind(&recipe_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let names: Vec<String> = ingredients.iter().map(|ingredient| ingredient.name.trim().to_lowercase()).collect();
+        let foods = foods_by_name(&self.pool, &names).await?;
+
+        let mut by_recipe: HashMap<Uuid, Vec<RecipeIngredient>> = HashMap::new();
+        for ingredient in ingredients {
+            by_recipe.entry(ingredient.recipe_id).or_default().push(ingredient);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut skipped = Vec::new();
+
+        for recipe in &recipes {
+            let lines = by_recipe.remove(&recipe.id).unwrap_or_default();
+            let nutrition = match compute_recipe_nutrition(&lines, &foods, recipe.servings) {
+                Ok(nutrition) => nutrition,
+                Err(unresolved_ingredients) => {
+                    skipped.push(SkippedRecipe {
+                        recipe_id: recipe.id,
+                        recipe_name: recipe.name.clone(),
+                        reason: if lines.is_empty() {
+                            "Recipe has no ingredients".to_string()
+                        } else {
+                            "Some ingredients could not be resolved".to_string()
+                        },
+                        unresolved_ingredients,
+                    });
+                    continue;
+                }
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO recipe_nutrition (recipe_id, calories, protein, fat, carbs, fiber, sugar, sodium)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (recipe_id) DO UPDATE
+                SET calories = EXCLUDED.calories,
+                    protein = EXCLUDED.protein,
+                    fat = EXCLUDED.fat,
+                    carbs = EXCLUDED.carbs,
+                    fiber = EXCLUDED.fiber,
+                    sugar = EXCLUDED.sugar,
+                    sodium = EXCLUDED.sodium
+                "#
+            )
+            .bind(recipe.id)
+            .bind(nutrition.calories)
+            .bind(nutrition.protein)
+            .bind(nutrition.fat)
+            .bind(nutrition.carbs)
+            .bind(nutrition.fiber)
+            .bind(nutrition.sugar)
+            .bind(nutrition.sodium)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE admin_jobs
+            SET processed = processed + $2,
+                failed = failed + $3,
+                last_processed_id = $4,
+                report = report || $5,
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(job_id)
+        .bind(recipes.len() as i32)
+        .bind(skipped.len() as i32)
+        .bind(last_id)
+        .bind(Json(&skipped))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(last_id))
+    }
+}
+
+/// Продукты по названию (`names` — в нижнем регистре); при совпадении названий предпочитаются
+/// проверенные и без бренда
+pub async fn foods_by_name(pool: &DbPool, names: &[String]) -> Result<HashMap<String, FoodItem>, AppError> {
+    let foods = sqlx::query_as::<_, FoodItem>(
+        r#"
+        SELECT DISTINCT ON (LOWER(name)) * FROM food_items
+        WHERE LOWER(name) = ANY($1)
+        ORDER BY LOWER(name), verified DESC, (brand IS NULL) DESC, updated_at DESC
+        "#
+    )
+    .bind(names)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(foods.into_iter().map(|food| (food.name.trim().to_lowercase(), food)).collect())
+}
+
+/// Питательность одной порции рецепта. Если хотя бы один ингредиент не найден в базе
+/// продуктов или его количество нельзя перевести в граммы, возвращает список таких ингредиентов.
+pub fn compute_recipe_nutrition(
+    ingredients: &[RecipeIngredient],
+    foods: &HashMap<String, FoodItem>,
+    servings: Option<i32>,
+) -> Result<NutritionTotals, Vec<String>> {
+    if ingredients.is_empty() {
+        return Err(Vec::new());
+    }
+
+    let mut total = NutritionTotals::default();
+    let mut unresolved = Vec::new();
+
+    for ingredient in ingredients {
+        let amount = ingredient.amount();
+        // "По вкусу" (соль, специи) на питательность не влияет
+        if matches!(amount, Some(IngredientQuantity::ToTaste)) {
+            continue;
+        }
+
+        let food = foods.get(&ingredient.name.trim().to_lowercase());
+        let grams = match amount.and_then(|amount| amount.to_base()) {
+            // Для жидкостей считаем плотность равной 1 г/мл
+            Some((quantity, BaseUnit::Kg | BaseUnit::L)) => Some(quantity * 1000.0),
+            _ => None,
+        };
+
+        match (food, grams) {
+            (Some(food), Some(grams)) => total = total.plus(&food.nutrition_for(grams as f32)),
+            _ => unresolved.push(format!("{} ({} {})", ingredient.name, ingredient.quantity, ingredient.unit)),
+        }
+    }
+
+    if !unresolved.is_empty() {
+        return Err(unresolved);
+    }
+
+    let servings = servings.unwrap_or(1).max(1) as f32;
+    Ok(NutritionTotals {
+        calories: total.calories / servings,
+        protein: total.protein / servings,
+        fat: total.fat / servings,
+        carbs: total.carbs / servings,
+        fiber: total.fiber / servings,
+        sugar: total.sugar / servings,
+        sodium: total.sodium / servings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{insert_recipe, insert_user};
+
+    const STALE_CALORIES: f32 = 999.0;
+
+    async fn insert_food(pool: &PgPool, name: &str, calories: f32, protein: f32, fat: f32, carbs: f32) {
+        sqlx::query(
+            r#"
+            INSERT INTO food_items (name, calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g, verified)
+            VALUES ($1, $2, $3, $4, $5, TRUE)
+            "#
+        )
+        .bind(name)
+        .bind(calories)
+        .bind(protein)
+        .bind(fat)
+        .bind(carbs)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// Рецепт с устаревшей питательностью `STALE_CALORIES`
+    async fn insert_stale_recipe(
+        pool: &PgPool,
+        user_id: Uuid,
+        name: &str,
+        servings: Option<i32>,
+        ingredients: &[(&str, f32, &str)],
+    ) -> Uuid {
+        let id = insert_recipe(pool, user_id, name, true).await;
+        sqlx::query("UPDATE recipes SET servings = $2 WHERE id = $1")
+            .bind(id)
+            .bind(servings)
+            .execute(pool)
+            .await
+            .unwrap();
+        for (ingredient, quantity, unit) in ingredients {
+            sqlx::query("INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit) VALUES ($1, $2, $3, $4)")
+                .bind(id)
+                .bind(ingredient)
+                .bind(quantity)
+                .bind(unit)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO recipe_nutrition (recipe_id, calories, protein) VALUES ($1, $2, $2)")
+            .bind(id)
+            .bind(STALE_CALORIES)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    async fn calories(pool: &PgPool, recipe_id: Uuid) -> f32 {
+        let (calories,): (f32,) = sqlx::query_as("SELECT calories FROM recipe_nutrition WHERE recipe_id = $1")
+            .bind(recipe_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (calories * 100.0).round() / 100.0
+    }
+
+    async fn wait_for_completion(pool: &PgPool, job_id: Uuid) -> AdminJob {
+        let service = AdminJobService::new(pool.clone());
+        for _ in 0..200 {
+            let job = service.get_job(job_id).await.unwrap();
+            if job.status == AdminJobStatus::Completed {
+                return job;
+            }
+            assert_ne!(job.status, AdminJobStatus::Failed, "job failed: {:?}", job.error);
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        panic!("job {} did not complete", job_id);
+    }
+
+    struct Fixture {
+        chicken_rice: Uuid,
+        porridge: Uuid,
+        dumplings: Uuid,
+        empty: Uuid,
+    }
+
+    /// Названия в нижнем регистре: тестовая база может быть в локали C, где LOWER() не трогает кириллицу
+    async fn fixture(pool: &PgPool, user_id: Uuid) -> Fixture {
+        insert_food(pool, "курица", 165.0, 31.0, 3.6, 0.0).await;
+        insert_food(pool, "рис", 130.0, 2.7, 0.3, 28.0).await;
+        insert_food(pool, "молоко", 60.0, 3.2, 3.6, 4.8).await;
+
+        Fixture {
+            // (330 + 390) / 2 порции; соль "по вкусу" не учитывается
+            chicken_rice: insert_stale_recipe(pool, user_id, "Курица с рисом", Some(2), &[
+                ("курица", 200.0, "г"),
+                ("рис", 0.3, "кг"),
+                ("соль", 0.0, "по вкусу"),
+            ]).await,
+            // 130 + 300, порций не указано — считается одна
+            porridge: insert_stale_recipe(pool, user_id, "Рисовая каша", None, &[
+                ("рис", 100.0, "г"),
+                ("молоко", 0.5, "л"),
+            ]).await,
+            dumplings: insert_stale_recipe(pool, user_id, "Пельмени", Some(4), &[
+                ("курица", 200.0, "г"),
+                ("тесто", 300.0, "г"),
+            ]).await,
+            empty: insert_stale_recipe(pool, user_id, "Пустой рецепт", Some(1), &[]).await,
+        }
+    }
+
+    #[sqlx::test]
+    async fn job_recomputes_resolvable_recipes_and_reports_skipped(pool: PgPool) {
+        let admin = insert_user(&pool, "Анна").await;
+        let recipes = fixture(&pool, admin).await;
+
+        let job = RecipeNutritionRecalculator::new(pool.clone())
+            .enqueue(admin, RecalculateNutritionParams::default())
+            .await
+            .unwrap();
+        assert_eq!(job.total, 4);
+
+        let job = wait_for_completion(&pool, job.id).await;
+        assert_eq!((job.processed, job.failed), (4, 2));
+        assert!(job.finished_at.is_some());
+
+        assert_eq!(calories(&pool, recipes.chicken_rice).await, 360.0);
+        assert_eq!(calories(&pool, recipes.porridge).await, 430.0);
+        assert_eq!(calories(&pool, recipes.dumplings).await, STALE_CALORIES);
+        assert_eq!(calories(&pool, recipes.empty).await, STALE_CALORIES);
+
+        let mut skipped: Vec<SkippedRecipe> = serde_json::from_value(job.report).unwrap();
+        skipped.sort_by(|a, b| a.recipe_name.cmp(&b.recipe_name));
+        assert_eq!(skipped[0].recipe_id, recipes.dumplings);
+        assert_eq!(skipped[0].reason, "Some ingredients could not be resolved");
+        assert_eq!(skipped[0].unresolved_ingredients, vec!["тесто (300 г)".to_string()]);
+        assert_eq!(skipped[1].recipe_id, recipes.empty);
+        assert_eq!(skipped[1].reason, "Recipe has no ingredients");
+    }
+
+    #[sqlx::test]
+    async fn ingredient_filter_limits_the_job(pool: PgPool) {
+        let admin = insert_user(&pool, "Анна").await;
+        let recipes = fixture(&pool, admin).await;
+
+        let job = RecipeNutritionRecalculator::new(pool.clone())
+            .enqueue(admin, RecalculateNutritionParams { ingredient_name: Some("молоко".to_string()) })
+            .await
+            .unwrap();
+        assert_eq!(job.total, 1);
+
+        let job = wait_for_completion(&pool, job.id).await;
+        assert_eq!((job.processed, job.failed), (1, 0));
+        assert_eq!(calories(&pool, recipes.porridge).await, 430.0);
+        assert_eq!(calories(&pool, recipes.chicken_rice).await, STALE_CALORIES);
+    }
+
+    #[sqlx::test]
+    async fn second_job_is_rejected_while_one_is_unfinished(pool: PgPool) {
+        let admin = insert_user(&pool, "Анна").await;
+        sqlx::query("INSERT INTO admin_jobs (kind, status) VALUES ($1, 'pending')")
+            .bind(RECALCULATE_NUTRITION_JOB)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let rejected = RecipeNutritionRecalculator::new(pool.clone())
+            .enqueue(admin, RecalculateNutritionParams::default())
+            .await;
+        assert!(matches!(rejected, Err(AppError::BadRequest(_))));
+    }
+
+    #[sqlx::test]
+    async fn interrupted_job_resumes_after_the_last_processed_id(pool: PgPool) {
+        let admin = insert_user(&pool, "Анна").await;
+        fixture(&pool, admin).await;
+
+        let mut ids: Vec<Uuid> = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM recipes")
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id,)| id)
+            .collect();
+        ids.sort();
+
+        // Задача "упала" после первых двух рецептов: их значения не трогаем
+        let (job_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO admin_jobs (kind, status, total, processed, last_processed_id, started_at)
+            VALUES ($1, 'running', 4, 2, $2, NOW())
+            RETURNING id
+            "#
+        )
+        .bind(RECALCULATE_NUTRITION_JOB)
+        .bind(ids[1])
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let resumed = RecipeNutritionRecalculator::new(pool.clone()).resume_unfinished().await.unwrap();
+        assert_eq!(resumed, 1);
+
+        let job = wait_for_completion(&pool, job_id).await;
+        assert_eq!(job.processed, 4);
+        assert_eq!(job.last_processed_id, Some(ids[3]));
+        for id in &ids[..2] {
+            assert_eq!(calories(&pool, *id).await, STALE_CALORIES);
+        }
+
+        let skipped: Vec<SkippedRecipe> = serde_json::from_value(job.report).unwrap();
+        assert_eq!(job.failed as usize, skipped.len());
+        assert!(skipped.iter().all(|recipe| ids[2..].contains(&recipe.recipe_id)));
+        let (recomputed,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM recipe_nutrition WHERE recipe_id = ANY($1) AND calories <> $2"
+        )
+        .bind(&ids[2..])
+        .bind(STALE_CALORIES)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(recomputed as usize + skipped.len(), 2);
+    }
+}
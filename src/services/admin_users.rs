@@ -0,0 +1,530 @@
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sqlx::{Postgres, Transaction};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        admin_user::{AdminAction, AdminAuditEntry, AdminGrant, AdminUserCounts, AdminUserDetail, AdminUserSummary},
+        user::{CreateUser, UserRole},
+    },
+    services::{
+        api_tokens::hash_token,
+        auth::AuthService,
+        mail::{MailMessage, MailService},
+    },
+    utils::errors::AppError,
+};
+
+/// Сколько действует ссылка для сброса пароля
+const PASSWORD_RESET_TTL_HOURS: i64 = 24;
+
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 48;
+
+/// Временный пароль администратора, созданного из командной строки
+const TEMPORARY_PASSWORD_LENGTH: usize = 24;
+
+const DEFAULT_PASSWORD_RESET_URL: &str = "https://ai-cook-frontend.vercel.app/reset-password";
+
+/// Сколько последних действий администраторов показывается в карточке пользователя
+const AUDIT_ENTRIES_IN_DETAIL: i64 = 20;
+
+#[derive(sqlx::FromRow)]
+struct AccountRow {
+    email_verified_at: Option<chrono::DateTime<Utc>>,
+    suspension_reason: Option<String>,
+    sessions_invalidated_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// Инструменты поддержки: поиск пользователей, состояние аккаунта, блокировка
+/// и принудительный сброс пароля. Каждое действие пишется в `admin_audit_log`
+/// в той же транзакции, что и само изменение.
+pub struct AdminUserService {
+    pool: DbPool,
+}
+
+impl AdminUserService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Поиск по подстроке email, имени или фамилии; без запроса — последние зарегистрированные
+    pub async fn search_users(&self, query: Option<&str>, limit: i64, offset: i64) -> Result<Vec<AdminUserSummary>, AppError> {
+        let pattern = query
+            .map(str::trim)
+            .filter(|query| !query.is_empty())
+            .map(|query| format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+
+        let users = sqlx::query_as::<_, AdminUserSummary>(
+            r#"
+            SELECT id, email, first_name, last_name, role, COALESCE(is_verified, FALSE) AS is_verified,
+                   suspended_at, last_login_at, created_at
+            FROM users
+            WHERE $1::text IS NULL
+               OR email ILIKE $1
+               OR first_name ILIKE $1
+               OR last_name ILIKE $1
+               OR (first_name || ' ' || last_name) ILIKE $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#
+        )
+        .bind(pattern)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    pub async fn user_detail(&self, user_id: Uuid) -> Result<AdminUserDetail, AppError> {
+        let summary = self.summary(user_id).await?;
+
+        let account = sqlx::query_as::<_, AccountRow>(
+            "SELECT email_verified_at, suspension_reason, sessions_invalidated_at FROM users WHERE id = $1"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let (diary_entries, recipes, posts, goals, fridge_items): (i64, i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM diary_entries WHERE user_id = $1),
+                (SELECT COUNT(*) FROM recipes WHERE created_by = $1),
+                (SELECT COUNT(*) FROM posts WHERE author_id = $1),
+                (SELECT COUNT(*) FROM goals WHERE user_id = $1),
+                (SELECT COUNT(*) FROM fridge_items WHERE user_id = $1 AND status = 'active')
+            "#
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let (active_sessions, last_session_at): (i64, Option<chrono::DateTime<Utc>>) = sqlx::query_as(
+            "SELECT COUNT(*), MAX(created_at) FROM user_sessions WHERE user_id = $1 AND expires_at > NOW()"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let (active_api_tokens, last_token_use_at): (i64, Option<chrono::DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())),
+                   MAX(last_used_at)
+            FROM api_tokens WHERE user_id = $1
+            "#
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let admin_actions = sqlx::query_as::<_, AdminAuditEntry>(
+            "SELECT * FROM admin_audit_log WHERE target_user_id = $1 ORDER BY created_at DESC LIMIT $2"
+        )
+        .bind(user_id)
+        .bind(AUDIT_ENTRIES_IN_DETAIL)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let last_activity_at = [summary.last_login_at, last_session_at, last_token_use_at]
+            .into_iter()
+            .flatten()
+            .max();
+
+        Ok(AdminUserDetail {
+            summary,
+            email_verified_at: account.email_verified_at,
+            suspension_reason: account.suspension_reason,
+            sessions_invalidated_at: account.sessions_invalidated_at,
+            counts: AdminUserCounts { fridge_items, diary_entries, recipes, posts, goals },
+            last_activity_at,
+            active_sessions,
+            active_api_tokens,
+            admin_actions,
+        })
+    }
+
+    /// Блокирует аккаунт: действующие JWT и токены API сразу перестают приниматься
+    pub async fn suspend(&self, admin_id: Uuid, user_id: Uuid, reason: &str) -> Result<AdminUserSummary, AppError> {
+        if admin_id == user_id {
+            return Err(AppError::BadRequest("Admins cannot suspend their own account".to_string()));
+        }
+        self.summary(user_id).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE users SET suspended_at = NOW(), suspension_reason = $2, updated_at = NOW() WHERE id = $1"
+        )
+        .bind(user_id)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+
+        // Обновить сессию по refresh-токену после разблокировки уже не получится
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        audit(&mut tx, Some(admin_id), AdminAction::SuspendUser, user_id, json!({ "reason": reason })).await?;
+        tx.commit().await?;
+
+        self.summary(user_id).await
+    }
+
+    pub async fn unsuspend(&self, admin_id: Uuid, user_id: Uuid) -> Result<AdminUserSummary, AppError> {
+        self.summary(user_id).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE users SET suspended_at = NULL, suspension_reason = NULL, updated_at = NOW() WHERE id = $1"
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        audit(&mut tx, Some(admin_id), AdminAction::UnsuspendUser, user_id, json!({})).await?;
+        tx.commit().await?;
+
+        self.summary(user_id).await
+    }
+
+    /// Права администратора по email: существующему пользователю они выдаются, иначе создается
+    /// новый аккаунт с временным паролем. Повторный вызов для администратора ничего не меняет.
+    pub async fn create_admin(&self, email: &str) -> Result<AdminGrant, AppError> {
+        let email = email.trim().to_lowercase();
+        if !email.contains('@') {
+            return Err(AppError::BadRequest("Invalid email".to_string()));
+        }
+
+        let existing: Option<(Uuid, UserRole)> = sqlx::query_as("SELECT id, role FROM users WHERE LOWER(email) = $1")
+            .bind(&email)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let (user_id, created, temporary_password) = match existing {
+            Some((user_id, UserRole::Admin)) => {
+                return Ok(AdminGrant {
+                    user: self.summary(user_id).await?,
+                    created: false,
+                    promoted: false,
+                    temporary_password: None,
+                });
+            }
+            Some((user_id, _)) => (user_id, false, None),
+            None => {
+                let password: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(TEMPORARY_PASSWORD_LENGTH)
+                    .map(char::from)
+                    .collect();
+                let user = AuthService::new(self.pool.clone())
+                    .create_user(CreateUser {
+                        email: email.clone(),
+                        password: password.clone(),
+                        first_name: "Admin".to_string(),
+                        last_name: String::new(),
+                        date_of_birth: None,
+                        gender: None,
+                        height: None,
+                        weight: None,
+                        activity_level: None,
+                        role: UserRole::Admin,
+                    })
+                    .await?;
+                (user.id, true, Some(password))
+            }
+        };
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE users SET role = 'admin', updated_at = NOW() WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        audit(&mut tx, None, AdminAction::GrantAdmin, user_id, json!({ "source": "cli", "created": created })).await?;
+        tx.commit().await?;
+
+        Ok(AdminGrant {
+            user: self.summary(user_id).await?,
+            created,
+            promoted: !created,
+            temporary_password,
+        })
+    }
+
+    /// Завершает все сессии пользователя, отзывает токены API и отправляет ссылку
+    /// для сброса пароля. Сессии завершаются, даже если письмо отправить не удалось.
+    pub async fn force_password_reset(&self, admin_id: Uuid, user_id: Uuid, mail: &MailService) -> Result<(), AppError> {
+        let user = self.summary(user_id).await?;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(PASSWORD_RESET_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE users SET sessions_invalidated_at = NOW(), updated_at = NOW() WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let revoked_tokens = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL"
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Прежние ссылки на сброс больше не действуют
+        sqlx::query("UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)"
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(Utc::now() + Duration::hours(PASSWORD_RESET_TTL_HOURS))
+        .execute(&mut *tx)
+        .await?;
+
+        audit(
+            &mut tx,
+            Some(admin_id),
+            AdminAction::ForcePasswordReset,
+            user_id,
+            json!({ "revoked_api_tokens": revoked_tokens }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let message = MailMessage {
+            to: user.email,
+            subject: "Сброс пароля IT Cook".to_string(),
+            html: password_reset_html(&password_reset_link(&token)),
+        };
+        if let Err(e) = mail.send(&message).await {
+            warn!("Failed to send password reset email to user {}: {:?}", user_id, e);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn summary(&self, user_id: Uuid) -> Result<AdminUserSummary, AppError> {
+        sqlx::query_as::<_, AdminUserSummary>(
+            r#"
+            SELECT id, email, first_name, last_name, role, COALESCE(is_verified, FALSE) AS is_verified,
+                   suspended_at, last_login_at, created_at
+            FROM users WHERE id = $1
+            "#
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+    }
+}
+
+/// Запись в `admin_audit_log` в транзакции самого действия
+/// `admin_id` — `None` для действий из командной строки (`it-cook create-admin`)
+pub async fn audit(
+    tx: &mut Transaction<'_, Postgres>,
+    admin_id: Option<Uuid>,
+    action: AdminAction,
+    target_user_id: Uuid,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO admin_audit_log (admin_id, action, target_user_id, details) VALUES ($1, $2, $3, $4)"
+    )
+    .bind(admin_id)
+    .bind(action.as_str())
+    .bind(target_user_id)
+    .bind(details)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+fn password_reset_link(token: &str) -> String {
+    let base = std::env::var("PASSWORD_RESET_URL").unwrap_or_else(|_| DEFAULT_PASSWORD_RESET_URL.to_string());
+    format!("{}?token={}", base, token)
+}
+
+fn password_reset_html(link: &str) -> String {
+    format!(
+        "<p>Здравствуйте!</p>\
+         <p>Служба поддержки IT Cook завершила все сессии вашего аккаунта. \
+         Чтобы снова войти, задайте новый пароль по ссылке (действует {} часа):</p>\
+         <p><a href=\"{}\">Сбросить пароль</a></p>",
+        PASSWORD_RESET_TTL_HOURS,
+        link,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_diary_entry, insert_fridge_item, insert_user, mail_server, request, send, test_router};
+
+    async fn insert_admin(pool: &PgPool) -> Uuid {
+        let admin = insert_user(pool, "Админ").await;
+        sqlx::query("UPDATE users SET role = 'admin' WHERE id = $1").bind(admin).execute(pool).await.unwrap();
+        admin
+    }
+
+    async fn audit_actions(pool: &PgPool, user_id: Uuid) -> Vec<(Option<Uuid>, String)> {
+        sqlx::query_as("SELECT admin_id, action FROM admin_audit_log WHERE target_user_id = $1 ORDER BY created_at, action")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn only_admins_reach_user_management(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let admin = insert_admin(&pool).await;
+        let user_token = access_token(&pool, user).await;
+        let admin_token = access_token(&pool, admin).await;
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/admin/users?q=test", Some(&user_token), None)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["code"], "ERR_FORBIDDEN");
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/admin/users", None, None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/admin/users?q=%D0%90%D0%BD%D0%BD%D0%B0", Some(&admin_token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let found: Vec<&str> = body.as_array().unwrap().iter().map(|user| user["id"].as_str().unwrap()).collect();
+        assert_eq!(found, vec![user.to_string()]);
+
+        // Поиск по части почты и постраничная выдача
+        let (_, body) = send(&router, request(Method::GET, &format!("/api/v1/admin/users?q={}", &user.to_string()[..8]), Some(&admin_token), None)).await;
+        assert_eq!(body[0]["id"], user.to_string());
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/admin/users?limit=1&offset=1", Some(&admin_token), None)).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        // Подстановочные символы LIKE ищутся буквально
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/admin/users?q=%25", Some(&admin_token), None)).await;
+        assert_eq!(body, serde_json::json!([]));
+    }
+
+    #[sqlx::test]
+    async fn detail_shows_counts_but_no_content(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        insert_fridge_item(&pool, user, "Секретный сыр", Some("Личная заметка"), Utc::now()).await;
+        insert_diary_entry(&pool, user, "lunch", 300.0, 20.0, Utc::now()).await;
+        sqlx::query("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'Мой тайный борщ', 'text')")
+            .bind(user)
+            .execute(&pool)
+            .await
+            .unwrap();
+        access_token(&pool, user).await;
+
+        let detail = AdminUserService::new(pool.clone()).user_detail(user).await.unwrap();
+        assert_eq!((detail.counts.fridge_items, detail.counts.diary_entries, detail.counts.posts), (1, 1, 1));
+        assert_eq!((detail.counts.recipes, detail.counts.goals), (0, 0));
+        assert_eq!(detail.active_sessions, 1);
+        assert!(detail.last_activity_at.is_some());
+
+        let json = serde_json::to_string(&detail).unwrap();
+        for content in ["Секретный сыр", "Личная заметка", "Мой тайный борщ", "password_hash", "\"hash\""] {
+            assert!(!json.contains(content), "{}", content);
+        }
+
+        let missing = AdminUserService::new(pool).user_detail(Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test]
+    async fn suspension_rejects_an_existing_jwt_immediately(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let admin = insert_admin(&pool).await;
+        let auth = AuthService::new(pool.clone());
+        let tokens = auth.generate_tokens(&auth.get_user_by_id(user).await.unwrap(), Default::default()).await.unwrap();
+        let token = tokens.access_token.clone();
+        let service = AdminUserService::new(pool.clone());
+
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let suspended = service.suspend(admin, user, "Спам в комментариях").await.unwrap();
+        assert!(suspended.suspended_at.is_some());
+
+        // Тот же токен, без перевыпуска: проверка аккаунта идет на каждом запросе
+        for uri in ["/api/v1/fridge", "/api/v1/goals", "/api/v1/diary"] {
+            let (status, body) = send(&router, request(Method::GET, uri, Some(&token), None)).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+            assert_eq!(body["error"]["code"], "ERR_ACCOUNT_SUSPENDED", "{}", uri);
+            assert!(body["error"]["details"].as_str().unwrap().contains("Спам в комментариях"), "{}", uri);
+        }
+        // Обновить сессию тоже нельзя: сессии удалены вместе с блокировкой
+        assert!(AuthService::new(pool.clone()).refresh_token(&tokens.refresh_token).await.is_err());
+
+        assert!(matches!(service.suspend(admin, admin, "Сам себя").await, Err(AppError::BadRequest(_))));
+
+        service.unsuspend(admin, user).await.unwrap();
+        let fresh = access_token(&pool, user).await;
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&fresh), None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        assert_eq!(
+            audit_actions(&pool, user).await,
+            vec![(Some(admin), "suspend_user".to_string()), (Some(admin), "unsuspend_user".to_string())]
+        );
+    }
+
+    #[sqlx::test]
+    async fn forced_reset_revokes_sessions_and_mails_a_working_link(pool: PgPool) {
+        let router = test_router(&pool);
+        let (mail, received, _) = mail_server().await;
+        let user = insert_user(&pool, "Анна").await;
+        let admin = insert_admin(&pool).await;
+        let token = access_token(&pool, user).await;
+
+        // JWT выпущен в ту же секунду, что и принудительный выход, — тоже отклоняется
+        AdminUserService::new(pool.clone()).force_password_reset(admin, user, &mail).await.unwrap();
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "ERR_SESSION_REVOKED");
+
+        let emails = received.lock().unwrap().clone();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0]["to"], serde_json::json!([format!("{}@example.com", user)]));
+        let html = emails[0]["html"].as_str().unwrap();
+        let reset_token = html.split("?token=").nth(1).unwrap().split('"').next().unwrap();
+        assert_eq!(reset_token.len(), PASSWORD_RESET_TOKEN_LENGTH);
+
+        let auth = AuthService::new(pool.clone());
+        auth.reset_password(reset_token, "new-password-123").await.unwrap();
+        // Ссылка одноразовая
+        assert!(matches!(auth.reset_password(reset_token, "another-password").await, Err(AppError::BadRequest(_))));
+
+        assert_eq!(audit_actions(&pool, user).await, vec![(Some(admin), "force_password_reset".to_string())]);
+    }
+}
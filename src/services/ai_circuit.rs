@@ -0,0 +1,258 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{config::AiResilienceConfig, utils::errors::AppError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    Closed,
+    /// Запросы к провайдеру не отправляются до конца паузы
+    Open,
+    /// Пауза закончилась, пропущен один пробный запрос
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    first_failure_at: Option<Instant>,
+    opened_at: Option<Instant>,
+}
+
+/// Размыкатель для одного провайдера ИИ. После `failure_threshold` сбоев подряд в пределах
+/// `window` вызовы отклоняются сразу, без обращения к провайдеру, — обработчики уходят
+/// в свой деградированный путь. Через `cooldown` пропускается один пробный запрос:
+/// успех замыкает цепь, сбой размыкает ее снова.
+/// Состояние под `std::sync::Mutex`: блокировка не удерживается через `.await`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    provider: &'static str,
+    failure_threshold: u32,
+    window: Duration,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(provider: &'static str, config: &AiResilienceConfig) -> Self {
+        Self {
+            provider,
+            failure_threshold: config.circuit_failure_threshold.max(1),
+            window: config.circuit_window,
+            cooldown: config.circuit_cooldown,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                first_failure_at: None,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Замкнутый размыкатель с теми же порогами для другого провайдера
+    #[cfg(test)]
+    pub(crate) fn for_provider(&self, provider: &'static str) -> Self {
+        Self {
+            provider,
+            failure_threshold: self.failure_threshold,
+            window: self.window,
+            cooldown: self.cooldown,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                first_failure_at: None,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Можно ли сейчас обращаться к провайдеру. Разомкнутая цепь отвечает `ExternalService`,
+    /// чтобы вызывающий код отдал fallback так же, как при сбое провайдера.
+    pub fn before_call(&self, now: Instant) -> Result<(), AppError> {
+        let mut breaker = self.state.lock().unwrap();
+        if breaker.state == CircuitState::Closed {
+            return Ok(());
+        }
+
+        // В полуоткрытом состоянии `opened_at` — начало пробного запроса; пока он в пути,
+        // остальные ждут. Если проба так и не отчиталась (запрос отменен), через паузу пускаем новую.
+        let cooled_down = breaker
+            .opened_at
+            .is_some_and(|opened_at| now.duration_since(opened_at) >= self.cooldown);
+        if !cooled_down {
+            return Err(self.open_error());
+        }
+
+        breaker.state = CircuitState::HalfOpen;
+        breaker.opened_at = Some(now);
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        let mut breaker = self.state.lock().unwrap();
+        if breaker.state != CircuitState::Closed {
+            tracing::info!(
+                metric = "ai_circuit_closed",
+                provider = self.provider,
+                "AI circuit for {} closed: provider responded again",
+                self.provider
+            );
+        }
+
+        breaker.state = CircuitState::Closed;
+        breaker.consecutive_failures = 0;
+        breaker.first_failure_at = None;
+        breaker.opened_at = None;
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut breaker = self.state.lock().unwrap();
+
+        if breaker.state == CircuitState::HalfOpen {
+            self.open(&mut breaker, now);
+            return;
+        }
+
+        let window_expired = breaker
+            .first_failure_at
+            .is_some_and(|first| now.duration_since(first) > self.window);
+        if breaker.first_failure_at.is_none() || window_expired {
+            breaker.first_failure_at = Some(now);
+            breaker.consecutive_failures = 0;
+        }
+        breaker.consecutive_failures += 1;
+
+        if breaker.state == CircuitState::Closed && breaker.consecutive_failures >= self.failure_threshold {
+            self.open(&mut breaker, now);
+        }
+    }
+
+    fn open(&self, breaker: &mut BreakerState, now: Instant) {
+        breaker.state = CircuitState::Open;
+        breaker.opened_at = Some(now);
+        tracing::warn!(
+            metric = "ai_circuit_opened",
+            provider = self.provider,
+            failures = breaker.consecutive_failures,
+            cooldown_secs = self.cooldown.as_secs(),
+            "AI circuit for {} opened after {} consecutive failures",
+            self.provider,
+            breaker.consecutive_failures
+        );
+    }
+
+    fn open_error(&self) -> AppError {
+        AppError::ExternalService(format!(
+            "AI provider {} is temporarily unavailable (circuit open)",
+            self.provider
+        ))
+    }
+}
+
+/// Ошибка транспорта: таймаут — отдельная ошибка (504), остальное — сбой внешнего сервиса
+pub fn transport_error(provider: &str, action: &str, error: reqwest::Error) -> AppError {
+    if error.is_timeout() {
+        AppError::ExternalTimeout(format!("{} API {} timed out: {}", provider, action, error))
+    } else {
+        AppError::ExternalService(format!("{} API {} failed: {}", provider, action, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(
+            "gemini",
+            &AiResilienceConfig {
+                connect_timeout: Duration::from_secs(5),
+                request_timeout: Duration::from_secs(30),
+                circuit_failure_threshold: 3,
+                circuit_window: Duration::from_secs(60),
+                circuit_cooldown: Duration::from_secs(30),
+            },
+        )
+    }
+
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn consecutive_failures_open_the_circuit() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        breaker.record_failure(start);
+        breaker.record_failure(secs(start, 1));
+        assert!(breaker.before_call(secs(start, 2)).is_ok());
+
+        breaker.record_failure(secs(start, 2));
+        let rejected = breaker.before_call(secs(start, 3)).unwrap_err();
+        assert!(matches!(rejected, AppError::ExternalService(ref message) if message.contains("circuit open")));
+        assert!(breaker.before_call(secs(start, 31)).is_err());
+    }
+
+    #[test]
+    fn success_or_an_expired_window_resets_the_count() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        breaker.record_failure(start);
+        breaker.record_failure(secs(start, 1));
+        breaker.record_success();
+        breaker.record_failure(secs(start, 2));
+        breaker.record_failure(secs(start, 3));
+        assert!(breaker.before_call(secs(start, 4)).is_ok());
+
+        // Третий сбой — уже за пределами окна от первого: счет начинается заново
+        breaker.record_failure(secs(start, 70));
+        breaker.record_failure(secs(start, 71));
+        assert!(breaker.before_call(secs(start, 72)).is_ok());
+        breaker.record_failure(secs(start, 72));
+        assert!(breaker.before_call(secs(start, 73)).is_err());
+    }
+
+    #[test]
+    fn probe_after_cooldown_closes_or_reopens_the_circuit() {
+        let breaker = breaker();
+        let start = Instant::now();
+        for second in 0..3 {
+            breaker.record_failure(secs(start, second));
+        }
+
+        // После паузы пропускается один пробный запрос; пока он в пути, остальные отклоняются
+        assert!(breaker.before_call(secs(start, 32)).is_ok());
+        assert!(breaker.before_call(secs(start, 33)).is_err());
+
+        // Проба не удалась — цепь снова разомкнута на всю паузу
+        breaker.record_failure(secs(start, 34));
+        assert!(breaker.before_call(secs(start, 50)).is_err());
+
+        assert!(breaker.before_call(secs(start, 64)).is_ok());
+        breaker.record_success();
+        for second in 65..70 {
+            assert!(breaker.before_call(secs(start, second)).is_ok());
+        }
+
+        // После замыкания для размыкания снова нужны три сбоя подряд
+        breaker.record_failure(secs(start, 70));
+        assert!(breaker.before_call(secs(start, 71)).is_ok());
+    }
+
+    #[test]
+    fn abandoned_probe_lets_the_next_one_through_after_cooldown() {
+        let breaker = breaker();
+        let start = Instant::now();
+        for second in 0..3 {
+            breaker.record_failure(secs(start, second));
+        }
+
+        assert!(breaker.before_call(secs(start, 40)).is_ok());
+        // Проба так и не отчиталась (запрос отменен)
+        assert!(breaker.before_call(secs(start, 60)).is_err());
+        assert!(breaker.before_call(secs(start, 70)).is_ok());
+    }
+}
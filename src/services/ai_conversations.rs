@@ -0,0 +1,230 @@
+use sqlx::{types::Json, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::conversation::{Conversation, ConversationKind, ConversationMessage, GoalReviewContext, GoalReviewOutcome, MessageRole},
+    services::ai::{AiResponseMeta, AiService},
+    utils::{
+        errors::AppError,
+        sanitize::{user_data_block, PROMPT_DATA_NOTICE},
+    },
+};
+
+/// Сколько последних сообщений диалога попадает в промпт
+const PROMPT_HISTORY_LIMIT: usize = 20;
+
+/// Диалоги с помощником: обычные и еженедельный разбор целей
+pub struct ConversationService {
+    pool: DbPool,
+}
+
+impl ConversationService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Создает диалог с первыми сообщениями. Для разбора целей — `None`, если разбор
+    /// за эту неделю уже создан (например, параллельным запуском планировщика).
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        kind: ConversationKind,
+        title: &str,
+        review: Option<&GoalReviewContext>,
+        messages: &[(MessageRole, String)],
+    ) -> Result<Option<Conversation>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let conversation = sqlx::query_as::<_, Conversation>(
+            r#"
+            INSERT INTO ai_conversations (user_id, kind, title, week_start, review)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, week_start) WHERE kind = 'goal_review' DO NOTHING
+            RETURNING *
+            "#
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(title)
+        .bind(review.map(|review| review.week_start))
+        .bind(review.map(Json))
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(conversation) = conversation else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+
+        for (role, content) in messages {
+            insert_message(&mut tx, conversation.id, *role, content).await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(conversation))
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Conversation>, AppError> {
+        let conversations = sqlx::query_as::<_, Conversation>(
+            "SELECT * FROM ai_conversations WHERE user_id = $1 ORDER BY updated_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(conversations)
+    }
+
+    pub async fn get(&self, user_id: Uuid, id: Uuid) -> Result<Conversation, AppError> {
+        sqlx::query_as::<_, Conversation>("SELECT * FROM ai_conversations WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))
+    }
+
+    pub async fn messages(&self, conversation_id: Uuid) -> Result<Vec<ConversationMessage>, AppError> {
+        let messages = sqlx::query_as::<_, ConversationMessage>(
+            "SELECT * FROM ai_conversation_messages WHERE conversation_id = $1 ORDER BY created_at, id"
+        )
+        .bind(conversation_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// Сообщение пользователя и ответ помощника. Ничего не сохраняется, если модель не ответила.
+    pub async fn reply(
+        &self,
+        ai_service: &AiService,
+        user_id: Uuid,
+        id: Uuid,
+        content: &str,
+    ) -> Result<(ConversationMessage, ConversationMessage, AiResponseMeta), AppError> {
+        let conversation = self.get(user_id, id).await?;
+        if conversation.concluded_at.is_some() {
+            return Err(AppError::Conflict("Conversation is already concluded".to_string()));
+        }
+
+        let history = self.messages(conversation.id).await?;
+        let prompt = conversation_prompt(&history, content);
+        let completion = ai_service.complete(&prompt).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let user = insert_message(&mut tx, conversation.id, MessageRole::User, content).await?;
+        let assistant = insert_message(&mut tx, conversation.id, MessageRole::Assistant, completion.text.trim()).await?;
+        sqlx::query("UPDATE ai_conversations SET updated_at = NOW() WHERE id = $1")
+            .bind(conversation.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok((user, assistant, completion.meta))
+    }
+
+    /// Сохраняет итог разбора; повторное завершение не допускается
+    pub async fn conclude(&self, id: Uuid, outcome: &GoalReviewOutcome) -> Result<Conversation, AppError> {
+        sqlx::query_as::<_, Conversation>(
+            r#"
+            UPDATE ai_conversations
+            SET outcome = $2, concluded_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND concluded_at IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(Json(outcome))
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Conversation is already concluded".to_string()))
+    }
+
+    /// Отмечает итог примененным; `false` — его уже применили
+    pub async fn claim_apply(&self, id: Uuid) -> Result<bool, AppError> {
+        let claimed = sqlx::query(
+            "UPDATE ai_conversations SET applied_at = NOW(), updated_at = NOW() WHERE id = $1 AND applied_at IS NULL"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(claimed.rows_affected() == 1)
+    }
+
+    /// Снимает отметку, если применить итог не удалось
+    pub async fn release_apply(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE ai_conversations SET applied_at = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// `clock_timestamp()`, а не `NOW()`: сообщения одной транзакции должны различаться по времени
+async fn insert_message(
+    conn: &mut PgConnection,
+    conversation_id: Uuid,
+    role: MessageRole,
+    content: &str,
+) -> Result<ConversationMessage, AppError> {
+    let message = sqlx::query_as::<_, ConversationMessage>(
+        r#"
+        INSERT INTO ai_conversation_messages (conversation_id, role, content, created_at)
+        VALUES ($1, $2, $3, clock_timestamp())
+        RETURNING *
+        "#
+    )
+    .bind(conversation_id)
+    .bind(role.as_str())
+    .bind(content)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(message)
+}
+
+/// Промпт для очередного ответа: системные данные диалога, последние сообщения и новый вопрос.
+/// Всё, что написал пользователь, передается блоками данных.
+pub fn conversation_prompt(history: &[ConversationMessage], content: &str) -> String {
+    let mut prompt = format!(
+        "{}Ты — ИИ-помощник в кулинарном приложении IT Cook. Помогай с питанием, рецептами и целями. \
+        Отвечай кратко и по делу, на русском.\n",
+        PROMPT_DATA_NOTICE
+    );
+
+    for message in history.iter().filter(|message| message.role == MessageRole::System.as_str()) {
+        prompt.push_str("Данные, на которых построен разговор:\n");
+        prompt.push_str(&user_data_block("context", &message.content));
+    }
+
+    prompt.push_str(&transcript(history));
+    prompt.push_str("Новое сообщение пользователя:\n");
+    prompt.push_str(&user_data_block("user_message", content));
+    prompt
+}
+
+/// Последние сообщения диалога без системных
+pub fn transcript(history: &[ConversationMessage]) -> String {
+    let dialog: Vec<&ConversationMessage> = history
+        .iter()
+        .filter(|message| message.role != MessageRole::System.as_str())
+        .collect();
+    let recent = &dialog[dialog.len().saturating_sub(PROMPT_HISTORY_LIMIT)..];
+    if recent.is_empty() {
+        return String::new();
+    }
+
+    let mut text = String::from("История разговора:\n");
+    for message in recent {
+        if message.role == MessageRole::User.as_str() {
+            text.push_str(&user_data_block("user_message", &message.content));
+        } else {
+            text.push_str(&format!("Помощник: {}\n", message.content));
+        }
+    }
+    text
+}
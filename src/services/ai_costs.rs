@@ -0,0 +1,660 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde_json::json;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::{AiCostConfig, ModelPrice},
+    db::DbPool,
+    models::ai_usage::{AiCostBreakdown, AiCostReport, AiUserCost},
+    services::{
+        ai_generation::AiFeature,
+        monthly_reports::month_bounds,
+        notifications::NotificationService,
+    },
+    utils::errors::{AppError, ErrorCode},
+};
+
+/// Для оценки токенов по длине текста (Gemini): примерно 4 символа на токен
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Пороги расхода месячного бюджета, о которых предупреждаются администраторы
+pub const BUDGET_ALERT_THRESHOLDS: [u8; 2] = [80, 100];
+
+const TOP_USERS_LIMIT: i64 = 20;
+
+/// Токены одного вызова провайдера
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Посчитаны по длине текста, а не взяты из ответа провайдера
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        Self {
+            prompt_tokens: estimate_tokens(prompt),
+            completion_tokens: estimate_tokens(completion),
+            estimated: true,
+        }
+    }
+}
+
+fn estimate_tokens(text: &str) -> u32 {
+    let chars = text.chars().count();
+    u32::try_from(chars.div_ceil(CHARS_PER_TOKEN)).unwrap_or(u32::MAX)
+}
+
+/// Стоимость вызова в долларах
+pub fn call_cost(price: &ModelPrice, usage: &TokenUsage) -> f64 {
+    (usage.prompt_tokens as f64 * price.input_per_million
+        + usage.completion_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
+/// Пороги, которые пересек расход `spent` и о которых еще не предупреждали
+pub fn reached_thresholds(spent: f64, budget: f64, alerted: &[u8]) -> Vec<u8> {
+    BUDGET_ALERT_THRESHOLDS
+        .into_iter()
+        .filter(|threshold| !alerted.contains(threshold) && spent >= budget * f64::from(*threshold) / 100.0)
+        .collect()
+}
+
+/// Прогноз расходов на конец месяца при текущем темпе. Первые сутки месяца считаются
+/// целыми, чтобы несколько утренних вызовов не давали огромный прогноз.
+pub fn projected_month_end(spent: f64, start: DateTime<Utc>, end: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    if now >= end {
+        return spent;
+    }
+    let total_secs = (end - start).num_seconds().max(1) as f64;
+    let elapsed_secs = ((now - start).num_seconds() as f64).clamp(86_400.0, total_secs);
+    spent * total_secs / elapsed_secs
+}
+
+/// Вызов провайдера для учета расходов
+#[derive(Debug, Clone, Copy)]
+pub struct AiCall<'a> {
+    pub user_id: Option<Uuid>,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub feature: AiFeature,
+    pub essential: bool,
+    pub usage: TokenUsage,
+}
+
+/// Расход текущего месяца в памяти процесса; при смене месяца перечитывается из базы
+#[derive(Debug)]
+struct MonthSpend {
+    month: NaiveDate,
+    spent_usd: f64,
+    /// Пороги, о которых уже предупредили в этом месяце
+    alerted: Vec<u8>,
+}
+
+/// Записывает стоимость вызовов ИИ и следит за месячным бюджетом. Общий для всех копий
+/// `AiService`; проверка бюджета стоит в той же обертке, что и размыкатель провайдера.
+#[derive(Debug)]
+pub struct AiCostTracker {
+    pool: DbPool,
+    config: AiCostConfig,
+    month: Mutex<Option<MonthSpend>>,
+}
+
+impl AiCostTracker {
+    pub fn new(pool: DbPool, config: AiCostConfig) -> Self {
+        Self {
+            pool,
+            config,
+            month: Mutex::new(None),
+        }
+    }
+
+    /// Фоновые вызовы не выполняются, если месячный бюджет исчерпан; вызовы по запросу
+    /// пользователя (чат и т.п.) работают всегда
+    pub async fn check_budget(&self, essential: bool, now: DateTime<Utc>) -> Result<(), AppError> {
+        let Some(budget) = self.config.monthly_budget_usd else {
+            return Ok(());
+        };
+        if essential {
+            return Ok(());
+        }
+
+        match self.month_spent(now).await {
+            Ok(spent) if spent >= budget => Err(AppError::ExternalService(
+                "Monthly AI budget is exhausted: non-essential AI features are paused".to_string(),
+            )
+            .with_code(ErrorCode::AiUnavailable)),
+            Ok(_) => Ok(()),
+            // Учет недоступен — вызов не блокируется
+            Err(e) => {
+                warn!("Failed to check AI budget: {:?}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Записывает вызов. Ошибки учета только логируются — ответ пользователю не страдает.
+    pub async fn record(&self, call: AiCall<'_>, now: DateTime<Utc>) {
+        if let Err(e) = self.try_record(call, now).await {
+            warn!("Failed to record AI usage for {} ({}): {:?}", call.provider, call.feature.as_str(), e);
+        }
+    }
+
+    async fn try_record(&self, call: AiCall<'_>, now: DateTime<Utc>) -> Result<(), AppError> {
+        let cost = match self.config.prices.get(call.model) {
+            Some(price) => call_cost(price, &call.usage),
+            None => {
+                warn!("No price configured for AI model {}, recording zero cost", call.model);
+                0.0
+            }
+        };
+
+        // Расход месяца загружается до записи, чтобы вызов не учелся дважды
+        let budget = self.config.monthly_budget_usd;
+        if budget.is_some() {
+            self.month_spent(now).await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_usage
+                (user_id, provider, model, feature, essential, prompt_tokens, completion_tokens, tokens_estimated, cost_usd, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#
+        )
+        .bind(call.user_id)
+        .bind(call.provider)
+        .bind(call.model)
+        .bind(call.feature.as_str())
+        .bind(call.essential)
+        .bind(i32::try_from(call.usage.prompt_tokens).unwrap_or(i32::MAX))
+        .bind(i32::try_from(call.usage.completion_tokens).unwrap_or(i32::MAX))
+        .bind(call.usage.estimated)
+        .bind(cost)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        let Some(budget) = budget else {
+            return Ok(());
+        };
+        let (month, spent, reached) = {
+            let mut guard = self.month.lock().unwrap();
+            let Some(state) = guard.as_mut().filter(|state| state.month == month_start(now)) else {
+                return Ok(());
+            };
+            state.spent_usd += cost;
+            let reached = reached_thresholds(state.spent_usd, budget, &state.alerted);
+            state.alerted.extend(&reached);
+            (state.month, state.spent_usd, reached)
+        };
+
+        for threshold in reached {
+            self.alert(month, threshold, spent, budget).await?;
+        }
+        Ok(())
+    }
+
+    /// Расход текущего месяца: из памяти или, при первом обращении в месяце, из базы
+    async fn month_spent(&self, now: DateTime<Utc>) -> Result<f64, AppError> {
+        let month = month_start(now);
+        if let Some(state) = self.month.lock().unwrap().as_ref().filter(|state| state.month == month) {
+            return Ok(state.spent_usd);
+        }
+
+        let (start, end) = month_bounds(month);
+        let (spent,): (f64,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION FROM ai_usage WHERE created_at >= $1 AND created_at < $2"
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_one(&self.pool)
+        .await?;
+        let alerted: Vec<(i16,)> = sqlx::query_as("SELECT threshold_percent FROM ai_budget_alerts WHERE month = $1")
+            .bind(month)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut guard = self.month.lock().unwrap();
+        match guard.as_ref() {
+            // Другой запрос успел загрузить месяц — его значение не старее нашего
+            Some(state) if state.month == month => Ok(state.spent_usd),
+            _ => {
+                *guard = Some(MonthSpend {
+                    month,
+                    spent_usd: spent,
+                    alerted: alerted.into_iter().filter_map(|(threshold,)| u8::try_from(threshold).ok()).collect(),
+                });
+                Ok(spent)
+            }
+        }
+    }
+
+    /// Громкий лог и уведомление администраторам; в базе — не больше одного раза на порог в месяц
+    async fn alert(&self, month: NaiveDate, threshold: u8, spent: f64, budget: f64) -> Result<(), AppError> {
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO ai_budget_alerts (month, threshold_percent, spent_usd, budget_usd)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (month, threshold_percent) DO NOTHING
+            "#
+        )
+        .bind(month)
+        .bind(i16::from(threshold))
+        .bind(spent)
+        .bind(budget)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(());
+        }
+
+        let (title, message) = if threshold >= 100 {
+            error!(
+                "AI BUDGET EXHAUSTED: ${:.2} of ${:.2} spent in {}; non-essential AI features are paused",
+                spent, budget, month.format("%Y-%m")
+            );
+            (
+                "Бюджет на ИИ исчерпан".to_string(),
+                format!(
+                    "Расходы на ИИ за месяц: ${:.2} из ${:.2}. Фоновые функции переведены на ответы без модели, чат работает.",
+                    spent, budget
+                ),
+            )
+        } else {
+            warn!(
+                "AI budget at {}%: ${:.2} of ${:.2} spent in {}",
+                threshold, spent, budget, month.format("%Y-%m")
+            );
+            (
+                format!("Израсходовано {}% бюджета на ИИ", threshold),
+                format!("Расходы на ИИ за месяц: ${:.2} из ${:.2}.", spent, budget),
+            )
+        };
+
+        let admins: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM users WHERE role = 'admin'")
+            .fetch_all(&self.pool)
+            .await?;
+        let notifications = NotificationService::new(self.pool.clone());
+        let data = json!({ "month": month, "threshold_percent": threshold, "spent_usd": spent, "budget_usd": budget });
+        for (admin_id,) in admins {
+            notifications.create(admin_id, "ai_budget", &title, &message, data.clone()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Отчет о расходах на ИИ для администраторов
+pub struct AiCostService {
+    pool: DbPool,
+    config: AiCostConfig,
+}
+
+impl AiCostService {
+    pub fn new(pool: DbPool, config: AiCostConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub async fn report(&self, month: NaiveDate, now: DateTime<Utc>) -> Result<AiCostReport, AppError> {
+        let (start, end) = month_bounds(month);
+
+        let (calls, cost_usd, estimated_cost_usd): (i64, f64, f64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*),
+                   COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION,
+                   COALESCE(SUM(cost_usd) FILTER (WHERE tokens_estimated), 0)::DOUBLE PRECISION
+            FROM ai_usage
+            WHERE created_at >= $1 AND created_at < $2
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let projected = projected_month_end(cost_usd, start, end, now);
+        let budget = self.config.monthly_budget_usd;
+
+        Ok(AiCostReport {
+            month,
+            period_start: start,
+            period_end: end,
+            calls,
+            cost_usd,
+            estimated_cost_usd,
+            projected_month_end_usd: projected,
+            budget_usd: budget,
+            budget_used_percent: budget.map(|budget| cost_usd / budget * 100.0),
+            non_essential_paused: month == month_start(now) && budget.is_some_and(|budget| cost_usd >= budget),
+            by_provider: self.breakdown("provider", start, end).await?,
+            by_feature: self.breakdown("feature", start, end).await?,
+            by_model: self.breakdown("model", start, end).await?,
+            top_users: self.top_users(start, end).await?,
+        })
+    }
+
+    /// `column` — только из фиксированного списка срезов выше
+    async fn breakdown(&self, column: &'static str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<AiCostBreakdown>, AppError> {
+        let rows = sqlx::query_as::<_, AiCostBreakdown>(&format!(
+            r#"
+            SELECT {column}::TEXT AS key,
+                   COUNT(*) AS calls,
+                   COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens,
+                   COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens,
+                   COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION AS cost_usd
+            FROM ai_usage
+            WHERE created_at >= $1 AND created_at < $2
+            GROUP BY {column}
+            ORDER BY cost_usd DESC, calls DESC
+            "#,
+            column = column,
+        ))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn top_users(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<AiUserCost>, AppError> {
+        let rows = sqlx::query_as::<_, AiUserCost>(
+            r#"
+            SELECT a.user_id, u.email, COUNT(*) AS calls, SUM(a.cost_usd)::DOUBLE PRECISION AS cost_usd
+            FROM ai_usage a
+            JOIN users u ON u.id = a.user_id
+            WHERE a.created_at >= $1 AND a.created_at < $2
+            GROUP BY a.user_id, u.email
+            ORDER BY cost_usd DESC
+            LIMIT $3
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .bind(TOP_USERS_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+/// Первое число месяца
+pub fn month_start(now: DateTime<Utc>) -> NaiveDate {
+    let today = now.date_naive();
+    today.with_day(1).unwrap_or(today)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        config::{parse_model_prices, AiResilienceConfig},
+        services::{
+            ai::AiService,
+            ai_providers::AiProvider,
+            clock::SandboxClockMode,
+        },
+        test_support::{frozen_clock, insert_user, StubAdapter},
+    };
+
+    fn at(month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, month, day, hour, 0, 0).unwrap()
+    }
+
+    fn price(input_per_million: f64, output_per_million: f64) -> ModelPrice {
+        ModelPrice { input_per_million, output_per_million }
+    }
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> TokenUsage {
+        TokenUsage { prompt_tokens, completion_tokens, estimated: false }
+    }
+
+    fn assert_close(actual: f64, expected: f64, context: &str) {
+        assert!((actual - expected).abs() < 1e-12, "{}: {} != {}", context, actual, expected);
+    }
+
+    #[test]
+    fn call_cost_multiplies_tokens_by_the_model_price() {
+        let cases = [
+            // OpenAI: 1200 входных и 300 выходных токенов по 0.50/1.50
+            ("gpt-3.5-turbo", usage(1_200, 300), 0.0006 + 0.00045),
+            ("gemini-1.5-flash", usage(1_000_000, 1_000_000), 0.075 + 0.30),
+            ("llama-3.1-8b-instant", usage(0, 2_000), 0.00016),
+            ("gpt-3.5-turbo", usage(0, 0), 0.0),
+        ];
+        let prices = AiCostConfig::from_env().prices;
+
+        for (model, usage, expected) in cases {
+            assert_close(call_cost(&prices[model], &usage), expected, model);
+        }
+    }
+
+    #[test]
+    fn gemini_tokens_are_estimated_from_characters() {
+        // Символы, а не байты: кириллица не должна считаться вдвое дороже
+        let cases = [
+            ("", "", 0, 0),
+            ("абв", "г", 1, 1),
+            ("абвг", "абвгд", 1, 2),
+            ("Что приготовить из гречки?", "Гречку с грибами", 7, 4),
+        ];
+        for (prompt, completion, prompt_tokens, completion_tokens) in cases {
+            let usage = TokenUsage::estimate(prompt, completion);
+            assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.estimated), (prompt_tokens, completion_tokens, true), "{}", prompt);
+        }
+
+        let estimated = TokenUsage::estimate(&"а".repeat(4_000), &"б".repeat(2_000));
+        assert_close(call_cost(&price(0.075, 0.30), &estimated), (1_000.0 * 0.075 + 500.0 * 0.30) / 1_000_000.0, "gemini");
+    }
+
+    #[test]
+    fn model_prices_are_parsed_and_invalid_entries_skipped() {
+        let parsed: HashMap<String, ModelPrice> =
+            parse_model_prices(" gpt-4o = 2.5/10 ,broken, =1/2,neg=-1/2,nan=NaN/1,free=0/0,half=1/").into_iter().collect();
+
+        let mut models: Vec<&str> = parsed.keys().map(String::as_str).collect();
+        models.sort();
+        assert_eq!(models, vec!["free", "gpt-4o"]);
+        assert_eq!((parsed["gpt-4o"].input_per_million, parsed["gpt-4o"].output_per_million), (2.5, 10.0));
+    }
+
+    #[test]
+    fn thresholds_fire_once_each() {
+        let cases: [(f64, &[u8], Vec<u8>); 6] = [
+            (7.99, &[], vec![]),
+            (8.0, &[], vec![80]),
+            (9.5, &[80], vec![]),
+            (10.0, &[80], vec![100]),
+            (12.0, &[], vec![80, 100]),
+            (12.0, &[80, 100], vec![]),
+        ];
+        for (spent, alerted, expected) in cases {
+            assert_eq!(reached_thresholds(spent, 10.0, alerted), expected, "spent {} alerted {:?}", spent, alerted);
+        }
+    }
+
+    #[test]
+    fn projection_extrapolates_the_month_pace() {
+        let (start, end) = month_bounds(NaiveDate::from_ymd_opt(2026, 4, 1).unwrap());
+        let cases = [
+            // 30 дней в апреле: за 10 дней $5 — к концу месяца $15
+            (at(4, 11, 0), 5.0, 15.0),
+            (at(4, 16, 0), 5.0, 10.0),
+            // Первые сутки считаются целыми
+            (at(4, 1, 2), 1.0, 30.0),
+            (at(4, 1, 23), 1.0, 30.0),
+            (at(5, 1, 0), 7.0, 7.0),
+            (at(4, 11, 0), 0.0, 0.0),
+        ];
+        for (now, spent, expected) in cases {
+            assert_close(projected_month_end(spent, start, end, now), expected, &now.to_string());
+        }
+    }
+
+    #[test]
+    fn month_start_is_the_first_day_in_utc() {
+        assert_eq!(month_start(at(2, 28, 23)), NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
+        assert_eq!(month_start(at(3, 1, 0)), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+    }
+
+    async fn scalar(pool: &PgPool, sql: &str) -> i64 {
+        sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+    }
+
+    /// Каждый вызов заглушки — 10 + 20 токенов, по цене 100/100 это $0.003
+    fn cost_config(budget: Option<f64>) -> AiCostConfig {
+        AiCostConfig {
+            prices: HashMap::from([("stub-model".to_string(), price(100.0, 100.0))]),
+            monthly_budget_usd: budget,
+        }
+    }
+
+    #[sqlx::test]
+    async fn exhausted_budget_pauses_background_calls_but_not_chat(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let admin_id = insert_user(&pool, "Админ").await;
+        sqlx::query("UPDATE users SET role = 'admin' WHERE id = $1").bind(admin_id).execute(&pool).await.unwrap();
+
+        let clock = frozen_clock(at(4, 10, 12));
+        let adapter = Arc::new(StubAdapter::new("stub", vec![Some("ok")]));
+        let ai = AiService::new(AiProvider::Mock, &AiResilienceConfig::default())
+            .with_adapter(adapter.clone())
+            .with_cost_tracker(AiCostTracker::new(pool.clone(), cost_config(Some(0.01))))
+            .with_clock(clock.clone())
+            .for_user(user_id);
+
+        // $0.003, $0.006, $0.009 (80%), $0.012 (100%) — пока бюджет не исчерпан, фоновые вызовы идут
+        for _ in 0..4 {
+            ai.background().complete_with("Дайджест", AiFeature::Chat, None).await.unwrap();
+        }
+        assert_eq!(adapter.calls(), 4);
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM ai_budget_alerts").await, 2);
+        let alerts: Vec<(i16, f64)> = sqlx::query_as("SELECT threshold_percent, spent_usd FROM ai_budget_alerts ORDER BY threshold_percent")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(alerts[0].0, 80);
+        assert_close(alerts[0].1, 0.009, "80% alert");
+        assert_eq!(alerts[1].0, 100);
+        assert_close(alerts[1].1, 0.012, "100% alert");
+        let notifications: Vec<String> = sqlx::query_scalar("SELECT title FROM notifications WHERE user_id = $1 AND kind = 'ai_budget' ORDER BY created_at")
+            .bind(admin_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(notifications, vec!["Израсходовано 80% бюджета на ИИ", "Бюджет на ИИ исчерпан"]);
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM notifications WHERE kind = 'ai_budget'").await, 2, "only admins are notified");
+
+        // Фоновые вызовы и проактивные сообщения до провайдера не доходят
+        for (service, feature) in [(ai.background(), AiFeature::Chat), (ai.clone(), AiFeature::Proactive)] {
+            let error = service.complete_with("Совет", feature, None).await.unwrap_err();
+            assert!(format!("{:?}", error).contains("Monthly AI budget is exhausted"), "{:?}", error);
+            assert_eq!(error.code(), ErrorCode::AiUnavailable);
+        }
+        assert_eq!(adapter.calls(), 4);
+
+        // Чат по запросу пользователя работает и продолжает учитываться, без повторных предупреждений
+        assert_eq!(ai.generate_response("Привет").await.unwrap(), "ok");
+        assert_eq!(adapter.calls(), 5);
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM ai_usage").await, 5);
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM notifications WHERE kind = 'ai_budget'").await, 2);
+
+        // После перезапуска расход и отправленные предупреждения читаются из базы
+        let restarted = AiService::new(AiProvider::Mock, &AiResilienceConfig::default())
+            .with_adapter(adapter.clone())
+            .with_cost_tracker(AiCostTracker::new(pool.clone(), cost_config(Some(0.01))))
+            .with_clock(clock.clone());
+        assert!(restarted.background().complete("Дайджест").await.is_err());
+        restarted.complete("Привет").await.unwrap();
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM notifications WHERE kind = 'ai_budget'").await, 2);
+
+        // В новом месяце бюджет снова доступен
+        clock.set_mode(SandboxClockMode::Frozen { at: at(5, 1, 0) });
+        restarted.background().complete("Дайджест").await.unwrap();
+        assert_eq!(adapter.calls(), 7);
+    }
+
+    #[sqlx::test]
+    async fn without_a_budget_nothing_is_paused_and_unknown_models_cost_nothing(pool: PgPool) {
+        let adapter = Arc::new(StubAdapter::new("stub", vec![Some("ok")]));
+        let config = AiCostConfig { prices: HashMap::new(), monthly_budget_usd: None };
+        let ai = AiService::new(AiProvider::Mock, &AiResilienceConfig::default())
+            .with_adapter(adapter.clone())
+            .with_cost_tracker(AiCostTracker::new(pool.clone(), config))
+            .with_clock(frozen_clock(at(4, 10, 12)));
+
+        for _ in 0..3 {
+            ai.background().complete_with("Совет", AiFeature::Proactive, None).await.unwrap();
+        }
+
+        let (calls, cost): (i64, f64) = sqlx::query_as("SELECT COUNT(*), SUM(cost_usd) FROM ai_usage").fetch_one(&pool).await.unwrap();
+        assert_eq!((calls, cost), (3, 0.0));
+        let essential: bool = sqlx::query_scalar("SELECT bool_or(essential) FROM ai_usage").fetch_one(&pool).await.unwrap();
+        assert!(!essential, "proactive calls are recorded as non-essential");
+        assert_eq!(scalar(&pool, "SELECT COUNT(*) FROM ai_budget_alerts").await, 0);
+    }
+
+    #[sqlx::test]
+    async fn report_breaks_spend_down_and_projects_the_month(pool: PgPool) {
+        let anna = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+        let tracker = AiCostTracker::new(pool.clone(), cost_config(Some(1.0)));
+        let call = |user_id, provider, feature, usage| AiCall {
+            user_id: Some(user_id),
+            provider,
+            model: "stub-model",
+            feature,
+            essential: true,
+            usage,
+        };
+
+        // $0.1 + $0.2 на OpenAI, $0.05 (оценка) на Gemini; вызов прошлого месяца не учитывается
+        tracker.record(call(anna, "openai", AiFeature::Chat, usage(1_000, 0)), at(4, 2, 9)).await;
+        tracker.record(call(boris, "openai", AiFeature::RecipeGeneration, usage(0, 2_000)), at(4, 3, 9)).await;
+        let estimated = TokenUsage { estimated: true, ..usage(250, 250) };
+        tracker.record(call(anna, "gemini", AiFeature::Chat, estimated), at(4, 4, 9)).await;
+        tracker.record(call(boris, "gemini", AiFeature::Chat, usage(5_000, 0)), at(3, 31, 9)).await;
+
+        let report = AiCostService::new(pool.clone(), cost_config(Some(1.0)))
+            .report(NaiveDate::from_ymd_opt(2026, 4, 1).unwrap(), at(4, 11, 0))
+            .await
+            .unwrap();
+
+        assert_eq!(report.calls, 3);
+        assert_close(report.cost_usd, 0.35, "total");
+        assert_close(report.estimated_cost_usd, 0.05, "estimated");
+        assert_close(report.projected_month_end_usd, 0.35 * 3.0, "projection");
+        assert_close(report.budget_used_percent.unwrap(), 35.0, "budget used");
+        assert!(!report.non_essential_paused);
+
+        let breakdown = |rows: &[AiCostBreakdown]| -> Vec<(String, i64, i64, i64)> {
+            rows.iter()
+                .map(|row| (row.key.clone(), row.calls, row.prompt_tokens, row.completion_tokens))
+                .collect()
+        };
+        assert_eq!(breakdown(&report.by_provider), vec![
+            ("openai".to_string(), 2, 1_000, 2_000),
+            ("gemini".to_string(), 1, 250, 250),
+        ]);
+        assert_eq!(breakdown(&report.by_feature), vec![
+            ("recipe_generation".to_string(), 1, 0, 2_000),
+            ("chat".to_string(), 2, 1_250, 250),
+        ]);
+        assert_eq!(breakdown(&report.by_model), vec![("stub-model".to_string(), 3, 1_250, 2_250)]);
+        let users: Vec<(Uuid, i64)> = report.top_users.iter().map(|user| (user.user_id, user.calls)).collect();
+        assert_eq!(users, vec![(boris, 1), (anna, 2)]);
+        assert_close(report.top_users[0].cost_usd, 0.2, "boris");
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Серверные границы: значения из запроса и из окружения в них урезаются
+pub const MIN_TEMPERATURE: f32 = 0.0;
+pub const MAX_TEMPERATURE: f32 = 1.0;
+pub const MIN_TOP_P: f32 = 0.0;
+pub const MAX_TOP_P: f32 = 1.0;
+pub const MIN_MAX_TOKENS: u32 = 1;
+
+/// Функции, которые обращаются к модели, — у каждой свои настройки генерации
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AiFeature {
+    Chat,
+    RecipeGeneration,
+    FridgeReport,
+    NutritionAnalysis,
+    Proactive,
+    ReceiptScan,
+}
+
+impl AiFeature {
+    pub const ALL: [AiFeature; 6] = [
+        AiFeature::Chat,
+        AiFeature::RecipeGeneration,
+        AiFeature::FridgeReport,
+        AiFeature::NutritionAnalysis,
+        AiFeature::Proactive,
+        AiFeature::ReceiptScan,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiFeature::Chat => "chat",
+            AiFeature::RecipeGeneration => "recipe_generation",
+            AiFeature::FridgeReport => "fridge_report",
+            AiFeature::NutritionAnalysis => "nutrition_analysis",
+            AiFeature::Proactive => "proactive",
+            AiFeature::ReceiptScan => "receipt_scan",
+        }
+    }
+
+    /// Вызывается по запросу пользователя. Проактивные подсказки — фоновые: при исчерпанном
+    /// месячном бюджете они строятся без модели.
+    pub fn is_essential(&self) -> bool {
+        !matches!(self, AiFeature::Proactive)
+    }
+
+    /// Префикс переменных окружения: `AI_CHAT_TEMPERATURE`, `AI_CHAT_MAX_TOKENS`, `AI_CHAT_TOP_P`
+    fn env_prefix(&self) -> &'static str {
+        match self {
+            AiFeature::Chat => "AI_CHAT",
+            AiFeature::RecipeGeneration => "AI_RECIPE_GENERATION",
+            AiFeature::FridgeReport => "AI_FRIDGE_REPORT",
+            AiFeature::NutritionAnalysis => "AI_NUTRITION_ANALYSIS",
+            AiFeature::Proactive => "AI_PROACTIVE",
+            AiFeature::ReceiptScan => "AI_RECEIPT_SCAN",
+        }
+    }
+
+    /// Встроенные значения: анализ питания, отчеты и чеки — сдержанно, чат и подсказки — живее
+    pub fn builtin(&self) -> GenerationSettings {
+        let (temperature, max_tokens, top_p) = match self {
+            AiFeature::Chat => (0.7, 800, 0.9),
+            AiFeature::RecipeGeneration => (0.5, 1200, 0.9),
+            AiFeature::FridgeReport => (0.3, 1500, 0.9),
+            AiFeature::NutritionAnalysis => (0.2, 800, 0.8),
+            AiFeature::Proactive => (0.8, 300, 0.95),
+            AiFeature::ReceiptScan => (0.0, 2000, 0.8),
+        };
+        GenerationSettings { temperature, max_tokens, top_p }
+    }
+
+    /// Потолок max_tokens: выше не поднять ни окружением, ни запросом
+    pub fn max_tokens_cap(&self) -> u32 {
+        match self {
+            AiFeature::Chat => 1500,
+            AiFeature::RecipeGeneration => 2000,
+            AiFeature::FridgeReport => 3000,
+            AiFeature::NutritionAnalysis => 1500,
+            AiFeature::Proactive => 600,
+            AiFeature::ReceiptScan => 4000,
+        }
+    }
+}
+
+/// Параметры генерации, которые уходят провайдеру
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct GenerationSettings {
+    pub temperature: f32,
+    pub max_tokens: u32,
+    pub top_p: f32,
+}
+
+/// Необязательный блок `generation` в теле запроса
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GenerationOverride {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+}
+
+/// Настройки, с которыми вызвана модель; возвращаются в метаданных ответа
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EffectiveGeneration {
+    pub feature: AiFeature,
+    #[serde(flatten)]
+    pub settings: GenerationSettings,
+    /// Значения из запроса пришлось урезать до серверных границ
+    pub clamped: bool,
+}
+
+/// Настройки по умолчанию для всех функций. Читаются из окружения один раз при старте.
+#[derive(Debug, Clone)]
+pub struct GenerationDefaults {
+    settings: HashMap<AiFeature, GenerationSettings>,
+}
+
+impl GenerationDefaults {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Встроенные значения, поверх которых — переменные окружения. Нечисловые значения игнорируются.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let settings = AiFeature::ALL
+            .iter()
+            .map(|feature| {
+                let prefix = feature.env_prefix();
+                let overrides = GenerationOverride {
+                    temperature: lookup(&format!("{}_TEMPERATURE", prefix)).and_then(|value| value.trim().parse().ok()),
+                    max_tokens: lookup(&format!("{}_MAX_TOKENS", prefix)).and_then(|value| value.trim().parse().ok()),
+                    top_p: lookup(&format!("{}_TOP_P", prefix)).and_then(|value| value.trim().parse().ok()),
+                };
+                let (settings, _) = apply_override(*feature, feature.builtin(), &overrides);
+                (*feature, settings)
+            })
+            .collect();
+
+        Self { settings }
+    }
+
+    pub fn get(&self, feature: AiFeature) -> GenerationSettings {
+        self.settings.get(&feature).copied().unwrap_or_else(|| feature.builtin())
+    }
+
+    /// Порядок: блок `generation` из запроса, затем окружение, затем встроенные значения
+    pub fn resolve(&self, feature: AiFeature, overrides: Option<&GenerationOverride>) -> EffectiveGeneration {
+        let (settings, clamped) = apply_override(feature, self.get(feature), &overrides.copied().unwrap_or_default());
+        EffectiveGeneration { feature, settings, clamped }
+    }
+}
+
+/// Накладывает заданные значения поверх `base`, удерживая их в границах функции.
+/// Второй элемент — было ли что-то урезано. Нечисловые значения (NaN, ∞) пропускаются.
+pub fn apply_override(
+    feature: AiFeature,
+    base: GenerationSettings,
+    overrides: &GenerationOverride,
+) -> (GenerationSettings, bool) {
+    let temperature = bounded(overrides.temperature, MIN_TEMPERATURE, MAX_TEMPERATURE);
+    let top_p = bounded(overrides.top_p, MIN_TOP_P, MAX_TOP_P);
+    let max_tokens = overrides.max_tokens.map(|value| {
+        let limited = value.clamp(MIN_MAX_TOKENS, feature.max_tokens_cap());
+        (limited, limited != value)
+    });
+
+    let clamped = [temperature.map(|(_, c)| c), top_p.map(|(_, c)| c), max_tokens.map(|(_, c)| c)]
+        .into_iter()
+        .flatten()
+        .any(|c| c);
+
+    let settings = GenerationSettings {
+        temperature: temperature.map_or(base.temperature, |(value, _)| value),
+        max_tokens: max_tokens.map_or(base.max_tokens, |(value, _)| value),
+        top_p: top_p.map_or(base.top_p, |(value, _)| value),
+    };
+
+    (settings, clamped)
+}
+
+fn bounded(value: Option<f32>, min: f32, max: f32) -> Option<(f32, bool)> {
+    let value = value.filter(|value| value.is_finite())?;
+    let limited = value.clamp(min, max);
+    Some((limited, limited != value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(temperature: Option<f32>, max_tokens: Option<u32>, top_p: Option<f32>) -> GenerationOverride {
+        GenerationOverride { temperature, max_tokens, top_p }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> GenerationDefaults {
+        let vars: HashMap<String, String> = vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        GenerationDefaults::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn overrides_are_clamped_to_server_bounds() {
+        let base = AiFeature::Chat.builtin();
+        let cases = [
+            // (запрос, ожидаемые настройки, было ли урезано)
+            (request(None, None, None), base, false),
+            (request(Some(0.4), Some(1000), Some(0.5)), GenerationSettings { temperature: 0.4, max_tokens: 1000, top_p: 0.5 }, false),
+            (request(Some(1.0), Some(1500), Some(0.0)), GenerationSettings { temperature: 1.0, max_tokens: 1500, top_p: 0.0 }, false),
+            (request(Some(1.7), None, None), GenerationSettings { temperature: 1.0, ..base }, true),
+            (request(Some(-0.3), None, None), GenerationSettings { temperature: 0.0, ..base }, true),
+            (request(None, Some(50_000), None), GenerationSettings { max_tokens: 1500, ..base }, true),
+            (request(None, Some(0), None), GenerationSettings { max_tokens: 1, ..base }, true),
+            (request(None, None, Some(1.2)), GenerationSettings { top_p: 1.0, ..base }, true),
+            // Нечисловые значения пропускаются, а не урезаются
+            (request(Some(f32::NAN), None, Some(f32::INFINITY)), base, false),
+        ];
+
+        for (overrides, expected, clamped) in cases {
+            assert_eq!(apply_override(AiFeature::Chat, base, &overrides), (expected, clamped), "{:?}", overrides);
+        }
+
+        // Потолок max_tokens свой у каждой функции
+        for feature in AiFeature::ALL {
+            let (settings, clamped) = apply_override(feature, feature.builtin(), &request(None, Some(u32::MAX), None));
+            assert_eq!((settings.max_tokens, clamped), (feature.max_tokens_cap(), true));
+            assert!(feature.builtin().max_tokens <= feature.max_tokens_cap());
+        }
+    }
+
+    #[test]
+    fn request_wins_over_env_which_wins_over_builtin() {
+        let defaults = env(&[
+            ("AI_CHAT_TEMPERATURE", "0.3"),
+            ("AI_CHAT_MAX_TOKENS", "600"),
+            ("AI_FRIDGE_REPORT_MAX_TOKENS", "99999"),
+            ("AI_PROACTIVE_TOP_P", "не число"),
+        ]);
+
+        // Окружение поверх встроенных значений; незаданное — встроенное
+        assert_eq!(defaults.get(AiFeature::Chat), GenerationSettings { temperature: 0.3, max_tokens: 600, top_p: 0.9 });
+        assert_eq!(defaults.get(AiFeature::RecipeGeneration), AiFeature::RecipeGeneration.builtin());
+        // Окружение тоже урезается до границ функции
+        assert_eq!(defaults.get(AiFeature::FridgeReport).max_tokens, AiFeature::FridgeReport.max_tokens_cap());
+        assert_eq!(defaults.get(AiFeature::Proactive), AiFeature::Proactive.builtin());
+
+        let effective = defaults.resolve(AiFeature::Chat, Some(&request(Some(0.9), None, None)));
+        assert_eq!(effective.feature, AiFeature::Chat);
+        assert_eq!(effective.settings, GenerationSettings { temperature: 0.9, max_tokens: 600, top_p: 0.9 });
+        assert!(!effective.clamped);
+
+        let effective = defaults.resolve(AiFeature::Chat, None);
+        assert_eq!(effective.settings, defaults.get(AiFeature::Chat));
+
+        let effective = defaults.resolve(AiFeature::NutritionAnalysis, Some(&request(Some(3.0), Some(100), None)));
+        assert_eq!(effective.settings, GenerationSettings { temperature: 1.0, max_tokens: 100, top_p: 0.8 });
+        assert!(effective.clamped);
+
+        let echoed = serde_json::to_value(effective).unwrap();
+        assert_eq!(echoed["feature"], "nutrition_analysis");
+        assert_eq!((echoed["max_tokens"].as_u64(), echoed["clamped"].as_bool()), (Some(100), Some(true)));
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::ai_response::{
+        AiHistoryExport, AiProvenance, AiResponseExportEntry, AiResponseKind, AiResponseProvenance, AiResponseRecord,
+        DiaryDayRef, FridgeItemRef, WasteEntryRef, WellbeingEntryRef,
+    },
+    services::{ai::{AiResponseMeta, FridgeContext}, personal_health_assistant::HealthContext},
+    utils::errors::AppError,
+};
+
+/// История ответов ИИ и данные, на которых они построены ("почему ИИ так посоветовал")
+pub struct AiHistoryService {
+    pool: DbPool,
+}
+
+impl AiHistoryService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Сохраняет ответ в том виде, в каком его получил клиент, вместе с провенансом
+    pub async fn record<T: Serialize>(
+        &self,
+        user_id: Uuid,
+        kind: AiResponseKind,
+        provenance: &AiProvenance,
+        response: &T,
+    ) -> Result<Uuid, AppError> {
+        let response = serde_json::to_value(response)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to serialize AI response: {}", e)))?;
+        let provenance_json = serde_json::to_value(provenance)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to serialize AI provenance: {}", e)))?;
+
+        let (id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO ai_responses (user_id, kind, template, provider, model, degraded, response, provenance)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(&provenance.template)
+        .bind(&provenance.provider)
+        .bind(&provenance.model)
+        .bind(provenance.degraded)
+        .bind(response)
+        .bind(provenance_json)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Как `record`, но ошибка записи истории не ломает ответ пользователю
+    pub async fn record_or_warn<T: Serialize>(
+        &self,
+        user_id: Uuid,
+        kind: AiResponseKind,
+        provenance: Option<&AiProvenance>,
+        response: &T,
+    ) -> Option<Uuid> {
+        let provenance = provenance?;
+        match self.record(user_id, kind, provenance, response).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                warn!("Failed to record AI response history for user {}: {:?}", user_id, e);
+                None
+            }
+        }
+    }
+
+    pub async fn provenance(&self, user_id: Uuid, response_id: Uuid) -> Result<AiResponseProvenance, AppError> {
+        let record = sqlx::query_as::<_, AiResponseRecord>(
+            "SELECT id, kind, response, provenance, created_at FROM ai_responses WHERE id = $1 AND user_id = $2"
+        )
+        .bind(response_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("AI response not found".to_string()))?;
+
+        Ok(AiResponseProvenance {
+            response_id: record.id,
+            kind: record.kind,
+            created_at: record.created_at,
+            provenance: record.provenance,
+        })
+    }
+
+    /// Вся история ответов ИИ пользователя, от старых к новым
+    pub async fn export(&self, user_id: Uuid) -> Result<AiHistoryExport, AppError> {
+        let records = sqlx::query_as::<_, AiResponseRecord>(
+            "SELECT id, kind, response, provenance, created_at FROM ai_responses WHERE user_id = $1 ORDER BY created_at, id"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let responses: Vec<AiResponseExportEntry> = records
+            .into_iter()
+            .map(|record| AiResponseExportEntry {
+                id: record.id,
+                kind: record.kind,
+                created_at: record.created_at,
+                response: record.response,
+                provenance: record.provenance,
+            })
+            .collect();
+
+        Ok(AiHistoryExport {
+            user_id,
+            exported_at: Utc::now(),
+            total: responses.len(),
+            responses,
+        })
+    }
+}
+
+/// Провенанс ответа помощника по здоровью: строится из уже загруженного контекста, без запросов
+pub fn health_provenance(context: &HealthContext, template: &str, meta: &AiResponseMeta, model: &str) -> AiProvenance {
+    AiProvenance {
+        template: template.to_string(),
+        provider: meta.provider.clone(),
+        model: model.to_string(),
+        degraded: meta.degraded,
+        diary_days: context
+            .recent_nutrition
+            .iter()
+            .map(|day| DiaryDayRef {
+                date: day.date.date_naive(),
+                calories: day.calories,
+                water_ml: day.water_ml,
+            })
+            .collect(),
+        wellbeing_entries: context
+            .recent_wellbeing
+            .iter()
+            .map(|entry| WellbeingEntryRef {
+                id: entry.id,
+                date: entry.date.date_naive(),
+                mood_score: entry.mood_score,
+                stress_level: entry.stress_level,
+                sleep_hours: entry.sleep_hours,
+            })
+            .collect(),
+        fridge_items: Vec::new(),
+        waste_entries: Vec::new(),
+    }
+}
+
+/// Провенанс анализа холодильника: все продукты контекста (истекающие отмечены) и недавние отходы
+pub fn fridge_provenance(context: &FridgeContext, template: &str, meta: &AiResponseMeta, model: &str) -> AiProvenance {
+    let expiring: HashSet<Uuid> = context.expiring_items.iter().map(|item| item.id).collect();
+
+    let mut fridge_items: Vec<FridgeItemRef> = context
+        .items
+        .iter()
+        .map(|item| FridgeItemRef {
+            id: item.id,
+            name: item.name.clone(),
+            quantity: item.quantity,
+            unit: item.unit.clone(),
+            expiry_date: item.expiry_date,
+            expiring: expiring.contains(&item.id),
+        })
+        .collect();
+
+    // Истекающие продукты, которых нет в общем списке (например, уже просроченные), тоже попадают в промпт
+    let listed: HashSet<Uuid> = fridge_items.iter().map(|item| item.id).collect();
+    fridge_items.extend(
+        context
+            .expiring_items
+            .iter()
+            .filter(|item| !listed.contains(&item.id))
+            .map(|item| FridgeItemRef {
+                id: item.id,
+                name: item.name.clone(),
+                quantity: item.quantity,
+                unit: item.unit.clone(),
+                expiry_date: item.expiry_date,
+                expiring: true,
+            }),
+    );
+
+    AiProvenance {
+        template: template.to_string(),
+        provider: meta.provider.clone(),
+        model: model.to_string(),
+        degraded: meta.degraded,
+        diary_days: Vec::new(),
+        wellbeing_entries: Vec::new(),
+        fridge_items,
+        waste_entries: context
+            .recent_waste
+            .iter()
+            .map(|waste| WasteEntryRef {
+                id: waste.id,
+                name: waste.name.clone(),
+                wasted_quantity: waste.wasted_quantity,
+                unit: waste.unit.clone(),
+                waste_date: waste.waste_date,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        config::AiResilienceConfig,
+        models::health::{DailyWellbeing, FitnessLevel},
+        services::{
+            ai::AiService,
+            ai_providers::AiProvider,
+            fridge::FridgeService,
+            fridge_report_feedback::ReportSectionWeights,
+            personal_health_assistant::{NutritionSummary, UserHealthSummary},
+        },
+        test_support::{insert_fridge_item, insert_user, StubAdapter},
+        utils::glossary::Glossary,
+    };
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap()
+    }
+
+    fn wellbeing(date: DateTime<Utc>, mood_score: i32) -> DailyWellbeing {
+        DailyWellbeing {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            date,
+            mood_score: Some(mood_score),
+            energy_level: Some(6),
+            stress_level: Some(4),
+            sleep_hours: Some(7.5),
+            sleep_quality: None,
+            water_intake_ml: None,
+            exercise_minutes: None,
+            notes: Some("Личная заметка".to_string()),
+            symptoms: Vec::new(),
+            created_at: date,
+        }
+    }
+
+    fn nutrition(date: DateTime<Utc>, calories: f32) -> NutritionSummary {
+        NutritionSummary { date, calories, protein: 80.0, carbs: 200.0, fat: 60.0, water_ml: 1500 }
+    }
+
+    #[test]
+    fn health_trace_lists_exactly_the_context_records() {
+        let entries = vec![wellbeing(day(14), 7), wellbeing(day(15), 4)];
+        let context = HealthContext {
+            user_profile: UserHealthSummary {
+                name: "Анна".to_string(),
+                age: Some(30),
+                fitness_level: FitnessLevel::ModeratelyActive,
+                sleep_goal: None,
+                water_goal: None,
+                dietary_restrictions: Vec::new(),
+                health_goals: Vec::new(),
+                medical_conditions: Vec::new(),
+                stress_level: None,
+            },
+            recent_wellbeing: entries.clone(),
+            recent_nutrition: vec![nutrition(day(14), 1850.0), nutrition(day(15), 2100.0)],
+            current_time: "09:00".to_string(),
+            current_season: "Осень".to_string(),
+            weather_context: None,
+        };
+        let meta = AiResponseMeta { provider: "gemini".to_string(), degraded: false, generation: None };
+
+        let provenance = health_provenance(&context, "health_caring", &meta, "gemini-1.5-flash");
+        assert_eq!((provenance.template.as_str(), provenance.provider.as_str(), provenance.model.as_str()), ("health_caring", "gemini", "gemini-1.5-flash"));
+        assert_eq!(
+            provenance.wellbeing_entries.iter().map(|entry| (entry.id, entry.mood_score)).collect::<Vec<_>>(),
+            entries.iter().map(|entry| (entry.id, entry.mood_score)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            provenance.diary_days.iter().map(|day| (day.date.to_string(), day.calories)).collect::<Vec<_>>(),
+            vec![("2026-10-14".to_string(), 1850.0), ("2026-10-15".to_string(), 2100.0)]
+        );
+        assert!(provenance.fridge_items.is_empty() && provenance.waste_entries.is_empty());
+        // Только ссылки и подписи: заметки из записей в след не попадают
+        assert!(!serde_json::to_string(&provenance).unwrap().contains("Личная заметка"));
+    }
+
+    async fn insert_waste(pool: &PgPool, user_id: Uuid, name: &str, waste_date: DateTime<Utc>) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO food_waste (user_id, name, wasted_quantity, unit, category, waste_reason, waste_date) \
+             VALUES ($1, $2, 0.5, 'l', 'other', 'expired', $3) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(waste_date)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn set_expiry(pool: &PgPool, item_id: Uuid, expiry_date: DateTime<Utc>) {
+        sqlx::query("UPDATE fridge_items SET expiry_date = $2 WHERE id = $1")
+            .bind(item_id)
+            .bind(expiry_date)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn fridge_trace_matches_the_fixture_and_survives_deletion(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        let stranger = insert_user(&pool, "Борис").await;
+        let now = Utc::now();
+
+        let milk = insert_fridge_item(&pool, user, "Молоко", None, now - Duration::days(3)).await;
+        let cheese = insert_fridge_item(&pool, user, "Сыр", None, now - Duration::days(3)).await;
+        let yogurt = insert_fridge_item(&pool, user, "Йогурт", None, now - Duration::days(10)).await;
+        set_expiry(&pool, milk.id, now + Duration::days(2)).await;
+        set_expiry(&pool, cheese.id, now + Duration::days(30)).await;
+        set_expiry(&pool, yogurt.id, now - Duration::days(1)).await;
+        insert_fridge_item(&pool, stranger, "Чужой кефир", None, now).await;
+        let recent_waste = insert_waste(&pool, user, "Сметана", now - Duration::days(2)).await;
+        insert_waste(&pool, user, "Старый творог", now - Duration::days(20)).await;
+
+        let ai = AiService::new(AiProvider::Mock, &AiResilienceConfig::default()).with_adapter(Arc::new(StubAdapter::new("stub", vec![Some("Все хорошо, используйте молоко в первую очередь.")])));
+        let mut report = ai
+            .create_fridge_report(user, ReportSectionWeights::default(), Glossary::default(), &FridgeService::new(pool.clone()))
+            .await
+            .unwrap();
+        let provenance = report.provenance.take().unwrap();
+
+        assert_eq!((provenance.provider.as_str(), provenance.model.as_str(), provenance.degraded), ("stub", "stub-model", false));
+        let mut traced: Vec<(Uuid, &str, bool)> = provenance
+            .fridge_items
+            .iter()
+            .map(|item| (item.id, item.name.as_str(), item.expiring))
+            .collect();
+        traced.sort();
+        let mut expected = vec![(milk.id, "Молоко", true), (cheese.id, "Сыр", false), (yogurt.id, "Йогурт", true)];
+        expected.sort();
+        assert_eq!(traced, expected);
+        assert_eq!(provenance.waste_entries.iter().map(|waste| waste.id).collect::<Vec<_>>(), vec![recent_waste]);
+        assert!(provenance.diary_days.is_empty() && provenance.wellbeing_entries.is_empty());
+
+        let history = AiHistoryService::new(pool.clone());
+        let response_id = history.record(user, AiResponseKind::FridgeAnalysis, &provenance, &report.summary).await.unwrap();
+
+        // Удаленный продукт остается в следе под своим названием
+        sqlx::query("DELETE FROM fridge_items WHERE id = $1").bind(milk.id).execute(&pool).await.unwrap();
+        let stored = history.provenance(user, response_id).await.unwrap();
+        assert_eq!(stored.kind, "fridge_analysis");
+        let stored: AiProvenance = serde_json::from_value(stored.provenance).unwrap();
+        assert_eq!(stored, provenance);
+        assert!(stored.fridge_items.iter().any(|item| item.id == milk.id && item.name == "Молоко"));
+
+        assert!(matches!(history.provenance(stranger, response_id).await, Err(AppError::NotFound(_))));
+
+        let export = history.export(user).await.unwrap();
+        assert_eq!(export.total, 1);
+        assert_eq!(export.responses[0].id, response_id);
+        assert_eq!(export.responses[0].response, serde_json::json!(report.summary));
+        assert_eq!(history.export(stranger).await.unwrap().total, 0);
+    }
+}
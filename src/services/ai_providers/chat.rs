@@ -0,0 +1,203 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{Completion, SYSTEM_PROMPT};
+use crate::services::ai_circuit::transport_error;
+use crate::services::ai_costs::TokenUsage;
+use crate::services::ai_generation::GenerationSettings;
+use crate::utils::errors::AppError;
+
+// Формат chat/completions, общий для OpenAI-совместимых API (OpenAI, Groq)
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+/// Блок `usage` ответа
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct ChatUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<ChatUsage> for TokenUsage {
+    fn from(usage: ChatUsage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            estimated: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+/// Куда и от чьего имени отправляется запрос
+pub(super) struct ChatEndpoint<'a> {
+    /// Название провайдера в сообщениях об ошибках
+    pub provider: &'static str,
+    pub url: &'a str,
+    pub model: &'static str,
+    pub api_key: &'a str,
+}
+
+/// Тело запроса chat/completions: системный промпт, запрос пользователя и параметры генерации
+pub(super) fn chat_request<'a>(model: &'a str, prompt: &str, settings: &GenerationSettings) -> ChatRequest<'a> {
+    ChatRequest {
+        model,
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: SYSTEM_PROMPT.to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            },
+        ],
+        max_tokens: Some(settings.max_tokens),
+        temperature: Some(settings.temperature),
+        top_p: Some(settings.top_p),
+    }
+}
+
+pub(super) async fn complete(
+    client: &Client,
+    endpoint: ChatEndpoint<'_>,
+    prompt: &str,
+    settings: &GenerationSettings,
+) -> Result<Completion, AppError> {
+    let request = chat_request(endpoint.model, prompt, settings);
+
+    let response = client
+        .post(endpoint.url)
+        .header("Authorization", format!("Bearer {}", endpoint.api_key))
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| transport_error(endpoint.provider, "request", e))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::ExternalService(format!(
+            "{} API returned status: {}",
+            endpoint.provider,
+            response.status()
+        )));
+    }
+
+    let chat_response: ChatResponse = response
+        .json()
+        .await
+        .map_err(|e| transport_error(endpoint.provider, "response", e))?;
+
+    let usage = chat_response.usage;
+    let text = chat_response
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message.content)
+        .ok_or_else(|| AppError::ExternalService(format!("No response from {}", endpoint.provider)))?;
+
+    // Без блока usage — оценка по длине текста
+    let usage = usage.map_or_else(|| TokenUsage::estimate(prompt, &text), TokenUsage::from);
+    Ok(Completion { text, usage })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    use super::*;
+    use crate::test_support::provider_server;
+
+    /// Адрес chat/completions локального сервера, который ждет `delay` и отвечает `status` с телом `body`
+    async fn serve(delay: Duration, status: StatusCode, body: &'static str) -> String {
+        let (base_url, _) = provider_server(delay, status, body).await;
+        format!("{}/v1/chat/completions", base_url)
+    }
+
+    fn client() -> Client {
+        Client::builder()
+            .connect_timeout(Duration::from_millis(200))
+            .timeout(Duration::from_millis(300))
+            .build()
+            .unwrap()
+    }
+
+    fn endpoint(url: &str) -> ChatEndpoint<'_> {
+        ChatEndpoint { provider: "Groq", url, model: "llama-3.1-8b-instant", api_key: "key" }
+    }
+
+    fn settings() -> GenerationSettings {
+        GenerationSettings { temperature: 0.5, max_tokens: 100, top_p: 0.9 }
+    }
+
+    const REPLY: &str = r#"{"choices":[{"message":{"role":"assistant","content":"Готово"}}],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#;
+
+    #[tokio::test]
+    async fn slow_response_trips_the_request_timeout() {
+        let url = serve(Duration::from_secs(2), StatusCode::OK, REPLY).await;
+
+        let started = std::time::Instant::now();
+        let error = complete(&client(), endpoint(&url), "Привет", &settings()).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(matches!(error, AppError::ExternalTimeout(ref message) if message.contains("Groq API request timed out")));
+        assert_eq!(error.into_response().status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn reply_within_the_timeout_is_parsed() {
+        let url = serve(Duration::from_millis(20), StatusCode::OK, REPLY).await;
+        let completion = complete(&client(), endpoint(&url), "Привет", &settings()).await.unwrap();
+        assert_eq!(completion.text, "Готово");
+        assert_eq!((completion.usage.prompt_tokens, completion.usage.completion_tokens), (12, 3));
+
+        let failing = serve(Duration::ZERO, StatusCode::INTERNAL_SERVER_ERROR, "{}").await;
+        let error = complete(&client(), endpoint(&failing), "Привет", &settings()).await.unwrap_err();
+        assert!(matches!(error, AppError::ExternalService(ref message) if message.contains("500")));
+    }
+
+    #[tokio::test]
+    async fn usage_block_is_taken_from_the_reply_or_estimated() {
+        let cases = [
+            (REPLY, (12, 3, false)),
+            // Поле без значения — ноль, а не оценка
+            (r#"{"choices":[{"message":{"role":"assistant","content":"Готово"}}],"usage":{"prompt_tokens":40}}"#, (40, 0, false)),
+            // Нет блока usage — оценка по длине промпта и ответа
+            (r#"{"choices":[{"message":{"role":"assistant","content":"Гречка с грибами"}}]}"#, (2, 4, true)),
+            (r#"{"choices":[{"message":{"role":"assistant","content":"Готово"}}],"usage":null}"#, (2, 2, true)),
+        ];
+
+        for (body, (prompt_tokens, completion_tokens, estimated)) in cases {
+            let url = serve(Duration::ZERO, StatusCode::OK, body).await;
+            let usage = complete(&client(), endpoint(&url), "Привет", &settings()).await.unwrap().usage;
+            assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.estimated), (prompt_tokens, completion_tokens, estimated), "{}", body);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{Capabilities, Completion, ImageInput, ProviderAdapter, SYSTEM_PROMPT};
+use crate::services::ai_circuit::transport_error;
+use crate::services::ai_costs::TokenUsage;
+use crate::services::ai_generation::GenerationSettings;
+use crate::utils::errors::AppError;
+
+const API_BASE: &str = "https://generativelanguage.googleapis.com";
+const MODEL: &str = "gemini-1.5-flash";
+
+#[derive(Debug, Serialize)]
+struct GeminiContent {
+    parts: Vec<GeminiPart>,
+}
+
+/// Часть сообщения: текст или встроенное изображение
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum GeminiPart {
+    Text { text: String },
+    InlineData { inline_data: GeminiInlineData },
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiInlineData {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(rename = "generationConfig")]
+    generation_config: Option<GeminiGenerationConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiGenerationConfig {
+    #[serde(rename = "maxOutputTokens")]
+    max_output_tokens: Option<u32>,
+    temperature: Option<f32>,
+    #[serde(rename = "topP")]
+    top_p: Option<f32>,
+}
+
+impl From<&GenerationSettings> for GeminiGenerationConfig {
+    fn from(settings: &GenerationSettings) -> Self {
+        Self {
+            max_output_tokens: Some(settings.max_tokens),
+            temperature: Some(settings.temperature),
+            top_p: Some(settings.top_p),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiCandidate {
+    content: GeminiResponseContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponseContent {
+    parts: Vec<GeminiResponsePart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponsePart {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Debug)]
+pub struct GeminiAdapter {
+    client: Client,
+    api_key: String,
+    /// Схема и хост API; в тестах — локальный сервер
+    base_url: String,
+}
+
+impl GeminiAdapter {
+    pub fn new(client: Client, api_key: String) -> Self {
+        Self { client, api_key, base_url: API_BASE.to_string() }
+    }
+
+    #[cfg(test)]
+    fn with_base_url(self, base_url: String) -> Self {
+        Self { base_url, ..self }
+    }
+
+    /// Отправляет сообщение из `parts`; `prompt_text` — текстовая часть, по ней оцениваются токены
+    async fn send(&self, parts: Vec<GeminiPart>, prompt_text: &str, settings: &GenerationSettings) -> Result<Completion, AppError> {
+        let request = GeminiRequest {
+            contents: vec![GeminiContent { parts }],
+            generation_config: Some(GeminiGenerationConfig::from(settings)),
+        };
+
+        let url = format!(
+            "{}/v1beta/models/{}:generateContent?key={}",
+            self.base_url, MODEL, self.api_key
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| transport_error("Gemini", "request", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::ExternalService(format!(
+                "Gemini API returned status: {}, error: {}",
+                status,
+                error_text
+            )));
+        }
+
+        let gemini_response: GeminiResponse = response
+            .json()
+            .await
+            .map_err(|e| transport_error("Gemini", "response", e))?;
+
+        let text = gemini_response
+            .candidates
+            .into_iter()
+            .next()
+            .and_then(|candidate| candidate.content.parts.into_iter().next())
+            .map(|part| part.text)
+            .ok_or_else(|| AppError::ExternalService("No response from Gemini".to_string()))?;
+
+        // Gemini считает токены иначе, чем OpenAI; для учета расходов достаточно оценки по длине
+        // (изображение в оценку не входит)
+        let usage = TokenUsage::estimate(prompt_text, &text);
+        Ok(Completion { text, usage })
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for GeminiAdapter {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn model(&self) -> &'static str {
+        MODEL
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            stream: true,
+            embed: true,
+            vision: true,
+        }
+    }
+
+    async fn generate(&self, prompt: &str, settings: &GenerationSettings) -> Result<Completion, AppError> {
+        // У Gemini нет отдельной системной роли: инструкция идет в начале текста
+        let full_prompt = format!("{} {}", SYSTEM_PROMPT, prompt);
+        let parts = vec![GeminiPart::Text { text: full_prompt.clone() }];
+        self.send(parts, &full_prompt, settings).await
+    }
+
+    async fn generate_with_image(
+        &self,
+        prompt: &str,
+        image: &ImageInput,
+        settings: &GenerationSettings,
+    ) -> Result<Completion, AppError> {
+        let parts = vec![
+            GeminiPart::InlineData {
+                inline_data: GeminiInlineData {
+                    mime_type: image.mime_type.clone(),
+                    data: image.data_base64.clone(),
+                },
+            },
+            GeminiPart::Text { text: prompt.to_string() },
+        ];
+        self.send(parts, prompt, settings).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::http::{header, Method, StatusCode};
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{provider_server, RecordedRequest};
+
+    #[test]
+    fn request_carries_generation_settings() {
+        let settings = GenerationSettings { temperature: 0.3, max_tokens: 1500, top_p: 0.9 };
+        let request = GeminiRequest {
+            contents: vec![GeminiContent { parts: vec![GeminiPart::Text { text: "Отчет по холодильнику".to_string() }] }],
+            generation_config: Some(GeminiGenerationConfig::from(&settings)),
+        };
+        let body = serde_json::to_value(request).unwrap();
+
+        let config = &body["generationConfig"];
+        assert_eq!(config["maxOutputTokens"], 1500);
+        assert_eq!(config["temperature"].as_f64().unwrap() as f32, 0.3);
+        assert_eq!(config["topP"].as_f64().unwrap() as f32, 0.9);
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Отчет по холодильнику");
+    }
+
+    const REPLY: &str = r#"{"candidates":[{"content":{"parts":[{"text":"Борщ со сметаной"}],"role":"model"},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":30,"candidatesTokenCount":5}}"#;
+
+    fn settings() -> GenerationSettings {
+        GenerationSettings { temperature: 0.3, max_tokens: 1500, top_p: 0.9 }
+    }
+
+    async fn adapter(status: StatusCode, body: &'static str) -> (GeminiAdapter, Arc<Mutex<Vec<RecordedRequest>>>) {
+        let (base_url, received) = provider_server(Duration::ZERO, status, body).await;
+        (GeminiAdapter::new(Client::new(), "AIzaTest".to_string()).with_base_url(base_url), received)
+    }
+
+    #[tokio::test]
+    async fn generate_posts_the_prompt_with_the_key_in_the_query() {
+        let (adapter, received) = adapter(StatusCode::OK, REPLY).await;
+
+        let completion = adapter.generate("Что приготовить?", &settings()).await.unwrap();
+        assert_eq!(completion.text, "Борщ со сметаной");
+        // usageMetadata не разбирается: токены оцениваются по длине текста вместе с системной инструкцией
+        let full_prompt = format!("{} Что приготовить?", SYSTEM_PROMPT);
+        assert_eq!(
+            (completion.usage.prompt_tokens, completion.usage.completion_tokens, completion.usage.estimated),
+            (full_prompt.chars().count().div_ceil(4) as u32, 4, true)
+        );
+
+        let requests = received.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.uri, "/v1beta/models/gemini-1.5-flash:generateContent?key=AIzaTest");
+        assert!(request.headers.get(header::AUTHORIZATION).is_none());
+        assert_eq!(request.headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(request.body["contents"], json!([{ "parts": [{ "text": full_prompt }] }]));
+        assert_eq!(request.body["generationConfig"]["maxOutputTokens"], 1500);
+    }
+
+    #[tokio::test]
+    async fn image_goes_first_and_the_prompt_has_no_system_instruction() {
+        let (adapter, received) = adapter(StatusCode::OK, REPLY).await;
+        let image = ImageInput { mime_type: "image/png".to_string(), data_base64: "iVBORw0KGgo=".to_string() };
+
+        assert!(adapter.capabilities().vision);
+        let completion = adapter.generate_with_image("Распознай чек", &image, &settings()).await.unwrap();
+        assert_eq!(completion.usage.prompt_tokens, 4, "the image is not counted");
+
+        let requests = received.lock().unwrap();
+        assert_eq!(requests[0].body["contents"][0]["parts"], json!([
+            { "inline_data": { "mime_type": "image/png", "data": "iVBORw0KGgo=" } },
+            { "text": "Распознай чек" }
+        ]));
+    }
+
+    #[tokio::test]
+    async fn failed_and_empty_replies_are_provider_errors() {
+        let cases: [(StatusCode, &'static str, &str); 4] = [
+            (
+                StatusCode::BAD_REQUEST,
+                r#"{"error":{"message":"API key not valid"}}"#,
+                r#"Gemini API returned status: 400 Bad Request, error: {"error":{"message":"API key not valid"}}"#,
+            ),
+            (StatusCode::OK, r#"{"candidates":[]}"#, "No response from Gemini"),
+            (StatusCode::OK, r#"{"candidates":[{"content":{"parts":[]}}]}"#, "No response from Gemini"),
+            (StatusCode::OK, "not json", "Gemini API response failed"),
+        ];
+
+        for (status, body, message) in cases {
+            let (adapter, _) = adapter(status, body).await;
+            match adapter.generate("Привет", &settings()).await {
+                Err(AppError::ExternalService(error)) => assert!(error.starts_with(message), "{}", error),
+                other => panic!("expected ExternalService({}), got {:?}", message, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::services::ai_costs::TokenUsage;
+use crate::services::ai_generation::GenerationSettings;
+use crate::utils::errors::AppError;
+
+mod chat;
+mod gemini;
+mod groq;
+mod openai;
+
+pub use gemini::GeminiAdapter;
+pub use groq::GroqAdapter;
+pub use openai::OpenAiAdapter;
+
+// =============================================================================
+// АДАПТЕРЫ ПРОВАЙДЕРОВ ИИ
+// =============================================================================
+
+/// Системная инструкция, с которой вызываются все провайдеры
+const SYSTEM_PROMPT: &str = "You are a helpful cooking assistant. Provide practical, easy-to-follow recipes.";
+
+/// Текст ответа провайдера и израсходованные токены
+#[derive(Debug)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Изображение для модели с `vision`: данные уже в base64, как их принимают провайдеры
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    pub mime_type: String,
+    pub data_base64: String,
+}
+
+/// Что поддерживает модель адаптера помимо генерации текста.
+/// Потоковую генерацию и эмбеддинги адаптеры пока не реализуют; изображения — `generate_with_image`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub stream: bool,
+    pub embed: bool,
+    pub vision: bool,
+}
+
+impl Capabilities {
+    pub fn names(&self) -> Vec<&'static str> {
+        [("stream", self.stream), ("embed", self.embed), ("vision", self.vision)]
+            .into_iter()
+            .filter_map(|(name, supported)| supported.then_some(name))
+            .collect()
+    }
+}
+
+/// Один провайдер: формат запроса, адрес, заголовки и разбор ответа.
+/// Бюджет, размыкатель и учет расходов остаются в `AiService`.
+#[async_trait]
+pub trait ProviderAdapter: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Модель, которую вызывает `generate`
+    fn model(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    async fn generate(&self, prompt: &str, settings: &GenerationSettings) -> Result<Completion, AppError>;
+
+    /// Генерация по тексту и изображению; реализуют адаптеры с `capabilities().vision`
+    async fn generate_with_image(
+        &self,
+        _prompt: &str,
+        _image: &ImageInput,
+        _settings: &GenerationSettings,
+    ) -> Result<Completion, AppError> {
+        Err(AppError::NotImplemented(format!("{} adapter cannot read images", self.name())))
+    }
+}
+
+/// Провайдер, выбранный по ключам окружения
+#[derive(Debug, Clone)]
+pub enum AiProvider {
+    OpenAI(String),
+    Groq(String),
+    Gemini(String),
+    Mock,
+}
+
+impl AiProvider {
+    /// Адаптер провайдера; у Mock-провайдера адаптера нет, ответы берутся из фикстур
+    pub fn adapter(self, client: Client) -> Option<Box<dyn ProviderAdapter>> {
+        match self {
+            AiProvider::OpenAI(api_key) => Some(Box::new(OpenAiAdapter::new(client, api_key))),
+            AiProvider::Groq(api_key) => Some(Box::new(GroqAdapter::new(client, api_key))),
+            AiProvider::Gemini(api_key) => Some(Box::new(GeminiAdapter::new(client, api_key))),
+            AiProvider::Mock => None,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use super::chat::{self, ChatEndpoint};
+use super::{Capabilities, Completion, ProviderAdapter};
+use crate::services::ai_generation::GenerationSettings;
+use crate::utils::errors::AppError;
+
+const API_BASE: &str = "https://api.openai.com";
+const PATH: &str = "/v1/chat/completions";
+const MODEL: &str = "gpt-3.5-turbo";
+
+#[derive(Debug)]
+pub struct OpenAiAdapter {
+    client: Client,
+    api_key: String,
+    /// Схема и хост API; в тестах — локальный сервер
+    base_url: String,
+}
+
+impl OpenAiAdapter {
+    pub fn new(client: Client, api_key: String) -> Self {
+        Self { client, api_key, base_url: API_BASE.to_string() }
+    }
+
+    #[cfg(test)]
+    fn with_base_url(self, base_url: String) -> Self {
+        Self { base_url, ..self }
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for OpenAiAdapter {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &'static str {
+        MODEL
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            stream: true,
+            embed: true,
+            vision: false,
+        }
+    }
+
+    async fn generate(&self, prompt: &str, settings: &GenerationSettings) -> Result<Completion, AppError> {
+        let url = format!("{}{}", self.base_url, PATH);
+        let endpoint = ChatEndpoint {
+            provider: "OpenAI",
+            url: &url,
+            model: MODEL,
+            api_key: &self.api_key,
+        };
+        chat::complete(&self.client, endpoint, prompt, settings).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::http::{header, Method, StatusCode};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        services::ai_providers::{ImageInput, SYSTEM_PROMPT},
+        test_support::{provider_server, RecordedRequest},
+    };
+
+    #[test]
+    fn request_carries_generation_settings() {
+        let settings = GenerationSettings { temperature: 1.0, max_tokens: 1500, top_p: 0.95 };
+        let body = serde_json::to_value(chat::chat_request(MODEL, "Рецепт борща", &settings)).unwrap();
+
+        assert_eq!(body["model"], MODEL);
+        assert_eq!(body["max_tokens"], 1500);
+        assert_eq!(body["temperature"].as_f64().unwrap() as f32, 1.0);
+        assert_eq!(body["top_p"].as_f64().unwrap() as f32, 0.95);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Рецепт борща");
+    }
+
+    const REPLY: &str = r#"{"id":"chatcmpl-1","object":"chat.completion","choices":[{"index":0,"message":{"role":"assistant","content":"Борщ"},"finish_reason":"stop"}],"usage":{"prompt_tokens":40,"completion_tokens":2,"total_tokens":42}}"#;
+
+    async fn adapter(status: StatusCode, body: &'static str) -> (OpenAiAdapter, Arc<Mutex<Vec<RecordedRequest>>>) {
+        let (base_url, received) = provider_server(Duration::ZERO, status, body).await;
+        (OpenAiAdapter::new(Client::new(), "sk-test".to_string()).with_base_url(base_url), received)
+    }
+
+    #[tokio::test]
+    async fn generate_posts_to_the_chat_endpoint_and_parses_the_reply() {
+        let (adapter, received) = adapter(StatusCode::OK, REPLY).await;
+        let settings = GenerationSettings { temperature: 0.7, max_tokens: 800, top_p: 0.9 };
+
+        let completion = adapter.generate("Что приготовить?", &settings).await.unwrap();
+        assert_eq!(completion.text, "Борщ");
+        assert_eq!(
+            (completion.usage.prompt_tokens, completion.usage.completion_tokens, completion.usage.estimated),
+            (40, 2, false)
+        );
+
+        let requests = received.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.uri, "/v1/chat/completions");
+        assert_eq!(request.headers[header::AUTHORIZATION], "Bearer sk-test");
+        assert_eq!(request.headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(request.body["model"], MODEL);
+        assert_eq!(request.body["messages"], json!([
+            { "role": "system", "content": SYSTEM_PROMPT },
+            { "role": "user", "content": "Что приготовить?" }
+        ]));
+        assert_eq!(request.body["max_tokens"], 800);
+    }
+
+    #[tokio::test]
+    async fn failed_and_empty_replies_are_provider_errors() {
+        let cases: [(StatusCode, &'static str, &str); 3] = [
+            (StatusCode::TOO_MANY_REQUESTS, r#"{"error":{"message":"Rate limit"}}"#, "OpenAI API returned status: 429 Too Many Requests"),
+            (StatusCode::OK, r#"{"choices":[]}"#, "No response from OpenAI"),
+            (StatusCode::OK, "<html>bad gateway</html>", "OpenAI API response failed"),
+        ];
+        let settings = GenerationSettings { temperature: 0.7, max_tokens: 800, top_p: 0.9 };
+
+        for (status, body, message) in cases {
+            let (adapter, _) = adapter(status, body).await;
+            match adapter.generate("Привет", &settings).await {
+                Err(AppError::ExternalService(error)) => assert!(error.starts_with(message), "{}", error),
+                other => panic!("expected ExternalService({}), got {:?}", message, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn images_are_not_supported() {
+        let (adapter, received) = adapter(StatusCode::OK, REPLY).await;
+        let image = ImageInput { mime_type: "image/jpeg".to_string(), data_base64: "AAAA".to_string() };
+        let settings = GenerationSettings { temperature: 0.7, max_tokens: 800, top_p: 0.9 };
+
+        assert!(!adapter.capabilities().vision);
+        let result = adapter.generate_with_image("Чек", &image, &settings).await;
+        assert!(matches!(result, Err(AppError::NotImplemented(_))));
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use sqlx::types::Json;
+
+use crate::{
+    db::DbPool,
+    models::api_token::{ApiToken, ApiTokenInfo, CreateApiToken, API_TOKEN_PREFIX},
+    services::auth::{AuthService, Claims},
+    utils::errors::AppError,
+};
+
+/// Длина случайной части секрета
+const SECRET_LENGTH: usize = 40;
+
+/// Сколько символов секрета показываем в списке токенов
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+const MAX_TOKENS_PER_USER: i64 = 20;
+
+pub fn hash_token(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+pub struct ApiTokenService {
+    pool: DbPool,
+}
+
+impl ApiTokenService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Создает токен и возвращает секрет — он показывается пользователю только один раз
+    pub async fn create_token(&self, token: CreateApiToken) -> Result<(ApiTokenInfo, String), AppError> {
+        if token.scopes.is_empty() {
+            return Err(AppError::BadRequest("At least one scope is required".to_string()));
+        }
+        if token.expires_at.map(|expires_at| expires_at <= Utc::now()).unwrap_or(false) {
+            return Err(AppError::BadRequest("Expiry must be in the future".to_string()));
+        }
+
+        let (active,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM api_tokens WHERE user_id = $1 AND revoked_at IS NULL"
+        )
+        .bind(token.user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if active >= MAX_TOKENS_PER_USER {
+            return Err(AppError::BadRequest(format!(
+                "Token limit reached ({}), revoke unused tokens first",
+                MAX_TOKENS_PER_USER
+            )));
+        }
+
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SECRET_LENGTH)
+            .map(char::from)
+            .collect();
+        let secret = format!("{}{}", API_TOKEN_PREFIX, random);
+
+        let mut scopes = Vec::with_capacity(token.scopes.len());
+        for scope in token.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+
+        let stored = sqlx::query_as::<_, ApiToken>(
+            r#"
+            INSERT INTO api_tokens (id, user_id, name, token_prefix, token_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(token.user_id)
+        .bind(&token.name)
+        .bind(&secret[..DISPLAY_PREFIX_LENGTH])
+        .bind(hash_token(&secret))
+        .bind(Json(&scopes))
+        .bind(token.expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((stored.into(), secret))
+    }
+
+    pub async fn list_tokens(&self, user_id: Uuid) -> Result<Vec<ApiTokenInfo>, AppError> {
+        let tokens = sqlx::query_as::<_, ApiToken>(
+            "SELECT * FROM api_tokens WHERE user_id = $1 AND revoked_at IS NULL ORDER BY created_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tokens.into_iter().map(ApiTokenInfo::from).collect())
+    }
+
+    pub async fn revoke_token(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("API token not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Окончательно удаляет токены, отозванные раньше `revoked_before`; с `dry_run` только считает их
+    pub async fn purge_revoked(&self, revoked_before: DateTime<Utc>, dry_run: bool) -> Result<u64, AppError> {
+        if dry_run {
+            let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM api_tokens WHERE revoked_at < $1")
+                .bind(revoked_before)
+                .fetch_one(&self.pool)
+                .await?;
+            return Ok(count as u64);
+        }
+
+        let result = sqlx::query("DELETE FROM api_tokens WHERE revoked_at < $1")
+            .bind(revoked_before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Проверяет секрет из заголовка Authorization и возвращает Claims с ограниченными правами
+    pub async fn authenticate(&self, secret: &str) -> Result<Claims, AppError> {
+        let token = sqlx::query_as::<_, ApiToken>(
+            "SELECT * FROM api_tokens WHERE token_hash = $1"
+        )
+        .bind(hash_token(secret))
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid API token".to_string()))?;
+
+        if token.revoked_at.is_some() {
+            return Err(AppError::Unauthorized("API token revoked".to_string()));
+        }
+
+        let now = Utc::now();
+        if token.expires_at.map(|expires_at| expires_at <= now).unwrap_or(false) {
+            return Err(AppError::Unauthorized("API token expired".to_string()));
+        }
+
+        sqlx::query("UPDATE api_tokens SET last_used_at = NOW() WHERE id = $1")
+            .bind(token.id)
+            .execute(&self.pool)
+            .await?;
+
+        let user = AuthService::new(self.pool.clone()).get_user_by_id(token.user_id).await
+            .map_err(|_| AppError::Unauthorized("Invalid API token".to_string()))?;
+
+        Ok(Claims {
+            sub: user.id,
+            email: user.email,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            role: user.role,
+            exp: token.expires_at.map(|expires_at| expires_at.timestamp() as usize).unwrap_or(usize::MAX),
+            iat: now.timestamp() as usize,
+            scopes: Some(token.scopes),
+            sid: None,
+        })
+    }
+}
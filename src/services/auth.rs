@@ -1,13 +1,16 @@
 use uuid::Uuid;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use bcrypt::{hash, verify, DEFAULT_COST};
 
 use crate::{
     db::DbPool,
-    models::user::{User, CreateUser, UserSession, CreateUserSession, UserRole},
-    utils::errors::AppError,
+    models::{
+        api_token::TokenScope,
+        user::{User, CreateUser, UserSession, CreateUserSession, UserRole},
+    },
+    utils::{device::DeviceInfo, errors::{AppError, ErrorCode}},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,12 +22,47 @@ pub struct Claims {
     pub role: UserRole,
     pub exp: usize,
     pub iat: usize,
+    /// Права персонального токена; у JWT-сессии `None` — полный доступ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<TokenScope>>,
+    /// Сессия (строка user_sessions), в которой выпущен JWT; у токенов API и старых JWT нет
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+}
+
+impl Claims {
+    pub fn is_api_token(&self) -> bool {
+        self.scopes.is_some()
+    }
+
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.as_ref().map(|scopes| scopes.contains(&scope)).unwrap_or(true)
+    }
+
+    pub fn require_scope(&self, scope: TokenScope) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!("API token lacks required scope {}", scope.as_str())))
+        }
+    }
+}
+
+/// Состояние аккаунта, проверяемое на каждом запросе с токеном
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct AccountStatus {
+    suspended_at: Option<DateTime<Utc>>,
+    suspension_reason: Option<String>,
+    sessions_invalidated_at: Option<DateTime<Utc>>,
+    /// Сессия токена не отозвана (или токен выпущен без сессии)
+    session_active: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct AuthTokens {
     pub access_token: String,
     pub refresh_token: String,
+    pub session_id: Uuid,
 }
 
 pub struct AuthService {
@@ -40,7 +78,17 @@ impl AuthService {
         Self { pool, jwt_secret }
     }
 
-    pub async fn register(&self, create_user: CreateUser) -> Result<(User, AuthTokens), AppError> {
+    pub async fn register(&self, create_user: CreateUser, device: DeviceInfo) -> Result<(User, AuthTokens), AppError> {
+        let user = self.create_user(create_user).await?;
+
+        // Generate tokens
+        let tokens = self.generate_tokens(&user, device).await?;
+
+        Ok((user, tokens))
+    }
+
+    /// Создает пользователя без входа в аккаунт (регистрация и `it-cook create-admin`)
+    pub async fn create_user(&self, create_user: CreateUser) -> Result<User, AppError> {
         // Check if user already exists
         let existing_user = sqlx::query_as::<_, User>(
             "SELECT * FROM users WHERE email = $1"
@@ -50,7 +98,7 @@ impl AuthService {
         .await?;
 
         if existing_user.is_some() {
-            return Err(AppError::BadRequest("Email already registered".to_string()));
+            return Err(AppError::BadRequest("Email already registered".to_string()).with_code(ErrorCode::Duplicate));
         }
 
         // Hash password
@@ -80,13 +128,10 @@ impl AuthService {
         .fetch_one(&self.pool)
         .await?;
 
-        // Generate tokens
-        let tokens = self.generate_tokens(&user).await?;
-
-        Ok((user, tokens))
+        Ok(user)
     }
 
-    pub async fn login(&self, email: &str, password: &str) -> Result<(User, AuthTokens), AppError> {
+    pub async fn login(&self, email: &str, password: &str, device: DeviceInfo) -> Result<(User, AuthTokens), AppError> {
         // Find user by email
         let user = sqlx::query_as::<_, User>(
             "SELECT * FROM users WHERE email = $1"
@@ -104,6 +149,8 @@ impl AuthService {
             return Err(AppError::Unauthorized("Invalid credentials".to_string()));
         }
 
+        self.ensure_not_suspended(user.id).await?;
+
         // Update last login
         sqlx::query("UPDATE users SET last_login_at = NOW() WHERE id = $1")
             .bind(user.id)
@@ -111,11 +158,13 @@ impl AuthService {
             .await?;
 
         // Generate tokens
-        let tokens = self.generate_tokens(&user).await?;
+        let tokens = self.generate_tokens(&user, device).await?;
 
         Ok((user, tokens))
     }
 
+    /// Новая пара токенов в той же сессии: refresh-токен заменяется на месте, id сессии не меняется.
+    /// Отозванной сессии больше нет в таблице, поэтому ее токен не обновляется.
     pub async fn refresh_token(&self, refresh_token: &str) -> Result<AuthTokens, AppError> {
         // Find session by refresh token
         let session = sqlx::query_as::<_, UserSession>(
@@ -134,16 +183,45 @@ impl AuthService {
         .fetch_one(&self.pool)
         .await?;
 
-        // Generate new tokens
-        let tokens = self.generate_tokens(&user).await?;
+        self.ensure_not_suspended(user.id).await?;
 
-        // Delete old session
-        sqlx::query("DELETE FROM user_sessions WHERE id = $1")
-            .bind(session.id)
-            .execute(&self.pool)
-            .await?;
+        let now = Utc::now();
+        let new_refresh_token = Uuid::new_v4().to_string();
 
-        Ok(tokens)
+        // Условие на старый токен: из двух одновременных обновлений проходит одно
+        let rotated = sqlx::query(
+            r#"
+            UPDATE user_sessions SET refresh_token = $3, expires_at = $4, last_used_at = NOW()
+            WHERE id = $1 AND refresh_token = $2
+            "#
+        )
+        .bind(session.id)
+        .bind(refresh_token)
+        .bind(&new_refresh_token)
+        .bind(now + Duration::days(30))
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if rotated == 0 {
+            return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+        }
+
+        Ok(AuthTokens {
+            access_token: self.access_token(&user, session.id, now)?,
+            refresh_token: new_refresh_token,
+            session_id: session.id,
+        })
+    }
+
+    pub async fn get_user_by_id(&self, user_id: Uuid) -> Result<User, AppError> {
+        sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
     }
 
     pub async fn logout(&self, user_id: Uuid) -> Result<(), AppError> {
@@ -155,10 +233,49 @@ impl AuthService {
         Ok(())
     }
 
-    async fn generate_tokens(&self, user: &User) -> Result<AuthTokens, AppError> {
+    /// Новая сессия с данными устройства и пара токенов для нее
+    pub(crate) async fn generate_tokens(&self, user: &User, device: DeviceInfo) -> Result<AuthTokens, AppError> {
         let now = Utc::now();
+        let session_id = Uuid::new_v4();
+
+        // Generate refresh token
+        let refresh_token = Uuid::new_v4().to_string();
+
+        // Store refresh token in database
+        let session = CreateUserSession {
+            user_id: user.id,
+            refresh_token: refresh_token.clone(),
+            expires_at: now + Duration::days(30),
+            device,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_sessions (id, user_id, refresh_token, expires_at, user_agent, browser, os, ip_address, location)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#
+        )
+        .bind(session_id)
+        .bind(session.user_id)
+        .bind(&session.refresh_token)
+        .bind(session.expires_at)
+        .bind(&session.device.user_agent)
+        .bind(&session.device.browser)
+        .bind(&session.device.os)
+        .bind(&session.device.ip_address)
+        .bind(&session.device.location)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(AuthTokens {
+            access_token: self.access_token(user, session_id, now)?,
+            refresh_token,
+            session_id,
+        })
+    }
+
+    fn access_token(&self, user: &User, session_id: Uuid, now: DateTime<Utc>) -> Result<String, AppError> {
         let access_exp = now + Duration::hours(1);
-        let refresh_exp = now + Duration::days(30);
 
         // Create access token claims
         let access_claims = Claims {
@@ -169,43 +286,114 @@ impl AuthService {
             role: user.role.clone(),
             exp: access_exp.timestamp() as usize,
             iat: now.timestamp() as usize,
+            scopes: None,
+            sid: Some(session_id),
         };
 
         // Generate access token
-        let access_token = encode(
+        encode(
             &Header::default(),
             &access_claims,
             &EncodingKey::from_secret(self.jwt_secret.as_ref()),
         )
-        .map_err(|e| AppError::InternalServerError(format!("Token generation failed: {}", e)))?;
+        .map_err(|e| AppError::InternalServerError(format!("Token generation failed: {}", e)))
+    }
 
-        // Generate refresh token
-        let refresh_token = Uuid::new_v4().to_string();
+    /// Проверка аккаунта владельца токена: заблокированный пользователь получает 403
+    /// с причиной, а JWT, выпущенные до принудительного выхода или в отозванной сессии, — 401.
+    /// Выполняется на каждом запросе, поэтому блокировка действует сразу.
+    pub async fn check_account_status(&self, claims: &Claims) -> Result<(), AppError> {
+        let status = sqlx::query_as::<_, AccountStatus>(
+            r#"
+            SELECT suspended_at, suspension_reason, sessions_invalidated_at,
+                   ($2::UUID IS NULL OR EXISTS (
+                       SELECT 1 FROM user_sessions WHERE id = $2 AND user_id = users.id
+                   )) AS session_active
+            FROM users WHERE id = $1
+            "#
+        )
+        .bind(claims.sub)
+        .bind(claims.sid)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))?;
+
+        if status.suspended_at.is_some() {
+            return Err(AppError::Forbidden(format!(
+                "Account suspended: {}",
+                status.suspension_reason.as_deref().unwrap_or("contact support")
+            ))
+            .with_code(ErrorCode::AccountSuspended));
+        }
 
-        // Store refresh token in database
-        let session = CreateUserSession {
-            user_id: user.id,
-            refresh_token: refresh_token.clone(),
-            expires_at: refresh_exp,
-        };
+        // Токены API отзываются отдельно, у JWT проверяется время выпуска
+        let revoked = !status.session_active
+            || status
+                .sessions_invalidated_at
+                .is_some_and(|invalidated_at| !claims.is_api_token() && claims.iat as i64 <= invalidated_at.timestamp());
+        if revoked {
+            return Err(
+                AppError::Unauthorized("Session has been revoked, please sign in again".to_string())
+                    .with_code(ErrorCode::SessionRevoked),
+            );
+        }
 
-        sqlx::query(
+        Ok(())
+    }
+
+    async fn ensure_not_suspended(&self, user_id: Uuid) -> Result<(), AppError> {
+        let (suspended_at, reason): (Option<DateTime<Utc>>, Option<String>) = sqlx::query_as(
+            "SELECT suspended_at, suspension_reason FROM users WHERE id = $1"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if suspended_at.is_some() {
+            return Err(AppError::Forbidden(format!(
+                "Account suspended: {}",
+                reason.as_deref().unwrap_or("contact support")
+            ))
+            .with_code(ErrorCode::AccountSuspended));
+        }
+
+        Ok(())
+    }
+
+    /// Новый пароль по одноразовой ссылке из письма. Все сессии завершаются.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (user_id,): (Uuid,) = sqlx::query_as(
             r#"
-            INSERT INTO user_sessions (id, user_id, refresh_token, expires_at)
-            VALUES ($1, $2, $3, $4)
+            UPDATE password_reset_tokens SET used_at = NOW()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING user_id
             "#
         )
-        .bind(Uuid::new_v4())
-        .bind(session.user_id)
-        .bind(&session.refresh_token)
-        .bind(session.expires_at)
-        .execute(&self.pool)
+        .bind(crate::services::api_tokens::hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Password reset link is invalid or expired".to_string()))?;
+
+        let password_hash = hash(new_password, DEFAULT_COST)
+            .map_err(|e| AppError::InternalServerError(format!("Password hashing failed: {}", e)))?;
+
+        sqlx::query(
+            "UPDATE users SET password_hash = $2, sessions_invalidated_at = NOW(), updated_at = NOW() WHERE id = $1"
+        )
+        .bind(user_id)
+        .bind(&password_hash)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(AuthTokens {
-            access_token,
-            refresh_token,
-        })
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
     }
 
     pub fn verify_token(&self, token: &str) -> Result<Claims, AppError> {
@@ -214,7 +402,11 @@ impl AuthService {
             &DecodingKey::from_secret(self.jwt_secret.as_ref()),
             &Validation::default(),
         )
-        .map_err(|e| AppError::Unauthorized(format!("Invalid token: {}", e)))?;
+        .map_err(|e| {
+            let expired = matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature);
+            let error = AppError::Unauthorized(format!("Invalid token: {}", e));
+            if expired { error.with_code(ErrorCode::TokenExpired) } else { error }
+        })?;
 
         Ok(token_data.claims)
     }
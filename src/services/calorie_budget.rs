@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::diary::{BudgetSource, MacroBudget, MealSlotSuggestion, RemainingBudget},
+    services::health::activity_multiplier,
+    utils::{errors::AppError, format::round_to},
+};
+
+/// Типичное распределение дневной нормы по приемам пищи. Остаток делится между
+/// незаписанными приемами пропорционально этим долям: если записаны завтрак и обед,
+/// ужин и перекус получают 70/30.
+pub const MEAL_SLOT_SHARES: [(&str, f64); 4] = [
+    ("breakfast", 0.25),
+    ("lunch", 0.35),
+    ("dinner", 0.28),
+    ("snack", 0.12),
+];
+
+/// Доли калорий на белок и жиры, если нет цели по белку; остальное — углеводы
+const DEFAULT_PROTEIN_SHARE: f64 = 0.2;
+const DEFAULT_FAT_SHARE: f64 = 0.3;
+const KCAL_PER_G_PROTEIN: f64 = 4.0;
+const KCAL_PER_G_FAT: f64 = 9.0;
+const KCAL_PER_G_CARBS: f64 = 4.0;
+
+/// Цели и профиль пользователя одним запросом
+#[derive(Debug, FromRow)]
+struct BudgetInputs {
+    calorie_target: Option<f32>,
+    protein_target: Option<f32>,
+    weight: Option<f32>,
+    height: Option<f32>,
+    date_of_birth: Option<DateTime<Utc>>,
+    gender: Option<String>,
+    activity_level: Option<String>,
+}
+
+/// Потреблено за день по приему пищи
+#[derive(Debug, FromRow)]
+struct MealTotals {
+    meal_type: String,
+    calories: f32,
+    protein: f32,
+    fat: f32,
+    carbs: f32,
+}
+
+/// Расход энергии за день: BMR по Миффлину — Сан Жеору, умноженный на коэффициент активности.
+/// Рост в сантиметрах; без указанного пола — формула без поправки на пол.
+pub fn estimated_tdee(
+    weight_kg: f64,
+    height_cm: f64,
+    age_years: u32,
+    gender: Option<&str>,
+    activity_level: Option<&str>,
+) -> f64 {
+    let base = 10.0 * weight_kg + 6.25 * height_cm - 5.0 * age_years as f64;
+    let bmr = match gender.map(str::to_lowercase).as_deref() {
+        Some("male" | "m") => base + 5.0,
+        Some("female" | "f") => base - 161.0,
+        _ => base,
+    };
+    bmr * activity_multiplier(activity_level) as f64
+}
+
+/// Дневная норма БЖУ при заданной калорийности. Белок — из цели, если она есть.
+pub fn daily_budget(calories: f64, protein_target_g: Option<f64>) -> MacroBudget {
+    let protein = protein_target_g.unwrap_or(calories * DEFAULT_PROTEIN_SHARE / KCAL_PER_G_PROTEIN);
+    let fat = calories * DEFAULT_FAT_SHARE / KCAL_PER_G_FAT;
+    let carbs = ((calories - protein * KCAL_PER_G_PROTEIN - fat * KCAL_PER_G_FAT) / KCAL_PER_G_CARBS).max(0.0);
+
+    MacroBudget {
+        calories: round_to(calories, 0),
+        protein: round_to(protein, 1),
+        fat: round_to(fat, 1),
+        carbs: round_to(carbs, 1),
+    }
+}
+
+/// Распределение остатка калорий по еще не записанным приемам пищи.
+/// Пусто, если остатка нет или все приемы уже записаны. Округленные значения в сумме дают остаток.
+pub fn suggest_meal_slots<S: AsRef<str>>(remaining_calories: f64, logged_meals: &[S]) -> Vec<MealSlotSuggestion> {
+    let remaining_calories = round_to(remaining_calories, 0);
+    let open: Vec<(&'static str, f64)> = MEAL_SLOT_SHARES
+        .iter()
+        .copied()
+        .filter(|(meal_type, _)| !logged_meals.iter().any(|logged| logged.as_ref() == *meal_type))
+        .collect();
+    if remaining_calories <= 0.0 || open.is_empty() {
+        return Vec::new();
+    }
+
+    let total_share: f64 = open.iter().map(|(_, share)| share).sum();
+    let mut allocated = 0.0;
+    open.iter()
+        .enumerate()
+        .map(|(i, &(meal_type, share))| {
+            let share = share / total_share;
+            let calories = if i + 1 == open.len() {
+                remaining_calories - allocated
+            } else {
+                round_to(remaining_calories * share, 0)
+            };
+            allocated += calories;
+            MealSlotSuggestion { meal_type, calories, share: round_to(share, 2) }
+        })
+        .collect()
+}
+
+/// Остаток нормы по потребленному за день (`consumed_by_meal` — итоги по приемам пищи)
+pub fn remaining_budget(
+    date: NaiveDate,
+    budget: Option<(BudgetSource, MacroBudget)>,
+    consumed_by_meal: &BTreeMap<String, MacroBudget>,
+) -> RemainingBudget {
+    let consumed = consumed_by_meal.values().fold(MacroBudget::default(), |total, meal| MacroBudget {
+        calories: total.calories + meal.calories,
+        protein: total.protein + meal.protein,
+        fat: total.fat + meal.fat,
+        carbs: total.carbs + meal.carbs,
+    });
+    let consumed = MacroBudget {
+        calories: round_to(consumed.calories, 0),
+        protein: round_to(consumed.protein, 1),
+        fat: round_to(consumed.fat, 1),
+        carbs: round_to(consumed.carbs, 1),
+    };
+    let logged_meals: Vec<String> = consumed_by_meal.keys().cloned().collect();
+
+    let Some((source, budget)) = budget else {
+        return RemainingBudget {
+            date,
+            source: None,
+            budget: None,
+            consumed,
+            remaining: None,
+            over_budget: false,
+            logged_meals,
+            suggestions: Vec::new(),
+            setup_needed: true,
+        };
+    };
+
+    let remaining = MacroBudget {
+        calories: budget.calories - consumed.calories,
+        protein: round_to(budget.protein - consumed.protein, 1),
+        fat: round_to(budget.fat - consumed.fat, 1),
+        carbs: round_to(budget.carbs - consumed.carbs, 1),
+    };
+
+    RemainingBudget {
+        date,
+        source: Some(source),
+        budget: Some(budget),
+        consumed,
+        remaining: Some(remaining),
+        over_budget: remaining.calories < 0.0,
+        suggestions: suggest_meal_slots(remaining.calories, &logged_meals),
+        logged_meals,
+        setup_needed: false,
+    }
+}
+
+/// Остаток дневной нормы калорий. Норма — из активной цели по калориям, иначе TDEE по профилю.
+pub struct CalorieBudgetService {
+    pool: DbPool,
+}
+
+impl CalorieBudgetService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Остаток на местный день, в который попадает `now`. Два запроса: норма и потребленное.
+    /// `exclude_estimates` — не учитывать записи с оценкой питательности от ИИ.
+    pub async fn remaining(
+        &self,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+        utc_offset_minutes: i32,
+        exclude_estimates: bool,
+    ) -> Result<RemainingBudget, AppError> {
+        let offset = Duration::minutes(utc_offset_minutes as i64);
+        let today = (now + offset).date_naive();
+        let day_start = today.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+
+        let inputs = sqlx::query_as::<_, BudgetInputs>(
+            r#"
+            SELECT
+                (SELECT daily_target FROM goals
+                 WHERE user_id = u.id AND status = 'active' AND goal_type = 'calorie_intake' AND daily_target > 0
+                 ORDER BY updated_at DESC LIMIT 1) AS calorie_target,
+                (SELECT daily_target FROM goals
+                 WHERE user_id = u.id AND status = 'active' AND goal_type = 'protein_intake' AND daily_target > 0
+                 ORDER BY updated_at DESC LIMIT 1) AS protein_target,
+                u.weight, u.height, u.date_of_birth, u.gender, u.activity_level
+            FROM users u
+            WHERE u.id = $1
+            "#
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let meals = sqlx::query_as::<_, MealTotals>(
+            r#"
+            SELECT meal_type,
+                   SUM(calories_per_100g * portion_size / 100)::REAL AS calories,
+                   SUM(protein_per_100g * portion_size / 100)::REAL AS protein,
+                   SUM(fat_per_100g * portion_size / 100)::REAL AS fat,
+                   SUM(carbs_per_100g * portion_size / 100)::REAL AS carbs
+            FROM diary_entries
+            WHERE user_id = $1 AND consumed_at >= $2 AND consumed_at < $3
+              AND NOT ($4 AND nutrition_source = 'ai_estimate')
+            GROUP BY meal_type
+            "#
+        )
+        .bind(user_id)
+        .bind(day_start)
+        .bind(day_start + Duration::days(1))
+        .bind(exclude_estimates)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let consumed_by_meal: BTreeMap<String, MacroBudget> = meals
+            .into_iter()
+            .map(|meal| {
+                let totals = MacroBudget {
+                    calories: meal.calories as f64,
+                    protein: meal.protein as f64,
+                    fat: meal.fat as f64,
+                    carbs: meal.carbs as f64,
+                };
+                (meal.meal_type, totals)
+            })
+            .collect();
+
+        Ok(remaining_budget(today, budget_for(&inputs, today), &consumed_by_meal))
+    }
+}
+
+/// Норма из цели по калориям, иначе из профиля; без веса, роста и даты рождения нормы нет
+fn budget_for(inputs: &BudgetInputs, today: NaiveDate) -> Option<(BudgetSource, MacroBudget)> {
+    let protein_target = inputs.protein_target.map(|target| target as f64);
+    if let Some(calories) = inputs.calorie_target {
+        return Some((BudgetSource::Goal, daily_budget(calories as f64, protein_target)));
+    }
+
+    let (weight, height, date_of_birth) = (inputs.weight?, inputs.height?, inputs.date_of_birth?);
+    let age = today.years_since(date_of_birth.date_naive())?;
+    let tdee = estimated_tdee(
+        weight as f64,
+        height as f64,
+        age,
+        inputs.gender.as_deref(),
+        inputs.activity_level.as_deref(),
+    );
+    Some((BudgetSource::Tdee, daily_budget(tdee, protein_target)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{counting_pool, insert_diary_entry, insert_user};
+
+    /// Прием пищи, калории и доля от остатка
+    type Slot<'a> = (&'a str, f64, f64);
+
+    fn split(suggestions: &[MealSlotSuggestion]) -> Vec<Slot<'_>> {
+        suggestions.iter().map(|slot| (slot.meal_type, slot.calories, slot.share)).collect()
+    }
+
+    #[test]
+    fn remainder_is_split_across_open_slots() {
+        let cases: Vec<(&str, f64, Vec<&str>, Vec<Slot>)> = vec![
+            (
+                "nothing logged",
+                1000.0,
+                vec![],
+                vec![("breakfast", 250.0, 0.25), ("lunch", 350.0, 0.35), ("dinner", 280.0, 0.28), ("snack", 120.0, 0.12)],
+            ),
+            ("breakfast and lunch logged", 1000.0, vec!["breakfast", "lunch"], vec![("dinner", 700.0, 0.7), ("snack", 300.0, 0.3)]),
+            (
+                "only breakfast logged",
+                1000.0,
+                vec!["breakfast"],
+                vec![("lunch", 467.0, 0.47), ("dinner", 373.0, 0.37), ("snack", 160.0, 0.16)],
+            ),
+            ("rounding goes to the last slot", 640.6, vec!["breakfast", "lunch"], vec![("dinner", 449.0, 0.7), ("snack", 192.0, 0.3)]),
+            ("only a snack left", 200.0, vec!["breakfast", "lunch", "dinner"], vec![("snack", 200.0, 1.0)]),
+            ("everything logged", 500.0, vec!["breakfast", "lunch", "dinner", "snack"], vec![]),
+            ("nothing left", 0.0, vec!["breakfast"], vec![]),
+            ("over budget", -150.0, vec![], vec![]),
+        ];
+        for (case, remaining, logged, expected) in cases {
+            let suggestions = suggest_meal_slots(remaining, &logged);
+            assert_eq!(split(&suggestions), expected, "{}", case);
+            if !suggestions.is_empty() {
+                let total: f64 = suggestions.iter().map(|slot| slot.calories).sum();
+                assert_eq!(total, round_to(remaining, 0), "{}: slots add up to the remainder", case);
+            }
+        }
+    }
+
+    #[test]
+    fn macros_follow_the_calorie_budget() {
+        let default = daily_budget(2000.0, None);
+        assert_eq!((default.calories, default.protein, default.fat, default.carbs), (2000.0, 100.0, 66.7, 250.0));
+
+        let high_protein = daily_budget(2000.0, Some(150.0));
+        assert_eq!((high_protein.protein, high_protein.carbs), (150.0, 200.0));
+
+        // Белок из цели не оставляет места углеводам — не уходим в минус
+        assert_eq!(daily_budget(1200.0, Some(250.0)).carbs, 0.0);
+
+        let tdee = estimated_tdee(80.0, 180.0, 30, Some("male"), Some("sedentary"));
+        assert!((tdee - 1780.0 * activity_multiplier(Some("sedentary")) as f64).abs() < 1e-6, "{}", tdee);
+        let gender_gap = estimated_tdee(60.0, 165.0, 30, Some("m"), None) - estimated_tdee(60.0, 165.0, 30, Some("F"), None);
+        assert!((gender_gap - 166.0 * activity_multiplier(None) as f64).abs() < 1e-6, "{}", gender_gap);
+    }
+
+    #[test]
+    fn fully_logged_day_over_budget_has_no_suggestions() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let meal = |calories| MacroBudget { calories, protein: 20.0, fat: 10.0, carbs: 50.0 };
+        let consumed: BTreeMap<String, MacroBudget> = [("breakfast", 500.0), ("lunch", 700.0), ("dinner", 800.0), ("snack", 100.0)]
+            .into_iter()
+            .map(|(meal_type, calories)| (meal_type.to_string(), meal(calories)))
+            .collect();
+
+        let result = remaining_budget(date, Some((BudgetSource::Goal, daily_budget(2000.0, None))), &consumed);
+        assert_eq!(result.consumed.calories, 2100.0);
+        assert_eq!(result.remaining.unwrap().calories, -100.0);
+        assert!(result.over_budget);
+        assert!(result.suggestions.is_empty());
+        assert_eq!(result.logged_meals.len(), 4);
+
+        let unknown = remaining_budget(date, None, &consumed);
+        assert!(unknown.setup_needed && !unknown.over_budget);
+        assert!(unknown.budget.is_none() && unknown.remaining.is_none() && unknown.suggestions.is_empty());
+        assert_eq!(unknown.consumed.calories, 2100.0);
+    }
+
+    #[sqlx::test]
+    async fn remaining_uses_two_queries_and_the_local_day(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        sqlx::query(
+            "INSERT INTO goals (user_id, title, goal_type, target_value, unit, daily_target) \
+             VALUES ($1, 'Калории', 'calorie_intake', 2000, 'kcal', 2000)"
+        )
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Москва, 16.10 10:00: запись в 23:30 накануне по местному времени в этот день не входит
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 7, 0, 0).unwrap();
+        insert_diary_entry(&pool, user_id, "snack", 900.0, 10.0, Utc.with_ymd_and_hms(2026, 10, 15, 20, 30, 0).unwrap()).await;
+        insert_diary_entry(&pool, user_id, "breakfast", 400.0, 20.0, Utc.with_ymd_and_hms(2026, 10, 15, 21, 30, 0).unwrap()).await;
+        let estimated = insert_diary_entry(&pool, user_id, "lunch", 600.0, 30.0, Utc.with_ymd_and_hms(2026, 10, 16, 6, 30, 0).unwrap()).await;
+        sqlx::query("UPDATE diary_entries SET nutrition_source = 'ai_estimate'::nutrition_source WHERE id = $1")
+            .bind(estimated)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let (counted, queries) = counting_pool(&pool);
+        let service = CalorieBudgetService::new(counted);
+        let result = service.remaining(user_id, now, 180, false).await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+        assert_eq!(result.date, NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+        assert_eq!(result.source, Some(BudgetSource::Goal));
+        assert_eq!(result.consumed.calories, 1000.0);
+        assert_eq!(result.remaining.unwrap().calories, 1000.0);
+        assert_eq!(result.logged_meals, vec!["breakfast", "lunch"]);
+        assert_eq!(split(&result.suggestions), vec![("dinner", 700.0, 0.7), ("snack", 300.0, 0.3)]);
+
+        let without_estimates = service.remaining(user_id, now, 180, true).await.unwrap();
+        assert_eq!(without_estimates.consumed.calories, 400.0);
+        assert_eq!(without_estimates.logged_meals, vec!["breakfast"]);
+
+        // Без цели и профиля — подсказка заполнить профиль; с профилем — норма по TDEE
+        let newcomer = insert_user(&pool, "Олег").await;
+        assert!(service.remaining(newcomer, now, 0, false).await.unwrap().setup_needed);
+        sqlx::query(
+            "UPDATE users SET weight = 80, height = 180, date_of_birth = '1996-01-01', gender = 'male', activity_level = 'sedentary' WHERE id = $1"
+        )
+        .bind(newcomer)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let profiled = service.remaining(newcomer, now, 0, false).await.unwrap();
+        assert_eq!(profiled.source, Some(BudgetSource::Tdee));
+        assert_eq!(profiled.budget.unwrap().calories, round_to(1780.0 * 1.2, 0));
+        assert_eq!(profiled.suggestions.len(), 4);
+
+        assert!(matches!(service.remaining(Uuid::new_v4(), now, 0, false).await, Err(AppError::NotFound(_))));
+    }
+}
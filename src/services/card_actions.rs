@@ -0,0 +1,497 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    api::ai::AiCard,
+    db::{DbPool, UserScope},
+    models::{
+        card_action::{CardAction, CardActionResult, DiaryDraft, GoalTemplate},
+        diary::CreateDiaryEntry,
+        goal::{CreateGoal, GoalStatus, GoalType},
+        health::DailyWellbeing,
+    },
+    services::{
+        clock::{self, SharedClock},
+        diary::DiaryService,
+        food_memory::FoodMemoryService,
+        goal::GoalService,
+        recipe::RecipeService,
+        shopping_advice::ShoppingAdviceService,
+        wellbeing::WellbeingService,
+    },
+    utils::errors::AppError,
+};
+
+/// Стакан воды по умолчанию для карточек о воде
+const DEFAULT_WATER_ML: i32 = 250;
+const MAX_WATER_ML: i32 = 3000;
+const MAX_TIMER_MINUTES: u32 = 240;
+const MAX_SHOPPING_ITEMS: usize = 50;
+const MAX_SHOPPING_ITEM_CHARS: usize = 100;
+/// За сколько дней ищется последний завтрак для черновика записи
+const BREAKFAST_LOOKBACK_DAYS: i64 = 30;
+
+/// Данные пользователя, на которые опираются действия карточек
+#[derive(Debug, Clone, Default)]
+pub struct ActionContext {
+    /// Обычный завтрак пользователя: последняя запись завтрака с самой частой порцией
+    pub breakfast_draft: Option<DiaryDraft>,
+}
+
+#[derive(sqlx::FromRow)]
+struct BreakfastRow {
+    food_name: String,
+    brand: Option<String>,
+    portion_size: f32,
+    unit: String,
+    calories_per_100g: f32,
+    protein_per_100g: f32,
+    fat_per_100g: f32,
+    carbs_per_100g: f32,
+}
+
+/// Действия карточек ИИ: подбор по данным пользователя и выполнение от его имени
+pub struct CardActionService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl CardActionService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn context(&self, user_id: Uuid) -> Result<ActionContext, AppError> {
+        Ok(ActionContext { breakfast_draft: self.breakfast_draft(user_id).await? })
+    }
+
+    /// Последняя запись завтрака за `BREAKFAST_LOOKBACK_DAYS` дней; порция — та, что
+    /// пользователь выбирает для этого продукта чаще всего
+    async fn breakfast_draft(&self, user_id: Uuid) -> Result<Option<DiaryDraft>, AppError> {
+        let last: Option<BreakfastRow> = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT food_name, brand, portion_size, unit,
+                       calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g
+                FROM diary_entries
+                WHERE user_id = $1 AND meal_type = 'breakfast' AND consumed_at >= $2
+                ORDER BY consumed_at DESC
+                LIMIT 1
+                "#
+            )
+            .bind(self.clock.now() - Duration::days(BREAKFAST_LOOKBACK_DAYS))
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(last) = last else {
+            return Ok(None);
+        };
+        let usual_portion = FoodMemoryService::new(self.pool.clone())
+            .get(user_id, &last.food_name)
+            .await?
+            .and_then(|memory| memory.portions.into_iter().max_by_key(|portion| portion.uses));
+        let (portion_size, unit) = match usual_portion {
+            Some(portion) => (portion.portion_size, portion.unit),
+            None => (last.portion_size, last.unit),
+        };
+
+        Ok(Some(DiaryDraft {
+            food_name: last.food_name,
+            brand: last.brand,
+            portion_size,
+            unit,
+            calories_per_100g: last.calories_per_100g,
+            protein_per_100g: last.protein_per_100g,
+            fat_per_100g: last.fat_per_100g,
+            carbs_per_100g: last.carbs_per_100g,
+            meal_type: "breakfast".to_string(),
+        }))
+    }
+
+    /// Выполняет действие от имени пользователя и возвращает созданный или открытый ресурс.
+    /// Чужие и несуществующие ресурсы неразличимы — NotFound.
+    pub async fn execute(&self, user_id: Uuid, action: CardAction) -> Result<CardActionResult, AppError> {
+        validate(&action)?;
+        let now = self.clock.now();
+
+        match action {
+            CardAction::OpenRecipe { recipe_id } => {
+                let visible: Option<bool> = sqlx::query_scalar(
+                    "SELECT is_public OR created_by = $2 FROM recipes WHERE id = $1"
+                )
+                .bind(recipe_id)
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+                if visible != Some(true) {
+                    return Err(AppError::NotFound("Recipe not found".to_string()));
+                }
+                let recipe = RecipeService::new(self.pool.clone()).with_clock(self.clock.clone()).get_recipe_by_id(recipe_id, Some(user_id)).await?;
+                Ok(CardActionResult::Recipe { recipe: Box::new(recipe) })
+            }
+            CardAction::LogWater { amount_ml } => {
+                let wellbeing = water_entry(user_id, amount_ml, now);
+                WellbeingService::new(self.pool.clone()).record(&wellbeing).await?;
+                Ok(CardActionResult::WaterLogged { wellbeing_id: wellbeing.id, amount_ml })
+            }
+            CardAction::StartTimer { minutes } => Ok(CardActionResult::Timer {
+                minutes,
+                ends_at: now + Duration::minutes(i64::from(minutes)),
+            }),
+            CardAction::CreateGoalFromTemplate { template } => {
+                let goal = GoalService::new(self.pool.clone()).with_clock(self.clock.clone()).create_goal(goal_from_template(user_id, template)).await?;
+                Ok(CardActionResult::Goal { goal })
+            }
+            CardAction::AddToShoppingList { items } => {
+                let names = items.into_iter().map(|item| item.trim().to_string()).collect();
+                let items = ShoppingAdviceService::new(self.pool.clone()).advise(user_id, names, now.date_naive()).await?;
+                Ok(CardActionResult::ShoppingList { items })
+            }
+            CardAction::PrefilledDiaryEntry { draft } => {
+                let entry = DiaryService::new(self.pool.clone())
+                    .with_clock(self.clock.clone())
+                    .create_entry(diary_entry(user_id, draft, now))
+                    .await?;
+                Ok(CardActionResult::DiaryEntry { entry })
+            }
+        }
+    }
+}
+
+fn validate(action: &CardAction) -> Result<(), AppError> {
+    match action {
+        CardAction::LogWater { amount_ml } if !(1..=MAX_WATER_ML).contains(amount_ml) => Err(AppError::BadRequest(
+            format!("amount_ml must be between 1 and {}", MAX_WATER_ML),
+        )),
+        CardAction::StartTimer { minutes } if !(1..=MAX_TIMER_MINUTES).contains(minutes) => Err(AppError::BadRequest(
+            format!("minutes must be between 1 and {}", MAX_TIMER_MINUTES),
+        )),
+        CardAction::AddToShoppingList { items } => {
+            if items.is_empty() || items.len() > MAX_SHOPPING_ITEMS {
+                return Err(AppError::BadRequest(format!("items must contain 1 to {} entries", MAX_SHOPPING_ITEMS)));
+            }
+            if items.iter().any(|item| item.trim().is_empty() || item.chars().count() > MAX_SHOPPING_ITEM_CHARS) {
+                return Err(AppError::BadRequest(format!(
+                    "Shopping items must be non-empty and at most {} characters",
+                    MAX_SHOPPING_ITEM_CHARS
+                )));
+            }
+            Ok(())
+        }
+        CardAction::PrefilledDiaryEntry { draft } => {
+            validator::Validate::validate(draft)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn water_entry(user_id: Uuid, amount_ml: i32, now: DateTime<Utc>) -> DailyWellbeing {
+    DailyWellbeing {
+        id: Uuid::new_v4(),
+        user_id,
+        date: now,
+        mood_score: None,
+        energy_level: None,
+        stress_level: None,
+        sleep_hours: None,
+        sleep_quality: None,
+        water_intake_ml: Some(amount_ml),
+        exercise_minutes: None,
+        notes: None,
+        symptoms: Vec::new(),
+        created_at: now,
+    }
+}
+
+fn diary_entry(user_id: Uuid, draft: DiaryDraft, now: DateTime<Utc>) -> CreateDiaryEntry {
+    CreateDiaryEntry {
+        user_id,
+        food_name: draft.food_name,
+        brand: draft.brand,
+        portion_size: draft.portion_size,
+        unit: draft.unit,
+        calories_per_100g: draft.calories_per_100g,
+        protein_per_100g: draft.protein_per_100g,
+        fat_per_100g: draft.fat_per_100g,
+        carbs_per_100g: draft.carbs_per_100g,
+        fiber_per_100g: None,
+        sugar_per_100g: None,
+        sodium_per_100g: None,
+        media_id: None,
+        meal_type: draft.meal_type,
+        consumed_at: now,
+    }
+}
+
+/// Ежедневная цель без срока: пользователь потом может поменять значение
+fn goal_from_template(user_id: Uuid, template: GoalTemplate) -> CreateGoal {
+    let (title, goal_type, target, unit) = match template {
+        GoalTemplate::DailyWater => ("Пить 2 л воды в день", GoalType::Water, 2000.0, "ml"),
+        GoalTemplate::ProteinIntake => ("100 г белка в день", GoalType::ProteinIntake, 100.0, "g"),
+        GoalTemplate::DailyExercise => ("30 минут активности в день", GoalType::Exercise, 30.0, "min"),
+    };
+
+    CreateGoal {
+        user_id,
+        title: title.to_string(),
+        description: None,
+        goal_type,
+        target_value: target,
+        current_value: 0.0,
+        unit: unit.to_string(),
+        target_date: None,
+        daily_target: Some(target),
+        weekly_target: None,
+        status: GoalStatus::Active,
+        reminder_days: Vec::new(),
+    }
+}
+
+/// Добавляет действия карточкам, для которых они есть. Правила по ключевым словам, как и сами
+/// шаблонные карточки; черновик завтрака получает только первая карточка-рецепт утреннего сообщения.
+pub fn attach_actions(cards: &mut [AiCard], trigger_type: &str, context: &ActionContext) {
+    let mut draft = context.breakfast_draft.clone().filter(|_| trigger_type == "breakfast");
+    for card in cards.iter_mut().filter(|card| card.action.is_none()) {
+        if card.category.as_deref() == Some("recipe") {
+            if let Some(draft) = draft.take() {
+                card.action = Some(CardAction::PrefilledDiaryEntry { draft });
+                continue;
+            }
+        }
+        card.action = keyword_action(card);
+    }
+}
+
+fn keyword_action(card: &AiCard) -> Option<CardAction> {
+    let text = format!("{} {}", card.title, card.content).to_lowercase();
+
+    if text.contains("воды") || text.contains("гидратац") {
+        return Some(CardAction::LogWater { amount_ml: DEFAULT_WATER_ML });
+    }
+    if card.category.as_deref() == Some("nutrition") && text.contains("белк") && text.contains("увелич") {
+        return Some(CardAction::CreateGoalFromTemplate { template: GoalTemplate::ProteinIntake });
+    }
+    minutes_in(&text).map(|minutes| CardAction::StartTimer { minutes })
+}
+
+/// Длительность вида "5-минутная" или "15 минут"
+fn minutes_in(text: &str) -> Option<u32> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.iter().enumerate().find_map(|(index, word)| {
+        let (number, rest) = word.split_at(word.find(|c: char| !c.is_ascii_digit()).unwrap_or(word.len()));
+        let minutes: u32 = number.parse().ok()?;
+        let is_minutes = rest.starts_with("-минут")
+            || (rest.is_empty() && words.get(index + 1).is_some_and(|next| next.starts_with("минут")));
+        (is_minutes && (1..=MAX_TIMER_MINUTES).contains(&minutes)).then_some(minutes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_fridge_item, insert_recipe, insert_user};
+
+    fn card(category: &str, title: &str, content: &str) -> AiCard {
+        AiCard {
+            title: title.to_string(),
+            content: content.to_string(),
+            emoji: None,
+            category: Some(category.to_string()),
+            priority: None,
+            action: None,
+        }
+    }
+
+    fn draft(food_name: &str) -> DiaryDraft {
+        DiaryDraft {
+            food_name: food_name.to_string(),
+            brand: None,
+            portion_size: 200.0,
+            unit: "g".to_string(),
+            calories_per_100g: 120.0,
+            protein_per_100g: 4.0,
+            fat_per_100g: 2.0,
+            carbs_per_100g: 20.0,
+            meal_type: "breakfast".to_string(),
+        }
+    }
+
+    fn service(pool: &PgPool) -> (CardActionService, DateTime<Utc>) {
+        let now = Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0).unwrap();
+        (CardActionService::new(pool.clone()).with_clock(frozen_clock(now)), now)
+    }
+
+    #[test]
+    fn keyword_rules_pick_the_action() {
+        let mut cards = vec![
+            card("health", "Больше воды", "Выпейте стакан воды"),
+            card("nutrition", "Белок", "Стоит увеличить долю белка в рационе"),
+            card("health", "Разминка", "Сделайте 15 минут растяжки"),
+            card("motivation", "Так держать", "Вы молодец"),
+        ];
+
+        attach_actions(&mut cards, "chat", &ActionContext::default());
+
+        assert!(matches!(cards[0].action, Some(CardAction::LogWater { amount_ml: DEFAULT_WATER_ML })));
+        assert!(matches!(cards[1].action, Some(CardAction::CreateGoalFromTemplate { template: GoalTemplate::ProteinIntake })));
+        assert!(matches!(cards[2].action, Some(CardAction::StartTimer { minutes: 15 })));
+        assert!(cards[3].action.is_none());
+    }
+
+    #[test]
+    fn timer_needs_a_minutes_word_and_a_sane_duration() {
+        assert_eq!(minutes_in("5-минутная прогулка"), Some(5));
+        assert_eq!(minutes_in("отдых 20 минут"), Some(20));
+        assert_eq!(minutes_in("выпейте 2 стакана"), None);
+        assert_eq!(minutes_in("подождите 300 минут"), None);
+        assert_eq!(minutes_in("0-минутная пауза"), None);
+    }
+
+    #[test]
+    fn breakfast_draft_goes_only_to_the_first_recipe_card_of_a_morning_message() {
+        let context = ActionContext { breakfast_draft: Some(draft("Овсянка")) };
+        let mut cards = vec![card("recipe", "Омлет", "Быстрый завтрак"), card("recipe", "Сырники", "Еще вариант")];
+
+        attach_actions(&mut cards, "breakfast", &context);
+        assert!(matches!(&cards[0].action, Some(CardAction::PrefilledDiaryEntry { draft }) if draft.food_name == "Овсянка"));
+        assert!(cards[1].action.is_none());
+
+        // В другом сообщении черновик не подставляется
+        let mut cards = vec![card("recipe", "Омлет", "Быстрый завтрак")];
+        attach_actions(&mut cards, "lunch", &context);
+        assert!(cards[0].action.is_none());
+
+        // Уже заданное действие не перезаписывается
+        let mut cards = vec![card("recipe", "Омлет", "Выпейте воды")];
+        cards[0].action = Some(CardAction::StartTimer { minutes: 3 });
+        attach_actions(&mut cards, "breakfast", &context);
+        assert!(matches!(cards[0].action, Some(CardAction::StartTimer { minutes: 3 })));
+    }
+
+    #[sqlx::test]
+    async fn breakfast_draft_uses_the_last_breakfast_with_the_usual_portion(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = CardActionService::new(pool.clone());
+        assert!(service.context(user_id).await.unwrap().breakfast_draft.is_none());
+
+        insert_diary_entry(&pool, user_id, "lunch", 500.0, 20.0, Utc::now() - Duration::hours(1)).await;
+        insert_diary_entry(&pool, user_id, "breakfast", 350.0, 12.0, Utc::now() - Duration::hours(4)).await;
+        let memory = FoodMemoryService::new(pool.clone());
+        for portion in [250.0, 250.0, 150.0] {
+            memory.record(user_id, "Каша", portion, "g", "breakfast").await.unwrap();
+        }
+
+        let draft = service.context(user_id).await.unwrap().breakfast_draft.expect("breakfast draft");
+        assert_eq!(draft.food_name, "Каша");
+        assert_eq!(draft.meal_type, "breakfast");
+        assert_eq!(draft.calories_per_100g, 350.0);
+        assert_eq!((draft.portion_size, draft.unit.as_str()), (250.0, "g"));
+    }
+
+    #[sqlx::test]
+    async fn each_action_type_is_executed_for_the_caller(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (service, now) = service(&pool);
+
+        let recipe_id = insert_recipe(&pool, user_id, "Мой суп", false).await;
+        let result = service.execute(user_id, CardAction::OpenRecipe { recipe_id }).await.unwrap();
+        assert!(matches!(result, CardActionResult::Recipe { recipe } if recipe.id == recipe_id));
+
+        let result = service.execute(user_id, CardAction::LogWater { amount_ml: 300 }).await.unwrap();
+        let CardActionResult::WaterLogged { wellbeing_id, amount_ml: 300 } = result else { panic!("{:?}", result) };
+        let (owner, water): (Uuid, Option<i32>) = sqlx::query_as("SELECT user_id, water_intake_ml FROM daily_wellbeing WHERE id = $1")
+            .bind(wellbeing_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((owner, water), (user_id, Some(300)));
+
+        let result = service.execute(user_id, CardAction::StartTimer { minutes: 10 }).await.unwrap();
+        assert!(matches!(result, CardActionResult::Timer { minutes: 10, ends_at } if ends_at == now + Duration::minutes(10)));
+
+        let result = service
+            .execute(user_id, CardAction::CreateGoalFromTemplate { template: GoalTemplate::DailyWater })
+            .await
+            .unwrap();
+        let CardActionResult::Goal { goal } = result else { panic!("{:?}", result) };
+        assert_eq!((goal.user_id, goal.goal_type, goal.target_value), (user_id, GoalType::Water, 2000.0));
+
+        let items = vec!["  Молоко ".to_string(), "Хлеб".to_string()];
+        let result = service.execute(user_id, CardAction::AddToShoppingList { items }).await.unwrap();
+        let CardActionResult::ShoppingList { items } = result else { panic!("{:?}", result) };
+        assert_eq!(items.iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), ["Молоко", "Хлеб"]);
+
+        let result = service.execute(user_id, CardAction::PrefilledDiaryEntry { draft: draft("Овсянка") }).await.unwrap();
+        let CardActionResult::DiaryEntry { entry } = result else { panic!("{:?}", result) };
+        assert_eq!((entry.user_id, entry.food_name.as_str(), entry.consumed_at), (user_id, "Овсянка", now));
+    }
+
+    #[sqlx::test]
+    async fn invalid_parameters_are_rejected_before_anything_is_written(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (service, _) = service(&pool);
+        let mut empty_name = draft("");
+        empty_name.portion_size = 0.0;
+
+        for action in [
+            CardAction::LogWater { amount_ml: 0 },
+            CardAction::LogWater { amount_ml: MAX_WATER_ML + 1 },
+            CardAction::StartTimer { minutes: 0 },
+            CardAction::StartTimer { minutes: MAX_TIMER_MINUTES + 1 },
+            CardAction::AddToShoppingList { items: Vec::new() },
+            CardAction::AddToShoppingList { items: vec!["  ".to_string()] },
+            CardAction::AddToShoppingList { items: vec!["я".repeat(MAX_SHOPPING_ITEM_CHARS + 1)] },
+            CardAction::PrefilledDiaryEntry { draft: empty_name },
+        ] {
+            let result = service.execute(user_id, action.clone()).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_) | AppError::Validation(_))), "{:?}: {:?}", action, result);
+        }
+
+        let written: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM daily_wellbeing WHERE user_id = $1) + (SELECT COUNT(*) FROM diary_entries WHERE user_id = $1)"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(written, 0);
+    }
+
+    #[sqlx::test]
+    async fn actions_cannot_reach_other_users_resources(pool: PgPool) {
+        let owner = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+        let (service, now) = service(&pool);
+
+        // Чужой закрытый рецепт неотличим от несуществующего; открытый виден всем
+        let private = insert_recipe(&pool, owner, "Семейный рецепт", false).await;
+        let public = insert_recipe(&pool, owner, "Борщ", true).await;
+        for recipe_id in [private, Uuid::new_v4()] {
+            let result = service.execute(other, CardAction::OpenRecipe { recipe_id }).await;
+            assert!(matches!(result, Err(AppError::NotFound(_))), "{:?}", result.map(|_| ()));
+        }
+        assert!(service.execute(other, CardAction::OpenRecipe { recipe_id: public }).await.is_ok());
+
+        // Совет к списку покупок не опирается на чужой холодильник
+        insert_fridge_item(&pool, owner, "Молоко", None, service.clock.now() - Duration::days(1)).await;
+        let items = vec!["Молоко".to_string()];
+        let result = service.execute(owner, CardAction::AddToShoppingList { items: items.clone() }).await.unwrap();
+        assert!(matches!(&result, CardActionResult::ShoppingList { items } if items[0].advice.is_some()));
+        let result = service.execute(other, CardAction::AddToShoppingList { items }).await.unwrap();
+        assert!(matches!(&result, CardActionResult::ShoppingList { items } if items[0].advice.is_none()));
+
+        // Черновик завтрака — только из своих записей
+        insert_diary_entry(&pool, owner, "breakfast", 350.0, 12.0, now - Duration::hours(1)).await;
+        assert!(service.context(owner).await.unwrap().breakfast_draft.is_some());
+        assert!(service.context(other).await.unwrap().breakfast_draft.is_none());
+    }
+}
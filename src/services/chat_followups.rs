@@ -0,0 +1,399 @@
+use crate::api::ai::AiCard;
+
+// Продолжение разговора по тому, что модель действительно ответила: блюда, время
+// приготовления и цифры пищевой ценности извлекаются из текста ответа без второго запроса к модели.
+
+const MAX_SUGGESTIONS: usize = 3;
+const MAX_CARDS: usize = 3;
+/// Длиннее — карточка обрезается, полный текст и так есть в ответе
+const MAX_CARD_CHARS: usize = 200;
+
+/// Названия блюд в именительном или винительном падеже ("суп", "запеканку")
+const DISH_WORDS: &[&str] = &[
+    "суп", "крем-суп", "суп-пюре", "борщ", "щи", "солянка", "солянку", "уха", "уху", "окрошка", "окрошку",
+    "гаспачо", "салат", "омлет", "фриттата", "фриттату", "шакшука", "шакшуку", "каша", "кашу", "плов",
+    "ризотто", "рагу", "гуляш", "жаркое", "карри", "запеканка", "запеканку", "лазанья", "лазанью", "паста",
+    "пасту", "спагетти", "котлеты", "тефтели", "фрикадельки", "пюре", "блины", "оладьи", "сырники", "драники",
+    "голубцы", "пельмени", "вареники", "пирог", "киш", "пицца", "пиццу", "бургер", "сэндвич", "бутерброд",
+    "рулет", "стейк", "шашлык", "смузи", "боул", "гратен",
+];
+
+/// Оценки, а не часть названия: "отличный суп" → "суп"
+const NOT_DISH_ADJECTIVES: &[&str] = &[
+    "отличный", "отличную", "хороший", "хорошую", "вкусный", "вкусную", "простой", "простую", "быстрый",
+    "быструю", "легкий", "легкую", "полезный", "полезную", "сытный", "сытную", "идеальный", "идеальную",
+    "любой", "любую", "такой", "такую", "этот", "эту", "ваш", "вашу", "свой", "свою", "другой", "другую",
+];
+
+const ADJECTIVE_ENDINGS: &[&str] = &["ый", "ий", "ой", "ая", "яя", "ое", "ее", "ые", "ие", "ую", "юю"];
+
+/// Подсказки и карточки, построенные по ответу модели
+pub struct Followups {
+    pub suggestions: Vec<String>,
+    pub cards: Vec<AiCard>,
+}
+
+/// Что удалось найти в ответе; `None`, если ни блюд, ни времени, ни цифр нет —
+/// тогда вызывающий код возвращается к подсказкам по ключевым словам вопроса.
+pub fn from_answer(question: &str, answer: &str) -> Option<Followups> {
+    let facts = AnswerFacts::extract(answer);
+    if facts.is_empty() {
+        return None;
+    }
+
+    Some(Followups {
+        suggestions: without_question(question, facts.suggestions()),
+        cards: facts.cards(),
+    })
+}
+
+/// Убирает подсказки, которые повторяют вопрос пользователя, и оставляет не больше трех
+pub fn without_question(question: &str, suggestions: Vec<String>) -> Vec<String> {
+    let question = normalize(question);
+    let mut kept: Vec<String> = Vec::new();
+    for suggestion in suggestions {
+        let normalized = normalize(&suggestion);
+        if normalized != question && !kept.iter().any(|existing| normalize(existing) == normalized) {
+            kept.push(suggestion);
+        }
+    }
+    kept.truncate(MAX_SUGGESTIONS);
+    kept
+}
+
+/// Нижний регистр, ё = е, без знаков препинания и лишних пробелов
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Упоминание в ответе вместе с предложением, из которого оно взято
+struct Mention {
+    text: String,
+    sentence: String,
+}
+
+#[derive(Default)]
+struct AnswerFacts {
+    dishes: Vec<Mention>,
+    cook_time: Option<Mention>,
+    /// "300 ккал", "25 г белка"
+    nutrition: Vec<String>,
+}
+
+impl AnswerFacts {
+    fn extract(answer: &str) -> Self {
+        let mut facts = AnswerFacts::default();
+
+        for sentence in sentences(answer) {
+            let words: Vec<String> = sentence.split_whitespace().map(clean_word).collect();
+
+            for (index, word) in words.iter().enumerate() {
+                if let Some(dish) = dish_at(&words, index) {
+                    if !facts.dishes.iter().any(|known| known.text == dish) {
+                        facts.dishes.push(Mention { text: dish, sentence: sentence.to_string() });
+                    }
+                    continue;
+                }
+
+                let Some(amount) = number(word) else { continue };
+                let Some(unit) = words.get(index + 1) else { continue };
+
+                if facts.cook_time.is_none() && (unit.starts_with("мин") || unit.starts_with("час")) {
+                    facts.cook_time = Some(Mention {
+                        text: format!("{} {}", amount, unit),
+                        sentence: sentence.to_string(),
+                    });
+                } else if unit == "ккал" || unit == "kcal" || unit.starts_with("калори") {
+                    facts.nutrition.push(format!("{} ккал", amount));
+                } else if matches!(unit.as_str(), "г" | "гр" | "грамм" | "граммов") {
+                    let nutrient = words
+                        .get(index + 2)
+                        .filter(|nutrient| ["белк", "жир", "углевод", "клетчат"].iter().any(|stem| nutrient.starts_with(stem)));
+                    if let Some(nutrient) = nutrient {
+                        facts.nutrition.push(format!("{} г {}", amount, nutrient));
+                    }
+                }
+            }
+        }
+
+        facts.nutrition.dedup();
+        facts
+    }
+
+    fn is_empty(&self) -> bool {
+        self.dishes.is_empty() && self.cook_time.is_none() && self.nutrition.is_empty()
+    }
+
+    fn suggestions(&self) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        let dish = self.dishes.first().map(|dish| dish.text.as_str());
+
+        if let [first, second, ..] = self.dishes.as_slice() {
+            suggestions.push(format!("Чем «{}» отличается от «{}»?", first.text, second.text));
+        }
+        match (dish, self.nutrition.is_empty()) {
+            (Some(dish), true) => suggestions.push(format!("Сколько калорий в блюде «{}»?", dish)),
+            (Some(dish), false) => suggestions.push(format!("Как сделать блюдо «{}» менее калорийным?", dish)),
+            (None, false) => suggestions.push("Как уменьшить калорийность?".to_string()),
+            (None, true) => {}
+        }
+        if let Some(time) = &self.cook_time {
+            suggestions.push(format!("Можно ли приготовить это быстрее, чем за {}?", time.text));
+        }
+        if let Some(dish) = dish {
+            suggestions.push(format!("Что подать к блюду «{}»?", dish));
+            suggestions.push(format!("Чем заменить ингредиенты в рецепте «{}»?", dish));
+        }
+
+        suggestions
+    }
+
+    /// Карточки пересказывают только то, что есть в ответе
+    fn cards(&self) -> Vec<AiCard> {
+        let mut cards = Vec::new();
+
+        if let Some(dish) = self.dishes.first() {
+            cards.push(card("🍽️", &capitalize(&dish.text), &dish.sentence, "recipe", "high"));
+        }
+        if !self.nutrition.is_empty() {
+            cards.push(card("📊", "Пищевая ценность", &self.nutrition.join(", "), "nutrition", "medium"));
+        }
+        if let Some(time) = &self.cook_time {
+            cards.push(card("⏱️", &time.text, &time.sentence, "general", "medium"));
+        }
+        for dish in self.dishes.iter().skip(1) {
+            cards.push(card("🍽️", &capitalize(&dish.text), &dish.sentence, "recipe", "medium"));
+        }
+
+        cards.truncate(MAX_CARDS);
+        cards
+    }
+}
+
+fn card(emoji: &str, title: &str, content: &str, category: &str, priority: &str) -> AiCard {
+    let content = if content.chars().count() > MAX_CARD_CHARS {
+        format!("{}…", content.chars().take(MAX_CARD_CHARS).collect::<String>().trim_end())
+    } else {
+        content.to_string()
+    };
+
+    AiCard {
+        title: format!("{} {}", emoji, title),
+        content,
+        emoji: Some(emoji.to_string()),
+        category: Some(category.to_string()),
+        priority: Some(priority.to_string()),
+        action: None,
+    }
+}
+
+/// Предложения ответа; точка внутри числа ("1.5 часа") предложение не заканчивает
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let next_is_space = !matches!(chars.peek(), Some((_, next)) if !next.is_whitespace());
+        if matches!(c, '!' | '?' | '\n') || (c == '.' && next_is_space) {
+            let sentence = text[start..index + c.len_utf8()].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = index + c.len_utf8();
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+
+    sentences
+}
+
+fn clean_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric() && c != '-')
+        .to_lowercase()
+        .replace('ё', "е")
+}
+
+/// Число или диапазон ("20", "1.5", "20-30"); "~300" уже очищено от тильды
+fn number(word: &str) -> Option<&str> {
+    let mut has_digit = false;
+    for c in word.chars() {
+        match c {
+            '0'..='9' => has_digit = true,
+            '.' | ',' | '-' | '–' => {}
+            _ => return None,
+        }
+    }
+    has_digit.then_some(word)
+}
+
+/// Название блюда, если на этой позиции стоит слово-блюдо: вместе с прилагательным перед ним
+/// и уточнением "с ..."/"из ..." после. Винительный падеж приводится к именительному.
+fn dish_at(words: &[String], index: usize) -> Option<String> {
+    let word = &words[index];
+    if !DISH_WORDS.contains(&word.as_str()) {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    if let Some(adjective) = index.checked_sub(1).map(|previous| &words[previous]) {
+        let is_adjective = ADJECTIVE_ENDINGS.iter().any(|ending| adjective.ends_with(ending));
+        if is_adjective && !NOT_DISH_ADJECTIVES.contains(&adjective.as_str()) {
+            parts.push(nominative(adjective));
+        }
+    }
+    parts.push(nominative(word));
+    if let (Some(preposition), Some(addition)) = (words.get(index + 1), words.get(index + 2)) {
+        if (preposition == "с" || preposition == "из") && !addition.is_empty() && number(addition).is_none() {
+            parts.push(preposition.clone());
+            parts.push(addition.clone());
+        }
+    }
+
+    Some(parts.join(" "))
+}
+
+/// "куриную запеканку" → "куриная запеканка"; остальные формы не меняются
+fn nominative(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ую") {
+        format!("{}ая", stem)
+    } else if let Some(stem) = word.strip_suffix("юю") {
+        format!("{}яя", stem)
+    } else if DISH_WORDS.contains(&word) && word.ends_with('у') && word.chars().count() > 3 {
+        format!("{}а", word.trim_end_matches('у'))
+    } else if DISH_WORDS.contains(&word) && word.ends_with('ю') {
+        format!("{}я", word.trim_end_matches('ю'))
+    } else {
+        word.to_string()
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    /// Ответ сценария default Mock-провайдера для /ai/chat
+    const MOCK_ANSWER: &str = "Из курицы и овощей получится отличный суп! Отварите филе 20 минут, добавьте картофель и морковь, в конце — лапшу и зелень. Порция — около 300 ккал и 25 г белка.";
+
+    fn suggestions_of(body: &serde_json::Value) -> Vec<String> {
+        body["suggestions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|suggestion| suggestion.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn cards_retell_the_answer() {
+        let followups = from_answer("Что приготовить из курицы?", MOCK_ANSWER).unwrap();
+
+        let cards: Vec<(&str, &str)> = followups
+            .cards
+            .iter()
+            .map(|card| (card.title.as_str(), card.content.as_str()))
+            .collect();
+        assert_eq!(
+            cards,
+            vec![
+                ("🍽️ Суп", "Из курицы и овощей получится отличный суп!"),
+                ("📊 Пищевая ценность", "300 ккал, 25 г белка"),
+                ("⏱️ 20 минут", "Отварите филе 20 минут, добавьте картофель и морковь, в конце — лапшу и зелень."),
+            ]
+        );
+        assert_eq!(
+            followups.suggestions,
+            vec![
+                "Как сделать блюдо «суп» менее калорийным?",
+                "Можно ли приготовить это быстрее, чем за 20 минут?",
+                "Что подать к блюду «суп»?",
+            ]
+        );
+    }
+
+    #[test]
+    fn answer_without_dishes_or_numbers_gives_nothing() {
+        assert!(from_answer("Как дела?", "Хорошо, спасибо! Чем помочь на кухне?").is_none());
+    }
+
+    #[test]
+    fn question_is_not_suggested_back() {
+        let suggestions = vec![
+            "Что подать к блюду «суп»?".to_string(),
+            "Сколько калорий в блюде «суп»?".to_string(),
+            "сколько калорий в блюде суп".to_string(),
+            "Чем заменить ингредиенты в рецепте «суп»?".to_string(),
+            "Какие специи лучше добавить?".to_string(),
+        ];
+
+        // Регистр, кавычки и ё не делают подсказку новой; повторы схлопываются
+        assert_eq!(
+            without_question("что подать к блюду суп", suggestions),
+            vec![
+                "Сколько калорий в блюде «суп»?",
+                "Чем заменить ингредиенты в рецепте «суп»?",
+                "Какие специи лучше добавить?",
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn mock_chat_builds_followups_from_its_answer(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user).await;
+
+        let (status, body) = send(
+            &router,
+            request(Method::POST, "/api/v1/ai/chat", Some(&token), Some(json!({ "message": "Что приготовить из курицы?" }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["response"], MOCK_ANSWER);
+        let cards = body["cards"].as_array().unwrap();
+        assert!(!cards.is_empty());
+        for card in cards {
+            let content = card["content"].as_str().unwrap();
+            assert!(
+                MOCK_ANSWER.contains(content) || content.split(", ").all(|fact| MOCK_ANSWER.contains(fact)),
+                "card is not from the answer: {}",
+                content
+            );
+        }
+
+        // Пользователь нажал подсказку — она не возвращается следующей подсказкой
+        let asked = suggestions_of(&body)[0].clone();
+        let (status, body) = send(
+            &router,
+            request(Method::POST, "/api/v1/ai/chat", Some(&token), Some(json!({ "message": asked }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let suggestions = suggestions_of(&body);
+        assert!(!suggestions.is_empty());
+        assert!(!suggestions.contains(&asked), "{:?}", suggestions);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Источник текущего времени. Сервисы, зависящие от "сейчас" (сроки годности, дни дневника),
+/// берут время отсюда, а не из `Utc::now()`, чтобы в песочнице его можно было сдвинуть.
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Часы приложения: общие для всех запросов, передаются через `AppState`
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Системное время
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Часы по умолчанию для сервисов, созданных вне запроса
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Режим часов песочницы
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SandboxClockMode {
+    /// Системное время
+    #[default]
+    Real,
+    /// Системное время со сдвигом (может быть отрицательным)
+    Offset { seconds: i64 },
+    /// Время остановлено на заданном моменте
+    Frozen { at: DateTime<Utc> },
+}
+
+/// Часы песочницы: администратор сдвигает или останавливает время, чтобы воспроизвести
+/// ошибку, зависящую от даты ("серия сбросилась в воскресенье"). Только вне production.
+#[derive(Debug, Default)]
+pub struct SandboxClock {
+    mode: RwLock<SandboxClockMode>,
+}
+
+impl SandboxClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(&self) -> SandboxClockMode {
+        *self.mode.read().unwrap()
+    }
+
+    pub fn set_mode(&self, mode: SandboxClockMode) {
+        *self.mode.write().unwrap() = mode;
+    }
+}
+
+impl Clock for SandboxClock {
+    fn now(&self) -> DateTime<Utc> {
+        match self.mode() {
+            SandboxClockMode::Real => Utc::now(),
+            SandboxClockMode::Offset { seconds } => Utc::now() + Duration::seconds(seconds),
+            SandboxClockMode::Frozen { at } => at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn frozen_clock_returns_the_set_moment() {
+        let clock = SandboxClock::new();
+        clock.set_mode(SandboxClockMode::Frozen { at: at(16, 12, 0, 0) });
+
+        assert_eq!(clock.now(), at(16, 12, 0, 0));
+        assert_eq!(clock.now(), at(16, 12, 0, 0));
+    }
+
+    #[test]
+    fn frozen_clock_rolls_the_day_over_at_midnight() {
+        let clock = SandboxClock::new();
+        clock.set_mode(SandboxClockMode::Frozen { at: at(16, 23, 59, 59) });
+        assert_eq!(clock.now().date_naive(), NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+
+        clock.set_mode(SandboxClockMode::Frozen { at: at(17, 0, 0, 0) });
+        assert_eq!(clock.now().date_naive(), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap());
+    }
+
+    #[test]
+    fn offset_clock_shifts_system_time() {
+        let clock = SandboxClock::new();
+        clock.set_mode(SandboxClockMode::Offset { seconds: 86_400 });
+
+        let shifted = clock.now() - Utc::now();
+        assert!((shifted - Duration::days(1)).num_seconds().abs() <= 1);
+    }
+
+    #[test]
+    fn real_mode_follows_system_time() {
+        let clock = SandboxClock::new();
+        assert_eq!(clock.mode(), SandboxClockMode::Real);
+
+        assert!((clock.now() - Utc::now()).num_seconds().abs() <= 1);
+    }
+}
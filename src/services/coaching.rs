@@ -0,0 +1,273 @@
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::coaching::{CoachLink, CoachLinkStatus, CoachingDomain, CreateCoachLink},
+    services::{activity::ActivityService, auth::Claims},
+    utils::errors::AppError,
+};
+
+pub struct CoachingService {
+    pool: DbPool,
+}
+
+impl CoachingService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Клиент приглашает коуча по email с явным списком доменов
+    pub async fn create_invite(&self, invite: CreateCoachLink) -> Result<CoachLink, AppError> {
+        if invite.domains.is_empty() {
+            return Err(AppError::BadRequest("At least one data domain must be granted".to_string()));
+        }
+
+        let existing = sqlx::query_as::<_, CoachLink>(
+            r#"
+            SELECT * FROM coach_links
+            WHERE client_id = $1 AND LOWER(coach_email) = LOWER($2) AND status != 'revoked'
+            "#
+        )
+        .bind(invite.client_id)
+        .bind(&invite.coach_email)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if existing.is_some() {
+            return Err(AppError::BadRequest("This coach already has a pending or active link".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let link = sqlx::query_as::<_, CoachLink>(
+            r#"
+            INSERT INTO coach_links (id, client_id, coach_email, invite_token, status)
+            VALUES ($1, $2, $3, $4, 'pending')
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(invite.client_id)
+        .bind(invite.coach_email.to_lowercase())
+        .bind(Uuid::new_v4().to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for domain in &invite.domains {
+            sqlx::query(
+                "INSERT INTO coach_grants (id, link_id, domain) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+            )
+            .bind(Uuid::new_v4())
+            .bind(link.id)
+            .bind(domain)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        ActivityService::new(self.pool.clone())
+            .log(
+                invite.client_id,
+                Some(invite.client_id),
+                "coach_invite_created",
+                serde_json::json!({ "link_id": link.id, "coach_email": link.coach_email }),
+            )
+            .await?;
+
+        Ok(link)
+    }
+
+    /// Коуч принимает приглашение. Email аккаунта должен совпадать с адресом в приглашении.
+    pub async fn accept_invite(&self, token: &str, coach: &Claims) -> Result<CoachLink, AppError> {
+        let link = sqlx::query_as::<_, CoachLink>(
+            "SELECT * FROM coach_links WHERE invite_token = $1"
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Invite not found".to_string()))?;
+
+        if link.status != CoachLinkStatus::Pending {
+            return Err(AppError::BadRequest("Invite is no longer pending".to_string()));
+        }
+        if !link.coach_email.eq_ignore_ascii_case(&coach.email) {
+            return Err(AppError::Forbidden("Invite was issued to a different email".to_string()));
+        }
+        if link.client_id == coach.sub {
+            return Err(AppError::BadRequest("You cannot coach yourself".to_string()));
+        }
+
+        let link = sqlx::query_as::<_, CoachLink>(
+            r#"
+            UPDATE coach_links
+            SET coach_id = $1, status = 'active', accepted_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#
+        )
+        .bind(coach.sub)
+        .bind(link.id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        ActivityService::new(self.pool.clone())
+            .log(
+                link.client_id,
+                Some(coach.sub),
+                "coach_invite_accepted",
+                serde_json::json!({ "link_id": link.id }),
+            )
+            .await?;
+
+        Ok(link)
+    }
+
+    /// Клиент отзывает доступ. Действует немедленно: проверка гранта идёт на каждом запросе.
+    pub async fn revoke_link(&self, link_id: Uuid, client_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE coach_links
+            SET status = 'revoked', revoked_at = NOW()
+            WHERE id = $1 AND client_id = $2 AND status != 'revoked'
+            "#
+        )
+        .bind(link_id)
+        .bind(client_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Coach link not found".to_string()));
+        }
+
+        ActivityService::new(self.pool.clone())
+            .log(client_id, Some(client_id), "coach_link_revoked", serde_json::json!({ "link_id": link_id }))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Заменить набор доменов, к которым у коуча есть доступ
+    pub async fn update_grants(
+        &self,
+        link_id: Uuid,
+        client_id: Uuid,
+        domains: Vec<CoachingDomain>,
+    ) -> Result<Vec<CoachingDomain>, AppError> {
+        let link = self.get_client_link(link_id, client_id).await?;
+        if link.status == CoachLinkStatus::Revoked {
+            return Err(AppError::BadRequest("Coach link has been revoked".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM coach_grants WHERE link_id = $1")
+            .bind(link.id)
+            .execute(&mut *tx)
+            .await?;
+
+        for domain in &domains {
+            sqlx::query(
+                "INSERT INTO coach_grants (id, link_id, domain) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+            )
+            .bind(Uuid::new_v4())
+            .bind(link.id)
+            .bind(domain)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        self.get_link_domains(link.id).await
+    }
+
+    pub async fn get_client_links(&self, client_id: Uuid) -> Result<Vec<CoachLink>, AppError> {
+        let links = sqlx::query_as::<_, CoachLink>(
+            "SELECT * FROM coach_links WHERE client_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(client_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(links)
+    }
+
+    pub async fn get_coach_links(&self, coach_id: Uuid) -> Result<Vec<CoachLink>, AppError> {
+        let links = sqlx::query_as::<_, CoachLink>(
+            "SELECT * FROM coach_links WHERE coach_id = $1 AND status = 'active' ORDER BY accepted_at DESC"
+        )
+        .bind(coach_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(links)
+    }
+
+    pub async fn get_link_domains(&self, link_id: Uuid) -> Result<Vec<CoachingDomain>, AppError> {
+        let domains = sqlx::query_scalar::<_, CoachingDomain>(
+            "SELECT domain FROM coach_grants WHERE link_id = $1"
+        )
+        .bind(link_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(domains)
+    }
+
+    /// Слой авторизации коуча: проверяет активную связь и грант на домен,
+    /// после чего записывает факт чтения в журнал активности клиента.
+    pub async fn authorize_read(
+        &self,
+        coach_id: Uuid,
+        client_id: Uuid,
+        domain: CoachingDomain,
+        resource: &str,
+    ) -> Result<(), AppError> {
+        let granted = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM coach_links l
+                JOIN coach_grants g ON g.link_id = l.id
+                WHERE l.coach_id = $1 AND l.client_id = $2
+                  AND l.status = 'active' AND g.domain = $3
+            )
+            "#
+        )
+        .bind(coach_id)
+        .bind(client_id)
+        .bind(domain)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if !granted {
+            return Err(AppError::Forbidden(format!(
+                "No coaching access to '{}' data of this client",
+                domain.as_str()
+            )));
+        }
+
+        ActivityService::new(self.pool.clone())
+            .log(
+                client_id,
+                Some(coach_id),
+                "coach_read",
+                serde_json::json!({ "domain": domain.as_str(), "resource": resource }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_client_link(&self, link_id: Uuid, client_id: Uuid) -> Result<CoachLink, AppError> {
+        sqlx::query_as::<_, CoachLink>(
+            "SELECT * FROM coach_links WHERE id = $1 AND client_id = $2"
+        )
+        .bind(link_id)
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Coach link not found".to_string()))
+    }
+}
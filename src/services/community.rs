@@ -1,61 +1,135 @@
 use std::sync::Arc;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use tracing::instrument;
 use crate::{
-    models::community::{CreatePost, CreateComment, PostType},
-    api::community::{PostResponse, CommentResponse, FollowResponse, UserSummary},
-    services::realtime::RealtimeService,
+    config::PostEditConfig,
+    db::{DbPools, ReadConsistency},
+    models::community::{CreatePost, CreateComment, PostType, PostVisibility, LinkPreview},
+    api::community::{PostResponse, CommentResponse, FollowResponse, UserSummary, PublicPostResponse, PublicAuthor},
+    services::{
+        clock::{self, SharedClock},
+        link_previews::LinkPreviewService,
+        post_edits::PostEditService,
+        realtime::RealtimeService,
+    },
     utils::errors::AppError,
 };
 
+/// Свои посты для ленты активности (колонки — см. `services::timeline`); заголовок — начало текста
+pub const TIMELINE_POSTS_SQL: &str = r#"
+    SELECT 'post_published'::text AS kind, created_at AS occurred_at, id AS event_id, id AS reference_id,
+           left(content, 80) AS title,
+           jsonb_build_object('post_type', post_type, 'visibility', visibility, 'recipe_id', recipe_id) AS summary
+    FROM posts
+    WHERE author_id = $1
+"#;
+
+/// Пост ленты с автором и счетчиками; `$1` — текущий пользователь (для is_liked), может быть NULL
+const FEED_POST_SELECT: &str = r#"
+    SELECT p.id, p.content, p.post_type, p.recipe_id, r.name AS recipe_name,
+           COALESCE(p.media_urls, '{}') AS media_urls, COALESCE(p.tags, '{}') AS tags, p.location,
+           p.link_previews, p.edited_at, p.edit_count, p.created_at, p.updated_at,
+           p.author_id, u.first_name, u.last_name, u.avatar_url, COALESCE(u.is_verified, FALSE) AS is_verified,
+           (SELECT COUNT(*) FROM follows f WHERE f.following_id = p.author_id) AS followers_count,
+           (SELECT COUNT(*) FROM likes l WHERE l.post_id = p.id) AS likes_count,
+           (SELECT COUNT(*) FROM comments c WHERE c.post_id = p.id) AS comments_count,
+           EXISTS (SELECT 1 FROM likes l WHERE l.post_id = p.id AND l.user_id = $1) AS is_liked
+    FROM posts p
+    JOIN users u ON u.id = p.author_id
+    LEFT JOIN recipes r ON r.id = p.recipe_id
+"#;
+
+/// Пост виден зрителю `$1`: публичный, свой или автора, на которого зритель подписан
+const VISIBLE_TO_VIEWER: &str = r#"(p.visibility = 'public' OR p.author_id = $1
+    OR EXISTS (SELECT 1 FROM follows f WHERE f.follower_id = $1 AND f.following_id = p.author_id))"#;
+
+/// Комментарий с автором и счетчиками; `$1` — текущий пользователь (для is_liked), может быть NULL
+const COMMENT_SELECT: &str = r#"
+    SELECT c.id, c.content, c.parent_comment_id, c.edited_at, c.created_at, c.updated_at,
+           c.author_id, u.first_name, u.last_name, u.avatar_url, COALESCE(u.is_verified, FALSE) AS is_verified,
+           (SELECT COUNT(*) FROM follows f WHERE f.following_id = c.author_id) AS followers_count,
+           (SELECT COUNT(*) FROM likes l WHERE l.comment_id = c.id) AS likes_count,
+           (SELECT COUNT(*) FROM comments r WHERE r.parent_comment_id = c.id) AS replies_count,
+           EXISTS (SELECT 1 FROM likes l WHERE l.comment_id = c.id AND l.user_id = $1) AS is_liked
+    FROM comments c
+    JOIN users u ON u.id = c.author_id
+"#;
+
+/// Наибольшая страница ленты
+const MAX_FEED_PAGE: i64 = 50;
+/// Наибольшая страница комментариев
+const MAX_COMMENTS_PAGE: i64 = 100;
+/// Популярное — за последние дни и не больше этого числа постов
+const TRENDING_WINDOW_DAYS: i64 = 7;
+const TRENDING_LIMIT: i64 = 10;
+
 pub struct CommunityService {
-    pool: crate::db::DbPool,
+    pools: DbPools,
     realtime_service: Option<Arc<RealtimeService>>,
+    clock: SharedClock,
 }
 
 impl CommunityService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { 
-            pool,
+        Self::with_pools(DbPools::single(pool))
+    }
+
+    /// Ленты читаются с реплики; записи и чтение сразу после них — в основной базе
+    pub fn with_pools(pools: DbPools) -> Self {
+        Self {
+            pools,
             realtime_service: None,
+            clock: clock::system(),
         }
     }
 
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn with_realtime(pool: crate::db::DbPool, realtime_service: Arc<RealtimeService>) -> Self {
         Self { 
-            pool,
+            pools: DbPools::single(pool),
             realtime_service: Some(realtime_service),
+            clock: clock::system(),
         }
     }
 
+    /// Публикует пост и возвращает его в том виде, в каком он попадет в ленту
+    #[instrument(skip_all, fields(user_id = %post.author_id))]
     pub async fn create_post(&self, post: CreatePost) -> Result<PostResponse, AppError> {
-        // Mock implementation - in production, this would save to database
-        let post_id = Uuid::new_v4();
-        
-        let post_response = PostResponse {
-            id: post_id,
-            content: post.content.clone(),
-            post_type: post.post_type,
-            recipe_id: post.recipe_id,
-            recipe_name: if post.recipe_id.is_some() { 
-                Some("Mock Recipe Name".to_string()) 
-            } else { 
-                None 
-            },
-            media_urls: post.media_urls,
-            tags: post.tags,
-            location: post.location,
-            likes_count: 0,
-            comments_count: 0,
-            shares_count: 0,
-            is_liked: false,
-            author: self.get_mock_user_summary(post.author_id).await,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
-
-        // Отправляем WebSocket уведомление о новом посте
-        if let Some(realtime_service) = &self.realtime_service {
+        self.ensure_recipe_exists(post.recipe_id).await?;
+
+        let now = self.clock.now();
+        let post_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO posts (author_id, content, post_type, recipe_id, media_urls, tags, location, visibility,
+                               link_previews, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10)
+            RETURNING id
+            "#
+        )
+        .bind(post.author_id)
+        .bind(&post.content)
+        .bind(&post.post_type)
+        .bind(post.recipe_id)
+        .bind(&post.media_urls)
+        .bind(&post.tags)
+        .bind(&post.location)
+        .bind(post.visibility)
+        .bind(Json(&post.link_previews))
+        .bind(now)
+        .fetch_one(self.pools.primary())
+        .await?;
+
+        let post_response = self.find_post(post_id, Some(post.author_id), ReadConsistency::AfterWrite).await?;
+
+        // Отправляем WebSocket уведомление о новом посте; пост только для подписчиков
+        // не рассылается всем подключенным пользователям
+        if let (Some(realtime_service), PostVisibility::Public) = (&self.realtime_service, post.visibility) {
             let author_name = format!("{} {}", 
                 post_response.author.first_name, 
                 post_response.author.last_name
@@ -70,298 +144,604 @@ impl CommunityService {
         Ok(post_response)
     }
 
+    /// Лента пользователя: публичные посты, свои и посты «для подписчиков» тех, на кого он подписан.
+    /// Новые первыми; `following_only` оставляет только авторов из подписок.
+    #[instrument(skip_all, fields(user_id = %user_id, limit = limit, offset = offset))]
     pub async fn get_feed(
         &self,
         user_id: Uuid,
         post_type: Option<PostType>,
-        _following_only: bool,
-        _tag: Option<String>,
+        following_only: bool,
+        tag: Option<String>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<PostResponse>, AppError> {
-        // Mock implementation
-        self.get_mock_posts(Some(user_id), post_type, limit, offset).await
+        let sql = format!(
+            r#"
+            {FEED_POST_SELECT}
+            WHERE ($2::post_type IS NULL OR p.post_type = $2)
+              AND ($3::TEXT IS NULL OR $3 = ANY(p.tags))
+              AND (NOT $4 OR EXISTS (SELECT 1 FROM follows f WHERE f.follower_id = $1 AND f.following_id = p.author_id))
+              AND {VISIBLE_TO_VIEWER}
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT $5 OFFSET $6
+            "#
+        );
+        let rows = sqlx::query_as::<_, FeedPostRow>(&sql)
+            .bind(user_id)
+            .bind(post_type)
+            .bind(tag.map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()))
+            .bind(following_only)
+            .bind(limit.clamp(1, MAX_FEED_PAGE))
+            .bind(offset.max(0))
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(rows.into_iter().map(FeedPostRow::into_response).collect())
+    }
+
+    /// Лента для гостей: только публичные посты. Видимость проверяется при каждом
+    /// запросе, поэтому пост, ставший `followers_only`, сразу пропадает из ленты.
+    #[instrument(skip_all, fields(limit = limit, offset = offset))]
+    pub async fn get_public_feed(&self, limit: i64, offset: i64) -> Result<Vec<PublicPostResponse>, AppError> {
+        let rows = sqlx::query_as::<_, PublicPostRow>(
+            r#"
+            SELECT p.id, p.content, p.post_type, p.recipe_id, r.name AS recipe_name,
+                   COALESCE(p.media_urls, '{}') AS media_urls, COALESCE(p.tags, '{}') AS tags, p.link_previews, p.created_at,
+                   u.first_name, u.avatar_url,
+                   (SELECT COUNT(*) FROM likes l WHERE l.post_id = p.id) AS likes_count,
+                   (SELECT COUNT(*) FROM comments c WHERE c.post_id = p.id) AS comments_count,
+                   (SELECT COUNT(*) FROM post_cooked k WHERE k.post_id = p.id) AS cooked_count
+            FROM posts p
+            JOIN users u ON u.id = p.author_id
+            LEFT JOIN recipes r ON r.id = p.recipe_id
+            WHERE p.visibility = $1
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT $2 OFFSET $3
+            "#
+        )
+        .bind(PostVisibility::Public)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        Ok(rows.into_iter().map(PublicPostRow::into_response).collect())
     }
 
+    /// Пост, если пользователь может его видеть; невидимые и несуществующие посты неразличимы
     pub async fn get_post_by_id(&self, id: Uuid, user_id: Option<Uuid>) -> Result<PostResponse, AppError> {
-        // Mock implementation
-        self.get_mock_post(id, user_id).await
+        self.find_post(id, user_id, ReadConsistency::Replica).await
     }
 
+    /// Правка поста автором: текст (с окном правки и историей версий), остальные поля и карточки ссылок.
+    /// Видимость не указана — остается прежней.
+    #[instrument(skip_all, fields(user_id = %user_id, post_id = %id))]
     pub async fn update_post(
         &self,
         id: Uuid,
         user_id: Uuid,
         payload: crate::api::community::CreatePostRequest,
+        link_previews: Vec<LinkPreview>,
+        edit_config: &PostEditConfig,
     ) -> Result<PostResponse, AppError> {
-        // Mock implementation - in production, verify ownership and update database
-        Ok(PostResponse {
-            id,
-            content: payload.content,
-            post_type: payload.post_type,
-            recipe_id: payload.recipe_id,
-            recipe_name: if payload.recipe_id.is_some() { 
-                Some("Updated Recipe Name".to_string()) 
-            } else { 
-                None 
-            },
-            media_urls: payload.media_urls.unwrap_or_default(),
-            tags: payload.tags.unwrap_or_default(),
-            location: payload.location,
-            likes_count: 15,
-            comments_count: 8,
-            shares_count: 3,
-            is_liked: true,
-            author: self.get_mock_user_summary(user_id).await,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        PostEditService::new(self.pools.primary().clone())
+            .edit_post(id, user_id, &payload.content, edit_config, self.clock.now())
+            .await?;
+        self.ensure_recipe_exists(payload.recipe_id).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE posts
+            SET post_type = $3, recipe_id = $4, media_urls = $5, tags = $6, location = $7,
+                visibility = COALESCE($8, visibility)
+            WHERE id = $1 AND author_id = $2
+            "#
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&payload.post_type)
+        .bind(payload.recipe_id)
+        .bind(payload.media_urls.unwrap_or_default())
+        .bind(payload.tags.unwrap_or_default())
+        .bind(payload.location)
+        .bind(payload.visibility)
+        .execute(self.pools.primary())
+        .await?;
+        LinkPreviewService::new(self.pools.primary().clone()).store(id, user_id, &link_previews).await?;
+
+        self.find_post(id, Some(user_id), ReadConsistency::AfterWrite).await
     }
 
-    pub async fn delete_post(&self, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
-        // Mock implementation - in production, verify ownership and delete from database
+    /// Удаляет пост автора вместе с комментариями, лайками и просмотрами
+    #[instrument(skip_all, fields(user_id = %user_id, post_id = %id))]
+    pub async fn delete_post(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let author_id = sqlx::query_scalar::<_, Uuid>("SELECT author_id FROM posts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pools.primary())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Post not found".to_string()))?;
+        if author_id != user_id {
+            return Err(AppError::Forbidden("Only the author can delete this post".to_string()));
+        }
+
+        sqlx::query("DELETE FROM posts WHERE id = $1")
+            .bind(id)
+            .execute(self.pools.primary())
+            .await?;
+
         Ok(())
     }
 
-    pub async fn toggle_post_like(&self, _post_id: Uuid, _user_id: Uuid) -> Result<bool, AppError> {
-        // Mock implementation - in production, toggle like status in database
-        Ok(true) // Return true indicating post is now liked
+    /// Ставит или снимает лайк; возвращает, стоит ли лайк теперь
+    #[instrument(skip_all, fields(user_id = %user_id, post_id = %post_id))]
+    pub async fn toggle_post_like(&self, post_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        self.ensure_post_visible(post_id, Some(user_id)).await?;
+
+        let removed = sqlx::query("DELETE FROM likes WHERE user_id = $1 AND post_id = $2")
+            .bind(user_id)
+            .bind(post_id)
+            .execute(self.pools.primary())
+            .await?
+            .rows_affected();
+        if removed > 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("INSERT INTO likes (user_id, post_id, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(user_id)
+            .bind(post_id)
+            .bind(self.clock.now())
+            .execute(self.pools.primary())
+            .await?;
+
+        Ok(true)
     }
 
+    /// Комментарий к видимому пользователю посту; ответ — только на комментарий того же поста
+    #[instrument(skip_all, fields(user_id = %comment.author_id, post_id = %comment.post_id))]
     pub async fn create_comment(&self, comment: CreateComment) -> Result<CommentResponse, AppError> {
-        // Mock implementation
-        let comment_id = Uuid::new_v4();
-        
-        Ok(CommentResponse {
-            id: comment_id,
-            content: comment.content,
-            parent_comment_id: comment.parent_comment_id,
-            likes_count: 0,
-            replies_count: 0,
-            is_liked: false,
-            author: self.get_mock_user_summary(comment.author_id).await,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        self.ensure_post_visible(comment.post_id, Some(comment.author_id)).await?;
+
+        if let Some(parent_id) = comment.parent_comment_id {
+            let parent_post_id = sqlx::query_scalar::<_, Uuid>("SELECT post_id FROM comments WHERE id = $1")
+                .bind(parent_id)
+                .fetch_optional(self.pools.primary())
+                .await?;
+            if parent_post_id != Some(comment.post_id) {
+                return Err(AppError::BadRequest("Parent comment does not belong to this post".to_string()));
+            }
+        }
+
+        let now = self.clock.now();
+        let comment_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO comments (post_id, author_id, content, parent_comment_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $5)
+            RETURNING id
+            "#
+        )
+        .bind(comment.post_id)
+        .bind(comment.author_id)
+        .bind(&comment.content)
+        .bind(comment.parent_comment_id)
+        .bind(now)
+        .fetch_one(self.pools.primary())
+        .await?;
+
+        self.find_comment(comment_id, comment.author_id, ReadConsistency::AfterWrite).await
     }
 
+    /// Комментарии поста по времени, старые первыми
+    #[instrument(skip_all, fields(post_id = %post_id, limit = limit, offset = offset))]
     pub async fn get_post_comments(
         &self,
-        _post_id: Uuid,
+        post_id: Uuid,
         user_id: Option<Uuid>,
         limit: i64,
-        _offset: i64,
+        offset: i64,
     ) -> Result<Vec<CommentResponse>, AppError> {
-        // Mock implementation
-        self.get_mock_comments(user_id, limit).await
+        self.ensure_post_visible(post_id, user_id).await?;
+
+        let sql = format!(
+            r#"
+            {COMMENT_SELECT}
+            WHERE c.post_id = $2
+            ORDER BY c.created_at, c.id
+            LIMIT $3 OFFSET $4
+            "#
+        );
+        let rows = sqlx::query_as::<_, CommentRow>(&sql)
+            .bind(user_id)
+            .bind(post_id)
+            .bind(limit.clamp(1, MAX_COMMENTS_PAGE))
+            .bind(offset.max(0))
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(rows.into_iter().map(CommentRow::into_response).collect())
     }
 
+    #[instrument(skip_all, fields(user_id = %user_id, comment_id = %id))]
     pub async fn update_comment(
         &self,
         id: Uuid,
         user_id: Uuid,
         content: String,
     ) -> Result<CommentResponse, AppError> {
-        // Mock implementation
-        Ok(CommentResponse {
-            id,
-            content,
-            parent_comment_id: None,
-            likes_count: 5,
-            replies_count: 2,
-            is_liked: false,
-            author: self.get_mock_user_summary(user_id).await,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        PostEditService::new(self.pools.primary().clone())
+            .edit_comment(id, user_id, &content, self.clock.now())
+            .await?;
+
+        self.find_comment(id, user_id, ReadConsistency::AfterWrite).await
     }
 
-    pub async fn delete_comment(&self, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
-        // Mock implementation - in production, verify ownership and delete from database
+    /// Удаляет комментарий автора вместе с ответами на него
+    #[instrument(skip_all, fields(user_id = %user_id, comment_id = %id))]
+    pub async fn delete_comment(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let author_id = sqlx::query_scalar::<_, Uuid>("SELECT author_id FROM comments WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pools.primary())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Comment not found".to_string()))?;
+        if author_id != user_id {
+            return Err(AppError::Forbidden("Only the author can delete this comment".to_string()));
+        }
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE thread AS (
+                SELECT id FROM comments WHERE id = $1
+                UNION ALL
+                SELECT c.id FROM comments c JOIN thread t ON c.parent_comment_id = t.id
+            )
+            DELETE FROM comments WHERE id IN (SELECT id FROM thread)
+            "#
+        )
+        .bind(id)
+        .execute(self.pools.primary())
+        .await?;
+
         Ok(())
     }
 
-    pub async fn toggle_follow(&self, _follower_id: Uuid, _following_id: Uuid) -> Result<bool, AppError> {
-        // Mock implementation - in production, toggle follow status in database
-        Ok(true) // Return true indicating now following
+    /// Подписывает или отписывает; возвращает, подписан ли пользователь теперь
+    #[instrument(skip_all, fields(user_id = %follower_id, following_id = %following_id))]
+    pub async fn toggle_follow(&self, follower_id: Uuid, following_id: Uuid) -> Result<bool, AppError> {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+            .bind(following_id)
+            .fetch_one(self.pools.primary())
+            .await?;
+        if !exists {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let removed = sqlx::query("DELETE FROM follows WHERE follower_id = $1 AND following_id = $2")
+            .bind(follower_id)
+            .bind(following_id)
+            .execute(self.pools.primary())
+            .await?
+            .rows_affected();
+        if removed > 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO follows (follower_id, following_id, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        )
+        .bind(follower_id)
+        .bind(following_id)
+        .bind(self.clock.now())
+        .execute(self.pools.primary())
+        .await?;
+
+        Ok(true)
     }
 
+    /// Посты автора, которые может видеть зритель; новые первыми
+    #[instrument(skip_all, fields(user_id = %user_id, limit = limit, offset = offset))]
     pub async fn get_user_posts(
         &self,
         user_id: Uuid,
-        _viewer_id: Option<Uuid>,
+        viewer_id: Option<Uuid>,
         post_type: Option<PostType>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<PostResponse>, AppError> {
-        // Mock implementation
-        self.get_mock_posts(Some(user_id), post_type, limit, offset).await
+        let sql = format!(
+            r#"
+            {FEED_POST_SELECT}
+            WHERE p.author_id = $2
+              AND ($3::post_type IS NULL OR p.post_type = $3)
+              AND {VISIBLE_TO_VIEWER}
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT $4 OFFSET $5
+            "#
+        );
+        let rows = sqlx::query_as::<_, FeedPostRow>(&sql)
+            .bind(viewer_id)
+            .bind(user_id)
+            .bind(post_type)
+            .bind(limit.clamp(1, MAX_FEED_PAGE))
+            .bind(offset.max(0))
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(rows.into_iter().map(FeedPostRow::into_response).collect())
     }
 
+    /// Подписчики пользователя, новые первыми
     pub async fn get_followers(&self, user_id: Uuid) -> Result<Vec<FollowResponse>, AppError> {
-        // Mock implementation
-        self.get_mock_follows(user_id, true).await
+        self.follows(user_id, "f.follower_id", "f.following_id").await
     }
 
+    /// На кого подписан пользователь, новые подписки первыми
     pub async fn get_following(&self, user_id: Uuid) -> Result<Vec<FollowResponse>, AppError> {
-        // Mock implementation
-        self.get_mock_follows(user_id, false).await
+        self.follows(user_id, "f.following_id", "f.follower_id").await
     }
 
+    /// Популярные публичные посты за последнюю неделю: по лайкам и комментариям
+    #[instrument(skip_all)]
     pub async fn get_trending_posts(&self, user_id: Option<Uuid>) -> Result<Vec<PostResponse>, AppError> {
-        // Mock implementation - return posts sorted by popularity
-        self.get_mock_posts(user_id, None, 10, 0).await
+        let sql = format!(
+            r#"
+            {FEED_POST_SELECT}
+            WHERE p.visibility = 'public' AND p.created_at >= $2
+            ORDER BY likes_count + comments_count DESC, p.created_at DESC, p.id DESC
+            LIMIT $3
+            "#
+        );
+        let rows = sqlx::query_as::<_, FeedPostRow>(&sql)
+            .bind(user_id)
+            .bind(self.clock.now() - chrono::Duration::days(TRENDING_WINDOW_DAYS))
+            .bind(TRENDING_LIMIT)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(rows.into_iter().map(FeedPostRow::into_response).collect())
+    }
+
+    /// `other_column` — пользователь в ответе, `own_column` — тот, чьи подписки смотрим
+    async fn follows(&self, user_id: Uuid, other_column: &str, own_column: &str) -> Result<Vec<FollowResponse>, AppError> {
+        let sql = format!(
+            r#"
+            SELECT f.id, f.created_at AS followed_at,
+                   u.id AS user_id, u.first_name, u.last_name, u.avatar_url, COALESCE(u.is_verified, FALSE) AS is_verified,
+                   (SELECT COUNT(*) FROM follows x WHERE x.following_id = u.id) AS followers_count
+            FROM follows f
+            JOIN users u ON u.id = {other_column}
+            WHERE {own_column} = $1
+            ORDER BY f.created_at DESC, f.id DESC
+            "#
+        );
+        let rows = sqlx::query_as::<_, FollowRow>(&sql)
+            .bind(user_id)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(rows.into_iter().map(FollowRow::into_response).collect())
     }
 
-    // Mock implementations for testing without database
-    async fn get_mock_user_summary(&self, user_id: Uuid) -> UserSummary {
-        UserSummary {
-            id: user_id,
-            first_name: "John".to_string(),
-            last_name: "Doe".to_string(),
-            avatar_url: Some("https://example.com/avatar.jpg".to_string()),
-            is_verified: true,
-            followers_count: 1250,
+    async fn find_post(&self, id: Uuid, viewer_id: Option<Uuid>, consistency: ReadConsistency) -> Result<PostResponse, AppError> {
+        let sql = format!("{FEED_POST_SELECT} WHERE p.id = $2 AND {VISIBLE_TO_VIEWER}");
+        sqlx::query_as::<_, FeedPostRow>(&sql)
+            .bind(viewer_id)
+            .bind(id)
+            .fetch_optional(self.pools.read(consistency))
+            .await?
+            .map(FeedPostRow::into_response)
+            .ok_or_else(|| AppError::NotFound("Post not found".to_string()))
+    }
+
+    async fn ensure_post_visible(&self, id: Uuid, viewer_id: Option<Uuid>) -> Result<(), AppError> {
+        let sql = format!("SELECT EXISTS (SELECT 1 FROM posts p WHERE p.id = $2 AND {VISIBLE_TO_VIEWER})");
+        let visible = sqlx::query_scalar::<_, bool>(&sql)
+            .bind(viewer_id)
+            .bind(id)
+            .fetch_one(self.pools.primary())
+            .await?;
+        if !visible {
+            return Err(AppError::NotFound("Post not found".to_string()));
         }
+        Ok(())
     }
 
-    async fn get_mock_post(&self, id: Uuid, user_id: Option<Uuid>) -> Result<PostResponse, AppError> {
-        let author_id = user_id.unwrap_or_else(Uuid::new_v4);
-        
-        Ok(PostResponse {
-            id,
-            content: "Check out this amazing recipe I just made! 🍝".to_string(),
-            post_type: PostType::Recipe,
-            recipe_id: Some(Uuid::new_v4()),
-            recipe_name: Some("Delicious Pasta Carbonara".to_string()),
-            media_urls: vec!["https://example.com/pasta1.jpg".to_string()],
-            tags: vec!["pasta".to_string(), "italian".to_string(), "dinner".to_string()],
-            location: Some("Kitchen".to_string()),
-            likes_count: 42,
-            comments_count: 18,
-            shares_count: 7,
-            is_liked: user_id.is_some(),
-            author: self.get_mock_user_summary(author_id).await,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+    async fn find_comment(&self, id: Uuid, viewer_id: Uuid, consistency: ReadConsistency) -> Result<CommentResponse, AppError> {
+        let sql = format!("{COMMENT_SELECT} WHERE c.id = $2");
+        sqlx::query_as::<_, CommentRow>(&sql)
+            .bind(viewer_id)
+            .bind(id)
+            .fetch_optional(self.pools.read(consistency))
+            .await?
+            .map(CommentRow::into_response)
+            .ok_or_else(|| AppError::NotFound("Comment not found".to_string()))
     }
 
-    async fn get_mock_posts(
-        &self,
-        user_id: Option<Uuid>,
-        post_type: Option<PostType>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<PostResponse>, AppError> {
-        let mut posts = vec![];
-        
-        // Generate different mock posts
-        for i in 0..std::cmp::min(limit, 10) {
-            let post_id = Uuid::new_v4();
-            let author_id = user_id.unwrap_or_else(Uuid::new_v4);
-            
-            let mock_post_type = match i % 3 {
-                0 => PostType::Recipe,
-                1 => PostType::Photo,
-                _ => PostType::Text,
-            };
-
-            // Filter by post_type if specified
-            if let Some(filter_type) = &post_type {
-                if mock_post_type != *filter_type {
-                    continue;
-                }
-            }
-            
-            let post = PostResponse {
-                id: post_id,
-                content: format!("This is mock post {} with some interesting content!", i + 1),
-                post_type: mock_post_type.clone(),
-                recipe_id: if mock_post_type == PostType::Recipe { 
-                    Some(Uuid::new_v4()) 
-                } else { 
-                    None 
-                },
-                recipe_name: if mock_post_type == PostType::Recipe { 
-                    Some(format!("Recipe {}", i + 1)) 
-                } else { 
-                    None 
-                },
-                media_urls: if mock_post_type != PostType::Text {
-                    vec![format!("https://example.com/image{}.jpg", i + 1)]
-                } else {
-                    vec![]
-                },
-                tags: vec![format!("tag{}", i + 1), "food".to_string()],
-                location: Some(format!("Location {}", i + 1)),
-                likes_count: (i as i32 + 1) * 10,
-                comments_count: (i as i32 + 1) * 3,
-                shares_count: (i as i32 + 1),
-                is_liked: i % 2 == 0,
-                author: self.get_mock_user_summary(author_id).await,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            };
-            posts.push(post);
+    async fn ensure_recipe_exists(&self, recipe_id: Option<Uuid>) -> Result<(), AppError> {
+        let Some(recipe_id) = recipe_id else { return Ok(()) };
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM recipes WHERE id = $1)")
+            .bind(recipe_id)
+            .fetch_one(self.pools.primary())
+            .await?;
+        if !exists {
+            return Err(AppError::NotFound("Recipe not found".to_string()));
         }
-        
-        let start = offset as usize;
-        let end = std::cmp::min(start + limit as usize, posts.len());
-        
-        if start >= posts.len() {
-            Ok(vec![])
-        } else {
-            Ok(posts[start..end].to_vec())
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedPostRow {
+    id: Uuid,
+    content: String,
+    post_type: PostType,
+    recipe_id: Option<Uuid>,
+    recipe_name: Option<String>,
+    media_urls: Vec<String>,
+    tags: Vec<String>,
+    location: Option<String>,
+    link_previews: Json<Vec<LinkPreview>>,
+    edited_at: Option<DateTime<Utc>>,
+    edit_count: i32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    author_id: Uuid,
+    first_name: String,
+    last_name: String,
+    avatar_url: Option<String>,
+    is_verified: bool,
+    followers_count: i64,
+    likes_count: i64,
+    comments_count: i64,
+    is_liked: bool,
+}
+
+impl FeedPostRow {
+    /// Просмотры и «приготовил(а)» подставляет обработчик (`with_counts`)
+    fn into_response(self) -> PostResponse {
+        PostResponse {
+            id: self.id,
+            content: self.content,
+            post_type: self.post_type,
+            recipe_id: self.recipe_id,
+            recipe_name: self.recipe_name,
+            media_urls: self.media_urls,
+            tags: self.tags,
+            location: self.location,
+            likes_count: self.likes_count as i32,
+            comments_count: self.comments_count as i32,
+            shares_count: 0,
+            views_count: 0,
+            cooked_count: 0,
+            is_liked: self.is_liked,
+            author: UserSummary {
+                id: self.author_id,
+                first_name: self.first_name,
+                last_name: self.last_name,
+                avatar_url: self.avatar_url,
+                is_verified: self.is_verified,
+                followers_count: self.followers_count as i32,
+            },
+            link_previews: self.link_previews.0,
+            is_edited: self.edited_at.is_some(),
+            edited_at: self.edited_at,
+            edit_count: self.edit_count,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
         }
     }
+}
 
-    async fn get_mock_comments(&self, user_id: Option<Uuid>, limit: i64) -> Result<Vec<CommentResponse>, AppError> {
-        let mut comments = vec![];
-        
-        for i in 0..std::cmp::min(limit, 5) {
-            let comment_id = Uuid::new_v4();
-            let author_id = user_id.unwrap_or_else(Uuid::new_v4);
-            
-            let comment = CommentResponse {
-                id: comment_id,
-                content: format!("This is a great comment number {}!", i + 1),
-                parent_comment_id: if i > 2 { Some(Uuid::new_v4()) } else { None },
-                likes_count: (i as i32 + 1) * 2,
-                replies_count: if i < 2 { i as i32 } else { 0 },
-                is_liked: i % 2 == 1,
-                author: self.get_mock_user_summary(author_id).await,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            };
-            comments.push(comment);
+#[derive(sqlx::FromRow)]
+struct PublicPostRow {
+    id: Uuid,
+    content: String,
+    post_type: PostType,
+    recipe_id: Option<Uuid>,
+    recipe_name: Option<String>,
+    media_urls: Vec<String>,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    first_name: String,
+    avatar_url: Option<String>,
+    likes_count: i64,
+    comments_count: i64,
+    cooked_count: i64,
+    link_previews: Json<Vec<LinkPreview>>,
+}
+
+impl PublicPostRow {
+    fn into_response(self) -> PublicPostResponse {
+        PublicPostResponse {
+            id: self.id,
+            content: self.content,
+            post_type: self.post_type,
+            recipe_id: self.recipe_id,
+            recipe_name: self.recipe_name,
+            media_urls: self.media_urls,
+            tags: self.tags,
+            likes_count: self.likes_count,
+            comments_count: self.comments_count,
+            cooked_count: self.cooked_count,
+            author: PublicAuthor {
+                first_name: self.first_name,
+                avatar_url: self.avatar_url,
+            },
+            link_previews: self.link_previews.0,
+            created_at: self.created_at,
         }
-        
-        Ok(comments)
     }
+}
 
-    async fn get_mock_follows(&self, _user_id: Uuid, _is_followers: bool) -> Result<Vec<FollowResponse>, AppError> {
-        let mut follows = vec![];
-        
-        for i in 0..5 {
-            let follow_id = Uuid::new_v4();
-            let user_id = Uuid::new_v4();
-            
-            let follow = FollowResponse {
-                id: follow_id,
-                user: UserSummary {
-                    id: user_id,
-                    first_name: format!("User{}", i + 1),
-                    last_name: "Smith".to_string(),
-                    avatar_url: Some(format!("https://example.com/avatar{}.jpg", i + 1)),
-                    is_verified: i % 2 == 0,
-                    followers_count: (i as i32 + 1) * 100,
-                },
-                followed_at: Utc::now(),
-            };
-            follows.push(follow);
+#[derive(sqlx::FromRow)]
+struct CommentRow {
+    id: Uuid,
+    content: String,
+    parent_comment_id: Option<Uuid>,
+    edited_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    author_id: Uuid,
+    first_name: String,
+    last_name: String,
+    avatar_url: Option<String>,
+    is_verified: bool,
+    followers_count: i64,
+    likes_count: i64,
+    replies_count: i64,
+    is_liked: bool,
+}
+
+impl CommentRow {
+    fn into_response(self) -> CommentResponse {
+        CommentResponse {
+            id: self.id,
+            content: self.content,
+            parent_comment_id: self.parent_comment_id,
+            likes_count: self.likes_count as i32,
+            replies_count: self.replies_count as i32,
+            is_liked: self.is_liked,
+            author: UserSummary {
+                id: self.author_id,
+                first_name: self.first_name,
+                last_name: self.last_name,
+                avatar_url: self.avatar_url,
+                is_verified: self.is_verified,
+                followers_count: self.followers_count as i32,
+            },
+            is_edited: self.edited_at.is_some(),
+            edited_at: self.edited_at,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct FollowRow {
+    id: Uuid,
+    followed_at: DateTime<Utc>,
+    user_id: Uuid,
+    first_name: String,
+    last_name: String,
+    avatar_url: Option<String>,
+    is_verified: bool,
+    followers_count: i64,
+}
+
+impl FollowRow {
+    fn into_response(self) -> FollowResponse {
+        FollowResponse {
+            id: self.id,
+            user: UserSummary {
+                id: self.user_id,
+                first_name: self.first_name,
+                last_name: self.last_name,
+                avatar_url: self.avatar_url,
+                is_verified: self.is_verified,
+                followers_count: self.followers_count as i32,
+            },
+            followed_at: self.followed_at,
         }
-        
-        Ok(follows)
     }
 }
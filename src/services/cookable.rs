@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::ExpiryUrgency,
+        recipe::{CookableRecipe, Recipe, RecipeIngredient},
+    },
+    services::{
+        clock::{self, SharedClock},
+        fridge::FridgeService,
+        recipe_cost::{estimate_cost, CostIngredient, PriceBook, RecipeCostService},
+    },
+    utils::{errors::AppError, format::round_percent, ingredient_matcher::IngredientIndex},
+};
+
+/// Доля имеющихся ингредиентов по умолчанию, начиная с которой рецепт показывается
+pub const DEFAULT_MIN_MATCH: f64 = 60.0;
+
+/// Сколько публичных рецептов просматривается за запрос
+const PUBLIC_CATALOG_LIMIT: i64 = 500;
+
+/// «Что приготовить прямо сейчас»: детерминированное сопоставление рецептов
+/// с холодильником без обращения к ИИ. Продукты нормализуются один раз на запрос.
+pub struct CookableService {
+    pool: DbPool,
+    /// Больше продуктов — сопоставление только по точному названию
+    max_fridge_items: usize,
+    clock: SharedClock,
+}
+
+/// Рецепт с ингредиентами для сопоставления
+pub struct CookableCandidate {
+    pub recipe: Recipe,
+    pub ingredients: Vec<RecipeIngredient>,
+    pub is_saved: bool,
+}
+
+impl CookableService {
+    pub fn new(pool: DbPool, max_fridge_items: usize) -> Self {
+        Self { pool, max_fridge_items, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn find_cookable(&self, user_id: Uuid, include_public: bool, min_match: f64) -> Result<Vec<CookableRecipe>, AppError> {
+        let items = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone()).get_user_items(user_id, None, None, None).await?;
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let exact_only = items.len() > self.max_fridge_items;
+        let now = self.clock.now();
+        let fridge = IngredientIndex::new(items.iter().map(|item| item.name.as_str()), exact_only);
+        let expiring = IngredientIndex::new(
+            items
+                .iter()
+                .filter(|item| {
+                    matches!(
+                        item.expiry_urgency(now),
+                        Some(ExpiryUrgency::Expired | ExpiryUrgency::Today | ExpiryUrgency::Soon)
+                    )
+                })
+                .map(|item| item.name.as_str()),
+            exact_only,
+        );
+
+        let prices = RecipeCostService::new(self.pool.clone()).price_book(user_id).await?;
+        let candidates = self.load_candidates(user_id, include_public).await?;
+        Ok(rank_cookable(candidates, &fridge, &expiring, &prices, min_match))
+    }
+
+    /// Свои и сохраненные рецепты пользователя, по запросу — еще и публичный каталог
+    async fn load_candidates(&self, user_id: Uuid, include_public: bool) -> Result<Vec<CookableCandidate>, AppError> {
+        let saved = sqlx::query_as::<_, Recipe>(
+            r#"
+            SELECT r.* FROM recipes r
+            WHERE r.created_by = $1
+               OR EXISTS (SELECT 1 FROM recipe_favorites f WHERE f.recipe_id = r.id AND f.user_id = $1)
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let saved_ids: HashSet<Uuid> = saved.iter().map(|recipe| recipe.id).collect();
+        let mut recipes: Vec<(Recipe, bool)> = saved.into_iter().map(|recipe| (recipe, true)).collect();
+
+        if include_public {
+            let public = sqlx::query_as::<_, Recipe>(
+                "SELECT * FROM recipes WHERE is_public = TRUE ORDER BY created_at DESC LIMIT $1"
+            )
+            .bind(PUBLIC_CATALOG_LIMIT)
+            .fetch_all(&self.pool)
+            .await?;
+
+            recipes.extend(
+                public
+                    .into_iter()
+                    .filter(|recipe| !saved_ids.contains(&recipe.id))
+                    .map(|recipe| (recipe, false)),
+            );
+        }
+
+        let ids: Vec<Uuid> = recipes.iter().map(|(recipe, _)| recipe.id).collect();
+        let ingredients = sqlx::query_as::<_, RecipeIngredient>(
+            "SELECT * FROM recipe_ingredients WHERE recipe_id = ANY($1)"
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_recipe: HashMap<Uuid, Vec<RecipeIngredient>> = HashMap::new();
+        for ingredient in ingredients {
+            by_recipe.entry(ingredient.recipe_id).or_default().push(ingredient);
+        }
+
+        Ok(recipes
+            .into_iter()
+            .map(|(recipe, is_saved)| CookableCandidate {
+                ingredients: by_recipe.remove(&recipe.id).unwrap_or_default(),
+                recipe,
+                is_saved,
+            })
+            .collect())
+    }
+}
+
+/// Ранжирование: сначала по доле имеющихся ингредиентов, затем по числу ингредиентов,
+/// закрывающих истекающие продукты, затем по числу недостающих и по названию.
+/// Рецепты без ингредиентов и ниже `min_match` (в процентах) отбрасываются.
+/// Каждому рецепту добавляется оценка стоимости по `prices`.
+pub fn rank_cookable(
+    candidates: Vec<CookableCandidate>,
+    fridge: &IngredientIndex,
+    expiring: &IngredientIndex,
+    prices: &PriceBook,
+    min_match: f64,
+) -> Vec<CookableRecipe> {
+    let mut cookable: Vec<(f64, CookableRecipe)> = candidates
+        .into_iter()
+        .filter(|candidate| !candidate.ingredients.is_empty())
+        .filter_map(|candidate| {
+            let total = candidate.ingredients.len();
+            let mut missing_ingredients = Vec::new();
+            let mut uses_expiring = Vec::new();
+
+            for ingredient in &candidate.ingredients {
+                if !fridge.contains(&ingredient.name) {
+                    missing_ingredients.push(ingredient.name.clone());
+                } else if expiring.contains(&ingredient.name) {
+                    uses_expiring.push(ingredient.name.clone());
+                }
+            }
+
+            let have = total - missing_ingredients.len();
+            // Порог сравнивается с точной долей, в ответе — округленная
+            let fraction = have as f64 / total as f64 * 100.0;
+            if fraction + f64::EPSILON < min_match {
+                return None;
+            }
+
+            let cost_estimate = estimate_cost(
+                candidate.ingredients.iter().map(|ingredient| CostIngredient {
+                    name: &ingredient.name,
+                    amount: ingredient.amount(),
+                }),
+                candidate.recipe.servings,
+                prices,
+            );
+
+            let recipe = candidate.recipe;
+            let total_time_minutes = match (recipe.prep_time_minutes, recipe.cook_time_minutes) {
+                (None, None) => None,
+                (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+            };
+
+            Some((fraction, CookableRecipe {
+                recipe_id: recipe.id,
+                name: recipe.name,
+                category: recipe.category,
+                difficulty: recipe.difficulty,
+                total_time_minutes,
+                image_url: recipe.image_url,
+                is_saved: candidate.is_saved,
+                match_percentage: round_percent(fraction),
+                have,
+                total,
+                missing_ingredients,
+                uses_expiring,
+                cost_estimate,
+            }))
+        })
+        .collect();
+
+    cookable.sort_by(|(a_fraction, a), (b_fraction, b)| {
+        b_fraction
+            .partial_cmp(a_fraction)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.uses_expiring.len().cmp(&a.uses_expiring.len()))
+            .then_with(|| a.missing_ingredients.len().cmp(&b.missing_ingredients.len()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    cookable.into_iter().map(|(_, recipe)| recipe).collect()
+}
+
+/// Сортировка от дешевых к дорогим: по цене порции, если число порций известно, иначе по
+/// полной стоимости. Неполные оценки занижают цену, поэтому идут после полных.
+/// Сортировка устойчивая — при равной цене сохраняется порядок по совпадению.
+pub fn sort_by_cost(recipes: &mut [CookableRecipe]) {
+    recipes.sort_by(|a, b| {
+        let key = |recipe: &CookableRecipe| {
+            let estimate = &recipe.cost_estimate;
+            (!estimate.complete, estimate.per_serving.unwrap_or(estimate.total))
+        };
+        let (a_incomplete, a_cost) = key(a);
+        let (b_incomplete, b_cost) = key(b);
+        a_incomplete
+            .cmp(&b_incomplete)
+            .then_with(|| a_cost.partial_cmp(&b_cost).unwrap_or(std::cmp::Ordering::Equal))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+    use sqlx::PgPool;
+
+    use crate::test_support::{access_token, insert_fridge_item, insert_recipe, insert_user, request, send, test_router};
+
+    async fn recipe_with(pool: &PgPool, created_by: uuid::Uuid, name: &str, is_public: bool, ingredients: &[&str]) {
+        let id = insert_recipe(pool, created_by, name, is_public).await;
+        for ingredient in ingredients {
+            sqlx::query("INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit) VALUES ($1, $2, 100, 'г')")
+                .bind(id)
+                .bind(ingredient)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Холодильник полностью закрывает плов и омлет, частично — запеканку (4/5),
+    /// блины (2/3) и суп (3/5, ровно порог); остальные пять рецептов ниже порога или пустые
+    async fn fixture(pool: &PgPool) -> uuid::Uuid {
+        let user = insert_user(pool, "Анна").await;
+        let bought = Utc::now() - Duration::days(2);
+        for name in ["Курица", "Рис белый", "Лук", "Морковь", "Яйца", "Молоко 3,2%", "Сыр"] {
+            insert_fridge_item(pool, user, name, None, bought).await;
+        }
+        sqlx::query("UPDATE fridge_items SET expiry_date = $2 WHERE user_id = $1 AND name IN ('Молоко 3,2%', 'Лук')")
+            .bind(user)
+            .bind(Utc::now() + Duration::days(1))
+            .execute(pool)
+            .await
+            .unwrap();
+
+        recipe_with(pool, user, "Плов", false, &["курица", "рис", "морковь", "лук"]).await;
+        recipe_with(pool, user, "Омлет", false, &["яйца", "молоко", "сыр"]).await;
+        recipe_with(pool, user, "Запеканка", false, &["картофель", "сыр", "яйца", "молоко", "лук"]).await;
+        recipe_with(pool, user, "Блины", false, &["яйца", "молоко", "мука"]).await;
+        recipe_with(pool, user, "Суп", false, &["курица", "картофель", "морковь", "лук", "укроп"]).await;
+        recipe_with(pool, user, "Пицца", false, &["мука", "томаты", "сыр"]).await;
+        recipe_with(pool, user, "Салат", false, &["огурцы", "томаты", "укроп"]).await;
+        recipe_with(pool, user, "Паста", false, &["макароны", "томаты", "сыр", "базилик"]).await;
+        recipe_with(pool, user, "Пирог", false, &["мука", "сахар", "сливочное масло", "яблоки", "яйца"]).await;
+        recipe_with(pool, user, "Пустой", false, &[]).await;
+        user
+    }
+
+    fn names(body: &Value) -> Vec<&str> {
+        body.as_array().unwrap().iter().map(|recipe| recipe["name"].as_str().unwrap()).collect()
+    }
+
+    #[sqlx::test]
+    async fn ranks_full_then_partial_matches_above_the_threshold(pool: PgPool) {
+        let user = fixture(&pool).await;
+        let router = test_router(&pool);
+        let token = access_token(&pool, user).await;
+
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        // Оба полных совпадения — 100%, но омлет использует два истекающих продукта, плов — один
+        assert_eq!(names(&body), vec!["Омлет", "Плов", "Запеканка", "Блины", "Суп"]);
+
+        let recipes = body.as_array().unwrap();
+        let summary: Vec<(f64, u64, u64)> = recipes
+            .iter()
+            .map(|recipe| (recipe["match_percentage"].as_f64().unwrap(), recipe["have"].as_u64().unwrap(), recipe["total"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(summary, vec![(100.0, 3, 3), (100.0, 4, 4), (80.0, 4, 5), (66.7, 2, 3), (60.0, 3, 5)]);
+
+        assert_eq!(recipes[0]["uses_expiring"], serde_json::json!(["молоко"]));
+        assert_eq!(recipes[1]["uses_expiring"], serde_json::json!(["лук"]));
+        assert_eq!(recipes[1]["missing_ingredients"], serde_json::json!([]));
+        assert_eq!(recipes[2]["missing_ingredients"], serde_json::json!(["картофель"]));
+        assert_eq!(recipes[3]["missing_ingredients"], serde_json::json!(["мука"]));
+        assert_eq!(recipes[4]["missing_ingredients"], serde_json::json!(["картофель", "укроп"]));
+        assert!(recipes.iter().all(|recipe| recipe["is_saved"] == true));
+
+        // Порог настраивается: при 70% суп и блины отпадают, при 0 — все рецепты с ингредиентами
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable?min_match=70", Some(&token), None)).await;
+        assert_eq!(names(&body), vec!["Омлет", "Плов", "Запеканка"]);
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable?min_match=0", Some(&token), None)).await;
+        assert_eq!(body.as_array().unwrap().len(), 9);
+        assert!(!names(&body).contains(&"Пустой"));
+
+        let (status, _) = send(&router, request(Method::GET, "/api/v1/recipes/cookable?min_match=120", Some(&token), None)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    async fn public_catalog_is_included_only_on_request(pool: PgPool) {
+        let user = fixture(&pool).await;
+        let author = insert_user(&pool, "Борис").await;
+        recipe_with(&pool, author, "Рисовая каша", true, &["рис", "молоко"]).await;
+        recipe_with(&pool, author, "Чужой секрет", false, &["рис", "молоко"]).await;
+        let router = test_router(&pool);
+        let token = access_token(&pool, user).await;
+
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable", Some(&token), None)).await;
+        assert!(!names(&body).contains(&"Рисовая каша"));
+
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable?include_public=true", Some(&token), None)).await;
+        let recipes = body.as_array().unwrap();
+        let porridge = recipes.iter().find(|recipe| recipe["name"] == "Рисовая каша").unwrap();
+        assert_eq!(porridge["is_saved"], false);
+        assert_eq!(porridge["match_percentage"], 100.0);
+        assert!(!names(&body).contains(&"Чужой секрет"));
+
+        // Пустой холодильник — пустой ответ без ошибок
+        let stranger = insert_user(&pool, "Вера").await;
+        let token = access_token(&pool, stranger).await;
+        let (status, body) = send(&router, request(Method::GET, "/api/v1/recipes/cookable?include_public=true", Some(&token), None)).await;
+        assert_eq!((status, body), (StatusCode::OK, serde_json::json!([])));
+    }
+}
@@ -0,0 +1,459 @@
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    api::recipes::CreateRecipeIngredientRequest,
+    db::DbPool,
+    models::{
+        diary::CreateDiaryEntry,
+        fridge::{CreateFridgeItem, FridgeCategory},
+        goal::{CreateGoal, GoalType, GoalStatus},
+        health::DailyWellbeing,
+        recipe::{CreateRecipe, RecipeCategory, RecipeStepInput, DifficultyLevel},
+    },
+    services::{diary::DiaryService, fridge::FridgeService, goal::GoalService, recipe::RecipeService},
+    utils::errors::AppError,
+};
+
+/// Метка, по которой демо-записи отличаются от записей пользователя
+pub const DEMO_TAG: &str = "demo";
+
+/// Сид по умолчанию, если клиент его не передал
+pub const DEFAULT_DEMO_SEED: u64 = 42;
+
+/// Название, категория, единица, базовое количество, срок годности в днях (None — без срока)
+const DEMO_FRIDGE_ITEMS: &[(&str, FridgeCategory, &str, f32, Option<i64>)] = &[
+    ("Молоко коровье", FridgeCategory::Dairy, "л", 1.0, Some(5)),
+    ("Сыр твердый", FridgeCategory::Dairy, "г", 300.0, Some(20)),
+    ("Йогурт натуральный", FridgeCategory::Dairy, "шт", 4.0, Some(7)),
+    ("Куриная грудка", FridgeCategory::Meat, "г", 600.0, Some(2)),
+    ("Говядина", FridgeCategory::Meat, "г", 500.0, Some(3)),
+    ("Лосось", FridgeCategory::Fish, "г", 400.0, Some(1)),
+    ("Брокколи", FridgeCategory::Vegetables, "г", 400.0, Some(4)),
+    ("Капуста белокочанная", FridgeCategory::Vegetables, "шт", 1.0, Some(1)),
+    ("Морковь", FridgeCategory::Vegetables, "кг", 1.0, Some(14)),
+    ("Яблоко", FridgeCategory::Fruits, "шт", 6.0, Some(21)),
+    ("Банан", FridgeCategory::Fruits, "шт", 5.0, Some(3)),
+    ("Рис белый", FridgeCategory::Grains, "кг", 1.0, None),
+    ("Хлеб пшеничный", FridgeCategory::Grains, "шт", 1.0, Some(2)),
+    ("Апельсиновый сок", FridgeCategory::Beverages, "л", 1.0, Some(6)),
+    ("Миндаль", FridgeCategory::Snacks, "г", 200.0, None),
+];
+
+/// Магазины демо-покупок — для разбивки трат по магазинам
+const DEMO_STORES: &[&str] = &["Пятёрочка", "Лента", "ВкусВилл"];
+
+/// Название, прием пищи, ккал/белки/жиры/углеводы на 100 г, порция в граммах
+const DEMO_MEALS: &[(&str, &str, f32, f32, f32, f32, f32)] = &[
+    ("Овсянка на молоке", "breakfast", 102.0, 3.2, 4.1, 13.6, 250.0),
+    ("Омлет из двух яиц", "breakfast", 154.0, 10.6, 11.7, 1.2, 150.0),
+    ("Куриный суп", "lunch", 45.0, 3.8, 1.7, 3.6, 300.0),
+    ("Гречка с говядиной", "lunch", 168.0, 11.2, 6.3, 16.9, 280.0),
+    ("Лосось с брокколи", "dinner", 142.0, 14.9, 7.8, 3.1, 300.0),
+    ("Салат из капусты и моркови", "dinner", 42.0, 1.3, 1.1, 6.8, 200.0),
+    ("Яблоко", "snack", 47.0, 0.4, 0.4, 9.8, 150.0),
+    ("Йогурт натуральный", "snack", 66.0, 5.0, 3.2, 3.5, 125.0),
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct DemoSeedSummary {
+    pub seed: u64,
+    pub fridge_items: usize,
+    pub diary_entries: usize,
+    pub goals: usize,
+    pub goal_progress_updates: usize,
+    pub recipes: usize,
+    pub wellbeing_checkins: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DemoCleanupSummary {
+    pub fridge_items: usize,
+    pub diary_entries: usize,
+    pub goals: usize,
+    pub recipes: usize,
+}
+
+pub struct DemoSeedService {
+    pool: DbPool,
+}
+
+impl DemoSeedService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Заполнение демо-данными необходимо только новым или пустым аккаунтам
+    pub async fn ensure_eligible(&self, user_id: Uuid, user_created_at: DateTime<Utc>) -> Result<(), AppError> {
+        let fridge_items = FridgeService::new(self.pool.clone())
+            .get_user_items(user_id, None, None, None)
+            .await?;
+
+        if fridge_items.iter().any(|item| Self::is_demo(item.notes.as_deref())) {
+            return Err(AppError::BadRequest("Demo data is already seeded for this account".to_string()));
+        }
+
+        let is_new_account = Utc::now() - user_created_at < Duration::hours(24);
+        if is_new_account {
+            return Ok(());
+        }
+
+        let diary_entries = DiaryService::new(self.pool.clone())
+            .get_user_entries(user_id, None, None, 1, 0)
+            .await?;
+
+        if fridge_items.is_empty() && diary_entries.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(
+                "Demo data can only be seeded into accounts younger than 24 hours or without records".to_string(),
+            ))
+        }
+    }
+
+    /// Детерминированное заполнение: одинаковый `seed` дает одинаковый набор данных
+    pub async fn seed(&self, user_id: Uuid, seed: u64) -> Result<DemoSeedSummary, AppError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let today = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|midnight| midnight.and_utc())
+            .unwrap_or_else(Utc::now);
+
+        let mut summary = DemoSeedSummary {
+            seed,
+            ..Default::default()
+        };
+
+        // Холодильник: продукты всех категорий со сроками годности "лесенкой"
+        let fridge_service = FridgeService::new(self.pool.clone());
+        for (index, (name, category, unit, base_quantity, shelf_days)) in DEMO_FRIDGE_ITEMS.iter().enumerate() {
+            let quantity = (base_quantity * rng.gen_range(0.5..1.5) * 10.0).round() / 10.0;
+            let price_per_unit = (rng.gen_range(50.0..500.0_f32)).round();
+            let expiry_date = shelf_days.map(|days| today + Duration::days(days + rng.gen_range(-1..=2)));
+
+            fridge_service.add_item(CreateFridgeItem {
+                user_id,
+                name: name.to_string(),
+                brand: None,
+                quantity,
+                unit: unit.to_string(),
+                category: category.clone(),
+                price_per_unit: Some(price_per_unit),
+                total_price: Some((price_per_unit * quantity).round()),
+                expiry_date,
+                purchase_date: today - Duration::days(rng.gen_range(0..4)),
+                notes: Some(DEMO_TAG.to_string()),
+                location: Some(if matches!(category, FridgeCategory::Grains | FridgeCategory::Snacks) { "pantry" } else { "fridge" }.to_string()),
+                // Магазин по порядку, а не из генератора: прежний сид дает прежние количества и цены
+                store: Some(DEMO_STORES[index % DEMO_STORES.len()].to_string()),
+                contains_allergens: vec![],
+                contains_intolerances: vec![],
+                suitable_for_diets: vec![],
+                ingredients: None,
+                nutritional_info: None,
+            }).await?;
+            summary.fridge_items += 1;
+        }
+
+        // Дневник: неделя приемов пищи
+        let diary_service = DiaryService::new(self.pool.clone());
+        for day in 0..7 {
+            let date = today - Duration::days(day);
+            for meal_type in ["breakfast", "lunch", "dinner", "snack"] {
+                let options: Vec<_> = DEMO_MEALS.iter().filter(|meal| meal.1 == meal_type).collect();
+                let (food_name, _, calories, protein, fat, carbs, portion) = *options[rng.gen_range(0..options.len())];
+                let hour = match meal_type {
+                    "breakfast" => 8,
+                    "lunch" => 13,
+                    "dinner" => 19,
+                    _ => 16,
+                };
+
+                diary_service.create_entry(CreateDiaryEntry {
+                    user_id,
+                    food_name: food_name.to_string(),
+                    brand: Some(DEMO_TAG.to_string()),
+                    portion_size: (portion * rng.gen_range(0.8..1.2)).round(),
+                    unit: "g".to_string(),
+                    calories_per_100g: calories,
+                    protein_per_100g: protein,
+                    fat_per_100g: fat,
+                    carbs_per_100g: carbs,
+                    fiber_per_100g: None,
+                    sugar_per_100g: None,
+                    sodium_per_100g: None,
+                    media_id: None,
+                    meal_type: meal_type.to_string(),
+                    consumed_at: date + Duration::hours(hour) + Duration::minutes(rng.gen_range(0..45)),
+                }).await?;
+                summary.diary_entries += 1;
+            }
+        }
+
+        // Цели с историей прогресса
+        let goal_service = GoalService::new(self.pool.clone());
+        let start_weight = (rng.gen_range(70.0..90.0_f32) * 10.0).round() / 10.0;
+        let goals = [
+            ("Снизить вес", GoalType::WeightLoss, start_weight - 5.0, start_weight, "kg"),
+            ("Пить больше воды", GoalType::Water, 2000.0, 1200.0, "ml"),
+        ];
+
+        for (title, goal_type, target_value, current_value, unit) in goals {
+            let goal = goal_service.create_goal(CreateGoal {
+                user_id,
+                title: title.to_string(),
+                description: Some(DEMO_TAG.to_string()),
+                goal_type,
+                target_value,
+                current_value,
+                unit: unit.to_string(),
+                target_date: Some((today + Duration::days(60)).date_naive()),
+                daily_target: None,
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days: Vec::new(),
+            }).await?;
+            summary.goals += 1;
+
+            let mut value = current_value;
+            for _ in 0..3 {
+                let step = (target_value - current_value) * rng.gen_range(0.05..0.15);
+                value = ((value + step) * 10.0).round() / 10.0;
+                goal_service.update_progress(goal.id, user_id, value, Some(DEMO_TAG.to_string())).await?;
+                summary.goal_progress_updates += 1;
+            }
+        }
+
+        // Несколько рецептов
+        let recipe_service = RecipeService::new(self.pool.clone());
+        let recipes = [
+            ("Тушеная капуста с рисом", RecipeCategory::Lunch, DifficultyLevel::Easy, vec![("Капуста белокочанная", 500.0, "г"), ("Рис белый", 150.0, "г")]),
+            ("Лосось с брокколи", RecipeCategory::Dinner, DifficultyLevel::Medium, vec![("Лосось", 300.0, "г"), ("Брокколи", 250.0, "г")]),
+            ("Банановые оладьи", RecipeCategory::Breakfast, DifficultyLevel::Easy, vec![("Банан", 2.0, "шт"), ("Мука", 80.0, "г")]),
+        ];
+
+        for (name, category, difficulty, ingredients) in recipes {
+            let prep_time = rng.gen_range(5..20);
+            let cook_time = rng.gen_range(10..40);
+
+            recipe_service.create_recipe(
+                CreateRecipe {
+                    name: name.to_string(),
+                    description: Some("Демо-рецепт".to_string()),
+                    category,
+                    difficulty,
+                    prep_time_minutes: Some(prep_time),
+                    cook_time_minutes: Some(cook_time),
+                    servings: Some(2),
+                    steps: vec![RecipeStepInput::text("Подготовьте ингредиенты и приготовьте по вкусу.")],
+                    gallery: Vec::new(),
+                    tags: vec![DEMO_TAG.to_string()],
+                    image_url: None,
+                    source_url: None,
+                    created_by: user_id,
+                    is_public: false,
+                },
+                ingredients
+                    .into_iter()
+                    .map(|(name, quantity, unit)| CreateRecipeIngredientRequest {
+                        name: name.to_string(),
+                        amount: None,
+                        quantity: Some(quantity),
+                        unit: Some(unit.to_string()),
+                        notes: None,
+                    })
+                    .collect(),
+                None,
+            ).await?;
+            summary.recipes += 1;
+        }
+
+        // Одна проверка самочувствия (отдельного хранилища для них пока нет, как и в /health/wellbeing)
+        let _checkin = DailyWellbeing {
+            id: Uuid::new_v4(),
+            user_id,
+            date: today,
+            mood_score: Some(rng.gen_range(5..=9)),
+            energy_level: Some(rng.gen_range(4..=8)),
+            stress_level: Some(rng.gen_range(2..=6)),
+            sleep_hours: Some(rng.gen_range(6..=9) as f32),
+            sleep_quality: Some(rng.gen_range(5..=9)),
+            water_intake_ml: Some(rng.gen_range(8..=20) * 100),
+            exercise_minutes: Some(rng.gen_range(0..=6) * 10),
+            notes: Some(DEMO_TAG.to_string()),
+            symptoms: vec![],
+            created_at: Utc::now(),
+        };
+        summary.wellbeing_checkins += 1;
+
+        Ok(summary)
+    }
+
+    /// Удаляет только записи с демо-меткой — все, что пользователь добавил сам, остается
+    pub async fn cleanup(&self, user_id: Uuid) -> Result<DemoCleanupSummary, AppError> {
+        let mut summary = DemoCleanupSummary::default();
+
+        let fridge_service = FridgeService::new(self.pool.clone());
+        for item in fridge_service.get_user_items(user_id, None, None, None).await? {
+            if Self::is_demo(item.notes.as_deref()) {
+                fridge_service.remove_item(item.id, user_id).await?;
+                summary.fridge_items += 1;
+            }
+        }
+
+        let diary_service = DiaryService::new(self.pool.clone());
+        for entry in diary_service.get_user_entries(user_id, None, None, i64::MAX, 0).await? {
+            if Self::is_demo(entry.brand.as_deref()) {
+                diary_service.delete_entry(entry.id, user_id).await?;
+                summary.diary_entries += 1;
+            }
+        }
+
+        let goal_service = GoalService::new(self.pool.clone());
+        for goal in goal_service.get_user_goals(user_id, None, None, true, i64::MAX, 0).await? {
+            if Self::is_demo(goal.description.as_deref()) {
+                goal_service.delete_goal(goal.id, user_id).await?;
+                summary.goals += 1;
+            }
+        }
+
+        let recipe_service = RecipeService::new(self.pool.clone());
+        let recipes = recipe_service
+            .get_recipes(Some(user_id), None, None, None, None, None, None, i64::MAX, 0)
+            .await?;
+        for recipe in recipes {
+            if recipe.created_by == user_id && recipe.tags.iter().any(|tag| tag == DEMO_TAG) {
+                recipe_service.delete_recipe(recipe.id, user_id).await?;
+                summary.recipes += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    fn is_demo(tag: Option<&str>) -> bool {
+        tag == Some(DEMO_TAG)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_diary_entry, insert_fridge_item, insert_recipe, insert_user};
+    use sqlx::PgPool;
+
+    async fn own_goal(pool: &PgPool, user_id: Uuid, description: Option<&str>) -> Uuid {
+        GoalService::new(pool.clone())
+            .create_goal(CreateGoal {
+                user_id,
+                title: "Бегать по утрам".to_string(),
+                description: description.map(str::to_string),
+                goal_type: GoalType::Exercise,
+                target_value: 30.0,
+                current_value: 0.0,
+                unit: "min".to_string(),
+                target_date: None,
+                daily_target: None,
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[sqlx::test]
+    async fn cleanup_removes_only_tagged_records(pool: PgPool) {
+        let user_id = insert_user(&pool, "Ира").await;
+        let now = Utc::now();
+
+        // Записи пользователя: без метки и с похожей, но не совпадающей
+        let own_item = insert_fridge_item(&pool, user_id, "Кефир", None, now).await.id;
+        let similar_item = insert_fridge_item(&pool, user_id, "Творог", Some("demo с рынка"), now).await.id;
+        let own_entry = insert_diary_entry(&pool, user_id, "lunch", 350.0, 20.0, now).await;
+        let own_goal = own_goal(&pool, user_id, Some("Demo")).await;
+        let own_recipe = insert_recipe(&pool, user_id, "Бабушкин борщ", false).await;
+
+        let service = DemoSeedService::new(pool.clone());
+        let seeded = service.seed(user_id, DEFAULT_DEMO_SEED).await.unwrap();
+        assert_eq!((seeded.fridge_items, seeded.diary_entries, seeded.goals, seeded.recipes), (15, 28, 2, 3));
+
+        let removed = service.cleanup(user_id).await.unwrap();
+        assert_eq!(
+            (removed.fridge_items, removed.diary_entries, removed.goals, removed.recipes),
+            (seeded.fridge_items, seeded.diary_entries, seeded.goals, seeded.recipes)
+        );
+
+        let items: Vec<Uuid> = FridgeService::new(pool.clone())
+            .get_user_items(user_id, None, None, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert!(items.contains(&own_item) && items.contains(&similar_item));
+
+        let entries = DiaryService::new(pool.clone()).get_user_entries(user_id, None, None, 100, 0).await.unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![own_entry]);
+
+        let goals = GoalService::new(pool.clone()).get_user_goals(user_id, None, None, true, 100, 0).await.unwrap();
+        assert_eq!(goals.iter().map(|goal| goal.id).collect::<Vec<_>>(), vec![own_goal]);
+
+        let recipes = RecipeService::new(pool.clone())
+            .get_recipes(Some(user_id), None, None, None, None, None, None, 100, 0)
+            .await
+            .unwrap();
+        assert_eq!(recipes.iter().map(|recipe| recipe.id).collect::<Vec<_>>(), vec![own_recipe]);
+
+        // Повторная очистка ничего не находит
+        let again = service.cleanup(user_id).await.unwrap();
+        assert_eq!((again.fridge_items, again.diary_entries, again.goals, again.recipes), (0, 0, 0, 0));
+    }
+
+    #[sqlx::test]
+    async fn cleanup_does_not_touch_other_users(pool: PgPool) {
+        let seeded_user = insert_user(&pool, "Олег").await;
+        let other_user = insert_user(&pool, "Вера").await;
+        let service = DemoSeedService::new(pool.clone());
+
+        service.seed(seeded_user, DEFAULT_DEMO_SEED).await.unwrap();
+        service.seed(other_user, DEFAULT_DEMO_SEED).await.unwrap();
+        service.cleanup(seeded_user).await.unwrap();
+
+        let remaining = FridgeService::new(pool.clone()).get_user_items(other_user, None, None, None).await.unwrap();
+        assert_eq!(remaining.len(), 15);
+        let entries = DiaryService::new(pool.clone()).get_user_entries(other_user, None, None, 100, 0).await.unwrap();
+        assert_eq!(entries.len(), 28);
+    }
+
+    #[sqlx::test]
+    async fn same_seed_gives_same_dataset(pool: PgPool) {
+        let first = insert_user(&pool, "Аня").await;
+        let second = insert_user(&pool, "Петя").await;
+        let service = DemoSeedService::new(pool.clone());
+
+        service.seed(first, 7).await.unwrap();
+        service.seed(second, 7).await.unwrap();
+
+        let quantities = |user_id| {
+            let pool = pool.clone();
+            async move {
+                let mut items: Vec<(String, f32)> = FridgeService::new(pool)
+                    .get_user_items(user_id, None, None, None)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|item| (item.name, item.quantity))
+                    .collect();
+                items.sort_by(|a, b| a.0.cmp(&b.0));
+                items
+            }
+        };
+        assert_eq!(quantities(first).await, quantities(second).await);
+    }
+}
@@ -1,96 +1,427 @@
 use uuid::Uuid;
-use chrono::{Utc, NaiveDate};
+use chrono::{Duration, NaiveDate};
+use sqlx::FromRow;
+use tracing::instrument;
 use crate::{
-    models::diary::{DiaryEntry, CreateDiaryEntry, NutritionSummary, MealSummary},
+    db::{DbPools, ReadConsistency, UserScope},
+    models::diary::{DiaryEntry, CreateDiaryEntry, NutritionSummary, MealSummary, NutritionSource},
+    services::{clock::{self, SharedClock}, food_memory::FoodMemoryService, preferences::PreferencesService},
     utils::errors::AppError,
 };
 
+/// Самый длинный период сводки по дням
+pub const MAX_SUMMARY_RANGE_DAYS: i64 = 92;
+/// Период по умолчанию — неделя
+const DEFAULT_RANGE_DAYS: i64 = 7;
+/// Наибольшая страница списка записей
+const MAX_ENTRIES_PAGE: i64 = 200;
+
+/// Записи дневника для ленты активности (колонки — см. `services::timeline`)
+pub const TIMELINE_ENTRIES_SQL: &str = r#"
+    SELECT 'diary_entry_logged'::text AS kind, consumed_at AS occurred_at, id AS event_id, id AS reference_id,
+           food_name AS title,
+           jsonb_build_object(
+               'meal_type', meal_type, 'portion_size', portion_size, 'unit', unit,
+               'calories', ROUND((calories_per_100g * portion_size / 100)::numeric)
+           ) AS summary
+    FROM diary_entries
+    WHERE user_id = $1
+"#;
+
 pub struct DiaryService {
-    pool: crate::db::DbPool,
+    pools: DbPools,
+    clock: SharedClock,
 }
 
 impl DiaryService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { pool }
+        Self::with_pools(DbPools::single(pool))
+    }
+
+    /// Сводки по дням читаются с реплики; записи — в основной базе
+    pub fn with_pools(pools: DbPools) -> Self {
+        Self { pools, clock: clock::system() }
     }
 
+    /// Часы из `AppState` — в песочнице "сегодня" дневника определяется по ним
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[instrument(skip_all, fields(user_id = %entry_data.user_id))]
     pub async fn create_entry(&self, entry_data: CreateDiaryEntry) -> Result<DiaryEntry, AppError> {
         let entry_id = Uuid::new_v4();
-        let now = Utc::now();
-
-        // Mock implementation for compilation without database
-        // TODO: Replace with real database operations when DATABASE_URL is available
-        Ok(DiaryEntry {
-            id: entry_id,
-            user_id: entry_data.user_id,
-            food_name: entry_data.food_name,
-            brand: entry_data.brand,
-            portion_size: entry_data.portion_size,
-            unit: entry_data.unit,
-            calories_per_100g: entry_data.calories_per_100g,
-            protein_per_100g: entry_data.protein_per_100g,
-            fat_per_100g: entry_data.fat_per_100g,
-            carbs_per_100g: entry_data.carbs_per_100g,
-            fiber_per_100g: entry_data.fiber_per_100g,
-            sugar_per_100g: entry_data.sugar_per_100g,
-            sodium_per_100g: entry_data.sodium_per_100g,
-            meal_type: entry_data.meal_type,
-            consumed_at: entry_data.consumed_at,
-            created_at: now,
-            updated_at: now,
-        })
+        let now = self.clock.now();
+
+        // Память о порциях не должна мешать записи: при ошибке порция просто не запоминается
+        if let Err(e) = FoodMemoryService::new(self.pools.primary().clone())
+            .with_clock(self.clock.clone())
+            .record(entry_data.user_id, &entry_data.food_name, entry_data.portion_size, &entry_data.unit, &entry_data.meal_type)
+            .await
+        {
+            tracing::warn!("Failed to remember portion of '{}' for user {}: {}", entry_data.food_name, entry_data.user_id, e);
+        }
+
+        let entry = sqlx::query_as::<_, DiaryEntry>(
+            r#"
+            INSERT INTO diary_entries (
+                id, user_id, food_name, brand, portion_size, unit,
+                calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g,
+                fiber_per_100g, sugar_per_100g, sodium_per_100g,
+                meal_type, nutrition_source, media_id, consumed_at, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $18)
+            RETURNING *
+            "#
+        )
+        .bind(entry_id)
+        .bind(entry_data.user_id)
+        .bind(entry_data.food_name)
+        .bind(entry_data.brand)
+        .bind(entry_data.portion_size)
+        .bind(entry_data.unit)
+        .bind(entry_data.calories_per_100g)
+        .bind(entry_data.protein_per_100g)
+        .bind(entry_data.fat_per_100g)
+        .bind(entry_data.carbs_per_100g)
+        .bind(entry_data.fiber_per_100g)
+        .bind(entry_data.sugar_per_100g)
+        .bind(entry_data.sodium_per_100g)
+        .bind(entry_data.meal_type)
+        .bind(NutritionSource::User)
+        .bind(entry_data.media_id)
+        .bind(entry_data.consumed_at)
+        .bind(now)
+        .fetch_one(self.pools.primary())
+        .await?;
+
+        Ok(entry)
     }
 
-    pub async fn get_user_entries(&self, _user_id: Uuid, _date: Option<NaiveDate>, _meal_type: Option<String>, _limit: i64, _offset: i64) -> Result<Vec<DiaryEntry>, AppError> {
-        // Mock implementation
-        Ok(vec![])
+    /// Записи пользователя, новые первыми; `date` — местный день пользователя
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_user_entries(&self, user_id: Uuid, date: Option<NaiveDate>, meal_type: Option<String>, limit: i64, offset: i64) -> Result<Vec<DiaryEntry>, AppError> {
+        let day = match date {
+            Some(date) => {
+                let offset = self.utc_offset(user_id).await?;
+                let start = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+                Some((start, start + Duration::days(1)))
+            }
+            None => None,
+        };
+
+        let entries = UserScope::new(user_id)
+            .query_as::<DiaryEntry>(
+                r#"
+                SELECT * FROM diary_entries
+                WHERE user_id = $1
+                  AND ($2::TIMESTAMPTZ IS NULL OR (consumed_at >= $2 AND consumed_at < $3))
+                  AND ($4::TEXT IS NULL OR meal_type = $4)
+                ORDER BY consumed_at DESC, id
+                LIMIT $5 OFFSET $6
+                "#
+            )
+            .bind(day.map(|(start, _)| start))
+            .bind(day.map(|(_, end)| end))
+            .bind(meal_type)
+            .bind(limit.clamp(1, MAX_ENTRIES_PAGE))
+            .bind(offset.max(0))
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(entries)
     }
 
-    pub async fn get_entry_by_id(&self, _id: Uuid, _user_id: Uuid) -> Result<DiaryEntry, AppError> {
-        // Mock implementation
-        Err(AppError::NotFound("Entry not found".to_string()))
+    pub async fn get_entry_by_id(&self, id: Uuid, user_id: Uuid) -> Result<DiaryEntry, AppError> {
+        UserScope::new(user_id)
+            .query_as::<DiaryEntry>("SELECT * FROM diary_entries WHERE user_id = $1 AND id = $2")
+            .bind(id)
+            .fetch_optional(self.pools.primary())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Entry not found".to_string()))
     }
 
-    pub async fn update_entry(&self, _id: Uuid, _user_id: Uuid, _payload: crate::api::diary::CreateDiaryEntryRequest) -> Result<DiaryEntry, AppError> {
-        // Mock implementation
-        Err(AppError::InternalServerError("Not implemented".to_string()))
+    /// Полная замена записи; без `meal_type` и `consumed_at` остаются прежние значения
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn update_entry(&self, id: Uuid, user_id: Uuid, payload: crate::api::diary::CreateDiaryEntryRequest) -> Result<DiaryEntry, AppError> {
+        UserScope::new(user_id)
+            .query_as::<DiaryEntry>(
+                r#"
+                UPDATE diary_entries
+                SET food_name = $3, brand = $4, portion_size = $5, unit = $6,
+                    calories_per_100g = $7, protein_per_100g = $8, fat_per_100g = $9, carbs_per_100g = $10,
+                    fiber_per_100g = $11, sugar_per_100g = $12, sodium_per_100g = $13,
+                    meal_type = COALESCE($14, meal_type), consumed_at = COALESCE($15, consumed_at),
+                    media_id = $16, nutrition_source = $17, updated_at = $18
+                WHERE user_id = $1 AND id = $2
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .bind(payload.food_name)
+            .bind(payload.brand)
+            .bind(payload.portion_size)
+            .bind(payload.unit)
+            .bind(payload.calories_per_100g)
+            .bind(payload.protein_per_100g)
+            .bind(payload.fat_per_100g)
+            .bind(payload.carbs_per_100g)
+            .bind(payload.fiber_per_100g)
+            .bind(payload.sugar_per_100g)
+            .bind(payload.sodium_per_100g)
+            .bind(payload.meal_type)
+            .bind(payload.consumed_at)
+            .bind(payload.media_id)
+            .bind(NutritionSource::User)
+            .bind(self.clock.now())
+            .fetch_optional(self.pools.primary())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Entry not found".to_string()))
     }
 
-    pub async fn delete_entry(&self, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
-        // Mock implementation
+    pub async fn delete_entry(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let deleted = UserScope::new(user_id)
+            .query("DELETE FROM diary_entries WHERE user_id = $1 AND id = $2")
+            .bind(id)
+            .execute(self.pools.primary())
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(AppError::NotFound("Entry not found".to_string()));
+        }
         Ok(())
     }
 
-    pub async fn get_daily_summary(&self, _user_id: Uuid, date: NaiveDate) -> Result<NutritionSummary, AppError> {
-        // Mock implementation
-        Ok(NutritionSummary {
+    /// Сводка за один местный день — тот же расчет, что и для периода
+    #[instrument(skip_all, fields(user_id = %user_id, %date))]
+    pub async fn get_daily_summary(&self, user_id: Uuid, date: NaiveDate) -> Result<NutritionSummary, AppError> {
+        let offset = self.utc_offset(user_id).await?;
+        self.summaries(user_id, date, date, offset)
+            .await?
+            .pop()
+            .ok_or_else(|| AppError::InternalServerError("Empty daily summary".to_string()))
+    }
+
+    /// Сводки по местным дням с `from` по `to` включительно, по возрастанию даты.
+    /// Без `to` — по сегодня, без `from` — неделя до `to`.
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_summary_range(
+        &self,
+        user_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<NutritionSummary>, AppError> {
+        let offset = self.utc_offset(user_id).await?;
+        let to = to.unwrap_or_else(|| (self.clock.now() + offset).date_naive());
+        let from = from.unwrap_or(to - Duration::days(DEFAULT_RANGE_DAYS - 1));
+
+        if from > to {
+            return Err(AppError::BadRequest("'from' must not be after 'to'".to_string()));
+        }
+        if (to - from).num_days() + 1 > MAX_SUMMARY_RANGE_DAYS {
+            return Err(AppError::BadRequest(format!("Range must not exceed {} days", MAX_SUMMARY_RANGE_DAYS)));
+        }
+
+        self.summaries(user_id, from, to, offset).await
+    }
+
+    /// Последние семь дней, начиная с сегодняшнего (прежний порядок ответа)
+    pub async fn get_weekly_nutrition(&self, user_id: Uuid) -> Result<Vec<NutritionSummary>, AppError> {
+        let mut summaries = self.get_summary_range(user_id, None, None).await?;
+        summaries.reverse();
+        Ok(summaries)
+    }
+
+    /// Смещение местного времени пользователя из настроек
+    async fn utc_offset(&self, user_id: Uuid) -> Result<Duration, AppError> {
+        let preferences = PreferencesService::new(self.pools.read(ReadConsistency::Replica).clone()).for_user(user_id).await?;
+        Ok(Duration::minutes(preferences.general.utc_offset_minutes as i64))
+    }
+
+    /// Один сгруппированный запрос на весь период и один — на цели, сколько бы дней ни было.
+    /// Дни без записей возвращаются с нулями, чтобы в графиках не было пропусков.
+    async fn summaries(&self, user_id: Uuid, from: NaiveDate, to: NaiveDate, offset: Duration) -> Result<Vec<NutritionSummary>, AppError> {
+        let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+        let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+
+        let scope = UserScope::new(user_id);
+        let rows = scope.query_as::<DayMealTotals>(
+            r#"
+            SELECT (consumed_at AT TIME ZONE 'UTC' + make_interval(mins => $4))::date AS day,
+                   meal_type,
+                   SUM(calories_per_100g * portion_size / 100)::REAL AS calories,
+                   SUM(protein_per_100g * portion_size / 100)::REAL AS protein,
+                   SUM(fat_per_100g * portion_size / 100)::REAL AS fat,
+                   SUM(carbs_per_100g * portion_size / 100)::REAL AS carbs,
+                   SUM(COALESCE(fiber_per_100g, 0) * portion_size / 100)::REAL AS fiber,
+                   SUM(COALESCE(sugar_per_100g, 0) * portion_size / 100)::REAL AS sugar,
+                   SUM(COALESCE(sodium_per_100g, 0) * portion_size / 100)::REAL AS sodium,
+                   COUNT(*)::INTEGER AS entries_count
+            FROM diary_entries
+            WHERE user_id = $1 AND consumed_at >= $2 AND consumed_at < $3
+            GROUP BY day, meal_type
+            ORDER BY day, MIN(consumed_at)
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .bind(offset.num_minutes() as i32)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        let (calorie_goal, protein_goal): (Option<f32>, Option<f32>) = scope.query_as(
+            r#"
+            SELECT
+                (SELECT daily_target FROM goals
+                 WHERE user_id = $1 AND status = 'active' AND goal_type = 'calorie_intake' AND daily_target > 0
+                 ORDER BY updated_at DESC LIMIT 1),
+                (SELECT daily_target FROM goals
+                 WHERE user_id = $1 AND status = 'active' AND goal_type = 'protein_intake' AND daily_target > 0
+                 ORDER BY updated_at DESC LIMIT 1)
+            "#
+        )
+        .fetch_one(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        Ok(summarize_days(from, to, rows, calorie_goal, protein_goal))
+    }
+}
+
+/// Итоги одного приема пищи за местный день
+#[derive(Debug, FromRow)]
+struct DayMealTotals {
+    day: NaiveDate,
+    meal_type: String,
+    calories: f32,
+    protein: f32,
+    fat: f32,
+    carbs: f32,
+    fiber: f32,
+    sugar: f32,
+    sodium: f32,
+    entries_count: i32,
+}
+
+/// Раскладывает итоги по дням периода; итоги дня — сумма его приемов пищи
+fn summarize_days(
+    from: NaiveDate,
+    to: NaiveDate,
+    rows: Vec<DayMealTotals>,
+    calorie_goal: Option<f32>,
+    protein_goal: Option<f32>,
+) -> Vec<NutritionSummary> {
+    let mut days: Vec<NutritionSummary> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| NutritionSummary {
             date,
-            total_calories: 2000.0,
-            total_protein: 100.0,
-            total_fat: 70.0,
-            total_carbs: 250.0,
-            total_fiber: 25.0,
-            total_sugar: 50.0,
-            total_sodium: 2300.0,
-            meal_breakdown: vec![],
-            calorie_goal: Some(2200.0),
-            protein_goal: Some(120.0),
-            fat_goal: Some(80.0),
-            carbs_goal: Some(300.0),
+            total_calories: 0.0,
+            total_protein: 0.0,
+            total_fat: 0.0,
+            total_carbs: 0.0,
+            total_fiber: 0.0,
+            total_sugar: 0.0,
+            total_sodium: 0.0,
+            meal_breakdown: Vec::new(),
+            calorie_goal,
+            protein_goal,
+            fat_goal: None,
+            carbs_goal: None,
         })
+        .collect();
+
+    for row in rows {
+        let Some(day) = days.get_mut((row.day - from).num_days() as usize) else {
+            continue;
+        };
+        day.total_calories += row.calories;
+        day.total_protein += row.protein;
+        day.total_fat += row.fat;
+        day.total_carbs += row.carbs;
+        day.total_fiber += row.fiber;
+        day.total_sugar += row.sugar;
+        day.total_sodium += row.sodium;
+        day.meal_breakdown.push(MealSummary {
+            meal_type: row.meal_type,
+            calories: row.calories,
+            protein: row.protein,
+            fat: row.fat,
+            carbs: row.carbs,
+            entries_count: row.entries_count,
+        });
     }
 
-    pub async fn get_weekly_nutrition(&self, user_id: Uuid) -> Result<Vec<NutritionSummary>, AppError> {
-        // Mock implementation - return 7 days of mock data
-        let mut summaries = Vec::new();
-        let today = chrono::Utc::now().date_naive();
-        
-        for i in 0..7 {
-            let date = today - chrono::Duration::days(i);
-            let summary = self.get_daily_summary(user_id, date).await?;
-            summaries.push(summary);
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_user};
+
+    #[sqlx::test]
+    async fn week_through_the_range_path_equals_each_day_on_its_own(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        PreferencesService::new(pool.clone())
+            .update(user_id, |preferences| preferences.general.utc_offset_minutes = 180)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO goals (user_id, title, goal_type, target_value, unit, daily_target) \
+             VALUES ($1, 'Калории', 'calorie_intake', 2000, 'kcal', 2000)"
+        )
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Москва (UTC+3): записи у местной полуночи попадают в соседние дни, 12.10 пустой
+        let at = |day, hour, minute| Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap();
+        for (meal_type, calories, protein, consumed_at) in [
+            ("breakfast", 350.0, 15.0, at(10, 5, 0)),
+            ("dinner", 700.0, 40.0, at(10, 17, 0)),
+            ("snack", 200.0, 5.0, at(10, 20, 59)),
+            ("snack", 150.0, 3.0, at(10, 21, 0)),
+            ("lunch", 600.0, 30.0, at(13, 9, 0)),
+            ("lunch", 250.0, 10.0, at(13, 10, 30)),
+            ("dinner", 800.0, 45.0, at(15, 18, 0)),
+            ("breakfast", 400.0, 20.0, at(16, 6, 0)),
+        ] {
+            insert_diary_entry(&pool, user_id, meal_type, calories, protein, consumed_at).await;
         }
+        // Чужие записи в сводку не попадают
+        let other = insert_user(&pool, "Олег").await;
+        insert_diary_entry(&pool, other, "lunch", 999.0, 99.0, at(13, 9, 0)).await;
 
-        Ok(summaries)
+        let service = DiaryService::new(pool.clone()).with_clock(frozen_clock(at(16, 9, 0)));
+        let from = NaiveDate::from_ymd_opt(2026, 10, 10).unwrap();
+        let to = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+
+        let range = service.get_summary_range(user_id, Some(from), Some(to)).await.unwrap();
+        let mut days = Vec::new();
+        for date in from.iter_days().take_while(|date| *date <= to) {
+            days.push(service.get_daily_summary(user_id, date).await.unwrap());
+        }
+        assert_eq!(serde_json::to_value(&range).unwrap(), serde_json::to_value(&days).unwrap());
+
+        let totals: Vec<(NaiveDate, f32)> = range.iter().map(|day| (day.date, day.total_calories)).collect();
+        let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        assert_eq!(
+            totals,
+            vec![(date(10), 1250.0), (date(11), 150.0), (date(12), 0.0), (date(13), 850.0), (date(14), 0.0), (date(15), 800.0), (date(16), 400.0)]
+        );
+        let lunch = &range[3].meal_breakdown;
+        assert_eq!(lunch.len(), 1);
+        assert_eq!((lunch[0].meal_type.as_str(), lunch[0].entries_count), ("lunch", 2));
+        assert!(range.iter().all(|day| day.calorie_goal == Some(2000.0)));
+
+        // Неделя по умолчанию — та же, только сегодня первым
+        let mut weekly = service.get_weekly_nutrition(user_id).await.unwrap();
+        weekly.reverse();
+        assert_eq!(serde_json::to_value(&weekly).unwrap(), serde_json::to_value(&range).unwrap());
     }
 }
@@ -0,0 +1,421 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, UserScope},
+    models::bulk::BulkItemFailure,
+    services::{
+        clock::{self, SharedClock},
+        media::MediaLibrary,
+        preferences::PreferencesService,
+    },
+    utils::errors::AppError,
+};
+
+pub const MAX_BULK_IDS: usize = 200;
+/// Период фильтра — не больше года
+pub const MAX_FILTER_DAYS: i64 = 366;
+const CONFIRMATION_TTL_MINUTES: i64 = 10;
+const CONFIRMATION_TOKEN_LENGTH: usize = 32;
+
+/// Какие записи удалить: по id или по местным дням и приему пищи
+#[derive(Debug, Clone)]
+pub enum DiarySelection {
+    Ids(Vec<Uuid>),
+    Filter {
+        from: NaiveDate,
+        to: NaiveDate,
+        meal_type: Option<String>,
+    },
+}
+
+impl DiarySelection {
+    /// Хеш выборки: токен подтверждает именно ее. Порядок и повторы id не важны.
+    fn hash(&self) -> String {
+        let canonical = match self {
+            DiarySelection::Ids(ids) => {
+                let mut ids: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+                ids.sort_unstable();
+                ids.dedup();
+                format!("ids:{}", ids.join(","))
+            }
+            DiarySelection::Filter { from, to, meal_type } => {
+                format!("filter:{}:{}:{}", from, to, meal_type.as_deref().unwrap_or(""))
+            }
+        };
+        token_hash(&canonical)
+    }
+}
+
+/// Что будет удалено; удаление — повторным запросом с токеном
+#[derive(Debug, Clone)]
+pub struct BulkDeletePreview {
+    pub matched: usize,
+    pub failures: Vec<BulkItemFailure>,
+    pub confirmation_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkDeleteOutcome {
+    pub deleted: usize,
+    pub failures: Vec<BulkItemFailure>,
+}
+
+#[derive(sqlx::FromRow)]
+struct MatchedEntry {
+    id: Uuid,
+    media_id: Option<Uuid>,
+}
+
+/// Массовое удаление записей дневника в два шага: предпросмотр выдает токен,
+/// удаление выполняется, только если тот же запрос пришел с токеном в течение 10 минут.
+/// Чужие и несуществующие id пропускаются и возвращаются списком.
+pub struct DiaryBulkDeleteService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl DiaryBulkDeleteService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn preview(&self, user_id: Uuid, selection: &DiarySelection) -> Result<BulkDeletePreview, AppError> {
+        validate(selection)?;
+        let now = self.clock.now();
+        let (matched, failures) = self.matched(user_id, selection).await?;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(CONFIRMATION_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        let expires_at = now + Duration::minutes(CONFIRMATION_TTL_MINUTES);
+
+        UserScope::new(user_id)
+            .query("DELETE FROM bulk_delete_confirmations WHERE user_id = $1 AND expires_at <= $2")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        UserScope::new(user_id)
+            .query(
+                r#"
+                INSERT INTO bulk_delete_confirmations (user_id, token_hash, selection_hash, expires_at)
+                VALUES ($1, $2, $3, $4)
+                "#
+            )
+            .bind(token_hash(&token))
+            .bind(selection.hash())
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(BulkDeletePreview { matched: matched.len(), failures, confirmation_token: token, expires_at })
+    }
+
+    /// Удаляет записи выборки. Токен одноразовый и подходит только к той выборке, для которой выдан;
+    /// по фильтру удаляются записи, подходящие под него в момент удаления.
+    pub async fn delete(&self, user_id: Uuid, selection: &DiarySelection, token: &str) -> Result<BulkDeleteOutcome, AppError> {
+        validate(selection)?;
+        let now = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+
+        let confirmed = UserScope::new(user_id)
+            .query(
+                r#"
+                DELETE FROM bulk_delete_confirmations
+                WHERE user_id = $1 AND token_hash = $2 AND selection_hash = $3 AND expires_at > $4
+                "#
+            )
+            .bind(token_hash(token))
+            .bind(selection.hash())
+            .bind(now)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if confirmed == 0 {
+            return Err(AppError::BadRequest(
+                "Confirmation token is invalid or expired, request a new preview".to_string(),
+            ));
+        }
+
+        let (matched, failures) = self.matched(user_id, selection).await?;
+        let ids: Vec<Uuid> = matched.iter().map(|entry| entry.id).collect();
+        let deleted = UserScope::new(user_id)
+            .query("DELETE FROM diary_entries WHERE user_id = $1 AND id = ANY($2)")
+            .bind(&ids)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as usize;
+
+        let details = match selection {
+            DiarySelection::Ids(requested) => json!({ "requested": requested.len(), "deleted": deleted, "skipped": failures.len() }),
+            DiarySelection::Filter { from, to, meal_type } => {
+                json!({ "from": from, "to": to, "meal_type": meal_type, "deleted": deleted })
+            }
+        };
+        sqlx::query(
+            "INSERT INTO activity_log (id, user_id, actor_id, action, details) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(user_id)
+        .bind("diary_bulk_delete")
+        .bind(details)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // Фото удаленных записей, на которые больше ничто не ссылается
+        let media: Vec<Uuid> = matched.iter().filter_map(|entry| entry.media_id).collect();
+        MediaLibrary::new(self.pool.clone()).delete_unreferenced(&media).await?;
+
+        Ok(BulkDeleteOutcome { deleted, failures })
+    }
+
+    async fn matched(&self, user_id: Uuid, selection: &DiarySelection) -> Result<(Vec<MatchedEntry>, Vec<BulkItemFailure>), AppError> {
+        match selection {
+            DiarySelection::Ids(ids) => {
+                let mut unique = HashSet::new();
+                let ids: Vec<Uuid> = ids.iter().copied().filter(|id| unique.insert(*id)).collect();
+
+                let matched: Vec<MatchedEntry> = UserScope::new(user_id)
+                    .query_as("SELECT id, media_id FROM diary_entries WHERE user_id = $1 AND id = ANY($2)")
+                    .bind(&ids)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let found: HashSet<Uuid> = matched.iter().map(|entry| entry.id).collect();
+                let failures = ids
+                    .into_iter()
+                    .filter(|id| !found.contains(id))
+                    .map(BulkItemFailure::not_found)
+                    .collect();
+
+                Ok((matched, failures))
+            }
+            DiarySelection::Filter { from, to, meal_type } => {
+                let preferences = PreferencesService::new(self.pool.clone()).for_user(user_id).await?;
+                let offset = Duration::minutes(preferences.general.utc_offset_minutes as i64);
+                // Местные дни с `from` по `to` включительно
+                let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+                let end = (*to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - offset;
+
+                let matched = UserScope::new(user_id)
+                    .query_as(
+                        r#"
+                        SELECT id, media_id FROM diary_entries
+                        WHERE user_id = $1 AND consumed_at >= $2 AND consumed_at < $3
+                          AND ($4::text IS NULL OR meal_type = $4)
+                        "#
+                    )
+                    .bind(start)
+                    .bind(end)
+                    .bind(meal_type)
+                    .fetch_all(&self.pool)
+                    .await?;
+
+                Ok((matched, Vec::new()))
+            }
+        }
+    }
+}
+
+fn validate(selection: &DiarySelection) -> Result<(), AppError> {
+    match selection {
+        DiarySelection::Ids(ids) if ids.is_empty() || ids.len() > MAX_BULK_IDS => Err(AppError::BadRequest(
+            format!("ids must contain 1 to {} entries", MAX_BULK_IDS),
+        )),
+        DiarySelection::Filter { from, to, .. } if from > to => {
+            Err(AppError::BadRequest("'from' must not be after 'to'".to_string()))
+        }
+        DiarySelection::Filter { from, to, .. } if (*to - *from).num_days() + 1 > MAX_FILTER_DAYS => Err(
+            AppError::BadRequest(format!("Range must not exceed {} days", MAX_FILTER_DAYS)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn token_hash(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::bulk::BulkFailureReason;
+    use crate::services::clock::SandboxClockMode;
+    use crate::test_support::{access_token, frozen_clock, insert_diary_entry, insert_user, request, send, test_router};
+
+    fn rejected(result: Result<BulkDeleteOutcome, AppError>) -> bool {
+        matches!(result, Err(AppError::BadRequest(_)))
+    }
+
+    async fn remaining(pool: &PgPool, user_id: Uuid) -> Vec<Uuid> {
+        UserScope::new(user_id)
+            .query_scalar("SELECT id FROM diary_entries WHERE user_id = $1 ORDER BY consumed_at")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn deletion_needs_a_fresh_token_for_the_same_selection(pool: PgPool) {
+        let (anna, boris) = (insert_user(&pool, "Анна").await, insert_user(&pool, "Борис").await);
+        let now = Utc.with_ymd_and_hms(2026, 10, 10, 12, 0, 0).unwrap();
+        let clock = frozen_clock(now);
+        let service = DiaryBulkDeleteService::new(pool.clone()).with_clock(clock.clone());
+        let first = insert_diary_entry(&pool, anna, "breakfast", 300.0, 10.0, now - Duration::hours(4)).await;
+        let second = insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, now - Duration::hours(1)).await;
+        let kept = insert_diary_entry(&pool, anna, "dinner", 600.0, 30.0, now).await;
+        let foreign = insert_diary_entry(&pool, boris, "lunch", 500.0, 20.0, now).await;
+
+        let selection = DiarySelection::Ids(vec![first, second, foreign]);
+        let preview = service.preview(anna, &selection).await.unwrap();
+        assert_eq!(preview.matched, 2);
+        assert_eq!(preview.failures.iter().map(|failure| (failure.id, failure.reason)).collect::<Vec<_>>(), vec![(foreign, BulkFailureReason::NotFound)]);
+        assert_eq!(preview.expires_at, now + Duration::minutes(CONFIRMATION_TTL_MINUTES));
+        assert_eq!(remaining(&pool, anna).await.len(), 3, "preview deletes nothing");
+
+        // Неверный токен, чужая выборка, чужой пользователь — ничего не удаляется, токен не сгорает
+        assert!(rejected(service.delete(anna, &selection, "wrong-token").await));
+        assert!(rejected(service.delete(anna, &DiarySelection::Ids(vec![first, second, kept]), &preview.confirmation_token).await));
+        assert!(rejected(service.delete(boris, &selection, &preview.confirmation_token).await));
+        assert_eq!(remaining(&pool, anna).await.len(), 3);
+
+        // Порядок и повторы id выборку не меняют
+        let reordered = DiarySelection::Ids(vec![foreign, second, first, second]);
+        let outcome = service.delete(anna, &reordered, &preview.confirmation_token).await.unwrap();
+        assert_eq!(outcome.deleted, 2);
+        assert_eq!(outcome.failures.iter().map(|failure| failure.id).collect::<Vec<_>>(), vec![foreign]);
+        assert_eq!(remaining(&pool, anna).await, vec![kept]);
+        assert_eq!(remaining(&pool, boris).await, vec![foreign]);
+
+        // Токен одноразовый
+        assert!(rejected(service.delete(anna, &selection, &preview.confirmation_token).await));
+
+        // Через 10 минут токен истекает
+        let selection = DiarySelection::Ids(vec![kept]);
+        let preview = service.preview(anna, &selection).await.unwrap();
+        clock.set_mode(SandboxClockMode::Frozen { at: now + Duration::minutes(CONFIRMATION_TTL_MINUTES) });
+        assert!(rejected(service.delete(anna, &selection, &preview.confirmation_token).await));
+        assert_eq!(remaining(&pool, anna).await, vec![kept]);
+
+        let logged: i64 = UserScope::new(anna)
+            .query_scalar("SELECT COUNT(*) FROM activity_log WHERE user_id = $1 AND action = 'diary_bulk_delete'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, 1);
+    }
+
+    #[sqlx::test]
+    async fn filter_covers_whole_local_days_and_the_meal_type(pool: PgPool) {
+        let (anna, boris) = (insert_user(&pool, "Анна").await, insert_user(&pool, "Борис").await);
+        PreferencesService::new(pool.clone())
+            .update(anna, |preferences| preferences.general.utc_offset_minutes = 180)
+            .await
+            .unwrap();
+        let service = DiaryBulkDeleteService::new(pool.clone());
+        let at = |day, hour, minute| Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap();
+
+        // UTC+3: местные 5 и 6 октября — с 4 окт. 21:00 до 6 окт. 21:00 UTC
+        let before_start = insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, at(4, 20, 59)).await;
+        let at_start = insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, at(4, 21, 0)).await;
+        let dinner = insert_diary_entry(&pool, anna, "dinner", 600.0, 30.0, at(5, 16, 0)).await;
+        let before_end = insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, at(6, 20, 59)).await;
+        let at_end = insert_diary_entry(&pool, anna, "lunch", 500.0, 20.0, at(6, 21, 0)).await;
+        let foreign = insert_diary_entry(&pool, boris, "lunch", 500.0, 20.0, at(5, 10, 0)).await;
+
+        let (from, to) = (NaiveDate::from_ymd_opt(2026, 10, 5).unwrap(), NaiveDate::from_ymd_opt(2026, 10, 6).unwrap());
+        let lunches = DiarySelection::Filter { from, to, meal_type: Some("lunch".to_string()) };
+        let preview = service.preview(anna, &lunches).await.unwrap();
+        assert_eq!(preview.matched, 2);
+        assert!(preview.failures.is_empty());
+
+        let outcome = service.delete(anna, &lunches, &preview.confirmation_token).await.unwrap();
+        assert_eq!(outcome.deleted, 2);
+        assert_eq!(remaining(&pool, anna).await, vec![before_start, dinner, at_end]);
+        assert_eq!(remaining(&pool, boris).await, vec![foreign]);
+        assert!(!remaining(&pool, anna).await.iter().any(|id| [at_start, before_end].contains(id)));
+
+        // Без приема пищи — все записи периода, включая добавленные после предпросмотра
+        let all = DiarySelection::Filter { from, to, meal_type: None };
+        let preview = service.preview(anna, &all).await.unwrap();
+        assert_eq!(preview.matched, 1);
+        let late = insert_diary_entry(&pool, anna, "snack", 200.0, 5.0, at(6, 12, 0)).await;
+        let outcome = service.delete(anna, &all, &preview.confirmation_token).await.unwrap();
+        assert_eq!(outcome.deleted, 2);
+        assert_eq!(remaining(&pool, anna).await, vec![before_start, at_end]);
+        assert!(!remaining(&pool, anna).await.contains(&late));
+
+        // Перевернутый и слишком длинный период, пустой и слишком длинный список id
+        let reversed = DiarySelection::Filter { from: to, to: from, meal_type: None };
+        let too_long = DiarySelection::Filter { from: from - Duration::days(MAX_FILTER_DAYS), to: from, meal_type: None };
+        for selection in [reversed, too_long, DiarySelection::Ids(Vec::new()), DiarySelection::Ids(vec![Uuid::new_v4(); MAX_BULK_IDS + 1])] {
+            assert!(matches!(service.preview(anna, &selection).await, Err(AppError::BadRequest(_))), "{:?}", selection);
+        }
+    }
+
+    #[sqlx::test]
+    async fn archived_goals_are_listed_only_on_request(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+
+        let mut goals = Vec::new();
+        for (title, status) in [("Вода", "active"), ("Белок", "completed"), ("Шаги", "cancelled")] {
+            let goal = serde_json::json!({ "title": title, "goal_type": "Other", "target_value": 10.0, "unit": "шт" });
+            let (status_code, created) = send(&router, request(Method::POST, "/api/v1/goals", Some(&token), Some(goal))).await;
+            assert_eq!(status_code, StatusCode::OK, "{}", created);
+            let id: Uuid = created["id"].as_str().unwrap().parse().unwrap();
+            sqlx::query("UPDATE goals SET status = $2::goal_status WHERE id = $1")
+                .bind(id)
+                .bind(status)
+                .execute(&pool)
+                .await
+                .unwrap();
+            goals.push(id);
+        }
+
+        // Цели только что изменены: в архив уходят с границей на завтра, и только завершенные
+        let (status, archived) = send(&router, request(Method::POST, "/api/v1/goals/archive", Some(&token), Some(serde_json::json!({ "before": Utc::now().date_naive() - Duration::days(1) })))).await;
+        assert_eq!(status, StatusCode::OK, "{}", archived);
+        assert!(archived["archived"].as_array().unwrap().is_empty());
+        let before = Utc::now().date_naive() + Duration::days(1);
+        let (status, archived) = send(&router, request(Method::POST, "/api/v1/goals/archive", Some(&token), Some(serde_json::json!({ "before": before })))).await;
+        assert_eq!(status, StatusCode::OK, "{}", archived);
+        assert_eq!(archived["archived"].as_array().unwrap().len(), 2);
+
+        let listed = |uri: &'static str| {
+            let (router, token) = (router.clone(), token.clone());
+            async move {
+                let (status, list) = send(&router, request(Method::GET, uri, Some(&token), None)).await;
+                assert_eq!(status, StatusCode::OK, "{}", list);
+                let mut ids: Vec<Uuid> = list.as_array().unwrap().iter().map(|goal| goal["id"].as_str().unwrap().parse().unwrap()).collect();
+                ids.sort();
+                ids
+            }
+        };
+        assert_eq!(listed("/api/v1/goals").await, vec![goals[0]]);
+        assert_eq!(listed("/api/v1/goals?include_archived=false").await, vec![goals[0]]);
+        let mut all = goals.clone();
+        all.sort();
+        assert_eq!(listed("/api/v1/goals?include_archived=true").await, all);
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{Duration, NaiveDate};
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, UserScope},
+    models::diary::{DiaryPhoto, DiaryPhotoDay, DiaryPhotoMonth, DiaryPhotoPage},
+    services::{
+        clock::{self, SharedClock},
+        preferences::PreferencesService,
+    },
+    utils::errors::AppError,
+};
+
+/// Период галереи без `from`: месяц до `to`
+const DEFAULT_RANGE_DAYS: i64 = 31;
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Фотогалерея дневника: записи с фото, сгруппированные по местным дням пользователя
+pub struct DiaryPhotoService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl DiaryPhotoService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Фото за местные дни с `from` по `to` включительно. Без `to` — по сегодня,
+    /// без `from` — месяц до `to`. Записи без фото в галерею не попадают.
+    pub async fn photos(
+        &self,
+        user_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<DiaryPhotoPage, AppError> {
+        let utc_offset = self.utc_offset(user_id).await?;
+        let to = to.unwrap_or_else(|| (self.clock.now() + utc_offset).date_naive());
+        let from = from.unwrap_or(to - Duration::days(DEFAULT_RANGE_DAYS - 1));
+
+        if from > to {
+            return Err(AppError::BadRequest("'from' must not be after 'to'".to_string()));
+        }
+        if (to - from).num_days() + 1 > MAX_RANGE_DAYS {
+            return Err(AppError::BadRequest(format!("Range must not exceed {} days", MAX_RANGE_DAYS)));
+        }
+
+        let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - utc_offset;
+        let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - utc_offset;
+
+        // На одну запись больше — так видно, есть ли следующая страница
+        let mut photos: Vec<DiaryPhoto> = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT d.id AS entry_id, m.id AS media_id, m.url, m.thumbnail_url, d.meal_type, d.food_name,
+                       (d.calories_per_100g * d.portion_size / 100)::REAL AS calories, d.consumed_at,
+                       (d.consumed_at AT TIME ZONE 'UTC' + make_interval(mins => $4))::date AS local_date
+                FROM diary_entries d
+                JOIN media_files m ON m.id = d.media_id
+                WHERE d.user_id = $1 AND d.consumed_at >= $2 AND d.consumed_at < $3
+                ORDER BY d.consumed_at DESC, d.id DESC
+                LIMIT $5 OFFSET $6
+                "#
+            )
+            .bind(start)
+            .bind(end)
+            .bind(utc_offset.num_minutes() as i32)
+            .bind(limit + 1)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let has_more = photos.len() as i64 > limit;
+        photos.truncate(limit.max(0) as usize);
+
+        Ok(DiaryPhotoPage { from, to, days: group_by_day(photos), has_more })
+    }
+
+    /// Число фото по местным месяцам, новые первыми; месяцы без фото пропускаются
+    pub async fn monthly_counts(&self, user_id: Uuid) -> Result<Vec<DiaryPhotoMonth>, AppError> {
+        let utc_offset = self.utc_offset(user_id).await?;
+
+        let months = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT to_char(consumed_at AT TIME ZONE 'UTC' + make_interval(mins => $2), 'YYYY-MM') AS month,
+                       COUNT(*) AS photos
+                FROM diary_entries
+                WHERE user_id = $1 AND media_id IS NOT NULL
+                GROUP BY month
+                ORDER BY month DESC
+                "#
+            )
+            .bind(utc_offset.num_minutes() as i32)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(months)
+    }
+
+    async fn utc_offset(&self, user_id: Uuid) -> Result<Duration, AppError> {
+        let preferences = PreferencesService::new(self.pool.clone()).for_user(user_id).await?;
+        Ok(Duration::minutes(preferences.general.utc_offset_minutes as i64))
+    }
+}
+
+/// Фото уже отсортированы по убыванию времени, поэтому фото одного дня идут подряд
+fn group_by_day(photos: Vec<DiaryPhoto>) -> Vec<DiaryPhotoDay> {
+    let mut days: Vec<DiaryPhotoDay> = Vec::new();
+    for photo in photos {
+        match days.last_mut() {
+            Some(day) if day.date == photo.local_date => day.photos.push(photo),
+            _ => days.push(DiaryPhotoDay { date: photo.local_date, photos: vec![photo] }),
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, RgbImage};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::media::{make_thumbnail, THUMBNAIL_SIZE};
+    use crate::test_support::{insert_diary_entry, insert_media, insert_user};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut encoded = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, image::Rgb([200, 120, 40])))
+            .write_to(&mut encoded, ImageOutputFormat::Png)
+            .unwrap();
+        encoded.into_inner()
+    }
+
+    fn thumbnail_dimensions(data: &[u8]) -> (u32, u32) {
+        let thumbnail = make_thumbnail(data).expect("thumbnail");
+        assert_eq!(image::guess_format(&thumbnail).unwrap(), ImageFormat::WebP);
+        image::load_from_memory_with_format(&thumbnail, ImageFormat::WebP).unwrap().dimensions()
+    }
+
+    #[test]
+    fn thumbnails_are_webp_fitted_into_the_grid_square() {
+        assert_eq!(thumbnail_dimensions(&png(1200, 800)), (THUMBNAIL_SIZE, 267));
+        assert_eq!(thumbnail_dimensions(&png(300, 900)), (133, THUMBNAIL_SIZE));
+        assert_eq!(thumbnail_dimensions(&png(400, 400)), (400, 400));
+        // Маленькие изображения не увеличиваются
+        assert_eq!(thumbnail_dimensions(&png(120, 80)), (120, 80));
+        assert!(make_thumbnail(b"not an image").is_none());
+    }
+
+    async fn photo_entry(pool: &PgPool, user_id: Uuid, meal_type: &str, consumed_at: DateTime<Utc>) -> Uuid {
+        let entry_id = insert_diary_entry(pool, user_id, meal_type, 300.0, 10.0, consumed_at).await;
+        sqlx::query("UPDATE diary_entries SET media_id = $1 WHERE id = $2")
+            .bind(insert_media(pool, user_id).await)
+            .bind(entry_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        entry_id
+    }
+
+    #[sqlx::test]
+    async fn photos_are_grouped_by_local_day_across_a_month_boundary(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        PreferencesService::new(pool.clone())
+            .update(user_id, |preferences| preferences.general.utc_offset_minutes = 180)
+            .await
+            .unwrap();
+
+        // UTC+3: 20:30 UTC — еще 30 сентября, 21:30 UTC — уже 1 октября
+        let late_dinner = photo_entry(&pool, user_id, "dinner", Utc.with_ymd_and_hms(2026, 9, 30, 20, 30, 0).unwrap()).await;
+        let midnight_snack = photo_entry(&pool, user_id, "snack", Utc.with_ymd_and_hms(2026, 9, 30, 21, 30, 0).unwrap()).await;
+        let breakfast = photo_entry(&pool, user_id, "breakfast", Utc.with_ymd_and_hms(2026, 10, 1, 5, 0, 0).unwrap()).await;
+        insert_diary_entry(&pool, user_id, "lunch", 500.0, 20.0, Utc.with_ymd_and_hms(2026, 10, 1, 9, 0, 0).unwrap()).await;
+
+        let service = DiaryPhotoService::new(pool.clone());
+        let september = NaiveDate::from_ymd_opt(2026, 9, 30).unwrap();
+        let october = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let page = service.photos(user_id, Some(september), Some(october), 50, 0).await.unwrap();
+
+        let days: Vec<(NaiveDate, Vec<Uuid>)> = page.days.iter()
+            .map(|day| (day.date, day.photos.iter().map(|photo| photo.entry_id).collect()))
+            .collect();
+        assert_eq!(days, vec![(october, vec![breakfast, midnight_snack]), (september, vec![late_dinner])]);
+        assert!(!page.has_more);
+        assert_eq!(page.days[1].photos[0].calories, 300.0);
+
+        // Границы периода считаются по местному дню
+        let only_september = service.photos(user_id, Some(september), Some(september), 50, 0).await.unwrap();
+        assert_eq!(only_september.days.len(), 1);
+        assert_eq!(only_september.days[0].photos.len(), 1);
+        assert_eq!(only_september.days[0].photos[0].entry_id, late_dinner);
+
+        // День может продолжиться на следующей странице
+        let first = service.photos(user_id, Some(september), Some(october), 1, 0).await.unwrap();
+        let second = service.photos(user_id, Some(september), Some(october), 1, 1).await.unwrap();
+        assert!(first.has_more && second.has_more);
+        assert_eq!((first.days[0].date, second.days[0].date), (october, october));
+
+        let months: Vec<(String, i64)> = service.monthly_counts(user_id).await.unwrap()
+            .into_iter()
+            .map(|month| (month.month, month.photos))
+            .collect();
+        assert_eq!(months, vec![("2026-10".to_string(), 2), ("2026-09".to_string(), 1)]);
+    }
+}
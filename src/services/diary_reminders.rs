@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::notification::NotificationEvent,
+    services::{
+        clock::{self, SharedClock},
+        home::count_streak,
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::{errors::AppError, format::format_days, i18n},
+};
+
+/// Время напоминания задается с точностью до минуты — проверка чаще, чем у других планировщиков
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// За сколько дней ищутся записи для подсчета серии
+const STREAK_LOOKBACK_DAYS: i64 = 365;
+/// Экран клиента, который открывает напоминание
+const DIARY_DEEP_LINK: &str = "/diary";
+
+/// Вечернее напоминание о дневнике тем, кто включил его в настройках и за местный день
+/// ничего не записал. Записавшим хоть что-то напоминание не приходит.
+pub struct DiaryReminderScheduler {
+    pool: DbPool,
+    delivery: NotificationDelivery,
+    clock: SharedClock,
+}
+
+impl DiaryReminderScheduler {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>) -> Self {
+        Self {
+            delivery: NotificationDelivery::new(pool.clone(), realtime_service),
+            pool,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.run_once(self.clock.now()).instrument(telemetry::job_span("diary_reminders")).await {
+                    Ok(sent) if sent > 0 => info!("Diary reminders sent: {}", sent),
+                    Ok(_) => {}
+                    Err(e) => warn!("Diary reminder run failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    /// Одна проверка на момент `now`. Напоминание уходит, когда местное время пользователя
+    /// дошло до выбранного, а записей за местный день нет; не больше одного в день.
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        // Выборка всех пользователей и отметка last_reminded_on — один запрос:
+        // повторный или параллельный запуск в тот же местный день никого не выберет
+        let due: Vec<(Uuid, NaiveDate, Option<String>)> = sqlx::query_as(
+            r#"
+            WITH enabled AS (
+                SELECT user_id,
+                       make_interval(mins => COALESCE((settings->'general'->>'utc_offset_minutes')::int, 0)) AS utc_offset
+                FROM user_preferences
+                WHERE (settings->'diary_reminder'->>'enabled')::boolean IS TRUE
+            ),
+            local_now AS (
+                SELECT user_id, utc_offset, ($1::timestamptz AT TIME ZONE 'UTC') + utc_offset AS local_now
+                FROM enabled
+            )
+            UPDATE user_preferences p
+            SET settings = jsonb_set(p.settings, '{diary_reminder,last_reminded_on}', to_jsonb(l.local_now::date::text))
+            FROM local_now l
+            WHERE p.user_id = l.user_id
+              AND l.local_now::time >= COALESCE((p.settings->'diary_reminder'->>'time')::time, '20:00')
+              AND (p.settings->'diary_reminder'->>'last_reminded_on') IS DISTINCT FROM l.local_now::date::text
+              AND NOT EXISTS (
+                  SELECT 1 FROM diary_entries d
+                  WHERE d.user_id = p.user_id
+                    AND d.consumed_at >= (l.local_now::date - l.utc_offset) AT TIME ZONE 'UTC'
+                    AND d.consumed_at < (l.local_now::date + 1 - l.utc_offset) AT TIME ZONE 'UTC'
+              )
+            RETURNING p.user_id, l.local_now::date, p.settings->'general'->>'locale'
+            "#
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if due.is_empty() {
+            return Ok(0);
+        }
+
+        let user_ids: Vec<Uuid> = due.iter().map(|(user_id, _, _)| *user_id).collect();
+        let mut logged_days = self.logged_days(&user_ids, now).await?;
+
+        for (user_id, local_date, locale) in &due {
+            let days = logged_days.remove(user_id).unwrap_or_default();
+            let streak_days = count_streak(*local_date, days.into_iter());
+            let locale = i18n::resolve_locale(locale.as_deref(), None);
+
+            let message = if streak_days > 0 {
+                i18n::tf(locale, "diary.reminder.message_streak", &[("days", &format_days(i64::from(streak_days), locale))])
+            } else {
+                i18n::t(locale, "diary.reminder.message").to_string()
+            };
+            let notification = OutgoingNotification {
+                kind: "diary_reminder",
+                title: i18n::t(locale, "diary.reminder.title").to_string(),
+                message,
+                data: serde_json::json!({
+                    "local_date": local_date,
+                    "streak_days": streak_days,
+                    "deep_link": DIARY_DEEP_LINK,
+                }),
+                event: WebSocketEvent::DiaryReminder {
+                    local_date: *local_date,
+                    streak_days,
+                    deep_link: DIARY_DEEP_LINK.to_string(),
+                },
+            };
+            self.delivery.send(*user_id, NotificationEvent::DiaryReminder, notification, now).await?;
+        }
+
+        Ok(due.len())
+    }
+
+    /// Местные дни с записями по убыванию — одним запросом для всех пользователей
+    async fn logged_days(&self, user_ids: &[Uuid], now: DateTime<Utc>) -> Result<HashMap<Uuid, Vec<NaiveDate>>, AppError> {
+        let rows: Vec<(Uuid, NaiveDate)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT d.user_id,
+                   ((d.consumed_at AT TIME ZONE 'UTC')
+                       + make_interval(mins => COALESCE((p.settings->'general'->>'utc_offset_minutes')::int, 0)))::date AS day
+            FROM diary_entries d
+            JOIN user_preferences p ON p.user_id = d.user_id
+            WHERE d.user_id = ANY($1) AND d.consumed_at >= $2
+            ORDER BY d.user_id, day DESC
+            "#
+        )
+        .bind(user_ids)
+        .bind(now - chrono::Duration::days(STREAK_LOOKBACK_DAYS))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut days: HashMap<Uuid, Vec<NaiveDate>> = HashMap::new();
+        for (user_id, day) in rows {
+            days.entry(user_id).or_default().push(day);
+        }
+        Ok(days)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::clock::{Clock, SandboxClock, SandboxClockMode};
+    use crate::services::preferences::PreferencesService;
+    use crate::services::realtime::WebSocketManager;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_user};
+    use crate::utils::format::Locale;
+
+    /// Москва: напоминание в 20:00 местного — 17:00 UTC
+    const UTC_OFFSET_MINUTES: i32 = 180;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    async fn enable_reminder(pool: &PgPool, user_id: Uuid, enabled: bool) {
+        PreferencesService::new(pool.clone())
+            .update(user_id, |preferences| {
+                preferences.general.utc_offset_minutes = UTC_OFFSET_MINUTES;
+                preferences.diary_reminder.enabled = enabled;
+            })
+            .await
+            .unwrap();
+    }
+
+    /// Переводит часы на `now` и запускает проверку, как это делает фоновая задача
+    async fn run_at(scheduler: &DiaryReminderScheduler, clock: &SandboxClock, now: DateTime<Utc>) -> usize {
+        clock.set_mode(SandboxClockMode::Frozen { at: now });
+        scheduler.run_once(clock.now()).await.unwrap()
+    }
+
+    async fn reminders(pool: &PgPool, user_id: Uuid) -> Vec<String> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT message FROM notifications WHERE user_id = $1 AND kind = 'diary_reminder' ORDER BY created_at"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        rows.into_iter().map(|(message,)| message).collect()
+    }
+
+    fn scheduler(pool: &PgPool, manager: Arc<WebSocketManager>, clock: Arc<SandboxClock>) -> DiaryReminderScheduler {
+        DiaryReminderScheduler::new(pool.clone(), Arc::new(RealtimeService::new(manager))).with_clock(clock)
+    }
+
+    #[sqlx::test]
+    async fn reminder_fires_at_local_time_when_nothing_is_logged(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        enable_reminder(&pool, user_id, true).await;
+        let manager = Arc::new(WebSocketManager::new());
+        let mut registration = manager.add_client(user_id, None, "Анна".to_string(), Locale::Ru).await.unwrap();
+        let clock = frozen_clock(at(16, 12, 0));
+        let scheduler = scheduler(&pool, manager, clock.clone());
+
+        // 19:59 по Москве — еще рано
+        assert_eq!(run_at(&scheduler, &clock, at(16, 16, 59)).await, 0);
+        assert_eq!(run_at(&scheduler, &clock, at(16, 17, 0)).await, 1);
+
+        assert_eq!(reminders(&pool, user_id).await, vec![i18n::t(Locale::Ru, "diary.reminder.message").to_string()]);
+        match registration.receiver.try_recv().unwrap() {
+            WebSocketEvent::DiaryReminder { local_date, streak_days, deep_link } => {
+                assert_eq!(local_date, NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+                assert_eq!((streak_days, deep_link.as_str()), (0, DIARY_DEEP_LINK));
+            }
+            other => panic!("expected DiaryReminder, got {:?}", other),
+        }
+    }
+
+    #[sqlx::test]
+    async fn yesterdays_entry_turns_the_reminder_into_a_streak_warning(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        enable_reminder(&pool, user_id, true).await;
+        // 23:30 и 12:00 по Москве двух прошлых дней
+        insert_diary_entry(&pool, user_id, "dinner", 500.0, 20.0, at(15, 20, 30)).await;
+        insert_diary_entry(&pool, user_id, "lunch", 500.0, 20.0, at(14, 9, 0)).await;
+        let clock = frozen_clock(at(16, 12, 0));
+        let scheduler = scheduler(&pool, Arc::new(WebSocketManager::new()), clock.clone());
+
+        assert_eq!(run_at(&scheduler, &clock, at(16, 17, 30)).await, 1);
+
+        let expected = i18n::tf(Locale::Ru, "diary.reminder.message_streak", &[("days", &format_days(2, Locale::Ru))]);
+        assert_eq!(reminders(&pool, user_id).await, vec![expected]);
+    }
+
+    #[sqlx::test]
+    async fn reminder_is_suppressed_by_a_local_entry_or_when_disabled(pool: PgPool) {
+        let logged = insert_user(&pool, "Анна").await;
+        let disabled = insert_user(&pool, "Борис").await;
+        let forgot = insert_user(&pool, "Вера").await;
+        enable_reminder(&pool, logged, true).await;
+        enable_reminder(&pool, disabled, false).await;
+        enable_reminder(&pool, forgot, true).await;
+        // 00:30 16-го по Москве — по UTC это еще 15-е
+        insert_diary_entry(&pool, logged, "snack", 150.0, 3.0, at(15, 21, 30)).await;
+        let clock = frozen_clock(at(16, 12, 0));
+        let scheduler = scheduler(&pool, Arc::new(WebSocketManager::new()), clock.clone());
+
+        assert_eq!(run_at(&scheduler, &clock, at(16, 18, 0)).await, 1);
+        assert_eq!(reminders(&pool, forgot).await.len(), 1);
+        assert!(reminders(&pool, logged).await.is_empty());
+        assert!(reminders(&pool, disabled).await.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn at_most_one_reminder_per_local_day(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        enable_reminder(&pool, user_id, true).await;
+        let clock = frozen_clock(at(16, 12, 0));
+        let scheduler = scheduler(&pool, Arc::new(WebSocketManager::new()), clock.clone());
+
+        assert_eq!(run_at(&scheduler, &clock, at(16, 17, 5)).await, 1);
+        // Позже тем же вечером и после полуночи по Москве (00:30 17-го) — ничего
+        assert_eq!(run_at(&scheduler, &clock, at(16, 20, 0)).await, 0);
+        assert_eq!(run_at(&scheduler, &clock, at(16, 21, 30)).await, 0);
+        assert_eq!(reminders(&pool, user_id).await.len(), 1);
+
+        let (last_reminded_on,): (Option<String>,) = sqlx::query_as(
+            "SELECT settings->'diary_reminder'->>'last_reminded_on' FROM user_preferences WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(last_reminded_on.as_deref(), Some("2026-10-16"));
+
+        // Следующий местный вечер — снова одно
+        assert_eq!(run_at(&scheduler, &clock, at(17, 17, 5)).await, 1);
+        assert_eq!(reminders(&pool, user_id).await.len(), 2);
+    }
+}
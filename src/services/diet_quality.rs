@@ -0,0 +1,524 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPools, ReadConsistency},
+    models::{
+        diary::DiaryEntry,
+        fridge::FridgeCategory,
+        health::{DietMoodInsight, DietQualityComponent, DietQualityDay, DietQualityFactor},
+    },
+    utils::{errors::AppError, food_category::categorize_food, format::round_to},
+};
+
+/// Самый длинный период, за который считается ряд оценок
+pub const MAX_DIET_QUALITY_DAYS: i64 = 365;
+/// Порог "хорошего" дня для сравнения настроения
+pub const HIGH_DIET_QUALITY_SCORE: u8 = 70;
+/// Меньше дней в одной из групп — сравнение настроения не показывается
+const MIN_DAYS_PER_GROUP: usize = 3;
+/// Приемы пищи, которые учитываются в регулярности питания
+const MAIN_MEALS: [&str; 3] = ["breakfast", "lunch", "dinner"];
+
+/// Рубрика оценки питания за день. Веса в сумме дают 100.
+/// Пищевые вещества, которых нет в записи дневника, считаются равными нулю:
+/// клетчатка без данных не засчитывается, сахар и натрий без данных не штрафуются.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DietQualityRubric {
+    pub protein_weight: f64,
+    pub fiber_weight: f64,
+    pub sugar_weight: f64,
+    pub sodium_weight: f64,
+    pub produce_weight: f64,
+    pub meal_regularity_weight: f64,
+    /// Норма белка, если у пользователя нет активной цели по белку с дневной нормой, г
+    pub default_protein_target_g: f64,
+    pub fiber_target_g: f64,
+    /// До этого значения — полный балл, от `sugar_max_g` и выше — ноль, между ними линейно
+    pub sugar_limit_g: f64,
+    pub sugar_max_g: f64,
+    pub sodium_limit_mg: f64,
+    pub sodium_max_mg: f64,
+}
+
+pub const RUBRIC: DietQualityRubric = DietQualityRubric {
+    protein_weight: 30.0,
+    fiber_weight: 20.0,
+    sugar_weight: 15.0,
+    sodium_weight: 15.0,
+    produce_weight: 10.0,
+    meal_regularity_weight: 10.0,
+    default_protein_target_g: 60.0,
+    fiber_target_g: 25.0,
+    sugar_limit_g: 25.0,
+    sugar_max_g: 75.0,
+    sodium_limit_mg: 2000.0,
+    sodium_max_mg: 4000.0,
+};
+
+/// Ряд оценок за период вместе с рубрикой, по которой они посчитаны
+#[derive(Debug, Clone, Serialize)]
+pub struct DietQualitySeries {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Только дни с записями в дневнике
+    pub days: Vec<DietQualityDay>,
+    pub average_score: Option<f64>,
+    pub protein_target_g: f64,
+    pub rubric: DietQualityRubric,
+}
+
+/// Оценка одного дня по записям дневника. `None`, если записей нет.
+pub fn score_day(date: NaiveDate, entries: &[DiaryEntry], protein_target_g: f64, rubric: &DietQualityRubric) -> Option<DietQualityDay> {
+    if entries.is_empty() {
+        return None;
+    }
+
+    let (mut protein, mut fiber, mut sugar, mut sodium) = (0.0, 0.0, 0.0, 0.0);
+    let mut produce: HashSet<FridgeCategory> = HashSet::new();
+    let mut meals: HashSet<&str> = HashSet::new();
+
+    for entry in entries {
+        let multiplier = entry.portion_size as f64 / 100.0;
+        protein += entry.protein_per_100g as f64 * multiplier;
+        fiber += entry.fiber_per_100g.unwrap_or(0.0) as f64 * multiplier;
+        sugar += entry.sugar_per_100g.unwrap_or(0.0) as f64 * multiplier;
+        sodium += entry.sodium_per_100g.unwrap_or(0.0) as f64 * multiplier;
+
+        if let Some(category) = categorize_food(&entry.food_name) {
+            produce.insert(category);
+        }
+        if let Some(meal) = MAIN_MEALS.iter().find(|meal| entry.meal_type.eq_ignore_ascii_case(meal)) {
+            meals.insert(*meal);
+        }
+    }
+
+    let components = vec![
+        component(DietQualityFactor::Protein, rubric.protein_weight, adequacy(protein, protein_target_g), protein, protein_target_g),
+        component(DietQualityFactor::Fiber, rubric.fiber_weight, adequacy(fiber, rubric.fiber_target_g), fiber, rubric.fiber_target_g),
+        component(DietQualityFactor::Sugar, rubric.sugar_weight, moderation(sugar, rubric.sugar_limit_g, rubric.sugar_max_g), sugar, rubric.sugar_limit_g),
+        component(DietQualityFactor::Sodium, rubric.sodium_weight, moderation(sodium, rubric.sodium_limit_mg, rubric.sodium_max_mg), sodium, rubric.sodium_limit_mg),
+        component(DietQualityFactor::Produce, rubric.produce_weight, produce.len() as f64 / 2.0, produce.len() as f64, 2.0),
+        component(
+            DietQualityFactor::MealRegularity,
+            rubric.meal_regularity_weight,
+            meals.len() as f64 / MAIN_MEALS.len() as f64,
+            meals.len() as f64,
+            MAIN_MEALS.len() as f64,
+        ),
+    ];
+
+    let total: f64 = components.iter().map(|component| component.score).sum();
+
+    Some(DietQualityDay {
+        date,
+        score: total.round().clamp(0.0, 100.0) as u8,
+        components,
+        entries_count: entries.len(),
+    })
+}
+
+fn component(factor: DietQualityFactor, weight: f64, fraction: f64, value: f64, target: f64) -> DietQualityComponent {
+    DietQualityComponent {
+        factor,
+        score: round_to(weight * fraction.clamp(0.0, 1.0), 1),
+        max_score: weight,
+        value: round_to(value, 1),
+        target,
+    }
+}
+
+/// Доля нормы: 1.0 при достижении цели и выше
+fn adequacy(value: f64, target: f64) -> f64 {
+    if target <= 0.0 {
+        return 1.0;
+    }
+    (value / target).min(1.0)
+}
+
+/// Умеренность: 1.0 до `limit`, 0.0 от `max`, между ними линейно
+fn moderation(value: f64, limit: f64, max: f64) -> f64 {
+    if value <= limit {
+        1.0
+    } else if value >= max {
+        0.0
+    } else {
+        (max - value) / (max - limit)
+    }
+}
+
+/// Среднее настроение в дни с оценкой выше порога и в остальные дни.
+/// Настроение — среднее за день по проверкам самочувствия (1–10).
+pub fn diet_mood_insight(days: &[DietQualityDay], moods: &BTreeMap<NaiveDate, f64>) -> Option<DietMoodInsight> {
+    let (mut high, mut other) = (Vec::new(), Vec::new());
+    for day in days {
+        let Some(mood) = moods.get(&day.date) else {
+            continue;
+        };
+        if day.score > HIGH_DIET_QUALITY_SCORE {
+            high.push(*mood);
+        } else {
+            other.push(*mood);
+        }
+    }
+
+    if high.len() < MIN_DAYS_PER_GROUP || other.len() < MIN_DAYS_PER_GROUP {
+        return None;
+    }
+
+    let average = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let avg_high = round_to(average(&high), 1);
+    let avg_other = round_to(average(&other), 1);
+    let difference = round_to(avg_high - avg_other, 1);
+
+    let finding = if difference > 0.0 {
+        format!("В дни с оценкой питания выше {} ваше среднее настроение выше на {:.1}", HIGH_DIET_QUALITY_SCORE, difference)
+    } else if difference < 0.0 {
+        format!("В дни с оценкой питания выше {} ваше среднее настроение ниже на {:.1}", HIGH_DIET_QUALITY_SCORE, -difference)
+    } else {
+        format!("Среднее настроение не зависит от того, выше ли оценка питания {}", HIGH_DIET_QUALITY_SCORE)
+    };
+
+    Some(DietMoodInsight {
+        threshold: HIGH_DIET_QUALITY_SCORE,
+        high_score_days: high.len(),
+        other_days: other.len(),
+        avg_mood_high_score: avg_high,
+        avg_mood_other: avg_other,
+        difference,
+        finding,
+    })
+}
+
+/// Ежедневная оценка качества питания по дневнику. Оценки пересчитываются при каждом
+/// запросе (записи дневника могут меняться задним числом) и сохраняются по дням.
+/// Дневник и цели читаются с реплики, оценки сохраняются в основную базу
+pub struct DietQualityService {
+    pools: DbPools,
+}
+
+impl DietQualityService {
+    pub fn new(pools: DbPools) -> Self {
+        Self { pools }
+    }
+
+    /// Оценки за `days` дней, заканчивая `today` включительно. С `exclude_estimates` записи
+    /// с оценкой питательности от ИИ не учитываются, а такие оценки не сохраняются.
+    pub async fn series(&self, user_id: Uuid, today: NaiveDate, days: i64, exclude_estimates: bool) -> Result<DietQualitySeries, AppError> {
+        let days = days.clamp(1, MAX_DIET_QUALITY_DAYS);
+        let start_date = today - Duration::days(days - 1);
+
+        let entries = sqlx::query_as::<_, DiaryEntry>(
+            r#"
+            SELECT * FROM diary_entries
+            WHERE user_id = $1 AND consumed_at::date >= $2 AND consumed_at::date <= $3
+              AND NOT ($4 AND nutrition_source = 'ai_estimate')
+            ORDER BY consumed_at
+            "#
+        )
+        .bind(user_id)
+        .bind(start_date)
+        .bind(today)
+        .bind(exclude_estimates)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        let protein_target_g = self.protein_target(user_id).await?;
+
+        let mut by_day: BTreeMap<NaiveDate, Vec<DiaryEntry>> = BTreeMap::new();
+        for entry in entries {
+            by_day.entry(entry.consumed_at.date_naive()).or_default().push(entry);
+        }
+
+        let scored: Vec<DietQualityDay> = by_day
+            .iter()
+            .filter_map(|(date, entries)| score_day(*date, entries, protein_target_g, &RUBRIC))
+            .collect();
+
+        if !exclude_estimates {
+            self.store(user_id, start_date, today, &scored).await?;
+        }
+
+        let average_score = (!scored.is_empty())
+            .then(|| round_to(scored.iter().map(|day| day.score as f64).sum::<f64>() / scored.len() as f64, 1));
+
+        Ok(DietQualitySeries {
+            start_date,
+            end_date: today,
+            days: scored,
+            average_score,
+            protein_target_g,
+            rubric: RUBRIC,
+        })
+    }
+
+    /// Дневная норма белка из активной цели, иначе норма рубрики
+    async fn protein_target(&self, user_id: Uuid) -> Result<f64, AppError> {
+        let target: Option<(Option<f32>,)> = sqlx::query_as(
+            r#"
+            SELECT daily_target FROM goals
+            WHERE user_id = $1 AND status = 'active' AND goal_type = 'protein_intake' AND daily_target > 0
+            ORDER BY updated_at DESC
+            LIMIT 1
+            "#
+        )
+        .bind(user_id)
+        .fetch_optional(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        Ok(target
+            .and_then(|(daily_target,)| daily_target)
+            .map_or(RUBRIC.default_protein_target_g, |daily_target| daily_target as f64))
+    }
+
+    /// Сохраняет оценки периода; дни, где записей больше нет, удаляются
+    async fn store(&self, user_id: Uuid, start_date: NaiveDate, end_date: NaiveDate, days: &[DietQualityDay]) -> Result<(), AppError> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let dates: Vec<NaiveDate> = days.iter().map(|day| day.date).collect();
+        sqlx::query(
+            "DELETE FROM diet_quality_scores WHERE user_id = $1 AND date >= $2 AND date <= $3 AND NOT (date = ANY($4))"
+        )
+        .bind(user_id)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(&dates)
+        .execute(&mut *tx)
+        .await?;
+
+        for day in days {
+            sqlx::query(
+                r#"
+                INSERT INTO diet_quality_scores (user_id, date, score, components, computed_at)
+                VALUES ($1, $2, $3, $4, NOW())
+                ON CONFLICT (user_id, date)
+                DO UPDATE SET score = EXCLUDED.score, components = EXCLUDED.components, computed_at = NOW()
+                "#
+            )
+            .bind(user_id)
+            .bind(day.date)
+            .bind(day.score as i16)
+            .bind(Json(&day.components))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::diary::NutritionSource;
+    use crate::test_support::insert_user;
+
+    fn day(offset: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 1).unwrap() + Duration::days(offset)
+    }
+
+    fn at(offset: i64, hour: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&day(offset).and_hms_opt(hour, 0, 0).unwrap())
+    }
+
+    /// Запись дневника: питательность на 100 г — белок, клетчатка, сахар, натрий (мг)
+    fn eaten(meal_type: &str, food_name: &str, portion_size: f32, per_100g: (f32, Option<f32>, Option<f32>, Option<f32>)) -> DiaryEntry {
+        let (protein, fiber, sugar, sodium) = per_100g;
+        DiaryEntry {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            food_name: food_name.to_string(),
+            brand: None,
+            portion_size,
+            unit: "g".to_string(),
+            calories_per_100g: 100.0,
+            protein_per_100g: protein,
+            fat_per_100g: 0.0,
+            carbs_per_100g: 0.0,
+            fiber_per_100g: fiber,
+            sugar_per_100g: sugar,
+            sodium_per_100g: sodium,
+            meal_type: meal_type.to_string(),
+            nutrition_source: NutritionSource::User,
+            media_id: None,
+            consumed_at: at(0, 12),
+            created_at: at(0, 12),
+            updated_at: at(0, 12),
+        }
+    }
+
+    /// Три приема пищи, овощи и фрукт: белок 73.6 г, клетчатка 26.6 г, сахар 21.5 г, натрий 431.5 мг
+    fn balanced_day() -> Vec<DiaryEntry> {
+        vec![
+            eaten("breakfast", "Овсянка", 200.0, (12.0, Some(10.0), Some(1.0), Some(5.0))),
+            eaten("lunch", "Салат из помидоров", 150.0, (2.0, Some(2.0), Some(3.0), Some(200.0))),
+            eaten("dinner", "Куриная грудка", 200.0, (23.0, None, None, Some(60.0))),
+            eaten("snack", "Яблоко", 150.0, (0.4, Some(2.4), Some(10.0), Some(1.0))),
+        ]
+    }
+
+    /// Один обед и газировка: белок 33 г, клетчатка 6 г, сахар 65 г, натрий 1820 мг
+    fn fast_food_day() -> Vec<DiaryEntry> {
+        vec![
+            eaten("lunch", "Пицца", 300.0, (11.0, Some(2.0), Some(4.0), Some(600.0))),
+            eaten("snack", "Кола", 500.0, (0.0, None, Some(10.6), Some(4.0))),
+        ]
+    }
+
+    /// Два завтрака без клетчатки и сахара, натрия больше предела: белок 17 г, натрий 4500 мг
+    fn salty_breakfast_day() -> Vec<DiaryEntry> {
+        vec![
+            eaten("Breakfast", "Яичница", 100.0, (13.0, None, None, Some(4500.0))),
+            eaten("breakfast", "Тост", 50.0, (8.0, None, None, None)),
+        ]
+    }
+
+    fn scores(day: &DietQualityDay) -> Vec<(DietQualityFactor, f64)> {
+        day.components.iter().map(|component| (component.factor, component.score)).collect()
+    }
+
+    #[test]
+    fn rubric_weights_add_up_to_100() {
+        let total = RUBRIC.protein_weight
+            + RUBRIC.fiber_weight
+            + RUBRIC.sugar_weight
+            + RUBRIC.sodium_weight
+            + RUBRIC.produce_weight
+            + RUBRIC.meal_regularity_weight;
+        assert_eq!(total, 100.0);
+    }
+
+    #[test]
+    fn fixture_days_match_hand_computed_scores() {
+        use DietQualityFactor::*;
+
+        let balanced = score_day(day(0), &balanced_day(), 60.0, &RUBRIC).unwrap();
+        assert_eq!(
+            scores(&balanced),
+            vec![(Protein, 30.0), (Fiber, 20.0), (Sugar, 15.0), (Sodium, 15.0), (Produce, 10.0), (MealRegularity, 10.0)]
+        );
+        assert_eq!((balanced.score, balanced.entries_count), (100, 4));
+        let values: Vec<f64> = balanced.components.iter().map(|component| component.value).collect();
+        assert_eq!(values, vec![73.6, 26.6, 21.5, 431.5, 2.0, 3.0]);
+
+        // Цель по белку 80 г: 73.6 / 80 × 30 = 27.6
+        let with_goal = score_day(day(0), &balanced_day(), 80.0, &RUBRIC).unwrap();
+        assert_eq!((with_goal.components[0].score, with_goal.components[0].target), (27.6, 80.0));
+        assert_eq!(with_goal.score, 98);
+
+        // 33/60 × 30 = 16.5; 6/25 × 20 = 4.8; сахар (75 − 65)/50 × 15 = 3; натрий в пределе — 15;
+        // без овощей и фруктов — 0; один основной прием из трех — 3.3. Итого 42.6
+        let fast_food = score_day(day(1), &fast_food_day(), 60.0, &RUBRIC).unwrap();
+        assert_eq!(
+            scores(&fast_food),
+            vec![(Protein, 16.5), (Fiber, 4.8), (Sugar, 3.0), (Sodium, 15.0), (Produce, 0.0), (MealRegularity, 3.3)]
+        );
+        assert_eq!(fast_food.score, 43);
+
+        // 17/60 × 30 = 8.5; сахара нет — полный балл; натрий выше максимума — 0;
+        // завтрак с разным регистром считается одним приемом. Итого 26.8
+        let salty = score_day(day(2), &salty_breakfast_day(), 60.0, &RUBRIC).unwrap();
+        assert_eq!(
+            scores(&salty),
+            vec![(Protein, 8.5), (Fiber, 0.0), (Sugar, 15.0), (Sodium, 0.0), (Produce, 0.0), (MealRegularity, 3.3)]
+        );
+        assert_eq!(salty.score, 27);
+
+        assert!(score_day(day(3), &[], 60.0, &RUBRIC).is_none());
+    }
+
+    fn scored(offset: i64, score: u8) -> DietQualityDay {
+        DietQualityDay { date: day(offset), score, components: Vec::new(), entries_count: 1 }
+    }
+
+    #[test]
+    fn mood_is_compared_between_high_and_other_days() {
+        // Порог не включается: день с оценкой 70 — в "остальных"
+        let days = vec![scored(0, 85), scored(1, 90), scored(2, 71), scored(3, 70), scored(4, 40), scored(5, 55), scored(6, 95)];
+        let moods: BTreeMap<NaiveDate, f64> =
+            [(0, 8.0), (1, 7.0), (2, 7.6), (3, 6.0), (4, 6.4), (5, 6.8)].into_iter().map(|(offset, mood)| (day(offset), mood)).collect();
+
+        let insight = diet_mood_insight(&days, &moods).expect("enough days in both groups");
+        assert_eq!((insight.high_score_days, insight.other_days), (3, 3));
+        assert_eq!((insight.avg_mood_high_score, insight.avg_mood_other, insight.difference), (7.5, 6.4, 1.1));
+        assert_eq!(insight.finding, "В дни с оценкой питания выше 70 ваше среднее настроение выше на 1.1");
+
+        let mut sad: BTreeMap<NaiveDate, f64> = moods.clone();
+        for offset in 0..3 {
+            sad.insert(day(offset), 5.0);
+        }
+        assert!(diet_mood_insight(&days, &sad).unwrap().finding.ends_with("ниже на 1.4"));
+
+        // День без проверки самочувствия не учитывается — в группе высоких оценок остается два дня
+        let mut sparse = moods.clone();
+        sparse.remove(&day(2));
+        assert!(diet_mood_insight(&days, &sparse).is_none());
+    }
+
+    async fn insert(pool: &PgPool, user_id: Uuid, entry: &DiaryEntry, consumed_at: DateTime<Utc>, source: &str) {
+        sqlx::query(
+            r#"
+            INSERT INTO diary_entries (
+                user_id, food_name, portion_size, unit, calories_per_100g, protein_per_100g, fat_per_100g,
+                carbs_per_100g, fiber_per_100g, sugar_per_100g, sodium_per_100g, meal_type, nutrition_source, consumed_at
+            )
+            VALUES ($1, $2, $3, 'g', 100, $4, 0, 0, $5, $6, $7, $8, $9::nutrition_source, $10)
+            "#
+        )
+        .bind(user_id)
+        .bind(&entry.food_name)
+        .bind(entry.portion_size)
+        .bind(entry.protein_per_100g)
+        .bind(entry.fiber_per_100g)
+        .bind(entry.sugar_per_100g)
+        .bind(entry.sodium_per_100g)
+        .bind(&entry.meal_type)
+        .bind(source)
+        .bind(consumed_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn series_is_stored_per_day_and_skips_estimates_on_request(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        for entry in balanced_day() {
+            insert(&pool, user_id, &entry, at(0, 12), "user").await;
+        }
+        for entry in fast_food_day() {
+            insert(&pool, user_id, &entry, at(1, 13), "ai_estimate").await;
+        }
+
+        let service = DietQualityService::new(DbPools::single(pool.clone()));
+        let series = service.series(user_id, day(1), 7, false).await.unwrap();
+        let days: Vec<(NaiveDate, u8)> = series.days.iter().map(|day| (day.date, day.score)).collect();
+        assert_eq!(days, vec![(day(0), 100), (day(1), 43)]);
+        assert_eq!((series.start_date, series.average_score, series.protein_target_g), (day(-5), Some(71.5), 60.0));
+
+        let stored: Vec<(NaiveDate, i16)> = sqlx::query_as("SELECT date, score FROM diet_quality_scores WHERE user_id = $1 ORDER BY date")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, vec![(day(0), 100), (day(1), 43)]);
+
+        // Без оценок ИИ остается только первый день, а сохраненный ряд не перезаписывается
+        let manual = service.series(user_id, day(1), 7, true).await.unwrap();
+        assert_eq!(manual.days.len(), 1);
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM diet_quality_scores WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,605 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+
+use crate::{
+    db::DbPool,
+    models::fridge::{
+        Allergen, AllergenSeverity, CreateDietaryProfile, CustomRestriction, DietaryCompatibility, DietaryProfile,
+        DietaryWarning, DietaryWarningType, FridgeComplianceReport, FridgeItem, UpdateDietaryProfile, WarningSeverity,
+    },
+    services::ai::DietaryRestriction,
+    utils::{errors::AppError, ingredient_matcher::normalize_ingredient, sanitize::sanitize_text},
+};
+
+const MAX_CUSTOM_RESTRICTIONS: usize = 30;
+const MAX_RESTRICTION_KEYWORDS: usize = 20;
+
+pub struct DietaryService {
+    pool: DbPool,
+}
+
+impl DietaryService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_profile(&self, user_id: Uuid) -> Result<Option<DietaryProfile>, AppError> {
+        let profile = sqlx::query_as::<_, DietaryProfile>(
+            "SELECT * FROM dietary_profiles WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(profile)
+    }
+
+    pub async fn upsert_profile(&self, profile: CreateDietaryProfile) -> Result<DietaryProfile, AppError> {
+        let profile = sqlx::query_as::<_, DietaryProfile>(
+            r#"
+            INSERT INTO dietary_profiles (user_id, allergies, intolerances, diets, custom_restrictions, severity_notes)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id) DO UPDATE
+            SET allergies = EXCLUDED.allergies,
+                intolerances = EXCLUDED.intolerances,
+                diets = EXCLUDED.diets,
+                custom_restrictions = EXCLUDED.custom_restrictions,
+                severity_notes = EXCLUDED.severity_notes
+            RETURNING *
+            "#
+        )
+        .bind(profile.user_id)
+        .bind(Json(&profile.allergies))
+        .bind(Json(&profile.intolerances))
+        .bind(Json(&profile.diets))
+        .bind(Json(&profile.custom_restrictions))
+        .bind(&profile.severity_notes)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(profile)
+    }
+
+    /// Частичное обновление: незаполненные поля остаются как были
+    pub async fn update_profile(&self, user_id: Uuid, update: UpdateDietaryProfile) -> Result<DietaryProfile, AppError> {
+        let existing = self.get_profile(user_id).await?;
+
+        let merged = CreateDietaryProfile {
+            user_id,
+            allergies: update.allergies
+                .or_else(|| existing.as_ref().map(|profile| profile.allergies.clone()))
+                .unwrap_or_default(),
+            intolerances: update.intolerances
+                .or_else(|| existing.as_ref().map(|profile| profile.intolerances.clone()))
+                .unwrap_or_default(),
+            diets: update.diets
+                .or_else(|| existing.as_ref().map(|profile| profile.diets.clone()))
+                .unwrap_or_default(),
+            custom_restrictions: update.custom_restrictions
+                .map(clean_custom_restrictions)
+                .transpose()?
+                .or_else(|| existing.as_ref().map(|profile| profile.custom_restrictions.clone()))
+                .unwrap_or_default(),
+            severity_notes: update.severity_notes
+                .or_else(|| existing.and_then(|profile| profile.severity_notes)),
+        };
+
+        self.upsert_profile(merged).await
+    }
+}
+
+/// Профиль после объединения аккаунтов: объединение всех ограничений.
+/// Аллергия или свое ограничение, которое есть в обоих профилях, получает более строгий уровень;
+/// заметки о серьезности склеиваются.
+pub fn merge_profiles(user_id: Uuid, target: Option<&DietaryProfile>, source: &DietaryProfile) -> CreateDietaryProfile {
+    let mut merged = CreateDietaryProfile {
+        user_id,
+        allergies: target.map(|profile| profile.allergies.clone()).unwrap_or_default(),
+        intolerances: target.map(|profile| profile.intolerances.clone()).unwrap_or_default(),
+        diets: target.map(|profile| profile.diets.clone()).unwrap_or_default(),
+        custom_restrictions: target.map(|profile| profile.custom_restrictions.clone()).unwrap_or_default(),
+        severity_notes: target.and_then(|profile| profile.severity_notes.clone()),
+    };
+
+    for entry in &source.allergies {
+        match merged.allergies.iter_mut().find(|existing| existing.allergen == entry.allergen) {
+            Some(existing) => existing.severity = stricter(existing.severity, entry.severity),
+            None => merged.allergies.push(entry.clone()),
+        }
+    }
+    for intolerance in &source.intolerances {
+        if !merged.intolerances.contains(intolerance) {
+            merged.intolerances.push(intolerance.clone());
+        }
+    }
+    for diet in &source.diets {
+        if !merged.diets.contains(diet) {
+            merged.diets.push(diet.clone());
+        }
+    }
+    for restriction in &source.custom_restrictions {
+        match merged
+            .custom_restrictions
+            .iter_mut()
+            .find(|existing| existing.name.to_lowercase() == restriction.name.to_lowercase())
+        {
+            Some(existing) => {
+                existing.severity = stricter(existing.severity, restriction.severity);
+                for keyword in &restriction.keywords {
+                    if !existing.keywords.contains(keyword) {
+                        existing.keywords.push(keyword.clone());
+                    }
+                }
+            }
+            None => merged.custom_restrictions.push(restriction.clone()),
+        }
+    }
+
+    merged.severity_notes = match (merged.severity_notes.take(), source.severity_notes.clone()) {
+        (Some(target_notes), Some(source_notes)) if target_notes != source_notes => {
+            Some(format!("{}\n{}", target_notes, source_notes))
+        }
+        (target_notes, source_notes) => target_notes.or(source_notes),
+    };
+
+    merged
+}
+
+fn stricter(a: AllergenSeverity, b: AllergenSeverity) -> AllergenSeverity {
+    let rank = |severity: AllergenSeverity| match severity {
+        AllergenSeverity::Avoid => 0,
+        AllergenSeverity::Strict => 1,
+        AllergenSeverity::LifeThreatening => 2,
+    };
+    if rank(b) > rank(a) { b } else { a }
+}
+
+/// Очищает текст пользовательских ограничений; ключевые слова по умолчанию — название
+fn clean_custom_restrictions(restrictions: Vec<CustomRestriction>) -> Result<Vec<CustomRestriction>, AppError> {
+    if restrictions.len() > MAX_CUSTOM_RESTRICTIONS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} custom restrictions are allowed",
+            MAX_CUSTOM_RESTRICTIONS
+        )));
+    }
+
+    let mut cleaned: Vec<CustomRestriction> = Vec::with_capacity(restrictions.len());
+    for restriction in restrictions {
+        let name = sanitize_text(&restriction.name);
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Custom restriction name must not be empty".to_string()));
+        }
+        if restriction.keywords.len() > MAX_RESTRICTION_KEYWORDS {
+            return Err(AppError::BadRequest(format!(
+                "Custom restriction {} has more than {} keywords",
+                name, MAX_RESTRICTION_KEYWORDS
+            )));
+        }
+
+        let mut keywords: Vec<String> = Vec::new();
+        for keyword in restriction.keywords.iter().map(|keyword| sanitize_text(keyword)) {
+            if !keyword.is_empty() && !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+        if keywords.is_empty() {
+            keywords.push(name.clone());
+        }
+
+        if cleaned.iter().any(|existing| existing.name.eq_ignore_ascii_case(&name)) {
+            return Err(AppError::BadRequest(format!("Duplicate custom restriction: {}", name)));
+        }
+        cleaned.push(CustomRestriction { name, severity: restriction.severity, keywords });
+    }
+
+    Ok(cleaned)
+}
+
+/// Упоминается ли аллерген в тексте (название продукта или ингредиента)
+pub fn mentions_allergen(text: &str, allergen: &Allergen) -> bool {
+    let text = normalize_ingredient(text);
+    allergen.keywords().iter().any(|keyword| text.contains(keyword))
+}
+
+/// Название и состав продукта — тексты, в которых ищутся аллергены и ограничения
+fn item_texts(item: &FridgeItem) -> impl Iterator<Item = &str> {
+    std::iter::once(item.name.as_str()).chain(item.ingredients.as_deref())
+}
+
+/// Аллергены профиля, которые содержит продукт, вместе с уровнем серьезности
+pub fn item_allergy_hits(profile: &DietaryProfile, item: &FridgeItem) -> Vec<(Allergen, AllergenSeverity)> {
+    profile
+        .allergies
+        .iter()
+        .filter(|entry| {
+            item.contains_allergens.contains(&entry.allergen)
+                || item_texts(item).any(|text| mentions_allergen(text, &entry.allergen))
+        })
+        .map(|entry| (entry.allergen.clone(), entry.severity))
+        .collect()
+}
+
+/// Пользовательские ограничения, которые встречаются в названии или составе продукта
+pub fn custom_restriction_hits<'a>(restrictions: &'a [CustomRestriction], item: &FridgeItem) -> Vec<&'a CustomRestriction> {
+    restrictions
+        .iter()
+        .filter(|restriction| item_texts(item).any(|text| restriction.matches(text)))
+        .collect()
+}
+
+/// Неблокирующие предупреждения при добавлении или изменении продукта
+pub fn custom_restriction_warnings(profile: &DietaryProfile, item: &FridgeItem) -> Vec<DietaryWarning> {
+    custom_restriction_hits(&profile.custom_restrictions, item)
+        .into_iter()
+        .map(|restriction| custom_restriction_warning(item, restriction))
+        .collect()
+}
+
+fn custom_restriction_warning(item: &FridgeItem, restriction: &CustomRestriction) -> DietaryWarning {
+    DietaryWarning {
+        warning_type: DietaryWarningType::CustomRestriction,
+        severity: restriction.severity.warning_severity(),
+        message: format!("{} содержит «{}» — {}", item.name, restriction.name, restriction.severity.label_ru()),
+        affected_restriction: restriction.name.clone(),
+    }
+}
+
+/// Ограничения профиля в формате, который понимает ИИ-сервис
+pub fn to_restriction(profile: &DietaryProfile) -> DietaryRestriction {
+    DietaryRestriction {
+        allergens: profile.allergies.clone(),
+        intolerances: profile.intolerances.clone(),
+        diets: profile.diets.clone(),
+        custom_restrictions: profile.custom_restrictions.clone(),
+    }
+}
+
+pub fn analyze_item(profile: &DietaryProfile, item: &FridgeItem) -> DietaryCompatibility {
+    let mut warnings = Vec::new();
+    let mut recommendations = Vec::new();
+    let mut score: f32 = 1.0;
+    let mut is_safe = true;
+
+    for (allergen, severity) in item_allergy_hits(profile, item) {
+        let message = match severity {
+            AllergenSeverity::LifeThreatening => {
+                score = 0.0;
+                is_safe = false;
+                recommendations.push(format!("Уберите «{}» из холодильника или храните отдельно", item.name));
+                format!("{} содержит {:?} — опасно для жизни, не употреблять", item.name, allergen)
+            },
+            AllergenSeverity::Strict => {
+                score -= 0.5;
+                is_safe = false;
+                format!("{} содержит {:?} — строго избегать", item.name, allergen)
+            },
+            AllergenSeverity::Avoid => {
+                score -= 0.1;
+                format!("{} содержит {:?} — вы предпочитаете этого избегать", item.name, allergen)
+            },
+        };
+
+        warnings.push(DietaryWarning {
+            warning_type: DietaryWarningType::Allergy,
+            severity: severity.warning_severity(),
+            message,
+            affected_restriction: format!("{:?}", allergen),
+        });
+    }
+
+    for restriction in custom_restriction_hits(&profile.custom_restrictions, item) {
+        match restriction.severity {
+            AllergenSeverity::LifeThreatening => {
+                score = 0.0;
+                is_safe = false;
+                recommendations.push(format!("Уберите «{}» из холодильника или храните отдельно", item.name));
+            },
+            AllergenSeverity::Strict => {
+                score -= 0.5;
+                is_safe = false;
+            },
+            AllergenSeverity::Avoid => score -= 0.1,
+        }
+
+        warnings.push(custom_restriction_warning(item, restriction));
+    }
+
+    for intolerance in item.contains_intolerances.iter().filter(|i| profile.intolerances.contains(i)) {
+        score -= 0.3;
+        is_safe = false;
+        warnings.push(DietaryWarning {
+            warning_type: DietaryWarningType::Intolerance,
+            severity: WarningSeverity::High,
+            message: format!("{} содержит {:?}", item.name, intolerance),
+            affected_restriction: format!("{:?}", intolerance),
+        });
+    }
+
+    if !item.suitable_for_diets.is_empty() {
+        for diet in profile.diets.iter().filter(|diet| !item.suitable_for_diets.contains(diet)) {
+            score -= 0.2;
+            warnings.push(DietaryWarning {
+                warning_type: DietaryWarningType::DietViolation,
+                severity: WarningSeverity::Medium,
+                message: format!("{} не подходит для диеты {:?}", item.name, diet),
+                affected_restriction: format!("{:?}", diet),
+            });
+        }
+    }
+
+    DietaryCompatibility {
+        item_id: item.id,
+        item_name: item.name.clone(),
+        is_safe,
+        compatibility_score: score.clamp(0.0, 1.0),
+        warnings,
+        recommendations,
+    }
+}
+
+pub fn build_compliance_report(profile: &DietaryProfile, items: &[FridgeItem], now: DateTime<Utc>) -> FridgeComplianceReport {
+    let item_analyses: Vec<DietaryCompatibility> = items.iter().map(|item| analyze_item(profile, item)).collect();
+
+    let safe_items = item_analyses.iter().filter(|analysis| analysis.is_safe).count();
+    let compliance_percentage = if items.is_empty() {
+        100.0
+    } else {
+        safe_items as f32 / items.len() as f32 * 100.0
+    };
+
+    let critical: Vec<&str> = item_analyses
+        .iter()
+        .filter(|analysis| analysis.warnings.iter().any(|w| matches!(w.severity, WarningSeverity::Critical)))
+        .map(|analysis| analysis.item_name.as_str())
+        .collect();
+
+    let mut overall_recommendations = Vec::new();
+    if !critical.is_empty() {
+        overall_recommendations.push(format!(
+            "Критично: в холодильнике есть продукты с опасными для вас аллергенами: {}",
+            critical.join(", ")
+        ));
+    }
+    if safe_items < items.len() {
+        overall_recommendations.push("Проверьте продукты с предупреждениями перед приготовлением".to_string());
+    }
+
+    let shopping_suggestions = item_analyses
+        .iter()
+        .filter(|analysis| !analysis.is_safe)
+        .map(|analysis| format!("Найдите безопасную замену для «{}»", analysis.item_name))
+        .collect();
+
+    FridgeComplianceReport {
+        user_id: profile.user_id,
+        analysis_date: now,
+        total_items: items.len(),
+        safe_items,
+        problematic_items: items.len() - safe_items,
+        compliance_percentage,
+        item_analyses,
+        overall_recommendations,
+        shopping_suggestions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use chrono::Duration;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::fridge::{AllergyEntry, CreateFridgeItem, FridgeCategory};
+    use crate::services::fridge::new_item;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    fn profile(severity: AllergenSeverity) -> DietaryProfile {
+        DietaryProfile {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            allergies: vec![AllergyEntry { allergen: Allergen::Peanuts, severity }],
+            intolerances: Vec::new(),
+            diets: Vec::new(),
+            custom_restrictions: Vec::new(),
+            severity_notes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn item(name: &str) -> FridgeItem {
+        let now = Utc::now();
+        new_item(
+            CreateFridgeItem {
+                user_id: Uuid::nil(),
+                name: name.to_string(),
+                brand: None,
+                quantity: 1.0,
+                unit: "шт".to_string(),
+                category: FridgeCategory::Snacks,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date: Some(now + Duration::days(30)),
+                purchase_date: now,
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            },
+            now,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn compliance_report_scores_each_severity() {
+        let items = [item("Арахисовая паста"), item("Яблоко")];
+
+        let avoid = build_compliance_report(&profile(AllergenSeverity::Avoid), &items, Utc::now());
+        let strict = build_compliance_report(&profile(AllergenSeverity::Strict), &items, Utc::now());
+        let fatal = build_compliance_report(&profile(AllergenSeverity::LifeThreatening), &items, Utc::now());
+
+        // Avoid только помечает продукт: он остается безопасным
+        assert_eq!((avoid.safe_items, avoid.compliance_percentage), (2, 100.0));
+        assert!(matches!(avoid.item_analyses[0].warnings[0].severity, WarningSeverity::Low));
+        assert!((avoid.item_analyses[0].compatibility_score - 0.9).abs() < 1e-6);
+
+        // Strict — заметное предупреждение, но не критичное
+        assert_eq!((strict.safe_items, strict.compliance_percentage), (1, 50.0));
+        assert!(matches!(strict.item_analyses[0].warnings[0].severity, WarningSeverity::High));
+        assert!(!strict.overall_recommendations.iter().any(|r| r.starts_with("Критично")));
+
+        // LifeThreatening — Critical, нулевая оценка и отдельная рекомендация
+        assert_eq!((fatal.safe_items, fatal.compliance_percentage), (1, 50.0));
+        assert!(matches!(fatal.item_analyses[0].warnings[0].severity, WarningSeverity::Critical));
+        assert_eq!(fatal.item_analyses[0].compatibility_score, 0.0);
+        assert!(fatal.overall_recommendations[0].starts_with("Критично") && fatal.overall_recommendations[0].contains("Арахисовая паста"));
+
+        for report in [&avoid, &strict, &fatal] {
+            assert!(report.item_analyses[1].warnings.is_empty());
+            assert!(report.item_analyses[1].is_safe);
+        }
+    }
+
+    #[test]
+    fn allergy_without_severity_defaults_to_strict() {
+        let entry: AllergyEntry = serde_json::from_value(serde_json::json!({ "allergen": "Peanuts" })).unwrap();
+        assert_eq!(entry.severity, AllergenSeverity::Strict);
+    }
+
+    fn kiwi(severity: AllergenSeverity) -> CustomRestriction {
+        CustomRestriction { name: "Киви".to_string(), severity, keywords: vec!["киви".to_string()] }
+    }
+
+    fn with_ingredients(name: &str, ingredients: &str) -> FridgeItem {
+        FridgeItem { ingredients: Some(ingredients.to_string()), ..item(name) }
+    }
+
+    #[test]
+    fn custom_restriction_matches_ingredients_text() {
+        let juice = with_ingredients("Нектар фруктовый", "Вода, Сок киви-банан, сахар");
+        let mut profile = profile(AllergenSeverity::Avoid);
+        profile.custom_restrictions = vec![kiwi(AllergenSeverity::LifeThreatening)];
+
+        let warnings = custom_restriction_warnings(&profile, &juice);
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].warning_type, DietaryWarningType::CustomRestriction));
+        assert!(matches!(warnings[0].severity, WarningSeverity::Critical));
+        assert_eq!(warnings[0].affected_restriction, "Киви");
+
+        // В отчете о соответствии ограничение работает как аллерген
+        let report = build_compliance_report(&profile, &[juice, item("Яблоко")], Utc::now());
+        assert_eq!(report.safe_items, 1);
+        assert!(!report.item_analyses[0].is_safe);
+        assert!(report.item_analyses[0]
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning.warning_type, DietaryWarningType::CustomRestriction)));
+        assert!(report.item_analyses[1].warnings.is_empty());
+    }
+
+    #[test]
+    fn custom_restriction_matching_ignores_case_and_diacritics() {
+        let restriction = kiwi(AllergenSeverity::Strict);
+        assert!(restriction.matches("Сок киви-банан"));
+        assert!(restriction.matches("КИВИ ГОЛД"));
+        assert!(!restriction.matches("Банан"));
+
+        let cinnamon = CustomRestriction {
+            name: "Корица".to_string(),
+            severity: AllergenSeverity::Strict,
+            keywords: vec!["корица".to_string(), "cinnamon".to_string(), "crème".to_string()],
+        };
+        assert!(cinnamon.matches("Булочка с КОРИЦЕЙ и корица молотая"));
+        assert!(cinnamon.matches("Ground Cinnamon"));
+        assert!(cinnamon.matches("Creme brulee"));
+
+        // Ключевое слово ищется с начала слова
+        let rum = CustomRestriction { name: "ром".to_string(), severity: AllergenSeverity::Avoid, keywords: Vec::new() };
+        assert!(rum.matches("Ромовая баба"));
+        assert!(!rum.matches("Рыба на пару, паром"));
+    }
+
+    #[test]
+    fn custom_restrictions_are_cleaned_and_validated() {
+        let cleaned = clean_custom_restrictions(vec![CustomRestriction {
+            name: "  Киви <b>зеленый</b> ".to_string(),
+            severity: AllergenSeverity::Avoid,
+            keywords: vec![" ".to_string()],
+        }])
+        .unwrap();
+        assert_eq!(cleaned[0].name, "Киви зеленый");
+        assert_eq!(cleaned[0].keywords, vec!["Киви зеленый".to_string()]);
+
+        let empty = clean_custom_restrictions(vec![CustomRestriction { name: " ".to_string(), ..kiwi(AllergenSeverity::Strict) }]);
+        assert!(matches!(empty, Err(AppError::BadRequest(_))));
+
+        let duplicate = clean_custom_restrictions(vec![kiwi(AllergenSeverity::Strict), kiwi(AllergenSeverity::Avoid)]);
+        assert!(matches!(duplicate, Err(AppError::BadRequest(message)) if message == "Duplicate custom restriction: Киви"));
+
+        let entry: CustomRestriction = serde_json::from_value(serde_json::json!({ "name": "Корица" })).unwrap();
+        assert_eq!((entry.severity, entry.keywords.len()), (AllergenSeverity::Strict, 0));
+        assert!(entry.matches("корица молотая"));
+    }
+
+    #[sqlx::test]
+    async fn adding_a_matching_fridge_item_warns_without_blocking(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+
+        let (status, profile) = send(&router, request(
+            Method::PUT,
+            "/api/v1/fridge/dietary-profile",
+            Some(&token),
+            Some(serde_json::json!({
+                "custom_restrictions": [{ "name": "киви", "severity": "LifeThreatening" }]
+            })),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", profile);
+        assert_eq!(profile["custom_restrictions"][0]["keywords"], serde_json::json!(["киви"]));
+
+        let item = |name: &str, ingredients: &str| serde_json::json!({
+            "name": name,
+            "quantity": 1.0,
+            "unit": "шт",
+            "category": "Beverages",
+            "ingredients": ingredients
+        });
+
+        let (status, body) = send(&router, request(
+            Method::POST,
+            "/api/v1/fridge",
+            Some(&token),
+            Some(item("Нектар", "Сок киви-банан")),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["dietary_warnings"][0]["warning_type"], "CustomRestriction");
+        assert_eq!(body["dietary_warnings"][0]["severity"], "Critical");
+
+        let (status, body) = send(&router, request(
+            Method::POST,
+            "/api/v1/fridge",
+            Some(&token),
+            Some(item("Сок яблочный", "Яблоко, вода")),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body.get("dietary_warnings").is_none());
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM fridge_items WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::experiment::{
+        ExperimentDefinition, ExperimentExposure, ExperimentStatus, ExperimentStop, ExperimentSummary, ExperimentVariant,
+    },
+    services::{
+        clock::{self, SharedClock},
+        feature_flags::stable_hash,
+    },
+    utils::errors::AppError,
+};
+
+/// Тон помощника по здоровью: `caring` (контроль) или `clinical`
+pub const HEALTH_TONE_EXPERIMENT: &str = "health_tone";
+
+/// Все эксперименты приложения. Новый эксперимент добавляется сюда; первый вариант — контрольный.
+pub const EXPERIMENTS: &[ExperimentDefinition] = &[
+    ExperimentDefinition {
+        key: HEALTH_TONE_EXPERIMENT,
+        description: "Тон помощника по здоровью: заботливый друг или сдержанный клинический стиль",
+        variants: &[
+            ExperimentVariant { name: "caring", weight: 50 },
+            ExperimentVariant { name: "clinical", weight: 50 },
+        ],
+        starts_at: Some("2026-10-19T00:00:00Z"),
+        ends_at: Some("2026-12-31T00:00:00Z"),
+    },
+];
+
+/// Вариант для эксперимента, которого нет в `EXPERIMENTS`
+const UNKNOWN_EXPERIMENT_VARIANT: &str = "control";
+
+/// Сколько живет кэш остановленных экспериментов
+const STOPS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+pub fn find_definition(key: &str) -> Option<&'static ExperimentDefinition> {
+    EXPERIMENTS.iter().find(|definition| definition.key == key)
+}
+
+pub fn control_variant(definition: &ExperimentDefinition) -> &'static str {
+    definition.variants.first().map_or(UNKNOWN_EXPERIMENT_VARIANT, |variant| variant.name)
+}
+
+/// Вариант пользователя по хешу (эксперимент, пользователь) с учетом весов.
+/// Не меняется между запросами и релизами, пока не меняются варианты и их веса.
+pub fn assign_variant(definition: &ExperimentDefinition, user_id: Uuid) -> &'static str {
+    let total: u64 = definition.variants.iter().map(|variant| variant.weight as u64).sum();
+    if total == 0 {
+        return control_variant(definition);
+    }
+
+    let mut point = stable_hash(definition.key, user_id) % total;
+    for variant in definition.variants {
+        if point < variant.weight as u64 {
+            return variant.name;
+        }
+        point -= variant.weight as u64;
+    }
+
+    control_variant(definition)
+}
+
+/// Состояние эксперимента. Некорректная дата в объявлении — эксперимент не запускается.
+pub fn experiment_status(definition: &ExperimentDefinition, stopped: bool, now: DateTime<Utc>) -> ExperimentStatus {
+    if stopped {
+        return ExperimentStatus::Stopped;
+    }
+
+    let parse = |value: &str| DateTime::parse_from_rfc3339(value).ok().map(|date| date.with_timezone(&Utc));
+    let starts_at = definition.starts_at.map(parse);
+    let ends_at = definition.ends_at.map(parse);
+    if matches!(starts_at, Some(None)) || matches!(ends_at, Some(None)) {
+        return ExperimentStatus::Ended;
+    }
+
+    if starts_at.flatten().is_some_and(|starts_at| now < starts_at) {
+        ExperimentStatus::Scheduled
+    } else if ends_at.flatten().is_some_and(|ends_at| now >= ends_at) {
+        ExperimentStatus::Ended
+    } else {
+        ExperimentStatus::Running
+    }
+}
+
+/// Остановленные эксперименты и момент загрузки
+type CachedStops = (Instant, HashMap<String, ExperimentStop>);
+
+/// Разделяемый handle экспериментов для обработчиков и админки
+#[derive(Clone)]
+pub struct Experiments {
+    pool: DbPool,
+    stops_cache: Arc<RwLock<Option<CachedStops>>>,
+    clock: SharedClock,
+}
+
+impl Experiments {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            stops_cache: Arc::new(RwLock::new(None)),
+            clock: clock::system(),
+        }
+    }
+
+    /// Часы из `AppState` — в песочнице период эксперимента проверяется по ним
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Варианты пользователя во всех экспериментах. Вне периода эксперимента и после
+    /// остановки — контрольный вариант.
+    pub async fn context(&self, user_id: Uuid) -> Result<ExperimentContext, AppError> {
+        let stops = self.stops().await?;
+        let now = self.clock.now();
+
+        let variants = EXPERIMENTS
+            .iter()
+            .map(|definition| {
+                let running = experiment_status(definition, stops.contains_key(definition.key), now) == ExperimentStatus::Running;
+                let variant = if running {
+                    assign_variant(definition, user_id)
+                } else {
+                    control_variant(definition)
+                };
+                (definition.key, AssignedVariant { variant, running })
+            })
+            .collect();
+
+        Ok(ExperimentContext {
+            pool: self.pool.clone(),
+            user_id,
+            variants,
+        })
+    }
+
+    /// Все эксперименты с числом показов по вариантам. Считаются записанные показы,
+    /// а не все пользователи: вариант фиксируется в момент первого показа.
+    pub async fn summaries(&self) -> Result<Vec<ExperimentSummary>, AppError> {
+        let stops = self.stops().await?;
+        let exposures = sqlx::query_as::<_, (String, String, i64)>(
+            "SELECT experiment_key, variant, COUNT(*) FROM experiment_exposures GROUP BY experiment_key, variant"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let now = self.clock.now();
+
+        Ok(EXPERIMENTS
+            .iter()
+            .map(|definition| {
+                let stop = stops.get(definition.key);
+
+                let mut exposure_counts: BTreeMap<String, i64> =
+                    definition.variants.iter().map(|variant| (variant.name.to_string(), 0)).collect();
+                for (_, variant, count) in exposures.iter().filter(|(key, _, _)| key == definition.key) {
+                    exposure_counts.insert(variant.clone(), *count);
+                }
+
+                ExperimentSummary {
+                    definition: *definition,
+                    status: experiment_status(definition, stop.is_some(), now),
+                    stopped_at: stop.map(|stop| stop.stopped_at),
+                    stopped_by: stop.and_then(|stop| stop.stopped_by),
+                    exposure_counts,
+                }
+            })
+            .collect())
+    }
+
+    /// Останавливает эксперимент: все получают контрольный вариант, новые показы не пишутся.
+    /// Повторная остановка возвращает первую.
+    pub async fn stop(&self, key: &str, admin_id: Uuid) -> Result<ExperimentStop, AppError> {
+        find_definition(key)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown experiment: {}", key)))?;
+
+        let stop = sqlx::query_as::<_, ExperimentStop>(
+            r#"
+            INSERT INTO experiment_stops (experiment_key, stopped_by)
+            VALUES ($1, $2)
+            ON CONFLICT (experiment_key) DO UPDATE SET experiment_key = EXCLUDED.experiment_key
+            RETURNING *
+            "#
+        )
+        .bind(key)
+        .bind(admin_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        *self.stops_cache.write().await = None;
+        Ok(stop)
+    }
+
+    /// События показа для выгрузки, в порядке времени
+    pub async fn exposures(&self, key: &str, since: Option<DateTime<Utc>>) -> Result<Vec<ExperimentExposure>, AppError> {
+        find_definition(key)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown experiment: {}", key)))?;
+
+        let exposures = sqlx::query_as::<_, ExperimentExposure>(
+            r#"
+            SELECT * FROM experiment_exposures
+            WHERE experiment_key = $1 AND ($2::timestamptz IS NULL OR exposed_at >= $2)
+            ORDER BY exposed_at, user_id
+            "#
+        )
+        .bind(key)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(exposures)
+    }
+
+    async fn stops(&self) -> Result<HashMap<String, ExperimentStop>, AppError> {
+        if let Some((loaded_at, stops)) = self.stops_cache.read().await.as_ref() {
+            if loaded_at.elapsed() < STOPS_CACHE_TTL {
+                return Ok(stops.clone());
+            }
+        }
+
+        let stops: HashMap<String, ExperimentStop> = sqlx::query_as::<_, ExperimentStop>(
+            "SELECT * FROM experiment_stops"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|stop| (stop.experiment_key.clone(), stop))
+        .collect();
+
+        *self.stops_cache.write().await = Some((Instant::now(), stops.clone()));
+        Ok(stops)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AssignedVariant {
+    variant: &'static str,
+    running: bool,
+}
+
+/// Варианты одного пользователя: `ctx.variant("health_tone") == "clinical"`
+pub struct ExperimentContext {
+    pool: DbPool,
+    user_id: Uuid,
+    variants: HashMap<&'static str, AssignedVariant>,
+}
+
+impl ExperimentContext {
+    /// Вариант пользователя; сам по себе показом не считается (см. `expose`)
+    pub fn variant(&self, key: &str) -> &'static str {
+        self.variants.get(key).map_or(UNKNOWN_EXPERIMENT_VARIANT, |assigned| assigned.variant)
+    }
+
+    /// Отмечает, что пользователь сейчас увидит поверхность эксперимента. Записывается только
+    /// первый показ работающего эксперимента; ошибка записи запрос не прерывает.
+    pub async fn expose(&self, key: &str) {
+        let Some(assigned) = self.variants.get(key).filter(|assigned| assigned.running) else {
+            return;
+        };
+
+        let recorded = sqlx::query(
+            r#"
+            INSERT INTO experiment_exposures (experiment_key, user_id, variant)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (experiment_key, user_id) DO NOTHING
+            "#
+        )
+        .bind(key)
+        .bind(self.user_id)
+        .bind(assigned.variant)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = recorded {
+            warn!("Failed to record exposure to experiment '{}' for user {}: {}", key, self.user_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_user};
+
+    const SKEWED: ExperimentDefinition = ExperimentDefinition {
+        key: "skewed",
+        description: "",
+        variants: &[
+            ExperimentVariant { name: "control", weight: 90 },
+            ExperimentVariant { name: "treatment", weight: 10 },
+        ],
+        starts_at: None,
+        ends_at: None,
+    };
+
+    fn running() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 11, 2, 12, 0, 0).unwrap()
+    }
+
+    fn health_tone() -> &'static ExperimentDefinition {
+        find_definition(HEALTH_TONE_EXPERIMENT).unwrap()
+    }
+
+    /// Доли вариантов у `users` случайных пользователей
+    fn shares(definition: &ExperimentDefinition, users: usize) -> BTreeMap<&'static str, f64> {
+        let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+        for _ in 0..users {
+            *counts.entry(assign_variant(definition, Uuid::new_v4())).or_default() += 1;
+        }
+        counts.into_iter().map(|(variant, count)| (variant, count as f64 / users as f64)).collect()
+    }
+
+    async fn exposures_of(pool: &PgPool, user_id: Uuid) -> Vec<String> {
+        sqlx::query_scalar("SELECT variant FROM experiment_exposures WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn assignment_is_stable_per_user_and_experiment() {
+        let user_id = Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap();
+        let first = assign_variant(health_tone(), user_id);
+
+        for _ in 0..100 {
+            assert_eq!(assign_variant(health_tone(), user_id), first);
+        }
+        // Вариант определяется только хешем (эксперимент, пользователь), а не состоянием процесса
+        assert_eq!(first, if stable_hash(HEALTH_TONE_EXPERIMENT, user_id) % 100 < 50 { "caring" } else { "clinical" });
+    }
+
+    #[test]
+    fn variants_follow_their_weights() {
+        let even = shares(health_tone(), 20_000);
+        assert!((even["caring"] - 0.5).abs() < 0.02, "{:?}", even);
+        assert!((even["clinical"] - 0.5).abs() < 0.02, "{:?}", even);
+
+        let skewed = shares(&SKEWED, 20_000);
+        assert!((skewed["control"] - 0.9).abs() < 0.02, "{:?}", skewed);
+        assert!((skewed["treatment"] - 0.1).abs() < 0.02, "{:?}", skewed);
+
+        // Все веса нулевые — все в контрольном варианте
+        const ZERO: ExperimentDefinition = ExperimentDefinition {
+            variants: &[ExperimentVariant { name: "a", weight: 0 }, ExperimentVariant { name: "b", weight: 0 }],
+            ..SKEWED
+        };
+        assert_eq!(shares(&ZERO, 100).into_keys().collect::<Vec<_>>(), vec!["a"]);
+    }
+
+    #[test]
+    fn status_follows_the_period() {
+        let before = Utc.with_ymd_and_hms(2026, 10, 18, 23, 59, 59).unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2026, 12, 31, 0, 0, 0).unwrap();
+
+        assert_eq!(experiment_status(health_tone(), false, before), ExperimentStatus::Scheduled);
+        assert_eq!(experiment_status(health_tone(), false, start), ExperimentStatus::Running);
+        assert_eq!(experiment_status(health_tone(), false, end), ExperimentStatus::Ended);
+        assert_eq!(experiment_status(health_tone(), true, start), ExperimentStatus::Stopped);
+        let broken = ExperimentDefinition { starts_at: Some("someday"), ..SKEWED };
+        assert_eq!(experiment_status(&broken, false, start), ExperimentStatus::Ended);
+    }
+
+    #[sqlx::test]
+    async fn exposure_is_recorded_once_and_only_while_running(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let assigned = assign_variant(health_tone(), user_id);
+
+        // До начала эксперимента — контрольный вариант и никаких показов
+        let scheduled = Experiments::new(pool.clone()).with_clock(frozen_clock(Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()));
+        let context = scheduled.context(user_id).await.unwrap();
+        assert_eq!(context.variant(HEALTH_TONE_EXPERIMENT), "caring");
+        context.expose(HEALTH_TONE_EXPERIMENT).await;
+        assert!(exposures_of(&pool, user_id).await.is_empty());
+
+        let experiments = Experiments::new(pool.clone()).with_clock(frozen_clock(running()));
+        for _ in 0..3 {
+            let context = experiments.context(user_id).await.unwrap();
+            assert_eq!(context.variant(HEALTH_TONE_EXPERIMENT), assigned);
+            context.expose(HEALTH_TONE_EXPERIMENT).await;
+        }
+        assert_eq!(exposures_of(&pool, user_id).await, vec![assigned.to_string()]);
+        assert_eq!(experiments.exposures(HEALTH_TONE_EXPERIMENT, None).await.unwrap().len(), 1);
+        // Неизвестный эксперимент — контрольный вариант, показ не пишется
+        assert_eq!(context_variant(&experiments, user_id, "no_such_experiment").await, UNKNOWN_EXPERIMENT_VARIANT);
+
+        // После остановки все в контрольном варианте, новые показы не пишутся
+        let admin_id = insert_user(&pool, "Админ").await;
+        let other = insert_user(&pool, "Борис").await;
+        experiments.stop(HEALTH_TONE_EXPERIMENT, admin_id).await.unwrap();
+        let context = experiments.context(other).await.unwrap();
+        assert_eq!(context.variant(HEALTH_TONE_EXPERIMENT), "caring");
+        context.expose(HEALTH_TONE_EXPERIMENT).await;
+        assert!(exposures_of(&pool, other).await.is_empty());
+    }
+
+    async fn context_variant(experiments: &Experiments, user_id: Uuid, key: &str) -> &'static str {
+        experiments.context(user_id).await.unwrap().variant(key)
+    }
+
+    #[sqlx::test]
+    async fn summaries_count_recorded_exposures_only(pool: PgPool) {
+        let experiments = Experiments::new(pool.clone()).with_clock(frozen_clock(running()));
+        let mut expected: BTreeMap<String, i64> = [("caring".to_string(), 0), ("clinical".to_string(), 0)].into();
+        for index in 0..6 {
+            let user_id = insert_user(&pool, &format!("Гость {}", index)).await;
+            let context = experiments.context(user_id).await.unwrap();
+            context.expose(HEALTH_TONE_EXPERIMENT).await;
+            *expected.get_mut(context.variant(HEALTH_TONE_EXPERIMENT)).unwrap() += 1;
+        }
+        // Пользователи без показа в сводку не попадают
+        for index in 0..4 {
+            insert_user(&pool, &format!("Молчун {}", index)).await;
+        }
+
+        let summaries = experiments.summaries().await.unwrap();
+        let summary = summaries.iter().find(|summary| summary.definition.key == HEALTH_TONE_EXPERIMENT).unwrap();
+        assert_eq!(summary.status, ExperimentStatus::Running);
+        assert_eq!(summary.exposure_counts, expected);
+        assert_eq!(summary.exposure_counts.values().sum::<i64>(), 6);
+    }
+}
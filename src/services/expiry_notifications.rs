@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::{ExpiryUrgency, FridgeItem},
+        notification::{NotificationEvent, NotificationPreferences},
+    },
+    services::{
+        clock::{self, SharedClock},
+        fridge::FridgeService,
+        fridge_notes::FridgeNoteService,
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        realtime::{ExpiringItem, RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::errors::AppError,
+};
+
+/// Как часто планировщик проверяет сроки годности
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Уведомления о продуктах, срок которых подходит к концу. Порог задается
+/// по категориям в настройках уведомлений; каждому пользователю — не чаще раза в день.
+pub struct ExpiryNotificationScheduler {
+    pool: DbPool,
+    delivery: NotificationDelivery,
+    // Отметки об отправке живут в памяти процесса: после перезапуска возможен повтор за день
+    notified_on: Mutex<HashMap<Uuid, NaiveDate>>,
+    clock: SharedClock,
+}
+
+impl ExpiryNotificationScheduler {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>) -> Self {
+        Self {
+            delivery: NotificationDelivery::new(pool.clone(), realtime_service),
+            pool,
+            notified_on: Mutex::new(HashMap::new()),
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                async {
+                    match self.run_once(self.clock.now()).await {
+                        Ok(sent) if sent > 0 => info!("Expiry notifications sent: {}", sent),
+                        Ok(_) => {}
+                        Err(e) => warn!("Expiry notification run failed: {:?}", e),
+                    }
+                    let reminders_sent = self.send_use_reminders(self.clock.now()).await;
+                    if reminders_sent > 0 {
+                        info!("Fridge item use reminders sent: {}", reminders_sent);
+                    }
+                }
+                .instrument(telemetry::job_span("expiry_notifications"))
+                .await;
+            }
+        });
+    }
+
+    /// Одна проверка на момент `now`; возвращает число пользователей, получивших уведомление
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let today = now.date_naive();
+        let fridge_service = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone());
+        let mut sent = 0;
+
+        for user_id in fridge_service.user_ids_with_items().await? {
+            if self.notified_on.lock().unwrap().get(&user_id) == Some(&today) {
+                continue;
+            }
+
+            let preferences = self.delivery.preferences(user_id).await?;
+            let items = fridge_service.get_user_items(user_id, None, None, None).await?;
+            let due = expiry_notice_items(&items, &preferences, now);
+            if due.is_empty() {
+                continue;
+            }
+
+            self.notified_on.lock().unwrap().insert(user_id, today);
+
+            let notification = OutgoingNotification {
+                kind: "expiring_items",
+                title: "Скоро истекает срок годности".to_string(),
+                message: expiry_notice_message(&due),
+                data: serde_json::json!({ "item_ids": due.iter().map(|item| item.id).collect::<Vec<_>>() }),
+                event: WebSocketEvent::expiring_items(due),
+            };
+
+            let plan = self
+                .delivery
+                .send_with(&preferences, user_id, NotificationEvent::ExpiringItems, notification, now)
+                .await?;
+            if !plan.is_skip() {
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Напоминания из заметок продуктов («до пятницы»), день которых наступил.
+    /// Для съеденных и выброшенных продуктов напоминание просто снимается.
+    pub async fn send_use_reminders(&self, now: DateTime<Utc>) -> usize {
+        let fridge_service = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone());
+        let mut sent = 0;
+
+        let due_reminders = match FridgeNoteService::new(self.pool.clone()).take_due(now).await {
+            Ok(due) => due,
+            Err(e) => {
+                warn!("Failed to claim use reminders: {:?}", e);
+                return 0;
+            }
+        };
+
+        for due in due_reminders {
+            let item = match fridge_service.get_item_by_id(due.item_id, due.user_id).await {
+                Ok(item) if item.is_active() => item,
+                _ => continue,
+            };
+
+            let recipe_suffix = due
+                .recipe
+                .as_ref()
+                .map(|recipe| format!(" для рецепта «{}»", recipe.name))
+                .unwrap_or_default();
+            let notification = OutgoingNotification {
+                kind: "fridge_item_use_reminder",
+                title: "Пора использовать продукт".to_string(),
+                message: format!(
+                    "Вы собирались использовать «{}» {}{}.",
+                    item.name, due.reminder.phrase, recipe_suffix
+                ),
+                data: serde_json::json!({
+                    "item_id": item.id,
+                    "remind_on": due.reminder.remind_on,
+                    "recipe_id": due.recipe.as_ref().map(|recipe| recipe.id),
+                }),
+                event: WebSocketEvent::FridgeItemUseReminder {
+                    item_id: item.id,
+                    name: item.name.clone(),
+                    use_by: due.reminder.remind_on,
+                    recipe_id: due.recipe.as_ref().map(|recipe| recipe.id),
+                    recipe_name: due.recipe.map(|recipe| recipe.name),
+                },
+            };
+
+            // Напоминание уже снято; сбой доставки одного не мешает остальным
+            match self.delivery.send(due.user_id, NotificationEvent::ExpiringItems, notification, now).await {
+                Ok(plan) if !plan.is_skip() => sent += 1,
+                Ok(_) => {}
+                Err(e) => warn!("Failed to send use reminder for item {}: {:?}", item.id, e),
+            }
+        }
+
+        sent
+    }
+}
+
+/// Продукты, о которых пора предупредить: срок наступает не позже порога категории
+/// или уже прошел (продукт все еще в холодильнике). Категории с выключенными
+/// уведомлениями пропускаются. Самые срочные — первыми.
+pub fn expiry_notice_items(items: &[FridgeItem], preferences: &NotificationPreferences, now: DateTime<Utc>) -> Vec<ExpiringItem> {
+    let mut due: Vec<ExpiringItem> = items
+        .iter()
+        .filter(|item| item.is_active())
+        .filter_map(|item| {
+            let expiry = item.expiry_date?;
+            let lead_days = preferences.expiry_lead_days(&item.category)?;
+
+            let days_left = days_left(expiry, now);
+            (days_left <= i32::from(lead_days)).then(|| ExpiringItem {
+                id: item.id,
+                name: item.name.clone(),
+                days_left,
+                urgency: ExpiryUrgency::for_expiry(expiry, now),
+                expires_at: expiry,
+            })
+        })
+        .collect();
+
+    due.sort_by_key(|item| (item.days_left, item.expires_at));
+    due
+}
+
+/// Полных дней до срока; у просроченного продукта — минус число дней с начала просрочки,
+/// округленное вверх: истек час назад — −1
+pub fn days_left(expiry: DateTime<Utc>, now: DateTime<Utc>) -> i32 {
+    let days = if expiry < now {
+        -((now - expiry).num_days() + 1)
+    } else {
+        (expiry - now).num_days()
+    };
+    days.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+fn expiry_notice_message(due: &[ExpiringItem]) -> String {
+    let names = |expired: bool| {
+        due.iter()
+            .filter(|item| (item.urgency == ExpiryUrgency::Expired) == expired)
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let (expired, expiring) = (names(true), names(false));
+
+    match (expired.is_empty(), expiring.is_empty()) {
+        (true, _) => format!("Стоит использовать в ближайшие дни: {}.", expiring),
+        (false, true) => format!("Срок годности уже истек: {}.", expired),
+        (false, false) => format!("Срок годности уже истек: {}. Стоит использовать в ближайшие дни: {}.", expired, expiring),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveTime, TimeZone};
+    use sqlx::PgPool;
+    use tokio::sync::broadcast::Receiver;
+
+    use super::*;
+    use crate::{
+        models::{fridge::{CreateFridgeItem, FridgeCategory}, notification::QuietHours},
+        services::{clock::SandboxClockMode, fridge::new_item, preferences::PreferencesService, realtime::WebSocketManager},
+        test_support::{frozen_clock, insert_fridge_item, insert_user},
+        utils::format::Locale,
+    };
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    async fn item(pool: &PgPool, user_id: Uuid, name: &str, category: &str, expiry_date: DateTime<Utc>) -> Uuid {
+        let item = insert_fridge_item(pool, user_id, name, None, expiry_date - Duration::days(10)).await;
+        sqlx::query("UPDATE fridge_items SET category = $2::fridge_category, expiry_date = $3 WHERE id = $1")
+            .bind(item.id)
+            .bind(category)
+            .bind(expiry_date)
+            .execute(pool)
+            .await
+            .unwrap();
+        item.id
+    }
+
+    async fn set_preferences(pool: &PgPool, user_id: Uuid, preferences: NotificationPreferences) {
+        PreferencesService::new(pool.clone()).set_notifications(user_id, preferences).await.unwrap();
+    }
+
+    async fn inbox_count(pool: &PgPool, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND kind = 'expiring_items'")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn scheduler(pool: &PgPool, user_id: Uuid, now: DateTime<Utc>) -> (ExpiryNotificationScheduler, NotificationDelivery, Receiver<WebSocketEvent>, Arc<crate::services::clock::SandboxClock>) {
+        let manager = Arc::new(WebSocketManager::new());
+        let registration = manager.add_client(user_id, None, "Анна".to_string(), Locale::Ru).await.unwrap();
+        let realtime = Arc::new(RealtimeService::new(manager));
+        let clock = frozen_clock(now);
+        let scheduler = ExpiryNotificationScheduler::new(pool.clone(), realtime.clone()).with_clock(clock.clone());
+        (scheduler, NotificationDelivery::new(pool.clone(), realtime), registration.receiver, clock)
+    }
+
+    fn notified_names(event: WebSocketEvent) -> Vec<String> {
+        match event {
+            WebSocketEvent::ExpiringItems { items, .. } => items.into_iter().map(|item| item.name).collect(),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[sqlx::test]
+    async fn thresholds_are_applied_per_category(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        let now = at(16, 12, 0);
+        item(&pool, user, "Фарш", "meat", now + Duration::days(2) + Duration::hours(1)).await;
+        item(&pool, user, "Стейк", "meat", now + Duration::days(3) + Duration::hours(1)).await;
+        item(&pool, user, "Рис", "grains", now + Duration::hours(5)).await;
+        item(&pool, user, "Кефир", "dairy", now + Duration::days(3) + Duration::hours(1)).await;
+        item(&pool, user, "Сливки", "dairy", now + Duration::days(4) + Duration::hours(1)).await;
+        item(&pool, user, "Яблоки", "fruits", now + Duration::hours(5)).await;
+        item(&pool, user, "Груши", "fruits", now + Duration::days(1) + Duration::hours(1)).await;
+        item(&pool, user, "Салат", "vegetables", now - Duration::hours(2)).await;
+
+        let mut preferences = NotificationPreferences::default();
+        preferences.expiry_lead_days.insert(FridgeCategory::Meat, Some(2));
+        preferences.expiry_lead_days.insert(FridgeCategory::Grains, None);
+        preferences.expiry_lead_days.insert(FridgeCategory::Fruits, Some(0));
+        // Молочные продукты не указаны — порог по умолчанию, 3 дня
+
+        let items = FridgeService::new(pool.clone()).get_user_items(user, None, None, None).await.unwrap();
+        let due = expiry_notice_items(&items, &preferences, now);
+        let summary: Vec<(&str, i32)> = due.iter().map(|item| (item.name.as_str(), item.days_left)).collect();
+        assert_eq!(summary, vec![("Салат", -1), ("Яблоки", 0), ("Фарш", 2), ("Кефир", 3)]);
+        assert_eq!(due[0].urgency, ExpiryUrgency::Expired);
+
+        preferences.expiry_lead_days.insert(FridgeCategory::Meat, Some(0));
+        let due = expiry_notice_items(&items, &preferences, now);
+        assert!(!due.iter().any(|item| item.name == "Фарш"));
+    }
+
+    #[sqlx::test]
+    async fn quiet_hours_queue_the_push_until_morning(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        // 20:30 UTC — 23:30 по Москве, внутри тихих часов 22:00–08:00
+        let night = at(16, 20, 30);
+        let morning = at(17, 5, 0);
+        item(&pool, user, "Молоко", "dairy", night + Duration::days(1)).await;
+        item(&pool, user, "Гречка", "grains", night + Duration::days(1)).await;
+        let mut preferences = NotificationPreferences {
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                utc_offset_minutes: 180,
+            }),
+            ..Default::default()
+        };
+        preferences.expiry_lead_days.insert(FridgeCategory::Grains, None);
+        set_preferences(&pool, user, preferences).await;
+        let (scheduler, delivery, mut events, clock) = scheduler(&pool, user, night).await;
+
+        assert_eq!(scheduler.run_once(night).await.unwrap(), 1);
+        // Inbox пишется сразу, push ждет утра
+        assert_eq!(inbox_count(&pool, user).await, 1);
+        assert!(events.try_recv().is_err());
+        let deliver_at: DateTime<Utc> = sqlx::query_scalar("SELECT deliver_at FROM queued_notifications WHERE user_id = $1")
+            .bind(user)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(deliver_at, morning);
+
+        // Повторная проверка в ту же ночь ничего не добавляет
+        clock.set_mode(SandboxClockMode::Frozen { at: night + Duration::hours(1) });
+        assert_eq!(scheduler.run_once(night + Duration::hours(1)).await.unwrap(), 0);
+
+        assert_eq!(delivery.deliver_queued(morning - Duration::minutes(1)).await.unwrap(), 0);
+        assert!(events.try_recv().is_err());
+        assert_eq!(delivery.deliver_queued(morning).await.unwrap(), 1);
+        assert_eq!(notified_names(events.try_recv().unwrap()), vec!["Молоко"]);
+        assert_eq!(delivery.deliver_queued(morning + Duration::hours(1)).await.unwrap(), 0);
+        assert_eq!(inbox_count(&pool, user).await, 1);
+    }
+
+    #[sqlx::test]
+    async fn queued_push_is_dropped_if_the_user_mutes_overnight(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        let night = at(16, 23, 0);
+        item(&pool, user, "Молоко", "dairy", night + Duration::days(1)).await;
+        let preferences = NotificationPreferences {
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                utc_offset_minutes: 0,
+            }),
+            ..Default::default()
+        };
+        set_preferences(&pool, user, preferences.clone()).await;
+        let (scheduler, delivery, mut events, _) = scheduler(&pool, user, night).await;
+
+        assert_eq!(scheduler.run_once(night).await.unwrap(), 1);
+        set_preferences(&pool, user, NotificationPreferences { muted: true, ..preferences }).await;
+
+        assert_eq!(delivery.deliver_queued(at(17, 7, 0)).await.unwrap(), 0);
+        assert!(events.try_recv().is_err());
+        let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM queued_notifications").fetch_one(&pool).await.unwrap();
+        assert_eq!(queued, 0);
+    }
+
+    fn fridge_item(name: &str, expiry_date: DateTime<Utc>) -> FridgeItem {
+        new_item(
+            CreateFridgeItem {
+                user_id: Uuid::nil(),
+                name: name.to_string(),
+                brand: None,
+                quantity: 1.0,
+                unit: "шт".to_string(),
+                category: FridgeCategory::Dairy,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date: Some(expiry_date),
+                purchase_date: expiry_date - Duration::days(10),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            },
+            expiry_date - Duration::days(10),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn days_left_is_negative_once_the_item_has_expired() {
+        let now = at(16, 12, 0);
+        let cases = [
+            (now + Duration::days(3) + Duration::hours(1), 3),
+            (now + Duration::days(1), 1),
+            (now + Duration::hours(23), 0),
+            (now, 0),
+            (now - Duration::minutes(1), -1),
+            (now - Duration::hours(23), -1),
+            (now - Duration::days(1), -2),
+            (now - Duration::days(400), -401),
+        ];
+        for (expiry, expected) in cases {
+            assert_eq!(days_left(expiry, now), expected, "{}", expiry);
+        }
+    }
+
+    #[test]
+    fn event_carries_each_item_and_the_most_urgent_headline() {
+        let now = at(16, 12, 0);
+        let items = vec![
+            fridge_item("Сметана", now + Duration::days(2) + Duration::hours(3)),
+            fridge_item("Молоко", now + Duration::hours(6)),
+            fridge_item("Кефир", now - Duration::days(2) - Duration::hours(1)),
+            fridge_item("Творог", now - Duration::hours(3)),
+        ];
+
+        let due = expiry_notice_items(&items, &NotificationPreferences::default(), now);
+        let summary: Vec<(&str, i32, ExpiryUrgency)> = due.iter().map(|item| (item.name.as_str(), item.days_left, item.urgency)).collect();
+        assert_eq!(summary, vec![
+            ("Кефир", -3, ExpiryUrgency::Expired),
+            ("Творог", -1, ExpiryUrgency::Expired),
+            ("Молоко", 0, ExpiryUrgency::Today),
+            ("Сметана", 2, ExpiryUrgency::Soon),
+        ]);
+        assert_eq!(expiry_notice_message(&due), "Срок годности уже истек: Кефир, Творог. Стоит использовать в ближайшие дни: Молоко, Сметана.");
+
+        let event = serde_json::to_value(WebSocketEvent::expiring_items(due)).unwrap();
+        assert_eq!(event["type"], "ExpiringItems");
+        assert_eq!(event["data"]["days_left"], -3);
+        assert_eq!(event["data"]["urgency"], "expired");
+        let items = event["data"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0]["days_left"], -3);
+        assert_eq!(items[0]["expires_at"], "2026-10-14T11:00:00Z");
+        assert_eq!(items[2]["name"], "Молоко");
+        assert_eq!(items[2]["days_left"], 0);
+        assert_eq!(items[2]["urgency"], "today");
+        assert_eq!(items[2]["expires_at"], "2026-10-16T18:00:00Z");
+        assert_eq!(items[3]["urgency"], "soon");
+    }
+
+    #[test]
+    fn headline_without_expired_items_comes_from_the_nearest_one() {
+        let now = at(16, 12, 0);
+        let items = vec![
+            fridge_item("Сметана", now + Duration::days(2) + Duration::hours(3)),
+            fridge_item("Молоко", now + Duration::hours(6)),
+            // Дальше порога по умолчанию — не попадает
+            fridge_item("Сыр", now + Duration::days(10)),
+        ];
+
+        let due = expiry_notice_items(&items, &NotificationPreferences::default(), now);
+        assert_eq!(expiry_notice_message(&due), "Стоит использовать в ближайшие дни: Молоко, Сметана.");
+        match WebSocketEvent::expiring_items(due) {
+            WebSocketEvent::ExpiringItems { items, days_left, urgency } => {
+                assert_eq!((items.len(), days_left, urgency), (2, 0, ExpiryUrgency::Today));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let only_expired = expiry_notice_items(&[fridge_item("Кефир", now - Duration::hours(1))], &NotificationPreferences::default(), now);
+        assert_eq!(expiry_notice_message(&only_expired), "Срок годности уже истек: Кефир.");
+        match WebSocketEvent::expiring_items(Vec::new()) {
+            WebSocketEvent::ExpiringItems { days_left, urgency, .. } => assert_eq!((days_left, urgency), (0, ExpiryUrgency::Later)),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
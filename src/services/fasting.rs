@@ -0,0 +1,396 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        diary::DiaryEntry,
+        fridge::CheckinDay,
+        health::{EatingWindow, FastingState, FastingStatus},
+        notification::NotificationEvent,
+    },
+    services::{
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        preferences::PreferencesService,
+        realtime::WebSocketEvent,
+    },
+    utils::{errors::AppError, format::round_to},
+};
+
+/// Записи дневника калорийностью ниже порога (вода, чай без сахара) голодание не прерывают
+pub const MIN_MEAL_CALORIES: f32 = 5.0;
+/// За сколько дней ищется самый долгий перерыв между приемами пищи
+const LONGEST_FAST_LOOKBACK_DAYS: i64 = 7;
+/// На сколько дней вперед ищется следующее окно: окно может действовать не каждый день
+const WINDOW_LOOKAHEAD_DAYS: i64 = 7;
+
+pub fn is_meal(entry: &DiaryEntry) -> bool {
+    entry.calories_per_100g * entry.portion_size / 100.0 >= MIN_MEAL_CALORIES
+}
+
+fn utc_offset(window: &EatingWindow) -> Duration {
+    Duration::minutes(window.utc_offset_minutes as i64)
+}
+
+fn to_utc(local: NaiveDateTime, offset: Duration) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(local - offset, Utc)
+}
+
+/// Дата в местном времени пользователя
+pub fn local_date(window: &EatingWindow, at: DateTime<Utc>) -> NaiveDate {
+    (at + utc_offset(window)).date_naive()
+}
+
+fn is_active_on(window: &EatingWindow, date: NaiveDate) -> bool {
+    window.weekdays.is_empty() || window.weekdays.contains(&CheckinDay::from_weekday(date.weekday()))
+}
+
+/// Окна, которые открываются в местные дни `from..=to`, как интервалы [открытие, закрытие) в UTC.
+/// Окно через полночь закрывается на следующий день.
+pub fn window_intervals(window: &EatingWindow, from: NaiveDate, to: NaiveDate) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    if window.start == window.end {
+        return Vec::new();
+    }
+
+    let offset = utc_offset(window);
+    let crosses_midnight = window.start > window.end;
+
+    from.iter_days()
+        .take_while(|date| *date <= to)
+        .filter(|date| is_active_on(window, *date))
+        .map(|date| {
+            let end_date = if crosses_midnight { date + Duration::days(1) } else { date };
+            (to_utc(date.and_time(window.start), offset), to_utc(end_date.and_time(window.end), offset))
+        })
+        .collect()
+}
+
+/// Окно, открытое в момент `at` (окно, открывшееся накануне, может быть еще открыто),
+/// иначе ближайшее следующее
+fn current_or_next_window(window: &EatingWindow, at: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let today = local_date(window, at);
+    window_intervals(window, today - Duration::days(1), today + Duration::days(WINDOW_LOOKAHEAD_DAYS))
+        .into_iter()
+        .find(|(_, close)| at < *close)
+}
+
+pub fn is_within_window(window: &EatingWindow, at: DateTime<Utc>) -> bool {
+    current_or_next_window(window, at).is_some_and(|(open, _)| open <= at)
+}
+
+fn hours_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    round_to((to - from).num_minutes() as f64 / 60.0, 1)
+}
+
+/// Состояние голодания на момент `now`. `meals` — время приемов пищи по возрастанию.
+pub fn fasting_status(window: Option<&EatingWindow>, meals: &[DateTime<Utc>], now: DateTime<Utc>) -> FastingStatus {
+    let past_meals: Vec<DateTime<Utc>> = meals.iter().copied().filter(|at| *at <= now).collect();
+    let last_meal_at = past_meals.last().copied();
+
+    // Перерывы, закончившиеся за последние дни, и текущий — от последнего приема пищи до сейчас
+    let lookback_start = now - Duration::days(LONGEST_FAST_LOOKBACK_DAYS);
+    let longest_recent_fast_hours = past_meals
+        .windows(2)
+        .filter(|pair| pair[1] > lookback_start)
+        .map(|pair| hours_between(pair[0], pair[1]))
+        .chain(last_meal_at.map(|at| hours_between(at, now)))
+        .reduce(f64::max);
+
+    let (state, window_opens_at, window_closes_at) = match window.and_then(|window| current_or_next_window(window, now)) {
+        Some((open, close)) if open <= now => (Some(FastingState::Eating), None, Some(close)),
+        Some((open, _)) => (Some(FastingState::Fasting), Some(open), None),
+        None => (window.map(|_| FastingState::Fasting), None, None),
+    };
+
+    FastingStatus {
+        state,
+        eating_window: window.cloned(),
+        last_meal_at,
+        hours_since_last_meal: last_meal_at.map(|at| hours_between(at, now)),
+        window_opens_at,
+        window_closes_at,
+        longest_recent_fast_hours,
+    }
+}
+
+/// Интервальное голодание: окно питания из настроек и приемы пищи из дневника
+pub struct FastingService {
+    pool: DbPool,
+}
+
+impl FastingService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn status(&self, user_id: Uuid, now: DateTime<Utc>) -> Result<FastingStatus, AppError> {
+        let window = PreferencesService::new(self.pool.clone()).for_user(user_id).await?.eating_window;
+
+        // На день больше периода поиска: первому перерыву нужно его начало
+        let meals: Vec<(DateTime<Utc>,)> = sqlx::query_as(
+            r#"
+            SELECT consumed_at FROM diary_entries
+            WHERE user_id = $1
+              AND consumed_at >= $2
+              AND consumed_at <= $3
+              AND calories_per_100g * portion_size / 100 >= $4
+            ORDER BY consumed_at
+            "#
+        )
+        .bind(user_id)
+        .bind(now - Duration::days(LONGEST_FAST_LOOKBACK_DAYS + 1))
+        .bind(now)
+        .bind(MIN_MEAL_CALORIES)
+        .fetch_all(&self.pool)
+        .await?;
+        let meals: Vec<DateTime<Utc>> = meals.into_iter().map(|(at,)| at).collect();
+
+        Ok(fasting_status(window.as_ref(), &meals, now))
+    }
+
+    /// Записан ли прием пищи вне окна. Запись не отклоняется; если пользователь включил
+    /// `notify_outside`, отправляется уведомление — не больше одного за местный день.
+    pub async fn check_entry(
+        &self,
+        delivery: &NotificationDelivery,
+        entry: &DiaryEntry,
+        now: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        if !is_meal(entry) {
+            return Ok(false);
+        }
+
+        let preferences = PreferencesService::new(self.pool.clone());
+        let settings = preferences.for_user(entry.user_id).await?;
+        let Some(window) = settings.eating_window else {
+            return Ok(false);
+        };
+        if is_within_window(&window, entry.consumed_at) {
+            return Ok(false);
+        }
+
+        let today = local_date(&window, now);
+        if !window.notify_outside || window.last_notified_on == Some(today) {
+            return Ok(true);
+        }
+
+        preferences
+            .update(entry.user_id, |settings| {
+                if let Some(window) = settings.eating_window.as_mut() {
+                    window.last_notified_on = Some(today);
+                }
+            })
+            .await?;
+
+        let window_opens_at = current_or_next_window(&window, now)
+            .map(|(open, _)| open)
+            .filter(|open| *open > now);
+        let local_time = (entry.consumed_at + utc_offset(&window)).format("%H:%M");
+        let notification = OutgoingNotification {
+            kind: "meal_outside_eating_window",
+            title: "Прием пищи вне окна питания".to_string(),
+            message: format!(
+                "«{}» записано в {} — вне окна {}–{}.",
+                entry.food_name,
+                local_time,
+                window.start.format("%H:%M"),
+                window.end.format("%H:%M")
+            ),
+            data: serde_json::json!({
+                "entry_id": entry.id,
+                "consumed_at": entry.consumed_at,
+                "window_opens_at": window_opens_at,
+            }),
+            event: WebSocketEvent::MealOutsideEatingWindow {
+                entry_id: entry.id,
+                consumed_at: entry.consumed_at,
+                window_opens_at,
+            },
+        };
+        delivery
+            .send_with(&settings.notifications, entry.user_id, NotificationEvent::EatingWindow, notification, now)
+            .await?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{NaiveTime, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        services::realtime::{RealtimeService, WebSocketManager},
+        test_support::{insert_diary_entry, insert_user},
+    };
+
+    const MOSCOW: i32 = 180;
+
+    fn window(start: u32, end: u32, utc_offset_minutes: i32, weekdays: Vec<CheckinDay>) -> EatingWindow {
+        EatingWindow {
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+            utc_offset_minutes,
+            weekdays,
+            notify_outside: true,
+            last_notified_on: None,
+        }
+    }
+
+    /// Местное время по Москве в октябре 2026 (16.10 — пятница)
+    fn msk(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap() - Duration::minutes(MOSCOW as i64)
+    }
+
+    #[test]
+    fn midnight_spanning_window_belongs_to_the_day_it_opens() {
+        let every_day = window(20, 4, MOSCOW, vec![]);
+        let cases = vec![
+            (msk(16, 19, 59), false),
+            (msk(16, 20, 0), true),
+            (msk(16, 23, 30), true),
+            (msk(17, 0, 0), true),
+            (msk(17, 3, 59), true),
+            (msk(17, 4, 0), false),
+            (msk(17, 12, 0), false),
+        ];
+        for (at, expected) in cases {
+            assert_eq!(is_within_window(&every_day, at), expected, "every day at {}", at);
+        }
+
+        // Окно только по пятницам: ночь на субботу внутри, ночь на пятницу (окно четверга) — нет
+        let fridays = window(20, 4, MOSCOW, vec![CheckinDay::Friday]);
+        let cases = vec![
+            (msk(16, 2, 0), false),
+            (msk(16, 21, 0), true),
+            (msk(17, 2, 0), true),
+            (msk(17, 21, 0), false),
+        ];
+        for (at, expected) in cases {
+            assert_eq!(is_within_window(&fridays, at), expected, "fridays at {}", at);
+        }
+    }
+
+    #[test]
+    fn intervals_are_shifted_by_the_user_offset() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let utc = |day, hour| Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap();
+
+        let new_york = window(12, 20, -300, vec![]);
+        assert_eq!(window_intervals(&new_york, date, date), vec![(utc(16, 17), utc(17, 1))]);
+
+        let late = window(22, 2, MOSCOW, vec![]);
+        assert_eq!(window_intervals(&late, date, date), vec![(utc(16, 19), utc(16, 23))]);
+
+        // Открывающееся и закрывающееся в одно время окно не действует
+        let empty = window(12, 12, MOSCOW, vec![]);
+        assert!(window_intervals(&empty, date, date + Duration::days(7)).is_empty());
+        assert!(!is_within_window(&empty, msk(16, 12, 0)));
+    }
+
+    #[test]
+    fn local_date_rolls_over_at_local_midnight() {
+        let moscow = window(12, 20, MOSCOW, vec![]);
+        assert_eq!(local_date(&moscow, msk(16, 23, 59)), NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+        assert_eq!(local_date(&moscow, msk(17, 0, 1)), NaiveDate::from_ymd_opt(2026, 10, 17).unwrap());
+    }
+
+    #[test]
+    fn status_follows_a_window_across_midnight() {
+        let late = window(20, 4, MOSCOW, vec![]);
+        let meals = vec![msk(15, 21, 0), msk(16, 2, 0), msk(16, 22, 0)];
+
+        // Днем: голодание с 02:00, окно откроется в 20:00; будущий прием пищи не учитывается
+        let status = fasting_status(Some(&late), &meals, msk(16, 16, 0));
+        assert_eq!(status.state, Some(FastingState::Fasting));
+        assert_eq!(status.last_meal_at, Some(msk(16, 2, 0)));
+        assert_eq!(status.hours_since_last_meal, Some(14.0));
+        assert_eq!(status.longest_recent_fast_hours, Some(14.0));
+        assert_eq!(status.window_opens_at, Some(msk(16, 20, 0)));
+        assert_eq!(status.window_closes_at, None);
+
+        // После полуночи окно, открывшееся накануне, еще идет
+        let status = fasting_status(Some(&late), &meals, msk(17, 1, 0));
+        assert_eq!(status.state, Some(FastingState::Eating));
+        assert_eq!(status.window_closes_at, Some(msk(17, 4, 0)));
+        assert_eq!(status.hours_since_last_meal, Some(3.0));
+        assert_eq!(status.longest_recent_fast_hours, Some(20.0));
+
+        let without_window = fasting_status(None, &meals, msk(16, 16, 0));
+        assert_eq!(without_window.state, None);
+        assert_eq!(without_window.hours_since_last_meal, Some(14.0));
+    }
+
+    async fn entry(pool: &PgPool, user_id: Uuid, calories: f32, consumed_at: DateTime<Utc>) -> DiaryEntry {
+        let id = insert_diary_entry(pool, user_id, "snack", calories, 0.0, consumed_at).await;
+        sqlx::query_as("SELECT * FROM diary_entries WHERE id = $1").bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    async fn notified(pool: &PgPool, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND kind = 'meal_outside_eating_window'")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn meals_outside_the_window_are_flagged_and_notified_once_a_local_day(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        PreferencesService::new(pool.clone())
+            .update(user_id, |settings| settings.eating_window = Some(window(12, 20, MOSCOW, vec![])))
+            .await
+            .unwrap();
+        let service = FastingService::new(pool.clone());
+        let delivery = NotificationDelivery::new(pool.clone(), Arc::new(RealtimeService::new(Arc::new(WebSocketManager::new()))));
+
+        // (прием пищи, сейчас, вне окна, уведомлений всего)
+        let cases = vec![
+            ("breakfast before the window", 300.0, msk(16, 9, 0), true, 1),
+            ("second meal the same day", 200.0, msk(16, 10, 0), true, 1),
+            ("water does not count", 0.0, msk(16, 10, 30), false, 1),
+            ("lunch inside the window", 500.0, msk(16, 13, 0), false, 1),
+            ("late snack after the window", 150.0, msk(16, 23, 0), true, 1),
+            ("after local midnight is a new day", 150.0, msk(17, 0, 30), true, 2),
+        ];
+        for (case, calories, at, outside, total) in cases {
+            let meal = entry(&pool, user_id, calories, at).await;
+            assert_eq!(service.check_entry(&delivery, &meal, at).await.unwrap(), outside, "{}", case);
+            assert_eq!(notified(&pool, user_id).await, total, "{}", case);
+        }
+        let window = PreferencesService::new(pool.clone()).for_user(user_id).await.unwrap().eating_window.unwrap();
+        assert_eq!(window.last_notified_on, NaiveDate::from_ymd_opt(2026, 10, 17));
+
+        // Вода после последнего приема пищи голодание не прерывает
+        entry(&pool, user_id, 0.0, msk(17, 6, 0)).await;
+        let status = service.status(user_id, msk(17, 8, 30)).await.unwrap();
+        assert_eq!(status.last_meal_at, Some(msk(17, 0, 30)));
+        assert_eq!(status.hours_since_last_meal, Some(8.0));
+        assert_eq!(status.window_opens_at, Some(msk(17, 12, 0)));
+
+        // Без окна флаг не ставится
+        let other = insert_user(&pool, "Олег").await;
+        let meal = entry(&pool, other, 300.0, msk(16, 3, 0)).await;
+        assert!(!service.check_entry(&delivery, &meal, msk(16, 3, 0)).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn flag_without_notification_when_notify_outside_is_off(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        PreferencesService::new(pool.clone())
+            .update(user_id, |settings| {
+                settings.eating_window = Some(EatingWindow { notify_outside: false, ..window(12, 20, MOSCOW, vec![]) })
+            })
+            .await
+            .unwrap();
+        let delivery = NotificationDelivery::new(pool.clone(), Arc::new(RealtimeService::new(Arc::new(WebSocketManager::new()))));
+
+        let meal = entry(&pool, user_id, 400.0, msk(16, 22, 0)).await;
+        assert!(FastingService::new(pool.clone()).check_entry(&delivery, &meal, msk(16, 22, 0)).await.unwrap());
+        assert_eq!(notified(&pool, user_id).await, 0);
+    }
+}
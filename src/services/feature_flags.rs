@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::feature_flag::{FeatureDefinition, FeatureFlagOverride, FeatureFlagState, FlagSource, ResolvedFlag},
+    utils::errors::AppError,
+};
+
+/// Все флаги приложения. Новый флаг добавляется сюда и сразу доступен через `FeatureFlags`.
+pub const FEATURES: &[FeatureDefinition] = &[
+    FeatureDefinition {
+        key: "fridge_photo_scan",
+        description: "Распознавание продуктов по фото: сканирование чека (POST /fridge/receipt-scan)",
+        default_enabled: false,
+    },
+    FeatureDefinition {
+        key: "semantic_search",
+        description: "Семантический поиск по рецептам и продуктам",
+        default_enabled: false,
+    },
+    FeatureDefinition {
+        key: "weekly_digest",
+        description: "Еженедельные дайджесты питания и холодильника",
+        default_enabled: false,
+    },
+    FeatureDefinition {
+        key: "demo_account",
+        description: "Демо-аккаунт: разрешает заполнение демо-данными в production",
+        default_enabled: false,
+    },
+];
+
+/// Сколько живет кэш глобальных состояний флагов
+const GLOBAL_CACHE_TTL: Duration = Duration::from_secs(30);
+
+type CachedFlags = (Instant, HashMap<String, FeatureFlagState>);
+
+pub fn find_definition(key: &str) -> Option<&'static FeatureDefinition> {
+    FEATURES.iter().find(|definition| definition.key == key)
+}
+
+/// Детерминированный bucket 0..100 для пары (флаг, пользователь)
+pub fn rollout_bucket(key: &str, user_id: Uuid) -> u32 {
+    (stable_hash(key, user_id) % 100) as u32
+}
+
+/// Хеш пары (ключ, пользователь) для раскатки флагов и распределения по экспериментам.
+/// FNV-1a, чтобы результат не зависел от версии компилятора и был стабилен между релизами.
+pub fn stable_hash(key: &str, user_id: Uuid) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.as_bytes().iter().chain(b":").chain(user_id.as_bytes().iter()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Порядок приоритета: персональное переопределение > глобальное состояние > значение из кода
+pub fn resolve(
+    definition: &FeatureDefinition,
+    global: Option<&FeatureFlagState>,
+    user_override: Option<bool>,
+    user_id: Uuid,
+) -> ResolvedFlag {
+    let (enabled, source) = if let Some(enabled) = user_override {
+        (enabled, FlagSource::UserOverride)
+    } else if let Some(state) = global {
+        match state.rollout_percentage {
+            Some(percentage) if state.enabled => (
+                rollout_bucket(definition.key, user_id) < percentage.clamp(0, 100) as u32,
+                FlagSource::Rollout,
+            ),
+            _ => (state.enabled, FlagSource::Global),
+        }
+    } else {
+        (definition.default_enabled, FlagSource::Default)
+    };
+
+    ResolvedFlag {
+        key: definition.key.to_string(),
+        enabled,
+        source,
+    }
+}
+
+/// Разделяемый handle для проверки флагов из любых обработчиков
+#[derive(Clone)]
+pub struct FeatureFlags {
+    pool: DbPool,
+    global_cache: Arc<RwLock<Option<CachedFlags>>>,
+}
+
+impl FeatureFlags {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            global_cache: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub async fn enabled(&self, user_id: Uuid, key: &str) -> Result<bool, AppError> {
+        let definition = find_definition(key)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown feature flag: {}", key)))?;
+
+        let globals = self.global_states().await?;
+        let user_override = self.user_overrides(user_id).await?.get(key).copied();
+
+        Ok(resolve(definition, globals.get(key), user_override, user_id).enabled)
+    }
+
+    /// Для обработчиков за флагом: ошибка `feature_not_enabled`, если флаг выключен
+    pub async fn require(&self, user_id: Uuid, key: &str) -> Result<(), AppError> {
+        if self.enabled(user_id, key).await? {
+            Ok(())
+        } else {
+            Err(AppError::FeatureNotEnabled(key.to_string()))
+        }
+    }
+
+    pub async fn resolve_all(&self, user_id: Uuid) -> Result<Vec<ResolvedFlag>, AppError> {
+        let globals = self.global_states().await?;
+        let overrides = self.user_overrides(user_id).await?;
+
+        Ok(FEATURES
+            .iter()
+            .map(|definition| {
+                resolve(
+                    definition,
+                    globals.get(definition.key),
+                    overrides.get(definition.key).copied(),
+                    user_id,
+                )
+            })
+            .collect())
+    }
+
+    pub async fn list_global_states(&self) -> Result<HashMap<String, FeatureFlagState>, AppError> {
+        self.global_states().await
+    }
+
+    pub async fn set_global(
+        &self,
+        key: &str,
+        enabled: bool,
+        rollout_percentage: Option<i32>,
+        admin_id: Uuid,
+    ) -> Result<FeatureFlagState, AppError> {
+        find_definition(key)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown feature flag: {}", key)))?;
+
+        if let Some(percentage) = rollout_percentage {
+            if !(0..=100).contains(&percentage) {
+                return Err(AppError::BadRequest("rollout_percentage must be between 0 and 100".to_string()));
+            }
+        }
+
+        let state = sqlx::query_as::<_, FeatureFlagState>(
+            r#"
+            INSERT INTO feature_flags (key, enabled, rollout_percentage, updated_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (key) DO UPDATE
+            SET enabled = EXCLUDED.enabled,
+                rollout_percentage = EXCLUDED.rollout_percentage,
+                updated_by = EXCLUDED.updated_by
+            RETURNING *
+            "#
+        )
+        .bind(key)
+        .bind(enabled)
+        .bind(rollout_percentage)
+        .bind(admin_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.invalidate().await;
+        Ok(state)
+    }
+
+    /// Убрать глобальное переопределение — флаг вернется к значению из кода
+    pub async fn reset_global(&self, key: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM feature_flags WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        self.invalidate().await;
+        Ok(())
+    }
+
+    pub async fn set_user_override(&self, key: &str, user_id: Uuid, enabled: bool) -> Result<(), AppError> {
+        find_definition(key)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown feature flag: {}", key)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO feature_flag_overrides (user_id, flag_key, enabled)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, flag_key) DO UPDATE SET enabled = EXCLUDED.enabled
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(enabled)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_user_override(&self, key: &str, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM feature_flag_overrides WHERE user_id = $1 AND flag_key = $2")
+            .bind(user_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn global_states(&self) -> Result<HashMap<String, FeatureFlagState>, AppError> {
+        if let Some((loaded_at, states)) = self.global_cache.read().await.as_ref() {
+            if loaded_at.elapsed() < GLOBAL_CACHE_TTL {
+                return Ok(states.clone());
+            }
+        }
+
+        let states: HashMap<String, FeatureFlagState> = sqlx::query_as::<_, FeatureFlagState>(
+            "SELECT * FROM feature_flags"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|state| (state.key.clone(), state))
+        .collect();
+
+        *self.global_cache.write().await = Some((Instant::now(), states.clone()));
+        Ok(states)
+    }
+
+    async fn user_overrides(&self, user_id: Uuid) -> Result<HashMap<String, bool>, AppError> {
+        let overrides = sqlx::query_as::<_, FeatureFlagOverride>(
+            "SELECT * FROM feature_flag_overrides WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(overrides
+            .into_iter()
+            .map(|user_override| (user_override.flag_key, user_override.enabled))
+            .collect())
+    }
+
+    async fn invalidate(&self) {
+        *self.global_cache.write().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    const FLAG: &str = "fridge_photo_scan";
+
+    fn definition(default_enabled: bool) -> FeatureDefinition {
+        FeatureDefinition { key: FLAG, description: "", default_enabled }
+    }
+
+    fn global(enabled: bool, rollout_percentage: Option<i32>) -> FeatureFlagState {
+        FeatureFlagState {
+            key: FLAG.to_string(),
+            enabled,
+            rollout_percentage,
+            updated_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn user(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn bucket_is_stable_across_calls_and_releases() {
+        let id = Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap();
+
+        assert_eq!(rollout_bucket(FLAG, id), rollout_bucket(FLAG, id));
+        // Значения закреплены: смена хеша перетасовала бы пользователей между группами раскатки
+        assert_eq!(rollout_bucket(FLAG, id), 49);
+        assert_eq!(rollout_bucket("semantic_search", id), 84);
+        assert_eq!(rollout_bucket(FLAG, user(1)), 62);
+    }
+
+    #[test]
+    fn buckets_are_spread_evenly() {
+        let in_rollout = (0..10_000).filter(|n| rollout_bucket(FLAG, user(*n)) < 25).count();
+
+        assert!((2_200..=2_800).contains(&in_rollout), "{} of 10000 users in a 25% rollout", in_rollout);
+    }
+
+    #[test]
+    fn widening_rollout_keeps_already_enabled_users() {
+        let state_20 = global(true, Some(20));
+        let state_50 = global(true, Some(50));
+
+        for n in 0..1_000 {
+            let id = user(n);
+            if resolve(&definition(false), Some(&state_20), None, id).enabled {
+                assert!(resolve(&definition(false), Some(&state_50), None, id).enabled);
+            }
+        }
+    }
+
+    #[test]
+    fn rollout_bounds_enable_nobody_or_everybody() {
+        for n in 0..200 {
+            let id = user(n);
+            assert!(!resolve(&definition(true), Some(&global(true, Some(0))), None, id).enabled);
+            assert!(resolve(&definition(false), Some(&global(true, Some(100))), None, id).enabled);
+        }
+    }
+
+    #[test]
+    fn default_applies_without_global_state_or_override() {
+        let flag = resolve(&definition(true), None, None, user(7));
+
+        assert!(flag.enabled);
+        assert_eq!(flag.source, FlagSource::Default);
+    }
+
+    #[test]
+    fn global_state_beats_default() {
+        let flag = resolve(&definition(true), Some(&global(false, None)), None, user(7));
+        assert!(!flag.enabled);
+        assert_eq!(flag.source, FlagSource::Global);
+
+        // Выключенный флаг с процентом раскатки выключен для всех
+        let flag = resolve(&definition(false), Some(&global(false, Some(100))), None, user(7));
+        assert!(!flag.enabled);
+        assert_eq!(flag.source, FlagSource::Global);
+    }
+
+    #[test]
+    fn user_override_beats_global_state_and_rollout() {
+        let flag = resolve(&definition(false), Some(&global(true, None)), Some(false), user(7));
+        assert!(!flag.enabled);
+        assert_eq!(flag.source, FlagSource::UserOverride);
+
+        let flag = resolve(&definition(false), Some(&global(true, Some(0))), Some(true), user(7));
+        assert!(flag.enabled);
+        assert_eq!(flag.source, FlagSource::UserOverride);
+
+        let flag = resolve(&definition(true), None, Some(false), user(7));
+        assert!(!flag.enabled);
+        assert_eq!(flag.source, FlagSource::UserOverride);
+    }
+}
@@ -0,0 +1,291 @@
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::diary::{FoodMemory, PortionPreset, RememberedPortion},
+    services::clock::{self, SharedClock},
+    utils::{errors::AppError, food_category::PortionKind, ingredient_matcher::normalize_ingredient},
+};
+
+/// Сколько разных порций помнить для одного продукта
+pub const MAX_REMEMBERED_PORTIONS: usize = 3;
+/// Сколько продуктов помнить для одного пользователя
+pub const MAX_REMEMBERED_FOODS: i64 = 500;
+/// Порции, отличающиеся меньше чем на столько, считаются одной
+const SAME_PORTION_TOLERANCE: f32 = 0.01;
+
+const WEIGHT_PRESETS: &[PortionPreset] = &[
+    PortionPreset { portion_size: 50.0, unit: "g" },
+    PortionPreset { portion_size: 100.0, unit: "g" },
+    PortionPreset { portion_size: 150.0, unit: "g" },
+    PortionPreset { portion_size: 200.0, unit: "g" },
+    PortionPreset { portion_size: 300.0, unit: "g" },
+];
+
+const VOLUME_PRESETS: &[PortionPreset] = &[
+    PortionPreset { portion_size: 100.0, unit: "ml" },
+    PortionPreset { portion_size: 200.0, unit: "ml" },
+    PortionPreset { portion_size: 250.0, unit: "ml" },
+    PortionPreset { portion_size: 330.0, unit: "ml" },
+    PortionPreset { portion_size: 500.0, unit: "ml" },
+];
+
+const PIECE_PRESETS: &[PortionPreset] = &[
+    PortionPreset { portion_size: 1.0, unit: "piece" },
+    PortionPreset { portion_size: 2.0, unit: "piece" },
+    PortionPreset { portion_size: 3.0, unit: "piece" },
+];
+
+/// Типовые порции для типа продукта
+pub fn portion_presets(kind: PortionKind) -> &'static [PortionPreset] {
+    match kind {
+        PortionKind::Weight => WEIGHT_PRESETS,
+        PortionKind::Volume => VOLUME_PRESETS,
+        PortionKind::Pieces => PIECE_PRESETS,
+    }
+}
+
+/// Ключ продукта в памяти: "Рис  отварной" и "рис отварной" — один продукт
+pub fn food_key(name: &str) -> String {
+    normalize_ingredient(name)
+}
+
+/// Учитывает новую порцию: такая же порция получает +1 к счетчику, новая добавляется.
+/// Список упорядочен по последнему использованию; лишние (давно не использованные) вытесняются.
+pub fn remember_portion(
+    portions: &mut Vec<RememberedPortion>,
+    portion_size: f32,
+    unit: &str,
+    meal_type: &str,
+    now: DateTime<Utc>,
+) {
+    let unit = unit.trim().to_lowercase();
+    match portions
+        .iter_mut()
+        .find(|portion| portion.unit == unit && (portion.portion_size - portion_size).abs() < SAME_PORTION_TOLERANCE)
+    {
+        Some(portion) => {
+            portion.uses += 1;
+            portion.meal_type = meal_type.to_string();
+            portion.last_used_at = now;
+        }
+        None => portions.push(RememberedPortion {
+            portion_size,
+            unit,
+            meal_type: meal_type.to_string(),
+            uses: 1,
+            last_used_at: now,
+        }),
+    }
+
+    portions.sort_by_key(|portion| Reverse(portion.last_used_at));
+    portions.truncate(MAX_REMEMBERED_PORTIONS);
+}
+
+/// Память о порциях продуктов из дневника
+pub struct FoodMemoryService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl FoodMemoryService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    /// Часы дневника — порядок вытеснения совпадает со временем записей
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn get(&self, user_id: Uuid, name: &str) -> Result<Option<FoodMemory>, AppError> {
+        let memory = sqlx::query_as::<_, FoodMemory>(
+            "SELECT food_name, portions, last_used_at FROM diary_food_memory WHERE user_id = $1 AND food_key = $2"
+        )
+        .bind(user_id)
+        .bind(food_key(name))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(memory)
+    }
+
+    /// Запоминает порцию из новой записи дневника и вытесняет продукты сверх `MAX_REMEMBERED_FOODS`
+    pub async fn record(&self, user_id: Uuid, food_name: &str, portion_size: f32, unit: &str, meal_type: &str) -> Result<(), AppError> {
+        let key = food_key(food_name);
+        if key.is_empty() || !portion_size.is_finite() || portion_size <= 0.0 {
+            return Ok(());
+        }
+        let now = self.clock.now();
+
+        let mut tx = self.pool.begin().await?;
+
+        // Строка блокируется, чтобы две одновременные записи не потеряли счетчик друг друга
+        let mut portions = sqlx::query_as::<_, FoodMemory>(
+            "SELECT food_name, portions, last_used_at FROM diary_food_memory WHERE user_id = $1 AND food_key = $2 FOR UPDATE"
+        )
+        .bind(user_id)
+        .bind(&key)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|memory| memory.portions)
+        .unwrap_or_default();
+
+        remember_portion(&mut portions, portion_size, unit, meal_type, now);
+
+        sqlx::query(
+            r#"
+            INSERT INTO diary_food_memory (user_id, food_key, food_name, portions, last_used_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, food_key) DO UPDATE
+            SET food_name = EXCLUDED.food_name,
+                portions = EXCLUDED.portions,
+                last_used_at = EXCLUDED.last_used_at
+            "#
+        )
+        .bind(user_id)
+        .bind(&key)
+        .bind(food_name.trim())
+        .bind(Json(&portions))
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM diary_food_memory
+            WHERE user_id = $1 AND food_key IN (
+                SELECT food_key FROM diary_food_memory
+                WHERE user_id = $1
+                ORDER BY last_used_at DESC
+                OFFSET $2
+            )
+            "#
+        )
+        .bind(user_id)
+        .bind(MAX_REMEMBERED_FOODS)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_user};
+    use crate::utils::food_category::portion_kind;
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    fn sizes(portions: &[RememberedPortion]) -> Vec<(f32, &str, i32)> {
+        portions.iter().map(|portion| (portion.portion_size, portion.unit.as_str(), portion.uses)).collect()
+    }
+
+    #[test]
+    fn same_portion_is_counted_and_the_oldest_is_rotated_out() {
+        let mut portions = Vec::new();
+        remember_portion(&mut portions, 150.0, "g", "lunch", at(0));
+        remember_portion(&mut portions, 200.0, "g", "dinner", at(1));
+        // В пределах допуска и в другом регистре единиц — та же порция
+        remember_portion(&mut portions, 150.004, " G ", "breakfast", at(2));
+        assert_eq!(sizes(&portions), vec![(150.0, "g", 2), (200.0, "g", 1)]);
+        assert_eq!(portions[0].meal_type, "breakfast");
+        assert_eq!(portions[0].last_used_at, at(2));
+
+        remember_portion(&mut portions, 1.0, "piece", "snack", at(3));
+        remember_portion(&mut portions, 300.0, "g", "dinner", at(4));
+        // Давно не использованная 200 г вытеснена, несмотря на то что порций с тем же числом использований больше
+        assert_eq!(sizes(&portions), vec![(300.0, "g", 1), (1.0, "piece", 1), (150.0, "g", 2)]);
+        assert_eq!(portions.len(), MAX_REMEMBERED_PORTIONS);
+    }
+
+    #[test]
+    fn liquids_get_millilitres_and_solids_grams() {
+        let cases = [
+            ("Молоко 3,2%", PortionKind::Volume, "ml"),
+            ("Кофе с молоком", PortionKind::Volume, "ml"),
+            ("Сок апельсиновый", PortionKind::Volume, "ml"),
+            ("tea with lemon", PortionKind::Volume, "ml"),
+            ("Гречка отварная", PortionKind::Weight, "g"),
+            ("Творог 5%", PortionKind::Weight, "g"),
+            ("Яблоко печеное", PortionKind::Pieces, "piece"),
+            ("Яйца", PortionKind::Pieces, "piece"),
+        ];
+
+        for (name, kind, unit) in cases {
+            assert_eq!(portion_kind(name), kind, "{}", name);
+            assert!(portion_presets(kind).iter().all(|preset| preset.unit == unit), "{}", name);
+        }
+    }
+
+    #[sqlx::test]
+    async fn record_upserts_one_row_per_normalized_name(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+
+        FoodMemoryService::new(pool.clone()).with_clock(frozen_clock(at(0))).record(user_id, "Рис  отварной", 150.0, "g", "lunch").await.unwrap();
+        FoodMemoryService::new(pool.clone()).with_clock(frozen_clock(at(1))).record(user_id, "рис отварной ", 150.0, "g", "dinner").await.unwrap();
+        FoodMemoryService::new(pool.clone()).with_clock(frozen_clock(at(2))).record(user_id, "Рис отварной", 200.0, "g", "dinner").await.unwrap();
+        // Пустое название и неположительная порция не запоминаются
+        let service = FoodMemoryService::new(pool.clone()).with_clock(frozen_clock(at(3)));
+        service.record(user_id, "   ", 100.0, "g", "lunch").await.unwrap();
+        service.record(user_id, "Рис отварной", 0.0, "g", "lunch").await.unwrap();
+        service.record(user_id, "Рис отварной", f32::NAN, "g", "lunch").await.unwrap();
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM diary_food_memory WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        let memory = service.get(user_id, "РИС ОТВАРНОЙ").await.unwrap().unwrap();
+        assert_eq!(memory.food_name, "Рис отварной");
+        assert_eq!(memory.last_used_at, at(2));
+        assert_eq!(sizes(&memory.portions), vec![(200.0, "g", 1), (150.0, "g", 2)]);
+        assert!(service.get(other, "Рис отварной").await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn least_recently_used_food_is_forgotten_over_the_limit(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        sqlx::query(
+            r#"
+            INSERT INTO diary_food_memory (user_id, food_key, food_name, portions, last_used_at)
+            SELECT $1, 'продукт ' || n, 'Продукт ' || n, '[]'::jsonb, $2::timestamptz + n * INTERVAL '1 second'
+            FROM generate_series(1, $3) AS n
+            "#,
+        )
+        .bind(user_id)
+        .bind(at(-60))
+        .bind(MAX_REMEMBERED_FOODS as i32)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let service = FoodMemoryService::new(pool.clone()).with_clock(frozen_clock(at(0)));
+        service.record(user_id, "Гречка", 200.0, "g", "lunch").await.unwrap();
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM diary_food_memory WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, MAX_REMEMBERED_FOODS);
+        assert!(service.get(user_id, "Продукт 1").await.unwrap().is_none());
+        assert!(service.get(user_id, "Продукт 2").await.unwrap().is_some());
+        assert!(service.get(user_id, "Гречка").await.unwrap().is_some());
+    }
+}
@@ -1,254 +1,746 @@
 use uuid::Uuid;
-use chrono::Utc;
-use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
-use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use tracing::instrument;
 use crate::{
-    models::fridge::{FridgeItem, CreateFridgeItem, FridgeCategory, FoodWaste, CreateFoodWaste, ExpenseAnalytics, EconomyInsights, CategoryExpense, WasteByReason, WasteReason},
-    utils::errors::AppError,
+    db::{DbPools, ReadConsistency, UserScope},
+    models::fridge::{FridgeItem, FridgeItemStatus, FridgeHistoryEntry, CreateFridgeItem, UpdateFridgeItem, FridgeCategory, FoodWaste, WasteHistoryEntry, CreateFoodWaste, ExpenseAnalytics, EconomyInsights, CategoryExpense, StoreExpense, AnalyticsOptions, CategoryAsOf, OutlierPurchase, CategoryChange, RecategorizationCandidate, WasteByReason, WasteReason, CheckinAction, CheckinAdjustment, CheckinSummary, FridgeStats, CategoryCount, LocationCount},
+    services::{clock::{self, SharedClock}, fridge_autocomplete, preferences::PreferencesService},
+    utils::{errors::AppError, food_category::suggest_category, format::{percent_of, round_money, round_to, Locale}, i18n},
 };
 
-// Глобальное хранилище для mock данных
-static MOCK_STORAGE: Lazy<Arc<Mutex<HashMap<Uuid, Vec<FridgeItem>>>>> = 
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+/// Продукты, не обновлявшиеся дольше этого срока, показываются в проверке холодильника первыми
+pub const CHECKIN_STALE_DAYS: i64 = 14;
 
-// Глобальное хранилище для отходов
-static WASTE_STORAGE: Lazy<Arc<Mutex<HashMap<Uuid, Vec<FoodWaste>>>>> = 
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+/// Продукты для ленты активности (колонки — см. `services::timeline`)
+pub const TIMELINE_ADDED_SQL: &str = r#"
+    SELECT 'fridge_item_added'::text AS kind, created_at AS occurred_at, id AS event_id, id AS reference_id,
+           name AS title,
+           jsonb_build_object('quantity', quantity, 'unit', unit, 'category', category, 'location', location) AS summary
+    FROM fridge_items
+    WHERE user_id = $1
+"#;
+
+/// Съеденные продукты: время — когда продукт закончился
+pub const TIMELINE_CONSUMED_SQL: &str = r#"
+    SELECT 'fridge_item_consumed'::text AS kind, finished_at AS occurred_at, id AS event_id, id AS reference_id,
+           name AS title,
+           jsonb_build_object('unit', unit, 'category', category, 'consumed_value', consumed_value) AS summary
+    FROM fridge_items
+    WHERE user_id = $1 AND status = 'consumed'
+"#;
+
+/// Выброшенное — из журнала отходов, ссылка на исходный продукт, если он известен
+pub const TIMELINE_WASTED_SQL: &str = r#"
+    SELECT 'fridge_item_wasted'::text AS kind, waste_date AS occurred_at, id AS event_id,
+           COALESCE(original_item_id, id) AS reference_id, name AS title,
+           jsonb_build_object(
+               'quantity', wasted_quantity, 'unit', unit, 'reason', waste_reason, 'wasted_value', wasted_value
+           ) AS summary
+    FROM food_waste
+    WHERE user_id = $1
+"#;
+
+/// Меньше покупок — выбросы в аналитике не ищутся
+const MIN_OUTLIER_SAMPLE: usize = 5;
+
+/// Остатки меньше этого считаются нулевыми (погрешность f32 при частичных списаниях)
+pub const QUANTITY_EPSILON: f64 = 1e-4;
+
+/// Допустимое расхождение между `price_per_unit × quantity` и `total_price`
+const PRICE_MISMATCH_TOLERANCE: f64 = 0.05;
+
+/// Приводит цены к согласованному виду: если известна хотя бы одна из них, вычисляется вторая.
+/// `price_per_unit` — цена за единицу `unit` продукта (за грамм, если продукт в граммах);
+/// сравнимая цена за кг / л / шт считается отдельно — `FridgeItem::normalized_unit_price`.
+/// Если переданы обе цены и они расходятся больше чем на 5%, запрос отклоняется.
+pub fn normalize_prices(
+    quantity: f32,
+    unit: &str,
+    price_per_unit: Option<f32>,
+    total_price: Option<f32>,
+) -> Result<(Option<f32>, Option<f32>), AppError> {
+    if price_per_unit.is_some_and(|price| !price.is_finite() || price < 0.0)
+        || total_price.is_some_and(|price| !price.is_finite() || price < 0.0)
+    {
+        return Err(AppError::BadRequest("Prices must be non-negative numbers".to_string()));
+    }
+
+    let quantity = quantity as f64;
+
+    match (price_per_unit, total_price) {
+        (Some(per_unit), Some(total)) => {
+            let expected = per_unit as f64 * quantity;
+            let largest = expected.max(total as f64);
+            if largest > 0.0 {
+                let discrepancy = (expected - total as f64).abs() / largest;
+                if discrepancy > PRICE_MISMATCH_TOLERANCE {
+                    return Err(AppError::BadRequest(format!(
+                        "total_price {} does not match price_per_unit {} × quantity {} {} = {} ({:.1}% apart, at most {}% allowed)",
+                        total,
+                        per_unit,
+                        quantity,
+                        unit.trim(),
+                        round_money(expected),
+                        discrepancy * 100.0,
+                        PRICE_MISMATCH_TOLERANCE * 100.0,
+                    )));
+                }
+            }
+            Ok((Some(per_unit), Some(total)))
+        }
+        (Some(per_unit), None) => Ok((Some(per_unit), Some(round_money(per_unit as f64 * quantity) as f32))),
+        (None, Some(total)) if quantity > 0.0 => {
+            Ok((Some(round_to(total as f64 / quantity, 4) as f32), Some(total)))
+        }
+        (None, total) => Ok((None, total)),
+    }
+}
+
+/// Группа продуктов без магазина в разбивке по магазинам
+pub const UNSPECIFIED_STORE: &str = "unspecified";
+
+/// Продукты без места хранения в сводке
+pub const UNSPECIFIED_LOCATION: &str = "unspecified";
+
+/// В сводке «скоро истекает» — срок в ближайшие 3 дня
+const STATS_EXPIRING_DAYS: i32 = 3;
+
+/// Магазин для сохранения: без лишних пробелов, пустой — `None`
+pub fn clean_store(store: Option<String>) -> Option<String> {
+    store
+        .map(|store| store.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|store| !store.is_empty())
+}
+
+/// Ключ магазина для группировки и фильтра: "Лента " и "лента" — один магазин
+pub fn store_key(store: &str) -> Option<String> {
+    clean_store(Some(store.to_string())).map(|store| store.to_lowercase().replace('ё', "е"))
+}
 
 pub struct FridgeService {
-    pool: crate::db::DbPool,
+    pools: DbPools,
+    clock: SharedClock,
 }
 
 impl FridgeService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { pool }
+        Self::with_pools(DbPools::single(pool))
+    }
+
+    /// Списки, история и аналитика читаются с реплики; записи и чтения внутри
+    /// операций изменения — в основной базе
+    pub fn with_pools(pools: DbPools) -> Self {
+        Self { pools, clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — в песочнице сроки годности и периоды аналитики считаются по ним
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub async fn add_item(&self, item_data: CreateFridgeItem) -> Result<FridgeItem, AppError> {
-        let item_id = Uuid::new_v4();
-        let now = Utc::now();
-
-        let item = FridgeItem {
-            id: item_id,
-            user_id: item_data.user_id,
-            name: item_data.name,
-            brand: item_data.brand,
-            quantity: item_data.quantity,
-            unit: item_data.unit,
-            category: item_data.category,
-            price_per_unit: item_data.price_per_unit,
-            total_price: item_data.total_price,
-            expiry_date: item_data.expiry_date,
-            purchase_date: item_data.purchase_date,
-            notes: item_data.notes,
-            location: item_data.location,
-            // Новые поля для диетических ограничений
-            contains_allergens: item_data.contains_allergens,
-            contains_intolerances: item_data.contains_intolerances,
-            suitable_for_diets: item_data.suitable_for_diets,
-            ingredients: item_data.ingredients,
-            nutritional_info: item_data.nutritional_info,
-            created_at: now,
-            updated_at: now,
-        };
+        let item = new_item(item_data, self.clock.now())?;
 
-        // Сохраняем в mock хранилище
-        let mut storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.entry(item_data.user_id).or_insert_with(Vec::new);
-        user_items.push(item.clone());
+        insert_item(self.pools.primary(), &item).await?;
+        fridge_autocomplete::invalidate(item.user_id);
 
         Ok(item)
     }
 
-    pub async fn get_user_items(&self, user_id: Uuid, category: Option<FridgeCategory>, location: Option<String>, search: Option<String>) -> Result<Vec<FridgeItem>, AppError> {
-        let storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.get(&user_id).cloned().unwrap_or_default();
+    /// Добавляет несколько продуктов разом: сначала проверяются все, затем сохраняются
+    /// одной транзакцией, так что при ошибке не добавляется ни один
+    pub async fn add_items(&self, user_id: Uuid, items: Vec<CreateFridgeItem>) -> Result<Vec<FridgeItem>, AppError> {
+        let now = self.clock.now();
+        let items = items.into_iter().map(|item| new_item(item, now)).collect::<Result<Vec<_>, _>>()?;
+        if items.iter().any(|item| item.user_id != user_id) {
+            return Err(AppError::BadRequest("All items must belong to the same user".to_string()));
+        }
 
-        // Фильтруем по категории
-        let filtered_items: Vec<FridgeItem> = user_items
-            .into_iter()
-            .filter(|item| {
-                // Фильтр по категории
-                if let Some(ref cat) = category {
-                    if &item.category != cat {
-                        return false;
-                    }
-                }
+        let mut tx = self.pools.primary().begin().await?;
+        for item in &items {
+            insert_item(&mut *tx, item).await?;
+        }
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
 
-                // Фильтр по локации
-                if let Some(ref loc) = location {
-                    if item.location.as_ref() != Some(loc) {
-                        return false;
-                    }
-                }
+        Ok(items)
+    }
 
-                // Фильтр по поиску
-                if let Some(ref search_term) = search {
-                    let search_lower = search_term.to_lowercase();
-                    let name_matches = item.name.to_lowercase().contains(&search_lower);
-                    let brand_matches = item.brand.as_ref()
-                        .map(|b| b.to_lowercase().contains(&search_lower))
-                        .unwrap_or(false);
-                    
-                    if !name_matches && !brand_matches {
-                        return false;
-                    }
-                }
+    /// Продукты, которые сейчас лежат в холодильнике
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_user_items(&self, user_id: Uuid, category: Option<FridgeCategory>, location: Option<String>, search: Option<String>) -> Result<Vec<FridgeItem>, AppError> {
+        self.get_items_by_status(user_id, FridgeItemStatus::Active, category, location, search).await
+    }
 
-                true
-            })
-            .collect();
+    /// Продукты со статусом `status`; поиск — по подстроке названия или бренда без учета регистра
+    pub async fn get_items_by_status(
+        &self,
+        user_id: Uuid,
+        status: FridgeItemStatus,
+        category: Option<FridgeCategory>,
+        location: Option<String>,
+        search: Option<String>,
+    ) -> Result<Vec<FridgeItem>, AppError> {
+        let pattern = search
+            .as_deref()
+            .filter(|search| !search.is_empty())
+            .map(|search| format!("%{}%", search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
 
-        Ok(filtered_items)
+        let items = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM fridge_items
+                WHERE user_id = $1 AND status = $2
+                  AND ($3::fridge_category IS NULL OR category = $3)
+                  AND ($4::text IS NULL OR location = $4)
+                  AND ($5::text IS NULL OR name ILIKE $5 OR brand ILIKE $5)
+                ORDER BY created_at, id
+                "#
+            )
+            .bind(status)
+            .bind(category)
+            .bind(location)
+            .bind(pattern)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(items)
     }
 
     pub async fn get_item_by_id(&self, id: Uuid, user_id: Uuid) -> Result<FridgeItem, AppError> {
-        let storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.get(&user_id).cloned().unwrap_or_default();
-
-        user_items
-            .into_iter()
-            .find(|item| item.id == id)
+        UserScope::new(user_id)
+            .query_as("SELECT * FROM fridge_items WHERE user_id = $1 AND id = $2")
+            .bind(id)
+            .fetch_optional(self.pools.primary())
+            .await?
             .ok_or_else(|| AppError::NotFound("Item not found".to_string()))
     }
 
+    #[instrument(skip_all, fields(user_id = %user_id, item_id = %id))]
     pub async fn update_item(&self, id: Uuid, user_id: Uuid, payload: crate::api::fridge::CreateFridgeItemRequest) -> Result<FridgeItem, AppError> {
-        let mut storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.entry(user_id).or_insert_with(Vec::new);
+        let (price_per_unit, total_price) = normalize_prices(
+            payload.quantity,
+            &payload.unit,
+            payload.price_per_unit,
+            payload.total_price,
+        )?;
 
-        let item_index = user_items
-            .iter()
-            .position(|item| item.id == id)
-            .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
-
-        let now = Utc::now();
-        let old_item = &user_items[item_index];
+        let mut tx = self.pools.primary().begin().await?;
+        let old_item = lock_item(&mut tx, user_id, id).await?;
+        if !old_item.is_active() {
+            return Err(AppError::BadRequest("Finished items cannot be edited".to_string()));
+        }
 
         let updated_item = FridgeItem {
-            id: old_item.id,
-            user_id: old_item.user_id,
             name: payload.name,
             brand: payload.brand,
             quantity: payload.quantity,
             unit: payload.unit,
             category: payload.category,
-            price_per_unit: payload.price_per_unit,
-            total_price: payload.total_price,
+            price_per_unit,
+            total_price,
             expiry_date: payload.expiry_date,
-            purchase_date: old_item.purchase_date, // Оставляем оригинальную дату покупки
             notes: payload.notes,
             location: payload.location,
+            store: clean_store(payload.store),
             // Новые поля для диетических ограничений
             contains_allergens: payload.contains_allergens.unwrap_or_default(),
             contains_intolerances: payload.contains_intolerances.unwrap_or_default(),
             suitable_for_diets: payload.suitable_for_diets.unwrap_or_default(),
             ingredients: payload.ingredients,
             nutritional_info: payload.nutritional_info,
-            created_at: old_item.created_at,
-            updated_at: now,
+            updated_at: self.clock.now(),
+            // Дата покупки, статус и списанная стоимость остаются прежними
+            ..old_item
         };
 
-        user_items[item_index] = updated_item.clone();
+        save_item(&mut tx, &updated_item).await?;
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
 
         Ok(updated_item)
     }
 
+    /// Частичное изменение: меняются только переданные поля. `check` получает продукт
+    /// с примененными изменениями до сохранения — так проверяются пределы по итоговым значениям.
+    pub async fn patch_item<F>(&self, id: Uuid, user_id: Uuid, patch: UpdateFridgeItem, check: F) -> Result<FridgeItem, AppError>
+    where
+        F: FnOnce(&FridgeItem) -> Result<(), AppError>,
+    {
+        let mut tx = self.pools.primary().begin().await?;
+        let old_item = lock_item(&mut tx, user_id, id).await?;
+        if !old_item.is_active() {
+            return Err(AppError::BadRequest("Finished items cannot be edited".to_string()));
+        }
+
+        let quantity = patch.quantity.unwrap_or(old_item.quantity);
+        let unit = patch.unit.unwrap_or_else(|| old_item.unit.clone());
+        let (price_per_unit, total_price) = if patch.price_per_unit.is_some() || patch.total_price.is_some() {
+            normalize_prices(quantity, &unit, patch.price_per_unit, patch.total_price)?
+        } else if patch.quantity.is_some() && old_item.price_per_unit.is_some() {
+            // Цена за единицу прежняя, итог пересчитывается под новое количество
+            normalize_prices(quantity, &unit, old_item.price_per_unit, None)?
+        } else {
+            (old_item.price_per_unit, old_item.total_price)
+        };
+
+        let updated_item = FridgeItem {
+            name: patch.name.flatten().unwrap_or_else(|| old_item.name.clone()),
+            brand: match patch.brand {
+                Some(brand) => Some(brand).filter(|brand| !brand.is_empty()),
+                None => old_item.brand.clone(),
+            },
+            quantity,
+            unit,
+            category: patch.category.unwrap_or_else(|| old_item.category.clone()),
+            price_per_unit,
+            total_price,
+            expiry_date: patch.expiry_date.unwrap_or(old_item.expiry_date),
+            notes: patch.notes.unwrap_or_else(|| old_item.notes.clone()),
+            location: patch.location.unwrap_or_else(|| old_item.location.clone()),
+            updated_at: self.clock.now(),
+            ..old_item
+        };
+        check(&updated_item)?;
+
+        save_item(&mut tx, &updated_item).await?;
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
+
+        Ok(updated_item)
+    }
+
+    /// Удаление без причины: продукт остается в истории со статусом Removed
     pub async fn remove_item(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
-        let mut storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.entry(user_id).or_insert_with(Vec::new);
+        let now = self.clock.now();
+        let mut tx = self.pools.primary().begin().await?;
+        let mut item = lock_active_item(&mut tx, user_id, id).await?;
 
-        let item_index = user_items
-            .iter()
-            .position(|item| item.id == id)
-            .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
+        item.status = FridgeItemStatus::Removed;
+        item.finished_at = Some(now);
+        item.updated_at = now;
 
-        user_items.remove(item_index);
+        save_item(&mut tx, &item).await?;
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
 
         Ok(())
     }
 
-    pub async fn get_expiring_items(&self, user_id: Uuid, days_ahead: Option<u32>) -> Result<Vec<FridgeItem>, AppError> {
+    /// Сводка по холодильнику: сколько продуктов, сколько просрочено и скоро истекает,
+    /// разбивка по категориям и местам хранения
+    pub async fn get_stats(&self, user_id: Uuid) -> Result<FridgeStats, AppError> {
+        let items = self.get_user_items(user_id, None, None, None).await?;
+        Ok(compute_stats(&items, self.clock.now()))
+    }
+
+    /// Продукты, требующие внимания: срок истекает в ближайшие `days_ahead` дней
+    /// (включительно) и, если `include_expired`, уже просроченные. Самые срочные — первыми.
+    /// Этим же методом пользуются API, ИИ-анализ холодильника и главный экран.
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_expiring_items(&self, user_id: Uuid, days_ahead: Option<u32>, include_expired: bool) -> Result<Vec<FridgeItem>, AppError> {
         let days = days_ahead.unwrap_or(7);
-        let now = Utc::now();
+        let now = self.clock.now();
         let future_date = now + chrono::Duration::days(days as i64);
 
-        let storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.get(&user_id).cloned().unwrap_or_default();
+        let items = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM fridge_items
+                WHERE user_id = $1 AND status = 'active'
+                  AND expiry_date <= $2 AND ($3 OR expiry_date >= $4)
+                ORDER BY expiry_date, id
+                "#
+            )
+            .bind(future_date)
+            .bind(include_expired)
+            .bind(now)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
 
-        let expiring_items: Vec<FridgeItem> = user_items
-            .into_iter()
-            .filter(|item| {
-                if let Some(expiry_date) = item.expiry_date {
-                    expiry_date >= now && expiry_date <= future_date
-                } else {
-                    false
-                }
-            })
-            .collect();
+        Ok(items)
+    }
+
+    /// Все продукты пользователя с известной ценой, включая съеденные и выброшенные —
+    /// история цен для оценки стоимости рецептов
+    pub async fn get_price_history(&self, user_id: Uuid) -> Result<Vec<FridgeItem>, AppError> {
+        let items = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM fridge_items
+                WHERE user_id = $1 AND (price_per_unit IS NOT NULL OR total_price IS NOT NULL)
+                ORDER BY created_at, id
+                "#
+            )
+            .fetch_all(self.pools.primary())
+            .await?;
 
-        Ok(expiring_items)
+        Ok(items)
     }
 
-    pub async fn check_and_notify_expiring_items(&self, user_id: Uuid) -> Result<Vec<FridgeItem>, AppError> {
-        self.get_expiring_items(user_id, Some(3)).await // Продукты, истекающие в ближайшие 3 дня
+    /// Пользователи, у которых есть продукты в холодильнике (для фоновых уведомлений)
+    pub async fn user_ids_with_items(&self) -> Result<Vec<Uuid>, AppError> {
+        let user_ids = sqlx::query_scalar("SELECT DISTINCT user_id FROM fridge_items WHERE status = 'active'")
+            .fetch_all(self.pools.primary())
+            .await?;
+
+        Ok(user_ids)
+    }
+
+    /// Все продукты пользователя, включая историю
+    pub async fn all_user_items(&self, user_id: Uuid) -> Result<Vec<FridgeItem>, AppError> {
+        let items = UserScope::new(user_id)
+            .query_as("SELECT * FROM fridge_items WHERE user_id = $1 ORDER BY created_at, id")
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        Ok(items)
     }
 
     // Новые методы для работы с отходами и аналитикой
-    pub async fn add_waste(&self, waste_data: CreateFoodWaste) -> Result<FoodWaste, AppError> {
-        let waste_id = Uuid::new_v4();
-        let now = Utc::now();
-
-        let waste = FoodWaste {
-            id: waste_id,
-            user_id: waste_data.user_id,
-            original_item_id: waste_data.original_item_id,
-            name: waste_data.name,
-            brand: waste_data.brand,
-            wasted_quantity: waste_data.wasted_quantity,
-            unit: waste_data.unit,
-            category: waste_data.category,
-            waste_reason: waste_data.waste_reason,
-            wasted_value: waste_data.wasted_value,
-            waste_date: now,
-            notes: waste_data.notes,
-            created_at: now,
-        };
 
-        // Сохраняем в mock хранилище отходов
-        let mut storage = WASTE_STORAGE.lock().unwrap();
-        let user_waste = storage.entry(waste_data.user_id).or_insert_with(Vec::new);
-        user_waste.push(waste.clone());
+    pub async fn get_waste_history(&self, user_id: Uuid, start_date: Option<chrono::DateTime<Utc>>, end_date: Option<chrono::DateTime<Utc>>) -> Result<Vec<FoodWaste>, AppError> {
+        let wastes = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM food_waste
+                WHERE user_id = $1
+                  AND ($2::timestamptz IS NULL OR waste_date >= $2)
+                  AND ($3::timestamptz IS NULL OR waste_date <= $3)
+                ORDER BY waste_date, id
+                "#
+            )
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
 
-        Ok(waste)
+        Ok(wastes)
     }
 
-    pub async fn get_waste_history(&self, user_id: Uuid, start_date: Option<chrono::DateTime<Utc>>, end_date: Option<chrono::DateTime<Utc>>) -> Result<Vec<FoodWaste>, AppError> {
-        let storage = WASTE_STORAGE.lock().unwrap();
-        let user_waste = storage.get(&user_id).cloned().unwrap_or_default();
+    /// История отходов с пометкой, доступен ли еще продукт, с которого они списаны.
+    /// После окончательного удаления продукта `original_item_id` обнуляется,
+    /// поэтому непустая ссылка всегда ведет на существующий продукт.
+    pub async fn get_waste_entries(&self, user_id: Uuid, start_date: Option<chrono::DateTime<Utc>>, end_date: Option<chrono::DateTime<Utc>>) -> Result<Vec<WasteHistoryEntry>, AppError> {
+        let wastes = self.get_waste_history(user_id, start_date, end_date).await?;
+
+        Ok(wastes
+            .into_iter()
+            .map(|waste| WasteHistoryEntry {
+                source_item_exists: waste.original_item_id.is_some(),
+                waste,
+            })
+            .collect())
+    }
+
+    /// Окончательное удаление продукта из истории. Удалить можно только закончившийся продукт —
+    /// активный сначала убирается через `remove_item`. Что происходит со ссылками на продукт:
+    /// - записи об отходах сохраняются: название и категория в них уже есть, недостающий бренд
+    ///   копируется из продукта, `original_item_id` обнуляется, и дальше запись учитывается
+    ///   в аналитике как отходы без привязки;
+    /// - резервы под план питания удаляются вместе с продуктом (ON DELETE CASCADE);
+    /// - id в уведомлениях — снимок на момент отправки, продукт по ним больше не находится.
+    pub async fn purge_item(&self, id: Uuid, user_id: Uuid) -> Result<FridgeItem, AppError> {
+        let mut tx = self.pools.primary().begin().await?;
+        let item = lock_item(&mut tx, user_id, id).await?;
+        if item.is_active() {
+            return Err(AppError::BadRequest("Only finished items can be deleted permanently; remove the item first".to_string()));
+        }
+
+        // Отвязываем записи об отходах, сохранив в них описание продукта
+        UserScope::new(user_id)
+            .query(
+                r#"
+                UPDATE food_waste
+                SET name = CASE WHEN btrim(name) = '' THEN $3 ELSE name END,
+                    brand = COALESCE(brand, $4),
+                    original_item_id = NULL
+                WHERE user_id = $1 AND original_item_id = $2
+                "#
+            )
+            .bind(item.id)
+            .bind(&item.name)
+            .bind(&item.brand)
+            .execute(&mut *tx)
+            .await?;
+        // Журнал смен категорий удаляется вместе с продуктом (ON DELETE CASCADE)
+        UserScope::new(user_id)
+            .query("DELETE FROM fridge_items WHERE user_id = $1 AND id = $2")
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
+
+        Ok(item)
+    }
+
+    /// Окончательно удаляет продукты, которые пользователи убрали (`removed`) раньше `finished_before`,
+    /// по одному через `purge_item`, чтобы ссылки на них обрабатывались так же. С `dry_run` только считает.
+    pub async fn purge_removed(&self, finished_before: DateTime<Utc>, dry_run: bool) -> Result<u64, AppError> {
+        let items: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            "SELECT user_id, id FROM fridge_items WHERE status = 'removed' AND finished_at < $1 ORDER BY finished_at"
+        )
+        .bind(finished_before)
+        .fetch_all(self.pools.primary())
+        .await?;
 
-        let filtered_waste: Vec<FoodWaste> = user_waste
+        if !dry_run {
+            for (user_id, id) in &items {
+                self.purge_item(*id, *user_id).await?;
+            }
+        }
+
+        Ok(items.len() as u64)
+    }
+
+    /// Продукты, которых больше нет в холодильнике, — что с ними случилось и когда.
+    /// Последние по времени — первыми.
+    pub async fn get_history(&self, user_id: Uuid, status: Option<FridgeItemStatus>) -> Result<Vec<FridgeHistoryEntry>, AppError> {
+        if status == Some(FridgeItemStatus::Active) {
+            return Err(AppError::BadRequest("History contains only finished items".to_string()));
+        }
+
+        let items: Vec<FridgeItem> = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM fridge_items
+                WHERE user_id = $1 AND status <> 'active' AND ($2::fridge_item_status IS NULL OR status = $2)
+                ORDER BY finished_at DESC NULLS LAST, id
+                "#
+            )
+            .bind(status)
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
+
+        let user_waste = self.get_waste_history(user_id, None, None).await?;
+
+        Ok(items
             .into_iter()
-            .filter(|waste| {
-                if let Some(start) = start_date {
-                    if waste.waste_date < start {
-                        return false;
+            .map(|item| {
+                let mut waste_reasons: Vec<WasteReason> = Vec::new();
+                for waste in user_waste.iter().filter(|waste| waste.original_item_id == Some(item.id)) {
+                    if !waste_reasons.contains(&waste.waste_reason) {
+                        waste_reasons.push(waste.waste_reason.clone());
                     }
                 }
-                if let Some(end) = end_date {
-                    if waste.waste_date > end {
-                        return false;
+
+                FridgeHistoryEntry {
+                    item_id: item.id,
+                    purchased_value: round_money(item.purchased_value() as f64),
+                    consumed_value: round_money(item.consumed_value as f64),
+                    wasted_value: round_money(item.wasted_value as f64),
+                    name: item.name,
+                    brand: item.brand,
+                    category: item.category,
+                    quantity: item.quantity,
+                    unit: item.unit,
+                    status: item.status,
+                    purchase_date: item.purchase_date,
+                    finished_at: item.finished_at,
+                    waste_reasons,
+                }
+            })
+            .collect())
+    }
+
+    /// Продукты для проверки холодильника: сначала давно не обновлявшиеся, самые старые первыми
+    pub async fn get_checkin_items(&self, user_id: Uuid) -> Result<Vec<FridgeItem>, AppError> {
+        let mut items = self.get_user_items(user_id, None, None, None).await?;
+
+        let stale_before = self.clock.now() - chrono::Duration::days(CHECKIN_STALE_DAYS);
+        items.sort_by_key(|item| (item.updated_at >= stale_before, item.updated_at));
+
+        Ok(items)
+    }
+
+    /// Применяет все корректировки проверки холодильника или ни одной:
+    /// продукты блокируются, и весь пакет записывается одной транзакцией.
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn apply_checkin(&self, user_id: Uuid, adjustments: Vec<CheckinAdjustment>) -> Result<CheckinSummary, AppError> {
+        if adjustments.is_empty() {
+            return Err(AppError::BadRequest("At least one adjustment is required".to_string()));
+        }
+
+        let now = self.clock.now();
+        let mut summary = CheckinSummary {
+            confirmed: 0,
+            adjusted: 0,
+            finished: 0,
+            wasted: 0,
+            removed: 0,
+            total_value_written_off: 0.0,
+            completed_at: now,
+        };
+
+        let mut tx = self.pools.primary().begin().await?;
+        let mut seen: HashSet<Uuid> = HashSet::new();
+        let mut written_off = 0.0_f64;
+
+        for adjustment in &adjustments {
+            if !seen.insert(adjustment.item_id) {
+                return Err(AppError::BadRequest(format!("Duplicate adjustment for item {}", adjustment.item_id)));
+            }
+
+            let mut item: FridgeItem = UserScope::new(user_id)
+                .query_as("SELECT * FROM fridge_items WHERE user_id = $1 AND id = $2 AND status = 'active' FOR UPDATE")
+                .bind(adjustment.item_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Item {} not found", adjustment.item_id)))?;
+
+            match &adjustment.action {
+                CheckinAction::Confirm => {
+                    item.updated_at = now;
+                    summary.confirmed += 1;
+                },
+                CheckinAction::NewQuantity { quantity } => {
+                    if !quantity.is_finite() || *quantity < 0.0 {
+                        return Err(AppError::BadRequest(format!("Invalid quantity for item {}", item.id)));
                     }
+                    if *quantity < item.quantity {
+                        // Недостающее количество считается съеденным
+                        item.write_off(Some(item.quantity - *quantity), FridgeItemStatus::Consumed, now);
+                    } else {
+                        // Стоимость остатка пересчитывается по цене за единицу
+                        if let Some(per_unit) = item.price_per_unit {
+                            item.total_price = Some(round_money(per_unit as f64 * *quantity as f64) as f32);
+                        }
+                        item.quantity = *quantity;
+                        item.updated_at = now;
+                    }
+                    summary.adjusted += 1;
+                },
+                CheckinAction::MarkFinished => {
+                    item.write_off(None, FridgeItemStatus::Consumed, now);
+                    summary.finished += 1;
+                },
+                CheckinAction::MarkWasted { reason } => {
+                    let wasted_quantity = item.quantity;
+                    let wasted_value = item.write_off(None, FridgeItemStatus::Wasted, now);
+                    written_off += wasted_value as f64;
+                    let waste = FoodWaste {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        original_item_id: Some(item.id),
+                        name: item.name.clone(),
+                        brand: item.brand.clone(),
+                        wasted_quantity,
+                        unit: item.unit.clone(),
+                        category: item.category.clone(),
+                        waste_reason: reason.clone(),
+                        wasted_value: Some(wasted_value),
+                        waste_date: now,
+                        notes: Some("Проверка холодильника".to_string()),
+                        created_at: now,
+                    };
+                    insert_waste(&mut tx, &waste).await?;
+                    summary.wasted += 1;
+                },
+            }
+
+            save_item(&mut tx, &item).await?;
+        }
+
+        tx.commit().await?;
+        summary.removed = summary.finished + summary.wasted;
+        summary.total_value_written_off = round_money(written_off);
+
+        PreferencesService::new(self.pools.primary().clone())
+            .with_clock(self.clock.clone())
+            .update(user_id, |settings| settings.checkin.last_checkin_at = Some(now))
+            .await?;
+
+        Ok(summary)
+    }
+
+    /// Продукты категории `category`, включая историю, с подсказкой категории по названию.
+    /// Ничего не меняет.
+    pub async fn preview_recategorization(&self, user_id: Uuid, category: FridgeCategory) -> Result<Vec<RecategorizationCandidate>, AppError> {
+        let items: Vec<FridgeItem> = UserScope::new(user_id)
+            .query_as("SELECT * FROM fridge_items WHERE user_id = $1 AND category = $2 ORDER BY created_at, id")
+            .bind(category)
+            .fetch_all(self.pools.primary())
+            .await?;
+
+        let candidates = items
+            .into_iter()
+            .map(|item| {
+                let suggestion = suggest_category(&item.name).filter(|(suggested, _)| *suggested != item.category);
+                RecategorizationCandidate {
+                    item_id: item.id,
+                    name: item.name,
+                    status: item.status,
+                    category: item.category,
+                    suggested_category: suggestion.as_ref().map(|(suggested, _)| suggested.clone()),
+                    confidence: suggestion.map(|(_, confidence)| confidence),
                 }
-                true
             })
             .collect();
 
-        Ok(filtered_waste)
+        Ok(candidates)
     }
 
-    pub async fn get_expense_analytics(&self, user_id: Uuid, period: &str) -> Result<ExpenseAnalytics, AppError> {
-        let now = Utc::now();
+    /// Меняет категории всех продуктов пакета или ни одного: пакет записывается одной транзакцией.
+    /// Каждая фактическая смена попадает в журнал; продукты, у которых категория уже такая, пропускаются.
+    pub async fn recategorize(&self, user_id: Uuid, changes: Vec<(Uuid, FridgeCategory)>) -> Result<Vec<CategoryChange>, AppError> {
+        let now = self.clock.now();
+        let mut tx = self.pools.primary().begin().await?;
+        let mut seen: HashSet<Uuid> = HashSet::new();
+        let mut applied: Vec<CategoryChange> = Vec::new();
+
+        for (item_id, new_category) in changes {
+            if !seen.insert(item_id) {
+                return Err(AppError::BadRequest(format!("Duplicate change for item {}", item_id)));
+            }
+
+            let item: FridgeItem = UserScope::new(user_id)
+                .query_as("SELECT * FROM fridge_items WHERE user_id = $1 AND id = $2 FOR UPDATE")
+                .bind(item_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Item {} not found", item_id)))?;
+            if item.category == new_category {
+                continue;
+            }
+
+            UserScope::new(user_id)
+                .query("UPDATE fridge_items SET category = $3, updated_at = $4 WHERE user_id = $1 AND id = $2")
+                .bind(item_id)
+                .bind(&new_category)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+            UserScope::new(user_id)
+                .query(
+                    r#"
+                    INSERT INTO fridge_category_changes (user_id, item_id, from_category, to_category, changed_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#
+                )
+                .bind(item_id)
+                .bind(&item.category)
+                .bind(&new_category)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+
+            applied.push(CategoryChange { item_id, from: item.category, to: new_category, changed_at: now });
+        }
+
+        tx.commit().await?;
+        fridge_autocomplete::invalidate(user_id);
+
+        Ok(applied)
+    }
+
+    pub async fn get_expense_analytics(&self, user_id: Uuid, period: &str, options: AnalyticsOptions) -> Result<ExpenseAnalytics, AppError> {
+        let now = self.clock.now();
         let (start_date, end_date) = match period {
             "day" => (now - chrono::Duration::days(1), now),
             "week" => (now - chrono::Duration::weeks(1), now),
@@ -256,110 +748,76 @@ impl FridgeService {
             _ => (now - chrono::Duration::weeks(1), now),
         };
 
-        // Получаем продукты за период
-        let storage = MOCK_STORAGE.lock().unwrap();
-        let user_items = storage.get(&user_id).cloned().unwrap_or_default();
-        
-        let items_in_period: Vec<&FridgeItem> = user_items
-            .iter()
-            .filter(|item| item.purchase_date >= start_date && item.purchase_date <= end_date)
-            .collect();
-
-        // Получаем отходы за период
-        let waste_storage = WASTE_STORAGE.lock().unwrap();
-        let user_waste = waste_storage.get(&user_id).cloned().unwrap_or_default();
-        
-        let waste_in_period: Vec<&FoodWaste> = user_waste
-            .iter()
-            .filter(|waste| waste.waste_date >= start_date && waste.waste_date <= end_date)
-            .collect();
-
-        // Рассчитываем аналитику
-        let total_purchased: f32 = items_in_period.iter()
-            .map(|item| item.calculate_total_value())
-            .sum();
-
-        let total_wasted: f32 = waste_in_period.iter()
-            .map(|waste| waste.wasted_value.unwrap_or(0.0))
-            .sum();
+        self.get_expense_analytics_between(user_id, period, start_date, end_date, options).await
+    }
 
-        let waste_percentage = if total_purchased > 0.0 {
-            (total_wasted / total_purchased) * 100.0
-        } else {
-            0.0
-        };
+    /// Аналитика за произвольный интервал — например, за календарный месяц для ежемесячного отчета
+    #[instrument(skip_all, fields(user_id = %user_id, period = period))]
+    pub async fn get_expense_analytics_between(
+        &self,
+        user_id: Uuid,
+        period: &str,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        options: AnalyticsOptions,
+    ) -> Result<ExpenseAnalytics, AppError> {
+        // Последняя проверка холодильника показывает, насколько можно доверять количествам
+        let last_checkin_at = PreferencesService::new(self.pools.read(ReadConsistency::Replica).clone())
+            .for_user(user_id)
+            .await?
+            .checkin
+            .last_checkin_at;
 
-        let savings_potential = total_wasted;
+        let now = self.clock.now();
 
-        // Группируем по категориям
-        let mut category_map: HashMap<FridgeCategory, (f32, f32)> = HashMap::new();
-        
-        for item in &items_in_period {
-            let entry = category_map.entry(item.category.clone()).or_insert((0.0, 0.0));
-            entry.0 += item.calculate_total_value();
+        // Медиана покупок считается по всей истории, поэтому продукты берутся все
+        let mut user_items = self.all_user_items(user_id).await?;
+        let user_waste = self.get_waste_history(user_id, None, None).await?;
+        if options.category_as_of == CategoryAsOf::Historical {
+            let changes = self.category_changes(user_id).await?;
+            for item in &mut user_items {
+                item.category = category_at(item, &changes, item.purchase_date);
+            }
         }
 
-        for waste in &waste_in_period {
-            let entry = category_map.entry(waste.category.clone()).or_insert((0.0, 0.0));
-            entry.1 += waste.wasted_value.unwrap_or(0.0);
+        // Одна ошибочная покупка на 45 000 ₽ не должна определять весь месяц
+        let (median_purchase, outliers) = purchase_outliers(&user_items, start_date, end_date, options.outlier_multiple);
+        if !options.include_outliers {
+            let outlier_ids: HashSet<Uuid> = outliers.iter().map(|outlier| outlier.item_id).collect();
+            user_items.retain(|item| !outlier_ids.contains(&item.id));
         }
 
-        let category_breakdown: Vec<CategoryExpense> = category_map
-            .into_iter()
-            .map(|(category, (purchased, wasted))| {
-                let waste_percentage = if purchased > 0.0 {
-                    (wasted / purchased) * 100.0
-                } else {
-                    0.0
-                };
-                CategoryExpense {
-                    category,
-                    purchased,
-                    wasted,
-                    waste_percentage,
-                }
-            })
-            .collect();
+        let mut analytics = compute_expense_analytics(&user_items, &user_waste, start_date, end_date);
+        analytics.period = period.to_string();
+        analytics.category_as_of = options.category_as_of;
+        analytics.last_checkin_at = last_checkin_at;
+        analytics.days_since_checkin = last_checkin_at.map(|checkin| (now - checkin).num_days());
+        analytics.median_purchase = median_purchase.map(round_money);
+        analytics.outliers = outliers;
+        analytics.outliers_included = options.include_outliers;
 
-        // Группируем отходы по причинам
-        let mut reason_map: HashMap<WasteReason, f32> = HashMap::new();
-        for waste in &waste_in_period {
-            let entry = reason_map.entry(waste.waste_reason.clone()).or_insert(0.0);
-            *entry += waste.wasted_value.unwrap_or(0.0);
-        }
+        Ok(analytics)
+    }
 
-        let waste_by_reason: Vec<WasteByReason> = reason_map
-            .into_iter()
-            .map(|(reason, amount)| {
-                let percentage = if total_wasted > 0.0 {
-                    (amount / total_wasted) * 100.0
-                } else {
-                    0.0
-                };
-                WasteByReason {
-                    reason,
-                    amount,
-                    percentage,
-                }
-            })
-            .collect();
+    async fn category_changes(&self, user_id: Uuid) -> Result<Vec<CategoryChange>, AppError> {
+        let rows: Vec<(Uuid, FridgeCategory, FridgeCategory, DateTime<Utc>)> = UserScope::new(user_id)
+            .query_as(
+                "SELECT item_id, from_category, to_category, changed_at FROM fridge_category_changes WHERE user_id = $1"
+            )
+            .fetch_all(self.pools.read(ReadConsistency::Replica))
+            .await?;
 
-        Ok(ExpenseAnalytics {
-            period: period.to_string(),
-            start_date,
-            end_date,
-            total_purchased,
-            total_wasted,
-            waste_percentage,
-            savings_potential,
-            category_breakdown,
-            waste_by_reason,
-        })
+        Ok(rows
+            .into_iter()
+            .map(|(item_id, from, to, changed_at)| CategoryChange { item_id, from, to, changed_at })
+            .collect())
     }
 
-    pub async fn get_economy_insights(&self, user_id: Uuid) -> Result<EconomyInsights, AppError> {
+    /// Итоги месяца и советы на языке пользователя
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_economy_insights(&self, user_id: Uuid, options: AnalyticsOptions, locale: Locale) -> Result<EconomyInsights, AppError> {
         // Получаем аналитику за месяц
-        let analytics = self.get_expense_analytics(user_id, "month").await?;
+        let analytics = self.get_expense_analytics(user_id, "month", options).await?;
         
         // Находим категорию с наибольшими отходами
         let most_wasted_category = analytics.category_breakdown
@@ -378,22 +836,22 @@ impl FridgeService {
         let mut tips = Vec::new();
         
         if analytics.waste_percentage > 20.0 {
-            tips.push("Попробуйте покупать меньше продуктов за раз".to_string());
+            tips.push(i18n::t(locale, "fridge.tip.buy_less").to_string());
         }
         
         if let Some(ref category) = most_wasted_category {
-            tips.push(format!("Обратите внимание на хранение продуктов категории {:?}", category));
+            tips.push(i18n::tf(locale, "fridge.tip.category_storage", &[("category", category.label(locale))]));
         }
         
         if analytics.waste_percentage < 10.0 {
-            tips.push("Отличная работа! Вы эффективно используете продукты".to_string());
+            tips.push(i18n::t(locale, "fridge.tip.great_job").to_string());
         }
 
-        tips.push("Проверяйте сроки годности при покупке".to_string());
-        tips.push("Планируйте меню заранее".to_string());
+        tips.push(i18n::t(locale, "fridge.tip.check_expiry").to_string());
+        tips.push(i18n::t(locale, "fridge.tip.plan_menu").to_string());
 
         Ok(EconomyInsights {
-            total_savings_this_month: analytics.total_purchased - analytics.total_wasted,
+            total_savings_this_month: round_money(analytics.total_purchased - analytics.total_wasted),
             avg_waste_percentage: analytics.waste_percentage,
             most_wasted_category,
             best_category,
@@ -401,3 +859,1714 @@ impl FridgeService {
         })
     }
 }
+
+/// Продукт пользователя, заблокированный до конца транзакции
+pub(crate) async fn lock_item(tx: &mut Transaction<'_, Postgres>, user_id: Uuid, id: Uuid) -> Result<FridgeItem, AppError> {
+    UserScope::new(user_id)
+        .query_as("SELECT * FROM fridge_items WHERE user_id = $1 AND id = $2 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Item not found".to_string()))
+}
+
+/// Записывает отходы внутри транзакции вызывающего кода (см. `FridgeReservationService::add_waste`).
+/// Если указан `original_item_id`, количество списывается с продукта, а стоимость считается по его цене;
+/// когда остаток заканчивается, продукт получает статус Wasted.
+pub(crate) async fn add_waste_in_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    waste_data: CreateFoodWaste,
+    now: DateTime<Utc>,
+) -> Result<FoodWaste, AppError> {
+    let mut wasted_value = waste_data.wasted_value;
+    if let Some(item_id) = waste_data.original_item_id {
+        if !waste_data.wasted_quantity.is_finite() || waste_data.wasted_quantity <= 0.0 {
+            return Err(AppError::BadRequest("Wasted quantity must be positive".to_string()));
+        }
+
+        let mut item = lock_active_item(tx, waste_data.user_id, item_id).await?;
+        wasted_value = Some(item.write_off(Some(waste_data.wasted_quantity), FridgeItemStatus::Wasted, now));
+        save_item(tx, &item).await?;
+    }
+
+    let waste = FoodWaste {
+        id: Uuid::new_v4(),
+        user_id: waste_data.user_id,
+        original_item_id: waste_data.original_item_id,
+        name: waste_data.name,
+        brand: waste_data.brand,
+        wasted_quantity: waste_data.wasted_quantity,
+        unit: waste_data.unit,
+        category: waste_data.category,
+        waste_reason: waste_data.waste_reason,
+        wasted_value,
+        waste_date: now,
+        notes: waste_data.notes,
+        created_at: now,
+    };
+
+    insert_waste(tx, &waste).await?;
+    Ok(waste)
+}
+
+/// Отмечает съеденной часть продукта или, без `quantity`, весь остаток, внутри транзакции
+/// вызывающего кода (см. `FridgeReservationService::consume_item`). Больше остатка списать нельзя;
+/// остаток стоимости уменьшается пропорционально, на нуле продукт уходит в историю со статусом Consumed.
+/// Каждое списание (и заметка к нему) пишется в fridge_consumptions.
+/// Готовка слота плана списывает продукты и снимает резерв одной транзакцией.
+pub(crate) async fn consume_in_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    id: Uuid,
+    quantity: Option<f32>,
+    note: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<FridgeItem, AppError> {
+    if quantity.is_some_and(|quantity| !quantity.is_finite() || quantity <= 0.0) {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+
+    let mut item = lock_active_item(tx, user_id, id).await?;
+    if let Some(requested) = quantity.filter(|quantity| *quantity as f64 > item.quantity as f64 + QUANTITY_EPSILON) {
+        return Err(AppError::BadRequest(format!(
+            "Cannot consume {} {}: only {} {} left",
+            requested, item.unit, item.quantity, item.unit
+        )));
+    }
+
+    // Остаток в пределах погрешности списывается целиком, чтобы не оставлять микроскопических хвостов
+    let quantity = quantity.filter(|quantity| (*quantity as f64) < item.quantity as f64 - QUANTITY_EPSILON);
+    let consumed = quantity.unwrap_or(item.quantity);
+    let value = item.write_off(quantity, FridgeItemStatus::Consumed, now);
+    save_item(tx, &item).await?;
+
+    UserScope::new(user_id)
+        .query(
+            r#"
+            INSERT INTO fridge_consumptions (user_id, item_id, quantity, unit, consumed_value, note, consumed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#
+        )
+        .bind(item.id)
+        .bind(consumed)
+        .bind(&item.unit)
+        .bind(value)
+        .bind(note.filter(|note| !note.is_empty()))
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(item)
+}
+
+/// Как `lock_item`, но продукт должен еще лежать в холодильнике
+pub(crate) async fn lock_active_item(tx: &mut Transaction<'_, Postgres>, user_id: Uuid, id: Uuid) -> Result<FridgeItem, AppError> {
+    let item = lock_item(tx, user_id, id).await?;
+    if !item.is_active() {
+        return Err(AppError::BadRequest("Item is no longer in the fridge".to_string()));
+    }
+    Ok(item)
+}
+
+async fn insert_item<'e, E: PgExecutor<'e>>(executor: E, item: &FridgeItem) -> Result<(), AppError> {
+    UserScope::new(item.user_id)
+        .query(
+            r#"
+            INSERT INTO fridge_items (
+                user_id, id, name, brand, quantity, unit, category, price_per_unit, total_price,
+                expiry_date, purchase_date, notes, location, store,
+                contains_allergens, contains_intolerances, suitable_for_diets, ingredients, nutritional_info,
+                status, finished_at, consumed_value, wasted_value, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14,
+                    $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25)
+            "#
+        )
+        .bind(item.id)
+        .bind(&item.name)
+        .bind(&item.brand)
+        .bind(item.quantity)
+        .bind(&item.unit)
+        .bind(&item.category)
+        .bind(item.price_per_unit)
+        .bind(item.total_price)
+        .bind(item.expiry_date)
+        .bind(item.purchase_date)
+        .bind(&item.notes)
+        .bind(&item.location)
+        .bind(&item.store)
+        .bind(&item.contains_allergens)
+        .bind(&item.contains_intolerances)
+        .bind(&item.suitable_for_diets)
+        .bind(&item.ingredients)
+        .bind(&item.nutritional_info)
+        .bind(item.status)
+        .bind(item.finished_at)
+        .bind(item.consumed_value)
+        .bind(item.wasted_value)
+        .bind(item.created_at)
+        .bind(item.updated_at)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Записывает изменяемые поля продукта; id, владелец, дата покупки и создания не меняются
+async fn save_item(tx: &mut Transaction<'_, Postgres>, item: &FridgeItem) -> Result<(), AppError> {
+    UserScope::new(item.user_id)
+        .query(
+            r#"
+            UPDATE fridge_items
+            SET name = $3, brand = $4, quantity = $5, unit = $6, category = $7, price_per_unit = $8,
+                total_price = $9, expiry_date = $10, notes = $11, location = $12, store = $13,
+                contains_allergens = $14, contains_intolerances = $15, suitable_for_diets = $16,
+                ingredients = $17, nutritional_info = $18, status = $19, finished_at = $20,
+                consumed_value = $21, wasted_value = $22, updated_at = $23
+            WHERE user_id = $1 AND id = $2
+            "#
+        )
+        .bind(item.id)
+        .bind(&item.name)
+        .bind(&item.brand)
+        .bind(item.quantity)
+        .bind(&item.unit)
+        .bind(&item.category)
+        .bind(item.price_per_unit)
+        .bind(item.total_price)
+        .bind(item.expiry_date)
+        .bind(&item.notes)
+        .bind(&item.location)
+        .bind(&item.store)
+        .bind(&item.contains_allergens)
+        .bind(&item.contains_intolerances)
+        .bind(&item.suitable_for_diets)
+        .bind(&item.ingredients)
+        .bind(&item.nutritional_info)
+        .bind(item.status)
+        .bind(item.finished_at)
+        .bind(item.consumed_value)
+        .bind(item.wasted_value)
+        .bind(item.updated_at)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+async fn insert_waste(tx: &mut Transaction<'_, Postgres>, waste: &FoodWaste) -> Result<(), AppError> {
+    UserScope::new(waste.user_id)
+        .query(
+            r#"
+            INSERT INTO food_waste (
+                user_id, id, original_item_id, name, brand, wasted_quantity, unit, category,
+                waste_reason, wasted_value, waste_date, notes, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#
+        )
+        .bind(waste.id)
+        .bind(waste.original_item_id)
+        .bind(&waste.name)
+        .bind(&waste.brand)
+        .bind(waste.wasted_quantity)
+        .bind(&waste.unit)
+        .bind(&waste.category)
+        .bind(&waste.waste_reason)
+        .bind(waste.wasted_value)
+        .bind(waste.waste_date)
+        .bind(&waste.notes)
+        .bind(waste.created_at)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// Категория продукта на момент `at`: исходная категория первой смены после этого момента,
+/// а если смен не было — текущая
+fn category_at(item: &FridgeItem, changes: &[CategoryChange], at: DateTime<Utc>) -> FridgeCategory {
+    changes
+        .iter()
+        .filter(|change| change.item_id == item.id && change.changed_at > at)
+        .min_by_key(|change| change.changed_at)
+        .map_or_else(|| item.category.clone(), |change| change.from.clone())
+}
+
+/// Сводка по продуктам в холодильнике на момент `now`. Просроченные не входят в `expiring_soon`;
+/// категории — в порядке по умолчанию, места хранения — по убыванию числа продуктов.
+/// Съеденные, выброшенные и удаленные продукты в сводку не входят.
+pub fn compute_stats(items: &[FridgeItem], now: DateTime<Utc>) -> FridgeStats {
+    let items: Vec<&FridgeItem> = items.iter().filter(|item| item.is_active()).collect();
+    let expired_items = items.iter().filter(|item| item.is_expired(now)).count();
+    let expiring_soon = items
+        .iter()
+        .filter(|item| !item.is_expired(now) && item.is_expiring_soon(STATS_EXPIRING_DAYS, now))
+        .count();
+
+    let categories = FridgeCategory::ALL
+        .iter()
+        .map(|category| CategoryCount {
+            category: category.clone(),
+            count: items.iter().filter(|item| item.category == *category).count() as i32,
+        })
+        .filter(|entry| entry.count > 0)
+        .collect();
+
+    // "Холодильник " и "холодильник" — одно место
+    let mut by_location: HashMap<String, i32> = HashMap::new();
+    for item in &items {
+        let location = item
+            .location
+            .as_deref()
+            .map(|location| location.trim().to_lowercase())
+            .filter(|location| !location.is_empty())
+            .unwrap_or_else(|| UNSPECIFIED_LOCATION.to_string());
+        *by_location.entry(location).or_default() += 1;
+    }
+    let mut locations: Vec<LocationCount> = by_location
+        .into_iter()
+        .map(|(location, count)| LocationCount { location, count })
+        .collect();
+    locations.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.location.cmp(&b.location)));
+
+    FridgeStats {
+        total_items: items.len() as i32,
+        expired_items: expired_items as i32,
+        expiring_soon: expiring_soon as i32,
+        categories,
+        locations,
+    }
+}
+
+/// Аналитика по продуктам, купленным в интервале: каждый учитывается целиком, что бы с ним
+/// ни случилось потом, поэтому total_purchased = total_consumed + total_wasted + active_value.
+/// Отходы без привязки к продукту холодильника (записанные вручную) входят и в купленное,
+/// и в выброшенное — они были куплены, но не отслеживались.
+pub fn compute_expense_analytics(
+    items: &[FridgeItem],
+    wastes: &[FoodWaste],
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> ExpenseAnalytics {
+    let cohort: Vec<&FridgeItem> = items
+        .iter()
+        .filter(|item| item.purchase_date >= start_date && item.purchase_date <= end_date)
+        .collect();
+    let cohort_ids: HashSet<Uuid> = cohort.iter().map(|item| item.id).collect();
+
+    let untracked_waste: Vec<&FoodWaste> = wastes
+        .iter()
+        .filter(|waste| waste.original_item_id.is_none())
+        .filter(|waste| waste.waste_date >= start_date && waste.waste_date <= end_date)
+        .collect();
+    let untracked_value = |waste: &FoodWaste| waste.wasted_value.unwrap_or(0.0) as f64;
+
+    // Суммируем в f64 и округляем только итоговые значения
+    let mut total_purchased = 0.0;
+    let mut total_consumed = 0.0;
+    let mut total_wasted = 0.0;
+    let mut active_value = 0.0;
+    // Категория -> (куплено, съедено, выброшено)
+    let mut category_map: HashMap<FridgeCategory, (f64, f64, f64)> = HashMap::new();
+    let mut stores = StoreTotals::default();
+
+    for item in &cohort {
+        let (purchased, consumed, wasted) = (item.purchased_value() as f64, item.consumed_value as f64, item.wasted_value as f64);
+        total_purchased += purchased;
+        total_consumed += consumed;
+        total_wasted += wasted;
+        active_value += item.remaining_value() as f64;
+
+        let entry = category_map.entry(item.category.clone()).or_insert((0.0, 0.0, 0.0));
+        entry.0 += purchased;
+        entry.1 += consumed;
+        entry.2 += wasted;
+
+        // Отходы продукта уже учтены в нем самом, поэтому идут в его магазин
+        stores.add(item.store.as_deref(), item.purchase_date, purchased, consumed, wasted);
+    }
+
+    for waste in &untracked_waste {
+        let value = untracked_value(waste);
+        total_purchased += value;
+        total_wasted += value;
+
+        let entry = category_map.entry(waste.category.clone()).or_insert((0.0, 0.0, 0.0));
+        entry.0 += value;
+        entry.2 += value;
+
+        // Без исходного продукта магазин неизвестен
+        stores.add(None, waste.waste_date, value, 0.0, value);
+    }
+
+    let category_breakdown: Vec<CategoryExpense> = category_map
+        .into_iter()
+        .map(|(category, (purchased, consumed, wasted))| CategoryExpense {
+            category,
+            purchased: round_money(purchased),
+            consumed: round_money(consumed),
+            wasted: round_money(wasted),
+            waste_percentage: percent_of(wasted, consumed + wasted),
+        })
+        .collect();
+
+    // Причины: записи об отходах по продуктам из выборки и записи без привязки
+    let mut reason_map: HashMap<WasteReason, f64> = HashMap::new();
+    let cohort_waste = wastes
+        .iter()
+        .filter(|waste| waste.original_item_id.is_some_and(|id| cohort_ids.contains(&id)));
+    for waste in cohort_waste.chain(untracked_waste.iter().copied()) {
+        *reason_map.entry(waste.waste_reason.clone()).or_insert(0.0) += waste.wasted_value.unwrap_or(0.0) as f64;
+    }
+
+    let waste_by_reason: Vec<WasteByReason> = reason_map
+        .into_iter()
+        .map(|(reason, amount)| WasteByReason {
+            reason,
+            amount: round_money(amount),
+            percentage: percent_of(amount, total_wasted),
+        })
+        .collect();
+
+    ExpenseAnalytics {
+        period: String::new(),
+        start_date,
+        end_date,
+        total_purchased: round_money(total_purchased),
+        total_consumed: round_money(total_consumed),
+        total_wasted: round_money(total_wasted),
+        active_value: round_money(active_value),
+        waste_percentage: percent_of(total_wasted, total_consumed + total_wasted),
+        savings_potential: round_money(total_wasted),
+        category_breakdown,
+        store_breakdown: stores.into_breakdown(),
+        category_as_of: CategoryAsOf::Current,
+        waste_by_reason,
+        last_checkin_at: None,
+        days_since_checkin: None,
+        median_purchase: None,
+        outliers: Vec::new(),
+        outliers_included: true,
+    }
+}
+
+/// Суммы по магазинам для `compute_expense_analytics`
+#[derive(Default)]
+struct StoreTotals {
+    /// Ключ магазина -> итоги; `None` — магазин не указан
+    groups: HashMap<Option<String>, StoreGroup>,
+}
+
+#[derive(Default)]
+struct StoreGroup {
+    /// Последнее написание названия — его и показываем
+    display: Option<(DateTime<Utc>, String)>,
+    purchased: f64,
+    consumed: f64,
+    wasted: f64,
+    item_count: usize,
+}
+
+impl StoreTotals {
+    fn add(&mut self, store: Option<&str>, at: DateTime<Utc>, purchased: f64, consumed: f64, wasted: f64) {
+        let display = store.and_then(|store| clean_store(Some(store.to_string())));
+        let group = self.groups.entry(display.as_deref().and_then(store_key)).or_default();
+        if let Some(display) = display {
+            if group.display.as_ref().is_none_or(|(seen_at, _)| at >= *seen_at) {
+                group.display = Some((at, display));
+            }
+        }
+        group.purchased += purchased;
+        group.consumed += consumed;
+        group.wasted += wasted;
+        group.item_count += 1;
+    }
+
+    fn into_breakdown(self) -> Vec<StoreExpense> {
+        let mut breakdown: Vec<StoreExpense> = self
+            .groups
+            .into_values()
+            .map(|group| StoreExpense {
+                store: group.display.map_or_else(|| UNSPECIFIED_STORE.to_string(), |(_, store)| store),
+                purchased: round_money(group.purchased),
+                wasted: round_money(group.wasted),
+                waste_percentage: percent_of(group.wasted, group.consumed + group.wasted),
+                item_count: group.item_count,
+            })
+            .collect();
+        breakdown.sort_by(|a, b| b.purchased.total_cmp(&a.purchased).then_with(|| a.store.cmp(&b.store)));
+        breakdown
+    }
+}
+
+/// Медиана стоимости всех покупок пользователя и покупки периода, которые дороже нее
+/// в `multiple` раз и больше. Пока покупок меньше `MIN_OUTLIER_SAMPLE`, медиане нельзя
+/// доверять, и выбросов нет.
+pub fn purchase_outliers(
+    items: &[FridgeItem],
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    multiple: f64,
+) -> (Option<f64>, Vec<OutlierPurchase>) {
+    let mut values: Vec<f64> = items
+        .iter()
+        .map(|item| item.purchased_value() as f64)
+        .filter(|value| *value > 0.0)
+        .collect();
+    if values.len() < MIN_OUTLIER_SAMPLE || !multiple.is_finite() || multiple <= 1.0 {
+        return (None, Vec::new());
+    }
+
+    values.sort_by(f64::total_cmp);
+    let count = values.len();
+    let median = (values[(count - 1) / 2] + values[count / 2]) / 2.0;
+
+    let outliers = items
+        .iter()
+        .filter(|item| item.purchase_date >= start_date && item.purchase_date <= end_date)
+        .filter_map(|item| {
+            let value = item.purchased_value() as f64;
+            (value >= median * multiple).then(|| OutlierPurchase {
+                item_id: item.id,
+                name: item.name.clone(),
+                purchased_value: round_money(value),
+                times_median: round_to(value / median, 1),
+            })
+        })
+        .collect();
+
+    (Some(median), outliers)
+}
+
+/// Новый продукт из данных формы; цены приводятся к согласованному виду
+pub(crate) fn new_item(item_data: CreateFridgeItem, now: DateTime<Utc>) -> Result<FridgeItem, AppError> {
+    let (price_per_unit, total_price) = normalize_prices(
+        item_data.quantity,
+        &item_data.unit,
+        item_data.price_per_unit,
+        item_data.total_price,
+    )?;
+
+    Ok(FridgeItem {
+        id: Uuid::new_v4(),
+        user_id: item_data.user_id,
+        name: item_data.name,
+        brand: item_data.brand,
+        quantity: item_data.quantity,
+        unit: item_data.unit,
+        category: item_data.category,
+        price_per_unit,
+        total_price,
+        expiry_date: item_data.expiry_date,
+        purchase_date: item_data.purchase_date,
+        notes: item_data.notes,
+        location: item_data.location,
+        store: clean_store(item_data.store),
+        // Новые поля для диетических ограничений
+        contains_allergens: item_data.contains_allergens,
+        contains_intolerances: item_data.contains_intolerances,
+        suitable_for_diets: item_data.suitable_for_diets,
+        ingredients: item_data.ingredients,
+        nutritional_info: item_data.nutritional_info,
+        status: FridgeItemStatus::Active,
+        finished_at: None,
+        consumed_value: 0.0,
+        wasted_value: 0.0,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::services::fridge_reservations::FridgeReservationService;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    fn item(category: FridgeCategory, location: Option<&str>, expiry_date: Option<DateTime<Utc>>) -> FridgeItem {
+        new_item(
+            CreateFridgeItem {
+                user_id: Uuid::nil(),
+                name: "Молоко".to_string(),
+                brand: None,
+                quantity: 1.0,
+                unit: "l".to_string(),
+                category,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date,
+                purchase_date: now() - Duration::days(1),
+                notes: None,
+                location: location.map(str::to_string),
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            },
+            now(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn stats_of_empty_fridge_are_zero() {
+        let stats = compute_stats(&[], now());
+
+        assert_eq!(stats.total_items, 0);
+        assert_eq!(stats.expired_items, 0);
+        assert_eq!(stats.expiring_soon, 0);
+        assert!(stats.categories.is_empty());
+        assert!(stats.locations.is_empty());
+    }
+
+    #[test]
+    fn items_without_expiry_are_neither_expired_nor_expiring() {
+        let items = [item(FridgeCategory::Grains, Some("pantry"), None), item(FridgeCategory::Grains, None, None)];
+        let stats = compute_stats(&items, now());
+
+        assert_eq!(stats.total_items, 2);
+        assert_eq!(stats.expired_items, 0);
+        assert_eq!(stats.expiring_soon, 0);
+    }
+
+    #[test]
+    fn expired_items_are_not_counted_as_expiring_soon() {
+        let items = [
+            item(FridgeCategory::Dairy, None, Some(now() - Duration::days(2))),
+            // Истек час назад: (expiry - now).num_days() == 0, но продукт уже просрочен
+            item(FridgeCategory::Dairy, None, Some(now() - Duration::hours(1))),
+        ];
+        let stats = compute_stats(&items, now());
+
+        assert_eq!(stats.expired_items, 2);
+        assert_eq!(stats.expiring_soon, 0);
+    }
+
+    #[test]
+    fn expiring_soon_covers_now_through_three_full_days() {
+        let items = [
+            // Срок ровно сейчас — еще не просрочен, days_left == 0
+            item(FridgeCategory::Meat, None, Some(now())),
+            item(FridgeCategory::Meat, None, Some(now() + Duration::days(1))),
+            // days_left == 3 — верхняя граница
+            item(FridgeCategory::Meat, None, Some(now() + Duration::days(3) + Duration::hours(23))),
+            // days_left == 4 — уже не скоро
+            item(FridgeCategory::Meat, None, Some(now() + Duration::days(4))),
+        ];
+        let stats = compute_stats(&items, now());
+
+        assert_eq!(stats.total_items, 4);
+        assert_eq!(stats.expired_items, 0);
+        assert_eq!(stats.expiring_soon, 3);
+    }
+
+    #[test]
+    fn inactive_items_are_excluded() {
+        let mut consumed = item(FridgeCategory::Fish, Some("freezer"), Some(now() - Duration::days(1)));
+        consumed.write_off(None, FridgeItemStatus::Consumed, now());
+        let mut wasted = item(FridgeCategory::Fish, Some("freezer"), Some(now() + Duration::days(1)));
+        wasted.write_off(None, FridgeItemStatus::Wasted, now());
+        let active = item(FridgeCategory::Vegetables, Some("fridge"), None);
+
+        let stats = compute_stats(&[consumed, wasted, active], now());
+
+        assert_eq!(stats.total_items, 1);
+        assert_eq!(stats.expired_items, 0);
+        assert_eq!(stats.expiring_soon, 0);
+        assert_eq!(stats.categories.len(), 1);
+        assert_eq!(stats.categories[0].category, FridgeCategory::Vegetables);
+        assert_eq!(stats.locations.len(), 1);
+        assert_eq!(stats.locations[0].location, "fridge");
+    }
+
+    /// Все числа JSON — не длиннее двух знаков после точки (без хвостов f32 вроде 43.599998)
+    fn assert_no_float_tails(value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Number(number) => {
+                let text = number.to_string();
+                let decimals = text.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+                assert!(decimals <= 2, "float tail in {}", text);
+            }
+            serde_json::Value::Array(values) => values.iter().for_each(assert_no_float_tails),
+            serde_json::Value::Object(fields) => fields.values().for_each(assert_no_float_tails),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn serialized_analytics_have_no_float_tails() {
+        let priced = |category, store: &str, price: f32, quantity: f32| {
+            let mut item = item(category, None, None);
+            item.price_per_unit = Some(price);
+            item.quantity = quantity;
+            item.store = Some(store.to_string());
+            item
+        };
+
+        let mut milk = priced(FridgeCategory::Dairy, "Лента", 43.6, 3.0);
+        let mut cheese = priced(FridgeCategory::Dairy, "Лента", 0.1 + 0.2, 7.0);
+        let mut apples = priced(FridgeCategory::Fruits, "Пятёрочка", 89.99, 1.3);
+        let bread = priced(FridgeCategory::Grains, "Пятёрочка", 33.33, 3.0);
+        milk.write_off(Some(1.1), FridgeItemStatus::Consumed, now());
+        cheese.write_off(Some(2.3), FridgeItemStatus::Wasted, now());
+        apples.write_off(None, FridgeItemStatus::Wasted, now());
+
+        let analytics = compute_expense_analytics(
+            &[milk, cheese, apples, bread],
+            &[],
+            now() - Duration::days(30),
+            now(),
+        );
+
+        let json = serde_json::to_value(&analytics).unwrap();
+        assert_no_float_tails(&json);
+        assert!(analytics.total_purchased > 0.0 && analytics.total_wasted > 0.0);
+        assert_eq!(
+            analytics.total_purchased,
+            round_money(analytics.total_consumed + analytics.total_wasted + analytics.active_value)
+        );
+
+    }
+
+    #[sqlx::test]
+    async fn stored_analytics_and_insights_have_no_float_tails(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone());
+        for (name, price, quantity) in [("Молоко", 43.6, 3.0), ("Сыр", 0.1 + 0.2, 7.0), ("Яблоки", 89.99, 1.3)] {
+            service
+                .add_item(CreateFridgeItem {
+                    user_id,
+                    name: name.to_string(),
+                    brand: None,
+                    quantity,
+                    unit: "kg".to_string(),
+                    category: FridgeCategory::Dairy,
+                    price_per_unit: Some(price),
+                    total_price: None,
+                    expiry_date: None,
+                    purchase_date: Utc::now() - Duration::days(1),
+                    notes: None,
+                    location: None,
+                    store: None,
+                    contains_allergens: Vec::new(),
+                    contains_intolerances: Vec::new(),
+                    suitable_for_diets: Vec::new(),
+                    ingredients: None,
+                    nutritional_info: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let analytics = service.get_expense_analytics(user_id, "month", AnalyticsOptions::default()).await.unwrap();
+        assert_no_float_tails(&serde_json::to_value(&analytics).unwrap());
+        assert_eq!(analytics.total_purchased, 249.89);
+
+        let insights = service.get_economy_insights(user_id, AnalyticsOptions::default(), Locale::Ru).await.unwrap();
+        assert_no_float_tails(&serde_json::to_value(&insights).unwrap());
+    }
+
+    #[test]
+    fn breakdowns_group_categories_and_normalized_locations() {
+        let items = [
+            item(FridgeCategory::Fruits, Some("Fridge "), None),
+            item(FridgeCategory::Dairy, Some("fridge"), None),
+            item(FridgeCategory::Dairy, Some("pantry"), None),
+            item(FridgeCategory::Dairy, None, None),
+            item(FridgeCategory::Dairy, Some("  "), None),
+        ];
+        let stats = compute_stats(&items, now());
+
+        // Порядок категорий — как в FridgeCategory::ALL, пустые пропущены
+        let categories: Vec<(FridgeCategory, i32)> =
+            stats.categories.iter().map(|entry| (entry.category.clone(), entry.count)).collect();
+        assert_eq!(categories, vec![(FridgeCategory::Dairy, 4), (FridgeCategory::Fruits, 1)]);
+
+        let locations: Vec<(&str, i32)> = stats.locations.iter().map(|entry| (entry.location.as_str(), entry.count)).collect();
+        assert_eq!(locations, vec![("fridge", 2), (UNSPECIFIED_LOCATION, 2), ("pantry", 1)]);
+    }
+
+    /// (количество, единица, цена за единицу, итог) → (цена за единицу, итог)
+    type PriceCase = (f32, &'static str, Option<f32>, Option<f32>, Option<f32>, Option<f32>);
+
+    const PRICE_MATRIX: &[PriceCase] = &[
+        // Только итог — цена за единицу выводится
+        (0.9, "kg", None, Some(120.0), Some(133.3333), Some(120.0)),
+        (250.0, "g", None, Some(80.0), Some(0.32), Some(80.0)),
+        (4.0, "шт", None, Some(90.0), Some(22.5), Some(90.0)),
+        // Только цена за единицу — итог выводится и округляется до копеек
+        (2.0, "pcs", Some(45.0), None, Some(45.0), Some(90.0)),
+        (1.3, "kg", Some(89.99), None, Some(89.99), Some(116.99)),
+        (500.0, "ml", Some(0.2), None, Some(0.2), Some(100.0)),
+        (3.0, "шт", Some(0.0), None, Some(0.0), Some(0.0)),
+        // Обе согласованы — сохраняются как переданы
+        (1.5, "l", Some(60.0), Some(90.0), Some(60.0), Some(90.0)),
+        (1.0, "kg", Some(0.0), Some(0.0), Some(0.0), Some(0.0)),
+        // Нулевое количество: цену за единицу из итога не вывести
+        (0.0, "kg", None, Some(100.0), None, Some(100.0)),
+        (0.0, "kg", Some(10.0), None, Some(10.0), Some(0.0)),
+        // Ни одной цены
+        (1.0, "kg", None, None, None, None),
+    ];
+
+    #[test]
+    fn prices_are_derived_from_whichever_is_present() {
+        for &(quantity, unit, per_unit, total, expected_per_unit, expected_total) in PRICE_MATRIX {
+            let (derived_per_unit, derived_total) = normalize_prices(quantity, unit, per_unit, total).unwrap();
+            let case = format!("{} {} per_unit={:?} total={:?}", quantity, unit, per_unit, total);
+            assert_eq!(derived_per_unit, expected_per_unit, "{}", case);
+            assert_eq!(derived_total, expected_total, "{}", case);
+        }
+    }
+
+    #[test]
+    fn contradicting_prices_are_rejected_beyond_five_percent() {
+        // Ожидаемый итог 100: расхождение считается от большей из двух сумм
+        for total in [100.0, 96.0, 95.0, 104.0, 105.0, 105.26] {
+            assert!(normalize_prices(1.0, "kg", Some(100.0), Some(total)).is_ok(), "total {} is within 5%", total);
+        }
+        for total in [94.9, 90.0, 105.27, 106.0, 200.0, 0.0] {
+            assert!(
+                matches!(normalize_prices(1.0, "kg", Some(100.0), Some(total)), Err(AppError::BadRequest(_))),
+                "total {} is more than 5% off",
+                total
+            );
+        }
+
+        // Цена за единицу в граммах: 250 г по 0.32 — это 80
+        assert!(normalize_prices(250.0, "g", Some(0.32), Some(80.0)).is_ok());
+        assert!(normalize_prices(250.0, "g", Some(320.0), Some(80.0)).is_err());
+
+        match normalize_prices(2.0, " kg ", Some(50.0), Some(120.0)) {
+            Err(AppError::BadRequest(message)) => {
+                assert_eq!(
+                    message,
+                    "total_price 120 does not match price_per_unit 50 × quantity 2 kg = 100 (16.7% apart, at most 5% allowed)"
+                );
+            }
+            other => panic!("expected BadRequest, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn negative_and_non_finite_prices_are_rejected() {
+        for (per_unit, total) in [
+            (Some(-1.0), None),
+            (None, Some(-0.01)),
+            (Some(f32::NAN), None),
+            (None, Some(f32::INFINITY)),
+            (Some(10.0), Some(f32::NAN)),
+        ] {
+            assert!(
+                matches!(normalize_prices(1.0, "kg", per_unit, total), Err(AppError::BadRequest(_))),
+                "per_unit={:?} total={:?}",
+                per_unit,
+                total
+            );
+        }
+    }
+
+    fn priced(quantity: f32, unit: &str, price_per_unit: Option<f32>, total_price: Option<f32>) -> FridgeItem {
+        FridgeItem { quantity, unit: unit.to_string(), price_per_unit, total_price, ..item(FridgeCategory::Other, None, None) }
+    }
+
+    #[test]
+    fn normalized_price_is_per_kg_litre_or_piece() {
+        use crate::utils::units::BaseUnit;
+
+        let cases = [
+            (priced(250.0, "g", Some(0.32), Some(80.0)), Some((320.0, BaseUnit::Kg))),
+            (priced(0.9, "кг", Some(133.3333), Some(120.0)), Some((133.33, BaseUnit::Kg))),
+            (priced(500.0, "мл", Some(0.2), None), Some((200.0, BaseUnit::L))),
+            (priced(1.5, "l", None, Some(90.0)), Some((60.0, BaseUnit::L))),
+            (priced(6.0, "шт", None, Some(90.0)), Some((15.0, BaseUnit::Piece))),
+            // Неизвестная единица, нулевое количество или нет цены — сравнивать нечего
+            (priced(2.0, "пачка", None, Some(90.0)), None),
+            (priced(0.0, "kg", Some(10.0), None), None),
+            (priced(1.0, "kg", None, None), None),
+        ];
+        for (item, expected) in cases {
+            assert_eq!(item.normalized_unit_price(), expected, "{} {}", item.quantity, item.unit);
+        }
+
+        // Одинаковый продукт в разных единицах сравним по цене
+        let grams = priced(400.0, "г", None, Some(100.0)).normalized_unit_price().unwrap();
+        let kilos = priced(1.0, "kg", None, Some(230.0)).normalized_unit_price().unwrap();
+        assert!(kilos.0 < grams.0);
+    }
+
+    #[test]
+    fn response_exposes_the_normalized_price() {
+        let response = crate::api::fridge::FridgeItemResponse::new(priced(250.0, "g", Some(0.32), Some(80.0)), now());
+        let body = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(body["unit_price_normalized"], 320.0);
+        assert_eq!(body["price_per_unit"].as_f64().map(|price| (price * 100.0).round() / 100.0), Some(0.32));
+        assert_eq!(body["total_price"], 80.0);
+
+        let unpriced = crate::api::fridge::FridgeItemResponse::new(priced(1.0, "kg", None, None), now());
+        assert!(serde_json::to_value(&unpriced).unwrap()["unit_price_normalized"].is_null());
+    }
+
+    #[sqlx::test]
+    async fn writes_store_both_prices_and_reject_contradictions(pool: sqlx::PgPool) {
+        use crate::api::fridge::CreateFridgeItemRequest;
+
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone());
+        let request = CreateFridgeItem {
+            user_id,
+            name: "Фарш".to_string(),
+            brand: None,
+            quantity: 0.9,
+            unit: "kg".to_string(),
+            category: FridgeCategory::Meat,
+            price_per_unit: None,
+            total_price: Some(120.0),
+            expiry_date: None,
+            purchase_date: now() - Duration::days(1),
+            notes: None,
+            location: None,
+            store: None,
+            contains_allergens: Vec::new(),
+            contains_intolerances: Vec::new(),
+            suitable_for_diets: Vec::new(),
+            ingredients: None,
+            nutritional_info: None,
+        };
+        let added = service.add_item(request.clone()).await.unwrap();
+        let stored = service.get_item_by_id(added.id, user_id).await.unwrap();
+        assert_eq!((stored.price_per_unit, stored.total_price), (Some(133.3333), Some(120.0)));
+
+        let rejected = service.add_item(CreateFridgeItem { price_per_unit: Some(100.0), ..request }).await;
+        assert!(matches!(rejected, Err(AppError::BadRequest(_))));
+
+        // Полная замена: 250 г по 80 — цена за грамм выводится из итога
+        let update = |total: Option<f32>, per_unit: Option<f32>| -> CreateFridgeItemRequest {
+            serde_json::from_value(serde_json::json!({
+                "name": "Фарш",
+                "quantity": 250.0,
+                "unit": "g",
+                "category": "Meat",
+                "price_per_unit": per_unit,
+                "total_price": total,
+                "expiry_date": null,
+                "purchase_date": null
+            }))
+            .unwrap()
+        };
+        let updated = service.update_item(added.id, user_id, update(Some(80.0), None)).await.unwrap();
+        assert_eq!((updated.price_per_unit, updated.total_price), (Some(0.32), Some(80.0)));
+        assert_eq!(updated.normalized_unit_price().map(|(price, _)| price), Some(320.0));
+
+        let contradiction = service.update_item(added.id, user_id, update(Some(80.0), Some(1.0))).await;
+        assert!(matches!(contradiction, Err(AppError::BadRequest(_))));
+        let unchanged = service.get_item_by_id(added.id, user_id).await.unwrap();
+        assert_eq!((unchanged.price_per_unit, unchanged.total_price), (Some(0.32), Some(80.0)));
+
+        // Частичное изменение количества пересчитывает итог по прежней цене за единицу
+        let patch: UpdateFridgeItem = serde_json::from_value(serde_json::json!({ "quantity": 500.0 })).unwrap();
+        let patched = service.patch_item(added.id, user_id, patch, |_| Ok(())).await.unwrap();
+        assert_eq!((patched.price_per_unit, patched.total_price), (Some(0.32), Some(160.0)));
+        assert_eq!(patched.normalized_unit_price().map(|(price, _)| price), Some(320.0));
+    }
+
+    async fn add_priced(service: &FridgeService, user_id: Uuid, name: &str, quantity: f32, price_per_unit: f32) -> FridgeItem {
+        service
+            .add_item(CreateFridgeItem {
+                user_id,
+                name: name.to_string(),
+                brand: None,
+                quantity,
+                unit: "шт".to_string(),
+                category: FridgeCategory::Other,
+                price_per_unit: Some(price_per_unit),
+                total_price: None,
+                expiry_date: None,
+                purchase_date: now() - Duration::days(20),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    fn adjustment(item: &FridgeItem, action: serde_json::Value) -> CheckinAdjustment {
+        let mut value = action;
+        value["item_id"] = serde_json::json!(item.id);
+        serde_json::from_value(value).unwrap()
+    }
+
+    async fn stored(pool: &sqlx::PgPool, id: Uuid) -> FridgeItem {
+        sqlx::query_as("SELECT * FROM fridge_items WHERE id = $1").bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    /// Триггер выставляет `updated_at = NOW()` при любом UPDATE, поэтому на время правки он отключается
+    async fn backdate(pool: &sqlx::PgPool, id: Uuid, updated_at: DateTime<Utc>) {
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("ALTER TABLE fridge_items DISABLE TRIGGER update_fridge_items_updated_at").execute(&mut *tx).await.unwrap();
+        sqlx::query("UPDATE fridge_items SET updated_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(updated_at)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE fridge_items ENABLE TRIGGER update_fridge_items_updated_at").execute(&mut *tx).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn checkin_lists_stale_items_first(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        let fresh = add_priced(&service, user_id, "Хлеб", 1.0, 40.0).await;
+        let old = add_priced(&service, user_id, "Рис", 1.0, 90.0).await;
+        let older = add_priced(&service, user_id, "Мука", 1.0, 60.0).await;
+        for (item, days) in [(&fresh, 2), (&old, 15), (&older, 30)] {
+            backdate(&pool, item.id, now() - Duration::days(days)).await;
+        }
+
+        let order: Vec<Uuid> = service.get_checkin_items(user_id).await.unwrap().iter().map(|item| item.id).collect();
+        assert_eq!(order, vec![older.id, old.id, fresh.id]);
+
+        // Подтвержденный продукт перестает быть устаревшим
+        service.apply_checkin(user_id, vec![adjustment(&older, serde_json::json!({ "action": "confirm" }))]).await.unwrap();
+        let order: Vec<Uuid> = service.get_checkin_items(user_id).await.unwrap().iter().map(|item| item.id).collect();
+        assert_eq!(order, vec![old.id, fresh.id, older.id]);
+    }
+
+    #[sqlx::test]
+    async fn checkin_applies_each_adjustment_verb(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        let milk = add_priced(&service, user_id, "Молоко", 2.0, 50.0).await;
+        let cheese = add_priced(&service, user_id, "Сыр", 4.0, 25.0).await;
+        let apples = add_priced(&service, user_id, "Яблоки", 2.0, 10.0).await;
+        let bread = add_priced(&service, user_id, "Хлеб", 1.0, 40.0).await;
+        let yogurt = add_priced(&service, user_id, "Йогурт", 3.0, 20.0).await;
+
+        let summary = service
+            .apply_checkin(user_id, vec![
+                adjustment(&milk, serde_json::json!({ "action": "confirm" })),
+                adjustment(&cheese, serde_json::json!({ "action": "new_quantity", "quantity": 1.0 })),
+                adjustment(&apples, serde_json::json!({ "action": "new_quantity", "quantity": 5.0 })),
+                adjustment(&bread, serde_json::json!({ "action": "mark_finished" })),
+                adjustment(&yogurt, serde_json::json!({ "action": "mark_wasted", "reason": "Expired" })),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            (summary.confirmed, summary.adjusted, summary.finished, summary.wasted, summary.removed),
+            (1, 2, 1, 1, 2)
+        );
+        assert_eq!(summary.total_value_written_off, 60.0);
+        assert_eq!(summary.completed_at, now());
+
+        let milk = stored(&pool, milk.id).await;
+        assert_eq!((milk.quantity, milk.status, milk.consumed_value), (2.0, FridgeItemStatus::Active, 0.0));
+
+        // Уменьшение остатка — недостающее считается съеденным
+        let cheese = stored(&pool, cheese.id).await;
+        assert_eq!((cheese.quantity, cheese.total_price, cheese.consumed_value), (1.0, Some(25.0), 75.0));
+
+        // Увеличение — стоимость пересчитывается по цене за единицу
+        let apples = stored(&pool, apples.id).await;
+        assert_eq!((apples.quantity, apples.total_price, apples.consumed_value), (5.0, Some(50.0), 0.0));
+
+        let bread = stored(&pool, bread.id).await;
+        assert_eq!((bread.status, bread.consumed_value, bread.finished_at), (FridgeItemStatus::Consumed, 40.0, Some(now())));
+
+        let yogurt = stored(&pool, yogurt.id).await;
+        assert_eq!((yogurt.status, yogurt.wasted_value), (FridgeItemStatus::Wasted, 60.0));
+        let waste = service.get_waste_history(user_id, None, None).await.unwrap();
+        assert_eq!(waste.len(), 1);
+        assert_eq!((waste[0].original_item_id, waste[0].wasted_quantity, waste[0].wasted_value), (Some(yogurt.id), 3.0, Some(60.0)));
+        assert_eq!(waste[0].waste_reason, WasteReason::Expired);
+
+        // Съеденные и выброшенные продукты из проверки уходят
+        let remaining: Vec<Uuid> = service.get_checkin_items(user_id).await.unwrap().iter().map(|item| item.id).collect();
+        assert_eq!(remaining.len(), 3);
+        assert!(!remaining.contains(&bread.id) && !remaining.contains(&yogurt.id));
+
+        let preferences = PreferencesService::new(pool.clone()).for_user(user_id).await.unwrap();
+        assert_eq!(preferences.checkin.last_checkin_at, Some(now()));
+    }
+
+    #[sqlx::test]
+    async fn checkin_batch_is_all_or_nothing(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let stranger = crate::test_support::insert_user(&pool, "Олег").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        let milk = add_priced(&service, user_id, "Молоко", 2.0, 50.0).await;
+        let yogurt = add_priced(&service, user_id, "Йогурт", 3.0, 20.0).await;
+        let foreign = add_priced(&service, stranger, "Йогурт", 1.0, 70.0).await;
+
+        let valid = || vec![
+            adjustment(&milk, serde_json::json!({ "action": "new_quantity", "quantity": 1.0 })),
+            adjustment(&yogurt, serde_json::json!({ "action": "mark_wasted", "reason": "Spoiled" })),
+        ];
+        let failing = [
+            (adjustment(&foreign, serde_json::json!({ "action": "mark_finished" })), "not found"),
+            (adjustment(&milk, serde_json::json!({ "action": "confirm" })), "duplicate"),
+            (adjustment(&milk, serde_json::json!({ "action": "new_quantity", "quantity": -1.0 })), "invalid quantity"),
+        ];
+
+        for (last, case) in failing {
+            let mut batch = valid();
+            if case == "invalid quantity" {
+                batch.remove(0);
+            }
+            batch.push(last);
+
+            let result = service.apply_checkin(user_id, batch).await;
+            match case {
+                "not found" => assert!(matches!(result, Err(AppError::NotFound(_))), "{}", case),
+                _ => assert!(matches!(result, Err(AppError::BadRequest(_))), "{}", case),
+            }
+
+            let milk = stored(&pool, milk.id).await;
+            let yogurt = stored(&pool, yogurt.id).await;
+            assert_eq!((milk.quantity, milk.consumed_value), (2.0, 0.0), "{}", case);
+            assert_eq!(yogurt.status, FridgeItemStatus::Active, "{}", case);
+            assert!(service.get_waste_history(user_id, None, None).await.unwrap().is_empty(), "{}", case);
+        }
+
+        assert_eq!(stored(&pool, foreign.id).await.status, FridgeItemStatus::Active);
+        let preferences = PreferencesService::new(pool.clone()).for_user(user_id).await.unwrap();
+        assert!(preferences.checkin.last_checkin_at.is_none());
+
+        let empty = service.apply_checkin(user_id, Vec::new()).await;
+        assert!(matches!(empty, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn urgency_tiers_switch_at_exact_boundaries() {
+        use crate::models::fridge::ExpiryUrgency;
+
+        let second = Duration::seconds(1);
+        let cases = [
+            (now() - Duration::days(3), ExpiryUrgency::Expired),
+            (now() - second, ExpiryUrgency::Expired),
+            // Ровно в момент `now` продукт еще не просрочен
+            (now(), ExpiryUrgency::Today),
+            (now() + Duration::days(1) - second, ExpiryUrgency::Today),
+            (now() + Duration::days(1), ExpiryUrgency::Soon),
+            (now() + Duration::days(4) - second, ExpiryUrgency::Soon),
+            (now() + Duration::days(4), ExpiryUrgency::ThisWeek),
+            (now() + Duration::days(8) - second, ExpiryUrgency::ThisWeek),
+            (now() + Duration::days(8), ExpiryUrgency::Later),
+        ];
+
+        for (expiry, expected) in cases {
+            assert_eq!(ExpiryUrgency::for_expiry(expiry, now()), expected, "{}", expiry - now());
+            let item = item(FridgeCategory::Dairy, None, Some(expiry));
+            assert_eq!(item.expiry_urgency(now()), Some(expected));
+        }
+        assert_eq!(item(FridgeCategory::Dairy, None, None).expiry_urgency(now()), None);
+    }
+
+    async fn expiring(pool: &sqlx::PgPool, user_id: Uuid, name: &str, expiry: Option<DateTime<Utc>>) -> Uuid {
+        let item = crate::test_support::insert_fridge_item(pool, user_id, name, None, now() - Duration::days(5)).await;
+        sqlx::query("UPDATE fridge_items SET expiry_date = $2 WHERE id = $1")
+            .bind(item.id)
+            .bind(expiry)
+            .execute(pool)
+            .await
+            .unwrap();
+        item.id
+    }
+
+    #[sqlx::test]
+    async fn expiring_items_are_sorted_by_urgency_up_to_the_cutoff(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let stranger = crate::test_support::insert_user(&pool, "Олег").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+
+        let week_old = expiring(&pool, user_id, "Кефир", Some(now() - Duration::days(7))).await;
+        let at_cutoff = expiring(&pool, user_id, "Сыр", Some(now() + Duration::days(3))).await;
+        let hour_ago = expiring(&pool, user_id, "Йогурт", Some(now() - Duration::hours(1))).await;
+        let right_now = expiring(&pool, user_id, "Молоко", Some(now())).await;
+        let tonight = expiring(&pool, user_id, "Салат", Some(now() + Duration::hours(10))).await;
+        let in_two_days = expiring(&pool, user_id, "Творог", Some(now() + Duration::days(2))).await;
+        expiring(&pool, user_id, "Масло", Some(now() + Duration::days(3) + Duration::seconds(1))).await;
+        expiring(&pool, user_id, "Соль", None).await;
+        expiring(&pool, stranger, "Чужой кефир", Some(now())).await;
+        let eaten = expiring(&pool, user_id, "Съеденный хлеб", Some(now() - Duration::days(1))).await;
+        sqlx::query("UPDATE fridge_items SET status = 'consumed' WHERE id = $1").bind(eaten).execute(&pool).await.unwrap();
+
+        let ids = |items: Vec<FridgeItem>| items.into_iter().map(|item| item.id).collect::<Vec<_>>();
+
+        // Срок ровно на границе окна (now + 3 дня) включается, секундой позже — нет
+        let all = ids(service.get_expiring_items(user_id, Some(3), true).await.unwrap());
+        assert_eq!(all, vec![week_old, hour_ago, right_now, tonight, in_two_days, at_cutoff]);
+
+        // Без просроченных остается и продукт, истекающий ровно сейчас
+        let upcoming = ids(service.get_expiring_items(user_id, Some(3), false).await.unwrap());
+        assert_eq!(upcoming, vec![right_now, tonight, in_two_days, at_cutoff]);
+    }
+
+    #[sqlx::test]
+    async fn expiring_endpoint_annotates_tiers_and_includes_expired_by_default(pool: sqlx::PgPool) {
+        use axum::extract::{Extension, Query};
+        use crate::api::fridge::{get_expiring_items, FridgeQueryParams};
+        use crate::models::fridge::ExpiryUrgency;
+
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        for (name, expiry) in [
+            ("Неделя", now() + Duration::days(5)),
+            ("Просрочено", now() - Duration::minutes(1)),
+            ("Сегодня", now() + Duration::hours(23)),
+            ("Скоро", now() + Duration::days(1)),
+        ] {
+            expiring(&pool, user_id, name, Some(expiry)).await;
+        }
+
+        let call = |query: serde_json::Value| {
+            let pool = pool.clone();
+            async move {
+                let params: FridgeQueryParams = serde_json::from_value(query).unwrap();
+                get_expiring_items(
+                    Extension(pool.clone()),
+                    Extension(crate::db::DbPools::single(pool.clone())),
+                    Extension(crate::test_support::frozen_clock(now()) as SharedClock),
+                    crate::test_support::claims_for(&pool, user_id).await,
+                    Query(params),
+                )
+                .await
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|item| (item.name, item.urgency, item.is_expired))
+                .collect::<Vec<_>>()
+            }
+        };
+
+        let default = call(serde_json::json!({ "expiring_days": 7 })).await;
+        assert_eq!(default, vec![
+            ("Просрочено".to_string(), Some(ExpiryUrgency::Expired), true),
+            ("Сегодня".to_string(), Some(ExpiryUrgency::Today), false),
+            ("Скоро".to_string(), Some(ExpiryUrgency::Soon), false),
+            ("Неделя".to_string(), Some(ExpiryUrgency::ThisWeek), false),
+        ]);
+
+        let opted_out = call(serde_json::json!({ "expiring_days": 7, "include_expired": false })).await;
+        assert_eq!(opted_out.len(), 3);
+        assert!(opted_out.iter().all(|(_, urgency, _)| *urgency != Some(ExpiryUrgency::Expired)));
+
+        // По умолчанию окно — 3 дня
+        let narrow = call(serde_json::json!({})).await;
+        assert_eq!(narrow.iter().map(|(name, ..)| name.as_str()).collect::<Vec<_>>(), vec!["Просрочено", "Сегодня", "Скоро"]);
+    }
+
+    #[sqlx::test]
+    async fn lifecycle_timeline_adds_up_in_analytics_and_history(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        let reservations = FridgeReservationService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+
+        let salmon = add_priced(&service, user_id, "Лосось", 1.0, 1000.0).await;
+        reservations.consume_item(user_id, salmon.id, Some(0.4), None, false).await.unwrap();
+        reservations
+            .add_waste(CreateFoodWaste {
+                user_id,
+                original_item_id: Some(salmon.id),
+                name: salmon.name.clone(),
+                brand: None,
+                wasted_quantity: 0.6,
+                unit: salmon.unit.clone(),
+                category: salmon.category.clone(),
+                waste_reason: WasteReason::Spoiled,
+                wasted_value: None,
+                notes: None,
+            }, false)
+            .await
+            .unwrap();
+
+        let milk = add_priced(&service, user_id, "Молоко", 2.0, 80.0).await;
+        reservations.consume_item(user_id, milk.id, None, None, false).await.unwrap();
+
+        let cheese = add_priced(&service, user_id, "Сыр", 1.0, 500.0).await;
+
+        let apples = add_priced(&service, user_id, "Яблоки", 4.0, 25.0).await;
+        reservations.consume_item(user_id, apples.id, Some(1.0), None, false).await.unwrap();
+
+        // Удаленный без причины продукт в суммах не участвует
+        let bread = add_priced(&service, user_id, "Хлеб", 1.0, 60.0).await;
+        service.remove_item(bread.id, user_id).await.unwrap();
+
+        // Покупка до начала периода
+        let old = add_priced(&service, user_id, "Гречка", 1.0, 300.0).await;
+        sqlx::query("UPDATE fridge_items SET purchase_date = $2 WHERE id = $1")
+            .bind(old.id)
+            .bind(now() - Duration::days(60))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Отходы без исходного продукта считаются и покупкой, и отходами
+        reservations
+            .add_waste(CreateFoodWaste {
+                user_id,
+                original_item_id: None,
+                name: "Суп".to_string(),
+                brand: None,
+                wasted_quantity: 1.0,
+                unit: "шт".to_string(),
+                category: FridgeCategory::Other,
+                waste_reason: WasteReason::TooMuch,
+                wasted_value: Some(50.0),
+                notes: None,
+            }, false)
+            .await
+            .unwrap();
+
+        let analytics = service.get_expense_analytics(user_id, "month", AnalyticsOptions::default()).await.unwrap();
+        assert_eq!(analytics.total_consumed, 585.0);
+        assert_eq!(analytics.total_wasted, 650.0);
+        assert_eq!(analytics.active_value, 575.0);
+        assert_eq!(analytics.total_purchased, 1810.0);
+        assert_eq!(
+            analytics.total_purchased,
+            round_money(analytics.total_consumed + analytics.total_wasted + analytics.active_value)
+        );
+        // Доля отходов — от использованного, а не от всего купленного
+        assert_eq!(analytics.waste_percentage, 52.6);
+
+        let active: Vec<String> = service.get_user_items(user_id, None, None, None).await.unwrap().into_iter().map(|item| item.name).collect();
+        assert_eq!(active.len(), 3);
+        assert!(["Сыр", "Яблоки", "Гречка"].iter().all(|name| active.contains(&name.to_string())));
+
+        let history = service.get_history(user_id, None).await.unwrap();
+        let summary: Vec<(&str, FridgeItemStatus, f64, f64, f64)> = history
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status, entry.purchased_value, entry.consumed_value, entry.wasted_value))
+            .collect();
+        assert_eq!(summary.len(), 3);
+        for expected in [
+            ("Лосось", FridgeItemStatus::Wasted, 1000.0, 400.0, 600.0),
+            ("Молоко", FridgeItemStatus::Consumed, 160.0, 160.0, 0.0),
+            ("Хлеб", FridgeItemStatus::Removed, 0.0, 0.0, 0.0),
+        ] {
+            assert!(summary.contains(&expected), "{:?}", expected);
+        }
+        assert!(history.iter().all(|entry| entry.finished_at == Some(now())));
+        let salmon_entry = history.iter().find(|entry| entry.item_id == salmon.id).unwrap();
+        assert_eq!(salmon_entry.waste_reasons, vec![WasteReason::Spoiled]);
+
+        let wasted = service.get_history(user_id, Some(FridgeItemStatus::Wasted)).await.unwrap();
+        assert_eq!(wasted.iter().map(|entry| entry.item_id).collect::<Vec<_>>(), vec![salmon.id]);
+        let removed = service.get_items_by_status(user_id, FridgeItemStatus::Removed, None, None, None).await.unwrap();
+        assert_eq!(removed.iter().map(|item| item.id).collect::<Vec<_>>(), vec![bread.id]);
+        assert!(matches!(service.get_history(user_id, Some(FridgeItemStatus::Active)).await, Err(AppError::BadRequest(_))));
+
+        // Закончившийся продукт снова не списать
+        assert!(matches!(reservations.consume_item(user_id, milk.id, None, None, false).await, Err(AppError::BadRequest(_))));
+        assert_eq!(stored(&pool, cheese.id).await.status, FridgeItemStatus::Active);
+    }
+
+    /// Траты по категориям в порядке `FridgeCategory::ALL`
+    fn purchased_by_category(analytics: &ExpenseAnalytics) -> Vec<(FridgeCategory, f64)> {
+        FridgeCategory::ALL
+            .iter()
+            .filter_map(|category| {
+                let entry = analytics.category_breakdown.iter().find(|entry| entry.category == *category)?;
+                Some((category.clone(), entry.purchased))
+            })
+            .collect()
+    }
+
+    fn sorted_ids(candidates: &[RecategorizationCandidate]) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = candidates.iter().map(|candidate| candidate.item_id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[sqlx::test]
+    async fn applying_the_preview_moves_exactly_the_suggested_items(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let stranger = crate::test_support::insert_user(&pool, "Олег").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+
+        let milk = add_priced(&service, user_id, "Молоко", 1.0, 90.0).await;
+        let yogurt = add_priced(&service, user_id, "Йогурт", 1.0, 70.0).await;
+        let foil = add_priced(&service, user_id, "Фольга", 1.0, 150.0).await;
+        // История тоже попадает в предпросмотр
+        service.remove_item(yogurt.id, user_id).await.unwrap();
+        let strangers_milk = add_priced(&service, stranger, "Молоко", 1.0, 90.0).await;
+
+        let preview = service.preview_recategorization(user_id, FridgeCategory::Other).await.unwrap();
+        let mut expected = vec![milk.id, yogurt.id, foil.id];
+        expected.sort();
+        assert_eq!(sorted_ids(&preview), expected);
+        let suggested: Vec<(Uuid, FridgeCategory)> = preview
+            .iter()
+            .filter_map(|candidate| candidate.suggested_category.clone().map(|category| (candidate.item_id, category)))
+            .collect();
+        assert_eq!(suggested.len(), 2);
+        assert!(suggested.contains(&(milk.id, FridgeCategory::Dairy)));
+        assert!(suggested.contains(&(yogurt.id, FridgeCategory::Dairy)));
+        assert!(preview.iter().all(|candidate| candidate.suggested_category.is_some() == candidate.confidence.is_some()));
+
+        // Чужой продукт отменяет весь пакет; повтор продукта в пакете — ошибка
+        let mut with_stranger = suggested.clone();
+        with_stranger.push((strangers_milk.id, FridgeCategory::Dairy));
+        assert!(matches!(service.recategorize(user_id, with_stranger).await, Err(AppError::NotFound(_))));
+        let duplicated = vec![(milk.id, FridgeCategory::Dairy), (milk.id, FridgeCategory::Beverages)];
+        assert!(matches!(service.recategorize(user_id, duplicated).await, Err(AppError::BadRequest(_))));
+        assert_eq!(service.preview_recategorization(user_id, FridgeCategory::Other).await.unwrap().len(), 3);
+
+        // Применение ровно того, что предложил предпросмотр; уже верная категория пропускается
+        let mut changes = suggested.clone();
+        changes.push((foil.id, FridgeCategory::Other));
+        let applied = service.recategorize(user_id, changes).await.unwrap();
+        let applied_pairs: Vec<(Uuid, FridgeCategory)> = applied.iter().map(|change| (change.item_id, change.to.clone())).collect();
+        assert_eq!(applied_pairs, suggested);
+        assert!(applied.iter().all(|change| change.from == FridgeCategory::Other && change.changed_at == now()));
+
+        let journal: Vec<(Uuid, FridgeCategory, FridgeCategory)> = sqlx::query_as(
+            "SELECT item_id, from_category, to_category FROM fridge_category_changes WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(journal.len(), applied.len());
+        assert!(applied.iter().all(|change| journal.contains(&(change.item_id, change.from.clone(), change.to.clone()))));
+
+        // Повторный предпросмотр согласован с примененным: в Other остался только неподсказанный продукт
+        let remaining = service.preview_recategorization(user_id, FridgeCategory::Other).await.unwrap();
+        assert_eq!(sorted_ids(&remaining), vec![foil.id]);
+        let dairy = service.preview_recategorization(user_id, FridgeCategory::Dairy).await.unwrap();
+        let mut moved: Vec<Uuid> = suggested.iter().map(|(item_id, _)| *item_id).collect();
+        moved.sort();
+        assert_eq!(sorted_ids(&dairy), moved);
+        assert!(dairy.iter().all(|candidate| candidate.suggested_category.is_none()));
+        assert_eq!(service.get_item_by_id(strangers_milk.id, stranger).await.unwrap().category, FridgeCategory::Other);
+    }
+
+    #[sqlx::test]
+    async fn analytics_group_by_current_or_purchase_time_category(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+
+        // Куплены 20 дней назад в Other
+        let milk = add_priced(&service, user_id, "Молоко", 1.0, 100.0).await;
+        let juice = add_priced(&service, user_id, "Сок", 1.0, 40.0).await;
+
+        // Молоко перенесено дважды после покупки: Other → Beverages → Dairy
+        let ten_days_ago = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now() - Duration::days(10)));
+        ten_days_ago.recategorize(user_id, vec![(milk.id, FridgeCategory::Beverages)]).await.unwrap();
+        service.recategorize(user_id, vec![(milk.id, FridgeCategory::Dairy), (juice.id, FridgeCategory::Beverages)]).await.unwrap();
+
+        // Куплено уже после переносов — исторически категория та же, что сейчас
+        let cheese = add_priced(&service, user_id, "Сыр", 1.0, 300.0).await;
+        sqlx::query("UPDATE fridge_items SET category = 'dairy', purchase_date = $2 WHERE id = $1")
+            .bind(cheese.id)
+            .bind(now() - Duration::days(1))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let current = service.get_expense_analytics(user_id, "month", AnalyticsOptions::default()).await.unwrap();
+        assert_eq!(current.category_as_of, CategoryAsOf::Current);
+        assert_eq!(
+            purchased_by_category(&current),
+            vec![(FridgeCategory::Dairy, 400.0), (FridgeCategory::Beverages, 40.0)]
+        );
+
+        let historical = service
+            .get_expense_analytics(
+                user_id,
+                "month",
+                AnalyticsOptions { category_as_of: CategoryAsOf::Historical, ..AnalyticsOptions::default() },
+            )
+            .await
+            .unwrap();
+        assert_eq!(historical.category_as_of, CategoryAsOf::Historical);
+        assert_eq!(
+            purchased_by_category(&historical),
+            vec![(FridgeCategory::Dairy, 300.0), (FridgeCategory::Other, 140.0)]
+        );
+        // Группировка меняет только распределение по категориям, не итог
+        assert_eq!(current.total_purchased, historical.total_purchased);
+    }
+
+    fn purchase(value: f32, days_ago: i64) -> FridgeItem {
+        FridgeItem {
+            id: Uuid::new_v4(),
+            purchase_date: now() - Duration::days(days_ago),
+            ..priced(1.0, "шт", None, Some(value))
+        }
+    }
+
+    fn period() -> (DateTime<Utc>, DateTime<Utc>) {
+        (now() - Duration::days(30), now())
+    }
+
+    #[test]
+    fn outliers_need_five_priced_purchases() {
+        let (start, end) = period();
+        // Продукты без цены не входят в выборку медианы
+        let mut items: Vec<FridgeItem> = [100.0, 100.0, 100.0, 5000.0].into_iter().map(|value| purchase(value, 1)).collect();
+        items.push(purchase(0.0, 1));
+
+        let (median, outliers) = purchase_outliers(&items, start, end, 10.0);
+        assert_eq!(median, None);
+        assert!(outliers.is_empty());
+
+        items.push(purchase(100.0, 1));
+        let (median, outliers) = purchase_outliers(&items, start, end, 10.0);
+        assert_eq!(median, Some(100.0));
+        assert_eq!(outliers.len(), 1);
+    }
+
+    #[test]
+    fn median_of_even_sample_averages_the_middle_pair() {
+        let (start, end) = period();
+        let items: Vec<FridgeItem> = [6000.0, 100.0, 400.0, 200.0, 5000.0, 300.0].into_iter().map(|value| purchase(value, 1)).collect();
+
+        let (median, outliers) = purchase_outliers(&items, start, end, 10.0);
+
+        assert_eq!(median, Some(350.0));
+        let found: Vec<(f64, f64)> = outliers.iter().map(|outlier| (outlier.purchased_value, outlier.times_median)).collect();
+        assert_eq!(found, vec![(6000.0, 17.1), (5000.0, 14.3)]);
+    }
+
+    #[test]
+    fn exactly_the_multiple_counts_as_an_outlier() {
+        let (start, end) = period();
+        let mut items: Vec<FridgeItem> = [100.0, 100.0, 100.0, 100.0].into_iter().map(|value| purchase(value, 1)).collect();
+        let at_multiple = purchase(1000.0, 1);
+        let below_multiple = purchase(999.0, 1);
+        items.extend([at_multiple.clone(), below_multiple]);
+
+        let (_, outliers) = purchase_outliers(&items, start, end, 10.0);
+
+        assert_eq!(outliers.iter().map(|outlier| outlier.item_id).collect::<Vec<_>>(), vec![at_multiple.id]);
+        assert_eq!(outliers[0].times_median, 10.0);
+    }
+
+    #[test]
+    fn older_purchases_count_for_the_median_but_are_not_flagged() {
+        let (start, end) = period();
+        let mut items: Vec<FridgeItem> = [100.0, 100.0, 100.0, 100.0].into_iter().map(|value| purchase(value, 60)).collect();
+        let old_outlier = purchase(9000.0, 60);
+        let recent_outlier = purchase(9000.0, 1);
+        items.extend([old_outlier, recent_outlier.clone()]);
+
+        let (median, outliers) = purchase_outliers(&items, start, end, 10.0);
+
+        assert_eq!(median, Some(100.0));
+        assert_eq!(outliers.iter().map(|outlier| outlier.item_id).collect::<Vec<_>>(), vec![recent_outlier.id]);
+    }
+
+    #[test]
+    fn meaningless_multiple_disables_outliers() {
+        let (start, end) = period();
+        let items: Vec<FridgeItem> = [100.0, 100.0, 100.0, 100.0, 5000.0].into_iter().map(|value| purchase(value, 1)).collect();
+
+        for multiple in [1.0, 0.5, f64::NAN, f64::INFINITY] {
+            let (median, outliers) = purchase_outliers(&items, start, end, multiple);
+            assert!(median.is_none() && outliers.is_empty(), "multiple {}", multiple);
+        }
+    }
+
+    #[sqlx::test]
+    async fn outliers_are_left_out_of_totals_unless_included(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        for name in ["Хлеб", "Рис", "Мука", "Соль"] {
+            add_priced(&service, user_id, name, 1.0, 100.0).await;
+        }
+        // Опечатка: 45 000 вместо 450
+        let typo = add_priced(&service, user_id, "Сыр", 1.0, 45_000.0).await;
+        let (start, end) = (now() - Duration::days(30), now());
+
+        let excluded = service
+            .get_expense_analytics_between(user_id, "month", start, end, AnalyticsOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(excluded.median_purchase, Some(100.0));
+        assert_eq!(excluded.outliers.iter().map(|outlier| outlier.item_id).collect::<Vec<_>>(), vec![typo.id]);
+        assert!(!excluded.outliers_included);
+        assert_eq!(excluded.total_purchased, 400.0);
+        assert_eq!(excluded.active_value, 400.0);
+
+        let included = service
+            .get_expense_analytics_between(
+                user_id,
+                "month",
+                start,
+                end,
+                AnalyticsOptions { include_outliers: true, ..AnalyticsOptions::default() },
+            )
+            .await
+            .unwrap();
+        assert!(included.outliers_included);
+        assert_eq!(included.outliers.len(), 1);
+        assert_eq!(included.total_purchased, 45_400.0);
+    }
+
+    #[test]
+    fn store_names_are_cleaned_and_keyed_case_and_yo_insensitively() {
+        assert_eq!(clean_store(Some("  Лента   Гипер ".to_string())).as_deref(), Some("Лента Гипер"));
+        assert_eq!(clean_store(Some(" \t ".to_string())), None);
+        assert_eq!(clean_store(None), None);
+
+        assert_eq!(store_key(" ПЯТЁРОЧКА ").as_deref(), Some("пятерочка"));
+        assert_eq!(store_key("Пятерочка"), store_key("пятёрочка"));
+        assert_eq!(store_key("Лента  Гипер"), store_key("лента гипер"));
+        assert_ne!(store_key("Лента"), store_key("Лента Гипер"));
+        assert_eq!(store_key("   "), None);
+    }
+
+    #[test]
+    fn store_breakdown_groups_spellings_and_keeps_waste_with_the_purchase() {
+        let (start, end) = period();
+        let at_store = |value: f32, days_ago: i64, store: Option<&str>| FridgeItem { store: store.map(str::to_string), ..purchase(value, days_ago) };
+
+        let mut eaten = at_store(100.0, 10, Some("Лента"));
+        eaten.write_off(None, FridgeItemStatus::Consumed, now());
+        let mut half_wasted = at_store(60.0, 5, Some("  лента "));
+        half_wasted.write_off(Some(0.5), FridgeItemStatus::Wasted, now());
+        let kept = at_store(200.0, 3, Some("Пятёрочка"));
+        let mut wasted = at_store(40.0, 2, Some("ПЯТЕРОЧКА"));
+        wasted.write_off(None, FridgeItemStatus::Wasted, now());
+        let no_store = at_store(15.0, 1, None);
+        let untracked = FoodWaste {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            original_item_id: None,
+            name: "Хлеб".to_string(),
+            brand: None,
+            wasted_quantity: 1.0,
+            unit: "шт".to_string(),
+            category: FridgeCategory::Grains,
+            waste_reason: WasteReason::Spoiled,
+            wasted_value: Some(10.0),
+            waste_date: now() - Duration::days(1),
+            notes: None,
+            created_at: now() - Duration::days(1),
+        };
+
+        let analytics = compute_expense_analytics(&[eaten, half_wasted, kept, wasted, no_store], &[untracked], start, end);
+        let breakdown: Vec<(&str, f64, f64, f64, usize)> = analytics
+            .store_breakdown
+            .iter()
+            .map(|store| (store.store.as_str(), store.purchased, store.wasted, store.waste_percentage, store.item_count))
+            .collect();
+
+        // Больше всего потрачено — выше; название — как в последней покупке; без магазина и
+        // отходы без продукта — в "unspecified"
+        assert_eq!(
+            breakdown,
+            vec![
+                ("ПЯТЕРОЧКА", 240.0, 40.0, 100.0, 2),
+                ("лента", 160.0, 30.0, percent_of(30.0, 130.0), 2),
+                (UNSPECIFIED_STORE, 25.0, 10.0, 100.0, 2),
+            ]
+        );
+        let purchased: f64 = analytics.store_breakdown.iter().map(|store| store.purchased).sum();
+        let wasted: f64 = analytics.store_breakdown.iter().map(|store| store.wasted).sum();
+        assert_eq!((purchased, wasted), (analytics.total_purchased, analytics.total_wasted));
+    }
+
+    fn patch(json: serde_json::Value) -> UpdateFridgeItem {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn patch_clears_null_fields_and_keeps_missing_ones(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Нина").await;
+        let service = FridgeService::new(pool.clone()).with_clock(crate::test_support::frozen_clock(now()));
+        let item = add_priced(&service, user_id, "Кефир", 2.0, 90.0).await;
+        sqlx::query("UPDATE fridge_items SET brand = 'Простоквашино', expiry_date = $2, notes = 'на блины', location = 'дверца' WHERE id = $1")
+            .bind(item.id)
+            .bind(now() + Duration::days(5))
+            .execute(&pool)
+            .await
+            .unwrap();
+        let accept = |_: &FridgeItem| Ok(());
+
+        // Пустое изменение ничего не трогает
+        let unchanged = service.patch_item(item.id, user_id, patch(serde_json::json!({})), accept).await.unwrap();
+        assert_eq!(
+            (unchanged.brand.as_deref(), unchanged.expiry_date, unchanged.notes.as_deref(), unchanged.location.as_deref()),
+            (Some("Простоквашино"), Some(now() + Duration::days(5)), Some("на блины"), Some("дверца")),
+        );
+
+        let cleared = service
+            .patch_item(item.id, user_id, patch(serde_json::json!({ "expiry_date": null, "notes": null })), accept)
+            .await
+            .unwrap();
+        assert_eq!((cleared.expiry_date, cleared.notes.as_deref()), (None, None));
+        assert_eq!((cleared.location.as_deref(), cleared.brand.as_deref()), (Some("дверца"), Some("Простоквашино")));
+
+        let cleared = service
+            .patch_item(item.id, user_id, patch(serde_json::json!({ "location": null, "brand": "" })), accept)
+            .await
+            .unwrap();
+        assert_eq!((cleared.location.as_deref(), cleared.brand.as_deref()), (None, None));
+        let saved = stored(&pool, item.id).await;
+        assert_eq!((saved.expiry_date, saved.notes, saved.location, saved.brand), (None, None, None, None));
+        assert_eq!((saved.name.as_str(), saved.quantity, saved.total_price), ("Кефир", 2.0, Some(180.0)));
+
+        // `"name": null` отклоняет обработчик; сам сервис название не стирает
+        let named = service.patch_item(item.id, user_id, patch(serde_json::json!({ "name": null })), accept).await.unwrap();
+        assert_eq!(named.name, "Кефир");
+
+        // Отказ проверки откатывает изменение целиком
+        let negative = patch(serde_json::json!({ "quantity": -1.0, "notes": "не сохранится" }));
+        let rejected = service.patch_item(item.id, user_id, negative, |item| {
+            assert_eq!(item.quantity, -1.0);
+            Err(AppError::BadRequest("Quantity must be positive".to_string()))
+        });
+        assert!(matches!(rejected.await, Err(AppError::BadRequest(_))));
+        let saved = stored(&pool, item.id).await;
+        assert_eq!((saved.quantity, saved.notes), (2.0, None));
+    }
+
+    #[sqlx::test]
+    async fn patch_endpoint_rejects_a_null_name_and_a_negative_quantity(pool: sqlx::PgPool) {
+        use axum::http::{Method, StatusCode};
+        use crate::test_support::{access_token, insert_fridge_item, insert_user, request, send, test_router};
+
+        let user_id = insert_user(&pool, "Нина").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+        let item = insert_fridge_item(&pool, user_id, "Кефир", Some("на блины"), Utc::now() - Duration::days(1)).await;
+        let uri = format!("/api/v1/fridge/{}", item.id);
+
+        let (status, body) = send(&router, request(Method::PATCH, &uri, Some(&token), Some(serde_json::json!({ "name": null })))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert!(body.to_string().contains("name must not be null"), "{}", body);
+
+        let negative = serde_json::json!({ "quantity": -2.0, "notes": null });
+        let (status, body) = send(&router, request(Method::PATCH, &uri, Some(&token), Some(negative))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(body["error"]["details"], "Validation error: quantity: Quantity must be positive");
+
+        let saved = stored(&pool, item.id).await;
+        assert_eq!((saved.name.as_str(), saved.quantity, saved.notes.as_deref()), ("Кефир", 1.0, Some("на блины")));
+
+        let (status, body) = send(&router, request(Method::PATCH, &uri, Some(&token), Some(serde_json::json!({ "notes": null })))).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["notes"], serde_json::Value::Null);
+        assert_eq!(body["name"], "Кефир");
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        fridge::{FridgeItem, NameSuggestion, NameSuggestionSource, StoreSuggestion},
+        presets::{FoodPresets, ProductPreset},
+    },
+    services::fridge::{clean_store, store_key, FridgeService},
+    utils::{errors::AppError, ingredient_matcher::normalize_ingredient},
+};
+
+const MAX_SUGGESTIONS: usize = 10;
+/// Короче — подсказок слишком много, чтобы они помогали
+const MIN_QUERY_CHARS: usize = 2;
+/// Сколько пользователей держится в кэше названий; дольше всех не печатавшие вытесняются
+const CACHE_CAPACITY: usize = 1000;
+
+/// Названия, которые пользователь уже вносил, — по пользователю, с вытеснением давно не использованных.
+/// Сбрасывается при записи в холодильник (`invalidate`), чтобы не обходить продукты на каждое нажатие клавиши.
+static NAME_CACHE: Lazy<Mutex<NameCache>> = Lazy::new(|| Mutex::new(NameCache::default()));
+
+/// Продукт из личной истории, сгруппированный по нормализованному названию
+#[derive(Debug, Clone)]
+struct PersonalName {
+    normalized: String,
+    uses: u32,
+    /// Самая свежая запись — из нее берутся название, единица, категория и остальное
+    latest: FridgeItem,
+    /// Бренд, с которым продукт вносился чаще всего
+    usual_brand: Option<String>,
+    last_price_per_unit: Option<f32>,
+}
+
+#[derive(Default)]
+struct NameCache {
+    entries: HashMap<Uuid, CachedNames>,
+    clock: u64,
+    /// Растет при каждом сбросе: список, построенный до сброса, в кэш уже не попадает
+    generation: u64,
+}
+
+struct CachedNames {
+    names: Arc<Vec<PersonalName>>,
+    last_access: u64,
+}
+
+impl NameCache {
+    fn get(&mut self, user_id: Uuid) -> Option<Arc<Vec<PersonalName>>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(&user_id).map(|entry| {
+            entry.last_access = clock;
+            entry.names.clone()
+        })
+    }
+
+    fn insert(&mut self, user_id: Uuid, names: Arc<Vec<PersonalName>>) {
+        if self.entries.len() >= CACHE_CAPACITY && !self.entries.contains_key(&user_id) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(id, _)| *id);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(user_id, CachedNames { names, last_access: self.clock });
+    }
+}
+
+/// Сбрасывает кэш названий пользователя; вызывается после записи в его холодильник
+pub fn invalidate(user_id: Uuid) {
+    let mut cache = NAME_CACHE.lock().unwrap();
+    cache.entries.remove(&user_id);
+    cache.generation += 1;
+}
+
+/// Подсказки названий при вводе: сначала продукты из истории пользователя (частые и недавние выше),
+/// затем каталог пресетов. Совпадение — по началу названия или любого его слова,
+/// без учета регистра и с ё = е.
+pub async fn suggest_names(fridge_service: &FridgeService, user_id: Uuid, query: &str) -> Result<Vec<NameSuggestion>, AppError> {
+    let query = normalize_ingredient(query);
+    if query.chars().count() < MIN_QUERY_CHARS {
+        return Ok(Vec::new());
+    }
+
+    let history = personal_names(fridge_service, user_id).await?;
+    let mut personal: Vec<(MatchKind, &PersonalName)> = history
+        .iter()
+        .filter_map(|name| match_kind(&name.normalized, &query).map(|kind| (kind, name)))
+        .collect();
+    personal.sort_by(|(kind_a, a), (kind_b, b)| {
+        kind_a
+            .cmp(kind_b)
+            .then_with(|| b.uses.cmp(&a.uses))
+            .then_with(|| b.latest.created_at.cmp(&a.latest.created_at))
+    });
+
+    let presets = FoodPresets::get_product_presets();
+    let mut catalog: Vec<(MatchKind, String, &ProductPreset)> = presets
+        .iter()
+        .filter_map(|preset| {
+            let normalized = normalize_ingredient(&preset.name);
+            match_kind(&normalized, &query).map(|kind| (kind, normalized, preset))
+        })
+        .collect();
+    catalog.sort_by(|(kind_a, _, a), (kind_b, _, b)| kind_a.cmp(kind_b).then_with(|| a.name.cmp(&b.name)));
+
+    let mut seen: Vec<&str> = Vec::new();
+    let mut suggestions = Vec::new();
+    let candidates = personal
+        .iter()
+        .map(|(_, name)| (name.normalized.as_str(), Candidate::Personal(name)))
+        .chain(catalog.iter().map(|(_, normalized, preset)| (normalized.as_str(), Candidate::Preset(preset))));
+
+    for (normalized, candidate) in candidates {
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+        // Личная запись идет раньше и вытесняет одноименный пресет
+        if seen.contains(&normalized) {
+            continue;
+        }
+        seen.push(normalized);
+        suggestions.push(candidate.into_suggestion());
+    }
+
+    Ok(suggestions)
+}
+
+/// Магазины, которые пользователь уже указывал: недавние выше, совпадение — по началу
+/// названия или любого его слова. Написание — как в последней покупке.
+pub async fn suggest_stores(fridge_service: &FridgeService, user_id: Uuid, query: &str) -> Result<Vec<StoreSuggestion>, AppError> {
+    let query = store_key(query).unwrap_or_default();
+    let mut stores: HashMap<String, StoreSuggestion> = HashMap::new();
+
+    for item in fridge_service.all_user_items(user_id).await? {
+        let Some(store) = clean_store(item.store) else {
+            continue;
+        };
+        let Some(key) = store_key(&store) else {
+            continue;
+        };
+        if !query.is_empty() && match_kind(&key, &query).is_none() {
+            continue;
+        }
+
+        let used_at = item.purchase_date;
+        let suggestion = stores.entry(key).or_insert(StoreSuggestion { store: store.clone(), times_used: 0, last_used_at: used_at });
+        suggestion.times_used += 1;
+        if used_at >= suggestion.last_used_at {
+            suggestion.store = store;
+            suggestion.last_used_at = used_at;
+        }
+    }
+
+    let mut suggestions: Vec<StoreSuggestion> = stores.into_values().collect();
+    suggestions.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at).then_with(|| b.times_used.cmp(&a.times_used)));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    Ok(suggestions)
+}
+
+/// Насколько хорошо название совпало с вводом; меньше — выше в списке
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    /// «мол» → «молоко 3,2%»
+    NamePrefix,
+    /// «мол» → «кокосовое молоко»
+    WordPrefix,
+}
+
+fn match_kind(normalized_name: &str, normalized_query: &str) -> Option<MatchKind> {
+    if normalized_name.starts_with(normalized_query) {
+        Some(MatchKind::NamePrefix)
+    } else if normalized_name.contains(&format!(" {}", normalized_query)) {
+        Some(MatchKind::WordPrefix)
+    } else {
+        None
+    }
+}
+
+async fn personal_names(fridge_service: &FridgeService, user_id: Uuid) -> Result<Arc<Vec<PersonalName>>, AppError> {
+    let generation = {
+        let mut cache = NAME_CACHE.lock().unwrap();
+        if let Some(names) = cache.get(user_id) {
+            return Ok(names);
+        }
+        cache.generation
+    };
+
+    let names = Arc::new(group_names(fridge_service.all_user_items(user_id).await?));
+    // Пока продукты читались, холодильник могли изменить — тогда список не кэшируется
+    let mut cache = NAME_CACHE.lock().unwrap();
+    if cache.generation == generation {
+        cache.insert(user_id, names.clone());
+    }
+    Ok(names)
+}
+
+/// Группирует все продукты пользователя (и текущие, и из истории) по нормализованному названию
+fn group_names(mut items: Vec<FridgeItem>) -> Vec<PersonalName> {
+    items.sort_by_key(|item| item.created_at);
+
+    let mut names: HashMap<String, PersonalName> = HashMap::new();
+    let mut brand_counts: HashMap<String, HashMap<String, (u32, DateTime<Utc>)>> = HashMap::new();
+
+    for item in items {
+        let normalized = normalize_ingredient(&item.name);
+        if normalized.is_empty() {
+            continue;
+        }
+
+        if let Some(brand) = item.brand.as_deref().map(str::trim).filter(|brand| !brand.is_empty()) {
+            let count = brand_counts
+                .entry(normalized.clone())
+                .or_default()
+                .entry(brand.to_string())
+                .or_insert((0, item.created_at));
+            count.0 += 1;
+            count.1 = item.created_at;
+        }
+
+        let price = item.price_per_unit;
+        match names.get_mut(&normalized) {
+            Some(name) => {
+                name.uses += 1;
+                name.last_price_per_unit = price.or(name.last_price_per_unit);
+                name.latest = item;
+            }
+            None => {
+                names.insert(
+                    normalized.clone(),
+                    PersonalName {
+                        normalized,
+                        uses: 1,
+                        latest: item,
+                        usual_brand: None,
+                        last_price_per_unit: price,
+                    },
+                );
+            }
+        }
+    }
+
+    names
+        .into_values()
+        .map(|mut name| {
+            // Самый частый бренд; при равенстве — тот, что покупался позже
+            name.usual_brand = brand_counts.get(&name.normalized).and_then(|brands| {
+                brands
+                    .iter()
+                    .max_by_key(|(_, (count, last_seen))| (*count, *last_seen))
+                    .map(|(brand, _)| brand.clone())
+            });
+            name
+        })
+        .collect()
+}
+
+enum Candidate<'a> {
+    Personal(&'a PersonalName),
+    Preset(&'a ProductPreset),
+}
+
+impl Candidate<'_> {
+    fn into_suggestion(self) -> NameSuggestion {
+        match self {
+            Candidate::Personal(name) => {
+                let item = &name.latest;
+                let shelf_life_days = item
+                    .expiry_date
+                    .map(|expiry| (expiry - item.purchase_date).num_days() as i32)
+                    .filter(|days| *days > 0);
+
+                NameSuggestion {
+                    name: item.name.trim().to_string(),
+                    source: NameSuggestionSource::Personal,
+                    brand: name.usual_brand.clone(),
+                    quantity: Some(item.quantity).filter(|quantity| *quantity > 0.0),
+                    unit: Some(item.unit.clone()),
+                    category: item.category.clone(),
+                    location: item.location.clone(),
+                    price_per_unit: name.last_price_per_unit,
+                    shelf_life_days,
+                    contains_allergens: item.contains_allergens.clone(),
+                    contains_intolerances: item.contains_intolerances.clone(),
+                    suitable_for_diets: item.suitable_for_diets.clone(),
+                    times_used: Some(name.uses),
+                    last_used_at: Some(item.created_at),
+                }
+            }
+            Candidate::Preset(preset) => NameSuggestion {
+                name: preset.name.clone(),
+                source: NameSuggestionSource::Preset,
+                brand: None,
+                quantity: None,
+                unit: None,
+                category: preset.category.clone(),
+                location: Some(preset.storage_location.clone()),
+                price_per_unit: None,
+                shelf_life_days: preset.typical_shelf_life_days,
+                contains_allergens: preset.common_allergens.clone(),
+                contains_intolerances: preset.common_intolerances.clone(),
+                suitable_for_diets: preset.suitable_diets.clone(),
+                times_used: None,
+                last_used_at: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::fridge::{CreateFridgeItem, FridgeCategory},
+        services::clock::{Clock, SandboxClock, SandboxClockMode},
+        test_support::{frozen_clock, insert_user},
+    };
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap()
+    }
+
+    fn create(user_id: Uuid, name: &str, brand: Option<&str>, unit: &str, price_per_unit: Option<f32>, purchase_date: DateTime<Utc>) -> CreateFridgeItem {
+        CreateFridgeItem {
+            user_id,
+            name: name.to_string(),
+            brand: brand.map(str::to_string),
+            quantity: 1.0,
+            unit: unit.to_string(),
+            category: FridgeCategory::Dairy,
+            price_per_unit,
+            total_price: None,
+            expiry_date: Some(purchase_date + Duration::days(7)),
+            purchase_date,
+            notes: None,
+            location: Some("fridge".to_string()),
+            store: None,
+            contains_allergens: Vec::new(),
+            contains_intolerances: Vec::new(),
+            suitable_for_diets: Vec::new(),
+            ingredients: None,
+            nutritional_info: None,
+        }
+    }
+
+    /// Добавляет продукты по одному в день, чтобы порядок записей был однозначным
+    async fn add(service: &FridgeService, clock: &SandboxClock, user_id: Uuid, items: &[(&str, Option<&str>, &str, Option<f32>)]) {
+        for (name, brand, unit, price) in items {
+            let at = clock.now() + Duration::days(1);
+            clock.set_mode(SandboxClockMode::Frozen { at });
+            service.add_item(create(user_id, name, *brand, unit, *price, at)).await.unwrap();
+        }
+    }
+
+    async fn names(service: &FridgeService, user_id: Uuid, query: &str) -> Vec<(String, NameSuggestionSource)> {
+        suggest_names(service, user_id, query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|suggestion| (suggestion.name, suggestion.source))
+            .collect()
+    }
+
+    fn is_cached(user_id: Uuid) -> bool {
+        NAME_CACHE.lock().unwrap().entries.contains_key(&user_id)
+    }
+
+    #[test]
+    fn query_matches_the_start_of_the_name_or_of_a_word() {
+        let cases = [
+            ("молоко 3 2", "мол", Some(MatchKind::NamePrefix)),
+            ("кокосовое молоко", "мол", Some(MatchKind::WordPrefix)),
+            ("кокосовое молоко", "молоко", Some(MatchKind::WordPrefix)),
+            ("простокваша", "ква", None),
+            ("сыр", "сыр твердый", None),
+        ];
+        for (name, query, expected) in cases {
+            assert_eq!(match_kind(name, query), expected, "{} / {}", name, query);
+        }
+    }
+
+    #[sqlx::test]
+    async fn own_names_rank_above_presets_by_match_uses_and_recency(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let clock = frozen_clock(start());
+        let service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+        add(&service, &clock, user_id, &[
+            ("Молоко 3,2%", Some("Простоквашино"), "л", Some(85.0)),
+            ("Кокосовое молоко", None, "мл", None),
+            ("Молоко 3,2%", Some("Домик в деревне"), "л", Some(95.0)),
+            ("Кокосовое молоко", None, "мл", None),
+            ("Кокосовое молоко", None, "мл", None),
+            ("Молоко 3,2%", Some("Простоквашино"), "л", None),
+            ("Молоко овсяное", None, "л", Some(150.0)),
+            ("МОЛОКО КОРОВЬЕ", Some("Ферма"), "л", Some(120.0)),
+            ("Мёд липовый", None, "г", None),
+        ])
+        .await;
+
+        // Свое «молоко коровье» вытесняет одноименный пресет; частое выше редкого, свежее — выше старого,
+        // совпадение по началу слова — после совпадения по началу названия, даже если вносилось чаще
+        let expected = vec![
+            ("Молоко 3,2%".to_string(), NameSuggestionSource::Personal),
+            ("МОЛОКО КОРОВЬЕ".to_string(), NameSuggestionSource::Personal),
+            ("Молоко овсяное".to_string(), NameSuggestionSource::Personal),
+            ("Кокосовое молоко".to_string(), NameSuggestionSource::Personal),
+        ];
+        assert_eq!(names(&service, user_id, "мол").await, expected);
+        assert_eq!(names(&service, user_id, "  Мол").await, expected);
+
+        // ё = е в обе стороны
+        assert_eq!(names(&service, user_id, "мед").await, vec![("Мёд липовый".to_string(), NameSuggestionSource::Personal)]);
+        assert_eq!(names(&service, user_id, "МЁД").await, vec![("Мёд липовый".to_string(), NameSuggestionSource::Personal)]);
+
+        assert!(names(&service, user_id, "м").await.is_empty());
+        assert!(names(&service, user_id, "").await.is_empty());
+
+        // Форма заполняется одним нажатием: частый бренд, последняя цена и единица
+        let milk = &suggest_names(&service, user_id, "молоко 3").await.unwrap()[0];
+        assert_eq!(milk.brand.as_deref(), Some("Простоквашино"));
+        assert_eq!((milk.unit.as_deref(), milk.price_per_unit, milk.quantity), (Some("л"), Some(95.0), Some(1.0)));
+        assert_eq!((milk.times_used, milk.shelf_life_days), (Some(3), Some(7)));
+        assert_eq!(milk.last_used_at, Some(start() + Duration::days(6)));
+        assert_eq!((milk.category.clone(), milk.location.as_deref()), (FridgeCategory::Dairy, Some("fridge")));
+
+        // Чужие продукты не подсказываются — только каталог
+        let other = insert_user(&pool, "Борис").await;
+        let presets = FoodPresets::get_product_presets();
+        let preset = presets.iter().find(|preset| preset.name == "Молоко коровье").unwrap();
+        let suggestions = suggest_names(&service, other, "мол").await.unwrap();
+        assert_eq!(suggestions.len(), 1);
+        let suggestion = &suggestions[0];
+        assert_eq!((suggestion.name.as_str(), suggestion.source), ("Молоко коровье", NameSuggestionSource::Preset));
+        assert_eq!(suggestion.shelf_life_days, preset.typical_shelf_life_days);
+        assert_eq!(suggestion.location.as_deref(), Some(preset.storage_location.as_str()));
+        assert_eq!((suggestion.times_used, suggestion.brand.as_deref()), (None, None));
+    }
+
+    #[sqlx::test]
+    async fn suggestions_are_capped_at_ten(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let clock = frozen_clock(start());
+        let service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+        let names_added: Vec<String> = (1..=12).map(|n| format!("Сыр {}", n)).collect();
+        let items: Vec<(&str, Option<&str>, &str, Option<f32>)> = names_added.iter().map(|name| (name.as_str(), None, "г", None)).collect();
+        add(&service, &clock, user_id, &items).await;
+
+        let suggestions = names(&service, user_id, "сыр").await;
+        assert_eq!(suggestions.len(), MAX_SUGGESTIONS);
+        // Все одинаково часты — свежие выше; пресеты «Сыр твердый» и «Сыр фета» не поместились
+        assert_eq!(suggestions[0].0, "Сыр 12");
+        assert_eq!(suggestions[9].0, "Сыр 3");
+        assert!(suggestions.iter().all(|(_, source)| *source == NameSuggestionSource::Personal));
+    }
+
+    #[sqlx::test]
+    async fn adding_an_item_refreshes_the_cached_names(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let clock = frozen_clock(start());
+        let service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+        add(&service, &clock, user_id, &[("Творог 5%", None, "г", None)]).await;
+
+        // Список кэшируется, только если за время чтения никто не сбрасывал кэш; параллельные тесты могут
+        for _ in 0..20 {
+            names(&service, user_id, "тво").await;
+            if is_cached(user_id) {
+                break;
+            }
+        }
+        assert!(is_cached(user_id));
+
+        // Запись мимо сервиса кэш не сбрасывает: подсказки берутся из памяти
+        sqlx::query("INSERT INTO fridge_items (user_id, name, quantity, unit, category, purchase_date, created_at) VALUES ($1, 'Твороженный сырок', 1, 'шт', 'dairy', $2, $2)")
+            .bind(user_id)
+            .bind(start())
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names(&service, user_id, "тво").await, vec![("Творог 5%".to_string(), NameSuggestionSource::Personal)]);
+
+        add(&service, &clock, user_id, &[("Творог 9%", None, "г", None)]).await;
+        assert!(!is_cached(user_id));
+        assert_eq!(names(&service, user_id, "тво").await, vec![
+            ("Творог 9%".to_string(), NameSuggestionSource::Personal),
+            ("Творог 5%".to_string(), NameSuggestionSource::Personal),
+            ("Твороженный сырок".to_string(), NameSuggestionSource::Personal),
+        ]);
+    }
+
+    #[test]
+    fn least_recently_used_user_is_evicted_beyond_capacity() {
+        let mut cache = NameCache::default();
+        let users: Vec<Uuid> = (0..CACHE_CAPACITY).map(|_| Uuid::new_v4()).collect();
+        for user_id in &users {
+            cache.insert(*user_id, Arc::new(Vec::new()));
+        }
+        // Первый пользователь снова печатает — вытесняется второй
+        assert!(cache.get(users[0]).is_some());
+        let newcomer = Uuid::new_v4();
+        cache.insert(newcomer, Arc::new(Vec::new()));
+
+        assert_eq!(cache.entries.len(), CACHE_CAPACITY);
+        assert!(cache.get(users[0]).is_some());
+        assert!(cache.get(users[1]).is_none());
+        assert!(cache.get(newcomer).is_some());
+    }
+
+    #[sqlx::test]
+    async fn stores_are_suggested_most_recent_first_in_the_latest_spelling(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let clock = frozen_clock(start());
+        let service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+        for store in [Some("Лента"), Some("Пятёрочка"), Some("Азбука  Вкуса"), None, Some("лента "), Some("Пятерочка")] {
+            let at = clock.now() + Duration::days(1);
+            clock.set_mode(SandboxClockMode::Frozen { at });
+            let item = CreateFridgeItem { store: store.map(str::to_string), ..create(user_id, "Молоко", None, "л", None, at) };
+            service.add_item(item).await.unwrap();
+        }
+
+        let stores = |query: &'static str| {
+            let service = &service;
+            async move {
+                suggest_stores(service, user_id, query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|suggestion| (suggestion.store, suggestion.times_used))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            stores("").await,
+            vec![("Пятерочка".to_string(), 2), ("лента".to_string(), 2), ("Азбука Вкуса".to_string(), 1)]
+        );
+        let latest = &suggest_stores(&service, user_id, "").await.unwrap()[0];
+        assert_eq!(latest.last_used_at, start() + Duration::days(6));
+
+        // Начало названия или слова, без учета регистра и ё
+        assert_eq!(stores("ПЯТЁ").await, vec![("Пятерочка".to_string(), 2)]);
+        assert_eq!(stores("вку").await, vec![("Азбука Вкуса".to_string(), 1)]);
+        assert!(stores("ента").await.is_empty());
+
+        let other = insert_user(&pool, "Борис").await;
+        assert!(suggest_stores(&service, other, "").await.unwrap().is_empty());
+    }
+}
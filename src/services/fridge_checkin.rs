@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Utc};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{fridge::CheckinDay, notification::NotificationEvent},
+    services::{
+        clock::{self, SharedClock},
+        fridge::{FridgeService, CHECKIN_STALE_DAYS},
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::errors::AppError,
+};
+
+/// Как часто планировщик проверяет, кому пора напомнить о проверке холодильника
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Напоминания о еженедельной проверке холодильника в выбранный пользователем день
+pub struct FridgeCheckinScheduler {
+    pool: DbPool,
+    delivery: NotificationDelivery,
+    clock: SharedClock,
+}
+
+impl FridgeCheckinScheduler {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>) -> Self {
+        Self {
+            delivery: NotificationDelivery::new(pool.clone(), realtime_service),
+            pool,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.run_once(self.clock.now()).instrument(telemetry::job_span("fridge_checkin")).await {
+                    Ok(sent) if sent > 0 => info!("Fridge check-in reminders sent: {}", sent),
+                    Ok(_) => {}
+                    Err(e) => warn!("Fridge check-in reminder run failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    /// Одна проверка на момент `now`; каждому пользователю напоминание уходит не чаще раза в день
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let today = now.date_naive();
+        let today_str = today.format("%Y-%m-%d").to_string();
+
+        // Отметка last_reminded_on ставится в том же запросе, что и выборка, — без повторов
+        let due: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            UPDATE user_preferences
+            SET settings = jsonb_set(settings, '{checkin,last_reminded_on}', to_jsonb($1::text))
+            WHERE (settings->'checkin'->>'enabled')::boolean IS TRUE
+              AND COALESCE(settings->'checkin'->>'day', 'sunday') = $2
+              AND (settings->'checkin'->>'last_reminded_on') IS DISTINCT FROM $1
+            RETURNING user_id
+            "#
+        )
+        .bind(&today_str)
+        .bind(CheckinDay::from_weekday(today.weekday()).as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let fridge_service = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone());
+        let stale_before = now - chrono::Duration::days(CHECKIN_STALE_DAYS);
+
+        for (user_id,) in &due {
+            let items = fridge_service.get_checkin_items(*user_id).await?;
+            let stale_items_count = items.iter().filter(|item| item.updated_at < stale_before).count();
+
+            let notification = OutgoingNotification {
+                kind: "fridge_checkin_due",
+                title: "Пора проверить холодильник".to_string(),
+                message: format!(
+                    "Сверьте количество продуктов: {} шт., из них {} давно не обновлялись.",
+                    items.len(),
+                    stale_items_count
+                ),
+                data: serde_json::json!({ "items_count": items.len(), "stale_items_count": stale_items_count }),
+                event: WebSocketEvent::FridgeCheckinDue { items_count: items.len(), stale_items_count },
+            };
+            self.delivery.send(*user_id, NotificationEvent::FridgeCheckin, notification, now).await?;
+        }
+
+        Ok(due.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        services::{preferences::PreferencesService, realtime::WebSocketManager},
+        test_support::{frozen_clock, insert_fridge_item, insert_user},
+        utils::format::Locale,
+    };
+
+    /// Пятница, 16 октября 2026
+    fn friday() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap()
+    }
+
+    async fn enable_checkin(pool: &PgPool, user_id: Uuid, day: CheckinDay) {
+        PreferencesService::new(pool.clone())
+            .update(user_id, |preferences| {
+                preferences.checkin.enabled = true;
+                preferences.checkin.day = day;
+            })
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn reminder_is_sent_on_the_chosen_day_once(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let opted_out = insert_user(&pool, "Борис").await;
+        let other_day = insert_user(&pool, "Вера").await;
+        enable_checkin(&pool, user_id, CheckinDay::Friday).await;
+        enable_checkin(&pool, other_day, CheckinDay::Monday).await;
+        // Молоко не обновлялось месяц, хлеб куплен сегодня
+        insert_fridge_item(&pool, user_id, "Молоко", None, friday() - Duration::days(30)).await;
+        insert_fridge_item(&pool, user_id, "Хлеб", None, friday()).await;
+        insert_fridge_item(&pool, opted_out, "Сыр", None, friday()).await;
+
+        let manager = Arc::new(WebSocketManager::new());
+        let mut registration = manager.add_client(user_id, None, "Анна".to_string(), Locale::Ru).await.unwrap();
+        let scheduler = FridgeCheckinScheduler::new(pool.clone(), Arc::new(RealtimeService::new(manager)))
+            .with_clock(frozen_clock(friday()));
+
+        // Четверг — не выбранный день
+        assert_eq!(scheduler.run_once(friday() - Duration::days(1)).await.unwrap(), 0);
+
+        assert_eq!(scheduler.run_once(friday()).await.unwrap(), 1);
+        match registration.receiver.try_recv().unwrap() {
+            WebSocketEvent::FridgeCheckinDue { items_count, stale_items_count } => {
+                assert_eq!((items_count, stale_items_count), (2, 1));
+            }
+            other => panic!("expected FridgeCheckinDue, got {:?}", other),
+        }
+
+        // Повторный запуск в тот же день ничего не шлет
+        assert_eq!(scheduler.run_once(friday() + Duration::hours(5)).await.unwrap(), 0);
+        assert!(registration.receiver.try_recv().is_err());
+
+        let kinds: Vec<(Uuid, String)> = sqlx::query_as("SELECT user_id, kind FROM notifications ORDER BY created_at")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(kinds, vec![(user_id, "fridge_checkin_due".to_string())]);
+
+        // Через неделю — снова
+        assert_eq!(scheduler.run_once(friday() + Duration::days(7)).await.unwrap(), 1);
+    }
+}
+
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::fridge::{FridgeItem, FridgeItemNoteLinks, ItemUseReminder, LinkedRecipe},
+    utils::{
+        errors::AppError,
+        ingredient_matcher::{normalize_ingredient, IngredientIndex},
+    },
+};
+
+const RECIPE_DIRECTIVE: &str = "recipe:";
+
+/// Предлоги перед днем недели или «завтра»: без них слово не считается указанием даты
+const DATE_PREPOSITIONS: [&str; 9] = ["до", "к", "на", "в", "for", "by", "on", "until", "before"];
+
+const WEEKDAYS: [(Weekday, &[&str]); 7] = [
+    (Weekday::Mon, &["понедельник", "понедельника", "понедельнику", "monday"]),
+    (Weekday::Tue, &["вторник", "вторника", "вторнику", "tuesday"]),
+    (Weekday::Wed, &["среда", "среду", "среды", "среде", "wednesday"]),
+    (Weekday::Thu, &["четверг", "четверга", "четвергу", "thursday"]),
+    (Weekday::Fri, &["пятница", "пятницу", "пятницы", "пятнице", "friday"]),
+    (Weekday::Sat, &["суббота", "субботу", "субботы", "субботе", "saturday"]),
+    (Weekday::Sun, &["воскресенье", "воскресенья", "воскресенью", "sunday"]),
+];
+
+const TOMORROW: [&str; 2] = ["завтра", "tomorrow"];
+
+/// Указания, найденные в заметке продукта
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteDirectives {
+    pub use_by: Option<UseByDirective>,
+    /// Название после `@recipe:`
+    pub recipe_name: Option<String>,
+}
+
+/// Дата из заметки и фраза, из которой она понята
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UseByDirective {
+    pub date: NaiveDate,
+    pub phrase: String,
+}
+
+/// Разбор заметки без догадок: распознаются только явные формы — «до/к/на/в» или
+/// «for/by/on/until/before» перед днем недели или «завтра», даты `ДД.ММ` и `ДД.ММ.ГГГГ`
+/// и `@recipe:<название>`. Берется первая найденная дата; прошедшие даты игнорируются,
+/// `ДД.ММ` без года — ближайшая такая дата, начиная с `today`.
+pub fn parse_note_directives(note: &str, today: NaiveDate) -> NoteDirectives {
+    NoteDirectives {
+        use_by: parse_use_by(note, today),
+        recipe_name: parse_recipe_name(note),
+    }
+}
+
+fn parse_use_by(note: &str, today: NaiveDate) -> Option<UseByDirective> {
+    let words: Vec<String> = note
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| matches!(c, ',' | ';' | ':' | '!' | '?' | '(' | ')' | '«' | '»' | '"' | '\''))
+                .trim_end_matches('.')
+                .to_lowercase()
+        })
+        .collect();
+
+    for (index, word) in words.iter().enumerate() {
+        if let Some(date) = parse_numeric_date(word, today) {
+            return Some(UseByDirective { date, phrase: word.clone() });
+        }
+
+        let Some(preposition) = index.checked_sub(1).map(|previous| words[previous].as_str()) else {
+            continue;
+        };
+        if !DATE_PREPOSITIONS.contains(&preposition) {
+            continue;
+        }
+
+        let date = if TOMORROW.contains(&word.as_str()) {
+            Some(today + Duration::days(1))
+        } else {
+            WEEKDAYS
+                .iter()
+                .find(|(_, forms)| forms.contains(&word.as_str()))
+                .map(|(weekday, _)| next_weekday(today, *weekday))
+        };
+
+        if let Some(date) = date {
+            return Some(UseByDirective { date, phrase: format!("{} {}", preposition, word) });
+        }
+    }
+
+    None
+}
+
+/// Ближайший такой день недели после `today` (через 1–7 дней)
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64 + 7) % 7;
+    today + Duration::days(if ahead == 0 { 7 } else { ahead })
+}
+
+/// `25.12` или `25.12.2026`: ровно две цифры дня и месяца, четыре — года
+fn parse_numeric_date(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let parts: Vec<&str> = word.split('.').collect();
+    let digits = |part: &str, len: usize| part.len() == len && part.chars().all(|c| c.is_ascii_digit());
+
+    match parts.as_slice() {
+        [day, month] if digits(day, 2) && digits(month, 2) => {
+            let (day, month) = (day.parse().ok()?, month.parse().ok()?);
+            NaiveDate::from_ymd_opt(today.year(), month, day)
+                .filter(|date| *date >= today)
+                .or_else(|| NaiveDate::from_ymd_opt(today.year() + 1, month, day))
+        }
+        [day, month, year] if digits(day, 2) && digits(month, 2) && digits(year, 4) => {
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+                .filter(|date| *date >= today)
+        }
+        _ => None,
+    }
+}
+
+/// Текст после `@recipe:` до конца строки, `,` `;` или следующего `@`
+fn parse_recipe_name(note: &str) -> Option<String> {
+    note.match_indices('@').find_map(|(at, _)| {
+        let start = at + 1 + RECIPE_DIRECTIVE.len();
+        let directive = note.get(at + 1..start)?;
+        if !directive.eq_ignore_ascii_case(RECIPE_DIRECTIVE) {
+            return None;
+        }
+
+        let rest = &note[start..];
+        let end = rest.find(['\n', ',', ';', '@']).unwrap_or(rest.len());
+        let name = rest[..end].trim();
+        (!name.is_empty()).then(|| name.to_string())
+    })
+}
+
+/// Напоминания и ссылки на рецепты, понятые из заметок продуктов (таблица `fridge_note_links`).
+/// Пересчитываются при каждом сохранении продукта: очищенная заметка убирает и напоминание,
+/// и ссылку. Отправляет напоминания планировщик уведомлений о сроках.
+pub struct FridgeNoteService {
+    pool: DbPool,
+}
+
+/// Напоминание, день которого наступил
+#[derive(Debug, Clone)]
+pub struct DueUseReminder {
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+    pub reminder: ItemUseReminder,
+    pub recipe: Option<LinkedRecipe>,
+}
+
+#[derive(Debug, FromRow)]
+struct NoteLinkRow {
+    item_id: Uuid,
+    user_id: Uuid,
+    remind_on: Option<NaiveDate>,
+    reminder_phrase: Option<String>,
+    reminder_sent_at: Option<DateTime<Utc>>,
+    recipe_id: Option<Uuid>,
+    recipe_name: Option<String>,
+    understood: Vec<String>,
+}
+
+impl NoteLinkRow {
+    fn reminder(&self) -> Option<ItemUseReminder> {
+        Some(ItemUseReminder {
+            remind_on: self.remind_on?,
+            phrase: self.reminder_phrase.clone().unwrap_or_default(),
+            sent: self.reminder_sent_at.is_some(),
+        })
+    }
+
+    /// Удаленный рецепт обнуляет recipe_id — ссылка пропадает вместе с ним
+    fn recipe(&self) -> Option<LinkedRecipe> {
+        Some(LinkedRecipe { id: self.recipe_id?, name: self.recipe_name.clone().unwrap_or_default() })
+    }
+
+    fn into_links(self) -> FridgeItemNoteLinks {
+        FridgeItemNoteLinks { reminder: self.reminder(), recipe: self.recipe(), understood: self.understood }
+    }
+}
+
+impl FridgeNoteService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Разбирает заметку сохраненного продукта и заменяет прежние напоминание и ссылку
+    pub async fn apply(&self, item: &FridgeItem, today: NaiveDate) -> Result<Option<FridgeItemNoteLinks>, AppError> {
+        let directives = item
+            .notes
+            .as_deref()
+            .map(|note| parse_note_directives(note, today))
+            .unwrap_or_default();
+
+        let mut understood = Vec::new();
+
+        if let Some(use_by) = &directives.use_by {
+            understood.push(format!(
+                "Напоминание создано на {} («{}»)",
+                use_by.date.format("%d.%m.%Y"),
+                use_by.phrase
+            ));
+        }
+
+        let recipe = match &directives.recipe_name {
+            Some(name) => {
+                let recipe = self.find_saved_recipe(item.user_id, name).await?;
+                understood.push(match &recipe {
+                    Some(recipe) => format!("Продукт связан с рецептом «{}»", recipe.name),
+                    None => format!("Рецепт «{}» не найден среди ваших сохраненных рецептов", name),
+                });
+                recipe
+            }
+            None => None,
+        };
+
+        if understood.is_empty() {
+            sqlx::query("DELETE FROM fridge_note_links WHERE item_id = $1 AND user_id = $2")
+                .bind(item.id)
+                .bind(item.user_id)
+                .execute(&self.pool)
+                .await?;
+            return Ok(None);
+        }
+
+        // Та же дата — напоминание не отправляется повторно
+        let row = sqlx::query_as::<_, NoteLinkRow>(
+            r#"
+            INSERT INTO fridge_note_links (
+                item_id, user_id, remind_on, reminder_phrase, recipe_id, recipe_name, understood, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+            ON CONFLICT (item_id) DO UPDATE SET
+                remind_on = EXCLUDED.remind_on,
+                reminder_phrase = EXCLUDED.reminder_phrase,
+                reminder_sent_at = CASE
+                    WHEN fridge_note_links.remind_on = EXCLUDED.remind_on THEN fridge_note_links.reminder_sent_at
+                END,
+                recipe_id = EXCLUDED.recipe_id,
+                recipe_name = EXCLUDED.recipe_name,
+                understood = EXCLUDED.understood,
+                updated_at = NOW()
+            RETURNING *
+            "#
+        )
+        .bind(item.id)
+        .bind(item.user_id)
+        .bind(directives.use_by.as_ref().map(|use_by| use_by.date))
+        .bind(directives.use_by.as_ref().map(|use_by| use_by.phrase.clone()))
+        .bind(recipe.as_ref().map(|recipe| recipe.id))
+        .bind(recipe.as_ref().map(|recipe| recipe.name.clone()))
+        .bind(&understood)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Some(row.into_links()))
+    }
+
+    pub async fn links(&self, user_id: Uuid, item_id: Uuid) -> Result<Option<FridgeItemNoteLinks>, AppError> {
+        let row = sqlx::query_as::<_, NoteLinkRow>(
+            "SELECT * FROM fridge_note_links WHERE item_id = $1 AND user_id = $2"
+        )
+        .bind(item_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(NoteLinkRow::into_links))
+    }
+
+    pub async fn user_links(&self, user_id: Uuid) -> Result<HashMap<Uuid, FridgeItemNoteLinks>, AppError> {
+        let rows = sqlx::query_as::<_, NoteLinkRow>("SELECT * FROM fridge_note_links WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| (row.item_id, row.into_links())).collect())
+    }
+
+    /// Неотправленные напоминания на сегодня и раньше. Отмечаются отправленными в том же
+    /// запросе, чтобы следующий (или параллельный) запуск планировщика их не повторил.
+    pub async fn take_due(&self, now: DateTime<Utc>) -> Result<Vec<DueUseReminder>, AppError> {
+        let rows = sqlx::query_as::<_, NoteLinkRow>(
+            r#"
+            UPDATE fridge_note_links SET reminder_sent_at = $2
+            WHERE reminder_sent_at IS NULL AND remind_on <= $1
+            RETURNING *
+            "#
+        )
+        .bind(now.date_naive())
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(DueUseReminder {
+                    user_id: row.user_id,
+                    item_id: row.item_id,
+                    reminder: row.reminder()?,
+                    recipe: row.recipe(),
+                })
+            })
+            .collect())
+    }
+
+    /// Свой или сохраненный в избранное рецепт: сначала точное совпадение названия,
+    /// затем по сопоставлению ингредиентов — но только если подходит ровно один рецепт
+    async fn find_saved_recipe(&self, user_id: Uuid, name: &str) -> Result<Option<LinkedRecipe>, AppError> {
+        let saved = sqlx::query_as::<_, (Uuid, String)>(
+            r#"
+            SELECT r.id, r.name FROM recipes r
+            WHERE r.created_by = $1
+               OR EXISTS (SELECT 1 FROM recipe_favorites f WHERE f.recipe_id = r.id AND f.user_id = $1)
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let normalized = normalize_ingredient(name);
+        if let Some((id, name)) = saved.iter().find(|(_, recipe)| normalize_ingredient(recipe) == normalized) {
+            return Ok(Some(LinkedRecipe { id: *id, name: name.clone() }));
+        }
+
+        let index = IngredientIndex::new([name], false);
+        let matches: Vec<&(Uuid, String)> = saved.iter().filter(|(_, recipe)| index.contains(recipe)).collect();
+        Ok(match matches.as_slice() {
+            [(id, name)] => Some(LinkedRecipe { id: *id, name: name.clone() }),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{insert_fridge_item, insert_recipe, insert_user};
+
+    /// Четверг, 23.04.2026
+    fn thursday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 4, 23).unwrap()
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn directive_table() {
+        /// Дата «использовать до» и фраза, из которой она взята
+        type UseBy = (NaiveDate, &'static str);
+
+        let cases: &[(&str, Option<UseBy>, Option<&str>)] = &[
+            ("до пятницы", Some((date(4, 24), "до пятницы")), None),
+            ("Съесть к Пятнице!", Some((date(4, 24), "к пятнице")), None),
+            ("use for Friday dinner", Some((date(4, 24), "for friday")), None),
+            ("by Thursday", Some((date(4, 30), "by thursday")), None),
+            ("на завтра", Some((date(4, 24), "на завтра")), None),
+            ("open tomorrow?", None, None),
+            ("tomorrow", None, None),
+            ("пятница", None, None),
+            ("до 25.12", Some((date(12, 25), "25.12")), None),
+            ("01.03", Some((NaiveDate::from_ymd_opt(2027, 3, 1).unwrap(), "01.03")), None),
+            ("до 01.03.2026", None, None),
+            ("5.12", None, None),
+            ("3.2% жирности", None, None),
+            ("@recipe: Борщ, до пятницы", Some((date(4, 24), "до пятницы")), Some("Борщ")),
+            ("for @Recipe:Pancakes", None, Some("Pancakes")),
+            ("@recipe:", None, None),
+            ("email@recipe.com", None, None),
+            ("", None, None),
+        ];
+
+        for (note, use_by, recipe) in cases {
+            let parsed = parse_note_directives(note, thursday());
+            assert_eq!(
+                parsed.use_by.map(|use_by| (use_by.date, use_by.phrase)),
+                use_by.map(|(date, phrase)| (date, phrase.to_string())),
+                "use_by for {:?}",
+                note
+            );
+            assert_eq!(parsed.recipe_name.as_deref(), *recipe, "recipe for {:?}", note);
+        }
+    }
+
+    #[sqlx::test]
+    async fn reminder_persists_fires_once_and_clears_with_note(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let bought = Utc.with_ymd_and_hms(2026, 4, 23, 9, 0, 0).unwrap();
+        let mut item = insert_fridge_item(&pool, user_id, "Сметана", Some("до пятницы"), bought).await;
+        let service = FridgeNoteService::new(pool.clone());
+
+        let links = service.apply(&item, thursday()).await.unwrap().unwrap();
+        assert_eq!(links.reminder.as_ref().unwrap().remind_on, date(4, 24));
+        assert_eq!(links.understood, vec!["Напоминание создано на 24.04.2026 («до пятницы»)"]);
+
+        // Новый экземпляр сервиса видит то же самое — ссылки живут в БД, а не в процессе
+        let stored = FridgeNoteService::new(pool.clone()).links(user_id, item.id).await.unwrap().unwrap();
+        assert!(!stored.reminder.unwrap().sent);
+
+        assert!(service.take_due(bought).await.unwrap().is_empty());
+        let friday = Utc.with_ymd_and_hms(2026, 4, 24, 8, 0, 0).unwrap();
+        let due = service.take_due(friday).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].item_id, item.id);
+        assert!(service.take_due(friday).await.unwrap().is_empty());
+
+        // Та же дата после правки заметки — повторно не отправляется
+        item.notes = Some("точно до пятницы".to_string());
+        service.apply(&item, thursday()).await.unwrap();
+        assert!(service.links(user_id, item.id).await.unwrap().unwrap().reminder.unwrap().sent);
+
+        item.notes = None;
+        assert!(service.apply(&item, thursday()).await.unwrap().is_none());
+        assert!(service.links(user_id, item.id).await.unwrap().is_none());
+        assert!(service.user_links(user_id).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn recipe_link_uses_own_recipes_and_disappears_with_item(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let other_id = insert_user(&pool, "Борис").await;
+        let recipe_id = insert_recipe(&pool, user_id, "Борщ", false).await;
+        insert_recipe(&pool, other_id, "Окрошка", true).await;
+        let now = Utc.with_ymd_and_hms(2026, 4, 23, 9, 0, 0).unwrap();
+        let service = FridgeNoteService::new(pool.clone());
+
+        let beet = insert_fridge_item(&pool, user_id, "Свекла", Some("@recipe:борщ"), now).await;
+        let links = service.apply(&beet, thursday()).await.unwrap().unwrap();
+        assert_eq!(links.recipe.unwrap().id, recipe_id);
+        assert!(links.reminder.is_none());
+
+        // Чужой рецепт не сохранен пользователем — ссылки нет, но пояснение есть
+        let kefir = insert_fridge_item(&pool, user_id, "Кефир", Some("@recipe:Окрошка"), now).await;
+        let links = service.apply(&kefir, thursday()).await.unwrap().unwrap();
+        assert!(links.recipe.is_none());
+        assert_eq!(links.understood, vec!["Рецепт «Окрошка» не найден среди ваших сохраненных рецептов"]);
+
+        assert_eq!(service.user_links(user_id).await.unwrap().len(), 2);
+        assert!(service.user_links(other_id).await.unwrap().is_empty());
+
+        sqlx::query("DELETE FROM fridge_items WHERE id = $1").bind(beet.id).execute(&pool).await.unwrap();
+        assert!(service.links(user_id, beet.id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::ai_response::{
+        AiResponseKind, ReportFeedbackResponse, ReportSection, ReportSectionKind, SectionFeedback,
+        SectionFeedbackStats, SectionPlacement, SectionPreference,
+    },
+    utils::errors::AppError,
+};
+
+/// Сколько последних отзывов на тип раздела учитывается: старые отзывы не держат раздел скрытым вечно
+const RECENT_FEEDBACK_PER_KIND: i64 = 10;
+/// Перевес отрицательных отзывов над полезными, с которого раздел уходит в конец отчета
+const DEMOTE_AFTER_NET_NEGATIVE: i64 = 2;
+/// Перевес, с которого раздел пропадает из отчета; общий обзор только опускается
+const OMIT_AFTER_NET_NEGATIVE: i64 = 4;
+const MAX_COMMENT_CHARS: usize = 1000;
+/// Сколько последних комментариев показывать в админке на тип раздела
+const ADMIN_RECENT_COMMENTS: i32 = 5;
+
+/// Последние отзывы пользователя по типам разделов
+const RECENT_FEEDBACK_SQL: &str = r#"
+    SELECT section_kind,
+           COUNT(*) FILTER (WHERE rating = 'helpful') AS helpful,
+           COUNT(*) FILTER (WHERE rating <> 'helpful') AS negative
+    FROM (
+        SELECT user_id, section_kind, rating,
+               ROW_NUMBER() OVER (PARTITION BY user_id, section_kind ORDER BY updated_at DESC) AS position
+        FROM fridge_report_feedback
+        WHERE $1::uuid IS NULL OR user_id = $1
+    ) recent
+    WHERE position <= $2
+    GROUP BY user_id, section_kind
+"#;
+
+/// Тип раздела, отзывы helpful / not_helpful / wrong и последние комментарии
+type SectionFeedbackRow = (String, i64, i64, i64, Option<Vec<String>>);
+
+/// Как показывать разделы отчета пользователю — по его последним отзывам
+#[derive(Debug, Clone, Default)]
+pub struct ReportSectionWeights {
+    preferences: Vec<SectionPreference>,
+}
+
+impl ReportSectionWeights {
+    pub fn placement(&self, kind: ReportSectionKind) -> SectionPlacement {
+        self.preferences
+            .iter()
+            .find(|preference| preference.kind == kind)
+            .map_or(SectionPlacement::Normal, |preference| preference.placement)
+    }
+
+    pub fn into_preferences(self) -> Vec<SectionPreference> {
+        self.preferences
+    }
+}
+
+/// Куда девать тип раздела при таком перевесе отзывов
+pub fn placement(kind: ReportSectionKind, helpful: i64, negative: i64) -> SectionPlacement {
+    let net_negative = negative - helpful;
+    if net_negative >= OMIT_AFTER_NET_NEGATIVE && kind != ReportSectionKind::Overview {
+        SectionPlacement::Omitted
+    } else if net_negative >= DEMOTE_AFTER_NET_NEGATIVE {
+        SectionPlacement::Demoted
+    } else {
+        SectionPlacement::Normal
+    }
+}
+
+/// Разделы для ответа: скрытые убираются, опущенные помечаются и уходят в конец
+pub fn arrange_sections(sections: Vec<ReportSection>, weights: &ReportSectionWeights) -> Vec<ReportSection> {
+    let mut sections: Vec<ReportSection> = sections
+        .into_iter()
+        .filter_map(|mut section| match weights.placement(section.kind) {
+            SectionPlacement::Omitted => None,
+            SectionPlacement::Demoted => {
+                section.demoted = true;
+                Some(section)
+            }
+            SectionPlacement::Normal => Some(section),
+        })
+        .collect();
+    // Сортировка устойчивая: внутри групп порядок отчета сохраняется
+    sections.sort_by_key(|section| section.demoted);
+    sections
+}
+
+/// Отзывы на разделы отчета о холодильнике и то, как они меняют следующие отчеты
+pub struct FridgeReportFeedbackService {
+    pool: DbPool,
+}
+
+impl FridgeReportFeedbackService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn weights(&self, user_id: Uuid) -> Result<ReportSectionWeights, AppError> {
+        let counts: HashMap<ReportSectionKind, (i64, i64)> = self
+            .recent_counts(Some(user_id))
+            .await?
+            .into_iter()
+            .map(|(kind, helpful, negative)| (kind, (helpful, negative)))
+            .collect();
+
+        let preferences = ReportSectionKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let (helpful, negative) = counts.get(&kind).copied().unwrap_or_default();
+                SectionPreference {
+                    kind,
+                    helpful,
+                    negative,
+                    placement: placement(kind, helpful, negative),
+                }
+            })
+            .collect();
+
+        Ok(ReportSectionWeights { preferences })
+    }
+
+    /// Сохраняет отзывы на разделы отчета; повторный отзыв на раздел заменяет прежний
+    pub async fn record(
+        &self,
+        user_id: Uuid,
+        report_id: Uuid,
+        feedback: &[SectionFeedback],
+    ) -> Result<ReportFeedbackResponse, AppError> {
+        let (kind, response): (String, serde_json::Value) = sqlx::query_as(
+            "SELECT kind, response FROM ai_responses WHERE id = $1 AND user_id = $2"
+        )
+        .bind(report_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Report not found".to_string()))?;
+
+        if kind != AiResponseKind::FridgeAnalysis.as_str() {
+            return Err(AppError::BadRequest("This AI response is not a fridge report".to_string()));
+        }
+
+        let sections: Vec<ReportSection> = response
+            .get("sections")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to read stored report sections: {}", e)))?
+            .unwrap_or_default();
+        if sections.is_empty() {
+            return Err(AppError::BadRequest("This report has no sections to rate".to_string()));
+        }
+
+        let mut seen = HashSet::new();
+        let mut rated = Vec::with_capacity(feedback.len());
+        for entry in feedback {
+            if !seen.insert(entry.section_id) {
+                return Err(AppError::BadRequest(format!("Section {} is rated twice", entry.section_id)));
+            }
+            if entry.comment.as_deref().is_some_and(|comment| comment.chars().count() > MAX_COMMENT_CHARS) {
+                return Err(AppError::BadRequest(format!("Comment must be at most {} characters", MAX_COMMENT_CHARS)));
+            }
+            let section = sections
+                .iter()
+                .find(|section| section.id == entry.section_id)
+                .ok_or_else(|| AppError::BadRequest(format!("Section {} is not part of this report", entry.section_id)))?;
+            rated.push((entry, section.kind));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (entry, kind) in &rated {
+            sqlx::query(
+                r#"
+                INSERT INTO fridge_report_feedback (report_id, section_id, user_id, section_kind, rating, comment)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (report_id, section_id)
+                DO UPDATE SET rating = EXCLUDED.rating, comment = EXCLUDED.comment, updated_at = NOW()
+                "#
+            )
+            .bind(report_id)
+            .bind(entry.section_id)
+            .bind(user_id)
+            .bind(kind.as_str())
+            .bind(entry.rating.as_str())
+            .bind(entry.comment.as_deref().filter(|comment| !comment.is_empty()))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(ReportFeedbackResponse {
+            report_id,
+            recorded: rated.len(),
+            sections: self.weights(user_id).await?.into_preferences(),
+        })
+    }
+
+    /// Отзывы по типам разделов с `since` и сколько пользователей сейчас их не видят; худшие — первыми
+    pub async fn admin_stats(&self, since: DateTime<Utc>) -> Result<Vec<SectionFeedbackStats>, AppError> {
+        let rows: Vec<SectionFeedbackRow> = sqlx::query_as(
+            r#"
+            SELECT section_kind,
+                   COUNT(*) FILTER (WHERE rating = 'helpful'),
+                   COUNT(*) FILTER (WHERE rating = 'not_helpful'),
+                   COUNT(*) FILTER (WHERE rating = 'wrong'),
+                   (ARRAY_AGG(comment ORDER BY updated_at DESC)
+                       FILTER (WHERE comment IS NOT NULL AND rating <> 'helpful'))[1:$2]
+            FROM fridge_report_feedback
+            WHERE updated_at >= $1
+            GROUP BY section_kind
+            "#
+        )
+        .bind(since)
+        .bind(ADMIN_RECENT_COMMENTS)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Положение раздела зависит от последних отзывов, а не от периода отчета
+        let mut hidden: HashMap<ReportSectionKind, (i64, i64)> = HashMap::new();
+        for (kind, helpful, negative) in self.recent_counts(None).await? {
+            let counts = hidden.entry(kind).or_default();
+            match placement(kind, helpful, negative) {
+                SectionPlacement::Demoted => counts.0 += 1,
+                SectionPlacement::Omitted => counts.1 += 1,
+                SectionPlacement::Normal => {}
+            }
+        }
+
+        let mut stats: Vec<SectionFeedbackStats> = rows
+            .into_iter()
+            .filter_map(|(kind, helpful, not_helpful, wrong, comments)| {
+                let kind = ReportSectionKind::parse(&kind)?;
+                let total = helpful + not_helpful + wrong;
+                let (users_demoted, users_omitted) = hidden.get(&kind).copied().unwrap_or_default();
+                Some(SectionFeedbackStats {
+                    kind,
+                    helpful,
+                    not_helpful,
+                    wrong,
+                    negative_rate: if total > 0 { (not_helpful + wrong) as f64 / total as f64 } else { 0.0 },
+                    users_demoted,
+                    users_omitted,
+                    recent_comments: comments.unwrap_or_default(),
+                })
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.negative_rate
+                .total_cmp(&a.negative_rate)
+                .then_with(|| (b.not_helpful + b.wrong).cmp(&(a.not_helpful + a.wrong)))
+        });
+
+        Ok(stats)
+    }
+
+    /// Последние отзывы по типу раздела, строка на пару пользователь-тип; `None` — по всем пользователям
+    async fn recent_counts(&self, user_id: Option<Uuid>) -> Result<Vec<(ReportSectionKind, i64, i64)>, AppError> {
+        let rows: Vec<(String, i64, i64)> = sqlx::query_as(RECENT_FEEDBACK_SQL)
+            .bind(user_id)
+            .bind(RECENT_FEEDBACK_PER_KIND)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(kind, helpful, negative)| ReportSectionKind::parse(&kind).map(|kind| (kind, helpful, negative)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{models::ai_response::SectionRating, test_support::insert_user};
+
+    fn section(kind: ReportSectionKind) -> ReportSection {
+        ReportSection {
+            id: Uuid::new_v4(),
+            kind,
+            title: kind.title().to_string(),
+            content: "Текст".to_string(),
+            items: Vec::new(),
+            demoted: false,
+        }
+    }
+
+    /// Сохраненный отчет со всеми типами разделов
+    async fn insert_report(pool: &PgPool, user_id: Uuid) -> (Uuid, Vec<ReportSection>) {
+        let sections: Vec<ReportSection> = ReportSectionKind::ALL.into_iter().map(section).collect();
+        let (id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO ai_responses (user_id, kind, template, provider, model, response, provenance)
+            VALUES ($1, 'fridge_analysis', 'fridge_report', 'mock', 'mock', $2, '{}')
+            RETURNING id
+            "#
+        )
+        .bind(user_id)
+        .bind(serde_json::json!({ "sections": sections }))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        (id, sections)
+    }
+
+    async fn rate(service: &FridgeReportFeedbackService, pool: &PgPool, user_id: Uuid, kind: ReportSectionKind, rating: SectionRating) {
+        let (report_id, sections) = insert_report(pool, user_id).await;
+        let section_id = sections.iter().find(|section| section.kind == kind).unwrap().id;
+        service
+            .record(user_id, report_id, &[SectionFeedback { section_id, rating, comment: None }])
+            .await
+            .unwrap();
+    }
+
+    fn arranged_kinds(weights: &ReportSectionWeights) -> Vec<(ReportSectionKind, bool)> {
+        let sections = ReportSectionKind::ALL.into_iter().map(section).collect();
+        arrange_sections(sections, weights).into_iter().map(|section| (section.kind, section.demoted)).collect()
+    }
+
+    #[sqlx::test]
+    async fn section_marked_unhelpful_sinks_then_disappears(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = FridgeReportFeedbackService::new(pool.clone());
+        let tips = ReportSectionKind::OptimizationTips;
+
+        let mut placements = Vec::new();
+        for rating in [SectionRating::NotHelpful, SectionRating::Wrong, SectionRating::NotHelpful, SectionRating::NotHelpful] {
+            rate(&service, &pool, user_id, tips, rating).await;
+            placements.push(service.weights(user_id).await.unwrap().placement(tips));
+        }
+        assert_eq!(
+            placements,
+            vec![SectionPlacement::Normal, SectionPlacement::Demoted, SectionPlacement::Demoted, SectionPlacement::Omitted]
+        );
+
+        let weights = service.weights(user_id).await.unwrap();
+        assert_eq!(
+            arranged_kinds(&weights),
+            vec![
+                (ReportSectionKind::Overview, false),
+                (ReportSectionKind::AttentionNeeded, false),
+                (ReportSectionKind::UsageRecommendations, false),
+            ]
+        );
+
+        // Полезный отзыв уменьшает перевес: раздел возвращается, но пока в конце
+        rate(&service, &pool, user_id, tips, SectionRating::Helpful).await;
+        let weights = service.weights(user_id).await.unwrap();
+        assert_eq!(weights.placement(tips), SectionPlacement::Demoted);
+        assert_eq!(arranged_kinds(&weights).last(), Some(&(tips, true)));
+
+        // Другой пользователь видит отчет целиком
+        let other = insert_user(&pool, "Олег").await;
+        assert_eq!(arranged_kinds(&service.weights(other).await.unwrap()).len(), ReportSectionKind::ALL.len());
+    }
+
+    #[sqlx::test]
+    async fn overview_is_only_demoted_and_old_feedback_ages_out(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = FridgeReportFeedbackService::new(pool.clone());
+
+        for _ in 0..OMIT_AFTER_NET_NEGATIVE + 1 {
+            rate(&service, &pool, user_id, ReportSectionKind::Overview, SectionRating::NotHelpful).await;
+        }
+        let weights = service.weights(user_id).await.unwrap();
+        assert_eq!(weights.placement(ReportSectionKind::Overview), SectionPlacement::Demoted);
+        assert_eq!(arranged_kinds(&weights).last(), Some(&(ReportSectionKind::Overview, true)));
+
+        // Учитываются только последние RECENT_FEEDBACK_PER_KIND отзывов
+        for _ in 0..RECENT_FEEDBACK_PER_KIND {
+            rate(&service, &pool, user_id, ReportSectionKind::Overview, SectionRating::Helpful).await;
+        }
+        let weights = service.weights(user_id).await.unwrap();
+        assert_eq!(weights.placement(ReportSectionKind::Overview), SectionPlacement::Normal);
+        let overview = weights.into_preferences().into_iter().find(|preference| preference.kind == ReportSectionKind::Overview).unwrap();
+        assert_eq!((overview.helpful, overview.negative), (RECENT_FEEDBACK_PER_KIND, 0));
+    }
+}
@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, UserScope},
+    models::{
+        fridge::{CreateFoodWaste, FoodWaste, FridgeItem, FridgeReservation, PlanSlot, ReservationConflict, ReservationShortfall, SlotReservation},
+        recipe::RecipeIngredient,
+    },
+    services::{
+        clock::{self, SharedClock},
+        fridge::{self, FridgeService, QUANTITY_EPSILON},
+    },
+    utils::{errors::AppError, ingredient_matcher::IngredientIndex, units::convert},
+};
+
+/// Мягкие резервы продуктов под слоты плана питания, чтобы план и готовка
+/// не рассчитывали на один и тот же продукт. Резерв не блокирует списание:
+/// API предупреждает о конфликте, а в строгом режиме отклоняет запрос.
+/// Резервы хранятся в fridge_reservations; прошедшие слоты перестают учитываться.
+pub struct FridgeReservationService {
+    pool: DbPool,
+    fridge_service: FridgeService,
+    clock: SharedClock,
+}
+
+impl FridgeReservationService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { fridge_service: FridgeService::new(pool.clone()), pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.fridge_service = FridgeService::new(self.pool.clone()).with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Резервирует ингредиенты рецепта под слот. Прежний резерв слота заменяется.
+    /// Продукты с более ранним сроком годности резервируются первыми.
+    pub async fn reserve_slot(
+        &self,
+        user_id: Uuid,
+        slot: PlanSlot,
+        ingredients: &[RecipeIngredient],
+    ) -> Result<SlotReservation, AppError> {
+        let now = self.clock.now();
+        let today = now.date_naive();
+        let mut tx = self.pool.begin().await?;
+
+        // Продукты блокируются до подсчета свободного остатка: параллельное резервирование
+        // или списание тех же продуктов ждет окончания этой транзакции
+        let items: Vec<FridgeItem> = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT * FROM fridge_items
+                WHERE user_id = $1 AND status = 'active'
+                ORDER BY expiry_date NULLS LAST, created_at, id
+                FOR UPDATE
+                "#
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+
+        UserScope::new(user_id)
+            .query("DELETE FROM fridge_reservations WHERE user_id = $1 AND slot_id = $2")
+            .bind(slot.slot_id)
+            .execute(&mut *tx)
+            .await?;
+        let reserved = reserved_by_item(&active_reservations(&mut *tx, user_id, ReservationFilter::All, today).await?);
+
+        let mut free: HashMap<Uuid, f32> = items
+            .iter()
+            .map(|item| (item.id, available_quantity(item.quantity, reserved.get(&item.id).copied().unwrap_or(0.0))))
+            .collect();
+        let indexes: Vec<(&FridgeItem, IngredientIndex)> = items
+            .iter()
+            .map(|item| (item, IngredientIndex::new([item.name.as_str()], false)))
+            .collect();
+
+        let mut reservations = Vec::new();
+        let mut shortfalls = Vec::new();
+
+        for ingredient in ingredients {
+            // "По вкусу" не резервируется; неразобранное количество — тоже, пока автор его не поправит
+            let Some(amount) = ingredient.amount() else {
+                continue;
+            };
+            let Some((required, unit)) = amount.measure() else {
+                continue;
+            };
+
+            let candidates: Vec<Candidate> = indexes
+                .iter()
+                .filter(|(_, index)| index.contains(&ingredient.name))
+                .map(|(item, _)| Candidate {
+                    item_id: item.id,
+                    free: free.get(&item.id).copied().unwrap_or(0.0),
+                    unit: &item.unit,
+                })
+                .collect();
+
+            let allocation = allocate(required as f64, unit, &candidates);
+
+            for (item_id, quantity) in allocation.allocations {
+                let item = items.iter().find(|item| item.id == item_id).expect("allocated item is a candidate");
+                if let Some(left) = free.get_mut(&item_id) {
+                    *left = (*left - quantity).max(0.0);
+                }
+
+                let reservation: FridgeReservation = UserScope::new(user_id)
+                    .query_as(
+                        r#"
+                        INSERT INTO fridge_reservations (user_id, item_id, slot_id, slot_date, recipe_id, ingredient_name, quantity, unit, created_at)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                        RETURNING *
+                        "#
+                    )
+                    .bind(item_id)
+                    .bind(slot.slot_id)
+                    .bind(slot.date)
+                    .bind(slot.recipe_id)
+                    .bind(&ingredient.name)
+                    .bind(quantity)
+                    .bind(&item.unit)
+                    .bind(now)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                reservations.push(reservation);
+            }
+
+            if allocation.shortfall > QUANTITY_EPSILON {
+                shortfalls.push(ReservationShortfall {
+                    ingredient_name: ingredient.name.clone(),
+                    missing_quantity: allocation.shortfall,
+                    unit: unit.to_string(),
+                });
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(SlotReservation { slot_id: slot.slot_id, reservations, shortfalls })
+    }
+
+    /// Резервирует продукты под рецепт слота: рецепт должен быть публичным или своим
+    pub async fn reserve_recipe(&self, user_id: Uuid, slot: PlanSlot) -> Result<SlotReservation, AppError> {
+        let ingredients = match slot.recipe_id {
+            Some(recipe_id) => {
+                let visible: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM recipes WHERE id = $1 AND (is_public OR created_by = $2)")
+                    .bind(recipe_id)
+                    .bind(user_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                if visible.is_none() {
+                    return Err(AppError::NotFound("Recipe not found".to_string()));
+                }
+
+                sqlx::query_as("SELECT * FROM recipe_ingredients WHERE recipe_id = $1")
+                    .bind(recipe_id)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+
+        self.reserve_slot(user_id, slot, &ingredients).await
+    }
+
+    /// Снимает резерв слота (слот удален из плана или рецепт заменен). Возвращает число снятых резервов.
+    pub async fn release_slot(&self, user_id: Uuid, slot_id: Uuid) -> Result<u64, AppError> {
+        let released = UserScope::new(user_id)
+            .query("DELETE FROM fridge_reservations WHERE user_id = $1 AND slot_id = $2")
+            .bind(slot_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(released)
+    }
+
+    /// Рецепт слота приготовлен: зарезервированное количество списывается как съеденное,
+    /// резерв снимается. Списание и снятие резерва — одна транзакция: если списание не удалось,
+    /// резерв остается и готовку можно повторить. Возвращает обновленные продукты.
+    pub async fn cook_slot(&self, user_id: Uuid, slot_id: Uuid) -> Result<Vec<FridgeItem>, AppError> {
+        let now = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+
+        // Резерв прошедшего слота не списывается
+        let reservations = active_reservations(&mut *tx, user_id, ReservationFilter::Slot(slot_id), now.date_naive()).await?;
+
+        let mut cooked: Vec<FridgeItem> = Vec::new();
+        for reservation in reservations {
+            // Продукт блокируется до списания; если он уже закончился (в том числе на
+            // предыдущем резерве этого же слота), списывать нечего
+            let item = fridge::lock_item(&mut tx, user_id, reservation.item_id).await?;
+            if !item.is_active() {
+                continue;
+            }
+            let quantity = reservation.quantity.min(item.quantity);
+            if quantity as f64 <= QUANTITY_EPSILON {
+                continue;
+            }
+
+            let item = fridge::consume_in_tx(&mut tx, user_id, reservation.item_id, Some(quantity), None, now).await?;
+            cooked.retain(|cooked_item| cooked_item.id != item.id);
+            cooked.push(item);
+        }
+
+        UserScope::new(user_id)
+            .query("DELETE FROM fridge_reservations WHERE user_id = $1 AND slot_id = $2")
+            .bind(slot_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(cooked)
+    }
+
+    /// Действующие резервы продукта (для отладки)
+    pub async fn item_reservations(&self, user_id: Uuid, item_id: Uuid) -> Result<Vec<FridgeReservation>, AppError> {
+        self.fridge_service.get_item_by_id(item_id, user_id).await?;
+
+        active_reservations(&self.pool, user_id, ReservationFilter::Item(item_id), self.clock.now().date_naive()).await
+    }
+
+    /// Снимает все резервы продукта перед его окончательным удалением.
+    /// Слоты плана при следующем резервировании посчитают недостачу заново.
+    pub async fn drop_item_reservations(&self, user_id: Uuid, item_id: Uuid) -> Result<u64, AppError> {
+        let released = UserScope::new(user_id)
+            .query("DELETE FROM fridge_reservations WHERE user_id = $1 AND item_id = $2")
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(released)
+    }
+
+    /// Зарезервированное количество по продуктам пользователя
+    pub async fn reserved_quantities(&self, user_id: Uuid) -> Result<HashMap<Uuid, f32>, AppError> {
+        let reservations = active_reservations(&self.pool, user_id, ReservationFilter::All, self.clock.now().date_naive()).await?;
+        Ok(reserved_by_item(&reservations))
+    }
+
+    /// Съедает продукт (или его часть) с проверкой резервов. Блокировка продукта, проверка
+    /// и списание — одна транзакция, поэтому резерв, созданный параллельно, не проскочит
+    /// между проверкой и списанием. Со `strict` списание, задевающее резерв, отклоняется.
+    pub async fn consume_item(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        quantity: Option<f32>,
+        note: Option<String>,
+        strict: bool,
+    ) -> Result<(FridgeItem, Option<ReservationConflict>), AppError> {
+        let now = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+
+        let item = fridge::lock_active_item(&mut tx, user_id, id).await?;
+        let conflict = draw_conflict(&mut tx, &item, quantity, now.date_naive()).await?;
+        reject_reserved_draw(strict, &conflict)?;
+
+        let item = fridge::consume_in_tx(&mut tx, user_id, id, quantity, note, now).await?;
+        tx.commit().await?;
+
+        Ok((item, conflict))
+    }
+
+    /// Записывает отходы; для продукта из холодильника — с той же проверкой резервов,
+    /// что и `consume_item`, в одной транзакции со списанием
+    pub async fn add_waste(&self, waste_data: CreateFoodWaste, strict: bool) -> Result<(FoodWaste, Option<ReservationConflict>), AppError> {
+        let now = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+
+        let conflict = match waste_data.original_item_id {
+            Some(item_id) => {
+                let item = fridge::lock_active_item(&mut tx, waste_data.user_id, item_id).await?;
+                draw_conflict(&mut tx, &item, Some(waste_data.wasted_quantity), now.date_naive()).await?
+            }
+            None => None,
+        };
+        reject_reserved_draw(strict, &conflict)?;
+
+        let waste = fridge::add_waste_in_tx(&mut tx, waste_data, now).await?;
+        tx.commit().await?;
+
+        Ok((waste, conflict))
+    }
+}
+
+/// Какие резервы выбирать
+#[derive(Debug, Clone, Copy)]
+enum ReservationFilter {
+    All,
+    Item(Uuid),
+    Slot(Uuid),
+}
+
+/// Действующие резервы пользователя. Единственное место, где определено, что резерв
+/// действует: слот сегодня или позже, прошедшие слоты не учитываются нигде.
+async fn active_reservations<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: Uuid,
+    filter: ReservationFilter,
+    today: NaiveDate,
+) -> Result<Vec<FridgeReservation>, AppError> {
+    let (item_id, slot_id) = match filter {
+        ReservationFilter::All => (None, None),
+        ReservationFilter::Item(item_id) => (Some(item_id), None),
+        ReservationFilter::Slot(slot_id) => (None, Some(slot_id)),
+    };
+
+    let reservations = UserScope::new(user_id)
+        .query_as(
+            r#"
+            SELECT * FROM fridge_reservations
+            WHERE user_id = $1 AND slot_date >= $2
+              AND ($3::uuid IS NULL OR item_id = $3)
+              AND ($4::uuid IS NULL OR slot_id = $4)
+            ORDER BY slot_date, created_at, id
+            "#
+        )
+        .bind(today)
+        .bind(item_id)
+        .bind(slot_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(reservations)
+}
+
+fn reserved_by_item(reservations: &[FridgeReservation]) -> HashMap<Uuid, f32> {
+    let mut totals = HashMap::new();
+    for reservation in reservations {
+        *totals.entry(reservation.item_id).or_insert(0.0) += reservation.quantity;
+    }
+    totals
+}
+
+/// Конфликт списания `quantity` (без значения — всего остатка) с резервами заблокированного продукта
+async fn draw_conflict(
+    tx: &mut Transaction<'_, Postgres>,
+    item: &FridgeItem,
+    quantity: Option<f32>,
+    today: NaiveDate,
+) -> Result<Option<ReservationConflict>, AppError> {
+    let reservations = active_reservations(&mut **tx, item.user_id, ReservationFilter::Item(item.id), today).await?;
+    Ok(reservation_conflict(item, &reservations, quantity))
+}
+
+/// `Some`, если списание заденет зарезервированную часть продукта
+fn reservation_conflict(item: &FridgeItem, reservations: &[FridgeReservation], quantity: Option<f32>) -> Option<ReservationConflict> {
+    let reserved: f32 = reservations.iter().map(|reservation| reservation.quantity).sum();
+    let used = reserved_draw(item.quantity, reserved, quantity);
+    if used as f64 <= QUANTITY_EPSILON {
+        return None;
+    }
+
+    let mut slot_ids: Vec<Uuid> = reservations.iter().map(|reservation| reservation.slot_id).collect();
+    slot_ids.sort();
+    slot_ids.dedup();
+
+    Some(ReservationConflict {
+        reserved_quantity_used: used,
+        unit: item.unit.clone(),
+        slot_ids,
+    })
+}
+
+/// Строгий режим: списание из резерва отклоняется
+fn reject_reserved_draw(strict: bool, conflict: &Option<ReservationConflict>) -> Result<(), AppError> {
+    match conflict {
+        Some(conflict) if strict => Err(AppError::BadRequest(format!(
+            "{} {} of this item is reserved for planned meals",
+            conflict.reserved_quantity_used, conflict.unit
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Продукт, из которого можно резервировать: свободный остаток в его единицах
+pub struct Candidate<'a> {
+    pub item_id: Uuid,
+    pub free: f32,
+    pub unit: &'a str,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Allocation {
+    /// (продукт, количество в единицах продукта)
+    pub allocations: Vec<(Uuid, f32)>,
+    /// Сколько не хватило, в единицах ингредиента
+    pub shortfall: f64,
+}
+
+/// Распределяет `required` (в единицах `unit`) по продуктам в порядке списка.
+/// Продукты в несовместимых единицах (г и шт, г и мл) пропускаются.
+pub fn allocate(required: f64, unit: &str, candidates: &[Candidate]) -> Allocation {
+    let mut remaining = required.max(0.0);
+    let mut allocations = Vec::new();
+
+    for candidate in candidates {
+        if remaining <= QUANTITY_EPSILON {
+            break;
+        }
+        if candidate.free as f64 <= QUANTITY_EPSILON {
+            continue;
+        }
+
+        // Свободный остаток продукта в единицах ингредиента
+        let Some(free) = convert(candidate.free as f64, candidate.unit, unit) else {
+            continue;
+        };
+
+        let taken = remaining.min(free);
+        let Some(taken_in_item_unit) = convert(taken, unit, candidate.unit) else {
+            continue;
+        };
+
+        allocations.push((candidate.item_id, (taken_in_item_unit as f32).min(candidate.free)));
+        remaining -= taken;
+    }
+
+    Allocation {
+        allocations,
+        shortfall: if remaining > QUANTITY_EPSILON { remaining } else { 0.0 },
+    }
+}
+
+/// Свободный остаток: количество минус резерв, не меньше нуля
+pub fn available_quantity(quantity: f32, reserved: f32) -> f32 {
+    (quantity - reserved).max(0.0)
+}
+
+/// Какая часть списания `draw` (без значения — весь остаток) приходится на резерв.
+/// Сначала расходуется свободный остаток, затем зарезервированный.
+pub fn reserved_draw(quantity: f32, reserved: f32, draw: Option<f32>) -> f32 {
+    let draw = draw.unwrap_or(quantity).clamp(0.0, quantity.max(0.0));
+    let free = available_quantity(quantity, reserved);
+
+    (draw - free).clamp(0.0, reserved.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::fridge::{FridgeCategory, WasteReason},
+        services::clock::SandboxClockMode,
+        test_support::{frozen_clock, insert_fridge_item, insert_user},
+    };
+
+    fn candidate(id: u128, free: f32, unit: &str) -> Candidate<'_> {
+        Candidate { item_id: Uuid::from_u128(id), free, unit }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn allocate_spreads_partial_quantities_in_order() {
+        let candidates = [candidate(1, 150.0, "г"), candidate(2, 500.0, "г")];
+
+        let allocation = allocate(400.0, "г", &candidates);
+
+        assert_eq!(allocation.allocations, vec![(Uuid::from_u128(1), 150.0), (Uuid::from_u128(2), 250.0)]);
+        assert_eq!(allocation.shortfall, 0.0);
+    }
+
+    #[test]
+    fn allocate_converts_into_item_units() {
+        let candidates = [candidate(1, 1.0, "кг")];
+
+        let allocation = allocate(250.0, "г", &candidates);
+
+        assert_eq!(allocation.allocations.len(), 1);
+        assert!((allocation.allocations[0].1 - 0.25).abs() < 1e-6);
+        assert_eq!(allocation.shortfall, 0.0);
+    }
+
+    #[test]
+    fn allocate_skips_incompatible_units() {
+        let candidates = [candidate(1, 3.0, "шт"), candidate(2, 200.0, "мл"), candidate(3, 100.0, "г")];
+
+        let allocation = allocate(300.0, "г", &candidates);
+
+        assert_eq!(allocation.allocations, vec![(Uuid::from_u128(3), 100.0)]);
+        assert!((allocation.shortfall - 200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn allocate_reports_shortfall_in_ingredient_units() {
+        let candidates = [candidate(1, 0.2, "кг"), candidate(2, 0.0, "г")];
+
+        let allocation = allocate(500.0, "г", &candidates);
+
+        assert_eq!(allocation.allocations.len(), 1);
+        assert!((allocation.shortfall - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn allocate_ignores_leftovers_within_epsilon() {
+        let candidates = [candidate(1, 99.99999, "г")];
+
+        let allocation = allocate(100.0, "г", &candidates);
+
+        assert_eq!(allocation.shortfall, 0.0);
+        assert_eq!(allocate(QUANTITY_EPSILON / 2.0, "г", &candidates), Allocation::default());
+    }
+
+    #[test]
+    fn available_quantity_never_goes_negative() {
+        assert_eq!(available_quantity(500.0, 200.0), 300.0);
+        assert_eq!(available_quantity(100.0, 250.0), 0.0);
+    }
+
+    #[test]
+    fn reserved_draw_spends_free_quantity_first() {
+        // 500 в остатке, 200 из них зарезервировано
+        assert_eq!(reserved_draw(500.0, 200.0, Some(300.0)), 0.0);
+        assert_eq!(reserved_draw(500.0, 200.0, Some(350.0)), 50.0);
+        // Весь остаток и списание больше остатка задевают весь резерв
+        assert_eq!(reserved_draw(500.0, 200.0, None), 200.0);
+        assert_eq!(reserved_draw(500.0, 200.0, Some(900.0)), 200.0);
+        assert_eq!(reserved_draw(500.0, 0.0, None), 0.0);
+    }
+
+    /// Молоко в холодильнике, `quantity` мл
+    async fn milk(pool: &PgPool, user_id: Uuid, quantity: f32) -> FridgeItem {
+        let item = insert_fridge_item(pool, user_id, "Молоко", None, Utc.with_ymd_and_hms(2026, 10, 1, 9, 0, 0).unwrap()).await;
+        sqlx::query_as("UPDATE fridge_items SET quantity = $2, unit = 'мл', category = 'dairy' WHERE id = $1 RETURNING *")
+            .bind(item.id)
+            .bind(quantity)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn milk_ingredient(quantity: f32) -> RecipeIngredient {
+        RecipeIngredient {
+            id: Uuid::new_v4(),
+            recipe_id: Uuid::nil(),
+            name: "молоко".to_string(),
+            quantity,
+            unit: "мл".to_string(),
+            notes: None,
+            needs_review: false,
+        }
+    }
+
+    fn slot(day: u32) -> PlanSlot {
+        PlanSlot { slot_id: Uuid::new_v4(), date: date(day), recipe_id: None }
+    }
+
+    fn noon(day: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn waste(user_id: Uuid, item_id: Uuid, quantity: f32) -> CreateFoodWaste {
+        CreateFoodWaste {
+            user_id,
+            original_item_id: Some(item_id),
+            name: "Молоко".to_string(),
+            brand: None,
+            wasted_quantity: quantity,
+            unit: "мл".to_string(),
+            category: FridgeCategory::Dairy,
+            waste_reason: WasteReason::Spoiled,
+            wasted_value: None,
+            notes: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn reservation_stays_active_through_the_slot_day(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let item = milk(&pool, user_id, 500.0).await;
+        let clock = frozen_clock(noon(15));
+        let service = FridgeReservationService::new(pool.clone()).with_clock(clock.clone());
+        service.reserve_slot(user_id, slot(16), &[milk_ingredient(200.0)]).await.unwrap();
+
+        // Все чтения резервов видят одно и то же: накануне и в день слота резерв действует, назавтра — нет
+        for (day, reserved) in [(15, 200.0), (16, 200.0), (17, 0.0)] {
+            clock.set_mode(SandboxClockMode::Frozen { at: noon(day) });
+            let quantities = service.reserved_quantities(user_id).await.unwrap();
+            assert_eq!(quantities.get(&item.id).copied().unwrap_or(0.0), reserved, "day {}", day);
+            let listed: f32 = service.item_reservations(user_id, item.id).await.unwrap().iter().map(|r| r.quantity).sum();
+            assert_eq!(listed, reserved, "day {}", day);
+
+            // Списать весь остаток без конфликта можно только после дня слота; проверка — в откатываемой транзакции
+            let mut tx = pool.begin().await.unwrap();
+            let conflict = draw_conflict(&mut tx, &item, None, date(day)).await.unwrap();
+            assert_eq!(conflict.map(|conflict| conflict.reserved_quantity_used).unwrap_or(0.0), reserved, "day {}", day);
+        }
+
+        // Резерв прошедшего слота не мешает новому и не списывается при готовке
+        let next = service.reserve_slot(user_id, slot(18), &[milk_ingredient(500.0)]).await.unwrap();
+        assert!(next.shortfalls.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn strict_draws_are_rejected_without_writing(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let item = milk(&pool, user_id, 500.0).await;
+        let service = FridgeReservationService::new(pool.clone()).with_clock(frozen_clock(noon(15)));
+        let planned = slot(16);
+        service.reserve_slot(user_id, planned, &[milk_ingredient(200.0)]).await.unwrap();
+
+        assert!(matches!(service.consume_item(user_id, item.id, Some(350.0), None, true).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(service.add_waste(waste(user_id, item.id, 400.0), true).await, Err(AppError::BadRequest(_))));
+        let (quantity,): (f32,) = sqlx::query_as("SELECT quantity FROM fridge_items WHERE id = $1").bind(item.id).fetch_one(&pool).await.unwrap();
+        assert_eq!(quantity, 500.0);
+        let (wastes,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM food_waste").fetch_one(&pool).await.unwrap();
+        assert_eq!(wastes, 0);
+
+        // Свободная часть списывается и в строгом режиме
+        let (item_after, conflict) = service.consume_item(user_id, item.id, Some(300.0), None, true).await.unwrap();
+        assert_eq!(item_after.quantity, 200.0);
+        assert!(conflict.is_none());
+
+        // Без strict — предупреждение и списание
+        let (_, conflict) = service.add_waste(waste(user_id, item.id, 50.0), false).await.unwrap();
+        let conflict = conflict.unwrap();
+        assert_eq!(conflict.reserved_quantity_used, 50.0);
+        assert_eq!(conflict.slot_ids, vec![planned.slot_id]);
+    }
+
+    #[sqlx::test]
+    async fn concurrent_reservations_do_not_double_spend(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let item = milk(&pool, user_id, 500.0).await;
+        let service = FridgeReservationService::new(pool.clone()).with_clock(frozen_clock(noon(15)));
+
+        let ingredients = [milk_ingredient(300.0)];
+        let (first, second) = tokio::join!(
+            service.reserve_slot(user_id, slot(16), &ingredients),
+            service.reserve_slot(user_id, slot(17), &ingredients),
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        let reserved = service.reserved_quantities(user_id).await.unwrap()[&item.id];
+        assert_eq!(reserved, 500.0);
+        let missing: f64 = first.shortfalls.iter().chain(&second.shortfalls).map(|shortfall| shortfall.missing_quantity).sum();
+        assert!((missing - 100.0).abs() < 1e-3);
+    }
+
+    #[sqlx::test]
+    async fn cooking_deducts_what_is_left_and_releases_the_slot(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let item = milk(&pool, user_id, 500.0).await;
+        let service = FridgeReservationService::new(pool.clone()).with_clock(frozen_clock(noon(15)));
+        let planned = slot(16);
+        service.reserve_slot(user_id, planned, &[milk_ingredient(300.0)]).await.unwrap();
+
+        // Часть резерва уже съедена вне плана
+        service.consume_item(user_id, item.id, Some(400.0), None, false).await.unwrap();
+
+        let cooked = service.cook_slot(user_id, planned.slot_id).await.unwrap();
+        assert_eq!(cooked.len(), 1);
+        assert!(!cooked[0].is_active());
+        assert!(service.reserved_quantities(user_id).await.unwrap().is_empty());
+    }
+}
@@ -1,39 +1,102 @@
+use std::collections::HashSet;
+
 use uuid::Uuid;
-use chrono::{Utc, NaiveDate};
+use chrono::{DateTime, Utc, NaiveDate};
+use serde_json::json;
 use crate::{
-    models::goal::{Goal, CreateGoal, GoalType, GoalStatus, WeightEntry, Achievement},
+    db::UserScope,
+    models::{
+        bulk::{BulkFailureReason, BulkItemFailure},
+        goal::{Goal, CreateGoal, GoalType, GoalStatus, WeightEntry, Achievement},
+    },
+    services::clock::{self, SharedClock},
     utils::errors::AppError,
 };
 
+/// Сколько id можно передать в архивирование
+pub const MAX_ARCHIVE_IDS: usize = 200;
+/// Наибольшая страница списка целей
+const MAX_GOALS_PAGE: i64 = 100;
+
+/// Записи прогресса целей для ленты активности (колонки — см. `services::timeline`)
+pub const TIMELINE_PROGRESS_SQL: &str = r#"
+    SELECT 'goal_progressed'::text AS kind, e.recorded_at AS occurred_at, e.id AS event_id, g.id AS reference_id,
+           g.title,
+           jsonb_build_object('value', e.value, 'target_value', g.target_value, 'unit', g.unit) AS summary
+    FROM goal_progress_entries e
+    JOIN goals g ON g.id = e.goal_id
+    WHERE g.user_id = $1
+"#;
+
+/// Выполненные цели: отдельной даты выполнения нет, берется последнее изменение
+pub const TIMELINE_COMPLETED_SQL: &str = r#"
+    SELECT 'goal_completed'::text AS kind, updated_at AS occurred_at, id AS event_id, id AS reference_id,
+           title,
+           jsonb_build_object('goal_type', goal_type, 'target_value', target_value, 'unit', unit) AS summary
+    FROM goals
+    WHERE user_id = $1 AND status = 'completed'
+"#;
+
+pub const TIMELINE_ACHIEVEMENTS_SQL: &str = r#"
+    SELECT 'achievement_earned'::text AS kind, earned_at AS occurred_at, id AS event_id, id AS reference_id,
+           title,
+           jsonb_build_object('description', description, 'icon', icon, 'goal_id', goal_related) AS summary
+    FROM achievements
+    WHERE user_id = $1
+"#;
+
+/// Итог архивирования целей
+#[derive(Debug, Clone)]
+pub struct GoalArchiveOutcome {
+    pub archived: Vec<Uuid>,
+    pub failures: Vec<BulkItemFailure>,
+}
+
 pub struct GoalService {
     pool: crate::db::DbPool,
+    clock: SharedClock,
 }
 
 impl GoalService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { pool }
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub async fn create_goal(&self, goal: CreateGoal) -> Result<Goal, AppError> {
-        // Mock implementation - in production, this would save to database
-        let goal_id = Uuid::new_v4();
-        
-        Ok(Goal {
-            id: goal_id,
-            user_id: goal.user_id,
-            title: goal.title,
-            description: goal.description,
-            goal_type: goal.goal_type,
-            target_value: goal.target_value,
-            current_value: goal.current_value,
-            unit: goal.unit,
-            target_date: goal.target_date,
-            daily_target: goal.daily_target,
-            weekly_target: goal.weekly_target,
-            status: goal.status,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        let now = self.clock.now();
+        let goal = sqlx::query_as::<_, Goal>(
+            r#"
+            INSERT INTO goals (
+                id, user_id, title, description, goal_type, target_value, current_value, unit,
+                target_date, daily_target, weekly_target, status, reminder_days, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $14)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(goal.user_id)
+        .bind(goal.title)
+        .bind(goal.description)
+        .bind(goal.goal_type)
+        .bind(goal.target_value)
+        .bind(goal.current_value)
+        .bind(goal.unit)
+        .bind(goal.target_date)
+        .bind(goal.daily_target)
+        .bind(goal.weekly_target)
+        .bind(goal.status)
+        .bind(goal.reminder_days)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(goal)
     }
 
     pub async fn get_user_goals(
@@ -41,16 +104,110 @@ impl GoalService {
         user_id: Uuid,
         goal_type: Option<GoalType>,
         status: Option<GoalStatus>,
+        include_archived: bool,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<Goal>, AppError> {
-        // Mock implementation
-        self.get_mock_goals(user_id, goal_type, status, limit, offset).await
+        let goals = UserScope::new(user_id)
+            .query_as::<Goal>(
+                r#"
+                SELECT * FROM goals
+                WHERE user_id = $1
+                  AND ($2::goal_type IS NULL OR goal_type = $2)
+                  AND ($3::goal_status IS NULL OR status = $3)
+                  AND ($4 OR archived_at IS NULL)
+                ORDER BY created_at DESC, id
+                LIMIT $5 OFFSET $6
+                "#
+            )
+            .bind(goal_type)
+            .bind(status)
+            .bind(include_archived)
+            .bind(limit.clamp(1, MAX_GOALS_PAGE))
+            .bind(offset.max(0))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(goals)
+    }
+
+    /// Убирает в архив завершенные, отмененные и просроченные цели, измененные раньше `before`.
+    /// С `ids` — только эти цели: чужие и несуществующие пропускаются как `not_found`,
+    /// неподходящие (активные, свежие, уже в архиве) — как `not_eligible`.
+    /// Одно событие в журнале активности на всю операцию.
+    pub async fn archive(
+        &self,
+        user_id: Uuid,
+        before: DateTime<Utc>,
+        ids: Option<&[Uuid]>,
+        now: DateTime<Utc>,
+    ) -> Result<GoalArchiveOutcome, AppError> {
+        if ids.is_some_and(|ids| ids.is_empty() || ids.len() > MAX_ARCHIVE_IDS) {
+            return Err(AppError::BadRequest(format!("ids must contain 1 to {} entries", MAX_ARCHIVE_IDS)));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let archived: Vec<Uuid> = UserScope::new(user_id)
+            .query_scalar(
+                r#"
+                UPDATE goals SET archived_at = $2
+                WHERE user_id = $1 AND archived_at IS NULL
+                  AND status IN ('completed', 'cancelled', 'expired')
+                  AND updated_at < $3
+                  AND ($4::uuid[] IS NULL OR id = ANY($4))
+                RETURNING id
+                "#
+            )
+            .bind(now)
+            .bind(before)
+            .bind(ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let failures = match ids {
+            Some(ids) => {
+                let owned: HashSet<Uuid> = UserScope::new(user_id)
+                    .query_scalar("SELECT id FROM goals WHERE user_id = $1 AND id = ANY($2)")
+                    .bind(ids)
+                    .fetch_all(&mut *tx)
+                    .await?
+                    .into_iter()
+                    .collect();
+                let mut seen = HashSet::new();
+                ids.iter()
+                    .copied()
+                    .filter(|id| seen.insert(*id) && !archived.contains(id))
+                    .map(|id| BulkItemFailure {
+                        id,
+                        reason: if owned.contains(&id) { BulkFailureReason::NotEligible } else { BulkFailureReason::NotFound },
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        sqlx::query(
+            "INSERT INTO activity_log (id, user_id, actor_id, action, details) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(user_id)
+        .bind("goals_archived")
+        .bind(json!({ "before": before, "requested": ids.map(<[Uuid]>::len), "archived": archived.len(), "skipped": failures.len() }))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(GoalArchiveOutcome { archived, failures })
     }
 
     pub async fn get_goal_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Goal, AppError> {
-        // Mock implementation
-        self.get_mock_goal(id, user_id).await
+        UserScope::new(user_id)
+            .query_as::<Goal>("SELECT * FROM goals WHERE user_id = $1 AND id = $2")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))
     }
 
     pub async fn update_goal(
@@ -59,27 +216,47 @@ impl GoalService {
         user_id: Uuid,
         payload: crate::api::goals::CreateGoalRequest,
     ) -> Result<Goal, AppError> {
-        // Mock implementation - in production, verify ownership and update database
-        Ok(Goal {
-            id,
-            user_id,
-            title: payload.title,
-            description: payload.description,
-            goal_type: payload.goal_type,
-            target_value: payload.target_value,
-            current_value: payload.current_value.unwrap_or(0.0),
-            unit: payload.unit,
-            target_date: payload.target_date,
-            daily_target: payload.daily_target,
-            weekly_target: payload.weekly_target,
-            status: GoalStatus::Active, // Default status
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        // Напоминания привязаны к target_date, поэтому после переноса срока они планируются заново.
+        let reminder_days = crate::api::goals::reminder_days(payload.reminders.as_ref())?;
+
+        UserScope::new(user_id)
+            .query_as::<Goal>(
+                r#"
+                UPDATE goals
+                SET title = $3, description = $4, goal_type = $5, target_value = $6,
+                    current_value = COALESCE($7, current_value), unit = $8, target_date = $9,
+                    daily_target = $10, weekly_target = $11, reminder_days = $12, updated_at = $13
+                WHERE user_id = $1 AND id = $2
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .bind(payload.title)
+            .bind(payload.description)
+            .bind(payload.goal_type)
+            .bind(payload.target_value)
+            .bind(payload.current_value)
+            .bind(payload.unit)
+            .bind(payload.target_date)
+            .bind(payload.daily_target)
+            .bind(payload.weekly_target)
+            .bind(reminder_days)
+            .bind(self.clock.now())
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))
     }
 
-    pub async fn delete_goal(&self, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
-        // Mock implementation - in production, verify ownership and delete from database
+    pub async fn delete_goal(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let deleted = UserScope::new(user_id)
+            .query("DELETE FROM goals WHERE user_id = $1 AND id = $2")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(AppError::NotFound("Goal not found".to_string()));
+        }
         Ok(())
     }
 
@@ -90,6 +267,43 @@ impl GoalService {
         value: f32,
         _notes: Option<String>,
     ) -> Result<Goal, AppError> {
+        // Цель из БД: обновляем значение и пишем точку истории для карточки прогресса
+        let mut tx = self.pool.begin().await?;
+        let stored = UserScope::new(user_id).query_as::<Goal>(
+            r#"
+            UPDATE goals
+            SET current_value = $3,
+                status = CASE WHEN target_value > 0 AND $3 >= target_value THEN 'completed' ELSE status END,
+                updated_at = NOW()
+            WHERE user_id = $1 AND id = $2
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(value)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(goal) = stored {
+            sqlx::query("INSERT INTO goal_progress_entries (goal_id, value) VALUES ($1, $2)")
+                .bind(goal.id)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            return Ok(goal);
+        }
+        tx.rollback().await?;
+
+        // Чужая цель — NotFound; заглушка только для целей, которых в БД нет вовсе
+        let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM goals WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        if exists {
+            return Err(AppError::NotFound("Goal not found".to_string()));
+        }
+
         // Mock implementation - in production, update current_value and check if goal is completed
         let mut goal = self.get_mock_goal(id, user_id).await?;
         goal.current_value = value;
@@ -99,10 +313,37 @@ impl GoalService {
             goal.status = GoalStatus::Completed;
         }
         
-        goal.updated_at = Utc::now();
+        goal.updated_at = self.clock.now();
         Ok(goal)
     }
 
+    /// Новое целевое значение цели из БД. Статус пересчитывается: если текущее значение
+    /// уже достигает новой цели, она выполнена.
+    pub async fn update_target(&self, id: Uuid, user_id: Uuid, target_value: f32) -> Result<Goal, AppError> {
+        if !target_value.is_finite() || target_value <= 0.0 {
+            return Err(AppError::BadRequest("Target value must be positive".to_string()));
+        }
+
+        UserScope::new(user_id).query_as::<Goal>(
+            r#"
+            UPDATE goals
+            SET target_value = $3,
+                status = CASE
+                    WHEN status = 'active' AND current_value >= $3 THEN 'completed'
+                    ELSE status
+                END,
+                updated_at = NOW()
+            WHERE user_id = $1 AND id = $2
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(target_value)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))
+    }
+
     pub async fn add_weight_entry(
         &self,
         user_id: Uuid,
@@ -122,7 +363,7 @@ impl GoalService {
             weight,
             date,
             notes,
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
         })
     }
 
@@ -157,99 +398,13 @@ impl GoalService {
             daily_target: Some(0.05),
             weekly_target: Some(0.35),
             status: GoalStatus::Active,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            reminder_days: vec![7, 3, 1],
+            archived_at: None,
+            created_at: self.clock.now(),
+            updated_at: self.clock.now(),
         })
     }
 
-    async fn get_mock_goals(
-        &self,
-        user_id: Uuid,
-        goal_type: Option<GoalType>,
-        status: Option<GoalStatus>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<Goal>, AppError> {
-        let mut goals = vec![];
-        
-        // Generate different mock goals
-        for i in 0..std::cmp::min(limit, 5) {
-            let goal_id = Uuid::new_v4();
-            let mock_goal_type = match i % 4 {
-                0 => GoalType::WeightLoss,
-                1 => GoalType::WeightGain,
-                2 => GoalType::CalorieIntake,
-                _ => GoalType::Exercise,
-            };
-            
-            let mock_status = match i % 3 {
-                0 => GoalStatus::Active,
-                1 => GoalStatus::Completed,
-                _ => GoalStatus::Paused,
-            };
-
-            // Filter by goal_type if specified
-            if let Some(filter_type) = &goal_type {
-                if mock_goal_type != *filter_type {
-                    continue;
-                }
-            }
-
-            // Filter by status if specified
-            if let Some(filter_status) = &status {
-                if mock_status != *filter_status {
-                    continue;
-                }
-            }
-            
-            let goal = Goal {
-                id: goal_id,
-                user_id,
-                title: format!("Goal {} - {}", i + 1, match &mock_goal_type {
-                    GoalType::WeightLoss => "Lose weight",
-                    GoalType::WeightGain => "Gain weight",
-                    GoalType::CalorieIntake => "Daily calories",
-                    GoalType::Exercise => "Exercise time",
-                    _ => "Other goal",
-                }),
-                description: Some(format!("Description for goal {}", i + 1)),
-                goal_type: mock_goal_type.clone(),
-                target_value: match &mock_goal_type {
-                    GoalType::WeightLoss | GoalType::WeightGain => 5.0 + (i as f32),
-                    GoalType::CalorieIntake => 2000.0 + (i as f32 * 200.0),
-                    GoalType::Exercise => 30.0 + (i as f32 * 15.0),
-                    _ => 100.0 + (i as f32 * 50.0),
-                },
-                current_value: match mock_status {
-                    GoalStatus::Completed => 5.0 + (i as f32),
-                    _ => (2.5 + (i as f32)) / 2.0,
-                },
-                unit: match &mock_goal_type {
-                    GoalType::WeightLoss | GoalType::WeightGain => "kg".to_string(),
-                    GoalType::CalorieIntake => "kcal".to_string(),
-                    GoalType::Exercise => "minutes".to_string(),
-                    _ => "units".to_string(),
-                },
-                target_date: Some(NaiveDate::from_ymd_opt(2024, 8 + i as u32, 1).unwrap()),
-                daily_target: Some(0.1 + (i as f32 * 0.05)),
-                weekly_target: Some(0.7 + (i as f32 * 0.3)),
-                status: mock_status,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            };
-            goals.push(goal);
-        }
-        
-        let start = offset as usize;
-        let end = std::cmp::min(start + limit as usize, goals.len());
-        
-        if start >= goals.len() {
-            Ok(vec![])
-        } else {
-            Ok(goals[start..end].to_vec())
-        }
-    }
-
     async fn get_mock_weight_entries(&self, user_id: Uuid, limit: i64) -> Result<Vec<WeightEntry>, AppError> {
         let mut entries = vec![];
         
@@ -260,7 +415,7 @@ impl GoalService {
                 weight: 70.0 - (i as f32 * 0.5), // Simulating weight loss
                 date: NaiveDate::from_ymd_opt(2024, 6, 1 + i as u32).unwrap(),
                 notes: if i % 3 == 0 { Some("Good progress".to_string()) } else { None },
-                created_at: Utc::now(),
+                created_at: self.clock.now(),
             };
             entries.push(entry);
         }
@@ -276,7 +431,7 @@ impl GoalService {
                 title: "First Goal".to_string(),
                 description: "Created your first goal".to_string(),
                 icon: "🎯".to_string(),
-                earned_at: Utc::now(),
+                earned_at: self.clock.now(),
                 goal_related: None,
             },
             Achievement {
@@ -285,7 +440,7 @@ impl GoalService {
                 title: "Consistency King".to_string(),
                 description: "Logged data for 7 days straight".to_string(),
                 icon: "⭐".to_string(),
-                earned_at: Utc::now(),
+                earned_at: self.clock.now(),
                 goal_related: None,
             },
             Achievement {
@@ -294,7 +449,7 @@ impl GoalService {
                 title: "Goal Crusher".to_string(),
                 description: "Completed your first goal".to_string(),
                 icon: "🏆".to_string(),
-                earned_at: Utc::now(),
+                earned_at: self.clock.now(),
                 goal_related: Some(Uuid::new_v4()),
             },
         ];
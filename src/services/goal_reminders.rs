@@ -0,0 +1,415 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveTime};
+use serde::Serialize;
+use tracing::{info, warn, Instrument};
+
+use crate::{
+    db::DbPool,
+    models::{goal::Goal, notification::NotificationEvent},
+    services::{
+        clock::{self, SharedClock},
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        preferences::PreferencesService,
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::{
+        errors::AppError,
+        format::{format_date, format_days, format_percent, Locale},
+        i18n,
+    },
+};
+
+/// Как часто планировщик проверяет сроки целей
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReminderRunSummary {
+    pub reminders_sent: usize,
+    pub goals_expired: usize,
+}
+
+/// Напоминания о приближении срока целей и перевод просроченных целей в статус Expired
+pub struct GoalReminderScheduler {
+    pool: DbPool,
+    delivery: NotificationDelivery,
+    clock: SharedClock,
+}
+
+impl GoalReminderScheduler {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>) -> Self {
+        Self {
+            delivery: NotificationDelivery::new(pool.clone(), realtime_service),
+            pool,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.run_once(self.clock.now().date_naive()).instrument(telemetry::job_span("goal_reminders")).await {
+                    Ok(summary) if summary.reminders_sent > 0 || summary.goals_expired > 0 => {
+                        info!("Goal reminders: {:?}", summary);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Goal reminder run failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    /// Одна проверка на дату `today`. Повторный запуск в тот же день ничего не отправляет повторно.
+    pub async fn run_once(&self, today: NaiveDate) -> Result<ReminderRunSummary, AppError> {
+        Ok(ReminderRunSummary {
+            goals_expired: self.expire_overdue(today).await?,
+            reminders_sent: self.send_reminders(today).await?,
+        })
+    }
+
+    async fn expire_overdue(&self, today: NaiveDate) -> Result<usize, AppError> {
+        let expired = sqlx::query_as::<_, Goal>(
+            r#"
+            UPDATE goals SET status = 'expired'
+            WHERE status = 'active' AND target_date < $1 AND current_value < target_value
+            RETURNING *
+            "#
+        )
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for goal in &expired {
+            let Some(target_date) = goal.target_date else {
+                continue;
+            };
+
+            let locale = self.locale(goal).await;
+            let notification = OutgoingNotification {
+                kind: "goal_expired",
+                title: i18n::t(locale, "goal.expired.title").to_string(),
+                message: i18n::tf(locale, "goal.expired.message", &[
+                    ("title", &goal.title),
+                    ("date", &format_date(target_date.and_time(NaiveTime::MIN).and_utc(), locale)),
+                    ("percent", &format_percent(f64::from(goal.progress_percentage()), locale)),
+                ]),
+                data: serde_json::json!({ "goal_id": goal.id, "target_date": target_date }),
+                event: WebSocketEvent::goal_expired(goal, target_date),
+            };
+            self.delivery.send(goal.user_id, NotificationEvent::GoalReminders, notification, self.clock.now()).await?;
+        }
+
+        Ok(expired.len())
+    }
+
+    async fn send_reminders(&self, today: NaiveDate) -> Result<usize, AppError> {
+        let due = sqlx::query_as::<_, Goal>(
+            r#"
+            SELECT * FROM goals
+            WHERE status = 'active'
+              AND target_date > $1
+              AND (target_date - $1) = ANY(reminder_days)
+            "#
+        )
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sent = 0;
+
+        for goal in due.iter().filter(|goal| !goal.is_completed()) {
+            let (Some(target_date), Some(days_left)) = (goal.target_date, goal.days_remaining(today)) else {
+                continue;
+            };
+
+            // Ключ включает target_date: после переноса срока напоминания отправляются для новой даты
+            let claimed = sqlx::query(
+                r#"
+                INSERT INTO goal_reminders_sent (goal_id, target_date, days_before)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                "#
+            )
+            .bind(goal.id)
+            .bind(target_date)
+            .bind(days_left)
+            .execute(&self.pool)
+            .await?
+            .rows_affected() > 0;
+
+            if !claimed {
+                continue;
+            }
+
+            let is_on_track = goal.is_on_track(today);
+            let locale = self.locale(goal).await;
+            let message_key = if is_on_track { "goal.deadline.on_track" } else { "goal.deadline.behind" };
+            let notification = OutgoingNotification {
+                kind: "goal_deadline_approaching",
+                title: i18n::t(locale, "goal.deadline.title").to_string(),
+                message: i18n::tf(locale, message_key, &[
+                    ("title", &goal.title),
+                    ("days", &format_days(i64::from(days_left), locale)),
+                    ("percent", &format_percent(f64::from(goal.progress_percentage()), locale)),
+                ]),
+                data: serde_json::json!({
+                    "goal_id": goal.id,
+                    "target_date": target_date,
+                    "days_left": days_left,
+                    "is_on_track": is_on_track,
+                }),
+                event: WebSocketEvent::goal_deadline_approaching(goal, target_date, days_left as u32, is_on_track),
+            };
+
+            // Напоминание, выключенное в настройках, считается обработанным и не повторяется
+            let plan = self.delivery.send(goal.user_id, NotificationEvent::GoalReminders, notification, self.clock.now()).await?;
+            if !plan.is_skip() {
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Язык уведомлений владельца цели; без запроса есть только настройки пользователя
+    async fn locale(&self, goal: &Goal) -> Locale {
+        PreferencesService::new(self.pool.clone())
+            .locale(goal.user_id, None)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to read locale preference for user {}: {:?}", goal.user_id, e);
+                Locale::default()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use sqlx::PgPool;
+    use tokio::sync::broadcast::Receiver;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        api::goals::CreateGoalRequest,
+        models::goal::{CreateGoal, GoalStatus, GoalType},
+        services::{clock::SandboxClockMode, goal::GoalService, realtime::WebSocketManager},
+        test_support::{frozen_clock, insert_user},
+    };
+
+    /// День `day` октября 2026, полдень UTC — вне тихих часов
+    fn noon(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        noon(day).date_naive()
+    }
+
+    async fn goal(pool: &PgPool, user_id: Uuid, target_date: Option<NaiveDate>, current_value: f32, reminder_days: Vec<i32>) -> Goal {
+        GoalService::new(pool.clone())
+            .create_goal(CreateGoal {
+                user_id,
+                title: "Пробежать 100 км".to_string(),
+                description: None,
+                goal_type: GoalType::Exercise,
+                target_value: 100.0,
+                current_value,
+                unit: "km".to_string(),
+                target_date,
+                daily_target: None,
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days,
+            })
+            .await
+            .unwrap()
+    }
+
+    fn drain(receiver: &mut Receiver<WebSocketEvent>) -> Vec<WebSocketEvent> {
+        std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+    }
+
+    async fn notification_kinds(pool: &PgPool, user_id: Uuid) -> Vec<(String, serde_json::Value)> {
+        sqlx::query_as("SELECT kind, data FROM notifications WHERE user_id = $1 ORDER BY created_at, kind")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn scheduler(pool: &PgPool, user_id: Uuid) -> (GoalReminderScheduler, Receiver<WebSocketEvent>, Arc<crate::services::clock::SandboxClock>) {
+        let manager = Arc::new(WebSocketManager::new());
+        let registration = manager.add_client(user_id, None, "Анна".to_string(), Locale::Ru).await.unwrap();
+        let clock = frozen_clock(noon(1));
+        let scheduler = GoalReminderScheduler::new(pool.clone(), Arc::new(RealtimeService::new(manager))).with_clock(clock.clone());
+        (scheduler, registration.receiver, clock)
+    }
+
+    #[sqlx::test]
+    async fn reminders_fire_on_each_configured_day_once(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (scheduler, mut events, clock) = scheduler(&pool, user_id).await;
+        // Срок 11.10, напоминания за 7, 3 и 1 день: 4.10, 8.10 и 10.10
+        let tracked = goal(&pool, user_id, Some(date(11)), 40.0, vec![7, 3, 1]).await;
+
+        let mut fired = Vec::new();
+        for day in 1..=10 {
+            clock.set_mode(SandboxClockMode::Frozen { at: noon(day) });
+            let summary = scheduler.run_once(date(day)).await.unwrap();
+            assert_eq!(summary.goals_expired, 0);
+            for event in drain(&mut events) {
+                match event {
+                    WebSocketEvent::GoalDeadlineApproaching { goal_id, target_date, days_left, progress_percentage, is_on_track, .. } => {
+                        assert_eq!((goal_id, target_date), (tracked.id, date(11)));
+                        assert_eq!(progress_percentage.round(), 40.0);
+                        fired.push((day, days_left, is_on_track));
+                    }
+                    other => panic!("unexpected event {:?}", other),
+                }
+            }
+            // Повторный запуск в тот же день ничего не отправляет
+            assert_eq!(scheduler.run_once(date(day)).await.unwrap().reminders_sent, 0);
+            assert!(drain(&mut events).is_empty());
+        }
+
+        // 40% меньше ожидаемого на каждом шаге: 76.7%, 90% и 96.7%
+        assert_eq!(fired, [(4, 7, false), (8, 3, false), (10, 1, false)]);
+        let stored = notification_kinds(&pool, user_id).await;
+        assert_eq!(stored.len(), 3);
+        assert!(stored.iter().all(|(kind, _)| kind == "goal_deadline_approaching"));
+        let days_left: Vec<i64> = stored.iter().map(|(_, data)| data["days_left"].as_i64().unwrap()).collect();
+        assert_eq!(days_left, [7, 3, 1]);
+    }
+
+    #[sqlx::test]
+    async fn on_track_flag_follows_progress(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (scheduler, mut events, _) = scheduler(&pool, user_id).await;
+        // За 3 дня до срока ожидается 90%
+        goal(&pool, user_id, Some(date(4)), 95.0, vec![3]).await;
+        goal(&pool, user_id, Some(date(4)), 80.0, vec![3]).await;
+
+        assert_eq!(scheduler.run_once(date(1)).await.unwrap().reminders_sent, 2);
+        let mut flags: Vec<(f32, bool)> = drain(&mut events)
+            .into_iter()
+            .map(|event| match event {
+                WebSocketEvent::GoalDeadlineApproaching { progress_percentage, is_on_track, .. } => (progress_percentage.round(), is_on_track),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        flags.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(flags, [(80.0, false), (95.0, true)]);
+    }
+
+    #[sqlx::test]
+    async fn overdue_incomplete_goal_expires_once(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (scheduler, mut events, clock) = scheduler(&pool, user_id).await;
+        let overdue = goal(&pool, user_id, Some(date(10)), 60.0, vec![1]).await;
+        let reached = goal(&pool, user_id, Some(date(10)), 100.0, vec![1]).await;
+        let open_ended = goal(&pool, user_id, None, 10.0, vec![1]).await;
+
+        // В день срока цель еще активна
+        clock.set_mode(SandboxClockMode::Frozen { at: noon(10) });
+        let summary = scheduler.run_once(date(10)).await.unwrap();
+        assert_eq!((summary.goals_expired, summary.reminders_sent), (0, 0));
+        assert!(drain(&mut events).is_empty());
+
+        clock.set_mode(SandboxClockMode::Frozen { at: noon(11) });
+        assert_eq!(scheduler.run_once(date(11)).await.unwrap().goals_expired, 1);
+        match drain(&mut events).as_slice() {
+            [WebSocketEvent::GoalExpired { goal_id, target_date, progress_percentage, .. }] => {
+                assert_eq!((*goal_id, *target_date, progress_percentage.round()), (overdue.id, date(10), 60.0));
+            }
+            other => panic!("expected one GoalExpired, got {:?}", other),
+        }
+        assert_eq!(notification_kinds(&pool, user_id).await.iter().map(|(kind, _)| kind.as_str()).collect::<Vec<_>>(), ["goal_expired"]);
+
+        // Следующий запуск не переводит цель повторно
+        assert_eq!(scheduler.run_once(date(12)).await.unwrap().goals_expired, 0);
+        assert!(drain(&mut events).is_empty());
+
+        let service = GoalService::new(pool.clone());
+        assert_eq!(service.get_goal_by_id(overdue.id, user_id).await.unwrap().status, GoalStatus::Expired);
+        assert_eq!(service.get_goal_by_id(reached.id, user_id).await.unwrap().status, GoalStatus::Active);
+        assert_eq!(service.get_goal_by_id(open_ended.id, user_id).await.unwrap().status, GoalStatus::Active);
+
+        // Фильтр списка по статусу Expired
+        let expired = service.get_user_goals(user_id, None, Some(GoalStatus::Expired), false, 20, 0).await.unwrap();
+        assert_eq!(expired.iter().map(|goal| goal.id).collect::<Vec<_>>(), [overdue.id]);
+        let active = service.get_user_goals(user_id, None, Some(GoalStatus::Active), false, 20, 0).await.unwrap();
+        assert_eq!(active.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn moving_the_target_date_reschedules_reminders(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let (scheduler, mut events, clock) = scheduler(&pool, user_id).await;
+        let tracked = goal(&pool, user_id, Some(date(5)), 10.0, vec![3, 1]).await;
+
+        clock.set_mode(SandboxClockMode::Frozen { at: noon(2) });
+        assert_eq!(scheduler.run_once(date(2)).await.unwrap().reminders_sent, 1);
+        drain(&mut events);
+
+        // Срок перенесен на 10.10 — напоминания за 3 и 1 день планируются от новой даты
+        let edit: CreateGoalRequest = serde_json::from_value(serde_json::json!({
+            "title": "Пробежать 100 км",
+            "goal_type": "Exercise",
+            "target_value": 100.0,
+            "unit": "km",
+            "target_date": "2026-10-10",
+            "reminders": { "days_before_deadline": [1, 3, 3] }
+        }))
+        .unwrap();
+        let updated = GoalService::new(pool.clone()).update_goal(tracked.id, user_id, edit).await.unwrap();
+        assert_eq!(updated.target_date, Some(date(10)));
+        assert_eq!(updated.reminder_days, [3, 1]);
+        assert_eq!(updated.current_value, 10.0);
+
+        let mut fired = Vec::new();
+        for day in 3..=11 {
+            clock.set_mode(SandboxClockMode::Frozen { at: noon(day) });
+            let summary = scheduler.run_once(date(day)).await.unwrap();
+            assert_eq!(summary.goals_expired, if day == 11 { 1 } else { 0 });
+            for event in drain(&mut events) {
+                if let WebSocketEvent::GoalDeadlineApproaching { days_left, target_date, .. } = event {
+                    fired.push((day, days_left, target_date));
+                }
+            }
+        }
+        // Ни напоминания «накануне» старого срока (4.10), ни истечения 6.10
+        assert_eq!(fired, [(7, 3, date(10)), (9, 1, date(10))]);
+    }
+
+    #[sqlx::test]
+    async fn editing_someone_elses_goal_is_not_found(pool: PgPool) {
+        let owner = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+        let tracked = goal(&pool, owner, Some(date(10)), 0.0, vec![1]).await;
+        let edit: CreateGoalRequest = serde_json::from_value(serde_json::json!({
+            "title": "Чужая цель",
+            "goal_type": "Exercise",
+            "target_value": 1.0,
+            "unit": "km",
+            "target_date": "2026-12-31"
+        }))
+        .unwrap();
+
+        let service = GoalService::new(pool.clone());
+        assert!(matches!(service.update_goal(tracked.id, other, edit).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.get_goal_by_id(tracked.id, other).await, Err(AppError::NotFound(_))));
+        assert_eq!(service.get_goal_by_id(tracked.id, owner).await.unwrap().title, "Пробежать 100 км");
+    }
+}
@@ -0,0 +1,1020 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use serde::Deserialize;
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        conversation::{
+            AppliedGoalChange, Conversation, ConversationKind, ConversationMessage, DailyTargetStats, DiaryAdherence, GoalAction, GoalDecision,
+            GoalReviewContext, GoalReviewOutcome, GoalWeekSummary, MessageRole, WeightDelta,
+        },
+        goal::{Goal, GoalType, WeightEntry},
+        notification::NotificationEvent,
+    },
+    services::{
+        clock::{self, SharedClock},
+        ai::AiService,
+        ai_conversations::{transcript, ConversationService},
+        goal::GoalService,
+        home::count_streak,
+        notification_delivery::{NotificationDelivery, OutgoingNotification},
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::{
+        errors::AppError,
+        format::percent_of,
+        sanitize::{user_data_block, PROMPT_DATA_NOTICE},
+    },
+};
+
+/// Как часто планировщик проверяет, не наступило ли воскресенье
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Сколько вопросов задает помощник в начале разбора
+const MAX_OPENING_QUESTIONS: usize = 3;
+
+/// Цель по калориям считается выполненной, если норма превышена не больше чем на 10%
+const CALORIE_TOLERANCE: f32 = 0.1;
+
+/// Дневник считается заполненным регулярно, если записи есть хотя бы в столько дней недели
+const REGULAR_DIARY_DAYS: u32 = 5;
+
+/// Сколько дней назад искать взвешивание, с которым сравнивается конец недели
+const WEIGHT_LOOKBACK_DAYS: i64 = 14;
+
+const STREAK_LOOKBACK_DAYS: i32 = 365;
+
+/// Калории и белок за день по дневнику питания
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DiaryDayTotals {
+    pub date: NaiveDate,
+    pub calories: f32,
+    pub protein: f32,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct GoalProgressRow {
+    pub goal_id: Uuid,
+    pub value: f32,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Понедельник недели, в которую входит `day`
+pub fn week_start(day: NaiveDate) -> NaiveDate {
+    day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+/// Еженедельный разбор целей: данные недели, первое сообщение помощника и разбор итога
+pub struct GoalReviewService {
+    pool: DbPool,
+    ai_service: AiService,
+}
+
+impl GoalReviewService {
+    pub fn new(pool: DbPool, ai_service: AiService) -> Self {
+        Self { pool, ai_service }
+    }
+
+    /// Данные недели, начинающейся с `week_start`
+    pub async fn review_context(&self, user_id: Uuid, week_start: NaiveDate) -> Result<GoalReviewContext, AppError> {
+        let week_end = week_start + chrono::Duration::days(6);
+        let start = week_start.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let end = start + chrono::Duration::days(7);
+
+        let goals = sqlx::query_as::<_, Goal>(
+            "SELECT * FROM goals WHERE user_id = $1 AND status = 'active' AND created_at < $2 ORDER BY created_at"
+        )
+        .bind(user_id)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let goal_ids: Vec<Uuid> = goals.iter().map(|goal| goal.id).collect();
+        let progress = sqlx::query_as::<_, GoalProgressRow>(
+            r#"
+            SELECT goal_id, value, recorded_at FROM goal_progress_entries
+            WHERE goal_id = ANY($1) AND recorded_at < $2
+            ORDER BY recorded_at
+            "#
+        )
+        .bind(&goal_ids)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let weights = sqlx::query_as::<_, WeightEntry>(
+            "SELECT * FROM weight_entries WHERE user_id = $1 AND date >= $2 AND date <= $3 ORDER BY date"
+        )
+        .bind(user_id)
+        .bind(week_start - chrono::Duration::days(WEIGHT_LOOKBACK_DAYS))
+        .bind(week_end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let diary_days = sqlx::query_as::<_, DiaryDayTotals>(
+            r#"
+            SELECT consumed_at::date AS date,
+                   SUM(portion_size * calories_per_100g / 100)::real AS calories,
+                   SUM(portion_size * protein_per_100g / 100)::real AS protein
+            FROM diary_entries
+            WHERE user_id = $1 AND consumed_at >= $2 AND consumed_at < $3
+            GROUP BY 1
+            ORDER BY 1
+            "#
+        )
+        .bind(user_id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let logged_days: Vec<(NaiveDate,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT consumed_at::date AS day
+            FROM diary_entries
+            WHERE user_id = $1 AND consumed_at < $2 AND consumed_at >= $2 - make_interval(days => $3)
+            ORDER BY day DESC
+            "#
+        )
+        .bind(user_id)
+        .bind(end)
+        .bind(STREAK_LOOKBACK_DAYS)
+        .fetch_all(&self.pool)
+        .await?;
+        let streak = count_streak(week_end, logged_days.into_iter().map(|(day,)| day));
+
+        Ok(build_review_context(week_start, &goals, &progress, &weights, &diary_days, streak))
+    }
+
+    /// Создает диалог разбора за неделю. `None` — разбор за эту неделю уже есть
+    /// или у пользователя нет активных целей.
+    pub async fn start_review(&self, user_id: Uuid, week_start: NaiveDate) -> Result<Option<Conversation>, AppError> {
+        let context = self.review_context(user_id, week_start).await?;
+        if context.goals.is_empty() {
+            return Ok(None);
+        }
+
+        let summary = review_summary(&context);
+        let opening = self.opening_message(&context, &summary).await;
+
+        ConversationService::new(self.pool.clone())
+            .create(
+                user_id,
+                ConversationKind::GoalReview,
+                &review_title(&context),
+                Some(&context),
+                &[(MessageRole::System, summary), (MessageRole::Assistant, opening)],
+            )
+            .await
+    }
+
+    /// Первое сообщение помощника. Если модель недоступна — те же вопросы без модели.
+    async fn opening_message(&self, context: &GoalReviewContext, summary: &str) -> String {
+        let fallback = fallback_opening(context);
+        if self.ai_service.is_mock() {
+            return fallback;
+        }
+
+        let prompt = format!(
+            "{}Ты — ИИ-помощник по питанию в приложении IT Cook и проводишь еженедельный разбор целей пользователя.\n\
+            Данные недели:\n{}\
+            Напиши короткое приветствие и задай 2-3 конкретных вопроса по этим данным: \
+            про пропущенные дневные нормы, цели без прогресса, изменение веса и дневник. \
+            Опирайся на цифры, например: «Вы трижды пропустили белковую цель — что мешало?». \
+            Не давай советов и не меняй цели — это будет в конце разговора. Отвечай на русском.",
+            PROMPT_DATA_NOTICE,
+            user_data_block("week", summary),
+        );
+
+        match self.ai_service.complete(&prompt).await {
+            Ok(completion) if !completion.text.trim().is_empty() => completion.text.trim().to_string(),
+            Ok(_) => fallback,
+            Err(e) => {
+                warn!("Goal review opening message failed, using fallback: {:?}", e);
+                fallback
+            }
+        }
+    }
+
+    /// Итог разбора по всему диалогу. Если модель недоступна или ответила не по формату —
+    /// все цели остаются без изменений, итог помечается `degraded`.
+    pub async fn conclude(&self, user_id: Uuid, id: Uuid) -> Result<GoalReviewOutcome, AppError> {
+        let conversations = ConversationService::new(self.pool.clone());
+        let conversation = conversations.get(user_id, id).await?;
+        let context = review_context_of(&conversation)?;
+        if conversation.concluded_at.is_some() {
+            return Err(AppError::Conflict("Conversation is already concluded".to_string()));
+        }
+
+        let history = conversations.messages(conversation.id).await?;
+        let outcome = self.model_outcome(context, &history).await;
+        conversations.conclude(conversation.id, &outcome).await?;
+
+        Ok(outcome)
+    }
+
+    async fn model_outcome(&self, context: &GoalReviewContext, history: &[ConversationMessage]) -> GoalReviewOutcome {
+        if self.ai_service.is_mock() {
+            return keep_all_outcome(context);
+        }
+
+        match self.ai_service.complete(&conclude_prompt(context, history)).await {
+            Ok(completion) => parse_outcome(&completion.text, context).unwrap_or_else(|| {
+                warn!("Goal review outcome is not valid JSON, keeping goals unchanged");
+                keep_all_outcome(context)
+            }),
+            Err(e) => {
+                warn!("Goal review conclusion failed, keeping goals unchanged: {:?}", e);
+                keep_all_outcome(context)
+            }
+        }
+    }
+
+    /// Применяет итог: меняет целевые значения через `GoalService`. Итог применяется один раз;
+    /// удаленные с тех пор цели пропускаются.
+    pub async fn apply(&self, user_id: Uuid, id: Uuid) -> Result<Vec<AppliedGoalChange>, AppError> {
+        let conversations = ConversationService::new(self.pool.clone());
+        let conversation = conversations.get(user_id, id).await?;
+        review_context_of(&conversation)?;
+        let Some(outcome) = conversation.outcome.as_ref().map(|outcome| &outcome.0) else {
+            return Err(AppError::BadRequest("Conversation is not concluded yet".to_string()));
+        };
+
+        if !conversations.claim_apply(conversation.id).await? {
+            return Err(AppError::Conflict("Goal review outcome is already applied".to_string()));
+        }
+
+        let goal_service = GoalService::new(self.pool.clone());
+        let mut applied = Vec::new();
+        for change in goal_patches(outcome) {
+            match goal_service.update_target(change.goal_id, user_id, change.new_target).await {
+                Ok(goal) => applied.push(AppliedGoalChange { title: goal.title, ..change }),
+                Err(AppError::NotFound(_)) => continue,
+                Err(e) => {
+                    conversations.release_apply(conversation.id).await?;
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(applied)
+    }
+}
+
+fn review_context_of(conversation: &Conversation) -> Result<&GoalReviewContext, AppError> {
+    conversation
+        .review
+        .as_ref()
+        .filter(|_| conversation.is_goal_review())
+        .map(|review| &review.0)
+        .ok_or_else(|| AppError::BadRequest("Only goal review conversations can be concluded".to_string()))
+}
+
+/// Промпт итога: данные недели, цели с идентификаторами и весь разговор
+pub fn conclude_prompt(context: &GoalReviewContext, history: &[ConversationMessage]) -> String {
+    let goals: Vec<String> = context
+        .goals
+        .iter()
+        .map(|goal| format!("goal_id {}: «{}», цель {} {}", goal.goal_id, goal.title, goal.target_value, goal.unit))
+        .collect();
+
+    format!(
+        "{}Ты — ИИ-помощник по питанию в приложении IT Cook. Подведи итог еженедельного разбора целей.\n\
+        Данные недели:\n{}Цели:\n{}{}\
+        Для каждой цели реши: оставить (keep) или изменить (adjust) с новым целевым значением \
+        в тех же единицах. Меняй цель, только если разговор и данные недели это подтверждают.\n\
+        Ответь только JSON без пояснений в формате:\n{}",
+        PROMPT_DATA_NOTICE,
+        user_data_block("week", &review_summary(context)),
+        user_data_block("goals", &goals.join("\n")),
+        transcript(history),
+        OUTCOME_FORMAT,
+    )
+}
+
+/// Собирает данные недели. `progress` — история прогресса до конца недели по возрастанию
+/// времени, `weights` — взвешивания с запасом до начала недели, `diary_days` — дни недели.
+pub fn build_review_context(
+    week_start: NaiveDate,
+    goals: &[Goal],
+    progress: &[GoalProgressRow],
+    weights: &[WeightEntry],
+    diary_days: &[DiaryDayTotals],
+    diary_streak_days: u32,
+) -> GoalReviewContext {
+    let week_end = week_start + chrono::Duration::days(6);
+    let in_week = |date: NaiveDate| date >= week_start && date <= week_end;
+
+    let goals = goals
+        .iter()
+        .map(|goal| {
+            let points: Vec<&GoalProgressRow> = progress.iter().filter(|point| point.goal_id == goal.id).collect();
+            let before = points.iter().rev().find(|point| point.recorded_at.date_naive() < week_start);
+            let first_in_week = points.iter().find(|point| in_week(point.recorded_at.date_naive()));
+            let start_value = before.or(first_in_week).map(|point| point.value);
+
+            GoalWeekSummary {
+                goal_id: goal.id,
+                title: goal.title.clone(),
+                goal_type: goal.goal_type.clone(),
+                unit: goal.unit.clone(),
+                target_value: goal.target_value,
+                daily_target: goal.daily_target,
+                start_value,
+                current_value: goal.current_value,
+                week_change: start_value.map(|start| goal.current_value - start),
+                progress_updates: points.iter().filter(|point| in_week(point.recorded_at.date_naive())).count(),
+                daily: daily_target_stats(goal, week_start, diary_days),
+                progress_percentage: goal.progress_percentage(),
+                on_track: goal.is_on_track(week_end),
+            }
+        })
+        .collect();
+
+    let days_logged = diary_days.iter().filter(|day| in_week(day.date)).count() as u32;
+
+    GoalReviewContext {
+        week_start,
+        week_end,
+        goals,
+        weight: weight_delta(weights, week_start, week_end),
+        diary: DiaryAdherence {
+            days_logged,
+            days_in_week: 7,
+            adherence_percentage: percent_of(days_logged as f64, 7.0),
+        },
+        diary_streak_days,
+    }
+}
+
+/// Выполнение дневной нормы по дням недели; только для целей по калориям и белку
+fn daily_target_stats(goal: &Goal, week_start: NaiveDate, diary_days: &[DiaryDayTotals]) -> Option<DailyTargetStats> {
+    if !matches!(goal.goal_type, GoalType::CalorieIntake | GoalType::ProteinIntake) {
+        return None;
+    }
+    let target = goal.daily_target.filter(|target| *target > 0.0).unwrap_or(goal.target_value);
+    if target <= 0.0 {
+        return None;
+    }
+
+    let hit = |day: &DiaryDayTotals| match goal.goal_type {
+        GoalType::CalorieIntake => day.calories <= target * (1.0 + CALORIE_TOLERANCE),
+        _ => day.protein >= target,
+    };
+
+    let mut stats = DailyTargetStats { days_hit: 0, days_missed: 0, days_without_data: 0 };
+    for offset in 0..7 {
+        let date = week_start + chrono::Duration::days(offset);
+        match diary_days.iter().find(|day| day.date == date).map(hit) {
+            Some(true) => stats.days_hit += 1,
+            Some(false) => stats.days_missed += 1,
+            None => stats.days_without_data += 1,
+        }
+    }
+
+    Some(stats)
+}
+
+/// Последнее взвешивание недели против последнего до нее (или первого за неделю)
+fn weight_delta(weights: &[WeightEntry], week_start: NaiveDate, week_end: NaiveDate) -> Option<WeightDelta> {
+    let end = weights
+        .iter()
+        .filter(|entry| entry.date >= week_start && entry.date <= week_end)
+        .max_by_key(|entry| entry.date)?;
+    let start = weights
+        .iter()
+        .filter(|entry| entry.date < week_start)
+        .max_by_key(|entry| entry.date)
+        .or_else(|| {
+            weights
+                .iter()
+                .filter(|entry| entry.date >= week_start && entry.date < end.date)
+                .min_by_key(|entry| entry.date)
+        })?;
+
+    Some(WeightDelta {
+        start_date: start.date,
+        start_weight: start.weight,
+        end_date: end.date,
+        end_weight: end.weight,
+        delta: end.weight - start.weight,
+    })
+}
+
+pub fn review_title(context: &GoalReviewContext) -> String {
+    format!(
+        "Разбор целей за неделю {}–{}",
+        context.week_start.format("%d.%m"),
+        context.week_end.format("%d.%m")
+    )
+}
+
+/// Данные недели текстом: системное сообщение диалога и контекст для модели
+pub fn review_summary(context: &GoalReviewContext) -> String {
+    let mut lines = vec![format!(
+        "Неделя {}–{}.",
+        context.week_start.format("%d.%m.%Y"),
+        context.week_end.format("%d.%m.%Y")
+    )];
+
+    lines.push("Цели:".to_string());
+    for goal in &context.goals {
+        let mut line = format!(
+            "- «{}»: {} из {} {} ({:.0}%)",
+            goal.title, goal.current_value, goal.target_value, goal.unit, goal.progress_percentage
+        );
+        if let Some(change) = goal.week_change {
+            line.push_str(&format!(", за неделю {:+.1} {}", change, goal.unit));
+        }
+        line.push_str(&format!(", обновлений прогресса: {}", goal.progress_updates));
+        if let Some(daily) = &goal.daily {
+            line.push_str(&format!(
+                "; дневная норма выполнена {} дн., пропущена {} дн., без записей {} дн.",
+                daily.days_hit, daily.days_missed, daily.days_without_data
+            ));
+        }
+        line.push_str(if goal.on_track { "; в графике" } else { "; отстает от графика" });
+        lines.push(line);
+    }
+
+    match &context.weight {
+        Some(weight) => lines.push(format!(
+            "Вес: {:.1} кг ({}) → {:.1} кг ({}), {:+.1} кг.",
+            weight.start_weight,
+            weight.start_date.format("%d.%m"),
+            weight.end_weight,
+            weight.end_date.format("%d.%m"),
+            weight.delta
+        )),
+        None => lines.push("Вес: недостаточно взвешиваний для сравнения.".to_string()),
+    }
+
+    lines.push(format!(
+        "Дневник питания: записи в {} из {} дней, серия {} дн. подряд.",
+        context.diary.days_logged, context.diary.days_in_week, context.diary_streak_days
+    ));
+
+    lines.join("\n")
+}
+
+/// Вопросы, которые стоит задать по данным недели, от самых важных
+pub fn review_questions(context: &GoalReviewContext) -> Vec<String> {
+    let mut questions = Vec::new();
+
+    let mut missed: Vec<(&GoalWeekSummary, u32)> = context
+        .goals
+        .iter()
+        .filter_map(|goal| goal.daily.map(|daily| (goal, daily.days_missed)))
+        .filter(|(_, days_missed)| *days_missed >= 2)
+        .collect();
+    missed.sort_by_key(|(_, days_missed)| std::cmp::Reverse(*days_missed));
+    for (goal, days_missed) in missed {
+        questions.push(format!("Вы {} пропустили {} — что мешало?", times_label(days_missed), goal_label(goal)));
+    }
+
+    for goal in context.goals.iter().filter(|goal| goal.progress_updates == 0 && goal.daily.is_none()) {
+        questions.push(format!("По цели «{}» на этой неделе не было обновлений — она еще актуальна?", goal.title));
+    }
+
+    if let Some(weight) = &context.weight {
+        let wrong_direction = context.goals.iter().any(|goal| match goal.goal_type {
+            GoalType::WeightLoss => weight.delta > 0.0,
+            GoalType::WeightGain => weight.delta < 0.0,
+            _ => false,
+        });
+        if wrong_direction {
+            questions.push(format!(
+                "Вес за неделю изменился на {:+.1} кг, а цель в другую сторону — как думаете, с чем это связано?",
+                weight.delta
+            ));
+        }
+    }
+
+    if context.diary.days_logged < REGULAR_DIARY_DAYS {
+        questions.push(format!(
+            "Дневник заполнен в {} из {} дней — в какие дни записывать было сложнее всего?",
+            context.diary.days_logged, context.diary.days_in_week
+        ));
+    }
+
+    if questions.is_empty() {
+        questions.push("Какая из целей далась на этой неделе легче всего, а какая — тяжелее?".to_string());
+    }
+
+    questions.truncate(MAX_OPENING_QUESTIONS);
+    questions
+}
+
+fn times_label(count: u32) -> String {
+    match count {
+        1 => "один раз".to_string(),
+        2 => "дважды".to_string(),
+        3 => "трижды".to_string(),
+        _ => format!("{} раз", count),
+    }
+}
+
+fn goal_label(goal: &GoalWeekSummary) -> String {
+    match goal.goal_type {
+        GoalType::ProteinIntake => "белковую цель".to_string(),
+        GoalType::CalorieIntake => "цель по калориям".to_string(),
+        _ => format!("цель «{}»", goal.title),
+    }
+}
+
+/// Первое сообщение без модели
+pub fn fallback_opening(context: &GoalReviewContext) -> String {
+    let questions: Vec<String> = review_questions(context)
+        .into_iter()
+        .enumerate()
+        .map(|(index, question)| format!("{}. {}", index + 1, question))
+        .collect();
+
+    format!(
+        "Привет! Давайте подведем итоги недели {}–{}.\n\n{}",
+        context.week_start.format("%d.%m"),
+        context.week_end.format("%d.%m"),
+        questions.join("\n")
+    )
+}
+
+/// Формат итога, который просим у модели
+pub const OUTCOME_FORMAT: &str = r#"{"summary": "2-3 предложения об итогах недели", "goals": [{"goal_id": "<uuid>", "action": "keep" | "adjust", "suggested_target": <число, только для adjust>, "reason": "почему"}]}"#;
+
+#[derive(Debug, Deserialize)]
+struct ModelOutcome {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    goals: Vec<ModelGoalDecision>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelGoalDecision {
+    goal_id: Uuid,
+    action: GoalAction,
+    #[serde(default)]
+    suggested_target: Option<f32>,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Итог из ответа модели: первый JSON-объект в тексте. `None`, если JSON не найден или не разобран.
+pub fn parse_outcome(text: &str, context: &GoalReviewContext) -> Option<GoalReviewOutcome> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    if end < start {
+        return None;
+    }
+
+    let model: ModelOutcome = serde_json::from_str(&text[start..=end]).ok()?;
+    Some(outcome_from_model(model, context))
+}
+
+/// Решение по каждой цели недели. Цели, которых не было в разборе, отбрасываются;
+/// `adjust` без корректного нового значения (положительного и отличного от текущего)
+/// превращается в `keep`; цели, про которые модель промолчала, остаются как есть.
+fn outcome_from_model(model: ModelOutcome, context: &GoalReviewContext) -> GoalReviewOutcome {
+    let goals = context
+        .goals
+        .iter()
+        .map(|goal| {
+            let decision = model.goals.iter().find(|decision| decision.goal_id == goal.goal_id);
+            let suggested_target = decision
+                .filter(|decision| decision.action == GoalAction::Adjust)
+                .and_then(|decision| decision.suggested_target)
+                .filter(|target| target.is_finite() && *target > 0.0 && (*target - goal.target_value).abs() > f32::EPSILON);
+
+            let reason = decision
+                .map(|decision| decision.reason.trim().to_string())
+                .filter(|reason| !reason.is_empty())
+                .unwrap_or_else(|| "Оставить без изменений".to_string());
+
+            GoalDecision {
+                goal_id: goal.goal_id,
+                title: goal.title.clone(),
+                action: if suggested_target.is_some() { GoalAction::Adjust } else { GoalAction::Keep },
+                current_target: goal.target_value,
+                suggested_target,
+                reason,
+            }
+        })
+        .collect();
+
+    GoalReviewOutcome {
+        summary: model.summary.trim().to_string(),
+        goals,
+        degraded: false,
+    }
+}
+
+/// Итог без модели: все цели остаются как есть
+pub fn keep_all_outcome(context: &GoalReviewContext) -> GoalReviewOutcome {
+    GoalReviewOutcome {
+        summary: "Не удалось получить итог от помощника — цели оставлены без изменений.".to_string(),
+        goals: context
+            .goals
+            .iter()
+            .map(|goal| GoalDecision {
+                goal_id: goal.goal_id,
+                title: goal.title.clone(),
+                action: GoalAction::Keep,
+                current_target: goal.target_value,
+                suggested_target: None,
+                reason: "Оставить без изменений".to_string(),
+            })
+            .collect(),
+        degraded: true,
+    }
+}
+
+/// Изменения целей, которые нужно применить по итогу
+pub fn goal_patches(outcome: &GoalReviewOutcome) -> Vec<AppliedGoalChange> {
+    outcome
+        .goals
+        .iter()
+        .filter(|decision| decision.action == GoalAction::Adjust)
+        .filter_map(|decision| {
+            decision.suggested_target.map(|new_target| AppliedGoalChange {
+                goal_id: decision.goal_id,
+                title: decision.title.clone(),
+                previous_target: decision.current_target,
+                new_target,
+            })
+        })
+        .collect()
+}
+
+/// По воскресеньям начинает разбор целей за неделю с теми, кто включил его в настройках
+/// отчетов и у кого есть активные цели
+pub struct GoalReviewScheduler {
+    pool: DbPool,
+    ai_service: AiService,
+    delivery: NotificationDelivery,
+    clock: SharedClock,
+}
+
+impl GoalReviewScheduler {
+    pub fn new(pool: DbPool, ai_service: AiService, realtime_service: Arc<RealtimeService>) -> Self {
+        Self {
+            delivery: NotificationDelivery::new(pool.clone(), realtime_service),
+            ai_service,
+            pool,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.run_once(self.clock.now()).instrument(telemetry::job_span("goal_review")).await {
+                    Ok(started) if started > 0 => info!("Goal reviews started: {}", started),
+                    Ok(_) => {}
+                    Err(e) => warn!("Goal review run failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    /// Одна проверка на момент `now`. Разбор за неделю создается один раз;
+    /// ошибка у одного пользователя не мешает остальным.
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let today = now.date_naive();
+        if today.weekday() != Weekday::Sun {
+            return Ok(0);
+        }
+
+        let week_start = week_start(today);
+        let recipients: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT p.user_id
+            FROM user_preferences p
+            WHERE (p.settings->'reports'->>'weekly_goal_review')::boolean IS TRUE
+              AND EXISTS (SELECT 1 FROM goals g WHERE g.user_id = p.user_id AND g.status = 'active')
+              AND NOT EXISTS (
+                  SELECT 1 FROM ai_conversations c
+                  WHERE c.user_id = p.user_id AND c.kind = 'goal_review' AND c.week_start = $1
+              )
+            "#
+        )
+        .bind(week_start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut started = 0;
+
+        for (user_id,) in recipients {
+            // Плановый разбор — фоновая функция: при исчерпанном бюджете вопросы без модели
+            let review_service = GoalReviewService::new(self.pool.clone(), self.ai_service.for_user(user_id).background());
+            let conversation = match review_service.start_review(user_id, week_start).await {
+                Ok(Some(conversation)) => conversation,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to start goal review for user {}: {:?}", user_id, e);
+                    continue;
+                }
+            };
+            started += 1;
+
+            let notification = OutgoingNotification {
+                kind: "goal_review",
+                title: "Разбор целей за неделю".to_string(),
+                message: "Помощник подготовил итоги недели и хочет задать пару вопросов.".to_string(),
+                data: serde_json::json!({ "conversation_id": conversation.id }),
+                event: WebSocketEvent::GoalReviewStarted {
+                    conversation_id: conversation.id,
+                    week_start,
+                    message: conversation.title.clone(),
+                },
+            };
+            if let Err(e) = self
+                .delivery
+                .send(user_id, NotificationEvent::GoalReminders, notification, now)
+                .await
+            {
+                warn!("Failed to notify user {} about goal review: {:?}", user_id, e);
+            }
+        }
+
+        Ok(started)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        config::AiResilienceConfig,
+        models::goal::{CreateGoal, GoalStatus},
+        services::ai_providers::AiProvider,
+        test_support::{insert_diary_entry, insert_user, StubAdapter},
+    };
+
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 5).unwrap()
+    }
+
+    fn at(day: i64, hour: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&(monday() + chrono::Duration::days(day)).and_hms_opt(hour, 0, 0).unwrap())
+    }
+
+    fn goal(title: &str, goal_type: GoalType, target_value: f32, current_value: f32, unit: &str, daily_target: Option<f32>) -> Goal {
+        Goal {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            title: title.to_string(),
+            description: None,
+            goal_type,
+            target_value,
+            current_value,
+            unit: unit.to_string(),
+            target_date: None,
+            daily_target,
+            weekly_target: None,
+            status: GoalStatus::Active,
+            reminder_days: Vec::new(),
+            archived_at: None,
+            created_at: at(-30, 8),
+            updated_at: at(-1, 8),
+        }
+    }
+
+    fn weighing(day: i64, weight: f32) -> WeightEntry {
+        WeightEntry {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            weight,
+            date: monday() + chrono::Duration::days(day),
+            notes: None,
+            created_at: at(day, 7),
+        }
+    }
+
+    fn diary_day(day: i64, calories: f32, protein: f32) -> DiaryDayTotals {
+        DiaryDayTotals { date: monday() + chrono::Duration::days(day), calories, protein }
+    }
+
+    /// Белок с дневной нормой, снижение веса с историей и вода без обновлений
+    fn week() -> (Vec<Goal>, GoalReviewContext) {
+        let goals = vec![
+            goal("Белок", GoalType::ProteinIntake, 120.0, 110.0, "g", Some(120.0)),
+            goal("Похудеть", GoalType::WeightLoss, 70.0, 78.0, "kg", None),
+            goal("Вода", GoalType::Water, 2000.0, 1500.0, "ml", None),
+        ];
+        let progress = vec![
+            GoalProgressRow { goal_id: goals[1].id, value: 81.0, recorded_at: at(-10, 9) },
+            GoalProgressRow { goal_id: goals[1].id, value: 80.0, recorded_at: at(-3, 9) },
+            GoalProgressRow { goal_id: goals[1].id, value: 79.0, recorded_at: at(1, 9) },
+            GoalProgressRow { goal_id: goals[1].id, value: 78.0, recorded_at: at(5, 9) },
+            GoalProgressRow { goal_id: goals[2].id, value: 1500.0, recorded_at: at(-4, 9) },
+        ];
+        // Последнее взвешивание до недели и последнее за неделю; первое за неделю не учитывается
+        let weights = vec![weighing(-12, 80.5), weighing(-2, 79.0), weighing(1, 78.8), weighing(6, 79.6)];
+        // Белок: выполнено в понедельник и пятницу, пропущено трижды, два дня без записей
+        let diary = vec![
+            diary_day(0, 1900.0, 130.0),
+            diary_day(1, 2000.0, 100.0),
+            diary_day(2, 2100.0, 90.0),
+            diary_day(3, 1800.0, 80.0),
+            diary_day(4, 1700.0, 120.0),
+        ];
+
+        let context = build_review_context(monday(), &goals, &progress, &weights, &diary, 9);
+        (goals, context)
+    }
+
+    #[test]
+    fn week_data_is_assembled_deterministically() {
+        let (_, context) = week();
+        assert_eq!((context.week_start, context.week_end), (monday(), NaiveDate::from_ymd_opt(2026, 10, 11).unwrap()));
+
+        let protein = &context.goals[0];
+        let daily = protein.daily.expect("protein goal has daily stats");
+        assert_eq!((daily.days_hit, daily.days_missed, daily.days_without_data), (2, 3, 2));
+        assert_eq!((protein.start_value, protein.progress_updates), (None, 0));
+
+        let weight_goal = &context.goals[1];
+        assert!(weight_goal.daily.is_none());
+        assert_eq!((weight_goal.start_value, weight_goal.week_change, weight_goal.progress_updates), (Some(80.0), Some(-2.0), 2));
+
+        let water = &context.goals[2];
+        assert_eq!((water.start_value, water.week_change, water.progress_updates), (Some(1500.0), Some(0.0), 0));
+
+        let weight = context.weight.as_ref().expect("weight delta");
+        assert_eq!((weight.start_date, weight.end_date), (monday() - chrono::Duration::days(2), monday() + chrono::Duration::days(6)));
+        assert!((weight.delta - 0.6).abs() < 1e-4);
+
+        assert_eq!((context.diary.days_logged, context.diary.days_in_week, context.diary_streak_days), (5, 7, 9));
+        assert!((context.diary.adherence_percentage - 71.4).abs() < 0.1);
+
+        // Одни и те же данные — один и тот же текст
+        let summary = review_summary(&context);
+        assert_eq!(summary, review_summary(&week().1));
+        assert!(summary.contains("дневная норма выполнена 2 дн., пропущена 3 дн., без записей 2 дн."));
+        assert!(summary.contains("Вес: 79.0 кг (03.10) → 79.6 кг (11.10), +0.6 кг."));
+        assert!(summary.contains("записи в 5 из 7 дней, серия 9 дн. подряд."));
+        assert_eq!(review_title(&context), "Разбор целей за неделю 05.10–11.10");
+    }
+
+    #[test]
+    fn opening_questions_follow_the_week() {
+        let (_, context) = week();
+        assert_eq!(
+            review_questions(&context),
+            vec![
+                "Вы трижды пропустили белковую цель — что мешало?".to_string(),
+                "По цели «Вода» на этой неделе не было обновлений — она еще актуальна?".to_string(),
+                "Вес за неделю изменился на +0.6 кг, а цель в другую сторону — как думаете, с чем это связано?".to_string(),
+            ]
+        );
+        assert!(fallback_opening(&context).contains("1. Вы трижды пропустили белковую цель"));
+
+        // Пустая неделя: не хватает дневника, остальные вопросы не срабатывают
+        let quiet = build_review_context(monday(), &[goal("Белок", GoalType::ProteinIntake, 120.0, 0.0, "g", Some(120.0))], &[], &[], &[], 0);
+        assert_eq!(review_questions(&quiet), vec!["Дневник заполнен в 0 из 7 дней — в какие дни записывать было сложнее всего?".to_string()]);
+
+        let good = build_review_context(
+            monday(),
+            &[goal("Белок", GoalType::ProteinIntake, 120.0, 120.0, "g", Some(120.0))],
+            &[],
+            &[],
+            &(0..7).map(|day| diary_day(day, 1800.0, 125.0)).collect::<Vec<_>>(),
+            7,
+        );
+        assert_eq!(review_questions(&good), vec!["Какая из целей далась на этой неделе легче всего, а какая — тяжелее?".to_string()]);
+    }
+
+    #[test]
+    fn conclusion_maps_onto_reviewed_goals_only() {
+        let (goals, context) = week();
+        let reply = format!(
+            "Вот итог:\n{}\nУдачи!",
+            serde_json::json!({
+                "summary": "  Неделя неровная.  ",
+                "goals": [
+                    { "goal_id": goals[0].id, "action": "adjust", "suggested_target": 100.0, "reason": "Норма слишком высокая" },
+                    // То же значение — менять нечего
+                    { "goal_id": goals[1].id, "action": "adjust", "suggested_target": 70.0, "reason": "Без изменений" },
+                    { "goal_id": goals[2].id, "action": "adjust", "suggested_target": -5.0 },
+                    { "goal_id": Uuid::new_v4(), "action": "adjust", "suggested_target": 10.0 },
+                ]
+            })
+        );
+
+        let outcome = parse_outcome(&reply, &context).expect("outcome");
+        assert_eq!(outcome.summary, "Неделя неровная.");
+        assert!(!outcome.degraded);
+        let decisions: Vec<(Uuid, GoalAction, Option<f32>)> =
+            outcome.goals.iter().map(|decision| (decision.goal_id, decision.action, decision.suggested_target)).collect();
+        assert_eq!(
+            decisions,
+            vec![
+                (goals[0].id, GoalAction::Adjust, Some(100.0)),
+                (goals[1].id, GoalAction::Keep, None),
+                (goals[2].id, GoalAction::Keep, None),
+            ]
+        );
+        assert_eq!(outcome.goals[2].reason, "Оставить без изменений");
+
+        let patches = goal_patches(&outcome);
+        assert_eq!(patches.len(), 1);
+        assert_eq!((patches[0].goal_id, patches[0].previous_target, patches[0].new_target), (goals[0].id, 120.0, 100.0));
+
+        // Цель, про которую модель промолчала, остается как есть
+        let silent = parse_outcome(r#"{"summary": "Все хорошо", "goals": []}"#, &context).expect("outcome");
+        assert!(silent.goals.iter().all(|decision| decision.action == GoalAction::Keep));
+        assert!(goal_patches(&silent).is_empty());
+
+        assert!(parse_outcome("Не могу ответить", &context).is_none());
+        assert!(parse_outcome(r#"{"goals": [{"goal_id": "not-a-uuid", "action": "adjust"}]}"#, &context).is_none());
+
+        let degraded = keep_all_outcome(&context);
+        assert!(degraded.degraded);
+        assert_eq!(degraded.goals.len(), 3);
+        assert!(goal_patches(&degraded).is_empty());
+    }
+
+    #[sqlx::test]
+    async fn review_thread_is_concluded_and_applied_once(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let protein = GoalService::new(pool.clone())
+            .create_goal(CreateGoal {
+                user_id,
+                title: "Белок".to_string(),
+                description: None,
+                goal_type: GoalType::ProteinIntake,
+                target_value: 120.0,
+                current_value: 0.0,
+                unit: "g".to_string(),
+                target_date: None,
+                daily_target: Some(120.0),
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let monday = week_start(Utc::now().date_naive());
+        for day in 0..3 {
+            let consumed_at = Utc.from_utc_datetime(&(monday + chrono::Duration::days(day)).and_hms_opt(12, 0, 0).unwrap());
+            insert_diary_entry(&pool, user_id, "lunch", 500.0, 60.0, consumed_at).await;
+        }
+
+        let conclusion = serde_json::json!({
+            "summary": "Норма белка пока великовата.",
+            "goals": [{ "goal_id": protein.id, "action": "adjust", "suggested_target": 100.0, "reason": "Трижды недобор" }]
+        })
+        .to_string();
+        let stub = Arc::new(StubAdapter::new("stub", vec![Some("Привет! Что мешало добрать белок?"), Some("Понимаю."), Some(&conclusion)]));
+        let ai_service = AiService::new(AiProvider::Mock, &AiResilienceConfig::default()).with_adapter(stub.clone());
+        let service = GoalReviewService::new(pool.clone(), ai_service.clone());
+
+        let conversation = service.start_review(user_id, monday).await.unwrap().expect("review started");
+        let conversations = ConversationService::new(pool.clone());
+        let messages = conversations.messages(conversation.id).await.unwrap();
+        let roles: Vec<&str> = messages.iter().map(|message| message.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "assistant"]);
+        assert!(messages[0].content.contains("пропущена 3 дн."));
+        assert_eq!(messages[1].content, "Привет! Что мешало добрать белок?");
+        assert!(stub.prompts.lock().unwrap()[0].contains("пропущена 3 дн."));
+
+        // Второй разбор за ту же неделю не создается
+        assert!(service.start_review(user_id, monday).await.unwrap().is_none());
+
+        conversations.reply(&ai_service, user_id, conversation.id, "Не успевала готовить").await.unwrap();
+
+        let outcome = service.conclude(user_id, conversation.id).await.unwrap();
+        assert!(stub.prompts.lock().unwrap().last().unwrap().contains("Не успевала готовить"));
+        assert_eq!((outcome.goals[0].action, outcome.goals[0].suggested_target), (GoalAction::Adjust, Some(100.0)));
+        assert!(matches!(service.conclude(user_id, conversation.id).await, Err(AppError::Conflict(_))));
+
+        let stranger = insert_user(&pool, "Олег").await;
+        assert!(matches!(service.apply(stranger, conversation.id).await, Err(AppError::NotFound(_))));
+
+        let applied = service.apply(user_id, conversation.id).await.unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!((applied[0].previous_target, applied[0].new_target), (120.0, 100.0));
+        let updated = GoalService::new(pool.clone()).get_goal_by_id(protein.id, user_id).await.unwrap();
+        assert_eq!(updated.target_value, 100.0);
+
+        assert!(matches!(service.apply(user_id, conversation.id).await, Err(AppError::Conflict(_))));
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::goal::{Achievement, Goal, GoalProgressPoint, GoalShareCard, GoalStatus, ShareCardAchievement},
+    services::clock::{self, SharedClock},
+    utils::{
+        errors::AppError,
+        format::{format_days, format_percent, format_quantity, Locale},
+    },
+};
+
+/// Сколько точек прогресса отдается для графика на карточке
+pub const MAX_SHARE_CARD_POINTS: usize = 20;
+
+const SHARE_TOKEN_LENGTH: usize = 32;
+
+/// Карточка цели для публикации в соцсетях и публичная ссылка на нее
+pub struct GoalShareService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl GoalShareService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — момент формирования карточки
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Публичный токен карточки; если он уже есть, возвращается существующий
+    pub async fn create_token(&self, goal_id: Uuid, user_id: Uuid) -> Result<String, AppError> {
+        self.owned_goal(goal_id, user_id).await?;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SHARE_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        let (token,): (String,) = sqlx::query_as(
+            r#"
+            INSERT INTO goal_share_tokens (goal_id, token)
+            VALUES ($1, $2)
+            ON CONFLICT (goal_id) DO UPDATE SET goal_id = EXCLUDED.goal_id
+            RETURNING token
+            "#
+        )
+        .bind(goal_id)
+        .bind(&token)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn revoke_token(&self, goal_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.owned_goal(goal_id, user_id).await?;
+
+        sqlx::query("DELETE FROM goal_share_tokens WHERE goal_id = $1")
+            .bind(goal_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Карточка доступна владельцу цели или по действующему публичному токену.
+    /// В остальных случаях — NotFound, чтобы не раскрывать существование цели.
+    pub async fn share_card(
+        &self,
+        goal_id: Uuid,
+        viewer: Option<Uuid>,
+        public_token: Option<&str>,
+        locale: Locale,
+    ) -> Result<GoalShareCard, AppError> {
+        let goal = sqlx::query_as::<_, Goal>("SELECT * FROM goals WHERE id = $1")
+            .bind(goal_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))?;
+
+        let is_owner = viewer == Some(goal.user_id);
+        if !is_owner && !self.token_matches(goal_id, public_token).await? {
+            return Err(AppError::NotFound("Goal not found".to_string()));
+        }
+
+        let (owner_first_name,): (String,) = sqlx::query_as("SELECT first_name FROM users WHERE id = $1")
+            .bind(goal.user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let history = sqlx::query_as::<_, GoalProgressPoint>(
+            "SELECT recorded_at, value FROM goal_progress_entries WHERE goal_id = $1 ORDER BY recorded_at"
+        )
+        .bind(goal_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let completed = goal.status == GoalStatus::Completed;
+        let achievement = if completed {
+            sqlx::query_as::<_, Achievement>(
+                "SELECT * FROM achievements WHERE goal_related = $1 ORDER BY earned_at DESC LIMIT 1"
+            )
+            .bind(goal_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|achievement| ShareCardAchievement {
+                title: achievement.title,
+                description: achievement.description,
+                icon: achievement.icon,
+                earned_at: achievement.earned_at,
+            })
+        } else {
+            None
+        };
+
+        Ok(build_share_card(goal, owner_first_name, history, achievement, self.clock.now(), locale))
+    }
+
+    async fn token_matches(&self, goal_id: Uuid, public_token: Option<&str>) -> Result<bool, AppError> {
+        let Some(token) = public_token.filter(|token| !token.is_empty()) else {
+            return Ok(false);
+        };
+
+        let (matches,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS(SELECT 1 FROM goal_share_tokens WHERE goal_id = $1 AND token = $2)"
+        )
+        .bind(goal_id)
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(matches)
+    }
+
+    async fn owned_goal(&self, goal_id: Uuid, user_id: Uuid) -> Result<Goal, AppError> {
+        sqlx::query_as::<_, Goal>("SELECT * FROM goals WHERE id = $1 AND user_id = $2")
+            .bind(goal_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))
+    }
+}
+
+/// Собирает карточку из цели и ее истории. Последняя точка графика всегда равна
+/// текущему значению цели.
+pub fn build_share_card(
+    goal: Goal,
+    owner_first_name: String,
+    mut history: Vec<GoalProgressPoint>,
+    achievement: Option<ShareCardAchievement>,
+    now: DateTime<Utc>,
+    locale: Locale,
+) -> GoalShareCard {
+    let start_value = history.first().map(|point| point.value).unwrap_or(goal.current_value);
+    if history.last().map(|point| point.value) != Some(goal.current_value) {
+        history.push(GoalProgressPoint { recorded_at: goal.updated_at, value: goal.current_value });
+    }
+
+    let completed_at = if goal.status == GoalStatus::Completed {
+        // Момент, когда значение впервые достигло цели; без истории — последнее изменение
+        history
+            .iter()
+            .find(|point| goal.target_value > 0.0 && point.value >= goal.target_value)
+            .map(|point| point.recorded_at)
+            .or(Some(goal.updated_at))
+    } else {
+        None
+    };
+    let duration_days = (completed_at.unwrap_or(now) - goal.created_at).num_days().max(1);
+
+    let share_text = share_text(&goal, duration_days, locale);
+
+    GoalShareCard {
+        goal_id: goal.id,
+        owner_first_name,
+        progress_percentage: goal.progress_percentage(),
+        progress: downsample(&history, MAX_SHARE_CARD_POINTS),
+        title: goal.title,
+        goal_type: goal.goal_type,
+        status: goal.status,
+        unit: goal.unit,
+        start_value,
+        current_value: goal.current_value,
+        target_value: goal.target_value,
+        started_at: goal.created_at,
+        completed_at,
+        duration_days,
+        achievement,
+        share_text,
+    }
+}
+
+/// Не больше `max_points` точек: первая и последняя сохраняются всегда,
+/// остальные берутся через равные промежутки по индексу
+pub fn downsample<T: Clone>(points: &[T], max_points: usize) -> Vec<T> {
+    if points.len() <= max_points {
+        return points.to_vec();
+    }
+    if max_points < 2 {
+        return points.last().cloned().into_iter().collect();
+    }
+
+    let last = points.len() - 1;
+    let steps = max_points - 1;
+    (0..max_points)
+        .map(|i| points[(i * last + steps / 2) / steps].clone())
+        .collect()
+}
+
+/// Готовый текст для публикации на языке пользователя
+pub fn share_text(goal: &Goal, duration_days: i64, locale: Locale) -> String {
+    let target = format_quantity(goal.target_value, &goal.unit, locale);
+    let duration = format_days(duration_days, locale);
+
+    if goal.status == GoalStatus::Completed {
+        return match locale {
+            Locale::Ru => format!("Цель «{}» достигнута: {} за {}! 🎉", goal.title, target, duration),
+            Locale::En => format!("Goal reached: “{}” — {} in {}! 🎉", goal.title, target, duration),
+        };
+    }
+
+    let current = format_quantity(goal.current_value, &goal.unit, locale);
+    let percent = format_percent(goal.progress_percentage() as f64, locale);
+    match locale {
+        Locale::Ru => format!("Уже {} пути к цели «{}»: {} из {} за {}. 💪", percent, goal.title, current, target, duration),
+        Locale::En => format!("{} of the way to “{}”: {} of {} in {}. 💪", percent, goal.title, current, target, duration),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::goal::{CreateGoal, GoalType},
+        services::goal::GoalService,
+        test_support::insert_user,
+    };
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 9, 1, 8, 0, 0).unwrap()
+    }
+
+    fn goal(title: &str, current_value: f32, status: GoalStatus) -> Goal {
+        Goal {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: None,
+            goal_type: GoalType::Exercise,
+            target_value: 100.0,
+            current_value,
+            unit: "km".to_string(),
+            target_date: None,
+            daily_target: None,
+            weekly_target: None,
+            status,
+            reminder_days: Vec::new(),
+            archived_at: None,
+            created_at: start(),
+            updated_at: start() + Duration::days(30),
+        }
+    }
+
+    fn point(day: i64, value: f32) -> GoalProgressPoint {
+        GoalProgressPoint { recorded_at: start() + Duration::days(day), value }
+    }
+
+    #[test]
+    fn downsampling_keeps_ends_and_spreads_evenly() {
+        let short: Vec<usize> = (0..20).collect();
+        assert_eq!(downsample(&short, MAX_SHARE_CARD_POINTS), short);
+        assert!(downsample::<usize>(&[], MAX_SHARE_CARD_POINTS).is_empty());
+
+        let long: Vec<usize> = (0..100).collect();
+        let sampled = downsample(&long, MAX_SHARE_CARD_POINTS);
+        assert_eq!(sampled.len(), MAX_SHARE_CARD_POINTS);
+        assert_eq!((sampled[0], sampled[19]), (0, 99));
+        assert!(sampled.windows(2).all(|pair| pair[0] < pair[1]));
+        // Шаг 99 / 19 ≈ 5.2: соседние точки отстоят на 5 или 6
+        assert!(sampled.windows(2).all(|pair| (5..=6).contains(&(pair[1] - pair[0]))));
+
+        let odd: Vec<usize> = (0..21).collect();
+        assert_eq!(downsample(&odd, 3), vec![0, 10, 20]);
+        assert_eq!(downsample(&odd, 2), vec![0, 20]);
+        assert_eq!(downsample(&odd, 1), vec![20]);
+        assert_eq!(downsample(&odd, 0), vec![20]);
+    }
+
+    #[test]
+    fn share_text_is_formatted_per_locale() {
+        let running = goal("Пробежать 100 км", 42.5, GoalStatus::Active);
+        assert_eq!(
+            share_text(&running, 21, Locale::Ru),
+            "Уже 42,5% пути к цели «Пробежать 100 км»: 42,5 km из 100 km за 21 день. 💪"
+        );
+        assert_eq!(
+            share_text(&running, 21, Locale::En),
+            "42.5% of the way to “Пробежать 100 км”: 42.5 km of 100 km in 21 days. 💪"
+        );
+
+        let done = goal("Run 100 km", 100.0, GoalStatus::Completed);
+        assert_eq!(share_text(&done, 2, Locale::Ru), "Цель «Run 100 km» достигнута: 100 km за 2 дня! 🎉");
+        assert_eq!(share_text(&done, 11, Locale::Ru), "Цель «Run 100 km» достигнута: 100 km за 11 дней! 🎉");
+        assert_eq!(share_text(&done, 1, Locale::En), "Goal reached: “Run 100 km” — 100 km in 1 day! 🎉");
+    }
+
+    #[test]
+    fn card_uses_first_value_and_the_day_the_target_was_reached() {
+        let history: Vec<GoalProgressPoint> = (0..40).map(|day| point(day, 10.0 + day as f32 * 2.5)).collect();
+        let done = goal("Пробежать 100 км", 100.0, GoalStatus::Completed);
+
+        let card = build_share_card(done, "Анна".to_string(), history, None, start() + Duration::days(60), Locale::Ru);
+        assert_eq!(card.start_value, 10.0);
+        // 10 + 36 * 2.5 = 100 — цель достигнута на 36-й день
+        assert_eq!(card.completed_at, Some(start() + Duration::days(36)));
+        assert_eq!(card.duration_days, 36);
+        assert_eq!(card.progress.len(), MAX_SHARE_CARD_POINTS);
+        assert_eq!(card.progress.first().unwrap().value, 10.0);
+        // Текущее значение отличается от последней точки истории — оно дописано в конец
+        assert_eq!(card.progress.last().unwrap().value, 100.0);
+        assert!(card.share_text.contains("за 36 дней"));
+
+        // Пока цель не выполнена, длительность считается до `now`, а без истории старт — текущее значение
+        let running = goal("Пробежать 100 км", 30.0, GoalStatus::Active);
+        let card = build_share_card(running, "Анна".to_string(), Vec::new(), None, start() + Duration::hours(5), Locale::En);
+        assert_eq!((card.start_value, card.completed_at, card.duration_days), (30.0, None, 1));
+        assert_eq!(card.progress.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn card_is_visible_to_owner_or_with_a_live_token(pool: PgPool) {
+        let owner = insert_user(&pool, "Анна").await;
+        let stranger = insert_user(&pool, "Борис").await;
+        let goal = GoalService::new(pool.clone())
+            .create_goal(CreateGoal {
+                user_id: owner,
+                title: "Пробежать 100 км".to_string(),
+                description: Some("Личное описание".to_string()),
+                goal_type: GoalType::Exercise,
+                target_value: 100.0,
+                current_value: 60.0,
+                unit: "km".to_string(),
+                target_date: None,
+                daily_target: None,
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days: Vec::new(),
+            })
+            .await
+            .unwrap();
+        for (day, value) in [(0, 10.0), (5, 35.0), (9, 60.0)] {
+            sqlx::query("INSERT INTO goal_progress_entries (goal_id, value, recorded_at) VALUES ($1, $2, $3)")
+                .bind(goal.id)
+                .bind(value as f32)
+                .bind(Utc::now() - Duration::days(10 - day))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let service = GoalShareService::new(pool.clone());
+
+        let card = service.share_card(goal.id, Some(owner), None, Locale::Ru).await.unwrap();
+        assert_eq!(card.progress.iter().map(|point| point.value).collect::<Vec<_>>(), vec![10.0, 35.0, 60.0]);
+        assert_eq!(card.owner_first_name, "Анна");
+
+        // Только эта цель и имя владельца: без фамилии, почты и описания
+        let json = serde_json::to_value(&card).unwrap();
+        let (email, last_name): (String, String) = sqlx::query_as("SELECT email, last_name FROM users WHERE id = $1")
+            .bind(owner)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let serialized = json.to_string();
+        assert!(!serialized.contains(&email) && !serialized.contains("Личное описание"));
+        assert!(last_name.is_empty() || !serialized.contains(&last_name));
+        assert!(json.get("user_id").is_none());
+
+        let hidden = service.share_card(goal.id, Some(stranger), None, Locale::Ru).await;
+        assert!(matches!(hidden, Err(AppError::NotFound(_))));
+        assert!(matches!(service.create_token(goal.id, stranger).await, Err(AppError::NotFound(_))));
+
+        let token = service.create_token(goal.id, owner).await.unwrap();
+        assert_eq!(service.create_token(goal.id, owner).await.unwrap(), token);
+        let public = service.share_card(goal.id, None, Some(&token), Locale::En).await.unwrap();
+        assert!(public.share_text.starts_with("60.0% of the way"));
+        assert!(matches!(service.share_card(goal.id, None, Some("wrong"), Locale::En).await, Err(AppError::NotFound(_))));
+
+        service.revoke_token(goal.id, owner).await.unwrap();
+        assert!(matches!(service.share_card(goal.id, None, Some(&token), Locale::En).await, Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,481 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, UserScope},
+    models::{
+        fridge::AnalyticsOptions,
+        goal::{CreateGoal, Goal, GoalStatus, GoalSuggestion, GoalSuggestionKind, GoalType},
+    },
+    services::{
+        calorie_budget::estimated_tdee,
+        clock::{self, SharedClock},
+        fridge::FridgeService,
+        goal::GoalService,
+        preferences::PreferencesService,
+    },
+    utils::{
+        errors::AppError,
+        format::{format_percent, format_quantity, Locale},
+        i18n,
+    },
+};
+
+/// Столько дней с записями в дневнике нужно, чтобы предложить цели без заполненного профиля
+pub const MIN_LOGGED_DAYS: i64 = 7;
+/// Отходы выше этой доли (в процентах) — повод предложить цель по отходам
+const WASTE_THRESHOLD_PERCENT: f64 = 15.0;
+const WASTE_TARGET_PERCENT: f32 = 10.0;
+const WASTE_GOAL_UNIT: &str = "%";
+const CALORIE_DEFICIT: f64 = 500.0;
+const CALORIE_SURPLUS: f64 = 300.0;
+/// Белок на кг веса: при снижении и наборе веса — больше
+const PROTEIN_G_PER_KG: f64 = 1.2;
+const PROTEIN_G_PER_KG_ACTIVE_GOAL: f64 = 1.6;
+const WATER_ML_PER_KG: f64 = 30.0;
+/// Шаги округления: небольшие изменения веса и рациона не меняют предложенную цель
+const CALORIE_STEP: f64 = 50.0;
+const PROTEIN_STEP: f64 = 5.0;
+const WATER_STEP: f64 = 100.0;
+
+/// Что пользователь хочет сделать с весом — по активной цели веса; без нее — удержать вес
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    Lose,
+    Gain,
+    #[default]
+    Maintain,
+}
+
+/// Данные, по которым считаются предложения
+#[derive(Debug, Clone, Default)]
+pub struct SuggestionInputs {
+    pub weight_kg: Option<f64>,
+    pub height_cm: Option<f64>,
+    pub age_years: Option<u32>,
+    pub gender: Option<String>,
+    pub activity_level: Option<String>,
+    pub objective: Objective,
+    /// Дней с записями в дневнике за все время
+    pub days_logged: i64,
+    /// Средние калории в день за последнюю неделю (по дням с записями)
+    pub average_calories: Option<f64>,
+    /// Доля выброшенных продуктов за последние 30 дней
+    pub waste_percentage: Option<f64>,
+    /// Цели, которые уже есть (активные и на паузе): такие не предлагаются
+    pub covered: Vec<GoalSuggestionKind>,
+}
+
+impl SuggestionInputs {
+    /// Предложения появляются после недели записей или сразу, если заполнены вес, рост и активность
+    pub fn eligible(&self) -> bool {
+        self.days_logged >= MIN_LOGGED_DAYS
+            || (self.weight_kg.is_some() && self.height_cm.is_some() && self.activity_level.is_some())
+    }
+}
+
+/// Предложения по данным пользователя. Расчет детерминированный: те же данные — те же цели.
+pub fn suggest(inputs: &SuggestionInputs, locale: Locale) -> Vec<GoalSuggestion> {
+    if !inputs.eligible() {
+        return Vec::new();
+    }
+
+    [
+        calorie_suggestion(inputs, locale),
+        protein_suggestion(inputs, locale),
+        water_suggestion(inputs, locale),
+        waste_suggestion(inputs, locale),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|suggestion| !inputs.covered.contains(&suggestion.kind))
+    .collect()
+}
+
+fn calorie_suggestion(inputs: &SuggestionInputs, locale: Locale) -> Option<GoalSuggestion> {
+    let (weight, height, age) = (inputs.weight_kg?, inputs.height_cm?, inputs.age_years?);
+    let tdee = round_step(
+        estimated_tdee(weight, height, age, inputs.gender.as_deref(), inputs.activity_level.as_deref()),
+        CALORIE_STEP,
+    );
+    let (target, key) = match inputs.objective {
+        Objective::Lose => (tdee - CALORIE_DEFICIT, "goal.suggestion.calories.lose"),
+        Objective::Gain => (tdee + CALORIE_SURPLUS, "goal.suggestion.calories.gain"),
+        Objective::Maintain => (tdee, "goal.suggestion.calories.maintain"),
+    };
+
+    let mut rationale = i18n::tf(
+        locale,
+        key,
+        &[("tdee", &number(tdee, locale)), ("weight", &number(weight, locale)), ("target", &number(target, locale))],
+    );
+    if let Some(average) = inputs.average_calories {
+        rationale.push(' ');
+        rationale.push_str(&i18n::tf(
+            locale,
+            "goal.suggestion.calories.average",
+            &[("average", &number(round_step(average, CALORIE_STEP), locale))],
+        ));
+    }
+
+    Some(GoalSuggestion {
+        kind: GoalSuggestionKind::Calories,
+        title: i18n::tf(locale, "goal.suggestion.calories.title", &[("target", &number(target, locale))]),
+        goal_type: GoalType::CalorieIntake,
+        target_value: target as f32,
+        unit: "kcal".to_string(),
+        rationale,
+    })
+}
+
+fn protein_suggestion(inputs: &SuggestionInputs, locale: Locale) -> Option<GoalSuggestion> {
+    let weight = inputs.weight_kg?;
+    let per_kg = match inputs.objective {
+        Objective::Lose | Objective::Gain => PROTEIN_G_PER_KG_ACTIVE_GOAL,
+        Objective::Maintain => PROTEIN_G_PER_KG,
+    };
+    let target = round_step(weight * per_kg, PROTEIN_STEP);
+
+    Some(GoalSuggestion {
+        kind: GoalSuggestionKind::Protein,
+        title: i18n::tf(locale, "goal.suggestion.protein.title", &[("target", &number(target, locale))]),
+        goal_type: GoalType::ProteinIntake,
+        target_value: target as f32,
+        unit: "g".to_string(),
+        rationale: i18n::tf(
+            locale,
+            "goal.suggestion.protein",
+            &[
+                ("per_kg", &number(per_kg, locale)),
+                ("weight", &number(weight, locale)),
+                ("target", &number(target, locale)),
+            ],
+        ),
+    })
+}
+
+fn water_suggestion(inputs: &SuggestionInputs, locale: Locale) -> Option<GoalSuggestion> {
+    let weight = inputs.weight_kg?;
+    let target = round_step(weight * WATER_ML_PER_KG, WATER_STEP);
+
+    Some(GoalSuggestion {
+        kind: GoalSuggestionKind::Water,
+        title: i18n::tf(locale, "goal.suggestion.water.title", &[("target", &number(target, locale))]),
+        goal_type: GoalType::Water,
+        target_value: target as f32,
+        unit: "ml".to_string(),
+        rationale: i18n::tf(
+            locale,
+            "goal.suggestion.water",
+            &[
+                ("per_kg", &number(WATER_ML_PER_KG, locale)),
+                ("weight", &number(weight, locale)),
+                ("target", &number(target, locale)),
+            ],
+        ),
+    })
+}
+
+fn waste_suggestion(inputs: &SuggestionInputs, locale: Locale) -> Option<GoalSuggestion> {
+    let waste = inputs.waste_percentage.filter(|waste| *waste > WASTE_THRESHOLD_PERCENT)?;
+    let target = format_percent(WASTE_TARGET_PERCENT as f64, locale);
+
+    Some(GoalSuggestion {
+        kind: GoalSuggestionKind::WasteReduction,
+        title: i18n::tf(locale, "goal.suggestion.waste.title", &[("target", &target)]),
+        goal_type: GoalType::Other,
+        target_value: WASTE_TARGET_PERCENT,
+        unit: WASTE_GOAL_UNIT.to_string(),
+        rationale: i18n::tf(
+            locale,
+            "goal.suggestion.waste",
+            &[("percent", &format_percent(waste, locale)), ("target", &target)],
+        ),
+    })
+}
+
+/// Какое предложение закрывает уже существующая цель
+fn covered_kind(goal_type: &GoalType, unit: &str) -> Option<GoalSuggestionKind> {
+    match goal_type {
+        GoalType::CalorieIntake => Some(GoalSuggestionKind::Calories),
+        GoalType::ProteinIntake => Some(GoalSuggestionKind::Protein),
+        GoalType::Water => Some(GoalSuggestionKind::Water),
+        GoalType::Other if unit == WASTE_GOAL_UNIT => Some(GoalSuggestionKind::WasteReduction),
+        _ => None,
+    }
+}
+
+fn round_step(value: f64, step: f64) -> f64 {
+    (value / step).round() * step
+}
+
+fn number(value: f64, locale: Locale) -> String {
+    format_quantity(value as f32, "", locale)
+}
+
+#[derive(sqlx::FromRow)]
+struct ProfileRow {
+    weight: Option<f32>,
+    height: Option<f32>,
+    date_of_birth: Option<DateTime<Utc>>,
+    gender: Option<String>,
+    activity_level: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DiaryActivityRow {
+    days_logged: i64,
+    average_calories: Option<f64>,
+}
+
+/// Предложенные цели после онбординга. Ничего не хранится: предложения считаются
+/// при каждом запросе и меняются вместе с данными, а округление целей гасит мелкие колебания.
+pub struct GoalSuggestionService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl GoalSuggestionService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn inputs(&self, user_id: Uuid) -> Result<SuggestionInputs, AppError> {
+        let now = self.clock.now();
+        let utc_offset = PreferencesService::new(self.pool.clone())
+            .for_user(user_id)
+            .await?
+            .general
+            .utc_offset_minutes;
+        let today = (now + Duration::minutes(utc_offset as i64)).date_naive();
+
+        let profile: ProfileRow = sqlx::query_as(
+            "SELECT weight, height, date_of_birth, gender, activity_level FROM users WHERE id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let diary: DiaryActivityRow = UserScope::new(user_id)
+            .query_as(
+                r#"
+                SELECT COUNT(DISTINCT (consumed_at AT TIME ZONE 'UTC' + make_interval(mins => $2))::date) AS days_logged,
+                       (SUM(calories_per_100g * portion_size / 100) FILTER (WHERE consumed_at >= $3)
+                        / NULLIF(COUNT(DISTINCT (consumed_at AT TIME ZONE 'UTC' + make_interval(mins => $2))::date)
+                                 FILTER (WHERE consumed_at >= $3), 0))::float8 AS average_calories
+                FROM diary_entries
+                WHERE user_id = $1
+                "#
+            )
+            .bind(utc_offset)
+            .bind(now - Duration::days(MIN_LOGGED_DAYS))
+            .fetch_one(&self.pool)
+            .await?;
+
+        let goals: Vec<(GoalType, String)> = UserScope::new(user_id)
+            .query_as("SELECT goal_type, unit FROM goals WHERE user_id = $1 AND status IN ('active', 'paused')")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let analytics = FridgeService::new(self.pool.clone())
+            .with_clock(self.clock.clone())
+            .get_expense_analytics(user_id, "month", AnalyticsOptions::default())
+            .await?;
+        let waste_percentage = (analytics.total_wasted > 0.0).then_some(analytics.waste_percentage);
+
+        Ok(SuggestionInputs {
+            weight_kg: profile.weight.map(f64::from),
+            height_cm: profile.height.map(f64::from),
+            age_years: profile.date_of_birth.and_then(|birth| today.years_since(birth.date_naive())),
+            gender: profile.gender,
+            activity_level: profile.activity_level,
+            objective: objective(&goals),
+            days_logged: diary.days_logged,
+            average_calories: diary.average_calories,
+            waste_percentage,
+            covered: goals.iter().filter_map(|(goal_type, unit)| covered_kind(goal_type, unit)).collect(),
+        })
+    }
+
+    /// Создает цель из предложения. Предложения нет (уже есть такая цель, не хватает данных) — NotFound.
+    pub async fn accept(&self, user_id: Uuid, kind: GoalSuggestionKind, locale: Locale) -> Result<Goal, AppError> {
+        let inputs = self.inputs(user_id).await?;
+        let suggestion = suggest(&inputs, locale)
+            .into_iter()
+            .find(|suggestion| suggestion.kind == kind)
+            .ok_or_else(|| AppError::NotFound("Goal suggestion not available".to_string()))?;
+
+        let daily_target = (kind != GoalSuggestionKind::WasteReduction).then_some(suggestion.target_value);
+        GoalService::new(self.pool.clone())
+            .with_clock(self.clock.clone())
+            .create_goal(CreateGoal {
+                user_id,
+                title: suggestion.title,
+                description: Some(suggestion.rationale),
+                goal_type: suggestion.goal_type,
+                target_value: suggestion.target_value,
+                current_value: 0.0,
+                unit: suggestion.unit,
+                target_date: None,
+                daily_target,
+                weekly_target: None,
+                status: GoalStatus::Active,
+                reminder_days: Vec::new(),
+            })
+            .await
+    }
+}
+
+/// Цель веса определяет, что пользователь хочет сделать с весом
+fn objective(goals: &[(GoalType, String)]) -> Objective {
+    goals
+        .iter()
+        .find_map(|(goal_type, _)| match goal_type {
+            GoalType::WeightLoss => Some(Objective::Lose),
+            GoalType::WeightGain => Some(Objective::Gain),
+            GoalType::MaintainWeight => Some(Objective::Maintain),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_user};
+
+    fn profile() -> SuggestionInputs {
+        SuggestionInputs {
+            weight_kg: Some(80.0),
+            height_cm: Some(180.0),
+            age_years: Some(30),
+            gender: Some("male".to_string()),
+            activity_level: Some("moderate".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn kinds(inputs: &SuggestionInputs) -> Vec<GoalSuggestionKind> {
+        suggest(inputs, Locale::Ru).into_iter().map(|suggestion| suggestion.kind).collect()
+    }
+
+    fn target(inputs: &SuggestionInputs, kind: GoalSuggestionKind) -> f32 {
+        suggest(inputs, Locale::Ru).into_iter().find(|suggestion| suggestion.kind == kind).unwrap().target_value
+    }
+
+    #[test]
+    fn suggestions_wait_for_a_week_of_logs_or_a_filled_profile() {
+        let empty = SuggestionInputs { weight_kg: Some(80.0), days_logged: MIN_LOGGED_DAYS - 1, ..Default::default() };
+        assert!(kinds(&empty).is_empty());
+
+        let logged = SuggestionInputs { days_logged: MIN_LOGGED_DAYS, ..empty };
+        assert_eq!(kinds(&logged), vec![GoalSuggestionKind::Protein, GoalSuggestionKind::Water]);
+
+        let no_activity = SuggestionInputs { activity_level: None, ..profile() };
+        assert!(kinds(&no_activity).is_empty());
+        assert_eq!(
+            kinds(&profile()),
+            vec![GoalSuggestionKind::Calories, GoalSuggestionKind::Protein, GoalSuggestionKind::Water],
+        );
+    }
+
+    #[test]
+    fn calories_need_the_age_and_follow_the_weight_objective() {
+        let no_age = SuggestionInputs { age_years: None, ..profile() };
+        assert!(!kinds(&no_age).contains(&GoalSuggestionKind::Calories));
+
+        let tdee = round_step(estimated_tdee(80.0, 180.0, 30, Some("male"), Some("moderate")), CALORIE_STEP) as f32;
+        assert_eq!(target(&profile(), GoalSuggestionKind::Calories), tdee);
+        let lose = SuggestionInputs { objective: Objective::Lose, ..profile() };
+        assert_eq!(target(&lose, GoalSuggestionKind::Calories), tdee - CALORIE_DEFICIT as f32);
+        let gain = SuggestionInputs { objective: Objective::Gain, ..profile() };
+        assert_eq!(target(&gain, GoalSuggestionKind::Calories), tdee + CALORIE_SURPLUS as f32);
+
+        // Средние калории попадают в объяснение
+        let logged = SuggestionInputs { average_calories: Some(2_180.0), ..profile() };
+        let calories = suggest(&logged, Locale::Ru).remove(0);
+        assert!(calories.rationale.contains("2200") || calories.rationale.contains("2 200"), "{}", calories.rationale);
+    }
+
+    #[test]
+    fn protein_and_water_scale_with_weight_in_rounded_steps() {
+        assert_eq!(target(&profile(), GoalSuggestionKind::Protein), 95.0);
+        let lose = SuggestionInputs { objective: Objective::Lose, ..profile() };
+        assert_eq!(target(&lose, GoalSuggestionKind::Protein), 130.0);
+        assert_eq!(target(&profile(), GoalSuggestionKind::Water), 2_400.0);
+
+        let slightly_heavier = SuggestionInputs { weight_kg: Some(81.0), ..profile() };
+        assert_eq!(target(&slightly_heavier, GoalSuggestionKind::Water), 2_400.0);
+    }
+
+    #[test]
+    fn waste_goal_is_suggested_only_above_the_threshold() {
+        let at_threshold = SuggestionInputs { waste_percentage: Some(WASTE_THRESHOLD_PERCENT), ..profile() };
+        assert!(!kinds(&at_threshold).contains(&GoalSuggestionKind::WasteReduction));
+
+        let wasteful = SuggestionInputs { waste_percentage: Some(22.5), ..profile() };
+        let waste = suggest(&wasteful, Locale::Ru).pop().unwrap();
+        assert_eq!(waste.kind, GoalSuggestionKind::WasteReduction);
+        assert_eq!(waste.goal_type, GoalType::Other);
+        assert_eq!((waste.target_value, waste.unit.as_str()), (WASTE_TARGET_PERCENT, WASTE_GOAL_UNIT));
+    }
+
+    #[test]
+    fn existing_goals_suppress_their_suggestions() {
+        let covered = SuggestionInputs {
+            waste_percentage: Some(30.0),
+            covered: vec![GoalSuggestionKind::Calories, GoalSuggestionKind::WasteReduction],
+            ..profile()
+        };
+        assert_eq!(kinds(&covered), vec![GoalSuggestionKind::Protein, GoalSuggestionKind::Water]);
+
+        assert_eq!(covered_kind(&GoalType::Water, "ml"), Some(GoalSuggestionKind::Water));
+        assert_eq!(covered_kind(&GoalType::Other, WASTE_GOAL_UNIT), Some(GoalSuggestionKind::WasteReduction));
+        assert_eq!(covered_kind(&GoalType::Other, "шт"), None);
+        assert_eq!(objective(&[(GoalType::Water, "ml".to_string()), (GoalType::WeightGain, "kg".to_string())]), Objective::Gain);
+    }
+
+    #[sqlx::test]
+    async fn accepted_or_paused_goals_are_not_suggested_again(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let now = Utc.with_ymd_and_hms(2026, 10, 10, 12, 0, 0).unwrap();
+        // Неделя записей без профиля: белок и вода требуют вес
+        for day in 0..MIN_LOGGED_DAYS {
+            insert_diary_entry(&pool, user_id, "lunch", 2_000.0, 80.0, now - Duration::days(day)).await;
+        }
+        let service = GoalSuggestionService::new(pool.clone()).with_clock(frozen_clock(now));
+        assert!(kinds(&service.inputs(user_id).await.unwrap()).is_empty());
+
+        sqlx::query("UPDATE users SET weight = 70 WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+        let inputs = service.inputs(user_id).await.unwrap();
+        assert_eq!(inputs.days_logged, MIN_LOGGED_DAYS);
+        assert_eq!(kinds(&inputs), vec![GoalSuggestionKind::Protein, GoalSuggestionKind::Water]);
+
+        let water = service.accept(user_id, GoalSuggestionKind::Water, Locale::Ru).await.unwrap();
+        assert_eq!((water.target_value, water.daily_target), (2_100.0, Some(2_100.0)));
+        assert_eq!(kinds(&service.inputs(user_id).await.unwrap()), vec![GoalSuggestionKind::Protein]);
+        assert!(matches!(
+            service.accept(user_id, GoalSuggestionKind::Water, Locale::Ru).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        // Цель на паузе тоже закрывает предложение, завершенная — нет
+        sqlx::query("UPDATE goals SET status = 'paused' WHERE id = $1").bind(water.id).execute(&pool).await.unwrap();
+        assert_eq!(kinds(&service.inputs(user_id).await.unwrap()), vec![GoalSuggestionKind::Protein]);
+        sqlx::query("UPDATE goals SET status = 'completed' WHERE id = $1").bind(water.id).execute(&pool).await.unwrap();
+        assert_eq!(kinds(&service.inputs(user_id).await.unwrap()), vec![GoalSuggestionKind::Protein, GoalSuggestionKind::Water]);
+        assert!(matches!(
+            service.accept(user_id, GoalSuggestionKind::Calories, Locale::Ru).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
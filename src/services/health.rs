@@ -3,16 +3,36 @@ use chrono::{Utc, Datelike};
 use crate::{
     models::user::UserProfile,
     api::goals::HealthStatsResponse,
+    services::clock::{self, SharedClock},
     utils::errors::AppError,
 };
 
+/// Коэффициент активности для расчета TDEE
+pub fn activity_multiplier(activity_level: Option<&str>) -> f32 {
+    match activity_level {
+        Some("sedentary") => 1.2,
+        Some("lightly_active") => 1.375,
+        Some("moderately_active") => 1.55,
+        Some("very_active") => 1.725,
+        Some("extremely_active") => 1.9,
+        _ => 1.375, // Default to lightly active
+    }
+}
+
 pub struct HealthService {
     pool: crate::db::DbPool,
+    clock: SharedClock,
 }
 
 impl HealthService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { pool }
+        Self { pool, clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — по ним считается возраст
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub async fn get_user_profile(&self, user_id: Uuid) -> Result<UserProfile, AppError> {
@@ -51,17 +71,7 @@ impl HealthService {
         let bmr = self.calculate_bmr(user_id).await?;
         let profile = self.get_user_profile(user_id).await?;
         
-        // Activity multipliers
-        let activity_multiplier = match profile.activity_level.as_deref() {
-            Some("sedentary") => 1.2,
-            Some("lightly_active") => 1.375,
-            Some("moderately_active") => 1.55,
-            Some("very_active") => 1.725,
-            Some("extremely_active") => 1.9,
-            _ => 1.375, // Default to lightly active
-        };
-
-        Ok(bmr * activity_multiplier)
+        Ok(bmr * activity_multiplier(profile.activity_level.as_deref()))
     }
 
     pub async fn get_comprehensive_stats(&self, user_id: Uuid) -> Result<HealthStatsResponse, AppError> {
@@ -109,7 +119,7 @@ impl HealthService {
             .unwrap()
             .with_timezone(&Utc);
         
-        let now = Utc::now().date_naive();
+        let now = self.clock.now().date_naive();
         let dob_date = birth_date.date_naive();
         let years = now.year() - dob_date.year();
         let age = if now.month() < dob_date.month() || (now.month() == dob_date.month() && now.day() < dob_date.day()) {
@@ -141,7 +151,7 @@ impl HealthService {
             following_count: 89,
             posts_count: 23,
             recipes_count: 15,
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
         })
     }
 }
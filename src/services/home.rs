@@ -0,0 +1,300 @@
+use std::future::Future;
+use std::time::Duration;
+
+use uuid::Uuid;
+use chrono::NaiveDate;
+use tracing::warn;
+
+use crate::{
+    api::home::{ActiveGoalsSummary, CaloriesSummary, HomeSummaryResponse},
+    db::DbPool,
+    models::goal::GoalStatus,
+    services::{clock::{self, SharedClock}, diary::DiaryService, fridge::FridgeService, goal::GoalService, notifications::NotificationService},
+    utils::errors::AppError,
+};
+
+/// Сколько ждем каждый подзапрос; медленный подзапрос превращается в `null`, а не тормозит весь ответ
+const SUBQUERY_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// "Скоро испортится" на главном экране — те же 3 дня, что и в уведомлениях
+const EXPIRING_DAYS: u32 = 3;
+
+/// Насколько далеко назад ищем непрерывную серию дней с записями в дневнике
+const STREAK_LOOKBACK_DAYS: i64 = 365;
+
+/// Компактная сводка для главного экрана мобильного приложения
+pub struct HomeService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl HomeService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Все подзапросы выполняются параллельно; ошибка или таймаут одного из них дает `null` в своем поле
+    pub async fn get_summary(&self, user_id: Uuid) -> HomeSummaryResponse {
+        let today = self.clock.now().date_naive();
+
+        let (fridge_items, expiring_soon, calories_today, active_goals, unread_notifications, diary_streak_days) = tokio::join!(
+            degrade("fridge_items", self.fridge_items_count(user_id)),
+            degrade("expiring_soon", self.expiring_count(user_id)),
+            degrade("calories_today", self.calories_today(user_id, today)),
+            degrade("active_goals", self.active_goals(user_id)),
+            degrade("unread_notifications", self.unread_notifications(user_id)),
+            degrade("diary_streak_days", self.diary_streak(user_id, today)),
+        );
+
+        HomeSummaryResponse {
+            fridge_items,
+            expiring_soon,
+            calories_today,
+            active_goals,
+            unread_notifications,
+            diary_streak_days,
+        }
+    }
+
+    async fn fridge_items_count(&self, user_id: Uuid) -> Result<usize, AppError> {
+        let fridge_service = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone());
+        Ok(fridge_service.get_user_items(user_id, None, None, None).await?.len())
+    }
+
+    async fn expiring_count(&self, user_id: Uuid) -> Result<usize, AppError> {
+        let fridge_service = FridgeService::new(self.pool.clone()).with_clock(self.clock.clone());
+        Ok(fridge_service.get_expiring_items(user_id, Some(EXPIRING_DAYS), true).await?.len())
+    }
+
+    async fn calories_today(&self, user_id: Uuid, today: NaiveDate) -> Result<CaloriesSummary, AppError> {
+        let diary_service = DiaryService::new(self.pool.clone()).with_clock(self.clock.clone());
+        let summary = diary_service.get_daily_summary(user_id, today).await?;
+
+        Ok(CaloriesSummary {
+            consumed: summary.total_calories.round() as i32,
+            goal: summary.calorie_goal.map(|goal| goal.round() as i32),
+        })
+    }
+
+    async fn active_goals(&self, user_id: Uuid) -> Result<ActiveGoalsSummary, AppError> {
+        let goal_service = GoalService::new(self.pool.clone()).with_clock(self.clock.clone());
+        let goals = goal_service.get_user_goals(user_id, None, Some(GoalStatus::Active), false, 100, 0).await?;
+
+        Ok(ActiveGoalsSummary {
+            count: goals.len(),
+            closest_deadline: goals.iter().filter_map(|goal| goal.target_date).min(),
+        })
+    }
+
+    async fn unread_notifications(&self, user_id: Uuid) -> Result<i64, AppError> {
+        NotificationService::new(self.pool.clone()).unread_count(user_id).await
+    }
+
+    /// Сколько дней подряд (по сегодня или по вчера включительно) в дневнике есть записи
+    async fn diary_streak(&self, user_id: Uuid, today: NaiveDate) -> Result<u32, AppError> {
+        let days: Vec<(NaiveDate,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT consumed_at::date AS day
+            FROM diary_entries
+            WHERE user_id = $1 AND consumed_at >= $2
+            ORDER BY day DESC
+            "#
+        )
+        .bind(user_id)
+        .bind(self.clock.now() - chrono::Duration::days(STREAK_LOOKBACK_DAYS))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(count_streak(today, days.into_iter().map(|(day,)| day)))
+    }
+}
+
+/// Дни идут по убыванию. Сегодняшний день может быть еще не заполнен — тогда серия считается со вчера.
+pub fn count_streak(today: NaiveDate, days: impl Iterator<Item = NaiveDate>) -> u32 {
+    let mut expected = today;
+    let mut streak = 0;
+
+    for day in days {
+        if day > expected {
+            continue;
+        }
+        if day == expected {
+            streak += 1;
+        } else if streak == 0 && day == today - chrono::Duration::days(1) {
+            streak = 1;
+        } else {
+            break;
+        }
+        expected = day - chrono::Duration::days(1);
+    }
+
+    streak
+}
+
+async fn degrade<T, F>(field: &'static str, query: F) -> Option<T>
+where
+    F: Future<Output = Result<T, AppError>>,
+{
+    match tokio::time::timeout(SUBQUERY_TIMEOUT, query).await {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            warn!("Home summary: {} failed: {:?}", field, e);
+            None
+        }
+        Err(_) => {
+            warn!("Home summary: {} timed out", field);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::fridge::{CreateFridgeItem, FridgeCategory};
+    use crate::services::clock::SandboxClockMode;
+    use crate::test_support::{access_token, delayed_pool, frozen_clock, insert_diary_entry, insert_fridge_item, insert_user, test_router};
+    use axum::http::{header, Request, StatusCode};
+    use hyper::Body;
+    use std::sync::atomic::Ordering;
+    use tower::ServiceExt;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn streak_counts_from_yesterday_when_today_is_empty() {
+        let day = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+
+        assert_eq!(count_streak(day(16), [day(16), day(15), day(13)].into_iter()), 2);
+        assert_eq!(count_streak(day(16), [day(15), day(14)].into_iter()), 2);
+        assert_eq!(count_streak(day(16), [day(14)].into_iter()), 0);
+    }
+
+    #[sqlx::test]
+    async fn sandbox_clock_moves_expiring_items_and_daily_summary_across_midnight(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let clock = frozen_clock(at(16, 23, 30));
+        let fridge_service = FridgeService::new(pool.clone()).with_clock(clock.clone());
+        let home_service = HomeService::new(pool.clone()).with_clock(clock.clone());
+
+        // Окно «скоро испортится» — 3 дня от текущего момента: 19.10 23:30, после полуночи — 20.10 00:30
+        fridge_service
+            .add_item(CreateFridgeItem {
+                user_id,
+                name: "Йогурт".to_string(),
+                brand: None,
+                quantity: 1.0,
+                unit: "шт".to_string(),
+                category: FridgeCategory::Dairy,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date: Some(at(19, 23, 50)),
+                purchase_date: at(15, 10, 0),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            })
+            .await
+            .unwrap();
+        insert_diary_entry(&pool, user_id, "dinner", 300.0, 10.0, at(16, 20, 0)).await;
+
+        let before = home_service.get_summary(user_id).await;
+        assert!(fridge_service.get_expiring_items(user_id, Some(EXPIRING_DAYS), true).await.unwrap().is_empty());
+        assert_eq!(before.expiring_soon, Some(0));
+        assert_eq!(before.calories_today.map(|calories| calories.consumed), Some(300));
+        assert_eq!(before.diary_streak_days, Some(1));
+
+        clock.set_mode(SandboxClockMode::Frozen { at: at(17, 0, 30) });
+
+        let after = home_service.get_summary(user_id).await;
+        let expiring = fridge_service.get_expiring_items(user_id, Some(EXPIRING_DAYS), true).await.unwrap();
+        assert_eq!(expiring.iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), ["Йогурт"]);
+        assert_eq!(after.expiring_soon, Some(1));
+        assert_eq!(after.calories_today.map(|calories| calories.consumed), Some(0));
+        // Вчерашняя запись продолжает серию, пока сегодня ничего не записано
+        assert_eq!(after.diary_streak_days, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn sub_queries_run_concurrently(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, at(15, 10, 0)).await;
+        insert_diary_entry(&pool, user_id, "breakfast", 250.0, 10.0, at(16, 8, 0)).await;
+
+        let delay = Duration::from_millis(100);
+        let (slow, acquired) = delayed_pool(&pool, delay);
+        let home_service = HomeService::new(slow).with_clock(frozen_clock(at(16, 12, 0)));
+
+        let started = std::time::Instant::now();
+        let summary = home_service.get_summary(user_id).await;
+        let elapsed = started.elapsed();
+
+        // Каждый запрос ждет `delay`: последовательно это заняло бы их сумму
+        let sequential = delay * acquired.load(Ordering::SeqCst) as u32;
+        assert!(acquired.load(Ordering::SeqCst) >= 6);
+        assert!(elapsed < sequential, "took {:?}, sequential would take {:?}", elapsed, sequential);
+        assert!(elapsed < SUBQUERY_TIMEOUT, "took {:?}", elapsed);
+
+        // Ни один подзапрос не упал в таймаут
+        assert_eq!(summary.fridge_items, Some(1));
+        assert_eq!(summary.expiring_soon, Some(0));
+        assert_eq!(summary.calories_today.map(|calories| calories.consumed), Some(250));
+        assert_eq!(summary.active_goals.map(|goals| goals.count), Some(0));
+        assert_eq!(summary.unread_notifications, Some(0));
+        assert_eq!(summary.diary_streak_days, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn failed_sub_query_degrades_to_null(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, at(15, 10, 0)).await;
+        sqlx::query("DROP TABLE notifications CASCADE").execute(&pool).await.unwrap();
+
+        let summary = HomeService::new(pool.clone()).with_clock(frozen_clock(at(16, 12, 0))).get_summary(user_id).await;
+
+        assert_eq!(summary.unread_notifications, None);
+        assert_eq!(summary.fridge_items, Some(1));
+        assert_eq!(summary.diary_streak_days, Some(0));
+        let body = serde_json::to_value(&summary).unwrap();
+        assert!(body["unread_notifications"].is_null());
+    }
+
+    #[sqlx::test]
+    async fn summary_is_cached_privately_and_fits_the_budget(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+
+        let request = Request::get("/api/v1/home/summary")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router(&pool).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "private, max-age=60");
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(bytes.len() < 2048, "{} bytes", bytes.len());
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let mut keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            ["active_goals", "calories_today", "diary_streak_days", "expiring_soon", "fridge_items", "unread_notifications"]
+        );
+    }
+}
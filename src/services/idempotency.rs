@@ -0,0 +1,336 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{db::DbPool, telemetry, utils::errors::AppError};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Сколько хранится ответ на запрос с ключом
+const KEY_TTL_HOURS: i64 = 24;
+
+/// Запрос "в процессе" дольше этого считается прерванным (например, перезапуском сервера),
+/// и ключ можно занять заново
+const IN_PROGRESS_TIMEOUT_SECONDS: i64 = 120;
+
+pub const MAX_KEY_LENGTH: usize = 255;
+
+const CLEANUP_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+/// Чем закончилась попытка занять ключ
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// Ключ занят этим запросом — обработчик нужно выполнить
+    Acquired,
+    /// Тот же запрос уже выполнен — вернуть сохраненный ответ
+    Replay { status: u16, body: Vec<u8> },
+    /// Тот же запрос выполняется прямо сейчас
+    InProgress,
+    /// Ключ уже использован с другим телом запроса
+    Mismatch,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredKey {
+    request_hash: String,
+    status: String,
+    response_status: Option<i16>,
+    response_body: Option<Vec<u8>>,
+}
+
+/// Хранилище ключей идемпотентности. Ключ уникален в пределах пользователя;
+/// одновременные запросы с одним ключом разделяет первичный ключ таблицы.
+#[derive(Clone)]
+pub struct IdempotencyService {
+    pool: DbPool,
+}
+
+impl IdempotencyService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Атомарно занимает ключ. Просроченный или брошенный "в процессе" ключ занимается заново.
+    pub async fn claim(&self, user_id: Uuid, key: &str, request_hash: &str) -> Result<IdempotencyClaim, AppError> {
+        let acquired = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (user_id, idempotency_key, request_hash, status, expires_at)
+            VALUES ($1, $2, $3, 'in_progress', $4)
+            ON CONFLICT (user_id, idempotency_key) DO UPDATE
+            SET request_hash = EXCLUDED.request_hash,
+                status = 'in_progress',
+                response_status = NULL,
+                response_body = NULL,
+                created_at = NOW(),
+                expires_at = EXCLUDED.expires_at
+            WHERE idempotency_keys.expires_at < NOW()
+               OR (idempotency_keys.status = 'in_progress'
+                   AND idempotency_keys.created_at < NOW() - make_interval(secs => $5))
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(request_hash)
+        .bind(Utc::now() + Duration::hours(KEY_TTL_HOURS))
+        .bind(IN_PROGRESS_TIMEOUT_SECONDS as f64)
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if acquired {
+            return Ok(IdempotencyClaim::Acquired);
+        }
+
+        let stored = sqlx::query_as::<_, StoredKey>(
+            r#"
+            SELECT request_hash, status, response_status, response_body
+            FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        // Ключ удалили между запросами (ошибка первого запроса) — клиент может повторить
+        let Some(stored) = stored else {
+            return Ok(IdempotencyClaim::InProgress);
+        };
+
+        Ok(classify(stored, request_hash))
+    }
+
+    /// Сохраняет ответ успешно выполненного запроса
+    pub async fn complete(&self, user_id: Uuid, key: &str, status: u16, body: &[u8]) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = 'completed', response_status = $3, response_body = $4
+            WHERE user_id = $1 AND idempotency_key = $2
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(status as i16)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Освобождает ключ после неуспешного запроса: повтор выполнится заново
+    pub async fn release(&self, user_id: Uuid, key: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2 AND status = 'in_progress'")
+            .bind(user_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self) -> Result<u64, AppError> {
+        let deleted = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(deleted)
+    }
+
+    /// Запускает ежечасное удаление просроченных ключей
+    pub fn start_cleanup_task(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match service.delete_expired().instrument(telemetry::job_span("idempotency_cleanup")).await {
+                    Ok(deleted) if deleted > 0 => info!("Deleted {} expired idempotency keys", deleted),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to delete expired idempotency keys: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+fn classify(stored: StoredKey, request_hash: &str) -> IdempotencyClaim {
+    if stored.request_hash != request_hash {
+        return IdempotencyClaim::Mismatch;
+    }
+
+    match (stored.status.as_str(), stored.response_status, stored.response_body) {
+        ("completed", Some(status), Some(body)) => IdempotencyClaim::Replay { status: status as u16, body },
+        _ => IdempotencyClaim::InProgress,
+    }
+}
+
+/// Отпечаток запроса: метод, путь с параметрами и тело
+pub fn request_hash(method: &str, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for part in [method.as_bytes(), path_and_query.as_bytes(), body] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::RETRY_AFTER, HeaderValue, Method, Request, StatusCode};
+    use axum::body::Body;
+    use serde_json::{json, Value};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    fn add_item(token: &str, key: &str, body: Value) -> Request<Body> {
+        let mut request = request(Method::POST, "/api/v1/fridge", Some(token), Some(body));
+        request.headers_mut().insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        request
+    }
+
+    fn kefir() -> Value {
+        json!({ "name": "Кефир", "quantity": 1, "unit": "l", "category": "Dairy" })
+    }
+
+    async fn items_count(pool: &PgPool, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM fridge_items WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn hash_covers_method_path_and_body() {
+        let base = request_hash("POST", "/api/v1/fridge", b"{}");
+        assert_eq!(base, request_hash("POST", "/api/v1/fridge", b"{}"));
+        assert_ne!(base, request_hash("PUT", "/api/v1/fridge", b"{}"));
+        assert_ne!(base, request_hash("POST", "/api/v1/diary", b"{}"));
+        assert_ne!(base, request_hash("POST", "/api/v1/fridge", b"{ }"));
+        // Границы частей учитываются: перенос байта из пути в тело дает другой отпечаток
+        assert_ne!(request_hash("POST", "/a", b"b"), request_hash("POST", "/ab", b""));
+    }
+
+    #[sqlx::test]
+    async fn retry_with_the_same_key_replays_the_stored_response(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user).await;
+
+        let (status, first) = send(&router, add_item(&token, "retry-1", kefir())).await;
+        assert!(status.is_success());
+
+        let response = tower::ServiceExt::oneshot(router.clone(), add_item(&token, "retry-1", kefir())).await.unwrap();
+        assert_eq!(response.status(), status);
+        assert_eq!(response.headers()["idempotent-replayed"], "true");
+        let replayed: Value = serde_json::from_slice(&hyper::body::to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(replayed, first);
+        assert_eq!(items_count(&pool, user).await, 1);
+
+        // Ключ привязан к пользователю: у другого тот же ключ — новый запрос
+        let other = insert_user(&pool, "Борис").await;
+        let other_token = access_token(&pool, other).await;
+        let (_, other_item) = send(&router, add_item(&other_token, "retry-1", kefir())).await;
+        assert_ne!(other_item["id"], first["id"]);
+
+        // Без заголовка повтор создает дубликат, как и раньше
+        send(&router, request(Method::POST, "/api/v1/fridge", Some(&token), Some(kefir()))).await;
+        assert_eq!(items_count(&pool, user).await, 2);
+    }
+
+    #[sqlx::test]
+    async fn same_key_with_another_body_is_rejected(pool: PgPool) {
+        let router = test_router(&pool);
+        let user = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user).await;
+
+        send(&router, add_item(&token, "mismatch", kefir())).await;
+        let (status, body) = send(&router, add_item(&token, "mismatch", json!({ "name": "Сыр", "quantity": 1, "unit": "kg", "category": "Dairy" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "ERR_IDEMPOTENCY_MISMATCH");
+        assert_eq!(items_count(&pool, user).await, 1);
+
+        let (status, _) = send(&router, add_item(&token, &"k".repeat(MAX_KEY_LENGTH + 1), kefir())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Неуспешный ответ не сохраняется: исправленный запрос с тем же ключом выполняется
+        let (status, _) = send(&router, add_item(&token, "fix-me", json!({ "name": "", "quantity": 1, "unit": "l", "category": "Dairy" }))).await;
+        assert!(status.is_client_error());
+        let (status, _) = send(&router, add_item(&token, "fix-me", kefir())).await;
+        assert!(status.is_success());
+        assert_eq!(items_count(&pool, user).await, 2);
+    }
+
+    #[sqlx::test]
+    async fn concurrent_requests_with_one_key_execute_once(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        let service = IdempotencyService::new(pool.clone());
+        let hash = request_hash("POST", "/api/v1/fridge", b"{}");
+
+        let claims = futures_util::future::join_all((0..8).map(|_| service.claim(user, "race", &hash))).await;
+        let acquired = claims.iter().filter(|claim| matches!(claim, Ok(IdempotencyClaim::Acquired))).count();
+        let in_progress = claims.iter().filter(|claim| matches!(claim, Ok(IdempotencyClaim::InProgress))).count();
+        assert_eq!((acquired, in_progress), (1, 7));
+
+        let router = test_router(&pool);
+        let token = access_token(&pool, user).await;
+        let response = tower::ServiceExt::oneshot(router.clone(), add_item(&token, "race", kefir())).await.unwrap();
+        // Тело другое, чем у занятого ключа, — это несовпадение, а не ожидание
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Пока первый запрос выполняется, повтор с тем же телом получает 409 с Retry-After:
+        // ответ первого запроса еще не сохранен
+        send(&router, add_item(&token, "in-flight", kefir())).await;
+        sqlx::query("UPDATE idempotency_keys SET status = 'in_progress', response_status = NULL, response_body = NULL WHERE idempotency_key = 'in-flight'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let response = tower::ServiceExt::oneshot(router.clone(), add_item(&token, "in-flight", kefir())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+        assert_eq!(items_count(&pool, user).await, 1);
+
+        // Одновременные запросы через роутер: продукт создается один раз
+        let responses = futures_util::future::join_all((0..4).map(|_| send(&router, add_item(&token, "parallel", kefir())))).await;
+        assert_eq!(items_count(&pool, user).await, 2);
+        let created: Vec<&Value> = responses.iter().filter(|(status, _)| status.is_success()).map(|(_, body)| body).collect();
+        assert!(!created.is_empty() && created.iter().all(|body| body["id"] == created[0]["id"]));
+        assert!(responses.iter().all(|(status, _)| status.is_success() || *status == StatusCode::CONFLICT));
+    }
+
+    #[sqlx::test]
+    async fn expired_and_abandoned_keys_can_be_claimed_again(pool: PgPool) {
+        let user = insert_user(&pool, "Анна").await;
+        let service = IdempotencyService::new(pool.clone());
+
+        assert!(matches!(service.claim(user, "done", "a").await.unwrap(), IdempotencyClaim::Acquired));
+        service.complete(user, "done", 201, b"{\"id\":1}").await.unwrap();
+        assert!(matches!(service.claim(user, "done", "a").await.unwrap(), IdempotencyClaim::Replay { status: 201, .. }));
+
+        assert!(matches!(service.claim(user, "abandoned", "b").await.unwrap(), IdempotencyClaim::Acquired));
+        sqlx::query("UPDATE idempotency_keys SET created_at = NOW() - INTERVAL '3 minutes' WHERE idempotency_key = 'abandoned'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(matches!(service.claim(user, "abandoned", "b").await.unwrap(), IdempotencyClaim::Acquired));
+
+        sqlx::query("UPDATE idempotency_keys SET expires_at = NOW() - INTERVAL '1 second' WHERE idempotency_key = 'done'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Через сутки ключ свободен, даже с другим телом
+        assert!(matches!(service.claim(user, "done", "c").await.unwrap(), IdempotencyClaim::Acquired));
+
+        sqlx::query("UPDATE idempotency_keys SET expires_at = NOW() - INTERVAL '1 second'").execute(&pool).await.unwrap();
+        assert_eq!(service.delete_expired().await.unwrap(), 2);
+    }
+}
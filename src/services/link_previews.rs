@@ -0,0 +1,795 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{redirect, Client, Url};
+use sqlx::types::Json;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        community::{LinkPreview, LinkPreviewKind, RecipeLinkPreview},
+        recipe::DifficultyLevel,
+    },
+    services::recipe_share::share_token_from_url,
+    utils::{errors::AppError, sanitize::sanitize_text},
+};
+
+/// Сколько ссылок поста получают карточку
+pub const MAX_LINK_PREVIEWS: usize = 3;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Дальше `<head>` страницы не читается
+const MAX_HTML_BYTES: usize = 512 * 1024;
+
+const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Неудачная загрузка кешируется ненадолго, чтобы не дергать недоступный сайт на каждом посте
+const FAILED_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_CACHED_URLS_PER_DOMAIN: usize = 50;
+const MAX_CACHED_DOMAINS: usize = 1000;
+
+const MAX_TITLE_LENGTH: usize = 200;
+const MAX_DESCRIPTION_LENGTH: usize = 500;
+const MAX_URL_LENGTH: usize = 2000;
+
+/// Данные OpenGraph внешней страницы (уже очищенные)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenGraph {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+}
+
+/// Ссылки http(s) из текста поста в порядке появления, без повторов.
+/// Посты, сохраненные до перехода на хранение неэкранированного текста, содержат `&amp;` —
+/// в адресе он возвращается к `&`.
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    content
+        .split(|c: char| c.is_whitespace() || c == '<' || c == '>')
+        .filter_map(|token| {
+            let start = token.find("https://").or_else(|| token.find("http://"))?;
+            let url = token[start..]
+                .replace("&amp;", "&")
+                .split(['"', '\''])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}'])
+                .to_string();
+            (url.len() <= MAX_URL_LENGTH).then_some(url)
+        })
+        .filter(|url| Url::parse(url).map(|parsed| parsed.host_str().is_some()).unwrap_or(false))
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+/// Теги `og:*`, `twitter:*` и `description` из HTML; без `og:title` — содержимое `<title>`.
+/// Относительный адрес картинки разрешается от адреса страницы, в карточку попадает только http(s).
+pub fn parse_open_graph(html: &str, page_url: &Url) -> OpenGraph {
+    // ASCII-нижний регистр не меняет длину строки: индексы совпадают с исходным HTML
+    let lower = html.to_ascii_lowercase();
+    let mut meta: HashMap<String, String> = HashMap::new();
+
+    let mut position = 0;
+    while let Some(offset) = lower[position..].find("<meta") {
+        let start = position + offset + "<meta".len();
+        let end = start + tag_end(&lower[start..]);
+        let attributes = tag_attributes(&html[start..end]);
+        position = end;
+
+        let key = attributes.get("property").or_else(|| attributes.get("name"));
+        if let (Some(key), Some(content)) = (key, attributes.get("content")) {
+            meta.entry(key.to_ascii_lowercase()).or_insert_with(|| content.clone());
+        }
+    }
+
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| meta.get(*key))
+            .map(|value| decode_entities(value).trim().to_string())
+            .find(|value| !value.is_empty())
+    };
+
+    let title = first(&["og:title", "twitter:title"]).or_else(|| {
+        let start = lower.find("<title")?;
+        let start = start + lower[start..].find('>')? + 1;
+        let end = start + lower[start..].find("</title")?;
+        Some(decode_entities(&html[start..end]).trim().to_string()).filter(|title| !title.is_empty())
+    });
+    let description = first(&["og:description", "twitter:description", "description"]);
+    let image_url = first(&["og:image:secure_url", "og:image", "og:image:url", "twitter:image"])
+        .and_then(|image| page_url.join(&image).ok())
+        .filter(|image| matches!(image.scheme(), "http" | "https"))
+        .map(String::from)
+        .filter(|image| image.len() <= MAX_URL_LENGTH);
+
+    OpenGraph {
+        title: title.map(|title| clean_text(&title, MAX_TITLE_LENGTH)),
+        description: description.map(|description| clean_text(&description, MAX_DESCRIPTION_LENGTH)),
+        image_url,
+    }
+}
+
+/// Конец тега: первый `>` вне кавычек значения атрибута (или конец текста)
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (index, ch) in tag.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') => quote = Some(ch),
+            (Some(open), _) if ch == open => quote = None,
+            (None, '>') => return index,
+            _ => {}
+        }
+    }
+    tag.len()
+}
+
+/// Атрибуты тега: `name="value"`, `name='value'` и `name=value`
+fn tag_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag.trim_start_matches('/');
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        if name_end == 0 {
+            break;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let after_eq = after_eq.trim_start();
+        let (value, remaining) = match after_eq.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let body = &after_eq[1..];
+                let end = body.find(quote).unwrap_or(body.len());
+                (&body[..end], body.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                (&after_eq[..end], &after_eq[end..])
+            }
+        };
+        attributes.entry(name).or_insert_with(|| value.to_string());
+        rest = remaining;
+    }
+
+    attributes
+}
+
+/// Основные именованные и числовые HTML-сущности
+fn decode_entities(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            ch.map(|ch| (ch, end))
+        });
+
+        match decoded {
+            Some((ch, end)) => {
+                output.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Схлопывает пробелы, обрезает по длине и убирает разметку
+fn clean_text(text: &str, max_chars: usize) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    sanitize_text(&collapsed.chars().take(max_chars).collect::<String>())
+}
+
+/// Адрес из публичного интернета. Запросы во внутреннюю сеть, на loopback и служебные
+/// диапазоны запрещены (защита от SSRF).
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ipv4(mapped),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00 // unique local
+                    || (first & 0xffc0) == 0xfe80 // link-local
+                    || (first == 0x2001 && ip.segments()[1] == 0x0db8) // documentation
+                    || (first == 0x0064 && ip.segments()[1] == 0xff9b)) // NAT64
+            }
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // CGNAT
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19)) // benchmarking
+        || a >= 240)
+}
+
+/// Загружает страницу и разбирает OpenGraph. Только http(s) на стандартных портах; хост
+/// резолвится заранее, и запрос идет на проверенный адрес, поэтому подмена DNS между
+/// проверкой и запросом не помогает. Редиректы не выполняются.
+pub async fn fetch_open_graph(url: &str) -> Result<OpenGraph, AppError> {
+    let parsed = Url::parse(url).map_err(|_| AppError::BadRequest("Invalid link".to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") || !matches!(parsed.port_or_known_default(), Some(80 | 443)) {
+        return Err(AppError::BadRequest("Only http(s) links on standard ports get a preview".to_string()));
+    }
+    // IPv6-адрес в ссылке записан в скобках, резолвер их не принимает
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| AppError::BadRequest("Invalid link".to_string()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<SocketAddr> = tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::lookup_host((host.as_str(), port)))
+        .await
+        .map_err(|_| AppError::ExternalService(format!("DNS lookup timed out for {}", host)))?
+        .map_err(|e| AppError::ExternalService(format!("DNS lookup failed for {}: {}", host, e)))?
+        .collect();
+    let address = match addresses.first() {
+        Some(_) if addresses.iter().any(|address| !is_public_ip(address.ip())) => {
+            return Err(AppError::BadRequest("Link points to a private network address".to_string()));
+        }
+        Some(address) => *address,
+        None => return Err(AppError::ExternalService(format!("Host {} did not resolve", host))),
+    };
+
+    let client = Client::builder()
+        .resolve(&host, address)
+        .redirect(redirect::Policy::none())
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(FETCH_TIMEOUT)
+        .user_agent("ITCookBot/1.0 (+link preview)")
+        .build()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build HTTP client: {}", e)))?;
+
+    let mut response = client
+        .get(parsed.clone())
+        .header(reqwest::header::ACCEPT, "text/html")
+        .send()
+        .await
+        .map_err(|e| AppError::ExternalService(format!("Link preview fetch failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(AppError::ExternalService(format!("Link preview fetch returned {}", response.status())));
+    }
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_ascii_lowercase().contains("text/html"))
+        .unwrap_or(false);
+    if !is_html {
+        return Err(AppError::ExternalService("Link does not point to an HTML page".to_string()));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::ExternalService(format!("Link preview fetch failed: {}", e)))?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_HTML_BYTES {
+            body.truncate(MAX_HTML_BYTES);
+            break;
+        }
+    }
+
+    Ok(parse_open_graph(&String::from_utf8_lossy(&body), &parsed))
+}
+
+type CachedPreview = (Instant, Option<OpenGraph>);
+
+/// Кеш OpenGraph по доменам: у одного домена хранится ограниченное число адресов,
+/// чтобы посты со ссылками на один сайт не вытесняли остальные
+#[derive(Clone, Default)]
+pub struct LinkPreviewCache {
+    domains: Arc<Mutex<HashMap<String, HashMap<String, CachedPreview>>>>,
+}
+
+impl LinkPreviewCache {
+    /// `Some(None)` — недавняя загрузка не удалась
+    fn get(&self, domain: &str, url: &str) -> Option<Option<OpenGraph>> {
+        let domains = self.domains.lock().ok()?;
+        let (fetched_at, preview) = domains.get(domain)?.get(url)?;
+        let ttl = if preview.is_some() { CACHE_TTL } else { FAILED_CACHE_TTL };
+        (fetched_at.elapsed() < ttl).then(|| preview.clone())
+    }
+
+    fn insert(&self, domain: &str, url: &str, preview: Option<OpenGraph>) {
+        let Ok(mut domains) = self.domains.lock() else {
+            return;
+        };
+
+        if !domains.contains_key(domain) && domains.len() >= MAX_CACHED_DOMAINS {
+            domains.retain(|_, urls| urls.values().any(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL));
+            if domains.len() >= MAX_CACHED_DOMAINS {
+                let oldest = domains
+                    .iter()
+                    .filter_map(|(name, urls)| urls.values().map(|(fetched_at, _)| *fetched_at).max().map(|at| (at, name.clone())))
+                    .min()
+                    .map(|(_, name)| name);
+                if let Some(oldest) = oldest {
+                    domains.remove(&oldest);
+                }
+            }
+        }
+
+        let urls = domains.entry(domain.to_string()).or_default();
+        if !urls.contains_key(url) && urls.len() >= MAX_CACHED_URLS_PER_DOMAIN {
+            let oldest = urls.iter().min_by_key(|(_, (fetched_at, _))| *fetched_at).map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                urls.remove(&oldest);
+            }
+        }
+        urls.insert(url.to_string(), (Instant::now(), preview));
+    }
+}
+
+/// Рецепт по публичной ссылке
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct SharedRecipeRow {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    image_url: Option<String>,
+    prep_time_minutes: Option<i32>,
+    cook_time_minutes: Option<i32>,
+    difficulty: DifficultyLevel,
+}
+
+impl SharedRecipeRow {
+    fn preview(self, url: String) -> LinkPreview {
+        let total_time_minutes = match (self.prep_time_minutes, self.cook_time_minutes) {
+            (None, None) => None,
+            (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+        };
+
+        LinkPreview {
+            url,
+            kind: LinkPreviewKind::Recipe,
+            title: Some(self.name.clone()),
+            description: self.description.map(|description| description.chars().take(MAX_DESCRIPTION_LENGTH).collect()),
+            image_url: self.image_url.clone(),
+            recipe_id: Some(self.id),
+            recipe: Some(RecipeLinkPreview {
+                name: self.name,
+                image_url: self.image_url,
+                total_time_minutes,
+                difficulty: self.difficulty,
+            }),
+            revoked: false,
+        }
+    }
+}
+
+/// Карточка после отзыва ссылки или удаления рецепта: остается только название
+fn degraded(preview: LinkPreview) -> LinkPreview {
+    LinkPreview {
+        description: None,
+        image_url: None,
+        recipe_id: None,
+        recipe: None,
+        revoked: true,
+        ..preview
+    }
+}
+
+const SHARED_RECIPE_COLUMNS: &str =
+    "r.id, r.name, r.description, r.image_url, r.prep_time_minutes, r.cook_time_minutes, r.difficulty";
+
+/// Карточки ссылок в постах: рецепты по публичным ссылкам и OpenGraph внешних сайтов.
+/// Карточки хранятся в `posts.link_previews` и переписываются при изменении рецепта.
+pub struct LinkPreviewService {
+    pool: DbPool,
+}
+
+impl LinkPreviewService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Карточки для первых ссылок текста поста. Ссылка, для которой карточку получить
+    /// не удалось (отозванный токен, недоступный сайт), остается простой ссылкой.
+    pub async fn previews_for(&self, content: &str, cache: &LinkPreviewCache) -> Vec<LinkPreview> {
+        let mut previews = Vec::new();
+
+        for url in extract_urls(content).into_iter().take(MAX_LINK_PREVIEWS) {
+            let preview = match share_token_from_url(&url) {
+                Some(token) => self.recipe_preview(url.clone(), &token).await,
+                None => external_preview(url.clone(), cache).await,
+            };
+            match preview {
+                Ok(Some(preview)) => previews.push(preview),
+                Ok(None) => {}
+                Err(e) => warn!("Link preview for {} skipped: {}", url, e),
+            }
+        }
+
+        previews
+    }
+
+    async fn recipe_preview(&self, url: String, token: &str) -> Result<Option<LinkPreview>, AppError> {
+        let recipe = sqlx::query_as::<_, SharedRecipeRow>(&format!(
+            "SELECT {} FROM recipe_share_tokens t JOIN recipes r ON r.id = t.recipe_id WHERE t.token = $1",
+            SHARED_RECIPE_COLUMNS
+        ))
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(recipe.map(|recipe| recipe.preview(url)))
+    }
+
+    /// Сохраняет карточки поста автора; чужой или удаленный пост не меняется
+    pub async fn store(&self, post_id: Uuid, author_id: Uuid, previews: &[LinkPreview]) -> Result<(), AppError> {
+        sqlx::query("UPDATE posts SET link_previews = $3 WHERE id = $1 AND author_id = $2")
+            .bind(post_id)
+            .bind(author_id)
+            .bind(Json(previews))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Обновляет карточки рецепта во всех постах после его изменения.
+    /// Ссылка отозвана или рецепт удален — карточки деградируют до названия.
+    pub async fn refresh_recipe(&self, recipe_id: Uuid) -> Result<(), AppError> {
+        let recipe = sqlx::query_as::<_, SharedRecipeRow>(&format!(
+            "SELECT {} FROM recipes r JOIN recipe_share_tokens t ON t.recipe_id = r.id WHERE r.id = $1",
+            SHARED_RECIPE_COLUMNS
+        ))
+        .bind(recipe_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match recipe {
+            Some(recipe) => {
+                self.rewrite_recipe_previews(recipe_id, |preview| recipe.clone().preview(preview.url))
+                    .await
+            }
+            None => self.degrade_recipe(recipe_id).await,
+        }
+    }
+
+    /// Оставляет в карточках рецепта только название
+    pub async fn degrade_recipe(&self, recipe_id: Uuid) -> Result<(), AppError> {
+        self.rewrite_recipe_previews(recipe_id, degraded).await
+    }
+
+    async fn rewrite_recipe_previews<F>(&self, recipe_id: Uuid, rewrite: F) -> Result<(), AppError>
+    where
+        F: Fn(LinkPreview) -> LinkPreview,
+    {
+        let mut tx = self.pool.begin().await?;
+
+        let posts: Vec<(Uuid, Json<Vec<LinkPreview>>)> = sqlx::query_as(
+            "SELECT id, link_previews FROM posts WHERE link_previews @> $1 FOR UPDATE"
+        )
+        .bind(serde_json::json!([{ "recipe_id": recipe_id }]))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (post_id, Json(previews)) in posts {
+            let previews: Vec<LinkPreview> = previews
+                .into_iter()
+                .map(|preview| if preview.recipe_id == Some(recipe_id) { rewrite(preview) } else { preview })
+                .collect();
+
+            sqlx::query("UPDATE posts SET link_previews = $2 WHERE id = $1")
+                .bind(post_id)
+                .bind(Json(&previews))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Карточка внешней ссылки с учетом кеша; страница без заголовка карточки не дает
+async fn external_preview(url: String, cache: &LinkPreviewCache) -> Result<Option<LinkPreview>, AppError> {
+    let domain = Url::parse(&url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_ascii_lowercase))
+        .ok_or_else(|| AppError::BadRequest("Invalid link".to_string()))?;
+
+    let open_graph = match cache.get(&domain, &url) {
+        Some(cached) => cached,
+        None => match fetch_open_graph(&url).await {
+            Ok(open_graph) => {
+                cache.insert(&domain, &url, Some(open_graph.clone()));
+                Some(open_graph)
+            }
+            Err(e) => {
+                cache.insert(&domain, &url, None);
+                return Err(e);
+            }
+        },
+    };
+
+    Ok(open_graph.filter(|og| og.title.is_some()).map(|og| LinkPreview {
+        url,
+        kind: LinkPreviewKind::External,
+        title: og.title,
+        description: og.description,
+        image_url: og.image_url,
+        recipe_id: None,
+        recipe: None,
+        revoked: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        services::recipe_share::{share_url, RecipeShareService},
+        test_support::{insert_recipe, insert_user},
+    };
+
+    /// Страница с полным набором OpenGraph, сущностями и относительной картинкой
+    const FULL_OG: &str = r#"<!doctype html>
+<html><head>
+<title>Запасной заголовок</title>
+<META property="og:title" content="Борщ &amp; пампушки">
+<meta property='og:description' content="  Классический   рецепт&#33; &#x441;о сметаной  ">
+<meta property="og:image" content="/img/borsch.jpg" />
+<meta name="twitter:title" content="Не этот заголовок">
+</head><body><p>Текст</p></body></html>"#;
+
+    /// Только twitter-карточка и описание страницы
+    const TWITTER_ONLY: &str = r#"<html><head>
+<meta name=twitter:title content=Окрошка>
+<meta name="description" content="Летний суп">
+<meta name="twitter:image" content="https://cdn.example.com/okroshka.png">
+</head></html>"#;
+
+    /// Ни одного мета-тега: заголовок из `<title>`, картинка со схемой javascript отбрасывается
+    const TITLE_ONLY: &str = r#"<html><head><TITLE>
+    Пельмени
+    домашние </TITLE>
+<meta property="og:image" content="javascript:alert(1)">
+</head></html>"#;
+
+    /// Разметка в заголовке и описании не доходит до карточки
+    const HOSTILE: &str = r#"<head>
+<meta property="og:title" content="&lt;script&gt;alert(1)&lt;/script&gt;Блины">
+<meta property="og:description" content="<b>жирным</b> текстом">
+</head>"#;
+
+    #[test]
+    fn open_graph_is_parsed_from_fixtures() {
+        let page = Url::parse("https://food.example.com/recipes/borsch?ref=feed").unwrap();
+
+        assert_eq!(
+            parse_open_graph(FULL_OG, &page),
+            OpenGraph {
+                title: Some("Борщ & пампушки".to_string()),
+                description: Some("Классический рецепт! со сметаной".to_string()),
+                image_url: Some("https://food.example.com/img/borsch.jpg".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_open_graph(TWITTER_ONLY, &page),
+            OpenGraph {
+                title: Some("Окрошка".to_string()),
+                description: Some("Летний суп".to_string()),
+                image_url: Some("https://cdn.example.com/okroshka.png".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_open_graph(TITLE_ONLY, &page),
+            OpenGraph { title: Some("Пельмени домашние".to_string()), description: None, image_url: None }
+        );
+
+        let hostile = parse_open_graph(HOSTILE, &page);
+        let title = hostile.title.unwrap();
+        assert!(!title.contains('<') && title.contains("Блины"), "{}", title);
+        assert_eq!(hostile.description.as_deref(), Some("жирным текстом"));
+
+        assert_eq!(parse_open_graph("<html><body>пусто</body></html>", &page), OpenGraph::default());
+    }
+
+    #[test]
+    fn urls_are_extracted_in_order_without_duplicates() {
+        let cases = vec![
+            ("без ссылок", vec![]),
+            (
+                "Рецепт: https://a.example.com/x, и еще (https://b.example.com/y). Снова https://a.example.com/x!",
+                vec!["https://a.example.com/x", "https://b.example.com/y"],
+            ),
+            ("старый текст https://a.example.com/?q=1&amp;p=2", vec!["https://a.example.com/?q=1&p=2"]),
+            (r#"<a href="https://a.example.com/z">ссылка</a>"#, vec!["https://a.example.com/z"]),
+            ("ftp://files.example.com и https:// без хоста", vec![]),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(extract_urls(content), expected, "{}", content);
+        }
+    }
+
+    #[test]
+    fn private_addresses_are_not_public() {
+        let cases = vec![
+            ("93.184.216.34", true),
+            ("2606:2800:220:1:248:1893:25c8:1946", true),
+            ("127.0.0.1", false),
+            ("10.1.2.3", false),
+            ("172.16.0.1", false),
+            ("192.168.1.1", false),
+            ("169.254.169.254", false),
+            ("100.64.0.1", false),
+            ("0.0.0.0", false),
+            ("::1", false),
+            ("fd00::1", false),
+            ("fe80::1", false),
+            ("::ffff:127.0.0.1", false),
+        ];
+        for (ip, expected) in cases {
+            assert_eq!(is_public_ip(ip.parse().unwrap()), expected, "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_refuses_private_hosts_and_odd_ports() {
+        for url in ["http://127.0.0.1/", "http://localhost/recipe", "http://[::1]/", "http://10.0.0.5/admin"] {
+            let result = fetch_open_graph(url).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{}: {:?}", url, result);
+        }
+        for url in ["https://example.com:8443/", "ftp://example.com/"] {
+            assert!(matches!(fetch_open_graph(url).await, Err(AppError::BadRequest(_))), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_pages_are_not_fetched_again() {
+        let cache = LinkPreviewCache::default();
+        let og = |title: &str| OpenGraph { title: Some(title.to_string()), description: None, image_url: None };
+        cache.insert("food.test", "https://food.test/a", Some(og("Щи")));
+        cache.insert("food.test", "https://food.test/untitled", Some(OpenGraph::default()));
+        cache.insert("down.test", "https://down.test/", None);
+
+        // Домены .test не резолвятся: карточки возможны только из кеша
+        let preview = external_preview("https://food.test/a".to_string(), &cache).await.unwrap().unwrap();
+        assert_eq!((preview.kind, preview.title.as_deref()), (LinkPreviewKind::External, Some("Щи")));
+        assert!(external_preview("https://food.test/untitled".to_string(), &cache).await.unwrap().is_none());
+        assert!(external_preview("https://down.test/".to_string(), &cache).await.unwrap().is_none());
+
+        // У домена ограниченное число адресов: самый старый вытесняется
+        for i in 0..MAX_CACHED_URLS_PER_DOMAIN {
+            cache.insert("busy.test", &format!("https://busy.test/{}", i), Some(og("Суп")));
+        }
+        cache.insert("busy.test", "https://busy.test/new", Some(og("Суп")));
+        assert!(cache.get("busy.test", "https://busy.test/0").is_none());
+        assert!(cache.get("busy.test", "https://busy.test/new").is_some());
+        assert!(cache.get("food.test", "https://food.test/a").is_some(), "other domains keep their entries");
+    }
+
+    async fn stored_previews(pool: &PgPool, post_id: Uuid) -> Vec<LinkPreview> {
+        let (Json(previews),): (Json<Vec<LinkPreview>>,) = sqlx::query_as("SELECT link_previews FROM posts WHERE id = $1")
+            .bind(post_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        previews
+    }
+
+    #[sqlx::test]
+    async fn shared_recipe_card_follows_edits_and_degrades_on_revoke(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let recipe_id = insert_recipe(&pool, author, "Сырники", true).await;
+        sqlx::query(
+            "UPDATE recipes SET description = 'Пышные', image_url = 'https://cdn.example.com/syrniki.jpg', \
+             prep_time_minutes = 10, cook_time_minutes = 15, difficulty = 'medium' WHERE id = $1"
+        )
+        .bind(recipe_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let shares = RecipeShareService::new(pool.clone());
+        let url = share_url(&shares.create_token(recipe_id, author).await.unwrap().token);
+
+        let service = LinkPreviewService::new(pool.clone());
+        let cache = LinkPreviewCache::default();
+        cache.insert("food.test", "https://food.test/a", Some(OpenGraph { title: Some("Щи".to_string()), ..Default::default() }));
+        let content = format!("Попробуйте {} и https://food.test/a, а еще {}/unknownToken1", url, share_url("x").trim_end_matches("/x"));
+        let previews = service.previews_for(&content, &cache).await;
+
+        // Неизвестный токен карточки не дает, внешняя ссылка — из кеша
+        assert_eq!(previews.len(), 2);
+        let card = &previews[0];
+        assert_eq!((card.kind, card.url.as_str(), card.recipe_id), (LinkPreviewKind::Recipe, url.as_str(), Some(recipe_id)));
+        assert_eq!(card.title.as_deref(), Some("Сырники"));
+        assert_eq!(card.description.as_deref(), Some("Пышные"));
+        let recipe = card.recipe.as_ref().unwrap();
+        assert_eq!(recipe.total_time_minutes, Some(25));
+        assert!(matches!(recipe.difficulty, DifficultyLevel::Medium));
+        assert_eq!(previews[1].kind, LinkPreviewKind::External);
+
+        let (post_id,): (Uuid,) = sqlx::query_as("INSERT INTO posts (author_id, content, post_type) VALUES ($1, $2, 'text') RETURNING id")
+            .bind(author)
+            .bind(&content)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        service.store(post_id, author, &previews).await.unwrap();
+
+        // Правка рецепта переписывает карточку в посте
+        sqlx::query("UPDATE recipes SET name = 'Сырники со сметаной', cook_time_minutes = 20 WHERE id = $1")
+            .bind(recipe_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        service.refresh_recipe(recipe_id).await.unwrap();
+        let stored = stored_previews(&pool, post_id).await;
+        assert_eq!(stored[0].title.as_deref(), Some("Сырники со сметаной"));
+        assert_eq!(stored[0].recipe.as_ref().unwrap().total_time_minutes, Some(30));
+
+        // После отзыва ссылки остается только название, внешняя карточка не трогается
+        shares.revoke_token(recipe_id, author).await.unwrap();
+        let stored = stored_previews(&pool, post_id).await;
+        let degraded = &stored[0];
+        assert!(degraded.revoked);
+        assert_eq!(degraded.title.as_deref(), Some("Сырники со сметаной"));
+        assert!(degraded.description.is_none() && degraded.image_url.is_none());
+        assert!(degraded.recipe.is_none() && degraded.recipe_id.is_none());
+        assert_eq!(stored[1].title.as_deref(), Some("Щи"));
+        assert!(!stored[1].revoked);
+
+        // Отозванная ссылка в новом посте остается простой ссылкой
+        assert!(service.previews_for(&url, &cache).await.is_empty());
+    }
+}
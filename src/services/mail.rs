@@ -0,0 +1,99 @@
+use reqwest::Client;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{config::Config, utils::errors::AppError};
+
+const DEFAULT_FROM: &str = "IT Cook <noreply@itcook.app>";
+
+#[derive(Debug, Clone)]
+enum MailTransport {
+    /// HTTP API почтового провайдера (Resend-совместимый: POST JSON с Bearer-ключом)
+    Http { api_url: String, api_key: String },
+    /// Провайдер не настроен: письмо только пишется в лог (для разработки)
+    Log,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+}
+
+#[derive(Serialize)]
+struct SendRequest<'a> {
+    from: &'a str,
+    to: [&'a str; 1],
+    subject: &'a str,
+    html: &'a str,
+}
+
+/// Отправка писем. Настраивается через MAIL_API_URL, MAIL_API_KEY и MAIL_FROM.
+#[derive(Debug, Clone)]
+pub struct MailService {
+    client: Client,
+    transport: MailTransport,
+    from: String,
+}
+
+impl MailService {
+    pub fn from_env() -> Self {
+        let transport = match (std::env::var("MAIL_API_URL"), std::env::var("MAIL_API_KEY")) {
+            (Ok(api_url), Ok(api_key)) => MailTransport::Http { api_url, api_key },
+            _ => MailTransport::Log,
+        };
+
+        Self {
+            client: Client::new(),
+            transport,
+            from: std::env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_FROM.to_string()),
+        }
+    }
+
+    /// HTTP-провайдер по явному адресу — для тестов с локальным сервером
+    #[cfg(test)]
+    pub fn http(api_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            transport: MailTransport::Http { api_url, api_key: "test".to_string() },
+            from: DEFAULT_FROM.to_string(),
+        }
+    }
+
+    pub async fn send(&self, message: &MailMessage) -> Result<(), AppError> {
+        match &self.transport {
+            MailTransport::Http { api_url, api_key } => {
+                let response = self.client
+                    .post(api_url)
+                    .bearer_auth(api_key)
+                    .json(&SendRequest {
+                        from: &self.from,
+                        to: [&message.to],
+                        subject: &message.subject,
+                        html: &message.html,
+                    })
+                    .send()
+                    .await
+                    .map_err(|e| AppError::ExternalService(format!("Mail request failed: {}", e)))?;
+
+                if !response.status().is_success() {
+                    return Err(AppError::ExternalService(format!(
+                        "Mail provider returned {}",
+                        response.status()
+                    )));
+                }
+
+                Ok(())
+            }
+            // В production письмо не считается отправленным, пока провайдер не настроен
+            MailTransport::Log if Config::is_production_from_env() => {
+                Err(AppError::ExternalService("Mail delivery is not configured".to_string()))
+            }
+            MailTransport::Log => {
+                info!("📧 Mail to {} (not sent, no provider configured): {}", message.to, message.subject);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use sqlx::PgConnection;
+
+use crate::{
+    db::DbPool,
+    models::diary::{
+        CreateMealTemplate, CreateMealTemplateItem, DiaryEntry, LogMealTemplate, MealSummary,
+        MealTemplate, MealTemplateItem, MealTemplateWithItems, NutritionSummary,
+    },
+    services::clock::{self, SharedClock},
+    utils::errors::AppError,
+};
+
+const MAX_TEMPLATE_ITEMS: usize = 30;
+const MAX_TEMPLATES_PER_USER: i64 = 50;
+
+pub struct MealTemplateService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl MealTemplateService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn create_template(&self, template: CreateMealTemplate) -> Result<MealTemplateWithItems, AppError> {
+        validate_items(&template.items)?;
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM meal_templates WHERE user_id = $1")
+            .bind(template.user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if count >= MAX_TEMPLATES_PER_USER {
+            return Err(AppError::BadRequest(format!(
+                "Template limit reached ({}), delete unused templates first",
+                MAX_TEMPLATES_PER_USER
+            )));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let stored = sqlx::query_as::<_, MealTemplate>(
+            "INSERT INTO meal_templates (id, user_id, name) VALUES ($1, $2, $3) RETURNING *"
+        )
+        .bind(Uuid::new_v4())
+        .bind(template.user_id)
+        .bind(&template.name)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let items = insert_items(&mut tx, stored.id, &template.items).await?;
+        tx.commit().await?;
+
+        Ok(MealTemplateWithItems { template: stored, items })
+    }
+
+    pub async fn list_templates(&self, user_id: Uuid) -> Result<Vec<MealTemplateWithItems>, AppError> {
+        let templates = sqlx::query_as::<_, MealTemplate>(
+            "SELECT * FROM meal_templates WHERE user_id = $1 ORDER BY name"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ids: Vec<Uuid> = templates.iter().map(|template| template.id).collect();
+        let items = sqlx::query_as::<_, MealTemplateItem>(
+            "SELECT * FROM meal_template_items WHERE template_id = ANY($1) ORDER BY position"
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_template: HashMap<Uuid, Vec<MealTemplateItem>> = HashMap::new();
+        for item in items {
+            by_template.entry(item.template_id).or_default().push(item);
+        }
+
+        Ok(templates
+            .into_iter()
+            .map(|template| {
+                let items = by_template.remove(&template.id).unwrap_or_default();
+                MealTemplateWithItems { template, items }
+            })
+            .collect())
+    }
+
+    pub async fn get_template(&self, user_id: Uuid, id: Uuid) -> Result<MealTemplateWithItems, AppError> {
+        let mut conn = self.pool.acquire().await?;
+        load_template(&mut conn, user_id, id).await
+    }
+
+    /// Заменяет название и строки шаблона целиком
+    pub async fn update_template(&self, id: Uuid, template: CreateMealTemplate) -> Result<MealTemplateWithItems, AppError> {
+        validate_items(&template.items)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let stored = sqlx::query_as::<_, MealTemplate>(
+            "UPDATE meal_templates SET name = $1 WHERE id = $2 AND user_id = $3 RETURNING *"
+        )
+        .bind(&template.name)
+        .bind(id)
+        .bind(template.user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Meal template not found".to_string()))?;
+
+        sqlx::query("DELETE FROM meal_template_items WHERE template_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let items = insert_items(&mut tx, id, &template.items).await?;
+        tx.commit().await?;
+
+        Ok(MealTemplateWithItems { template: stored, items })
+    }
+
+    pub async fn delete_template(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query("DELETE FROM meal_templates WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Meal template not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Создает записи дневника для всех строк шаблона в одной транзакции:
+    /// либо записываются все строки, либо ни одной.
+    /// Возвращает созданные записи и обновленные итоги дня.
+    pub async fn log_template(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        request: LogMealTemplate,
+    ) -> Result<(Vec<DiaryEntry>, NutritionSummary), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let template = load_template(&mut tx, user_id, id).await?;
+        let portions = resolve_portions(&template.items, &request.portion_overrides)?;
+        let consumed_at = consumed_at_for(request.date, self.clock.now());
+
+        let mut entries = Vec::with_capacity(template.items.len());
+        for (item, portion_size) in template.items.iter().zip(portions) {
+            let entry = sqlx::query_as::<_, DiaryEntry>(
+                r#"
+                INSERT INTO diary_entries (
+                    id, user_id, food_name, brand, portion_size, unit,
+                    calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g,
+                    fiber_per_100g, sugar_per_100g, sodium_per_100g, meal_type, consumed_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                RETURNING *
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(&item.food_name)
+            .bind(&item.brand)
+            .bind(portion_size)
+            .bind(&item.unit)
+            .bind(item.calories_per_100g)
+            .bind(item.protein_per_100g)
+            .bind(item.fat_per_100g)
+            .bind(item.carbs_per_100g)
+            .bind(item.fiber_per_100g)
+            .bind(item.sugar_per_100g)
+            .bind(item.sodium_per_100g)
+            .bind(&request.meal_type)
+            .bind(consumed_at)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            entries.push(entry);
+        }
+
+        let day_entries = sqlx::query_as::<_, DiaryEntry>(
+            "SELECT * FROM diary_entries WHERE user_id = $1 AND consumed_at::date = $2 ORDER BY consumed_at"
+        )
+        .bind(user_id)
+        .bind(request.date)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((entries, summarize_day(request.date, &day_entries)))
+    }
+}
+
+fn validate_items(items: &[CreateMealTemplateItem]) -> Result<(), AppError> {
+    if items.is_empty() {
+        return Err(AppError::BadRequest("Template must contain at least one item".to_string()));
+    }
+    if items.len() > MAX_TEMPLATE_ITEMS {
+        return Err(AppError::BadRequest(format!(
+            "Template can contain at most {} items",
+            MAX_TEMPLATE_ITEMS
+        )));
+    }
+    if let Some(item) = items.iter().find(|item| !is_valid_portion(item.portion_size)) {
+        return Err(AppError::BadRequest(format!("Invalid portion size for {}", item.food_name)));
+    }
+
+    Ok(())
+}
+
+fn is_valid_portion(portion_size: f32) -> bool {
+    portion_size.is_finite() && portion_size > 0.0
+}
+
+/// Порции для каждой строки шаблона: переопределенная из запроса или порция по умолчанию.
+/// Переопределения для чужих строк и некорректные порции отклоняются.
+pub fn resolve_portions(items: &[MealTemplateItem], overrides: &[(Uuid, f32)]) -> Result<Vec<f32>, AppError> {
+    let mut portions: HashMap<Uuid, f32> = HashMap::with_capacity(overrides.len());
+
+    for (item_id, portion_size) in overrides {
+        if !items.iter().any(|item| item.id == *item_id) {
+            return Err(AppError::BadRequest(format!("Item {} does not belong to this template", item_id)));
+        }
+        if !is_valid_portion(*portion_size) {
+            return Err(AppError::BadRequest(format!("Invalid portion size for item {}", item_id)));
+        }
+        if portions.insert(*item_id, *portion_size).is_some() {
+            return Err(AppError::BadRequest(format!("Duplicate portion override for item {}", item_id)));
+        }
+    }
+
+    Ok(items
+        .iter()
+        .map(|item| portions.get(&item.id).copied().unwrap_or(item.portion_size))
+        .collect())
+}
+
+/// Для сегодняшней даты — текущее время, для других дней — полдень UTC
+fn consumed_at_for(date: NaiveDate, now: DateTime<Utc>) -> DateTime<Utc> {
+    if date == now.date_naive() {
+        return now;
+    }
+
+    let noon = date.and_hms_opt(12, 0, 0).expect("noon is a valid time");
+    Utc.from_utc_datetime(&noon)
+}
+
+fn summarize_day(date: NaiveDate, entries: &[DiaryEntry]) -> NutritionSummary {
+    let mut meal_breakdown: Vec<MealSummary> = Vec::new();
+    let (mut fiber, mut sugar, mut sodium) = (0.0, 0.0, 0.0);
+
+    for entry in entries {
+        let (calories, protein, fat, carbs) = entry.calculate_nutrition();
+        let multiplier = entry.portion_size / 100.0;
+        fiber += entry.fiber_per_100g.unwrap_or(0.0) * multiplier;
+        sugar += entry.sugar_per_100g.unwrap_or(0.0) * multiplier;
+        sodium += entry.sodium_per_100g.unwrap_or(0.0) * multiplier;
+
+        let index = match meal_breakdown.iter().position(|meal| meal.meal_type == entry.meal_type) {
+            Some(index) => index,
+            None => {
+                meal_breakdown.push(MealSummary {
+                    meal_type: entry.meal_type.clone(),
+                    calories: 0.0,
+                    protein: 0.0,
+                    fat: 0.0,
+                    carbs: 0.0,
+                    entries_count: 0,
+                });
+                meal_breakdown.len() - 1
+            }
+        };
+
+        let meal = &mut meal_breakdown[index];
+        meal.calories += calories;
+        meal.protein += protein;
+        meal.fat += fat;
+        meal.carbs += carbs;
+        meal.entries_count += 1;
+    }
+
+    NutritionSummary {
+        date,
+        total_calories: meal_breakdown.iter().map(|meal| meal.calories).sum(),
+        total_protein: meal_breakdown.iter().map(|meal| meal.protein).sum(),
+        total_fat: meal_breakdown.iter().map(|meal| meal.fat).sum(),
+        total_carbs: meal_breakdown.iter().map(|meal| meal.carbs).sum(),
+        total_fiber: fiber,
+        total_sugar: sugar,
+        total_sodium: sodium,
+        meal_breakdown,
+        calorie_goal: None,
+        protein_goal: None,
+        fat_goal: None,
+        carbs_goal: None,
+    }
+}
+
+async fn load_template(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<MealTemplateWithItems, AppError> {
+    let template = sqlx::query_as::<_, MealTemplate>(
+        "SELECT * FROM meal_templates WHERE id = $1 AND user_id = $2"
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Meal template not found".to_string()))?;
+
+    let items = sqlx::query_as::<_, MealTemplateItem>(
+        "SELECT * FROM meal_template_items WHERE template_id = $1 ORDER BY position"
+    )
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(MealTemplateWithItems { template, items })
+}
+
+async fn insert_items(
+    conn: &mut PgConnection,
+    template_id: Uuid,
+    items: &[CreateMealTemplateItem],
+) -> Result<Vec<MealTemplateItem>, AppError> {
+    let mut stored = Vec::with_capacity(items.len());
+
+    for (position, item) in items.iter().enumerate() {
+        let row = sqlx::query_as::<_, MealTemplateItem>(
+            r#"
+            INSERT INTO meal_template_items (
+                id, template_id, position, food_name, brand, portion_size, unit,
+                calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g,
+                fiber_per_100g, sugar_per_100g, sodium_per_100g
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(template_id)
+        .bind(position as i32)
+        .bind(&item.food_name)
+        .bind(&item.brand)
+        .bind(item.portion_size)
+        .bind(&item.unit)
+        .bind(item.calories_per_100g)
+        .bind(item.protein_per_100g)
+        .bind(item.fat_per_100g)
+        .bind(item.carbs_per_100g)
+        .bind(item.fiber_per_100g)
+        .bind(item.sugar_per_100g)
+        .bind(item.sodium_per_100g)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        stored.push(row);
+    }
+
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::diary::NutritionTotals;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_user};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap()
+    }
+
+    fn line(food_name: &str, portion_size: f32, per_100g: [f32; 4], fiber: Option<f32>, sugar: Option<f32>) -> CreateMealTemplateItem {
+        let [calories, protein, fat, carbs] = per_100g;
+        CreateMealTemplateItem {
+            food_name: food_name.to_string(),
+            brand: None,
+            portion_size,
+            unit: "g".to_string(),
+            calories_per_100g: calories,
+            protein_per_100g: protein,
+            fat_per_100g: fat,
+            carbs_per_100g: carbs,
+            fiber_per_100g: fiber,
+            sugar_per_100g: sugar,
+            sodium_per_100g: None,
+        }
+    }
+
+    /// Овсянка 60 г, банан 120 г, кофе 200 г
+    fn breakfast(user_id: Uuid) -> CreateMealTemplate {
+        CreateMealTemplate {
+            user_id,
+            name: "Мой обычный завтрак".to_string(),
+            items: vec![
+                line("Овсянка", 60.0, [350.0, 12.0, 6.0, 60.0], Some(10.0), None),
+                line("Банан", 120.0, [89.0, 1.1, 0.3, 23.0], Some(2.6), Some(12.0)),
+                line("Кофе", 200.0, [2.0, 0.3, 0.0, 0.0], None, None),
+            ],
+        }
+    }
+
+    fn stored_item(id: Uuid, portion_size: f32) -> MealTemplateItem {
+        MealTemplateItem {
+            id,
+            template_id: Uuid::nil(),
+            position: 0,
+            food_name: "Овсянка".to_string(),
+            brand: None,
+            portion_size,
+            unit: "g".to_string(),
+            calories_per_100g: 350.0,
+            protein_per_100g: 12.0,
+            fat_per_100g: 6.0,
+            carbs_per_100g: 60.0,
+            fiber_per_100g: None,
+            sugar_per_100g: None,
+            sodium_per_100g: None,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "expected {}, got {}", expected, actual);
+    }
+
+    fn assert_totals(totals: NutritionTotals, calories: f32, protein: f32, fat: f32, carbs: f32) {
+        assert_close(totals.calories, calories);
+        assert_close(totals.protein, protein);
+        assert_close(totals.fat, fat);
+        assert_close(totals.carbs, carbs);
+    }
+
+    async fn entries_count(pool: &PgPool, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM diary_entries WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn overrides_replace_only_their_lines() {
+        let (oats, banana, coffee) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let items = vec![stored_item(oats, 60.0), stored_item(banana, 120.0), stored_item(coffee, 200.0)];
+
+        assert_eq!(resolve_portions(&items, &[]).unwrap(), vec![60.0, 120.0, 200.0]);
+        assert_eq!(resolve_portions(&items, &[(coffee, 300.0), (oats, 80.0)]).unwrap(), vec![80.0, 120.0, 300.0]);
+        // Переопределение, равное порции по умолчанию, ничего не меняет
+        assert_eq!(resolve_portions(&items, &[(banana, 120.0)]).unwrap(), vec![60.0, 120.0, 200.0]);
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        let oats = Uuid::new_v4();
+        let items = vec![stored_item(oats, 60.0)];
+
+        for overrides in [
+            vec![(Uuid::new_v4(), 50.0)],
+            vec![(oats, 0.0)],
+            vec![(oats, -10.0)],
+            vec![(oats, f32::NAN)],
+            vec![(oats, f32::INFINITY)],
+            vec![(oats, 50.0), (oats, 70.0)],
+        ] {
+            assert!(
+                matches!(resolve_portions(&items, &overrides), Err(AppError::BadRequest(_))),
+                "{:?} must be rejected",
+                overrides
+            );
+        }
+    }
+
+    #[test]
+    fn line_nutrition_scales_with_the_portion() {
+        let item = stored_item(Uuid::new_v4(), 60.0);
+
+        assert_totals(item.nutrition_for(60.0), 210.0, 7.2, 3.6, 36.0);
+        assert_totals(item.nutrition_for(100.0), 350.0, 12.0, 6.0, 60.0);
+        assert_totals(item.nutrition_for(250.0), 875.0, 30.0, 15.0, 150.0);
+    }
+
+    #[test]
+    fn other_days_are_logged_at_noon() {
+        assert_eq!(consumed_at_for(now().date_naive(), now()), now());
+
+        let yesterday = now().date_naive() - Duration::days(1);
+        assert_eq!(consumed_at_for(yesterday, now()), Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn template_items_are_validated() {
+        let user_id = Uuid::new_v4();
+
+        let mut empty = breakfast(user_id);
+        empty.items.clear();
+        assert!(matches!(validate_items(&empty.items), Err(AppError::BadRequest(_))));
+
+        let mut zero = breakfast(user_id);
+        zero.items[1].portion_size = 0.0;
+        assert!(matches!(validate_items(&zero.items), Err(AppError::BadRequest(_))));
+
+        let mut too_many = breakfast(user_id);
+        too_many.items = (0..=MAX_TEMPLATE_ITEMS).map(|_| too_many.items[0].clone()).collect();
+        assert!(matches!(validate_items(&too_many.items), Err(AppError::BadRequest(_))));
+
+        assert!(validate_items(&breakfast(user_id).items).is_ok());
+    }
+
+    #[sqlx::test]
+    async fn totals_follow_default_portions_and_recompute_after_edit(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = MealTemplateService::new(pool.clone());
+
+        let created = service.create_template(breakfast(user_id)).await.unwrap();
+        // 210 + 106.8 + 4 ккал; клетчатка 6 + 3.12, сахар 14.4
+        let totals = created.totals();
+        assert_totals(totals, 320.8, 9.12, 3.96, 63.6);
+        assert_close(totals.fiber, 9.12);
+        assert_close(totals.sugar, 14.4);
+        assert_eq!(created.items.iter().map(|item| item.position).collect::<Vec<_>>(), [0, 1, 2]);
+
+        // Банан — 60 г вместо 120, кофе убран
+        let mut edited = breakfast(user_id);
+        edited.items[1].portion_size = 60.0;
+        edited.items.pop();
+        service.update_template(created.template.id, edited).await.unwrap();
+
+        let listed = service.list_templates(user_id).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].items.len(), 2);
+        assert_totals(listed[0].totals(), 263.4, 7.86, 3.78, 49.8);
+
+        // Чужой шаблон не найти и не изменить
+        let other = insert_user(&pool, "Борис").await;
+        assert!(matches!(service.get_template(other, created.template.id).await, Err(AppError::NotFound(_))));
+        assert!(matches!(
+            service.update_template(created.template.id, breakfast(other)).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(service.get_template(user_id, created.template.id).await.unwrap().items.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn logging_creates_every_line_with_overrides_and_day_totals(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = MealTemplateService::new(pool.clone()).with_clock(frozen_clock(now()));
+        let template = service.create_template(breakfast(user_id)).await.unwrap();
+        let (oats, coffee) = (template.items[0].id, template.items[2].id);
+
+        // Уже записанное за сегодня и за вчера
+        insert_diary_entry(&pool, user_id, "breakfast", 150.0, 5.0, now() - Duration::hours(1)).await;
+        insert_diary_entry(&pool, user_id, "dinner", 500.0, 20.0, now() - Duration::days(1)).await;
+
+        let (entries, summary) = service
+            .log_template(user_id, template.template.id, LogMealTemplate {
+                date: now().date_naive(),
+                meal_type: "breakfast".to_string(),
+                portion_overrides: vec![(oats, 80.0), (coffee, 300.0)],
+            })
+            .await
+            .unwrap();
+
+        let portions: Vec<(&str, f32)> = entries.iter().map(|entry| (entry.food_name.as_str(), entry.portion_size)).collect();
+        assert_eq!(portions, [("Овсянка", 80.0), ("Банан", 120.0), ("Кофе", 300.0)]);
+        assert!(entries.iter().all(|entry| entry.meal_type == "breakfast" && entry.consumed_at == now()));
+        assert_eq!(entries[1].sugar_per_100g, Some(12.0));
+
+        // 280 + 106.8 + 6 ккал из шаблона и 150 уже записанных
+        let logged: f32 = entries.iter().map(|entry| entry.calculate_nutrition().0).sum();
+        assert_close(logged, 392.8);
+        assert_eq!(summary.date, now().date_naive());
+        assert_close(summary.total_calories, 542.8);
+        assert_close(summary.total_protein, 9.6 + 1.32 + 0.9 + 5.0);
+        assert_close(summary.total_fiber, 8.0 + 3.12);
+        assert_eq!(summary.meal_breakdown.len(), 1);
+        assert_eq!(summary.meal_breakdown[0].entries_count, 4);
+
+        // Шаблон после записи не меняется
+        let stored = service.get_template(user_id, template.template.id).await.unwrap();
+        assert_eq!(stored.items[0].portion_size, 60.0);
+
+        // Запись за другой день — в полдень, в итоги того дня
+        let yesterday = now().date_naive() - Duration::days(1);
+        let (entries, summary) = service
+            .log_template(user_id, template.template.id, LogMealTemplate {
+                date: yesterday,
+                meal_type: "lunch".to_string(),
+                portion_overrides: Vec::new(),
+            })
+            .await
+            .unwrap();
+        assert!(entries.iter().all(|entry| entry.consumed_at == consumed_at_for(yesterday, now())));
+        assert_close(summary.total_calories, 500.0 + 320.8);
+        assert_eq!(summary.meal_breakdown.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn failed_line_rolls_back_the_whole_log(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = MealTemplateService::new(pool.clone()).with_clock(frozen_clock(now()));
+        let template = service.create_template(breakfast(user_id)).await.unwrap();
+
+        // Вставка третьей строки падает уже после того, как первые две записаны
+        sqlx::query(
+            r#"
+            CREATE FUNCTION reject_coffee() RETURNS trigger AS $$
+            BEGIN
+                IF NEW.food_name = 'Кофе' THEN
+                    RAISE EXCEPTION 'coffee rejected';
+                END IF;
+                RETURN NEW;
+            END;
+            $$ LANGUAGE plpgsql
+            "#
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("CREATE TRIGGER reject_coffee BEFORE INSERT ON diary_entries FOR EACH ROW EXECUTE FUNCTION reject_coffee()")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let request = || LogMealTemplate {
+            date: now().date_naive(),
+            meal_type: "breakfast".to_string(),
+            portion_overrides: Vec::new(),
+        };
+        let result = service.log_template(user_id, template.template.id, request()).await;
+        assert!(matches!(result, Err(AppError::Database(_))));
+        assert_eq!(entries_count(&pool, user_id).await, 0);
+
+        // Некорректное переопределение отклоняется до вставки
+        sqlx::query("DROP TRIGGER reject_coffee ON diary_entries").execute(&pool).await.unwrap();
+        let invalid = LogMealTemplate { portion_overrides: vec![(Uuid::new_v4(), 50.0)], ..request() };
+        assert!(matches!(
+            service.log_template(user_id, template.template.id, invalid).await,
+            Err(AppError::BadRequest(_))
+        ));
+        assert_eq!(entries_count(&pool, user_id).await, 0);
+
+        // Чужой шаблон записать нельзя
+        let other = insert_user(&pool, "Борис").await;
+        assert!(matches!(
+            service.log_template(other, template.template.id, request()).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(entries_count(&pool, other).await, 0);
+
+        let (entries, _) = service.log_template(user_id, template.template.id, request()).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries_count(&pool, user_id).await, 3);
+    }
+}
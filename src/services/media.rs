@@ -1,7 +1,12 @@
+use std::collections::HashMap;
 use std::path::Path;
+use image::{codecs::webp::WebPEncoder, ColorType};
 use tokio::fs;
 use uuid::Uuid;
-use crate::utils::errors::AppError;
+use crate::{api::community::MediaUploadResponse, db::DbPool, utils::errors::AppError};
+
+/// Сторона квадрата, в который вписывается миниатюра для сеток (галерея дневника, ленты)
+pub const THUMBNAIL_SIZE: u32 = 400;
 
 #[derive(Debug, Clone)]
 pub struct MediaService {
@@ -17,7 +22,7 @@ impl MediaService {
         }
     }
 
-    pub async fn upload_file(&self, user_id: Uuid, data: Vec<u8>) -> Result<crate::api::community::MediaUploadResponse, AppError> {
+    pub async fn upload_file(&self, user_id: Uuid, data: Vec<u8>) -> Result<MediaUploadResponse, AppError> {
         // Validate file size
         if data.len() > self.max_file_size {
             return Err(AppError::BadRequest(format!(
@@ -40,15 +45,32 @@ impl MediaService {
         fs::write(&file_path, &data).await
             .map_err(|e| AppError::InternalServerError(format!("Failed to save file: {}", e)))?;
 
+        let file_size = data.len() as i64;
+        let media_type = image::guess_format(&data)
+            .map_or("image/jpeg", |format| format.to_mime_type())
+            .to_string();
+
+        // Миниатюра для сеток; данные, которые не разбираются как изображение, сохраняются без нее
+        let thumbnail = tokio::task::spawn_blocking(move || make_thumbnail(&data)).await.ok().flatten();
+        let thumbnail_url = match thumbnail {
+            Some(thumbnail) => {
+                let thumbnail_name = format!("thumb_{}_{}.webp", user_id, file_id);
+                fs::write(user_dir.join(&thumbnail_name), thumbnail).await
+                    .map_err(|e| AppError::InternalServerError(format!("Failed to save thumbnail: {}", e)))?;
+                Some(format!("/uploads/media/{}/{}", user_id, thumbnail_name))
+            }
+            None => None,
+        };
+
         // Generate public URLs
         let public_url = format!("/uploads/media/{}/{}", user_id, filename);
-        let thumbnail_url = Some(format!("/uploads/media/{}/thumb_{}", user_id, filename));
 
-        Ok(crate::api::community::MediaUploadResponse {
+        Ok(MediaUploadResponse {
+            id: file_id,
             url: public_url,
             thumbnail_url,
-            media_type: "image/jpeg".to_string(),
-            file_size: data.len() as i64,
+            media_type,
+            file_size,
         })
     }
 
@@ -107,3 +129,128 @@ impl MediaService {
         Ok(())
     }
 }
+
+/// Миниатюра в webp: изображение вписывается в квадрат `THUMBNAIL_SIZE` с сохранением пропорций,
+/// маленькие не увеличиваются. `None` — данные не разбираются как изображение.
+pub fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(data).ok()?;
+    let image = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image
+    };
+
+    let rgba = image.to_rgba8();
+    let mut encoded = Vec::new();
+    // В `image` есть только кодировщик webp без потерь; для 400px этого достаточно
+    if let Err(e) = WebPEncoder::new_lossless(&mut encoded).encode(rgba.as_raw(), rgba.width(), rgba.height(), ColorType::Rgba8) {
+        tracing::warn!("Failed to encode thumbnail: {}", e);
+        return None;
+    }
+    Some(encoded)
+}
+
+/// Загруженные файлы в БД: шаги и галерея рецепта ссылаются на них по id
+pub struct MediaLibrary {
+    pool: DbPool,
+}
+
+impl MediaLibrary {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, user_id: Uuid, upload: &MediaUploadResponse) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO media_files (id, user_id, url, thumbnail_url, media_type, file_size) VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(upload.id)
+        .bind(user_id)
+        .bind(&upload.url)
+        .bind(&upload.thumbnail_url)
+        .bind(&upload.media_type)
+        .bind(upload.file_size)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Адреса файлов по id. Чужие и несуществующие файлы отклоняются одной ошибкой,
+    /// чтобы по ответу нельзя было узнать, существует ли чужой файл.
+    pub async fn owned_urls(&self, user_id: Uuid, ids: &[Uuid]) -> Result<HashMap<Uuid, String>, AppError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT id, url FROM media_files WHERE id = ANY($1) AND user_id = $2"
+        )
+        .bind(ids)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let urls: HashMap<Uuid, String> = rows.into_iter().collect();
+        if let Some(missing) = ids.iter().find(|id| !urls.contains_key(id)) {
+            return Err(AppError::BadRequest(format!("Media {} not found among your uploads", missing)));
+        }
+
+        Ok(urls)
+    }
+
+    /// Адреса файлов по id без проверки владельца — для показа уже сохраненных рецептов
+    pub async fn urls(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, String>, AppError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, url FROM media_files WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Удаляет файлы (вместе с миниатюрами), на которые больше не ссылаются ни шаги, ни галереи
+    /// рецептов, ни посты, ни отметки «приготовил(а)», ни записи дневника.
+    /// Вызывается после удаления шага или рецепта; файл удаляется с диска после записи в БД.
+    pub async fn delete_unreferenced(&self, ids: &[Uuid]) -> Result<usize, AppError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let urls: Vec<(String, Option<String>)> = sqlx::query_as(
+            r#"
+            DELETE FROM media_files m
+            WHERE m.id = ANY($1)
+              AND NOT EXISTS (SELECT 1 FROM recipe_steps s WHERE s.media_id = m.id)
+              AND NOT EXISTS (SELECT 1 FROM recipe_media g WHERE g.media_id = m.id)
+              AND NOT EXISTS (SELECT 1 FROM posts p WHERE m.url = ANY(p.media_urls))
+              AND NOT EXISTS (SELECT 1 FROM post_cooked c WHERE c.media_id = m.id)
+              AND NOT EXISTS (SELECT 1 FROM diary_entries d WHERE d.media_id = m.id)
+            RETURNING m.url, m.thumbnail_url
+            "#
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let deleted = urls.len();
+        // Пути собираются заранее: итератор с заимствованием через await делает future не-Send
+        let paths: Vec<String> = urls
+            .into_iter()
+            .flat_map(|(url, thumbnail_url)| std::iter::once(url).chain(thumbnail_url))
+            .collect();
+
+        let media_service = MediaService::new();
+        for path in &paths {
+            if let Err(e) = media_service.delete_file(path).await {
+                tracing::warn!("Failed to delete media file {}: {}", path, e);
+            }
+        }
+
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{services::ai::AiResponseMeta, utils::errors::AppError};
+
+// =============================================================================
+// СЦЕНАРИИ MOCK-ПРОВАЙДЕРА ДЛЯ РАЗРАБОТКИ ФРОНТЕНДА
+// =============================================================================
+
+pub const DEFAULT_SCENARIO: &str = "default";
+
+/// Сценарий, в котором Mock-провайдер имитирует сбой и возвращает `AppError::ExternalService`
+pub const PROVIDER_ERROR_SCENARIO: &str = "provider_error";
+
+pub const MOCK_SCENARIO_HEADER: &str = "x-mock-scenario";
+pub const MOCK_SCENARIO_QUERY: &str = "mock_scenario";
+
+const FIXTURES_JSON: &str = include_str!("../../fixtures/mock_ai_scenarios.json");
+
+/// Эндпоинты, для которых есть фикстуры
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockEndpoint {
+    Completion,
+    FridgeAnalysis,
+    Chat,
+    GenerateRecipe,
+    AnalyzeNutrition,
+    PersonalHealth,
+}
+
+impl MockEndpoint {
+    pub const ALL: [MockEndpoint; 6] = [
+        MockEndpoint::Completion,
+        MockEndpoint::FridgeAnalysis,
+        MockEndpoint::Chat,
+        MockEndpoint::GenerateRecipe,
+        MockEndpoint::AnalyzeNutrition,
+        MockEndpoint::PersonalHealth,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            MockEndpoint::Completion => "completion",
+            MockEndpoint::FridgeAnalysis => "fridge_analysis",
+            MockEndpoint::Chat => "chat",
+            MockEndpoint::GenerateRecipe => "generate_recipe",
+            MockEndpoint::AnalyzeNutrition => "analyze_nutrition",
+            MockEndpoint::PersonalHealth => "personal_health",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockScenarioInfo {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MockFixtures {
+    scenarios: Vec<MockScenarioInfo>,
+    /// эндпоинт → сценарий → ответ
+    responses: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
+static FIXTURES: Lazy<MockFixtures> = Lazy::new(|| {
+    serde_json::from_str(FIXTURES_JSON).expect("fixtures/mock_ai_scenarios.json is not valid")
+});
+
+/// Сценарий вместе с эндпоинтами, у которых есть собственные фикстуры
+#[derive(Debug, Clone, Serialize)]
+pub struct MockScenarioDescription {
+    #[serde(flatten)]
+    pub info: MockScenarioInfo,
+    pub endpoints: Vec<&'static str>,
+}
+
+pub fn describe_scenarios() -> Vec<MockScenarioDescription> {
+    FIXTURES
+        .scenarios
+        .iter()
+        .map(|scenario| MockScenarioDescription {
+            info: scenario.clone(),
+            endpoints: MockEndpoint::ALL
+                .iter()
+                .filter(|endpoint| {
+                    FIXTURES
+                        .responses
+                        .get(endpoint.key())
+                        .map(|by_scenario| by_scenario.contains_key(&scenario.name))
+                        .unwrap_or(false)
+                })
+                .map(|endpoint| endpoint.key())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Неизвестные сценарии сводятся к `default`
+pub fn resolve_scenario(name: Option<&str>) -> &'static str {
+    name.and_then(|name| {
+        FIXTURES
+            .scenarios
+            .iter()
+            .find(|scenario| scenario.name.eq_ignore_ascii_case(name.trim()))
+    })
+    .map(|scenario| scenario.name.as_str())
+    .unwrap_or(DEFAULT_SCENARIO)
+}
+
+/// Фикстура для эндпоинта и сценария (или `default` этого эндпоинта).
+/// Поля `provider`/`degraded` подставляются из `meta`, чтобы ответ выглядел как настоящий.
+pub fn fixture<T: DeserializeOwned>(
+    endpoint: MockEndpoint,
+    scenario: &str,
+    meta: &AiResponseMeta,
+) -> Result<Option<T>, AppError> {
+    let Some(by_scenario) = FIXTURES.responses.get(endpoint.key()) else {
+        return Ok(None);
+    };
+    let Some(value) = by_scenario.get(scenario).or_else(|| by_scenario.get(DEFAULT_SCENARIO)) else {
+        return Ok(None);
+    };
+
+    let mut value = value.clone();
+    if let Some(object) = value.as_object_mut() {
+        object.insert("provider".to_string(), serde_json::json!(meta.provider));
+        object.insert("degraded".to_string(), serde_json::json!(meta.degraded));
+    }
+
+    serde_json::from_value(value).map(Some).map_err(|e| {
+        AppError::InternalServerError(format!(
+            "Mock fixture {}/{} does not match response type: {}",
+            endpoint.key(),
+            scenario,
+            e
+        ))
+    })
+}
+
+/// Сценарий из заголовка `X-Mock-Scenario` или параметра `?mock_scenario=`.
+/// Учитывается только Mock-провайдером.
+#[derive(Debug, Clone, Default)]
+pub struct MockScenario(pub Option<String>);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for MockScenario
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let from_header = parts
+            .headers
+            .get(MOCK_SCENARIO_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let from_query = || {
+            parts.uri.query().and_then(|query| {
+                query
+                    .split('&')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| *key == MOCK_SCENARIO_QUERY)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+
+        Ok(MockScenario(from_header.or_else(from_query)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+    use crate::api::ai::AiChatResponse;
+    use crate::services::ai::{FridgeAnalysisType, SmartFridgeResponse};
+    use crate::services::personal_health_assistant::PersonalizedResponse;
+
+    fn meta() -> AiResponseMeta {
+        AiResponseMeta { provider: "mock".to_string(), degraded: false, generation: None }
+    }
+
+    /// Каждое поле фикстуры должно попасть в тип ответа: опечатка в ключе иначе молча теряется
+    fn assert_fields_survive(fixture: &serde_json::Value, typed: &serde_json::Value, path: &str) {
+        match (fixture, typed) {
+            (serde_json::Value::Object(fixture), serde_json::Value::Object(typed)) => {
+                for (key, value) in fixture {
+                    match typed.get(key) {
+                        Some(typed_value) => assert_fields_survive(value, typed_value, &format!("{}.{}", path, key)),
+                        None => assert!(value.is_null(), "{}.{} is not part of the response type", path, key),
+                    }
+                }
+            }
+            (serde_json::Value::Array(fixture), serde_json::Value::Array(typed)) => {
+                assert_eq!(fixture.len(), typed.len(), "{}", path);
+                for (index, (value, typed_value)) in fixture.iter().zip(typed).enumerate() {
+                    assert_fields_survive(value, typed_value, &format!("{}[{}]", path, index));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check<T: DeserializeOwned + Serialize>(endpoint: MockEndpoint, scenario: &str) {
+        let typed: T = fixture(endpoint, scenario, &meta())
+            .unwrap_or_else(|e| panic!("{:?}", e))
+            .unwrap_or_else(|| panic!("no fixture for {}/{}", endpoint.key(), scenario));
+        let raw = &FIXTURES.responses[endpoint.key()][scenario];
+        assert_fields_survive(raw, &serde_json::to_value(&typed).unwrap(), &format!("{}/{}", endpoint.key(), scenario));
+    }
+
+    #[test]
+    fn every_fixture_deserializes_into_its_response_type() {
+        let mut checked = 0;
+        for endpoint in MockEndpoint::ALL {
+            let scenarios = FIXTURES.responses.get(endpoint.key()).expect("every endpoint has fixtures");
+            assert!(scenarios.contains_key(DEFAULT_SCENARIO), "{} has no default", endpoint.key());
+
+            for scenario in scenarios.keys() {
+                match endpoint {
+                    MockEndpoint::Completion => check::<String>(endpoint, scenario),
+                    MockEndpoint::FridgeAnalysis => check::<SmartFridgeResponse>(endpoint, scenario),
+                    MockEndpoint::Chat | MockEndpoint::GenerateRecipe | MockEndpoint::AnalyzeNutrition => {
+                        check::<AiChatResponse>(endpoint, scenario)
+                    }
+                    MockEndpoint::PersonalHealth => check::<PersonalizedResponse>(endpoint, scenario),
+                }
+                checked += 1;
+            }
+        }
+        assert!(checked >= MockEndpoint::ALL.len());
+    }
+
+    #[test]
+    fn fixtures_only_use_declared_scenarios() {
+        let declared: Vec<&str> = FIXTURES.scenarios.iter().map(|scenario| scenario.name.as_str()).collect();
+        assert!(declared.contains(&DEFAULT_SCENARIO) && declared.contains(&PROVIDER_ERROR_SCENARIO));
+
+        for (endpoint, scenarios) in &FIXTURES.responses {
+            assert!(MockEndpoint::ALL.iter().any(|known| known.key() == endpoint), "unknown endpoint {}", endpoint);
+            for scenario in scenarios.keys() {
+                assert!(declared.contains(&scenario.as_str()), "{}/{} is not declared", endpoint, scenario);
+            }
+            // Сбой провайдера — ошибка, а не ответ
+            assert!(!scenarios.contains_key(PROVIDER_ERROR_SCENARIO));
+        }
+    }
+
+    #[test]
+    fn fridge_fixtures_exercise_every_response_section() {
+        let all: Vec<SmartFridgeResponse> = FIXTURES.responses["fridge_analysis"]
+            .keys()
+            .map(|scenario| fixture(MockEndpoint::FridgeAnalysis, scenario, &meta()).unwrap().unwrap())
+            .collect();
+
+        // Разделы полного отчета сервер строит сам из уведомлений, рекомендаций и инсайтов
+        assert!(all.iter().any(|response| matches!(response.analysis_type, FridgeAnalysisType::FullReport)));
+        assert!(all.iter().any(|response| !response.alerts.is_empty()));
+        assert!(all.iter().any(|response| !response.insights.is_empty()));
+        assert!(all.iter().any(|response| response.recipes.as_ref().is_some_and(|recipes| recipes.len() >= 5)));
+        assert!(all.iter().any(|response| response.recipes.as_ref().is_some_and(Vec::is_empty) || response.recipes.is_none()));
+    }
+
+    #[test]
+    fn unknown_scenarios_fall_back_to_default() {
+        assert_eq!(resolve_scenario(None), DEFAULT_SCENARIO);
+        assert_eq!(resolve_scenario(Some("no_such_scenario")), DEFAULT_SCENARIO);
+        assert_eq!(resolve_scenario(Some(" Empty_Fridge ")), "empty_fridge");
+
+        // Сценарий без своей фикстуры для эндпоинта получает фикстуру по умолчанию
+        let fallback: AiChatResponse = fixture(MockEndpoint::GenerateRecipe, "empty_fridge", &meta()).unwrap().unwrap();
+        let default: AiChatResponse = fixture(MockEndpoint::GenerateRecipe, DEFAULT_SCENARIO, &meta()).unwrap().unwrap();
+        assert_eq!(fallback.response, default.response);
+    }
+}
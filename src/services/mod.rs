@@ -1,11 +1,76 @@
 pub mod auth;
 pub mod diary;
+pub mod diary_reminders;
+pub mod diary_photos;
+pub mod diary_bulk;
+pub mod card_actions;
 pub mod fridge;
 pub mod recipe;
 pub mod goal;
 pub mod community;
 pub mod ai;
+pub mod ai_generation;
+pub mod ai_circuit;
+pub mod ai_providers;
 pub mod health;
 pub mod media;
 pub mod realtime;
 pub mod personal_health_assistant;
+pub mod activity;
+pub mod coaching;
+pub mod feature_flags;
+pub mod demo_seed;
+pub mod dietary;
+pub mod api_tokens;
+pub mod mock_ai;
+pub mod preferences;
+pub mod meal_templates;
+pub mod home;
+pub mod post_views;
+pub mod post_edits;
+pub mod post_cooked;
+pub mod notifications;
+pub mod goal_reminders;
+pub mod activity_status;
+pub mod admin_jobs;
+pub mod fridge_checkin;
+pub mod mail;
+pub mod monthly_reports;
+pub mod goal_share;
+pub mod goal_suggestions;
+pub mod cookable;
+pub mod fridge_reservations;
+pub mod fridge_notes;
+pub mod admin_users;
+pub mod account_merge;
+pub mod idempotency;
+pub mod ai_history;
+pub mod notification_delivery;
+pub mod expiry_notifications;
+pub mod recipe_cost;
+pub mod ai_conversations;
+pub mod goal_review;
+pub mod diet_quality;
+pub mod wellbeing;
+pub mod fasting;
+pub mod calorie_budget;
+pub mod sessions;
+pub mod recipe_share;
+pub mod link_previews;
+pub mod nutrition_backfill;
+pub mod ai_costs;
+pub mod shopping_advice;
+pub mod snack_suggestions;
+pub mod fridge_autocomplete;
+pub mod recipe_tags;
+pub mod starter_packs;
+pub mod realtime_schema;
+pub mod realtime_queue;
+pub mod fridge_report_feedback;
+pub mod chat_followups;
+pub mod task_supervisor;
+pub mod notification_digests;
+pub mod experiments;
+pub mod food_memory;
+pub mod clock;
+pub mod timeline;
@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use askama::Template;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::{AnalyticsOptions, ExpenseAnalytics, FoodWaste},
+        goal::{Goal, GoalStatus},
+        report::MonthlyReport,
+    },
+    services::{
+        clock::{self, SharedClock},
+        fridge::FridgeService,
+        mail::{MailMessage, MailService},
+    },
+    telemetry,
+    utils::errors::AppError,
+};
+
+/// Как часто планировщик проверяет, не наступило ли первое число
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Сколько позиций показывать в списках отчета
+const TOP_LIMIT: usize = 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "январь", "февраль", "март", "апрель", "май", "июнь",
+    "июль", "август", "сентябрь", "октябрь", "ноябрь", "декабрь",
+];
+
+/// HTML-шаблон отчета. Askama экранирует все подставляемые значения,
+/// поэтому названия продуктов, рецептов и целей можно выводить как есть.
+/// `None` в секции означает, что данные не удалось получить, — секция
+/// выводится с пояснением, а остальной отчет формируется как обычно.
+#[derive(Template)]
+#[template(path = "monthly_report.html")]
+pub struct MonthlyReportTemplate {
+    pub user_name: String,
+    pub month_label: String,
+    pub fridge: Option<FridgeSection>,
+    pub waste: Option<WasteSection>,
+    pub saved_recipes: Option<Vec<SavedRecipeLine>>,
+    pub goals: Option<Vec<GoalLine>>,
+}
+
+pub struct FridgeSection {
+    pub total_purchased: String,
+    pub total_consumed: String,
+    pub total_wasted: String,
+    pub waste_percentage: String,
+    pub savings_potential: String,
+    pub categories: Vec<CategoryLine>,
+}
+
+pub struct CategoryLine {
+    pub label: &'static str,
+    pub purchased: String,
+    pub wasted: String,
+}
+
+pub struct WasteSection {
+    pub by_reason: Vec<ReasonLine>,
+    pub top_products: Vec<WastedProductLine>,
+}
+
+pub struct ReasonLine {
+    pub label: &'static str,
+    pub amount: String,
+    pub percentage: String,
+}
+
+pub struct WastedProductLine {
+    pub name: String,
+    pub times: usize,
+    pub value: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct SavedRecipeLine {
+    pub name: String,
+    pub saves: i64,
+}
+
+pub struct GoalLine {
+    pub title: String,
+    pub progress: String,
+    pub status: &'static str,
+}
+
+#[derive(sqlx::FromRow)]
+struct Recipient {
+    user_id: Uuid,
+    email: String,
+    first_name: String,
+    has_report: bool,
+}
+
+/// Первое число месяца из строки вида "2024-05"
+pub fn parse_month(value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d")
+        .map_err(|_| AppError::BadRequest("Invalid month, expected YYYY-MM".to_string()))
+}
+
+/// Первое число предыдущего месяца
+pub fn previous_month(today: NaiveDate) -> NaiveDate {
+    let first = today.with_day(1).unwrap_or(today);
+    (first - chrono::Duration::days(1)).with_day(1).unwrap_or(first)
+}
+
+/// Границы месяца: [начало месяца, начало следующего месяца)
+pub fn month_bounds(month: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let next = if month.month() == 12 {
+        NaiveDate::from_ymd_opt(month.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1)
+    }
+    .unwrap_or(month);
+
+    let start = month.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = next.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    (start, end)
+}
+
+fn month_label(month: NaiveDate) -> String {
+    format!("{} {}", MONTH_NAMES[month.month0() as usize], month.year())
+}
+
+fn money(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn goal_status_label(status: &GoalStatus) -> &'static str {
+    match status {
+        GoalStatus::Active => "в процессе",
+        GoalStatus::Completed => "выполнена",
+        GoalStatus::Paused => "на паузе",
+        GoalStatus::Cancelled => "отменена",
+        GoalStatus::Expired => "срок истек",
+    }
+}
+
+/// Результат секции: при ошибке секция пропускается, отчет формируется без нее
+fn section<T>(name: &str, user_id: Uuid, result: Result<T, AppError>) -> Option<T> {
+    result
+        .map_err(|e| warn!("Monthly report section '{}' failed for user {}: {:?}", name, user_id, e))
+        .ok()
+}
+
+pub struct MonthlyReportService {
+    pool: DbPool,
+}
+
+impl MonthlyReportService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_report(&self, user_id: Uuid, month: NaiveDate) -> Result<MonthlyReport, AppError> {
+        sqlx::query_as::<_, MonthlyReport>(
+            "SELECT * FROM monthly_reports WHERE user_id = $1 AND month = $2"
+        )
+        .bind(user_id)
+        .bind(month)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Report not found".to_string()))
+    }
+
+    /// Формирует HTML отчета. `None` — за месяц у пользователя нет данных, отчет не нужен.
+    pub async fn render(&self, user_id: Uuid, user_name: &str, month: NaiveDate) -> Result<Option<String>, AppError> {
+        let (start, end) = month_bounds(month);
+        // Аналитика холодильника включает конец интервала
+        let inclusive_end = end - chrono::Duration::microseconds(1);
+        let fridge_service = FridgeService::new(self.pool.clone());
+
+        let analytics = section(
+            "fridge",
+            user_id,
+            fridge_service.get_expense_analytics_between(user_id, "month", start, inclusive_end, AnalyticsOptions::default()).await,
+        );
+        let wastes = section(
+            "waste",
+            user_id,
+            fridge_service.get_waste_history(user_id, Some(start), Some(inclusive_end)).await,
+        );
+        let saved_recipes = section("saved_recipes", user_id, self.saved_recipes(user_id, start, end).await);
+        let goals = section("goals", user_id, self.goals(user_id, start, end).await);
+
+        let has_data = analytics.as_ref().is_some_and(|analytics| analytics.total_purchased > 0.0 || analytics.total_wasted > 0.0)
+            || wastes.as_ref().is_some_and(|wastes| !wastes.is_empty())
+            || saved_recipes.as_ref().is_some_and(|recipes| !recipes.is_empty())
+            || goals.as_ref().is_some_and(|goals| goals.iter().any(|goal| goal.updated_at >= start && goal.updated_at < end));
+
+        if !has_data {
+            return Ok(None);
+        }
+
+        let template = MonthlyReportTemplate {
+            user_name: user_name.to_string(),
+            month_label: month_label(month),
+            waste: analytics.as_ref().zip(wastes.as_ref()).map(|(analytics, wastes)| waste_section(analytics, wastes)),
+            fridge: analytics.map(fridge_section),
+            saved_recipes,
+            goals: goals.map(|goals| {
+                goals
+                    .iter()
+                    .map(|goal| GoalLine {
+                        title: goal.title.clone(),
+                        progress: format!("{:.0}", goal.progress_percentage()),
+                        status: goal_status_label(&goal.status),
+                    })
+                    .collect()
+            }),
+        };
+
+        template
+            .render()
+            .map(Some)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to render monthly report: {}", e)))
+    }
+
+    /// Рецепты, сохраненные за месяц; первыми — самые популярные у всех пользователей
+    async fn saved_recipes(&self, user_id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SavedRecipeLine>, AppError> {
+        let recipes = sqlx::query_as::<_, SavedRecipeLine>(
+            r#"
+            SELECT r.name, (SELECT COUNT(*) FROM recipe_favorites all_f WHERE all_f.recipe_id = r.id) AS saves
+            FROM recipe_favorites f
+            JOIN recipes r ON r.id = f.recipe_id
+            WHERE f.user_id = $1 AND f.created_at >= $2 AND f.created_at < $3
+            ORDER BY saves DESC, f.created_at DESC
+            LIMIT $4
+            "#
+        )
+        .bind(user_id)
+        .bind(start)
+        .bind(end)
+        .bind(TOP_LIMIT as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(recipes)
+    }
+
+    /// Цели, которые были активны в течение месяца (текущий прогресс)
+    async fn goals(&self, user_id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Goal>, AppError> {
+        let goals = sqlx::query_as::<_, Goal>(
+            r#"
+            SELECT * FROM goals
+            WHERE user_id = $1
+              AND created_at < $3
+              AND (status = 'active' OR updated_at >= $2)
+            ORDER BY updated_at DESC
+            LIMIT 10
+            "#
+        )
+        .bind(user_id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(goals)
+    }
+}
+
+fn fridge_section(analytics: ExpenseAnalytics) -> FridgeSection {
+    let mut categories = analytics.category_breakdown;
+    categories.sort_by(|a, b| b.purchased.partial_cmp(&a.purchased).unwrap_or(std::cmp::Ordering::Equal));
+
+    FridgeSection {
+        total_purchased: money(analytics.total_purchased),
+        total_consumed: money(analytics.total_consumed),
+        total_wasted: money(analytics.total_wasted),
+        waste_percentage: format!("{:.1}", analytics.waste_percentage),
+        savings_potential: money(analytics.savings_potential),
+        categories: categories
+            .into_iter()
+            .map(|category| CategoryLine {
+                label: category.category.label_ru(),
+                purchased: money(category.purchased),
+                wasted: money(category.wasted),
+            })
+            .collect(),
+    }
+}
+
+fn waste_section(analytics: &ExpenseAnalytics, wastes: &[FoodWaste]) -> WasteSection {
+    let mut by_reason: Vec<_> = analytics.waste_by_reason.iter().collect();
+    by_reason.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Один и тот же продукт мог выбрасываться несколько раз
+    let mut products: HashMap<String, (String, usize, f64)> = HashMap::new();
+    for waste in wastes {
+        let entry = products
+            .entry(waste.name.trim().to_lowercase())
+            .or_insert_with(|| (waste.name.trim().to_string(), 0, 0.0));
+        entry.1 += 1;
+        entry.2 += waste.wasted_value.unwrap_or(0.0) as f64;
+    }
+
+    let mut top_products: Vec<_> = products.into_values().collect();
+    top_products.sort_by(|a, b| {
+        b.1.cmp(&a.1).then(b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    WasteSection {
+        by_reason: by_reason
+            .into_iter()
+            .map(|reason| ReasonLine {
+                label: reason.reason.label_ru(),
+                amount: money(reason.amount),
+                percentage: format!("{:.1}", reason.percentage),
+            })
+            .collect(),
+        top_products: top_products
+            .into_iter()
+            .take(TOP_LIMIT)
+            .map(|(name, times, value)| WastedProductLine { name, times, value: money(value) })
+            .collect(),
+    }
+}
+
+/// Первого числа формирует отчеты за прошлый месяц и отправляет их тем,
+/// кто включил ежемесячный отчет в настройках холодильника
+pub struct MonthlyReportScheduler {
+    pool: DbPool,
+    mail_service: MailService,
+    clock: SharedClock,
+}
+
+impl MonthlyReportScheduler {
+    pub fn new(pool: DbPool, mail_service: MailService) -> Self {
+        Self { pool, mail_service, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Запускает периодическую проверку в фоне
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.run_once(self.clock.now().date_naive()).instrument(telemetry::job_span("monthly_reports")).await {
+                    Ok(sent) if sent > 0 => info!("Monthly reports sent: {}", sent),
+                    Ok(_) => {}
+                    Err(e) => warn!("Monthly report run failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    /// Одна проверка на дату `today`. Отчет за месяц формируется один раз и не отправляется
+    /// повторно; если отправка не удалась, следующая проверка в тот же день повторит ее.
+    pub async fn run_once(&self, today: NaiveDate) -> Result<usize, AppError> {
+        if today.day() != 1 {
+            return Ok(0);
+        }
+
+        let month = previous_month(today);
+        let recipients = sqlx::query_as::<_, Recipient>(
+            r#"
+            SELECT u.id AS user_id, u.email, u.first_name, (r.id IS NOT NULL) AS has_report
+            FROM user_preferences p
+            JOIN users u ON u.id = p.user_id
+            LEFT JOIN monthly_reports r ON r.user_id = p.user_id AND r.month = $1
+            WHERE (p.settings->'reports'->>'monthly_email')::boolean IS TRUE
+              AND r.sent_at IS NULL
+            "#
+        )
+        .bind(month)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let report_service = MonthlyReportService::new(self.pool.clone());
+        let mut sent = 0;
+
+        for recipient in &recipients {
+            if !recipient.has_report {
+                let Some(html) = report_service.render(recipient.user_id, &recipient.first_name, month).await? else {
+                    continue;
+                };
+
+                // Если отчет уже сохранен параллельным запуском, остается первая версия
+                sqlx::query(
+                    r#"
+                    INSERT INTO monthly_reports (id, user_id, month, html)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (user_id, month) DO NOTHING
+                    "#
+                )
+                .bind(Uuid::new_v4())
+                .bind(recipient.user_id)
+                .bind(month)
+                .bind(&html)
+                .execute(&self.pool)
+                .await?;
+            }
+
+            if self.send_report(recipient, month).await? {
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Отмечает отчет отправленным до отправки, чтобы письмо не ушло дважды;
+    /// при ошибке отметка снимается
+    async fn send_report(&self, recipient: &Recipient, month: NaiveDate) -> Result<bool, AppError> {
+        let claimed: Option<(String,)> = sqlx::query_as(
+            r#"
+            UPDATE monthly_reports SET sent_at = NOW()
+            WHERE user_id = $1 AND month = $2 AND sent_at IS NULL
+            RETURNING html
+            "#
+        )
+        .bind(recipient.user_id)
+        .bind(month)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((html,)) = claimed else {
+            return Ok(false);
+        };
+
+        let message = MailMessage {
+            to: recipient.email.clone(),
+            subject: format!("Ваш холодильник за {}", month_label(month)),
+            html,
+        };
+
+        if let Err(e) = self.mail_service.send(&message).await {
+            warn!("Failed to send monthly report to user {}: {:?}", recipient.user_id, e);
+            sqlx::query("UPDATE monthly_reports SET sent_at = NULL WHERE user_id = $1 AND month = $2")
+                .bind(recipient.user_id)
+                .bind(month)
+                .execute(&self.pool)
+                .await?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use axum::http::StatusCode;
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        services::preferences::PreferencesService,
+        test_support::{access_token, insert_fridge_item, insert_recipe, insert_user, mail_server, request, test_router},
+    };
+
+    fn fixture() -> MonthlyReportTemplate {
+        MonthlyReportTemplate {
+            user_name: "Анна <script>alert(1)</script>".to_string(),
+            month_label: month_label(NaiveDate::from_ymd_opt(2026, 9, 1).unwrap()),
+            fridge: Some(FridgeSection {
+                total_purchased: money(1520.5),
+                total_consumed: money(1210.0),
+                total_wasted: money(310.5),
+                waste_percentage: "20.4".to_string(),
+                savings_potential: money(248.4),
+                categories: vec![CategoryLine { label: "Молочные продукты", purchased: money(820.0), wasted: money(120.0) }],
+            }),
+            waste: Some(WasteSection {
+                by_reason: vec![ReasonLine { label: "Истек срок годности", amount: money(310.5), percentage: "100.0".to_string() }],
+                top_products: vec![WastedProductLine { name: "<b>Сыр & \"Ко\"</b>".to_string(), times: 2, value: money(190.0) }],
+            }),
+            saved_recipes: Some(vec![SavedRecipeLine { name: "Борщ <img src=x onerror=alert(1)>".to_string(), saves: 12 }]),
+            goals: Some(vec![GoalLine { title: "Меньше отходов".to_string(), progress: "75".to_string(), status: "в процессе" }]),
+        }
+    }
+
+    #[test]
+    fn template_renders_fixture_and_escapes_user_content() {
+        let html = fixture().render().unwrap();
+
+        assert!(html.contains("Ваш холодильник за сентябрь 2026"));
+        assert!(html.contains("1520.50") && html.contains("310.50 (20.4%)") && html.contains("248.40"));
+        assert!(html.contains("Молочные продукты") && html.contains("Истек срок годности"));
+        assert!(html.contains("2 раз") && html.contains("сохранили 12 чел."));
+        assert!(html.contains("Меньше отходов") && html.contains("75%"));
+
+        assert!(html.contains("Анна &lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("&lt;b&gt;Сыр &amp; &quot;Ко&quot;&lt;/b&gt;"));
+        assert!(html.contains("Борщ &lt;img src=x onerror=alert(1)&gt;"));
+        assert!(!html.contains("<script>") && !html.contains("<b>Сыр") && !html.contains("<img"));
+    }
+
+    #[test]
+    fn missing_and_empty_sections_render_notes() {
+        let missing = MonthlyReportTemplate { fridge: None, waste: None, saved_recipes: None, goals: None, ..fixture() }
+            .render()
+            .unwrap();
+        for note in [
+            "Данные о покупках сейчас недоступны.",
+            "Данные об отходах сейчас недоступны.",
+            "Список рецептов сейчас недоступен.",
+            "Прогресс целей сейчас недоступен.",
+        ] {
+            assert!(missing.contains(note), "{}", note);
+        }
+
+        let empty = MonthlyReportTemplate {
+            waste: Some(WasteSection { by_reason: Vec::new(), top_products: Vec::new() }),
+            saved_recipes: Some(Vec::new()),
+            goals: Some(Vec::new()),
+            ..fixture()
+        }
+        .render()
+        .unwrap();
+        assert!(empty.contains("В этом месяце ничего не выброшено"));
+        assert!(empty.contains("В этом месяце вы не сохраняли рецепты."));
+        assert!(empty.contains("Активных целей нет."));
+        assert!(!empty.contains("Чаще всего выбрасывались"));
+    }
+
+    #[test]
+    fn month_helpers() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(parse_month(" 2024-05 ").unwrap(), date(2024, 5, 1));
+        assert!(matches!(parse_month("2024-13"), Err(AppError::BadRequest(_))));
+        assert!(matches!(parse_month("май"), Err(AppError::BadRequest(_))));
+
+        assert_eq!(previous_month(date(2026, 10, 1)), date(2026, 9, 1));
+        assert_eq!(previous_month(date(2026, 1, 17)), date(2025, 12, 1));
+
+        let (start, end) = month_bounds(date(2025, 12, 1));
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    async fn opt_in(pool: &PgPool, user_id: Uuid) {
+        PreferencesService::new(pool.clone())
+            .update(user_id, |settings| settings.reports.monthly_email = true)
+            .await
+            .unwrap();
+    }
+
+    fn september(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 9, day, 12, 0, 0).unwrap()
+    }
+
+    fn first_of_october() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 1).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn monthly_job_sends_each_report_once(pool: PgPool) {
+        let (mail, received, failing) = mail_server().await;
+        let active = insert_user(&pool, "Анна").await;
+        let idle = insert_user(&pool, "Борис").await;
+        let opted_out = insert_user(&pool, "Вера").await;
+        opt_in(&pool, active).await;
+        opt_in(&pool, idle).await;
+
+        for user_id in [active, opted_out] {
+            let item = insert_fridge_item(&pool, user_id, "Молоко", None, september(10)).await;
+            sqlx::query("UPDATE fridge_items SET price_per_unit = 120, total_price = 120 WHERE id = $1")
+                .bind(item.id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let recipe = insert_recipe(&pool, opted_out, "Борщ <b>по-домашнему</b>", true).await;
+        sqlx::query("INSERT INTO recipe_favorites (recipe_id, user_id, created_at) VALUES ($1, $2, $3)")
+            .bind(recipe)
+            .bind(active)
+            .bind(september(15))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let scheduler = MonthlyReportScheduler::new(pool.clone(), mail);
+
+        // Не первое число — ничего не происходит
+        assert_eq!(scheduler.run_once(first_of_october().succ_opt().unwrap()).await.unwrap(), 0);
+
+        // Сбой провайдера: отчет сохранен, но не отмечен отправленным
+        failing.store(true, Ordering::SeqCst);
+        assert_eq!(scheduler.run_once(first_of_october()).await.unwrap(), 0);
+        let report = MonthlyReportService::new(pool.clone()).get_report(active, previous_month(first_of_october())).await.unwrap();
+        assert!(report.sent_at.is_none());
+
+        failing.store(false, Ordering::SeqCst);
+        assert_eq!(scheduler.run_once(first_of_october()).await.unwrap(), 1);
+        // Повторные запуски писем не дублируют
+        assert_eq!(scheduler.run_once(first_of_october()).await.unwrap(), 0);
+        assert_eq!(scheduler.run_once(first_of_october()).await.unwrap(), 0);
+
+        let emails = received.lock().unwrap().clone();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0]["subject"], "Ваш холодильник за сентябрь 2026");
+        let html = emails[0]["html"].as_str().unwrap().to_string();
+        assert!(html.contains("120.00"));
+        assert!(html.contains("Борщ &lt;b&gt;по-домашнему&lt;/b&gt;"));
+
+        // Без данных за месяц отчета нет; без согласия — тоже
+        let (reports,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM monthly_reports").fetch_one(&pool).await.unwrap();
+        assert_eq!(reports, 1);
+        assert!(MonthlyReportService::new(pool.clone()).get_report(idle, previous_month(first_of_october())).await.is_err());
+
+        // В приложении отдается тот же HTML, что ушел по почте
+        let token = access_token(&pool, active).await;
+        let response = test_router(&pool)
+            .oneshot(request(axum::http::Method::GET, "/api/v1/reports/monthly?month=2026-09", Some(&token), None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap(), html);
+    }
+}
+
@@ -0,0 +1,287 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::types::Json;
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::notification::{NotificationChannel, NotificationEvent, NotificationPreferences, QuietHours},
+    services::{
+        notifications::NotificationService,
+        preferences::PreferencesService,
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::errors::AppError,
+};
+
+/// Как часто доставляются уведомления, отложенные на тихие часы
+const QUEUE_SWEEP_INTERVAL: StdDuration = StdDuration::from_secs(5 * 60);
+
+/// Уведомление для inbox и WebSocket
+#[derive(Debug, Clone)]
+pub struct OutgoingNotification {
+    pub kind: &'static str,
+    pub title: String,
+    pub message: String,
+    pub data: serde_json::Value,
+    pub event: WebSocketEvent,
+}
+
+/// Что делать с уведомлением по настройкам пользователя
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeliveryPlan {
+    /// Уведомления выключены (общий mute или канал `off`)
+    Skip,
+    Now(NotificationChannel),
+    /// Тихие часы: inbox пишется сразу, WebSocket-событие откладывается до `deliver_at`
+    Queue { channel: NotificationChannel, deliver_at: DateTime<Utc> },
+}
+
+impl DeliveryPlan {
+    pub fn is_skip(&self) -> bool {
+        matches!(self, DeliveryPlan::Skip)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct QueuedNotification {
+    user_id: Uuid,
+    kind: String,
+    websocket_event: Json<WebSocketEvent>,
+}
+
+/// Доставка персональных уведомлений с учетом настроек: mute, канал для типа события
+/// и тихие часы. Планировщики отправляют уведомления только через этот сервис.
+#[derive(Clone)]
+pub struct NotificationDelivery {
+    pool: DbPool,
+    realtime_service: Arc<RealtimeService>,
+}
+
+impl NotificationDelivery {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>) -> Self {
+        Self { pool, realtime_service }
+    }
+
+    pub async fn preferences(&self, user_id: Uuid) -> Result<NotificationPreferences, AppError> {
+        Ok(PreferencesService::new(self.pool.clone()).for_user(user_id).await?.notifications)
+    }
+
+    pub async fn send(
+        &self,
+        user_id: Uuid,
+        event: NotificationEvent,
+        notification: OutgoingNotification,
+        now: DateTime<Utc>,
+    ) -> Result<DeliveryPlan, AppError> {
+        let preferences = self.preferences(user_id).await?;
+        self.send_with(&preferences, user_id, event, notification, now).await
+    }
+
+    /// Как `send`, когда настройки уже загружены
+    pub async fn send_with(
+        &self,
+        preferences: &NotificationPreferences,
+        user_id: Uuid,
+        event: NotificationEvent,
+        notification: OutgoingNotification,
+        now: DateTime<Utc>,
+    ) -> Result<DeliveryPlan, AppError> {
+        let plan = plan_delivery(preferences, event, now);
+        let channel = match plan {
+            DeliveryPlan::Skip => return Ok(plan),
+            DeliveryPlan::Now(channel) | DeliveryPlan::Queue { channel, .. } => channel,
+        };
+
+        if channel.inbox() {
+            NotificationService::new(self.pool.clone())
+                .create(user_id, notification.kind, &notification.title, &notification.message, notification.data)
+                .await?;
+        }
+
+        if channel.websocket() {
+            match plan {
+                DeliveryPlan::Queue { deliver_at, .. } => {
+                    sqlx::query(
+                        "INSERT INTO queued_notifications (user_id, kind, websocket_event, deliver_at) VALUES ($1, $2, $3, $4)"
+                    )
+                    .bind(user_id)
+                    .bind(notification.kind)
+                    .bind(Json(&notification.event))
+                    .bind(deliver_at)
+                    .execute(&self.pool)
+                    .await?;
+                }
+                _ => {
+                    let _ = self.realtime_service.send_to_user(user_id, notification.event).await;
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Отправляет отложенные события, у которых закончились тихие часы.
+    /// Если пользователь за это время выключил уведомления, событие отбрасывается.
+    pub async fn deliver_queued(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let due = sqlx::query_as::<_, QueuedNotification>(
+            "DELETE FROM queued_notifications WHERE deliver_at <= $1 RETURNING user_id, kind, websocket_event"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut delivered = 0;
+        for queued in due {
+            if self.preferences(queued.user_id).await?.is_muted(now) {
+                continue;
+            }
+
+            if let Err(e) = self.realtime_service.send_to_user(queued.user_id, queued.websocket_event.0).await {
+                warn!("Failed to deliver queued {} notification to user {}: {:?}", queued.kind, queued.user_id, e);
+                continue;
+            }
+            delivered += 1;
+        }
+
+        Ok(delivered)
+    }
+
+    /// Запускает периодическую доставку отложенных уведомлений
+    pub fn start_queue_sweep(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QUEUE_SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.deliver_queued(Utc::now()).instrument(telemetry::job_span("notification_queue")).await {
+                    Ok(delivered) if delivered > 0 => info!("Delivered {} queued notifications", delivered),
+                    Ok(_) => {}
+                    Err(e) => warn!("Queued notification delivery failed: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+/// Решение о доставке на момент `now`
+pub fn plan_delivery(preferences: &NotificationPreferences, event: NotificationEvent, now: DateTime<Utc>) -> DeliveryPlan {
+    if preferences.is_muted(now) {
+        return DeliveryPlan::Skip;
+    }
+
+    let channel = preferences.channels.for_event(event);
+    if channel == NotificationChannel::Off {
+        return DeliveryPlan::Skip;
+    }
+
+    match preferences.quiet_hours.as_ref().and_then(|quiet_hours| quiet_hours_end(quiet_hours, now)) {
+        Some(deliver_at) => DeliveryPlan::Queue { channel, deliver_at },
+        None => DeliveryPlan::Now(channel),
+    }
+}
+
+/// Если `now` попадает в тихие часы — момент их окончания (UTC), иначе `None`
+pub fn quiet_hours_end(quiet_hours: &QuietHours, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let (start, end) = (quiet_hours.start, quiet_hours.end);
+    if start == end {
+        return None;
+    }
+
+    let offset = Duration::minutes(quiet_hours.utc_offset_minutes as i64);
+    let local = (now + offset).naive_utc();
+    let time = local.time();
+
+    let crosses_midnight = start > end;
+    let inside = if crosses_midnight {
+        time >= start || time < end
+    } else {
+        time >= start && time < end
+    };
+    if !inside {
+        return None;
+    }
+
+    // Окно 22:00–08:00 в 23:00 заканчивается завтра, в 02:00 — сегодня
+    let end_date = if crosses_midnight && time >= start {
+        local.date().succ_opt()?
+    } else {
+        local.date()
+    };
+
+    Some(DateTime::from_naive_utc_and_offset(end_date.and_time(end) - offset, Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone};
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    fn quiet(start: u32, end: u32, utc_offset_minutes: i32) -> QuietHours {
+        QuietHours {
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+            utc_offset_minutes,
+        }
+    }
+
+    #[test]
+    fn quiet_hours_end_across_midnight_and_offsets() {
+        let night = quiet(22, 8, 0);
+        assert_eq!(quiet_hours_end(&night, at(16, 21, 59)), None);
+        assert_eq!(quiet_hours_end(&night, at(16, 22, 0)), Some(at(17, 8, 0)));
+        assert_eq!(quiet_hours_end(&night, at(17, 3, 0)), Some(at(17, 8, 0)));
+        // Конец окна — уже не тихие часы
+        assert_eq!(quiet_hours_end(&night, at(17, 8, 0)), None);
+
+        // Москва (UTC+3): 20:30 UTC — 23:30 местного, утро наступит в 05:00 UTC
+        let moscow = quiet(22, 8, 180);
+        assert_eq!(quiet_hours_end(&moscow, at(16, 20, 30)), Some(at(17, 5, 0)));
+        assert_eq!(quiet_hours_end(&moscow, at(16, 18, 59)), None);
+        // Нью-Йорк (UTC−4): 03:00 UTC — 23:00 прошлого дня по местному
+        assert_eq!(quiet_hours_end(&quiet(22, 7, -240), at(17, 3, 0)), Some(at(17, 11, 0)));
+
+        // Дневное окно без перехода через полночь
+        let siesta = quiet(13, 15, 0);
+        assert_eq!(quiet_hours_end(&siesta, at(16, 14, 0)), Some(at(16, 15, 0)));
+        assert_eq!(quiet_hours_end(&siesta, at(16, 23, 0)), None);
+
+        assert_eq!(quiet_hours_end(&quiet(9, 9, 0), at(16, 9, 0)), None);
+    }
+
+    #[test]
+    fn plan_follows_mute_channel_and_quiet_hours() {
+        let mut preferences = NotificationPreferences {
+            quiet_hours: Some(quiet(22, 8, 0)),
+            ..Default::default()
+        };
+        preferences.channels.goal_reminders = NotificationChannel::Inbox;
+        preferences.channels.diary_reminder = NotificationChannel::Off;
+
+        let day = at(16, 12, 0);
+        let night = at(16, 23, 0);
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::ExpiringItems, day), DeliveryPlan::Now(NotificationChannel::Both));
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::GoalReminders, day), DeliveryPlan::Now(NotificationChannel::Inbox));
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::DiaryReminder, day), DeliveryPlan::Skip);
+        assert_eq!(
+            plan_delivery(&preferences, NotificationEvent::ExpiringItems, night),
+            DeliveryPlan::Queue { channel: NotificationChannel::Both, deliver_at: at(17, 8, 0) }
+        );
+
+        // Mute до указанного момента, после него уведомления снова идут
+        preferences.muted = true;
+        preferences.muted_until = Some(at(16, 18, 0));
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::ExpiringItems, day), DeliveryPlan::Skip);
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::ExpiringItems, at(16, 18, 0)), DeliveryPlan::Now(NotificationChannel::Both));
+        preferences.muted_until = None;
+        assert_eq!(plan_delivery(&preferences, NotificationEvent::ExpiringItems, at(30, 12, 0)), DeliveryPlan::Skip);
+    }
+}
@@ -0,0 +1,566 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use sqlx::types::Json;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    db::DbPool,
+    services::{
+        activity_status::ActivityStatusService,
+        clock::{self, SharedClock},
+        preferences::PreferencesService,
+        realtime::{RealtimeService, WebSocketEvent},
+        task_supervisor::TaskSupervisor,
+    },
+    utils::{errors::AppError, format::plural_ru},
+};
+
+/// Как часто сводки с закончившимся окном переносятся в inbox
+const FLUSH_INTERVAL: StdDuration = StdDuration::from_secs(60);
+/// Сколько сводок переносится за один проход
+const FLUSH_BATCH_SIZE: i64 = 500;
+/// Сколько имен показывать в сводке
+const MAX_ACTOR_NAMES_IN_DATA: usize = 3;
+
+/// Типы событий, которые объединяются в сводки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestKind {
+    /// Новые посты автора, на которого подписан получатель; источник — автор
+    NewPost,
+    /// Лайки поста получателя; источник — пост
+    PostLiked,
+    /// Комментарии к посту получателя; источник — пост
+    NewComment,
+    /// Отметки «приготовил(а)» на посте получателя; источник — пост
+    PostCooked,
+}
+
+impl DigestKind {
+    /// Тип уведомления в inbox (`notifications.kind`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestKind::NewPost => "new_post",
+            DigestKind::PostLiked => "post_liked",
+            DigestKind::NewComment => "new_comment",
+            DigestKind::PostCooked => "post_cooked",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        [DigestKind::NewPost, DigestKind::PostLiked, DigestKind::NewComment, DigestKind::PostCooked]
+            .into_iter()
+            .find(|digest| digest.as_str() == kind)
+    }
+
+    /// Заголовок и текст уведомления для `count` событий; `actor` — первый, кто их вызвал
+    pub fn render(&self, count: i64, actor: &str) -> (String, String) {
+        match (self, count) {
+            (DigestKind::NewPost, 1) => ("Новый пост".to_string(), format!("{} опубликовал(а) новый пост", actor)),
+            (DigestKind::NewPost, _) => (
+                "Новые посты".to_string(),
+                format!("{} опубликовал(а) {} {}", actor, count, plural_ru(count, "новый пост", "новых поста", "новых постов")),
+            ),
+            (DigestKind::PostLiked, 1) => ("Новый лайк".to_string(), format!("{} оценил(а) ваш пост", actor)),
+            (DigestKind::PostLiked, _) => (
+                "Новые лайки".to_string(),
+                format!("Ваш пост понравился {} {}", count, plural_ru(count, "человеку", "людям", "людям")),
+            ),
+            (DigestKind::NewComment, 1) => ("Новый комментарий".to_string(), format!("{} прокомментировал(а) ваш пост", actor)),
+            (DigestKind::NewComment, _) => (
+                "Новые комментарии".to_string(),
+                format!(
+                    "{} {} к вашему посту",
+                    count,
+                    plural_ru(count, "новый комментарий", "новых комментария", "новых комментариев")
+                ),
+            ),
+            (DigestKind::PostCooked, 1) => ("Приготовили ваш рецепт".to_string(), format!("{} приготовил(а) блюдо по вашему посту", actor)),
+            (DigestKind::PostCooked, _) => (
+                "Ваш рецепт готовят".to_string(),
+                format!("По вашему посту приготовили {} {}", count, plural_ru(count, "человек", "человека", "человек")),
+            ),
+        }
+    }
+}
+
+/// Одно событие для сводки
+#[derive(Debug, Clone)]
+pub struct DigestEvent {
+    pub kind: DigestKind,
+    pub source_id: Uuid,
+    /// Одинаковый у повторов того же события (ретрай запроса, повторный лайк)
+    pub event_key: String,
+    pub actor_name: String,
+    /// Данные первого события — попадают в `data` уведомления
+    pub data: serde_json::Value,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingDigest {
+    id: Uuid,
+    user_id: Uuid,
+    kind: String,
+    source_id: Uuid,
+    event_keys: Vec<String>,
+    actor_names: Vec<String>,
+    data: Json<serde_json::Value>,
+}
+
+/// Объединение уведомлений: события за окно копятся в `notification_digests`,
+/// после окна в inbox попадает одна запись со счетчиком
+#[derive(Clone)]
+pub struct NotificationDigestService {
+    pool: DbPool,
+    window: Duration,
+    clock: SharedClock,
+}
+
+impl NotificationDigestService {
+    pub fn new(pool: DbPool, window_minutes: i64) -> Self {
+        Self { pool, window: Duration::minutes(window_minutes), clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — фоновый перенос закрывает окна по времени песочницы
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Добавляет событие в открытую сводку получателя или открывает новую.
+    /// Повтор уже учтенного события ничего не меняет; возвращает, было ли событие учтено.
+    pub async fn record(&self, user_id: Uuid, event: &DigestEvent, now: DateTime<Utc>) -> Result<bool, AppError> {
+        let counted = sqlx::query(
+            r#"
+            INSERT INTO notification_digests
+                (user_id, kind, source_id, event_keys, actor_names, data, first_event_at, flush_after)
+            SELECT $1, $2, $3, ARRAY[$4], ARRAY[$5], $6, $7, $8
+            WHERE NOT EXISTS (
+                SELECT 1 FROM notification_digests
+                WHERE user_id = $1 AND kind = $2 AND source_id = $3 AND $4 = ANY(event_keys)
+            )
+            ON CONFLICT (user_id, kind, source_id) WHERE flushed_at IS NULL
+            DO UPDATE SET
+                event_keys = array_append(notification_digests.event_keys, $4),
+                actor_names = CASE
+                    WHEN $5 = ANY(notification_digests.actor_names) THEN notification_digests.actor_names
+                    ELSE array_append(notification_digests.actor_names, $5)
+                END
+            "#
+        )
+        .bind(user_id)
+        .bind(event.kind.as_str())
+        .bind(event.source_id)
+        .bind(&event.event_key)
+        .bind(&event.actor_name)
+        .bind(Json(&event.data))
+        .bind(now)
+        .bind(now + self.window)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(counted > 0)
+    }
+
+    /// Переносит в inbox сводки, у которых закончилось окно. Сводка закрывается в той же транзакции,
+    /// что и создается запись inbox, поэтому повторный или параллельный проход ее не продублирует.
+    pub async fn flush_due(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let due: Vec<PendingDigest> = sqlx::query_as(
+            r#"
+            SELECT id, user_id, kind, source_id, event_keys, actor_names, data
+            FROM notification_digests
+            WHERE flushed_at IS NULL AND flush_after <= $1
+            ORDER BY flush_after
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .bind(now)
+        .bind(FLUSH_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let preferences = PreferencesService::new(self.pool.clone());
+        let mut flushed = 0;
+        for digest in due {
+            let kind = DigestKind::parse(&digest.kind);
+            // Пока уведомления выключены, сводка закрывается без записи в inbox
+            let muted = preferences.for_user(digest.user_id).await?.notifications.is_muted(now);
+
+            let notification_id = match kind.filter(|_| !muted) {
+                Some(kind) => {
+                    let count = digest.event_keys.len() as i64;
+                    let actor = digest.actor_names.first().map(String::as_str).unwrap_or_default();
+                    let (title, message) = kind.render(count, actor);
+                    let mut data = digest.data.0;
+                    if let Some(fields) = data.as_object_mut() {
+                        fields.insert("source_id".to_string(), json!(digest.source_id));
+                        fields.insert("count".to_string(), json!(count));
+                        fields.insert(
+                            "actors".to_string(),
+                            json!(digest.actor_names.iter().take(MAX_ACTOR_NAMES_IN_DATA).collect::<Vec<_>>()),
+                        );
+                    }
+
+                    let (id,): (Uuid,) = sqlx::query_as(
+                        r#"
+                        INSERT INTO notifications (id, user_id, kind, title, message, data)
+                        VALUES ($1, $2, $3, $4, $5, $6)
+                        RETURNING id
+                        "#
+                    )
+                    .bind(Uuid::new_v4())
+                    .bind(digest.user_id)
+                    .bind(kind.as_str())
+                    .bind(&title)
+                    .bind(&message)
+                    .bind(Json(data))
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    flushed += 1;
+                    Some(id)
+                }
+                None => None,
+            };
+
+            sqlx::query("UPDATE notification_digests SET flushed_at = $2, notification_id = $3 WHERE id = $1")
+                .bind(digest.id)
+                .bind(now)
+                .bind(notification_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(flushed)
+    }
+
+    /// Регистрирует периодический перенос сводок в inbox в супервизоре фоновых задач
+    pub fn register_flush_task(self, supervisor: &TaskSupervisor) {
+        supervisor.register_task("notification_digests", FLUSH_INTERVAL, move || {
+            let digests = self.clone();
+            async move {
+                let flushed = digests.flush_due(digests.clock.now()).await?;
+                if flushed > 0 {
+                    info!("Flushed {} notification digests", flushed);
+                }
+                Ok(())
+            }
+        });
+    }
+}
+
+/// Уведомления сообщества: подключенный получатель сразу получает каждое событие по WebSocket,
+/// а в inbox оно попадает сводкой. Ошибки только логируются — действие пользователя уже выполнено.
+pub struct CommunityNotifier {
+    pool: DbPool,
+    digests: NotificationDigestService,
+    realtime_service: Arc<RealtimeService>,
+}
+
+/// Для обработчиков: пул, WebSocket и окно сводок берутся из расширений запроса
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for CommunityNotifier
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let missing = |name: &str| AppError::InternalServerError(format!("{} extension is missing", name));
+
+        let pool = parts.extensions.get::<DbPool>().cloned().ok_or_else(|| missing("DbPool"))?;
+        let realtime_service = parts.extensions.get::<Arc<RealtimeService>>().cloned().ok_or_else(|| missing("RealtimeService"))?;
+        let config = parts.extensions.get::<Config>().ok_or_else(|| missing("Config"))?;
+
+        Ok(Self::new(pool, realtime_service, config.notification_digest_window_minutes))
+    }
+}
+
+impl CommunityNotifier {
+    pub fn new(pool: DbPool, realtime_service: Arc<RealtimeService>, window_minutes: i64) -> Self {
+        Self {
+            digests: NotificationDigestService::new(pool.clone(), window_minutes),
+            pool,
+            realtime_service,
+        }
+    }
+
+    /// Новый пост — подписчикам автора
+    pub async fn post_published(&self, post_id: Uuid, author_id: Uuid, content: &str, now: DateTime<Utc>) {
+        if let Err(e) = self.try_post_published(post_id, author_id, content, now).await {
+            warn!("Failed to notify followers about post {}: {:?}", post_id, e);
+        }
+    }
+
+    /// Лайк — автору поста
+    pub async fn post_liked(&self, post_id: Uuid, liker_id: Uuid, now: DateTime<Utc>) {
+        if let Err(e) = self.try_post_liked(post_id, liker_id, now).await {
+            warn!("Failed to notify about like of post {}: {:?}", post_id, e);
+        }
+    }
+
+    /// Комментарий — автору поста
+    pub async fn comment_added(&self, post_id: Uuid, comment_id: Uuid, author_id: Uuid, content: &str, now: DateTime<Utc>) {
+        if let Err(e) = self.try_comment_added(post_id, comment_id, author_id, content, now).await {
+            warn!("Failed to notify about comment {} on post {}: {:?}", comment_id, post_id, e);
+        }
+    }
+
+    /// Отметка «приготовил(а)» — автору поста; только первая отметка пользователя, не ее правки
+    pub async fn post_cooked(&self, post_id: Uuid, cook_id: Uuid, rating: Option<i16>, cooked_count: i64, now: DateTime<Utc>) {
+        if let Err(e) = self.try_post_cooked(post_id, cook_id, rating, cooked_count, now).await {
+            warn!("Failed to notify about cooked post {}: {:?}", post_id, e);
+        }
+    }
+
+    async fn try_post_published(&self, post_id: Uuid, author_id: Uuid, content: &str, now: DateTime<Utc>) -> Result<(), AppError> {
+        let author_name = self.user_name(author_id).await?;
+        let event = DigestEvent {
+            kind: DigestKind::NewPost,
+            source_id: author_id,
+            event_key: post_id.to_string(),
+            actor_name: author_name.clone(),
+            data: json!({ "author_id": author_id, "post_id": post_id }),
+        };
+        let websocket_event = WebSocketEvent::NewCommunityPost {
+            post_id,
+            author_name,
+            content: content.to_string(),
+            timestamp: now,
+        };
+
+        for follower_id in ActivityStatusService::new(self.pool.clone()).follower_ids(author_id).await? {
+            if self.digests.record(follower_id, &event, now).await? {
+                let _ = self.realtime_service.send_to_user(follower_id, websocket_event.clone()).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn try_post_liked(&self, post_id: Uuid, liker_id: Uuid, now: DateTime<Utc>) -> Result<(), AppError> {
+        let Some(post_author_id) = self.post_author(post_id).await? else { return Ok(()) };
+        if post_author_id == liker_id {
+            return Ok(());
+        }
+
+        let liker_name = self.user_name(liker_id).await?;
+        let event = DigestEvent {
+            kind: DigestKind::PostLiked,
+            source_id: post_id,
+            // Лайк, снятый и поставленный снова, — то же событие
+            event_key: liker_id.to_string(),
+            actor_name: liker_name.clone(),
+            data: json!({ "post_id": post_id }),
+        };
+        if !self.digests.record(post_author_id, &event, now).await? {
+            return Ok(());
+        }
+
+        let (total_likes,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM likes WHERE post_id = $1")
+            .bind(post_id)
+            .fetch_one(&self.pool)
+            .await?;
+        let websocket_event = WebSocketEvent::PostLiked {
+            post_id,
+            liker_name,
+            total_likes: total_likes.max(0) as u32,
+        };
+        let _ = self.realtime_service.send_to_user(post_author_id, websocket_event).await;
+        Ok(())
+    }
+
+    async fn try_comment_added(
+        &self,
+        post_id: Uuid,
+        comment_id: Uuid,
+        author_id: Uuid,
+        content: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let Some(post_author_id) = self.post_author(post_id).await? else { return Ok(()) };
+        if post_author_id == author_id {
+            return Ok(());
+        }
+
+        let author_name = self.user_name(author_id).await?;
+        let event = DigestEvent {
+            kind: DigestKind::NewComment,
+            source_id: post_id,
+            event_key: comment_id.to_string(),
+            actor_name: author_name.clone(),
+            data: json!({ "post_id": post_id, "comment_id": comment_id }),
+        };
+        if !self.digests.record(post_author_id, &event, now).await? {
+            return Ok(());
+        }
+
+        let websocket_event = WebSocketEvent::NewComment {
+            post_id,
+            comment_id,
+            author_name,
+            content: content.to_string(),
+        };
+        let _ = self.realtime_service.send_to_user(post_author_id, websocket_event).await;
+        Ok(())
+    }
+
+    async fn try_post_cooked(
+        &self,
+        post_id: Uuid,
+        cook_id: Uuid,
+        rating: Option<i16>,
+        cooked_count: i64,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let Some(post_author_id) = self.post_author(post_id).await? else { return Ok(()) };
+        if post_author_id == cook_id {
+            return Ok(());
+        }
+
+        let cook_name = self.user_name(cook_id).await?;
+        let event = DigestEvent {
+            kind: DigestKind::PostCooked,
+            source_id: post_id,
+            event_key: cook_id.to_string(),
+            actor_name: cook_name.clone(),
+            data: json!({ "post_id": post_id, "rating": rating }),
+        };
+        if !self.digests.record(post_author_id, &event, now).await? {
+            return Ok(());
+        }
+
+        let websocket_event = WebSocketEvent::PostCooked {
+            post_id,
+            cook_name,
+            rating,
+            cooked_count: cooked_count.max(0) as u32,
+        };
+        let _ = self.realtime_service.send_to_user(post_author_id, websocket_event).await;
+        Ok(())
+    }
+
+    async fn post_author(&self, post_id: Uuid) -> Result<Option<Uuid>, AppError> {
+        let author: Option<(Uuid,)> = sqlx::query_as("SELECT author_id FROM posts WHERE id = $1")
+            .bind(post_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(author.map(|(id,)| id))
+    }
+
+    async fn user_name(&self, user_id: Uuid) -> Result<String, AppError> {
+        let (first_name, last_name): (String, String) =
+            sqlx::query_as("SELECT first_name, last_name FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(format!("{} {}", first_name, last_name).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::realtime::WebSocketManager;
+    use crate::test_support::{frozen_clock, insert_user};
+
+    const WINDOW_MINUTES: i64 = 15;
+
+    fn at(minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, minute, second).unwrap()
+    }
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid) -> Uuid {
+        let (id,): (Uuid,) = sqlx::query_as("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'Борщ', 'text') RETURNING id")
+            .bind(author_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    async fn inbox(pool: &PgPool, user_id: Uuid) -> Vec<(String, String, serde_json::Value)> {
+        sqlx::query_as::<_, (String, String, Json<serde_json::Value>)>(
+            "SELECT kind, message, data FROM notifications WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(kind, message, data)| (kind, message, data.0))
+        .collect()
+    }
+
+    #[sqlx::test]
+    async fn likes_within_the_window_become_one_inbox_row(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post_id = insert_post(&pool, author).await;
+        let notifier = CommunityNotifier::new(
+            pool.clone(),
+            Arc::new(RealtimeService::new(Arc::new(WebSocketManager::new()))),
+            WINDOW_MINUTES,
+        );
+
+        let mut likers = Vec::new();
+        for index in 0..10 {
+            let liker = insert_user(&pool, &format!("Гость {}", index)).await;
+            notifier.post_liked(post_id, liker, at(0, index)).await;
+            likers.push(liker);
+        }
+        // Повторный лайк того же пользователя и лайк собственного поста не считаются
+        notifier.post_liked(post_id, likers[0], at(5, 0)).await;
+        notifier.post_liked(post_id, author, at(5, 0)).await;
+
+        // Окно еще открыто: в inbox ничего нет
+        let digests = NotificationDigestService::new(pool.clone(), WINDOW_MINUTES);
+        assert_eq!(digests.flush_due(at(14, 59)).await.unwrap(), 0);
+        assert!(inbox(&pool, author).await.is_empty());
+
+        // Фоновый перенос берет время из часов сервиса, а не системное
+        let clock = frozen_clock(at(15, 0));
+        let tasks = TaskSupervisor::new();
+        digests.with_clock(clock).register_flush_task(&tasks);
+        let mut rows = Vec::new();
+        for _ in 0..100 {
+            rows = inbox(&pool, author).await;
+            if !rows.is_empty() {
+                break;
+            }
+            tokio::time::sleep(StdDuration::from_millis(50)).await;
+        }
+        tasks.shutdown(StdDuration::from_secs(5)).await;
+
+        assert_eq!(rows.len(), 1);
+        let (kind, message, data) = &rows[0];
+        assert_eq!(kind, "post_liked");
+        assert_eq!(message, "Ваш пост понравился 10 людям");
+        assert_eq!(data["count"], 10);
+        assert_eq!(data["post_id"], post_id.to_string());
+        assert_eq!(data["actors"].as_array().unwrap().len(), MAX_ACTOR_NAMES_IN_DATA);
+
+        // Следующий лайк открывает новое окно, закрытая сводка второй раз не переносится
+        let late = insert_user(&pool, "Борис").await;
+        notifier.post_liked(post_id, late, at(20, 0)).await;
+        let digests = NotificationDigestService::new(pool.clone(), WINDOW_MINUTES);
+        assert_eq!(digests.flush_due(at(34, 59)).await.unwrap(), 0);
+        assert_eq!(digests.flush_due(at(35, 0)).await.unwrap(), 1);
+
+        let rows = inbox(&pool, author).await;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].1, "Борис Test оценил(а) ваш пост");
+        assert_eq!(rows[1].2["count"], 1);
+    }
+}
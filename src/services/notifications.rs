@@ -0,0 +1,56 @@
+use uuid::Uuid;
+use sqlx::types::Json;
+
+use crate::{db::{DbPool, UserScope}, utils::errors::AppError};
+
+/// Сохраненные уведомления: то же, что уходит по WebSocket, но доступно и тем, кто был офлайн
+pub struct NotificationService {
+    pool: DbPool,
+}
+
+impl NotificationService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        kind: &str,
+        title: &str,
+        message: &str,
+        data: serde_json::Value,
+    ) -> Result<Uuid, AppError> {
+        let id = UserScope::new(user_id).query_scalar::<Uuid>(
+            r#"
+            INSERT INTO notifications (user_id, id, kind, title, message, data)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(kind)
+        .bind(title)
+        .bind(message)
+        .bind(Json(data))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Непрочитанные уведомления; открытая сводка (notification_digests) считается одним
+    /// уведомлением сразу, а не после переноса в inbox
+    pub async fn unread_count(&self, user_id: Uuid) -> Result<i64, AppError> {
+        let count = UserScope::new(user_id).query_scalar::<i64>(
+            r#"
+            SELECT (SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL)
+                 + (SELECT COUNT(*) FROM notification_digests WHERE user_id = $1 AND flushed_at IS NULL)
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+}
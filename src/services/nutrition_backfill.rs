@@ -0,0 +1,706 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use sqlx::FromRow;
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        admin_job::AdminJobStatus,
+        diary::{FoodItem, NutritionBackfillJob, NutritionSource},
+    },
+    services::{
+        admin_jobs::foods_by_name,
+        ai::AiService,
+        ai_generation::AiFeature,
+        notifications::NotificationService,
+        realtime::{RealtimeService, WebSocketEvent},
+    },
+    telemetry,
+    utils::{
+        errors::AppError,
+        sanitize::{prompt_safe, user_data_block, PROMPT_DATA_NOTICE},
+    },
+};
+
+/// Сколько записей обрабатывается за одну транзакцию
+const BATCH_SIZE: i64 = 25;
+
+/// Обращений к ИИ за пачку; после пачки с обращениями к ИИ — пауза, чтобы задача
+/// не выбирала лимиты провайдера, общие с чатом и генерацией рецептов
+const AI_ESTIMATES_PER_BATCH: usize = 10;
+const AI_BATCH_PAUSE: Duration = Duration::from_secs(3);
+
+const MAX_FOOD_NAME_PROMPT_CHARS: usize = 100;
+
+/// Пищевая ценность на 100 г (натрий — в мг)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct NutritionPer100g {
+    pub calories: f32,
+    pub protein: f32,
+    pub fat: f32,
+    pub carbs: f32,
+    #[serde(default)]
+    pub fiber: Option<f32>,
+    #[serde(default)]
+    pub sugar: Option<f32>,
+    #[serde(default)]
+    pub sodium: Option<f32>,
+}
+
+impl From<&FoodItem> for NutritionPer100g {
+    fn from(food: &FoodItem) -> Self {
+        Self {
+            calories: food.calories_per_100g,
+            protein: food.protein_per_100g,
+            fat: food.fat_per_100g,
+            carbs: food.carbs_per_100g,
+            fiber: food.fiber_per_100g,
+            sugar: food.sugar_per_100g,
+            sodium: food.sodium_per_100g,
+        }
+    }
+}
+
+/// Значения для записи: заполняются только пустые поля (нули в БЖУ, отсутствующие
+/// клетчатка, сахар и натрий). То, что пользователь ввел сам, не перезаписывается.
+pub fn fill_missing(current: &NutritionPer100g, found: &NutritionPer100g) -> NutritionPer100g {
+    let pick = |current: f32, found: f32| if current > 0.0 { current } else { found };
+
+    NutritionPer100g {
+        calories: pick(current.calories, found.calories),
+        protein: pick(current.protein, found.protein),
+        fat: pick(current.fat, found.fat),
+        carbs: pick(current.carbs, found.carbs),
+        fiber: current.fiber.or(found.fiber),
+        sugar: current.sugar.or(found.sugar),
+        sodium: current.sodium.or(found.sodium),
+    }
+}
+
+/// Оценка ИИ из ответа вида `{"calories": ..., "protein": ..., ...}`. Ответ `null`,
+/// ответ без JSON и физически невозможные значения дают `None`.
+pub fn parse_ai_estimate(text: &str) -> Option<NutritionPer100g> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let estimate: NutritionPer100g = serde_json::from_str(text.get(start..=end)?).ok()?;
+
+    let in_range = |value: f32, max: f32| value.is_finite() && (0.0..=max).contains(&value);
+    let plausible = in_range(estimate.calories, 900.0)
+        && in_range(estimate.protein, 100.0)
+        && in_range(estimate.fat, 100.0)
+        && in_range(estimate.carbs, 100.0)
+        && estimate.protein + estimate.fat + estimate.carbs <= 105.0;
+    if !plausible {
+        return None;
+    }
+
+    Some(NutritionPer100g {
+        fiber: estimate.fiber.filter(|value| in_range(*value, 100.0)),
+        sugar: estimate.sugar.filter(|value| in_range(*value, 100.0)),
+        sodium: estimate.sodium.filter(|value| in_range(*value, 40_000.0)),
+        ..estimate
+    })
+}
+
+/// Запись дневника без калорий
+#[derive(Debug, FromRow)]
+struct MissingEntry {
+    id: Uuid,
+    food_name: String,
+    calories_per_100g: f32,
+    protein_per_100g: f32,
+    fat_per_100g: f32,
+    carbs_per_100g: f32,
+    fiber_per_100g: Option<f32>,
+    sugar_per_100g: Option<f32>,
+    sodium_per_100g: Option<f32>,
+}
+
+impl MissingEntry {
+    fn current(&self) -> NutritionPer100g {
+        NutritionPer100g {
+            calories: self.calories_per_100g,
+            protein: self.protein_per_100g,
+            fat: self.fat_per_100g,
+            carbs: self.carbs_per_100g,
+            fiber: self.fiber_per_100g,
+            sugar: self.sugar_per_100g,
+            sodium: self.sodium_per_100g,
+        }
+    }
+}
+
+/// Оценки ИИ в пределах одного запуска задачи: одинаковые продукты не спрашиваются дважды
+struct AiEstimates {
+    enabled: bool,
+    by_name: HashMap<String, Option<NutritionPer100g>>,
+}
+
+enum BatchOutcome {
+    Processed { last_id: Uuid, used_ai: bool },
+    Finished,
+    Cancelled,
+}
+
+/// Заполнение питательности старых записей дневника: сначала база продуктов, затем
+/// (если попросили) оценка ИИ. Заполненные записи получают источник `database` или
+/// `ai_estimate`; записи, для которых ничего не нашлось, остаются как есть.
+#[derive(Clone)]
+pub struct NutritionBackfillService {
+    pool: DbPool,
+    ai_service: AiService,
+    realtime_service: Arc<RealtimeService>,
+}
+
+impl NutritionBackfillService {
+    pub fn new(pool: DbPool, ai_service: AiService, realtime_service: Arc<RealtimeService>) -> Self {
+        Self { pool, ai_service, realtime_service }
+    }
+
+    /// Создает задачу и запускает ее в фоне. У пользователя одна незавершенная задача.
+    pub async fn enqueue(&self, user_id: Uuid, use_ai: bool) -> Result<NutritionBackfillJob, AppError> {
+        let (total,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM diary_entries WHERE user_id = $1 AND calories_per_100g = 0 AND nutrition_source = 'user'"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let job = sqlx::query_as::<_, NutritionBackfillJob>(
+            r#"
+            INSERT INTO nutrition_backfill_jobs (user_id, use_ai, total)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) WHERE status IN ('pending', 'running') DO NOTHING
+            RETURNING *
+            "#
+        )
+        .bind(user_id)
+        .bind(use_ai)
+        .bind(total as i32)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Nutrition backfill is already in progress".to_string()))?;
+
+        self.clone().spawn(job.id);
+        Ok(job)
+    }
+
+    pub async fn get(&self, user_id: Uuid, job_id: Uuid) -> Result<NutritionBackfillJob, AppError> {
+        sqlx::query_as::<_, NutritionBackfillJob>("SELECT * FROM nutrition_backfill_jobs WHERE id = $1 AND user_id = $2")
+            .bind(job_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Job not found".to_string()))
+    }
+
+    /// Останавливает задачу. Уже заполненные записи остаются заполненными:
+    /// пачка, которая выполняется в момент отмены, откатывается целиком.
+    pub async fn cancel(&self, user_id: Uuid, job_id: Uuid) -> Result<NutritionBackfillJob, AppError> {
+        let job = sqlx::query_as::<_, NutritionBackfillJob>(
+            r#"
+            UPDATE nutrition_backfill_jobs
+            SET status = $3, finished_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND status IN ('pending', 'running')
+            RETURNING *
+            "#
+        )
+        .bind(job_id)
+        .bind(user_id)
+        .bind(AdminJobStatus::Cancelled)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match job {
+            Some(job) => Ok(job),
+            None => {
+                self.get(user_id, job_id).await?;
+                Err(AppError::BadRequest("Job has already finished".to_string()))
+            }
+        }
+    }
+
+    /// Продолжает задачи, прерванные перезапуском сервера
+    pub async fn resume_unfinished(&self) -> Result<usize, AppError> {
+        let ids: Vec<(Uuid,)> = sqlx::query_as(
+            "SELECT id FROM nutrition_backfill_jobs WHERE status IN ('pending', 'running') ORDER BY created_at"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for (id,) in &ids {
+            info!("Resuming nutrition backfill job {}", id);
+            self.clone().spawn(*id);
+        }
+
+        Ok(ids.len())
+    }
+
+    fn spawn(self, job_id: Uuid) {
+        let span = telemetry::detached_job_span("nutrition_backfill");
+        tokio::spawn(async move {
+            if let Err(e) = self.run(job_id).await {
+                warn!("Nutrition backfill job {} failed: {:?}", job_id, e);
+                let _ = sqlx::query(
+                    r#"
+                    UPDATE nutrition_backfill_jobs
+                    SET status = 'failed', error = $2, finished_at = NOW(), updated_at = NOW()
+                    WHERE id = $1 AND status = 'running'
+                    "#
+                )
+                .bind(job_id)
+                .bind(e.to_string())
+                .execute(&self.pool)
+                .await;
+            }
+        }.instrument(span));
+    }
+
+    async fn run(&self, job_id: Uuid) -> Result<(), AppError> {
+        let job = sqlx::query_as::<_, NutritionBackfillJob>(
+            r#"
+            UPDATE nutrition_backfill_jobs
+            SET status = 'running', started_at = COALESCE(started_at, NOW()), updated_at = NOW()
+            WHERE id = $1 AND status IN ('pending', 'running')
+            RETURNING *
+            "#
+        )
+        .bind(job_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        // Задачу отменили до запуска
+        let Some(job) = job else {
+            return Ok(());
+        };
+
+        // Mock-провайдер оценок не дает
+        let mut estimates = AiEstimates {
+            enabled: job.use_ai && !self.ai_service.is_mock(),
+            by_name: HashMap::new(),
+        };
+        let mut cursor = job.last_processed_id;
+        loop {
+            match self.process_batch(&job, cursor, &mut estimates).await? {
+                BatchOutcome::Processed { last_id, used_ai } => {
+                    cursor = Some(last_id);
+                    if used_ai {
+                        tokio::time::sleep(AI_BATCH_PAUSE).await;
+                    }
+                }
+                BatchOutcome::Finished => break,
+                BatchOutcome::Cancelled => return Ok(()),
+            }
+        }
+
+        let job = sqlx::query_as::<_, NutritionBackfillJob>(
+            r#"
+            UPDATE nutrition_backfill_jobs
+            SET status = $2, finished_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND status = 'running'
+            RETURNING *
+            "#
+        )
+        .bind(job_id)
+        .bind(AdminJobStatus::Completed)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(job) = job else {
+            return Ok(());
+        };
+
+        info!(
+            "Nutrition backfill job {} completed: {} from database, {} by AI, {} unresolved",
+            job.id, job.filled_from_database, job.filled_by_ai, job.unresolved
+        );
+        self.notify_completed(&job).await;
+        Ok(())
+    }
+
+    /// Обрабатывает записи после `cursor`. Пачка заканчивается раньше, если исчерпан
+    /// лимит обращений к ИИ: оставшиеся записи попадут в следующую пачку.
+    async fn process_batch(
+        &self,
+        job: &NutritionBackfillJob,
+        cursor: Option<Uuid>,
+        estimates: &mut AiEstimates,
+    ) -> Result<BatchOutcome, AppError> {
+        let entries = sqlx::query_as::<_, MissingEntry>(
+            r#"
+            SELECT id, food_name, calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g,
+                   fiber_per_100g, sugar_per_100g, sodium_per_100g
+            FROM diary_entries
+            WHERE user_id = $1 AND calories_per_100g = 0 AND nutrition_source = 'user'
+              AND ($2::uuid IS NULL OR id > $2)
+            ORDER BY id
+            LIMIT $3
+            "#
+        )
+        .bind(job.user_id)
+        .bind(cursor)
+        .bind(BATCH_SIZE)
+        .fetch_all(&self.pool)
+        .await?;
+        if entries.is_empty() {
+            return Ok(BatchOutcome::Finished);
+        }
+
+        let names: Vec<String> = entries.iter().map(|entry| entry.food_name.trim().to_lowercase()).collect();
+        let foods = foods_by_name(&self.pool, &names).await?;
+
+        let mut ai_calls = 0;
+        let mut resolved = Vec::with_capacity(entries.len());
+        for (entry, name) in entries.iter().zip(&names) {
+            if let Some(food) = foods.get(name) {
+                resolved.push((entry, Some((NutritionSource::Database, NutritionPer100g::from(food)))));
+                continue;
+            }
+            if !estimates.enabled {
+                resolved.push((entry, None));
+                continue;
+            }
+
+            let estimate = match estimates.by_name.get(name) {
+                Some(estimate) => *estimate,
+                None if ai_calls >= AI_ESTIMATES_PER_BATCH => break,
+                None => {
+                    ai_calls += 1;
+                    match self.estimate(job.user_id, &entry.food_name).await {
+                        Ok(estimate) => {
+                            estimates.by_name.insert(name.clone(), estimate);
+                            estimate
+                        }
+                        // Провайдер недоступен: оставшиеся записи заполняются только из базы
+                        Err(e) if e.is_external_failure() => {
+                            warn!("AI estimates disabled for nutrition backfill job {}: {}", job.id, e);
+                            estimates.enabled = false;
+                            None
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            };
+            resolved.push((entry, estimate.map(|estimate| (NutritionSource::AiEstimate, estimate))));
+        }
+
+        let Some(last_id) = resolved.last().map(|(entry, _)| entry.id) else {
+            return Ok(BatchOutcome::Finished);
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let (mut from_database, mut by_ai) = (0, 0);
+
+        for (entry, found) in &resolved {
+            let Some((source, found)) = found else {
+                continue;
+            };
+            let values = fill_missing(&entry.current(), found);
+
+            // Пользователь мог изменить запись, пока задача работала
+            let updated = sqlx::query(
+                r#"
+                UPDATE diary_entries
+                SET calories_per_100g = $2, protein_per_100g = $3, fat_per_100g = $4, carbs_per_100g = $5,
+                    fiber_per_100g = $6, sugar_per_100g = $7, sodium_per_100g = $8,
+                    nutrition_source = $9, updated_at = NOW()
+                WHERE id = $1 AND calories_per_100g = 0 AND nutrition_source = 'user'
+                "#
+            )
+            .bind(entry.id)
+            .bind(values.calories)
+            .bind(values.protein)
+            .bind(values.fat)
+            .bind(values.carbs)
+            .bind(values.fiber)
+            .bind(values.sugar)
+            .bind(values.sodium)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            if updated > 0 {
+                match source {
+                    NutritionSource::AiEstimate => by_ai += 1,
+                    _ => from_database += 1,
+                }
+            }
+        }
+
+        let processed = resolved.len() as i32;
+        let job_updated = sqlx::query(
+            r#"
+            UPDATE nutrition_backfill_jobs
+            SET processed = processed + $2,
+                filled_from_database = filled_from_database + $3,
+                filled_by_ai = filled_by_ai + $4,
+                unresolved = unresolved + $5,
+                last_processed_id = $6,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'running'
+            "#
+        )
+        .bind(job.id)
+        .bind(processed)
+        .bind(from_database)
+        .bind(by_ai)
+        .bind(processed - from_database - by_ai)
+        .bind(last_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Задачу отменили во время пачки: транзакция откатывается вместе с записями
+        if job_updated == 0 {
+            return Ok(BatchOutcome::Cancelled);
+        }
+        tx.commit().await?;
+
+        Ok(BatchOutcome::Processed { last_id, used_ai: ai_calls > 0 })
+    }
+
+    /// Оценка ИИ — фоновый вызов: при исчерпанном бюджете ИИ записи заполняются только из базы
+    async fn estimate(&self, user_id: Uuid, food_name: &str) -> Result<Option<NutritionPer100g>, AppError> {
+        let mut prompt = String::from(PROMPT_DATA_NOTICE);
+        prompt.push_str("Оцени пищевую ценность продукта или блюда на 100 г.\n");
+        prompt.push_str(&user_data_block("food_name", &prompt_safe(food_name, MAX_FOOD_NAME_PROMPT_CHARS)));
+        prompt.push_str(
+            "Ответь только JSON без пояснений: {\"calories\": ккал, \"protein\": г, \"fat\": г, \"carbs\": г, \
+             \"fiber\": г, \"sugar\": г, \"sodium\": мг}. Если продукт не распознан, ответь null.",
+        );
+
+        let completion = self
+            .ai_service
+            .for_user(user_id)
+            .background()
+            .complete_with(&prompt, AiFeature::NutritionAnalysis, None)
+            .await?;
+        Ok(parse_ai_estimate(&completion.text))
+    }
+
+    /// Уведомление в inbox и по WebSocket; ошибка доставки на результат задачи не влияет
+    async fn notify_completed(&self, job: &NutritionBackfillJob) {
+        let filled = job.filled_from_database + job.filled_by_ai;
+        let message = format!(
+            "Заполнено записей: {} (из базы продуктов — {}, оценка ИИ — {}). Не удалось заполнить: {}.",
+            filled, job.filled_from_database, job.filled_by_ai, job.unresolved
+        );
+        let data = serde_json::json!({
+            "job_id": job.id,
+            "filled_from_database": job.filled_from_database,
+            "filled_by_ai": job.filled_by_ai,
+            "unresolved": job.unresolved,
+        });
+
+        if let Err(e) = NotificationService::new(self.pool.clone())
+            .create(job.user_id, "nutrition_backfill_completed", "Питательность дневника заполнена", &message, data)
+            .await
+        {
+            warn!("Failed to store nutrition backfill notification: {:?}", e);
+        }
+
+        let event = WebSocketEvent::NutritionBackfillCompleted {
+            job_id: job.id,
+            filled_from_database: job.filled_from_database,
+            filled_by_ai: job.filled_by_ai,
+            unresolved: job.unresolved,
+        };
+        if let Err(e) = self.realtime_service.send_to_user(job.user_id, event).await {
+            warn!("Failed to send nutrition backfill event: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        config::AiResilienceConfig,
+        services::{ai_providers::AiProvider, realtime::WebSocketManager},
+        test_support::{insert_diary_entry, insert_user, StubAdapter},
+    };
+
+    const PIE_ESTIMATE: &str = r#"Оценка: {"calories": 320, "protein": 6, "fat": 14, "carbs": 42, "sugar": 18}"#;
+
+    fn service(pool: &PgPool, adapter: Option<Arc<StubAdapter>>) -> NutritionBackfillService {
+        let ai_service = match adapter {
+            Some(adapter) => AiService::new(AiProvider::Mock, &AiResilienceConfig::default()).with_adapter(adapter),
+            None => AiService::new(AiProvider::Mock, &AiResilienceConfig::default()),
+        };
+        NutritionBackfillService::new(pool.clone(), ai_service, Arc::new(RealtimeService::new(Arc::new(WebSocketManager::new()))))
+    }
+
+    async fn entry(pool: &PgPool, user_id: Uuid, name: &str, calories: f32, protein: f32) -> Uuid {
+        let id = insert_diary_entry(pool, user_id, "lunch", calories, protein, Utc::now()).await;
+        sqlx::query("UPDATE diary_entries SET food_name = $2 WHERE id = $1").bind(id).bind(name).execute(pool).await.unwrap();
+        id
+    }
+
+    async fn wait_finished(service: &NutritionBackfillService, user_id: Uuid, job_id: Uuid) -> NutritionBackfillJob {
+        for _ in 0..150 {
+            let job = service.get(user_id, job_id).await.unwrap();
+            if !matches!(job.status, AdminJobStatus::Pending | AdminJobStatus::Running) {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("backfill job {} did not finish", job_id);
+    }
+
+    /// (калории, белок, источник) записей с названием
+    async fn entries_named(pool: &PgPool, user_id: Uuid, name: &str) -> Vec<(f32, f32, NutritionSource)> {
+        sqlx::query_as(
+            "SELECT calories_per_100g, protein_per_100g, nutrition_source FROM diary_entries WHERE user_id = $1 AND food_name = $2"
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn user_values_are_never_overwritten() {
+        let found = NutritionPer100g { calories: 340.0, protein: 12.6, fat: 3.3, carbs: 62.0, fiber: Some(10.0), sugar: None, sodium: Some(3.0) };
+        let current = NutritionPer100g { calories: 0.0, protein: 7.0, fat: 0.0, carbs: 0.0, fiber: Some(2.0), sugar: None, sodium: None };
+
+        let filled = fill_missing(&current, &found);
+        assert_eq!(
+            filled,
+            NutritionPer100g { calories: 340.0, protein: 7.0, fat: 3.3, carbs: 62.0, fiber: Some(2.0), sugar: None, sodium: Some(3.0) }
+        );
+    }
+
+    #[test]
+    fn implausible_ai_estimates_are_dropped() {
+        let cases = vec![
+            (PIE_ESTIMATE, Some(320.0)),
+            ("null", None),
+            ("Не знаю такого блюда", None),
+            (r#"{"calories": 1200, "protein": 10, "fat": 10, "carbs": 10}"#, None),
+            (r#"{"calories": 400, "protein": 60, "fat": 40, "carbs": 30}"#, None),
+            (r#"{"calories": -5, "protein": 1, "fat": 1, "carbs": 1}"#, None),
+            (r#"{"calories": 100, "protein": 1}"#, None),
+        ];
+        for (reply, calories) in cases {
+            assert_eq!(parse_ai_estimate(reply).map(|estimate| estimate.calories), calories, "{}", reply);
+        }
+
+        let sodium = parse_ai_estimate(r#"{"calories": 50, "protein": 1, "fat": 1, "carbs": 1, "sodium": 90000, "fiber": 2}"#).unwrap();
+        assert_eq!((sodium.sodium, sodium.fiber), (None, Some(2.0)));
+    }
+
+    /// 50 записей: 20 без калорий с продуктом из базы (у 5 белок введен вручную),
+    /// 20 без калорий с продуктом, которого в базе нет, и 10 с калориями от пользователя
+    async fn fixture(pool: &PgPool, user_id: Uuid) {
+        sqlx::query(
+            "INSERT INTO food_items (name, calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g, fiber_per_100g, verified) \
+             VALUES ('гречка', 343, 13.3, 3.4, 62, 10, TRUE)"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        for i in 0..20 {
+            entry(pool, user_id, "гречка", 0.0, if i < 5 { 7.0 } else { 0.0 }).await;
+            entry(pool, user_id, "пирог бабушкин", 0.0, 0.0).await;
+        }
+        for _ in 0..10 {
+            entry(pool, user_id, "гречка", 250.0, 9.0).await;
+        }
+    }
+
+    #[sqlx::test]
+    async fn database_first_then_ai_and_user_values_stay(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        fixture(&pool, user_id).await;
+        let adapter = Arc::new(StubAdapter::new("stub", vec![Some(PIE_ESTIMATE)]));
+
+        // Без ИИ: заполняется только то, что нашлось в базе продуктов
+        let without_ai = service(&pool, Some(adapter.clone()));
+        let job = without_ai.enqueue(user_id, false).await.unwrap();
+        assert_eq!(job.total, 40);
+        let job = wait_finished(&without_ai, user_id, job.id).await;
+        assert_eq!(job.status, AdminJobStatus::Completed);
+        assert_eq!((job.processed, job.filled_from_database, job.filled_by_ai, job.unresolved), (40, 20, 0, 20));
+        assert_eq!(adapter.calls(), 0);
+
+        let buckwheat = entries_named(&pool, user_id, "гречка").await;
+        let count = |predicate: &dyn Fn(&(f32, f32, NutritionSource)) -> bool| buckwheat.iter().filter(|row| predicate(row)).count();
+        assert_eq!(count(&|row| *row == (343.0, 13.3, NutritionSource::Database)), 15);
+        assert_eq!(count(&|row| *row == (343.0, 7.0, NutritionSource::Database)), 5, "hand-entered protein is kept");
+        assert_eq!(count(&|row| *row == (250.0, 9.0, NutritionSource::User)), 10, "entries with calories are not touched");
+        assert!(entries_named(&pool, user_id, "пирог бабушкин").await.iter().all(|row| *row == (0.0, 0.0, NutritionSource::User)));
+
+        // С ИИ: остальные записи получают оценку; одинаковое блюдо спрашивается один раз
+        let job = without_ai.enqueue(user_id, true).await.unwrap();
+        assert_eq!(job.total, 20);
+        let job = wait_finished(&without_ai, user_id, job.id).await;
+        assert_eq!((job.processed, job.filled_from_database, job.filled_by_ai, job.unresolved), (20, 0, 20, 0));
+        assert_eq!(adapter.calls(), 1);
+        assert!(adapter.prompts.lock().unwrap()[0].contains("пирог бабушкин"));
+        assert!(entries_named(&pool, user_id, "пирог бабушкин").await.iter().all(|row| *row == (320.0, 6.0, NutritionSource::AiEstimate)));
+
+        let (notifications,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND kind = 'nutrition_backfill_completed'"
+        )
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(notifications, 2);
+
+        // Mock-провайдер оценок не дает: записи остаются незаполненными
+        let other = insert_user(&pool, "Олег").await;
+        entry(&pool, other, "пирог бабушкин", 0.0, 0.0).await;
+        let mock = service(&pool, None);
+        let job = mock.enqueue(other, true).await.unwrap();
+        let job = wait_finished(&mock, other, job.id).await;
+        assert_eq!((job.filled_by_ai, job.unresolved), (0, 1));
+    }
+
+    #[sqlx::test]
+    async fn cancelling_rolls_back_the_running_batch(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        fixture(&pool, user_id).await;
+        let adapter = Arc::new(StubAdapter::new("stub", vec![Some(PIE_ESTIMATE)]).with_delay(Duration::from_millis(500)));
+        let backfill = service(&pool, Some(adapter.clone()));
+
+        let job = backfill.enqueue(user_id, true).await.unwrap();
+        let again = backfill.enqueue(user_id, false).await;
+        assert!(matches!(again, Err(AppError::BadRequest(_))), "one unfinished job per user");
+
+        // Пачка ждет ответа ИИ; отмена в этот момент откатывает и записи из базы продуктов
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let cancelled = backfill.cancel(user_id, job.id).await.unwrap();
+        assert_eq!(cancelled.status, AdminJobStatus::Cancelled);
+        tokio::time::sleep(Duration::from_millis(700)).await;
+
+        let job = backfill.get(user_id, job.id).await.unwrap();
+        assert_eq!((job.status, job.processed, job.last_processed_id), (AdminJobStatus::Cancelled, 0, None));
+        assert_eq!(adapter.calls(), 1);
+        assert!(entries_named(&pool, user_id, "гречка").await.iter().all(|row| row.2 == NutritionSource::User));
+
+        assert!(matches!(backfill.cancel(user_id, job.id).await, Err(AppError::BadRequest(_))));
+        let stranger = insert_user(&pool, "Олег").await;
+        assert!(matches!(backfill.cancel(stranger, job.id).await, Err(AppError::NotFound(_))));
+
+        // Задача, отмененная до запуска, ничего не делает
+        let (pending,): (Uuid,) = sqlx::query_as("INSERT INTO nutrition_backfill_jobs (user_id, total) VALUES ($1, 40) RETURNING id")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        backfill.cancel(user_id, pending).await.unwrap();
+        backfill.run(pending).await.unwrap();
+        assert_eq!(backfill.get(user_id, pending).await.unwrap().processed, 0);
+        assert!(entries_named(&pool, user_id, "гречка").await.iter().all(|row| row.2 == NutritionSource::User));
+    }
+}
@@ -1,15 +1,54 @@
 use crate::models::health::*;
 use crate::models::user::User;
 use crate::models::diary::DiaryEntry;
-use crate::services::ai::AiService;
+use crate::models::ai_response::AiProvenance;
+use crate::services::ai::{AiService, AiResponseMeta};
+use crate::services::ai_history::health_provenance;
+use crate::services::clock::{self, SharedClock};
+use crate::services::mock_ai::MockEndpoint;
 use crate::utils::errors::AppError;
+use crate::utils::sanitize::{prompt_safe, user_data_block, PROMPT_DATA_NOTICE, MAX_PROMPT_FIELD_CHARS};
+use crate::utils::{format::Locale, i18n};
 use chrono::{DateTime, Utc, Local, Timelike};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Имя шаблона промпта для провенанса ответов
+pub const PERSONAL_HEALTH_TEMPLATE: &str = "personal_health.caring_assistant";
+/// Шаблон клинического тона (вариант `clinical` эксперимента `health_tone`)
+pub const PERSONAL_HEALTH_CLINICAL_TEMPLATE: &str = "personal_health.clinical_assistant";
+
+/// Тон системного промпта; по умолчанию — заботливый
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HealthTone {
+    #[default]
+    Caring,
+    Clinical,
+}
+
+impl HealthTone {
+    /// По варианту эксперимента `health_tone`; неизвестный вариант — заботливый тон
+    pub fn from_variant(variant: &str) -> Self {
+        match variant {
+            "clinical" => HealthTone::Clinical,
+            _ => HealthTone::Caring,
+        }
+    }
+
+    pub fn template(&self) -> &'static str {
+        match self {
+            HealthTone::Caring => PERSONAL_HEALTH_TEMPLATE,
+            HealthTone::Clinical => PERSONAL_HEALTH_CLINICAL_TEMPLATE,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PersonalHealthAssistant {
     ai_service: AiService,
+    tone: HealthTone,
+    locale: Locale,
+    clock: SharedClock,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,11 +92,40 @@ pub struct PersonalizedResponse {
     pub mood_check: Option<String>,
     pub encouragement: Option<String>,
     pub next_suggestions: Vec<String>,
+    #[serde(flatten)]
+    pub meta: AiResponseMeta,
+    /// Запись в истории ответов ИИ (GET /ai/responses/{id}/provenance)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<Uuid>,
+    /// Какие данные попали в промпт; сохраняется в историю, клиенту не отдается
+    #[serde(skip)]
+    pub provenance: Option<AiProvenance>,
 }
 
 impl PersonalHealthAssistant {
     pub fn new(ai_service: AiService) -> Self {
-        Self { ai_service }
+        Self { ai_service, tone: HealthTone::default(), locale: Locale::default(), clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_tone(mut self, tone: HealthTone) -> Self {
+        self.tone = tone;
+        self
+    }
+
+    /// Язык готовых фраз ассистента (ответы модели — на языке промпта)
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Метаданные для ответов, собранных без вызова модели
+    pub fn response_meta(&self) -> AiResponseMeta {
+        self.ai_service.response_meta()
     }
 
     /// Главная функция - персонализированный ответ на основе контекста здоровья
@@ -66,10 +134,23 @@ impl PersonalHealthAssistant {
         user_message: &str,
         health_context: &HealthContext,
     ) -> Result<PersonalizedResponse, AppError> {
-        let system_prompt = self.build_caring_system_prompt(health_context);
-        let full_prompt = format!("{}\n\nСообщение пользователя: {}", system_prompt, user_message);
+        if let Some(mut response) = self.ai_service.mock_fixture::<PersonalizedResponse>(MockEndpoint::PersonalHealth)? {
+            response.provenance = Some(self.provenance(health_context, &response.meta));
+            return Ok(response);
+        }
+
+        let system_prompt = match self.tone {
+            HealthTone::Caring => self.build_caring_system_prompt(health_context),
+            HealthTone::Clinical => self.build_clinical_system_prompt(health_context),
+        };
+        let full_prompt = format!(
+            "{}\n\n{}Сообщение пользователя:\n{}",
+            system_prompt,
+            PROMPT_DATA_NOTICE,
+            user_data_block("message", user_message)
+        );
 
-        let ai_response = self.ai_service.generate_response(&full_prompt).await?;
+        let completion = self.ai_service.complete(&full_prompt).await?;
         
         // Анализируем ответ и генерируем дополнительные инсайты
         let insights = self.generate_health_insights(health_context, user_message).await?;
@@ -79,25 +160,69 @@ impl PersonalHealthAssistant {
         let next_suggestions = self.generate_contextual_suggestions(user_message, health_context);
 
         Ok(PersonalizedResponse {
-            response: ai_response,
+            response: completion.text,
             insights,
             recommendations,
             mood_check,
             encouragement,
             next_suggestions,
+            provenance: Some(self.provenance(health_context, &completion.meta)),
+            meta: completion.meta,
+            response_id: None,
         })
     }
 
+    fn provenance(&self, health_context: &HealthContext, meta: &AiResponseMeta) -> AiProvenance {
+        health_provenance(health_context, self.tone.template(), meta, self.ai_service.model_name())
+    }
+
     /// Создает заботливый системный промпт на основе данных пользователя
     fn build_caring_system_prompt(&self, context: &HealthContext) -> String {
-        let user = &context.user_profile;
-        let current_time = &context.current_time;
-        
         let mut prompt = format!(
             "Ты - заботливый персональный помощник по здоровью для {}. Время сейчас: {}. 
             Ты знаешь пользователя лично и искренне заботишься о его благополучии.",
-            user.name, current_time
+            prompt_safe(&context.user_profile.name, MAX_PROMPT_FIELD_CHARS), context.current_time
         );
+        prompt.push_str(&self.user_facts(context));
+
+        prompt.push_str("\n\nТвой стиль общения:
+        - Теплый, понимающий и поддерживающий
+        - Даешь практичные, персонализированные советы
+        - Учитываешь эмоциональное состояние
+        - Мотивируешь без давления
+        - Используешь данные пользователя для точных рекомендаций
+        - Проявляешь эмпатию и понимание
+        - Предлагаешь конкретные действия, а не общие советы
+        
+        Отвечай как заботливый друг, который хорошо знает пользователя и искренне хочет помочь.");
+
+        prompt
+    }
+
+    /// Тот же контекст в сдержанном клиническом тоне (эксперимент `health_tone`)
+    fn build_clinical_system_prompt(&self, context: &HealthContext) -> String {
+        let mut prompt = format!(
+            "Ты - консультант по здоровому образу жизни. Пользователь: {}. Время сейчас: {}.",
+            prompt_safe(&context.user_profile.name, MAX_PROMPT_FIELD_CHARS), context.current_time
+        );
+        prompt.push_str(&self.user_facts(context));
+
+        prompt.push_str("\n\nТвой стиль общения:
+        - Нейтральный и точный, без эмоциональных оценок
+        - Опираешься на данные пользователя и общепринятые рекомендации
+        - Называешь конкретные показатели и целевые значения
+        - Даешь короткий список действий по приоритету
+        - При тревожных симптомах рекомендуешь обратиться к врачу
+        
+        Отвечай кратко и по существу, как специалист на консультации.");
+
+        prompt
+    }
+
+    /// Факты о пользователе для системного промпта: возраст, цели, ограничения, самочувствие
+    fn user_facts(&self, context: &HealthContext) -> String {
+        let user = &context.user_profile;
+        let mut prompt = String::new();
 
         // Добавляем персональную информацию
         if let Some(age) = user.age {
@@ -107,15 +232,15 @@ impl PersonalHealthAssistant {
         prompt.push_str(&format!(" Уровень активности: {:?}.", user.fitness_level));
 
         if !user.health_goals.is_empty() {
-            prompt.push_str(&format!(" Цели пользователя: {}.", user.health_goals.join(", ")));
+            prompt.push_str(&format!(" Цели пользователя: {}.", prompt_safe(&user.health_goals.join(", "), MAX_PROMPT_FIELD_CHARS)));
         }
 
         if !user.medical_conditions.is_empty() {
-            prompt.push_str(&format!(" Важно учитывать: {}.", user.medical_conditions.join(", ")));
+            prompt.push_str(&format!(" Важно учитывать: {}.", prompt_safe(&user.medical_conditions.join(", "), MAX_PROMPT_FIELD_CHARS)));
         }
 
         if !user.dietary_restrictions.is_empty() {
-            prompt.push_str(&format!(" Диетические ограничения: {}.", user.dietary_restrictions.join(", ")));
+            prompt.push_str(&format!(" Диетические ограничения: {}.", prompt_safe(&user.dietary_restrictions.join(", "), MAX_PROMPT_FIELD_CHARS)));
         }
 
         // Добавляем контекст недавнего самочувствия
@@ -129,17 +254,6 @@ impl PersonalHealthAssistant {
             ));
         }
 
-        prompt.push_str("\n\nТвой стиль общения:
-        - Теплый, понимающий и поддерживающий
-        - Даешь практичные, персонализированные советы
-        - Учитываешь эмоциональное состояние
-        - Мотивируешь без давления
-        - Используешь данные пользователя для точных рекомендаций
-        - Проявляешь эмпатию и понимание
-        - Предлагаешь конкретные действия, а не общие советы
-        
-        Отвечай как заботливый друг, который хорошо знает пользователя и искренне хочет помочь.");
-
         prompt
     }
 
@@ -172,7 +286,7 @@ impl PersonalHealthAssistant {
                                 "Избегайте экранов за час до сна".to_string(),
                             ],
                             data_sources: vec!["sleep_tracking".to_string()],
-                            created_at: Utc::now(),
+                            created_at: self.clock.now(),
                             is_read: false,
                         });
                     }
@@ -200,7 +314,7 @@ impl PersonalHealthAssistant {
                             "Выпейте теплый травяной чай".to_string(),
                         ],
                         data_sources: vec!["mood_tracking".to_string()],
-                        created_at: Utc::now(),
+                        created_at: self.clock.now(),
                         is_read: false,
                     });
                 }
@@ -227,7 +341,7 @@ impl PersonalHealthAssistant {
                             "Добавьте лимон или мяту для вкуса".to_string(),
                         ],
                         data_sources: vec!["water_tracking".to_string()],
-                        created_at: Utc::now(),
+                        created_at: self.clock.now(),
                         is_read: false,
                     });
                 }
@@ -245,7 +359,7 @@ impl PersonalHealthAssistant {
         let mut recommendations = Vec::new();
 
         // Рекомендации на основе времени дня
-        let hour = Local::now().hour();
+        let hour = self.clock.now().with_timezone(&Local).hour();
         
         match hour {
             6..=9 => {
@@ -268,7 +382,7 @@ impl PersonalHealthAssistant {
                     frequency: "daily".to_string(),
                     difficulty: 2,
                     estimated_time_minutes: Some(15),
-                    created_at: Utc::now(),
+                    created_at: self.clock.now(),
                     is_active: true,
                 });
             },
@@ -291,7 +405,7 @@ impl PersonalHealthAssistant {
                     frequency: "daily".to_string(),
                     difficulty: 3,
                     estimated_time_minutes: Some(20),
-                    created_at: Utc::now(),
+                    created_at: self.clock.now(),
                     is_active: true,
                 });
             },
@@ -316,7 +430,7 @@ impl PersonalHealthAssistant {
                     frequency: "daily".to_string(),
                     difficulty: 2,
                     estimated_time_minutes: Some(30),
-                    created_at: Utc::now(),
+                    created_at: self.clock.now(),
                     is_active: true,
                 });
             },
@@ -330,28 +444,30 @@ impl PersonalHealthAssistant {
     fn generate_mood_check(&self, context: &HealthContext) -> Option<String> {
         if let Some(recent) = context.recent_wellbeing.first() {
             if let Some(mood) = recent.mood_score {
-                return match mood {
-                    1..=3 => Some("Я вижу, что у вас непростой период. Помните - это временно, и я здесь, чтобы поддержать вас. Что могло бы сейчас помочь вам почувствовать себя чуть лучше?".to_string()),
-                    4..=6 => Some("Как ваше настроение сегодня? Если хотите, расскажите, что на душе - иногда просто поговорить уже помогает.".to_string()),
-                    7..=8 => Some("Рада видеть, что у вас хорошее настроение! Что помогло вам так прекрасно себя чувствовать?".to_string()),
-                    9..=10 => Some("Вы просто сияете сегодня! Это заразительно - делитесь своей радостью с миром! 🌟".to_string()),
-                    _ => None,
+                let key = match mood {
+                    1..=3 => "health.mood.low",
+                    4..=6 => "health.mood.neutral",
+                    7..=8 => "health.mood.good",
+                    9..=10 => "health.mood.great",
+                    _ => return None,
                 };
+                return Some(i18n::t(self.locale, key).to_string());
             }
         }
-        Some("Как дела? Как настроение? Я всегда готова выслушать и помочь.".to_string())
+        Some(i18n::t(self.locale, "health.mood.unknown").to_string())
     }
 
     /// Генерирует ободряющее сообщение
     fn generate_encouragement(&self, _context: &HealthContext) -> Option<String> {
-        let encouragements = vec![
-            "Помните: каждый маленький шаг к здоровью важен. Вы делаете больше, чем думаете! 💪",
-            "Ваша забота о себе вдохновляет. Продолжайте в том же духе! ✨",
-            "Здоровье - это марафон, не спринт. Будьте терпеливы к себе. 🌱",
-            "Каждый день - новая возможность позаботиться о себе. Вы справляетесь отлично! 🌟",
+        const ENCOURAGEMENTS: [&str; 4] = [
+            "health.encouragement.small_steps",
+            "health.encouragement.self_care",
+            "health.encouragement.marathon",
+            "health.encouragement.new_day",
         ];
         
-        Some(encouragements[rand::random::<usize>() % encouragements.len()].to_string())
+        let key = ENCOURAGEMENTS[rand::random::<usize>() % ENCOURAGEMENTS.len()];
+        Some(i18n::t(self.locale, key).to_string())
     }
 
     /// Генерирует контекстные предложения для продолжения разговора
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, UserScope},
+    models::{
+        community::{CookedIt, CookedItEntry, CookedItInput, PostType, PostVisibility},
+        diary::{CreateDiaryEntry, DiaryEntry},
+        recipe::{IngredientQuantity, RecipeIngredient},
+    },
+    services::{
+        clock::{self, SharedClock},
+        diary::DiaryService,
+        media::MediaLibrary,
+        preferences::PreferencesService,
+    },
+    utils::{errors::AppError, units::BaseUnit},
+};
+
+/// Отметки «приготовил(а)» для ленты активности (колонки — см. `services::timeline`).
+/// Время — первая отметка, ссылка — на пост с рецептом.
+pub const TIMELINE_COOKED_SQL: &str = r#"
+    SELECT 'recipe_cooked'::text AS kind, c.created_at AS occurred_at, c.id AS event_id, c.post_id AS reference_id,
+           r.name AS title,
+           jsonb_build_object('rating', c.rating, 'recipe_id', p.recipe_id) AS summary
+    FROM post_cooked c
+    JOIN posts p ON p.id = c.post_id
+    LEFT JOIN recipes r ON r.id = p.recipe_id
+    WHERE c.user_id = $1
+"#;
+
+/// Результат отметки «приготовил(а)»
+#[derive(Debug)]
+pub struct CookedItOutcome {
+    pub cooked: CookedIt,
+    /// Отметка поставлена впервые (а не обновлена)
+    pub created: bool,
+    pub cooked_count: i64,
+    /// У рецепта поста есть питательность — блюдо можно записать в дневник
+    pub can_log_meal: bool,
+    pub diary_entry: Option<DiaryEntry>,
+}
+
+/// Галерея отметок поста
+#[derive(Debug)]
+pub struct CookedGallery {
+    pub cooked_count: i64,
+    pub average_rating: Option<f64>,
+    pub entries: Vec<CookedItEntry>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CookedPost {
+    author_id: Uuid,
+    post_type: PostType,
+    recipe_id: Option<Uuid>,
+}
+
+#[derive(sqlx::FromRow)]
+struct UpsertedCookedIt {
+    #[sqlx(flatten)]
+    cooked: CookedIt,
+    inserted: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct RecipeNutritionRow {
+    name: String,
+    servings: Option<i32>,
+    calories: Option<f32>,
+    protein: Option<f32>,
+    fat: Option<f32>,
+    carbs: Option<f32>,
+    fiber: Option<f32>,
+    sugar: Option<f32>,
+    sodium: Option<f32>,
+}
+
+/// Порция рецепта для записи в дневник: питательность на 100 г
+struct RecipeMeal {
+    food_name: String,
+    grams: f32,
+    calories: f32,
+    protein: f32,
+    fat: f32,
+    carbs: f32,
+    fiber: Option<f32>,
+    sugar: Option<f32>,
+    sodium: Option<f32>,
+}
+
+/// Отметки «приготовил(а)» на постах с рецептами
+pub struct CookedItService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl CookedItService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Ставит отметку или обновляет уже поставленную. С `log_meal` блюдо записывается
+    /// в дневник одной порцией, если у рецепта поста есть питательность.
+    pub async fn mark(&self, post_id: Uuid, user_id: Uuid, input: CookedItInput, log_meal: bool) -> Result<CookedItOutcome, AppError> {
+        let post = self.visible_post(post_id, user_id).await?;
+        if post.post_type != PostType::Recipe {
+            return Err(AppError::BadRequest("Only recipe posts can be marked as cooked".to_string()));
+        }
+        if post.author_id == user_id {
+            return Err(AppError::BadRequest("You can't mark your own post as cooked".to_string()));
+        }
+        if let Some(media_id) = input.media_id {
+            MediaLibrary::new(self.pool.clone()).owned_urls(user_id, &[media_id]).await?;
+        }
+
+        let now = self.clock.now();
+        let upserted: UpsertedCookedIt = UserScope::new(user_id)
+            .query_as(
+                r#"
+                INSERT INTO post_cooked (user_id, post_id, rating, media_id, note, is_public, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                ON CONFLICT (post_id, user_id) DO UPDATE
+                SET rating = EXCLUDED.rating, media_id = EXCLUDED.media_id, note = EXCLUDED.note,
+                    is_public = EXCLUDED.is_public, updated_at = EXCLUDED.updated_at
+                RETURNING id, post_id, user_id, rating, media_id, note, is_public, created_at, updated_at,
+                          (xmax = 0) AS inserted
+                "#
+            )
+            .bind(post_id)
+            .bind(input.rating)
+            .bind(input.media_id)
+            .bind(input.note)
+            .bind(input.is_public)
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let meal = match post.recipe_id {
+            Some(recipe_id) => self.recipe_meal(recipe_id).await?,
+            None => None,
+        };
+        let can_log_meal = meal.is_some();
+        let diary_entry = match meal.filter(|_| log_meal) {
+            Some(meal) => Some(self.log_meal(user_id, meal).await?),
+            None => None,
+        };
+
+        Ok(CookedItOutcome {
+            cooked: upserted.cooked,
+            created: upserted.inserted,
+            cooked_count: self.cooked_count(post_id).await?,
+            can_log_meal,
+            diary_entry,
+        })
+    }
+
+    /// Галерея поста: автор видит все отметки, остальные — открытые и свою
+    pub async fn gallery(&self, post_id: Uuid, viewer_id: Uuid, limit: i64, offset: i64) -> Result<CookedGallery, AppError> {
+        let post = self.visible_post(post_id, viewer_id).await?;
+
+        let entries: Vec<CookedItEntry> = sqlx::query_as(
+            r#"
+            SELECT c.id, c.user_id, u.first_name, u.avatar_url, c.rating, m.url AS photo_url,
+                   c.note, c.is_public, c.created_at, c.updated_at
+            FROM post_cooked c
+            JOIN users u ON u.id = c.user_id
+            LEFT JOIN media_files m ON m.id = c.media_id
+            WHERE c.post_id = $1 AND ($2 OR c.is_public OR c.user_id = $3)
+            ORDER BY c.updated_at DESC, c.id DESC
+            LIMIT $4 OFFSET $5
+            "#
+        )
+        .bind(post_id)
+        .bind(post.author_id == viewer_id)
+        .bind(viewer_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Счетчик и средняя оценка — по всем отметкам: они не раскрывают закрытые фото и заметки
+        let (cooked_count, average_rating): (i64, Option<f64>) =
+            sqlx::query_as("SELECT COUNT(*), AVG(rating)::float8 FROM post_cooked WHERE post_id = $1")
+                .bind(post_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(CookedGallery { cooked_count, average_rating, entries })
+    }
+
+    pub async fn cooked_count(&self, post_id: Uuid) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_cooked WHERE post_id = $1")
+            .bind(post_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// Счетчики для ленты одним запросом; постов без отметок в ответе нет
+    pub async fn cooked_counts(&self, post_ids: &[Uuid]) -> Result<HashMap<Uuid, i64>, AppError> {
+        let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+            "SELECT post_id, COUNT(*) FROM post_cooked WHERE post_id = ANY($1) GROUP BY post_id"
+        )
+        .bind(post_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Пост, который пользователь может видеть: публичный, свой или автора, на которого он подписан.
+    /// Невидимые и несуществующие посты неразличимы — NotFound.
+    async fn visible_post(&self, post_id: Uuid, user_id: Uuid) -> Result<CookedPost, AppError> {
+        sqlx::query_as(
+            r#"
+            SELECT p.author_id, p.post_type, p.recipe_id
+            FROM posts p
+            WHERE p.id = $1
+              AND (p.visibility = $3 OR p.author_id = $2
+                   OR EXISTS (SELECT 1 FROM follows f WHERE f.follower_id = $2 AND f.following_id = p.author_id))
+            "#
+        )
+        .bind(post_id)
+        .bind(user_id)
+        .bind(PostVisibility::Public)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Post not found".to_string()))
+    }
+
+    /// Порция рецепта с питательностью на 100 г. `None` — у рецепта нет калорийности
+    /// или вес порции нельзя посчитать по ингредиентам.
+    async fn recipe_meal(&self, recipe_id: Uuid) -> Result<Option<RecipeMeal>, AppError> {
+        let recipe: Option<RecipeNutritionRow> = sqlx::query_as(
+            r#"
+            SELECT r.name, r.servings, n.calories, n.protein, n.fat, n.carbs, n.fiber, n.sugar, n.sodium
+            FROM recipes r
+            JOIN recipe_nutrition n ON n.recipe_id = r.id
+            WHERE r.id = $1
+            "#
+        )
+        .bind(recipe_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(recipe) = recipe else { return Ok(None) };
+        let Some(calories) = recipe.calories else { return Ok(None) };
+
+        let ingredients: Vec<RecipeIngredient> = sqlx::query_as("SELECT * FROM recipe_ingredients WHERE recipe_id = $1")
+            .bind(recipe_id)
+            .fetch_all(&self.pool)
+            .await?;
+        let Some(grams) = serving_grams(&ingredients, recipe.servings) else { return Ok(None) };
+
+        // recipe_nutrition хранит значения на порцию
+        let per_100g = |value: f32| value / grams * 100.0;
+        Ok(Some(RecipeMeal {
+            food_name: recipe.name,
+            grams,
+            calories: per_100g(calories),
+            protein: recipe.protein.map_or(0.0, per_100g),
+            fat: recipe.fat.map_or(0.0, per_100g),
+            carbs: recipe.carbs.map_or(0.0, per_100g),
+            fiber: recipe.fiber.map(per_100g),
+            sugar: recipe.sugar.map(per_100g),
+            sodium: recipe.sodium.map(per_100g),
+        }))
+    }
+
+    async fn log_meal(&self, user_id: Uuid, meal: RecipeMeal) -> Result<DiaryEntry, AppError> {
+        let now = self.clock.now();
+        let preferences = PreferencesService::new(self.pool.clone()).for_user(user_id).await?;
+        let meal_type = preferences.meal_times.meal_type_at(now, preferences.general.utc_offset_minutes);
+
+        DiaryService::new(self.pool.clone())
+            .with_clock(self.clock.clone())
+            .create_entry(CreateDiaryEntry {
+                user_id,
+                food_name: meal.food_name,
+                brand: None,
+                portion_size: meal.grams,
+                unit: "г".to_string(),
+                calories_per_100g: meal.calories,
+                protein_per_100g: meal.protein,
+                fat_per_100g: meal.fat,
+                carbs_per_100g: meal.carbs,
+                fiber_per_100g: meal.fiber,
+                sugar_per_100g: meal.sugar,
+                sodium_per_100g: meal.sodium,
+                media_id: None,
+                meal_type: meal_type.to_string(),
+                consumed_at: now,
+            })
+            .await
+    }
+}
+
+/// Вес одной порции по ингредиентам (жидкости — 1 г/мл, как в расчете питательности рецепта).
+/// `None`, если хотя бы один ингредиент нельзя перевести в граммы.
+fn serving_grams(ingredients: &[RecipeIngredient], servings: Option<i32>) -> Option<f32> {
+    let mut total = 0.0;
+    for ingredient in ingredients {
+        let amount = ingredient.amount()?;
+        if amount == IngredientQuantity::ToTaste {
+            continue;
+        }
+        match amount.to_base()? {
+            (quantity, BaseUnit::Kg | BaseUnit::L) => total += quantity * 1000.0,
+            (_, BaseUnit::Piece) => return None,
+        }
+    }
+
+    let grams = total as f32 / servings.unwrap_or(1).max(1) as f32;
+    (grams > 0.0).then_some(grams)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::services::clock::{SandboxClock, SandboxClockMode};
+    use crate::test_support::{frozen_clock, insert_recipe, insert_user};
+
+    fn noon() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap()
+    }
+
+    /// Пост с рецептом на 2 порции из 300 г и 100 мл; с `nutrition` — 400 ккал и 20 г белка на порцию
+    async fn recipe_post(pool: &PgPool, author_id: Uuid, visibility: PostVisibility, nutrition: bool) -> Uuid {
+        let recipe_id = insert_recipe(pool, author_id, "Суп-пюре", true).await;
+        sqlx::query("UPDATE recipes SET servings = 2 WHERE id = $1").bind(recipe_id).execute(pool).await.unwrap();
+        for (name, quantity, unit) in [("Тыква", 300.0_f32, "г"), ("Сливки", 100.0, "мл"), ("Соль", 0.0, "по вкусу")] {
+            sqlx::query("INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit) VALUES ($1, $2, $3, $4)")
+                .bind(recipe_id)
+                .bind(name)
+                .bind(quantity)
+                .bind(unit)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        if nutrition {
+            sqlx::query("INSERT INTO recipe_nutrition (recipe_id, calories, protein) VALUES ($1, 400, 20)")
+                .bind(recipe_id)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+
+        sqlx::query_scalar("INSERT INTO posts (author_id, content, post_type, recipe_id, visibility) VALUES ($1, 'Суп', 'recipe', $2, $3) RETURNING id")
+            .bind(author_id)
+            .bind(recipe_id)
+            .bind(visibility)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn input(rating: i16, note: &str, is_public: bool) -> CookedItInput {
+        CookedItInput { rating: Some(rating), media_id: None, note: Some(note.to_string()), is_public }
+    }
+
+    fn service(pool: &PgPool) -> (CookedItService, std::sync::Arc<SandboxClock>) {
+        let clock = frozen_clock(noon());
+        (CookedItService::new(pool.clone()).with_clock(clock.clone()), clock)
+    }
+
+    #[sqlx::test]
+    async fn marking_again_updates_the_single_mark_of_the_user(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let cook = insert_user(&pool, "Борис").await;
+        let post_id = recipe_post(&pool, author, PostVisibility::Public, false).await;
+        let (service, clock) = service(&pool);
+
+        let first = service.mark(post_id, cook, input(3, "Пересолил", true), false).await.unwrap();
+        assert!(first.created);
+        assert_eq!(first.cooked_count, 1);
+
+        clock.set_mode(SandboxClockMode::Frozen { at: noon() + Duration::days(1) });
+        let second = service.mark(post_id, cook, input(5, "Со второго раза идеально", false), false).await.unwrap();
+        assert!(!second.created);
+        assert_eq!(second.cooked_count, 1);
+        assert_eq!(second.cooked.id, first.cooked.id);
+        assert_eq!((second.cooked.rating, second.cooked.note.as_deref(), second.cooked.is_public), (Some(5), Some("Со второго раза идеально"), false));
+        assert_eq!((second.cooked.created_at, second.cooked.updated_at), (noon(), noon() + Duration::days(1)));
+
+        let other = insert_user(&pool, "Вера").await;
+        assert_eq!(service.mark(post_id, other, input(4, "", true), false).await.unwrap().cooked_count, 2);
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_cooked WHERE post_id = $1 AND user_id = $2")
+            .bind(post_id)
+            .bind(cook)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[sqlx::test]
+    async fn only_visible_recipe_posts_of_others_can_be_marked(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let cook = insert_user(&pool, "Борис").await;
+        let (service, _) = service(&pool);
+
+        let own = recipe_post(&pool, cook, PostVisibility::Public, false).await;
+        assert!(matches!(service.mark(own, cook, input(5, "", true), false).await, Err(AppError::BadRequest(_))));
+
+        let text: Uuid = sqlx::query_scalar("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'Привет', 'text') RETURNING id")
+            .bind(author)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(matches!(service.mark(text, cook, input(5, "", true), false).await, Err(AppError::BadRequest(_))));
+
+        // Пост для подписчиков без подписки неотличим от несуществующего
+        let followers_only = recipe_post(&pool, author, PostVisibility::FollowersOnly, false).await;
+        for post_id in [followers_only, Uuid::new_v4()] {
+            assert!(matches!(service.mark(post_id, cook, input(5, "", true), false).await, Err(AppError::NotFound(_))));
+        }
+    }
+
+    #[sqlx::test]
+    async fn private_marks_are_seen_by_the_post_author_and_their_cook_only(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let open = insert_user(&pool, "Борис").await;
+        let private = insert_user(&pool, "Вера").await;
+        let stranger = insert_user(&pool, "Глеб").await;
+        let post_id = recipe_post(&pool, author, PostVisibility::Public, false).await;
+        let (service, _) = service(&pool);
+        service.mark(post_id, open, input(4, "Вкусно", true), false).await.unwrap();
+        service.mark(post_id, private, input(2, "Не мое", false), false).await.unwrap();
+
+        for (viewer, mut expected) in [
+            (author, vec![open, private]),
+            (private, vec![open, private]),
+            (open, vec![open]),
+            (stranger, vec![open]),
+        ] {
+            let gallery = service.gallery(post_id, viewer, 20, 0).await.unwrap();
+            let mut cooks: Vec<Uuid> = gallery.entries.iter().map(|entry| entry.user_id).collect();
+            cooks.sort();
+            expected.sort();
+            assert_eq!(cooks, expected);
+            // Счетчик и средняя оценка — по всем отметкам, в том числе закрытым
+            assert_eq!((gallery.cooked_count, gallery.average_rating), (2, Some(3.0)));
+        }
+    }
+
+    #[sqlx::test]
+    async fn meal_is_logged_only_on_request_and_with_known_nutrition(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let cook = insert_user(&pool, "Борис").await;
+        let (service, _) = service(&pool);
+        let diary_entries = || async {
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM diary_entries WHERE user_id = $1")
+                .bind(cook)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            count
+        };
+
+        let with_nutrition = recipe_post(&pool, author, PostVisibility::Public, true).await;
+        let outcome = service.mark(with_nutrition, cook, input(5, "", true), false).await.unwrap();
+        assert!(outcome.can_log_meal);
+        assert!(outcome.diary_entry.is_none());
+        assert_eq!(diary_entries().await, 0);
+
+        // Порция — 400 г на 2 порции; питательность пересчитана на 100 г
+        let entry = service.mark(with_nutrition, cook, input(5, "", true), true).await.unwrap().diary_entry.expect("diary entry");
+        assert_eq!((entry.user_id, entry.food_name.as_str(), entry.portion_size, entry.unit.as_str()), (cook, "Суп-пюре", 200.0, "г"));
+        assert_eq!((entry.calories_per_100g, entry.protein_per_100g, entry.fat_per_100g), (200.0, 10.0, 0.0));
+        assert_eq!((entry.meal_type.as_str(), entry.consumed_at), ("lunch", noon()));
+        assert_eq!(diary_entries().await, 1);
+
+        let without_nutrition = recipe_post(&pool, author, PostVisibility::Public, false).await;
+        let outcome = service.mark(without_nutrition, cook, input(5, "", true), true).await.unwrap();
+        assert!(!outcome.can_log_meal);
+        assert!(outcome.diary_entry.is_none());
+        assert_eq!(diary_entries().await, 1);
+    }
+}
@@ -0,0 +1,434 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    config::PostEditConfig,
+    db::DbPool,
+    models::{community::PostEdit, user::UserRole},
+    services::auth::Claims,
+    utils::errors::{AppError, ErrorCode},
+};
+
+/// Сколько прежних версий текста поста хранится
+const MAX_STORED_VERSIONS: i64 = 5;
+
+#[derive(sqlx::FromRow)]
+struct EditablePost {
+    author_id: Uuid,
+    content: String,
+    created_at: DateTime<Utc>,
+    likes_count: i64,
+}
+
+/// Правки постов и комментариев: окно правки для постов с откликом и история версий
+pub struct PostEditService {
+    pool: DbPool,
+}
+
+impl PostEditService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Меняет текст поста, если правка разрешена, и сохраняет прежний текст.
+    /// Тот же текст правкой не считается.
+    pub async fn edit_post(
+        &self,
+        post_id: Uuid,
+        user_id: Uuid,
+        content: &str,
+        config: &PostEditConfig,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let post = sqlx::query_as::<_, EditablePost>(
+            r#"
+            SELECT p.author_id, p.content, p.created_at,
+                   (SELECT COUNT(*) FROM likes l WHERE l.post_id = p.id) AS likes_count
+            FROM posts p
+            WHERE p.id = $1
+            FOR UPDATE
+            "#
+        )
+        .bind(post_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(post) = post else {
+            return Err(AppError::NotFound("Post not found".to_string()));
+        };
+        if post.author_id != user_id {
+            return Err(AppError::Forbidden("Only the author can edit this post".to_string()));
+        }
+        if post.content == content {
+            return Ok(());
+        }
+
+        check_edit_window(config, post.created_at, post.likes_count, &post.content, content, now)?;
+
+        sqlx::query("INSERT INTO post_edits (post_id, content, replaced_at) VALUES ($1, $2, $3)")
+            .bind(post_id)
+            .bind(&post.content)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM post_edits
+            WHERE post_id = $1
+              AND id NOT IN (SELECT id FROM post_edits WHERE post_id = $1 ORDER BY replaced_at DESC LIMIT $2)
+            "#
+        )
+        .bind(post_id)
+        .bind(MAX_STORED_VERSIONS)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE posts SET content = $2, edited_at = $3, edit_count = edit_count + 1 WHERE id = $1")
+            .bind(post_id)
+            .bind(content)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Прежние версии текста, новые первыми. Видны автору и модераторам (разбор жалоб).
+    pub async fn history(&self, post_id: Uuid, viewer: &Claims) -> Result<Vec<PostEdit>, AppError> {
+        let author_id = sqlx::query_scalar::<_, Uuid>("SELECT author_id FROM posts WHERE id = $1")
+            .bind(post_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Post not found".to_string()))?;
+
+        if !can_view_history(author_id, viewer) {
+            return Err(AppError::Forbidden("Only the author can view the edit history of this post".to_string()));
+        }
+
+        let edits = sqlx::query_as::<_, PostEdit>(
+            "SELECT id, post_id, content, replaced_at FROM post_edits WHERE post_id = $1 ORDER BY replaced_at DESC"
+        )
+        .bind(post_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(edits)
+    }
+
+    /// Меняет текст комментария и отмечает время правки; версии комментариев не хранятся.
+    /// Возвращает время последней правки (`None`, если текст не менялся).
+    pub async fn edit_comment(
+        &self,
+        comment_id: Uuid,
+        user_id: Uuid,
+        content: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, AppError> {
+        let author_id = sqlx::query_scalar::<_, Uuid>("SELECT author_id FROM comments WHERE id = $1")
+            .bind(comment_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match author_id {
+            None => Err(AppError::NotFound("Comment not found".to_string())),
+            Some(author_id) if author_id != user_id => {
+                Err(AppError::Forbidden("Only the author can edit this comment".to_string()))
+            }
+            Some(_) => {
+                sqlx::query("UPDATE comments SET content = $2, edited_at = $3 WHERE id = $1 AND content <> $2")
+                    .bind(comment_id)
+                    .bind(content)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await?;
+
+                sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT edited_at FROM comments WHERE id = $1")
+                    .bind(comment_id)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(AppError::from)
+            }
+        }
+    }
+}
+
+/// Историю правок видят автор поста и модераторы
+pub fn can_view_history(author_id: Uuid, viewer: &Claims) -> bool {
+    viewer.sub == author_id || matches!(viewer.role, UserRole::Moderator | UserRole::Admin)
+}
+
+/// Пост с откликом (старше окна или с лайками сверх порога) можно только подправить:
+/// новый текст должен быть достаточно похож на прежний
+pub fn check_edit_window(
+    config: &PostEditConfig,
+    created_at: DateTime<Utc>,
+    likes_count: i64,
+    old_content: &str,
+    new_content: &str,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let window_closed = now - created_at > Duration::hours(config.window_hours);
+    let popular = likes_count > config.max_likes;
+    if !window_closed && !popular {
+        return Ok(());
+    }
+
+    if text_similarity(old_content, new_content) >= config.small_edit_similarity {
+        return Ok(());
+    }
+
+    let reason = if popular {
+        format!("the post has more than {} likes", config.max_likes)
+    } else {
+        format!("the post is older than {} hours", config.window_hours)
+    };
+    Err(AppError::Conflict(format!("Only small corrections are allowed: {}", reason))
+        .with_code(ErrorCode::EditWindowClosed))
+}
+
+/// Сходство текстов от 0.0 до 1.0: единица минус расстояние Левенштейна по символам,
+/// деленное на длину более длинного текста
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Extension, Path};
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        api::community::get_post_edits,
+        test_support::{claims_for, insert_user},
+    };
+
+    fn config() -> PostEditConfig {
+        PostEditConfig { window_hours: 24, max_likes: 10, small_edit_similarity: 0.9 }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    const ORIGINAL: &str = "Мой борщ со сметаной и чесноком";
+    /// Исправлена одна буква: сходство около 0.97
+    const TYPO_FIX: &str = "Мой борщ со сметаной и чесночком";
+    const REWRITE: &str = "Вообще-то это был щавелевый суп";
+
+    #[test]
+    fn similarity_is_normalized_edit_distance() {
+        let cases = [
+            ("", "", 1.0),
+            ("борщ", "борщ", 1.0),
+            ("борщ", "", 0.0),
+            // Одна замена из четырех символов — по символам, а не байтам
+            ("борщ", "борш", 0.75),
+            ("kitten", "sitting", 1.0 - 3.0 / 7.0),
+            ("abc", "xyz", 0.0),
+        ];
+        for (a, b, expected) in cases {
+            assert!((text_similarity(a, b) - expected).abs() < 1e-9, "{} / {}: {}", a, b, text_similarity(a, b));
+            assert_eq!(text_similarity(a, b), text_similarity(b, a));
+        }
+
+        assert!(text_similarity(ORIGINAL, TYPO_FIX) >= 0.9);
+        assert!(text_similarity(ORIGINAL, REWRITE) < 0.5);
+    }
+
+    #[test]
+    fn engaged_posts_accept_only_small_edits() {
+        let hours = |hours: i64| now() - Duration::hours(hours);
+        let cases = [
+            // Свежий пост без лайков можно переписать
+            (hours(1), 0, REWRITE, None),
+            (hours(24), 10, REWRITE, None),
+            // Старше окна или больше порога лайков — только мелкая правка
+            (hours(25), 0, TYPO_FIX, None),
+            (hours(1), 11, TYPO_FIX, None),
+            (hours(25), 0, REWRITE, Some("Only small corrections are allowed: the post is older than 24 hours")),
+            (hours(1), 11, REWRITE, Some("Only small corrections are allowed: the post has more than 10 likes")),
+            (hours(48), 50, REWRITE, Some("Only small corrections are allowed: the post has more than 10 likes")),
+        ];
+
+        for (created_at, likes, content, expected) in cases {
+            let result = check_edit_window(&config(), created_at, likes, ORIGINAL, content, now());
+            match (result, expected) {
+                (Ok(()), None) => {}
+                (Err(error), Some(message)) => {
+                    assert_eq!(error.code(), ErrorCode::EditWindowClosed);
+                    assert!(format!("{:?}", error).contains(message), "{:?}", error);
+                }
+                (result, expected) => panic!("{} likes, created {}: got {:?}, expected {:?}", likes, created_at, result, expected),
+            }
+        }
+    }
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid, created_at: DateTime<Utc>, likes: usize) -> Uuid {
+        let post_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO posts (author_id, content, post_type, created_at) VALUES ($1, $2, 'text', $3) RETURNING id"
+        )
+        .bind(author_id)
+        .bind(ORIGINAL)
+        .bind(created_at)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        for i in 0..likes {
+            let fan = insert_user(pool, &format!("Фанат {}", i)).await;
+            sqlx::query("INSERT INTO likes (user_id, post_id) VALUES ($1, $2)").bind(fan).bind(post_id).execute(pool).await.unwrap();
+        }
+        post_id
+    }
+
+    async fn content(pool: &PgPool, post_id: Uuid) -> (String, i32, Option<DateTime<Utc>>) {
+        sqlx::query_as("SELECT content, edit_count, edited_at FROM posts WHERE id = $1")
+            .bind(post_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn window_is_enforced_against_stored_likes_and_age(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let service = PostEditService::new(pool.clone());
+
+        // 11 лайков: переписать нельзя, опечатку исправить можно
+        let popular = insert_post(&pool, author, now() - Duration::hours(1), 11).await;
+        let error = service.edit_post(popular, author, REWRITE, &config(), now()).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::EditWindowClosed);
+        assert_eq!(content(&pool, popular).await, (ORIGINAL.to_string(), 0, None), "a rejected edit changes nothing");
+
+        service.edit_post(popular, author, TYPO_FIX, &config(), now()).await.unwrap();
+        assert_eq!(content(&pool, popular).await, (TYPO_FIX.to_string(), 1, Some(now())));
+
+        // Старый пост без лайков
+        let old = insert_post(&pool, author, now() - Duration::days(2), 0).await;
+        assert!(service.edit_post(old, author, REWRITE, &config(), now()).await.is_err());
+        assert!(service.edit_post(old, author, TYPO_FIX, &config(), now()).await.is_ok());
+
+        // Свежий пост с 10 лайками переписывается целиком
+        let fresh = insert_post(&pool, author, now() - Duration::hours(1), 10).await;
+        service.edit_post(fresh, author, REWRITE, &config(), now()).await.unwrap();
+        assert_eq!(content(&pool, fresh).await.0, REWRITE);
+
+        // Тот же текст — не правка; чужой пост — 403; поста нет в БД — без учета
+        service.edit_post(fresh, author, REWRITE, &config(), now() + Duration::hours(1)).await.unwrap();
+        assert_eq!(content(&pool, fresh).await, (REWRITE.to_string(), 1, Some(now())));
+        let stranger = insert_user(&pool, "Борис").await;
+        assert!(matches!(
+            service.edit_post(fresh, stranger, TYPO_FIX, &config(), now()).await,
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            service.edit_post(Uuid::new_v4(), author, REWRITE, &config(), now()).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn only_the_last_five_versions_are_kept(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post_id = insert_post(&pool, author, now(), 0).await;
+        let service = PostEditService::new(pool.clone());
+
+        for version in 1..=7 {
+            let at = now() + Duration::minutes(version);
+            service.edit_post(post_id, author, &format!("Версия {}", version), &config(), at).await.unwrap();
+        }
+
+        let claims = claims_for(&pool, author).await;
+        let history = service.history(post_id, &claims).await.unwrap();
+        let texts: Vec<&str> = history.iter().map(|edit| edit.content.as_str()).collect();
+        assert_eq!(texts, vec!["Версия 6", "Версия 5", "Версия 4", "Версия 3", "Версия 2"]);
+        assert_eq!(history[0].replaced_at, now() + Duration::minutes(7));
+        assert_eq!(content(&pool, post_id).await.1, 7);
+    }
+
+    #[sqlx::test]
+    async fn history_is_visible_to_the_author_and_moderators_only(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post_id = insert_post(&pool, author, now(), 0).await;
+        PostEditService::new(pool.clone()).edit_post(post_id, author, REWRITE, &config(), now()).await.unwrap();
+
+        let stranger = insert_user(&pool, "Борис").await;
+        let moderator = insert_user(&pool, "Модератор").await;
+        let admin = insert_user(&pool, "Админ").await;
+        sqlx::query("UPDATE users SET role = 'moderator' WHERE id = $1").bind(moderator).execute(&pool).await.unwrap();
+        sqlx::query("UPDATE users SET role = 'admin' WHERE id = $1").bind(admin).execute(&pool).await.unwrap();
+
+        for (viewer, allowed) in [(author, true), (moderator, true), (admin, true), (stranger, false)] {
+            let claims = claims_for(&pool, viewer).await;
+            assert_eq!(can_view_history(author, &claims), allowed);
+
+            match get_post_edits(Extension(pool.clone()), claims, Path(post_id)).await {
+                Ok(edits) => {
+                    assert!(allowed);
+                    assert_eq!(edits.0.len(), 1);
+                    assert_eq!(edits.0[0].content, ORIGINAL);
+                }
+                Err(error) => {
+                    assert!(!allowed, "{:?}", error);
+                    assert!(matches!(error, AppError::Forbidden(_)));
+                }
+            }
+        }
+
+        let claims = claims_for(&pool, author).await;
+        assert!(matches!(
+            get_post_edits(Extension(pool.clone()), claims, Path(Uuid::new_v4())).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn comment_edits_are_marked_without_versions(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post_id = insert_post(&pool, author, now(), 0).await;
+        let comment_id: Uuid = sqlx::query_scalar("INSERT INTO comments (post_id, author_id, content) VALUES ($1, $2, 'Вкусно') RETURNING id")
+            .bind(post_id)
+            .bind(author)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let service = PostEditService::new(pool.clone());
+
+        // Тот же текст — правки нет
+        assert_eq!(service.edit_comment(comment_id, author, "Вкусно", now()).await.unwrap(), None);
+        assert_eq!(service.edit_comment(comment_id, author, "Очень вкусно", now()).await.unwrap(), Some(now()));
+        let stored: String = sqlx::query_scalar("SELECT content FROM comments WHERE id = $1").bind(comment_id).fetch_one(&pool).await.unwrap();
+        assert_eq!(stored, "Очень вкусно");
+
+        let stranger = insert_user(&pool, "Борис").await;
+        assert!(matches!(service.edit_comment(comment_id, stranger, "Фу", now()).await, Err(AppError::Forbidden(_))));
+        assert!(matches!(service.edit_comment(Uuid::new_v4(), author, "Фу", now()).await, Err(AppError::NotFound(_))));
+        let versions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_edits").fetch_one(&pool).await.unwrap();
+        assert_eq!(versions, 0);
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use uuid::Uuid;
+use chrono::NaiveDate;
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use tracing::{warn, Instrument};
+
+use crate::{
+    api::community::{PostAnalyticsDay, PostAnalyticsResponse, ReferrerCount},
+    db::DbPool,
+    services::clock::{self, SharedClock},
+    telemetry,
+    utils::errors::AppError,
+};
+
+/// Как часто буфер просмотров сбрасывается в БД
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Сверх этого просмотры отбрасываются, пока буфер не будет сброшен
+const MAX_BUFFERED_VIEWS: usize = 50_000;
+
+/// Размер множества уже учтенных за день просмотров; при переполнении оно очищается,
+/// окончательная дедупликация все равно выполняется в БД
+const MAX_SEEN_VIEWS: usize = 200_000;
+
+const MAX_SOURCE_LENGTH: usize = 50;
+
+/// Период аналитики для автора
+pub const ANALYTICS_DAYS: i32 = 30;
+
+/// Кто смотрит пост
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Viewer {
+    User(Uuid),
+    /// Хеш IP с солью текущего дня: по нему нельзя восстановить IP или связать просмотры разных дней
+    Anonymous(String),
+}
+
+impl Viewer {
+    fn key(&self) -> String {
+        match self {
+            Viewer::User(user_id) => format!("user:{}", user_id),
+            Viewer::Anonymous(hash) => format!("anon:{}", hash),
+        }
+    }
+
+    fn user_id(&self) -> Option<Uuid> {
+        match self {
+            Viewer::User(user_id) => Some(*user_id),
+            Viewer::Anonymous(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingView {
+    post_id: Uuid,
+    day: NaiveDate,
+    viewer: Viewer,
+    source: Option<String>,
+}
+
+struct AggregatorState {
+    day: NaiveDate,
+    /// Соль для хеширования IP; живет только в памяти и меняется каждый день
+    salt: String,
+    seen: HashSet<(Uuid, Viewer)>,
+    pending: Vec<PendingView>,
+}
+
+impl AggregatorState {
+    fn new(day: NaiveDate) -> Self {
+        Self {
+            day,
+            salt: random_salt(),
+            seen: HashSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn roll_day(&mut self, today: NaiveDate) {
+        if self.day != today {
+            self.day = today;
+            self.salt = random_salt();
+            self.seen.clear();
+        }
+    }
+}
+
+fn random_salt() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+/// Только буквы, цифры, `_` и `-`; пустой источник не сохраняется
+fn normalize_source(source: Option<&str>) -> Option<String> {
+    let source: String = source?
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .take(MAX_SOURCE_LENGTH)
+        .collect();
+
+    (!source.is_empty()).then_some(source)
+}
+
+/// Копит просмотры постов в памяти и пачками записывает их в БД фоновой задачей,
+/// чтобы событие прокрутки ленты не стоило отдельной вставки
+#[derive(Clone)]
+pub struct PostViewAggregator {
+    pool: DbPool,
+    clock: SharedClock,
+    state: Arc<Mutex<AggregatorState>>,
+}
+
+impl PostViewAggregator {
+    pub fn new(pool: DbPool) -> Self {
+        let clock = clock::system();
+        Self {
+            pool,
+            state: Arc::new(Mutex::new(AggregatorState::new(clock.now().date_naive()))),
+            clock,
+        }
+    }
+
+    /// Часы из `AppState` — день просмотра и смена соли считаются по ним
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.state = Arc::new(Mutex::new(AggregatorState::new(clock.now().date_naive())));
+        self.clock = clock;
+        self
+    }
+
+    /// Анонимный зритель по IP и соли текущего дня
+    pub fn anonymous_viewer(&self, ip: &str) -> Viewer {
+        let mut state = self.state.lock().unwrap();
+        state.roll_day(self.clock.now().date_naive());
+
+        let hash = format!("{:x}", Sha256::digest(format!("{}:{}", state.salt, ip).as_bytes()));
+        Viewer::Anonymous(hash[..32].to_string())
+    }
+
+    /// Ставит просмотр в очередь. Возвращает `false`, если этот зритель уже смотрел пост сегодня
+    /// или буфер переполнен.
+    pub fn record(&self, post_id: Uuid, viewer: Viewer, source: Option<&str>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let today = self.clock.now().date_naive();
+        state.roll_day(today);
+
+        if state.seen.len() >= MAX_SEEN_VIEWS {
+            state.seen.clear();
+        }
+        if !state.seen.insert((post_id, viewer.clone())) {
+            return false;
+        }
+        if state.pending.len() >= MAX_BUFFERED_VIEWS {
+            warn!("Post view buffer is full, dropping view of post {}", post_id);
+            return false;
+        }
+
+        state.pending.push(PendingView {
+            post_id,
+            day: today,
+            viewer,
+            source: normalize_source(source),
+        });
+        true
+    }
+
+    /// Записывает накопленные просмотры одной вставкой. Повторы за день отсекаются первичным ключом,
+    /// счетчики увеличиваются только на реально вставленные строки.
+    pub async fn flush(&self) -> Result<usize, AppError> {
+        let pending = std::mem::take(&mut self.state.lock().unwrap().pending);
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let post_ids: Vec<Uuid> = pending.iter().map(|view| view.post_id).collect();
+        let days: Vec<NaiveDate> = pending.iter().map(|view| view.day).collect();
+        let viewer_keys: Vec<String> = pending.iter().map(|view| view.viewer.key()).collect();
+        let user_ids: Vec<Option<Uuid>> = pending.iter().map(|view| view.viewer.user_id()).collect();
+        let sources: Vec<Option<String>> = pending.iter().map(|view| view.source.clone()).collect();
+
+        let result = sqlx::query_as::<_, (i64,)>(
+            r#"
+            WITH inserted AS (
+                INSERT INTO post_views (post_id, day, viewer_key, user_id, source)
+                SELECT * FROM UNNEST($1::uuid[], $2::date[], $3::text[], $4::uuid[], $5::text[])
+                ON CONFLICT DO NOTHING
+                RETURNING post_id
+            ),
+            counted AS (
+                INSERT INTO post_view_counts (post_id, views_count)
+                SELECT post_id, COUNT(*) FROM inserted GROUP BY post_id
+                ON CONFLICT (post_id) DO UPDATE
+                SET views_count = post_view_counts.views_count + EXCLUDED.views_count
+            )
+            SELECT COUNT(*) FROM inserted
+            "#
+        )
+        .bind(&post_ids)
+        .bind(&days)
+        .bind(&viewer_keys)
+        .bind(&user_ids)
+        .bind(&sources)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok((inserted,)) => Ok(inserted as usize),
+            Err(e) => {
+                // Возвращаем просмотры в буфер, чтобы не потерять их из-за временной ошибки БД
+                let mut state = self.state.lock().unwrap();
+                let room = MAX_BUFFERED_VIEWS.saturating_sub(state.pending.len());
+                state.pending.extend(pending.into_iter().take(room));
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Запускает фоновый сброс буфера каждые несколько секунд
+    pub fn start_flush_task(&self) {
+        let aggregator = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = aggregator.flush().instrument(telemetry::job_span("post_views_flush")).await {
+                    warn!("Failed to flush post views: {:?}", e);
+                }
+            }
+        });
+    }
+}
+
+pub struct PostViewService {
+    pool: DbPool,
+}
+
+impl PostViewService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Счетчики просмотров для ленты одним запросом
+    pub async fn views_counts(&self, post_ids: &[Uuid]) -> Result<HashMap<Uuid, i64>, AppError> {
+        if post_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+            "SELECT post_id, views_count FROM post_view_counts WHERE post_id = ANY($1)"
+        )
+        .bind(post_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Пересчитывает счетчики ленты по таблице просмотров; возвращает, сколько счетчиков исправлено
+    pub async fn recount_views(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO post_view_counts (post_id, views_count)
+            SELECT p.id, COUNT(v.post_id)
+            FROM posts p
+            LEFT JOIN post_views v ON v.post_id = p.id
+            GROUP BY p.id
+            ON CONFLICT (post_id) DO UPDATE SET views_count = EXCLUDED.views_count
+            WHERE post_view_counts.views_count <> EXCLUDED.views_count
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Аналитика поста за последние 30 дней — только для автора
+    pub async fn get_post_analytics(&self, post_id: Uuid, user_id: Uuid) -> Result<PostAnalyticsResponse, AppError> {
+        let (author_id,): (Uuid,) = sqlx::query_as("SELECT author_id FROM posts WHERE id = $1")
+            .bind(post_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Post not found".to_string()))?;
+
+        if author_id != user_id {
+            return Err(AppError::Forbidden("Only the author can see post analytics".to_string()));
+        }
+
+        let daily = sqlx::query_as::<_, (NaiveDate, i64, i64, i64, i64)>(
+            r#"
+            SELECT d.day,
+                   (SELECT COUNT(*) FROM post_views v WHERE v.post_id = $1 AND v.day = d.day),
+                   (SELECT COUNT(*) FROM post_views v WHERE v.post_id = $1 AND v.day = d.day AND v.user_id IS NULL),
+                   (SELECT COUNT(*) FROM likes l WHERE l.post_id = $1 AND l.created_at::date = d.day),
+                   (SELECT COUNT(*) FROM comments c WHERE c.post_id = $1 AND c.created_at::date = d.day)
+            FROM (SELECT CURRENT_DATE - offset_days AS day FROM generate_series($2 - 1, 0, -1) AS offset_days) AS d
+            ORDER BY d.day
+            "#
+        )
+        .bind(post_id)
+        .bind(ANALYTICS_DAYS)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let (unique_viewers,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(DISTINCT user_id) FROM post_views
+            WHERE post_id = $1 AND day > CURRENT_DATE - $2
+            "#
+        )
+        .bind(post_id)
+        .bind(ANALYTICS_DAYS)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let referrers = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT source, COUNT(*) FROM post_views
+            WHERE post_id = $1 AND day > CURRENT_DATE - $2 AND source IS NOT NULL
+            GROUP BY source
+            ORDER BY COUNT(*) DESC
+            "#
+        )
+        .bind(post_id)
+        .bind(ANALYTICS_DAYS)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let daily: Vec<PostAnalyticsDay> = daily
+            .into_iter()
+            .map(|(date, views, anonymous_views, likes, comments)| PostAnalyticsDay {
+                date,
+                views,
+                anonymous_views,
+                likes,
+                comments,
+            })
+            .collect();
+
+        Ok(PostAnalyticsResponse {
+            post_id,
+            period_days: ANALYTICS_DAYS,
+            views: daily.iter().map(|day| day.views).sum(),
+            unique_viewers,
+            anonymous_views: daily.iter().map(|day| day.anonymous_views).sum(),
+            likes: daily.iter().map(|day| day.likes).sum(),
+            comments: daily.iter().map(|day| day.comments).sum(),
+            daily,
+            referrers: referrers
+                .into_iter()
+                .map(|(source, views)| ReferrerCount { source, views })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::insert_user;
+
+    async fn insert_post(pool: &PgPool, author_id: Uuid) -> Uuid {
+        sqlx::query_scalar("INSERT INTO posts (author_id, content, post_type) VALUES ($1, 'Мой борщ', 'text') RETURNING id")
+            .bind(author_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn stored_count(pool: &PgPool, post_id: Uuid) -> Option<i64> {
+        sqlx::query_scalar("SELECT views_count FROM post_view_counts WHERE post_id = $1")
+            .bind(post_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    fn pending(aggregator: &PostViewAggregator) -> usize {
+        aggregator.state.lock().unwrap().pending.len()
+    }
+
+    #[tokio::test]
+    async fn views_are_deduplicated_per_viewer_post_and_day() {
+        let aggregator = PostViewAggregator::new(crate::test_support::lazy_pool());
+        let (post, other_post) = (Uuid::new_v4(), Uuid::new_v4());
+        let user = Viewer::User(Uuid::new_v4());
+
+        assert!(aggregator.record(post, user.clone(), Some("feed")));
+        assert!(!aggregator.record(post, user.clone(), Some("profile")));
+        assert!(aggregator.record(other_post, user.clone(), None));
+        assert!(aggregator.record(post, Viewer::User(Uuid::new_v4()), None));
+
+        // Анонимный зритель — по IP; тот же IP в тот же день — тот же зритель
+        let anonymous = aggregator.anonymous_viewer("203.0.113.7");
+        assert_eq!(anonymous, aggregator.anonymous_viewer("203.0.113.7"));
+        assert_ne!(anonymous, aggregator.anonymous_viewer("203.0.113.8"));
+        assert!(aggregator.record(post, anonymous.clone(), None));
+        assert!(!aggregator.record(post, anonymous, None));
+
+        assert_eq!(pending(&aggregator), 4);
+    }
+
+    #[tokio::test]
+    async fn anonymous_hash_hides_the_ip_and_changes_with_the_day() {
+        let aggregator = PostViewAggregator::new(crate::test_support::lazy_pool());
+        let Viewer::Anonymous(hash) = aggregator.anonymous_viewer("203.0.113.7") else {
+            panic!("expected an anonymous viewer");
+        };
+        assert_eq!(hash.len(), 32);
+        assert!(!hash.contains("203"));
+
+        // Новый день: новая соль и пустое множество учтенных просмотров
+        let post = Uuid::new_v4();
+        let viewer = Viewer::User(Uuid::new_v4());
+        assert!(aggregator.record(post, viewer.clone(), None));
+        {
+            let mut state = aggregator.state.lock().unwrap();
+            let salt = state.salt.clone();
+            let tomorrow = state.day.succ_opt().unwrap();
+            state.roll_day(tomorrow);
+            assert_ne!(state.salt, salt);
+            assert!(state.seen.is_empty());
+            // Уже ожидающие сброса просмотры остаются
+            assert_eq!(state.pending.len(), 1);
+        }
+        assert_ne!(aggregator.anonymous_viewer("203.0.113.7"), Viewer::Anonymous(hash));
+    }
+
+    #[test]
+    fn sources_are_normalized() {
+        assert_eq!(normalize_source(Some(" Feed ")), Some("feed".to_string()));
+        assert_eq!(normalize_source(Some("push/notification?x=1")), Some("pushnotificationx1".to_string()));
+        assert_eq!(normalize_source(Some("tg_share-2")), Some("tg_share-2".to_string()));
+        assert_eq!(normalize_source(Some("  ")), None);
+        assert_eq!(normalize_source(Some("лента")), None);
+        assert_eq!(normalize_source(None), None);
+        assert_eq!(normalize_source(Some(&"a".repeat(80))).unwrap().len(), MAX_SOURCE_LENGTH);
+    }
+
+    #[sqlx::test]
+    async fn flush_writes_the_buffer_in_one_batch(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let reader = insert_user(&pool, "Борис").await;
+        let post = insert_post(&pool, author).await;
+        let other_post = insert_post(&pool, author).await;
+        let aggregator = PostViewAggregator::new(pool.clone());
+
+        // Нечего сбрасывать — в БД не ходим
+        assert_eq!(aggregator.flush().await.unwrap(), 0);
+
+        aggregator.record(post, Viewer::User(reader), Some("feed"));
+        aggregator.record(post, Viewer::User(reader), Some("feed"));
+        aggregator.record(post, aggregator.anonymous_viewer("203.0.113.7"), None);
+        aggregator.record(other_post, Viewer::User(reader), None);
+        // До сброса в БД ничего нет
+        assert_eq!(stored_count(&pool, post).await, None);
+
+        assert_eq!(aggregator.flush().await.unwrap(), 3);
+        assert_eq!(pending(&aggregator), 0);
+        assert_eq!(stored_count(&pool, post).await, Some(2));
+        assert_eq!(stored_count(&pool, other_post).await, Some(1));
+        assert_eq!(aggregator.flush().await.unwrap(), 0);
+
+        let counts = PostViewService::new(pool.clone()).views_counts(&[post, other_post, Uuid::new_v4()]).await.unwrap();
+        assert_eq!((counts[&post], counts[&other_post], counts.len()), (2, 1, 2));
+
+        // После перезапуска память пуста, но повтор за день отсекает первичный ключ
+        let restarted = PostViewAggregator::new(pool.clone());
+        assert!(restarted.record(post, Viewer::User(reader), None));
+        assert!(restarted.record(post, Viewer::User(author), None));
+        assert_eq!(restarted.flush().await.unwrap(), 1);
+        assert_eq!(stored_count(&pool, post).await, Some(3));
+    }
+
+    #[sqlx::test]
+    async fn failed_flush_keeps_views_for_the_next_attempt(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post = insert_post(&pool, author).await;
+        let aggregator = PostViewAggregator::new(pool.clone());
+        aggregator.record(post, Viewer::User(author), None);
+        aggregator.record(post, aggregator.anonymous_viewer("203.0.113.7"), None);
+
+        sqlx::query("ALTER TABLE post_views RENAME TO post_views_unavailable").execute(&pool).await.unwrap();
+        assert!(aggregator.flush().await.is_err());
+        assert_eq!(pending(&aggregator), 2);
+
+        sqlx::query("ALTER TABLE post_views_unavailable RENAME TO post_views").execute(&pool).await.unwrap();
+        assert_eq!(aggregator.flush().await.unwrap(), 2);
+        assert_eq!(stored_count(&pool, post).await, Some(2));
+    }
+
+    #[sqlx::test]
+    async fn background_task_flushes_without_a_request(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let post = insert_post(&pool, author).await;
+        let aggregator = PostViewAggregator::new(pool.clone());
+        aggregator.record(post, Viewer::User(author), None);
+
+        // Первый тик интервала срабатывает сразу
+        aggregator.start_flush_task();
+        let mut flushed = None;
+        for _ in 0..50 {
+            flushed = stored_count(&pool, post).await;
+            if flushed.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(flushed, Some(1));
+        assert_eq!(pending(&aggregator), 0);
+    }
+
+    #[sqlx::test]
+    async fn analytics_are_for_the_author_only(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let reader = insert_user(&pool, "Борис").await;
+        let post = insert_post(&pool, author).await;
+        let aggregator = PostViewAggregator::new(pool.clone());
+        aggregator.record(post, Viewer::User(reader), Some("feed"));
+        aggregator.record(post, aggregator.anonymous_viewer("203.0.113.7"), Some("Feed"));
+        aggregator.record(post, aggregator.anonymous_viewer("203.0.113.8"), Some("share"));
+        aggregator.flush().await.unwrap();
+
+        let service = PostViewService::new(pool.clone());
+        let analytics = service.get_post_analytics(post, author).await.unwrap();
+        assert_eq!(analytics.daily.len(), ANALYTICS_DAYS as usize);
+        assert_eq!((analytics.views, analytics.anonymous_views, analytics.unique_viewers), (3, 2, 1));
+        let today = analytics.daily.last().unwrap();
+        assert_eq!((today.views, today.anonymous_views), (3, 2));
+        let referrers: Vec<(&str, i64)> = analytics.referrers.iter().map(|r| (r.source.as_str(), r.views)).collect();
+        assert_eq!(referrers, [("feed", 2), ("share", 1)]);
+
+        assert!(matches!(service.get_post_analytics(post, reader).await, Err(AppError::Forbidden(_))));
+        assert!(matches!(service.get_post_analytics(Uuid::new_v4(), author).await, Err(AppError::NotFound(_))));
+    }
+}
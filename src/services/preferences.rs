@@ -0,0 +1,732 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::ACCEPT_LANGUAGE, request::Parts},
+};
+use serde_json::{Map, Value};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::{CategoryPreferences, FridgeCategory},
+        health::EatingWindow,
+        notification::NotificationPreferences,
+        preferences::{PreferencesDocument, PreferencesRecord, PreferencesSection, UserPreferences, PREFERENCES_VERSION},
+    },
+    services::{
+        auth::Claims,
+        clock::{self, SharedClock},
+    },
+    utils::{errors::AppError, format::Locale, glossary::Glossary, i18n},
+};
+
+/// Настройки пользователя — один JSONB-документ на пользователя (таблица `user_preferences`).
+/// При чтении отсутствующие поля получают значения по умолчанию. При записи сохраняются
+/// только измененные разделы: одновременные изменения разных разделов не затирают друг друга,
+/// внутри раздела побеждает последняя запись.
+pub struct PreferencesService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl PreferencesService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    /// Часы из `AppState` — время изменения разделов
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Настройки пользователя; если он ничего не менял — значения по умолчанию
+    pub async fn for_user(&self, user_id: Uuid) -> Result<UserPreferences, AppError> {
+        Ok(self.document(user_id).await?.preferences)
+    }
+
+    /// Настройки вместе со временем изменения разделов
+    pub async fn document(&self, user_id: Uuid) -> Result<PreferencesDocument, AppError> {
+        let record = sqlx::query_as::<_, PreferencesRecord>(
+            "SELECT settings, section_updated_at FROM user_preferences WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record.map(PreferencesDocument::from).unwrap_or_default())
+    }
+
+    /// Читает документ, применяет изменение и сохраняет измененные разделы
+    pub async fn update<F>(&self, user_id: Uuid, apply: F) -> Result<UserPreferences, AppError>
+    where
+        F: FnOnce(&mut UserPreferences),
+    {
+        let document = self
+            .try_update(user_id, |preferences| {
+                apply(preferences);
+                Ok(())
+            })
+            .await?;
+        Ok(document.preferences)
+    }
+
+    /// Как `update`, но изменение может быть отклонено ошибкой — тогда ничего не сохраняется
+    pub async fn try_update<F>(&self, user_id: Uuid, apply: F) -> Result<PreferencesDocument, AppError>
+    where
+        F: FnOnce(&mut UserPreferences) -> Result<(), AppError>,
+    {
+        let current = self.document(user_id).await?;
+        let mut preferences = current.preferences.clone();
+        apply(&mut preferences)?;
+
+        let mut changed = Map::new();
+        for section in PreferencesSection::ALL {
+            let value = section_value(&preferences, section)?;
+            if value != section_value(&current.preferences, section)? {
+                changed.insert(section.as_str().to_string(), value);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(current);
+        }
+
+        let now = self.clock.now();
+        let touched: BTreeMap<String, _> = changed.keys().map(|section| (section.clone(), now)).collect();
+        changed.insert("version".to_string(), Value::from(PREFERENCES_VERSION));
+
+        // Разделы сливаются с документом в базе, а не заменяют его целиком
+        let record = sqlx::query_as::<_, PreferencesRecord>(
+            r#"
+            INSERT INTO user_preferences (user_id, settings, section_updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE
+            SET settings = user_preferences.settings || EXCLUDED.settings,
+                section_updated_at = user_preferences.section_updated_at || EXCLUDED.section_updated_at
+            RETURNING settings, section_updated_at
+            "#
+        )
+        .bind(user_id)
+        .bind(Json(Value::Object(changed)))
+        .bind(Json(&touched))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(record.into())
+    }
+
+    pub async fn get_categories(&self, user_id: Uuid) -> Result<CategoryPreferences, AppError> {
+        Ok(self.for_user(user_id).await?.categories)
+    }
+
+    pub async fn set_categories(&self, user_id: Uuid, categories: CategoryPreferences) -> Result<CategoryPreferences, AppError> {
+        let preferences = self.update(user_id, |preferences| preferences.categories = categories).await?;
+        Ok(preferences.categories)
+    }
+
+    pub async fn get_notifications(&self, user_id: Uuid) -> Result<NotificationPreferences, AppError> {
+        Ok(self.for_user(user_id).await?.notifications)
+    }
+
+    pub async fn set_notifications(&self, user_id: Uuid, notifications: NotificationPreferences) -> Result<NotificationPreferences, AppError> {
+        let preferences = self.update(user_id, |preferences| preferences.notifications = notifications).await?;
+        Ok(preferences.notifications)
+    }
+
+    pub async fn get_eating_window(&self, user_id: Uuid) -> Result<Option<EatingWindow>, AppError> {
+        Ok(self.for_user(user_id).await?.eating_window)
+    }
+
+    /// Сохраняет окно питания (`None` — выключает отслеживание). Дата последнего
+    /// уведомления сохраняется, чтобы изменение окна не вызвало повторное уведомление за день.
+    pub async fn set_eating_window(&self, user_id: Uuid, window: Option<EatingWindow>) -> Result<Option<EatingWindow>, AppError> {
+        let preferences = self
+            .update(user_id, |preferences| set_eating_window(preferences, window))
+            .await?;
+        Ok(preferences.eating_window)
+    }
+
+    /// Язык пользователя: из настроек, иначе из Accept-Language, иначе русский
+    pub async fn locale(&self, user_id: Uuid, accept_language: Option<&str>) -> Result<Locale, AppError> {
+        let preferences = self.for_user(user_id).await?;
+        Ok(i18n::resolve_locale(preferences.general.locale.as_deref(), accept_language))
+    }
+
+    /// Глоссарий продуктов для промптов: названия языка `locale` и замены пользователя
+    pub async fn glossary(&self, user_id: Uuid, locale: Locale) -> Result<Glossary, AppError> {
+        let preferences = self.for_user(user_id).await?;
+        Ok(Glossary::new(locale, &preferences.glossary))
+    }
+}
+
+/// Язык ответа для обработчика. Для авторизованного запроса учитывает настройки
+/// пользователя; запрос не отклоняет — при ошибке чтения настроек остается Accept-Language.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserLocale(pub Locale);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for UserLocale
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept_language = parts.headers.get(ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok());
+        let header_locale = || i18n::resolve_locale(None, accept_language);
+
+        let (Some(claims), Some(pool)) = (parts.extensions.get::<Claims>(), parts.extensions.get::<DbPool>()) else {
+            return Ok(UserLocale(header_locale()));
+        };
+
+        match PreferencesService::new(pool.clone()).locale(claims.sub, accept_language).await {
+            Ok(locale) => Ok(UserLocale(locale)),
+            Err(e) => {
+                tracing::warn!("Failed to read locale preference for user {}: {}", claims.sub, e);
+                Ok(UserLocale(header_locale()))
+            }
+        }
+    }
+}
+
+impl From<PreferencesRecord> for PreferencesDocument {
+    fn from(record: PreferencesRecord) -> Self {
+        Self {
+            preferences: record.settings,
+            updated_at: record.section_updated_at,
+        }
+    }
+}
+
+/// Заменяет окно питания, сохраняя дату последнего уведомления
+pub fn set_eating_window(preferences: &mut UserPreferences, window: Option<EatingWindow>) {
+    let last_notified_on = preferences.eating_window.as_ref().and_then(|current| current.last_notified_on);
+    preferences.eating_window = window.map(|window| EatingWindow { last_notified_on, ..window });
+}
+
+/// Раздел документа в JSON
+pub fn section_value(preferences: &UserPreferences, section: PreferencesSection) -> Result<Value, AppError> {
+    preferences
+        .section(section)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to serialize {} preferences: {}", section.as_str(), e)))
+}
+
+/// JSON Merge Patch (RFC 7386): объекты сливаются рекурсивно, `null` удаляет ключ,
+/// остальные значения заменяются целиком
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(&key);
+            } else {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Проверяет названия категорий: неизвестные и повторяющиеся отклоняются
+pub fn parse_category_list(field: &str, names: &[String]) -> Result<Vec<FridgeCategory>, AppError> {
+    let mut categories = Vec::with_capacity(names.len());
+
+    for name in names {
+        let category = FridgeCategory::parse(name)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown category in {}: {}", field, name)))?;
+
+        if categories.contains(&category) {
+            return Err(AppError::BadRequest(format!("Duplicate category in {}: {}", field, name)));
+        }
+        categories.push(category);
+    }
+
+    Ok(categories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::fridge::{validate_category_preferences, CategoryPreferencesRequest},
+        test_support::{access_token, insert_fridge_item, insert_user, request, send, test_router},
+    };
+    use axum::http::{Method, StatusCode};
+    use chrono::Utc;
+    use serde_json::json;
+    use sqlx::PgPool;
+    use FridgeCategory::*;
+
+    fn categories(order: Vec<FridgeCategory>, hidden: Vec<FridgeCategory>) -> CategoryPreferences {
+        CategoryPreferences { order, hidden }
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_preferences_give_the_default_order() {
+        let preferences = CategoryPreferences::default();
+
+        assert_eq!(preferences.full_order(), FridgeCategory::ALL.to_vec());
+        assert_eq!(preferences.effective_order(), FridgeCategory::ALL.to_vec());
+    }
+
+    #[test]
+    fn user_order_comes_first_then_remaining_defaults() {
+        let preferences = categories(vec![Snacks, Dairy, Beverages], Vec::new());
+
+        assert_eq!(
+            preferences.effective_order(),
+            vec![Snacks, Dairy, Beverages, Meat, Fish, Vegetables, Fruits, Grains, Condiments, Other]
+        );
+
+        // Полный список из пользователя остается как есть
+        let reversed: Vec<FridgeCategory> = FridgeCategory::ALL.iter().rev().cloned().collect();
+        assert_eq!(categories(reversed.clone(), Vec::new()).effective_order(), reversed);
+    }
+
+    #[test]
+    fn hidden_categories_are_excluded_wherever_they_would_be() {
+        // Скрытая категория из пользовательского порядка и из оставшихся по умолчанию
+        let preferences = categories(vec![Fruits, Fish], vec![Fish, Other]);
+
+        let effective = preferences.effective_order();
+        assert_eq!(effective, vec![Fruits, Dairy, Meat, Vegetables, Grains, Beverages, Condiments, Snacks]);
+        assert_eq!(preferences.full_order().len(), FridgeCategory::ALL.len());
+        assert!(preferences.is_hidden(&Fish) && preferences.is_hidden(&Other));
+        assert!(!preferences.is_hidden(&Fruits));
+    }
+
+    #[test]
+    fn sort_key_puts_hidden_categories_last_in_full_order() {
+        let preferences = categories(vec![Grains, Fish], vec![Fish, Dairy]);
+
+        let mut sorted = FridgeCategory::ALL.to_vec();
+        sorted.sort_by_key(|category| preferences.sort_key(category));
+
+        assert_eq!(
+            sorted,
+            vec![Grains, Meat, Vegetables, Fruits, Beverages, Condiments, Snacks, Other, Fish, Dairy]
+        );
+        assert_eq!(sorted[..8].to_vec(), preferences.effective_order());
+    }
+
+    #[test]
+    fn validation_accepts_any_case_and_rejects_unknown_and_duplicate_names() {
+        let parsed = validate_category_preferences(CategoryPreferencesRequest {
+            order: names(&["snacks", "DAIRY", " Fish "]),
+            hidden: names(&["other"]),
+        })
+        .unwrap();
+        assert_eq!(parsed.order, vec![Snacks, Dairy, Fish]);
+        assert_eq!(parsed.hidden, vec![Other]);
+
+        let rejected = [
+            (names(&["Dairy", "Sweets"]), Vec::new(), "Unknown category in order: Sweets"),
+            (Vec::new(), names(&["Candy"]), "Unknown category in hidden: Candy"),
+            (names(&["Dairy", "dairy"]), Vec::new(), "Duplicate category in order: dairy"),
+            (Vec::new(), names(&["Fish", "FISH"]), "Duplicate category in hidden: FISH"),
+        ];
+        for (order, hidden, message) in rejected {
+            match validate_category_preferences(CategoryPreferencesRequest { order, hidden }) {
+                Err(AppError::BadRequest(error)) => assert_eq!(error, message),
+                other => panic!("expected BadRequest({}), got {:?}", message, other),
+            }
+        }
+    }
+
+    #[test]
+    fn validation_keeps_at_least_one_category_visible() {
+        let all: Vec<String> = FridgeCategory::ALL.iter().map(|category| format!("{:?}", category)).collect();
+
+        let result = validate_category_preferences(CategoryPreferencesRequest { order: Vec::new(), hidden: all.clone() });
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let one_visible = validate_category_preferences(CategoryPreferencesRequest {
+            order: Vec::new(),
+            hidden: all[1..].to_vec(),
+        })
+        .unwrap();
+        assert_eq!(one_visible.effective_order(), vec![Dairy]);
+    }
+
+    #[test]
+    fn missing_document_fields_get_defaults() {
+        let preferences: UserPreferences = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(preferences.version, 0);
+        assert_eq!(preferences.categories.effective_order(), FridgeCategory::ALL.to_vec());
+
+        // Раздел без одного из полей и неизвестные ключи из более новой версии
+        let preferences: UserPreferences = serde_json::from_value(json!({
+            "version": 7,
+            "categories": { "hidden": ["Fish"] },
+            "future_section": { "enabled": true }
+        }))
+        .unwrap();
+        assert!(preferences.categories.order.is_empty());
+        assert_eq!(preferences.categories.hidden, vec![Fish]);
+        assert_eq!(preferences.categories.effective_order().len(), FridgeCategory::ALL.len() - 1);
+    }
+
+    #[test]
+    fn merge_patch_follows_rfc_7386() {
+        let mut target = json!({
+            "categories": { "order": ["Fish"], "hidden": ["Other"] },
+            "general": { "locale": "ru", "utc_offset_minutes": 180 },
+            "glossary": { "overrides": { "творожок": "творог" } }
+        });
+
+        merge_patch(&mut target, json!({
+            "categories": { "order": ["Dairy", "Meat"] },
+            "general": { "locale": null },
+            "glossary": { "overrides": { "сырок": "сыр" } },
+            "privacy": { "public_profile": false }
+        }));
+
+        assert_eq!(target, json!({
+            "categories": { "order": ["Dairy", "Meat"], "hidden": ["Other"] },
+            "general": { "utc_offset_minutes": 180 },
+            "glossary": { "overrides": { "творожок": "творог", "сырок": "сыр" } },
+            "privacy": { "public_profile": false }
+        }));
+
+        // Не-объект заменяет значение целиком, объект заменяет не-объект
+        let mut scalar = json!({ "a": [1, 2] });
+        merge_patch(&mut scalar, json!({ "a": { "b": 1 } }));
+        assert_eq!(scalar, json!({ "a": { "b": 1 } }));
+        merge_patch(&mut scalar, json!([3]));
+        assert_eq!(scalar, json!([3]));
+    }
+
+    /// Документ текущей версии, в котором заполнено каждое поле каждого раздела
+    fn full_document() -> Value {
+        json!({
+            "version": PREFERENCES_VERSION,
+            "general": { "locale": "en-US", "utc_offset_minutes": 180, "units": "imperial" },
+            "categories": { "order": ["Fruits", "Dairy"], "hidden": ["Fish"] },
+            "checkin": {
+                "enabled": true,
+                "day": "friday",
+                "last_checkin_at": "2026-10-09T08:00:00Z",
+                "last_reminded_on": "2026-10-09"
+            },
+            "reports": { "monthly_email": true, "weekly_goal_review": true },
+            "notifications": {
+                "expiry_lead_days": { "Dairy": 1, "Meat": null },
+                "quiet_hours": { "start": "22:00:00", "end": "07:00:00", "utc_offset_minutes": 180 },
+                "channels": {
+                    "expiring_items": "inbox",
+                    "goal_reminders": "websocket",
+                    "fridge_checkin": "off",
+                    "eating_window": "inbox",
+                    "diary_reminder": "websocket"
+                },
+                "muted": true,
+                "muted_until": "2026-10-20T00:00:00Z",
+                "new_device_email": true
+            },
+            "eating_window": {
+                "start": "10:00:00",
+                "end": "18:00:00",
+                "utc_offset_minutes": 180,
+                "weekdays": ["monday", "friday"],
+                "notify_outside": true,
+                "last_notified_on": "2026-10-09"
+            },
+            "privacy": { "default_post_visibility": "followers_only" },
+            "meal_times": { "lunch_from": "12:00:00", "dinner_from": "17:00:00", "snack_from": "22:00:00" },
+            "diary_reminder": { "enabled": true, "time": "21:30:00", "last_reminded_on": "2026-10-09" },
+            "glossary": { "overrides": { "творожок": "творог" } }
+        })
+    }
+
+    /// Самый старый вид раздела: пустой объект, а у окна питания — только обязательные поля
+    fn minimal_section(section: PreferencesSection) -> Value {
+        match section {
+            PreferencesSection::EatingWindow => json!({ "start": "10:00:00", "end": "18:00:00" }),
+            _ => json!({}),
+        }
+    }
+
+    fn parse(document: Value) -> UserPreferences {
+        serde_json::from_value(document.clone()).unwrap_or_else(|e| panic!("{} does not parse: {}", document, e))
+    }
+
+    fn section_of(preferences: &UserPreferences, section: PreferencesSection) -> Value {
+        section_value(preferences, section).unwrap()
+    }
+
+    #[test]
+    fn section_keys_match_the_serialized_document() {
+        let serialized = serde_json::to_value(UserPreferences::default()).unwrap();
+        let mut keys: Vec<&str> = serialized.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+
+        let mut expected: Vec<&str> = PreferencesSection::ALL.iter().map(|section| section.as_str()).collect();
+        expected.push("version");
+        expected.sort();
+        assert_eq!(keys, expected);
+
+        for section in PreferencesSection::ALL {
+            assert_eq!(PreferencesSection::parse(section.as_str()), Some(section));
+            assert_eq!(section_of(&UserPreferences::default(), section), serialized[section.as_str()]);
+        }
+        assert_eq!(PreferencesSection::parse("version"), None);
+        assert_eq!(PreferencesSection::parse("future_section"), None);
+    }
+
+    #[test]
+    fn full_document_round_trips_without_losing_fields() {
+        let full = full_document();
+        let preferences = parse(full.clone());
+
+        assert_eq!(serde_json::to_value(&preferences).unwrap(), full);
+        for section in PreferencesSection::ALL {
+            assert_eq!(section_of(&preferences, section), full[section.as_str()], "{}", section.as_str());
+        }
+    }
+
+    #[test]
+    fn document_without_a_section_reads_its_default_and_keeps_the_rest() {
+        // Так выглядит документ, записанный до появления раздела
+        let full = full_document();
+        let defaults = UserPreferences::default();
+
+        for missing in PreferencesSection::ALL {
+            let mut document = full.clone();
+            document.as_object_mut().unwrap().remove(missing.as_str());
+            let preferences = parse(document);
+
+            for section in PreferencesSection::ALL {
+                let expected = if section == missing { section_of(&defaults, section) } else { full[section.as_str()].clone() };
+                assert_eq!(section_of(&preferences, section), expected, "{} without {}", section.as_str(), missing.as_str());
+            }
+        }
+
+        // Документ без версии — записан до появления версий
+        let mut document = full;
+        document.as_object_mut().unwrap().remove("version");
+        assert_eq!(parse(document).version, 0);
+    }
+
+    #[test]
+    fn section_without_a_field_reads_its_default_and_keeps_the_rest() {
+        // Так выглядит раздел, записанный до появления поля
+        let full = full_document();
+
+        for section in PreferencesSection::ALL {
+            let mut minimal = full.clone();
+            minimal[section.as_str()] = minimal_section(section);
+            let defaults = section_of(&parse(minimal), section);
+
+            for field in full[section.as_str()].as_object().unwrap().keys() {
+                let mut document = full.clone();
+                document[section.as_str()].as_object_mut().unwrap().remove(field);
+                let required = section == PreferencesSection::EatingWindow && (field == "start" || field == "end");
+
+                match serde_json::from_value::<UserPreferences>(document) {
+                    Ok(_) if required => panic!("eating_window without {} must not parse", field),
+                    Err(e) if !required => panic!("{} without {} does not parse: {}", section.as_str(), field, e),
+                    Err(_) => {}
+                    Ok(preferences) => {
+                        let value = section_of(&preferences, section);
+                        for (key, expected) in full[section.as_str()].as_object().unwrap() {
+                            let expected = if key == field { &defaults[key] } else { expected };
+                            assert_eq!(&value[key], expected, "{}.{} without {}", section.as_str(), key, field);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fields_and_sections_from_a_newer_version_are_ignored() {
+        let full = full_document();
+        let mut newer = full.clone();
+        newer["version"] = json!(PREFERENCES_VERSION + 1);
+        newer["future_section"] = json!({ "enabled": true });
+        for section in PreferencesSection::ALL {
+            newer[section.as_str()]["added_later"] = json!({ "nested": [1, 2, 3] });
+        }
+
+        let preferences = parse(newer);
+        assert_eq!(preferences.version, PREFERENCES_VERSION + 1);
+        for section in PreferencesSection::ALL {
+            assert_eq!(section_of(&preferences, section), full[section.as_str()], "{}", section.as_str());
+        }
+    }
+
+    #[sqlx::test]
+    async fn user_without_a_row_reads_defaults(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = PreferencesService::new(pool.clone());
+
+        let document = service.document(user_id).await.unwrap();
+        assert!(document.updated_at.is_empty());
+        assert_eq!(service.get_categories(user_id).await.unwrap().effective_order(), FridgeCategory::ALL.to_vec());
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_preferences WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 0, "reading must not create a row");
+    }
+
+    #[sqlx::test]
+    async fn saved_categories_round_trip_and_leave_other_sections_alone(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = PreferencesService::new(pool.clone());
+
+        service.update(user_id, |preferences| preferences.general.utc_offset_minutes = 180).await.unwrap();
+        let saved = service
+            .set_categories(user_id, categories(vec![Fruits, Dairy], vec![Fish]))
+            .await
+            .unwrap();
+        assert_eq!(saved.order, vec![Fruits, Dairy]);
+
+        let document = service.document(user_id).await.unwrap();
+        assert_eq!(document.preferences.general.utc_offset_minutes, 180);
+        assert_eq!(document.preferences.categories.hidden, vec![Fish]);
+        assert_eq!(document.preferences.version, PREFERENCES_VERSION);
+        assert!(document.updated_at.contains_key("general"));
+        assert!(document.updated_at.contains_key("categories"));
+
+        // В базе хранятся только измененные разделы, не весь документ с умолчаниями
+        let stored: Value = sqlx::query_scalar("SELECT settings FROM user_preferences WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let mut keys: Vec<&String> = stored.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["categories", "general", "version"]);
+
+        // Повторное сохранение тех же настроек ничего не пишет
+        let before = document.updated_at["categories"];
+        service.set_categories(user_id, categories(vec![Fruits, Dairy], vec![Fish])).await.unwrap();
+        assert_eq!(service.document(user_id).await.unwrap().updated_at["categories"], before);
+    }
+
+    #[sqlx::test]
+    async fn stored_documents_of_other_versions_are_read_and_kept(pool: PgPool) {
+        let service = PreferencesService::new(pool.clone());
+        let store = |user_id: Uuid, settings: Value| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("INSERT INTO user_preferences (user_id, settings, section_updated_at) VALUES ($1, $2, '{}')")
+                    .bind(user_id)
+                    .bind(settings)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+
+        // Документ без версии с частью разделов
+        let legacy = insert_user(&pool, "Анна").await;
+        store(legacy, json!({ "categories": { "hidden": ["Fish"] }, "checkin": { "enabled": true } })).await;
+        let preferences = service.for_user(legacy).await.unwrap();
+        assert_eq!(preferences.version, 0);
+        assert_eq!(preferences.categories.hidden, vec![Fish]);
+        assert!(preferences.checkin.enabled);
+        assert_eq!(preferences.meal_times, Default::default());
+
+        // Документ более новой версии: неизвестный раздел и поле переживают запись старым кодом
+        let newer = insert_user(&pool, "Борис").await;
+        let mut document = full_document();
+        document["future_section"] = json!({ "enabled": true });
+        document["privacy"]["added_later"] = json!(true);
+        store(newer, document).await;
+
+        let preferences = service.for_user(newer).await.unwrap();
+        assert_eq!(preferences.general.locale.as_deref(), Some("en-US"));
+        service.update(newer, |preferences| preferences.general.utc_offset_minutes = 60).await.unwrap();
+
+        let stored: Value = sqlx::query_scalar("SELECT settings FROM user_preferences WHERE user_id = $1")
+            .bind(newer)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored["general"]["utc_offset_minutes"], 60);
+        assert_eq!(stored["future_section"], json!({ "enabled": true }));
+        assert_eq!(stored["privacy"]["added_later"], true, "untouched sections are stored as they were");
+    }
+
+    #[sqlx::test]
+    async fn rejected_update_saves_nothing(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let service = PreferencesService::new(pool.clone());
+
+        let result = service
+            .try_update(user_id, |preferences| {
+                preferences.categories.order = vec![Meat];
+                Err(AppError::BadRequest("nope".to_string()))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert!(service.get_categories(user_id).await.unwrap().order.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn category_endpoints_use_the_effective_order(pool: PgPool) {
+        let router = test_router(&pool);
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        insert_fridge_item(&pool, user_id, "Молоко", None, Utc::now()).await;
+
+        let (status, body) = send(&router, request(
+            Method::PUT,
+            "/api/v1/fridge/categories/preferences",
+            Some(&token),
+            Some(json!({ "order": ["snacks", "beverages"], "hidden": ["other", "fish"] })),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["order"], json!(["Snacks", "Beverages"]));
+        assert_eq!(body["hidden"], json!(["Other", "Fish"]));
+        let expected = json!(["Snacks", "Beverages", "Dairy", "Meat", "Vegetables", "Fruits", "Grains", "Condiments"]);
+        assert_eq!(body["effective_order"], expected);
+
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/fridge/categories/preferences", Some(&token), None)).await;
+        assert_eq!(body["effective_order"], expected);
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/fridge/categories", Some(&token), None)).await;
+        assert_eq!(body, expected);
+
+        // Продукты скрытой категории остаются в списке, но с флагом
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/fridge", Some(&token), None)).await;
+        let items = body.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["category"], "Other");
+        assert_eq!(items[0]["category_hidden"], true);
+
+        let (status, body) = send(&router, request(
+            Method::PUT,
+            "/api/v1/fridge/categories/preferences",
+            Some(&token),
+            Some(json!({ "order": ["Dairy", "Dairy"] })),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"], "Bad request: Duplicate category in order: Dairy");
+
+        // Другой пользователь видит порядок по умолчанию
+        let other = insert_user(&pool, "Борис").await;
+        let other_token = access_token(&pool, other).await;
+        let (_, body) = send(&router, request(Method::GET, "/api/v1/fridge/categories", Some(&other_token), None)).await;
+        assert_eq!(body.as_array().unwrap().len(), FridgeCategory::ALL.len());
+    }
+}
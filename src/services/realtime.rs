@@ -1,20 +1,31 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use axum::extract::WebSocketUpgrade;
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use axum::response::Response;
 use futures_util::{sink::SinkExt, stream::StreamExt};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, oneshot, RwLock};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
-use tracing::{info, warn, error};
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::{info, instrument, warn, error};
 
+use crate::models::community::{ActivityKind, FriendActivityStatus};
+use crate::models::fridge::ExpiryUrgency;
+use crate::models::goal::Goal;
 use crate::services::auth::Claims;
+use crate::services::clock::{self, SharedClock};
+use crate::services::realtime_queue::{BackpressureMetrics, BackpressureStats, Outgoing, OutgoingQueue};
+use crate::services::realtime_schema::{self, EVENT_SCHEMA_VERSION};
+use crate::services::task_supervisor::TaskSupervisor;
 use crate::utils::errors::AppError;
+use crate::utils::{format::Locale, i18n};
 
-/// Типы WebSocket событий
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Типы WebSocket событий. Любое изменение формы события — новая версия
+/// `realtime_schema::EVENT_SCHEMA_VERSION`; клиенты видят схему в GET /realtime/schema.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum WebSocketEvent {
     /// Новое сообщение в чате сообщества
@@ -37,10 +48,54 @@ pub enum WebSocketEvent {
         author_name: String,
         content: String,
     },
+    /// Кто-то приготовил блюдо по посту с рецептом
+    PostCooked {
+        post_id: Uuid,
+        cook_name: String,
+        rating: Option<i16>,
+        cooked_count: u32,
+    },
     /// Уведомление о скоропортящихся продуктах
     ExpiringItems {
         items: Vec<ExpiringItem>,
-        days_left: u32,
+        /// Минимальный среди продуктов; отрицательный — срок самого срочного уже прошел
+        days_left: i32,
+        /// Срочность самого срочного продукта
+        urgency: ExpiryUrgency,
+    },
+    /// Наступил день, к которому пользователь собирался использовать продукт (по заметке)
+    FridgeItemUseReminder {
+        item_id: Uuid,
+        name: String,
+        use_by: NaiveDate,
+        recipe_id: Option<Uuid>,
+        recipe_name: Option<String>,
+    },
+    /// Наступил выбранный пользователем день проверки холодильника
+    FridgeCheckinDue {
+        items_count: usize,
+        stale_items_count: usize,
+    },
+    /// Прием пищи записан вне окна питания
+    MealOutsideEatingWindow {
+        entry_id: Uuid,
+        consumed_at: DateTime<Utc>,
+        window_opens_at: Option<DateTime<Utc>>,
+    },
+    /// Вечернее напоминание: за местный день в дневнике ничего не записано
+    DiaryReminder {
+        local_date: NaiveDate,
+        /// Серия дней с записями, которая прервется, если сегодня ничего не записать
+        streak_days: u32,
+        /// Экран клиента, который стоит открыть
+        deep_link: String,
+    },
+    /// Заполнение питательности старых записей дневника закончилось
+    NutritionBackfillCompleted {
+        job_id: Uuid,
+        filled_from_database: i32,
+        filled_by_ai: i32,
+        unresolved: i32,
     },
     /// Достижение цели
     GoalAchieved {
@@ -48,6 +103,28 @@ pub enum WebSocketEvent {
         title: String,
         achievement_type: String,
     },
+    /// До срока цели осталось одно из настроенных количеств дней
+    GoalDeadlineApproaching {
+        goal_id: Uuid,
+        title: String,
+        target_date: NaiveDate,
+        days_left: u32,
+        progress_percentage: f32,
+        is_on_track: bool,
+    },
+    /// Срок цели прошел, цель не достигнута и переведена в статус Expired
+    GoalExpired {
+        goal_id: Uuid,
+        title: String,
+        target_date: NaiveDate,
+        progress_percentage: f32,
+    },
+    /// Помощник начал еженедельный разбор целей
+    GoalReviewStarted {
+        conversation_id: Uuid,
+        week_start: NaiveDate,
+        message: String,
+    },
     /// Новый подписчик
     NewFollower {
         follower_id: Uuid,
@@ -59,6 +136,22 @@ pub enum WebSocketEvent {
         title: String,
         ingredients_count: u32,
     },
+    /// Кто-то сохранил себе копию рецепта пользователя
+    RecipeForked {
+        recipe_id: Uuid,
+        title: String,
+        forked_by_name: String,
+        forks_count: u32,
+    },
+    /// Пользователь, на которого подписан получатель, сменил статус активности
+    FriendActivity {
+        user_id: Uuid,
+        user_name: String,
+        activity: ActivityKind,
+        recipe_id: Option<Uuid>,
+        recipe_name: Option<String>,
+        expires_at: DateTime<Utc>,
+    },
     /// Системное уведомление
     SystemNotification {
         title: String,
@@ -71,14 +164,112 @@ pub enum WebSocketEvent {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl WebSocketEvent {
+    /// Имена типов событий (поле `type`) в порядке `type_index`
+    pub const TYPE_NAMES: [&'static str; 20] = [
+        "NewCommunityPost",
+        "PostLiked",
+        "NewComment",
+        "PostCooked",
+        "ExpiringItems",
+        "FridgeItemUseReminder",
+        "FridgeCheckinDue",
+        "MealOutsideEatingWindow",
+        "DiaryReminder",
+        "NutritionBackfillCompleted",
+        "GoalAchieved",
+        "GoalDeadlineApproaching",
+        "GoalExpired",
+        "GoalReviewStarted",
+        "NewFollower",
+        "RecipeGenerated",
+        "RecipeForked",
+        "FriendActivity",
+        "SystemNotification",
+        "Heartbeat",
+    ];
+
+    /// Имя типа события (поле `type`)
+    pub fn type_name(&self) -> &'static str {
+        Self::TYPE_NAMES[self.type_index()]
+    }
+
+    /// Номер типа события в `TYPE_NAMES`
+    pub fn type_index(&self) -> usize {
+        match self {
+            WebSocketEvent::NewCommunityPost { .. } => 0,
+            WebSocketEvent::PostLiked { .. } => 1,
+            WebSocketEvent::NewComment { .. } => 2,
+            WebSocketEvent::PostCooked { .. } => 3,
+            WebSocketEvent::ExpiringItems { .. } => 4,
+            WebSocketEvent::FridgeItemUseReminder { .. } => 5,
+            WebSocketEvent::FridgeCheckinDue { .. } => 6,
+            WebSocketEvent::MealOutsideEatingWindow { .. } => 7,
+            WebSocketEvent::DiaryReminder { .. } => 8,
+            WebSocketEvent::NutritionBackfillCompleted { .. } => 9,
+            WebSocketEvent::GoalAchieved { .. } => 10,
+            WebSocketEvent::GoalDeadlineApproaching { .. } => 11,
+            WebSocketEvent::GoalExpired { .. } => 12,
+            WebSocketEvent::GoalReviewStarted { .. } => 13,
+            WebSocketEvent::NewFollower { .. } => 14,
+            WebSocketEvent::RecipeGenerated { .. } => 15,
+            WebSocketEvent::RecipeForked { .. } => 16,
+            WebSocketEvent::FriendActivity { .. } => 17,
+            WebSocketEvent::SystemNotification { .. } => 18,
+            WebSocketEvent::Heartbeat { .. } => 19,
+        }
+    }
+
+    /// Необязательное событие: при переполнении очереди соединения его можно выбросить —
+    /// набор текста, присутствие друзей и heartbeat быстро устаревают сами
+    pub fn is_droppable(&self) -> bool {
+        match self {
+            WebSocketEvent::FriendActivity { .. } | WebSocketEvent::Heartbeat { .. } => true,
+            WebSocketEvent::SystemNotification { title, .. } => title == TYPING_TITLE,
+            _ => false,
+        }
+    }
+
+    /// Скоропортящиеся продукты; `days_left` и `urgency` — самого срочного из них,
+    /// включая уже просроченные
+    pub fn expiring_items(items: Vec<ExpiringItem>) -> Self {
+        let days_left = items.iter().map(|item| item.days_left).min().unwrap_or(0);
+        let urgency = items.iter().map(|item| item.urgency).min().unwrap_or(ExpiryUrgency::Later);
+        WebSocketEvent::ExpiringItems { items, days_left, urgency }
+    }
+
+    pub fn goal_deadline_approaching(goal: &Goal, target_date: NaiveDate, days_left: u32, is_on_track: bool) -> Self {
+        WebSocketEvent::GoalDeadlineApproaching {
+            goal_id: goal.id,
+            title: goal.title.clone(),
+            target_date,
+            days_left,
+            progress_percentage: goal.progress_percentage(),
+            is_on_track,
+        }
+    }
+
+    pub fn goal_expired(goal: &Goal, target_date: NaiveDate) -> Self {
+        WebSocketEvent::GoalExpired {
+            goal_id: goal.id,
+            title: goal.title.clone(),
+            target_date,
+            progress_percentage: goal.progress_percentage(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExpiringItem {
     pub id: Uuid,
     pub name: String,
-    pub days_left: u32,
+    /// Отрицательный — срок уже прошел
+    pub days_left: i32,
+    pub urgency: ExpiryUrgency,
+    pub expires_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum NotificationLevel {
     Info,
     Warning,
@@ -86,22 +277,201 @@ pub enum NotificationLevel {
     Success,
 }
 
-/// Информация о подключенном клиенте
+/// Информация о подключенном клиенте (одно соединение; у пользователя их может быть несколько)
 #[derive(Debug, Clone, Serialize)]
 pub struct ConnectedClient {
+    pub connection_id: Uuid,
     pub user_id: Uuid,
+    /// Сессия входа, из которой открыто соединение (claim sid токена)
+    pub session_id: Option<Uuid>,
     pub user_name: String,
     pub connected_at: DateTime<Utc>,
     pub last_heartbeat: DateTime<Utc>,
+    /// Типы событий, которые получает соединение; `null` — все
+    pub event_filter: EventFilter,
+}
+
+/// Маска фильтра без ограничений
+const ALL_EVENT_TYPES: u32 = u32::MAX;
+
+// Каждому типу события нужен свой бит маски
+const _: () = assert!(WebSocketEvent::TYPE_NAMES.len() <= u32::BITS as usize);
+
+/// Фильтр событий соединения: бит на каждый тип из `WebSocketEvent::TYPE_NAMES`.
+/// Клон разделяет маску с оригиналом — смена фильтра сразу видна задаче отправки.
+/// Heartbeat доставляется всегда: по нему клиент проверяет соединение.
+#[derive(Debug, Clone)]
+pub struct EventFilter(Arc<AtomicU32>);
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU32::new(ALL_EVENT_TYPES)))
+    }
+
+    pub fn allows(&self, event: &WebSocketEvent) -> bool {
+        matches!(event, WebSocketEvent::Heartbeat { .. })
+            || self.0.load(Ordering::Relaxed) & (1 << event.type_index()) != 0
+    }
+
+    /// Заменяет фильтр; `None` — снова все события. При неизвестном типе фильтр не меняется.
+    pub fn set(&self, event_types: Option<&[String]>) -> Result<(), AppError> {
+        let mask = match event_types {
+            None => ALL_EVENT_TYPES,
+            Some(event_types) => {
+                let mut mask = 0;
+                let mut unknown = Vec::new();
+                for event_type in event_types {
+                    match WebSocketEvent::TYPE_NAMES.iter().position(|name| name == event_type) {
+                        Some(index) => mask |= 1 << index,
+                        None => unknown.push(event_type.as_str()),
+                    }
+                }
+                if !unknown.is_empty() {
+                    return Err(AppError::BadRequest(format!("Unknown event types: {}", unknown.join(", "))));
+                }
+                mask
+            }
+        };
+
+        self.0.store(mask, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Разрешенные типы событий; `None` — фильтра нет
+    pub fn event_types(&self) -> Option<Vec<&'static str>> {
+        let mask = self.0.load(Ordering::Relaxed);
+        if mask == ALL_EVENT_TYPES {
+            return None;
+        }
+        Some(
+            WebSocketEvent::TYPE_NAMES
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| mask & (1 << index) != 0)
+                .map(|(_, name)| *name)
+                .collect(),
+        )
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serialize for EventFilter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.event_types().serialize(serializer)
+    }
+}
+
+/// Close-код, с которым закрывается самое старое соединение при превышении лимита на пользователя
+pub const CLOSE_CODE_CONNECTION_LIMIT: u16 = 4008;
+/// Close-код соединения, сессия которого отозвана (DELETE /auth/sessions)
+pub const CLOSE_CODE_SESSION_REVOKED: u16 = 4001;
+
+/// Почему менеджер закрывает соединение
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Вытеснено более новым подключением того же пользователя
+    ConnectionLimit,
+    SessionRevoked,
+}
+
+impl DisconnectReason {
+    pub fn close_code(&self) -> u16 {
+        match self {
+            DisconnectReason::ConnectionLimit => CLOSE_CODE_CONNECTION_LIMIT,
+            DisconnectReason::SessionRevoked => CLOSE_CODE_SESSION_REVOKED,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisconnectReason::ConnectionLimit => "connection_limit_exceeded",
+            DisconnectReason::SessionRevoked => "session_revoked",
+        }
+    }
+}
+
+/// Заголовок системного уведомления «печатает...»
+pub(crate) const TYPING_TITLE: &str = "Typing";
+
+/// Размеры буферов событий
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RealtimeBuffers {
+    /// Общий broadcast-канал; отставшее на столько событий соединение их пропускает
+    pub global: usize,
+    /// Канал группы (`create_channel`)
+    pub channel: usize,
+    /// Очередь исходящих событий одного соединения
+    pub outgoing_queue: usize,
+}
+
+impl Default for RealtimeBuffers {
+    fn default() -> Self {
+        Self {
+            global: 1000,
+            channel: 100,
+            outgoing_queue: 256,
+        }
+    }
+}
+
+/// Лимиты одновременных WebSocket-подключений
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ConnectionLimits {
+    pub max_per_user: usize,
+    pub max_global: usize,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_per_user: 5,
+            max_global: 10_000,
+        }
+    }
+}
+
+/// Зарегистрированное соединение: данные клиента + сигнал принудительного закрытия
+struct ClientConnection {
+    info: ConnectedClient,
+    /// Порядковый номер подключения: вытесняются меньшие, даже если `connected_at` совпадает
+    sequence: u64,
+    evict_sender: oneshot::Sender<DisconnectReason>,
+    queue: Arc<OutgoingQueue>,
+}
+
+/// Результат регистрации нового соединения
+pub struct ClientRegistration {
+    pub connection_id: Uuid,
+    pub receiver: broadcast::Receiver<WebSocketEvent>,
+    /// Тот же фильтр, что в записи о клиенте
+    pub event_filter: EventFilter,
+    /// Срабатывает, если менеджер закрывает соединение: вытеснено или сессия отозвана
+    pub evicted: oneshot::Receiver<DisconnectReason>,
+    /// Очередь между `receiver` и сокетом
+    pub queue: Arc<OutgoingQueue>,
+    pub locale: Locale,
 }
 
 /// WebSocket сообщение от клиента
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
-    Subscribe { channels: Vec<String> },
+    Subscribe {
+        channels: Vec<String>,
+        /// Версия схемы событий, которую понимает клиент; без нее — текущая
+        #[serde(default)]
+        version: Option<u32>,
+    },
     Unsubscribe { channels: Vec<String> },
     Heartbeat,
+    /// Оставляет соединению только перечисленные типы событий (`type` события);
+    /// `null` — снова все. Действует сразу, в том числе на уже отправленные в очередь события.
+    SetFilters { events: Option<Vec<String>> },
     TypingStart { post_id: Uuid },
     TypingStop { post_id: Uuid },
 }
@@ -110,66 +480,186 @@ pub enum ClientMessage {
 pub struct WebSocketManager {
     /// Глобальный канал для рассылки всем подключенным клиентам
     global_sender: broadcast::Sender<WebSocketEvent>,
-    /// Клиенты, подключенные к WebSocket
-    clients: Arc<RwLock<HashMap<Uuid, ConnectedClient>>>,
+    /// Подключенные соединения по connection_id
+    clients: Arc<RwLock<HashMap<Uuid, ClientConnection>>>,
     /// Каналы для групповых уведомлений (например, подписчики пользователя)
     channels: Arc<RwLock<HashMap<String, broadcast::Sender<WebSocketEvent>>>>,
+    limits: ConnectionLimits,
+    buffers: RealtimeBuffers,
+    metrics: Arc<BackpressureMetrics>,
+    /// Счетчик подключений для порядка вытеснения
+    next_sequence: AtomicU64,
+    /// Время событий и heartbeat соединений
+    clock: SharedClock,
 }
 
 impl WebSocketManager {
     pub fn new() -> Self {
-        let (global_sender, _) = broadcast::channel(1000);
+        Self::with_limits(ConnectionLimits::default())
+    }
+
+    pub fn with_limits(limits: ConnectionLimits) -> Self {
+        let buffers = RealtimeBuffers::default();
+        let (global_sender, _) = broadcast::channel(buffers.global);
         
         Self {
             global_sender,
             clients: Arc::new(RwLock::new(HashMap::new())),
             channels: Arc::new(RwLock::new(HashMap::new())),
+            limits,
+            buffers,
+            metrics: Arc::new(BackpressureMetrics::default()),
+            next_sequence: AtomicU64::new(0),
+            clock: clock::system(),
         }
     }
 
-    /// Добавляет нового клиента
-    pub async fn add_client(&self, user_id: Uuid, user_name: String) -> broadcast::Receiver<WebSocketEvent> {
-        let client = ConnectedClient {
-            user_id,
-            user_name: user_name.clone(),
-            connected_at: Utc::now(),
-            last_heartbeat: Utc::now(),
-        };
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Размеры буферов; задаются до первого подключения — общий канал создается заново
+    pub fn with_buffers(mut self, buffers: RealtimeBuffers) -> Self {
+        let (global_sender, _) = broadcast::channel(buffers.global.max(1));
+        self.global_sender = global_sender;
+        self.buffers = buffers;
+        self
+    }
+
+    pub fn limits(&self) -> ConnectionLimits {
+        self.limits
+    }
+
+    pub fn buffers(&self) -> RealtimeBuffers {
+        self.buffers
+    }
+
+    pub fn metrics(&self) -> &BackpressureMetrics {
+        &self.metrics
+    }
+
+    /// Счетчики медленных клиентов и текущая глубина очередей
+    pub async fn backpressure_stats(&self) -> BackpressureStats {
+        let depths: Vec<usize> = self.clients.read().await.values().map(|connection| connection.queue.depth()).collect();
+        self.metrics.snapshot(&depths)
+    }
 
-        self.clients.write().await.insert(user_id, client);
+    /// Добавляет новое соединение.
+    /// При превышении лимита на пользователя закрывается его самое старое соединение,
+    /// при превышении глобального лимита новое соединение отклоняется (503).
+    pub async fn add_client(&self, user_id: Uuid, session_id: Option<Uuid>, user_name: String, locale: Locale) -> Result<ClientRegistration, AppError> {
+        let mut clients = self.clients.write().await;
+
+        if clients.len() >= self.limits.max_global {
+            warn!("WebSocket global connection limit reached ({}), rejecting {}", self.limits.max_global, user_id);
+            return Err(AppError::ExternalService("Too many realtime connections, try again later".to_string()));
+        }
+
+        let mut user_connections: Vec<(Uuid, u64)> = clients
+            .values()
+            .filter(|connection| connection.info.user_id == user_id)
+            .map(|connection| (connection.info.connection_id, connection.sequence))
+            .collect();
+        user_connections.sort_by_key(|(_, sequence)| *sequence);
+
+        // Освобождаем место под новое соединение, начиная с самых старых
+        let excess = (user_connections.len() + 1).saturating_sub(self.limits.max_per_user);
+        for (connection_id, _) in user_connections.into_iter().take(excess) {
+            if let Some(connection) = clients.remove(&connection_id) {
+                warn!("WebSocket per-user limit reached for {}, evicting connection {}", user_id, connection_id);
+                let _ = connection.evict_sender.send(DisconnectReason::ConnectionLimit);
+            }
+        }
+
+        let connection_id = Uuid::new_v4();
+        let (evict_sender, evicted) = oneshot::channel();
+        let now = self.clock.now();
+        let event_filter = EventFilter::new();
+        let queue = Arc::new(OutgoingQueue::new(self.buffers.outgoing_queue));
+
+        clients.insert(connection_id, ClientConnection {
+            info: ConnectedClient {
+                connection_id,
+                user_id,
+                session_id,
+                user_name: user_name.clone(),
+                connected_at: now,
+                last_heartbeat: now,
+                event_filter: event_filter.clone(),
+            },
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            evict_sender,
+            queue: queue.clone(),
+        });
+        drop(clients);
         
-        info!("WebSocket client connected: {} ({})", user_name, user_id);
+        info!("WebSocket client connected: {} ({}, connection {})", user_name, user_id, connection_id);
         
         // Отправляем приветственное сообщение
         let welcome_event = WebSocketEvent::SystemNotification {
-            title: "Добро пожаловать!".to_string(),
-            message: "Вы подключились к real-time уведомлениям IT Cook".to_string(),
+            title: i18n::t(locale, "realtime.welcome.title").to_string(),
+            message: i18n::t(locale, "realtime.welcome.message").to_string(),
             level: NotificationLevel::Success,
         };
         
         let _ = self.global_sender.send(welcome_event);
         
-        self.global_sender.subscribe()
+        Ok(ClientRegistration {
+            connection_id,
+            receiver: self.global_sender.subscribe(),
+            event_filter,
+            evicted,
+            queue,
+            locale,
+        })
     }
 
-    /// Удаляет клиента
-    pub async fn remove_client(&self, user_id: Uuid) {
-        if let Some(client) = self.clients.write().await.remove(&user_id) {
-            info!("WebSocket client disconnected: {} ({})", client.user_name, user_id);
+    /// Удаляет одно соединение (остальные устройства пользователя остаются подключенными)
+    pub async fn remove_client(&self, connection_id: Uuid) {
+        if let Some(connection) = self.clients.write().await.remove(&connection_id) {
+            info!(
+                "WebSocket client disconnected: {} ({}, connection {})",
+                connection.info.user_name, connection.info.user_id, connection_id
+            );
         }
     }
 
-    /// Обновляет heartbeat клиента
-    pub async fn update_heartbeat(&self, user_id: Uuid) {
-        if let Some(client) = self.clients.write().await.get_mut(&user_id) {
-            client.last_heartbeat = Utc::now();
+    /// Закрывает соединения отозванных сессий. Возвращает число закрытых соединений.
+    pub async fn disconnect_sessions(&self, session_ids: &[Uuid]) -> usize {
+        let mut clients = self.clients.write().await;
+        let revoked: Vec<Uuid> = clients
+            .values()
+            .filter(|connection| connection.info.session_id.is_some_and(|session_id| session_ids.contains(&session_id)))
+            .map(|connection| connection.info.connection_id)
+            .collect();
+
+        for connection_id in &revoked {
+            if let Some(connection) = clients.remove(connection_id) {
+                info!(
+                    "Closing WebSocket connection {} of {}: session revoked",
+                    connection_id, connection.info.user_id
+                );
+                let _ = connection.evict_sender.send(DisconnectReason::SessionRevoked);
+            }
+        }
+
+        revoked.len()
+    }
+
+    /// Обновляет heartbeat соединения
+    pub async fn update_heartbeat(&self, connection_id: Uuid) {
+        if let Some(connection) = self.clients.write().await.get_mut(&connection_id) {
+            connection.info.last_heartbeat = self.clock.now();
         }
     }
 
     /// Отправляет событие всем подключенным клиентам
+    #[instrument(skip_all, fields(event_type = event.type_name(), receivers = tracing::field::Empty))]
     pub async fn broadcast_global(&self, event: WebSocketEvent) -> Result<(), AppError> {
         match self.global_sender.send(event.clone()) {
             Ok(receiver_count) => {
+                tracing::Span::current().record("receivers", receiver_count);
                 info!("Broadcasted event to {} clients: {:?}", receiver_count, event);
                 Ok(())
             }
@@ -188,12 +678,14 @@ impl WebSocketManager {
     }
 
     /// Отправляет событие группе пользователей (например, подписчикам)
+    #[instrument(skip_all, fields(channel = channel_name, event_type = event.type_name(), receivers = tracing::field::Empty))]
     pub async fn send_to_channel(&self, channel_name: &str, event: WebSocketEvent) -> Result<(), AppError> {
         let channels = self.channels.read().await;
         
         if let Some(sender) = channels.get(channel_name) {
             match sender.send(event.clone()) {
                 Ok(receiver_count) => {
+                    tracing::Span::current().record("receivers", receiver_count);
                     info!("Sent event to channel '{}' ({} subscribers): {:?}", channel_name, receiver_count, event);
                     Ok(())
                 }
@@ -210,7 +702,7 @@ impl WebSocketManager {
 
     /// Создает новый канал для группы
     pub async fn create_channel(&self, channel_name: String) -> broadcast::Receiver<WebSocketEvent> {
-        let (sender, receiver) = broadcast::channel(100);
+        let (sender, receiver) = broadcast::channel(self.buffers.channel.max(1));
         self.channels.write().await.insert(channel_name.clone(), sender);
         info!("Created WebSocket channel: {}", channel_name);
         receiver
@@ -221,26 +713,38 @@ impl WebSocketManager {
         self.clients.read().await.len()
     }
 
+    /// Количество соединений по каждому пользователю
+    pub async fn connections_per_user(&self) -> HashMap<Uuid, usize> {
+        let mut counts = HashMap::new();
+        for connection in self.clients.read().await.values() {
+            *counts.entry(connection.info.user_id).or_insert(0) += 1;
+        }
+        counts
+    }
+
     /// Возвращает список подключенных клиентов
     pub async fn get_clients(&self) -> Vec<ConnectedClient> {
-        self.clients.read().await.values().cloned().collect()
+        self.clients.read().await.values().map(|connection| connection.info.clone()).collect()
     }
 
     /// Очищает неактивные соединения (heartbeat старше 30 секунд)
     pub async fn cleanup_inactive_clients(&self) {
-        let now = Utc::now();
+        let now = self.clock.now();
         let timeout = chrono::Duration::seconds(30);
         
         let mut clients = self.clients.write().await;
-        let inactive_clients: Vec<Uuid> = clients
+        let inactive_connections: Vec<Uuid> = clients
             .iter()
-            .filter(|(_, client)| now.signed_duration_since(client.last_heartbeat) > timeout)
-            .map(|(user_id, _)| *user_id)
+            .filter(|(_, connection)| now.signed_duration_since(connection.info.last_heartbeat) > timeout)
+            .map(|(connection_id, _)| *connection_id)
             .collect();
 
-        for user_id in inactive_clients {
-            if let Some(client) = clients.remove(&user_id) {
-                warn!("Removed inactive WebSocket client: {} ({})", client.user_name, user_id);
+        for connection_id in inactive_connections {
+            if let Some(connection) = clients.remove(&connection_id) {
+                warn!(
+                    "Removed inactive WebSocket client: {} ({}, connection {})",
+                    connection.info.user_name, connection.info.user_id, connection_id
+                );
             }
         }
     }
@@ -256,22 +760,81 @@ impl Default for WebSocketManager {
 pub async fn handle_websocket(
     socket: WebSocket,
     claims: Claims,
+    registration: ClientRegistration,
     ws_manager: Arc<WebSocketManager>,
 ) {
     let user_id = claims.sub;
     let user_name = format!("{} {}", claims.first_name, claims.last_name);
-    
-    // Регистрируем клиента и получаем receiver для событий
-    let mut receiver = ws_manager.add_client(user_id, user_name.clone()).await;
+    let ClientRegistration { connection_id, mut receiver, event_filter, mut evicted, queue, locale } = registration;
     
     // Разделяем WebSocket на отправку и получение
     let (mut sender, mut recv) = socket.split();
+
+    // Версия схемы событий для этого соединения; клиент может понизить ее в Subscribe
+    let schema_version = Arc::new(AtomicU32::new(EVENT_SCHEMA_VERSION));
+    let send_version = schema_version.clone();
+    let send_filter = event_filter.clone();
+
+    // Перенос событий из общего канала в очередь соединения: не ждет сокет,
+    // поэтому медленный клиент не отстает от канала, пока очередь не переполнится
+    let pump_queue = queue.clone();
+    let pump_filter = event_filter.clone();
+    let pump_manager = ws_manager.clone();
+    let pump_task = tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if !pump_filter.allows(&event) {
+                        continue;
+                    }
+                    let outcome = pump_queue.push(event);
+                    pump_manager.metrics().record_push(outcome, pump_queue.depth());
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("WebSocket connection {} lagged behind, {} events skipped", connection_id, skipped);
+                    pump_manager.metrics().record_lagged(skipped);
+                    pump_queue.record_skipped(skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
     
     // Задача для отправки событий клиенту
+    let send_manager = ws_manager.clone();
     let send_task = tokio::spawn(async move {
-        while let Ok(event) = receiver.recv().await {
-            let message = match serde_json::to_string(&event) {
-                Ok(json) => Message::Text(json.into()),
+        loop {
+            let event = tokio::select! {
+                outgoing = queue.next() => match outgoing {
+                    Outgoing::Event(event) => event,
+                    Outgoing::Resync { skipped } => {
+                        send_manager.metrics().record_resync();
+                        resync_notification(locale, skipped)
+                    }
+                },
+                signal = &mut evicted => {
+                    // Ok — менеджер закрывает соединение (вытеснено или сессия отозвана),
+                    // Err — соединение уже удалено из менеджера (например, по таймауту heartbeat)
+                    if let Ok(reason) = signal {
+                        let _ = sender.send(Message::Close(Some(CloseFrame {
+                            code: reason.close_code(),
+                            reason: reason.as_str().into(),
+                        }))).await;
+                    }
+                    break;
+                }
+            };
+
+            // Персональные и общие события идут через один канал — фильтр проверяется здесь
+            // еще раз: он мог смениться, пока событие ждало в очереди
+            if !send_filter.allows(&event) {
+                continue;
+            }
+
+            let message = match realtime_schema::encode_event(&event, send_version.load(Ordering::Relaxed)) {
+                Ok(Some(json)) => Message::Text(json),
+                // В старой версии схемы такое событие не выразить
+                Ok(None) => continue,
                 Err(e) => {
                     error!("Failed to serialize WebSocket event: {}", e);
                     continue;
@@ -295,20 +858,36 @@ pub async fn handle_websocket(
                     if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
                         match client_msg {
                             ClientMessage::Heartbeat => {
-                                ws_manager_recv.update_heartbeat(user_id).await;
+                                ws_manager_recv.update_heartbeat(connection_id).await;
                             }
-                            ClientMessage::Subscribe { channels } => {
+                            ClientMessage::Subscribe { channels, version } => {
                                 info!("Client {} subscribed to channels: {:?}", user_name, channels);
+                                if let Some(version) = version {
+                                    if realtime_schema::is_supported(version) {
+                                        schema_version.store(version, Ordering::Relaxed);
+                                    } else {
+                                        warn!(
+                                            "Client {} requested unsupported event schema version {}, keeping {}",
+                                            user_name, version, schema_version.load(Ordering::Relaxed)
+                                        );
+                                    }
+                                }
                                 // Здесь можно реализовать подписку на каналы
                             }
                             ClientMessage::Unsubscribe { channels } => {
                                 info!("Client {} unsubscribed from channels: {:?}", user_name, channels);
                                 // Здесь можно реализовать отписку от каналов
                             }
+                            ClientMessage::SetFilters { events } => {
+                                match event_filter.set(events.as_deref()) {
+                                    Ok(()) => info!("Client {} set event filter: {:?}", user_name, event_filter.event_types()),
+                                    Err(e) => warn!("Client {} sent invalid event filter, keeping previous: {}", user_name, e),
+                                }
+                            }
                             ClientMessage::TypingStart { post_id: _ } => {
                                 // Уведомляем других пользователей что кто-то печатает
                                 let typing_event = WebSocketEvent::SystemNotification {
-                                    title: "Typing".to_string(),
+                                    title: TYPING_TITLE.to_string(),
                                     message: format!("{} печатает...", user_name),
                                     level: NotificationLevel::Info,
                                 };
@@ -325,7 +904,7 @@ pub async fn handle_websocket(
                     break;
                 }
                 Ok(Message::Pong(_)) => {
-                    ws_manager_recv.update_heartbeat(user_id).await;
+                    ws_manager_recv.update_heartbeat(connection_id).await;
                 }
                 Err(e) => {
                     error!("WebSocket error: {}", e);
@@ -346,8 +925,18 @@ pub async fn handle_websocket(
         }
     }
     
-    // Убираем клиента из списка подключенных
-    ws_manager.remove_client(user_id).await;
+    pump_task.abort();
+    // Убираем только это соединение из списка подключенных
+    ws_manager.remove_client(connection_id).await;
+}
+
+/// Просьба перезапросить данные после пропущенных событий; личные уведомления — во входящих
+fn resync_notification(locale: Locale, skipped: u64) -> WebSocketEvent {
+    WebSocketEvent::SystemNotification {
+        title: i18n::t(locale, "realtime.resync.title").to_string(),
+        message: i18n::tf(locale, "realtime.resync.message", &[("count", &skipped.to_string())]),
+        level: NotificationLevel::Warning,
+    }
 }
 
 /// Сервис для интеграции с другими частями приложения
@@ -366,7 +955,7 @@ impl RealtimeService {
             post_id,
             author_name,
             content,
-            timestamp: Utc::now(),
+            timestamp: self.ws_manager.clock.now(),
         };
         self.ws_manager.broadcast_global(event).await
     }
@@ -381,15 +970,9 @@ impl RealtimeService {
         self.ws_manager.broadcast_global(event).await
     }
 
-    /// Уведомляет о скоропортящихся продуктах
-    pub async fn notify_expiring_items(&self, user_id: Uuid, items: Vec<ExpiringItem>) -> Result<(), AppError> {
-        if items.is_empty() {
-            return Ok(());
-        }
-
-        let days_left = items.iter().map(|item| item.days_left).min().unwrap_or(0);
-        let event = WebSocketEvent::ExpiringItems { items, days_left };
-        
+    /// Отправляет событие пользователю. Персональные уведомления с настройками
+    /// (сроки продуктов, цели, проверка холодильника) идут через `NotificationDelivery`.
+    pub async fn send_to_user(&self, user_id: Uuid, event: WebSocketEvent) -> Result<(), AppError> {
         self.ws_manager.send_to_user(user_id, event).await
     }
 
@@ -422,6 +1005,33 @@ impl RealtimeService {
         self.ws_manager.send_to_user(user_id, event).await
     }
 
+    /// Уведомляет автора о том, что его рецепт сохранили себе
+    pub async fn notify_recipe_forked(&self, user_id: Uuid, recipe_id: Uuid, title: String, forked_by_name: String, forks_count: i32) -> Result<(), AppError> {
+        let event = WebSocketEvent::RecipeForked {
+            recipe_id,
+            title,
+            forked_by_name,
+            forks_count: forks_count.max(0) as u32,
+        };
+        self.ws_manager.send_to_user(user_id, event).await
+    }
+
+    /// Рассылает новый статус активности подписчикам пользователя
+    pub async fn notify_friend_activity(&self, follower_ids: &[Uuid], status: &FriendActivityStatus) -> Result<(), AppError> {
+        let event = WebSocketEvent::FriendActivity {
+            user_id: status.user_id,
+            user_name: format!("{} {}", status.first_name, status.last_name),
+            activity: status.activity,
+            recipe_id: status.recipe_id,
+            recipe_name: status.recipe_name.clone(),
+            expires_at: status.expires_at,
+        };
+        for follower_id in follower_ids {
+            self.ws_manager.send_to_user(*follower_id, event.clone()).await?;
+        }
+        Ok(())
+    }
+
     /// Отправляет системное уведомление
     pub async fn send_system_notification(&self, title: String, message: String, level: NotificationLevel) -> Result<(), AppError> {
         let event = WebSocketEvent::SystemNotification {
@@ -432,14 +1042,14 @@ impl RealtimeService {
         self.ws_manager.broadcast_global(event).await
     }
 
-    /// Запускает периодическую очистку неактивных соединений
-    pub fn start_cleanup_task(&self) {
+    /// Регистрирует периодическую очистку неактивных соединений в супервизоре фоновых задач
+    pub fn register_cleanup_task(&self, supervisor: &TaskSupervisor) {
         let ws_manager = self.ws_manager.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
-            loop {
-                interval.tick().await;
+        supervisor.register_task("websocket_cleanup", tokio::time::Duration::from_secs(60), move || {
+            let ws_manager = ws_manager.clone();
+            async move {
                 ws_manager.cleanup_inactive_clients().await;
+                Ok(())
             }
         });
     }
@@ -447,15 +1057,22 @@ impl RealtimeService {
     /// Отправляет heartbeat всем клиентам
     pub async fn send_heartbeat(&self) -> Result<(), AppError> {
         let event = WebSocketEvent::Heartbeat {
-            timestamp: Utc::now(),
+            timestamp: self.ws_manager.clock.now(),
         };
         self.ws_manager.broadcast_global(event).await
     }
 
     /// Возвращает статистику подключений
     pub async fn get_stats(&self) -> RealtimeStats {
+        let connections_per_user = self.ws_manager.connections_per_user().await;
+
         RealtimeStats {
             connected_clients: self.ws_manager.client_count().await,
+            connected_users: connections_per_user.len(),
+            limits: self.ws_manager.limits(),
+            buffers: self.ws_manager.buffers(),
+            backpressure: self.ws_manager.backpressure_stats().await,
+            connections_per_user,
             clients: self.ws_manager.get_clients().await,
         }
     }
@@ -464,5 +1081,220 @@ impl RealtimeService {
 #[derive(Debug, Serialize)]
 pub struct RealtimeStats {
     pub connected_clients: usize,
+    pub connected_users: usize,
+    pub limits: ConnectionLimits,
+    pub buffers: RealtimeBuffers,
+    pub backpressure: BackpressureStats,
+    pub connections_per_user: HashMap<Uuid, usize>,
     pub clients: Vec<ConnectedClient>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use axum::response::IntoResponse;
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_support::frozen_clock;
+
+    /// Часы заморожены: все подключения получают одинаковый `connected_at`
+    fn manager(max_per_user: usize, max_global: usize) -> Arc<WebSocketManager> {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        Arc::new(WebSocketManager::with_limits(ConnectionLimits { max_per_user, max_global }).with_clock(frozen_clock(now)))
+    }
+
+    async fn connect(manager: &WebSocketManager, user_id: Uuid) -> ClientRegistration {
+        manager.add_client(user_id, None, "Тест".to_string(), Locale::Ru).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn per_user_limit_evicts_oldest_connections_first() {
+        let manager = manager(5, 10_000);
+        let user_id = Uuid::new_v4();
+        let other_user = Uuid::new_v4();
+
+        let mut other = connect(&manager, other_user).await;
+        let mut registrations = Vec::new();
+        for _ in 0..50 {
+            registrations.push(connect(&manager, user_id).await);
+        }
+
+        // Вытеснены ровно первые 45, каждое — с кодом лимита
+        let (evicted, kept) = registrations.split_at_mut(45);
+        for registration in evicted.iter_mut() {
+            let reason = registration.evicted.try_recv().expect("oldest connection is evicted");
+            assert_eq!(reason, DisconnectReason::ConnectionLimit);
+            assert_eq!(reason.close_code(), CLOSE_CODE_CONNECTION_LIMIT);
+        }
+        for registration in kept.iter_mut() {
+            assert!(registration.evicted.try_recv().is_err());
+        }
+
+        let mut remaining: Vec<Uuid> = manager
+            .get_clients()
+            .await
+            .into_iter()
+            .filter(|client| client.user_id == user_id)
+            .map(|client| client.connection_id)
+            .collect();
+        let mut expected: Vec<Uuid> = kept.iter().map(|registration| registration.connection_id).collect();
+        remaining.sort();
+        expected.sort();
+        assert_eq!(remaining, expected);
+
+        // Соединение другого пользователя не тронуто
+        assert!(other.evicted.try_recv().is_err());
+        let counts = manager.connections_per_user().await;
+        assert_eq!((counts[&user_id], counts[&other_user]), (5, 1));
+    }
+
+    #[tokio::test]
+    async fn concurrent_reconnect_storm_stays_within_the_per_user_limit() {
+        let manager = manager(5, 10_000);
+        let user_id = Uuid::new_v4();
+
+        let tasks: Vec<_> = (0..200)
+            .map(|_| {
+                let manager = manager.clone();
+                tokio::spawn(async move { connect(&manager, user_id).await })
+            })
+            .collect();
+        let mut registrations = Vec::new();
+        for task in tasks {
+            registrations.push(task.await.unwrap());
+        }
+
+        let survivors: Vec<Uuid> = manager.get_clients().await.into_iter().map(|client| client.connection_id).collect();
+        assert_eq!(survivors.len(), 5);
+
+        let mut evicted = 0;
+        for registration in &mut registrations {
+            match registration.evicted.try_recv() {
+                Ok(reason) => {
+                    assert_eq!(reason.close_code(), CLOSE_CODE_CONNECTION_LIMIT);
+                    assert!(!survivors.contains(&registration.connection_id));
+                    evicted += 1;
+                }
+                Err(_) => assert!(survivors.contains(&registration.connection_id)),
+            }
+        }
+        assert_eq!(evicted, 195);
+    }
+
+    #[tokio::test]
+    async fn global_limit_rejects_new_connections_with_503() {
+        let manager = manager(5, 10);
+        let mut registrations = Vec::new();
+        for _ in 0..10 {
+            registrations.push(connect(&manager, Uuid::new_v4()).await);
+        }
+
+        let rejected = manager
+            .add_client(Uuid::new_v4(), None, "Тест".to_string(), Locale::Ru)
+            .await
+            .err()
+            .expect("global limit reached");
+        assert_eq!(rejected.into_response().status(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        // Существующие соединения не вытесняются
+        for registration in &mut registrations {
+            assert!(registration.evicted.try_recv().is_err());
+        }
+
+        manager.remove_client(registrations[0].connection_id).await;
+        connect(&manager, Uuid::new_v4()).await;
+        assert_eq!(manager.client_count().await, 10);
+    }
+
+    #[tokio::test]
+    async fn removing_one_connection_keeps_the_users_other_devices() {
+        let manager = manager(5, 10_000);
+        let user_id = Uuid::new_v4();
+        let phone = connect(&manager, user_id).await;
+        let laptop = connect(&manager, user_id).await;
+
+        manager.remove_client(phone.connection_id).await;
+
+        let clients = manager.get_clients().await;
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].connection_id, laptop.connection_id);
+
+        let stats = RealtimeService::new(manager.clone()).get_stats().await;
+        assert_eq!((stats.connected_clients, stats.connected_users), (1, 1));
+        assert_eq!((stats.limits.max_per_user, stats.limits.max_global), (5, 10_000));
+    }
+
+    type ClientSocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    /// Настоящее WebSocket-соединение с сервером `address` от имени владельца `token`
+    async fn open_socket(address: std::net::SocketAddr, token: &str) -> ClientSocket {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let mut request = format!("ws://{}/api/v1/realtime/ws", address).into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert(axum::http::header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        tokio_tungstenite::connect_async(request).await.unwrap().0
+    }
+
+    /// Отправляет SetFilters и ждет, пока менеджер увидит новый фильтр соединения.
+    /// `events` — в порядке `WebSocketEvent::TYPE_NAMES`, как их возвращает `event_types`.
+    async fn set_filters(manager: &WebSocketManager, socket: &mut ClientSocket, events: &[&str]) {
+        let message = serde_json::json!({ "type": "SetFilters", "events": events });
+        socket
+            .send(tokio_tungstenite::tungstenite::Message::Text(message.to_string()))
+            .await
+            .unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !manager.get_clients().await.iter().any(|client| client.event_filter.event_types().as_deref() == Some(events)) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("filter is applied");
+    }
+
+    /// Типы полученных событий до `last` включительно
+    async fn received_types(socket: &mut ClientSocket, last: &str) -> Vec<String> {
+        let mut types = Vec::new();
+        while types.last().map(String::as_str) != Some(last) {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+                .await
+                .expect("event arrives")
+                .unwrap()
+                .unwrap();
+            let tokio_tungstenite::tungstenite::Message::Text(text) = message else { continue };
+            let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+            types.push(event["type"].as_str().unwrap().to_string());
+        }
+        types
+    }
+
+    #[sqlx::test]
+    async fn connections_of_one_user_receive_only_their_filtered_events(pool: sqlx::PgPool) {
+        let user_id = crate::test_support::insert_user(&pool, "Анна").await;
+        let token = crate::test_support::access_token(&pool, user_id).await;
+        let mut config = crate::config::Config::new().unwrap();
+        config.sandbox_clock = false;
+        let state = crate::app::AppState::new(config, crate::db::DbPools::single(pool.clone()));
+        let (manager, realtime) = (state.ws_manager.clone(), state.realtime_service.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(crate::app::build_router(state).into_make_service()));
+
+        // Фильтр телефона ставится до подключения планшета, поэтому приветствие планшета до телефона не доходит
+        let mut phone = open_socket(address, &token).await;
+        set_filters(&manager, &mut phone, &["GoalAchieved", "NewFollower"]).await;
+        let mut tablet = open_socket(address, &token).await;
+        set_filters(&manager, &mut tablet, &["NewFollower", "RecipeGenerated"]).await;
+        assert_eq!(manager.connections_per_user().await[&user_id], 2);
+
+        realtime.notify_goal_achieved(user_id, Uuid::new_v4(), "10 000 шагов".to_string()).await.unwrap();
+        realtime.notify_recipe_generated(user_id, Uuid::new_v4(), "Сырники".to_string(), 5).await.unwrap();
+        realtime.send_system_notification("Обслуживание".to_string(), "Сегодня ночью".to_string(), NotificationLevel::Info).await.unwrap();
+        realtime.notify_new_follower(user_id, Uuid::new_v4(), "Борис".to_string()).await.unwrap();
+
+        assert_eq!(received_types(&mut phone, "NewFollower").await, vec!["GoalAchieved", "NewFollower"]);
+        assert_eq!(received_types(&mut tablet, "NewFollower").await, vec!["RecipeGenerated", "NewFollower"]);
+    }
+}
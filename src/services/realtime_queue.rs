@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::services::realtime::WebSocketEvent;
+
+/// Что случилось с событием при постановке в очередь
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// Очередь полна: вытеснено самое старое необязательное событие
+    DroppedOldest,
+    /// Очередь полна, а само событие необязательное — отброшено
+    Dropped,
+    /// Очередь полна обязательными событиями: событие не отправится,
+    /// клиент получит просьбу перезапросить данные
+    Skipped,
+}
+
+/// Следующее, что нужно отправить клиенту
+#[derive(Debug)]
+pub enum Outgoing {
+    Event(WebSocketEvent),
+    /// Столько событий не дошло — клиенту пора перезапросить данные
+    Resync { skipped: u64 },
+}
+
+struct QueueState {
+    events: VecDeque<WebSocketEvent>,
+    skipped: u64,
+}
+
+/// Очередь исходящих событий одного соединения, между общим broadcast-каналом и сокетом.
+/// Медленный клиент копит события здесь, а не в общем канале. При переполнении вытесняются
+/// необязательные события (набор текста, присутствие); обязательные не вытесняются никогда:
+/// если места нет, событие пропускается и считается, а клиент получит просьбу перезапросить
+/// данные — личные уведомления к этому времени уже лежат в inbox.
+pub struct OutgoingQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+    capacity: usize,
+}
+
+impl OutgoingQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState { events: VecDeque::new(), skipped: 0 }),
+            notify: Notify::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&self, event: WebSocketEvent) -> PushOutcome {
+        let mut state = self.state.lock().unwrap();
+        let outcome = if state.events.len() < self.capacity {
+            PushOutcome::Queued
+        } else if let Some(index) = state.events.iter().position(WebSocketEvent::is_droppable) {
+            state.events.remove(index);
+            PushOutcome::DroppedOldest
+        } else if event.is_droppable() {
+            return PushOutcome::Dropped;
+        } else {
+            state.skipped += 1;
+            drop(state);
+            self.notify.notify_one();
+            return PushOutcome::Skipped;
+        };
+
+        state.events.push_back(event);
+        drop(state);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// События, пропущенные до очереди (отставание от broadcast-канала)
+    pub fn record_skipped(&self, count: u64) {
+        self.state.lock().unwrap().skipped += count;
+        self.notify.notify_one();
+    }
+
+    /// Ждет следующее событие. Просьба перезапросить данные идет раньше событий из очереди:
+    /// клиент, который успел отстать, первым делом узнает об этом.
+    pub async fn next(&self) -> Outgoing {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.skipped > 0 {
+                    return Outgoing::Resync { skipped: std::mem::take(&mut state.skipped) };
+                }
+                if let Some(event) = state.events.pop_front() {
+                    return Outgoing::Event(event);
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Сколько событий ждет отправки
+    pub fn depth(&self) -> usize {
+        self.state.lock().unwrap().events.len()
+    }
+}
+
+/// Счетчики медленных клиентов с запуска процесса
+#[derive(Debug, Default)]
+pub struct BackpressureMetrics {
+    lagged_events: AtomicU64,
+    dropped_events: AtomicU64,
+    skipped_events: AtomicU64,
+    resyncs_sent: AtomicU64,
+    max_queue_depth: AtomicUsize,
+}
+
+impl BackpressureMetrics {
+    /// Соединение отстало от broadcast-канала на `count` событий
+    pub fn record_lagged(&self, count: u64) {
+        self.lagged_events.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_push(&self, outcome: PushOutcome, queue_depth: usize) {
+        match outcome {
+            PushOutcome::Queued => {}
+            PushOutcome::DroppedOldest | PushOutcome::Dropped => {
+                self.dropped_events.fetch_add(1, Ordering::Relaxed);
+            }
+            PushOutcome::Skipped => {
+                self.skipped_events.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.max_queue_depth.fetch_max(queue_depth, Ordering::Relaxed);
+    }
+
+    pub fn record_resync(&self) {
+        self.resyncs_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, queue_depths: &[usize]) -> BackpressureStats {
+        BackpressureStats {
+            lagged_events: self.lagged_events.load(Ordering::Relaxed),
+            dropped_low_priority_events: self.dropped_events.load(Ordering::Relaxed),
+            skipped_events: self.skipped_events.load(Ordering::Relaxed),
+            resyncs_sent: self.resyncs_sent.load(Ordering::Relaxed),
+            queued_events: queue_depths.iter().sum(),
+            current_max_queue_depth: queue_depths.iter().copied().max().unwrap_or(0),
+            peak_queue_depth: self.max_queue_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackpressureStats {
+    /// Событий, которые соединения пропустили, отстав от общего канала
+    pub lagged_events: u64,
+    /// Необязательных событий (набор текста, присутствие), вытесненных из очередей
+    pub dropped_low_priority_events: u64,
+    /// Обязательных событий, не поместившихся в очередь; клиенту ушла просьба перезапросить данные
+    pub skipped_events: u64,
+    pub resyncs_sent: u64,
+    /// Сейчас в очередях всех соединений
+    pub queued_events: usize,
+    pub current_max_queue_depth: usize,
+    /// Наибольшая глубина очереди с запуска
+    pub peak_queue_depth: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::services::realtime::NotificationLevel;
+
+    fn typing() -> WebSocketEvent {
+        WebSocketEvent::SystemNotification {
+            title: crate::services::realtime::TYPING_TITLE.to_string(),
+            message: "Анна печатает".to_string(),
+            level: NotificationLevel::Info,
+        }
+    }
+
+    fn goal(title: &str) -> WebSocketEvent {
+        WebSocketEvent::GoalAchieved { goal_id: Uuid::nil(), title: title.to_string(), achievement_type: "goal_completed".to_string() }
+    }
+
+    fn title(outgoing: Outgoing) -> String {
+        match outgoing {
+            Outgoing::Event(WebSocketEvent::GoalAchieved { title, .. }) => title,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn droppable_events_make_room_and_important_ones_are_never_evicted() {
+        let queue = OutgoingQueue::new(2);
+        assert_eq!(queue.push(typing()), PushOutcome::Queued);
+        assert_eq!(queue.push(goal("первая")), PushOutcome::Queued);
+        assert_eq!(queue.push(goal("вторая")), PushOutcome::DroppedOldest);
+        assert_eq!(queue.push(typing()), PushOutcome::Dropped);
+        assert_eq!(queue.push(goal("третья")), PushOutcome::Skipped);
+        assert_eq!(queue.depth(), 2);
+
+        // Сначала просьба перезапросить данные, затем то, что успело встать в очередь
+        assert!(matches!(queue.next().await, Outgoing::Resync { skipped: 1 }));
+        assert_eq!(title(queue.next().await), "первая");
+        assert_eq!(title(queue.next().await), "вторая");
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn lag_is_reported_once_before_queued_events() {
+        let queue = Arc::new(OutgoingQueue::new(4));
+        queue.push(goal("после отставания"));
+        queue.record_skipped(5);
+        queue.record_skipped(2);
+
+        assert!(matches!(queue.next().await, Outgoing::Resync { skipped: 7 }));
+        assert_eq!(title(queue.next().await), "после отставания");
+
+        // Пустая очередь ждет следующего события
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { title(queue.next().await) }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        queue.push(goal("новое"));
+        assert_eq!(waiting.await.unwrap(), "новое");
+    }
+
+    #[sqlx::test]
+    async fn slow_consumer_keeps_its_connection_and_is_told_to_resync(pool: sqlx::PgPool) {
+        use crate::models::notification::NotificationEvent;
+        use crate::services::notification_delivery::{NotificationDelivery, OutgoingNotification};
+        use crate::services::notifications::NotificationService;
+        use crate::utils::{format::Locale, i18n};
+
+        let user_id = crate::test_support::insert_user(&pool, "Анна").await;
+        let token = crate::test_support::access_token(&pool, user_id).await;
+        let mut config = crate::config::Config::new().unwrap();
+        config.sandbox_clock = false;
+        config.ws_outgoing_queue = 4;
+        let state = crate::app::AppState::new(config, crate::db::DbPools::single(pool.clone()));
+        let (manager, realtime) = (state.ws_manager.clone(), state.realtime_service.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(crate::app::build_router(state).into_make_service()));
+
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let mut request = format!("ws://{}/api/v1/realtime/ws", address).into_client_request().unwrap();
+        request.headers_mut().insert(axum::http::header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+        // Клиент не читает: сокет забивается, затем очередь соединения, затем события пропускаются
+        let news = "Новости ".repeat(8 * 1024);
+        let mut sent = 0;
+        while manager.metrics().snapshot(&[]).skipped_events + manager.metrics().snapshot(&[]).lagged_events == 0 {
+            assert!(sent < 5_000, "queue never overflowed");
+            realtime.send_system_notification("Новости".to_string(), news.clone(), NotificationLevel::Info).await.unwrap();
+            sent += 1;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // Личное уведомление тоже не помещается в очередь, но уже лежит во входящих
+        let notification = OutgoingNotification {
+            kind: "goal_achieved",
+            title: "Цель достигнута".to_string(),
+            message: "10 000 шагов".to_string(),
+            data: serde_json::json!({}),
+            event: goal("10 000 шагов"),
+        };
+        NotificationDelivery::new(pool.clone(), realtime.clone())
+            .send(user_id, NotificationEvent::GoalReminders, notification, chrono::Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(NotificationService::new(pool.clone()).unread_count(user_id).await.unwrap(), 1);
+        assert_eq!(manager.get_clients().await.len(), 1, "slow client is not disconnected");
+
+        // Клиент снова читает: получает накопленное и просьбу перезапросить данные
+        let resync_title = i18n::t(Locale::Ru, "realtime.resync.title");
+        let mut resync = None;
+        while let Ok(Some(message)) = tokio::time::timeout(Duration::from_millis(500), socket.next()).await {
+            let Message::Text(text) = message.unwrap() else { continue };
+            let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if event["data"]["title"] == resync_title {
+                resync = Some(event["data"]["message"].as_str().unwrap().to_string());
+            }
+        }
+        let resync = resync.expect("resync notification after resuming");
+        let skipped = manager.metrics().snapshot(&[]);
+        assert!(resync.contains(&(skipped.skipped_events + skipped.lagged_events).to_string()), "{}", resync);
+        assert!(skipped.resyncs_sent >= 1);
+
+        // Соединение живо: новые события доходят
+        realtime.send_system_notification("Маркер".to_string(), "после паузы".to_string(), NotificationLevel::Info).await.unwrap();
+        let marker = loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), socket.next()).await.expect("event arrives").unwrap().unwrap();
+            let Message::Text(text) = message else { continue };
+            let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if event["data"]["title"] == "Маркер" {
+                break event;
+            }
+        };
+        assert_eq!(marker["data"]["message"], "после паузы");
+        socket.send(Message::Close(None)).await.unwrap();
+    }
+}
@@ -0,0 +1,480 @@
+use once_cell::sync::Lazy;
+use schemars::{schema::RootSchema, schema_for};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::services::realtime::{ClientMessage, WebSocketEvent};
+
+/// Текущая версия формы WebSocket-событий; передается в поле `v` каждого события
+pub const EVENT_SCHEMA_VERSION: u32 = 2;
+/// Предыдущая версия: события без поля `v`, в ExpiringItems нет просроченных продуктов,
+/// `urgency` и `expires_at`
+pub const PREVIOUS_EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Поле версии в конверте события
+const VERSION_FIELD: &str = "v";
+
+static CURRENT_EVENT_SCHEMA: Lazy<Value> = Lazy::new(current_event_schema);
+static PREVIOUS_EVENT_SCHEMA: Lazy<Value> = Lazy::new(previous_event_schema);
+
+pub fn is_supported(version: u32) -> bool {
+    version == EVENT_SCHEMA_VERSION || version == PREVIOUS_EVENT_SCHEMA_VERSION
+}
+
+/// Каталог схем для контрактных тестов фронтенда
+#[derive(Debug, Serialize)]
+pub struct SchemaCatalog {
+    pub current_version: u32,
+    pub versions: Vec<VersionedSchema>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionedSchema {
+    pub version: u32,
+    /// JSON Schema сообщения сервера целиком, вместе с полем версии
+    pub events: Value,
+    pub client_messages: Value,
+}
+
+/// Схемы текущей и предыдущей версии. Сообщения клиента в обеих версиях одинаковы:
+/// `version` в Subscribe необязателен.
+pub fn catalog() -> SchemaCatalog {
+    let client_messages = schema_value(schema_for!(ClientMessage));
+
+    SchemaCatalog {
+        current_version: EVENT_SCHEMA_VERSION,
+        versions: vec![
+            VersionedSchema {
+                version: EVENT_SCHEMA_VERSION,
+                events: CURRENT_EVENT_SCHEMA.clone(),
+                client_messages: client_messages.clone(),
+            },
+            VersionedSchema {
+                version: PREVIOUS_EVENT_SCHEMA_VERSION,
+                events: PREVIOUS_EVENT_SCHEMA.clone(),
+                client_messages,
+            },
+        ],
+    }
+}
+
+/// Событие в JSON для клиента, который понимает версию `version`. `None` — событие
+/// нельзя выразить в этой версии, и клиенту оно не отправляется.
+/// В отладочной сборке паникует, если результат не проходит собственную опубликованную схему.
+pub fn encode_event(event: &WebSocketEvent, version: u32) -> serde_json::Result<Option<String>> {
+    let (value, schema) = if version == PREVIOUS_EVENT_SCHEMA_VERSION {
+        match v1::downgrade(event)? {
+            Some(value) => (value, &*PREVIOUS_EVENT_SCHEMA),
+            None => return Ok(None),
+        }
+    } else {
+        let mut value = serde_json::to_value(event)?;
+        if let Value::Object(fields) = &mut value {
+            fields.insert(VERSION_FIELD.to_string(), json!(EVENT_SCHEMA_VERSION));
+        }
+        (value, &*CURRENT_EVENT_SCHEMA)
+    };
+
+    if cfg!(debug_assertions) {
+        if let Err(error) = validate(&value, schema, schema) {
+            panic!("WebSocket event does not match its published schema (v{}): {}", version, error);
+        }
+    }
+
+    serde_json::to_string(&value).map(Some)
+}
+
+fn schema_value(schema: RootSchema) -> Value {
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+fn variant_tag(variant: &Value) -> Option<&str> {
+    variant.pointer("/properties/type/enum/0").and_then(Value::as_str)
+}
+
+/// Схема событий, в каждом варианте которой обязательно поле версии
+fn current_event_schema() -> Value {
+    let mut schema = schema_value(schema_for!(WebSocketEvent));
+
+    let variants = schema.get_mut("oneOf").and_then(Value::as_array_mut).into_iter().flatten();
+    for variant in variants {
+        if let Some(properties) = variant.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert(
+                VERSION_FIELD.to_string(),
+                json!({ "type": "integer", "enum": [EVENT_SCHEMA_VERSION] }),
+            );
+        }
+        if let Some(required) = variant.get_mut("required").and_then(Value::as_array_mut) {
+            required.push(json!(VERSION_FIELD));
+        }
+    }
+
+    schema
+}
+
+/// Схема версии 1: текущие варианты без поля версии, измененные — в прежней форме
+fn previous_event_schema() -> Value {
+    let mut schema = schema_value(schema_for!(WebSocketEvent));
+    let legacy = schema_value(schema_for!(v1::LegacyEvent));
+
+    let legacy_variants = legacy.get("oneOf").and_then(Value::as_array).cloned().unwrap_or_default();
+    let replaced: Vec<&str> = legacy_variants.iter().filter_map(variant_tag).collect();
+
+    if let Some(variants) = schema.get_mut("oneOf").and_then(Value::as_array_mut) {
+        variants.retain(|variant| !matches!(variant_tag(variant), Some(tag) if replaced.contains(&tag)));
+        variants.extend(legacy_variants.iter().cloned());
+    }
+
+    let legacy_definitions = legacy.get("definitions").and_then(Value::as_object);
+    if let (Some(definitions), Some(legacy_definitions)) =
+        (schema.get_mut("definitions").and_then(Value::as_object_mut), legacy_definitions)
+    {
+        for (name, definition) in legacy_definitions {
+            definitions.insert(name.clone(), definition.clone());
+        }
+    }
+
+    schema
+}
+
+/// Проверка значения по тому подмножеству JSON Schema, которое генерирует schemars:
+/// $ref, allOf/anyOf/oneOf, type, enum, minimum, properties, required, additionalProperties, items
+fn validate(value: &Value, schema: &Value, root: &Value) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return match schema.as_bool() {
+            Some(false) => Err("value is not allowed here".to_string()),
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        let target = root
+            .get("definitions")
+            .and_then(|definitions| definitions.get(name))
+            .ok_or_else(|| format!("unknown reference {}", reference))?;
+        validate(value, target, root)?;
+    }
+
+    if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+        for subschema in all_of {
+            validate(value, subschema, root)?;
+        }
+    }
+
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        if !any_of.iter().any(|subschema| validate(value, subschema, root).is_ok()) {
+            return Err(format!("{} matches none of the allowed forms", value));
+        }
+    }
+
+    if let Some(one_of) = schema.get("oneOf").and_then(Value::as_array) {
+        let matched = one_of.iter().filter(|subschema| validate(value, subschema, root).is_ok()).count();
+        if matched != 1 {
+            // Для тегированных перечислений понятнее ошибка варианта с тем же тегом
+            let tag = value.get("type").and_then(Value::as_str);
+            let variant_error = one_of
+                .iter()
+                .find(|subschema| tag.is_some() && variant_tag(subschema) == tag)
+                .and_then(|subschema| validate(value, subschema, root).err());
+            return Err(variant_error.unwrap_or_else(|| format!("{} matches {} variants instead of one", value, matched)));
+        }
+    }
+
+    if let Some(types) = schema.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|name| has_type(value, name)) {
+            return Err(format!("expected {}, got {}", allowed.join(" | "), value));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!("{} is not one of {:?}", value, options));
+        }
+    }
+
+    if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+        if number < minimum {
+            return Err(format!("{} is less than {}", number, minimum));
+        }
+    }
+
+    if let Some(fields) = value.as_object() {
+        for field in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains_key(field) {
+                return Err(format!("missing field '{}'", field));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+        for (name, field) in fields {
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(field_schema) => validate(field, field_schema, root).map_err(|error| format!("{}: {}", name, error))?,
+                None if closed => return Err(format!("unexpected field '{}'", name)),
+                None => {}
+            }
+        }
+    }
+
+    if let (Some(items), Some(elements)) = (schema.get("items"), value.as_array()) {
+        for (index, element) in elements.iter().enumerate() {
+            validate(element, items, root).map_err(|error| format!("[{}]: {}", index, error))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+/// Форма событий версии 1 там, где она отличается от текущей
+mod v1 {
+    use schemars::JsonSchema;
+    use serde::Serialize;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use crate::services::realtime::WebSocketEvent;
+
+    #[derive(Serialize, JsonSchema)]
+    #[serde(tag = "type", content = "data")]
+    pub enum LegacyEvent {
+        /// Уведомление о скоропортящихся продуктах; уже просроченные не передаются
+        ExpiringItems {
+            items: Vec<LegacyExpiringItem>,
+            days_left: u32,
+        },
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    pub struct LegacyExpiringItem {
+        pub id: Uuid,
+        pub name: String,
+        pub days_left: u32,
+    }
+
+    /// Событие в форме версии 1; `None`, если в нем остались только просроченные продукты
+    pub fn downgrade(event: &WebSocketEvent) -> serde_json::Result<Option<Value>> {
+        match event {
+            WebSocketEvent::ExpiringItems { items, .. } => {
+                let items: Vec<LegacyExpiringItem> = items
+                    .iter()
+                    .filter_map(|item| {
+                        u32::try_from(item.days_left).ok().map(|days_left| LegacyExpiringItem {
+                            id: item.id,
+                            name: item.name.clone(),
+                            days_left,
+                        })
+                    })
+                    .collect();
+                let Some(days_left) = items.iter().map(|item| item.days_left).min() else {
+                    return Ok(None);
+                };
+                serde_json::to_value(LegacyEvent::ExpiringItems { items, days_left }).map(Some)
+            }
+            event => serde_json::to_value(event).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        models::{community::ActivityKind, fridge::ExpiryUrgency},
+        services::realtime::{ExpiringItem, NotificationLevel},
+    };
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 20).unwrap()
+    }
+
+    fn expiring(name: &str, days_left: i32, urgency: ExpiryUrgency) -> ExpiringItem {
+        ExpiringItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            days_left,
+            urgency,
+            expires_at: now() + Duration::days(days_left as i64),
+        }
+    }
+
+    /// По событию каждого типа, в порядке `WebSocketEvent::TYPE_NAMES`
+    fn one_of_each() -> Vec<WebSocketEvent> {
+        let id = Uuid::new_v4();
+        vec![
+            WebSocketEvent::NewCommunityPost { post_id: id, author_name: "Анна".to_string(), content: "Привет".to_string(), timestamp: now() },
+            WebSocketEvent::PostLiked { post_id: id, liker_name: "Борис".to_string(), total_likes: 3 },
+            WebSocketEvent::NewComment { post_id: id, comment_id: id, author_name: "Борис".to_string(), content: "Класс".to_string() },
+            WebSocketEvent::PostCooked { post_id: id, cook_name: "Борис".to_string(), rating: None, cooked_count: 1 },
+            WebSocketEvent::expiring_items(vec![expiring("Кефир", -1, ExpiryUrgency::Expired), expiring("Молоко", 2, ExpiryUrgency::Soon)]),
+            WebSocketEvent::FridgeItemUseReminder { item_id: id, name: "Фарш".to_string(), use_by: date(), recipe_id: Some(id), recipe_name: None },
+            WebSocketEvent::FridgeCheckinDue { items_count: 12, stale_items_count: 3 },
+            WebSocketEvent::MealOutsideEatingWindow { entry_id: id, consumed_at: now(), window_opens_at: None },
+            WebSocketEvent::DiaryReminder { local_date: date(), streak_days: 5, deep_link: "/diary".to_string() },
+            WebSocketEvent::NutritionBackfillCompleted { job_id: id, filled_from_database: 10, filled_by_ai: 2, unresolved: 0 },
+            WebSocketEvent::GoalAchieved { goal_id: id, title: "10 000 шагов".to_string(), achievement_type: "daily".to_string() },
+            WebSocketEvent::GoalDeadlineApproaching { goal_id: id, title: "Минус 3 кг".to_string(), target_date: date(), days_left: 4, progress_percentage: 62.5, is_on_track: true },
+            WebSocketEvent::GoalExpired { goal_id: id, title: "Минус 3 кг".to_string(), target_date: date(), progress_percentage: 40.0 },
+            WebSocketEvent::GoalReviewStarted { conversation_id: id, week_start: date(), message: "Как прошла неделя?".to_string() },
+            WebSocketEvent::NewFollower { follower_id: id, follower_name: "Борис".to_string() },
+            WebSocketEvent::RecipeGenerated { recipe_id: id, title: "Борщ".to_string(), ingredients_count: 9 },
+            WebSocketEvent::RecipeForked { recipe_id: id, title: "Борщ".to_string(), forked_by_name: "Борис".to_string(), forks_count: 2 },
+            WebSocketEvent::FriendActivity { user_id: id, user_name: "Борис".to_string(), activity: ActivityKind::Cooking, recipe_id: None, recipe_name: None, expires_at: now() },
+            WebSocketEvent::SystemNotification { title: "Обновление".to_string(), message: "Скоро".to_string(), level: NotificationLevel::Info },
+            WebSocketEvent::Heartbeat { timestamp: now() },
+        ]
+    }
+
+    fn encode(event: &WebSocketEvent, version: u32) -> Option<Value> {
+        encode_event(event, version).unwrap().map(|json| serde_json::from_str(&json).unwrap())
+    }
+
+    fn snapshot_path(version: u32) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("fixtures/realtime_schema/v{}.json", version))
+    }
+
+    /// Опубликованная схема версии не меняется. Изменили форму события — поднимите
+    /// `EVENT_SCHEMA_VERSION` и сохраните снимок новой версии: UPDATE_SCHEMA_SNAPSHOTS=1 cargo test
+    #[test]
+    fn published_schemas_match_their_snapshots() {
+        let catalog = catalog();
+        assert_eq!(catalog.current_version, EVENT_SCHEMA_VERSION);
+        let versions: Vec<u32> = catalog.versions.iter().map(|schema| schema.version).collect();
+        assert_eq!(versions, vec![EVENT_SCHEMA_VERSION, PREVIOUS_EVENT_SCHEMA_VERSION]);
+
+        for schema in &catalog.versions {
+            let published = json!({ "events": schema.events, "client_messages": schema.client_messages });
+            let path = snapshot_path(schema.version);
+            if std::env::var_os("UPDATE_SCHEMA_SNAPSHOTS").is_some() && !path.exists() {
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, serde_json::to_string_pretty(&published).unwrap() + "\n").unwrap();
+            }
+
+            let snapshot = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| panic!("no schema snapshot for v{}: run with UPDATE_SCHEMA_SNAPSHOTS=1", schema.version));
+            let snapshot: Value = serde_json::from_str(&snapshot).unwrap();
+            assert!(
+                snapshot == published,
+                "schema of v{} changed without a version bump: raise EVENT_SCHEMA_VERSION and snapshot the new version",
+                schema.version
+            );
+        }
+    }
+
+    #[test]
+    fn every_event_type_validates_against_its_schema_in_both_versions() {
+        let events = one_of_each();
+        let names: Vec<&str> = events.iter().map(WebSocketEvent::type_name).collect();
+        assert_eq!(names, WebSocketEvent::TYPE_NAMES.to_vec(), "add a sample of the new event type");
+
+        for event in &events {
+            let current = encode(event, EVENT_SCHEMA_VERSION).unwrap();
+            assert_eq!(current["v"], EVENT_SCHEMA_VERSION, "{}", event.type_name());
+            assert_eq!(validate(&current, &CURRENT_EVENT_SCHEMA, &CURRENT_EVENT_SCHEMA), Ok(()), "{}", event.type_name());
+
+            let previous = encode(event, PREVIOUS_EVENT_SCHEMA_VERSION).unwrap();
+            assert!(previous.get("v").is_none(), "{}", event.type_name());
+            assert_eq!(validate(&previous, &PREVIOUS_EVENT_SCHEMA, &PREVIOUS_EVENT_SCHEMA), Ok(()), "{}", event.type_name());
+        }
+    }
+
+    #[test]
+    fn previous_version_gets_the_old_expiring_items_shape() {
+        let event = WebSocketEvent::expiring_items(vec![
+            expiring("Кефир", -1, ExpiryUrgency::Expired),
+            expiring("Молоко", 0, ExpiryUrgency::Today),
+            expiring("Сметана", 2, ExpiryUrgency::Soon),
+        ]);
+
+        let current = encode(&event, EVENT_SCHEMA_VERSION).unwrap();
+        assert_eq!((current["data"]["days_left"].clone(), current["data"]["urgency"].clone()), (json!(-1), json!("expired")));
+        assert_eq!(current["data"]["items"].as_array().unwrap().len(), 3);
+
+        let previous = encode(&event, PREVIOUS_EVENT_SCHEMA_VERSION).unwrap();
+        assert_eq!(previous["data"]["days_left"], 0);
+        assert!(previous["data"].get("urgency").is_none());
+        let items = previous["data"]["items"].as_array().unwrap();
+        let names: Vec<&str> = items.iter().map(|item| item["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Молоко", "Сметана"]);
+        assert!(items.iter().all(|item| item.get("expires_at").is_none()));
+
+        // Только просроченные — старому клиенту нечего отправить
+        let expired_only = WebSocketEvent::expiring_items(vec![expiring("Кефир", -2, ExpiryUrgency::Expired)]);
+        assert_eq!(encode(&expired_only, PREVIOUS_EVENT_SCHEMA_VERSION), None);
+        assert!(encode(&expired_only, EVENT_SCHEMA_VERSION).is_some());
+    }
+
+    #[test]
+    fn payload_outside_the_published_schema_is_rejected() {
+        let valid = encode(&WebSocketEvent::PostLiked { post_id: Uuid::new_v4(), liker_name: "Борис".to_string(), total_likes: 3 }, EVENT_SCHEMA_VERSION).unwrap();
+        let check = |value: &Value| validate(value, &CURRENT_EVENT_SCHEMA, &CURRENT_EVENT_SCHEMA);
+        assert_eq!(check(&valid), Ok(()));
+
+        let mut wrong_type = valid.clone();
+        wrong_type["data"]["total_likes"] = json!("три");
+        let mut negative = valid.clone();
+        negative["data"]["total_likes"] = json!(-1);
+        let mut missing_field = valid.clone();
+        missing_field["data"].as_object_mut().unwrap().remove("liker_name");
+        let mut no_version = valid.clone();
+        no_version.as_object_mut().unwrap().remove("v");
+        let mut old_version = valid.clone();
+        old_version["v"] = json!(PREVIOUS_EVENT_SCHEMA_VERSION);
+        let mut unknown_type = valid.clone();
+        unknown_type["type"] = json!("PostDisliked");
+
+        for invalid in [wrong_type, negative, missing_field, no_version, old_version, unknown_type] {
+            assert!(check(&invalid).is_err(), "{}", invalid);
+        }
+
+        // Старый клиент не получает поля, которых нет в его версии
+        let mut legacy = encode(&WebSocketEvent::expiring_items(vec![expiring("Молоко", 1, ExpiryUrgency::Soon)]), PREVIOUS_EVENT_SCHEMA_VERSION).unwrap();
+        assert_eq!(validate(&legacy, &PREVIOUS_EVENT_SCHEMA, &PREVIOUS_EVENT_SCHEMA), Ok(()));
+        legacy["data"]["days_left"] = json!(-1);
+        assert!(validate(&legacy, &PREVIOUS_EVENT_SCHEMA, &PREVIOUS_EVENT_SCHEMA).is_err());
+    }
+
+    #[test]
+    fn both_versions_accept_the_same_client_messages() {
+        let schema = schema_value(schema_for!(ClientMessage));
+        let messages = [
+            json!({ "type": "Subscribe", "channels": ["fridge"] }),
+            json!({ "type": "Subscribe", "channels": ["fridge"], "version": 1 }),
+            json!({ "type": "SetFilters", "events": null }),
+            json!({ "type": "Heartbeat" }),
+        ];
+        for message in &messages {
+            assert_eq!(validate(message, &schema, &schema), Ok(()), "{}", message);
+            serde_json::from_value::<ClientMessage>(message.clone()).unwrap();
+        }
+        assert!(validate(&json!({ "type": "Subscribe" }), &schema, &schema).is_err());
+        assert!(is_supported(1) && is_supported(2) && !is_supported(3) && !is_supported(0));
+    }
+}
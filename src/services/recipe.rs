@@ -1,12 +1,38 @@
 use uuid::Uuid;
-use chrono::Utc;
+use std::collections::HashSet;
 use std::fmt;
+use sqlx::{Postgres, Transaction};
 use crate::{
-    models::recipe::{CreateRecipe, RecipeCategory, DifficultyLevel},
-    api::recipes::{RecipeResponse, RecipeIngredientResponse, NutritionInfoResponse, CreateRecipeIngredientRequest, NutritionInfoRequest},
+    db::{DbPools, ReadConsistency},
+    models::recipe::{
+        instructions_text, CreateRecipe, Recipe, RecipeIngredient, RecipeCategory, RecipeStep, RecipeStepInput,
+        DifficultyLevel,
+    },
+    api::recipes::{
+        gallery_responses, step_responses, RecipeResponse, RecipeIngredientResponse, NutritionInfoResponse,
+        CreateRecipeIngredientRequest, NutritionInfoRequest, RecipeAttribution, RecipeForksResponse,
+    },
+    services::{
+        clock::{self, SharedClock},
+        link_previews::LinkPreviewService,
+        media::MediaLibrary,
+        recipe_tags::{normalize_tags, replace_recipe_tags},
+    },
     utils::errors::AppError,
 };
 
+/// Свои рецепты (и форки) для ленты активности (колонки — см. `services::timeline`)
+pub const TIMELINE_CREATED_SQL: &str = r#"
+    SELECT 'recipe_created'::text AS kind, created_at AS occurred_at, id AS event_id, id AS reference_id,
+           name AS title,
+           jsonb_build_object(
+               'category', category, 'difficulty', difficulty, 'is_public', is_public,
+               'forked', original_recipe_id IS NOT NULL
+           ) AS summary
+    FROM recipes
+    WHERE created_by = $1
+"#;
+
 // Display implementations for enums
 impl fmt::Display for RecipeCategory {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -33,146 +59,297 @@ impl fmt::Display for DifficultyLevel {
     }
 }
 
+/// Самая длинная страница списков рецептов
+const MAX_PAGE_SIZE: i64 = 100;
+/// Сколько рецептов в подборке популярных
+const POPULAR_LIMIT: i64 = 10;
+
 pub struct RecipeService {
-    pool: crate::db::DbPool,
+    pools: DbPools,
+    clock: SharedClock,
 }
 
 impl RecipeService {
     pub fn new(pool: crate::db::DbPool) -> Self {
-        Self { pool }
+        Self::with_pools(DbPools::single(pool))
+    }
+
+    /// Чтения, которым не нужна только что сделанная запись, идут в реплику
+    pub fn with_pools(pools: DbPools) -> Self {
+        Self { pools, clock: clock::system() }
     }
 
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Сохраняет рецепт вместе с ингредиентами, пищевой ценностью, шагами, галереей и тегами
     pub async fn create_recipe(
         &self, 
         recipe: CreateRecipe, 
         ingredients: Vec<CreateRecipeIngredientRequest>, 
         nutrition: Option<NutritionInfoRequest>
     ) -> Result<RecipeResponse, AppError> {
-        // Mock implementation - in production, this would use actual database operations
-        let recipe_id = Uuid::new_v4();
-        
-        Ok(RecipeResponse {
-            id: recipe_id,
-            name: recipe.name,
-            description: recipe.description,
-            category: recipe.category,
-            difficulty: recipe.difficulty,
-            prep_time_minutes: recipe.prep_time_minutes,
-            cook_time_minutes: recipe.cook_time_minutes,
-            total_time_minutes: match (recipe.prep_time_minutes, recipe.cook_time_minutes) {
-                (Some(prep), Some(cook)) => Some(prep + cook),
-                (Some(prep), None) => Some(prep),
-                (None, Some(cook)) => Some(cook),
-                (None, None) => None,
-            },
-            servings: recipe.servings,
-            instructions: recipe.instructions,
-            ingredients: ingredients.into_iter().map(|ing| RecipeIngredientResponse {
-                name: ing.name,
-                quantity: ing.quantity,
-                unit: ing.unit,
-                notes: ing.notes,
-            }).collect(),
-            tags: recipe.tags,
-            image_url: recipe.image_url,
-            source_url: recipe.source_url,
-            nutrition_per_serving: nutrition.map(|n| NutritionInfoResponse {
-                calories: n.calories,
-                protein: n.protein,
-                fat: n.fat,
-                carbs: n.carbs,
-                fiber: n.fiber,
-                sugar: n.sugar,
-                sodium: n.sodium,
-            }),
-            average_rating: Some(0.0),
-            ratings_count: 0,
-            is_favorite: false,
-            created_by: recipe.created_by,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        let ingredients = ingredient_responses(ingredients)?;
+        let tags = normalize_tags(&recipe.tags);
+        MediaLibrary::new(self.pools.primary().clone())
+            .owned_urls(recipe.created_by, &media_ids(&recipe.steps, &recipe.gallery))
+            .await?;
+
+        let mut tx = self.pools.primary().begin().await?;
+
+        let saved = sqlx::query_as::<_, Recipe>(
+            r#"
+            INSERT INTO recipes (
+                id, name, description, category, difficulty, prep_time_minutes, cook_time_minutes,
+                servings, instructions, tags, image_url, source_url, created_by, is_public, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $15)
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(&recipe.name)
+        .bind(&recipe.description)
+        .bind(&recipe.category)
+        .bind(&recipe.difficulty)
+        .bind(recipe.prep_time_minutes)
+        .bind(recipe.cook_time_minutes)
+        .bind(recipe.servings)
+        .bind(instructions_text(recipe.steps.iter().map(|step| step.text.as_str())))
+        .bind(&tags)
+        .bind(&recipe.image_url)
+        .bind(&recipe.source_url)
+        .bind(recipe.created_by)
+        .bind(recipe.is_public)
+        .bind(self.clock.now())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        insert_details(&mut tx, saved.id, &ingredients, nutrition.as_ref()).await?;
+        insert_steps(&mut tx, saved.id, &recipe.steps, &recipe.gallery).await?;
+        replace_recipe_tags(&mut tx, saved.id, &tags).await?;
+
+        tx.commit().await?;
+
+        // Рецепт только что записан — реплика могла его еще не получить
+        self.build_response(saved, ReadConsistency::AfterWrite).await
     }
 
+    /// Опубликованные и свои рецепты с фильтрами, новые — первыми. `search` — подстрока
+    /// названия или описания без учета регистра, `tags` — через запятую, нужны все.
+    /// Читается с реплики: только что созданный рецепт может появиться в списке с задержкой.
     pub async fn get_recipes(
         &self,
         user_id: Option<Uuid>,
-        _category: Option<RecipeCategory>,
-        _difficulty: Option<DifficultyLevel>,
-        _max_prep_time: Option<i32>,
-        _max_cook_time: Option<i32>,
-        _search: Option<String>,
-        _tags: Option<String>,
+        category: Option<RecipeCategory>,
+        difficulty: Option<DifficultyLevel>,
+        max_prep_time: Option<i32>,
+        max_cook_time: Option<i32>,
+        search: Option<String>,
+        tags: Option<String>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<RecipeResponse>, AppError> {
-        // Mock implementation - return sample recipes
-        self.get_mock_recipes(user_id, limit, offset).await
+        let pattern = search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(|search| format!("%{}%", search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+        let tags: Vec<String> = tags
+            .map(|tags| normalize_tags(&tags.split(',').map(str::to_string).collect::<Vec<_>>()))
+            .unwrap_or_default();
+
+        let recipes = sqlx::query_as::<_, Recipe>(
+            r#"
+            SELECT r.* FROM recipes r
+            WHERE (r.is_public OR r.created_by = $1)
+              AND ($2::recipe_category IS NULL OR r.category = $2)
+              AND ($3::difficulty_level IS NULL OR r.difficulty = $3)
+              AND ($4::INTEGER IS NULL OR r.prep_time_minutes <= $4)
+              AND ($5::INTEGER IS NULL OR r.cook_time_minutes <= $5)
+              AND ($6::TEXT IS NULL OR r.name ILIKE $6 OR r.description ILIKE $6)
+              AND (SELECT COUNT(*) FROM recipe_tags t WHERE t.recipe_id = r.id AND t.tag = ANY($7)) = cardinality($7::TEXT[])
+            ORDER BY r.created_at DESC, r.id
+            LIMIT $8 OFFSET $9
+            "#
+        )
+        .bind(user_id)
+        .bind(category)
+        .bind(difficulty)
+        .bind(max_prep_time)
+        .bind(max_cook_time)
+        .bind(pattern)
+        .bind(&tags)
+        .bind(limit.clamp(0, MAX_PAGE_SIZE))
+        .bind(offset.max(0))
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        self.build_responses(recipes).await
     }
 
     pub async fn get_recipe_by_id(&self, id: Uuid, user_id: Option<Uuid>) -> Result<RecipeResponse, AppError> {
-        // Mock implementation
-        self.get_mock_recipe(id, user_id).await
+        let recipe = sqlx::query_as::<_, Recipe>(
+            "SELECT * FROM recipes WHERE id = $1 AND (is_public OR created_by = $2)"
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(self.pools.read(ReadConsistency::Replica))
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+
+        self.build_response(recipe, ReadConsistency::Replica).await
     }
 
+    /// Заменяет поля, ингредиенты, шаги, галерею и теги своего рецепта.
+    /// Поля оригинала не редактируются: копия остается связанной с автором после любых правок.
+    /// Фото, на которые рецепт больше не ссылается, удаляются.
     pub async fn update_recipe(
         &self,
         id: Uuid,
         user_id: Uuid,
         payload: crate::api::recipes::CreateRecipeRequest,
     ) -> Result<RecipeResponse, AppError> {
-        // Mock implementation - in production, verify ownership and update database
-        Ok(RecipeResponse {
-            id,
-            name: payload.name,
-            description: payload.description,
-            category: payload.category,
-            difficulty: payload.difficulty,
-            prep_time_minutes: payload.prep_time_minutes,
-            cook_time_minutes: payload.cook_time_minutes,
-            total_time_minutes: match (payload.prep_time_minutes, payload.cook_time_minutes) {
-                (Some(prep), Some(cook)) => Some(prep + cook),
-                (Some(prep), None) => Some(prep),
-                (None, Some(cook)) => Some(cook),
-                (None, None) => None,
-            },
-            servings: payload.servings,
-            instructions: payload.instructions,
-            ingredients: payload.ingredients.into_iter().map(|ing| RecipeIngredientResponse {
-                name: ing.name,
-                quantity: ing.quantity,
-                unit: ing.unit,
-                notes: ing.notes,
-            }).collect(),
-            tags: payload.tags,
-            image_url: payload.image_url,
-            source_url: payload.source_url,
-            nutrition_per_serving: payload.nutrition_per_serving.map(|n| NutritionInfoResponse {
-                calories: n.calories,
-                protein: n.protein,
-                fat: n.fat,
-                carbs: n.carbs,
-                fiber: n.fiber,
-                sugar: n.sugar,
-                sodium: n.sodium,
-            }),
-            average_rating: Some(4.2),
-            ratings_count: 15,
-            is_favorite: true,
-            created_by: user_id,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+        let ingredients = ingredient_responses(payload.ingredients)?;
+        let steps = payload.instructions.into_steps()?;
+        let tags = normalize_tags(&payload.tags);
+        let media = MediaLibrary::new(self.pools.primary().clone());
+
+        let mut tx = self.pools.primary().begin().await?;
+
+        let created_by: Option<(Uuid,)> = sqlx::query_as("SELECT created_by FROM recipes WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match created_by {
+            None => return Err(AppError::NotFound("Recipe not found".to_string())),
+            Some((created_by,)) if created_by != user_id => {
+                return Err(AppError::Forbidden("Only the author can edit this recipe".to_string()));
+            }
+            Some(_) => {}
+        }
+
+        // Фото, которые рецепт уже показывает, остаются доступны при правке: у копии чужого
+        // рецепта это файлы автора оригинала. Новые фото должны быть загружены самим пользователем.
+        let previous_media = recipe_media_ids(&mut tx, id).await?;
+        let new_media: Vec<Uuid> = media_ids(&steps, &payload.gallery)
+            .into_iter()
+            .filter(|media_id| !previous_media.contains(media_id))
+            .collect();
+        media.owned_urls(user_id, &new_media).await?;
+
+        let recipe = sqlx::query_as::<_, Recipe>(
+            r#"
+            UPDATE recipes
+            SET name = $2, description = $3, category = $4, difficulty = $5, prep_time_minutes = $6,
+                cook_time_minutes = $7, servings = $8, instructions = $9, tags = $10, image_url = $11,
+                source_url = $12, is_public = $13, updated_at = $14
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(&payload.name)
+        .bind(&payload.description)
+        .bind(&payload.category)
+        .bind(&payload.difficulty)
+        .bind(payload.prep_time_minutes)
+        .bind(payload.cook_time_minutes)
+        .bind(payload.servings)
+        .bind(instructions_text(steps.iter().map(|step| step.text.as_str())))
+        .bind(&tags)
+        .bind(&payload.image_url)
+        .bind(&payload.source_url)
+        .bind(payload.is_public)
+        .bind(self.clock.now())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for table in ["recipe_ingredients", "recipe_nutrition", "recipe_steps", "recipe_media"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE recipe_id = $1", table))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        insert_details(&mut tx, id, &ingredients, payload.nutrition_per_serving.as_ref()).await?;
+        insert_steps(&mut tx, id, &steps, &payload.gallery).await?;
+        replace_recipe_tags(&mut tx, id, &tags).await?;
+
+        tx.commit().await?;
+
+        let current = media_ids(&steps, &payload.gallery);
+        let removed_media: Vec<Uuid> = previous_media.into_iter().filter(|media_id| !current.contains(media_id)).collect();
+        media.delete_unreferenced(&removed_media).await?;
+        LinkPreviewService::new(self.pools.primary().clone()).refresh_recipe(id).await?;
+
+        self.build_response(recipe, ReadConsistency::AfterWrite).await
     }
 
-    pub async fn delete_recipe(&self, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
-        // Mock implementation - in production, verify ownership and delete from database
+    /// Удаляет рецепт вместе с шагами и галереей; фото, на которые больше ничто не ссылается,
+    /// удаляются. Рецепта уже нет — ничего не делает.
+    pub async fn delete_recipe(&self, id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let created_by: Option<(Uuid,)> = sqlx::query_as("SELECT created_by FROM recipes WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match created_by {
+            None => return Ok(()),
+            Some((created_by,)) if created_by != user_id => {
+                return Err(AppError::Forbidden("Only the author can delete this recipe".to_string()));
+            }
+            Some(_) => {}
+        }
+
+        let media_ids = recipe_media_ids(&mut tx, id).await?;
+        sqlx::query("DELETE FROM recipes WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        MediaLibrary::new(self.pools.primary().clone()).delete_unreferenced(&media_ids).await?;
+        // Карточки рецепта в постах остаются с одним названием
+        LinkPreviewService::new(self.pools.primary().clone()).degrade_recipe(id).await?;
         Ok(())
     }
 
-    pub async fn toggle_favorite(&self, _recipe_id: Uuid, _user_id: Uuid) -> Result<bool, AppError> {
-        // Mock implementation - in production, check if already favorited and toggle
-        Ok(true) // Return true indicating it's now favorited
+    /// Добавляет видимый пользователю рецепт в избранное или убирает из него.
+    /// Возвращает, в избранном ли рецепт теперь.
+    pub async fn toggle_favorite(&self, recipe_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let visible: Option<(Uuid,)> = sqlx::query_as(
+            "SELECT id FROM recipes WHERE id = $1 AND (is_public OR created_by = $2)"
+        )
+        .bind(recipe_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if visible.is_none() {
+            return Err(AppError::NotFound("Recipe not found".to_string()));
+        }
+
+        let removed = sqlx::query("DELETE FROM recipe_favorites WHERE recipe_id = $1 AND user_id = $2")
+            .bind(recipe_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() > 0;
+        if !removed {
+            sqlx::query("INSERT INTO recipe_favorites (recipe_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                .bind(recipe_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(!removed)
     }
 
     pub async fn rate_recipe(
@@ -212,125 +389,778 @@ impl RecipeService {
         ).await
     }
 
+    /// Видимые пользователю рецепты, которые чаще всего добавляют в избранное и оценивают
     pub async fn get_popular_recipes(&self, user_id: Option<Uuid>) -> Result<Vec<RecipeResponse>, AppError> {
-        self.get_mock_recipes(user_id, 10, 0).await
+        let recipes = sqlx::query_as::<_, Recipe>(
+            r#"
+            SELECT r.* FROM recipes r
+            WHERE r.is_public OR r.created_by = $1
+            ORDER BY (SELECT COUNT(*) FROM recipe_favorites f WHERE f.recipe_id = r.id)
+                   + (SELECT COUNT(*) FROM recipe_ratings rt WHERE rt.recipe_id = r.id) DESC,
+                     r.created_at DESC, r.id
+            LIMIT $2
+            "#
+        )
+        .bind(user_id)
+        .bind(POPULAR_LIMIT)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        self.build_responses(recipes).await
     }
 
+    /// Избранное пользователя, последние добавленные — первыми. Рецепт, который автор
+    /// скрыл, из избранного не пропадает, но и не показывается.
     pub async fn get_favorite_recipes(&self, user_id: Uuid) -> Result<Vec<RecipeResponse>, AppError> {
-        self.get_mock_recipes(Some(user_id), 20, 0).await
+        let recipes = sqlx::query_as::<_, Recipe>(
+            r#"
+            SELECT r.* FROM recipes r
+            JOIN recipe_favorites f ON f.recipe_id = r.id AND f.user_id = $1
+            WHERE r.is_public OR r.created_by = $1
+            ORDER BY f.created_at DESC, r.id
+            LIMIT $2
+            "#
+        )
+        .bind(user_id)
+        .bind(MAX_PAGE_SIZE)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        let mut responses = self.build_responses(recipes).await?;
+        for recipe in responses.iter_mut() {
+            recipe.is_favorite = true;
+        }
+        Ok(responses)
+    }
+
+    /// Сохраняет копию чужого опубликованного рецепта в рецепты пользователя.
+    /// Возвращает копию и оригинал с обновленным счетчиком копий.
+    pub async fn fork_recipe(&self, id: Uuid, user_id: Uuid) -> Result<(RecipeResponse, Recipe), AppError> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let original = sqlx::query_as::<_, Recipe>("SELECT * FROM recipes WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+
+        if original.created_by == user_id {
+            return Err(AppError::BadRequest("Recipe already belongs to you".to_string()));
+        }
+        // Закрытые рецепты и рецепты, публикацию которых автор отменил, копировать нельзя
+        if !original.is_public {
+            return Err(AppError::Forbidden("Recipe is not shared".to_string()));
+        }
+
+        let fork_id = Uuid::new_v4();
+        let fork = sqlx::query_as::<_, Recipe>(
+            r#"
+            INSERT INTO recipes (
+                id, name, description, category, difficulty, prep_time_minutes, cook_time_minutes,
+                servings, instructions, tags, image_url, source_url, created_by,
+                is_public, original_recipe_id, original_author_id
+            )
+            SELECT $1, name, description, category, difficulty, prep_time_minutes, cook_time_minutes,
+                   servings, instructions, tags, image_url, source_url, $2,
+                   FALSE, id, created_by
+            FROM recipes WHERE id = $3
+            RETURNING *
+            "#
+        )
+        .bind(fork_id)
+        .bind(user_id)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit, notes, needs_review)
+            SELECT $1, name, quantity, unit, notes, needs_review FROM recipe_ingredients WHERE recipe_id = $2
+            "#
+        )
+        .bind(fork_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Фото остаются файлами автора оригинала: копия ссылается на них, и они не удаляются,
+        // пока на них ссылается хотя бы один рецепт
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_steps (recipe_id, position, text, media_id)
+            SELECT $1, position, text, media_id FROM recipe_steps WHERE recipe_id = $2
+            "#
+        )
+        .bind(fork_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_media (recipe_id, media_id, position)
+            SELECT $1, media_id, position FROM recipe_media WHERE recipe_id = $2
+            "#
+        )
+        .bind(fork_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO recipe_tags (recipe_id, tag) SELECT $1, tag FROM recipe_tags WHERE recipe_id = $2")
+            .bind(fork_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_nutrition (recipe_id, calories, protein, fat, carbs, fiber, sugar, sodium)
+            SELECT $1, calories, protein, fat, carbs, fiber, sugar, sodium FROM recipe_nutrition WHERE recipe_id = $2
+            "#
+        )
+        .bind(fork_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let original = sqlx::query_as::<_, Recipe>(
+            "UPDATE recipes SET forks_count = forks_count + 1 WHERE id = $1 RETURNING *"
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // Копия только что записана — реплика могла ее еще не получить
+        let response = self.build_response(fork, ReadConsistency::AfterWrite).await?;
+        Ok((response, original))
     }
 
-    // Mock implementations for testing without database
-    async fn get_mock_recipe(&self, id: Uuid, user_id: Option<Uuid>) -> Result<RecipeResponse, AppError> {
+    /// Сколько раз рецепт сохраняли себе — видно только автору
+    pub async fn get_forks_count(&self, id: Uuid, user_id: Uuid) -> Result<RecipeForksResponse, AppError> {
+        let (created_by, forks_count): (Uuid, i32) = sqlx::query_as(
+            "SELECT created_by, forks_count FROM recipes WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(self.pools.read(ReadConsistency::Replica))
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+
+        if created_by != user_id {
+            return Err(AppError::Forbidden("Only the author can see fork statistics".to_string()));
+        }
+
+        Ok(RecipeForksResponse { recipe_id: id, forks_count })
+    }
+
+    /// Ссылка на оригинал для копии чужого рецепта
+    async fn get_attribution(&self, id: Uuid, consistency: ReadConsistency) -> Result<Option<RecipeAttribution>, AppError> {
+        /// Оригинал, его автор, название оригинала, имя и фамилия автора
+        type AttributionRow = (Option<Uuid>, Option<Uuid>, Option<String>, Option<String>, Option<String>);
+
+        let row: Option<AttributionRow> = sqlx::query_as(
+            r#"
+            SELECT r.original_recipe_id, r.original_author_id, o.name, u.first_name, u.last_name
+            FROM recipes r
+            LEFT JOIN recipes o ON o.id = r.original_recipe_id
+            LEFT JOIN users u ON u.id = r.original_author_id
+            WHERE r.id = $1 AND (r.original_recipe_id IS NOT NULL OR r.original_author_id IS NOT NULL)
+            "#
+        )
+        .bind(id)
+        .fetch_optional(self.pools.read(consistency))
+        .await?;
+
+        Ok(row.map(|(original_recipe_id, original_author_id, original_name, first_name, last_name)| {
+            let original_author_name = match (first_name, last_name) {
+                (Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+                (Some(first), None) => Some(first),
+                _ => None,
+            };
+
+            let label = match (&original_name, &original_author_name) {
+                (Some(name), Some(author)) => format!("Адаптировано из «{}» ({})", name, author),
+                (None, Some(author)) => format!("Адаптировано из рецепта автора {}", author),
+                (Some(name), None) => format!("Адаптировано из «{}»", name),
+                (None, None) => "Адаптировано из рецепта другого автора".to_string(),
+            };
+
+            RecipeAttribution {
+                original_recipe_id,
+                original_author_id,
+                original_author_name,
+                label,
+            }
+        }))
+    }
+
+    /// Ответы для списка с реплики, в том же порядке
+    async fn build_responses(&self, recipes: Vec<Recipe>) -> Result<Vec<RecipeResponse>, AppError> {
+        let mut responses = Vec::with_capacity(recipes.len());
+        for recipe in recipes {
+            responses.push(self.build_response(recipe, ReadConsistency::Replica).await?);
+        }
+        Ok(responses)
+    }
+
+    async fn build_response(&self, recipe: Recipe, consistency: ReadConsistency) -> Result<RecipeResponse, AppError> {
+        let pool = self.pools.read(consistency);
+
+        let ingredients = sqlx::query_as::<_, RecipeIngredient>(
+            "SELECT * FROM recipe_ingredients WHERE recipe_id = $1"
+        )
+        .bind(recipe.id)
+        .fetch_all(pool)
+        .await?;
+
+        #[allow(clippy::type_complexity)]
+        let nutrition: Option<(Option<f32>, Option<f32>, Option<f32>, Option<f32>, Option<f32>, Option<f32>, Option<f32>)> = sqlx::query_as(
+            "SELECT calories, protein, fat, carbs, fiber, sugar, sodium FROM recipe_nutrition WHERE recipe_id = $1"
+        )
+        .bind(recipe.id)
+        .fetch_optional(pool)
+        .await?;
+
+        let steps = sqlx::query_as::<_, RecipeStep>(
+            "SELECT * FROM recipe_steps WHERE recipe_id = $1 ORDER BY position"
+        )
+        .bind(recipe.id)
+        .fetch_all(pool)
+        .await?;
+        let steps: Vec<RecipeStepInput> = steps
+            .into_iter()
+            .map(|step| RecipeStepInput { text: step.text, media_id: step.media_id })
+            .collect();
+
+        let gallery: Vec<(Uuid,)> = sqlx::query_as(
+            "SELECT media_id FROM recipe_media WHERE recipe_id = $1 ORDER BY position"
+        )
+        .bind(recipe.id)
+        .fetch_all(pool)
+        .await?;
+        let gallery: Vec<Uuid> = gallery.into_iter().map(|(media_id,)| media_id).collect();
+
+        let urls = MediaLibrary::new(pool.clone()).urls(&media_ids(&steps, &gallery)).await?;
+        let attribution = self.get_attribution(recipe.id, consistency).await?;
+
         Ok(RecipeResponse {
-            id,
-            name: "Mock Chicken Pasta".to_string(),
-            description: Some("Delicious pasta with chicken and vegetables".to_string()),
-            category: RecipeCategory::Dinner,
-            difficulty: DifficultyLevel::Medium,
-            prep_time_minutes: Some(20),
-            cook_time_minutes: Some(30),
-            total_time_minutes: Some(50),
-            servings: Some(4),
-            instructions: "1. Cook pasta\n2. Cook chicken\n3. Mix together".to_string(),
-            ingredients: vec![
-                RecipeIngredientResponse {
-                    name: "Pasta".to_string(),
-                    quantity: 300.0,
-                    unit: "g".to_string(),
-                    notes: None,
-                },
-                RecipeIngredientResponse {
-                    name: "Chicken breast".to_string(),
-                    quantity: 500.0,
-                    unit: "g".to_string(),
-                    notes: Some("Cut into pieces".to_string()),
-                },
-            ],
-            tags: vec!["pasta".to_string(), "chicken".to_string(), "easy".to_string()],
-            image_url: Some("https://example.com/image.jpg".to_string()),
-            source_url: None,
-            nutrition_per_serving: Some(NutritionInfoResponse {
-                calories: Some(450.0),
-                protein: Some(35.0),
-                fat: Some(12.0),
-                carbs: Some(55.0),
-                fiber: Some(3.0),
-                sugar: Some(5.0),
-                sodium: Some(800.0),
+            id: recipe.id,
+            name: recipe.name,
+            description: recipe.description,
+            category: recipe.category,
+            difficulty: recipe.difficulty,
+            prep_time_minutes: recipe.prep_time_minutes,
+            cook_time_minutes: recipe.cook_time_minutes,
+            total_time_minutes: match (recipe.prep_time_minutes, recipe.cook_time_minutes) {
+                (Some(prep), Some(cook)) => Some(prep + cook),
+                (Some(prep), None) => Some(prep),
+                (None, Some(cook)) => Some(cook),
+                (None, None) => None,
+            },
+            servings: recipe.servings,
+            instructions: recipe.instructions,
+            steps: step_responses(&steps, &urls),
+            gallery: gallery_responses(&gallery, &urls),
+            ingredients: ingredients.into_iter().map(RecipeIngredientResponse::from_row).collect(),
+            tags: recipe.tags,
+            image_url: recipe.image_url,
+            source_url: recipe.source_url,
+            nutrition_per_serving: nutrition.map(|(calories, protein, fat, carbs, fiber, sugar, sodium)| NutritionInfoResponse {
+                calories,
+                protein,
+                fat,
+                carbs,
+                fiber,
+                sugar,
+                sodium,
             }),
-            average_rating: Some(4.5),
-            ratings_count: 23,
-            is_favorite: user_id.is_some(),
-            created_by: user_id.unwrap_or_else(Uuid::new_v4),
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            average_rating: None,
+            ratings_count: 0,
+            is_favorite: false,
+            created_by: recipe.created_by,
+            created_at: recipe.created_at,
+            updated_at: recipe.updated_at,
+            availability: None,
+            attribution,
         })
     }
+}
 
-    async fn get_mock_recipes(&self, user_id: Option<Uuid>, limit: i64, offset: i64) -> Result<Vec<RecipeResponse>, AppError> {
-        let mut recipes = vec![];
-        
-        // Generate different mock recipes
-        for i in 0..std::cmp::min(limit, 10) {
-            let recipe_id = Uuid::new_v4();
-            let recipe = RecipeResponse {
-                id: recipe_id,
-                name: format!("Mock Recipe {}", i + 1),
-                description: Some(format!("Description for recipe {}", i + 1)),
-                category: match i % 4 {
-                    0 => RecipeCategory::Breakfast,
-                    1 => RecipeCategory::Lunch,
-                    2 => RecipeCategory::Dinner,
-                    _ => RecipeCategory::Snack,
-                },
-                difficulty: match i % 3 {
-                    0 => DifficultyLevel::Easy,
-                    1 => DifficultyLevel::Medium,
-                    _ => DifficultyLevel::Hard,
-                },
-                prep_time_minutes: Some(10 + (i as i32 * 5)),
-                cook_time_minutes: Some(20 + (i as i32 * 10)),
-                total_time_minutes: Some(30 + (i as i32 * 15)),
-                servings: Some(2 + (i as i32)),
-                instructions: format!("Instructions for recipe {}", i + 1),
-                ingredients: vec![
-                    RecipeIngredientResponse {
-                        name: format!("Ingredient {}", i + 1),
-                        quantity: 100.0 + (i as f32 * 50.0),
-                        unit: "g".to_string(),
-                        notes: None,
-                    },
-                ],
-                tags: vec![format!("tag{}", i + 1)],
-                image_url: Some(format!("https://example.com/image{}.jpg", i + 1)),
-                source_url: None,
-                nutrition_per_serving: Some(NutritionInfoResponse {
-                    calories: Some(300.0 + (i as f32 * 50.0)),
-                    protein: Some(20.0 + (i as f32 * 5.0)),
-                    fat: Some(10.0 + (i as f32 * 2.0)),
-                    carbs: Some(40.0 + (i as f32 * 10.0)),
-                    fiber: Some(5.0),
-                    sugar: Some(8.0),
-                    sodium: Some(600.0),
-                }),
-                average_rating: Some(3.0 + (i as f32 * 0.5)),
-                ratings_count: (i as i32 + 1) * 3,
-                is_favorite: i % 2 == 0,
-                created_by: user_id.unwrap_or_else(Uuid::new_v4),
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            };
-            recipes.push(recipe);
+/// Ингредиенты из запроса с проверенными количествами
+fn ingredient_responses(ingredients: Vec<CreateRecipeIngredientRequest>) -> Result<Vec<RecipeIngredientResponse>, AppError> {
+    ingredients
+        .into_iter()
+        .map(|ingredient| {
+            let amount = ingredient.parsed_amount()?;
+            Ok(RecipeIngredientResponse::new(ingredient.name, amount, ingredient.notes))
+        })
+        .collect()
+}
+
+/// Фото шагов и галереи без повторов
+fn media_ids(steps: &[RecipeStepInput], gallery: &[Uuid]) -> Vec<Uuid> {
+    let mut seen = HashSet::new();
+    steps
+        .iter()
+        .filter_map(|step| step.media_id)
+        .chain(gallery.iter().copied())
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+/// Ингредиенты (количество — в колонках `quantity` + `unit`) и пищевая ценность рецепта
+async fn insert_details(
+    tx: &mut Transaction<'_, Postgres>,
+    recipe_id: Uuid,
+    ingredients: &[RecipeIngredientResponse],
+    nutrition: Option<&NutritionInfoRequest>,
+) -> Result<(), AppError> {
+    let (quantities, units): (Vec<f32>, Vec<String>) = ingredients
+        .iter()
+        .map(|ingredient| ingredient.amount.as_ref().map_or((ingredient.quantity, ingredient.unit.clone()), |amount| amount.legacy_parts()))
+        .unzip();
+
+    sqlx::query(
+        r#"
+        INSERT INTO recipe_ingredients (recipe_id, name, quantity, unit, notes)
+        SELECT $1, item.name, item.quantity, item.unit, item.notes
+        FROM UNNEST($2::TEXT[], $3::REAL[], $4::TEXT[], $5::TEXT[]) AS item(name, quantity, unit, notes)
+        "#
+    )
+    .bind(recipe_id)
+    .bind(ingredients.iter().map(|ingredient| ingredient.name.clone()).collect::<Vec<String>>())
+    .bind(quantities)
+    .bind(units)
+    .bind(ingredients.iter().map(|ingredient| ingredient.notes.clone()).collect::<Vec<Option<String>>>())
+    .execute(&mut **tx)
+    .await?;
+
+    if let Some(n) = nutrition {
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_nutrition (recipe_id, calories, protein, fat, carbs, fiber, sugar, sodium)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#
+        )
+        .bind(recipe_id)
+        .bind(n.calories)
+        .bind(n.protein)
+        .bind(n.fat)
+        .bind(n.carbs)
+        .bind(n.fiber)
+        .bind(n.sugar)
+        .bind(n.sodium)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Шаги (позиции с 1) и галерея по порядку
+async fn insert_steps(
+    tx: &mut Transaction<'_, Postgres>,
+    recipe_id: Uuid,
+    steps: &[RecipeStepInput],
+    gallery: &[Uuid],
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO recipe_steps (recipe_id, position, text, media_id)
+        SELECT $1, step.position, step.text, step.media_id
+        FROM UNNEST($2::INTEGER[], $3::TEXT[], $4::UUID[]) AS step(position, text, media_id)
+        "#
+    )
+    .bind(recipe_id)
+    .bind((1..=steps.len() as i32).collect::<Vec<i32>>())
+    .bind(steps.iter().map(|step| step.text.clone()).collect::<Vec<String>>())
+    .bind(steps.iter().map(|step| step.media_id).collect::<Vec<Option<Uuid>>>())
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO recipe_media (recipe_id, media_id, position)
+        SELECT $1, item.media_id, item.position::INTEGER
+        FROM UNNEST($2::UUID[]) WITH ORDINALITY AS item(media_id, position)
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(recipe_id)
+    .bind(gallery)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Фото, на которые ссылаются шаги и галерея сохраненного рецепта
+async fn recipe_media_ids(tx: &mut Transaction<'_, Postgres>, recipe_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT media_id FROM recipe_steps WHERE recipe_id = $1 AND media_id IS NOT NULL
+        UNION
+        SELECT media_id FROM recipe_media WHERE recipe_id = $1
+        "#
+    )
+    .bind(recipe_id)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(rows.into_iter().map(|(media_id,)| media_id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::recipes::CreateRecipeRequest;
+    use crate::test_support::{counting_pool, insert_media, insert_recipe, insert_user};
+    use std::sync::atomic::Ordering;
+    use crate::models::recipe::IngredientQuantity;
+
+    fn request(instructions: serde_json::Value, gallery: Vec<Uuid>, is_public: bool) -> CreateRecipeRequest {
+        serde_json::from_value(json!({
+            "name": "Сырники",
+            "description": "К завтраку",
+            "category": "Breakfast",
+            "difficulty": "Easy",
+            "prep_time_minutes": 10,
+            "cook_time_minutes": 15,
+            "servings": 2,
+            "instructions": instructions,
+            "gallery": gallery,
+            "ingredients": [
+                {"name": "Творог", "amount": {"kind": "measured", "value": 400, "unit": "г"}},
+                {"name": "Яйцо", "quantity": 1, "unit": ""},
+                {"name": "Соль", "amount": {"kind": "to_taste"}}
+            ],
+            "tags": ["Завтрак", "творог"],
+            "nutrition_per_serving": {"calories": 320.0, "protein": 24.0},
+            "is_public": is_public
+        }))
+        .expect("request")
+    }
+
+    /// Как обработчик `POST /recipes`
+    async fn create(service: &RecipeService, user_id: Uuid, payload: CreateRecipeRequest) -> Result<RecipeResponse, AppError> {
+        let recipe = CreateRecipe {
+            name: payload.name,
+            description: payload.description,
+            category: payload.category,
+            difficulty: payload.difficulty,
+            prep_time_minutes: payload.prep_time_minutes,
+            cook_time_minutes: payload.cook_time_minutes,
+            servings: payload.servings,
+            steps: payload.instructions.into_steps()?,
+            gallery: payload.gallery,
+            tags: payload.tags,
+            image_url: payload.image_url,
+            source_url: payload.source_url,
+            created_by: user_id,
+            is_public: payload.is_public,
+        };
+        service.create_recipe(recipe, payload.ingredients, payload.nutrition_per_serving).await
+    }
+
+    fn step_texts(recipe: &RecipeResponse) -> Vec<&str> {
+        recipe.steps.iter().map(|step| step.text.as_str()).collect()
+    }
+
+    #[sqlx::test]
+    async fn both_instruction_shapes_round_trip(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let photo = insert_media(&pool, author).await;
+        let service = RecipeService::new(pool.clone());
+
+        let created = create(&service, author, request(json!("Смешать творог с яйцом\n\nОбжарить"), vec![], false))
+            .await
+            .unwrap();
+        let stored = service.get_recipe_by_id(created.id, Some(author)).await.unwrap();
+        assert_eq!(step_texts(&stored), ["Смешать творог с яйцом", "Обжарить"]);
+        assert_eq!(stored.instructions, "Смешать творог с яйцом\nОбжарить");
+        assert_eq!(stored.tags, ["завтрак", "творог"]);
+        assert_eq!(stored.ingredients.len(), 3);
+        assert_eq!(stored.ingredients[0].amount, Some(IngredientQuantity::Measured { value: 400.0, unit: "г".to_string() }));
+        assert_eq!(stored.ingredients[1].amount, Some(IngredientQuantity::Count { value: 1.0 }));
+        assert_eq!(stored.ingredients[2].amount, Some(IngredientQuantity::ToTaste));
+        assert_eq!(stored.nutrition_per_serving.as_ref().and_then(|n| n.calories), Some(320.0));
+
+        let steps = json!([{"text": "Смешать"}, {"text": "Обжарить", "media_id": photo}]);
+        service.update_recipe(created.id, author, request(steps, vec![photo], false)).await.unwrap();
+        let stored = service.get_recipe_by_id(created.id, Some(author)).await.unwrap();
+        assert_eq!(step_texts(&stored), ["Смешать", "Обжарить"]);
+        assert_eq!(stored.steps[1].media_id, Some(photo));
+        assert_eq!(stored.steps[1].media_url, Some(format!("/uploads/{}.jpg", photo)));
+        assert_eq!(stored.gallery.iter().map(|item| item.media_id).collect::<Vec<_>>(), [photo]);
+        assert_eq!(stored.instructions, "Смешать\nОбжарить");
+    }
+
+    #[sqlx::test]
+    async fn private_recipe_is_hidden_from_others(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+        let service = RecipeService::new(pool.clone());
+
+        let created = create(&service, author, request(json!("Смешать"), vec![], false)).await.unwrap();
+
+        assert!(matches!(service.get_recipe_by_id(created.id, Some(other)).await, Err(AppError::NotFound(_))));
+        assert!(matches!(
+            service.update_recipe(created.id, other, request(json!("Иначе"), vec![], false)).await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn fork_can_keep_original_media_but_not_add_foreign(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let forker = insert_user(&pool, "Борис").await;
+        let stranger = insert_user(&pool, "Вера").await;
+        let author_photo = insert_media(&pool, author).await;
+        let author_cover = insert_media(&pool, author).await;
+        let own_photo = insert_media(&pool, forker).await;
+        let foreign_photo = insert_media(&pool, stranger).await;
+        let service = RecipeService::new(pool.clone());
+
+        // Чужие файлы нельзя прикрепить и к новому рецепту
+        let foreign = create(&service, author, request(json!("Смешать"), vec![foreign_photo], true)).await;
+        assert!(matches!(foreign, Err(AppError::BadRequest(_))));
+
+        let steps = json!([{"text": "Смешать", "media_id": author_photo}]);
+        let original = create(&service, author, request(steps.clone(), vec![author_cover], true)).await.unwrap();
+        let (fork, _) = service.fork_recipe(original.id, forker).await.unwrap();
+
+        // Правка копии с фото автора оригинала
+        let edited = service
+            .update_recipe(fork.id, forker, request(steps.clone(), vec![author_cover, own_photo], false))
+            .await
+            .unwrap();
+        assert_eq!(edited.steps[0].media_id, Some(author_photo));
+        assert_eq!(edited.gallery.len(), 2);
+        assert!(edited.attribution.is_some());
+
+        // Новое чужое фото отклоняется
+        let rejected = service
+            .update_recipe(fork.id, forker, request(steps, vec![author_cover, foreign_photo], false))
+            .await;
+        match rejected {
+            Err(AppError::BadRequest(message)) => assert!(message.contains(&foreign_photo.to_string())),
+            other => panic!("expected BadRequest, got {:?}", other.map(|recipe| recipe.id)),
         }
-        
-        let start = offset as usize;
-        let end = std::cmp::min(start + limit as usize, recipes.len());
-        
-        if start >= recipes.len() {
-            Ok(vec![])
-        } else {
-            Ok(recipes[start..end].to_vec())
+
+        // Фото оригинала не удаляются, пока на них ссылается копия
+        let stored = service.get_recipe_by_id(original.id, Some(author)).await.unwrap();
+        assert_eq!(stored.steps[0].media_id, Some(author_photo));
+    }
+
+    fn renamed(name: &str, is_public: bool) -> CreateRecipeRequest {
+        let mut payload = request(json!("Смешать\n\nЗапечь"), vec![], is_public);
+        payload.name = name.to_string();
+        payload
+    }
+
+    async fn forks_count(pool: &PgPool, id: Uuid) -> i32 {
+        sqlx::query_scalar("SELECT forks_count FROM recipes WHERE id = $1").bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    #[sqlx::test]
+    async fn fork_attribution_survives_edits_of_both_recipes(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let forker = insert_user(&pool, "Борис").await;
+        let service = RecipeService::new(pool.clone());
+        let original = create(&service, author, request(json!("Смешать"), vec![], true)).await.unwrap();
+        assert!(original.attribution.is_none());
+
+        let (fork, updated_original) = service.fork_recipe(original.id, forker).await.unwrap();
+        assert_ne!(fork.id, original.id);
+        assert_eq!(fork.created_by, forker);
+        let fork_public: bool = sqlx::query_scalar("SELECT is_public FROM recipes WHERE id = $1")
+            .bind(fork.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(!fork_public, "a fork starts private");
+        assert_eq!(fork.ingredients.len(), 3);
+        assert_eq!(updated_original.forks_count, 1);
+        let attribution = fork.attribution.clone().unwrap();
+        assert_eq!(attribution.original_recipe_id, Some(original.id));
+        assert_eq!(attribution.original_author_id, Some(author));
+        assert!(attribution.label.starts_with("Адаптировано из «Сырники»"));
+
+        // Правка копии: ссылка на автора остается, оригинал не меняется
+        let edited = service.update_recipe(fork.id, forker, renamed("Сырники по-моему", true)).await.unwrap();
+        assert_eq!(edited.name, "Сырники по-моему");
+        let edited_attribution = edited.attribution.unwrap();
+        assert_eq!(edited_attribution.original_recipe_id, Some(original.id));
+        assert_eq!(edited_attribution.original_author_id, Some(author));
+        assert_eq!(edited_attribution.original_author_name, attribution.original_author_name);
+
+        let stored_original = service.get_recipe_by_id(original.id, Some(author)).await.unwrap();
+        assert_eq!(stored_original.name, "Сырники");
+        assert_eq!(step_texts(&stored_original), ["Смешать"]);
+        assert!(stored_original.attribution.is_none());
+
+        // Переименование оригинала видно в подписи, ссылка та же
+        service.update_recipe(original.id, author, renamed("Сырники бабушки", true)).await.unwrap();
+        let reread = service.get_recipe_by_id(fork.id, Some(forker)).await.unwrap();
+        assert_eq!(reread.name, "Сырники по-моему");
+        let reread_attribution = reread.attribution.unwrap();
+        assert_eq!(reread_attribution.original_recipe_id, Some(original.id));
+        assert!(reread_attribution.label.starts_with("Адаптировано из «Сырники бабушки»"));
+
+        // Копию видят другие — с той же подписью
+        let stranger = insert_user(&pool, "Вера").await;
+        let seen = service.get_recipe_by_id(fork.id, Some(stranger)).await.unwrap();
+        assert_eq!(seen.attribution.unwrap().original_author_id, Some(author));
+    }
+
+    #[sqlx::test]
+    async fn forks_count_grows_and_is_visible_only_to_the_author(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+        let vera = insert_user(&pool, "Вера").await;
+        let service = RecipeService::new(pool.clone());
+        let original = create(&service, author, request(json!("Смешать"), vec![], true)).await.unwrap();
+
+        service.fork_recipe(original.id, boris).await.unwrap();
+        let (_, updated) = service.fork_recipe(original.id, vera).await.unwrap();
+        assert_eq!(updated.forks_count, 2);
+
+        let stats = service.get_forks_count(original.id, author).await.unwrap();
+        assert_eq!((stats.recipe_id, stats.forks_count), (original.id, 2));
+        assert!(matches!(service.get_forks_count(original.id, boris).await, Err(AppError::Forbidden(_))));
+        assert!(matches!(service.get_forks_count(Uuid::new_v4(), author).await, Err(AppError::NotFound(_))));
+
+        // В ответе только счетчик — без копий и их владельцев
+        let body = serde_json::to_value(&stats).unwrap();
+        let mut keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["forks_count", "recipe_id"]);
+    }
+
+    #[sqlx::test]
+    async fn forking_private_or_unpublished_recipes_is_rejected(pool: PgPool) {
+        let author = insert_user(&pool, "Анна").await;
+        let forker = insert_user(&pool, "Борис").await;
+        let service = RecipeService::new(pool.clone());
+        let private = create(&service, author, request(json!("Смешать"), vec![], false)).await.unwrap();
+        let unpublished = create(&service, author, request(json!("Смешать"), vec![], true)).await.unwrap();
+
+        // Публикацию отменили после того, как рецепт уже видели в ленте
+        service.fork_recipe(unpublished.id, forker).await.unwrap();
+        service.update_recipe(unpublished.id, author, renamed("Сырники", false)).await.unwrap();
+
+        for id in [private.id, unpublished.id] {
+            assert!(matches!(service.fork_recipe(id, forker).await, Err(AppError::Forbidden(_))));
         }
+        assert!(matches!(service.fork_recipe(Uuid::new_v4(), forker).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.fork_recipe(private.id, author).await, Err(AppError::BadRequest(_))));
+
+        assert_eq!(forks_count(&pool, private.id).await, 0);
+        assert_eq!(forks_count(&pool, unpublished.id).await, 1);
+        let owned: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM recipes WHERE created_by = $1")
+            .bind(forker)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(owned, 1, "rejected forks must not leave copies");
+    }
+
+    #[sqlx::test]
+    async fn list_filters_respect_visibility(pool: PgPool) {
+        let anna = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+        let service = RecipeService::new(pool.clone());
+        let syrniki = create(&service, anna, request(json!("Смешать"), vec![], true)).await.unwrap();
+        insert_recipe(&pool, anna, "Секретный суп", false).await;
+        let okroshka = insert_recipe(&pool, boris, "Окрошка", false).await;
+        let pancakes = insert_recipe(&pool, anna, "Оладьи", true).await;
+
+        let list = |user_id: Option<Uuid>, search: Option<&str>, tags: Option<&str>, category: Option<RecipeCategory>, max_prep: Option<i32>| {
+            let service = &service;
+            let (search, tags) = (search.map(str::to_string), tags.map(str::to_string));
+            async move {
+                let recipes = service.get_recipes(user_id, category, None, max_prep, None, search, tags, 20, 0).await.unwrap();
+                let mut ids: Vec<Uuid> = recipes.into_iter().map(|recipe| recipe.id).collect();
+                ids.sort();
+                ids
+            }
+        };
+        let sorted = |mut ids: Vec<Uuid>| {
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(list(Some(boris), None, None, None, None).await, sorted(vec![syrniki.id, okroshka, pancakes]));
+        assert_eq!(list(None, None, None, None, None).await, sorted(vec![syrniki.id, pancakes]));
+        assert_eq!(list(Some(boris), Some("ырни"), None, None, None).await, vec![syrniki.id]);
+        assert_eq!(list(Some(boris), Some("завтраку"), None, None, None).await, vec![syrniki.id]);
+        assert!(list(Some(boris), Some("%"), None, None, None).await.is_empty());
+        assert!(list(Some(boris), Some("Секретный"), None, None, None).await.is_empty());
+        assert_eq!(list(Some(boris), None, Some("Завтраки"), None, None).await, vec![syrniki.id]);
+        assert!(list(Some(boris), None, Some("завтрак, мясо"), None, None).await.is_empty());
+        assert_eq!(list(Some(boris), None, None, Some(RecipeCategory::Dinner), None).await, sorted(vec![okroshka, pancakes]));
+        assert_eq!(list(Some(boris), None, None, None, Some(10)).await, vec![syrniki.id]);
+        assert!(list(Some(boris), None, None, None, Some(5)).await.is_empty());
+
+        let found = service.search_recipes("Сырники".to_string(), Some(boris), None, None, 20, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].ingredients.len(), 3);
+
+        let page = service.get_recipes(Some(boris), None, None, None, None, None, None, 2, 2).await.unwrap();
+        assert_eq!(page.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn favorites_feed_popular_and_hide_private(pool: PgPool) {
+        let anna = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+        let service = RecipeService::new(pool.clone());
+        let soup = insert_recipe(&pool, anna, "Суп", true).await;
+        let pancakes = insert_recipe(&pool, anna, "Оладьи", true).await;
+        let secret = insert_recipe(&pool, anna, "Секретный суп", false).await;
+
+        assert!(service.toggle_favorite(soup, boris).await.unwrap());
+        assert!(matches!(service.toggle_favorite(secret, boris).await, Err(AppError::NotFound(_))));
+
+        let favorites = service.get_favorite_recipes(boris).await.unwrap();
+        assert_eq!(favorites.iter().map(|recipe| recipe.id).collect::<Vec<_>>(), vec![soup]);
+        assert!(favorites[0].is_favorite);
+
+        let popular: Vec<Uuid> = service.get_popular_recipes(Some(boris)).await.unwrap().into_iter().map(|recipe| recipe.id).collect();
+        assert_eq!(popular, vec![soup, pancakes]);
+        let own: Vec<Uuid> = service.get_popular_recipes(Some(anna)).await.unwrap().into_iter().map(|recipe| recipe.id).collect();
+        assert_eq!(own.len(), 3);
+
+        assert!(!service.toggle_favorite(soup, boris).await.unwrap());
+        assert!(service.get_favorite_recipes(boris).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn lists_read_from_replica_and_created_recipe_from_primary(pool: PgPool) {
+        let anna = insert_user(&pool, "Анна").await;
+        let (primary, primary_queries) = counting_pool(&pool);
+        let (replica, replica_queries) = counting_pool(&pool);
+        let service = RecipeService::with_pools(DbPools::new(primary, Some(replica)));
+
+        // Ответ на создание читается после записи — только основной пул
+        let created = create(&service, anna, request(json!("Смешать"), vec![], true)).await.unwrap();
+        assert_eq!(created.ingredients.len(), 3);
+        assert_eq!(replica_queries.load(Ordering::SeqCst), 0);
+        let writes = primary_queries.load(Ordering::SeqCst);
+        assert!(writes > 0);
+
+        let listed = service.get_recipes(Some(anna), None, None, None, None, Some("Сыр".to_string()), None, 20, 0).await.unwrap();
+        assert_eq!(listed[0].id, created.id);
+        service.get_popular_recipes(Some(anna)).await.unwrap();
+        service.get_favorite_recipes(anna).await.unwrap();
+        service.get_recipe_by_id(created.id, Some(anna)).await.unwrap();
+        assert!(replica_queries.load(Ordering::SeqCst) > 0);
+        assert_eq!(primary_queries.load(Ordering::SeqCst), writes);
     }
 }
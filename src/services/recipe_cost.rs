@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::FridgeItem,
+        recipe::{CostConfidence, IngredientCost, IngredientQuantity, PriceSource, RecipeCostEstimate, UnpricedIngredient, UnpricedReason},
+    },
+    services::fridge::FridgeService,
+    utils::{
+        errors::AppError,
+        format::round_money,
+        ingredient_matcher::{normalize_ingredient, IngredientIndex},
+        units::{base_factor, BaseUnit},
+    },
+};
+
+/// Валюта цен в холодильнике и в справочной таблице
+pub const COST_CURRENCY: &str = "RUB";
+
+/// Средние цены на базовые продукты (руб. за кг / л / шт) — когда пользователь
+/// сам такого не покупал
+const DEFAULT_PRICES: &[(&str, f64, BaseUnit)] = &[
+    ("соль", 30.0, BaseUnit::Kg),
+    ("сахар", 80.0, BaseUnit::Kg),
+    ("мука", 60.0, BaseUnit::Kg),
+    ("рис", 120.0, BaseUnit::Kg),
+    ("гречка", 110.0, BaseUnit::Kg),
+    ("макароны", 130.0, BaseUnit::Kg),
+    ("овсяные хлопья", 100.0, BaseUnit::Kg),
+    ("картофель", 50.0, BaseUnit::Kg),
+    ("морковь", 50.0, BaseUnit::Kg),
+    ("лук", 45.0, BaseUnit::Kg),
+    ("чеснок", 300.0, BaseUnit::Kg),
+    ("капуста", 40.0, BaseUnit::Kg),
+    ("помидоры", 250.0, BaseUnit::Kg),
+    ("огурцы", 200.0, BaseUnit::Kg),
+    ("яблоки", 130.0, BaseUnit::Kg),
+    ("бананы", 140.0, BaseUnit::Kg),
+    ("куриная грудка", 450.0, BaseUnit::Kg),
+    ("курица", 300.0, BaseUnit::Kg),
+    ("говядина", 750.0, BaseUnit::Kg),
+    ("свинина", 450.0, BaseUnit::Kg),
+    ("фарш", 450.0, BaseUnit::Kg),
+    ("сыр", 900.0, BaseUnit::Kg),
+    ("творог", 500.0, BaseUnit::Kg),
+    ("сливочное масло", 1000.0, BaseUnit::Kg),
+    ("сметана", 350.0, BaseUnit::Kg),
+    ("молоко", 90.0, BaseUnit::L),
+    ("кефир", 110.0, BaseUnit::L),
+    ("сливки", 400.0, BaseUnit::L),
+    ("растительное масло", 180.0, BaseUnit::L),
+    ("оливковое масло", 900.0, BaseUnit::L),
+    ("вода", 0.0, BaseUnit::L),
+    ("яйца", 12.0, BaseUnit::Piece),
+    ("яйцо", 12.0, BaseUnit::Piece),
+    ("хлеб", 60.0, BaseUnit::Piece),
+    ("лимон", 40.0, BaseUnit::Piece),
+];
+
+/// Ингредиент рецепта для оценки стоимости. `amount` — `None`, если количество не разобрано.
+#[derive(Debug, Clone)]
+pub struct CostIngredient<'a> {
+    pub name: &'a str,
+    pub amount: Option<IngredientQuantity>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UnitPrice {
+    price: f64,
+    base: BaseUnit,
+    source: PriceSource,
+}
+
+struct PriceEntry {
+    index: IngredientIndex,
+    price: UnitPrice,
+}
+
+/// Цены за кг / л / шт: последние цены пользователя по нормализованному названию
+/// и справочная таблица. Строится один раз на запрос.
+pub struct PriceBook {
+    personal_exact: HashMap<String, UnitPrice>,
+    personal: Vec<PriceEntry>,
+    default_exact: HashMap<String, UnitPrice>,
+    defaults: Vec<PriceEntry>,
+}
+
+impl PriceBook {
+    /// Для одного названия берется цена самой поздней покупки
+    pub fn from_items(items: &[FridgeItem]) -> Self {
+        let mut latest: HashMap<String, (&FridgeItem, UnitPrice)> = HashMap::new();
+        for item in items {
+            let Some((price, base)) = item_unit_price(item) else {
+                continue;
+            };
+            let name = normalize_ingredient(&item.name);
+            if name.is_empty() {
+                continue;
+            }
+
+            let price = UnitPrice { price, base, source: PriceSource::Personal };
+            match latest.get(&name) {
+                Some((seen, _)) if seen.purchase_date >= item.purchase_date => {}
+                _ => {
+                    latest.insert(name, (item, price));
+                }
+            }
+        }
+
+        let personal_exact: HashMap<String, UnitPrice> =
+            latest.into_iter().map(|(name, (_, price))| (name, price)).collect();
+        let default_exact: HashMap<String, UnitPrice> = DEFAULT_PRICES
+            .iter()
+            .map(|(name, price, base)| {
+                (normalize_ingredient(name), UnitPrice { price: *price, base: *base, source: PriceSource::Default })
+            })
+            .collect();
+
+        Self {
+            personal: entries(&personal_exact),
+            defaults: entries(&default_exact),
+            personal_exact,
+            default_exact,
+        }
+    }
+
+    /// Своя цена (точное название, затем похожее), затем справочная
+    fn lookup(&self, name: &str) -> Option<UnitPrice> {
+        let normalized = normalize_ingredient(name);
+        if normalized.is_empty() {
+            return None;
+        }
+
+        self.personal_exact
+            .get(&normalized)
+            .copied()
+            .or_else(|| find_similar(&self.personal, name))
+            .or_else(|| self.default_exact.get(&normalized).copied())
+            .or_else(|| find_similar(&self.defaults, name))
+    }
+}
+
+fn entries(prices: &HashMap<String, UnitPrice>) -> Vec<PriceEntry> {
+    let mut entries: Vec<(&String, &UnitPrice)> = prices.iter().collect();
+    // Порядок важен при нескольких похожих названиях — делаем его детерминированным
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .map(|(name, price)| PriceEntry {
+            index: IngredientIndex::new(std::iter::once(name.as_str()), false),
+            price: *price,
+        })
+        .collect()
+}
+
+fn find_similar(entries: &[PriceEntry], name: &str) -> Option<UnitPrice> {
+    entries.iter().find(|entry| entry.index.contains(name)).map(|entry| entry.price)
+}
+
+/// Цена продукта за базовую единицу. `price_per_unit` не зависит от остатка,
+/// поэтому подходит и для уже съеденных продуктов.
+fn item_unit_price(item: &FridgeItem) -> Option<(f64, BaseUnit)> {
+    if let Some(price_per_unit) = item.price_per_unit {
+        let (factor, base) = base_factor(&item.unit)?;
+        return (factor > 0.0).then_some((price_per_unit as f64 / factor, base));
+    }
+    item.normalized_unit_price()
+}
+
+/// Стоимость рецепта. Ингредиенты без цены или в несопоставимых единицах
+/// перечисляются отдельно и в сумму не входят (а не считаются бесплатными).
+pub fn estimate_cost<'a>(
+    ingredients: impl IntoIterator<Item = CostIngredient<'a>>,
+    servings: Option<i32>,
+    prices: &PriceBook,
+) -> RecipeCostEstimate {
+    let mut priced = Vec::new();
+    let mut unpriced = Vec::new();
+    let mut confidence = CostConfidence::default();
+
+    for ingredient in ingredients {
+        match price_ingredient(&ingredient, prices) {
+            Ok(cost) => {
+                match cost.source {
+                    PriceSource::Personal => confidence.personal += 1,
+                    PriceSource::Default => confidence.default += 1,
+                }
+                priced.push(cost);
+            }
+            Err(reason) => {
+                confidence.unpriced += 1;
+                unpriced.push(UnpricedIngredient { name: ingredient.name.to_string(), reason });
+            }
+        }
+    }
+
+    let total = round_money(priced.iter().map(|cost| cost.cost).sum());
+    let servings = servings.filter(|servings| *servings > 0);
+
+    RecipeCostEstimate {
+        currency: COST_CURRENCY,
+        total,
+        per_serving: servings.map(|servings| round_money(total / servings as f64)),
+        servings,
+        complete: unpriced.is_empty(),
+        ingredients: priced,
+        unpriced,
+        confidence,
+    }
+}
+
+fn price_ingredient(ingredient: &CostIngredient, prices: &PriceBook) -> Result<IngredientCost, UnpricedReason> {
+    let amount = ingredient.amount.as_ref().ok_or(UnpricedReason::UnknownUnit)?;
+    let (quantity, unit) = amount.measure().ok_or(UnpricedReason::NoQuantity)?;
+    let (base_quantity, base) = amount.to_base().ok_or(UnpricedReason::UnknownUnit)?;
+    let price = prices.lookup(ingredient.name).ok_or(UnpricedReason::NoPrice)?;
+    if price.base != base {
+        return Err(UnpricedReason::IncompatibleUnit);
+    }
+
+    Ok(IngredientCost {
+        name: ingredient.name.to_string(),
+        quantity,
+        unit: unit.to_string(),
+        cost: round_money(base_quantity * price.price),
+        unit_price: round_money(price.price),
+        base_unit: base,
+        source: price.source,
+    })
+}
+
+/// Оценка стоимости рецептов по истории цен пользователя
+pub struct RecipeCostService {
+    pool: DbPool,
+}
+
+impl RecipeCostService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn price_book(&self, user_id: Uuid) -> Result<PriceBook, AppError> {
+        let items = FridgeService::new(self.pool.clone()).get_price_history(user_id).await?;
+        Ok(PriceBook::from_items(&items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::*;
+    use crate::models::fridge::{FridgeCategory, FridgeItemStatus};
+
+    fn bought(name: &str, quantity: f32, unit: &str, price_per_unit: Option<f32>, total_price: Option<f32>, days_ago: i64) -> FridgeItem {
+        let purchase_date: DateTime<Utc> = Utc::now() - Duration::days(days_ago);
+        FridgeItem {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            name: name.to_string(),
+            brand: None,
+            quantity,
+            unit: unit.to_string(),
+            category: FridgeCategory::Other,
+            price_per_unit,
+            total_price,
+            expiry_date: None,
+            purchase_date,
+            notes: None,
+            location: None,
+            store: None,
+            contains_allergens: Vec::new(),
+            contains_intolerances: Vec::new(),
+            suitable_for_diets: Vec::new(),
+            ingredients: None,
+            nutritional_info: None,
+            status: FridgeItemStatus::Consumed,
+            finished_at: None,
+            consumed_value: 0.0,
+            wasted_value: 0.0,
+            created_at: purchase_date,
+            updated_at: purchase_date,
+        }
+    }
+
+    fn prices() -> PriceBook {
+        PriceBook::from_items(&[
+            bought("Молоко", 1.0, "л", Some(100.0), None, 10),
+            // Более поздняя покупка того же продукта важнее
+            bought("молоко", 1.0, "л", Some(120.0), None, 2),
+            // Только общая цена: 300 руб. за 500 г — 600 руб./кг
+            bought("Куриное филе", 500.0, "г", None, Some(300.0), 5),
+            bought("Яйца С0", 10.0, "шт", Some(15.0), None, 3),
+            // Без цены — в справочник не попадает
+            bought("Сыр", 0.3, "кг", None, None, 1),
+        ])
+    }
+
+    fn measured(value: f32, unit: &str) -> Option<IngredientQuantity> {
+        Some(IngredientQuantity::Measured { value, unit: unit.to_string() })
+    }
+
+    fn count(value: f32) -> Option<IngredientQuantity> {
+        Some(IngredientQuantity::Count { value })
+    }
+
+    #[test]
+    fn each_ingredient_is_priced_or_explained() {
+        type Priced = Result<(f64, PriceSource), UnpricedReason>;
+
+        let prices = prices();
+        let cases: Vec<(&str, Option<IngredientQuantity>, Priced)> = vec![
+            ("молоко", measured(250.0, "мл"), Ok((30.0, PriceSource::Personal))),
+            ("Куриное филе", measured(200.0, "г"), Ok((120.0, PriceSource::Personal))),
+            ("Яйца С0", count(2.0), Ok((30.0, PriceSource::Personal))),
+            // Своей цены нет — справочная, по точному и по похожему названию
+            ("яйцо", count(3.0), Ok((36.0, PriceSource::Default))),
+            ("Мука пшеничная", measured(300.0, "г"), Ok((18.0, PriceSource::Default))),
+            ("Сыр", measured(100.0, "г"), Ok((90.0, PriceSource::Default))),
+            ("Вода", measured(500.0, "мл"), Ok((0.0, PriceSource::Default))),
+            ("Сахар", count(1.0), Err(UnpricedReason::IncompatibleUnit)),
+            ("Соль", Some(IngredientQuantity::ToTaste), Err(UnpricedReason::NoQuantity)),
+            ("Шафран", measured(1.0, "г"), Err(UnpricedReason::NoPrice)),
+            ("Сливки", measured(2.0, "ст. л."), Err(UnpricedReason::UnknownUnit)),
+            ("Перец", None, Err(UnpricedReason::UnknownUnit)),
+        ];
+
+        for (name, amount, expected) in cases {
+            let result = price_ingredient(&CostIngredient { name, amount }, &prices).map(|cost| (cost.cost, cost.source));
+            assert_eq!(result, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn totals_exclude_unpriced_ingredients() {
+        let prices = prices();
+        let recipe = vec![
+            CostIngredient { name: "молоко", amount: measured(500.0, "мл") },
+            CostIngredient { name: "Яйца С0", amount: count(3.0) },
+            CostIngredient { name: "Мука пшеничная", amount: measured(250.0, "г") },
+            CostIngredient { name: "Шафран", amount: measured(1.0, "г") },
+            CostIngredient { name: "Соль", amount: Some(IngredientQuantity::ToTaste) },
+        ];
+
+        let estimate = estimate_cost(recipe.clone(), Some(4), &prices);
+        // 60 + 45 + 15; шафран и соль не считаются бесплатными, а перечислены отдельно
+        assert_eq!(estimate.total, 120.0);
+        assert_eq!((estimate.servings, estimate.per_serving), (Some(4), Some(30.0)));
+        assert!(!estimate.complete);
+        assert_eq!((estimate.confidence.personal, estimate.confidence.default, estimate.confidence.unpriced), (2, 1, 2));
+        let unpriced: Vec<(&str, UnpricedReason)> = estimate.unpriced.iter().map(|item| (item.name.as_str(), item.reason)).collect();
+        assert_eq!(unpriced, vec![("Шафран", UnpricedReason::NoPrice), ("Соль", UnpricedReason::NoQuantity)]);
+        assert_eq!(estimate.ingredients[0].unit_price, 120.0);
+        assert_eq!(estimate.currency, "RUB");
+
+        // Без числа порций или с нулем — только общая стоимость
+        assert_eq!(estimate_cost(recipe.clone(), None, &prices).per_serving, None);
+        assert_eq!(estimate_cost(recipe[..3].to_vec(), Some(0), &prices).per_serving, None);
+
+        let complete = estimate_cost(recipe[..3].to_vec(), Some(3), &prices);
+        assert!(complete.complete && complete.unpriced.is_empty());
+        assert_eq!((complete.total, complete.per_serving), (120.0, Some(40.0)));
+
+        let empty = estimate_cost(Vec::new(), Some(2), &prices);
+        assert_eq!((empty.total, empty.per_serving, empty.complete), (0.0, Some(0.0), true));
+    }
+}
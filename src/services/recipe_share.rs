@@ -0,0 +1,236 @@
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::recipe::{RecipeEmbed, RecipeEmbedIngredient, RecipeEmbedStep, RecipeEmbedTime, RecipeIngredient},
+    services::link_previews::LinkPreviewService,
+    utils::errors::AppError,
+};
+
+const DEFAULT_RECIPE_SHARE_URL: &str = "https://ai-cook-frontend.vercel.app/r";
+const SHARE_TOKEN_LENGTH: usize = 32;
+/// Версия схемы `RecipeEmbed`
+pub const EMBED_SCHEMA_VERSION: u32 = 1;
+
+/// Адрес публичных ссылок на рецепты (без завершающего `/`)
+fn share_base_url() -> String {
+    std::env::var("RECIPE_SHARE_URL")
+        .unwrap_or_else(|_| DEFAULT_RECIPE_SHARE_URL.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+pub fn share_url(token: &str) -> String {
+    format!("{}/{}", share_base_url(), token)
+}
+
+/// Токен из публичной ссылки на рецепт; для других адресов — `None`
+pub fn share_token_from_url(url: &str) -> Option<String> {
+    let base = share_base_url();
+    let rest = url.strip_prefix(base.as_str())?.strip_prefix('/')?;
+    let token = rest.split(['/', '?', '#']).next()?;
+
+    (!token.is_empty() && token.len() <= 64 && token.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then(|| token.to_string())
+}
+
+/// Сайт, на котором открываются публичные ссылки (провайдер для oEmbed)
+pub fn provider_url() -> String {
+    let base = share_base_url();
+    let path_start = base.find("://").map_or(0, |scheme| scheme + 3);
+    match base[path_start..].find('/') {
+        Some(index) => base[..path_start + index].to_string(),
+        None => base,
+    }
+}
+
+/// Публичная ссылка рецепта, как ее видит автор
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ShareLink {
+    pub token: String,
+    /// Просмотры виджета рецепта на чужих сайтах
+    pub embed_views: i64,
+}
+
+/// Рецепт по публичной ссылке для виджета и oEmbed
+#[derive(Debug, Clone)]
+pub struct SharedRecipeEmbed {
+    pub recipe_id: Uuid,
+    pub author_name: String,
+    pub embed: RecipeEmbed,
+}
+
+#[derive(sqlx::FromRow)]
+struct EmbedRecipeRow {
+    id: Uuid,
+    name: String,
+    image_url: Option<String>,
+    prep_time_minutes: Option<i32>,
+    cook_time_minutes: Option<i32>,
+    servings: Option<i32>,
+    author_name: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct EmbedStepRow {
+    text: String,
+    image: Option<String>,
+}
+
+/// Публичные ссылки на рецепты. Удаление токена отзывает ссылку.
+pub struct RecipeShareService {
+    pool: DbPool,
+}
+
+impl RecipeShareService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Публичный токен рецепта; если он уже есть, возвращается существующий
+    pub async fn create_token(&self, recipe_id: Uuid, user_id: Uuid) -> Result<ShareLink, AppError> {
+        self.ensure_author(recipe_id, user_id).await?;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SHARE_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        let link = sqlx::query_as(
+            r#"
+            INSERT INTO recipe_share_tokens (recipe_id, token)
+            VALUES ($1, $2)
+            ON CONFLICT (recipe_id) DO UPDATE SET recipe_id = EXCLUDED.recipe_id
+            RETURNING token, (SELECT embed_views FROM recipes WHERE id = $1) AS embed_views
+            "#
+        )
+        .bind(recipe_id)
+        .bind(&token)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(link)
+    }
+
+    /// Действующая ссылка рецепта со счетчиком просмотров виджета; только для автора
+    pub async fn link(&self, recipe_id: Uuid, user_id: Uuid) -> Result<ShareLink, AppError> {
+        self.ensure_author(recipe_id, user_id).await?;
+
+        sqlx::query_as(
+            r#"
+            SELECT t.token, r.embed_views
+            FROM recipe_share_tokens t
+            JOIN recipes r ON r.id = t.recipe_id
+            WHERE t.recipe_id = $1
+            "#
+        )
+        .bind(recipe_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipe is not shared".to_string()))
+    }
+
+    /// Рецепт для виджета по публичному токену; отозванный и неизвестный токены — `None`
+    pub async fn embed(&self, token: &str) -> Result<Option<SharedRecipeEmbed>, AppError> {
+        let recipe: Option<EmbedRecipeRow> = sqlx::query_as(
+            r#"
+            SELECT r.id, r.name, r.image_url, r.prep_time_minutes, r.cook_time_minutes, r.servings,
+                   u.first_name AS author_name
+            FROM recipe_share_tokens t
+            JOIN recipes r ON r.id = t.recipe_id
+            JOIN users u ON u.id = r.created_by
+            WHERE t.token = $1
+            "#
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(recipe) = recipe else { return Ok(None) };
+
+        let ingredients: Vec<RecipeIngredient> = sqlx::query_as("SELECT * FROM recipe_ingredients WHERE recipe_id = $1")
+            .bind(recipe.id)
+            .fetch_all(&self.pool)
+            .await?;
+        let steps: Vec<EmbedStepRow> = sqlx::query_as(
+            r#"
+            SELECT s.text, m.url AS image
+            FROM recipe_steps s
+            LEFT JOIN media_files m ON m.id = s.media_id
+            WHERE s.recipe_id = $1
+            ORDER BY s.position
+            "#
+        )
+        .bind(recipe.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total_minutes = match (recipe.prep_time_minutes, recipe.cook_time_minutes) {
+            (None, None) => None,
+            (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+        };
+
+        Ok(Some(SharedRecipeEmbed {
+            recipe_id: recipe.id,
+            author_name: recipe.author_name,
+            embed: RecipeEmbed {
+                schema_version: EMBED_SCHEMA_VERSION,
+                name: recipe.name,
+                image: recipe.image_url,
+                time: RecipeEmbedTime {
+                    prep_minutes: recipe.prep_time_minutes,
+                    cook_minutes: recipe.cook_time_minutes,
+                    total_minutes,
+                },
+                servings: recipe.servings,
+                ingredients: ingredients
+                    .into_iter()
+                    .map(|ingredient| RecipeEmbedIngredient {
+                        amount: ingredient.amount(),
+                        name: ingredient.name,
+                        notes: ingredient.notes,
+                    })
+                    .collect(),
+                steps: steps.into_iter().map(|step| RecipeEmbedStep { text: step.text, image: step.image }).collect(),
+                url: share_url(token),
+            },
+        }))
+    }
+
+    /// Учитывает просмотр виджета. Ответы кешируются, так что счетчик видит только
+    /// запросы, дошедшие до сервера.
+    pub async fn record_embed_view(&self, recipe_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE recipes SET embed_views = embed_views + 1 WHERE id = $1")
+            .bind(recipe_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Отзывает ссылку; карточки в постах теряют данные рецепта, но сохраняют название
+    pub async fn revoke_token(&self, recipe_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.ensure_author(recipe_id, user_id).await?;
+
+        sqlx::query("DELETE FROM recipe_share_tokens WHERE recipe_id = $1")
+            .bind(recipe_id)
+            .execute(&self.pool)
+            .await?;
+
+        LinkPreviewService::new(self.pool.clone()).degrade_recipe(recipe_id).await?;
+        Ok(())
+    }
+
+    async fn ensure_author(&self, recipe_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let created_by: Option<(Uuid,)> = sqlx::query_as("SELECT created_by FROM recipes WHERE id = $1")
+            .bind(recipe_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match created_by {
+            Some((created_by,)) if created_by == user_id => Ok(()),
+            Some(_) => Err(AppError::Forbidden("Only the author can share this recipe".to_string())),
+            None => Err(AppError::NotFound("Recipe not found".to_string())),
+        }
+    }
+}
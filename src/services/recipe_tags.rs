@@ -0,0 +1,460 @@
+use std::time::Duration;
+
+use sqlx::{Postgres, Transaction};
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    db::{DbPool, DbPools, ReadConsistency},
+    models::recipe::RelatedRecipe,
+    telemetry,
+    utils::{errors::AppError, ingredient_matcher::normalize_ingredient},
+};
+
+const MAX_RELATED: i64 = 10;
+/// Как часто пересчитываются веса тегов
+const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Частые русские формы множественного числа, которые не сводятся простым правилом
+const PLURAL_WORDS: &[(&str, &str)] = &[
+    ("завтраки", "завтрак"),
+    ("обеды", "обед"),
+    ("ужины", "ужин"),
+    ("перекусы", "перекус"),
+    ("десерты", "десерт"),
+    ("салаты", "салат"),
+    ("супы", "суп"),
+    ("закуски", "закуска"),
+    ("напитки", "напиток"),
+    ("пироги", "пирог"),
+    ("пирожки", "пирожок"),
+    ("блины", "блин"),
+    ("блинчики", "блинчик"),
+    ("котлеты", "котлета"),
+    ("запеканки", "запеканка"),
+    ("каши", "каша"),
+    ("соусы", "соус"),
+    ("овощи", "овощ"),
+    ("фрукты", "фрукт"),
+    ("ягоды", "ягода"),
+    ("грибы", "гриб"),
+    ("морепродукты", "морепродукт"),
+];
+
+/// Английские окончания множественного числа: (окончание, замена). Первое подходящее побеждает.
+const PLURAL_SUFFIXES: &[(&str, &str)] = &[
+    ("ies", "y"),
+    ("ches", "ch"),
+    ("shes", "sh"),
+    ("oes", "o"),
+    ("s", ""),
+];
+
+/// Окончания слов, которые только похожи на множественное число (hummus, glass, tennis)
+const NOT_PLURAL_SUFFIXES: &[&str] = &["ss", "us", "is"];
+
+/// Синонимы после приведения к единственному числу → канонический тег
+const TAG_SYNONYMS: &[(&str, &str)] = &[
+    ("breakfast", "завтрак"),
+    ("lunch", "обед"),
+    ("dinner", "ужин"),
+    ("supper", "ужин"),
+    ("snack", "перекус"),
+    ("dessert", "десерт"),
+    ("soup", "суп"),
+    ("salad", "салат"),
+    ("appetizer", "закуска"),
+    ("drink", "напиток"),
+    ("beverage", "напиток"),
+    ("baking", "выпечка"),
+    ("pastry", "выпечка"),
+    ("vegan", "веган"),
+    ("веганский", "веган"),
+    ("веганское", "веган"),
+    ("веганская", "веган"),
+    ("vegetarian", "вегетарианское"),
+    ("вегетарианский", "вегетарианское"),
+    ("вегетарианская", "вегетарианское"),
+    ("quick", "быстро"),
+    ("fast", "быстро"),
+    ("быстрый", "быстро"),
+    ("быстрое", "быстро"),
+    ("быстрая", "быстро"),
+    ("easy", "просто"),
+    ("simple", "просто"),
+    ("простой", "просто"),
+    ("простое", "просто"),
+    ("простая", "просто"),
+    ("healthy", "пп"),
+    ("правильное-питание", "пп"),
+    ("зож", "пп"),
+    ("keto", "кето"),
+    ("gluten-free", "без-глютена"),
+    ("georgian", "грузинская-кухня"),
+    ("georgian-cuisine", "грузинская-кухня"),
+    ("грузинская", "грузинская-кухня"),
+    ("italian", "итальянская-кухня"),
+    ("italian-cuisine", "итальянская-кухня"),
+    ("итальянская", "итальянская-кухня"),
+    ("asian", "азиатская-кухня"),
+    ("азиатская", "азиатская-кухня"),
+    ("russian", "русская-кухня"),
+    ("русская", "русская-кухня"),
+    ("макароны", "паста"),
+    ("pasta", "паста"),
+    ("chicken", "курица"),
+];
+
+/// Нормализованный тег: нижний регистр, ё → е, без пунктуации, слова через дефис,
+/// единственное число и канонический синоним ("Завтраки", "breakfast" → "завтрак").
+/// Пустой после очистки тег — `None`.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let normalized = normalize_ingredient(tag);
+    if normalized.is_empty() {
+        return None;
+    }
+
+    let tag = normalized.split(' ').map(singularize).collect::<Vec<_>>().join("-");
+    let canonical = TAG_SYNONYMS
+        .iter()
+        .find(|(synonym, _)| *synonym == tag)
+        .map(|(_, canonical)| canonical.to_string());
+
+    Some(canonical.unwrap_or(tag))
+}
+
+/// Нормализованные теги без повторов, в исходном порядке
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+fn singularize(word: &str) -> String {
+    if let Some((_, singular)) = PLURAL_WORDS.iter().find(|(plural, _)| *plural == word) {
+        return singular.to_string();
+    }
+
+    // Суффиксные правила — только для латиницы: у русских слов окончания неоднозначны
+    let latin = word.chars().all(|c| c.is_ascii_lowercase());
+    if !latin || word.len() <= 3 || NOT_PLURAL_SUFFIXES.iter().any(|suffix| word.ends_with(suffix)) {
+        return word.to_string();
+    }
+
+    PLURAL_SUFFIXES
+        .iter()
+        .find_map(|(suffix, replacement)| {
+            word.strip_suffix(suffix).map(|stem| format!("{}{}", stem, replacement))
+        })
+        .unwrap_or_else(|| word.to_string())
+}
+
+/// Вес тега по редкости (сглаженный IDF): тег почти каждого рецепта ("ужин") весит мало,
+/// но не ноль; редкий ("грузинская-кухня") — много
+pub fn tag_weight(total_recipes: i64, recipes_with_tag: i64) -> f64 {
+    (1.0 + total_recipes.max(1) as f64 / recipes_with_tag.max(1) as f64).ln()
+}
+
+/// Заменяет нормализованные теги рецепта в транзакции вызывающего кода
+pub async fn replace_recipe_tags(tx: &mut Transaction<'_, Postgres>, recipe_id: Uuid, tags: &[String]) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM recipe_tags WHERE recipe_id = $1")
+        .bind(recipe_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO recipe_tags (recipe_id, tag)
+        SELECT $1, tag FROM UNNEST($2::TEXT[]) AS tag
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(recipe_id)
+    .bind(tags)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Похожие рецепты по общим тегам и периодический пересчет весов тегов
+pub struct RecipeTagService {
+    pools: DbPools,
+}
+
+impl RecipeTagService {
+    pub fn new(pool: DbPool) -> Self {
+        Self::with_pools(DbPools::single(pool))
+    }
+
+    /// Похожие рецепты читаются с реплики; пересчет весов и перенос тегов — в основной базе
+    pub fn with_pools(pools: DbPools) -> Self {
+        Self { pools }
+    }
+
+    /// Рецепты с наибольшей суммой весов общих тегов. Видны только опубликованные и свои;
+    /// свои копии этого рецепта (и его оригинала) не показываются.
+    pub async fn related(&self, recipe_id: Uuid, user_id: Uuid) -> Result<Vec<RelatedRecipe>, AppError> {
+        let (created_by, is_public, original_recipe_id): (Uuid, bool, Option<Uuid>) = sqlx::query_as(
+            "SELECT created_by, is_public, original_recipe_id FROM recipes WHERE id = $1"
+        )
+        .bind(recipe_id)
+        .fetch_optional(self.pools.read(ReadConsistency::Replica))
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+
+        if !is_public && created_by != user_id {
+            return Err(AppError::NotFound("Recipe not found".to_string()));
+        }
+
+        // Рецепт, от которого пошли копии: сам рецепт или его оригинал
+        let root_id = original_recipe_id.unwrap_or(recipe_id);
+
+        let related = sqlx::query_as::<_, RelatedRecipe>(
+            r#"
+            SELECT r.id, r.name, r.category, r.image_url,
+                   ARRAY_AGG(t.tag ORDER BY w.weight DESC NULLS FIRST, t.tag) AS shared_tags,
+                   SUM(COALESCE(w.weight, (SELECT MAX(weight) FROM recipe_tag_weights), 1.0)) AS score
+            FROM recipe_tags src
+            JOIN recipe_tags t ON t.tag = src.tag AND t.recipe_id <> src.recipe_id
+            JOIN recipes r ON r.id = t.recipe_id
+            LEFT JOIN recipe_tag_weights w ON w.tag = t.tag
+            WHERE src.recipe_id = $1
+              AND (r.is_public OR r.created_by = $2)
+              AND NOT (r.created_by = $2 AND (r.id = $3 OR r.original_recipe_id IS NOT DISTINCT FROM $3))
+            GROUP BY r.id
+            ORDER BY score DESC, r.created_at DESC
+            LIMIT $4
+            "#
+        )
+        .bind(recipe_id)
+        .bind(user_id)
+        .bind(root_id)
+        .bind(MAX_RELATED)
+        .fetch_all(self.pools.read(ReadConsistency::Replica))
+        .await?;
+
+        Ok(related)
+    }
+
+    /// Нормализует теги рецептов, у которых их еще нет в `recipe_tags` (созданных до миграции)
+    pub async fn backfill(&self) -> Result<usize, AppError> {
+        let recipes: Vec<(Uuid, Vec<String>)> = sqlx::query_as(
+            r#"
+            SELECT r.id, COALESCE(r.tags, '{}') FROM recipes r
+            WHERE cardinality(r.tags) > 0
+              AND NOT EXISTS (SELECT 1 FROM recipe_tags t WHERE t.recipe_id = r.id)
+            "#
+        )
+        .fetch_all(self.pools.primary())
+        .await?;
+
+        let mut tx = self.pools.primary().begin().await?;
+        for (recipe_id, tags) in &recipes {
+            let tags = normalize_tags(tags);
+            // Теги, которые нормализовались в пустоту, убираются, чтобы рецепт не выбирался снова
+            sqlx::query("UPDATE recipes SET tags = $2 WHERE id = $1")
+                .bind(recipe_id)
+                .bind(&tags)
+                .execute(&mut *tx)
+                .await?;
+            replace_recipe_tags(&mut tx, *recipe_id, &tags).await?;
+        }
+        tx.commit().await?;
+
+        Ok(recipes.len())
+    }
+
+    /// Пересчитывает веса всех тегов; возвращает число тегов
+    pub async fn refresh_weights(&self) -> Result<usize, AppError> {
+        let (total_recipes,): (i64,) = sqlx::query_as("SELECT COUNT(DISTINCT recipe_id) FROM recipe_tags")
+            .fetch_one(self.pools.primary())
+            .await?;
+        let counts: Vec<(String, i64)> = sqlx::query_as("SELECT tag, COUNT(*) FROM recipe_tags GROUP BY tag")
+            .fetch_all(self.pools.primary())
+            .await?;
+
+        let tags: Vec<String> = counts.iter().map(|(tag, _)| tag.clone()).collect();
+        let recipes_counts: Vec<i32> = counts.iter().map(|(_, count)| *count as i32).collect();
+        let weights: Vec<f64> = counts.iter().map(|(_, count)| tag_weight(total_recipes, *count)).collect();
+
+        let mut tx = self.pools.primary().begin().await?;
+        sqlx::query("DELETE FROM recipe_tag_weights")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO recipe_tag_weights (tag, recipes_count, weight)
+            SELECT * FROM UNNEST($1::TEXT[], $2::INTEGER[], $3::DOUBLE PRECISION[])
+            "#
+        )
+        .bind(&tags)
+        .bind(&recipes_counts)
+        .bind(&weights)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(counts.len())
+    }
+
+    /// Запускает в фоне перенос тегов старых рецептов и периодический пересчет весов
+    pub fn start_refresh_task(self) {
+        tokio::spawn(async move {
+            match self.backfill().instrument(telemetry::job_span("recipe_tag_backfill")).await {
+                Ok(migrated) if migrated > 0 => info!("Normalized tags of {} recipes", migrated),
+                Ok(_) => {}
+                Err(e) => warn!("Recipe tag backfill failed: {:?}", e),
+            }
+
+            let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.refresh_weights().instrument(telemetry::job_span("recipe_tag_weights")).await {
+                    warn!("Failed to refresh recipe tag weights: {:?}", e);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{insert_recipe, insert_user};
+
+    #[test]
+    fn tags_are_normalized_by_the_rule_table() {
+        let cases = [
+            ("Завтрак", Some("завтрак")),
+            ("завтраки", Some("завтрак")),
+            ("breakfast", Some("завтрак")),
+            ("Breakfasts", Some("завтрак")),
+            ("  Ужин! ", Some("ужин")),
+            ("Dinners", Some("ужин")),
+            ("Грузинская кухня", Some("грузинская-кухня")),
+            ("Georgian cuisine", Some("грузинская-кухня")),
+            ("Gluten-free", Some("без-глютена")),
+            ("Быстрый", Some("быстро")),
+            ("Макароны", Some("паста")),
+            ("Пироги с грибами", Some("пирог-с-грибами")),
+            ("Блинчики", Some("блинчик")),
+            ("Пельмени", Some("пельмени")),
+            ("Ёлка", Some("елка")),
+            // Английские окончания
+            ("berries", Some("berry")),
+            ("dishes", Some("dish")),
+            ("sandwiches", Some("sandwich")),
+            ("tomatoes", Some("tomato")),
+            ("Chickens", Some("курица")),
+            // Только похожи на множественное число
+            ("hummus", Some("hummus")),
+            ("glass", Some("glass")),
+            ("bus", Some("bus")),
+            ("!!!", None),
+            ("", None),
+        ];
+
+        for (tag, expected) in cases {
+            assert_eq!(normalize_tag(tag).as_deref(), expected, "{:?}", tag);
+        }
+    }
+
+    #[test]
+    fn normalized_tags_keep_order_without_duplicates() {
+        let tags: Vec<String> = ["Завтраки", "breakfast", "Ужин", "завтрак", "  ", "ужины"].iter().map(|tag| tag.to_string()).collect();
+        assert_eq!(normalize_tags(&tags), vec!["завтрак", "ужин"]);
+    }
+
+    #[test]
+    fn rare_tags_weigh_more_but_common_ones_never_zero() {
+        assert!(tag_weight(100, 1) > tag_weight(100, 10));
+        assert!(tag_weight(100, 10) > tag_weight(100, 100));
+        assert_eq!(tag_weight(100, 100), 2f64.ln());
+        assert_eq!(tag_weight(0, 0), 2f64.ln());
+    }
+
+    async fn recipe(pool: &PgPool, created_by: Uuid, name: &str, is_public: bool, tags: &[&str]) -> Uuid {
+        let id = insert_recipe(pool, created_by, name, is_public).await;
+        sqlx::query("UPDATE recipes SET tags = $2 WHERE id = $1")
+            .bind(id)
+            .bind(tags)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    fn names(related: &[RelatedRecipe]) -> Vec<&str> {
+        related.iter().map(|recipe| recipe.name.as_str()).collect()
+    }
+
+    #[sqlx::test]
+    async fn related_recipes_rank_rare_shared_tags_first(pool: PgPool) {
+        let anna = insert_user(&pool, "Анна").await;
+        let boris = insert_user(&pool, "Борис").await;
+
+        // Теги вносятся как есть, до нормализации — их переносит backfill
+        let source = recipe(&pool, anna, "Чахохбили", true, &["Ужины", "Грузинская", "Мясо"]).await;
+        let khachapuri = recipe(&pool, boris, "Хачапури", true, &["georgian", "Выпечка"]).await;
+        for n in 1..=9 {
+            recipe(&pool, boris, &format!("Ужин {}", n), true, &["dinner"]).await;
+        }
+        let stew = recipe(&pool, boris, "Мясо по-грузински", true, &["Грузинская кухня", "мясо", "ужин"]).await;
+        recipe(&pool, boris, "Черновик Бориса", false, &["ужин", "грузинская"]).await;
+        let anna_draft = recipe(&pool, anna, "Черновик Анны", false, &["Dinner"]).await;
+        recipe(&pool, boris, "Без тегов", true, &[]).await;
+        let fork = recipe(&pool, anna, "Чахохбили (моя версия)", true, &["ужин", "грузинская-кухня", "мясо"]).await;
+        sqlx::query("UPDATE recipes SET original_recipe_id = $2 WHERE id = $1")
+            .bind(fork)
+            .bind(source)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let service = RecipeTagService::new(pool.clone());
+        assert_eq!(service.backfill().await.unwrap(), 15);
+        assert_eq!(service.backfill().await.unwrap(), 0);
+        let stored: Vec<String> = sqlx::query_scalar("SELECT tags FROM recipes WHERE id = $1")
+            .bind(source)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, vec!["ужин", "грузинская-кухня", "мясо"]);
+
+        // ужин — у 14 рецептов из 15, грузинская кухня — у 5, мясо — у 3
+        assert_eq!(service.refresh_weights().await.unwrap(), 4);
+
+        let related = service.related(source, anna).await.unwrap();
+        assert_eq!(related.len(), 10);
+        assert_eq!(names(&related[..2]), vec!["Мясо по-грузински", "Хачапури"]);
+        assert_eq!(related[0].id, stew);
+        assert_eq!(related[0].shared_tags, vec!["мясо", "грузинская-кухня", "ужин"]);
+        let expected = tag_weight(15, 3) + tag_weight(15, 5) + tag_weight(15, 14);
+        assert!((related[0].score - expected).abs() < 1e-9, "{}", related[0].score);
+        assert_eq!((related[1].id, related[1].shared_tags.clone()), (khachapuri, vec!["грузинская-кухня".to_string()]));
+        assert!((related[1].score - tag_weight(15, 5)).abs() < 1e-9);
+        // Своя копия не показывается, свой черновик — да, чужой черновик — нет
+        assert!(!related.iter().any(|recipe| recipe.id == fork || recipe.id == source));
+        assert!(!names(&related).contains(&"Черновик Бориса"));
+        assert!(related.iter().any(|recipe| recipe.id == anna_draft));
+        assert!(related[2..].iter().all(|recipe| recipe.shared_tags == vec!["ужин"]));
+
+        // Для другого пользователя копия Анны — обычный рецепт, похожий сильнее всех
+        let related = service.related(source, boris).await.unwrap();
+        let mut top: Vec<&str> = names(&related[..2]);
+        top.sort();
+        assert_eq!(top, vec!["Мясо по-грузински", "Чахохбили (моя версия)"]);
+        // Свой черновик с двумя общими тегами выше Хачапури
+        assert_eq!(names(&related[2..4]), vec!["Черновик Бориса", "Хачапури"]);
+        assert!(!names(&related).contains(&"Черновик Анны"));
+
+        assert!(matches!(service.related(anna_draft, boris).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.related(Uuid::new_v4(), anna).await, Err(AppError::NotFound(_))));
+    }
+}
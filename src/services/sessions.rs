@@ -0,0 +1,208 @@
+use serde_json::json;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::user::{SessionInfo, User},
+    services::{
+        mail::{MailMessage, MailService},
+        preferences::PreferencesService,
+    },
+    utils::{device::DeviceInfo, errors::AppError},
+};
+
+/// События безопасности аккаунта (таблица `security_events`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityEvent {
+    NewDeviceLogin,
+    SessionRevoked,
+}
+
+impl SecurityEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEvent::NewDeviceLogin => "new_device_login",
+            SecurityEvent::SessionRevoked => "session_revoked",
+        }
+    }
+}
+
+/// Сессии входа: список устройств, удаленный выход и учет новых устройств.
+/// Отзыв сессии — удаление строки `user_sessions`; соединения WebSocket этой сессии
+/// закрывает вызывающий код через `WebSocketManager::disconnect_sessions`.
+pub struct SessionService {
+    pool: DbPool,
+}
+
+impl SessionService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Действующие сессии пользователя, недавно использованные — первыми
+    pub async fn list(&self, user_id: Uuid, current: Option<Uuid>) -> Result<Vec<SessionInfo>, AppError> {
+        let sessions = sqlx::query_as::<_, SessionInfo>(
+            r#"
+            SELECT id, browser, os, ip_address, location, created_at, last_used_at, expires_at,
+                   COALESCE(id = $2, FALSE) AS current
+            FROM user_sessions
+            WHERE user_id = $1 AND expires_at > NOW()
+            ORDER BY last_used_at DESC NULLS LAST, created_at DESC
+            "#
+        )
+        .bind(user_id)
+        .bind(current)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Отзывает одну сессию пользователя
+    pub async fn revoke(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted = sqlx::query("DELETE FROM user_sessions WHERE id = $1 AND user_id = $2")
+            .bind(session_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(AppError::NotFound("Session not found".to_string()));
+        }
+
+        security_event(&mut tx, user_id, SecurityEvent::SessionRevoked, Some(session_id), json!({})).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Отзывает все сессии, кроме `keep` ("выйти на остальных устройствах").
+    /// Возвращает id отозванных сессий.
+    pub async fn revoke_others(&self, user_id: Uuid, keep: Option<Uuid>) -> Result<Vec<Uuid>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let revoked: Vec<(Uuid,)> = sqlx::query_as(
+            "DELETE FROM user_sessions WHERE user_id = $1 AND ($2::UUID IS NULL OR id <> $2) RETURNING id"
+        )
+        .bind(user_id)
+        .bind(keep)
+        .fetch_all(&mut *tx)
+        .await?;
+        let revoked: Vec<Uuid> = revoked.into_iter().map(|(id,)| id).collect();
+
+        if !revoked.is_empty() {
+            security_event(
+                &mut tx,
+                user_id,
+                SecurityEvent::SessionRevoked,
+                None,
+                json!({ "sessions": revoked, "kept": keep }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(revoked)
+    }
+
+    /// Запоминает устройство входа. Вход с устройства, которого у пользователя еще не было
+    /// (новая пара User-Agent + IP), пишется в журнал и, если включено, приходит письмом.
+    /// Самое первое устройство аккаунта новым не считается.
+    pub async fn record_login(&self, user: &User, session_id: Uuid, device: &DeviceInfo, mail: &MailService) -> Result<bool, AppError> {
+        let fingerprint = device.fingerprint();
+        let mut tx = self.pool.begin().await?;
+
+        let (known_devices, seen): (i64, bool) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(BOOL_OR(fingerprint = $2), FALSE) FROM known_devices WHERE user_id = $1"
+        )
+        .bind(user.id)
+        .bind(&fingerprint)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO known_devices (user_id, fingerprint) VALUES ($1, $2)
+            ON CONFLICT (user_id, fingerprint) DO UPDATE SET last_seen_at = NOW()
+            "#
+        )
+        .bind(user.id)
+        .bind(&fingerprint)
+        .execute(&mut *tx)
+        .await?;
+
+        let new_device = known_devices > 0 && !seen;
+        if new_device {
+            security_event(
+                &mut tx,
+                user.id,
+                SecurityEvent::NewDeviceLogin,
+                Some(session_id),
+                json!({
+                    "browser": device.browser,
+                    "os": device.os,
+                    "ip_address": device.ip_address,
+                    "location": device.location,
+                }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        if new_device {
+            let preferences = PreferencesService::new(self.pool.clone()).get_notifications(user.id).await?;
+            if preferences.new_device_email {
+                mail.send(&MailMessage {
+                    to: user.email.clone(),
+                    subject: "Вход с нового устройства".to_string(),
+                    html: new_device_html(device),
+                })
+                .await?;
+            }
+        }
+
+        Ok(new_device)
+    }
+}
+
+/// Запись в `security_events` в транзакции самого действия
+async fn security_event(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    event: SecurityEvent,
+    session_id: Option<Uuid>,
+    details: serde_json::Value,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO security_events (user_id, event, session_id, details) VALUES ($1, $2, $3, $4)"
+    )
+    .bind(user_id)
+    .bind(event.as_str())
+    .bind(session_id)
+    .bind(details)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+fn new_device_html(device: &DeviceInfo) -> String {
+    // Браузер, ОС и страна — из фиксированных списков, IP проверен при разборе запроса
+    let place = match (device.location.as_deref(), device.ip_address.as_deref()) {
+        (Some(location), Some(ip)) => format!(" ({}, IP {})", location, ip),
+        (Some(location), None) => format!(" ({})", location),
+        (None, Some(ip)) => format!(" (IP {})", ip),
+        (None, None) => String::new(),
+    };
+
+    format!(
+        "<p>Здравствуйте!</p>\
+         <p>В ваш аккаунт IT Cook выполнен вход с нового устройства: {}{}.</p>\
+         <p>Если это были не вы, завершите эту сессию в настройках безопасности и смените пароль.</p>",
+        device.describe(),
+        place,
+    )
+}
@@ -0,0 +1,506 @@
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::{FoodWaste, FridgeHistoryEntry, FridgeItem, FridgeItemStatus},
+        presets::{FoodPresets, LongLifeAlternative},
+        shopping::{ShoppingAction, ShoppingAdvice, ShoppingAdviceReason, ShoppingListItem},
+    },
+    services::fridge::FridgeService,
+    utils::{
+        errors::AppError,
+        ingredient_matcher::{normalize_ingredient, IngredientIndex},
+    },
+};
+
+/// Продукт, срок которого истекает не позже чем через столько дней после даты готовки, — «скоро испортится»
+const EXPIRING_SOON_DAYS: i64 = 2;
+/// Доля покупок, ушедших в отходы, с которой продукт считается привычно выбрасываемым
+const HABITUAL_WASTE_RATE: f32 = 0.5;
+/// Меньше покупок — слишком мало истории для вывода о привычке
+const MIN_PURCHASES: usize = 2;
+
+/// Советы к списку покупок: правила над содержимым холодильника, историей отходов
+/// и справочником долгохранящихся замен. Без обращения к ИИ.
+pub struct ShoppingAdviceService {
+    fridge_service: FridgeService,
+}
+
+impl ShoppingAdviceService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { fridge_service: FridgeService::new(pool) }
+    }
+
+    /// Список покупок с советами; `use_on` — день, к которому нужны продукты
+    pub async fn advise(&self, user_id: Uuid, names: Vec<String>, use_on: NaiveDate) -> Result<Vec<ShoppingListItem>, AppError> {
+        let inventory = self.fridge_service.get_user_items(user_id, None, None, None).await?;
+        let history = self.fridge_service.get_history(user_id, None).await?;
+        let wastes = self.fridge_service.get_waste_history(user_id, None, None).await?;
+        let alternatives = FoodPresets::get_long_life_alternatives();
+
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let pattern = waste_pattern(&name, &history, &wastes);
+                let advice = advise_item(&name, use_on, &inventory, pattern, &alternatives);
+                ShoppingListItem { name, advice }
+            })
+            .collect())
+    }
+}
+
+/// Сколько раз продукт покупали и сколько из этих покупок ушло в отходы
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WastePattern {
+    pub purchases: usize,
+    pub wasted: usize,
+}
+
+impl WastePattern {
+    pub fn rate(&self) -> Option<f32> {
+        (self.purchases > 0).then(|| self.wasted as f32 / self.purchases as f32)
+    }
+
+    pub fn is_habitual(&self) -> bool {
+        self.purchases >= MIN_PURCHASES && self.rate().unwrap_or(0.0) >= HABITUAL_WASTE_RATE
+    }
+}
+
+/// История продукта: закончившиеся продукты с похожим названием (удаленные без причины
+/// не учитываются) и отходы, записанные вручную, — каждая такая запись считается покупкой,
+/// целиком ушедшей в отходы. Продукт выброшен, если выброшено не меньше, чем съедено.
+pub fn waste_pattern(name: &str, history: &[FridgeHistoryEntry], wastes: &[FoodWaste]) -> WastePattern {
+    let index = IngredientIndex::new([name], false);
+    let mut pattern = WastePattern::default();
+
+    for entry in history
+        .iter()
+        .filter(|entry| entry.status != FridgeItemStatus::Removed && index.contains(&entry.name))
+    {
+        pattern.purchases += 1;
+        if entry.status == FridgeItemStatus::Wasted || (entry.wasted_value > 0.0 && entry.wasted_value >= entry.consumed_value) {
+            pattern.wasted += 1;
+        }
+    }
+
+    let manual = wastes
+        .iter()
+        .filter(|waste| waste.original_item_id.is_none() && index.contains(&waste.name))
+        .count();
+    pattern.purchases += manual;
+    pattern.wasted += manual;
+
+    pattern
+}
+
+/// Продукт холодильника, годный к `use_on`; из нескольких — тот, что испортится раньше
+pub fn usable_stock<'a>(name: &str, inventory: &'a [FridgeItem], use_on: NaiveDate) -> Option<&'a FridgeItem> {
+    let index = IngredientIndex::new([name], false);
+
+    inventory
+        .iter()
+        .filter(|item| item.is_active() && item.quantity > 0.0 && index.contains(&item.name))
+        .filter(|item| match item.expiry_date {
+            Some(expiry) => expiry.date_naive() >= use_on,
+            None => true,
+        })
+        .min_by_key(|item| (item.expiry_date.is_none(), item.expiry_date))
+}
+
+/// Долгохранящаяся замена продукта, если он сам ею еще не является
+pub fn long_life_alternative<'a>(name: &str, alternatives: &'a [LongLifeAlternative]) -> Option<&'a LongLifeAlternative> {
+    let normalized = normalize_ingredient(name);
+    let words: Vec<&str> = normalized.split(' ').collect();
+
+    alternatives.iter().find(|alternative| {
+        let already_long_life = normalize_ingredient(&alternative.alternative)
+            .split(' ')
+            .all(|word| words.contains(&word));
+        !already_long_life && IngredientIndex::new([alternative.product.as_str()], false).contains(name)
+    })
+}
+
+/// Правила по порядку: продукт уже есть (покупку пропустить; если он скоро испортится —
+/// использовать его первым), продукт обычно выбрасывается (взять долгохранящуюся замену
+/// или купить меньше). Нет совета — покупка не вызывает вопросов.
+pub fn advise_item(
+    name: &str,
+    use_on: NaiveDate,
+    inventory: &[FridgeItem],
+    pattern: WastePattern,
+    alternatives: &[LongLifeAlternative],
+) -> Option<ShoppingAdvice> {
+    let waste_rate = pattern.rate().filter(|_| pattern.is_habitual());
+
+    if let Some(item) = usable_stock(name, inventory, use_on) {
+        let expires_soon = item
+            .expiry_date
+            .map(|expiry| expiry.date_naive())
+            .filter(|expiry| (*expiry - use_on).num_days() <= EXPIRING_SOON_DAYS);
+
+        let (reason, message) = match expires_soon {
+            Some(expiry) => {
+                let mut message = format!(
+                    "В холодильнике есть «{}», годен до {}: используйте его вместо покупки",
+                    item.name,
+                    expiry.format("%d.%m")
+                );
+                if let Some(rate) = waste_rate {
+                    message.push_str(&format!(", а {}% покупок этого продукта уходит в отходы", percent(rate)));
+                }
+                (ShoppingAdviceReason::ExpiringInStock, message)
+            }
+            None => (
+                ShoppingAdviceReason::InStock,
+                format!("«{}» уже есть в холодильнике — покупать не нужно", item.name),
+            ),
+        };
+
+        return Some(ShoppingAdvice {
+            reason,
+            action: ShoppingAction::Skip,
+            message,
+            fridge_item_id: Some(item.id),
+            alternative: None,
+            waste_rate,
+        });
+    }
+
+    let rate = waste_rate?;
+    let advice = match long_life_alternative(name, alternatives) {
+        Some(alternative) => ShoppingAdvice {
+            reason: ShoppingAdviceReason::HabitualWaste,
+            action: ShoppingAction::SwitchToLongLasting,
+            message: format!(
+                "{}% покупок «{}» уходит в отходы. Возьмите «{}» — хранится до {} дней",
+                percent(rate),
+                name,
+                alternative.alternative,
+                alternative.shelf_life_days
+            ),
+            fridge_item_id: None,
+            alternative: Some(alternative.alternative.clone()),
+            waste_rate: Some(rate),
+        },
+        None => ShoppingAdvice {
+            reason: ShoppingAdviceReason::HabitualWaste,
+            action: ShoppingAction::ReduceQuantity,
+            message: format!("{}% покупок «{}» уходит в отходы. Купите меньше, чем обычно", percent(rate), name),
+            fridge_item_id: None,
+            alternative: None,
+            waste_rate: Some(rate),
+        },
+    };
+
+    Some(advice)
+}
+
+fn percent(rate: f32) -> u32 {
+    (rate * 100.0).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::fridge::{CreateFridgeItem, FridgeCategory, WasteReason},
+        services::fridge::new_item,
+        test_support::insert_user,
+    };
+
+    fn use_on() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 16).unwrap()
+    }
+
+    fn day(offset: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap() + Duration::days(offset)
+    }
+
+    fn stock(name: &str, expires_in_days: Option<i64>) -> FridgeItem {
+        new_item(
+            CreateFridgeItem {
+                user_id: Uuid::nil(),
+                name: name.to_string(),
+                brand: None,
+                quantity: 1.0,
+                unit: "pcs".to_string(),
+                category: FridgeCategory::Vegetables,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date: expires_in_days.map(day),
+                purchase_date: day(-3),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            },
+            day(-3),
+        )
+        .unwrap()
+    }
+
+    fn finished(name: &str, status: FridgeItemStatus, consumed_value: f64, wasted_value: f64) -> FridgeHistoryEntry {
+        FridgeHistoryEntry {
+            item_id: Uuid::new_v4(),
+            name: name.to_string(),
+            brand: None,
+            category: FridgeCategory::Vegetables,
+            quantity: 0.0,
+            unit: "pcs".to_string(),
+            status,
+            purchase_date: day(-30),
+            finished_at: Some(day(-20)),
+            purchased_value: consumed_value + wasted_value,
+            consumed_value,
+            wasted_value,
+            waste_reasons: Vec::new(),
+        }
+    }
+
+    fn manual_waste(name: &str, original_item_id: Option<Uuid>) -> FoodWaste {
+        FoodWaste {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            original_item_id,
+            name: name.to_string(),
+            brand: None,
+            wasted_quantity: 1.0,
+            unit: "pcs".to_string(),
+            category: FridgeCategory::Vegetables,
+            waste_reason: WasteReason::Spoiled,
+            wasted_value: None,
+            waste_date: day(-10),
+            notes: None,
+            created_at: day(-10),
+        }
+    }
+
+    fn pattern(purchases: usize, wasted: usize) -> WastePattern {
+        WastePattern { purchases, wasted }
+    }
+
+    #[test]
+    fn waste_pattern_counts_matching_purchases() {
+        use FridgeItemStatus::*;
+        let history = vec![
+            finished("Шпинат свежий", Wasted, 0.0, 0.0),
+            // Съедено меньше, чем выброшено, — покупка ушла в отходы
+            finished("шпинат", Consumed, 40.0, 60.0),
+            finished("Шпинат", Consumed, 60.0, 40.0),
+            finished("Шпинат", Consumed, 100.0, 0.0),
+            // Удаленные без причины и другие продукты не считаются
+            finished("Шпинат", Removed, 0.0, 0.0),
+            finished("Щавель", Wasted, 0.0, 0.0),
+        ];
+        let wastes = vec![
+            manual_waste("Шпинат", None),
+            // Отходы, списанные с продукта из истории, уже учтены выше
+            manual_waste("Шпинат", Some(history[2].item_id)),
+        ];
+
+        assert_eq!(waste_pattern("Шпинат", &history, &wastes), pattern(5, 3));
+        assert_eq!(waste_pattern("Щавель", &history, &wastes), pattern(1, 1));
+        assert_eq!(waste_pattern("Морковь", &history, &wastes), pattern(0, 0));
+    }
+
+    #[test]
+    fn habit_needs_enough_history_and_half_wasted() {
+        let cases = [
+            (pattern(0, 0), None, false),
+            // Одна выброшенная покупка — еще не привычка
+            (pattern(1, 1), Some(1.0), false),
+            (pattern(2, 1), Some(0.5), true),
+            (pattern(3, 1), Some(1.0 / 3.0), false),
+            (pattern(5, 4), Some(0.8), true),
+        ];
+        for (pattern, rate, habitual) in cases {
+            assert_eq!(pattern.rate(), rate, "{:?}", pattern);
+            assert_eq!(pattern.is_habitual(), habitual, "{:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn usable_stock_is_the_earliest_item_still_good_on_the_use_date() {
+        let expired = stock("Молоко", Some(-1));
+        let tomorrow = stock("Молоко 3,2%", Some(1));
+        let next_week = stock("молоко", Some(7));
+        let no_expiry = stock("Молоко", None);
+        let mut finished = stock("Молоко", Some(0));
+        finished.status = FridgeItemStatus::Consumed;
+        let mut empty = stock("Молоко", Some(0));
+        empty.quantity = 0.0;
+
+        let inventory = vec![no_expiry.clone(), next_week.clone(), expired, tomorrow.clone(), finished, empty];
+        assert_eq!(usable_stock("Молоко", &inventory, use_on()).map(|item| item.id), Some(tomorrow.id));
+        // К более поздней дате годен только продукт без срока и тот, что на неделю
+        assert_eq!(usable_stock("Молоко", &inventory, use_on() + Duration::days(3)).map(|item| item.id), Some(next_week.id));
+        assert_eq!(usable_stock("Молоко", &inventory, use_on() + Duration::days(30)).map(|item| item.id), Some(no_expiry.id));
+        assert!(usable_stock("Кефир", &inventory, use_on()).is_none());
+    }
+
+    #[test]
+    fn long_life_alternative_is_offered_only_for_the_perishable_form() {
+        let alternatives = FoodPresets::get_long_life_alternatives();
+        let cases = [
+            ("Шпинат", Some("Шпинат замороженный")),
+            ("шпинат свежий", Some("Шпинат замороженный")),
+            ("Молоко коровье", Some("Молоко ультрапастеризованное")),
+            ("Шпинат замороженный", None),
+            ("Картофель", None),
+        ];
+        for (name, expected) in cases {
+            let alternative = long_life_alternative(name, &alternatives).map(|alternative| alternative.alternative.as_str());
+            assert_eq!(alternative, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rules_apply_in_order() {
+        let alternatives = FoodPresets::get_long_life_alternatives();
+        let fresh = stock("Шпинат", Some(5));
+        let expiring = stock("Шпинат", Some(2));
+        let spoiled_before_use = stock("Шпинат", Some(-1));
+        let habitual = pattern(5, 3);
+
+        /// Причина, действие, продукт из холодильника, альтернатива и доля выброшенного
+        type Advice = (ShoppingAdviceReason, ShoppingAction, Option<Uuid>, Option<&'static str>, Option<f32>);
+
+        struct Case {
+            name: &'static str,
+            inventory: Vec<FridgeItem>,
+            pattern: WastePattern,
+            expected: Option<Advice>,
+            message: &'static str,
+        }
+        let cases = [
+            Case {
+                name: "Шпинат",
+                inventory: vec![fresh.clone()],
+                pattern: habitual,
+                expected: Some((ShoppingAdviceReason::InStock, ShoppingAction::Skip, Some(fresh.id), None, Some(0.6))),
+                message: "«Шпинат» уже есть в холодильнике — покупать не нужно",
+            },
+            Case {
+                name: "Шпинат",
+                inventory: vec![expiring.clone()],
+                pattern: pattern(1, 0),
+                expected: Some((ShoppingAdviceReason::ExpiringInStock, ShoppingAction::Skip, Some(expiring.id), None, None)),
+                message: "В холодильнике есть «Шпинат», годен до 18.10: используйте его вместо покупки",
+            },
+            Case {
+                name: "Шпинат",
+                inventory: vec![expiring.clone()],
+                pattern: habitual,
+                expected: Some((ShoppingAdviceReason::ExpiringInStock, ShoppingAction::Skip, Some(expiring.id), None, Some(0.6))),
+                message: "В холодильнике есть «Шпинат», годен до 18.10: используйте его вместо покупки, а 60% покупок этого продукта уходит в отходы",
+            },
+            // Испортится до готовки — как будто его нет
+            Case {
+                name: "Шпинат",
+                inventory: vec![spoiled_before_use],
+                pattern: habitual,
+                expected: Some((ShoppingAdviceReason::HabitualWaste, ShoppingAction::SwitchToLongLasting, None, Some("Шпинат замороженный"), Some(0.6))),
+                message: "60% покупок «Шпинат» уходит в отходы. Возьмите «Шпинат замороженный» — хранится до 180 дней",
+            },
+            Case {
+                name: "Кабачок",
+                inventory: Vec::new(),
+                pattern: habitual,
+                expected: Some((ShoppingAdviceReason::HabitualWaste, ShoppingAction::ReduceQuantity, None, None, Some(0.6))),
+                message: "60% покупок «Кабачок» уходит в отходы. Купите меньше, чем обычно",
+            },
+            Case { name: "Шпинат", inventory: Vec::new(), pattern: pattern(4, 1), expected: None, message: "" },
+            Case { name: "Шпинат", inventory: Vec::new(), pattern: pattern(0, 0), expected: None, message: "" },
+        ];
+
+        for case in cases {
+            let advice = advise_item(case.name, use_on(), &case.inventory, case.pattern, &alternatives);
+            let actual = advice.as_ref().map(|advice| {
+                (advice.reason, advice.action, advice.fridge_item_id, advice.alternative.as_deref(), advice.waste_rate)
+            });
+            assert_eq!(actual, case.expected, "{} with {:?}", case.name, case.pattern);
+            if let Some(advice) = advice {
+                assert_eq!(advice.message, case.message);
+            }
+        }
+    }
+
+    #[test]
+    fn advice_serializes_with_a_typed_reason_and_is_omitted_when_absent() {
+        let plain = ShoppingListItem { name: "Соль".to_string(), advice: None };
+        assert_eq!(serde_json::to_value(&plain).unwrap(), json!({ "name": "Соль" }));
+
+        let alternatives = FoodPresets::get_long_life_alternatives();
+        let advice = advise_item("Шпинат", use_on(), &[], pattern(2, 2), &alternatives);
+        let value = serde_json::to_value(ShoppingListItem { name: "Шпинат".to_string(), advice }).unwrap();
+        assert_eq!(value["advice"]["reason"], "habitual_waste");
+        assert_eq!(value["advice"]["action"], "switch_to_long_lasting");
+        assert_eq!(value["advice"]["waste_rate"], 1.0);
+        assert!(value["advice"].get("fridge_item_id").is_none());
+    }
+
+    async fn insert_item(pool: &PgPool, user_id: Uuid, name: &str, status: &str, expiry: Option<DateTime<Utc>>, consumed: f32, wasted: f32) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO fridge_items
+                (user_id, name, quantity, unit, category, purchase_date, expiry_date, status, finished_at, consumed_value, wasted_value)
+            VALUES ($1, $2, 1, 'pcs', 'vegetables', $3, $4, $5::fridge_item_status,
+                    CASE WHEN $5 = 'active' THEN NULL ELSE $3 END, $6, $7)
+            RETURNING id
+            "#
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(day(-20))
+        .bind(expiry)
+        .bind(status)
+        .bind(consumed)
+        .bind(wasted)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn advice_uses_the_users_own_fridge_and_history(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let other = insert_user(&pool, "Борис").await;
+
+        insert_item(&pool, user_id, "Шпинат", "wasted", None, 0.0, 0.0).await;
+        insert_item(&pool, user_id, "Шпинат", "consumed", None, 100.0, 0.0).await;
+        insert_item(&pool, user_id, "Шпинат", "wasted", None, 0.0, 0.0).await;
+        let milk = insert_item(&pool, user_id, "Молоко", "active", Some(day(1)), 0.0, 0.0).await;
+        // Чужой холодильник и чужие отходы не влияют на советы
+        insert_item(&pool, other, "Соль", "active", None, 0.0, 0.0).await;
+        insert_item(&pool, other, "Кабачок", "wasted", None, 0.0, 0.0).await;
+        insert_item(&pool, other, "Кабачок", "wasted", None, 0.0, 0.0).await;
+
+        let list = ShoppingAdviceService::new(pool.clone())
+            .advise(user_id, vec!["Шпинат".to_string(), "Молоко".to_string(), "Соль".to_string(), "Кабачок".to_string()], use_on())
+            .await
+            .unwrap();
+
+        let summary: Vec<(&str, Option<ShoppingAdviceReason>)> =
+            list.iter().map(|item| (item.name.as_str(), item.advice.as_ref().map(|advice| advice.reason))).collect();
+        assert_eq!(summary, vec![
+            ("Шпинат", Some(ShoppingAdviceReason::HabitualWaste)),
+            ("Молоко", Some(ShoppingAdviceReason::ExpiringInStock)),
+            ("Соль", None),
+            ("Кабачок", None),
+        ]);
+        let spinach = list[0].advice.as_ref().unwrap();
+        assert_eq!(spinach.alternative.as_deref(), Some("Шпинат замороженный"));
+        assert_eq!(spinach.waste_rate, Some(2.0 / 3.0));
+        assert_eq!(list[1].advice.as_ref().unwrap().fridge_item_id, Some(milk));
+    }
+}
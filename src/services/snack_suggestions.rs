@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        diary::{CreateDiaryEntry, DiaryEntry},
+        fridge::{ExpiryUrgency, FridgeItem},
+        presets::{FoodPresets, ProductPreset},
+        snack::{SnackNutritionSource, SnackSuggestion, SnackSuggestionsResponse},
+    },
+    services::{
+        admin_jobs::foods_by_name,
+        diary::DiaryService,
+        fridge::FridgeService,
+        nutrition_backfill::NutritionPer100g,
+    },
+    utils::{
+        errors::AppError,
+        format::plural_ru,
+        ingredient_matcher::IngredientIndex,
+        units::{to_base, BaseUnit},
+    },
+};
+
+const MAX_SUGGESTIONS: usize = 5;
+/// Меньше — уже не перекус, а проба
+const MIN_PORTION_GRAMS: f32 = 30.0;
+/// Больше — уже не перекус, даже если калорий хватает (огурцы, зелень)
+const MAX_PORTION_GRAMS: f32 = 250.0;
+/// Порция округляется вниз до шага, чтобы ее было удобно отмерить
+const PORTION_STEP_GRAMS: f32 = 10.0;
+
+/// Ограничения перекуса из запроса
+#[derive(Debug, Clone, Copy)]
+pub struct SnackLimits {
+    pub max_calories: f32,
+    pub min_protein: f32,
+}
+
+/// Перекусы из содержимого холодильника без обращения к ИИ: порция под лимит калорий,
+/// порядок — по белку на калорию и сроку годности
+pub struct SnackSuggestionService {
+    pool: DbPool,
+    fridge_service: FridgeService,
+}
+
+impl SnackSuggestionService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            fridge_service: FridgeService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    pub async fn suggest(&self, user_id: Uuid, limits: SnackLimits, now: DateTime<Utc>) -> Result<SnackSuggestionsResponse, AppError> {
+        let items = self.fridge_service.get_user_items(user_id, None, None, None).await?;
+        let nutrition = self.nutrition_for(&items).await?;
+        let presets = FoodPresets::get_product_presets();
+
+        let candidates: Vec<&FridgeItem> = items.iter().filter(|item| is_candidate(item, now)).collect();
+        let unevaluated_items = candidates.iter().filter(|item| !nutrition.contains_key(&item.id)).count();
+
+        let mut suggestions: Vec<SnackSuggestion> = candidates
+            .into_iter()
+            .filter_map(|item| {
+                let (per_100g, source) = nutrition.get(&item.id)?;
+                suggest_snack(item, per_100g, *source, limits, &presets, now)
+            })
+            .collect();
+        rank_snacks(&mut suggestions);
+        suggestions.truncate(MAX_SUGGESTIONS);
+
+        let note = (unevaluated_items > 0).then(|| {
+            format!(
+                "Не удалось оценить {} {}: нет данных о пищевой ценности",
+                unevaluated_items,
+                plural_ru(unevaluated_items as i64, "продукт", "продукта", "продуктов")
+            )
+        });
+
+        Ok(SnackSuggestionsResponse { suggestions, unevaluated_items, note })
+    }
+
+    /// Записывает выбранный перекус в дневник той порцией, которую предложил `suggest`
+    pub async fn log(&self, user_id: Uuid, item_id: Uuid, limits: SnackLimits, now: DateTime<Utc>) -> Result<DiaryEntry, AppError> {
+        let item = self.fridge_service.get_item_by_id(item_id, user_id).await?;
+        if !is_candidate(&item, now) {
+            return Err(AppError::BadRequest("This item cannot be suggested as a snack".to_string()));
+        }
+
+        let (per_100g, source) = self
+            .nutrition_for(std::slice::from_ref(&item))
+            .await?
+            .remove(&item.id)
+            .ok_or_else(|| AppError::UnprocessableEntity("No nutrition data for this item".to_string()))?;
+        let snack = suggest_snack(&item, &per_100g, source, limits, &FoodPresets::get_product_presets(), now)
+            .ok_or_else(|| AppError::BadRequest("This item does not fit the snack limits".to_string()))?;
+
+        DiaryService::new(self.pool.clone())
+            .create_entry(CreateDiaryEntry {
+                user_id,
+                food_name: item.name,
+                brand: item.brand,
+                portion_size: snack.portion_grams,
+                unit: "г".to_string(),
+                calories_per_100g: per_100g.calories,
+                protein_per_100g: per_100g.protein,
+                fat_per_100g: per_100g.fat,
+                carbs_per_100g: per_100g.carbs,
+                fiber_per_100g: per_100g.fiber,
+                sugar_per_100g: per_100g.sugar,
+                sodium_per_100g: per_100g.sodium,
+                media_id: None,
+                meal_type: "snack".to_string(),
+                consumed_at: now,
+            })
+            .await
+    }
+
+    /// Пищевая ценность продуктов: из поля «Пищевая ценность», иначе из базы продуктов по названию
+    async fn nutrition_for(&self, items: &[FridgeItem]) -> Result<HashMap<Uuid, (NutritionPer100g, SnackNutritionSource)>, AppError> {
+        let mut nutrition = HashMap::new();
+        let mut unlabeled = Vec::new();
+
+        for item in items {
+            match item.nutritional_info.as_deref().and_then(parse_nutrition_label) {
+                Some(label) => {
+                    nutrition.insert(item.id, (label, SnackNutritionSource::Label));
+                }
+                None => unlabeled.push(item),
+            }
+        }
+
+        if !unlabeled.is_empty() {
+            let names: Vec<String> = unlabeled.iter().map(|item| item.name.trim().to_lowercase()).collect();
+            let foods = foods_by_name(&self.pool, &names).await?;
+            for item in unlabeled {
+                if let Some(food) = foods.get(&item.name.trim().to_lowercase()) {
+                    nutrition.insert(item.id, (NutritionPer100g::from(food), SnackNutritionSource::FoodDatabase));
+                }
+            }
+        }
+
+        Ok(nutrition)
+    }
+}
+
+/// Продукт, который вообще можно съесть: в наличии и не просрочен
+fn is_candidate(item: &FridgeItem, now: DateTime<Utc>) -> bool {
+    item.is_active() && item.quantity > 0.0 && item.expiry_urgency(now) != Some(ExpiryUrgency::Expired)
+}
+
+/// Пищевая ценность на 100 г из свободного текста: «Ккал 59, белки 10 г, жиры 0,4 г, углеводы 3,6 г»
+/// или «59 kcal; protein 10 g». Калории и белок обязательны, жиры и углеводы без значения — ноль.
+pub fn parse_nutrition_label(text: &str) -> Option<NutritionPer100g> {
+    let text = text.to_lowercase();
+    let segments: Vec<&str> = text
+        .split(['\n', ';', '|'])
+        .flat_map(|line| line.split(", "))
+        .collect();
+    let value = |keywords: &[&str]| {
+        segments
+            .iter()
+            .find(|segment| keywords.iter().any(|keyword| segment.contains(keyword)))
+            .and_then(|segment| first_number(segment))
+    };
+
+    let calories = value(&["ккал", "калор", "kcal", "calor", "энерг", "energy"])?;
+    let protein = value(&["белк", "белок", "protein"])?;
+    let fat = value(&["жир", "fat"]).unwrap_or(0.0);
+    let carbs = value(&["углевод", "carb"]).unwrap_or(0.0);
+
+    let plausible = calories <= 900.0 && protein <= 100.0 && fat <= 100.0 && carbs <= 100.0;
+    plausible.then_some(NutritionPer100g {
+        calories,
+        protein,
+        fat,
+        carbs,
+        fiber: None,
+        sugar: None,
+        sodium: None,
+    })
+}
+
+/// Первое число в тексте; десятичная запятая допускается
+fn first_number(text: &str) -> Option<f32> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let number: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    number
+        .trim_end_matches(['.', ','])
+        .replace(',', ".")
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// Сколько граммов продукта есть; миллилитры считаются граммами.
+/// Штуки и неизвестные единицы — `None`: порция остатком не ограничивается.
+pub fn available_grams(quantity: f32, unit: &str) -> Option<f32> {
+    match to_base(quantity as f64, unit)? {
+        (amount, BaseUnit::Kg | BaseUnit::L) => Some((amount * 1000.0) as f32),
+        (_, BaseUnit::Piece) => None,
+    }
+}
+
+/// Порция в граммах под лимит калорий, не больше остатка и разумного перекуса,
+/// кратная 10 г. Слишком маленькая порция — `None`.
+pub fn snack_portion(calories_per_100g: f32, max_calories: f32, available: Option<f32>) -> Option<f32> {
+    let by_calories = if calories_per_100g > 0.0 {
+        max_calories / calories_per_100g * 100.0
+    } else {
+        MAX_PORTION_GRAMS
+    };
+    let portion = by_calories.min(MAX_PORTION_GRAMS).min(available.unwrap_or(MAX_PORTION_GRAMS));
+    let portion = (portion / PORTION_STEP_GRAMS).floor() * PORTION_STEP_GRAMS;
+
+    (portion >= MIN_PORTION_GRAMS).then_some(portion)
+}
+
+/// Перекус из продукта, если порция укладывается в лимиты
+pub fn suggest_snack(
+    item: &FridgeItem,
+    per_100g: &NutritionPer100g,
+    source: SnackNutritionSource,
+    limits: SnackLimits,
+    presets: &[ProductPreset],
+    now: DateTime<Utc>,
+) -> Option<SnackSuggestion> {
+    let portion = snack_portion(per_100g.calories, limits.max_calories, available_grams(item.quantity, &item.unit))?;
+    let scale = |per_100g: f32| round_1(per_100g * portion / 100.0);
+
+    let protein = scale(per_100g.protein);
+    if protein < limits.min_protein {
+        return None;
+    }
+
+    let protein_per_100_kcal = if per_100g.calories > 0.0 {
+        round_1(per_100g.protein / per_100g.calories * 100.0)
+    } else {
+        0.0
+    };
+
+    Some(SnackSuggestion {
+        item_id: item.id,
+        name: item.name.clone(),
+        portion_grams: portion,
+        calories: scale(per_100g.calories),
+        protein,
+        fat: scale(per_100g.fat),
+        carbs: scale(per_100g.carbs),
+        protein_per_100_kcal,
+        expiry_urgency: item.expiry_urgency(now),
+        serving_idea: serving_idea(&item.name, presets),
+        nutrition_source: source,
+    })
+}
+
+/// Первый быстрый способ использовать продукт из пресетов
+fn serving_idea(name: &str, presets: &[ProductPreset]) -> Option<String> {
+    presets
+        .iter()
+        .find(|preset| IngredientIndex::new([preset.name.as_str()], false).contains(name))
+        .and_then(|preset| preset.quick_uses.first().cloned())
+}
+
+/// Порядок подсказок: белок на калорию, поднятый для продуктов, которые скоро испортятся.
+/// При равенстве — по названию, чтобы порядок не зависел от хранилища.
+pub fn rank_snacks(suggestions: &mut [SnackSuggestion]) {
+    let score = |snack: &SnackSuggestion| snack.protein_per_100_kcal * urgency_weight(snack.expiry_urgency);
+    suggestions.sort_by(|a, b| score(b).total_cmp(&score(a)).then_with(|| a.name.cmp(&b.name)));
+}
+
+fn urgency_weight(urgency: Option<ExpiryUrgency>) -> f32 {
+    match urgency {
+        Some(ExpiryUrgency::Today) => 1.5,
+        Some(ExpiryUrgency::Soon) => 1.25,
+        _ => 1.0,
+    }
+}
+
+fn round_1(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::fridge::{CreateFridgeItem, FridgeCategory},
+        services::fridge::new_item,
+        test_support::insert_user,
+    };
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 15, 0, 0).unwrap()
+    }
+
+    fn per_100g(calories: f32, protein: f32) -> NutritionPer100g {
+        NutritionPer100g { calories, protein, fat: 2.0, carbs: 4.0, fiber: None, sugar: None, sodium: None }
+    }
+
+    fn limits(max_calories: f32, min_protein: f32) -> SnackLimits {
+        SnackLimits { max_calories, min_protein }
+    }
+
+    fn item(name: &str, quantity: f32, unit: &str, expires_in_hours: Option<i64>) -> FridgeItem {
+        new_item(
+            CreateFridgeItem {
+                user_id: Uuid::nil(),
+                name: name.to_string(),
+                brand: None,
+                quantity,
+                unit: unit.to_string(),
+                category: FridgeCategory::Dairy,
+                price_per_unit: None,
+                total_price: None,
+                expiry_date: expires_in_hours.map(|hours| now() + Duration::hours(hours)),
+                purchase_date: now() - Duration::days(1),
+                notes: None,
+                location: None,
+                store: None,
+                contains_allergens: Vec::new(),
+                contains_intolerances: Vec::new(),
+                suitable_for_diets: Vec::new(),
+                ingredients: None,
+                nutritional_info: None,
+            },
+            now(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn nutrition_labels_are_parsed_in_both_languages() {
+        let cases = [
+            ("Ккал 59, белки 10 г, жиры 0,4 г, углеводы 3,6 г", Some((59.0, 10.0, 0.4, 3.6))),
+            ("Энергетическая ценность: 121 ккал\nБелки: 17.2 г\nЖиры: 5 г", Some((121.0, 17.2, 5.0, 0.0))),
+            ("59 kcal; protein 10 g; fat 0.4 g; carbs 3.6 g", Some((59.0, 10.0, 0.4, 3.6))),
+            ("Calories 250 | Protein 8. | Fat 12", Some((250.0, 8.0, 12.0, 0.0))),
+            // Без белка или калорий — не оценить
+            ("Ккал 59, жиры 0,4 г", None),
+            ("белки 10 г", None),
+            ("Вкусный и полезный", None),
+            // Неправдоподобные значения на 100 г
+            ("Ккал 5900, белки 10 г", None),
+            ("Ккал 100, белки 110 г", None),
+        ];
+
+        for (text, expected) in cases {
+            let parsed = parse_nutrition_label(text).map(|n| (n.calories, n.protein, n.fat, n.carbs));
+            assert_eq!(parsed, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn available_amount_is_converted_to_grams() {
+        let cases = [
+            (0.5, "kg", Some(500.0)),
+            (200.0, "г", Some(200.0)),
+            (1.0, "л", Some(1000.0)),
+            (330.0, "ml", Some(330.0)),
+            (10.0, "шт", None),
+            (1.0, "пачка", None),
+        ];
+        for (quantity, unit, expected) in cases {
+            assert_eq!(available_grams(quantity, unit), expected, "{} {}", quantity, unit);
+        }
+    }
+
+    #[test]
+    fn portion_fits_the_cap_the_stock_and_a_sensible_snack() {
+        let cases = [
+            // Творог 121 ккал: 165 г под 200 ккал, вниз до 10 г
+            (121.0, 200.0, None, Some(160.0)),
+            // Йогурт 59 ккал: по калориям 338 г, но перекус не больше 250 г
+            (59.0, 200.0, None, Some(250.0)),
+            // Остатка меньше, чем позволяют калории
+            (59.0, 200.0, Some(125.0), Some(120.0)),
+            // Миндаль 609 ккал: 32 г — еще перекус, под 150 ккал — уже нет
+            (609.0, 200.0, None, Some(30.0)),
+            (609.0, 150.0, None, None),
+            (121.0, 200.0, Some(25.0), None),
+            (0.0, 200.0, None, Some(250.0)),
+            (100.0, 30.0, None, Some(30.0)),
+        ];
+
+        for (calories, max_calories, available, expected) in cases {
+            assert_eq!(snack_portion(calories, max_calories, available), expected, "{} kcal under {} from {:?}", calories, max_calories, available);
+        }
+    }
+
+    #[test]
+    fn suggestion_scales_macros_to_the_portion() {
+        let presets = FoodPresets::get_product_presets();
+        let yogurt = item("Йогурт натуральный", 0.5, "kg", Some(12));
+
+        let snack = suggest_snack(&yogurt, &per_100g(59.0, 10.0), SnackNutritionSource::Label, limits(200.0, 10.0), &presets, now()).unwrap();
+        assert_eq!(snack.portion_grams, 250.0);
+        assert_eq!((snack.calories, snack.protein, snack.fat, snack.carbs), (147.5, 25.0, 5.0, 10.0));
+        assert_eq!(snack.protein_per_100_kcal, 16.9);
+        assert_eq!(snack.expiry_urgency, Some(ExpiryUrgency::Today));
+        assert_eq!(snack.serving_idea.as_deref(), Some("Соус к мясу: йогурт с солью, перцем и сушеным чесноком"));
+
+        // Белка в порции меньше минимума — не предлагается
+        let almonds = item("Миндаль", 200.0, "г", None);
+        assert!(suggest_snack(&almonds, &per_100g(609.0, 18.6), SnackNutritionSource::Label, limits(200.0, 10.0), &presets, now()).is_none());
+        let snack = suggest_snack(&almonds, &per_100g(609.0, 18.6), SnackNutritionSource::Label, limits(200.0, 5.0), &presets, now()).unwrap();
+        assert_eq!((snack.portion_grams, snack.protein), (30.0, 5.6));
+        assert!(snack.serving_idea.is_some());
+        assert_eq!(snack.expiry_urgency, None);
+
+        let unknown = item("Сырок глазированный", 0.1, "kg", None);
+        let snack = suggest_snack(&unknown, &per_100g(400.0, 8.0), SnackNutritionSource::FoodDatabase, limits(200.0, 0.0), &presets, now()).unwrap();
+        assert_eq!((snack.portion_grams, snack.serving_idea), (50.0, None));
+    }
+
+    fn ranked(snacks: &[(&str, f32, Option<ExpiryUrgency>)]) -> Vec<String> {
+        let mut suggestions: Vec<SnackSuggestion> = snacks
+            .iter()
+            .map(|(name, density, urgency)| SnackSuggestion {
+                item_id: Uuid::new_v4(),
+                name: name.to_string(),
+                portion_grams: 100.0,
+                calories: 100.0,
+                protein: *density,
+                fat: 0.0,
+                carbs: 0.0,
+                protein_per_100_kcal: *density,
+                expiry_urgency: *urgency,
+                serving_idea: None,
+                nutrition_source: SnackNutritionSource::Label,
+            })
+            .collect();
+        rank_snacks(&mut suggestions);
+        suggestions.into_iter().map(|snack| snack.name).collect()
+    }
+
+    #[test]
+    fn ranking_weighs_protein_density_by_expiry_urgency() {
+        use ExpiryUrgency::*;
+
+        assert_eq!(ranked(&[("сыр", 7.0, None), ("грудка", 20.0, None), ("творог", 14.0, None)]), vec!["грудка", "творог", "сыр"]);
+        // Творог, который испортится сегодня, обгоняет более белковую грудку (14 × 1.5 > 20)
+        assert_eq!(ranked(&[("грудка", 20.0, Some(Later)), ("творог", 14.0, Some(Today))]), vec!["творог", "грудка"]);
+        // «Скоро» весит меньше: 14 × 1.25 < 20
+        assert_eq!(ranked(&[("грудка", 20.0, Some(ThisWeek)), ("творог", 14.0, Some(Soon))]), vec!["грудка", "творог"]);
+        assert_eq!(ranked(&[("грудка", 16.0, None), ("творог", 12.8, Some(Soon))]), vec!["грудка", "творог"]);
+        // Равный счет — по названию
+        assert_eq!(ranked(&[("яйца", 10.0, None), ("кефир", 10.0, None), ("йогурт", 8.0, Some(Soon))]), vec!["йогурт", "кефир", "яйца"]);
+    }
+
+    async fn insert_item(
+        pool: &PgPool,
+        user_id: Uuid,
+        name: &str,
+        quantity: f32,
+        unit: &str,
+        expires_in_hours: Option<i64>,
+        label: Option<&str>,
+    ) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO fridge_items (user_id, name, quantity, unit, category, purchase_date, expiry_date, nutritional_info)
+            VALUES ($1, $2, $3, $4, 'other', $5, $6, $7)
+            RETURNING id
+            "#
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(quantity)
+        .bind(unit)
+        .bind(now() - Duration::days(2))
+        .bind(expires_in_hours.map(|hours| now() + Duration::hours(hours)))
+        .bind(label)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn fridge_gives_at_most_five_ranked_snacks_and_counts_unknown_items(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        sqlx::query("INSERT INTO food_items (name, calories_per_100g, protein_per_100g, fat_per_100g, carbs_per_100g, verified) VALUES ('куриная грудка', 113, 23.6, 1.9, 0.4, TRUE)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let yogurt = insert_item(&pool, user_id, "Йогурт натуральный", 500.0, "г", Some(12), Some("59 kcal; protein 10 g; fat 0.4 g; carbs 3.6 g")).await;
+        insert_item(&pool, user_id, "куриная грудка", 0.3, "кг", Some(24 * 10), None).await;
+        insert_item(&pool, user_id, "Творог 5%", 400.0, "г", Some(24 * 5), Some("Ккал 121, белки 17,2 г, жиры 5 г, углеводы 1,8 г")).await;
+        insert_item(&pool, user_id, "Тофу", 300.0, "г", None, Some("Ккал 76, белки 8 г")).await;
+        insert_item(&pool, user_id, "Яйца куриные", 10.0, "шт", None, Some("Ккал 157, белки 12,7 г")).await;
+        insert_item(&pool, user_id, "Сыр твердый", 200.0, "г", None, Some("Ккал 350, белки 25 г")).await;
+        let almonds = insert_item(&pool, user_id, "Миндаль", 200.0, "г", None, Some("Ккал 609, белки 18,6 г")).await;
+        // Без пищевой ценности — в примечании; просроченный не оценивается и не считается
+        let kefir = insert_item(&pool, user_id, "Кефир", 1.0, "л", None, None).await;
+        insert_item(&pool, user_id, "Хлеб", 300.0, "г", Some(-2), Some("Ккал 250, белки 8 г")).await;
+        // Чужой холодильник
+        let other = insert_user(&pool, "Борис").await;
+        let foreign = insert_item(&pool, other, "Творог 9%", 200.0, "г", None, Some("Ккал 159, белки 16,7 г")).await;
+
+        let service = SnackSuggestionService::new(pool.clone());
+        let response = service.suggest(user_id, limits(200.0, 10.0), now()).await.unwrap();
+
+        let summary: Vec<(&str, f32, f32, SnackNutritionSource)> = response
+            .suggestions
+            .iter()
+            .map(|snack| (snack.name.as_str(), snack.portion_grams, snack.protein, snack.nutrition_source))
+            .collect();
+        assert_eq!(summary, vec![
+            ("Йогурт натуральный", 250.0, 25.0, SnackNutritionSource::Label),
+            ("куриная грудка", 170.0, 40.1, SnackNutritionSource::FoodDatabase),
+            ("Творог 5%", 160.0, 27.5, SnackNutritionSource::Label),
+            ("Тофу", 250.0, 20.0, SnackNutritionSource::Label),
+            ("Яйца куриные", 120.0, 15.2, SnackNutritionSource::Label),
+        ]);
+        assert!(response.suggestions.iter().all(|snack| snack.calories <= 200.0));
+        assert_eq!(response.unevaluated_items, 1);
+        assert_eq!(response.note.as_deref(), Some("Не удалось оценить 1 продукт: нет данных о пищевой ценности"));
+
+        // Запись в дневник — той же порцией
+        let entry = service.log(user_id, yogurt, limits(200.0, 10.0), now()).await.unwrap();
+        assert_eq!((entry.food_name.as_str(), entry.portion_size, entry.meal_type.as_str()), ("Йогурт натуральный", 250.0, "snack"));
+        assert_eq!((entry.calories_per_100g, entry.protein_per_100g), (59.0, 10.0));
+        assert_eq!(entry.consumed_at, now());
+
+        assert!(matches!(service.log(user_id, almonds, limits(200.0, 10.0), now()).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(service.log(user_id, kefir, limits(200.0, 10.0), now()).await, Err(AppError::UnprocessableEntity(_))));
+        assert!(matches!(service.log(user_id, foreign, limits(200.0, 10.0), now()).await, Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::{
+        fridge::{Allergen, AllergenSeverity, CreateFridgeItem, DietType, DietaryProfile, FridgeItem, Intolerance},
+        presets::{
+            ExcludedStarterItem, FoodPresets, ProductPreset, StarterPack, StarterPackItem, StarterPackItemView,
+            StarterPackSelection, StarterPackView,
+        },
+    },
+    services::{
+        dietary::{mentions_allergen, DietaryService},
+        fridge::FridgeService,
+    },
+    utils::{errors::AppError, format::Locale},
+};
+
+/// Если после фильтрации по профилю осталось меньше продуктов, набор не показывается
+const MIN_PACK_ITEMS: usize = 5;
+
+/// Стартовые наборы продуктов для пустого холодильника с учетом диетического профиля
+pub struct StarterPackService {
+    pool: DbPool,
+}
+
+impl StarterPackService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self, user_id: Uuid, locale: Locale) -> Result<Vec<StarterPackView>, AppError> {
+        let profile = DietaryService::new(self.pool.clone()).get_profile(user_id).await?;
+        let presets = FoodPresets::get_product_presets();
+
+        Ok(FoodPresets::get_starter_packs()
+            .iter()
+            .filter_map(|pack| pack_view(pack, &presets, profile.as_ref(), locale))
+            .collect())
+    }
+
+    /// Добавляет выбранные продукты набора одним пакетом. Продукты, убранные из набора
+    /// профилем, выбрать нельзя; срок годности — покупка сегодня плюс типичный срок хранения.
+    pub async fn apply(
+        &self,
+        user_id: Uuid,
+        pack_id: &str,
+        selection: &[StarterPackSelection],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<FridgeItem>, AppError> {
+        let pack = FoodPresets::get_starter_packs()
+            .into_iter()
+            .find(|pack| pack.id == pack_id)
+            .ok_or_else(|| AppError::NotFound("Starter pack not found".to_string()))?;
+        let profile = DietaryService::new(self.pool.clone()).get_profile(user_id).await?;
+        let presets = FoodPresets::get_product_presets();
+
+        // Язык не важен: нужен только состав набора после фильтрации
+        let view = pack_view(&pack, &presets, profile.as_ref(), Locale::Ru)
+            .ok_or_else(|| AppError::BadRequest("This starter pack does not fit your dietary profile".to_string()))?;
+
+        let items = selected_items(user_id, &pack, &view, &presets, selection, now)?;
+        FridgeService::new(self.pool.clone()).add_items(user_id, items).await
+    }
+}
+
+/// Набор после фильтрации по профилю; `None` — в наборе почти ничего не осталось
+pub fn pack_view(
+    pack: &StarterPack,
+    presets: &[ProductPreset],
+    profile: Option<&DietaryProfile>,
+    locale: Locale,
+) -> Option<StarterPackView> {
+    let mut items = Vec::new();
+    let mut excluded_items = Vec::new();
+
+    for item in &pack.items {
+        let Some(preset) = find_preset(presets, &item.preset_name) else {
+            continue;
+        };
+        let name = item_name(item, locale);
+
+        match profile.map_or(Verdict::Fits, |profile| check_preset(profile, preset, locale)) {
+            Verdict::Excluded(reason) => excluded_items.push(ExcludedStarterItem {
+                preset_name: item.preset_name.clone(),
+                name,
+                reason,
+            }),
+            verdict => items.push(StarterPackItemView {
+                preset_name: item.preset_name.clone(),
+                name,
+                quantity: item.quantity,
+                unit: item.unit.clone(),
+                category: preset.category.clone(),
+                location: preset.storage_location.clone(),
+                shelf_life_days: preset.typical_shelf_life_days,
+                warning: match verdict {
+                    Verdict::Warning(warning) => Some(warning),
+                    _ => None,
+                },
+            }),
+        }
+    }
+
+    if items.len() < MIN_PACK_ITEMS {
+        return None;
+    }
+
+    let (name, description) = match locale {
+        Locale::Ru => (&pack.name_ru, &pack.description_ru),
+        Locale::En => (&pack.name_en, &pack.description_en),
+    };
+
+    Some(StarterPackView {
+        id: pack.id.clone(),
+        name: name.clone(),
+        description: description.clone(),
+        items,
+        excluded_items,
+    })
+}
+
+/// Продукты для добавления: ровно выбранные, без повторов, с количеством из запроса или из набора
+pub fn selected_items(
+    user_id: Uuid,
+    pack: &StarterPack,
+    view: &StarterPackView,
+    presets: &[ProductPreset],
+    selection: &[StarterPackSelection],
+    now: DateTime<Utc>,
+) -> Result<Vec<CreateFridgeItem>, AppError> {
+    if selection.is_empty() {
+        return Err(AppError::BadRequest("Select at least one item".to_string()));
+    }
+
+    let mut seen = HashSet::new();
+    let mut items = Vec::with_capacity(selection.len());
+
+    for selected in selection {
+        if !seen.insert(selected.preset_name.as_str()) {
+            return Err(AppError::BadRequest(format!("Item '{}' is selected twice", selected.preset_name)));
+        }
+        if view.excluded_items.iter().any(|item| item.preset_name == selected.preset_name) {
+            return Err(AppError::BadRequest(format!(
+                "Item '{}' is excluded by your dietary profile",
+                selected.preset_name
+            )));
+        }
+
+        let (pack_item, preset) = pack
+            .items
+            .iter()
+            .find(|item| item.preset_name == selected.preset_name)
+            .and_then(|item| find_preset(presets, &item.preset_name).map(|preset| (item, preset)))
+            .ok_or_else(|| AppError::BadRequest(format!("Item '{}' is not in this starter pack", selected.preset_name)))?;
+
+        let quantity = selected.quantity.unwrap_or(pack_item.quantity);
+        if !quantity.is_finite() || quantity <= 0.0 {
+            return Err(AppError::BadRequest("Quantity must be a positive number".to_string()));
+        }
+
+        items.push(CreateFridgeItem {
+            user_id,
+            name: preset.name.clone(),
+            brand: None,
+            quantity,
+            unit: pack_item.unit.clone(),
+            category: preset.category.clone(),
+            price_per_unit: None,
+            total_price: None,
+            expiry_date: preset.typical_shelf_life_days.map(|days| now + Duration::days(days as i64)),
+            purchase_date: now,
+            notes: None,
+            location: Some(preset.storage_location.clone()),
+            store: None,
+            contains_allergens: preset.common_allergens.clone(),
+            contains_intolerances: preset.common_intolerances.clone(),
+            suitable_for_diets: preset.suitable_diets.clone(),
+            ingredients: None,
+            nutritional_info: None,
+        });
+    }
+
+    Ok(items)
+}
+
+fn find_preset<'a>(presets: &'a [ProductPreset], name: &str) -> Option<&'a ProductPreset> {
+    presets.iter().find(|preset| preset.name == name)
+}
+
+fn item_name(item: &StarterPackItem, locale: Locale) -> String {
+    match locale {
+        Locale::Ru => item.preset_name.clone(),
+        Locale::En => item.name_en.clone(),
+    }
+}
+
+enum Verdict {
+    Fits,
+    /// Остается в наборе с пометкой
+    Warning(String),
+    /// Убирается из набора с объяснением
+    Excluded(String),
+}
+
+/// Проверка продукта каталога по профилю: диеты, аллергии, непереносимости и свои ограничения.
+/// Аллергии и ограничения «желательно избегать» только помечаются, остальные — исключают продукт.
+fn check_preset(profile: &DietaryProfile, preset: &ProductPreset, locale: Locale) -> Verdict {
+    if let Some(diet) = profile.diets.iter().find(|diet| !preset.suitable_diets.contains(diet)) {
+        return Verdict::Excluded(match locale {
+            Locale::Ru => format!("Не подходит для диеты «{}»", diet_name(diet, locale)),
+            Locale::En => format!("Not suitable for the {} diet", diet_name(diet, locale)),
+        });
+    }
+
+    let mut warning = None;
+
+    for entry in &profile.allergies {
+        let contains = preset.common_allergens.contains(&entry.allergen) || mentions_allergen(&preset.name, &entry.allergen);
+        if !contains {
+            continue;
+        }
+        let allergen = allergen_name(&entry.allergen, locale);
+        if entry.severity != AllergenSeverity::Avoid {
+            return Verdict::Excluded(match locale {
+                Locale::Ru => format!("Содержит аллерген «{}»", allergen),
+                Locale::En => format!("Contains allergen: {}", allergen),
+            });
+        }
+        if warning.is_none() {
+            warning = Some(match locale {
+                Locale::Ru => format!("Содержит аллерген «{}» — вы предпочитаете его избегать", allergen),
+                Locale::En => format!("Contains {}, which you prefer to avoid", allergen),
+            });
+        }
+    }
+
+    if let Some(intolerance) = preset.common_intolerances.iter().find(|i| profile.intolerances.contains(i)) {
+        let intolerance = intolerance_name(intolerance, locale);
+        return Verdict::Excluded(match locale {
+            Locale::Ru => format!("Содержит то, что вы не переносите: {}", intolerance),
+            Locale::En => format!("Contains {}, which you don't tolerate", intolerance),
+        });
+    }
+
+    for restriction in profile.custom_restrictions.iter().filter(|restriction| restriction.matches(&preset.name)) {
+        if restriction.severity != AllergenSeverity::Avoid {
+            return Verdict::Excluded(match locale {
+                Locale::Ru => format!("Ваше ограничение: «{}»", restriction.name),
+                Locale::En => format!("Your restriction: “{}”", restriction.name),
+            });
+        }
+        if warning.is_none() {
+            warning = Some(match locale {
+                Locale::Ru => format!("Содержит «{}» — вы предпочитаете этого избегать", restriction.name),
+                Locale::En => format!("Contains “{}”, which you prefer to avoid", restriction.name),
+            });
+        }
+    }
+
+    warning.map_or(Verdict::Fits, Verdict::Warning)
+}
+
+fn diet_name(diet: &DietType, locale: Locale) -> String {
+    FoodPresets::get_diet_info()
+        .into_iter()
+        .find(|info| &info.diet == diet)
+        .map(|info| match locale {
+            Locale::Ru => info.name_ru,
+            Locale::En => info.name_en,
+        })
+        .unwrap_or_else(|| format!("{:?}", diet))
+}
+
+fn allergen_name(allergen: &Allergen, locale: Locale) -> String {
+    FoodPresets::get_allergen_info()
+        .into_iter()
+        .find(|info| &info.allergen == allergen)
+        .map(|info| match locale {
+            Locale::Ru => info.name_ru,
+            Locale::En => info.name_en,
+        })
+        .unwrap_or_else(|| format!("{:?}", allergen))
+}
+
+fn intolerance_name(intolerance: &Intolerance, locale: Locale) -> String {
+    FoodPresets::get_intolerance_info()
+        .into_iter()
+        .find(|info| &info.intolerance == intolerance)
+        .map(|info| match locale {
+            Locale::Ru => info.name_ru,
+            Locale::En => info.name_en,
+        })
+        .unwrap_or_else(|| format!("{:?}", intolerance))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        models::fridge::{AllergyEntry, CustomRestriction, UpdateDietaryProfile},
+        test_support::insert_user,
+    };
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()
+    }
+
+    fn profile() -> DietaryProfile {
+        DietaryProfile {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            allergies: Vec::new(),
+            intolerances: Vec::new(),
+            diets: Vec::new(),
+            custom_restrictions: Vec::new(),
+            severity_notes: None,
+            created_at: now(),
+            updated_at: now(),
+        }
+    }
+
+    fn pack(id: &str) -> StarterPack {
+        FoodPresets::get_starter_packs().into_iter().find(|pack| pack.id == id).unwrap()
+    }
+
+    fn view(id: &str, profile: Option<&DietaryProfile>, locale: Locale) -> Option<StarterPackView> {
+        pack_view(&pack(id), &FoodPresets::get_product_presets(), profile, locale)
+    }
+
+    fn item_names(view: &StarterPackView) -> Vec<&str> {
+        view.items.iter().map(|item| item.preset_name.as_str()).collect()
+    }
+
+    fn excluded_names(view: &StarterPackView) -> Vec<&str> {
+        view.excluded_items.iter().map(|item| item.preset_name.as_str()).collect()
+    }
+
+    fn select(items: &[(&str, Option<f32>)]) -> Vec<StarterPackSelection> {
+        items
+            .iter()
+            .map(|(preset_name, quantity)| StarterPackSelection { preset_name: preset_name.to_string(), quantity: *quantity })
+            .collect()
+    }
+
+    #[test]
+    fn every_pack_item_is_backed_by_a_preset_in_both_locales() {
+        let presets = FoodPresets::get_product_presets();
+        for pack in FoodPresets::get_starter_packs() {
+            assert!((10..=15).contains(&pack.items.len()), "{}", pack.id);
+            for item in &pack.items {
+                assert!(find_preset(&presets, &item.preset_name).is_some(), "{} / {}", pack.id, item.preset_name);
+                assert!(!item.name_en.is_empty());
+            }
+
+            let full = pack_view(&pack, &presets, None, Locale::Ru).unwrap();
+            assert_eq!(full.items.len(), pack.items.len());
+            assert!(full.excluded_items.is_empty());
+            let english = pack_view(&pack, &presets, None, Locale::En).unwrap();
+            assert_eq!((english.name.as_str(), english.description.as_str()), (pack.name_en.as_str(), pack.description_en.as_str()));
+        }
+    }
+
+    #[test]
+    fn vegan_never_sees_animal_products() {
+        let vegan = DietaryProfile { diets: vec![DietType::Vegan], ..profile() };
+
+        let basic = view("basic", Some(&vegan), Locale::Ru).unwrap();
+        assert_eq!(excluded_names(&basic), vec!["Молоко коровье", "Яйца куриные", "Масло сливочное", "Сыр твердый", "Хлеб пшеничный", "Куриная грудка"]);
+        assert!(!item_names(&basic).contains(&"Куриная грудка"));
+        assert!(basic.excluded_items.iter().all(|item| item.reason.starts_with("Не подходит для диеты «")));
+
+        let english = view("basic", Some(&vegan), Locale::En).unwrap();
+        assert_eq!(english.excluded_items[5].name, "Chicken breast");
+        assert!(english.excluded_items[5].reason.starts_with("Not suitable for the "));
+
+        let vegan_start = view("vegan-start", Some(&vegan), Locale::Ru).unwrap();
+        assert!(vegan_start.excluded_items.is_empty());
+    }
+
+    #[test]
+    fn strict_allergies_strip_items_and_soft_ones_only_warn() {
+        let strict = DietaryProfile {
+            allergies: vec![AllergyEntry { allergen: Allergen::TreeNuts, severity: AllergenSeverity::LifeThreatening }],
+            ..profile()
+        };
+        let mediterranean = view("mediterranean", Some(&strict), Locale::Ru).unwrap();
+        assert_eq!(excluded_names(&mediterranean), vec!["Миндаль"]);
+        assert!(mediterranean.excluded_items[0].reason.starts_with("Содержит аллерген «"));
+
+        let avoid = DietaryProfile {
+            allergies: vec![AllergyEntry { allergen: Allergen::TreeNuts, severity: AllergenSeverity::Avoid }],
+            ..profile()
+        };
+        let mediterranean = view("mediterranean", Some(&avoid), Locale::Ru).unwrap();
+        assert!(mediterranean.excluded_items.is_empty());
+        let warned: Vec<&str> = mediterranean.items.iter().filter(|item| item.warning.is_some()).map(|item| item.preset_name.as_str()).collect();
+        assert_eq!(warned, vec!["Миндаль"]);
+
+        let lactose = DietaryProfile { intolerances: vec![Intolerance::Lactose], ..profile() };
+        let basic = view("basic", Some(&lactose), Locale::Ru).unwrap();
+        assert!(excluded_names(&basic).contains(&"Молоко коровье"));
+        assert!(basic.excluded_items[0].reason.starts_with("Содержит то, что вы не переносите"));
+
+        let garlic = |severity| DietaryProfile {
+            custom_restrictions: vec![CustomRestriction { name: "Чеснок".to_string(), severity, keywords: Vec::new() }],
+            ..profile()
+        };
+        let mediterranean = view("mediterranean", Some(&garlic(AllergenSeverity::Strict)), Locale::En).unwrap();
+        assert_eq!(excluded_names(&mediterranean), vec!["Чеснок"]);
+        assert_eq!(mediterranean.excluded_items[0].reason, "Your restriction: “Чеснок”");
+        let mediterranean = view("mediterranean", Some(&garlic(AllergenSeverity::Avoid)), Locale::Ru).unwrap();
+        let garlic_item = mediterranean.items.iter().find(|item| item.preset_name == "Чеснок").unwrap();
+        assert_eq!(garlic_item.warning.as_deref(), Some("Содержит «Чеснок» — вы предпочитаете этого избегать"));
+    }
+
+    #[test]
+    fn pack_with_too_few_items_left_is_hidden() {
+        let mut small = pack("basic");
+        small.items.truncate(MIN_PACK_ITEMS);
+        let presets = FoodPresets::get_product_presets();
+        let lactose = DietaryProfile { intolerances: vec![Intolerance::Lactose], ..profile() };
+
+        assert!(pack_view(&small, &presets, None, Locale::Ru).is_some());
+        assert!(pack_view(&small, &presets, Some(&lactose), Locale::Ru).is_none());
+    }
+
+    #[test]
+    fn selection_becomes_exactly_the_chosen_items() {
+        let vegan = DietaryProfile { diets: vec![DietType::Vegan], ..profile() };
+        let basic = pack("basic");
+        let presets = FoodPresets::get_product_presets();
+        let view = pack_view(&basic, &presets, Some(&vegan), Locale::Ru).unwrap();
+        let user_id = Uuid::new_v4();
+
+        let items = selected_items(user_id, &basic, &view, &presets, &select(&[("Гречка", None), ("Картофель", Some(3.5))]), now()).unwrap();
+        let summary: Vec<(&str, f32, &str)> = items.iter().map(|item| (item.name.as_str(), item.quantity, item.unit.as_str())).collect();
+        assert_eq!(summary, vec![("Гречка", 1.0, "кг"), ("Картофель", 3.5, "кг")]);
+        let buckwheat = find_preset(&presets, "Гречка").unwrap();
+        assert_eq!(items[0].expiry_date, buckwheat.typical_shelf_life_days.map(|days| now() + Duration::days(days as i64)));
+        assert_eq!((items[0].purchase_date, items[0].user_id), (now(), user_id));
+        assert_eq!(items[0].location.as_deref(), Some(buckwheat.storage_location.as_str()));
+
+        let rejected = [
+            select(&[]),
+            select(&[("Гречка", None), ("Гречка", Some(2.0))]),
+            select(&[("Куриная грудка", None)]),
+            select(&[("Тофу", None)]),
+            select(&[("Картофель", Some(0.0))]),
+            select(&[("Картофель", Some(f32::NAN))]),
+        ];
+        for selection in rejected {
+            let result = selected_items(user_id, &basic, &view, &presets, &selection, now());
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{:?}", selection);
+        }
+    }
+
+    async fn fridge_names(pool: &PgPool, user_id: Uuid) -> Vec<(String, f32)> {
+        sqlx::query_as("SELECT name, quantity FROM fridge_items WHERE user_id = $1 ORDER BY name")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn apply_adds_only_the_selected_items(pool: PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        DietaryService::new(pool.clone())
+            .update_profile(user_id, UpdateDietaryProfile {
+                allergies: None,
+                intolerances: None,
+                diets: Some(vec![DietType::Vegan]),
+                custom_restrictions: None,
+                severity_notes: None,
+            })
+            .await
+            .unwrap();
+        let service = StarterPackService::new(pool.clone());
+
+        let packs = service.list(user_id, Locale::Ru).await.unwrap();
+        let basic = packs.iter().find(|pack| pack.id == "basic").unwrap();
+        assert!(!basic.items.iter().any(|item| item.preset_name == "Куриная грудка"));
+
+        // Исключенный профилем продукт не добавляется, и вместе с ним — ничего
+        let result = service.apply(user_id, "basic", &select(&[("Гречка", None), ("Куриная грудка", None)]), now()).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert!(fridge_names(&pool, user_id).await.is_empty());
+        assert!(matches!(service.apply(user_id, "keto", &select(&[("Гречка", None)]), now()).await, Err(AppError::NotFound(_))));
+
+        let added = service
+            .apply(user_id, "basic", &select(&[("Гречка", None), ("Картофель", Some(3.5)), ("Банан", None)]), now())
+            .await
+            .unwrap();
+        assert_eq!(added.len(), 3);
+        assert_eq!(fridge_names(&pool, user_id).await, vec![
+            ("Банан".to_string(), 1.0),
+            ("Гречка".to_string(), 1.0),
+            ("Картофель".to_string(), 3.5),
+        ]);
+    }
+}
@@ -0,0 +1,290 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use serde::Serialize;
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{error, info, warn, Instrument};
+
+use crate::{telemetry, utils::errors::AppError};
+
+/// Первая пауза перед перезапуском упавшей задачи; дальше удваивается
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(5);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(10 * 60);
+/// Столько неудач подряд — и задача считается сломанной, а сервис — деградировавшим
+const DEGRADED_AFTER_FAILURES: u32 = 3;
+
+tokio::task_local! {
+    /// Имя задачи, которая сейчас выполняется под супервизором; нужно panic hook
+    static CURRENT_TASK: &'static str;
+}
+
+/// Имя фоновой задачи, в которой произошла паника; `None` — паника вне супервизора
+pub fn current_task() -> Option<&'static str> {
+    CURRENT_TASK.try_with(|name| *name).ok()
+}
+
+/// Состояние одной фоновой задачи
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub interval_secs: u64,
+    pub running: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    /// Следующий запуск после неудачи — не по расписанию, а после паузы
+    pub next_restart_at: Option<DateTime<Utc>>,
+    pub degraded: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisorStatus {
+    /// Хотя бы одна задача падает `DEGRADED_AFTER_FAILURES` раз подряд
+    pub degraded: bool,
+    pub tasks: Vec<TaskStatus>,
+}
+
+/// Запуск фоновых задач по расписанию: паники и ошибки перехватываются и логируются с именем задачи,
+/// задача перезапускается с нарастающей паузой, а ее состояние видно в /health и в админке
+#[derive(Clone)]
+pub struct TaskSupervisor {
+    tasks: Arc<Mutex<Vec<TaskStatus>>>,
+    handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Default for TaskSupervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            handles: Arc::new(Mutex::new(Vec::new())),
+            shutdown: watch::channel(false).0,
+        }
+    }
+
+    /// Запускает `task` каждые `every`, первый раз — сразу.
+    /// Запуск, начатый до остановки сервера, доводится до конца.
+    pub fn register_task<F, Fut>(&self, name: &'static str, every: Duration, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        self.tasks.lock().unwrap().push(TaskStatus {
+            name,
+            interval_secs: every.as_secs(),
+            running: false,
+            last_run: None,
+            last_success: None,
+            last_error: None,
+            consecutive_failures: 0,
+            total_failures: 0,
+            next_restart_at: None,
+            degraded: false,
+        });
+
+        let supervisor = self.clone();
+        let mut shutdown = self.shutdown.subscribe();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            let mut restart_after: Option<Duration> = None;
+
+            loop {
+                let wait = async {
+                    match restart_after {
+                        Some(backoff) => tokio::time::sleep(backoff).await,
+                        None => {
+                            interval.tick().await;
+                        }
+                    }
+                };
+                tokio::select! {
+                    _ = wait => {}
+                    _ = shutdown.changed() => break,
+                }
+
+                supervisor.update(name, |status| {
+                    status.running = true;
+                    status.last_run = Some(Utc::now());
+                    status.next_restart_at = None;
+                });
+
+                // Каждый запуск — отдельная корневая трасса
+                let run = task().instrument(telemetry::job_span(name));
+                let outcome = CURRENT_TASK.scope(name, AssertUnwindSafe(run).catch_unwind()).await;
+                let failure = match outcome {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(panic) => Some(format!("panicked: {}", panic_message(panic.as_ref()))),
+                };
+
+                restart_after = match failure {
+                    None => {
+                        supervisor.update(name, |status| {
+                            status.running = false;
+                            status.last_success = status.last_run;
+                            status.last_error = None;
+                            status.consecutive_failures = 0;
+                            status.degraded = false;
+                        });
+                        // После перезапуска снова по расписанию, от момента успешного запуска
+                        interval.reset();
+                        None
+                    }
+                    Some(message) => {
+                        let mut backoff = RESTART_BACKOFF_BASE;
+                        supervisor.update(name, |status| {
+                            status.running = false;
+                            status.consecutive_failures += 1;
+                            status.total_failures += 1;
+                            status.degraded = status.consecutive_failures >= DEGRADED_AFTER_FAILURES;
+                            backoff = restart_backoff(status.consecutive_failures);
+                            status.next_restart_at = chrono::Duration::from_std(backoff).ok().map(|backoff| Utc::now() + backoff);
+                            status.last_error = Some(message.clone());
+                        });
+                        error!("Background task '{}' failed, restarting in {:?}: {}", name, backoff, message);
+                        Some(backoff)
+                    }
+                };
+            }
+
+            info!("Background task '{}' stopped", name);
+        });
+
+        self.handles.lock().unwrap().push(handle);
+    }
+
+    pub fn status(&self) -> SupervisorStatus {
+        let tasks = self.tasks.lock().unwrap().clone();
+        SupervisorStatus {
+            degraded: tasks.iter().any(|task| task.degraded),
+            tasks,
+        }
+    }
+
+    /// Останавливает все задачи: новые запуски не начинаются, текущие доводятся до конца
+    /// не дольше `grace`
+    pub async fn shutdown(&self, grace: Duration) {
+        let _ = self.shutdown.send(true);
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+
+        if tokio::time::timeout(grace, futures_util::future::join_all(handles)).await.is_err() {
+            let unfinished: Vec<&str> = self.tasks.lock().unwrap().iter().filter(|task| task.running).map(|task| task.name).collect();
+            warn!("Background tasks did not stop within {:?}: {}", grace, unfinished.join(", "));
+        }
+    }
+
+    fn update(&self, name: &'static str, change: impl FnOnce(&mut TaskStatus)) {
+        if let Some(status) = self.tasks.lock().unwrap().iter_mut().find(|status| status.name == name) {
+            change(status);
+        }
+    }
+}
+
+/// 5 с, 10 с, 20 с, ... но не больше `RESTART_BACKOFF_MAX`
+fn restart_backoff(consecutive_failures: u32) -> Duration {
+    let doublings = consecutive_failures.saturating_sub(1).min(16);
+    (RESTART_BACKOFF_BASE * 2u32.pow(doublings)).min(RESTART_BACKOFF_MAX)
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::http::{Method, StatusCode};
+
+    use super::*;
+    use crate::app::{self, AppState};
+    use crate::config::Config;
+    use crate::db::DbPools;
+    use crate::test_support::{lazy_pool, request, send};
+
+    /// Ждет в виртуальном времени, пока состояние задачи не станет нужным
+    async fn wait_for(tasks: &TaskSupervisor, done: impl Fn(&TaskStatus) -> bool) -> TaskStatus {
+        for _ in 0..10_000 {
+            if let Some(status) = tasks.status().tasks.into_iter().find(|status| done(status)) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("task did not reach the expected state: {:?}", tasks.status());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(restart_backoff(1), Duration::from_secs(5));
+        assert_eq!(restart_backoff(2), Duration::from_secs(10));
+        assert_eq!(restart_backoff(4), Duration::from_secs(40));
+        assert_eq!(restart_backoff(20), RESTART_BACKOFF_MAX);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn panicking_task_restarts_and_health_reports_it() {
+        let mut config = Config::new().expect("test config");
+        config.sandbox_clock = false;
+        let state = AppState::new(config, DbPools::single(lazy_pool()));
+        let tasks = state.tasks.clone();
+        let router = app::build_router(state);
+
+        // Три запуска подряд падают, четвертый проходит
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        tasks.register_task("flaky_job", Duration::from_secs(3600), move || {
+            let run = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if run <= DEGRADED_AFTER_FAILURES {
+                    panic!("intentional failure #{}", run);
+                }
+                Ok(())
+            }
+        });
+
+        let (status, body) = send(&router, request(Method::GET, "/health", None, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+
+        let failing = wait_for(&tasks, |status| status.consecutive_failures == 1).await;
+        assert!(!failing.degraded);
+        assert_eq!(failing.last_error.as_deref(), Some("panicked: intentional failure #1"));
+        assert!(failing.next_restart_at.is_some());
+
+        let broken = wait_for(&tasks, |status| status.degraded).await;
+        assert_eq!(broken.consecutive_failures, DEGRADED_AFTER_FAILURES);
+        assert_eq!(runs.load(Ordering::SeqCst), DEGRADED_AFTER_FAILURES);
+        let (status, body) = send(&router, request(Method::GET, "/health", None, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["failing_tasks"], serde_json::json!(["flaky_job"]));
+
+        // Перезапуск после паузы проходит — задача снова здорова
+        let recovered = wait_for(&tasks, |status| status.last_success.is_some()).await;
+        assert_eq!(recovered.consecutive_failures, 0);
+        assert_eq!(recovered.total_failures, u64::from(DEGRADED_AFTER_FAILURES));
+        assert_eq!(recovered.last_error, None);
+        let (_, body) = send(&router, request(Method::GET, "/health", None, None)).await;
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["failing_tasks"], serde_json::json!([]));
+
+        tasks.shutdown(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::{types::Json, FromRow};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    models::timeline::{TimelineEvent, TimelineEventType, TimelinePage},
+    services::{
+        clock::{self, SharedClock},
+        community, diary, fridge, goal, post_cooked,
+        preferences::PreferencesService,
+        recipe, wellbeing,
+    },
+    utils::{errors::AppError, format::Locale, i18n},
+};
+
+/// Период ленты без `from`: месяц до `to`
+const DEFAULT_RANGE_DAYS: i64 = 31;
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Запрос событий одного типа. Каждый выбирает колонки
+/// `kind, occurred_at, event_id, reference_id, title, summary` только для пользователя `$1`;
+/// `event_id` уникален внутри типа и вместе с временем и типом задает порядок ленты.
+fn source(event_type: TimelineEventType) -> &'static str {
+    match event_type {
+        TimelineEventType::DiaryEntryLogged => diary::TIMELINE_ENTRIES_SQL,
+        TimelineEventType::FridgeItemAdded => fridge::TIMELINE_ADDED_SQL,
+        TimelineEventType::FridgeItemConsumed => fridge::TIMELINE_CONSUMED_SQL,
+        TimelineEventType::FridgeItemWasted => fridge::TIMELINE_WASTED_SQL,
+        TimelineEventType::RecipeCreated => recipe::TIMELINE_CREATED_SQL,
+        TimelineEventType::RecipeCooked => post_cooked::TIMELINE_COOKED_SQL,
+        TimelineEventType::GoalProgressed => goal::TIMELINE_PROGRESS_SQL,
+        TimelineEventType::GoalCompleted => goal::TIMELINE_COMPLETED_SQL,
+        TimelineEventType::PostPublished => community::TIMELINE_POSTS_SQL,
+        TimelineEventType::AchievementEarned => goal::TIMELINE_ACHIEVEMENTS_SQL,
+        TimelineEventType::WellbeingCheckin => wellbeing::TIMELINE_CHECKINS_SQL,
+    }
+}
+
+/// Позиция в ленте: последнее событие предыдущей страницы.
+/// Для клиента курсор непрозрачен: `<микросекунды>_<тип>_<event_id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineCursor {
+    pub occurred_at: DateTime<Utc>,
+    pub event_type: TimelineEventType,
+    pub event_id: Uuid,
+}
+
+impl TimelineCursor {
+    pub fn encode(&self) -> String {
+        format!("{}_{}_{}", self.occurred_at.timestamp_micros(), self.event_type.as_str(), self.event_id)
+    }
+
+    pub fn decode(value: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest("Invalid timeline cursor".to_string());
+
+        let (micros, rest) = value.split_once('_').ok_or_else(invalid)?;
+        let (kind, event_id) = rest.rsplit_once('_').ok_or_else(invalid)?;
+        let micros: i64 = micros.parse().map_err(|_| invalid())?;
+        let occurred_at = DateTime::from_timestamp(micros.div_euclid(1_000_000), (micros.rem_euclid(1_000_000) * 1000) as u32)
+            .ok_or_else(invalid)?;
+
+        Ok(Self {
+            occurred_at,
+            event_type: TimelineEventType::parse(kind).ok_or_else(invalid)?,
+            event_id: event_id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Фильтр ленты: местные дни с `from` по `to` включительно и типы событий (пусто — все)
+#[derive(Debug, Clone)]
+pub struct TimelineQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub types: Vec<TimelineEventType>,
+    pub cursor: Option<TimelineCursor>,
+    pub limit: i64,
+}
+
+#[derive(FromRow)]
+struct TimelineRow {
+    kind: String,
+    occurred_at: DateTime<Utc>,
+    event_id: Uuid,
+    reference_id: Uuid,
+    title: Option<String>,
+    summary: Json<serde_json::Value>,
+}
+
+/// Лента активности пользователя: его собственные события из всех модулей одним потоком,
+/// новые первыми. Отдельной таблицы событий нет — лента собирается из таблиц модулей.
+pub struct TimelineService {
+    pool: DbPool,
+    clock: SharedClock,
+}
+
+impl TimelineService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool, clock: clock::system() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Страница ленты после курсора. Порядок — время, затем тип и id события по убыванию,
+    /// поэтому курсор стабилен, даже если у нескольких событий одно время.
+    pub async fn events(&self, user_id: Uuid, query: &TimelineQuery, locale: Locale) -> Result<TimelinePage, AppError> {
+        let preferences = PreferencesService::new(self.pool.clone()).for_user(user_id).await?;
+        let utc_offset = Duration::minutes(preferences.general.utc_offset_minutes as i64);
+        let to = query.to.unwrap_or_else(|| (self.clock.now() + utc_offset).date_naive());
+        let from = query.from.unwrap_or(to - Duration::days(DEFAULT_RANGE_DAYS - 1));
+
+        if from > to {
+            return Err(AppError::BadRequest("'from' must not be after 'to'".to_string()));
+        }
+        if (to - from).num_days() + 1 > MAX_RANGE_DAYS {
+            return Err(AppError::BadRequest(format!("Range must not exceed {} days", MAX_RANGE_DAYS)));
+        }
+
+        let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - utc_offset;
+        let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() - utc_offset;
+
+        let types: &[TimelineEventType] = if query.types.is_empty() { &TimelineEventType::ALL } else { &query.types };
+        let union = types.iter().map(|event_type| source(*event_type)).collect::<Vec<_>>().join(" UNION ALL ");
+        let sql = format!(
+            r#"
+            SELECT kind, occurred_at, event_id, reference_id, title, summary
+            FROM ({}) events
+            WHERE occurred_at >= $2 AND occurred_at < $3
+              AND ($4::timestamptz IS NULL OR (occurred_at, kind, event_id) < ($4, $5::text, $6::uuid))
+            ORDER BY occurred_at DESC, kind DESC, event_id DESC
+            LIMIT $7
+            "#,
+            union
+        );
+
+        // Не UserScope: у постов и рецептов владелец — author_id / created_by, каждый источник фильтрует по `$1` сам.
+        // На одно событие больше — так видно, есть ли следующая страница.
+        let mut rows: Vec<TimelineRow> = sqlx::query_as(&sql)
+            .bind(user_id)
+            .bind(start)
+            .bind(end)
+            .bind(query.cursor.map(|cursor| cursor.occurred_at))
+            .bind(query.cursor.map(|cursor| cursor.event_type.as_str()))
+            .bind(query.cursor.map(|cursor| cursor.event_id))
+            .bind(query.limit + 1)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let has_more = rows.len() as i64 > query.limit;
+        rows.truncate(query.limit.max(0) as usize);
+
+        let next_cursor = rows
+            .last()
+            .filter(|_| has_more)
+            .and_then(|row| {
+                Some(TimelineCursor {
+                    occurred_at: row.occurred_at,
+                    event_type: TimelineEventType::parse(&row.kind)?,
+                    event_id: row.event_id,
+                })
+            })
+            .map(|cursor| cursor.encode());
+        let events = rows.into_iter().filter_map(|row| event(row, locale)).collect();
+
+        Ok(TimelinePage { events, next_cursor })
+    }
+}
+
+fn event(row: TimelineRow, locale: Locale) -> Option<TimelineEvent> {
+    let event_type = TimelineEventType::parse(&row.kind)?;
+    let title = match (row.title, event_type) {
+        (Some(title), _) => title,
+        (None, TimelineEventType::WellbeingCheckin) => i18n::t(locale, "timeline.wellbeing_checkin").to_string(),
+        (None, _) => String::new(),
+    };
+
+    Some(TimelineEvent {
+        event_type,
+        timestamp: row.occurred_at,
+        title,
+        reference_id: row.reference_id,
+        summary_fields: row.summary.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{frozen_clock, insert_diary_entry, insert_fridge_item, insert_recipe, insert_user};
+
+    async fn set_created_at(pool: &PgPool, table: &str, id: Uuid, at: DateTime<Utc>) {
+        sqlx::query(&format!("UPDATE {} SET created_at = $2 WHERE id = $1", table))
+            .bind(id)
+            .bind(at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    fn query(cursor: Option<&str>) -> TimelineQuery {
+        TimelineQuery {
+            from: None,
+            to: None,
+            types: Vec::new(),
+            cursor: cursor.map(|cursor| TimelineCursor::decode(cursor).unwrap()),
+            limit: 3,
+        }
+    }
+
+    #[test]
+    fn cursor_round_trips_and_rejects_garbage() {
+        let cursor = TimelineCursor {
+            occurred_at: Utc.with_ymd_and_hms(2026, 10, 10, 12, 0, 0).unwrap() + Duration::microseconds(123_456),
+            event_type: TimelineEventType::FridgeItemAdded,
+            event_id: Uuid::new_v4(),
+        };
+        assert_eq!(TimelineCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        for garbage in ["", "abc", "1_fridge_item_added", "x_fridge_item_added_00000000-0000-0000-0000-000000000000", "1_unknown_00000000-0000-0000-0000-000000000000"] {
+            assert!(matches!(TimelineCursor::decode(garbage), Err(AppError::BadRequest(_))), "{}", garbage);
+        }
+    }
+
+    #[sqlx::test]
+    async fn interleaved_modules_page_in_one_global_order(pool: PgPool) {
+        let (anna, boris) = (insert_user(&pool, "Анна").await, insert_user(&pool, "Борис").await);
+        let now = Utc.with_ymd_and_hms(2026, 10, 10, 12, 0, 0).unwrap();
+        let at = |hours: i64| now - Duration::hours(hours);
+
+        // Модули чередуются; в 3 и 6 часов назад у событий разных типов и одного типа общее время
+        let mut expected = Vec::new();
+        for (hours, meal) in [(1, "snack"), (3, "lunch"), (6, "breakfast"), (6, "breakfast"), (9, "dinner")] {
+            let id = insert_diary_entry(&pool, anna, meal, 300.0, 10.0, at(hours)).await;
+            expected.push((at(hours), TimelineEventType::DiaryEntryLogged, id));
+        }
+        for (hours, name) in [(2, "Молоко"), (3, "Сыр"), (8, "Хлеб")] {
+            let id = insert_fridge_item(&pool, anna, name, None, at(hours)).await.id;
+            set_created_at(&pool, "fridge_items", id, at(hours)).await;
+            expected.push((at(hours), TimelineEventType::FridgeItemAdded, id));
+        }
+        for (hours, name) in [(4, "Омлет"), (6, "Сырники")] {
+            let id = insert_recipe(&pool, anna, name, false).await;
+            set_created_at(&pool, "recipes", id, at(hours)).await;
+            expected.push((at(hours), TimelineEventType::RecipeCreated, id));
+        }
+        let foreign = insert_diary_entry(&pool, boris, "lunch", 500.0, 20.0, at(5)).await;
+        expected.sort_by(|a, b| (b.0, b.1.as_str(), b.2).cmp(&(a.0, a.1.as_str(), a.2)));
+
+        let service = TimelineService::new(pool.clone()).with_clock(frozen_clock(now));
+        let mut seen = Vec::new();
+        let mut cursors = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = service.events(anna, &query(cursor.as_deref()), Locale::Ru).await.unwrap();
+            assert!(page.events.len() <= 3);
+            seen.extend(page.events.iter().map(|event| (event.timestamp, event.event_type, event.reference_id)));
+            match page.next_cursor {
+                Some(next) => {
+                    cursors.push(next.clone());
+                    cursor = Some(next);
+                }
+                None => break,
+            }
+        }
+        assert_eq!(seen, expected);
+        assert_eq!(cursors.len(), 3, "10 events, 3 per page");
+        assert!(!seen.iter().any(|(_, _, id)| *id == foreign));
+
+        // Новые события не сдвигают уже выданные страницы: тот же курсор — та же страница
+        let second_page = service.events(anna, &query(Some(&cursors[0])), Locale::Ru).await.unwrap();
+        insert_diary_entry(&pool, anna, "snack", 100.0, 1.0, now - Duration::minutes(1)).await;
+        let recipe = insert_recipe(&pool, anna, "Блины", false).await;
+        set_created_at(&pool, "recipes", recipe, at(3)).await;
+        let again = service.events(anna, &query(Some(&cursors[0])), Locale::Ru).await.unwrap();
+        let ids = |page: &TimelinePage| page.events.iter().map(|event| event.reference_id).collect::<Vec<_>>();
+        assert_eq!(ids(&again), ids(&second_page));
+        assert_eq!(again.next_cursor, second_page.next_cursor);
+
+        // Фильтр по типу тоже пагинируется по общему порядку
+        let fridge = TimelineQuery { types: vec![TimelineEventType::FridgeItemAdded], limit: 2, ..query(None) };
+        let page = service.events(anna, &fridge, Locale::Ru).await.unwrap();
+        let expected_fridge: Vec<Uuid> = expected.iter().filter(|event| event.1 == TimelineEventType::FridgeItemAdded).map(|event| event.2).collect();
+        assert_eq!(ids(&page), expected_fridge[..2]);
+        let rest = TimelineQuery { cursor: Some(TimelineCursor::decode(page.next_cursor.as_deref().unwrap()).unwrap()), ..fridge };
+        let page = service.events(anna, &rest, Locale::Ru).await.unwrap();
+        assert_eq!(ids(&page), expected_fridge[2..]);
+        assert!(page.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{db::{DbPool, UserScope}, models::health::DailyWellbeing, utils::errors::AppError};
+
+/// Отметки самочувствия для ленты активности (колонки — см. `services::timeline`);
+/// заголовка нет — его подставляет лента на языке пользователя
+pub const TIMELINE_CHECKINS_SQL: &str = r#"
+    SELECT 'wellbeing_checkin'::text AS kind, date AS occurred_at, id AS event_id, id AS reference_id,
+           NULL::text AS title,
+           jsonb_strip_nulls(jsonb_build_object(
+               'mood_score', mood_score, 'energy_level', energy_level, 'stress_level', stress_level,
+               'sleep_hours', sleep_hours, 'water_intake_ml', water_intake_ml, 'exercise_minutes', exercise_minutes
+           )) AS summary
+    FROM daily_wellbeing
+    WHERE user_id = $1
+"#;
+
+/// Проверки самочувствия пользователя
+pub struct WellbeingService {
+    pool: DbPool,
+}
+
+impl WellbeingService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, wellbeing: &DailyWellbeing) -> Result<(), AppError> {
+        UserScope::new(wellbeing.user_id).query(
+            r#"
+            INSERT INTO daily_wellbeing (
+                user_id, id, date, mood_score, energy_level, stress_level, sleep_hours,
+                sleep_quality, water_intake_ml, exercise_minutes, notes, symptoms, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#
+        )
+        .bind(wellbeing.id)
+        .bind(wellbeing.date)
+        .bind(wellbeing.mood_score)
+        .bind(wellbeing.energy_level)
+        .bind(wellbeing.stress_level)
+        .bind(wellbeing.sleep_hours)
+        .bind(wellbeing.sleep_quality)
+        .bind(wellbeing.water_intake_ml)
+        .bind(wellbeing.exercise_minutes)
+        .bind(&wellbeing.notes)
+        .bind(&wellbeing.symptoms)
+        .bind(wellbeing.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Среднее настроение по дням периода (включительно); дни без оценки настроения пропускаются
+    pub async fn daily_moods(&self, user_id: Uuid, start_date: NaiveDate, end_date: NaiveDate) -> Result<BTreeMap<NaiveDate, f64>, AppError> {
+        let rows: Vec<(NaiveDate, f64)> = UserScope::new(user_id).query_as(
+            r#"
+            SELECT date::date AS day, AVG(mood_score)::float8 AS mood
+            FROM daily_wellbeing
+            WHERE user_id = $1 AND mood_score IS NOT NULL AND date::date >= $2 AND date::date <= $3
+            GROUP BY 1
+            "#
+        )
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use axum::{
+    extract::MatchedPath,
+    http::{Request, Response},
+};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    runtime,
+    trace::{self as sdktrace, Sampler},
+    Resource,
+};
+use tracing::{field, info_span, Span};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config::TelemetryConfig;
+
+/// Какие span экспортируются, если OTEL_TRACES_FILTER не задан
+const DEFAULT_TRACES_FILTER: &str = "info";
+
+/// Логи в stdout — как раньше, по RUST_LOG; если задан OTLP-адрес — еще и экспорт трасс
+/// со своим фильтром. Возвращает, включен ли экспорт.
+pub fn init(config: &TelemetryConfig) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let fmt_layer = fmt::layer().with_filter(EnvFilter::from_default_env());
+
+    let otel_layer = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+                .with_trace_config(
+                    sdktrace::config()
+                        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_rate))))
+                        .with_resource(Resource::new(vec![KeyValue::new("service.name", config.service_name.clone())])),
+                )
+                .install_batch(runtime::Tokio)?;
+            let filter = EnvFilter::try_from_env("OTEL_TRACES_FILTER").unwrap_or_else(|_| EnvFilter::new(DEFAULT_TRACES_FILTER));
+            Some(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(filter))
+        }
+        None => None,
+    };
+    let exporting = otel_layer.is_some();
+
+    tracing_subscriber::registry().with(fmt_layer).with(otel_layer).try_init()?;
+    Ok(exporting)
+}
+
+/// Отправляет накопленные span перед остановкой процесса
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Span HTTP-запроса: шаблон маршрута, а не путь (`/api/v1/fridge/:id`), чтобы трассы
+/// группировались. `user.id` записывает auth middleware, статус — `record_response`.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or("unmatched");
+
+    info_span!(
+        "http.request",
+        otel.name = %format!("{} {}", request.method(), route),
+        otel.kind = "server",
+        otel.status_code = field::Empty,
+        http.method = %request.method(),
+        http.route = route,
+        http.status_code = field::Empty,
+        user.id = field::Empty,
+    )
+}
+
+pub fn record_response<B>(response: &Response<B>, _latency: Duration, span: &Span) {
+    span.record("http.status_code", response.status().as_u16());
+    if response.status().is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+/// Корневой span одного запуска фоновой задачи: у каждого запуска — своя трасса
+pub fn job_span(job: &'static str) -> Span {
+    info_span!(parent: None, "job", otel.name = job, job)
+}
+
+/// Span задачи, запущенной из запроса: своя трасса, связанная с трассой запроса
+pub fn detached_job_span(job: &'static str) -> Span {
+    let span = job_span(job);
+    span.follows_from(Span::current());
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::http::{Method, StatusCode};
+    use futures_util::future::BoxFuture;
+    use opentelemetry::trace::{SpanId, SpanKind, TracerProvider as _};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+    use super::*;
+    use crate::test_support::{access_token, insert_user, request, send, test_router};
+
+    /// Экспортер в память: законченные span копятся, пока тест их не заберет
+    #[derive(Debug, Clone, Default)]
+    struct RecordingExporter(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for RecordingExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    /// Трассы всего, что выполнит `run`, — на этом потоке, поэтому тесты идут на однопоточном runtime
+    async fn record_spans<F: std::future::Future>(run: F) -> Vec<SpanData> {
+        let exporter = RecordingExporter::default();
+        let provider = sdktrace::TracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let guard = tracing::subscriber::set_default(subscriber);
+        run.await;
+        drop(guard);
+
+        provider.force_flush();
+        let spans = exporter.0.lock().unwrap().clone();
+        spans
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes.iter().find(|attribute| attribute.key.as_str() == key).map(|attribute| &attribute.value)
+    }
+
+    fn named<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no span {}, got {:?}", name, spans.iter().map(|span| &span.name).collect::<Vec<_>>()))
+    }
+
+    #[sqlx::test]
+    async fn request_span_is_the_root_of_its_service_spans(pool: sqlx::PgPool) {
+        let user_id = insert_user(&pool, "Анна").await;
+        let token = access_token(&pool, user_id).await;
+        let router = test_router(&pool);
+
+        let spans = record_spans(async {
+            let (status, _) = send(&router, request(Method::GET, "/api/v1/fridge/analytics/expenses?period=month", Some(&token), None)).await;
+            assert_eq!(status, StatusCode::OK);
+        })
+        .await;
+
+        let root = named(&spans, "GET /api/v1/fridge/analytics/expenses");
+        assert_eq!(root.parent_span_id, SpanId::INVALID);
+        assert_eq!(root.span_kind, SpanKind::Server);
+        assert_eq!(attribute(root, "http.route"), Some(&Value::from("/api/v1/fridge/analytics/expenses")));
+        assert_eq!(attribute(root, "http.status_code").map(|value| value.as_str().into_owned()), Some("200".to_string()));
+        assert_eq!(attribute(root, "user.id"), Some(&Value::from(user_id.to_string())));
+
+        // Сервисный span — прямой потомок запроса в той же трассе
+        let service = named(&spans, "get_expense_analytics_between");
+        assert_eq!(service.parent_span_id, root.span_context.span_id());
+        assert_eq!(service.span_context.trace_id(), root.span_context.trace_id());
+
+        // Остальные span запроса тоже в этой трассе и выводятся к корню по цепочке родителей
+        let trace: Vec<&SpanData> = spans.iter().filter(|span| span.span_context.trace_id() == root.span_context.trace_id()).collect();
+        assert!(trace.len() > 1);
+        for span in &trace {
+            let mut current = *span;
+            while current.parent_span_id != SpanId::INVALID {
+                current = trace
+                    .iter()
+                    .find(|parent| parent.span_context.span_id() == current.parent_span_id)
+                    .unwrap_or_else(|| panic!("parent of {} is not in the trace", current.name));
+            }
+            assert_eq!(current.span_context.span_id(), root.span_context.span_id());
+        }
+    }
+
+    #[tokio::test]
+    async fn job_spans_start_their_own_traces() {
+        let spans = record_spans(async {
+            let request = info_span!("http.request");
+            let _entered = request.enter();
+            job_span("nightly").in_scope(|| {});
+            detached_job_span("export").in_scope(|| {});
+        })
+        .await;
+
+        let request = named(&spans, "http.request");
+        let nightly = named(&spans, "nightly");
+        let export = named(&spans, "export");
+        for job in [nightly, export] {
+            assert_eq!(job.parent_span_id, SpanId::INVALID);
+            assert_ne!(job.span_context.trace_id(), request.span_context.trace_id());
+        }
+        // Запущенная из запроса задача ссылается на его span
+        assert!(nightly.links.iter().next().is_none());
+        let links: Vec<SpanId> = export.links.iter().map(|link| link.span_context.span_id()).collect();
+        assert_eq!(links, vec![request.span_context.span_id()]);
+    }
+}
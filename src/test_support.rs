@@ -0,0 +1,365 @@
+//! Общие заготовки для тестов. Тесты с БД (`#[sqlx::test]`) получают отдельную базу
+//! с миграциями; адрес сервера — из DATABASE_URL.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use uuid::Uuid;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::Extension,
+    http::{header, HeaderMap, Method, Request, StatusCode, Uri},
+    routing::post,
+    Json, Router,
+};
+use tower::ServiceExt;
+
+use crate::{
+    app::{self, AppState},
+    config::Config,
+    db::DbPools,
+    models::fridge::FridgeItem,
+    services::{
+        ai_costs::TokenUsage,
+        ai_generation::GenerationSettings,
+        ai_providers::{Capabilities, Completion, ProviderAdapter},
+        auth::{AuthService, Claims},
+        clock::{SandboxClock, SandboxClockMode},
+        mail::MailService,
+    },
+    utils::{device::DeviceInfo, errors::AppError},
+};
+
+/// Пользователь с уникальной почтой
+pub async fn insert_user(pool: &PgPool, first_name: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO users (id, email, password_hash, first_name, last_name) VALUES ($1, $2, 'hash', $3, 'Test')"
+    )
+    .bind(id)
+    .bind(format!("{}@example.com", id))
+    .bind(first_name)
+    .execute(pool)
+    .await
+    .expect("insert user");
+    id
+}
+
+/// Загруженный пользователем файл
+pub async fn insert_media(pool: &PgPool, user_id: Uuid) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO media_files (id, user_id, url, media_type, file_size) VALUES ($1, $2, $3, 'image', 1024)"
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(format!("/uploads/{}.jpg", id))
+    .execute(pool)
+    .await
+    .expect("insert media");
+    id
+}
+
+/// Часы песочницы, остановленные на `at`; время двигается через `set_mode`
+pub fn frozen_clock(at: DateTime<Utc>) -> Arc<SandboxClock> {
+    let clock = Arc::new(SandboxClock::new());
+    clock.set_mode(SandboxClockMode::Frozen { at });
+    clock
+}
+
+/// Полный роутер приложения поверх тестовой базы — для запросов через `tower::ServiceExt::oneshot`
+pub fn test_router(pool: &PgPool) -> Router {
+    let mut config = Config::new().expect("test config");
+    config.sandbox_clock = false;
+    app::build_router(AppState::new(config, DbPools::single(pool.clone())))
+}
+
+/// Запрос к роутеру: JSON-тело и Bearer-токен по желанию
+pub fn request(method: Method, uri: &str, token: Option<&str>, body: Option<serde_json::Value>) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    }
+}
+
+/// Статус и JSON ответа; пустое или не-JSON тело — `Null`
+pub async fn send(router: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = router.clone().oneshot(request).await.expect("router response");
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.expect("response body");
+    (status, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+}
+
+/// Access-токен пользователя с новой сессией, как после входа
+pub async fn access_token(pool: &PgPool, user_id: Uuid) -> String {
+    let auth = AuthService::new(pool.clone());
+    let user = auth.get_user_by_id(user_id).await.expect("token user");
+    auth.generate_tokens(&user, DeviceInfo::default()).await.expect("generate tokens").access_token
+}
+
+/// Claims из свежего access-токена — для прямого вызова обработчиков
+pub async fn claims_for(pool: &PgPool, user_id: Uuid) -> Claims {
+    let token = access_token(pool, user_id).await;
+    AuthService::new(pool.clone()).verify_token(&token).expect("verify token")
+}
+
+/// Пул без соединения — для кода, который до БД не доходит
+pub fn lazy_pool() -> PgPool {
+    PgPoolOptions::new().connect_lazy("postgres://localhost/unused").expect("lazy pool")
+}
+
+/// Запись дневника: `calories` ккал и `protein` г белка на порцию 100 г
+pub async fn insert_diary_entry(pool: &PgPool, user_id: Uuid, meal_type: &str, calories: f32, protein: f32, consumed_at: DateTime<Utc>) -> Uuid {
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO diary_entries (
+            user_id, food_name, portion_size, unit, calories_per_100g, protein_per_100g, fat_per_100g,
+            carbs_per_100g, meal_type, consumed_at
+        )
+        VALUES ($1, 'Каша', 100, 'g', $2, $3, 0, 0, $4, $5)
+        RETURNING id
+        "#
+    )
+    .bind(user_id)
+    .bind(calories)
+    .bind(protein)
+    .bind(meal_type)
+    .bind(consumed_at)
+    .fetch_one(pool)
+    .await
+    .expect("insert diary entry");
+    id
+}
+
+/// Активный продукт холодильника: 1 шт., куплен в `purchased_at`
+pub async fn insert_fridge_item(pool: &PgPool, user_id: Uuid, name: &str, notes: Option<&str>, purchased_at: DateTime<Utc>) -> FridgeItem {
+    sqlx::query_as(
+        r#"
+        INSERT INTO fridge_items (user_id, name, quantity, unit, category, purchase_date, notes, created_at, updated_at)
+        VALUES ($1, $2, 1, 'pcs', 'other', $3, $4, $3, $3)
+        RETURNING *
+        "#
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(purchased_at)
+    .bind(notes)
+    .fetch_one(pool)
+    .await
+    .expect("insert fridge item")
+}
+
+/// Рецепт без ингредиентов и шагов: ужин, простой
+pub async fn insert_recipe(pool: &PgPool, created_by: Uuid, name: &str, is_public: bool) -> Uuid {
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO recipes (name, category, difficulty, instructions, created_by, is_public)
+        VALUES ($1, 'dinner', 'easy', 'Приготовить', $2, $3)
+        RETURNING id
+        "#
+    )
+    .bind(name)
+    .bind(created_by)
+    .bind(is_public)
+    .fetch_one(pool)
+    .await
+    .expect("insert recipe");
+    id
+}
+
+/// Локальный почтовый провайдер: запоминает письма, пока `failing` выключен, иначе отвечает 500
+pub async fn mail_server() -> (MailService, Arc<Mutex<Vec<serde_json::Value>>>, Arc<AtomicBool>) {
+    let received: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+    let failing = Arc::new(AtomicBool::new(false));
+
+    async fn accept(
+        Extension(received): Extension<Arc<Mutex<Vec<serde_json::Value>>>>,
+        Extension(failing): Extension<Arc<AtomicBool>>,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        if failing.load(Ordering::SeqCst) {
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+        received.lock().unwrap().push(body);
+        StatusCode::OK
+    }
+
+    let router = Router::new()
+        .route("/emails", post(accept))
+        .layer(Extension(received.clone()))
+        .layer(Extension(failing.clone()));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(router.into_make_service()));
+
+    (MailService::http(format!("http://{}/emails", address)), received, failing)
+}
+
+/// Запрос, который получил `provider_server`
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// Путь вместе со строкой запроса
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: serde_json::Value,
+}
+
+/// Локальный провайдер ИИ с заготовленным ответом: на любой запрос ждет `delay`
+/// и отвечает `status` с JSON-телом `body`. Возвращает адрес сервера и полученные запросы.
+pub async fn provider_server(delay: Duration, status: StatusCode, body: &'static str) -> (String, Arc<Mutex<Vec<RecordedRequest>>>) {
+    let received: Arc<Mutex<Vec<RecordedRequest>>> = Arc::default();
+
+    let recorded = received.clone();
+    let reply = move |method: Method, uri: Uri, headers: HeaderMap, request_body: Bytes| async move {
+        recorded.lock().unwrap().push(RecordedRequest {
+            method,
+            uri: uri.to_string(),
+            headers,
+            body: serde_json::from_slice(&request_body).unwrap_or(serde_json::Value::Null),
+        });
+        tokio::time::sleep(delay).await;
+        (status, [(header::CONTENT_TYPE, "application/json")], body)
+    };
+
+    let router = Router::new().fallback(reply);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(router.into_make_service()));
+
+    (format!("http://{}", address), received)
+}
+
+/// Второй пул к той же тестовой базе со счетчиком выданных соединений — по нему видно,
+/// в какой пул ушли запросы (каждый запрос вне транзакции берет соединение заново)
+pub fn counting_pool(pool: &PgPool) -> (PgPool, Arc<AtomicUsize>) {
+    let acquired = Arc::new(AtomicUsize::new(0));
+    let (on_connect, on_acquire) = (acquired.clone(), acquired.clone());
+    let counted = PgPoolOptions::new()
+        .max_connections(2)
+        // Новое соединение before_acquire не проходит — считается при подключении
+        .after_connect(move |_, _| {
+            on_connect.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(()) })
+        })
+        .before_acquire(move |_, _| {
+            on_acquire.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(true) })
+        })
+        .connect_lazy_with((*pool.connect_options()).clone());
+    (counted, acquired)
+}
+
+/// Как `counting_pool`, но каждое выданное соединение сначала ждет `delay` — по общему
+/// времени видно, шли ли запросы параллельно
+pub fn delayed_pool(pool: &PgPool, delay: Duration) -> (PgPool, Arc<AtomicUsize>) {
+    let acquired = Arc::new(AtomicUsize::new(0));
+    let (on_connect, on_acquire) = (acquired.clone(), acquired.clone());
+    let delayed = PgPoolOptions::new()
+        .max_connections(10)
+        .after_connect(move |_, _| {
+            on_connect.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            })
+        })
+        .before_acquire(move |_, _| {
+            on_acquire.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                Ok(true)
+            })
+        })
+        .connect_lazy_with((*pool.connect_options()).clone());
+    (delayed, acquired)
+}
+
+/// Провайдер ИИ с заранее заданными ответами: `Some(text)` — ответ, `None` — сбой провайдера.
+/// Когда ответы кончились, повторяется последний. Каждый вызов ждет `delay`.
+#[derive(Debug)]
+pub struct StubAdapter {
+    name: &'static str,
+    replies: Mutex<VecDeque<Option<String>>>,
+    delay: Duration,
+    /// Сколько раз вызывалась генерация
+    pub calls: AtomicUsize,
+    /// Промпты вызовов по порядку
+    pub prompts: Mutex<Vec<String>>,
+    /// Настройки генерации вызовов по порядку
+    pub settings: Mutex<Vec<GenerationSettings>>,
+}
+
+impl StubAdapter {
+    pub fn new(name: &'static str, replies: Vec<Option<&str>>) -> Self {
+        Self {
+            name,
+            replies: Mutex::new(replies.into_iter().map(|reply| reply.map(str::to_string)).collect()),
+            delay: Duration::ZERO,
+            calls: AtomicUsize::new(0),
+            prompts: Mutex::new(Vec::new()),
+            settings: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_delay(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for StubAdapter {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn model(&self) -> &'static str {
+        "stub-model"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    async fn generate(&self, prompt: &str, settings: &GenerationSettings) -> Result<Completion, AppError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        self.settings.lock().unwrap().push(*settings);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        let reply = {
+            let mut replies = self.replies.lock().unwrap();
+            if replies.len() > 1 { replies.pop_front().flatten() } else { replies.front().cloned().flatten() }
+        };
+        match reply {
+            Some(text) => Ok(Completion {
+                usage: TokenUsage { prompt_tokens: 10, completion_tokens: 20, estimated: false },
+                text,
+            }),
+            None => Err(AppError::ExternalService(format!("{} is unavailable", self.name))),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::net::IpAddr;
+
+use axum::http::HeaderMap;
+use serde::Serialize;
+
+use crate::{services::api_tokens::hash_token, utils::rate_limit::client_ip};
+
+/// Длина сохраняемого User-Agent
+const MAX_USER_AGENT_LENGTH: usize = 500;
+
+/// Устройство, с которого выполнен вход: сохраняется в сессии и показывается в списке сессий
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub ip_address: Option<String>,
+    /// Страна из заголовка CDN/прокси или "Local network" для внутренних адресов
+    pub location: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Данные устройства из заголовков запроса; `peer_ip` — адрес соединения, если прокси не передал IP
+    pub fn from_request(headers: &HeaderMap, peer_ip: Option<IpAddr>) -> Self {
+        let user_agent = headers
+            .get("user-agent")
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect::<String>());
+        // Заголовки прокси присылает клиент: сохраняется только корректный адрес
+        let ip_address = client_ip(headers)
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .or(peer_ip)
+            .map(|ip| ip.to_string());
+        let (browser, os) = user_agent.as_deref().map(parse_user_agent).unwrap_or_default();
+
+        Self {
+            browser: browser.map(str::to_string),
+            os: os.map(str::to_string),
+            location: coarse_location(headers, ip_address.as_deref()),
+            user_agent,
+            ip_address,
+        }
+    }
+
+    /// Отпечаток пары User-Agent + IP: по нему вход узнается как вход с нового устройства
+    pub fn fingerprint(&self) -> String {
+        hash_token(&format!(
+            "{}|{}",
+            self.user_agent.as_deref().unwrap_or_default(),
+            self.ip_address.as_deref().unwrap_or_default()
+        ))
+    }
+
+    /// "Chrome, Windows" для писем и списка сессий
+    pub fn describe(&self) -> String {
+        match (self.browser.as_deref(), self.os.as_deref()) {
+            (Some(browser), Some(os)) => format!("{}, {}", browser, os),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            (None, None) => "Неизвестное устройство".to_string(),
+        }
+    }
+}
+
+/// Браузер и ОС по строке User-Agent. Порядок проверок важен: Edge и Opera содержат "Chrome",
+/// Chrome содержит "Safari", iOS — "Mac OS X", Android — "Linux".
+pub fn parse_user_agent(user_agent: &str) -> (Option<&'static str>, Option<&'static str>) {
+    let has = |marker: &str| user_agent.contains(marker);
+
+    let browser = if has("Edg/") || has("EdgA/") || has("EdgiOS/") {
+        Some("Edge")
+    } else if has("OPR/") || has("Opera") {
+        Some("Opera")
+    } else if has("YaBrowser/") {
+        Some("Yandex Browser")
+    } else if has("Firefox/") || has("FxiOS/") {
+        Some("Firefox")
+    } else if has("Chrome/") || has("CriOS/") {
+        Some("Chrome")
+    } else if has("Safari/") {
+        Some("Safari")
+    } else {
+        None
+    };
+
+    let os = if has("Windows") {
+        Some("Windows")
+    } else if has("iPhone") || has("iPad") || has("iPod") {
+        Some("iOS")
+    } else if has("Android") {
+        Some("Android")
+    } else if has("CrOS") {
+        Some("ChromeOS")
+    } else if has("Macintosh") || has("Mac OS X") {
+        Some("macOS")
+    } else if has("Linux") {
+        Some("Linux")
+    } else {
+        None
+    };
+
+    (browser, os)
+}
+
+/// Грубое местоположение: код страны от CDN (Cloudflare, Vercel), для внутренних адресов — "Local network".
+/// Базы GeoIP в проекте нет, поэтому без заголовка страна неизвестна.
+pub fn coarse_location(headers: &HeaderMap, ip_address: Option<&str>) -> Option<String> {
+    let country = ["cf-ipcountry", "x-vercel-ip-country", "x-country-code"]
+        .iter()
+        .filter_map(|name| headers.get(*name).and_then(|value| value.to_str().ok()))
+        .map(|value| value.trim().to_uppercase())
+        // XX — Cloudflare не смог определить страну, T1 (Tor) отсеивается проверкой на буквы
+        .find(|code| code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) && code != "XX");
+    if country.is_some() {
+        return country;
+    }
+
+    let ip: IpAddr = ip_address?.parse().ok()?;
+    let internal = match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    };
+    internal.then(|| "Local network".to_string())
+}
@@ -31,12 +31,194 @@ pub enum AppError {
     
     #[error("External service error: {0}")]
     ExternalService(String),
+
+    #[error("External service timeout: {0}")]
+    ExternalTimeout(String),
+    
+    #[error("Feature not enabled: {0}")]
+    FeatureNotEnabled(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Unprocessable entity: {0}")]
+    UnprocessableEntity(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    /// Ошибка с более точным кодом, чем следует из варианта; статус и сообщение — от `inner`
+    #[error("{inner}")]
+    WithCode { code: ErrorCode, inner: Box<AppError> },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(ref err) => {
+/// Машиночитаемый код ошибки в ответе (`error.code`). Фронтенд ориентируется на код,
+/// а не на текст сообщения. Коды только добавляются: переименование ломает клиентов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Database,
+    Validation,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    BadRequest,
+    Internal,
+    ExternalService,
+    ExternalTimeout,
+    FeatureDisabled,
+    RateLimited,
+    Conflict,
+    Unprocessable,
+    TokenExpired,
+    SessionRevoked,
+    AccountSuspended,
+    Duplicate,
+    AiUnavailable,
+    IdempotencyInProgress,
+    IdempotencyMismatch,
+    EditWindowClosed,
+    PayloadTooLarge,
+    NotImplemented,
+    VisionUnavailable,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 24] = [
+        ErrorCode::Database,
+        ErrorCode::Validation,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::BadRequest,
+        ErrorCode::Internal,
+        ErrorCode::ExternalService,
+        ErrorCode::ExternalTimeout,
+        ErrorCode::FeatureDisabled,
+        ErrorCode::RateLimited,
+        ErrorCode::Conflict,
+        ErrorCode::Unprocessable,
+        ErrorCode::TokenExpired,
+        ErrorCode::SessionRevoked,
+        ErrorCode::AccountSuspended,
+        ErrorCode::Duplicate,
+        ErrorCode::AiUnavailable,
+        ErrorCode::IdempotencyInProgress,
+        ErrorCode::IdempotencyMismatch,
+        ErrorCode::EditWindowClosed,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::NotImplemented,
+        ErrorCode::VisionUnavailable,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Database => "ERR_DATABASE",
+            ErrorCode::Validation => "ERR_VALIDATION",
+            ErrorCode::Unauthorized => "ERR_UNAUTHORIZED",
+            ErrorCode::Forbidden => "ERR_FORBIDDEN",
+            ErrorCode::NotFound => "ERR_NOT_FOUND",
+            ErrorCode::BadRequest => "ERR_BAD_REQUEST",
+            ErrorCode::Internal => "ERR_INTERNAL",
+            ErrorCode::ExternalService => "ERR_EXTERNAL_SERVICE",
+            ErrorCode::ExternalTimeout => "ERR_EXTERNAL_TIMEOUT",
+            ErrorCode::FeatureDisabled => "ERR_FEATURE_DISABLED",
+            ErrorCode::RateLimited => "ERR_RATE_LIMITED",
+            ErrorCode::Conflict => "ERR_CONFLICT",
+            ErrorCode::Unprocessable => "ERR_UNPROCESSABLE",
+            ErrorCode::TokenExpired => "ERR_TOKEN_EXPIRED",
+            ErrorCode::SessionRevoked => "ERR_SESSION_REVOKED",
+            ErrorCode::AccountSuspended => "ERR_ACCOUNT_SUSPENDED",
+            ErrorCode::Duplicate => "ERR_DUPLICATE",
+            ErrorCode::AiUnavailable => "ERR_AI_UNAVAILABLE",
+            ErrorCode::IdempotencyInProgress => "ERR_IDEMPOTENCY_IN_PROGRESS",
+            ErrorCode::IdempotencyMismatch => "ERR_IDEMPOTENCY_MISMATCH",
+            ErrorCode::EditWindowClosed => "ERR_EDIT_WINDOW_CLOSED",
+            ErrorCode::PayloadTooLarge => "ERR_PAYLOAD_TOO_LARGE",
+            ErrorCode::NotImplemented => "ERR_NOT_IMPLEMENTED",
+            ErrorCode::VisionUnavailable => "ERR_VISION_UNAVAILABLE",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::Database => "Database error",
+            ErrorCode::Validation => "Request body failed field validation",
+            ErrorCode::Unauthorized => "Missing or invalid credentials",
+            ErrorCode::Forbidden => "Not allowed for this user",
+            ErrorCode::NotFound => "Resource not found",
+            ErrorCode::BadRequest => "Invalid request",
+            ErrorCode::Internal => "Internal server error",
+            ErrorCode::ExternalService => "External service failed",
+            ErrorCode::ExternalTimeout => "External service timed out",
+            ErrorCode::FeatureDisabled => "Feature is not enabled for this user",
+            ErrorCode::RateLimited => "Too many requests, retry later",
+            ErrorCode::Conflict => "Request conflicts with the current state of the resource",
+            ErrorCode::Unprocessable => "Request is well-formed but cannot be processed",
+            ErrorCode::TokenExpired => "Access token expired, refresh it or sign in again",
+            ErrorCode::SessionRevoked => "Session was revoked, sign in again",
+            ErrorCode::AccountSuspended => "Account is suspended",
+            ErrorCode::Duplicate => "Resource already exists",
+            ErrorCode::AiUnavailable => "AI provider is unavailable or timed out",
+            ErrorCode::IdempotencyInProgress => "A request with this Idempotency-Key is still in progress",
+            ErrorCode::IdempotencyMismatch => "Idempotency-Key was already used with a different request body",
+            ErrorCode::EditWindowClosed => "Post already has engagement, only small corrections to its text are allowed",
+            ErrorCode::PayloadTooLarge => "Request body is larger than allowed",
+            ErrorCode::NotImplemented => "Not supported by this server",
+            ErrorCode::VisionUnavailable => "No AI provider that can read images is configured",
+        }
+    }
+}
+
+impl AppError {
+    /// Уточняет код ошибки; статус и сообщение остаются прежними
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        match self {
+            AppError::WithCode { inner, .. } => AppError::WithCode { code, inner },
+            other => AppError::WithCode { code, inner: Box::new(other) },
+        }
+    }
+
+    /// Код ошибки: уточненный, если он задан, иначе — по варианту
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Database(_) => ErrorCode::Database,
+            AppError::Validation(_) => ErrorCode::Validation,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::InternalServerError(_) => ErrorCode::Internal,
+            AppError::ExternalService(_) => ErrorCode::ExternalService,
+            AppError::ExternalTimeout(_) => ErrorCode::ExternalTimeout,
+            AppError::FeatureNotEnabled(_) => ErrorCode::FeatureDisabled,
+            AppError::TooManyRequests(_) => ErrorCode::RateLimited,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::UnprocessableEntity(_) => ErrorCode::Unprocessable,
+            AppError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            AppError::NotImplemented(_) => ErrorCode::NotImplemented,
+            AppError::WithCode { code, .. } => *code,
+        }
+    }
+
+    /// Сбой или таймаут внешнего сервиса (в том числе с уточненным кодом) —
+    /// вызывающий код может отдать деградированный ответ
+    pub fn is_external_failure(&self) -> bool {
+        match self {
+            AppError::ExternalService(_) | AppError::ExternalTimeout(_) => true,
+            AppError::WithCode { inner, .. } => inner.is_external_failure(),
+            _ => false,
+        }
+    }
+
+    fn status_and_message(&self) -> (StatusCode, &'static str) {
+        match self {
+            AppError::Database(err) => {
                 tracing::error!("Database error: {:?}", err);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error occurred")
             }
@@ -53,10 +235,28 @@ impl IntoResponse for AppError {
                 tracing::error!("External service error: {:?}", self);
                 (StatusCode::SERVICE_UNAVAILABLE, "External service error")
             }
-        };
+            AppError::ExternalTimeout(_) => {
+                tracing::error!("External service timeout: {:?}", self);
+                (StatusCode::GATEWAY_TIMEOUT, "External service timeout")
+            }
+            AppError::FeatureNotEnabled(_) => (StatusCode::FORBIDDEN, "feature_not_enabled"),
+            AppError::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, "Conflict"),
+            AppError::UnprocessableEntity(_) => (StatusCode::UNPROCESSABLE_ENTITY, "Unprocessable entity"),
+            AppError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "Payload too large"),
+            AppError::NotImplemented(_) => (StatusCode::NOT_IMPLEMENTED, "Not implemented"),
+            AppError::WithCode { inner, .. } => inner.status_and_message(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = self.status_and_message();
 
         let body = Json(json!({
             "error": {
+                "code": self.code().as_str(),
                 "message": error_message,
                 "details": self.to_string()
             }
@@ -65,3 +265,135 @@ impl IntoResponse for AppError {
         (status, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Имя варианта. Сопоставление исчерпывающее: новый вариант не скомпилируется,
+    /// пока его не добавят сюда и в `samples`.
+    fn variant(error: &AppError) -> &'static str {
+        match error {
+            AppError::Database(_) => "Database",
+            AppError::Validation(_) => "Validation",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::Forbidden(_) => "Forbidden",
+            AppError::NotFound(_) => "NotFound",
+            AppError::BadRequest(_) => "BadRequest",
+            AppError::InternalServerError(_) => "InternalServerError",
+            AppError::ExternalService(_) => "ExternalService",
+            AppError::ExternalTimeout(_) => "ExternalTimeout",
+            AppError::FeatureNotEnabled(_) => "FeatureNotEnabled",
+            AppError::TooManyRequests(_) => "TooManyRequests",
+            AppError::Conflict(_) => "Conflict",
+            AppError::UnprocessableEntity(_) => "UnprocessableEntity",
+            AppError::PayloadTooLarge(_) => "PayloadTooLarge",
+            AppError::NotImplemented(_) => "NotImplemented",
+            AppError::WithCode { .. } => "WithCode",
+        }
+    }
+
+    const VARIANTS: usize = 16;
+
+    fn samples() -> Vec<(AppError, StatusCode)> {
+        let text = || "details".to_string();
+        vec![
+            (AppError::Database(sqlx::Error::RowNotFound), StatusCode::INTERNAL_SERVER_ERROR),
+            (AppError::Validation(validator::ValidationErrors::new()), StatusCode::BAD_REQUEST),
+            (AppError::Unauthorized(text()), StatusCode::UNAUTHORIZED),
+            (AppError::Forbidden(text()), StatusCode::FORBIDDEN),
+            (AppError::NotFound(text()), StatusCode::NOT_FOUND),
+            (AppError::BadRequest(text()), StatusCode::BAD_REQUEST),
+            (AppError::InternalServerError(text()), StatusCode::INTERNAL_SERVER_ERROR),
+            (AppError::ExternalService(text()), StatusCode::SERVICE_UNAVAILABLE),
+            (AppError::ExternalTimeout(text()), StatusCode::GATEWAY_TIMEOUT),
+            (AppError::FeatureNotEnabled(text()), StatusCode::FORBIDDEN),
+            (AppError::TooManyRequests(text()), StatusCode::TOO_MANY_REQUESTS),
+            (AppError::Conflict(text()), StatusCode::CONFLICT),
+            (AppError::UnprocessableEntity(text()), StatusCode::UNPROCESSABLE_ENTITY),
+            (AppError::PayloadTooLarge(text()), StatusCode::PAYLOAD_TOO_LARGE),
+            (AppError::NotImplemented(text()), StatusCode::NOT_IMPLEMENTED),
+            (AppError::Conflict(text()).with_code(ErrorCode::Duplicate), StatusCode::CONFLICT),
+        ]
+    }
+
+    async fn envelope(error: AppError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn every_variant_serializes_with_its_own_code() {
+        let samples = samples();
+        let mut covered: Vec<&str> = samples.iter().map(|(error, _)| variant(error)).collect();
+        covered.sort();
+        covered.dedup();
+        assert_eq!(covered.len(), VARIANTS, "every AppError variant needs a sample");
+
+        let mut owners: HashMap<String, &str> = HashMap::new();
+        for (error, expected_status) in samples {
+            let name = variant(&error);
+            let (status, body) = envelope(error).await;
+            assert_eq!(status, expected_status, "{}", name);
+
+            let code = body["error"]["code"].as_str().unwrap_or_default().to_string();
+            assert!(code.starts_with("ERR_") && code.len() > 4, "{} has code {:?}", name, code);
+            assert!(!body["error"]["message"].as_str().unwrap_or_default().is_empty(), "{}", name);
+            assert!(!body["error"]["details"].as_str().unwrap_or_default().is_empty(), "{}", name);
+
+            if let Some(other) = owners.insert(code.clone(), name) {
+                panic!("{} and {} share {}", other, name, code);
+            }
+        }
+    }
+
+    #[test]
+    fn published_codes_are_unique_and_described() {
+        let mut seen = HashMap::new();
+        for code in ErrorCode::ALL {
+            assert!(!code.description().is_empty(), "{:?}", code);
+            assert!(code.as_str().starts_with("ERR_"), "{:?}", code);
+            if let Some(other) = seen.insert(code.as_str(), code) {
+                panic!("{:?} and {:?} share {}", other, code, code.as_str());
+            }
+        }
+
+        // Коды вариантов публикуются в списке для фронтенда
+        for (error, _) in samples() {
+            assert!(ErrorCode::ALL.contains(&error.code()), "{:?} is not in ErrorCode::ALL", error.code());
+        }
+    }
+
+    #[tokio::test]
+    async fn specific_code_keeps_status_and_message_of_the_variant() {
+        let generic = AppError::BadRequest("Unit ml cannot be converted to g".to_string());
+        let specific = AppError::BadRequest("Unit ml cannot be converted to g".to_string())
+            .with_code(ErrorCode::Unprocessable)
+            .with_code(ErrorCode::Duplicate);
+        assert!(matches!(&specific, AppError::WithCode { inner, .. } if matches!(**inner, AppError::BadRequest(_))));
+
+        let (generic_status, generic_body) = envelope(generic).await;
+        let (status, body) = envelope(specific).await;
+        assert_eq!(status, generic_status);
+        assert_eq!(body["error"]["message"], generic_body["error"]["message"]);
+        assert_eq!(body["error"]["details"], generic_body["error"]["details"]);
+        assert_eq!(body["error"]["code"], "ERR_DUPLICATE");
+
+        assert!(AppError::ExternalTimeout("groq".to_string()).with_code(ErrorCode::AiUnavailable).is_external_failure());
+        assert!(!AppError::NotFound("post".to_string()).with_code(ErrorCode::Duplicate).is_external_failure());
+    }
+
+    #[tokio::test]
+    async fn meta_endpoint_lists_every_code() {
+        let codes = crate::api::meta::get_error_codes().await.0;
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+        for (listed, code) in codes.iter().zip(ErrorCode::ALL) {
+            assert_eq!(listed.code, code.as_str());
+            assert_eq!(listed.description, code.description());
+        }
+    }
+}
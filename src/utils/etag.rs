@@ -0,0 +1,74 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json as ResponseJson, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Построитель слабого ETag. Для данных конкретного пользователя в ETag
+/// обязательно входит его id — одинаковые ответы разных пользователей не совпадают.
+#[derive(Default)]
+pub struct ETagBuilder {
+    hasher: Sha256,
+}
+
+impl ETagBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ETag, который различается для разных пользователей
+    pub fn for_user(user_id: Uuid) -> Self {
+        Self::new().part(user_id.as_bytes())
+    }
+
+    pub fn part(mut self, bytes: &[u8]) -> Self {
+        // Длина перед значением, чтобы ("ab", "c") и ("a", "bc") давали разный хеш
+        self.hasher.update((bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+        self
+    }
+
+    pub fn timestamp(self, value: Option<DateTime<Utc>>) -> Self {
+        let micros = value.map(|value| value.timestamp_micros()).unwrap_or(0);
+        self.part(&micros.to_le_bytes())
+    }
+
+    /// Хеш сериализованного значения — для ответов без собственного updated_at
+    pub fn content<T: Serialize>(self, value: &T) -> Self {
+        let bytes = serde_json::to_vec(value).unwrap_or_default();
+        self.part(&bytes)
+    }
+
+    pub fn build(self) -> String {
+        let digest = self.hasher.finalize();
+        let hex: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("W/\"{}\"", hex)
+    }
+}
+
+/// Совпадает ли ETag с заголовком If-None-Match (слабое сравнение, поддерживаются списки и `*`)
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let expected = opaque(etag);
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == expected)
+}
+
+/// 304 с пустым телом, если клиент уже имеет эту версию, иначе JSON с заголовком ETag
+pub fn json_with_etag<T: Serialize>(headers: &HeaderMap, etag: String, body: T) -> Response {
+    let etag_header = HeaderValue::from_str(&etag).expect("ETag is always a valid header value");
+
+    if if_none_match(headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag_header)]).into_response();
+    }
+
+    ([(header::ETAG, etag_header)], ResponseJson(body)).into_response()
+}
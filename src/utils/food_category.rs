@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use crate::{
+    models::{fridge::FridgeCategory, presets::FoodPresets},
+    utils::ingredient_matcher::normalize_ingredient,
+};
+
+/// Начала слов, по которым блюдо относится к овощам
+const VEGETABLE_STEMS: &[&str] = &[
+    "овощ", "огур", "помид", "томат", "морков", "капуст", "брокк", "цветн", "салат", "шпинат",
+    "кабач", "цукин", "баклаж", "перец", "свекл", "тыкв", "редис", "чеснок", "лук", "зелен",
+    "укроп", "петруш", "сельдер", "спарж", "фасол", "горош", "кукуруз", "руккол", "авокадо",
+    "vegetab", "tomato", "cucumb", "carrot", "cabbag", "brocco", "cauliflow", "lettuc", "spinach",
+    "zucchin", "eggplant", "pepper", "beet", "pumpkin", "onion", "garlic", "celery", "asparag",
+    "salad", "kale", "avocado",
+];
+
+/// Начала слов, по которым блюдо относится к фруктам и ягодам
+const FRUIT_STEMS: &[&str] = &[
+    "фрукт", "ягод", "яблок", "банан", "апельсин", "мандарин", "груш", "персик", "абрикос",
+    "нектарин", "виноград", "клубник", "земляник", "малин", "черник", "голубик", "смородин",
+    "вишн", "черешн", "киви", "ананас", "манго", "лимон", "лайм", "грейпфрут", "арбуз", "дын",
+    "гранат", "хурм", "инжир",
+    "fruit", "berr", "apple", "banana", "orange", "tangerin", "pear", "peach", "apricot",
+    "grape", "strawb", "raspb", "bluebe", "cherr", "kiwi", "pineap", "mango", "lemon", "lime",
+    "melon", "pomegran",
+];
+
+/// Категория продукта по названию блюда или продукта из дневника.
+/// Пока распознаются только овощи и фрукты; для остального — `None`.
+pub fn categorize_food(name: &str) -> Option<FridgeCategory> {
+    let normalized = normalize_ingredient(name);
+    let words: Vec<&str> = normalized.split(' ').filter(|word| !word.is_empty()).collect();
+    let matches = |stems: &[&str]| words.iter().any(|word| stems.iter().any(|stem| word.starts_with(stem)));
+
+    // Фрукты проверяются первыми: "салат из яблок" — скорее фрукт, чем овощ
+    if matches(FRUIT_STEMS) {
+        Some(FridgeCategory::Fruits)
+    } else if matches(VEGETABLE_STEMS) {
+        Some(FridgeCategory::Vegetables)
+    } else {
+        None
+    }
+}
+
+/// Уверенность подсказки: название совпало с предустановленным продуктом целиком
+const PRESET_EXACT_CONFIDENCE: f64 = 0.95;
+/// Совпало первое слово предустановленного продукта ("молоко 3 2" — "молоко коровье")
+const PRESET_WORD_CONFIDENCE: f64 = 0.75;
+/// Распознано по началам слов (`categorize_food`)
+const STEM_CONFIDENCE: f64 = 0.6;
+
+/// Подсказка категории продукта по названию: категория и уверенность от 0 до 1.
+/// Сначала ищется предустановленный продукт, затем — начала слов овощей и фруктов.
+pub fn suggest_category(name: &str) -> Option<(FridgeCategory, f64)> {
+    let normalized = normalize_ingredient(name);
+    if normalized.is_empty() {
+        return None;
+    }
+    let first_word = normalized.split(' ').next().unwrap_or_default();
+
+    let presets = FoodPresets::get_product_presets();
+    if let Some(preset) = presets.iter().find(|preset| normalize_ingredient(&preset.name) == normalized) {
+        return Some((preset.category.clone(), PRESET_EXACT_CONFIDENCE));
+    }
+    if let Some(preset) = presets
+        .iter()
+        .find(|preset| normalize_ingredient(&preset.name).split(' ').next() == Some(first_word))
+    {
+        return Some((preset.category.clone(), PRESET_WORD_CONFIDENCE));
+    }
+
+    categorize_food(name).map(|category| (category, STEM_CONFIDENCE))
+}
+
+/// Начала слов напитков и жидких продуктов — порция в миллилитрах
+const LIQUID_STEMS: &[&str] = &[
+    "вод", "сок", "чай", "кофе", "капучин", "латте", "американо", "молок", "кефир", "ряженк",
+    "айран", "морс", "компот", "квас", "лимонад", "смузи", "коктейл", "бульон", "напит", "какао",
+    "вино", "пиво",
+    "water", "juice", "tea", "coffee", "cappucc", "latte", "milk", "kefir", "smoothie", "shake",
+    "broth", "drink", "cocoa", "wine", "beer", "soda",
+];
+
+/// Начала слов продуктов, которые считают штуками
+const COUNTABLE_STEMS: &[&str] = &[
+    "яйц", "яйк", "банан", "яблок", "апельсин", "мандарин", "груш", "персик", "киви", "сосиск",
+    "сардельк", "котлет", "блин", "сырник", "пельмен", "вареник", "печень", "конфет", "круассан",
+    "булочк", "батончик",
+    "egg", "banana", "apple", "orange", "tangerin", "pear", "peach", "kiwi", "sausage", "cutlet",
+    "pancake", "dumpling", "cookie", "candy", "croissant", "bun",
+];
+
+/// Как обычно отмеряют порцию продукта
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortionKind {
+    /// Граммы — твердые продукты и блюда
+    Weight,
+    /// Миллилитры — напитки и жидкости
+    Volume,
+    /// Штуки — яйца, фрукты, выпечка
+    Pieces,
+}
+
+/// Тип порции по названию: сначала жидкости (в том числе категория «Напитки»
+/// у предустановленных продуктов), затем штучные продукты; остальное — по весу.
+/// Первое слово решает раньше остальных: "кофе с молоком" — напиток, "яблоко печеное" — штуки.
+pub fn portion_kind(name: &str) -> PortionKind {
+    let normalized = normalize_ingredient(name);
+    let words: Vec<&str> = normalized.split(' ').filter(|word| !word.is_empty()).collect();
+    let starts = |word: &str, stems: &[&str]| stems.iter().any(|stem| word.starts_with(stem));
+
+    if let Some(first) = words.first() {
+        if starts(first, LIQUID_STEMS) {
+            return PortionKind::Volume;
+        }
+        if starts(first, COUNTABLE_STEMS) {
+            return PortionKind::Pieces;
+        }
+    }
+    if matches!(suggest_category(name), Some((FridgeCategory::Beverages, _))) {
+        return PortionKind::Volume;
+    }
+
+    PortionKind::Weight
+}
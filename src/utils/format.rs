@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Язык форматирования текста для пользователя и промптов ИИ.
+/// В JSON API даты по-прежнему отдаются в RFC3339, числа — без форматирования.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Locale {
+    /// По тегу BCP 47: "en-US" → En, "ru" → Ru; неподдерживаемый язык — `None`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "ru" => Some(Locale::Ru),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Обозначение валюты пользователя
+    pub fn currency(&self) -> &'static str {
+        match self {
+            Locale::Ru => "руб.",
+            Locale::En => "RUB",
+        }
+    }
+
+    fn decimal_separator(&self) -> char {
+        match self {
+            Locale::Ru => ',',
+            Locale::En => '.',
+        }
+    }
+
+    fn group_separator(&self) -> char {
+        match self {
+            Locale::Ru => ' ',
+            Locale::En => ',',
+        }
+    }
+}
+
+/// Семейство единиц измерения определяет точность отображения количества
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitFamily {
+    Count, // шт, упаковки — без дробной части, если число целое
+    Bulk,  // кг, л — один знак после запятой
+    Fine,  // г, мл — целые
+    Other,
+}
+
+impl UnitFamily {
+    pub fn of(unit: &str) -> Self {
+        match unit.trim().trim_end_matches('.').to_lowercase().as_str() {
+            "шт" | "штук" | "штуки" | "pcs" | "pc" | "piece" | "pieces" | "упак" | "уп" | "pack" => UnitFamily::Count,
+            "кг" | "kg" | "л" | "l" | "литр" | "литра" => UnitFamily::Bulk,
+            "г" | "гр" | "g" | "мл" | "ml" => UnitFamily::Fine,
+            _ => UnitFamily::Other,
+        }
+    }
+}
+
+pub fn round_to(value: f64, decimals: i32) -> f64 {
+    if !value.is_finite() {
+        return 0.0;
+    }
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+/// Денежные суммы храним и отдаем с точностью до копейки
+pub fn round_money(value: f64) -> f64 {
+    round_to(value, 2)
+}
+
+/// Проценты ограничены диапазоном 0..100 и округлены до десятых
+pub fn round_percent(value: f64) -> f64 {
+    if !value.is_finite() {
+        return 0.0;
+    }
+    round_to(value.clamp(0.0, 100.0), 1)
+}
+
+/// Доля `part` от `whole` в процентах; при нулевом знаменателе — 0
+pub fn percent_of(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 {
+        round_percent(part / whole * 100.0)
+    } else {
+        0.0
+    }
+}
+
+fn format_decimal(value: f64, decimals: usize, locale: Locale) -> String {
+    let formatted = format!("{:.*}", decimals, round_to(value, decimals as i32).abs());
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    let digits: Vec<char> = integer.chars().collect();
+    let mut grouped = String::new();
+    for (i, digit) in digits.iter().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(locale.group_separator());
+        }
+        grouped.push(*digit);
+    }
+
+    let sign = if value < 0.0 && round_to(value, decimals as i32) != 0.0 { "-" } else { "" };
+    match fraction {
+        Some(fraction) => format!("{}{}{}{}", sign, grouped, locale.decimal_separator(), fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+/// "1 234,50 руб."
+pub fn format_money(amount: f64, locale: Locale) -> String {
+    format!("{} {}", format_decimal(amount, 2, locale), locale.currency())
+}
+
+/// "12,3%"
+pub fn format_percent(value: f64, locale: Locale) -> String {
+    format!("{}%", format_decimal(round_percent(value), 1, locale))
+}
+
+/// Количество с единицей измерения: "3 шт", "1,5 кг", "250 г"
+pub fn format_quantity(quantity: f32, unit: &str, locale: Locale) -> String {
+    let quantity = quantity as f64;
+    let number = match UnitFamily::of(unit) {
+        UnitFamily::Count | UnitFamily::Other if round_to(quantity, 2).fract() == 0.0 => {
+            format_decimal(quantity, 0, locale)
+        },
+        UnitFamily::Count | UnitFamily::Bulk => format_decimal(quantity, 1, locale),
+        UnitFamily::Fine => format_decimal(quantity, 0, locale),
+        UnitFamily::Other => format_decimal(quantity, 2, locale).trim_end_matches('0').to_string(),
+    };
+
+    let unit = unit.trim();
+    if unit.is_empty() {
+        number
+    } else {
+        format!("{} {}", number, unit)
+    }
+}
+
+/// Форма русского слова для числа: plural_ru(1, "день", "дня", "дней") — "день",
+/// 3 — "дня", 11 и 25 — "дней"
+pub fn plural_ru<'a>(n: i64, one: &'a str, few: &'a str, many: &'a str) -> &'a str {
+    let n = n.unsigned_abs();
+    match (n % 10, n % 100) {
+        (1, rem) if rem != 11 => one,
+        (2..=4, rem) if !(12..=14).contains(&rem) => few,
+        _ => many,
+    }
+}
+
+/// "5 дней" / "5 days"
+pub fn format_days(days: i64, locale: Locale) -> String {
+    match locale {
+        Locale::Ru => format!("{} {}", days, plural_ru(days, "день", "дня", "дней")),
+        Locale::En if days == 1 => "1 day".to_string(),
+        Locale::En => format!("{} days", days),
+    }
+}
+
+/// Дата для текста промпта: "16.10.2026" или "Oct 16, 2026"
+pub fn format_date(date: DateTime<Utc>, locale: Locale) -> String {
+    match locale {
+        Locale::Ru => date.format("%d.%m.%Y").to_string(),
+        Locale::En => date.format("%b %-d, %Y").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn money_is_rounded_to_kopecks_with_the_locale_currency() {
+        assert_eq!(format_money(43.6_f32 as f64, Locale::Ru), "43,60 руб.");
+        assert_eq!(format_money(1234.5, Locale::Ru), "1 234,50 руб.");
+        assert_eq!(format_money(1234567.891, Locale::En), "1,234,567.89 RUB");
+        assert_eq!(format_money(-0.001, Locale::Ru), "0,00 руб.");
+        assert_eq!(format_money(-12.5, Locale::En), "-12.50 RUB");
+        assert_eq!(format_money(f64::NAN, Locale::Ru), "0,00 руб.");
+    }
+
+    #[test]
+    fn percentages_are_clamped_and_rounded_to_one_decimal() {
+        assert_eq!(format_percent(12.345, Locale::Ru), "12,3%");
+        assert_eq!(format_percent(33.333333, Locale::En), "33.3%");
+        assert_eq!(format_percent(123.4, Locale::Ru), "100,0%");
+        assert_eq!(format_percent(-5.0, Locale::Ru), "0,0%");
+        assert_eq!(percent_of(1.0, 3.0), 33.3);
+        assert_eq!(percent_of(5.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn quantities_follow_the_unit_family() {
+        assert_eq!(format_quantity(3.0, "шт", Locale::Ru), "3 шт");
+        assert_eq!(format_quantity(2.5, "шт", Locale::Ru), "2,5 шт");
+        assert_eq!(format_quantity(1.5, "кг", Locale::Ru), "1,5 кг");
+        assert_eq!(format_quantity(2.0, "l", Locale::En), "2.0 l");
+        assert_eq!(format_quantity(0.3_f32 + 0.6_f32, "л", Locale::Ru), "0,9 л");
+        assert_eq!(format_quantity(249.6, "г", Locale::Ru), "250 г");
+        assert_eq!(format_quantity(1500.0, "ml", Locale::En), "1,500 ml");
+        assert_eq!(format_quantity(1.5, "пучок", Locale::Ru), "1,5 пучок");
+        assert_eq!(format_quantity(0.25, "стакан", Locale::Ru), "0,25 стакан");
+        assert_eq!(format_quantity(2.0, "", Locale::Ru), "2");
+    }
+
+    #[test]
+    fn dates_and_days_use_the_locale() {
+        let date = Utc.with_ymd_and_hms(2026, 10, 6, 23, 30, 0).unwrap();
+        assert_eq!(format_date(date, Locale::Ru), "06.10.2026");
+        assert_eq!(format_date(date, Locale::En), "Oct 6, 2026");
+
+        assert_eq!(format_days(1, Locale::Ru), "1 день");
+        assert_eq!(format_days(3, Locale::Ru), "3 дня");
+        assert_eq!(format_days(11, Locale::Ru), "11 дней");
+        assert_eq!(format_days(22, Locale::Ru), "22 дня");
+        assert_eq!(format_days(1, Locale::En), "1 day");
+        assert_eq!(format_days(5, Locale::En), "5 days");
+    }
+
+    #[test]
+    fn unit_families() {
+        assert_eq!(UnitFamily::of("Шт."), UnitFamily::Count);
+        assert_eq!(UnitFamily::of(" KG "), UnitFamily::Bulk);
+        assert_eq!(UnitFamily::of("гр"), UnitFamily::Fine);
+        assert_eq!(UnitFamily::of("щепотка"), UnitFamily::Other);
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    models::preferences::GlossaryPreferences,
+    utils::{
+        format::Locale,
+        ingredient_matcher::normalize_ingredient,
+        sanitize::{prompt_safe, user_data_block, MAX_PROMPT_FIELD_CHARS},
+    },
+};
+
+/// Сколько продуктов глоссария попадает в промпт
+const MAX_PROMPT_TERMS: usize = 20;
+/// Сколько других названий продукта перечисляется в промпте
+const MAX_PROMPT_ALTERNATIVES: usize = 3;
+
+/// Продукты: название по-русски, по-английски и другие названия, которые встречаются в ответах
+/// модели и у пользователей (разговорные, региональные, множественное число). Основа — продукты
+/// из пресетов и стартовых наборов. После `normalize_ingredient` названия не повторяются.
+const TERMS: &[(&str, &str, &[&str])] = &[
+    // Молочные продукты
+    ("молоко", "milk", &["молоко коровье", "коровье молоко", "cow's milk", "whole milk"]),
+    ("творог", "cottage cheese", &["twaróg", "tvorog", "quark", "farmer cheese", "curd cheese"]),
+    ("сметана", "sour cream", &["śmietana", "smetana"]),
+    ("кефир", "kefir", &[]),
+    ("ряженка", "fermented baked milk", &["ryazhenka"]),
+    ("йогурт натуральный", "plain yogurt", &["йогурт", "натуральный йогурт", "yogurt", "yoghurt", "natural yogurt"]),
+    ("сливки", "cream", &["heavy cream", "whipping cream"]),
+    ("масло сливочное", "butter", &["сливочное масло"]),
+    ("сыр твердый", "hard cheese", &["твердый сыр"]),
+    ("сыр фета", "feta cheese", &["фета", "feta"]),
+    ("моцарелла", "mozzarella", &["сыр моцарелла"]),
+    ("пармезан", "parmesan", &["сыр пармезан", "parmigiano reggiano", "parmigiano"]),
+    ("сливочный сыр", "cream cheese", &["творожный сыр"]),
+    ("сгущенное молоко", "condensed milk", &["сгущенка", "sweetened condensed milk"]),
+    // Мясо, птица, рыба
+    ("куриная грудка", "chicken breast", &["куриное филе", "филе куриной грудки", "chicken fillet", "chicken breasts"]),
+    ("куриные бедра", "chicken thighs", &["куриное бедро", "бедро куриное", "chicken thigh"]),
+    ("говядина", "beef", &[]),
+    ("свинина", "pork", &[]),
+    ("баранина", "lamb", &["mutton"]),
+    ("индейка", "turkey", &["мясо индейки"]),
+    ("фарш", "minced meat", &["мясной фарш", "ground meat", "mince"]),
+    ("бекон", "bacon", &[]),
+    ("ветчина", "ham", &[]),
+    ("сосиски", "sausages", &["сосиска", "frankfurters", "hot dogs"]),
+    ("лосось", "salmon", &["семга", "atlantic salmon"]),
+    ("треска", "cod", &[]),
+    ("тунец", "tuna", &[]),
+    ("скумбрия", "mackerel", &[]),
+    ("сельдь", "herring", &["селедка"]),
+    ("креветки", "shrimp", &["креветка", "shrimps", "prawns", "prawn"]),
+    // Яйца и растительный белок
+    ("яйца куриные", "eggs", &["яйца", "яйцо", "куриные яйца", "egg", "chicken eggs"]),
+    ("тофу", "tofu", &["bean curd"]),
+    ("нут", "chickpeas", &["турецкий горох", "chickpea", "garbanzo beans"]),
+    ("чечевица", "lentils", &["lentil"]),
+    ("фасоль", "beans", &["bean", "kidney beans"]),
+    ("зеленый горошек", "green peas", &["горошек", "peas", "garden peas"]),
+    // Овощи и зелень
+    ("брокколи", "broccoli", &[]),
+    ("капуста белокочанная", "white cabbage", &["капуста", "белокочанная капуста", "cabbage"]),
+    ("цветная капуста", "cauliflower", &["капуста цветная"]),
+    ("пекинская капуста", "napa cabbage", &["капуста пекинская", "chinese cabbage"]),
+    ("помидоры", "tomatoes", &["помидор", "томаты", "томат", "tomato"]),
+    ("помидоры черри", "cherry tomatoes", &["черри", "томаты черри", "cherry tomato"]),
+    ("огурцы", "cucumbers", &["огурец", "cucumber"]),
+    ("лук репчатый", "onion", &["лук", "репчатый лук", "onions", "yellow onion"]),
+    ("зеленый лук", "spring onions", &["лук зеленый", "spring onion", "green onions", "scallions"]),
+    ("лук-порей", "leek", &["порей", "leeks"]),
+    ("чеснок", "garlic", &[]),
+    ("картофель", "potatoes", &["картошка", "potato"]),
+    ("морковь", "carrots", &["морковка", "carrot"]),
+    ("свекла", "beetroot", &["beet", "beets"]),
+    ("кабачок", "zucchini", &["кабачки", "цукини", "courgette", "courgettes"]),
+    ("баклажан", "eggplant", &["баклажаны", "aubergine", "aubergines"]),
+    ("болгарский перец", "bell pepper", &["сладкий перец", "перец болгарский", "bell peppers", "sweet pepper", "capsicum"]),
+    ("перец чили", "chili pepper", &["чили", "острый перец", "chilli", "chile"]),
+    ("тыква", "pumpkin", &[]),
+    ("шпинат", "spinach", &[]),
+    ("салат листовой", "lettuce", &["листовой салат", "салат латук", "латук"]),
+    ("руккола", "arugula", &["рукола", "rocket"]),
+    ("сельдерей", "celery", &[]),
+    ("редис", "radishes", &["редиска", "radish"]),
+    ("кукуруза", "sweetcorn", &["corn", "sweet corn"]),
+    ("шампиньоны", "mushrooms", &["грибы", "шампиньон", "button mushrooms", "champignons"]),
+    ("укроп", "dill", &[]),
+    ("петрушка", "parsley", &[]),
+    ("кинза", "cilantro", &["fresh coriander", "coriander leaves"]),
+    ("базилик", "basil", &[]),
+    ("зелень", "fresh herbs", &["herbs"]),
+    ("имбирь", "ginger", &["корень имбиря", "ginger root"]),
+    ("авокадо", "avocado", &["avocados"]),
+    // Фрукты и ягоды
+    ("яблоко", "apples", &["яблоки", "apple"]),
+    ("банан", "bananas", &["бананы", "banana"]),
+    ("лимон", "lemons", &["лимоны", "lemon"]),
+    ("лайм", "lime", &["лаймы", "limes"]),
+    ("апельсин", "oranges", &["апельсины", "orange"]),
+    ("мандарин", "tangerines", &["мандарины", "tangerine", "clementines", "clementine"]),
+    ("груша", "pears", &["груши", "pear"]),
+    ("виноград", "grapes", &[]),
+    ("клубника", "strawberries", &["strawberry"]),
+    ("малина", "raspberries", &["raspberry"]),
+    ("черника", "blueberries", &["blueberry", "bilberries"]),
+    ("ягоды", "berries", &[]),
+    ("изюм", "raisins", &["raisin"]),
+    ("чернослив", "prunes", &["prune"]),
+    // Крупы, мука, хлеб
+    ("рис белый", "white rice", &["рис", "белый рис", "rice"]),
+    ("гречка", "buckwheat", &["гречневая крупа", "греча", "buckwheat groats", "kasha"]),
+    ("овсяные хлопья", "rolled oats", &["овсянка", "геркулес", "oats", "oatmeal", "porridge oats"]),
+    ("киноа", "quinoa", &[]),
+    ("булгур", "bulgur", &["bulgur wheat", "bulghur"]),
+    ("пшено", "millet", &["пшенная крупа"]),
+    ("перловка", "pearl barley", &["перловая крупа", "barley"]),
+    ("манная крупа", "semolina", &["манка"]),
+    ("макароны", "pasta", &["паста", "макаронные изделия"]),
+    ("хлеб пшеничный", "wheat bread", &["хлеб", "белый хлеб", "bread", "white bread"]),
+    ("мука пшеничная", "wheat flour", &["мука", "flour", "plain flour", "all-purpose flour"]),
+    ("лаваш", "lavash", &[]),
+    // Орехи и масла
+    ("миндаль", "almonds", &["almond"]),
+    ("грецкий орех", "walnuts", &["грецкие орехи", "walnut"]),
+    ("масло оливковое", "olive oil", &["оливковое масло", "extra virgin olive oil"]),
+    ("масло подсолнечное", "sunflower oil", &["подсолнечное масло", "растительное масло", "vegetable oil"]),
+    // Бакалея, соусы и специи
+    ("сахар", "sugar", &[]),
+    ("соль", "salt", &[]),
+    ("мед", "honey", &[]),
+    ("черный перец", "black pepper", &["перец черный", "молотый перец", "ground black pepper"]),
+    ("корица", "cinnamon", &[]),
+    ("уксус", "vinegar", &[]),
+    ("соевый соус", "soy sauce", &[]),
+    ("томатная паста", "tomato paste", &[]),
+    ("майонез", "mayonnaise", &["mayo"]),
+    ("горчица", "mustard", &[]),
+    ("разрыхлитель", "baking powder", &["разрыхлитель теста"]),
+    ("пищевая сода", "baking soda", &["сода", "bicarbonate of soda"]),
+    ("крахмал", "starch", &["картофельный крахмал", "potato starch"]),
+    ("какао", "cocoa powder", &["какао-порошок", "cocoa"]),
+    ("темный шоколад", "dark chocolate", &["горький шоколад"]),
+];
+
+#[derive(Debug)]
+struct Term {
+    preferred: String,
+    /// Другие названия в исходном написании: первыми — те, что модель пишет чаще
+    alternatives: Vec<String>,
+}
+
+/// Глоссарий продуктов пользователя: названия его языка и его собственные замены.
+/// По нему промпт подсказывает модели привычные названия, а ответ модели приводится к ним.
+#[derive(Debug)]
+pub struct Glossary {
+    terms: Vec<Term>,
+    /// Нормализованное название (любое, включая предпочтительное) → продукт
+    index: HashMap<String, usize>,
+}
+
+impl Glossary {
+    pub fn new(locale: Locale, preferences: &GlossaryPreferences) -> Self {
+        let mut glossary = Self { terms: Vec::with_capacity(TERMS.len()), index: HashMap::new() };
+        for (ru, en, aliases) in TERMS {
+            let (preferred, other) = match locale {
+                Locale::Ru => (ru, en),
+                Locale::En => (en, ru),
+            };
+            let alternatives = std::iter::once(*other).chain(aliases.iter().copied()).map(str::to_string).collect();
+            glossary.push(preferred.to_string(), alternatives);
+        }
+        for (name, preferred) in &preferences.overrides {
+            glossary.override_term(name, preferred);
+        }
+        glossary
+    }
+
+    fn push(&mut self, preferred: String, alternatives: Vec<String>) {
+        let id = self.terms.len();
+        for name in std::iter::once(&preferred).chain(&alternatives) {
+            self.index.entry(normalize_ingredient(name)).or_insert(id);
+        }
+        self.terms.push(Term { preferred, alternatives });
+    }
+
+    /// Замена пользователя: `name` называть `preferred`. Если одно из названий уже есть
+    /// в глоссарии, меняется предпочтительное название этого продукта, иначе продукт добавляется.
+    fn override_term(&mut self, name: &str, preferred: &str) {
+        let (name_key, preferred_key) = (normalize_ingredient(name), normalize_ingredient(preferred));
+        if name_key.is_empty() || preferred_key.is_empty() {
+            return;
+        }
+        let Some(id) = self.index.get(&name_key).or_else(|| self.index.get(&preferred_key)).copied() else {
+            self.push(preferred.to_string(), vec![name.to_string()]);
+            return;
+        };
+
+        let term = &mut self.terms[id];
+        let previous = std::mem::replace(&mut term.preferred, preferred.to_string());
+        let mut alternatives = vec![previous, name.to_string()];
+        alternatives.append(&mut term.alternatives);
+        let mut seen = HashSet::from([preferred_key.clone()]);
+        alternatives.retain(|alternative| seen.insert(normalize_ingredient(alternative)));
+        term.alternatives = alternatives;
+
+        self.index.insert(name_key, id);
+        self.index.insert(preferred_key, id);
+    }
+
+    /// Продукты глоссария, которые встречаются в названиях, — в порядке первого появления.
+    /// Продукт найден, если его название совпадает с названием целиком или с несколькими словами
+    /// подряд; из вложенных совпадений остается длинное («цветная капуста», а не «капуста»).
+    fn relevant_terms<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Vec<usize> {
+        let mut found = Vec::new();
+        for name in names {
+            let normalized = normalize_ingredient(name);
+            let words: Vec<&str> = normalized.split(' ').collect();
+
+            let mut matches = Vec::new();
+            for start in 0..words.len() {
+                for end in start + 1..=words.len() {
+                    if let Some(&id) = self.index.get(&words[start..end].join(" ")) {
+                        matches.push((start, end, id));
+                    }
+                }
+            }
+            for &(start, end, id) in &matches {
+                let nested = matches.iter().any(|&(other_start, other_end, _)| {
+                    other_start <= start && end <= other_end && other_end - other_start > end - start
+                });
+                if !nested && !found.contains(&id) {
+                    found.push(id);
+                }
+            }
+        }
+        found
+    }
+
+    /// Блок промпта с привычными пользователю названиями продуктов из `names` — только тех,
+    /// что встретились, а не всего глоссария. `None`, если не встретился ни один.
+    pub fn prompt_block<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let terms = self.relevant_terms(names);
+        if terms.is_empty() {
+            return None;
+        }
+
+        let mut lines = String::new();
+        for &id in terms.iter().take(MAX_PROMPT_TERMS) {
+            let term = &self.terms[id];
+            lines.push_str(&format!("- {}", prompt_safe(&term.preferred, MAX_PROMPT_FIELD_CHARS)));
+            if !term.alternatives.is_empty() {
+                let alternatives: Vec<String> = term
+                    .alternatives
+                    .iter()
+                    .take(MAX_PROMPT_ALTERNATIVES)
+                    .map(|alternative| format!("«{}»", prompt_safe(alternative, MAX_PROMPT_FIELD_CHARS)))
+                    .collect();
+                lines.push_str(&format!(" (а не {})", alternatives.join(", ")));
+            }
+            lines.push('\n');
+        }
+
+        Some(format!(
+            "\nНАЗВАНИЯ ПРОДУКТОВ: называй эти продукты именно так, в том числе в списках ингредиентов:\n{}",
+            user_data_block("glossary", &lines)
+        ))
+    }
+
+    /// Название ингредиента в терминах пользователя: если оно целиком совпадает с одним
+    /// из названий продукта — предпочтительное название, иначе без изменений
+    pub fn normalize_name(&self, name: &str) -> String {
+        match self.index.get(&normalize_ingredient(name)) {
+            Some(&id) => match_case(name, &self.terms[id].preferred),
+            None => name.to_string(),
+        }
+    }
+}
+
+impl Default for Glossary {
+    fn default() -> Self {
+        Self::new(Locale::default(), &GlossaryPreferences::default())
+    }
+}
+
+/// Заглавная первая буква, если с нее начиналось исходное название ("Cottage cheese" → "Творог")
+fn match_case(original: &str, preferred: &str) -> String {
+    let capitalized = original.trim_start().chars().next().is_some_and(char::is_uppercase);
+    let mut chars = preferred.chars();
+    match chars.next() {
+        Some(first) if capitalized => first.to_uppercase().chain(chars).collect(),
+        _ => preferred.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::GeneratedRecipe;
+
+    /// Продукты, которые чаще всего встречаются в холодильниках и рецептах: название по-русски
+    /// и по-английски в том виде, как их пишут пользователи и модель
+    const TOP_INGREDIENTS: &[(&str, &str)] = &[
+        ("молоко", "milk"),
+        ("яйца", "eggs"),
+        ("куриное филе", "chicken breast"),
+        ("говядина", "beef"),
+        ("фарш", "ground meat"),
+        ("картошка", "potato"),
+        ("морковка", "carrot"),
+        ("лук", "onions"),
+        ("чеснок", "garlic"),
+        ("помидор", "tomato"),
+        ("огурец", "cucumber"),
+        ("рис", "rice"),
+        ("гречка", "buckwheat"),
+        ("овсянка", "oats"),
+        ("макароны", "pasta"),
+        ("хлеб", "bread"),
+        ("мука", "flour"),
+        ("сахар", "sugar"),
+        ("соль", "salt"),
+        ("сливочное масло", "butter"),
+        ("растительное масло", "vegetable oil"),
+        ("творог", "cottage cheese"),
+        ("сметана", "sour cream"),
+        ("сыр твердый", "hard cheese"),
+        ("яблоки", "apple"),
+        ("бананы", "banana"),
+    ];
+
+    fn glossary(locale: Locale, overrides: &[(&str, &str)]) -> Glossary {
+        let overrides = overrides.iter().map(|(name, preferred)| (name.to_string(), preferred.to_string())).collect();
+        Glossary::new(locale, &GlossaryPreferences { overrides })
+    }
+
+    fn preferred(glossary: &Glossary, names: &[&str]) -> Vec<String> {
+        glossary.relevant_terms(names.iter().copied()).into_iter().map(|id| glossary.terms[id].preferred.clone()).collect()
+    }
+
+    #[test]
+    fn every_name_belongs_to_one_product_after_normalization() {
+        let mut owners: HashMap<String, &str> = HashMap::new();
+        for (ru, en, aliases) in TERMS {
+            for name in [ru, en].into_iter().chain(aliases.iter()) {
+                let key = normalize_ingredient(name);
+                assert!(!key.is_empty(), "{}", name);
+                if let Some(owner) = owners.insert(key.clone(), ru) {
+                    assert_eq!(owner, *ru, "«{}» is listed for both «{}» and «{}»", key, owner, ru);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn top_ingredients_are_covered_in_both_languages() {
+        let (ru, en) = (glossary(Locale::Ru, &[]), glossary(Locale::En, &[]));
+        for (ru_name, en_name) in TOP_INGREDIENTS {
+            let in_russian = ru.normalize_name(en_name);
+            assert_ne!(in_russian, *en_name, "no Russian name for {}", en_name);
+            assert_eq!(ru.normalize_name(ru_name), in_russian, "{} / {}", ru_name, en_name);
+
+            let in_english = en.normalize_name(ru_name);
+            assert_ne!(in_english, *ru_name, "no English name for {}", ru_name);
+            assert_eq!(en.normalize_name(en_name), in_english, "{} / {}", ru_name, en_name);
+        }
+    }
+
+    #[test]
+    fn only_products_found_in_the_names_are_relevant() {
+        let glossary = glossary(Locale::Ru, &[]);
+
+        // Порядок первого появления, без повторов; из вложенных совпадений — самое длинное
+        assert_eq!(
+            preferred(&glossary, &["Цветная капуста свежая", "Молоко 3,2%", "Шоколадка", "молоко ультрапастеризованное"]),
+            ["цветная капуста", "молоко"]
+        );
+        // Несколько слов подряд внутри названия и другие названия продукта
+        assert_eq!(preferred(&glossary, &["Филе куриной грудки охлажденное", "Sweet potato"]), ["куриная грудка", "картофель"]);
+        assert!(preferred(&glossary, &["Соус песто", ""]).is_empty());
+        assert!(glossary.prompt_block(["Соус песто"]).is_none());
+    }
+
+    #[test]
+    fn prompt_block_is_limited_to_relevant_terms() {
+        let glossary = glossary(Locale::Ru, &[]);
+        let block = glossary.prompt_block(["Творог 5%", "Помидоры черри"]).unwrap();
+
+        assert!(block.contains("- творог (а не «cottage cheese», «twaróg», «tvorog»)\n"), "{}", block);
+        assert!(block.contains("- помидоры черри (а не «cherry tomatoes», «черри», «томаты черри»)\n"), "{}", block);
+        assert!(!block.contains("- помидоры ("), "{}", block);
+        assert!(block.contains("<<<USER_DATA glossary"));
+
+        // Не больше MAX_PROMPT_TERMS продуктов, даже если в холодильнике встретились все
+        let all: Vec<&str> = TERMS.iter().map(|(ru, _, _)| *ru).collect();
+        let block = glossary.prompt_block(all).unwrap();
+        assert_eq!(block.lines().filter(|line| line.starts_with("- ")).count(), MAX_PROMPT_TERMS);
+    }
+
+    #[test]
+    fn names_are_normalized_only_on_a_whole_name_match() {
+        let glossary = glossary(Locale::Ru, &[]);
+
+        assert_eq!(glossary.normalize_name("chicken breasts"), "куриная грудка");
+        assert_eq!(glossary.normalize_name("Chicken Fillet"), "Куриная грудка");
+        assert_eq!(glossary.normalize_name("  ЁЖИК  "), "  ЁЖИК  ");
+        assert_eq!(glossary.normalize_name("Śmietana"), "Сметана");
+        assert_eq!(glossary.normalize_name("молоко овсяное"), "молоко овсяное");
+
+        let english = self::glossary(Locale::En, &[]);
+        assert_eq!(english.normalize_name("Творог"), "Cottage cheese");
+        assert_eq!(english.normalize_name("гречневая крупа"), "buckwheat");
+    }
+
+    #[test]
+    fn user_overrides_change_the_preferred_name() {
+        let glossary = glossary(Locale::Ru, &[("кабачок", "цукини"), ("боул", "поке")]);
+
+        // Известный продукт: все его названия теперь приводятся к замене пользователя
+        for name in ["кабачок", "кабачки", "zucchini", "courgette"] {
+            assert_eq!(glossary.normalize_name(name), "цукини", "{}", name);
+        }
+        let block = glossary.prompt_block(["Кабачок молодой"]).unwrap();
+        assert!(block.contains("- цукини (а не «кабачок», «zucchini», «кабачки»)\n"), "{}", block);
+
+        // Новый продукт добавляется в глоссарий
+        assert_eq!(glossary.normalize_name("Боул"), "Поке");
+        assert_eq!(preferred(&glossary, &["боул, тунец"]), ["поке", "тунец"]);
+    }
+
+    #[test]
+    fn generated_recipes_use_the_users_names() {
+        let mut recipe: GeneratedRecipe = serde_json::from_value(serde_json::json!({
+            "name": "Омлет",
+            "description": "",
+            "ingredients": [
+                { "name": "Eggs", "amount": "3", "unit": "pcs", "available_in_fridge": true },
+                { "name": "коровье молоко", "amount": "50", "unit": "ml", "available_in_fridge": true },
+                { "name": "соус песто", "amount": "1", "unit": "tbsp", "available_in_fridge": false }
+            ],
+            "instructions": [],
+            "cook_time": "10 min",
+            "servings": 1,
+            "difficulty": "easy",
+            "available_ingredients": ["eggs", "коровье молоко"],
+            "missing_ingredients": ["Spring onions"]
+        }))
+        .unwrap();
+
+        recipe.normalize_terms(&glossary(Locale::Ru, &[]));
+
+        let names: Vec<&str> = recipe.ingredients.iter().map(|ingredient| ingredient.name.as_str()).collect();
+        assert_eq!(names, ["Яйца куриные", "молоко", "соус песто"]);
+        assert_eq!(recipe.available_ingredients, ["яйца куриные", "молоко"]);
+        assert_eq!(recipe.missing_ingredients, ["Зеленый лук"]);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+use crate::utils::format::Locale;
+
+/// Русский каталог — основной: ключ, которого нет в другом языке, берется отсюда.
+/// Подстановки — `{name}`, см. `tf`.
+const RU: &[(&str, &str)] = &[
+    ("category.dairy", "Молочные продукты"),
+    ("category.meat", "Мясо"),
+    ("category.fish", "Рыба"),
+    ("category.vegetables", "Овощи"),
+    ("category.fruits", "Фрукты"),
+    ("category.grains", "Крупы"),
+    ("category.beverages", "Напитки"),
+    ("category.condiments", "Соусы и специи"),
+    ("category.snacks", "Снеки"),
+    ("category.other", "Другое"),
+    ("fridge.tip.buy_less", "Попробуйте покупать меньше продуктов за раз"),
+    ("fridge.tip.category_storage", "Обратите внимание на хранение продуктов категории «{category}»"),
+    ("fridge.tip.great_job", "Отличная работа! Вы эффективно используете продукты"),
+    ("fridge.tip.check_expiry", "Проверяйте сроки годности при покупке"),
+    ("fridge.tip.plan_menu", "Планируйте меню заранее"),
+    ("realtime.welcome.title", "Добро пожаловать!"),
+    ("realtime.welcome.message", "Вы подключились к real-time уведомлениям IT Cook"),
+    ("realtime.resync.title", "Обновите данные"),
+    ("realtime.resync.message", "Соединение не успевало и пропустило событий: {count}. Обновите ленту и данные — уведомления сохранены во входящих."),
+    ("goal.expired.title", "Срок цели истек"),
+    ("goal.expired.message", "Срок цели «{title}» истек {date}. Достигнуто {percent}."),
+    ("goal.deadline.title", "Срок цели приближается"),
+    ("goal.deadline.on_track", "До срока цели «{title}» осталось {days}. Прогресс: {percent}."),
+    ("goal.deadline.behind", "До срока цели «{title}» осталось {days}. Прогресс: {percent} — нужно ускориться."),
+    ("goal.suggestion.calories.title", "{target} ккал в день"),
+    ("goal.suggestion.calories.lose", "Ваш расход энергии — около {tdee} ккал в день при весе {weight} кг. Для снижения веса норма на 500 ккал меньше: {target} ккал."),
+    ("goal.suggestion.calories.gain", "Ваш расход энергии — около {tdee} ккал в день при весе {weight} кг. Для набора веса норма на 300 ккал больше: {target} ккал."),
+    ("goal.suggestion.calories.maintain", "Ваш расход энергии — около {tdee} ккал в день при весе {weight} кг. Чтобы удерживать вес, норма — {target} ккал."),
+    ("goal.suggestion.calories.average", "За последнюю неделю вы в среднем съедали {average} ккал в день."),
+    ("goal.suggestion.protein.title", "{target} г белка в день"),
+    ("goal.suggestion.protein", "{per_kg} г белка на кг веса при весе {weight} кг — {target} г в день."),
+    ("goal.suggestion.water.title", "{target} мл воды в день"),
+    ("goal.suggestion.water", "{per_kg} мл воды на кг веса при весе {weight} кг — {target} мл в день."),
+    ("goal.suggestion.waste.title", "Выбрасывать не больше {target} продуктов"),
+    ("goal.suggestion.waste", "За последние 30 дней выброшено {percent} продуктов. Попробуйте снизить до {target}: покупайте меньше за раз и следите за сроками."),
+    ("timeline.wellbeing_checkin", "Отметка самочувствия"),
+    ("diary.reminder.title", "Сегодня в дневнике пусто"),
+    ("diary.reminder.message", "Вы еще ничего не записали за сегодня. Добавьте хотя бы один прием пищи — это займет минуту."),
+    ("diary.reminder.message_streak", "Вы еще ничего не записали за сегодня. Серия — {days} подряд, не прервите ее!"),
+    ("health.mood.low", "Я вижу, что у вас непростой период. Помните - это временно, и я здесь, чтобы поддержать вас. Что могло бы сейчас помочь вам почувствовать себя чуть лучше?"),
+    ("health.mood.neutral", "Как ваше настроение сегодня? Если хотите, расскажите, что на душе - иногда просто поговорить уже помогает."),
+    ("health.mood.good", "Рада видеть, что у вас хорошее настроение! Что помогло вам так прекрасно себя чувствовать?"),
+    ("health.mood.great", "Вы просто сияете сегодня! Это заразительно - делитесь своей радостью с миром! 🌟"),
+    ("health.mood.unknown", "Как дела? Как настроение? Я всегда готова выслушать и помочь."),
+    ("health.encouragement.small_steps", "Помните: каждый маленький шаг к здоровью важен. Вы делаете больше, чем думаете! 💪"),
+    ("health.encouragement.self_care", "Ваша забота о себе вдохновляет. Продолжайте в том же духе! ✨"),
+    ("health.encouragement.marathon", "Здоровье - это марафон, не спринт. Будьте терпеливы к себе. 🌱"),
+    ("health.encouragement.new_day", "Каждый день - новая возможность позаботиться о себе. Вы справляетесь отлично! 🌟"),
+    ("health.dashboard.motivation", "Вы заботитесь о своем здоровье уже 7 дней подряд! Это отличная привычка. 🌟"),
+];
+
+const EN: &[(&str, &str)] = &[
+    ("category.dairy", "Dairy"),
+    ("category.meat", "Meat"),
+    ("category.fish", "Fish"),
+    ("category.vegetables", "Vegetables"),
+    ("category.fruits", "Fruits"),
+    ("category.grains", "Grains"),
+    ("category.beverages", "Beverages"),
+    ("category.condiments", "Sauces and spices"),
+    ("category.snacks", "Snacks"),
+    ("category.other", "Other"),
+    ("fridge.tip.buy_less", "Try buying fewer groceries at a time"),
+    ("fridge.tip.category_storage", "Pay attention to how you store {category}"),
+    ("fridge.tip.great_job", "Great job! You are using your groceries efficiently"),
+    ("fridge.tip.check_expiry", "Check expiry dates when shopping"),
+    ("fridge.tip.plan_menu", "Plan your meals ahead"),
+    ("realtime.welcome.title", "Welcome!"),
+    ("realtime.welcome.message", "You are connected to IT Cook real-time notifications"),
+    ("realtime.resync.title", "Refresh your data"),
+    ("realtime.resync.message", "The connection fell behind and missed {count} events. Refresh your feed and data — notifications are kept in your inbox."),
+    ("goal.expired.title", "Goal deadline passed"),
+    ("goal.expired.message", "The deadline for “{title}” passed on {date}. Reached {percent}."),
+    ("goal.deadline.title", "Goal deadline is approaching"),
+    ("goal.deadline.on_track", "{days} left until the deadline for “{title}”. Progress: {percent}."),
+    ("goal.deadline.behind", "{days} left until the deadline for “{title}”. Progress: {percent} — time to speed up."),
+    ("goal.suggestion.calories.title", "{target} kcal a day"),
+    ("goal.suggestion.calories.lose", "You burn about {tdee} kcal a day at {weight} kg. To lose weight, eat 500 kcal less: {target} kcal."),
+    ("goal.suggestion.calories.gain", "You burn about {tdee} kcal a day at {weight} kg. To gain weight, eat 300 kcal more: {target} kcal."),
+    ("goal.suggestion.calories.maintain", "You burn about {tdee} kcal a day at {weight} kg. To keep your weight, aim for {target} kcal."),
+    ("goal.suggestion.calories.average", "Over the last week you ate {average} kcal a day on average."),
+    ("goal.suggestion.protein.title", "{target} g of protein a day"),
+    ("goal.suggestion.protein", "{per_kg} g of protein per kg of body weight at {weight} kg is {target} g a day."),
+    ("goal.suggestion.water.title", "{target} ml of water a day"),
+    ("goal.suggestion.water", "{per_kg} ml of water per kg of body weight at {weight} kg is {target} ml a day."),
+    ("goal.suggestion.waste.title", "Waste no more than {target} of groceries"),
+    ("goal.suggestion.waste", "You threw away {percent} of your groceries over the last 30 days. Try to get it down to {target}: buy less at a time and keep an eye on expiry dates."),
+    ("timeline.wellbeing_checkin", "Wellbeing check-in"),
+    ("diary.reminder.title", "Your diary is empty today"),
+    ("diary.reminder.message", "You haven't logged anything today. Add at least one meal — it only takes a minute."),
+    ("diary.reminder.message_streak", "You haven't logged anything today. Your streak is {days} in a row — don't break it!"),
+    ("health.mood.low", "I can see you're going through a hard time. Remember, it's temporary, and I'm here to support you. What could help you feel a little better right now?"),
+    ("health.mood.neutral", "How is your mood today? If you like, tell me what's on your mind - sometimes just talking helps."),
+    ("health.mood.good", "Glad to see you're in a good mood! What helped you feel this great?"),
+    ("health.mood.great", "You're simply glowing today! It's contagious - share your joy with the world! 🌟"),
+    ("health.mood.unknown", "How are you? How is your mood? I'm always here to listen and help."),
+    ("health.encouragement.small_steps", "Remember: every small step towards health matters. You're doing more than you think! 💪"),
+    ("health.encouragement.self_care", "Your self-care is inspiring. Keep it up! ✨"),
+    ("health.encouragement.marathon", "Health is a marathon, not a sprint. Be patient with yourself. 🌱"),
+    ("health.encouragement.new_day", "Every day is a new chance to take care of yourself. You're doing great! 🌟"),
+    ("health.dashboard.motivation", "You've been taking care of your health for 7 days in a row! That's a great habit. 🌟"),
+];
+
+static RU_CATALOG: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| RU.iter().copied().collect());
+static EN_CATALOG: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| translation(Locale::En, EN));
+
+/// Каталог перевода; расхождение ключей с русским каталогом — в лог один раз при первом обращении
+fn translation(locale: Locale, entries: &'static [(&'static str, &'static str)]) -> HashMap<&'static str, &'static str> {
+    let catalog: HashMap<_, _> = entries.iter().copied().collect();
+    let missing: Vec<&str> = RU.iter().map(|(key, _)| *key).filter(|key| !catalog.contains_key(key)).collect();
+    let extra: Vec<&str> = entries.iter().map(|(key, _)| *key).filter(|key| !RU_CATALOG.contains_key(key)).collect();
+    if !missing.is_empty() || !extra.is_empty() {
+        warn!("{:?} i18n catalog differs from ru: missing {:?}, unknown {:?}", locale, missing, extra);
+    }
+    catalog
+}
+
+fn catalog(locale: Locale) -> &'static HashMap<&'static str, &'static str> {
+    match locale {
+        Locale::Ru => &RU_CATALOG,
+        Locale::En => &EN_CATALOG,
+    }
+}
+
+/// Строка по ключу. Нет перевода — русская строка, нет и ее — сам ключ; оба случая в логе.
+pub fn t(locale: Locale, key: &'static str) -> &'static str {
+    if let Some(text) = catalog(locale).get(key).copied() {
+        return text;
+    }
+    if let Some(text) = catalog(Locale::Ru).get(key).copied() {
+        warn!("Missing {:?} translation for '{}', falling back to ru", locale, key);
+        return text;
+    }
+    warn!("Unknown i18n key '{}'", key);
+    key
+}
+
+/// Строка с подстановками: `tf(locale, "fridge.tip.category_storage", &[("category", name)])`
+pub fn tf(locale: Locale, key: &'static str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(t(locale, key).to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// Язык из заголовка Accept-Language: первый поддерживаемый по убыванию веса `q`
+pub fn locale_from_accept_language(header: &str) -> Option<Locale> {
+    let mut ranges: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let weight = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            (weight > 0.0).then_some((tag, weight))
+        })
+        .collect();
+    // Сортировка устойчивая: при равном весе решает порядок в заголовке
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges.into_iter().find_map(|(tag, _)| Locale::from_tag(tag))
+}
+
+/// Язык пользователя: из настроек, иначе из Accept-Language, иначе русский
+pub fn resolve_locale(preference: Option<&str>, accept_language: Option<&str>) -> Locale {
+    preference
+        .and_then(Locale::from_tag)
+        .or_else(|| accept_language.and_then(locale_from_accept_language))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    /// Исходный список каталога; новый язык без каталога здесь не скомпилируется
+    fn entries(locale: Locale) -> &'static [(&'static str, &'static str)] {
+        match locale {
+            Locale::Ru => RU,
+            Locale::En => EN,
+        }
+    }
+
+    const LOCALES: [Locale; 2] = [Locale::Ru, Locale::En];
+
+    /// Имена подстановок `{name}` в строке
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{').skip(1).filter_map(|rest| rest.split_once('}').map(|(name, _)| name)).collect()
+    }
+
+    #[test]
+    fn every_catalog_has_exactly_the_russian_keys() {
+        let ru_keys: BTreeSet<&str> = RU.iter().map(|(key, _)| *key).collect();
+
+        for locale in LOCALES {
+            let keys: BTreeSet<&str> = entries(locale).iter().map(|(key, _)| *key).collect();
+            assert_eq!(keys.len(), entries(locale).len(), "{:?} catalog has duplicate keys", locale);
+            assert_eq!(
+                ru_keys.difference(&keys).collect::<Vec<_>>(),
+                Vec::<&&str>::new(),
+                "{:?} catalog is missing keys",
+                locale
+            );
+            assert_eq!(
+                keys.difference(&ru_keys).collect::<Vec<_>>(),
+                Vec::<&&str>::new(),
+                "{:?} catalog has keys unknown to ru",
+                locale
+            );
+        }
+    }
+
+    #[test]
+    fn translations_keep_the_placeholders_and_are_not_empty() {
+        for locale in LOCALES {
+            for (key, text) in entries(locale) {
+                assert!(!text.trim().is_empty(), "{:?} '{}' is empty", locale, key);
+                assert_eq!(placeholders(text), placeholders(RU_CATALOG[key]), "{:?} '{}' placeholders", locale, key);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_key_falls_back_to_the_key_itself() {
+        assert_eq!(t(Locale::En, "no.such.key"), "no.such.key");
+        assert_eq!(
+            tf(Locale::En, "fridge.tip.category_storage", &[("category", "Dairy")]),
+            EN_CATALOG["fridge.tip.category_storage"].replace("{category}", "Dairy")
+        );
+    }
+
+    #[test]
+    fn locale_comes_from_preference_then_header_then_default() {
+        assert_eq!(resolve_locale(Some("en"), Some("ru")), Locale::En);
+        assert_eq!(resolve_locale(Some("de"), Some("fr, en-GB;q=0.8, ru;q=0.5")), Locale::En);
+        assert_eq!(resolve_locale(None, Some("en;q=0.2, ru;q=0.9")), Locale::Ru);
+        assert_eq!(resolve_locale(None, Some("de")), Locale::Ru);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+/// Длина "основы" слова: отбрасывает падежные окончания (курица/куриная → курин/куриц)
+const STEM_CHARS: usize = 5;
+
+/// Нормализация названия: нижний регистр, ё → е, латиница без диакритики (é → e),
+/// без HTML-сущностей и пунктуации, одиночные пробелы
+pub fn normalize_ingredient(name: &str) -> String {
+    let decoded = name
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'");
+
+    decoded
+        .to_lowercase()
+        .chars()
+        .map(fold_diacritic)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'ё' => 'е',
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        c => c,
+    }
+}
+
+fn stems(normalized: &str) -> HashSet<String> {
+    normalized
+        .split(' ')
+        .filter(|word| word.chars().count() >= 3)
+        .map(|word| word.chars().take(STEM_CHARS).collect())
+        .collect()
+}
+
+/// Индекс продуктов пользователя для сопоставления с ингредиентами рецептов.
+/// Строится один раз на запрос, дальше сопоставление идет только в памяти.
+pub struct IngredientIndex {
+    exact: HashSet<String>,
+    item_stems: Vec<HashSet<String>>,
+    exact_only: bool,
+}
+
+impl IngredientIndex {
+    /// `exact_only` — деградация до точного совпадения названий (для очень больших холодильников)
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>, exact_only: bool) -> Self {
+        let normalized: Vec<String> = names
+            .into_iter()
+            .map(normalize_ingredient)
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        let item_stems = if exact_only {
+            Vec::new()
+        } else {
+            normalized.iter().map(|name| stems(name)).filter(|set| !set.is_empty()).collect()
+        };
+
+        Self {
+            exact: normalized.into_iter().collect(),
+            item_stems,
+            exact_only,
+        }
+    }
+
+    /// Есть ли ингредиент среди продуктов: точное совпадение или одно название уточняет другое
+    /// ("Рис" ~ "Рис белый", "Куриная грудка" ~ "Куриная грудка охлажденная")
+    pub fn contains(&self, ingredient: &str) -> bool {
+        let normalized = normalize_ingredient(ingredient);
+        if normalized.is_empty() {
+            return false;
+        }
+        if self.exact.contains(&normalized) {
+            return true;
+        }
+        if self.exact_only {
+            return false;
+        }
+
+        let ingredient_stems = stems(&normalized);
+        if ingredient_stems.is_empty() {
+            return false;
+        }
+
+        self.item_stems
+            .iter()
+            .any(|item| ingredient_stems.is_subset(item) || item.is_subset(&ingredient_stems))
+    }
+}
@@ -1 +1,12 @@
 pub mod errors;
+pub mod sanitize;
+pub mod ingredient_matcher;
+pub mod format;
+pub mod units;
+pub mod etag;
+pub mod rate_limit;
+pub mod food_category;
+pub mod device;
+pub mod receipt;
+pub mod i18n;
+pub mod glossary;
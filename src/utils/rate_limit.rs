@@ -0,0 +1,107 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::http::HeaderMap;
+use dashmap::DashMap;
+
+/// Сколько IP хранится, прежде чем удаляются истекшие окна
+const MAX_TRACKED_CLIENTS: usize = 50_000;
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Ограничение частоты запросов по IP с фиксированным окном. Счетчики в памяти
+/// процесса — для защиты публичных маршрутов от перебора этого достаточно.
+/// Ключом может быть и не IP: виджет рецепта ограничивается по публичному токену.
+#[derive(Clone)]
+pub struct IpRateLimiter {
+    windows: Arc<DashMap<String, Window>>,
+    max_requests: u32,
+    window: Duration,
+}
+
+impl IpRateLimiter {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            windows: Arc::new(DashMap::new()),
+            max_requests,
+            window,
+        }
+    }
+
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    /// Учитывает запрос; `false` — лимит в текущем окне исчерпан
+    pub fn check(&self, ip: &str) -> bool {
+        let now = Instant::now();
+
+        if self.windows.len() > MAX_TRACKED_CLIENTS {
+            self.windows.retain(|_, window| now.duration_since(window.started) < self.window);
+        }
+
+        let mut window = self.windows
+            .entry(ip.to_string())
+            .or_insert(Window { started: now, count: 0 });
+
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.count = 0;
+        }
+
+        if window.count >= self.max_requests {
+            return false;
+        }
+
+        window.count += 1;
+        true
+    }
+}
+
+/// IP клиента из заголовков прокси (первый адрес X-Forwarded-For или X-Real-IP)
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn limit_is_counted_per_ip_and_resets_with_the_window() {
+        let limiter = IpRateLimiter::new(2, Duration::from_millis(50));
+        assert!(limiter.check("10.0.0.1"));
+        assert!(limiter.check("10.0.0.1"));
+        assert!(!limiter.check("10.0.0.1"));
+        // Другой адрес считается отдельно
+        assert!(limiter.check("10.0.0.2"));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check("10.0.0.1"));
+    }
+
+    #[test]
+    fn client_ip_prefers_the_first_forwarded_address() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers), None);
+
+        headers.insert("x-real-ip", HeaderValue::from_static("192.0.2.7"));
+        assert_eq!(client_ip(&headers).as_deref(), Some("192.0.2.7"));
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static(" 203.0.113.5 , 10.0.0.1"));
+        assert_eq!(client_ip(&headers).as_deref(), Some("203.0.113.5"));
+    }
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{fridge::FridgeCategory, presets::FoodPresets},
+    utils::{
+        food_category::suggest_category,
+        format::{round_money, round_to},
+        ingredient_matcher::normalize_ingredient,
+        units::{to_base, BaseUnit},
+    },
+};
+
+/// Расхождение суммы строк с итогом чека до рубля — округление на кассе
+pub const RECONCILIATION_TOLERANCE: f64 = 1.0;
+
+/// Слова чека, которые не относятся к названию: пометки весового товара, тары, упаковки
+const RECEIPT_NOISE_WORDS: &[&str] = &[
+    "вес", "весов", "весовой", "фас", "уп", "упак", "пэт", "пак", "тп", "ст", "шт", "кг", "г", "гр", "л", "мл",
+];
+
+/// Начала первого слова строки со скидкой
+const DISCOUNT_STEMS: &[&str] = &["скидк", "discount"];
+
+/// Первые слова строки с итогом чека
+const TOTAL_WORDS: &[&str] = &["итог", "итого", "всего", "сумма", "total"];
+
+/// Строка чека, как ее прочитал провайдер: текст и, если есть, количество и сумма строки
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawReceiptLine {
+    pub text: String,
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub price: Option<f64>,
+}
+
+/// Ответ провайдера по фотографии чека
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RawReceipt {
+    #[serde(default)]
+    pub lines: Vec<RawReceiptLine>,
+    #[serde(default)]
+    pub total: Option<f64>,
+}
+
+/// Ответ ИИ вида `{"lines": [...], "total": ...}`; ответ без JSON — `None`
+pub fn parse_receipt_reply(text: &str) -> Option<RawReceipt> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+/// Почему строку стоит проверить перед добавлением
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptLineIssue {
+    /// Продукт не найден среди предустановленных, категория — «Другое»
+    NameUnrecognized,
+    /// В строке нет суммы
+    PriceMissing,
+    /// Ни в строке, ни в названии нет количества — принята 1 шт
+    QuantityAssumed,
+}
+
+/// Строка чека, приведенная к продукту холодильника
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptItem {
+    pub raw_text: String,
+    pub name: String,
+    pub category: FridgeCategory,
+    /// В базовых единицах: кг, л или шт
+    pub quantity: f64,
+    pub unit: &'static str,
+    /// Сумма строки
+    pub total_price: Option<f64>,
+    pub price_per_unit: Option<f64>,
+    pub issues: Vec<ReceiptLineIssue>,
+}
+
+impl ReceiptItem {
+    pub fn is_confident(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Сверка суммы распознанных строк с итогом чека
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptReconciliation {
+    /// Сумма строк продуктов за вычетом скидок
+    pub lines_total: f64,
+    /// Скидки (отрицательное число или 0)
+    pub discounts: f64,
+    /// Итог, напечатанный на чеке, если его удалось найти
+    pub receipt_total: Option<f64>,
+    /// Итог чека минус сумма строк: положительная — часть строк не прочитана или без цены
+    pub difference: Option<f64>,
+    pub lines_without_price: usize,
+    /// Итог найден, все строки с ценой и расхождение в пределах `RECONCILIATION_TOLERANCE`
+    pub reconciled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizedReceipt {
+    pub items: Vec<ReceiptItem>,
+    pub reconciliation: ReceiptReconciliation,
+}
+
+/// Чем оказалась строка чека
+enum LineKind {
+    Item(ReceiptItem),
+    Discount(f64),
+    Total(f64),
+    Skipped,
+}
+
+/// Приводит строки чека к продуктам и сверяет их сумму с итогом. Детерминирована:
+/// одинаковый ответ провайдера — одинаковый результат.
+pub fn normalize_receipt(receipt: &RawReceipt) -> NormalizedReceipt {
+    let mut items = Vec::new();
+    let mut discounts = 0.0;
+    let mut total_line = None;
+
+    for line in &receipt.lines {
+        match classify_line(line) {
+            LineKind::Item(item) => items.push(item),
+            LineKind::Discount(amount) => discounts += amount,
+            LineKind::Total(amount) => total_line = Some(amount),
+            LineKind::Skipped => {}
+        }
+    }
+
+    let items_total: f64 = items.iter().filter_map(|item| item.total_price).sum();
+    let lines_total = round_money(items_total + discounts);
+    let lines_without_price = items.iter().filter(|item| item.total_price.is_none()).count();
+    let receipt_total = receipt
+        .total
+        .filter(|total| total.is_finite() && *total > 0.0)
+        .or(total_line)
+        .map(round_money);
+    let difference = receipt_total.map(|total| round_money(total - lines_total));
+
+    NormalizedReceipt {
+        reconciliation: ReceiptReconciliation {
+            lines_total,
+            discounts: round_money(discounts),
+            receipt_total,
+            difference,
+            lines_without_price,
+            reconciled: lines_without_price == 0
+                && difference.is_some_and(|difference| difference.abs() <= RECONCILIATION_TOLERANCE),
+        },
+        items,
+    }
+}
+
+fn classify_line(line: &RawReceiptLine) -> LineKind {
+    let price = line.price.filter(|price| price.is_finite());
+    let normalized = normalize_ingredient(&line.text);
+    let first_word = normalized.split(' ').next().unwrap_or_default();
+
+    if TOTAL_WORDS.contains(&first_word) {
+        return match price.filter(|price| *price > 0.0) {
+            Some(total) => LineKind::Total(total),
+            None => LineKind::Skipped,
+        };
+    }
+    if DISCOUNT_STEMS.iter().any(|stem| first_word.starts_with(stem)) || price.is_some_and(|price| price < 0.0) {
+        return LineKind::Discount(-price.unwrap_or(0.0).abs());
+    }
+
+    match normalize_line(&line.text, line.quantity, line.unit.as_deref(), price) {
+        Some(item) => LineKind::Item(item),
+        None => LineKind::Skipped,
+    }
+}
+
+/// Одна строка чека: название без кодов, процентов и фасовки; количество из строки
+/// или из фасовки в названии ("930МЛ", "2X450Г"); цена за базовую единицу.
+/// Строка без единого слова (только цифры) — `None`.
+pub fn normalize_line(text: &str, quantity: Option<f64>, unit: Option<&str>, price: Option<f64>) -> Option<ReceiptItem> {
+    let (words, pack) = split_name(text);
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut issues = Vec::new();
+    let (name, category) = match resolve_name(&words) {
+        Some(resolved) => resolved,
+        None => {
+            issues.push(ReceiptLineIssue::NameUnrecognized);
+            (capitalize(&words.join(" ")), FridgeCategory::Other)
+        }
+    };
+
+    // Весовой товар — количество из строки; штучный — штуки × фасовка из названия
+    let counted = quantity
+        .filter(|quantity| quantity.is_finite() && *quantity > 0.0)
+        .map(|quantity| to_base(quantity, unit.unwrap_or("шт")).unwrap_or((quantity, BaseUnit::Piece)));
+    let (quantity, base) = match (counted, pack) {
+        (Some((quantity, base)), _) if base != BaseUnit::Piece => (quantity, base),
+        (Some((count, _)), Some((size, base))) => (count * size, base),
+        (Some(counted), None) => counted,
+        (None, Some(pack)) => pack,
+        (None, None) => {
+            issues.push(ReceiptLineIssue::QuantityAssumed);
+            (1.0, BaseUnit::Piece)
+        }
+    };
+    let quantity = round_to(quantity, 3);
+
+    let total_price = price.filter(|price| *price >= 0.0).map(round_money);
+    if total_price.is_none() {
+        issues.push(ReceiptLineIssue::PriceMissing);
+    }
+
+    Some(ReceiptItem {
+        raw_text: text.trim().to_string(),
+        name,
+        category,
+        quantity,
+        unit: base_unit_name(base),
+        total_price,
+        price_per_unit: total_price.filter(|_| quantity > 0.0).map(|total| round_money(total / quantity)),
+        issues,
+    })
+}
+
+fn base_unit_name(base: BaseUnit) -> &'static str {
+    match base {
+        BaseUnit::Kg => "кг",
+        BaseUnit::L => "л",
+        BaseUnit::Piece => "шт",
+    }
+}
+
+/// Слова названия и фасовка в базовых единицах. Коды товаров, проценты жирности
+/// и пометки вроде "ВЕС" отбрасываются.
+fn split_name(text: &str) -> (Vec<String>, Option<(f64, BaseUnit)>) {
+    let lowered = text.to_lowercase().replace('ё', "е");
+    let tokens: Vec<&str> = lowered
+        .split_whitespace()
+        // "КУР.ГРУДКИ" — сокращение слито со следующим словом; в числах точка остается
+        .flat_map(|token| match token.chars().any(|c| c.is_ascii_digit()) {
+            true => vec![token],
+            false => token.split('.').collect(),
+        })
+        .map(|token| token.trim_matches(|c: char| matches!(c, ',' | ';' | ':' | '"' | '\'' | '(' | ')')))
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    let mut words = Vec::new();
+    let mut pack = None;
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = tokens[index];
+        index += 1;
+
+        if let Some(size) = parse_pack(token) {
+            pack = Some(size);
+            continue;
+        }
+        // "930 МЛ" — число и единица раздельно
+        if let (Some(number), Some(unit)) = (parse_number(token), tokens.get(index)) {
+            if let Some(size) = to_base(number, unit) {
+                pack = Some(size);
+                index += 1;
+                continue;
+            }
+        }
+        if token.chars().any(|c| c.is_ascii_digit() || c == '%') {
+            continue;
+        }
+
+        let word: String = token.chars().filter(|c| c.is_alphabetic() || *c == '-').collect();
+        let word = word.trim_matches('-');
+        if word.chars().count() >= 2 && !RECEIPT_NOISE_WORDS.contains(&word) {
+            words.push(word.to_string());
+        }
+    }
+
+    (words, pack)
+}
+
+/// Фасовка из одного токена: "930мл", "0,5л", "10шт", "2x450г" (упаковка из двух по 450 г)
+fn parse_pack(token: &str) -> Option<(f64, BaseUnit)> {
+    if let Some((count, size)) = token.split_once(['x', 'х', '*']) {
+        let count = parse_number(count).filter(|count| count.fract() == 0.0)?;
+        let (quantity, base) = parse_pack(size)?;
+        return Some((count * quantity, base));
+    }
+
+    let split = token.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))?;
+    let number = parse_number(&token[..split])?;
+    to_base(number, &token[split..])
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    token
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite() && *number > 0.0)
+}
+
+/// Предустановленный продукт, все слова которого есть в строке (в том числе сокращенными:
+/// "сыр тв" — "Сыр твердый"), причем хотя бы одно — целиком: иначе "ВОДА МИН." стала бы
+/// "Миндалем". Если такого нет — категория по названию. `None` — продукт не узнан.
+fn resolve_name(words: &[String]) -> Option<(String, FridgeCategory)> {
+    let exact = |preset_word: &str| words.iter().any(|word| word == preset_word);
+    let covers = |preset_word: &str| {
+        exact(preset_word) || words.iter().any(|word| word.chars().count() >= 2 && preset_word.starts_with(word.as_str()))
+    };
+    let preset = FoodPresets::get_product_presets()
+        .into_iter()
+        .filter(|preset| {
+            let name = normalize_ingredient(&preset.name);
+            name.split(' ').all(covers) && name.split(' ').any(exact)
+        })
+        .max_by_key(|preset| preset.name.split_whitespace().count());
+    if let Some(preset) = preset {
+        return Some((preset.name, preset.category));
+    }
+
+    let name = words.join(" ");
+    suggest_category(&name).map(|(category, _)| (capitalize(&name), category))
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    /// Строки настоящих чеков: сокращения, проценты, коды товаров, фасовка слитно и раздельно
+    const FIXTURES_JSON: &str = include_str!("../../fixtures/receipt_lines.json");
+
+    #[derive(Deserialize)]
+    struct Fixtures {
+        lines: Vec<LineFixture>,
+        receipts: Vec<ReceiptFixture>,
+    }
+
+    #[derive(Deserialize)]
+    struct LineFixture {
+        #[serde(flatten)]
+        line: RawReceiptLine,
+        /// Проверяемые поля `ReceiptItem`; `null` — строка не продукт
+        expected: Value,
+    }
+
+    #[derive(Deserialize)]
+    struct ReceiptFixture {
+        #[serde(flatten)]
+        receipt: RawReceipt,
+        expected_names: Vec<String>,
+        expected_reconciliation: Value,
+    }
+
+    fn fixtures() -> Fixtures {
+        serde_json::from_str(FIXTURES_JSON).unwrap()
+    }
+
+    /// Поля `expected` совпадают с полями `actual`; остальные поля `actual` не проверяются
+    fn assert_fields(actual: &Value, expected: &Value, context: &str) {
+        for (field, value) in expected.as_object().unwrap() {
+            assert_eq!(&actual[field], value, "{}: {}", context, field);
+        }
+    }
+
+    #[test]
+    fn messy_receipt_lines_are_normalized() {
+        for fixture in fixtures().lines {
+            let RawReceiptLine { text, quantity, unit, price } = &fixture.line;
+            let item = normalize_line(text, *quantity, unit.as_deref(), *price);
+
+            match (&item, fixture.expected.is_null()) {
+                (None, true) => {}
+                (Some(item), false) => assert_fields(&serde_json::to_value(item).unwrap(), &fixture.expected, text),
+                _ => panic!("{}: expected {}, got {:?}", text, fixture.expected, item),
+            }
+        }
+    }
+
+    #[test]
+    fn abbreviations_do_not_match_unrelated_presets() {
+        let water = normalize_line("ВОДА МИН. 2X1,5Л", Some(2.0), Some("шт"), Some(79.8)).unwrap();
+        assert_ne!(water.name, "Миндаль");
+
+        let cheese = normalize_line("СЫР ТВ.", None, None, None).unwrap();
+        assert_eq!(cheese.name, "Сыр твердый");
+    }
+
+    #[test]
+    fn fixture_receipts_reconcile_with_their_totals() {
+        for (index, fixture) in fixtures().receipts.into_iter().enumerate() {
+            let normalized = normalize_receipt(&fixture.receipt);
+
+            let names: Vec<&str> = normalized.items.iter().map(|item| item.name.as_str()).collect();
+            assert_eq!(names, fixture.expected_names, "receipt {}", index);
+            assert_fields(
+                &serde_json::to_value(&normalized.reconciliation).unwrap(),
+                &fixture.expected_reconciliation,
+                &format!("receipt {}", index),
+            );
+        }
+    }
+
+    #[test]
+    fn normalization_is_deterministic() {
+        let receipt = &fixtures().receipts[0].receipt;
+        let first = serde_json::to_value(normalize_receipt(receipt)).unwrap();
+        let second = serde_json::to_value(normalize_receipt(receipt)).unwrap();
+        assert_eq!(first, second);
+    }
+}
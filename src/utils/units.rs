@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+/// Базовая единица, к которой приводятся цены для сравнения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BaseUnit {
+    Kg,
+    L,
+    Piece,
+}
+
+/// Единицы, которые перечисляются в подсказке при неизвестной единице ингредиента.
+/// Полный список написаний — в `base_factor`.
+pub const ACCEPTED_UNITS: &[&str] = &["г", "кг", "мл", "л", "шт", "g", "kg", "ml", "l", "pcs"];
+
+/// Сколько базовых единиц в одной единице `unit`: "г" → (0.001, кг), "мл" → (0.001, л), "шт" → (1, шт).
+/// Для неизвестных единиц — `None`.
+pub fn base_factor(unit: &str) -> Option<(f64, BaseUnit)> {
+    let factor = match normalize_unit(unit).as_str() {
+        "кг" | "kg" | "килограмм" => (1.0, BaseUnit::Kg),
+        "г" | "гр" | "g" | "грамм" => (0.001, BaseUnit::Kg),
+        "л" | "l" | "литр" | "литра" => (1.0, BaseUnit::L),
+        "мл" | "ml" => (0.001, BaseUnit::L),
+        "шт" | "штук" | "штуки" | "pcs" | "pc" | "piece" | "pieces" => (1.0, BaseUnit::Piece),
+        _ => return None,
+    };
+
+    Some(factor)
+}
+
+/// Количество в базовых единицах: 250 г → 0.25 кг
+pub fn to_base(quantity: f64, unit: &str) -> Option<(f64, BaseUnit)> {
+    base_factor(unit).map(|(factor, base)| (quantity * factor, base))
+}
+
+/// Перевод количества между единицами одной величины: 500 г → 0.5 кг.
+/// Одинаковые (в том числе неизвестные) единицы переводятся как есть; г ↔ мл — нет.
+pub fn convert(quantity: f64, from: &str, to: &str) -> Option<f64> {
+    if normalize_unit(from) == normalize_unit(to) {
+        return Some(quantity);
+    }
+
+    match (base_factor(from), base_factor(to)) {
+        (Some((from_factor, from_base)), Some((to_factor, to_base))) if from_base == to_base => {
+            Some(quantity * from_factor / to_factor)
+        }
+        _ => None,
+    }
+}
+
+fn normalize_unit(unit: &str) -> String {
+    unit.trim().trim_end_matches('.').to_lowercase()
+}